@@ -6,6 +6,10 @@ use tokio::sync::mpsc;
 use tokio_tungstenite::{connect_async, tungstenite::Message};
 use futures_util::{SinkExt, StreamExt};
 
+/// Sottoprotocolli richiesti al server, in ordine di preferenza: con "msgpack" i frame
+/// viaggiano in MessagePack, altrimenti in JSON
+const WS_PROTOCOLS: &str = "msgpack, json";
+
 /// Stato del WebSocket condiviso
 struct WebSocketState {
     sender: Arc<Mutex<Option<mpsc::UnboundedSender<String>>>>,
@@ -53,6 +57,7 @@ async fn connect_websocket(
         .header("Connection", "Upgrade")
         .header("Upgrade", "websocket")
         .header("Sec-WebSocket-Key", tokio_tungstenite::tungstenite::handshake::client::generate_key())
+        .header("Sec-WebSocket-Protocol", WS_PROTOCOLS)
         .body(())
         .map_err(|e| format!("Errore creazione richiesta: {}", e))?;
     
@@ -68,8 +73,14 @@ async fn connect_websocket(
     // Spawn task per gestire la connessione WebSocket
     tokio::spawn(async move {
         match connect_async(request).await {
-            Ok((ws_stream, _)) => {
-                println!("WebSocket connesso con successo!");
+            Ok((ws_stream, response)) => {
+                // Il server indica nella risposta la codifica scelta, JSON se assente
+                let use_msgpack = response
+                    .headers()
+                    .get("Sec-WebSocket-Protocol")
+                    .and_then(|protocol| protocol.to_str().ok())
+                    == Some("msgpack");
+                println!("WebSocket connesso con successo! (msgpack: {})", use_msgpack);
                 
                 // Emetti evento di connessione
                 let _ = app_handle.emit("ws-connected", ());
@@ -86,6 +97,16 @@ async fn connect_websocket(
                                 // Emetti evento con il messaggio ricevuto
                                 let _ = app_handle_read.emit("ws-message", text);
                             }
+                            Ok(Message::Binary(bytes)) => {
+                                // Il frontend riceve sempre JSON, qualunque sia la codifica
+                                match decode_msgpack(&bytes) {
+                                    Ok(text) => {
+                                        println!("Messaggio ricevuto: {}", text);
+                                        let _ = app_handle_read.emit("ws-message", text);
+                                    }
+                                    Err(e) => eprintln!("Errore decodifica messaggio: {}", e),
+                                }
+                            }
                             Ok(Message::Pong(_)) => {
                                 println!("Pong ricevuto dal server");
                             }
@@ -115,7 +136,14 @@ async fn connect_websocket(
                         tokio::select! {
                             // Messaggio da inviare
                             Some(msg) = rx.recv() => {
-                                if let Err(e) = write.send(Message::Text(msg)).await {
+                                let frame = match encode_outgoing(msg, use_msgpack) {
+                                    Ok(frame) => frame,
+                                    Err(e) => {
+                                        eprintln!("Errore codifica messaggio: {}", e);
+                                        continue;
+                                    }
+                                };
+                                if let Err(e) = write.send(frame).await {
                                     eprintln!("Errore invio messaggio: {}", e);
                                     let _ = app_handle_write.emit("ws-error", format!("{}", e));
                                     break;
@@ -150,6 +178,25 @@ async fn connect_websocket(
     Ok("WebSocket connection started".to_string())
 }
 
+/// Converte un frame MessagePack ricevuto dal server nel JSON atteso dal frontend
+fn decode_msgpack(bytes: &[u8]) -> Result<String, String> {
+    rmp_serde::from_slice::<serde_json::Value>(bytes)
+        .map(|value| value.to_string())
+        .map_err(|e| e.to_string())
+}
+
+/// Prepara il frame da inviare: il frontend produce JSON, convertito in MessagePack
+/// se è la codifica negoziata con il server
+fn encode_outgoing(message: String, use_msgpack: bool) -> Result<Message, String> {
+    if !use_msgpack {
+        return Ok(Message::Text(message));
+    }
+    let value: serde_json::Value = serde_json::from_str(&message).map_err(|e| e.to_string())?;
+    rmp_serde::to_vec_named(&value)
+        .map(Message::Binary)
+        .map_err(|e| e.to_string())
+}
+
 /// Comando per inviare un messaggio tramite WebSocket
 #[tauri::command]
 async fn send_websocket_message(
@@ -6,7 +6,7 @@ import { invoke } from '@tauri-apps/api/core';
 import { listen } from '@tauri-apps/api/event';
 import { useNotifications } from '../hooks/useNotifications';
 
-const WS_URL = import.meta.env.VITE_WS_URL || 'ws://localhost:8080/ws';
+const WS_URL = import.meta.env.VITE_WS_URL || 'ws://localhost:8080/api/v1/ws';
 
 interface WebSocketContextType {
   isConnected: boolean;
@@ -100,9 +100,10 @@ export function WebSocketProvider({ children }: WebSocketProviderProps) {
               return;
             }
             
-            // Gestione segnali AddChat/RemoveChat/Invitation
-            if (data.AddChat !== undefined) {
-              const chatId = data.AddChat;
+            // Gestione segnali ChatCreated/ChatJoined/RemoveChat/Invitation
+            const addedChat = data.ChatCreated ?? data.ChatJoined;
+            if (addedChat !== undefined) {
+              const chatId: number = addedChat.chat_id;
               chatAddedCallbacksRef.current.forEach(callback => callback(chatId));
               return;
             }
@@ -51,7 +51,7 @@ export default function Home() {
     };
   }, [chats, selectedChatId, subscribeToChat]);
 
-  // Gestisci AddChat dal WebSocket
+  // Gestisci ChatCreated/ChatJoined dal WebSocket
   useEffect(() => {
     const unsubscribe = onChatAdded(async (_chatId) => {
       try {
@@ -59,7 +59,7 @@ export default function Home() {
         const chatsData = await api.listChats();
         setChats(chatsData);
       } catch (error) {
-        console.error('Errore ricaricamento chat dopo AddChat:', error);
+        console.error('Errore ricaricamento chat dopo ChatCreated/ChatJoined:', error);
       }
     });
 
@@ -1,7 +1,7 @@
 // API Service - Gestisce tutte le chiamate HTTP al backend
 import { ChatDTO, UserDTO, MessageDTO, EnrichedInvitationDTO, UserChatMetadataDTO, ChatType, UserRole } from '../models/types';
 
-const API_BASE_URL = import.meta.env.VITE_API_URL || 'http://localhost:8080';
+const API_BASE_URL = import.meta.env.VITE_API_URL || 'http://localhost:8080/api/v1';
 
 // Interfaccia per gli errori del backend
 interface BackendError {
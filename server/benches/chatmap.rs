@@ -0,0 +1,201 @@
+//! Benchmark della ChatMap: throughput degli invii con migliaia di chat attive e più thread,
+//! confrontato con una mappa protetta da un singolo RwLock.
+//!
+//! Eseguire con `cargo bench --bench chatmap`
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use server::dtos::MessageDTO;
+use server::entities::MessageType;
+use server::ws::chatmap::{ChatEvent, ChatMap};
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::{self, Receiver, Sender};
+
+/// Chat con almeno un iscritto online
+const CHATS: i32 = 5_000;
+
+/// Invii eseguiti in ogni iterazione, divisi tra i thread
+const SENDS_PER_ITER: usize = 20_000;
+
+const THREADS: [usize; 3] = [1, 4, 16];
+
+fn test_message() -> Arc<MessageDTO> {
+    Arc::new(MessageDTO {
+        message_id: Some(1),
+        chat_id: Some(1),
+        sender_id: Some(1),
+        content: Some("benchmark".to_string()),
+        created_at: None,
+        message_type: Some(MessageType::UserMessage),
+        deleted_at: None,
+        reply_to_message_id: None,
+        client_msg_id: None,
+        attachment_id: None,
+        content_format: None,
+    })
+}
+
+/// La mappa prima dello sharding: un solo lock per tutte le chat
+struct SingleLockChatMap {
+    channels: RwLock<HashMap<i32, Sender<ChatEvent>>>,
+}
+
+impl SingleLockChatMap {
+    fn subscribe(&self, chat_id: i32) -> Receiver<ChatEvent> {
+        self.channels
+            .write()
+            .unwrap()
+            .entry(chat_id)
+            .or_insert_with(|| broadcast::channel(100).0)
+            .subscribe()
+    }
+
+    fn send(&self, chat_id: i32, msg: Arc<MessageDTO>) -> usize {
+        let channels = self.channels.read().unwrap();
+        channels
+            .get(&chat_id)
+            .and_then(|tx| tx.send(ChatEvent::Message(msg)).ok())
+            .unwrap_or(0)
+    }
+
+    /// Come `ChatMap::release`: serve il lock in scrittura su tutta la mappa
+    fn release(&self, chat_id: i32) {
+        let mut channels = self.channels.write().unwrap();
+        if channels
+            .get(&chat_id)
+            .is_some_and(|tx| tx.receiver_count() == 0)
+        {
+            channels.remove(&chat_id);
+        }
+    }
+}
+
+/// Esegue `op(thread, i)` `SENDS_PER_ITER` volte divise su `threads` thread e misura il tempo
+fn run_parallel<F>(iters: u64, threads: usize, op: F) -> Duration
+where
+    F: Fn(usize, usize) + Sync,
+{
+    let per_thread = SENDS_PER_ITER / threads;
+    let mut total = Duration::ZERO;
+    for _ in 0..iters {
+        let start = Instant::now();
+        thread::scope(|scope| {
+            for t in 0..threads {
+                let op = &op;
+                scope.spawn(move || {
+                    for i in 0..per_thread {
+                        op(t, i);
+                    }
+                });
+            }
+        });
+        total += start.elapsed();
+    }
+    total
+}
+
+/// Chat scelta dal thread `t` all'invio `i`, distribuita su tutte le chat
+fn chat_for(t: usize, i: usize) -> i32 {
+    ((t * 7919 + i * 104_729) % CHATS as usize) as i32
+}
+
+fn bench_send(c: &mut Criterion) {
+    let msg = test_message();
+
+    let sharded = ChatMap::new();
+    let single = SingleLockChatMap {
+        channels: RwLock::new(HashMap::new()),
+    };
+    // i receiver restano vivi per tutto il benchmark, altrimenti gli invii eliminerebbero i canali
+    let _receivers: Vec<_> = (0..CHATS)
+        .flat_map(|chat_id| [sharded.subscribe(&chat_id), single.subscribe(chat_id)])
+        .collect();
+
+    let mut group = c.benchmark_group("chatmap_send");
+    group.throughput(Throughput::Elements(SENDS_PER_ITER as u64));
+    for threads in THREADS {
+        group.bench_with_input(
+            BenchmarkId::new("sharded", threads),
+            &threads,
+            |b, &threads| {
+                b.iter_custom(|iters| {
+                    run_parallel(iters, threads, |t, i| {
+                        black_box(sharded.send(&chat_for(t, i), msg.clone()).ok());
+                    })
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("single_lock", threads),
+            &threads,
+            |b, &threads| {
+                b.iter_custom(|iters| {
+                    run_parallel(iters, threads, |t, i| {
+                        black_box(single.send(chat_for(t, i), msg.clone()));
+                    })
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Connessioni che si aprono e si chiudono mentre gli altri thread inviano:
+/// metà delle operazioni sono subscribe + release, metà invii
+fn bench_churn(c: &mut Criterion) {
+    let msg = test_message();
+
+    let sharded = ChatMap::new();
+    let single = SingleLockChatMap {
+        channels: RwLock::new(HashMap::new()),
+    };
+    let _receivers: Vec<_> = (0..CHATS)
+        .flat_map(|chat_id| [sharded.subscribe(&chat_id), single.subscribe(chat_id)])
+        .collect();
+
+    let mut group = c.benchmark_group("chatmap_churn");
+    group.throughput(Throughput::Elements(SENDS_PER_ITER as u64));
+    for threads in THREADS {
+        group.bench_with_input(
+            BenchmarkId::new("sharded", threads),
+            &threads,
+            |b, &threads| {
+                b.iter_custom(|iters| {
+                    run_parallel(iters, threads, |t, i| {
+                        let chat_id = chat_for(t, i);
+                        if i % 2 == 0 {
+                            drop(sharded.subscribe(&chat_id));
+                            sharded.release([chat_id]);
+                        } else {
+                            black_box(sharded.send(&chat_id, msg.clone()).ok());
+                        }
+                    })
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("single_lock", threads),
+            &threads,
+            |b, &threads| {
+                b.iter_custom(|iters| {
+                    run_parallel(iters, threads, |t, i| {
+                        let chat_id = chat_for(t, i);
+                        if i % 2 == 0 {
+                            drop(single.subscribe(chat_id));
+                            single.release(chat_id);
+                        } else {
+                            black_box(single.send(chat_id, msg.clone()));
+                        }
+                    })
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_send, bench_churn);
+criterion_main!(benches);
@@ -0,0 +1,259 @@
+//! Benchmark della UserMap: registrazione, ricerca e broadcast dei segnali con migliaia di
+//! utenti online e più thread, confrontati con la precedente mappa basata su DashMap.
+//!
+//! Eseguire con `cargo bench --bench usermap`
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use dashmap::DashMap;
+use server::core::config::WsOverflowPolicy;
+use server::ws::signal_queue::{SignalReceiver, SignalSender, signal_channel};
+use server::ws::usermap::{ConnectionInfo, InternalSignal, UserMap};
+use std::hint::black_box;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Utenti online durante il benchmark
+const USERS: i32 = 10_000;
+
+/// Operazioni eseguite in ogni iterazione, divise tra i thread
+const OPS_PER_ITER: usize = 20_000;
+
+/// Capacità ridotta delle code: oltre questa soglia i segnali più vecchi vengono scartati,
+/// così la memoria resta costante per tutta la durata del benchmark
+const QUEUE_CAPACITY: usize = 64;
+
+const THREADS: [usize; 3] = [1, 4, 16];
+
+fn new_queue() -> (SignalSender, SignalReceiver) {
+    signal_channel(QUEUE_CAPACITY, WsOverflowPolicy::DropOldest)
+}
+
+/// La mappa prima della riscrittura: DashMap con lock per shard anche in lettura
+struct DashUserMap {
+    users_online: DashMap<i32, (SignalSender, ConnectionInfo)>,
+}
+
+impl DashUserMap {
+    fn register_connection(&self, user_id: i32, tx: SignalSender, connection: ConnectionInfo) {
+        self.users_online.insert(user_id, (tx, connection));
+    }
+
+    fn remove_from_online(&self, user_id: &i32) {
+        self.users_online.remove(user_id);
+    }
+
+    fn send_server_message_if_online(&self, user_id: &i32, message: InternalSignal) {
+        if let Some(entry) = self.users_online.get(user_id) {
+            let _ = entry.0.send(message);
+        }
+    }
+
+    fn is_user_online(&self, user_id: &i32) -> bool {
+        self.users_online.contains_key(user_id)
+    }
+}
+
+/// Esegue `op(thread, i)` `ops` volte divise su `threads` thread e misura il tempo
+fn run_parallel<F>(iters: u64, threads: usize, ops: usize, op: F) -> Duration
+where
+    F: Fn(usize, usize) + Sync,
+{
+    let per_thread = ops / threads;
+    let mut total = Duration::ZERO;
+    for _ in 0..iters {
+        let start = Instant::now();
+        thread::scope(|scope| {
+            for t in 0..threads {
+                let op = &op;
+                scope.spawn(move || {
+                    for i in 0..per_thread {
+                        op(t, i);
+                    }
+                });
+            }
+        });
+        total += start.elapsed();
+    }
+    total
+}
+
+/// Utente scelto dal thread `t` all'operazione `i`, distribuito su tutti gli utenti
+fn user_for(t: usize, i: usize) -> i32 {
+    ((t * 7919 + i * 104_729) % USERS as usize) as i32
+}
+
+/// Registra `USERS` utenti in entrambe le mappe, ritornando i receiver da tenere vivi
+fn populate(lock_free: &UserMap, dash: &DashUserMap) -> Vec<SignalReceiver> {
+    let mut receivers = Vec::with_capacity(USERS as usize * 2);
+    for user_id in 0..USERS {
+        let (tx, rx) = new_queue();
+        lock_free.register_connection(user_id, tx, ConnectionInfo::new(None, None, None));
+        receivers.push(rx);
+        let (tx, rx) = new_queue();
+        dash.register_connection(user_id, tx, ConnectionInfo::new(None, None, None));
+        receivers.push(rx);
+    }
+    receivers
+}
+
+/// Segnale inviato ad ogni messaggio di una chat: presenza dell'utente e accodamento
+fn bench_lookup(c: &mut Criterion) {
+    let lock_free = UserMap::new();
+    let dash = DashUserMap {
+        users_online: DashMap::new(),
+    };
+    let _receivers = populate(&lock_free, &dash);
+
+    let mut group = c.benchmark_group("usermap_lookup");
+    group.throughput(Throughput::Elements(OPS_PER_ITER as u64));
+    for threads in THREADS {
+        group.bench_with_input(
+            BenchmarkId::new("lock_free", threads),
+            &threads,
+            |b, &threads| {
+                b.iter_custom(|iters| {
+                    run_parallel(iters, threads, OPS_PER_ITER, |t, i| {
+                        let user_id = user_for(t, i);
+                        if black_box(lock_free.is_user_online(&user_id)) {
+                            lock_free.send_server_message_if_online(
+                                &user_id,
+                                InternalSignal::RemoveChat(1),
+                            );
+                        }
+                    })
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("dashmap", threads),
+            &threads,
+            |b, &threads| {
+                b.iter_custom(|iters| {
+                    run_parallel(iters, threads, OPS_PER_ITER, |t, i| {
+                        let user_id = user_for(t, i);
+                        if black_box(dash.is_user_online(&user_id)) {
+                            dash.send_server_message_if_online(
+                                &user_id,
+                                InternalSignal::RemoveChat(1),
+                            );
+                        }
+                    })
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Connessioni che si aprono e si chiudono mentre gli altri utenti ricevono segnali:
+/// un'operazione su quattro è registrazione + rimozione, le altre sono ricerche
+fn bench_register(c: &mut Criterion) {
+    let lock_free = UserMap::new();
+    let dash = DashUserMap {
+        users_online: DashMap::new(),
+    };
+    let _receivers = populate(&lock_free, &dash);
+
+    let mut group = c.benchmark_group("usermap_register");
+    group.throughput(Throughput::Elements(OPS_PER_ITER as u64));
+    for threads in THREADS {
+        group.bench_with_input(
+            BenchmarkId::new("lock_free", threads),
+            &threads,
+            |b, &threads| {
+                b.iter_custom(|iters| {
+                    run_parallel(iters, threads, OPS_PER_ITER, |t, i| {
+                        let user_id = user_for(t, i);
+                        if i % 4 == 0 {
+                            // utenti fuori da quelli popolati, le loro code restano intatte
+                            let (tx, _rx) = new_queue();
+                            let user_id = USERS + user_id;
+                            let epoch = lock_free.register_connection(
+                                user_id,
+                                tx,
+                                ConnectionInfo::new(None, None, None),
+                            );
+                            black_box(lock_free.remove_connection(&user_id, epoch));
+                        } else {
+                            black_box(lock_free.is_user_online(&user_id));
+                        }
+                    })
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("dashmap", threads),
+            &threads,
+            |b, &threads| {
+                b.iter_custom(|iters| {
+                    run_parallel(iters, threads, OPS_PER_ITER, |t, i| {
+                        let user_id = user_for(t, i);
+                        if i % 4 == 0 {
+                            let (tx, _rx) = new_queue();
+                            let user_id = USERS + user_id;
+                            dash.register_connection(
+                                user_id,
+                                tx,
+                                ConnectionInfo::new(None, None, None),
+                            );
+                            dash.remove_from_online(&user_id);
+                        } else {
+                            black_box(dash.is_user_online(&user_id));
+                        }
+                    })
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Segnale inviato a tutti gli utenti online (es. avviso di arresto), con gli utenti divisi
+/// tra i thread: ogni iterazione raggiunge ciascun utente una volta
+fn bench_broadcast(c: &mut Criterion) {
+    let lock_free = UserMap::new();
+    let dash = DashUserMap {
+        users_online: DashMap::new(),
+    };
+    let _receivers = populate(&lock_free, &dash);
+
+    let mut group = c.benchmark_group("usermap_broadcast");
+    group.throughput(Throughput::Elements(USERS as u64));
+    for threads in THREADS {
+        let per_thread = USERS as usize / threads;
+        group.bench_with_input(
+            BenchmarkId::new("lock_free", threads),
+            &threads,
+            |b, &threads| {
+                b.iter_custom(|iters| {
+                    run_parallel(iters, threads, USERS as usize, |t, i| {
+                        let user_id = (t * per_thread + i) as i32;
+                        lock_free.send_server_message_if_online(
+                            &user_id,
+                            InternalSignal::ChatDeleted(1),
+                        );
+                    })
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("dashmap", threads),
+            &threads,
+            |b, &threads| {
+                b.iter_custom(|iters| {
+                    run_parallel(iters, threads, USERS as usize, |t, i| {
+                        let user_id = (t * per_thread + i) as i32;
+                        dash.send_server_message_if_online(
+                            &user_id,
+                            InternalSignal::ChatDeleted(1),
+                        );
+                    })
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_lookup, bench_register, bench_broadcast);
+criterion_main!(benches);
@@ -11,18 +11,30 @@ INSERT INTO chats (chat_id, title, description, chat_type) VALUES
 (2, 'Private Alice-Bob', NULL, 'PRIVATE'),
 (3, 'Dev Team', 'Chat del team di sviluppo', 'GROUP');
 
+-- Ruoli base di ogni chat: Owner e Admin con tutti i permessi, Member senza permessi
+INSERT INTO chat_roles (role_id, chat_id, name, base_role, can_invite, can_remove_members, can_moderate, can_delete_messages, can_pin_messages, can_edit_chat, can_post_announcements, created_at) VALUES
+(1, 1, 'Owner', 'OWNER', 1, 1, 1, 1, 1, 1, 1, NOW()),
+(2, 1, 'Admin', 'ADMIN', 1, 1, 1, 1, 1, 1, 1, NOW()),
+(3, 1, 'Member', 'MEMBER', 0, 0, 0, 0, 0, 0, 0, NOW()),
+(4, 2, 'Owner', 'OWNER', 1, 1, 1, 1, 1, 1, 1, NOW()),
+(5, 2, 'Admin', 'ADMIN', 1, 1, 1, 1, 1, 1, 1, NOW()),
+(6, 2, 'Member', 'MEMBER', 0, 0, 0, 0, 0, 0, 0, NOW()),
+(7, 3, 'Owner', 'OWNER', 1, 1, 1, 1, 1, 1, 1, NOW()),
+(8, 3, 'Admin', 'ADMIN', 1, 1, 1, 1, 1, 1, 1, NOW()),
+(9, 3, 'Member', 'MEMBER', 0, 0, 0, 0, 0, 0, 0, NOW());
+
 -- Associa utenti alle chat con metadata
--- Colonne richieste: user_id, chat_id, messages_visible_from, messages_received_until, user_role, member_since
-INSERT INTO userchatmetadata (user_id, chat_id, messages_visible_from, messages_received_until, user_role, member_since) VALUES
+-- Colonne richieste: user_id, chat_id, messages_visible_from, messages_received_until, user_role, role_id, member_since
+INSERT INTO userchatmetadata (user_id, chat_id, messages_visible_from, messages_received_until, user_role, role_id, member_since) VALUES
 -- General Chat: alice (OWNER), bob (MEMBER), charlie (MEMBER)
-(1, 1, NOW(), NOW(), 'OWNER', NOW()),
-(2, 1, NOW(), NOW(), 'MEMBER', NOW()),
-(3, 1, NOW(), NOW(), 'MEMBER', NOW()),
+(1, 1, NOW(), NOW(), 'OWNER', 1, NOW()),
+(2, 1, NOW(), NOW(), 'MEMBER', 3, NOW()),
+(3, 1, NOW(), NOW(), 'MEMBER', 3, NOW()),
 
 -- Private Alice-Bob: alice (OWNER), bob (MEMBER)
-(1, 2, NOW(), NOW(), 'OWNER', NOW()),
-(2, 2, NOW(), NOW(), 'MEMBER', NOW()),
+(1, 2, NOW(), NOW(), 'OWNER', 4, NOW()),
+(2, 2, NOW(), NOW(), 'MEMBER', 6, NOW()),
 
 -- Dev Team: alice (OWNER), charlie (ADMIN)
-(1, 3, NOW(), NOW(), 'OWNER', NOW()),
-(3, 3, NOW(), NOW(), 'ADMIN', NOW());
+(1, 3, NOW(), NOW(), 'OWNER', 7, NOW()),
+(3, 3, NOW(), NOW(), 'ADMIN', 8, NOW());
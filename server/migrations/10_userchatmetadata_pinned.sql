@@ -0,0 +1,9 @@
+-- ============================================================================
+-- Chat fissate in cima alla lista (per utente)
+-- ============================================================================
+-- `pinned_at` è NULL per le chat non fissate; per le altre determina l'ordine,
+-- le chat fissate più di recente vengono mostrate per prime.
+-- ============================================================================
+
+ALTER TABLE `userchatmetadata`
+  ADD COLUMN `pinned_at` timestamp NULL DEFAULT NULL AFTER `member_since`;
@@ -0,0 +1,10 @@
+-- ============================================================================
+-- Avatar delle chat
+-- ============================================================================
+-- L'immagine viene salvata come allegato della chat stessa. Nessun vincolo di
+-- foreign key: gli allegati vengono eliminati a cascata insieme alla chat, e un
+-- SET NULL su `chats` durante la stessa cascata verrebbe rifiutato da InnoDB.
+-- ============================================================================
+
+ALTER TABLE `chats`
+  ADD COLUMN `avatar_attachment_id` int NULL DEFAULT NULL AFTER `chat_type`;
@@ -0,0 +1,9 @@
+-- ============================================================================
+-- Chat di soli annunci
+-- ============================================================================
+-- Se attivo, solo ADMIN e OWNER possono scrivere nella chat; i MEMBER restano
+-- in sola lettura.
+-- ============================================================================
+
+ALTER TABLE `chats`
+  ADD COLUMN `announcement_only` tinyint(1) NOT NULL DEFAULT 0 AFTER `avatar_attachment_id`;
@@ -0,0 +1,9 @@
+-- ============================================================================
+-- Chat pubbliche
+-- ============================================================================
+-- Le chat di gruppo pubbliche compaiono nella directory (GET /chats/discover).
+-- ============================================================================
+
+ALTER TABLE `chats`
+  ADD COLUMN `is_public` tinyint(1) NOT NULL DEFAULT 0 AFTER `announcement_only`,
+  ADD KEY `idx_chats_is_public` (`is_public`);
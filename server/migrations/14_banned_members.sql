@@ -0,0 +1,20 @@
+-- ============================================================================
+-- Utenti bannati dalle chat
+-- ============================================================================
+-- Un utente bannato non può più accedere alla chat né esservi invitato. Il ban
+-- sopravvive alla rimozione del membro, viene eliminato solo insieme alla chat
+-- o all'utente.
+-- ============================================================================
+
+CREATE TABLE `banned_members` (
+  `user_id` int NOT NULL,
+  `chat_id` int NOT NULL,
+  `banned_by` int NOT NULL,
+  `banned_at` timestamp NOT NULL,
+  PRIMARY KEY (`user_id`,`chat_id`),
+  KEY `idx_BannedMembers_chat` (`chat_id`),
+  KEY `idx_BannedMembers_banned_by` (`banned_by`),
+  CONSTRAINT `banned_members_ibfk_1` FOREIGN KEY (`user_id`) REFERENCES `users` (`user_id`) ON DELETE CASCADE,
+  CONSTRAINT `banned_members_ibfk_2` FOREIGN KEY (`chat_id`) REFERENCES `chats` (`chat_id`) ON DELETE CASCADE,
+  CONSTRAINT `banned_members_ibfk_3` FOREIGN KEY (`banned_by`) REFERENCES `users` (`user_id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
@@ -0,0 +1,10 @@
+-- ============================================================================
+-- Silenziamento temporaneo dei membri
+-- ============================================================================
+-- `muted_until` è NULL per i membri non silenziati; altrimenti il membro non
+-- può scrivere nella chat fino a quell'istante (ESCLUSO), poi torna a poterlo
+-- fare senza bisogno di azzerare la colonna.
+-- ============================================================================
+
+ALTER TABLE `userchatmetadata`
+  ADD COLUMN `muted_until` timestamp NULL DEFAULT NULL AFTER `pinned_at`;
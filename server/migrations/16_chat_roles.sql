@@ -0,0 +1,32 @@
+-- ============================================================================
+-- Ruoli personalizzati per chat
+-- ============================================================================
+-- L'Owner può definire ruoli con un nome e un insieme di permessi. Un membro
+-- può avere al più un ruolo personalizzato (`userchatmetadata.role_id`) che si
+-- aggiunge ai permessi del suo ruolo base: la colonna `user_role` resta con i
+-- valori OWNER/ADMIN/MEMBER, quindi i metadata esistenti non cambiano
+-- significato (role_id NULL = solo il ruolo base).
+-- Eliminando un ruolo, i membri che lo avevano tornano al solo ruolo base.
+-- ============================================================================
+
+CREATE TABLE `chat_roles` (
+  `role_id` int NOT NULL AUTO_INCREMENT,
+  `chat_id` int NOT NULL,
+  `name` varchar(50) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `can_invite` tinyint(1) NOT NULL DEFAULT 0,
+  `can_remove_members` tinyint(1) NOT NULL DEFAULT 0,
+  `can_moderate` tinyint(1) NOT NULL DEFAULT 0,
+  `can_delete_messages` tinyint(1) NOT NULL DEFAULT 0,
+  `can_pin_messages` tinyint(1) NOT NULL DEFAULT 0,
+  `can_edit_chat` tinyint(1) NOT NULL DEFAULT 0,
+  `can_post_announcements` tinyint(1) NOT NULL DEFAULT 0,
+  `created_at` timestamp NOT NULL,
+  PRIMARY KEY (`role_id`),
+  UNIQUE KEY `uq_ChatRoles_chat_name` (`chat_id`,`name`),
+  CONSTRAINT `chat_roles_ibfk_1` FOREIGN KEY (`chat_id`) REFERENCES `chats` (`chat_id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+ALTER TABLE `userchatmetadata`
+  ADD COLUMN `role_id` int NULL DEFAULT NULL AFTER `user_role`,
+  ADD KEY `idx_UCM_role` (`role_id`),
+  ADD CONSTRAINT `userchatmetadata_ibfk_3` FOREIGN KEY (`role_id`) REFERENCES `chat_roles` (`role_id`) ON DELETE SET NULL;
@@ -0,0 +1,26 @@
+-- ============================================================================
+-- Registro delle azioni amministrative
+-- ============================================================================
+-- Ogni azione privilegiata su una chat (ruoli, espulsioni, ban, trasferimento
+-- di ownership, modifica delle impostazioni...) viene registrata con autore,
+-- eventuale utente coinvolto e un payload JSON con i dettagli.
+-- Autore e target diventano NULL se l'utente elimina l'account, così il
+-- registro della chat resta completo.
+-- ============================================================================
+
+CREATE TABLE `audit_log` (
+  `audit_id` int NOT NULL AUTO_INCREMENT,
+  `chat_id` int NOT NULL,
+  `actor_id` int DEFAULT NULL,
+  `target_user_id` int DEFAULT NULL,
+  `action` enum('MEMBER_ROLE_CHANGED','MEMBER_REMOVED','MEMBER_BANNED','MEMBER_MUTED','OWNERSHIP_TRANSFERRED','CHAT_UPDATED','MESSAGE_DELETED','CUSTOM_ROLE_CREATED','CUSTOM_ROLE_DELETED','CUSTOM_ROLE_ASSIGNED') COLLATE utf8mb4_unicode_ci NOT NULL,
+  `payload` text COLLATE utf8mb4_unicode_ci,
+  `created_at` timestamp NOT NULL,
+  PRIMARY KEY (`audit_id`),
+  KEY `idx_AuditLog_chat_created` (`chat_id`,`created_at`),
+  KEY `idx_AuditLog_actor` (`actor_id`),
+  KEY `idx_AuditLog_target` (`target_user_id`),
+  CONSTRAINT `audit_log_ibfk_1` FOREIGN KEY (`chat_id`) REFERENCES `chats` (`chat_id`) ON DELETE CASCADE,
+  CONSTRAINT `audit_log_ibfk_2` FOREIGN KEY (`actor_id`) REFERENCES `users` (`user_id`) ON DELETE SET NULL,
+  CONSTRAINT `audit_log_ibfk_3` FOREIGN KEY (`target_user_id`) REFERENCES `users` (`user_id`) ON DELETE SET NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
@@ -0,0 +1,9 @@
+-- ============================================================================
+-- Messaggio facoltativo allegato agli inviti
+-- ============================================================================
+-- `note` è NULL se l'inviter non ha scritto nulla; viene mostrata all'invitato
+-- nella lista degli inviti pendenti e nella notifica in tempo reale.
+-- ============================================================================
+
+ALTER TABLE `invitations`
+  ADD COLUMN `note` varchar(200) COLLATE utf8mb4_unicode_ci DEFAULT NULL AFTER `invitee_id`;
@@ -0,0 +1,29 @@
+-- ============================================================================
+-- Richieste di accesso alle chat pubbliche
+-- ============================================================================
+-- Le chat pubbliche con `requires_approval` non accettano ingressi diretti:
+-- POST /chats/{chat_id}/join crea una richiesta PENDING che un Admin (o un
+-- membro con il permesso di invitare) approva o rifiuta. Le richieste alle
+-- chat senza approvazione vengono salvate già APPROVED, con `resolved_by`
+-- NULL, così lo storico degli ingressi resta completo.
+-- ============================================================================
+
+ALTER TABLE `chats`
+  ADD COLUMN `requires_approval` tinyint(1) NOT NULL DEFAULT 0 AFTER `is_public`;
+
+CREATE TABLE `join_requests` (
+  `request_id` int NOT NULL AUTO_INCREMENT,
+  `chat_id` int NOT NULL,
+  `user_id` int NOT NULL,
+  `state` enum('PENDING','APPROVED','DENIED') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'PENDING',
+  `created_at` timestamp NOT NULL,
+  `resolved_by` int DEFAULT NULL,
+  `resolved_at` timestamp NULL DEFAULT NULL,
+  PRIMARY KEY (`request_id`),
+  KEY `idx_JoinRequests_chat_state` (`chat_id`,`state`),
+  KEY `idx_JoinRequests_user` (`user_id`),
+  KEY `idx_JoinRequests_resolved_by` (`resolved_by`),
+  CONSTRAINT `join_requests_ibfk_1` FOREIGN KEY (`chat_id`) REFERENCES `chats` (`chat_id`) ON DELETE CASCADE,
+  CONSTRAINT `join_requests_ibfk_2` FOREIGN KEY (`user_id`) REFERENCES `users` (`user_id`) ON DELETE CASCADE,
+  CONSTRAINT `join_requests_ibfk_3` FOREIGN KEY (`resolved_by`) REFERENCES `users` (`user_id`) ON DELETE SET NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
@@ -0,0 +1,10 @@
+-- ============================================================================
+-- Chi può invitare nuovi membri
+-- ============================================================================
+-- ADMINS_ONLY (default, comportamento precedente): solo Owner, Admin e i
+-- membri con un ruolo personalizzato che concede INVITE_MEMBERS.
+-- EVERYONE: qualsiasi membro della chat può invitare.
+-- ============================================================================
+
+ALTER TABLE `chats`
+  ADD COLUMN `invite_policy` enum('EVERYONE','ADMINS_ONLY') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'ADMINS_ONLY' AFTER `requires_approval`;
@@ -0,0 +1,13 @@
+-- ============================================================================
+-- Profilo utente
+-- ============================================================================
+-- Campi facoltativi modificabili dall'utente tramite PATCH /users/me/profile:
+-- `display_name` nome mostrato al posto dello username
+-- `bio`          breve descrizione
+-- `avatar_url`   URL http(s) dell'immagine del profilo
+-- ============================================================================
+
+ALTER TABLE `users`
+  ADD COLUMN `display_name` varchar(50) COLLATE utf8mb4_unicode_ci DEFAULT NULL AFTER `password`,
+  ADD COLUMN `bio` varchar(500) COLLATE utf8mb4_unicode_ci DEFAULT NULL AFTER `display_name`,
+  ADD COLUMN `avatar_url` varchar(500) COLLATE utf8mb4_unicode_ci DEFAULT NULL AFTER `bio`;
@@ -0,0 +1,11 @@
+-- ============================================================================
+-- Stato personalizzato degli utenti
+-- ============================================================================
+-- `status_text`       breve messaggio di stato ("in riunione"), NULL se assente
+-- `status_expires_at` scadenza facoltativa: oltre questa data lo stato non viene
+--                     più mostrato. NULL = nessuna scadenza.
+-- ============================================================================
+
+ALTER TABLE `users`
+  ADD COLUMN `status_text` varchar(100) COLLATE utf8mb4_unicode_ci DEFAULT NULL AFTER `avatar_url`,
+  ADD COLUMN `status_expires_at` timestamp NULL DEFAULT NULL AFTER `status_text`;
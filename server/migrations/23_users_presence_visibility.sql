@@ -0,0 +1,11 @@
+-- ============================================================================
+-- Privacy della presenza
+-- ============================================================================
+-- Chi può vedere lo stato online e il last_seen dell'utente:
+-- EVERYONE (default) tutti gli utenti
+-- CONTACTS           solo chi condivide almeno una chat con l'utente
+-- NOBODY             nessuno (gli eventi di presenza non vengono inviati)
+-- ============================================================================
+
+ALTER TABLE `users`
+  ADD COLUMN `presence_visibility` enum('EVERYONE','CONTACTS','NOBODY') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'EVERYONE' AFTER `last_seen`;
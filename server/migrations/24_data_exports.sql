@@ -0,0 +1,21 @@
+-- ============================================================================
+-- Export dei dati personali (GDPR)
+-- ============================================================================
+-- POST /users/me/export crea un job PENDING; un task in background raccoglie
+-- profilo, membership, messaggi e inviti dell'utente in un archivio JSON salvato
+-- sullo storage degli allegati (`storage_key`) e porta il job a COMPLETED,
+-- oppure a FAILED in caso di errore. Il client interroga lo stato con
+-- GET /users/me/export e scarica l'archivio quando è pronto.
+-- ============================================================================
+
+CREATE TABLE `data_exports` (
+  `export_id` int NOT NULL AUTO_INCREMENT,
+  `user_id` int NOT NULL,
+  `state` enum('PENDING','COMPLETED','FAILED') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'PENDING',
+  `created_at` timestamp NOT NULL,
+  `completed_at` timestamp NULL DEFAULT NULL,
+  `storage_key` varchar(255) COLLATE utf8mb4_unicode_ci DEFAULT NULL,
+  PRIMARY KEY (`export_id`),
+  KEY `idx_DataExports_user` (`user_id`,`created_at`),
+  CONSTRAINT `data_exports_ibfk_1` FOREIGN KEY (`user_id`) REFERENCES `users` (`user_id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
@@ -0,0 +1,12 @@
+-- ============================================================================
+-- Disattivazione degli account
+-- ============================================================================
+-- DELETE /users/me?mode=deactivate imposta `deactivated_at` invece di cancellare
+-- l'account: l'utente appare come "Deleted User" e non può autenticarsi, ma
+-- membership e messaggi restano intatti. Un nuovo login entro la finestra di
+-- riattivazione azzera il campo; scaduta la finestra l'account viene cancellato
+-- definitivamente da un task periodico.
+-- ============================================================================
+
+ALTER TABLE `users`
+  ADD COLUMN `deactivated_at` timestamp NULL DEFAULT NULL AFTER `presence_visibility`;
@@ -0,0 +1,25 @@
+-- ============================================================================
+-- Refresh token con rotazione
+-- ============================================================================
+-- Il login emette un access token JWT di breve durata e un refresh token opaco,
+-- salvato qui solo come hash SHA-256. POST /auth/refresh consuma il refresh
+-- token (`used_at`) e ne emette uno nuovo nella stessa famiglia (`family_id`).
+-- Se un token già usato o revocato viene presentato di nuovo, l'intera
+-- famiglia viene revocata (`revoked_at`) e l'utente deve rifare il login.
+-- ============================================================================
+
+CREATE TABLE `refresh_tokens` (
+  `token_id` int NOT NULL AUTO_INCREMENT,
+  `user_id` int NOT NULL,
+  `family_id` char(36) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `token_hash` char(64) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `created_at` timestamp NOT NULL,
+  `expires_at` timestamp NOT NULL,
+  `used_at` timestamp NULL DEFAULT NULL,
+  `revoked_at` timestamp NULL DEFAULT NULL,
+  PRIMARY KEY (`token_id`),
+  UNIQUE KEY `uq_RefreshTokens_hash` (`token_hash`),
+  KEY `idx_RefreshTokens_family` (`family_id`),
+  KEY `idx_RefreshTokens_user` (`user_id`),
+  CONSTRAINT `refresh_tokens_ibfk_1` FOREIGN KEY (`user_id`) REFERENCES `users` (`user_id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
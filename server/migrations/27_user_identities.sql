@@ -0,0 +1,20 @@
+-- ============================================================================
+-- Identità esterne (OpenID Connect)
+-- ============================================================================
+-- Con il login tramite un provider OIDC l'utente è identificato dalla coppia
+-- (`issuer`, `subject`) dell'id_token. Al primo accesso viene creato un nuovo
+-- utente locale e l'identità viene collegata qui; gli accessi successivi
+-- ritrovano lo stesso utente anche se cambia lo username sul provider.
+-- ============================================================================
+
+CREATE TABLE `user_identities` (
+  `identity_id` int NOT NULL AUTO_INCREMENT,
+  `user_id` int NOT NULL,
+  `issuer` varchar(255) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `subject` varchar(255) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `created_at` timestamp NOT NULL,
+  PRIMARY KEY (`identity_id`),
+  UNIQUE KEY `uq_UserIdentities_subject` (`issuer`,`subject`),
+  KEY `idx_UserIdentities_user` (`user_id`),
+  CONSTRAINT `user_identities_ibfk_1` FOREIGN KEY (`user_id`) REFERENCES `users` (`user_id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
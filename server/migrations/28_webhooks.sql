@@ -0,0 +1,30 @@
+-- ============================================================================
+-- Webhook in ingresso
+-- ============================================================================
+-- Owner e Admin di un gruppo possono creare un webhook: un utente bot, membro
+-- della chat, e un token segreto (salvato qui solo come hash SHA-256) con cui
+-- servizi esterni (CI, monitoraggio...) pubblicano messaggi tramite
+-- POST /webhooks/{webhook_token}. Eliminando il webhook il bot esce dalla chat
+-- ma resta autore dei messaggi già inviati.
+-- ============================================================================
+
+CREATE TABLE `webhooks` (
+  `webhook_id` int NOT NULL AUTO_INCREMENT,
+  `chat_id` int NOT NULL,
+  `bot_user_id` int NOT NULL,
+  `name` varchar(50) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `token_hash` char(64) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `created_by` int DEFAULT NULL,
+  `created_at` timestamp NOT NULL,
+  PRIMARY KEY (`webhook_id`),
+  UNIQUE KEY `uq_Webhooks_token_hash` (`token_hash`),
+  KEY `idx_Webhooks_chat` (`chat_id`),
+  KEY `idx_Webhooks_bot_user` (`bot_user_id`),
+  KEY `idx_Webhooks_created_by` (`created_by`),
+  CONSTRAINT `webhooks_ibfk_1` FOREIGN KEY (`chat_id`) REFERENCES `chats` (`chat_id`) ON DELETE CASCADE,
+  CONSTRAINT `webhooks_ibfk_2` FOREIGN KEY (`bot_user_id`) REFERENCES `users` (`user_id`) ON DELETE CASCADE,
+  CONSTRAINT `webhooks_ibfk_3` FOREIGN KEY (`created_by`) REFERENCES `users` (`user_id`) ON DELETE SET NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+ALTER TABLE `audit_log`
+  MODIFY COLUMN `action` enum('MEMBER_ROLE_CHANGED','MEMBER_REMOVED','MEMBER_BANNED','MEMBER_MUTED','OWNERSHIP_TRANSFERRED','CHAT_UPDATED','MESSAGE_DELETED','CUSTOM_ROLE_CREATED','CUSTOM_ROLE_DELETED','CUSTOM_ROLE_ASSIGNED','WEBHOOK_CREATED','WEBHOOK_DELETED') COLLATE utf8mb4_unicode_ci NOT NULL;
@@ -0,0 +1,12 @@
+-- ============================================================================
+-- Id dei messaggi scelto dal client
+-- ============================================================================
+-- `client_msg_id` è generato dal client per ogni messaggio inviato via
+-- WebSocket: il server lo restituisce nel frame SendResult insieme al
+-- message_id salvato. Se il client ritrasmette lo stesso messaggio (es. dopo
+-- una riconnessione) il vincolo di unicità per mittente evita il duplicato.
+-- ============================================================================
+
+ALTER TABLE `messages`
+  ADD COLUMN `client_msg_id` varchar(64) COLLATE utf8mb4_unicode_ci DEFAULT NULL AFTER `attachment_id`,
+  ADD UNIQUE KEY `uq_Messages_sender_client_msg_id` (`sender_id`,`client_msg_id`);
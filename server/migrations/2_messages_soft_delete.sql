@@ -0,0 +1,9 @@
+-- ============================================================================
+-- Soft-delete dei messaggi
+-- ============================================================================
+-- I messaggi eliminati non vengono rimossi dalla tabella: viene valorizzato
+-- `deleted_at` e il server restituisce un tombstone (contenuto nascosto).
+-- ============================================================================
+
+ALTER TABLE `messages`
+  ADD COLUMN `deleted_at` timestamp NULL DEFAULT NULL AFTER `created_at`;
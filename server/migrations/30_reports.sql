@@ -0,0 +1,37 @@
+-- ============================================================================
+-- Segnalazioni di messaggi e utenti
+-- ============================================================================
+-- Un utente può segnalare un messaggio di una chat di cui è membro
+-- (POST /chats/{chat_id}/messages/{message_id}/report) oppure un altro utente
+-- (POST /users/{user_id}/report). `reported_user_id` è sempre valorizzato:
+-- per le segnalazioni di un messaggio è il suo autore, così la coda degli
+-- amministratori del server può essere filtrata per utente. Le segnalazioni
+-- restano PENDING finché un amministratore non le chiude come RESOLVED
+-- (eventualmente eliminando il messaggio) o DISMISSED.
+-- ============================================================================
+
+CREATE TABLE `reports` (
+  `report_id` int NOT NULL AUTO_INCREMENT,
+  `reporter_id` int NOT NULL,
+  `reported_user_id` int NOT NULL,
+  `chat_id` int DEFAULT NULL,
+  `message_id` int DEFAULT NULL,
+  `reason` enum('SPAM','HARASSMENT','INAPPROPRIATE_CONTENT','IMPERSONATION','OTHER') COLLATE utf8mb4_unicode_ci NOT NULL,
+  `details` varchar(500) COLLATE utf8mb4_unicode_ci DEFAULT NULL,
+  `state` enum('PENDING','RESOLVED','DISMISSED') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'PENDING',
+  `created_at` timestamp NOT NULL,
+  `resolved_by` int DEFAULT NULL,
+  `resolved_at` timestamp NULL DEFAULT NULL,
+  PRIMARY KEY (`report_id`),
+  KEY `idx_Reports_state_created` (`state`,`created_at`),
+  KEY `idx_Reports_reporter` (`reporter_id`),
+  KEY `idx_Reports_reported_user` (`reported_user_id`),
+  KEY `idx_Reports_chat` (`chat_id`),
+  KEY `idx_Reports_message` (`message_id`),
+  KEY `idx_Reports_resolved_by` (`resolved_by`),
+  CONSTRAINT `reports_ibfk_1` FOREIGN KEY (`reporter_id`) REFERENCES `users` (`user_id`) ON DELETE CASCADE,
+  CONSTRAINT `reports_ibfk_2` FOREIGN KEY (`reported_user_id`) REFERENCES `users` (`user_id`) ON DELETE CASCADE,
+  CONSTRAINT `reports_ibfk_3` FOREIGN KEY (`chat_id`) REFERENCES `chats` (`chat_id`) ON DELETE CASCADE,
+  CONSTRAINT `reports_ibfk_4` FOREIGN KEY (`message_id`) REFERENCES `messages` (`message_id`) ON DELETE CASCADE,
+  CONSTRAINT `reports_ibfk_5` FOREIGN KEY (`resolved_by`) REFERENCES `users` (`user_id`) ON DELETE SET NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
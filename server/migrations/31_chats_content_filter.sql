@@ -0,0 +1,17 @@
+-- ============================================================================
+-- Filtro dei contenuti dei messaggi
+-- ============================================================================
+-- Ogni chat sceglie cosa fare dei messaggi che contengono parole della lista
+-- configurata sul server o che il classificatore esterno segnala:
+-- OFF (default): nessun controllo.
+-- FLAG: il messaggio viene consegnato e segnalato agli amministratori del
+--   server con una segnalazione automatica, senza reporter.
+-- REDACT: le parole della lista vengono mascherate prima della consegna.
+-- REJECT: il messaggio viene rifiutato.
+-- ============================================================================
+
+ALTER TABLE `chats`
+  ADD COLUMN `content_filter` enum('OFF','FLAG','REDACT','REJECT') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'OFF' AFTER `invite_policy`;
+
+ALTER TABLE `reports`
+  MODIFY COLUMN `reporter_id` int DEFAULT NULL;
@@ -0,0 +1,30 @@
+-- ============================================================================
+-- Chiavi pubbliche per la cifratura end-to-end (stile X3DH)
+-- ============================================================================
+-- Ogni utente pubblica una chiave di identità, una signed prekey con la sua
+-- firma e una scorta di one-time prekey. Chi vuole avviare una sessione cifrata
+-- scarica il bundle con GET /users/{user_id}/keys, che consuma una one-time
+-- prekey: ognuna viene consegnata al più una volta. Il server conserva solo
+-- chiavi pubbliche (in base64) e il contenuto cifrato dei messaggi, mai le
+-- chiavi private. Cambiare la chiave di identità invalida le one-time prekey
+-- pubblicate con la precedente.
+-- ============================================================================
+
+CREATE TABLE `user_keys` (
+  `user_id` int NOT NULL,
+  `identity_key` varchar(64) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `signed_prekey_id` int NOT NULL,
+  `signed_prekey` varchar(64) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `signed_prekey_signature` varchar(128) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `updated_at` timestamp NOT NULL,
+  PRIMARY KEY (`user_id`),
+  CONSTRAINT `user_keys_ibfk_1` FOREIGN KEY (`user_id`) REFERENCES `users` (`user_id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+CREATE TABLE `one_time_prekeys` (
+  `user_id` int NOT NULL,
+  `prekey_id` int NOT NULL,
+  `public_key` varchar(64) COLLATE utf8mb4_unicode_ci NOT NULL,
+  PRIMARY KEY (`user_id`,`prekey_id`),
+  CONSTRAINT `one_time_prekeys_ibfk_1` FOREIGN KEY (`user_id`) REFERENCES `users` (`user_id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
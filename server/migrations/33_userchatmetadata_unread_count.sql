@@ -0,0 +1,26 @@
+-- ============================================================================
+-- Contatore persistente dei messaggi non letti
+-- ============================================================================
+-- `unread_count` conta i messaggi della chat creati dopo
+-- `messages_received_until`, visibili al membro, non inviati da lui e non
+-- eliminati. Viene incrementato nella stessa transazione in cui un messaggio
+-- viene scritto, decrementato quando un messaggio ancora non letto viene
+-- eliminato, scalato dei messaggi consegnati quando il membro avanza il
+-- proprio marcatore di lettura e azzerato quando il marcatore raggiunge
+-- l'ultimo messaggio: i badge non richiedono più un COUNT sui messaggi.
+-- Il conteggio qui sotto serve solo a inizializzare le righe esistenti.
+-- ============================================================================
+
+ALTER TABLE `userchatmetadata`
+  ADD COLUMN `unread_count` int NOT NULL DEFAULT 0 AFTER `messages_received_until`;
+
+UPDATE `userchatmetadata` ucm
+SET ucm.`unread_count` = (
+  SELECT COUNT(*)
+  FROM `messages` m
+  WHERE m.`chat_id` = ucm.`chat_id`
+    AND m.`created_at` > ucm.`messages_received_until`
+    AND m.`created_at` >= ucm.`messages_visible_from`
+    AND m.`sender_id` <> ucm.`user_id`
+    AND m.`deleted_at` IS NULL
+);
@@ -0,0 +1,21 @@
+-- ============================================================================
+-- Coda di consegna per gli utenti offline
+-- ============================================================================
+-- Quando un messaggio viene salvato, per ogni membro della chat senza una
+-- connessione WebSocket aperta viene accodata una riga. Alla connessione
+-- successiva il server invia i messaggi in coda nell'ordine di `message_id`,
+-- prima del traffico in tempo reale, e valorizza `delivered_at`. Le righe
+-- consegnate e quelle troppo vecchie vengono eliminate periodicamente.
+-- ============================================================================
+
+CREATE TABLE `offline_queue` (
+  `user_id` int NOT NULL,
+  `message_id` int NOT NULL,
+  `enqueued_at` timestamp NOT NULL,
+  `delivered_at` timestamp NULL DEFAULT NULL,
+  PRIMARY KEY (`user_id`,`message_id`),
+  KEY `idx_offline_queue_pending` (`user_id`,`delivered_at`,`message_id`),
+  KEY `idx_offline_queue_message` (`message_id`),
+  CONSTRAINT `offline_queue_ibfk_1` FOREIGN KEY (`user_id`) REFERENCES `users` (`user_id`) ON DELETE CASCADE,
+  CONSTRAINT `offline_queue_ibfk_2` FOREIGN KEY (`message_id`) REFERENCES `messages` (`message_id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
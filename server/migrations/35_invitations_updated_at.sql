@@ -0,0 +1,11 @@
+-- ============================================================================
+-- Data dell'ultima risposta a un invito
+-- ============================================================================
+-- `updated_at` viene valorizzato quando l'invitato accetta o rifiuta l'invito:
+-- la sincronizzazione incrementale (`GET /sync`) la usa per restituire
+-- all'inviter solo gli inviti che hanno cambiato stato dopo il suo token.
+-- ============================================================================
+
+ALTER TABLE `invitations`
+  ADD COLUMN `updated_at` timestamp NULL DEFAULT NULL AFTER `created_at`,
+  ADD KEY `idx_invitations_inviter_updated` (`invitee_id`,`updated_at`);
@@ -0,0 +1,13 @@
+-- ============================================================================
+-- Versione dei metadati utente-chat (optimistic concurrency)
+-- ============================================================================
+-- `version` viene incrementata da ogni scrittura che può cambiare il ruolo di un
+-- membro (`update`, `update_user_role`, `transfer_ownership`). Gli aggiornamenti
+-- indicano la versione letta e falliscono con 409 Conflict se nel frattempo un
+-- altro amministratore ha modificato la riga, invece di sovrascriverla.
+-- Marcatori di lettura, pin e silenziamento non la incrementano: cambiano spesso
+-- e non entrano in conflitto con le modifiche ai ruoli.
+-- ============================================================================
+
+ALTER TABLE `userchatmetadata`
+  ADD COLUMN `version` int NOT NULL DEFAULT 0;
@@ -0,0 +1,20 @@
+-- ============================================================================
+-- Cronologia delle modifiche ai messaggi
+-- ============================================================================
+-- Ad ogni modifica del contenuto, il contenuto precedente viene copiato in
+-- `message_revisions` nella stessa transazione dell'aggiornamento. Owner e
+-- Admin della chat possono consultare la cronologia
+-- (`GET /chats/{chat_id}/messages/{message_id}/history`) per la moderazione e
+-- per risolvere contestazioni. Le revisioni restano anche se il messaggio viene
+-- eliminato (soft-delete) e spariscono solo con la riga del messaggio.
+-- ============================================================================
+
+CREATE TABLE `message_revisions` (
+  `revision_id` int NOT NULL AUTO_INCREMENT,
+  `message_id` int NOT NULL,
+  `content` text COLLATE utf8mb4_unicode_ci NOT NULL,
+  `replaced_at` timestamp NOT NULL,
+  PRIMARY KEY (`revision_id`),
+  KEY `idx_message_revisions_message` (`message_id`,`revision_id`),
+  CONSTRAINT `message_revisions_ibfk_1` FOREIGN KEY (`message_id`) REFERENCES `messages` (`message_id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
@@ -0,0 +1,21 @@
+-- ============================================================================
+-- Conteggio dei riferimenti agli allegati
+-- ============================================================================
+-- `ref_count` conta i messaggi non eliminati e le chat (avatar) che usano
+-- l'allegato; viene aggiornato nella stessa transazione che aggiunge o toglie
+-- il riferimento. Un messaggio eliminato (soft-delete) perde il proprio
+-- allegato. Il job di pulizia elimina dallo storage e dal database gli
+-- allegati con `ref_count = 0` caricati da più di un giorno: il periodo di
+-- grazia copre il tempo tra il caricamento e l'invio del messaggio.
+-- ============================================================================
+
+ALTER TABLE `attachments`
+  ADD COLUMN `ref_count` int NOT NULL DEFAULT 0 AFTER `storage_key`,
+  ADD KEY `idx_attachments_orphans` (`ref_count`,`created_at`);
+
+UPDATE `messages` SET `attachment_id` = NULL
+WHERE `deleted_at` IS NOT NULL AND `attachment_id` IS NOT NULL;
+
+UPDATE `attachments` a SET `ref_count` =
+  (SELECT COUNT(*) FROM `messages` m WHERE m.`attachment_id` = a.`attachment_id`)
+  + (SELECT COUNT(*) FROM `chats` c WHERE c.`avatar_attachment_id` = a.`attachment_id`);
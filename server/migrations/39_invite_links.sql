@@ -0,0 +1,27 @@
+-- ============================================================================
+-- Link di invito
+-- ============================================================================
+-- Un membro di un gruppo (chi può invitare secondo la `invite_policy` della
+-- chat) crea un link di invito: un token segreto, salvato qui solo come hash
+-- SHA-256, con cui qualsiasi utente autenticato entra nella chat tramite
+-- POST /invite_links/{invite_token}/join. Il link vale fino a `expires_at`
+-- (NULL = senza scadenza) o finché non viene revocato, cioè eliminato.
+-- ============================================================================
+
+CREATE TABLE `invite_links` (
+  `link_id` int NOT NULL AUTO_INCREMENT,
+  `chat_id` int NOT NULL,
+  `token_hash` char(64) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `created_by` int DEFAULT NULL,
+  `created_at` timestamp NOT NULL,
+  `expires_at` timestamp NULL DEFAULT NULL,
+  PRIMARY KEY (`link_id`),
+  UNIQUE KEY `uq_InviteLinks_token_hash` (`token_hash`),
+  KEY `idx_InviteLinks_chat` (`chat_id`),
+  KEY `idx_InviteLinks_created_by` (`created_by`),
+  CONSTRAINT `invite_links_ibfk_1` FOREIGN KEY (`chat_id`) REFERENCES `chats` (`chat_id`) ON DELETE CASCADE,
+  CONSTRAINT `invite_links_ibfk_2` FOREIGN KEY (`created_by`) REFERENCES `users` (`user_id`) ON DELETE SET NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+ALTER TABLE `audit_log`
+  MODIFY COLUMN `action` enum('MEMBER_ROLE_CHANGED','MEMBER_REMOVED','MEMBER_BANNED','MEMBER_MUTED','OWNERSHIP_TRANSFERRED','CHAT_UPDATED','MESSAGE_DELETED','CUSTOM_ROLE_CREATED','CUSTOM_ROLE_DELETED','CUSTOM_ROLE_ASSIGNED','WEBHOOK_CREATED','WEBHOOK_DELETED','INVITE_LINK_CREATED','INVITE_LINK_REVOKED') COLLATE utf8mb4_unicode_ci NOT NULL;
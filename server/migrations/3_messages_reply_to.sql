@@ -0,0 +1,12 @@
+-- ============================================================================
+-- Risposte / citazioni tra messaggi
+-- ============================================================================
+-- `reply_to_message_id` punta ad un messaggio precedente della stessa chat.
+-- Se il messaggio citato viene eliminato fisicamente (clean_chat) il
+-- riferimento viene azzerato.
+-- ============================================================================
+
+ALTER TABLE `messages`
+  ADD COLUMN `reply_to_message_id` int NULL DEFAULT NULL AFTER `message_type`,
+  ADD KEY `idx_Messages_reply_to` (`reply_to_message_id`),
+  ADD CONSTRAINT `messages_ibfk_3` FOREIGN KEY (`reply_to_message_id`) REFERENCES `messages` (`message_id`) ON DELETE SET NULL;
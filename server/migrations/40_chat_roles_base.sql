@@ -0,0 +1,39 @@
+-- ============================================================================
+-- Ruoli base come righe di chat_roles
+-- ============================================================================
+-- Ogni chat ha tre righe di ruolo base (`base_role` valorizzato): Owner e Admin
+-- con tutti i permessi, Member senza permessi aggiuntivi. Un ruolo
+-- personalizzato ha `base_role` NULL.
+-- `userchatmetadata.role_id` diventa il riferimento al ruolo del membro: la
+-- riga base corrispondente a `user_role`, oppure un ruolo personalizzato
+-- assegnato a un Member. La colonna `user_role` resta come mappatura di
+-- compatibilità e viene aggiornata insieme a `role_id`.
+-- ============================================================================
+
+ALTER TABLE `chat_roles`
+  ADD COLUMN `base_role` enum('OWNER','ADMIN','MEMBER') COLLATE utf8mb4_unicode_ci NULL DEFAULT NULL AFTER `name`,
+  ADD UNIQUE KEY `uq_ChatRoles_chat_base` (`chat_id`,`base_role`);
+
+-- I nomi dei ruoli base sono riservati: i ruoli personalizzati omonimi vengono rinominati
+UPDATE `chat_roles`
+SET `name` = CONCAT(`name`, ' (custom)')
+WHERE `name` IN ('Owner', 'Admin', 'Member');
+
+INSERT INTO `chat_roles` (`chat_id`, `name`, `base_role`, `can_invite`, `can_remove_members`,
+  `can_moderate`, `can_delete_messages`, `can_pin_messages`, `can_edit_chat`,
+  `can_post_announcements`, `created_at`)
+SELECT c.`chat_id`, b.`name`, b.`base_role`, b.`granted`, b.`granted`, b.`granted`, b.`granted`,
+  b.`granted`, b.`granted`, b.`granted`, NOW()
+FROM `chats` c
+CROSS JOIN (
+  SELECT 'Owner' AS `name`, 'OWNER' AS `base_role`, 1 AS `granted`
+  UNION ALL SELECT 'Admin', 'ADMIN', 1
+  UNION ALL SELECT 'Member', 'MEMBER', 0
+) b;
+
+-- Owner e Admin puntano sempre alla loro riga base (il ruolo personalizzato non aggiungeva
+-- nulla), i Member mantengono l'eventuale ruolo personalizzato
+UPDATE `userchatmetadata` m
+JOIN `chat_roles` r ON r.`chat_id` = m.`chat_id` AND r.`base_role` = m.`user_role`
+SET m.`role_id` = r.`role_id`
+WHERE m.`role_id` IS NULL OR m.`user_role` <> 'MEMBER';
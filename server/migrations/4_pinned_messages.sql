@@ -0,0 +1,19 @@
+-- ============================================================================
+-- Messaggi fissati (pin) in una chat
+-- ============================================================================
+-- Un messaggio può essere fissato una sola volta per chat. I pin vengono
+-- rimossi automaticamente se il messaggio o la chat vengono eliminati.
+-- ============================================================================
+
+CREATE TABLE `pinned_messages` (
+  `chat_id` int NOT NULL,
+  `message_id` int NOT NULL,
+  `pinned_by` int NOT NULL,
+  `pinned_at` timestamp NOT NULL,
+  PRIMARY KEY (`chat_id`,`message_id`),
+  KEY `idx_PinnedMessages_message` (`message_id`),
+  KEY `idx_PinnedMessages_pinned_by` (`pinned_by`),
+  CONSTRAINT `pinned_messages_ibfk_1` FOREIGN KEY (`chat_id`) REFERENCES `chats` (`chat_id`) ON DELETE CASCADE,
+  CONSTRAINT `pinned_messages_ibfk_2` FOREIGN KEY (`message_id`) REFERENCES `messages` (`message_id`) ON DELETE CASCADE,
+  CONSTRAINT `pinned_messages_ibfk_3` FOREIGN KEY (`pinned_by`) REFERENCES `users` (`user_id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
@@ -0,0 +1,9 @@
+-- ============================================================================
+-- Presenza utenti: ultimo accesso
+-- ============================================================================
+-- `last_seen` viene aggiornato quando l'utente chiude la connessione WebSocket.
+-- NULL se l'utente non si è mai connesso.
+-- ============================================================================
+
+ALTER TABLE `users`
+  ADD COLUMN `last_seen` timestamp NULL DEFAULT NULL AFTER `password`;
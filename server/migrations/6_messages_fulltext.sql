@@ -0,0 +1,9 @@
+-- ============================================================================
+-- Ricerca full-text nei messaggi
+-- ============================================================================
+-- Indice FULLTEXT sul contenuto, usato da MessageRepository::search_in_chat
+-- con MATCH ... AGAINST.
+-- ============================================================================
+
+ALTER TABLE `messages`
+  ADD FULLTEXT KEY `idx_Messages_content_fulltext` (`content`);
@@ -0,0 +1,29 @@
+-- ============================================================================
+-- Allegati dei messaggi
+-- ============================================================================
+-- Il file viene caricato prima del messaggio (POST /chats/{chat_id}/attachments)
+-- e salvato sullo storage configurato (disco locale o S3 compatibile);
+-- `storage_key` è il percorso dell'oggetto all'interno dello storage.
+-- Il messaggio poi referenzia l'allegato tramite `attachment_id`.
+-- ============================================================================
+
+CREATE TABLE `attachments` (
+  `attachment_id` int NOT NULL AUTO_INCREMENT,
+  `chat_id` int NOT NULL,
+  `uploader_id` int NOT NULL,
+  `file_name` varchar(255) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `content_type` varchar(255) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `size_bytes` bigint NOT NULL,
+  `storage_key` varchar(512) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `created_at` timestamp NOT NULL,
+  PRIMARY KEY (`attachment_id`),
+  KEY `idx_Attachments_chat` (`chat_id`),
+  KEY `idx_Attachments_uploader` (`uploader_id`),
+  CONSTRAINT `attachments_ibfk_1` FOREIGN KEY (`chat_id`) REFERENCES `chats` (`chat_id`) ON DELETE CASCADE,
+  CONSTRAINT `attachments_ibfk_2` FOREIGN KEY (`uploader_id`) REFERENCES `users` (`user_id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+ALTER TABLE `messages`
+  ADD COLUMN `attachment_id` int NULL DEFAULT NULL AFTER `reply_to_message_id`,
+  ADD KEY `idx_Messages_attachment` (`attachment_id`),
+  ADD CONSTRAINT `messages_ibfk_4` FOREIGN KEY (`attachment_id`) REFERENCES `attachments` (`attachment_id`) ON DELETE SET NULL;
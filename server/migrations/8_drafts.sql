@@ -0,0 +1,17 @@
+-- ============================================================================
+-- Bozze dei messaggi sincronizzate tra dispositivi
+-- ============================================================================
+-- Al massimo una bozza per utente e chat; viene eliminata quando il client
+-- la svuota o quando l'utente o la chat vengono rimossi.
+-- ============================================================================
+
+CREATE TABLE `drafts` (
+  `user_id` int NOT NULL,
+  `chat_id` int NOT NULL,
+  `content` text COLLATE utf8mb4_unicode_ci NOT NULL,
+  `updated_at` timestamp NOT NULL,
+  PRIMARY KEY (`user_id`,`chat_id`),
+  KEY `idx_Drafts_chat` (`chat_id`),
+  CONSTRAINT `drafts_ibfk_1` FOREIGN KEY (`user_id`) REFERENCES `users` (`user_id`) ON DELETE CASCADE,
+  CONSTRAINT `drafts_ibfk_2` FOREIGN KEY (`chat_id`) REFERENCES `chats` (`chat_id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
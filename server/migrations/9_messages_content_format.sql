@@ -0,0 +1,9 @@
+-- ============================================================================
+-- Formato del contenuto dei messaggi
+-- ============================================================================
+-- `content_format` indica al client come renderizzare il contenuto.
+-- I messaggi esistenti restano testo semplice.
+-- ============================================================================
+
+ALTER TABLE `messages`
+  ADD COLUMN `content_format` enum('PLAIN','MARKDOWN') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'PLAIN' AFTER `message_type`;
@@ -0,0 +1,323 @@
+-- ============================================================================
+-- Schema SQLite
+-- ============================================================================
+-- Versione per SQLite dello schema che si ottiene applicando tutte le
+-- migrazioni MySQL in `migrations/` (fino a 40_chat_roles_base.sql), usata
+-- quando DATABASE_URL inizia con `sqlite:`. Il server applica le migrazioni di
+-- questa cartella all'avvio. Una modifica allo schema va scritta in entrambe
+-- le cartelle, qui come nuova migrazione numerata.
+--
+-- Differenze rispetto a MySQL:
+-- - gli enum sono TEXT con un CHECK sui valori ammessi
+-- - i booleani sono INTEGER 0/1
+-- - i timestamp sono TEXT in formato RFC 3339 UTC, come li scrive sqlx:
+--   l'ordine alfabetico coincide con quello cronologico
+-- - username e nomi dei ruoli usano COLLATE NOCASE al posto della collation
+--   utf8mb4_unicode_ci (il confronto ignora le maiuscole solo per i caratteri ASCII)
+-- - la ricerca full-text su `messages.content` non ha un indice dedicato
+-- ============================================================================
+
+CREATE TABLE users (
+  user_id INTEGER PRIMARY KEY AUTOINCREMENT,
+  username TEXT NOT NULL UNIQUE COLLATE NOCASE,
+  password TEXT NOT NULL,
+  display_name TEXT DEFAULT NULL,
+  bio TEXT DEFAULT NULL,
+  avatar_url TEXT DEFAULT NULL,
+  status_text TEXT DEFAULT NULL,
+  status_expires_at TEXT DEFAULT NULL,
+  last_seen TEXT DEFAULT NULL,
+  presence_visibility TEXT NOT NULL DEFAULT 'EVERYONE'
+    CHECK (presence_visibility IN ('EVERYONE', 'CONTACTS', 'NOBODY')),
+  deactivated_at TEXT DEFAULT NULL
+);
+
+CREATE TABLE chats (
+  chat_id INTEGER PRIMARY KEY AUTOINCREMENT,
+  title TEXT DEFAULT NULL,
+  description TEXT DEFAULT NULL,
+  chat_type TEXT NOT NULL CHECK (chat_type IN ('GROUP', 'PRIVATE')),
+  avatar_attachment_id INTEGER DEFAULT NULL,
+  announcement_only INTEGER NOT NULL DEFAULT 0,
+  is_public INTEGER NOT NULL DEFAULT 0,
+  requires_approval INTEGER NOT NULL DEFAULT 0,
+  invite_policy TEXT NOT NULL DEFAULT 'ADMINS_ONLY'
+    CHECK (invite_policy IN ('EVERYONE', 'ADMINS_ONLY')),
+  content_filter TEXT NOT NULL DEFAULT 'OFF'
+    CHECK (content_filter IN ('OFF', 'FLAG', 'REDACT', 'REJECT'))
+);
+
+CREATE INDEX idx_chats_is_public ON chats (is_public);
+
+CREATE TABLE attachments (
+  attachment_id INTEGER PRIMARY KEY AUTOINCREMENT,
+  chat_id INTEGER NOT NULL REFERENCES chats (chat_id) ON DELETE CASCADE,
+  uploader_id INTEGER NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+  file_name TEXT NOT NULL,
+  content_type TEXT NOT NULL,
+  size_bytes INTEGER NOT NULL,
+  storage_key TEXT NOT NULL,
+  ref_count INTEGER NOT NULL DEFAULT 0,
+  created_at TEXT NOT NULL
+);
+
+CREATE INDEX idx_Attachments_chat ON attachments (chat_id);
+CREATE INDEX idx_Attachments_uploader ON attachments (uploader_id);
+CREATE INDEX idx_attachments_orphans ON attachments (ref_count, created_at);
+
+CREATE TABLE messages (
+  message_id INTEGER PRIMARY KEY AUTOINCREMENT,
+  chat_id INTEGER NOT NULL REFERENCES chats (chat_id) ON DELETE CASCADE,
+  sender_id INTEGER NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+  content TEXT NOT NULL,
+  message_type TEXT NOT NULL DEFAULT 'USERMESSAGE'
+    CHECK (message_type IN ('USERMESSAGE', 'SYSTEMMESSAGE')),
+  content_format TEXT NOT NULL DEFAULT 'PLAIN' CHECK (content_format IN ('PLAIN', 'MARKDOWN')),
+  reply_to_message_id INTEGER DEFAULT NULL REFERENCES messages (message_id) ON DELETE SET NULL,
+  attachment_id INTEGER DEFAULT NULL REFERENCES attachments (attachment_id) ON DELETE SET NULL,
+  client_msg_id TEXT DEFAULT NULL,
+  created_at TEXT NOT NULL,
+  deleted_at TEXT DEFAULT NULL,
+  UNIQUE (sender_id, client_msg_id)
+);
+
+CREATE INDEX idx_Messages_chat_createdAt ON messages (chat_id, created_at DESC);
+CREATE INDEX idx_Messages_sender ON messages (sender_id);
+CREATE INDEX idx_Messages_reply_to ON messages (reply_to_message_id);
+CREATE INDEX idx_Messages_attachment ON messages (attachment_id);
+
+CREATE TABLE message_revisions (
+  revision_id INTEGER PRIMARY KEY AUTOINCREMENT,
+  message_id INTEGER NOT NULL REFERENCES messages (message_id) ON DELETE CASCADE,
+  content TEXT NOT NULL,
+  replaced_at TEXT NOT NULL
+);
+
+CREATE INDEX idx_message_revisions_message ON message_revisions (message_id, revision_id);
+
+CREATE TABLE chat_roles (
+  role_id INTEGER PRIMARY KEY AUTOINCREMENT,
+  chat_id INTEGER NOT NULL REFERENCES chats (chat_id) ON DELETE CASCADE,
+  name TEXT NOT NULL COLLATE NOCASE,
+  base_role TEXT DEFAULT NULL CHECK (base_role IN ('OWNER', 'ADMIN', 'MEMBER')),
+  can_invite INTEGER NOT NULL DEFAULT 0,
+  can_remove_members INTEGER NOT NULL DEFAULT 0,
+  can_moderate INTEGER NOT NULL DEFAULT 0,
+  can_delete_messages INTEGER NOT NULL DEFAULT 0,
+  can_pin_messages INTEGER NOT NULL DEFAULT 0,
+  can_edit_chat INTEGER NOT NULL DEFAULT 0,
+  can_post_announcements INTEGER NOT NULL DEFAULT 0,
+  created_at TEXT NOT NULL,
+  UNIQUE (chat_id, name),
+  UNIQUE (chat_id, base_role)
+);
+
+CREATE TABLE userchatmetadata (
+  user_id INTEGER NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+  chat_id INTEGER NOT NULL REFERENCES chats (chat_id) ON DELETE CASCADE,
+  messages_visible_from TEXT NOT NULL,
+  messages_received_until TEXT NOT NULL,
+  unread_count INTEGER NOT NULL DEFAULT 0,
+  user_role TEXT DEFAULT NULL CHECK (user_role IN ('OWNER', 'ADMIN', 'MEMBER')),
+  role_id INTEGER DEFAULT NULL REFERENCES chat_roles (role_id) ON DELETE SET NULL,
+  member_since TEXT NOT NULL,
+  pinned_at TEXT DEFAULT NULL,
+  muted_until TEXT DEFAULT NULL,
+  version INTEGER NOT NULL DEFAULT 0,
+  PRIMARY KEY (chat_id, user_id)
+);
+
+CREATE INDEX idx_UCM_user ON userchatmetadata (user_id);
+CREATE INDEX idx_UCM_chat ON userchatmetadata (chat_id);
+CREATE INDEX idx_UCM_role ON userchatmetadata (role_id);
+
+CREATE TABLE invitations (
+  invite_id INTEGER PRIMARY KEY AUTOINCREMENT,
+  target_chat_id INTEGER NOT NULL REFERENCES chats (chat_id) ON DELETE CASCADE,
+  invited_id INTEGER NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+  invitee_id INTEGER NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+  note TEXT DEFAULT NULL,
+  state TEXT NOT NULL DEFAULT 'PENDING' CHECK (state IN ('PENDING', 'ACCEPTED', 'REJECTED')),
+  created_at TEXT NOT NULL,
+  updated_at TEXT DEFAULT NULL,
+  UNIQUE (target_chat_id, invited_id, state)
+);
+
+CREATE INDEX idx_Invitations_group ON invitations (target_chat_id);
+CREATE INDEX idx_Invitations_invited_user ON invitations (invited_id);
+CREATE INDEX idx_Invitations_invitee_user ON invitations (invitee_id);
+CREATE INDEX idx_invitations_inviter_updated ON invitations (invitee_id, updated_at);
+
+CREATE TABLE pinned_messages (
+  chat_id INTEGER NOT NULL REFERENCES chats (chat_id) ON DELETE CASCADE,
+  message_id INTEGER NOT NULL REFERENCES messages (message_id) ON DELETE CASCADE,
+  pinned_by INTEGER NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+  pinned_at TEXT NOT NULL,
+  PRIMARY KEY (chat_id, message_id)
+);
+
+CREATE INDEX idx_PinnedMessages_message ON pinned_messages (message_id);
+CREATE INDEX idx_PinnedMessages_pinned_by ON pinned_messages (pinned_by);
+
+CREATE TABLE drafts (
+  user_id INTEGER NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+  chat_id INTEGER NOT NULL REFERENCES chats (chat_id) ON DELETE CASCADE,
+  content TEXT NOT NULL,
+  updated_at TEXT NOT NULL,
+  PRIMARY KEY (user_id, chat_id)
+);
+
+CREATE INDEX idx_Drafts_chat ON drafts (chat_id);
+
+CREATE TABLE banned_members (
+  user_id INTEGER NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+  chat_id INTEGER NOT NULL REFERENCES chats (chat_id) ON DELETE CASCADE,
+  banned_by INTEGER NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+  banned_at TEXT NOT NULL,
+  PRIMARY KEY (user_id, chat_id)
+);
+
+CREATE INDEX idx_BannedMembers_chat ON banned_members (chat_id);
+CREATE INDEX idx_BannedMembers_banned_by ON banned_members (banned_by);
+
+CREATE TABLE audit_log (
+  audit_id INTEGER PRIMARY KEY AUTOINCREMENT,
+  chat_id INTEGER NOT NULL REFERENCES chats (chat_id) ON DELETE CASCADE,
+  actor_id INTEGER DEFAULT NULL REFERENCES users (user_id) ON DELETE SET NULL,
+  target_user_id INTEGER DEFAULT NULL REFERENCES users (user_id) ON DELETE SET NULL,
+  action TEXT NOT NULL CHECK (action IN ('MEMBER_ROLE_CHANGED', 'MEMBER_REMOVED',
+    'MEMBER_BANNED', 'MEMBER_MUTED', 'OWNERSHIP_TRANSFERRED', 'CHAT_UPDATED', 'MESSAGE_DELETED',
+    'CUSTOM_ROLE_CREATED', 'CUSTOM_ROLE_DELETED', 'CUSTOM_ROLE_ASSIGNED', 'WEBHOOK_CREATED',
+    'WEBHOOK_DELETED', 'INVITE_LINK_CREATED', 'INVITE_LINK_REVOKED')),
+  payload TEXT DEFAULT NULL,
+  created_at TEXT NOT NULL
+);
+
+CREATE INDEX idx_AuditLog_chat_created ON audit_log (chat_id, created_at);
+CREATE INDEX idx_AuditLog_actor ON audit_log (actor_id);
+CREATE INDEX idx_AuditLog_target ON audit_log (target_user_id);
+
+CREATE TABLE join_requests (
+  request_id INTEGER PRIMARY KEY AUTOINCREMENT,
+  chat_id INTEGER NOT NULL REFERENCES chats (chat_id) ON DELETE CASCADE,
+  user_id INTEGER NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+  state TEXT NOT NULL DEFAULT 'PENDING' CHECK (state IN ('PENDING', 'APPROVED', 'DENIED')),
+  created_at TEXT NOT NULL,
+  resolved_by INTEGER DEFAULT NULL REFERENCES users (user_id) ON DELETE SET NULL,
+  resolved_at TEXT DEFAULT NULL
+);
+
+CREATE INDEX idx_JoinRequests_chat_state ON join_requests (chat_id, state);
+CREATE INDEX idx_JoinRequests_user ON join_requests (user_id);
+CREATE INDEX idx_JoinRequests_resolved_by ON join_requests (resolved_by);
+
+CREATE TABLE data_exports (
+  export_id INTEGER PRIMARY KEY AUTOINCREMENT,
+  user_id INTEGER NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+  state TEXT NOT NULL DEFAULT 'PENDING' CHECK (state IN ('PENDING', 'COMPLETED', 'FAILED')),
+  created_at TEXT NOT NULL,
+  completed_at TEXT DEFAULT NULL,
+  storage_key TEXT DEFAULT NULL
+);
+
+CREATE INDEX idx_DataExports_user ON data_exports (user_id, created_at);
+
+CREATE TABLE refresh_tokens (
+  token_id INTEGER PRIMARY KEY AUTOINCREMENT,
+  user_id INTEGER NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+  family_id TEXT NOT NULL,
+  token_hash TEXT NOT NULL UNIQUE,
+  created_at TEXT NOT NULL,
+  expires_at TEXT NOT NULL,
+  used_at TEXT DEFAULT NULL,
+  revoked_at TEXT DEFAULT NULL
+);
+
+CREATE INDEX idx_RefreshTokens_family ON refresh_tokens (family_id);
+CREATE INDEX idx_RefreshTokens_user ON refresh_tokens (user_id);
+
+CREATE TABLE user_identities (
+  identity_id INTEGER PRIMARY KEY AUTOINCREMENT,
+  user_id INTEGER NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+  issuer TEXT NOT NULL,
+  subject TEXT NOT NULL,
+  created_at TEXT NOT NULL,
+  UNIQUE (issuer, subject)
+);
+
+CREATE INDEX idx_UserIdentities_user ON user_identities (user_id);
+
+CREATE TABLE webhooks (
+  webhook_id INTEGER PRIMARY KEY AUTOINCREMENT,
+  chat_id INTEGER NOT NULL REFERENCES chats (chat_id) ON DELETE CASCADE,
+  bot_user_id INTEGER NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+  name TEXT NOT NULL,
+  token_hash TEXT NOT NULL UNIQUE,
+  created_by INTEGER DEFAULT NULL REFERENCES users (user_id) ON DELETE SET NULL,
+  created_at TEXT NOT NULL
+);
+
+CREATE INDEX idx_Webhooks_chat ON webhooks (chat_id);
+CREATE INDEX idx_Webhooks_bot_user ON webhooks (bot_user_id);
+CREATE INDEX idx_Webhooks_created_by ON webhooks (created_by);
+
+CREATE TABLE reports (
+  report_id INTEGER PRIMARY KEY AUTOINCREMENT,
+  reporter_id INTEGER DEFAULT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+  reported_user_id INTEGER NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+  chat_id INTEGER DEFAULT NULL REFERENCES chats (chat_id) ON DELETE CASCADE,
+  message_id INTEGER DEFAULT NULL REFERENCES messages (message_id) ON DELETE CASCADE,
+  reason TEXT NOT NULL CHECK (reason IN ('SPAM', 'HARASSMENT', 'INAPPROPRIATE_CONTENT',
+    'IMPERSONATION', 'OTHER')),
+  details TEXT DEFAULT NULL,
+  state TEXT NOT NULL DEFAULT 'PENDING' CHECK (state IN ('PENDING', 'RESOLVED', 'DISMISSED')),
+  created_at TEXT NOT NULL,
+  resolved_by INTEGER DEFAULT NULL REFERENCES users (user_id) ON DELETE SET NULL,
+  resolved_at TEXT DEFAULT NULL
+);
+
+CREATE INDEX idx_Reports_state_created ON reports (state, created_at);
+CREATE INDEX idx_Reports_reporter ON reports (reporter_id);
+CREATE INDEX idx_Reports_reported_user ON reports (reported_user_id);
+CREATE INDEX idx_Reports_chat ON reports (chat_id);
+CREATE INDEX idx_Reports_message ON reports (message_id);
+CREATE INDEX idx_Reports_resolved_by ON reports (resolved_by);
+
+CREATE TABLE user_keys (
+  user_id INTEGER PRIMARY KEY REFERENCES users (user_id) ON DELETE CASCADE,
+  identity_key TEXT NOT NULL,
+  signed_prekey_id INTEGER NOT NULL,
+  signed_prekey TEXT NOT NULL,
+  signed_prekey_signature TEXT NOT NULL,
+  updated_at TEXT NOT NULL
+);
+
+CREATE TABLE one_time_prekeys (
+  user_id INTEGER NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+  prekey_id INTEGER NOT NULL,
+  public_key TEXT NOT NULL,
+  PRIMARY KEY (user_id, prekey_id)
+);
+
+CREATE TABLE offline_queue (
+  user_id INTEGER NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+  message_id INTEGER NOT NULL REFERENCES messages (message_id) ON DELETE CASCADE,
+  enqueued_at TEXT NOT NULL,
+  delivered_at TEXT DEFAULT NULL,
+  PRIMARY KEY (user_id, message_id)
+);
+
+CREATE INDEX idx_offline_queue_pending ON offline_queue (user_id, delivered_at, message_id);
+CREATE INDEX idx_offline_queue_message ON offline_queue (message_id);
+
+CREATE TABLE invite_links (
+  link_id INTEGER PRIMARY KEY AUTOINCREMENT,
+  chat_id INTEGER NOT NULL REFERENCES chats (chat_id) ON DELETE CASCADE,
+  token_hash TEXT NOT NULL UNIQUE,
+  created_by INTEGER DEFAULT NULL REFERENCES users (user_id) ON DELETE SET NULL,
+  created_at TEXT NOT NULL,
+  expires_at TEXT DEFAULT NULL
+);
+
+CREATE INDEX idx_InviteLinks_chat ON invite_links (chat_id);
+CREATE INDEX idx_InviteLinks_created_by ON invite_links (created_by);
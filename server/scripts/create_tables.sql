@@ -49,6 +49,101 @@ USE `rugginedb`;
 /*!40101 SET @OLD_SQL_MODE=@@SQL_MODE, SQL_MODE='NO_AUTO_VALUE_ON_ZERO' */;
 /*!40111 SET @OLD_SQL_NOTES=@@SQL_NOTES, SQL_NOTES=0 */;
 
+--
+-- Table structure for table `attachments`
+--
+
+DROP TABLE IF EXISTS `attachments`;
+/*!40101 SET @saved_cs_client     = @@character_set_client */;
+/*!50503 SET character_set_client = utf8mb4 */;
+CREATE TABLE `attachments` (
+  `attachment_id` int NOT NULL AUTO_INCREMENT,
+  `chat_id` int NOT NULL,
+  `uploader_id` int NOT NULL,
+  `file_name` varchar(255) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `content_type` varchar(255) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `size_bytes` bigint NOT NULL,
+  `storage_key` varchar(512) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `created_at` timestamp NOT NULL,
+  PRIMARY KEY (`attachment_id`),
+  KEY `idx_Attachments_chat` (`chat_id`),
+  KEY `idx_Attachments_uploader` (`uploader_id`),
+  CONSTRAINT `attachments_ibfk_1` FOREIGN KEY (`chat_id`) REFERENCES `chats` (`chat_id`) ON DELETE CASCADE,
+  CONSTRAINT `attachments_ibfk_2` FOREIGN KEY (`uploader_id`) REFERENCES `users` (`user_id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+/*!40101 SET character_set_client = @saved_cs_client */;
+
+--
+-- Table structure for table `audit_log`
+--
+
+DROP TABLE IF EXISTS `audit_log`;
+/*!40101 SET @saved_cs_client     = @@character_set_client */;
+/*!50503 SET character_set_client = utf8mb4 */;
+CREATE TABLE `audit_log` (
+  `audit_id` int NOT NULL AUTO_INCREMENT,
+  `chat_id` int NOT NULL,
+  `actor_id` int DEFAULT NULL,
+  `target_user_id` int DEFAULT NULL,
+  `action` enum('MEMBER_ROLE_CHANGED','MEMBER_REMOVED','MEMBER_BANNED','MEMBER_MUTED','OWNERSHIP_TRANSFERRED','CHAT_UPDATED','MESSAGE_DELETED','CUSTOM_ROLE_CREATED','CUSTOM_ROLE_DELETED','CUSTOM_ROLE_ASSIGNED','WEBHOOK_CREATED','WEBHOOK_DELETED') COLLATE utf8mb4_unicode_ci NOT NULL,
+  `payload` text COLLATE utf8mb4_unicode_ci,
+  `created_at` timestamp NOT NULL,
+  PRIMARY KEY (`audit_id`),
+  KEY `idx_AuditLog_chat_created` (`chat_id`,`created_at`),
+  KEY `idx_AuditLog_actor` (`actor_id`),
+  KEY `idx_AuditLog_target` (`target_user_id`),
+  CONSTRAINT `audit_log_ibfk_1` FOREIGN KEY (`chat_id`) REFERENCES `chats` (`chat_id`) ON DELETE CASCADE,
+  CONSTRAINT `audit_log_ibfk_2` FOREIGN KEY (`actor_id`) REFERENCES `users` (`user_id`) ON DELETE SET NULL,
+  CONSTRAINT `audit_log_ibfk_3` FOREIGN KEY (`target_user_id`) REFERENCES `users` (`user_id`) ON DELETE SET NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+/*!40101 SET character_set_client = @saved_cs_client */;
+
+--
+-- Table structure for table `banned_members`
+--
+
+DROP TABLE IF EXISTS `banned_members`;
+/*!40101 SET @saved_cs_client     = @@character_set_client */;
+/*!50503 SET character_set_client = utf8mb4 */;
+CREATE TABLE `banned_members` (
+  `user_id` int NOT NULL,
+  `chat_id` int NOT NULL,
+  `banned_by` int NOT NULL,
+  `banned_at` timestamp NOT NULL,
+  PRIMARY KEY (`user_id`,`chat_id`),
+  KEY `idx_BannedMembers_chat` (`chat_id`),
+  KEY `idx_BannedMembers_banned_by` (`banned_by`),
+  CONSTRAINT `banned_members_ibfk_1` FOREIGN KEY (`user_id`) REFERENCES `users` (`user_id`) ON DELETE CASCADE,
+  CONSTRAINT `banned_members_ibfk_2` FOREIGN KEY (`chat_id`) REFERENCES `chats` (`chat_id`) ON DELETE CASCADE,
+  CONSTRAINT `banned_members_ibfk_3` FOREIGN KEY (`banned_by`) REFERENCES `users` (`user_id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+/*!40101 SET character_set_client = @saved_cs_client */;
+
+--
+-- Table structure for table `chat_roles`
+--
+
+DROP TABLE IF EXISTS `chat_roles`;
+/*!40101 SET @saved_cs_client     = @@character_set_client */;
+/*!50503 SET character_set_client = utf8mb4 */;
+CREATE TABLE `chat_roles` (
+  `role_id` int NOT NULL AUTO_INCREMENT,
+  `chat_id` int NOT NULL,
+  `name` varchar(50) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `can_invite` tinyint(1) NOT NULL DEFAULT '0',
+  `can_remove_members` tinyint(1) NOT NULL DEFAULT '0',
+  `can_moderate` tinyint(1) NOT NULL DEFAULT '0',
+  `can_delete_messages` tinyint(1) NOT NULL DEFAULT '0',
+  `can_pin_messages` tinyint(1) NOT NULL DEFAULT '0',
+  `can_edit_chat` tinyint(1) NOT NULL DEFAULT '0',
+  `can_post_announcements` tinyint(1) NOT NULL DEFAULT '0',
+  `created_at` timestamp NOT NULL,
+  PRIMARY KEY (`role_id`),
+  UNIQUE KEY `uq_ChatRoles_chat_name` (`chat_id`,`name`),
+  CONSTRAINT `chat_roles_ibfk_1` FOREIGN KEY (`chat_id`) REFERENCES `chats` (`chat_id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+/*!40101 SET character_set_client = @saved_cs_client */;
+
 --
 -- Table structure for table `chats`
 --
@@ -61,7 +156,52 @@ CREATE TABLE `chats` (
   `title` varchar(255) COLLATE utf8mb4_unicode_ci DEFAULT NULL,
   `description` text COLLATE utf8mb4_unicode_ci,
   `chat_type` enum('GROUP','PRIVATE') COLLATE utf8mb4_unicode_ci NOT NULL,
-  PRIMARY KEY (`chat_id`)
+  `avatar_attachment_id` int DEFAULT NULL,
+  `announcement_only` tinyint(1) NOT NULL DEFAULT '0',
+  `is_public` tinyint(1) NOT NULL DEFAULT '0',
+  `requires_approval` tinyint(1) NOT NULL DEFAULT '0',
+  `invite_policy` enum('EVERYONE','ADMINS_ONLY') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'ADMINS_ONLY',
+  PRIMARY KEY (`chat_id`),
+  KEY `idx_chats_is_public` (`is_public`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+/*!40101 SET character_set_client = @saved_cs_client */;
+
+--
+-- Table structure for table `data_exports`
+--
+
+DROP TABLE IF EXISTS `data_exports`;
+/*!40101 SET @saved_cs_client     = @@character_set_client */;
+/*!50503 SET character_set_client = utf8mb4 */;
+CREATE TABLE `data_exports` (
+  `export_id` int NOT NULL AUTO_INCREMENT,
+  `user_id` int NOT NULL,
+  `state` enum('PENDING','COMPLETED','FAILED') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'PENDING',
+  `created_at` timestamp NOT NULL,
+  `completed_at` timestamp NULL DEFAULT NULL,
+  `storage_key` varchar(255) COLLATE utf8mb4_unicode_ci DEFAULT NULL,
+  PRIMARY KEY (`export_id`),
+  KEY `idx_DataExports_user` (`user_id`,`created_at`),
+  CONSTRAINT `data_exports_ibfk_1` FOREIGN KEY (`user_id`) REFERENCES `users` (`user_id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+/*!40101 SET character_set_client = @saved_cs_client */;
+
+--
+-- Table structure for table `drafts`
+--
+
+DROP TABLE IF EXISTS `drafts`;
+/*!40101 SET @saved_cs_client     = @@character_set_client */;
+/*!50503 SET character_set_client = utf8mb4 */;
+CREATE TABLE `drafts` (
+  `user_id` int NOT NULL,
+  `chat_id` int NOT NULL,
+  `content` text COLLATE utf8mb4_unicode_ci NOT NULL,
+  `updated_at` timestamp NOT NULL,
+  PRIMARY KEY (`user_id`,`chat_id`),
+  KEY `idx_Drafts_chat` (`chat_id`),
+  CONSTRAINT `drafts_ibfk_1` FOREIGN KEY (`user_id`) REFERENCES `users` (`user_id`) ON DELETE CASCADE,
+  CONSTRAINT `drafts_ibfk_2` FOREIGN KEY (`chat_id`) REFERENCES `chats` (`chat_id`) ON DELETE CASCADE
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
 /*!40101 SET character_set_client = @saved_cs_client */;
 
@@ -77,6 +217,7 @@ CREATE TABLE `invitations` (
   `target_chat_id` int NOT NULL,
   `invited_id` int NOT NULL,
   `invitee_id` int NOT NULL,
+  `note` varchar(200) COLLATE utf8mb4_unicode_ci DEFAULT NULL,
   `state` enum('PENDING','ACCEPTED','REJECTED') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'PENDING',
   `created_at` timestamp NOT NULL,
   PRIMARY KEY (`invite_id`),
@@ -89,6 +230,31 @@ CREATE TABLE `invitations` (
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
 /*!40101 SET character_set_client = @saved_cs_client */;
 
+--
+-- Table structure for table `join_requests`
+--
+
+DROP TABLE IF EXISTS `join_requests`;
+/*!40101 SET @saved_cs_client     = @@character_set_client */;
+/*!50503 SET character_set_client = utf8mb4 */;
+CREATE TABLE `join_requests` (
+  `request_id` int NOT NULL AUTO_INCREMENT,
+  `chat_id` int NOT NULL,
+  `user_id` int NOT NULL,
+  `state` enum('PENDING','APPROVED','DENIED') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'PENDING',
+  `created_at` timestamp NOT NULL,
+  `resolved_by` int DEFAULT NULL,
+  `resolved_at` timestamp NULL DEFAULT NULL,
+  PRIMARY KEY (`request_id`),
+  KEY `idx_JoinRequests_chat_state` (`chat_id`,`state`),
+  KEY `idx_JoinRequests_user` (`user_id`),
+  KEY `idx_JoinRequests_resolved_by` (`resolved_by`),
+  CONSTRAINT `join_requests_ibfk_1` FOREIGN KEY (`chat_id`) REFERENCES `chats` (`chat_id`) ON DELETE CASCADE,
+  CONSTRAINT `join_requests_ibfk_2` FOREIGN KEY (`user_id`) REFERENCES `users` (`user_id`) ON DELETE CASCADE,
+  CONSTRAINT `join_requests_ibfk_3` FOREIGN KEY (`resolved_by`) REFERENCES `users` (`user_id`) ON DELETE SET NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+/*!40101 SET character_set_client = @saved_cs_client */;
+
 --
 -- Table structure for table `messages`
 --
@@ -102,12 +268,88 @@ CREATE TABLE `messages` (
   `sender_id` int NOT NULL,
   `content` text COLLATE utf8mb4_unicode_ci NOT NULL,
   `message_type` enum('USERMESSAGE','SYSTEMMESSAGE') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'USERMESSAGE',
+  `content_format` enum('PLAIN','MARKDOWN') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'PLAIN',
   `created_at` timestamp NOT NULL,
+  `deleted_at` timestamp NULL DEFAULT NULL,
+  `reply_to_message_id` int DEFAULT NULL,
+  `attachment_id` int DEFAULT NULL,
+  `client_msg_id` varchar(64) COLLATE utf8mb4_unicode_ci DEFAULT NULL,
   PRIMARY KEY (`message_id`),
+  UNIQUE KEY `uq_Messages_sender_client_msg_id` (`sender_id`,`client_msg_id`),
   KEY `idx_Messages_chat_createdAt` (`chat_id`,`created_at` DESC),
   KEY `idx_Messages_sender` (`sender_id`),
+  KEY `idx_Messages_reply_to` (`reply_to_message_id`),
+  KEY `idx_Messages_attachment` (`attachment_id`),
+  FULLTEXT KEY `idx_Messages_content_fulltext` (`content`),
   CONSTRAINT `messages_ibfk_1` FOREIGN KEY (`chat_id`) REFERENCES `chats` (`chat_id`) ON DELETE CASCADE,
-  CONSTRAINT `messages_ibfk_2` FOREIGN KEY (`sender_id`) REFERENCES `users` (`user_id`) ON DELETE CASCADE
+  CONSTRAINT `messages_ibfk_2` FOREIGN KEY (`sender_id`) REFERENCES `users` (`user_id`) ON DELETE CASCADE,
+  CONSTRAINT `messages_ibfk_3` FOREIGN KEY (`reply_to_message_id`) REFERENCES `messages` (`message_id`) ON DELETE SET NULL,
+  CONSTRAINT `messages_ibfk_4` FOREIGN KEY (`attachment_id`) REFERENCES `attachments` (`attachment_id`) ON DELETE SET NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+/*!40101 SET character_set_client = @saved_cs_client */;
+
+--
+-- Table structure for table `pinned_messages`
+--
+
+DROP TABLE IF EXISTS `pinned_messages`;
+/*!40101 SET @saved_cs_client     = @@character_set_client */;
+/*!50503 SET character_set_client = utf8mb4 */;
+CREATE TABLE `pinned_messages` (
+  `chat_id` int NOT NULL,
+  `message_id` int NOT NULL,
+  `pinned_by` int NOT NULL,
+  `pinned_at` timestamp NOT NULL,
+  PRIMARY KEY (`chat_id`,`message_id`),
+  KEY `idx_PinnedMessages_message` (`message_id`),
+  KEY `idx_PinnedMessages_pinned_by` (`pinned_by`),
+  CONSTRAINT `pinned_messages_ibfk_1` FOREIGN KEY (`chat_id`) REFERENCES `chats` (`chat_id`) ON DELETE CASCADE,
+  CONSTRAINT `pinned_messages_ibfk_2` FOREIGN KEY (`message_id`) REFERENCES `messages` (`message_id`) ON DELETE CASCADE,
+  CONSTRAINT `pinned_messages_ibfk_3` FOREIGN KEY (`pinned_by`) REFERENCES `users` (`user_id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+/*!40101 SET character_set_client = @saved_cs_client */;
+
+--
+-- Table structure for table `refresh_tokens`
+--
+
+DROP TABLE IF EXISTS `refresh_tokens`;
+/*!40101 SET @saved_cs_client     = @@character_set_client */;
+/*!50503 SET character_set_client = utf8mb4 */;
+CREATE TABLE `refresh_tokens` (
+  `token_id` int NOT NULL AUTO_INCREMENT,
+  `user_id` int NOT NULL,
+  `family_id` char(36) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `token_hash` char(64) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `created_at` timestamp NOT NULL,
+  `expires_at` timestamp NOT NULL,
+  `used_at` timestamp NULL DEFAULT NULL,
+  `revoked_at` timestamp NULL DEFAULT NULL,
+  PRIMARY KEY (`token_id`),
+  UNIQUE KEY `uq_RefreshTokens_hash` (`token_hash`),
+  KEY `idx_RefreshTokens_family` (`family_id`),
+  KEY `idx_RefreshTokens_user` (`user_id`),
+  CONSTRAINT `refresh_tokens_ibfk_1` FOREIGN KEY (`user_id`) REFERENCES `users` (`user_id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+/*!40101 SET character_set_client = @saved_cs_client */;
+
+--
+-- Table structure for table `user_identities`
+--
+
+DROP TABLE IF EXISTS `user_identities`;
+/*!40101 SET @saved_cs_client     = @@character_set_client */;
+/*!50503 SET character_set_client = utf8mb4 */;
+CREATE TABLE `user_identities` (
+  `identity_id` int NOT NULL AUTO_INCREMENT,
+  `user_id` int NOT NULL,
+  `issuer` varchar(255) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `subject` varchar(255) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `created_at` timestamp NOT NULL,
+  PRIMARY KEY (`identity_id`),
+  UNIQUE KEY `uq_UserIdentities_subject` (`issuer`,`subject`),
+  KEY `idx_UserIdentities_user` (`user_id`),
+  CONSTRAINT `user_identities_ibfk_1` FOREIGN KEY (`user_id`) REFERENCES `users` (`user_id`) ON DELETE CASCADE
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
 /*!40101 SET character_set_client = @saved_cs_client */;
 
@@ -124,12 +366,17 @@ CREATE TABLE `userchatmetadata` (
   `messages_visible_from` timestamp NOT NULL,
   `messages_received_until` timestamp NOT NULL,
   `user_role` enum('OWNER','ADMIN','MEMBER') COLLATE utf8mb4_unicode_ci DEFAULT NULL,
+  `role_id` int DEFAULT NULL,
   `member_since` timestamp NOT NULL,
+  `pinned_at` timestamp NULL DEFAULT NULL,
+  `muted_until` timestamp NULL DEFAULT NULL,
   PRIMARY KEY (`chat_id`,`user_id`),
   KEY `idx_UCM_user` (`user_id`),
   KEY `idx_UCM_chat` (`chat_id`),
+  KEY `idx_UCM_role` (`role_id`),
   CONSTRAINT `userchatmetadata_ibfk_1` FOREIGN KEY (`chat_id`) REFERENCES `chats` (`chat_id`) ON DELETE CASCADE,
-  CONSTRAINT `userchatmetadata_ibfk_2` FOREIGN KEY (`user_id`) REFERENCES `users` (`user_id`) ON DELETE CASCADE
+  CONSTRAINT `userchatmetadata_ibfk_2` FOREIGN KEY (`user_id`) REFERENCES `users` (`user_id`) ON DELETE CASCADE,
+  CONSTRAINT `userchatmetadata_ibfk_3` FOREIGN KEY (`role_id`) REFERENCES `chat_roles` (`role_id`) ON DELETE SET NULL
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
 /*!40101 SET character_set_client = @saved_cs_client */;
 
@@ -144,10 +391,44 @@ CREATE TABLE `users` (
   `user_id` int NOT NULL AUTO_INCREMENT,
   `username` varchar(255) COLLATE utf8mb4_unicode_ci NOT NULL,
   `password` text COLLATE utf8mb4_unicode_ci NOT NULL,
+  `display_name` varchar(50) COLLATE utf8mb4_unicode_ci DEFAULT NULL,
+  `bio` varchar(500) COLLATE utf8mb4_unicode_ci DEFAULT NULL,
+  `avatar_url` varchar(500) COLLATE utf8mb4_unicode_ci DEFAULT NULL,
+  `status_text` varchar(100) COLLATE utf8mb4_unicode_ci DEFAULT NULL,
+  `status_expires_at` timestamp NULL DEFAULT NULL,
+  `last_seen` timestamp NULL DEFAULT NULL,
+  `presence_visibility` enum('EVERYONE','CONTACTS','NOBODY') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'EVERYONE',
+  `deactivated_at` timestamp NULL DEFAULT NULL,
   PRIMARY KEY (`user_id`),
   UNIQUE KEY `username` (`username`)
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
 /*!40101 SET character_set_client = @saved_cs_client */;
+
+--
+-- Table structure for table `webhooks`
+--
+
+DROP TABLE IF EXISTS `webhooks`;
+/*!40101 SET @saved_cs_client     = @@character_set_client */;
+/*!50503 SET character_set_client = utf8mb4 */;
+CREATE TABLE `webhooks` (
+  `webhook_id` int NOT NULL AUTO_INCREMENT,
+  `chat_id` int NOT NULL,
+  `bot_user_id` int NOT NULL,
+  `name` varchar(50) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `token_hash` char(64) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `created_by` int DEFAULT NULL,
+  `created_at` timestamp NOT NULL,
+  PRIMARY KEY (`webhook_id`),
+  UNIQUE KEY `uq_Webhooks_token_hash` (`token_hash`),
+  KEY `idx_Webhooks_chat` (`chat_id`),
+  KEY `idx_Webhooks_bot_user` (`bot_user_id`),
+  KEY `idx_Webhooks_created_by` (`created_by`),
+  CONSTRAINT `webhooks_ibfk_1` FOREIGN KEY (`chat_id`) REFERENCES `chats` (`chat_id`) ON DELETE CASCADE,
+  CONSTRAINT `webhooks_ibfk_2` FOREIGN KEY (`bot_user_id`) REFERENCES `users` (`user_id`) ON DELETE CASCADE,
+  CONSTRAINT `webhooks_ibfk_3` FOREIGN KEY (`created_by`) REFERENCES `users` (`user_id`) ON DELETE SET NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+/*!40101 SET character_set_client = @saved_cs_client */;
 /*!40103 SET TIME_ZONE=@OLD_TIME_ZONE */;
 
 /*!40101 SET SQL_MODE=@OLD_SQL_MODE */;
@@ -0,0 +1,582 @@
+//! ACME - Certificati ottenuti e rinnovati automaticamente (RFC 8555)
+//!
+//! Client minimale che parla direttamente il protocollo con la CA configurata: registra
+//! un account (chiave ECDSA P-256 salvata in `ACME_CACHE_DIR`), crea un ordine per i
+//! domini in `ACME_DOMAINS` e li verifica con la sfida TLS-ALPN-01 (RFC 8737), a cui
+//! risponde lo stesso listener TLS del server: basta che la porta 443 dei domini arrivi
+//! a `SERVER_PORT`. Il certificato viene salvato nella cache, riusato ai riavvii e
+//! rinnovato `RENEW_AFTER` dopo l'emissione.
+
+use crate::core::config::AcmeConfig;
+use crate::core::tls::{CertResolver, certified_key, load_certificate};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use reqwest::StatusCode;
+use reqwest::header::{ACCEPT, CONTENT_TYPE, LOCATION};
+use ring::rand::SystemRandom;
+use ring::signature::{ECDSA_P256_SHA256_FIXED_SIGNING, EcdsaKeyPair, KeyPair};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio_rustls::rustls::crypto::ring::default_provider;
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::sign::CertifiedKey;
+use tracing::{error, info, warn};
+
+/// Protocollo ALPN con cui la CA apre le connessioni di verifica TLS-ALPN-01
+pub const ACME_TLS_ALPN_PROTOCOL: &[u8] = b"acme-tls/1";
+
+/// Età del certificato oltre la quale viene rinnovato: i certificati di Let's Encrypt
+/// durano 90 giorni, così restano 30 giorni per riprovare in caso di errori
+const RENEW_AFTER: Duration = Duration::from_secs(60 * 24 * 60 * 60);
+
+/// Ogni quanto controllare se il certificato va rinnovato
+const RENEWAL_CHECK_INTERVAL: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// Attesa prima di riprovare dopo un'emissione fallita
+const RETRY_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Controlli dello stato di autorizzazioni e ordine prima di rinunciare
+const MAX_POLL_ATTEMPTS: u32 = 30;
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+const ACCOUNT_KEY_FILE: &str = "account.der";
+const CERT_FILE: &str = "cert.pem";
+const KEY_FILE: &str = "key.pem";
+const DOMAINS_FILE: &str = "domains";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Directory {
+    new_nonce: String,
+    new_account: String,
+    new_order: String,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Pending,
+    Ready,
+    Processing,
+    Valid,
+    Invalid,
+    Deactivated,
+    Expired,
+    Revoked,
+}
+
+#[derive(Deserialize)]
+struct Order {
+    status: Status,
+    authorizations: Vec<String>,
+    finalize: String,
+    certificate: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Authorization {
+    status: Status,
+    identifier: Identifier,
+    challenges: Vec<Challenge>,
+}
+
+#[derive(Deserialize)]
+struct Identifier {
+    value: String,
+}
+
+#[derive(Deserialize)]
+struct Challenge {
+    #[serde(rename = "type")]
+    kind: String,
+    url: String,
+    token: String,
+}
+
+/// Errore restituito dalla CA (RFC 7807)
+#[derive(Deserialize)]
+struct Problem {
+    #[serde(rename = "type")]
+    kind: String,
+    detail: Option<String>,
+}
+
+/// Avvia in background l'emissione e il rinnovo del certificato. Se nella cache c'è
+/// già un certificato per gli stessi domini viene servito subito
+pub fn start_acme(config: AcmeConfig, resolver: Arc<CertResolver>) {
+    if cached_domains(&config.cache_dir).as_ref() == Some(&config.domains) {
+        match load_certificate(
+            &config.cache_dir.join(CERT_FILE),
+            &config.cache_dir.join(KEY_FILE),
+        ) {
+            Ok(certificate) => resolver.set_certificate(certificate),
+            Err(e) => warn!("Cached ACME certificate not usable: {}", e),
+        }
+    }
+
+    tokio::spawn(async move {
+        loop {
+            if !resolver.has_certificate() || needs_renewal(&config) {
+                info!(
+                    "Requesting ACME certificate for {}",
+                    config.domains.join(", ")
+                );
+                if let Err(e) = renew(&config, &resolver).await {
+                    error!("ACME certificate request failed: {}", e);
+                    tokio::time::sleep(RETRY_INTERVAL).await;
+                    continue;
+                }
+                info!("ACME certificate issued");
+            }
+            tokio::time::sleep(RENEWAL_CHECK_INTERVAL).await;
+        }
+    });
+}
+
+fn cached_domains(cache_dir: &Path) -> Option<Vec<String>> {
+    let domains = std::fs::read_to_string(cache_dir.join(DOMAINS_FILE)).ok()?;
+    Some(domains.lines().map(str::to_string).collect())
+}
+
+/// Il certificato in cache manca, è per altri domini o è stato emesso da più di `RENEW_AFTER`
+fn needs_renewal(config: &AcmeConfig) -> bool {
+    if cached_domains(&config.cache_dir).as_ref() != Some(&config.domains) {
+        return true;
+    }
+    std::fs::metadata(config.cache_dir.join(CERT_FILE))
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|issued| SystemTime::now().duration_since(issued).ok())
+        .is_none_or(|age| age > RENEW_AFTER)
+}
+
+/// Ottiene un nuovo certificato, lo salva nella cache e lo mette in uso
+async fn renew(config: &AcmeConfig, resolver: &CertResolver) -> Result<(), String> {
+    std::fs::create_dir_all(&config.cache_dir)
+        .map_err(|e| format!("Cannot create {}: {}", config.cache_dir.display(), e))?;
+
+    let mut client = AcmeClient::connect(config).await?;
+    client.register(config.contact_email.as_deref()).await?;
+    let result = client.order_certificate(&config.domains, resolver).await;
+    resolver.clear_challenges();
+    let (chain_pem, key_pem) = result?;
+
+    let chain = CertificateDer::pem_slice_iter(chain_pem.as_bytes())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Invalid certificate chain from CA: {}", e))?;
+    let key = PrivateKeyDer::from_pem_slice(key_pem.as_bytes())
+        .map_err(|e| format!("Invalid certificate key: {}", e))?;
+    let certificate = certified_key(chain, key)?;
+
+    write_private(&config.cache_dir.join(KEY_FILE), key_pem.as_bytes())?;
+    std::fs::write(config.cache_dir.join(CERT_FILE), chain_pem)
+        .and_then(|_| {
+            std::fs::write(
+                config.cache_dir.join(DOMAINS_FILE),
+                config.domains.join("\n"),
+            )
+        })
+        .map_err(|e| format!("Cannot save certificate: {}", e))?;
+
+    resolver.set_certificate(certificate);
+    Ok(())
+}
+
+/// Scrive un file leggibile solo dall'utente del server
+fn write_private(path: &Path, contents: &[u8]) -> Result<(), String> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(contents))
+        .map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+}
+
+struct AcmeClient {
+    http: reqwest::Client,
+    directory: Directory,
+    key: EcdsaKeyPair,
+    rng: SystemRandom,
+    /// URL dell'account, che dopo la registrazione identifica la chiave nelle richieste
+    account_url: Option<String>,
+    nonce: Option<String>,
+}
+
+impl AcmeClient {
+    /// Legge la directory della CA e carica (o genera) la chiave dell'account
+    async fn connect(config: &AcmeConfig) -> Result<Self, String> {
+        let rng = SystemRandom::new();
+        let key_path = config.cache_dir.join(ACCOUNT_KEY_FILE);
+        let pkcs8 = match std::fs::read(&key_path) {
+            Ok(pkcs8) => pkcs8,
+            Err(_) => {
+                let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng)
+                    .map_err(|_| "Cannot generate ACME account key".to_string())?;
+                write_private(&key_path, pkcs8.as_ref())?;
+                pkcs8.as_ref().to_vec()
+            }
+        };
+        let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &pkcs8, &rng)
+            .map_err(|_| format!("Invalid ACME account key {}", key_path.display()))?;
+
+        let http = reqwest::Client::new();
+        let directory = http
+            .get(&config.directory_url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("Cannot reach ACME directory: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid ACME directory: {}", e))?;
+
+        Ok(Self {
+            http,
+            directory,
+            key,
+            rng,
+            account_url: None,
+            nonce: None,
+        })
+    }
+
+    /// Crea l'account o, se la chiave è già registrata, ne recupera l'URL
+    async fn register(&mut self, contact_email: Option<&str>) -> Result<(), String> {
+        let mut payload = json!({ "termsOfServiceAgreed": true });
+        if let Some(email) = contact_email {
+            payload["contact"] = json!([format!("mailto:{}", email)]);
+        }
+        let url = self.directory.new_account.clone();
+        let response = self.post(&url, Some(&payload)).await?;
+        self.account_url = Some(location(&response)?);
+        Ok(())
+    }
+
+    /// Esegue un ordine completo: sfide, CSR e download del certificato
+    ///
+    /// # Returns
+    /// Catena di certificati e chiave privata del certificato, in formato PEM
+    async fn order_certificate(
+        &mut self,
+        domains: &[String],
+        resolver: &CertResolver,
+    ) -> Result<(String, String), String> {
+        // 1. Creare l'ordine per i domini
+        let identifiers: Vec<Value> = domains
+            .iter()
+            .map(|domain| json!({ "type": "dns", "value": domain }))
+            .collect();
+        let url = self.directory.new_order.clone();
+        let response = self
+            .post(&url, Some(&json!({ "identifiers": identifiers })))
+            .await?;
+        let order_url = location(&response)?;
+        let order: Order = parse(response).await?;
+
+        // 2. Rispondere alla sfida TLS-ALPN-01 di ogni autorizzazione ancora pendente
+        for authorization_url in &order.authorizations {
+            let authorization: Authorization =
+                parse(self.post(authorization_url, None).await?).await?;
+            if authorization.status == Status::Valid {
+                continue;
+            }
+            let domain = authorization.identifier.value;
+            let challenge = authorization
+                .challenges
+                .into_iter()
+                .find(|challenge| challenge.kind == "tls-alpn-01")
+                .ok_or_else(|| format!("CA offers no tls-alpn-01 challenge for {}", domain))?;
+
+            let key_authorization = format!("{}.{}", challenge.token, thumbprint(&self.key));
+            resolver.set_challenge(&domain, challenge_certificate(&domain, &key_authorization)?);
+            self.post(&challenge.url, Some(&json!({}))).await?;
+
+            self.poll_authorization(authorization_url, &domain).await?;
+        }
+
+        // 3. Attendere che l'ordine sia pronto e inviare la CSR con una nuova chiave
+        let order = self.poll_order(&order_url, Status::Ready).await?;
+        let certificate_key = rcgen::KeyPair::generate()
+            .map_err(|e| format!("Cannot generate certificate key: {}", e))?;
+        let csr = rcgen::CertificateParams::new(domains.to_vec())
+            .and_then(|params| params.serialize_request(&certificate_key))
+            .map_err(|e| format!("Cannot create CSR: {}", e))?;
+        let payload = json!({ "csr": URL_SAFE_NO_PAD.encode(csr.der()) });
+        self.post(&order.finalize, Some(&payload)).await?;
+
+        // 4. Scaricare il certificato emesso
+        let order = self.poll_order(&order_url, Status::Valid).await?;
+        let certificate_url = order
+            .certificate
+            .ok_or_else(|| "Valid order without certificate URL".to_string())?;
+        let chain_pem = self
+            .post(&certificate_url, None)
+            .await?
+            .text()
+            .await
+            .map_err(|e| format!("Cannot download certificate: {}", e))?;
+
+        Ok((chain_pem, certificate_key.serialize_pem()))
+    }
+
+    async fn poll_authorization(&mut self, url: &str, domain: &str) -> Result<(), String> {
+        for _ in 0..MAX_POLL_ATTEMPTS {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let authorization: Authorization = parse(self.post(url, None).await?).await?;
+            match authorization.status {
+                Status::Valid => return Ok(()),
+                Status::Pending | Status::Processing => continue,
+                status => {
+                    return Err(format!("Validation of {} failed: {:?}", domain, status));
+                }
+            }
+        }
+        Err(format!("Validation of {} timed out", domain))
+    }
+
+    /// Attende che l'ordine raggiunga `target`
+    async fn poll_order(&mut self, url: &str, target: Status) -> Result<Order, String> {
+        for _ in 0..MAX_POLL_ATTEMPTS {
+            let order: Order = parse(self.post(url, None).await?).await?;
+            if order.status == target {
+                return Ok(order);
+            }
+            if matches!(order.status, Status::Invalid | Status::Expired) {
+                return Err(format!("Order failed: {:?}", order.status));
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+        Err(format!("Order not {:?} in time", target))
+    }
+
+    /// Richiesta firmata (JWS). Senza payload è un POST-as-GET. Un nonce scaduto
+    /// viene rinnovato e la richiesta ripetuta una volta
+    async fn post(
+        &mut self,
+        url: &str,
+        payload: Option<&Value>,
+    ) -> Result<reqwest::Response, String> {
+        let mut retried = false;
+        loop {
+            let nonce = self.take_nonce().await?;
+            let mut protected = json!({ "alg": "ES256", "nonce": nonce, "url": url });
+            match &self.account_url {
+                Some(account_url) => protected["kid"] = json!(account_url),
+                None => protected["jwk"] = jwk(&self.key),
+            }
+            let body = sign_jws(&self.key, &self.rng, &protected, payload)?;
+
+            let response = self
+                .http
+                .post(url)
+                .header(CONTENT_TYPE, "application/jose+json")
+                .header(
+                    ACCEPT,
+                    "application/pem-certificate-chain, application/json",
+                )
+                .body(body.to_string())
+                .send()
+                .await
+                .map_err(|e| format!("ACME request failed: {}", e))?;
+            self.nonce = response
+                .headers()
+                .get("Replay-Nonce")
+                .and_then(|nonce| nonce.to_str().ok())
+                .map(str::to_string);
+
+            if response.status().is_success() {
+                return Ok(response);
+            }
+            let status = response.status();
+            let problem: Option<Problem> = response.json().await.ok();
+            if status == StatusCode::BAD_REQUEST
+                && !retried
+                && problem
+                    .as_ref()
+                    .is_some_and(|p| p.kind == "urn:ietf:params:acme:error:badNonce")
+            {
+                retried = true;
+                continue;
+            }
+            return Err(match problem {
+                Some(problem) => format!(
+                    "CA returned {}: {}",
+                    problem.kind,
+                    problem.detail.unwrap_or_default()
+                ),
+                None => format!("CA returned status {}", status),
+            });
+        }
+    }
+
+    async fn take_nonce(&mut self) -> Result<String, String> {
+        if let Some(nonce) = self.nonce.take() {
+            return Ok(nonce);
+        }
+        self.http
+            .head(&self.directory.new_nonce)
+            .send()
+            .await
+            .map_err(|e| format!("Cannot get ACME nonce: {}", e))?
+            .headers()
+            .get("Replay-Nonce")
+            .and_then(|nonce| nonce.to_str().ok())
+            .map(str::to_string)
+            .ok_or_else(|| "CA returned no nonce".to_string())
+    }
+}
+
+fn location(response: &reqwest::Response) -> Result<String, String> {
+    response
+        .headers()
+        .get(LOCATION)
+        .and_then(|location| location.to_str().ok())
+        .map(str::to_string)
+        .ok_or_else(|| "CA response without Location header".to_string())
+}
+
+async fn parse<T: serde::de::DeserializeOwned>(response: reqwest::Response) -> Result<T, String> {
+    response
+        .json()
+        .await
+        .map_err(|e| format!("Invalid ACME response: {}", e))
+}
+
+/// Chiave pubblica dell'account come JWK: il punto P-256 non compresso è 0x04 || x || y
+fn jwk(key: &EcdsaKeyPair) -> Value {
+    let point = key.public_key().as_ref();
+    json!({
+        "crv": "P-256",
+        "kty": "EC",
+        "x": URL_SAFE_NO_PAD.encode(&point[1..33]),
+        "y": URL_SAFE_NO_PAD.encode(&point[33..65]),
+    })
+}
+
+/// Impronta della JWK (RFC 7638): SHA-256 dei membri obbligatori in ordine alfabetico
+fn thumbprint(key: &EcdsaKeyPair) -> String {
+    let jwk = jwk(key);
+    let canonical = format!(
+        r#"{{"crv":"P-256","kty":"EC","x":"{}","y":"{}"}}"#,
+        jwk["x"].as_str().unwrap_or_default(),
+        jwk["y"].as_str().unwrap_or_default()
+    );
+    URL_SAFE_NO_PAD.encode(Sha256::digest(canonical.as_bytes()))
+}
+
+/// Corpo JWS in serializzazione flattened; il payload vuoto indica un POST-as-GET
+fn sign_jws(
+    key: &EcdsaKeyPair,
+    rng: &SystemRandom,
+    protected: &Value,
+    payload: Option<&Value>,
+) -> Result<Value, String> {
+    let protected = URL_SAFE_NO_PAD.encode(protected.to_string());
+    let payload = payload
+        .map(|payload| URL_SAFE_NO_PAD.encode(payload.to_string()))
+        .unwrap_or_default();
+    let signature = key
+        .sign(rng, format!("{}.{}", protected, payload).as_bytes())
+        .map_err(|_| "Cannot sign ACME request".to_string())?;
+
+    Ok(json!({
+        "protected": protected,
+        "payload": payload,
+        "signature": URL_SAFE_NO_PAD.encode(signature.as_ref()),
+    }))
+}
+
+/// Certificato autofirmato per la sfida TLS-ALPN-01: contiene il dominio e l'hash
+/// della key authorization nell'estensione acmeIdentifier
+fn challenge_certificate(
+    domain: &str,
+    key_authorization: &str,
+) -> Result<Arc<CertifiedKey>, String> {
+    let key =
+        rcgen::KeyPair::generate().map_err(|e| format!("Cannot generate challenge key: {}", e))?;
+    let mut params = rcgen::CertificateParams::new(vec![domain.to_string()])
+        .map_err(|e| format!("Invalid domain {}: {}", domain, e))?;
+    params.custom_extensions = vec![rcgen::CustomExtension::new_acme_identifier(
+        &Sha256::digest(key_authorization.as_bytes()),
+    )];
+    let certificate = params
+        .self_signed(&key)
+        .map_err(|e| format!("Cannot create challenge certificate: {}", e))?;
+
+    // non passa da `certified_key`: il controllo della coppia chiave/certificato
+    // rifiuta l'estensione critica acmeIdentifier, che rustls non conosce
+    let signing_key = default_provider()
+        .key_provider
+        .load_private_key(PrivateKeyDer::Pkcs8(key.serialize_der().into()))
+        .map_err(|e| format!("Invalid challenge key: {}", e))?;
+    Ok(Arc::new(CertifiedKey::new(
+        vec![certificate.der().clone()],
+        signing_key,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::signature::{ECDSA_P256_SHA256_FIXED, UnparsedPublicKey};
+
+    fn account_key() -> (EcdsaKeyPair, SystemRandom) {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+        let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng)
+            .unwrap();
+        (key, rng)
+    }
+
+    #[test]
+    fn test_jws_signature_verifies_with_jwk() {
+        let (key, rng) = account_key();
+        let protected = json!({ "alg": "ES256", "nonce": "abc", "url": "https://ca/new-order" });
+        let jws = sign_jws(&key, &rng, &protected, Some(&json!({ "a": 1 }))).unwrap();
+
+        // la CA ricostruisce la chiave pubblica dalle coordinate della JWK
+        let jwk = jwk(&key);
+        let mut point = vec![4u8];
+        point.extend(URL_SAFE_NO_PAD.decode(jwk["x"].as_str().unwrap()).unwrap());
+        point.extend(URL_SAFE_NO_PAD.decode(jwk["y"].as_str().unwrap()).unwrap());
+        let signing_input = format!(
+            "{}.{}",
+            jws["protected"].as_str().unwrap(),
+            jws["payload"].as_str().unwrap()
+        );
+        let signature = URL_SAFE_NO_PAD
+            .decode(jws["signature"].as_str().unwrap())
+            .unwrap();
+        assert!(
+            UnparsedPublicKey::new(&ECDSA_P256_SHA256_FIXED, &point)
+                .verify(signing_input.as_bytes(), &signature)
+                .is_ok()
+        );
+
+        // POST-as-GET: payload vuoto
+        let jws = sign_jws(&key, &rng, &protected, None).unwrap();
+        assert_eq!(jws["payload"], "");
+    }
+
+    #[test]
+    fn test_thumbprint_is_stable_sha256() {
+        let (key, _) = account_key();
+        let thumbprint = thumbprint(&key);
+        assert_eq!(URL_SAFE_NO_PAD.decode(&thumbprint).unwrap().len(), 32);
+        assert_eq!(thumbprint, super::thumbprint(&key));
+    }
+
+    #[test]
+    fn test_challenge_certificate() {
+        assert!(challenge_certificate("chat.example.org", "token.thumbprint").is_ok());
+    }
+}
@@ -0,0 +1,118 @@
+//! Limiti di dimensione del corpo delle richieste
+//!
+//! Le route JSON accettano corpi piccoli (`MAX_JSON_BODY_BYTES`), gli upload di file
+//! corpi grandi quanto l'allegato più il margine del formato multipart. Il middleware
+//! rifiuta subito le richieste che dichiarano un `Content-Length` oltre il limite e
+//! interrompe la lettura dei corpi in streaming appena lo superano: in entrambi i casi
+//! il client riceve 413 con il corpo JSON di AppError.
+
+use crate::core::error::AppError;
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{Response, StatusCode, header},
+    middleware::Next,
+};
+use http_body_util::Limited;
+use tracing::warn;
+
+/// Margine per boundary e intestazioni del corpo multipart, oltre al file vero e proprio
+pub const MULTIPART_OVERHEAD_BYTES: usize = 64 * 1024;
+
+/// Middleware che limita il corpo della richiesta a `max_bytes`
+///
+/// Va applicato per route con `from_fn_with_state(max_bytes, body_limit_middleware)`.
+/// Gli estrattori che superano il limite durante la lettura rispondono 413 in testo
+/// semplice: la risposta viene sostituita con l'errore JSON dell'applicazione.
+pub async fn body_limit_middleware(
+    State(max_bytes): State<usize>,
+    req: Request,
+    next: Next,
+) -> Result<Response<Body>, AppError> {
+    // 1. Rifiutare senza leggerlo un corpo che dichiara una dimensione oltre il limite
+    // 2. Limitare la lettura del corpo, per le richieste senza Content-Length
+    // 3. Convertire in AppError il 413 prodotto dagli estrattori
+
+    let declared = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+    if declared.is_some_and(|length| length > max_bytes as u64) {
+        warn!("Request body exceeds {} bytes", max_bytes);
+        return Err(AppError::payload_too_large(max_bytes));
+    }
+
+    let req = req.map(|body| Body::new(Limited::new(body, max_bytes)));
+    let response = next.run(req).await;
+
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|value| value.as_bytes().starts_with(b"application/json"));
+    if response.status() == StatusCode::PAYLOAD_TOO_LARGE && !is_json {
+        warn!("Request body exceeds {} bytes", max_bytes);
+        return Err(AppError::payload_too_large(max_bytes));
+    }
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Json, Router, middleware, routing::post};
+    use axum_test::TestServer;
+    use serde_json::{Value, json};
+
+    fn server(max_bytes: usize) -> TestServer {
+        let app = Router::new()
+            .route(
+                "/",
+                post(|Json(body): Json<Value>| async move { Json(body) }),
+            )
+            .layer(middleware::from_fn_with_state(
+                max_bytes,
+                body_limit_middleware,
+            ));
+        TestServer::new(app).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_body_within_limit_accepted() {
+        let server = server(64);
+
+        let response = server.post("/").json(&json!({ "content": "hi" })).await;
+        response.assert_status_ok();
+        assert_eq!(response.json::<Value>()["content"], "hi");
+    }
+
+    #[tokio::test]
+    async fn test_declared_length_over_limit_rejected() {
+        let server = server(64);
+
+        // il corpo non viene letto: basta la dimensione dichiarata
+        let response = server
+            .post("/")
+            .json(&json!({ "content": "hi" }))
+            .add_header(header::CONTENT_LENGTH, "1048576")
+            .await;
+        response.assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+        let body: Value = response.json();
+        assert_eq!(body["error"], "Request body too large");
+        assert_eq!(body["details"], "The request body can be at most 64 bytes");
+    }
+
+    #[tokio::test]
+    async fn test_streamed_body_over_limit_rejected() {
+        let server = server(64);
+
+        // senza Content-Length il limite scatta durante la lettura del corpo
+        let response = server
+            .post("/")
+            .json(&json!({ "content": "x".repeat(100) }))
+            .await;
+        response.assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(response.json::<Value>()["error"], "Request body too large");
+    }
+}
@@ -1,91 +1,1965 @@
+use super::auth::JwtKeys;
+use super::cache::RedisCache;
+use super::config_file::{CONFIG_FILE_ENV, ConfigFile, config_file_path};
+use super::cors::build_cors_layer;
+use super::event_bus::{KafkaRestEventPublisher, NatsEventPublisher};
+use super::ldap::LdapAuthProvider;
+use super::password_hash::PasswordHasher;
+use super::secrets::{
+    DEFAULT_VAULT_KV_MOUNT, SECRET_VARS, VaultClient, read_secret_file, with_credentials,
+};
 use dotenv::dotenv;
+use jsonwebtoken::Algorithm;
+use reqwest::Url;
+use std::collections::HashMap;
 use std::env;
+use std::fmt;
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+/// Dimensione massima di default di un allegato (10 MiB)
+pub const DEFAULT_MAX_ATTACHMENT_BYTES: usize = 10 * 1024 * 1024;
+
+/// Dimensione massima di default del corpo delle richieste JSON (64 KiB)
+pub const DEFAULT_MAX_JSON_BODY_BYTES: usize = 64 * 1024;
+
+/// Numero massimo di default di membri in una chat di gruppo (Owner compreso)
+pub const DEFAULT_MAX_GROUP_MEMBERS: usize = 256;
+
+/// Lunghezza minima di JWT_SECRET in byte (256 bit, come la chiave di HS256)
+pub const MIN_JWT_SECRET_BYTES: usize = 32;
+
+/// Durata di default dell'access token JWT in minuti
+pub const DEFAULT_ACCESS_TOKEN_TTL_MINUTES: i64 = 15;
+
+/// Durata di un refresh token in giorni (ogni rotazione ne emette uno nuovo con durata piena)
+pub const REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+
+/// Giorni entro cui un account disattivato può essere riattivato con un nuovo login
+pub const ACCOUNT_REACTIVATION_WINDOW_DAYS: i64 = 30;
+
+/// Richieste al minuto di default per indirizzo IP sulle route `/auth/*`
+pub const DEFAULT_AUTH_RATE_LIMIT_PER_MINUTE: u32 = 20;
+
+/// Richieste al minuto di default per utente sulle route autenticate
+pub const DEFAULT_USER_RATE_LIMIT_PER_MINUTE: u32 = 300;
+
+/// Login falliti consecutivi di default prima del blocco temporaneo
+pub const DEFAULT_LOGIN_MAX_FAILURES: u32 = 5;
+
+/// Durata di default del primo blocco del login, in secondi
+pub const DEFAULT_LOGIN_LOCKOUT_SECS: u64 = 30;
+
+/// Durata massima di default del blocco del login, in secondi
+pub const DEFAULT_LOGIN_LOCKOUT_MAX_SECS: u64 = 60 * 60;
+
+/// Finestra di default delle euristiche anti-flood, in secondi
+pub const DEFAULT_FLOOD_WINDOW_SECS: u64 = 60;
+
+/// Ripetizioni di default dello stesso messaggio nella finestra prima del silenziamento
+pub const DEFAULT_FLOOD_REPEAT_THRESHOLD: u32 = 5;
+
+/// Messaggi con link di default nella finestra prima del silenziamento
+pub const DEFAULT_FLOOD_LINK_THRESHOLD: u32 = 5;
+
+/// Ingressi e uscite di default dalla stessa chat nella finestra prima del silenziamento
+pub const DEFAULT_FLOOD_JOIN_LEAVE_THRESHOLD: u32 = 6;
+
+/// Durata di default del primo silenziamento automatico, in secondi
+pub const DEFAULT_FLOOD_MUTE_SECS: u64 = 60;
+
+/// Durata massima di default del silenziamento automatico, in secondi
+pub const DEFAULT_FLOOD_MUTE_MAX_SECS: u64 = 60 * 60;
+
+/// Lunghezza minima di default delle password, in caratteri
+pub const DEFAULT_PASSWORD_MIN_LENGTH: usize = 8;
+
+/// Parametri di default di Argon2id (raccomandazione OWASP: 19 MiB, 2 iterazioni, 1 thread)
+pub const DEFAULT_ARGON2_MEMORY_KIB: u32 = 19 * 1024;
+pub const DEFAULT_ARGON2_ITERATIONS: u32 = 2;
+pub const DEFAULT_ARGON2_PARALLELISM: u32 = 1;
+
+/// Intervallo massimo di default tra invii batch di messaggi WebSocket, in millisecondi
+pub const DEFAULT_WS_BATCH_INTERVAL_MS: u64 = 1000;
+
+/// Numero massimo di default di messaggi in un batch WebSocket
+pub const DEFAULT_WS_BATCH_MAX_SIZE: usize = 10;
+
+/// Inattività di default prima della chiusura di una connessione WebSocket, in secondi
+pub const DEFAULT_WS_IDLE_TIMEOUT_SECS: u64 = 300;
+
+/// Messaggi al secondo di default accettati da una connessione WebSocket, a regime
+pub const DEFAULT_WS_MESSAGES_PER_SECOND: u32 = 100;
+
+/// Messaggi di default che una connessione WebSocket può inviare di seguito oltre il regime
+pub const DEFAULT_WS_MESSAGE_BURST: u32 = 20;
+
+/// Segnali di default in coda per una connessione WebSocket prima dell'overflow
+pub const DEFAULT_WS_SIGNAL_QUEUE_CAPACITY: usize = 1000;
+
+/// Intervallo minimo di default tra due aggiornamenti di `messages_received_until` per
+/// connessione WebSocket, in millisecondi
+pub const DEFAULT_WS_RECEIVED_FLUSH_INTERVAL_MS: u64 = 5000;
+
+/// Durata di default delle voci nella cache di utenti e appartenenze, in secondi
+pub const DEFAULT_CACHE_TTL_SECS: u64 = 60;
+
+/// Voci di default conservate da ciascuna cache di utenti e appartenenze
+pub const DEFAULT_CACHE_MAX_ENTRIES: u64 = 100_000;
+
+/// Durata di default della cache delle risposte preflight CORS nel browser, in secondi
+pub const DEFAULT_CORS_MAX_AGE_SECS: u64 = 600;
+
+/// Directory ACME di default (Let's Encrypt, produzione)
+pub const DEFAULT_ACME_DIRECTORY_URL: &str = "https://acme-v02.api.letsencrypt.org/directory";
+
+/// Cartella di default in cui vengono salvati account e certificati ACME
+pub const DEFAULT_ACME_CACHE_DIR: &str = "acme";
+
+/// Topic (o subject NATS) di default su cui vengono pubblicati gli eventi di dominio
+pub const DEFAULT_EVENT_BUS_TOPIC: &str = "ironlink.events";
+
+/// Durata e claim standard degli access token JWT
+#[derive(Debug, Clone)]
+pub struct AccessTokenConfig {
+    pub ttl_minutes: i64,
+    /// Claim `iss`: se impostato, i token senza lo stesso issuer vengono rifiutati
+    pub issuer: Option<String>,
+    /// Claim `aud`: se impostato, i token senza la stessa audience vengono rifiutati
+    pub audience: Option<String>,
+}
+
+impl Default for AccessTokenConfig {
+    fn default() -> Self {
+        Self {
+            ttl_minutes: DEFAULT_ACCESS_TOKEN_TTL_MINUTES,
+            issuer: None,
+            audience: None,
+        }
+    }
+}
+
+/// Coppia di chiavi con cui firmare i JWT al posto del segreto condiviso `JWT_SECRET`
+#[derive(Debug, Clone)]
+pub struct JwtKeyPairConfig {
+    /// `RS256` (chiavi RSA) oppure `EdDSA` (chiavi Ed25519)
+    pub algorithm: Algorithm,
+    pub private_key_path: PathBuf,
+    /// Chiave pubblica da distribuire ai servizi che verificano i token
+    pub public_key_path: PathBuf,
+    /// Chiavi pubbliche ritirate, accettate finché i token firmati con esse non scadono
+    pub previous_public_key_paths: Vec<PathBuf>,
+}
+
+/// Segreto di Vault (motore KV versione 2) da cui leggere JWT_SECRET e credenziali
+#[derive(Debug, Clone)]
+pub struct VaultConfig {
+    pub addr: String,
+    pub kv_mount: String,
+    pub secret_path: String,
+    /// Namespace di Vault Enterprise / HCP, se usato
+    pub namespace: Option<String>,
+}
+
+/// Backend su cui vengono salvati i file allegati
+#[derive(Debug, Clone)]
+pub enum StorageConfig {
+    /// File salvati sul disco locale, sotto la cartella `root`
+    Local { root: PathBuf },
+    /// Bucket S3 o compatibile (MinIO, Ceph, R2...), `endpoint` None usa AWS
+    S3 {
+        bucket: String,
+        region: String,
+        endpoint: Option<String>,
+        access_key_id: String,
+        secret_access_key: String,
+    },
+}
+
+/// Provider OpenID Connect per il login SSO
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    /// URL dell'issuer, da cui si legge `/.well-known/openid-configuration`
+    pub issuer_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    /// URL di GET /auth/oidc/callback registrato sul provider
+    pub redirect_url: String,
+    /// Accetta id_token firmati HS* con il client secret (di default solo firme asimmetriche)
+    pub allow_hmac: bool,
+}
+
+/// Directory LDAP per la verifica delle password
+#[derive(Debug, Clone)]
+pub struct LdapConfig {
+    /// `ldap://host[:port]` oppure `ldaps://host[:port]`
+    pub url: String,
+    /// DN con cui fare il bind, es. `uid={username},ou=people,dc=example,dc=org`
+    pub bind_dn_template: String,
+}
+
+/// Backend che verifica le credenziali al login
+#[derive(Debug, Clone)]
+pub enum AuthBackendConfig {
+    /// Password confrontata con l'hash salvato nel database
+    Local,
+    /// Password verificata con un bind sulla directory LDAP
+    Ldap(LdapConfig),
+}
+
+/// Broker su cui pubblicare gli eventi di dominio (messaggi, chat, nuovi membri)
+#[derive(Debug, Clone, Default)]
+pub enum EventBusConfig {
+    /// Nessuna pubblicazione
+    #[default]
+    Disabled,
+    /// Server NATS (`nats://[user:password@]host[:port]`), eventi sul subject `subject`
+    Nats { url: String, subject: String },
+    /// Cluster Kafka raggiunto tramite REST Proxy (API v2) all'indirizzo `rest_url`
+    Kafka { rest_url: String, topic: String },
+}
+
+/// Certificato ottenuto automaticamente da una CA ACME (Let's Encrypt, ZeroSSL...)
+#[derive(Debug, Clone)]
+pub struct AcmeConfig {
+    /// Domini del certificato, tutti risolti verso questo server sulla porta 443
+    pub domains: Vec<String>,
+    /// Email a cui la CA invia gli avvisi di scadenza
+    pub contact_email: Option<String>,
+    pub directory_url: String,
+    /// Cartella in cui salvare la chiave dell'account e l'ultimo certificato
+    pub cache_dir: PathBuf,
+}
+
+/// Cifratura delle connessioni HTTP e WebSocket servita direttamente dal server
+#[derive(Debug, Clone, Default)]
+pub enum TlsConfig {
+    /// HTTP in chiaro, ad esempio dietro un reverse proxy che termina il TLS
+    #[default]
+    Disabled,
+    /// Catena di certificati e chiave privata in formato PEM
+    Files {
+        cert_path: PathBuf,
+        key_path: PathBuf,
+    },
+    /// Certificato richiesto e rinnovato via ACME
+    Acme(AcmeConfig),
+}
+
+/// Profilo di esecuzione scelto con `APP_ENV`
+///
+/// Cambia i default di formato dei log, cookie, CORS e rate limit; ogni impostazione resta
+/// sovrascrivibile con la propria variabile
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AppProfile {
+    #[default]
+    Development,
+    Staging,
+    Production,
+}
+
+impl AppProfile {
+    /// Interpreta `APP_ENV`; `test` vale come sviluppo
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "development" | "dev" | "test" => Some(Self::Development),
+            "staging" => Some(Self::Staging),
+            "production" | "prod" => Some(Self::Production),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Development => "development",
+            Self::Staging => "staging",
+            Self::Production => "production",
+        }
+    }
+
+    /// Log leggibili a terminale in sviluppo, una riga per evento altrove
+    pub fn default_log_format(self) -> LogFormat {
+        match self {
+            Self::Development => LogFormat::Pretty,
+            Self::Staging | Self::Production => LogFormat::Compact,
+        }
+    }
+
+    /// In sviluppo il server gira spesso in HTTP su localhost, dove un cookie `Secure`
+    /// non verrebbe salvato dal browser
+    pub fn default_cookie_secure(self) -> bool {
+        self != Self::Development
+    }
+
+    /// Solo in produzione l'assenza di CORS_ALLOWED_ORIGINS blocca le richieste cross-origin
+    pub fn default_cors_strict(self) -> bool {
+        self == Self::Production
+    }
+
+    /// Richieste al minuto per IP sulle route `/auth/*`; in sviluppo nessun limite
+    pub fn default_auth_rate_limit(self) -> u32 {
+        match self {
+            Self::Development => 0,
+            Self::Staging | Self::Production => DEFAULT_AUTH_RATE_LIMIT_PER_MINUTE,
+        }
+    }
+
+    /// Richieste al minuto per utente sulle route autenticate; in sviluppo nessun limite
+    pub fn default_user_rate_limit(self) -> u32 {
+        match self {
+            Self::Development => 0,
+            Self::Staging | Self::Production => DEFAULT_USER_RATE_LIMIT_PER_MINUTE,
+        }
+    }
+
+    /// Fuori dallo sviluppo il segreto di default dei JWT non è accettato
+    pub fn requires_jwt_secret(self) -> bool {
+        self != Self::Development
+    }
+}
+
+/// Formato delle righe di log
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Più righe per evento, con colori
+    Pretty,
+    /// Formato standard di tracing, una riga per evento con colori
+    Full,
+    /// Una riga per evento senza colori, adatta ai raccoglitori di log
+    Compact,
+}
+
+/// Politica CORS per i client che girano nel browser
+#[derive(Debug, Clone)]
+pub struct CorsConfig {
+    /// Origini ammesse (es. `https://chat.example.org`); vuoto = qualsiasi origine,
+    /// oppure nessuna se `strict`
+    pub allowed_origins: Vec<String>,
+    /// Header ammessi nelle richieste; vuoto = quelli richiesti dal browser
+    pub allowed_headers: Vec<String>,
+    /// Richieste con credenziali (cookie): richiede origini esplicite
+    pub allow_credentials: bool,
+    pub max_age_secs: u64,
+    /// Senza origini elencate nega le richieste cross-origin invece di ammetterle tutte
+    pub strict: bool,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: Vec::new(),
+            allowed_headers: Vec::new(),
+            allow_credentials: false,
+            max_age_secs: DEFAULT_CORS_MAX_AGE_SECS,
+            strict: false,
+        }
+    }
+}
+
+/// Sorgenti del filtro dei contenuti, applicato solo nelle chat che lo attivano
+#[derive(Debug, Clone, Default)]
+pub struct ContentFilterConfig {
+    /// File con le parole vietate, una per riga
+    pub wordlist_path: Option<String>,
+    /// Endpoint del classificatore esterno
+    pub classifier_url: Option<String>,
+}
+
+/// Cache in memoria di utenti e appartenenze alle chat (TTL a 0 la disabilita)
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    /// Durata massima di una voce, anche se nessuna scrittura la invalida
+    pub ttl_secs: u64,
+    /// Voci conservate per ciascuna cache prima di scartare le meno usate
+    pub max_entries: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: DEFAULT_CACHE_TTL_SECS,
+            max_entries: DEFAULT_CACHE_MAX_ENTRIES,
+        }
+    }
+}
+
+/// Limiti di richieste HTTP al minuto (0 disabilita il limite)
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    /// Per indirizzo IP sulle route `/auth/*`
+    pub auth_per_minute: u32,
+    /// Per utente sulle route autenticate
+    pub user_per_minute: u32,
+    /// Legge l'IP del client da `X-Forwarded-For` (solo dietro un reverse proxy fidato)
+    pub trust_forwarded_for: bool,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            auth_per_minute: DEFAULT_AUTH_RATE_LIMIT_PER_MINUTE,
+            user_per_minute: DEFAULT_USER_RATE_LIMIT_PER_MINUTE,
+            trust_forwarded_for: false,
+        }
+    }
+}
+
+/// Blocco temporaneo del login dopo troppi tentativi falliti (0 fallimenti disabilita)
+#[derive(Debug, Clone)]
+pub struct LoginLockoutConfig {
+    /// Errori consecutivi per coppia (username, IP) prima del blocco
+    pub max_failures: u32,
+    /// Durata del primo blocco, raddoppiata ad ogni errore successivo
+    pub lockout_secs: u64,
+    /// Durata massima del blocco
+    pub max_lockout_secs: u64,
+}
+
+impl Default for LoginLockoutConfig {
+    fn default() -> Self {
+        Self {
+            max_failures: DEFAULT_LOGIN_MAX_FAILURES,
+            lockout_secs: DEFAULT_LOGIN_LOCKOUT_SECS,
+            max_lockout_secs: DEFAULT_LOGIN_LOCKOUT_MAX_SECS,
+        }
+    }
+}
+
+/// Rilevamento di flood e spam nelle chat (una soglia a 0 disabilita l'euristica)
+#[derive(Debug, Clone)]
+pub struct FloodConfig {
+    /// Finestra in cui vengono contati messaggi, ingressi e uscite
+    pub window_secs: u64,
+    /// Invii dello stesso messaggio nella finestra
+    pub repeat_threshold: u32,
+    /// Messaggi con link nella finestra
+    pub link_threshold: u32,
+    /// Ingressi e uscite dalla stessa chat nella finestra
+    pub join_leave_threshold: u32,
+    /// Durata del primo silenziamento, raddoppiata ad ogni recidiva
+    pub mute_secs: u64,
+    /// Durata massima del silenziamento
+    pub max_mute_secs: u64,
+}
+
+impl Default for FloodConfig {
+    fn default() -> Self {
+        Self {
+            window_secs: DEFAULT_FLOOD_WINDOW_SECS,
+            repeat_threshold: DEFAULT_FLOOD_REPEAT_THRESHOLD,
+            link_threshold: DEFAULT_FLOOD_LINK_THRESHOLD,
+            join_leave_threshold: DEFAULT_FLOOD_JOIN_LEAVE_THRESHOLD,
+            mute_secs: DEFAULT_FLOOD_MUTE_SECS,
+            max_mute_secs: DEFAULT_FLOOD_MUTE_MAX_SECS,
+        }
+    }
+}
+
+/// Requisiti delle password scelte alla registrazione
+#[derive(Debug, Clone)]
+pub struct PasswordPolicyConfig {
+    /// Numero minimo di caratteri
+    pub min_length: usize,
+    /// Almeno una lettera maiuscola e una minuscola
+    pub require_mixed_case: bool,
+    /// Almeno una cifra
+    pub require_digit: bool,
+    /// Rifiuta le password presenti nella lista di quelle più comuni
+    pub reject_common: bool,
+}
+
+impl Default for PasswordPolicyConfig {
+    fn default() -> Self {
+        Self {
+            min_length: DEFAULT_PASSWORD_MIN_LENGTH,
+            require_mixed_case: true,
+            require_digit: true,
+            reject_common: true,
+        }
+    }
+}
+
+/// Comportamento quando la coda dei segnali di una connessione WebSocket è piena
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WsOverflowPolicy {
+    /// Scarta i segnali più vecchi e invia al client un suggerimento di risincronizzazione
+    #[default]
+    DropOldest,
+    /// Chiude la connessione, il client si riconnette e recupera i messaggi persi
+    Disconnect,
+}
+
+/// Batching dei messaggi in uscita e limiti dei messaggi in ingresso delle connessioni WebSocket
+#[derive(Debug, Clone)]
+pub struct WsConfig {
+    /// Intervallo massimo tra invii batch
+    pub batch_interval_ms: u64,
+    /// Messaggi oltre i quali il batch viene inviato senza attendere l'intervallo
+    pub batch_max_size: usize,
+    /// Inattività del client dopo cui la connessione viene chiusa
+    pub idle_timeout_secs: u64,
+    /// Messaggi al secondo accettati a regime (0 disabilita il limite)
+    pub messages_per_second: u32,
+    /// Messaggi accettati di seguito prima che il limite intervenga
+    pub message_burst: u32,
+    /// Segnali in coda per connessione oltre i quali si applica `overflow_policy`
+    pub signal_queue_capacity: usize,
+    pub overflow_policy: WsOverflowPolicy,
+    /// Attesa minima tra due aggiornamenti dei marcatori di ricezione dei messaggi
+    /// consegnati (0 disabilita l'aggiornamento automatico)
+    pub received_flush_interval_ms: u64,
+}
+
+impl Default for WsConfig {
+    fn default() -> Self {
+        Self {
+            batch_interval_ms: DEFAULT_WS_BATCH_INTERVAL_MS,
+            batch_max_size: DEFAULT_WS_BATCH_MAX_SIZE,
+            idle_timeout_secs: DEFAULT_WS_IDLE_TIMEOUT_SECS,
+            messages_per_second: DEFAULT_WS_MESSAGES_PER_SECOND,
+            message_burst: DEFAULT_WS_MESSAGE_BURST,
+            signal_queue_capacity: DEFAULT_WS_SIGNAL_QUEUE_CAPACITY,
+            overflow_policy: WsOverflowPolicy::default(),
+            received_flush_interval_ms: DEFAULT_WS_RECEIVED_FLUSH_INTERVAL_MS,
+        }
+    }
+}
+
+/// Algoritmo e parametri usati per l'hash delle nuove password
+#[derive(Debug, Clone)]
+pub enum PasswordHashConfig {
+    /// bcrypt con il costo indicato (da 4 a 31)
+    Bcrypt { cost: u32 },
+    /// Argon2id con memoria in KiB, numero di iterazioni e grado di parallelismo
+    Argon2id {
+        memory_kib: u32,
+        iterations: u32,
+        parallelism: u32,
+    },
+}
+
+impl Default for PasswordHashConfig {
+    fn default() -> Self {
+        Self::Bcrypt {
+            cost: bcrypt::DEFAULT_COST,
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Config {
+    /// File di configurazione letto sotto le variabili d'ambiente, se indicato
+    pub config_file: Option<PathBuf>,
+    /// Vault da cui sono stati letti i segreti, se configurato
+    pub vault: Option<VaultConfig>,
     pub database_url: String,
+    /// Replica in sola lettura per storico, liste e ricerche, se configurata
+    pub database_read_url: Option<String>,
     pub jwt_secret: String,
+    /// Chiavi di firma ritirate, accettate solo per verificare i token già emessi
+    pub jwt_previous_secrets: Vec<String>,
+    /// Firma asimmetrica dei JWT (`JWT_ALGORITHM` RS256 o EdDSA), None con HS256
+    pub jwt_key_pair: Option<JwtKeyPairConfig>,
+    pub access_token: AccessTokenConfig,
     pub server_host: String,
     pub server_port: u16,
+    pub tls: TlsConfig,
+    pub cors: CorsConfig,
     pub max_connections: u32,
+    /// Connessioni che il pool tiene aperte anche quando è inattivo
+    pub min_connections: u32,
     pub connection_lifetime_secs: u64,
-    pub app_env: String,
+    /// Attesa massima per ottenere una connessione dal pool
+    pub acquire_timeout_secs: u64,
+    /// Inattività dopo la quale una connessione oltre il minimo viene chiusa (0 = mai)
+    pub idle_timeout_secs: u64,
+    /// Verifica con un ping che la connessione sia ancora valida prima di consegnarla
+    pub test_before_acquire: bool,
+    /// Durata oltre la quale una query finisce nel log come lenta (0 = log disattivato)
+    pub slow_query_threshold_ms: u64,
+    pub cache: CacheConfig,
+    pub profile: AppProfile,
     pub log_level: String,
+    pub log_format: LogFormat,
+    /// Attributo `Secure` sul cookie del token
+    pub cookie_secure: bool,
+    pub storage: StorageConfig,
+    pub max_attachment_bytes: usize,
+    pub max_json_body_bytes: usize,
+    pub legacy_api_paths: bool,
+    pub max_group_members: usize,
+    pub translation_api_url: Option<String>,
+    pub translation_api_key: Option<String>,
+    pub redis_url: Option<String>,
+    pub oidc: Option<OidcConfig>,
+    pub auth_backend: AuthBackendConfig,
+    pub rate_limit: RateLimitConfig,
+    pub login_lockout: LoginLockoutConfig,
+    pub flood: FloodConfig,
+    pub password_policy: PasswordPolicyConfig,
+    pub password_hash: PasswordHashConfig,
+    pub ws: WsConfig,
+    pub event_bus: EventBusConfig,
+    pub content_filter: ContentFilterConfig,
+    /// Id degli amministratori del server, che ricevono il ruolo nel JWT
+    pub server_admins: Vec<i32>,
+}
+
+/// Problemi trovati caricando la configurazione, riportati tutti insieme
+#[derive(Debug, Clone)]
+pub struct ConfigError {
+    pub problems: Vec<String>,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Invalid configuration ({} problem{}):",
+            self.problems.len(),
+            if self.problems.len() == 1 { "" } else { "s" }
+        )?;
+        for problem in &self.problems {
+            writeln!(f, "  - {}", problem)?;
+        }
+        write!(
+            f,
+            "Fix these values in the environment, the .env file or the config file and restart."
+        )
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<ConfigError> for String {
+    fn from(error: ConfigError) -> Self {
+        error.to_string()
+    }
+}
+
+/// Annota l'errore di un valore e prosegue con un segnaposto
+trait Report<T> {
+    fn report(self, problems: &mut Vec<String>) -> T;
+}
+
+impl<T: Default> Report<T> for Result<T, String> {
+    fn report(self, problems: &mut Vec<String>) -> T {
+        self.unwrap_or_else(|problem| {
+            problems.push(problem);
+            T::default()
+        })
+    }
+}
+
+/// Controlla che `value` sia un URL con host e uno degli schemi ammessi
+///
+/// Un valore vuoto viene ignorato: la variabile mancante è già stata segnalata
+fn check_url(name: &str, value: &str, schemes: &[&str]) -> Option<String> {
+    if value.is_empty() {
+        return None;
+    }
+    match Url::parse(value) {
+        Ok(url) if schemes.contains(&url.scheme()) && url.has_host() => None,
+        _ => {
+            let schemes: Vec<String> = schemes.iter().map(|s| format!("{}://", s)).collect();
+            Some(format!(
+                "Invalid {}: must be a URL starting with {}",
+                name,
+                schemes.join(" or ")
+            ))
+        }
+    }
+}
+
+/// Se l'URL del database indica un file SQLite invece di un server MySQL
+pub fn is_sqlite_url(url: &str) -> bool {
+    url.starts_with("sqlite:")
+}
+
+/// Come `check_url` per gli URL del database: MySQL/MariaDB o, con la feature `sqlite`,
+/// un file SQLite (`sqlite://percorso.db`)
+fn check_database_url(name: &str, value: &str) -> Option<String> {
+    if !is_sqlite_url(value) {
+        return check_url(name, value, &["mysql", "mariadb"]);
+    }
+    if cfg!(feature = "sqlite") {
+        None
+    } else {
+        Some(format!(
+            "Invalid {}: SQLite requires a server built with `--features sqlite`",
+            name
+        ))
+    }
 }
 
 impl Config {
     /// Carica la configurazione dalle variabili d'ambiente
-    /// Chiama dotenv() automaticamente
-    pub fn from_env() -> Result<Self, String> {
+    /// Chiama dotenv() automaticamente; se è indicato un file di configurazione
+    /// (`--config <path>` o `CONFIG_FILE`) i suoi valori valgono per le variabili non impostate.
+    /// I segreti si possono leggere anche da file (`<NOME>_FILE`) o da Vault
+    ///
+    /// # Returns
+    /// * `Ok(Config)` - Configurazione valida
+    /// * `Err(ConfigError)` - Elenco di tutti i valori mancanti, malformati o incompatibili
+    pub async fn from_env() -> Result<Self, ConfigError> {
         dotenv().ok();
 
-        let database_url = env::var("DATABASE_URL")
-            .map_err(|_| "DATABASE_URL must be set in .env file".to_string())?;
+        // ogni valore non valido viene annotato e sostituito da un segnaposto: il caricamento
+        // prosegue e fallisce alla fine con l'elenco completo dei problemi
+        let mut problems: Vec<String> = Vec::new();
+
+        let config_path = config_file_path(env::args().skip(1), env::var(CONFIG_FILE_ENV).ok());
+        let file_vars = match &config_path {
+            Some(path) => ConfigFile::load(path)
+                .map(ConfigFile::into_vars)
+                .unwrap_or_else(|problem| {
+                    problems.push(problem);
+                    HashMap::new()
+                }),
+            None => HashMap::new(),
+        };
+
+        let mut secrets: HashMap<&str, String> = HashMap::new();
+        for &name in SECRET_VARS {
+            if let Ok(path) = env::var(format!("{}_FILE", name)) {
+                match read_secret_file(&path) {
+                    Ok(value) => {
+                        secrets.insert(name, value);
+                    }
+                    Err(e) => problems.push(format!("Invalid {}_FILE: {}", name, e)),
+                }
+            }
+        }
+
+        // Vault si configura solo dall'ambiente: il token non deve finire nel file
+        let vault = match env::var("VAULT_ADDR") {
+            Ok(addr) => Some(VaultConfig {
+                addr,
+                kv_mount: env::var("VAULT_KV_MOUNT")
+                    .unwrap_or_else(|_| DEFAULT_VAULT_KV_MOUNT.to_string()),
+                secret_path: env::var("VAULT_SECRET_PATH")
+                    .map_err(|_| "VAULT_SECRET_PATH must be set when VAULT_ADDR is set".to_string())
+                    .report(&mut problems),
+                namespace: env::var("VAULT_NAMESPACE").ok(),
+            }),
+            Err(_) => None,
+        };
+        if let Some(vault) = vault.as_ref().filter(|vault| !vault.secret_path.is_empty()) {
+            let token = env::var("VAULT_TOKEN")
+                .ok()
+                .or_else(|| secrets.get("VAULT_TOKEN").cloned())
+                .unwrap_or_default();
+            let values = match VaultClient::new(&vault.addr, token, vault.namespace.clone()) {
+                Ok(client) => client.read_kv(&vault.kv_mount, &vault.secret_path).await,
+                Err(e) => Err(e),
+            };
+            match values {
+                // i file dei segreti prevalgono su Vault; le altre chiavi del segreto sono ignorate
+                Ok(values) => {
+                    for (key, value) in values {
+                        if let Some(&name) = SECRET_VARS.iter().find(|&&name| name == key) {
+                            secrets.entry(name).or_insert(value);
+                        }
+                    }
+                }
+                Err(e) => problems.push(e),
+            }
+        }
+
+        // l'ambiente prevale sui segreti, che prevalgono sul file
+        let var = |name: &str| -> Result<String, env::VarError> {
+            env::var(name).or_else(|error| {
+                secrets
+                    .get(name)
+                    .or_else(|| file_vars.get(name))
+                    .cloned()
+                    .ok_or(error)
+            })
+        };
+
+        // il profilo va letto per primo: cambia i default di diverse impostazioni
+        let profile = var("APP_ENV")
+            .map(|name| {
+                AppProfile::from_name(&name)
+                    .ok_or_else(|| {
+                        "Invalid APP_ENV: must be 'development', 'staging' or 'production'"
+                            .to_string()
+                    })
+                    .report(&mut problems)
+            })
+            .unwrap_or_default();
+
+        // credenziali tenute separate dall'URL, ad esempio lette da file o da Vault
+        let database_user = var("DATABASE_USER").ok();
+        let database_password = var("DATABASE_PASSWORD").ok();
+        let credentials = |name: &str, url: String| -> Result<String, String> {
+            if url.is_empty() {
+                return Ok(url);
+            }
+            with_credentials(&url, database_user.as_deref(), database_password.as_deref())
+                .map_err(|e| format!("Invalid {}: {}", name, e))
+        };
+
+        let database_url = var("DATABASE_URL")
+            .map_err(|_| "DATABASE_URL must be set in .env file".to_string())
+            .and_then(|url| credentials("DATABASE_URL", url))
+            .report(&mut problems);
+
+        // senza replica tutte le query vanno sul database primario
+        let database_read_url = var("DATABASE_READ_URL")
+            .ok()
+            .filter(|url| !url.is_empty())
+            .map(|url| credentials("DATABASE_READ_URL", url).report(&mut problems));
 
-        let jwt_secret = env::var("JWT_SECRET").unwrap_or_else(|_| {
-            eprintln!("WARNING: JWT_SECRET not set, using default (not secure for production!)");
-            "un segreto meno bello".to_string()
-        });
+        // firma asimmetrica: chi verifica i token ha bisogno solo della chiave pubblica
+        let jwt_algorithm = var("JWT_ALGORITHM").unwrap_or_else(|_| "HS256".to_string());
+        let mut jwt_key_path = |name: &str| {
+            var(name)
+                .map(PathBuf::from)
+                .map_err(|_| format!("{} must be set when JWT_ALGORITHM={}", name, jwt_algorithm))
+                .report(&mut problems)
+        };
+        let jwt_key_pair = match jwt_algorithm.to_uppercase().as_str() {
+            "HS256" => {
+                if var("JWT_PRIVATE_KEY_PATH").is_ok() || var("JWT_PUBLIC_KEY_PATH").is_ok() {
+                    problems.push(
+                        "JWT_PRIVATE_KEY_PATH and JWT_PUBLIC_KEY_PATH need JWT_ALGORITHM=RS256 or EdDSA"
+                            .to_string(),
+                    );
+                }
+                None
+            }
+            algorithm @ ("RS256" | "EDDSA") => Some(JwtKeyPairConfig {
+                algorithm: if algorithm == "RS256" {
+                    Algorithm::RS256
+                } else {
+                    Algorithm::EdDSA
+                },
+                private_key_path: jwt_key_path("JWT_PRIVATE_KEY_PATH"),
+                public_key_path: jwt_key_path("JWT_PUBLIC_KEY_PATH"),
+                previous_public_key_paths: var("JWT_PREVIOUS_PUBLIC_KEY_PATHS")
+                    .map(|paths| {
+                        paths
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|path| !path.is_empty())
+                            .map(PathBuf::from)
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            }),
+            _ => {
+                problems.push(format!(
+                    "Invalid JWT_ALGORITHM: {} (expected HS256, RS256 or EdDSA)",
+                    jwt_algorithm
+                ));
+                None
+            }
+        };
 
-        let server_host = env::var("SERVER_HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
+        let jwt_secret = match var("JWT_SECRET") {
+            Ok(secret) => {
+                if secret.len() < MIN_JWT_SECRET_BYTES {
+                    problems.push(format!(
+                        "JWT_SECRET is too short: use at least {} random bytes (e.g. `openssl rand -base64 48`)",
+                        MIN_JWT_SECRET_BYTES
+                    ));
+                }
+                secret
+            }
+            // con RS256 o EdDSA il segreto non firma nulla
+            Err(_) if jwt_key_pair.is_some() => "un segreto meno bello".to_string(),
+            Err(_) => {
+                if profile.requires_jwt_secret() {
+                    problems.push(format!(
+                        "JWT_SECRET must be set when APP_ENV={}",
+                        profile.as_str()
+                    ));
+                }
+                eprintln!(
+                    "WARNING: JWT_SECRET not set, using default (not secure for production!)"
+                );
+                "un segreto meno bello".to_string()
+            }
+        };
 
-        let server_port = env::var("SERVER_PORT")
+        // rotazione: la chiave precedente resta qui finché i suoi token non scadono
+        let jwt_previous_secrets: Vec<String> = var("JWT_PREVIOUS_SECRETS")
+            .map(|secrets| {
+                secrets
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|secret| !secret.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        if jwt_previous_secrets
+            .iter()
+            .any(|secret| secret.len() < MIN_JWT_SECRET_BYTES)
+        {
+            problems.push(format!(
+                "JWT_PREVIOUS_SECRETS contains a key shorter than {} bytes",
+                MIN_JWT_SECRET_BYTES
+            ));
+        }
+
+        let access_token = AccessTokenConfig {
+            ttl_minutes: var("ACCESS_TOKEN_TTL_MINUTES")
+                .unwrap_or_else(|_| DEFAULT_ACCESS_TOKEN_TTL_MINUTES.to_string())
+                .parse::<i64>()
+                .ok()
+                .filter(|&minutes| minutes >= 1)
+                .ok_or_else(|| {
+                    "Invalid ACCESS_TOKEN_TTL_MINUTES: must be a positive number of minutes"
+                        .to_string()
+                })
+                .report(&mut problems),
+            issuer: var("JWT_ISSUER").ok().filter(|issuer| !issuer.is_empty()),
+            audience: var("JWT_AUDIENCE")
+                .ok()
+                .filter(|audience| !audience.is_empty()),
+        };
+
+        let server_host = var("SERVER_HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
+
+        let server_port = var("SERVER_PORT")
             .unwrap_or_else(|_| "3000".to_string())
             .parse::<u16>()
-            .map_err(|_| "Invalid SERVER_PORT: must be a number between 0-65535".to_string())?;
+            .ok()
+            .filter(|&port| port >= 1)
+            .ok_or_else(|| "Invalid SERVER_PORT: must be a number between 1-65535".to_string())
+            .report(&mut problems);
 
-        let max_connections = env::var("MAX_DB_CONNECTIONS")
+        // TLS opzionale: certificato da file oppure via ACME, non entrambi
+        let tls = match (var("TLS_CERT_PATH"), var("ACME_DOMAINS")) {
+            (Ok(_), Ok(_)) => {
+                problems.push(
+                    "TLS_CERT_PATH and ACME_DOMAINS cannot be set together: keep only one of them"
+                        .to_string(),
+                );
+                TlsConfig::Disabled
+            }
+            (Ok(cert_path), Err(_)) => TlsConfig::Files {
+                cert_path: PathBuf::from(cert_path),
+                key_path: var("TLS_KEY_PATH")
+                    .map(PathBuf::from)
+                    .map_err(|_| "TLS_KEY_PATH must be set when TLS_CERT_PATH is set".to_string())
+                    .report(&mut problems),
+            },
+            (Err(_), Ok(domains)) => {
+                let domains: Vec<String> = domains
+                    .split(',')
+                    .map(|domain| domain.trim().to_lowercase())
+                    .filter(|domain| !domain.is_empty())
+                    .collect();
+                if domains.is_empty() {
+                    problems
+                        .push("Invalid ACME_DOMAINS: must list at least one domain".to_string());
+                }
+                TlsConfig::Acme(AcmeConfig {
+                    domains,
+                    contact_email: var("ACME_CONTACT_EMAIL").ok(),
+                    directory_url: var("ACME_DIRECTORY_URL")
+                        .unwrap_or_else(|_| DEFAULT_ACME_DIRECTORY_URL.to_string()),
+                    cache_dir: PathBuf::from(
+                        var("ACME_CACHE_DIR")
+                            .unwrap_or_else(|_| DEFAULT_ACME_CACHE_DIR.to_string()),
+                    ),
+                })
+            }
+            (Err(_), Err(_)) => {
+                if var("TLS_KEY_PATH").is_ok() {
+                    problems.push(
+                        "TLS_KEY_PATH is set but TLS_CERT_PATH is not: set both or neither"
+                            .to_string(),
+                    );
+                }
+                TlsConfig::Disabled
+            }
+        };
+
+        // liste separate da virgole; "*" o variabile assente = nessuna restrizione
+        let list = |name: &str| -> Vec<String> {
+            var(name)
+                .map(|value| {
+                    value
+                        .split(',')
+                        .map(|item| item.trim().to_string())
+                        .filter(|item| !item.is_empty() && item != "*")
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        let cors = CorsConfig {
+            allowed_origins: list("CORS_ALLOWED_ORIGINS"),
+            allowed_headers: list("CORS_ALLOWED_HEADERS"),
+            allow_credentials: var("CORS_ALLOW_CREDENTIALS")
+                .map(|value| value == "true")
+                .unwrap_or(false),
+            max_age_secs: match var("CORS_MAX_AGE_SECS") {
+                Ok(value) => value
+                    .parse::<u64>()
+                    .map_err(|_| "Invalid CORS_MAX_AGE_SECS: must be a number".to_string())
+                    .report(&mut problems),
+                Err(_) => DEFAULT_CORS_MAX_AGE_SECS,
+            },
+            // "*" esplicito ammette qualsiasi origine anche nei profili restrittivi
+            strict: var("CORS_STRICT")
+                .map(|value| value == "true")
+                .unwrap_or_else(|_| {
+                    profile.default_cors_strict()
+                        && !var("CORS_ALLOWED_ORIGINS")
+                            .is_ok_and(|value| value.split(',').any(|origin| origin.trim() == "*"))
+                }),
+        };
+        if cors.allow_credentials && cors.allowed_origins.is_empty() {
+            problems.push(
+                "CORS_ALLOW_CREDENTIALS requires CORS_ALLOWED_ORIGINS to list explicit origins"
+                    .to_string(),
+            );
+        }
+
+        let max_connections = var("MAX_DB_CONNECTIONS")
             .unwrap_or_else(|_| "1000".to_string())
             .parse::<u32>()
-            .map_err(|_| "Invalid MAX_DB_CONNECTIONS: must be a positive number".to_string())?;
+            .ok()
+            .filter(|&max| max >= 1)
+            .ok_or_else(|| "Invalid MAX_DB_CONNECTIONS: must be a positive number".to_string())
+            .report(&mut problems);
 
-        let connection_lifetime_secs = env::var("DB_CONNECTION_LIFETIME_SECS")
+        let min_connections = var("MIN_DB_CONNECTIONS")
+            .unwrap_or_else(|_| "0".to_string())
+            .parse::<u32>()
+            .map_err(|_| "Invalid MIN_DB_CONNECTIONS: must be a number".to_string())
+            .report(&mut problems);
+        // con MAX_DB_CONNECTIONS non valido il confronto non avrebbe senso
+        if max_connections >= 1 && min_connections > max_connections {
+            problems.push("MIN_DB_CONNECTIONS cannot exceed MAX_DB_CONNECTIONS".to_string());
+        }
+
+        let connection_lifetime_secs = var("DB_CONNECTION_LIFETIME_SECS")
             .unwrap_or_else(|_| "1".to_string())
             .parse::<u64>()
             .map_err(|_| {
                 "Invalid DB_CONNECTION_LIFETIME_SECS: must be a positive number".to_string()
-            })?;
+            })
+            .report(&mut problems);
+
+        let acquire_timeout_secs = var("DB_ACQUIRE_TIMEOUT_SECS")
+            .unwrap_or_else(|_| "2".to_string())
+            .parse::<u64>()
+            .ok()
+            .filter(|&secs| secs >= 1)
+            .ok_or_else(|| "Invalid DB_ACQUIRE_TIMEOUT_SECS: must be a positive number".to_string())
+            .report(&mut problems);
 
-        let app_env = env::var("APP_ENV").unwrap_or_else(|_| "development".to_string());
+        let idle_timeout_secs = var("DB_IDLE_TIMEOUT_SECS")
+            .unwrap_or_else(|_| "600".to_string())
+            .parse::<u64>()
+            .map_err(|_| "Invalid DB_IDLE_TIMEOUT_SECS: must be a number".to_string())
+            .report(&mut problems);
+
+        let test_before_acquire = var("DB_TEST_BEFORE_ACQUIRE")
+            .map(|value| value == "true")
+            .unwrap_or(true);
+
+        let slow_query_threshold_ms = var("SLOW_QUERY_THRESHOLD_MS")
+            .unwrap_or_else(|_| "500".to_string())
+            .parse::<u64>()
+            .map_err(|_| "Invalid SLOW_QUERY_THRESHOLD_MS: must be a number".to_string())
+            .report(&mut problems);
+
+        let log_level = var("LOG_LEVEL").unwrap_or_else(|_| "info".to_string());
+
+        let log_format = match var("LOG_FORMAT") {
+            Ok(format) => match format.as_str() {
+                "pretty" => LogFormat::Pretty,
+                "full" => LogFormat::Full,
+                "compact" => LogFormat::Compact,
+                _ => {
+                    problems.push(
+                        "Invalid LOG_FORMAT: must be 'pretty', 'full' or 'compact'".to_string(),
+                    );
+                    profile.default_log_format()
+                }
+            },
+            Err(_) => profile.default_log_format(),
+        };
+
+        let cookie_secure = var("COOKIE_SECURE")
+            .map(|value| value == "true")
+            .unwrap_or(profile.default_cookie_secure());
+
+        let storage = match var("STORAGE_BACKEND")
+            .unwrap_or_else(|_| "local".to_string())
+            .as_str()
+        {
+            "local" => StorageConfig::Local {
+                root: PathBuf::from(var("UPLOAD_DIR").unwrap_or_else(|_| "uploads".to_string())),
+            },
+            "s3" => StorageConfig::S3 {
+                bucket: var("S3_BUCKET")
+                    .map_err(|_| "S3_BUCKET must be set when STORAGE_BACKEND=s3".to_string())
+                    .report(&mut problems),
+                region: var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+                endpoint: var("S3_ENDPOINT").ok(),
+                access_key_id: var("S3_ACCESS_KEY_ID")
+                    .map_err(|_| "S3_ACCESS_KEY_ID must be set when STORAGE_BACKEND=s3".to_string())
+                    .report(&mut problems),
+                secret_access_key: var("S3_SECRET_ACCESS_KEY")
+                    .map_err(|_| {
+                        "S3_SECRET_ACCESS_KEY must be set when STORAGE_BACKEND=s3".to_string()
+                    })
+                    .report(&mut problems),
+            },
+            _ => {
+                problems.push("Invalid STORAGE_BACKEND: must be 'local' or 's3'".to_string());
+                StorageConfig::Local {
+                    root: PathBuf::new(),
+                }
+            }
+        };
+
+        let max_attachment_bytes = match var("MAX_ATTACHMENT_BYTES") {
+            Ok(value) => value
+                .parse::<usize>()
+                .map_err(|_| "Invalid MAX_ATTACHMENT_BYTES: must be a positive number".to_string())
+                .report(&mut problems),
+            Err(_) => DEFAULT_MAX_ATTACHMENT_BYTES,
+        };
+
+        let max_json_body_bytes = match var("MAX_JSON_BODY_BYTES") {
+            Ok(value) => value
+                .parse::<usize>()
+                .ok()
+                .filter(|&bytes| bytes > 0)
+                .ok_or_else(|| "Invalid MAX_JSON_BODY_BYTES: must be a positive number".to_string())
+                .report(&mut problems),
+            Err(_) => DEFAULT_MAX_JSON_BODY_BYTES,
+        };
+
+        let legacy_api_paths = var("LEGACY_API_PATHS")
+            .map(|value| value != "false")
+            .unwrap_or(true);
+
+        let max_group_members = match var("MAX_GROUP_MEMBERS") {
+            Ok(value) => value
+                .parse::<usize>()
+                .ok()
+                .filter(|&max| max >= 1)
+                .ok_or_else(|| "Invalid MAX_GROUP_MEMBERS: must be at least 1".to_string())
+                .report(&mut problems),
+            Err(_) => DEFAULT_MAX_GROUP_MEMBERS,
+        };
+
+        // la traduzione dei messaggi è opzionale: senza URL l'endpoint risponde 503
+        let translation_api_url = var("TRANSLATION_API_URL").ok();
+        let translation_api_key = var("TRANSLATION_API_KEY").ok();
+
+        // senza Redis revoche dei token, presenza e cache dei repository restano in memoria
+        // (singola istanza)
+        let redis_url = var("REDIS_URL").ok();
+
+        // il login SSO è opzionale: senza OIDC_ISSUER_URL gli endpoint rispondono 503
+        let oidc = match var("OIDC_ISSUER_URL") {
+            Ok(issuer_url) => Some(OidcConfig {
+                issuer_url,
+                client_id: var("OIDC_CLIENT_ID")
+                    .map_err(|_| {
+                        "OIDC_CLIENT_ID must be set when OIDC_ISSUER_URL is set".to_string()
+                    })
+                    .report(&mut problems),
+                client_secret: var("OIDC_CLIENT_SECRET")
+                    .map_err(|_| {
+                        "OIDC_CLIENT_SECRET must be set when OIDC_ISSUER_URL is set".to_string()
+                    })
+                    .report(&mut problems),
+                redirect_url: var("OIDC_REDIRECT_URL")
+                    .map_err(|_| {
+                        "OIDC_REDIRECT_URL must be set when OIDC_ISSUER_URL is set".to_string()
+                    })
+                    .report(&mut problems),
+                allow_hmac: var("OIDC_ALLOW_HMAC")
+                    .map(|value| value == "true")
+                    .unwrap_or(false),
+            }),
+            Err(_) => None,
+        };
+
+        let auth_backend = match var("AUTH_BACKEND")
+            .unwrap_or_else(|_| "local".to_string())
+            .as_str()
+        {
+            "local" => AuthBackendConfig::Local,
+            "ldap" => AuthBackendConfig::Ldap(LdapConfig {
+                url: var("LDAP_URL")
+                    .map_err(|_| "LDAP_URL must be set when AUTH_BACKEND=ldap".to_string())
+                    .report(&mut problems),
+                bind_dn_template: var("LDAP_BIND_DN_TEMPLATE")
+                    .map_err(|_| {
+                        "LDAP_BIND_DN_TEMPLATE must be set when AUTH_BACKEND=ldap".to_string()
+                    })
+                    .report(&mut problems),
+            }),
+            _ => {
+                problems.push("Invalid AUTH_BACKEND: must be 'local' or 'ldap'".to_string());
+                AuthBackendConfig::Local
+            }
+        };
+
+        let cache = CacheConfig {
+            ttl_secs: match var("CACHE_TTL_SECS") {
+                Ok(value) => value
+                    .parse::<u64>()
+                    .map_err(|_| {
+                        "Invalid CACHE_TTL_SECS: must be a number (0 disables)".to_string()
+                    })
+                    .report(&mut problems),
+                Err(_) => DEFAULT_CACHE_TTL_SECS,
+            },
+            max_entries: match var("CACHE_MAX_ENTRIES") {
+                Ok(value) => value
+                    .parse::<u64>()
+                    .map_err(|_| "Invalid CACHE_MAX_ENTRIES: must be a positive number".to_string())
+                    .report(&mut problems),
+                Err(_) => DEFAULT_CACHE_MAX_ENTRIES,
+            },
+        };
+
+        let rate_limit = RateLimitConfig {
+            auth_per_minute: match var("RATE_LIMIT_AUTH_PER_MINUTE") {
+                Ok(value) => value
+                    .parse::<u32>()
+                    .map_err(|_| {
+                        "Invalid RATE_LIMIT_AUTH_PER_MINUTE: must be a number (0 disables)"
+                            .to_string()
+                    })
+                    .report(&mut problems),
+                Err(_) => profile.default_auth_rate_limit(),
+            },
+            user_per_minute: match var("RATE_LIMIT_USER_PER_MINUTE") {
+                Ok(value) => value
+                    .parse::<u32>()
+                    .map_err(|_| {
+                        "Invalid RATE_LIMIT_USER_PER_MINUTE: must be a number (0 disables)"
+                            .to_string()
+                    })
+                    .report(&mut problems),
+                Err(_) => profile.default_user_rate_limit(),
+            },
+            trust_forwarded_for: var("RATE_LIMIT_TRUST_PROXY")
+                .map(|value| value == "true")
+                .unwrap_or(false),
+        };
+
+        let login_lockout = LoginLockoutConfig {
+            max_failures: match var("LOGIN_MAX_FAILURES") {
+                Ok(value) => value
+                    .parse::<u32>()
+                    .map_err(|_| {
+                        "Invalid LOGIN_MAX_FAILURES: must be a number (0 disables)".to_string()
+                    })
+                    .report(&mut problems),
+                Err(_) => DEFAULT_LOGIN_MAX_FAILURES,
+            },
+            lockout_secs: match var("LOGIN_LOCKOUT_SECS") {
+                Ok(value) => value
+                    .parse::<u64>()
+                    .map_err(|_| {
+                        "Invalid LOGIN_LOCKOUT_SECS: must be a positive number".to_string()
+                    })
+                    .report(&mut problems),
+                Err(_) => DEFAULT_LOGIN_LOCKOUT_SECS,
+            },
+            max_lockout_secs: match var("LOGIN_LOCKOUT_MAX_SECS") {
+                Ok(value) => value
+                    .parse::<u64>()
+                    .map_err(|_| {
+                        "Invalid LOGIN_LOCKOUT_MAX_SECS: must be a positive number".to_string()
+                    })
+                    .report(&mut problems),
+                Err(_) => DEFAULT_LOGIN_LOCKOUT_MAX_SECS,
+            },
+        };
 
-        let log_level = env::var("LOG_LEVEL").unwrap_or_else(|_| "info".to_string());
+        let flood = FloodConfig {
+            window_secs: match var("FLOOD_WINDOW_SECS") {
+                Ok(value) => value
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|&secs| secs >= 1)
+                    .ok_or_else(|| "Invalid FLOOD_WINDOW_SECS: must be at least 1".to_string())
+                    .report(&mut problems),
+                Err(_) => DEFAULT_FLOOD_WINDOW_SECS,
+            },
+            repeat_threshold: match var("FLOOD_REPEAT_THRESHOLD") {
+                Ok(value) => value
+                    .parse::<u32>()
+                    .map_err(|_| {
+                        "Invalid FLOOD_REPEAT_THRESHOLD: must be a number (0 disables)".to_string()
+                    })
+                    .report(&mut problems),
+                Err(_) => DEFAULT_FLOOD_REPEAT_THRESHOLD,
+            },
+            link_threshold: match var("FLOOD_LINK_THRESHOLD") {
+                Ok(value) => value
+                    .parse::<u32>()
+                    .map_err(|_| {
+                        "Invalid FLOOD_LINK_THRESHOLD: must be a number (0 disables)".to_string()
+                    })
+                    .report(&mut problems),
+                Err(_) => DEFAULT_FLOOD_LINK_THRESHOLD,
+            },
+            join_leave_threshold: match var("FLOOD_JOIN_LEAVE_THRESHOLD") {
+                Ok(value) => value
+                    .parse::<u32>()
+                    .map_err(|_| {
+                        "Invalid FLOOD_JOIN_LEAVE_THRESHOLD: must be a number (0 disables)"
+                            .to_string()
+                    })
+                    .report(&mut problems),
+                Err(_) => DEFAULT_FLOOD_JOIN_LEAVE_THRESHOLD,
+            },
+            mute_secs: match var("FLOOD_MUTE_SECS") {
+                Ok(value) => value
+                    .parse::<u64>()
+                    .map_err(|_| "Invalid FLOOD_MUTE_SECS: must be a positive number".to_string())
+                    .report(&mut problems),
+                Err(_) => DEFAULT_FLOOD_MUTE_SECS,
+            },
+            max_mute_secs: match var("FLOOD_MUTE_MAX_SECS") {
+                Ok(value) => value
+                    .parse::<u64>()
+                    .map_err(|_| {
+                        "Invalid FLOOD_MUTE_MAX_SECS: must be a positive number".to_string()
+                    })
+                    .report(&mut problems),
+                Err(_) => DEFAULT_FLOOD_MUTE_MAX_SECS,
+            },
+        };
 
-        Ok(Config {
+        // i requisiti sono attivi di default: si disabilitano solo con "false"
+        let password_policy = PasswordPolicyConfig {
+            min_length: match var("PASSWORD_MIN_LENGTH") {
+                Ok(value) => value
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&min| min >= 1)
+                    .ok_or_else(|| "Invalid PASSWORD_MIN_LENGTH: must be at least 1".to_string())
+                    .report(&mut problems),
+                Err(_) => DEFAULT_PASSWORD_MIN_LENGTH,
+            },
+            require_mixed_case: var("PASSWORD_REQUIRE_MIXED_CASE")
+                .map(|value| value != "false")
+                .unwrap_or(true),
+            require_digit: var("PASSWORD_REQUIRE_DIGIT")
+                .map(|value| value != "false")
+                .unwrap_or(true),
+            reject_common: var("PASSWORD_REJECT_COMMON")
+                .map(|value| value != "false")
+                .unwrap_or(true),
+        };
+
+        // cambiare algoritmo o parametri non invalida gli hash esistenti:
+        // vengono ricalcolati al login successivo di ogni utente
+        let password_hash = match var("PASSWORD_HASH_ALGORITHM")
+            .unwrap_or_else(|_| "bcrypt".to_string())
+            .as_str()
+        {
+            "bcrypt" => PasswordHashConfig::Bcrypt {
+                cost: match var("BCRYPT_COST") {
+                    Ok(value) => value
+                        .parse::<u32>()
+                        .map_err(|_| "Invalid BCRYPT_COST: must be a number".to_string())
+                        .report(&mut problems),
+                    Err(_) => bcrypt::DEFAULT_COST,
+                },
+            },
+            "argon2id" => PasswordHashConfig::Argon2id {
+                memory_kib: match var("ARGON2_MEMORY_KIB") {
+                    Ok(value) => value
+                        .parse::<u32>()
+                        .map_err(|_| "Invalid ARGON2_MEMORY_KIB: must be a number".to_string())
+                        .report(&mut problems),
+                    Err(_) => DEFAULT_ARGON2_MEMORY_KIB,
+                },
+                iterations: match var("ARGON2_ITERATIONS") {
+                    Ok(value) => value
+                        .parse::<u32>()
+                        .map_err(|_| "Invalid ARGON2_ITERATIONS: must be a number".to_string())
+                        .report(&mut problems),
+                    Err(_) => DEFAULT_ARGON2_ITERATIONS,
+                },
+                parallelism: match var("ARGON2_PARALLELISM") {
+                    Ok(value) => value
+                        .parse::<u32>()
+                        .map_err(|_| "Invalid ARGON2_PARALLELISM: must be a number".to_string())
+                        .report(&mut problems),
+                    Err(_) => DEFAULT_ARGON2_PARALLELISM,
+                },
+            },
+            _ => {
+                problems.push(
+                    "Invalid PASSWORD_HASH_ALGORITHM: must be 'bcrypt' or 'argon2id'".to_string(),
+                );
+                PasswordHashConfig::default()
+            }
+        };
+
+        let ws = WsConfig {
+            batch_interval_ms: match var("WS_BATCH_INTERVAL_MS") {
+                Ok(value) => value
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|&ms| ms >= 1)
+                    .ok_or_else(|| "Invalid WS_BATCH_INTERVAL_MS: must be at least 1".to_string())
+                    .report(&mut problems),
+                Err(_) => DEFAULT_WS_BATCH_INTERVAL_MS,
+            },
+            batch_max_size: match var("WS_BATCH_MAX_SIZE") {
+                Ok(value) => value
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&size| size >= 1)
+                    .ok_or_else(|| "Invalid WS_BATCH_MAX_SIZE: must be at least 1".to_string())
+                    .report(&mut problems),
+                Err(_) => DEFAULT_WS_BATCH_MAX_SIZE,
+            },
+            idle_timeout_secs: match var("WS_IDLE_TIMEOUT_SECS") {
+                Ok(value) => value
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|&secs| secs >= 1)
+                    .ok_or_else(|| "Invalid WS_IDLE_TIMEOUT_SECS: must be at least 1".to_string())
+                    .report(&mut problems),
+                Err(_) => DEFAULT_WS_IDLE_TIMEOUT_SECS,
+            },
+            messages_per_second: match var("WS_MESSAGES_PER_SECOND") {
+                Ok(value) => value
+                    .parse::<u32>()
+                    .map_err(|_| {
+                        "Invalid WS_MESSAGES_PER_SECOND: must be a number (0 disables)".to_string()
+                    })
+                    .report(&mut problems),
+                Err(_) => DEFAULT_WS_MESSAGES_PER_SECOND,
+            },
+            message_burst: match var("WS_MESSAGE_BURST") {
+                Ok(value) => value
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|&burst| burst >= 1)
+                    .ok_or_else(|| "Invalid WS_MESSAGE_BURST: must be at least 1".to_string())
+                    .report(&mut problems),
+                Err(_) => DEFAULT_WS_MESSAGE_BURST,
+            },
+            signal_queue_capacity: match var("WS_SIGNAL_QUEUE_CAPACITY") {
+                Ok(value) => value
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&capacity| capacity >= 1)
+                    .ok_or_else(|| {
+                        "Invalid WS_SIGNAL_QUEUE_CAPACITY: must be at least 1".to_string()
+                    })
+                    .report(&mut problems),
+                Err(_) => DEFAULT_WS_SIGNAL_QUEUE_CAPACITY,
+            },
+            overflow_policy: match var("WS_OVERFLOW_POLICY")
+                .unwrap_or_else(|_| "drop_oldest".to_string())
+                .as_str()
+            {
+                "drop_oldest" => WsOverflowPolicy::DropOldest,
+                "disconnect" => WsOverflowPolicy::Disconnect,
+                _ => {
+                    problems.push(
+                        "Invalid WS_OVERFLOW_POLICY: must be 'drop_oldest' or 'disconnect'"
+                            .to_string(),
+                    );
+                    WsOverflowPolicy::DropOldest
+                }
+            },
+            received_flush_interval_ms: match var("WS_RECEIVED_FLUSH_INTERVAL_MS") {
+                Ok(value) => value
+                    .parse::<u64>()
+                    .map_err(|_| {
+                        "Invalid WS_RECEIVED_FLUSH_INTERVAL_MS: must be a number (0 disables)"
+                            .to_string()
+                    })
+                    .report(&mut problems),
+                Err(_) => DEFAULT_WS_RECEIVED_FLUSH_INTERVAL_MS,
+            },
+        };
+
+        // senza EVENT_BUS_BACKEND gli eventi di dominio non vengono pubblicati
+        let event_bus = match var("EVENT_BUS_BACKEND")
+            .unwrap_or_else(|_| "none".to_string())
+            .as_str()
+        {
+            "none" => EventBusConfig::Disabled,
+            backend @ ("nats" | "kafka") => {
+                let url = var("EVENT_BUS_URL")
+                    .map_err(|_| {
+                        format!(
+                            "EVENT_BUS_URL must be set when EVENT_BUS_BACKEND={}",
+                            backend
+                        )
+                    })
+                    .report(&mut problems);
+                let topic =
+                    var("EVENT_BUS_TOPIC").unwrap_or_else(|_| DEFAULT_EVENT_BUS_TOPIC.to_string());
+                if backend == "nats" {
+                    EventBusConfig::Nats {
+                        url,
+                        subject: topic,
+                    }
+                } else {
+                    EventBusConfig::Kafka {
+                        rest_url: url,
+                        topic,
+                    }
+                }
+            }
+            _ => {
+                problems.push(
+                    "Invalid EVENT_BUS_BACKEND: must be 'none', 'nats' or 'kafka'".to_string(),
+                );
+                EventBusConfig::Disabled
+            }
+        };
+
+        let content_filter = ContentFilterConfig {
+            wordlist_path: var("CONTENT_FILTER_WORDLIST").ok(),
+            classifier_url: var("CONTENT_FILTER_CLASSIFIER_URL").ok(),
+        };
+
+        // nessun amministratore se la variabile manca: le route /admin rispondono 403 a tutti
+        // si usano gli id e non gli username: un username torna libero quando l'account viene
+        // eliminato e chi lo registra di nuovo non deve ereditare il ruolo
+        let server_admins = var("SERVER_ADMINS")
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|user_id| !user_id.is_empty())
+                    .map(|user_id| user_id.parse::<i32>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| "Invalid SERVER_ADMINS: must be a list of user ids".to_string())
+                    .report(&mut problems)
+            })
+            .unwrap_or_default();
+
+        let config = Config {
+            config_file: config_path,
+            vault,
             database_url,
+            database_read_url,
             jwt_secret,
+            jwt_previous_secrets,
+            jwt_key_pair,
+            access_token,
             server_host,
             server_port,
+            tls,
+            cors,
             max_connections,
+            min_connections,
             connection_lifetime_secs,
-            app_env,
+            acquire_timeout_secs,
+            idle_timeout_secs,
+            test_before_acquire,
+            slow_query_threshold_ms,
+            cache,
+            profile,
             log_level,
-        })
+            log_format,
+            cookie_secure,
+            storage,
+            max_attachment_bytes,
+            max_json_body_bytes,
+            legacy_api_paths,
+            max_group_members,
+            translation_api_url,
+            translation_api_key,
+            redis_url,
+            oidc,
+            auth_backend,
+            rate_limit,
+            login_lockout,
+            flood,
+            password_policy,
+            password_hash,
+            ws,
+            event_bus,
+            content_filter,
+            server_admins,
+        };
+
+        problems.extend(config.validate());
+        if !problems.is_empty() {
+            return Err(ConfigError { problems });
+        }
+        Ok(config)
+    }
+
+    /// Controlli sulla configurazione completa: formati degli URL, file indicati e valori
+    /// che altrimenti verrebbero rifiutati solo dal componente che li usa, a server avviato
+    fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if self.server_host.parse::<IpAddr>().is_err() {
+            problems.push(
+                "Invalid SERVER_HOST: must be an IP address such as 127.0.0.1 or 0.0.0.0"
+                    .to_string(),
+            );
+        }
+
+        problems.extend(check_database_url("DATABASE_URL", &self.database_url));
+        if let Some(url) = &self.database_read_url {
+            problems.extend(check_database_url("DATABASE_READ_URL", url));
+            if is_sqlite_url(url) != is_sqlite_url(&self.database_url) {
+                problems.push(
+                    "Invalid DATABASE_READ_URL: must use the same database as DATABASE_URL"
+                        .to_string(),
+                );
+            }
+        }
+
+        let http_schemes = ["http", "https"];
+        if let Some(url) = &self.translation_api_url {
+            problems.extend(check_url("TRANSLATION_API_URL", url, &http_schemes));
+        }
+        if let Some(url) = &self.content_filter.classifier_url {
+            problems.extend(check_url(
+                "CONTENT_FILTER_CLASSIFIER_URL",
+                url,
+                &http_schemes,
+            ));
+        }
+        if let Some(oidc) = &self.oidc {
+            problems.extend(check_url(
+                "OIDC_ISSUER_URL",
+                &oidc.issuer_url,
+                &http_schemes,
+            ));
+            problems.extend(check_url(
+                "OIDC_REDIRECT_URL",
+                &oidc.redirect_url,
+                &http_schemes,
+            ));
+        }
+        if let StorageConfig::S3 {
+            endpoint: Some(endpoint),
+            ..
+        } = &self.storage
+        {
+            problems.extend(check_url("S3_ENDPOINT", endpoint, &http_schemes));
+        }
+
+        match &self.tls {
+            TlsConfig::Files {
+                cert_path,
+                key_path,
+            } => {
+                for (name, path) in [("TLS_CERT_PATH", cert_path), ("TLS_KEY_PATH", key_path)] {
+                    if !path.as_os_str().is_empty() && !path.is_file() {
+                        problems.push(format!("{}: file not found: {}", name, path.display()));
+                    }
+                }
+            }
+            TlsConfig::Acme(acme) => {
+                problems.extend(check_url(
+                    "ACME_DIRECTORY_URL",
+                    &acme.directory_url,
+                    &http_schemes,
+                ));
+            }
+            TlsConfig::Disabled => {}
+        }
+
+        // legge le chiavi e controlla che privata e pubblica siano della stessa coppia
+        if let Some(pair) = &self.jwt_key_pair
+            && !pair.private_key_path.as_os_str().is_empty()
+            && !pair.public_key_path.as_os_str().is_empty()
+        {
+            problems.extend(JwtKeys::from_config(self).err());
+        }
+
+        if let Some(path) = &self.content_filter.wordlist_path
+            && !PathBuf::from(path).is_file()
+        {
+            problems.push(format!("CONTENT_FILTER_WORDLIST: file not found: {}", path));
+        }
+
+        // i valori mancanti sono già stati segnalati: qui si controlla solo il formato
+        if let Some(url) = &self.redis_url {
+            problems.extend(RedisCache::from_url(url).err());
+        }
+        if let AuthBackendConfig::Ldap(ldap) = &self.auth_backend
+            && !ldap.url.is_empty()
+            && !ldap.bind_dn_template.is_empty()
+        {
+            problems.extend(LdapAuthProvider::new(ldap).err());
+        }
+        match &self.event_bus {
+            EventBusConfig::Nats { url, subject } if !url.is_empty() => {
+                problems.extend(NatsEventPublisher::new(url, subject).err());
+            }
+            EventBusConfig::Kafka { rest_url, topic } if !rest_url.is_empty() => {
+                problems.extend(KafkaRestEventPublisher::new(rest_url, topic).err());
+            }
+            _ => {}
+        }
+        problems.extend(build_cors_layer(&self.cors).err());
+        problems.extend(PasswordHasher::new(&self.password_hash).err());
+
+        problems
     }
 
     /// Stampa la configurazione (nascondendo i segreti)
     pub fn print_info(&self) {
         println!("   Server Configuration:");
-        println!("   Environment: {}", self.app_env);
-        println!("   Log Level: {}", self.log_level);
+        if let Some(path) = &self.config_file {
+            println!("   Config File: {}", path.display());
+        }
+        if let Some(vault) = &self.vault {
+            println!(
+                "   Vault Secrets: {}/{} at {}",
+                vault.kv_mount, vault.secret_path, vault.addr
+            );
+        }
+        println!("   Environment: {}", self.profile.as_str());
+        println!(
+            "   Log Level: {} ({:?} format)",
+            self.log_level, self.log_format
+        );
+        println!(
+            "   Token Cookie: {}",
+            if self.cookie_secure {
+                "Secure"
+            } else {
+                "without Secure (HTTP allowed)"
+            }
+        );
         println!(
             "   Server Address: {}:{}",
             self.server_host, self.server_port
         );
+        match &self.tls {
+            TlsConfig::Disabled => println!("   TLS: disabled"),
+            TlsConfig::Files { cert_path, .. } => {
+                println!("   TLS: certificate from {}", cert_path.display())
+            }
+            TlsConfig::Acme(acme) => println!(
+                "   TLS: ACME for {} ({})",
+                acme.domains.join(", "),
+                acme.directory_url
+            ),
+        }
+        println!(
+            "   CORS: origins {}, headers {}, credentials {}",
+            if !self.cors.allowed_origins.is_empty() {
+                self.cors.allowed_origins.join(", ")
+            } else if self.cors.strict {
+                "none (same origin only)".to_string()
+            } else {
+                "any".to_string()
+            },
+            if self.cors.allowed_headers.is_empty() {
+                "any".to_string()
+            } else {
+                self.cors.allowed_headers.join(", ")
+            },
+            self.cors.allow_credentials
+        );
         println!("   Database: {}", Self::mask_url(&self.database_url));
+        println!(
+            "   Database Read Replica: {}",
+            self.database_read_url
+                .as_deref()
+                .map(Self::mask_url)
+                .unwrap_or_else(|| "disabled".to_string())
+        );
         println!("   Max DB Connections: {}", self.max_connections);
+        println!("   Min DB Connections: {}", self.min_connections);
         println!("   Connection Lifetime: {}s", self.connection_lifetime_secs);
+        println!("   Acquire Timeout: {}s", self.acquire_timeout_secs);
+        if self.idle_timeout_secs == 0 {
+            println!("   Idle Timeout: disabled");
+        } else {
+            println!("   Idle Timeout: {}s", self.idle_timeout_secs);
+        }
+        println!("   Test Before Acquire: {}", self.test_before_acquire);
+        if self.slow_query_threshold_ms == 0 {
+            println!("   Slow Query Log: disabled");
+        } else {
+            println!("   Slow Query Log: over {}ms", self.slow_query_threshold_ms);
+        }
+        if self.cache.ttl_secs == 0 {
+            println!("   Repository Cache: disabled");
+        } else {
+            println!(
+                "   Repository Cache: {}s TTL, {} entries",
+                self.cache.ttl_secs, self.cache.max_entries
+            );
+        }
+        match &self.storage {
+            StorageConfig::Local { root } => {
+                println!("   Attachment Storage: local ({})", root.display())
+            }
+            StorageConfig::S3 {
+                bucket, endpoint, ..
+            } => println!(
+                "   Attachment Storage: s3 (bucket {}, endpoint {})",
+                bucket,
+                endpoint.as_deref().unwrap_or("AWS")
+            ),
+        }
+        println!("   Max Attachment Size: {} bytes", self.max_attachment_bytes);
+        println!("   Max JSON Body Size: {} bytes", self.max_json_body_bytes);
         println!(
-            "   JWT Secret: {}",
-            if self.jwt_secret == "un segreto meno bello" {
-                "   USING DEFAULT (INSECURE!)"
+            "   Legacy API Paths: {}",
+            if self.legacy_api_paths {
+                "enabled (deprecated)"
             } else {
-                "✓ Custom secret configured"
+                "disabled"
+            }
+        );
+        println!("   Max Group Members: {}", self.max_group_members);
+        println!(
+            "   Translation: {}",
+            self.translation_api_url.as_deref().unwrap_or("disabled")
+        );
+        println!(
+            "   Shared Cache (revocation, presence, repositories): {}",
+            self.redis_url
+                .as_deref()
+                .map(|url| format!("redis ({})", Self::mask_url(url)))
+                .unwrap_or_else(|| "in-memory".to_string())
+        );
+        println!(
+            "   SSO (OIDC): {}",
+            self.oidc
+                .as_ref()
+                .map(|oidc| oidc.issuer_url.as_str())
+                .unwrap_or("disabled")
+        );
+        match &self.auth_backend {
+            AuthBackendConfig::Local => println!("   Auth Backend: local"),
+            AuthBackendConfig::Ldap(ldap) => println!("   Auth Backend: ldap ({})", ldap.url),
+        }
+        println!(
+            "   Rate Limits: {}/min per IP on /auth, {}/min per user",
+            self.rate_limit.auth_per_minute, self.rate_limit.user_per_minute
+        );
+        println!(
+            "   Login Lockout: after {} failures, {}s up to {}s",
+            self.login_lockout.max_failures,
+            self.login_lockout.lockout_secs,
+            self.login_lockout.max_lockout_secs
+        );
+        println!(
+            "   Flood Detection: {}s window, repeats {}, links {}, joins/leaves {}, mute {}s up to {}s",
+            self.flood.window_secs,
+            self.flood.repeat_threshold,
+            self.flood.link_threshold,
+            self.flood.join_leave_threshold,
+            self.flood.mute_secs,
+            self.flood.max_mute_secs
+        );
+        println!(
+            "   Password Policy: min {} chars, mixed case {}, digit {}, reject common {}",
+            self.password_policy.min_length,
+            self.password_policy.require_mixed_case,
+            self.password_policy.require_digit,
+            self.password_policy.reject_common
+        );
+        match &self.password_hash {
+            PasswordHashConfig::Bcrypt { cost } => {
+                println!("   Password Hashing: bcrypt (cost {})", cost)
+            }
+            PasswordHashConfig::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => println!(
+                "   Password Hashing: argon2id (m={} KiB, t={}, p={})",
+                memory_kib, iterations, parallelism
+            ),
+        }
+        println!(
+            "   WebSocket: batch {} msgs / {}ms, idle timeout {}s, {} msg/s (burst {})",
+            self.ws.batch_max_size,
+            self.ws.batch_interval_ms,
+            self.ws.idle_timeout_secs,
+            self.ws.messages_per_second,
+            self.ws.message_burst
+        );
+        println!(
+            "   WebSocket Signal Queue: {} signals, on overflow {:?}",
+            self.ws.signal_queue_capacity, self.ws.overflow_policy
+        );
+        match self.ws.received_flush_interval_ms {
+            0 => println!("   WebSocket Received Markers: disabled"),
+            ms => println!("   WebSocket Received Markers: updated every {}ms", ms),
+        }
+        match &self.event_bus {
+            EventBusConfig::Disabled => println!("   Event Bus: disabled"),
+            EventBusConfig::Nats { url, subject } => println!(
+                "   Event Bus: nats ({}, subject {})",
+                Self::mask_url(url),
+                subject
+            ),
+            EventBusConfig::Kafka { rest_url, topic } => {
+                println!("   Event Bus: kafka ({}, topic {})", rest_url, topic)
             }
+        }
+        println!(
+            "   Content Filter: wordlist {}, classifier {}",
+            self.content_filter
+                .wordlist_path
+                .as_deref()
+                .unwrap_or("none"),
+            self.content_filter
+                .classifier_url
+                .as_deref()
+                .unwrap_or("none")
+        );
+        println!("   Server Admins: {}", self.server_admins.len());
+        match &self.jwt_key_pair {
+            Some(pair) => println!(
+                "   JWT Signing: {:?} with {}",
+                pair.algorithm,
+                pair.public_key_path.display()
+            ),
+            None => println!(
+                "   JWT Secret: {}",
+                if self.jwt_secret == "un segreto meno bello" {
+                    "   USING DEFAULT (INSECURE!)"
+                } else {
+                    "✓ Custom secret configured"
+                }
+            ),
+        }
+        if !self.jwt_previous_secrets.is_empty() {
+            println!(
+                "   Previous JWT Keys: {} (accepted until their tokens expire)",
+                self.jwt_previous_secrets.len()
+            );
+        }
+        println!(
+            "   Access Token: {} minutes, issuer {}, audience {}",
+            self.access_token.ttl_minutes,
+            self.access_token.issuer.as_deref().unwrap_or("none"),
+            self.access_token.audience.as_deref().unwrap_or("none")
         );
     }
 
     /// Maschera l'URL del database per il logging
     fn mask_url(url: &str) -> String {
+        // il percorso di un file SQLite non contiene credenziali
+        if is_sqlite_url(url) {
+            return url.to_string();
+        }
         if let Some(at_pos) = url.find('@') {
             if let Some(scheme_end) = url.find("://") {
                 let scheme = &url[..scheme_end + 3];
@@ -96,3 +1970,49 @@ impl Config {
         "***".to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_url_requires_scheme_and_host() {
+        assert_eq!(
+            check_url("DATABASE_URL", "mysql://user:pw@db:3306/app", &["mysql"]),
+            None
+        );
+        assert_eq!(check_url("DATABASE_URL", "", &["mysql"]), None);
+
+        let problem = check_url("REDIS_URL", "127.0.0.1:6379", &["http", "https"]).unwrap();
+        assert_eq!(
+            problem,
+            "Invalid REDIS_URL: must be a URL starting with http:// or https://"
+        );
+        assert!(check_url("OIDC_ISSUER_URL", "https://", &["https"]).is_some());
+    }
+
+    #[test]
+    fn test_check_database_url_accepts_sqlite_only_with_the_feature() {
+        assert_eq!(
+            check_database_url("DATABASE_URL", "mariadb://user:pw@db/app"),
+            None
+        );
+        assert!(check_database_url("DATABASE_URL", "postgres://db/app").is_some());
+
+        let problem = check_database_url("DATABASE_URL", "sqlite://ironlink.db");
+        assert_eq!(problem.is_none(), cfg!(feature = "sqlite"));
+    }
+
+    #[test]
+    fn test_config_error_lists_every_problem() {
+        let mut problems = Vec::new();
+        let port: u16 = Err("Invalid SERVER_PORT".to_string()).report(&mut problems);
+        let workers: u32 = Ok(4).report(&mut problems);
+        problems.push("Invalid STORAGE_BACKEND".to_string());
+
+        assert_eq!((port, workers), (0, 4));
+        let report = ConfigError { problems }.to_string();
+        assert!(report.starts_with("Invalid configuration (2 problems):"));
+        assert!(report.contains("  - Invalid SERVER_PORT\n  - Invalid STORAGE_BACKEND\n"));
+    }
+}
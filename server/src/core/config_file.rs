@@ -0,0 +1,443 @@
+//! File di configurazione - Impostazioni in TOML o YAML sotto le variabili d'ambiente
+//!
+//! Il file (`--config <path>` da riga di comando oppure `CONFIG_FILE`) è diviso nelle
+//! sezioni `server`, `database`, `ws`, `auth` e `storage`. Ogni chiave corrisponde a una
+//! variabile d'ambiente: `Config::from_env` usa il valore del file solo se la variabile
+//! non è impostata, così un deployment può tenere nel file le impostazioni comuni e
+//! sovrascriverne alcune per singola istanza. Le impostazioni senza una sezione restano
+//! configurabili solo con le variabili d'ambiente.
+//!
+//! Il formato dipende dall'estensione: `.toml` (letto con `toml_edit`) oppure `.yaml`/`.yml`
+//! (letto con `serde_yaml`).
+
+use serde::Deserialize;
+use serde_json::{Map, Number, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Variabile d'ambiente con il percorso del file, se `--config` non è passato
+pub const CONFIG_FILE_ENV: &str = "CONFIG_FILE";
+
+/// Indirizzo, ambiente e limiti delle richieste HTTP
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerSection {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub app_env: Option<String>,
+    pub log_level: Option<String>,
+    /// `pretty`, `full` oppure `compact`
+    pub log_format: Option<String>,
+    pub max_json_body_bytes: Option<usize>,
+    pub legacy_api_paths: Option<bool>,
+    /// Id degli amministratori del server
+    pub admins: Option<Vec<i32>>,
+}
+
+/// Database primario, replica di lettura e pool di connessioni
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseSection {
+    pub url: Option<String>,
+    pub read_url: Option<String>,
+    pub max_connections: Option<u32>,
+    pub min_connections: Option<u32>,
+    pub connection_lifetime_secs: Option<u64>,
+    pub acquire_timeout_secs: Option<u64>,
+    pub idle_timeout_secs: Option<u64>,
+    pub test_before_acquire: Option<bool>,
+    pub slow_query_threshold_ms: Option<u64>,
+}
+
+/// Batching e limiti delle connessioni WebSocket
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WsSection {
+    pub batch_interval_ms: Option<u64>,
+    pub batch_max_size: Option<usize>,
+    pub idle_timeout_secs: Option<u64>,
+    pub messages_per_second: Option<u32>,
+    pub message_burst: Option<u32>,
+    pub signal_queue_capacity: Option<usize>,
+    /// `drop_oldest` oppure `disconnect`
+    pub overflow_policy: Option<String>,
+    pub received_flush_interval_ms: Option<u64>,
+}
+
+/// Firma dei JWT e backend di login (locale, LDAP, OIDC)
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthSection {
+    pub jwt_secret: Option<String>,
+    /// Chiavi di firma ritirate durante la rotazione
+    pub jwt_previous_secrets: Option<Vec<String>>,
+    /// `HS256`, `RS256` oppure `EdDSA`
+    pub jwt_algorithm: Option<String>,
+    pub jwt_private_key_path: Option<String>,
+    pub jwt_public_key_path: Option<String>,
+    pub jwt_previous_public_key_paths: Option<Vec<String>>,
+    pub access_token_ttl_minutes: Option<i64>,
+    pub jwt_issuer: Option<String>,
+    pub jwt_audience: Option<String>,
+    /// `local` oppure `ldap`
+    pub backend: Option<String>,
+    pub ldap_url: Option<String>,
+    pub ldap_bind_dn_template: Option<String>,
+    pub oidc_issuer_url: Option<String>,
+    pub oidc_client_id: Option<String>,
+    pub oidc_client_secret: Option<String>,
+    pub oidc_redirect_url: Option<String>,
+    pub oidc_allow_hmac: Option<bool>,
+}
+
+/// Backend degli allegati e loro dimensione massima
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageSection {
+    /// `local` oppure `s3`
+    pub backend: Option<String>,
+    pub upload_dir: Option<String>,
+    pub max_attachment_bytes: Option<usize>,
+    pub s3_bucket: Option<String>,
+    pub s3_region: Option<String>,
+    pub s3_endpoint: Option<String>,
+    pub s3_access_key_id: Option<String>,
+    pub s3_secret_access_key: Option<String>,
+}
+
+/// Contenuto del file di configurazione; le sezioni assenti restano vuote
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    pub server: ServerSection,
+    pub database: DatabaseSection,
+    pub ws: WsSection,
+    pub auth: AuthSection,
+    pub storage: StorageSection,
+}
+
+impl ConfigFile {
+    /// Read and parse the file, choosing TOML or YAML from its extension
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read config file {}: {}", path.display(), e))?;
+        let value = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => toml_to_json(&text),
+            Some("yaml" | "yml") => yaml_to_json(&text),
+            _ => {
+                return Err(format!(
+                    "Invalid config file {}: extension must be .toml, .yaml or .yml",
+                    path.display()
+                ));
+            }
+        }
+        .map_err(|e| format!("Invalid config file {}: {}", path.display(), e))?;
+
+        serde_json::from_value(value)
+            .map_err(|e| format!("Invalid config file {}: {}", path.display(), e))
+    }
+
+    /// Values keyed by the environment variable each one stands in for
+    pub fn into_vars(self) -> HashMap<&'static str, String> {
+        let mut vars = HashMap::new();
+        let mut set = |name: &'static str, value: Option<String>| {
+            if let Some(value) = value {
+                vars.insert(name, value);
+            }
+        };
+        fn text<T: ToString>(value: Option<T>) -> Option<String> {
+            value.map(|value| value.to_string())
+        }
+
+        let ConfigFile {
+            server,
+            database,
+            ws,
+            auth,
+            storage,
+        } = self;
+
+        set("SERVER_HOST", server.host);
+        set("SERVER_PORT", text(server.port));
+        set("APP_ENV", server.app_env);
+        set("LOG_LEVEL", server.log_level);
+        set("LOG_FORMAT", server.log_format);
+        set("MAX_JSON_BODY_BYTES", text(server.max_json_body_bytes));
+        set("LEGACY_API_PATHS", text(server.legacy_api_paths));
+        set(
+            "SERVER_ADMINS",
+            server.admins.map(|admins| {
+                admins
+                    .iter()
+                    .map(i32::to_string)
+                    .collect::<Vec<_>>()
+                    .join(",")
+            }),
+        );
+
+        set("DATABASE_URL", database.url);
+        set("DATABASE_READ_URL", database.read_url);
+        set("MAX_DB_CONNECTIONS", text(database.max_connections));
+        set("MIN_DB_CONNECTIONS", text(database.min_connections));
+        set(
+            "DB_CONNECTION_LIFETIME_SECS",
+            text(database.connection_lifetime_secs),
+        );
+        set(
+            "DB_ACQUIRE_TIMEOUT_SECS",
+            text(database.acquire_timeout_secs),
+        );
+        set("DB_IDLE_TIMEOUT_SECS", text(database.idle_timeout_secs));
+        set("DB_TEST_BEFORE_ACQUIRE", text(database.test_before_acquire));
+        set(
+            "SLOW_QUERY_THRESHOLD_MS",
+            text(database.slow_query_threshold_ms),
+        );
+
+        set("WS_BATCH_INTERVAL_MS", text(ws.batch_interval_ms));
+        set("WS_BATCH_MAX_SIZE", text(ws.batch_max_size));
+        set("WS_IDLE_TIMEOUT_SECS", text(ws.idle_timeout_secs));
+        set("WS_MESSAGES_PER_SECOND", text(ws.messages_per_second));
+        set("WS_MESSAGE_BURST", text(ws.message_burst));
+        set("WS_SIGNAL_QUEUE_CAPACITY", text(ws.signal_queue_capacity));
+        set("WS_OVERFLOW_POLICY", ws.overflow_policy);
+        set(
+            "WS_RECEIVED_FLUSH_INTERVAL_MS",
+            text(ws.received_flush_interval_ms),
+        );
+
+        set("JWT_SECRET", auth.jwt_secret);
+        set(
+            "JWT_PREVIOUS_SECRETS",
+            auth.jwt_previous_secrets.map(|secrets| secrets.join(",")),
+        );
+        set("JWT_ALGORITHM", auth.jwt_algorithm);
+        set("JWT_PRIVATE_KEY_PATH", auth.jwt_private_key_path);
+        set("JWT_PUBLIC_KEY_PATH", auth.jwt_public_key_path);
+        set(
+            "JWT_PREVIOUS_PUBLIC_KEY_PATHS",
+            auth.jwt_previous_public_key_paths
+                .map(|paths| paths.join(",")),
+        );
+        set(
+            "ACCESS_TOKEN_TTL_MINUTES",
+            text(auth.access_token_ttl_minutes),
+        );
+        set("JWT_ISSUER", auth.jwt_issuer);
+        set("JWT_AUDIENCE", auth.jwt_audience);
+        set("AUTH_BACKEND", auth.backend);
+        set("LDAP_URL", auth.ldap_url);
+        set("LDAP_BIND_DN_TEMPLATE", auth.ldap_bind_dn_template);
+        set("OIDC_ISSUER_URL", auth.oidc_issuer_url);
+        set("OIDC_CLIENT_ID", auth.oidc_client_id);
+        set("OIDC_CLIENT_SECRET", auth.oidc_client_secret);
+        set("OIDC_REDIRECT_URL", auth.oidc_redirect_url);
+        set("OIDC_ALLOW_HMAC", text(auth.oidc_allow_hmac));
+
+        set("STORAGE_BACKEND", storage.backend);
+        set("UPLOAD_DIR", storage.upload_dir);
+        set("MAX_ATTACHMENT_BYTES", text(storage.max_attachment_bytes));
+        set("S3_BUCKET", storage.s3_bucket);
+        set("S3_REGION", storage.s3_region);
+        set("S3_ENDPOINT", storage.s3_endpoint);
+        set("S3_ACCESS_KEY_ID", storage.s3_access_key_id);
+        set("S3_SECRET_ACCESS_KEY", storage.s3_secret_access_key);
+
+        vars
+    }
+}
+
+/// Percorso del file: `--config <path>` (o `--config=<path>`) prevale su `CONFIG_FILE`
+pub fn config_file_path(
+    mut args: impl Iterator<Item = String>,
+    env_path: Option<String>,
+) -> Option<PathBuf> {
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(PathBuf::from(path));
+        }
+    }
+    env_path.filter(|path| !path.is_empty()).map(PathBuf::from)
+}
+
+// ============================================================
+// TOML
+// ============================================================
+
+fn toml_to_json(text: &str) -> Result<Value, String> {
+    let document = toml_edit::Document::parse(text).map_err(|e| e.to_string())?;
+    Ok(toml_table_to_json(document.as_table()))
+}
+
+fn toml_table_to_json(table: &toml_edit::Table) -> Value {
+    Value::Object(
+        table
+            .iter()
+            .filter_map(|(key, item)| toml_item_to_json(item).map(|value| (key.to_string(), value)))
+            .collect(),
+    )
+}
+
+fn toml_item_to_json(item: &toml_edit::Item) -> Option<Value> {
+    match item {
+        toml_edit::Item::None => None,
+        toml_edit::Item::Value(value) => Some(toml_value_to_json(value)),
+        toml_edit::Item::Table(table) => Some(toml_table_to_json(table)),
+        toml_edit::Item::ArrayOfTables(tables) => Some(Value::Array(
+            tables.iter().map(toml_table_to_json).collect(),
+        )),
+    }
+}
+
+fn toml_value_to_json(value: &toml_edit::Value) -> Value {
+    match value {
+        toml_edit::Value::String(text) => Value::String(text.value().clone()),
+        toml_edit::Value::Integer(number) => Value::from(*number.value()),
+        toml_edit::Value::Float(number) => Number::from_f64(*number.value())
+            .map(Value::Number)
+            .unwrap_or(Value::Null),
+        toml_edit::Value::Boolean(flag) => Value::Bool(*flag.value()),
+        toml_edit::Value::Datetime(datetime) => Value::String(datetime.value().to_string()),
+        toml_edit::Value::Array(array) => {
+            Value::Array(array.iter().map(toml_value_to_json).collect())
+        }
+        toml_edit::Value::InlineTable(table) => Value::Object(
+            table
+                .iter()
+                .map(|(key, value)| (key.to_string(), toml_value_to_json(value)))
+                .collect(),
+        ),
+    }
+}
+
+// ============================================================
+// YAML
+// ============================================================
+
+fn yaml_to_json(text: &str) -> Result<Value, String> {
+    match serde_yaml::from_str(text).map_err(|e| e.to_string())? {
+        // un file vuoto (o di soli commenti) non imposta nulla
+        Value::Null => Ok(Value::Object(Map::new())),
+        value => Ok(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_config(name: &str, contents: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("config-file-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_load_toml_sections() {
+        let path = write_config(
+            "server.toml",
+            r#"
+            [server]
+            host = "0.0.0.0"
+            port = 8080
+            admins = [1, 2]
+
+            [database]
+            url = "mysql://user:pass@db/app"
+            test_before_acquire = false
+
+            [ws]
+            overflow_policy = "disconnect"
+            "#,
+        );
+
+        let vars = ConfigFile::load(&path).unwrap().into_vars();
+        assert_eq!(vars["SERVER_HOST"], "0.0.0.0");
+        assert_eq!(vars["SERVER_PORT"], "8080");
+        assert_eq!(vars["SERVER_ADMINS"], "1,2");
+        assert_eq!(vars["DATABASE_URL"], "mysql://user:pass@db/app");
+        assert_eq!(vars["DB_TEST_BEFORE_ACQUIRE"], "false");
+        assert_eq!(vars["WS_OVERFLOW_POLICY"], "disconnect");
+        // le chiavi assenti non diventano variabili
+        assert!(!vars.contains_key("JWT_SECRET"));
+    }
+
+    #[test]
+    fn test_load_yaml_sections() {
+        let path = write_config(
+            "server.yaml",
+            r#"
+# impostazioni comuni a tutte le istanze
+server:
+  port: 8080
+  admins:
+    - 1
+    - 2
+auth:
+  jwt_secret: 'it''s a secret # not a comment'
+  backend: ldap
+storage:
+  backend: s3
+  s3_bucket: attachments   # bucket condiviso
+  max_attachment_bytes: 1048576
+"#,
+        );
+
+        let vars = ConfigFile::load(&path).unwrap().into_vars();
+        assert_eq!(vars["SERVER_PORT"], "8080");
+        assert_eq!(vars["SERVER_ADMINS"], "1,2");
+        assert_eq!(vars["JWT_SECRET"], "it's a secret # not a comment");
+        assert_eq!(vars["AUTH_BACKEND"], "ldap");
+        assert_eq!(vars["STORAGE_BACKEND"], "s3");
+        assert_eq!(vars["S3_BUCKET"], "attachments");
+        assert_eq!(vars["MAX_ATTACHMENT_BYTES"], "1048576");
+
+        // un file di soli commenti non imposta nulla
+        let empty = write_config("empty.yaml", "# nessuna impostazione\n");
+        assert!(ConfigFile::load(&empty).unwrap().into_vars().is_empty());
+    }
+
+    #[test]
+    fn test_load_rejects_unknown_keys_and_wrong_types() {
+        let unknown = write_config("unknown.yml", "database:\n  max_conections: 10\n");
+        let error = ConfigFile::load(&unknown).unwrap_err();
+        assert!(error.contains("max_conections"), "{}", error);
+
+        let wrong_type = write_config("wrong.toml", "[server]\nport = \"http\"\n");
+        assert!(ConfigFile::load(&wrong_type).is_err());
+
+        let extension = write_config("server.json", "{}");
+        assert!(ConfigFile::load(&extension).is_err());
+    }
+
+    #[test]
+    fn test_config_file_path_prefers_flag() {
+        let args = |args: &[&str]| {
+            args.iter()
+                .map(|arg| arg.to_string())
+                .collect::<Vec<_>>()
+                .into_iter()
+        };
+        let env_path = Some("env.toml".to_string());
+
+        assert_eq!(
+            config_file_path(args(&["server", "--config", "flag.toml"]), env_path.clone()),
+            Some(PathBuf::from("flag.toml"))
+        );
+        assert_eq!(
+            config_file_path(args(&["server", "--config=flag.yaml"]), env_path.clone()),
+            Some(PathBuf::from("flag.yaml"))
+        );
+        assert_eq!(
+            config_file_path(args(&["server"]), env_path),
+            Some(PathBuf::from("env.toml"))
+        );
+        assert_eq!(config_file_path(args(&["server"]), None), None);
+    }
+}
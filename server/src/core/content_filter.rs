@@ -0,0 +1,255 @@
+//! Content filter - Filtro dei contenuti dei messaggi
+//!
+//! Il controllo ha due fasi: una lista di parole vietate, confrontate come parole intere e
+//! senza distinzione tra maiuscole e minuscole, e un classificatore esterno opzionale dietro
+//! il trait `ContentClassifier`, interrogato solo se la lista non trova nulla. Cosa fare di
+//! un messaggio che non supera il filtro lo decide la policy della chat
+//! (`ContentFilterPolicy`), applicata da `deliver_message`.
+
+use crate::core::config::ContentFilterConfig;
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tracing::{error, warn};
+
+/// Classificatore esterno dei contenuti (modello di moderazione, servizio di terze parti...)
+pub trait ContentClassifier: Send + Sync {
+    /// Classifica il testo di un messaggio
+    ///
+    /// # Returns
+    /// * `Ok(true)` - Il testo va filtrato
+    /// * `Ok(false)` - Il testo è accettabile
+    /// * `Err(String)` - Descrizione dell'errore del classificatore (solo per i log)
+    fn classify<'a>(&'a self, text: &'a str) -> BoxFuture<'a, Result<bool, String>>;
+}
+
+/// Classificatore raggiungibile via HTTP: `POST {url}` con `{"text": ...}`,
+/// risponde `{"flagged": true|false}`
+pub struct HttpContentClassifier {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl HttpContentClassifier {
+    pub fn new(url: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ClassifyRequest<'a> {
+    text: &'a str,
+}
+
+#[derive(Deserialize)]
+struct ClassifyResponse {
+    flagged: bool,
+}
+
+impl ContentClassifier for HttpContentClassifier {
+    fn classify<'a>(&'a self, text: &'a str) -> BoxFuture<'a, Result<bool, String>> {
+        Box::pin(async move {
+            let response = self
+                .client
+                .post(&self.url)
+                .json(&ClassifyRequest { text })
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+                .map_err(|e| e.to_string())?;
+
+            let body: ClassifyResponse = response.json().await.map_err(|e| e.to_string())?;
+            Ok(body.flagged)
+        })
+    }
+}
+
+/// Esito del controllo di un messaggio
+#[derive(Debug, PartialEq)]
+pub enum FilterVerdict {
+    /// Nessuna parola vietata e nessuna segnalazione del classificatore
+    Clean,
+    /// Il messaggio non supera il filtro. `redacted` è il testo con le parole vietate
+    /// mascherate, None se lo ha segnalato il classificatore (che non indica quali parti)
+    Matched { redacted: Option<String> },
+}
+
+/// Lista di parole vietate e classificatore, condivisi da tutte le chat
+#[derive(Default)]
+pub struct ContentFilter {
+    /// Parole vietate, in minuscolo
+    words: HashSet<String>,
+    classifier: Option<Arc<dyn ContentClassifier>>,
+}
+
+impl ContentFilter {
+    pub fn new(words: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            words: words
+                .into_iter()
+                .map(|word| word.trim().to_lowercase())
+                .filter(|word| !word.is_empty())
+                .collect(),
+            classifier: None,
+        }
+    }
+
+    /// Aggiunge il classificatore esterno, consultato dopo la lista di parole
+    pub fn with_classifier(mut self, classifier: Arc<dyn ContentClassifier>) -> Self {
+        self.classifier = Some(classifier);
+        self
+    }
+
+    /// false se non ci sono né parole vietate né classificatore: ogni messaggio passa
+    pub fn is_enabled(&self) -> bool {
+        !self.words.is_empty() || self.classifier.is_some()
+    }
+
+    /// Controlla il testo di un messaggio. Se il classificatore non risponde il messaggio
+    /// è considerato accettabile: un servizio esterno guasto non deve bloccare le chat
+    pub async fn check(&self, text: &str) -> FilterVerdict {
+        if let Some(redacted) = self.redact(text) {
+            return FilterVerdict::Matched {
+                redacted: Some(redacted),
+            };
+        }
+
+        let Some(classifier) = &self.classifier else {
+            return FilterVerdict::Clean;
+        };
+        match classifier.classify(text).await {
+            Ok(true) => FilterVerdict::Matched { redacted: None },
+            Ok(false) => FilterVerdict::Clean,
+            Err(e) => {
+                error!("Content classifier failed, message accepted: {}", e);
+                FilterVerdict::Clean
+            }
+        }
+    }
+
+    /// Testo con ogni parola vietata sostituita da asterischi, None se non ne contiene
+    fn redact(&self, text: &str) -> Option<String> {
+        if self.words.is_empty() {
+            return None;
+        }
+
+        let mut redacted = String::with_capacity(text.len());
+        let mut matched = false;
+        let mut word_start = None;
+        // il carattere nullo finale chiude l'ultima parola
+        for (i, c) in text
+            .char_indices()
+            .chain(std::iter::once((text.len(), '\0')))
+        {
+            if c.is_alphanumeric() {
+                word_start.get_or_insert(i);
+                continue;
+            }
+            if let Some(start) = word_start.take() {
+                let word = &text[start..i];
+                if self.words.contains(&word.to_lowercase()) {
+                    matched = true;
+                    redacted.extend(std::iter::repeat_n('*', word.chars().count()));
+                } else {
+                    redacted.push_str(word);
+                }
+            }
+            if i < text.len() {
+                redacted.push(c);
+            }
+        }
+
+        matched.then_some(redacted)
+    }
+}
+
+/// Costruisce il filtro dalla configurazione, leggendo la lista di parole dal file indicato
+/// (una parola per riga, le righe vuote e quelle che iniziano con `#` sono ignorate)
+pub fn build_content_filter(config: &ContentFilterConfig) -> Result<ContentFilter, String> {
+    let words = match &config.wordlist_path {
+        Some(path) => std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read content filter wordlist {}: {}", path, e))?
+            .lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .map(str::to_string)
+            .collect(),
+        None => Vec::new(),
+    };
+    if words.is_empty() && config.wordlist_path.is_some() {
+        warn!("Content filter wordlist is empty");
+    }
+
+    let mut filter = ContentFilter::new(words);
+    if let Some(url) = &config.classifier_url {
+        filter = filter.with_classifier(Arc::new(HttpContentClassifier::new(url.clone())));
+    }
+    Ok(filter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FixedClassifier(Result<bool, String>);
+
+    impl ContentClassifier for FixedClassifier {
+        fn classify<'a>(&'a self, _text: &'a str) -> BoxFuture<'a, Result<bool, String>> {
+            let result = self.0.clone();
+            Box::pin(async move { result })
+        }
+    }
+
+    fn wordlist_filter() -> ContentFilter {
+        ContentFilter::new(["darn".to_string(), " Heck ".to_string()])
+    }
+
+    #[test]
+    fn test_redact_whole_words_ignoring_case() {
+        let filter = wordlist_filter();
+        assert_eq!(
+            filter.redact("Darn it, what the HECK!"),
+            Some("**** it, what the ****!".to_string())
+        );
+        // le parole che contengono una parola vietata non vengono toccate
+        assert_eq!(filter.redact("darned heckler"), None);
+    }
+
+    #[test]
+    fn test_redact_counts_characters_not_bytes() {
+        let filter = ContentFilter::new(["perché".to_string()]);
+        assert_eq!(filter.redact("ma perché?"), Some("ma ******?".to_string()));
+    }
+
+    #[test]
+    fn test_empty_filter_is_disabled() {
+        assert!(!ContentFilter::default().is_enabled());
+        assert!(!ContentFilter::new(["  ".to_string()]).is_enabled());
+        assert!(wordlist_filter().is_enabled());
+    }
+
+    #[tokio::test]
+    async fn test_check_consults_classifier_only_without_wordlist_match() {
+        let filter = wordlist_filter().with_classifier(Arc::new(FixedClassifier(Ok(true))));
+        assert_eq!(
+            filter.check("darn").await,
+            FilterVerdict::Matched {
+                redacted: Some("****".to_string())
+            }
+        );
+        assert_eq!(
+            filter.check("hello").await,
+            FilterVerdict::Matched { redacted: None }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_accepts_message_when_classifier_fails() {
+        let filter = ContentFilter::default()
+            .with_classifier(Arc::new(FixedClassifier(Err("timeout".to_string()))));
+        assert_eq!(filter.check("hello").await, FilterVerdict::Clean);
+    }
+}
@@ -0,0 +1,156 @@
+//! CORS - Richieste cross-origin dai client nel browser
+//!
+//! Il layer risponde alle richieste preflight e aggiunge gli header CORS secondo
+//! `CorsConfig`. Con le credenziali abilitate lo standard vieta i caratteri jolly,
+//! quindi origini, metodi e header esposti sono sempre elencati esplicitamente.
+
+use crate::core::config::CorsConfig;
+use crate::core::versioning::DEPRECATION_HEADER;
+use axum::http::{HeaderName, HeaderValue, Method, header};
+use std::time::Duration;
+use tower_http::cors::{AllowHeaders, AllowOrigin, CorsLayer};
+
+/// Metodi usati dalle route dell'API
+const ALLOWED_METHODS: [Method; 6] = [
+    Method::GET,
+    Method::POST,
+    Method::PUT,
+    Method::PATCH,
+    Method::DELETE,
+    Method::OPTIONS,
+];
+
+/// Costruisce il layer CORS dalla configurazione
+pub fn build_cors_layer(config: &CorsConfig) -> Result<CorsLayer, String> {
+    let origins = if config.allowed_origins.is_empty() && config.strict {
+        AllowOrigin::list(Vec::<HeaderValue>::new())
+    } else if config.allowed_origins.is_empty() {
+        AllowOrigin::any()
+    } else {
+        let origins = config
+            .allowed_origins
+            .iter()
+            .map(|origin| {
+                HeaderValue::from_str(origin.trim_end_matches('/'))
+                    .map_err(|_| format!("Invalid CORS origin: {}", origin))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        AllowOrigin::list(origins)
+    };
+
+    let headers = if config.allowed_headers.is_empty() {
+        AllowHeaders::mirror_request()
+    } else {
+        let headers = config
+            .allowed_headers
+            .iter()
+            .map(|name| {
+                HeaderName::try_from(name.as_str())
+                    .map_err(|_| format!("Invalid CORS header: {}", name))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        AllowHeaders::list(headers)
+    };
+
+    Ok(CorsLayer::new()
+        .allow_origin(origins)
+        .allow_methods(ALLOWED_METHODS)
+        .allow_headers(headers)
+        .allow_credentials(config.allow_credentials)
+        .expose_headers([
+            header::AUTHORIZATION,
+            header::SET_COOKIE,
+            header::RETRY_AFTER,
+            header::LINK,
+            DEPRECATION_HEADER,
+        ])
+        .max_age(Duration::from_secs(config.max_age_secs)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, routing::get};
+    use axum_test::TestServer;
+
+    fn server(config: &CorsConfig) -> TestServer {
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(build_cors_layer(config).unwrap());
+        TestServer::new(app).unwrap()
+    }
+
+    async fn preflight(server: &TestServer, origin: &str) -> axum_test::TestResponse {
+        server
+            .method(Method::OPTIONS, "/")
+            .add_header(header::ORIGIN, origin)
+            .add_header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .add_header(header::ACCESS_CONTROL_REQUEST_HEADERS, "authorization")
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_any_origin_by_default() {
+        let server = server(&CorsConfig::default());
+
+        let response = preflight(&server, "https://app.example.org").await;
+        assert_eq!(response.header(header::ACCESS_CONTROL_ALLOW_ORIGIN), "*");
+        assert_eq!(
+            response.header(header::ACCESS_CONTROL_ALLOW_HEADERS),
+            "authorization"
+        );
+        assert_eq!(response.header(header::ACCESS_CONTROL_MAX_AGE), "600");
+    }
+
+    #[tokio::test]
+    async fn test_explicit_origins_with_credentials() {
+        let server = server(&CorsConfig {
+            allowed_origins: vec!["https://app.example.org/".to_string()],
+            allowed_headers: vec!["authorization".to_string(), "content-type".to_string()],
+            allow_credentials: true,
+            ..CorsConfig::default()
+        });
+
+        let response = preflight(&server, "https://app.example.org").await;
+        assert_eq!(
+            response.header(header::ACCESS_CONTROL_ALLOW_ORIGIN),
+            "https://app.example.org"
+        );
+        assert_eq!(
+            response.header(header::ACCESS_CONTROL_ALLOW_CREDENTIALS),
+            "true"
+        );
+
+        // un'origine non in lista non riceve gli header CORS
+        let response = preflight(&server, "https://evil.example.com").await;
+        assert!(
+            response
+                .maybe_header(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_strict_without_origins_denies_cross_origin() {
+        let server = server(&CorsConfig {
+            strict: true,
+            ..CorsConfig::default()
+        });
+
+        let response = preflight(&server, "https://app.example.org").await;
+        assert!(
+            response
+                .maybe_header(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_invalid_header_rejected() {
+        let config = CorsConfig {
+            allowed_headers: vec!["not a header".to_string()],
+            ..CorsConfig::default()
+        };
+        assert!(build_cors_layer(&config).is_err());
+    }
+}
@@ -1,17 +1,35 @@
-use axum::{Json, http::StatusCode, response::IntoResponse};
+use crate::repositories::is_version_conflict;
+use axum::{
+    Json,
+    http::{HeaderValue, StatusCode, header},
+    response::IntoResponse,
+};
 use serde::Serialize;
-
-#[derive(Serialize)]
-struct ErrorResponse {
+use std::collections::BTreeMap;
+use std::time::Duration;
+use utoipa::ToSchema;
+
+/// Corpo JSON di tutte le risposte di errore
+#[derive(Serialize, ToSchema)]
+pub struct ErrorResponse {
+    /// Messaggio fisso per il tipo di errore
     error: &'static str,
+    /// Spiegazione specifica della richiesta, se presente
     #[serde(skip_serializing_if = "Option::is_none")]
     details: Option<String>,
+    /// Errori di validazione per campo
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fields: Option<BTreeMap<String, Vec<String>>>,
 }
 
 pub struct AppError {
     status: StatusCode,
     message: &'static str,
     details: Option<String>,
+    /// Secondi da indicare nell'header `Retry-After` (solo per le risposte 429)
+    retry_after: Option<u64>,
+    /// Errori di validazione per campo: nome del campo -> messaggi
+    fields: Option<BTreeMap<String, Vec<String>>>,
 }
 
 impl AppError {
@@ -20,6 +38,8 @@ impl AppError {
             status,
             message,
             details: None,
+            retry_after: None,
+            fields: None,
         }
     }
 
@@ -56,6 +76,31 @@ impl AppError {
     pub fn service_unavailable(message: &'static str) -> Self {
         Self::new(StatusCode::SERVICE_UNAVAILABLE, message)
     }
+
+    /// Troppe richieste: il client può riprovare dopo `retry_after`
+    /// (arrotondato per eccesso al secondo nell'header `Retry-After`)
+    pub fn too_many_requests(message: &'static str, retry_after: Duration) -> Self {
+        let seconds = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+        Self {
+            retry_after: Some(seconds),
+            ..Self::new(StatusCode::TOO_MANY_REQUESTS, message)
+        }
+        .with_details(format!("Retry in {} seconds", seconds))
+    }
+
+    /// Corpo della richiesta oltre il limite di dimensione della route
+    pub fn payload_too_large(max_bytes: usize) -> Self {
+        Self::new(StatusCode::PAYLOAD_TOO_LARGE, "Request body too large").with_details(format!(
+            "The request body can be at most {} bytes",
+            max_bytes
+        ))
+    }
+
+    /// La chat di gruppo ha raggiunto il numero massimo di membri configurato
+    pub fn group_full(max_members: usize) -> Self {
+        Self::conflict("Group is full")
+            .with_details(format!("A group can have at most {} members", max_members))
+    }
 }
 
 impl From<sqlx::Error> for AppError {
@@ -63,6 +108,10 @@ impl From<sqlx::Error> for AppError {
         match err {
             sqlx::Error::RowNotFound => Self::not_found("Resource not found"),
 
+            ref err if is_version_conflict(err) => {
+                Self::conflict("Resource was modified concurrently, reload it and retry")
+            }
+
             sqlx::Error::Database(_) => Self::bad_request("Database error"),
 
             sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed => {
@@ -82,7 +131,26 @@ impl From<axum::Error> for AppError {
 
 impl From<validator::ValidationErrors> for AppError {
     fn from(err: validator::ValidationErrors) -> Self {
-        Self::bad_request("Validation error").with_details(err.to_string())
+        let fields = err
+            .field_errors()
+            .into_iter()
+            .map(|(field, errors)| {
+                let messages = errors
+                    .iter()
+                    .map(|e| {
+                        e.message
+                            .as_ref()
+                            .map(|message| message.to_string())
+                            .unwrap_or_else(|| e.code.to_string())
+                    })
+                    .collect();
+                (field.to_string(), messages)
+            })
+            .collect();
+        Self {
+            fields: Some(fields),
+            ..Self::bad_request("Validation error").with_details(err.to_string())
+        }
     }
 }
 
@@ -91,7 +159,14 @@ impl IntoResponse for AppError {
         let body = Json(ErrorResponse {
             error: self.message,
             details: self.details,
+            fields: self.fields,
         });
-        (self.status, body).into_response()
+        let mut response = (self.status, body).into_response();
+        if let Some(seconds) = self.retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(seconds));
+        }
+        response
     }
 }
@@ -0,0 +1,137 @@
+//! ETag - Richieste GET condizionali sulle liste
+//!
+//! I client aggiornano spesso liste che cambiano di rado (chat, membri, inviti): il
+//! middleware calcola l'ETag dal corpo della risposta e, se coincide con quello inviato
+//! in `If-None-Match`, risponde 304 senza corpo. La risposta viene comunque costruita,
+//! il risparmio è sulla banda verso il client.
+
+use crate::core::error::AppError;
+use axum::{
+    body::{Body, to_bytes},
+    extract::Request,
+    http::{HeaderMap, HeaderValue, Response, StatusCode, header},
+    middleware::Next,
+};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use sha2::{Digest, Sha256};
+
+/// Le risposte possono essere conservate solo dal client, che deve sempre rivalidarle
+const CACHE_CONTROL: HeaderValue = HeaderValue::from_static("private, no-cache");
+
+/// Middleware che aggiunge l'ETag alle risposte 200 e gestisce `If-None-Match`
+///
+/// Va applicato alle singole route GET delle liste.
+pub async fn etag_middleware(req: Request, next: Next) -> Result<Response<Body>, AppError> {
+    // 1. Eseguire la richiesta: solo le risposte 200 ricevono l'ETag
+    // 2. Calcolare l'ETag come hash del corpo
+    // 3. Se il client ha già questa versione rispondere 304 senza corpo
+
+    let if_none_match = req.headers().get(header::IF_NONE_MATCH).cloned();
+    let response = next.run(req).await;
+    if response.status() != StatusCode::OK {
+        return Ok(response);
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let body = to_bytes(body, usize::MAX).await?;
+    let etag = format!("\"{}\"", URL_SAFE_NO_PAD.encode(Sha256::digest(&body)));
+    let etag = HeaderValue::try_from(etag).expect("base64 is a valid header value");
+
+    parts.headers.insert(header::ETAG, etag.clone());
+    parts
+        .headers
+        .entry(header::CACHE_CONTROL)
+        .or_insert(CACHE_CONTROL);
+
+    if if_none_match.is_some_and(|value| etag_matches(&value, &etag)) {
+        let mut headers = HeaderMap::new();
+        for name in [header::ETAG, header::CACHE_CONTROL] {
+            if let Some(value) = parts.headers.remove(&name) {
+                headers.insert(name, value);
+            }
+        }
+        parts.status = StatusCode::NOT_MODIFIED;
+        parts.headers = headers;
+        return Ok(Response::from_parts(parts, Body::empty()));
+    }
+
+    Ok(Response::from_parts(parts, Body::from(body)))
+}
+
+/// Confronto debole tra `If-None-Match` (lista separata da virgole o `*`) e l'ETag
+fn etag_matches(if_none_match: &HeaderValue, etag: &HeaderValue) -> bool {
+    let Ok(if_none_match) = if_none_match.to_str() else {
+        return false;
+    };
+    let Ok(etag) = etag.to_str() else {
+        return false;
+    };
+    if_none_match.split(',').map(str::trim).any(|candidate| {
+        candidate == "*" || candidate.strip_prefix("W/").unwrap_or(candidate) == etag
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, middleware, routing::get};
+    use axum_test::TestServer;
+
+    fn server() -> TestServer {
+        let app = Router::new()
+            .route("/", get(|| async { "[1, 2, 3]" }))
+            .route(
+                "/missing",
+                get(|| async { (StatusCode::NOT_FOUND, "not found") }),
+            )
+            .layer(middleware::from_fn(etag_middleware));
+        TestServer::new(app).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_matching_etag_returns_not_modified() {
+        let server = server();
+
+        let response = server.get("/").await;
+        response.assert_status_ok();
+        let etag = response.header(header::ETAG);
+        assert_eq!(response.header(header::CACHE_CONTROL), "private, no-cache");
+
+        let response = server
+            .get("/")
+            .add_header(header::IF_NONE_MATCH, etag.clone())
+            .await;
+        response.assert_status(StatusCode::NOT_MODIFIED);
+        assert_eq!(response.header(header::ETAG), etag);
+        assert!(response.text().is_empty());
+
+        // anche come ETag debole e in una lista
+        let weak = format!("\"other\", W/{}", etag.to_str().unwrap());
+        let response = server
+            .get("/")
+            .add_header(header::IF_NONE_MATCH, weak)
+            .await;
+        response.assert_status(StatusCode::NOT_MODIFIED);
+    }
+
+    #[tokio::test]
+    async fn test_stale_etag_returns_body() {
+        let server = server();
+
+        let response = server
+            .get("/")
+            .add_header(header::IF_NONE_MATCH, "\"stale\"")
+            .await;
+        response.assert_status_ok();
+        assert_eq!(response.text(), "[1, 2, 3]");
+    }
+
+    #[tokio::test]
+    async fn test_error_responses_have_no_etag() {
+        let server = server();
+
+        let response = server.get("/missing").await;
+        response.assert_status(StatusCode::NOT_FOUND);
+        assert!(response.maybe_header(header::ETAG).is_none());
+    }
+}
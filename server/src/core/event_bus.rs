@@ -0,0 +1,479 @@
+//! Event Bus - Pubblicazione degli eventi di dominio su un broker esterno
+//!
+//! I service notificano messaggi inviati, chat create e nuovi membri con `AppState::publish_event`,
+//! senza attendere il broker: gli eventi passano da una coda in memoria a un task dedicato
+//! che li pubblica in ordine, a blocchi, su NATS oppure su Kafka tramite REST Proxy.
+//! Se il broker non è raggiungibile gli eventi vengono scartati e registrati nei log:
+//! la consegna è "at most once", pensata per analytics e sistemi a valle.
+
+use crate::core::config::EventBusConfig;
+use crate::entities::{ChatType, MessageType};
+use chrono::{DateTime, Utc};
+use futures_util::future::BoxFuture;
+use reqwest::Url;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWriteExt, BufStream};
+use tokio::net::TcpStream;
+use tokio::sync::{Mutex, mpsc};
+use tracing::warn;
+
+/// Eventi in attesa di pubblicazione oltre i quali i nuovi vengono scartati
+const EVENT_QUEUE_CAPACITY: usize = 4096;
+
+/// Eventi pubblicati al massimo con una singola richiesta al broker
+const MAX_EVENTS_PER_BATCH: usize = 256;
+
+/// Tempo massimo concesso al broker per confermare un blocco di eventi
+const BROKER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Evento di dominio pubblicato come JSON, con il tipo nel campo `type`.
+/// Il contenuto dei messaggi non lascia il server: i consumatori lo leggono dalle API se serve
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "type", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DomainEvent {
+    MessageCreated {
+        message_id: i32,
+        chat_id: i32,
+        sender_id: i32,
+        message_type: MessageType,
+        created_at: DateTime<Utc>,
+    },
+    ChatCreated {
+        chat_id: i32,
+        chat_type: ChatType,
+        created_by: i32,
+        members: Vec<i32>,
+        created_at: DateTime<Utc>,
+    },
+    MemberJoined {
+        chat_id: i32,
+        user_id: i32,
+        joined_at: DateTime<Utc>,
+    },
+}
+
+impl DomainEvent {
+    /// Chat a cui si riferisce l'evento, usata come chiave di partizione su Kafka
+    pub fn chat_id(&self) -> i32 {
+        match self {
+            DomainEvent::MessageCreated { chat_id, .. }
+            | DomainEvent::ChatCreated { chat_id, .. }
+            | DomainEvent::MemberJoined { chat_id, .. } => *chat_id,
+        }
+    }
+}
+
+/// Broker su cui vengono pubblicati gli eventi
+pub trait EventPublisher: Send + Sync {
+    /// Pubblica un blocco di eventi, nell'ordine in cui sono stati generati
+    ///
+    /// # Returns
+    /// * `Err(String)` - Descrizione dell'errore del broker (solo per i log)
+    fn publish<'a>(&'a self, events: &'a [DomainEvent]) -> BoxFuture<'a, Result<(), String>>;
+}
+
+/// Coda degli eventi da pubblicare, clonabile a costo zero
+#[derive(Clone)]
+pub struct EventBus {
+    queue: mpsc::Sender<DomainEvent>,
+}
+
+impl EventBus {
+    /// Avvia il task che pubblica gli eventi accodati con `publisher`.
+    /// Va chiamato all'interno del runtime tokio
+    pub fn start(publisher: Arc<dyn EventPublisher>) -> Self {
+        let (queue, events) = mpsc::channel(EVENT_QUEUE_CAPACITY);
+        tokio::spawn(run_publisher(publisher, events));
+        Self { queue }
+    }
+
+    /// Accoda un evento senza attendere; se la coda è piena l'evento viene scartato
+    pub fn publish(&self, event: DomainEvent) {
+        if let Err(e) = self.queue.try_send(event) {
+            warn!("Domain event dropped, publisher queue unavailable: {}", e);
+        }
+    }
+}
+
+/// Costruisce il bus degli eventi dalla configurazione
+///
+/// # Returns
+/// * `Ok(None)` se la pubblicazione è disabilitata
+/// * `Ok(Some(EventBus))` con il task di pubblicazione avviato (la connessione al broker
+///   viene aperta al primo evento), oppure un messaggio d'errore se l'URL non è valido
+pub fn build_event_bus(config: &EventBusConfig) -> Result<Option<EventBus>, String> {
+    let publisher: Arc<dyn EventPublisher> = match config {
+        EventBusConfig::Disabled => return Ok(None),
+        EventBusConfig::Nats { url, subject } => Arc::new(NatsEventPublisher::new(url, subject)?),
+        EventBusConfig::Kafka { rest_url, topic } => {
+            Arc::new(KafkaRestEventPublisher::new(rest_url, topic)?)
+        }
+    };
+    Ok(Some(EventBus::start(publisher)))
+}
+
+/// Svuota la coda a blocchi finché esiste almeno un `EventBus`
+async fn run_publisher(
+    publisher: Arc<dyn EventPublisher>,
+    mut events: mpsc::Receiver<DomainEvent>,
+) {
+    let mut batch = Vec::with_capacity(MAX_EVENTS_PER_BATCH);
+    while events.recv_many(&mut batch, MAX_EVENTS_PER_BATCH).await > 0 {
+        if let Err(e) = publisher.publish(&batch).await {
+            warn!(
+                events = batch.len(),
+                "Failed to publish domain events: {}", e
+            );
+        }
+        batch.clear();
+    }
+}
+
+/// Pubblicazione su NATS con il protocollo testuale (CONNECT, PUB, PING/PONG)
+/// su una singola connessione riutilizzata. Ogni blocco termina con un PING:
+/// il PONG conferma che il server ha accettato tutti i PUB precedenti
+pub struct NatsEventPublisher {
+    address: String,
+    username: Option<String>,
+    password: Option<String>,
+    token: Option<String>,
+    subject: String,
+    connection: Mutex<Option<BufStream<TcpStream>>>,
+}
+
+impl NatsEventPublisher {
+    /// Interpreta un URL nella forma `nats://[user:password@|token@]host[:port]`
+    pub fn new(url: &str, subject: &str) -> Result<Self, String> {
+        let rest = url
+            .strip_prefix("nats://")
+            .ok_or_else(|| "Invalid EVENT_BUS_URL: must start with nats://".to_string())?;
+
+        let (credentials, host) = match rest.trim_end_matches('/').rsplit_once('@') {
+            Some((credentials, host)) => (Some(credentials), host),
+            None => (None, rest.trim_end_matches('/')),
+        };
+        let (username, password, token) = match credentials {
+            Some(credentials) => match credentials.split_once(':') {
+                Some((username, password)) => {
+                    (Some(username.to_string()), Some(password.to_string()), None)
+                }
+                None => (None, None, Some(credentials.to_string())),
+            },
+            None => (None, None, None),
+        };
+
+        if host.is_empty() {
+            return Err("Invalid EVENT_BUS_URL: missing host".to_string());
+        }
+        if subject.is_empty() || subject.contains(char::is_whitespace) {
+            return Err("Invalid EVENT_BUS_TOPIC: not a valid NATS subject".to_string());
+        }
+        let address = if host.contains(':') {
+            host.to_string()
+        } else {
+            format!("{}:4222", host)
+        };
+
+        Ok(Self {
+            address,
+            username,
+            password,
+            token,
+            subject: subject.to_string(),
+            connection: Mutex::new(None),
+        })
+    }
+
+    /// Opzioni del comando CONNECT; `verbose` disattivato, quindi nessun +OK per ogni PUB
+    fn connect_options(&self) -> serde_json::Value {
+        let mut options = serde_json::json!({
+            "verbose": false,
+            "pedantic": false,
+            "lang": "rust",
+            "name": "ironlink-server",
+            "version": env!("CARGO_PKG_VERSION"),
+        });
+        if let Some(username) = &self.username {
+            options["user"] = username.clone().into();
+        }
+        if let Some(password) = &self.password {
+            options["pass"] = password.clone().into();
+        }
+        if let Some(token) = &self.token {
+            options["auth_token"] = token.clone().into();
+        }
+        options
+    }
+
+    async fn connect(&self) -> Result<BufStream<TcpStream>, String> {
+        let stream = TcpStream::connect(&self.address)
+            .await
+            .map_err(|e| format!("Unable to connect to NATS: {}", e))?;
+        let mut stream = BufStream::new(stream);
+
+        // il server si presenta con INFO prima di accettare comandi
+        let mut info = String::new();
+        stream
+            .read_line(&mut info)
+            .await
+            .map_err(|e| format!("NATS read failed: {}", e))?;
+        if !info.starts_with("INFO") {
+            return Err(format!("Unexpected NATS greeting: {}", info.trim_end()));
+        }
+
+        let connect = format!("CONNECT {}\r\n", self.connect_options());
+        write_all(&mut stream, connect.as_bytes()).await?;
+        flush_with_ping(&mut stream).await?;
+        Ok(stream)
+    }
+
+    /// Pubblica il blocco sulla connessione aperta, aprendola se necessario.
+    /// In caso di errore la connessione viene scartata
+    async fn publish_once(
+        &self,
+        connection: &mut Option<BufStream<TcpStream>>,
+        payload: &[u8],
+    ) -> Result<(), String> {
+        let result = tokio::time::timeout(BROKER_TIMEOUT, async {
+            if connection.is_none() {
+                *connection = Some(self.connect().await?);
+            }
+            let stream = connection.as_mut().expect("connection just opened");
+            write_all(stream, payload).await?;
+            flush_with_ping(stream).await
+        })
+        .await
+        .unwrap_or_else(|_| Err("NATS request timed out".to_string()));
+
+        if result.is_err() {
+            *connection = None;
+        }
+        result
+    }
+}
+
+impl EventPublisher for NatsEventPublisher {
+    fn publish<'a>(&'a self, events: &'a [DomainEvent]) -> BoxFuture<'a, Result<(), String>> {
+        Box::pin(async move {
+            let mut payload = Vec::new();
+            for event in events {
+                let body = serde_json::to_vec(event).map_err(|e| e.to_string())?;
+                encode_pub(&mut payload, &self.subject, &body);
+            }
+
+            let mut connection = self.connection.lock().await;
+            if let Err(e) = self.publish_once(&mut connection, &payload).await {
+                // la connessione inattiva può essere stata chiusa dal server: un solo nuovo tentativo
+                warn!("NATS publish failed, reconnecting: {}", e);
+                self.publish_once(&mut connection, &payload).await?;
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Accoda a `buffer` il comando `PUB <subject> <bytes>` seguito dal payload
+fn encode_pub(buffer: &mut Vec<u8>, subject: &str, body: &[u8]) {
+    buffer.extend_from_slice(format!("PUB {} {}\r\n", subject, body.len()).as_bytes());
+    buffer.extend_from_slice(body);
+    buffer.extend_from_slice(b"\r\n");
+}
+
+async fn write_all(stream: &mut BufStream<TcpStream>, data: &[u8]) -> Result<(), String> {
+    stream
+        .write_all(data)
+        .await
+        .map_err(|e| format!("NATS write failed: {}", e))
+}
+
+/// Invia PING e attende il PONG, rispondendo ai PING del server nel frattempo
+async fn flush_with_ping(stream: &mut BufStream<TcpStream>) -> Result<(), String> {
+    write_all(stream, b"PING\r\n").await?;
+    stream
+        .flush()
+        .await
+        .map_err(|e| format!("NATS write failed: {}", e))?;
+    loop {
+        match read_control_line(stream).await? {
+            NatsControl::Pong => return Ok(()),
+            NatsControl::Ping => {
+                write_all(stream, b"PONG\r\n").await?;
+                stream
+                    .flush()
+                    .await
+                    .map_err(|e| format!("NATS write failed: {}", e))?;
+            }
+            NatsControl::Other => {}
+        }
+    }
+}
+
+/// Riga di controllo inviata dal server NATS a un client che non ha sottoscrizioni
+#[derive(Debug, PartialEq)]
+enum NatsControl {
+    Ping,
+    Pong,
+    /// +OK o un nuovo INFO (es. cambiamenti del cluster), ignorati
+    Other,
+}
+
+async fn read_control_line<R>(stream: &mut R) -> Result<NatsControl, String>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut line = String::new();
+    stream
+        .read_line(&mut line)
+        .await
+        .map_err(|e| format!("NATS read failed: {}", e))?;
+    match line.trim_end() {
+        "" => Err("NATS closed the connection".to_string()),
+        "PING" => Ok(NatsControl::Ping),
+        "PONG" => Ok(NatsControl::Pong),
+        error if error.starts_with("-ERR") => Err(format!("NATS error: {}", error)),
+        _ => Ok(NatsControl::Other),
+    }
+}
+
+/// Pubblicazione su Kafka tramite Confluent REST Proxy (`POST {rest_url}/topics/{topic}`).
+/// Ogni record usa il chat_id come chiave, così gli eventi di una chat restano ordinati
+pub struct KafkaRestEventPublisher {
+    client: reqwest::Client,
+    endpoint: Url,
+}
+
+#[derive(Serialize)]
+struct KafkaRecords<'a> {
+    records: Vec<KafkaRecord<'a>>,
+}
+
+#[derive(Serialize)]
+struct KafkaRecord<'a> {
+    key: String,
+    value: &'a DomainEvent,
+}
+
+impl KafkaRestEventPublisher {
+    pub fn new(rest_url: &str, topic: &str) -> Result<Self, String> {
+        let mut endpoint =
+            Url::parse(rest_url).map_err(|e| format!("Invalid EVENT_BUS_URL: {}", e))?;
+        if !matches!(endpoint.scheme(), "http" | "https") {
+            return Err("Invalid EVENT_BUS_URL: must start with http:// or https://".to_string());
+        }
+        if topic.is_empty() {
+            return Err("Invalid EVENT_BUS_TOPIC: must not be empty".to_string());
+        }
+        endpoint
+            .path_segments_mut()
+            .map_err(|_| "Invalid EVENT_BUS_URL: cannot be a base URL".to_string())?
+            .pop_if_empty()
+            .extend(["topics", topic]);
+
+        Ok(Self {
+            client: reqwest::Client::new(),
+            endpoint,
+        })
+    }
+}
+
+impl EventPublisher for KafkaRestEventPublisher {
+    fn publish<'a>(&'a self, events: &'a [DomainEvent]) -> BoxFuture<'a, Result<(), String>> {
+        Box::pin(async move {
+            let records = KafkaRecords {
+                records: events
+                    .iter()
+                    .map(|event| KafkaRecord {
+                        key: event.chat_id().to_string(),
+                        value: event,
+                    })
+                    .collect(),
+            };
+            let body = serde_json::to_vec(&records).map_err(|e| e.to_string())?;
+
+            self.client
+                .post(self.endpoint.clone())
+                .header(
+                    reqwest::header::CONTENT_TYPE,
+                    "application/vnd.kafka.json.v2+json",
+                )
+                .header(reqwest::header::ACCEPT, "application/vnd.kafka.v2+json")
+                .timeout(BROKER_TIMEOUT)
+                .body(body)
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+                .map_err(|e| e.to_string())?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nats_url_parsing() {
+        let publisher = NatsEventPublisher::new("nats://app:pw@broker.local", "events").unwrap();
+        assert_eq!(publisher.address, "broker.local:4222");
+        assert_eq!(publisher.username.as_deref(), Some("app"));
+        assert_eq!(publisher.password.as_deref(), Some("pw"));
+
+        let publisher = NatsEventPublisher::new("nats://s3cr3t@127.0.0.1:4223/", "events").unwrap();
+        assert_eq!(publisher.address, "127.0.0.1:4223");
+        assert_eq!(publisher.token.as_deref(), Some("s3cr3t"));
+        assert_eq!(publisher.connect_options()["auth_token"], "s3cr3t");
+
+        assert!(NatsEventPublisher::new("http://localhost", "events").is_err());
+        assert!(NatsEventPublisher::new("nats://localhost", "bad subject").is_err());
+    }
+
+    #[test]
+    fn test_kafka_endpoint() {
+        let publisher =
+            KafkaRestEventPublisher::new("http://kafka-rest:8082/", "ironlink.events").unwrap();
+        assert_eq!(
+            publisher.endpoint.as_str(),
+            "http://kafka-rest:8082/topics/ironlink.events"
+        );
+        assert!(KafkaRestEventPublisher::new("kafka://broker:9092", "events").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_encode_pub_and_control_lines() {
+        let event = DomainEvent::MemberJoined {
+            chat_id: 1,
+            user_id: 3,
+            joined_at: DateTime::from_timestamp(0, 0).unwrap(),
+        };
+        let body = serde_json::to_vec(&event).unwrap();
+        assert_eq!(
+            String::from_utf8(body.clone()).unwrap(),
+            r#"{"type":"MEMBER_JOINED","chat_id":1,"user_id":3,"joined_at":"1970-01-01T00:00:00Z"}"#
+        );
+
+        let mut buffer = Vec::new();
+        encode_pub(&mut buffer, "ironlink.events", &body);
+        let expected = format!("PUB ironlink.events {}\r\n", body.len());
+        assert!(buffer.starts_with(expected.as_bytes()));
+        assert!(buffer.ends_with(b"}\r\n"));
+
+        let mut input: &[u8] = b"+OK\r\nPING\r\nPONG\r\n-ERR 'Authorization Violation'\r\n";
+        assert_eq!(
+            read_control_line(&mut input).await.unwrap(),
+            NatsControl::Other
+        );
+        assert_eq!(
+            read_control_line(&mut input).await.unwrap(),
+            NatsControl::Ping
+        );
+        assert_eq!(
+            read_control_line(&mut input).await.unwrap(),
+            NatsControl::Pong
+        );
+        assert!(read_control_line(&mut input).await.is_err());
+        assert!(read_control_line(&mut input).await.is_err());
+    }
+}
@@ -0,0 +1,349 @@
+//! Flood - Rilevamento di flood e spam nelle chat
+//!
+//! Il rate limiting della connessione WebSocket limita solo il volume dei messaggi. Qui,
+//! per ogni coppia (utente, chat), vengono riconosciuti comportamenti che lo rispettano ma
+//! disturbano gli altri membri: lo stesso messaggio ripetuto, una serie di messaggi con
+//! link e ingressi e uscite continui dalla chat. Ogni infrazione comporta un silenziamento
+//! temporaneo che raddoppia ad ogni recidiva fino a `max_mute`; applicarlo spetta al
+//! chiamante. Lo stato è in memoria e vale per la singola istanza del server.
+
+use crate::core::config::FloodConfig;
+use crate::entities::FloodReason;
+use dashmap::DashMap;
+use std::collections::VecDeque;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+
+/// Oltre questo numero di coppie tracciate vengono scartate quelle inattive
+const MAX_TRACKED_KEYS: usize = 10_000;
+
+/// Infrazione rilevata, con la durata del silenziamento da applicare
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FloodStrike {
+    pub reason: FloodReason,
+    pub mute: Duration,
+}
+
+/// Messaggio recente di un utente in una chat
+struct RecentMessage {
+    sent_at: Instant,
+    /// Hash del contenuto normalizzato, per riconoscere le ripetizioni
+    fingerprint: u64,
+    has_link: bool,
+}
+
+/// Attività recente di una coppia (utente, chat)
+#[derive(Default)]
+struct Activity {
+    messages: VecDeque<RecentMessage>,
+    /// Ingressi e uscite dalla chat
+    membership_changes: VecDeque<Instant>,
+    /// Infrazioni dall'ultimo periodo senza infrazioni lungo `max_mute`
+    strikes: u32,
+    last_strike: Option<Instant>,
+}
+
+impl Activity {
+    fn last_seen(&self) -> Option<Instant> {
+        let last_message = self.messages.back().map(|m| m.sent_at);
+        let last_change = self.membership_changes.back().copied();
+        last_message.max(last_change).max(self.last_strike)
+    }
+}
+
+/// Euristiche anti-flood per coppia (utente, chat); una soglia a 0 disabilita l'euristica
+pub struct FloodGuard {
+    window: Duration,
+    repeat_threshold: u32,
+    link_threshold: u32,
+    join_leave_threshold: u32,
+    base_mute: Duration,
+    max_mute: Duration,
+    activity: DashMap<(i32, i32), Activity>,
+}
+
+impl FloodGuard {
+    pub fn new(config: &FloodConfig) -> Self {
+        Self {
+            window: Duration::from_secs(config.window_secs),
+            repeat_threshold: config.repeat_threshold,
+            link_threshold: config.link_threshold,
+            join_leave_threshold: config.join_leave_threshold,
+            base_mute: Duration::from_secs(config.mute_secs),
+            max_mute: Duration::from_secs(config.max_mute_secs),
+            activity: DashMap::new(),
+        }
+    }
+
+    /// Registra un messaggio inviato da `user_id` nella chat
+    ///
+    /// # Returns
+    /// L'infrazione se il messaggio è l'ultima ripetizione consentita di uno già inviato,
+    /// o l'ultimo di troppi messaggi con link
+    pub fn record_message(&self, user_id: i32, chat_id: i32, content: &str) -> Option<FloodStrike> {
+        self.record_message_at(user_id, chat_id, content, Instant::now())
+    }
+
+    /// Registra l'uscita di `user_id` dalla chat
+    pub fn record_leave(&self, user_id: i32, chat_id: i32) {
+        self.record_membership_change_at(user_id, chat_id, false, Instant::now());
+    }
+
+    /// Registra l'ingresso di `user_id` nella chat
+    ///
+    /// # Returns
+    /// L'infrazione se ingressi e uscite nella finestra hanno raggiunto la soglia
+    pub fn record_join(&self, user_id: i32, chat_id: i32) -> Option<FloodStrike> {
+        self.record_membership_change_at(user_id, chat_id, true, Instant::now())
+    }
+
+    fn record_message_at(
+        &self,
+        user_id: i32,
+        chat_id: i32,
+        content: &str,
+        now: Instant,
+    ) -> Option<FloodStrike> {
+        if self.repeat_threshold == 0 && self.link_threshold == 0 {
+            return None;
+        }
+        let message = RecentMessage {
+            sent_at: now,
+            fingerprint: fingerprint(content),
+            has_link: contains_link(content),
+        };
+
+        let mut activity = self.activity_for((user_id, chat_id), now);
+        while activity
+            .messages
+            .front()
+            .is_some_and(|m| now.duration_since(m.sent_at) > self.window)
+        {
+            activity.messages.pop_front();
+        }
+
+        let repeats = activity
+            .messages
+            .iter()
+            .filter(|m| m.fingerprint == message.fingerprint)
+            .count() as u32
+            + 1;
+        let has_link = message.has_link;
+        let links =
+            activity.messages.iter().filter(|m| m.has_link).count() as u32 + has_link as u32;
+        activity.messages.push_back(message);
+
+        let reason = if self.repeat_threshold > 0 && repeats >= self.repeat_threshold {
+            FloodReason::RepeatedMessage
+        } else if self.link_threshold > 0 && has_link && links >= self.link_threshold {
+            FloodReason::LinkSpam
+        } else {
+            return None;
+        };
+        // i messaggi già sanzionati non contano per l'infrazione successiva
+        activity.messages.clear();
+        Some(self.strike(&mut activity, reason, now))
+    }
+
+    /// Conta un ingresso o un'uscita. Solo un membro può essere silenziato, quindi
+    /// l'infrazione scatta sempre al rientro nella chat
+    fn record_membership_change_at(
+        &self,
+        user_id: i32,
+        chat_id: i32,
+        joined: bool,
+        now: Instant,
+    ) -> Option<FloodStrike> {
+        if self.join_leave_threshold == 0 {
+            return None;
+        }
+        let mut activity = self.activity_for((user_id, chat_id), now);
+        while activity
+            .membership_changes
+            .front()
+            .is_some_and(|at| now.duration_since(*at) > self.window)
+        {
+            activity.membership_changes.pop_front();
+        }
+        activity.membership_changes.push_back(now);
+
+        if !joined || (activity.membership_changes.len() as u32) < self.join_leave_threshold {
+            return None;
+        }
+        activity.membership_changes.clear();
+        Some(self.strike(&mut activity, FloodReason::JoinLeaveThrash, now))
+    }
+
+    fn activity_for(
+        &self,
+        key: (i32, i32),
+        now: Instant,
+    ) -> dashmap::mapref::one::RefMut<'_, (i32, i32), Activity> {
+        if !self.activity.contains_key(&key) && self.activity.len() >= MAX_TRACKED_KEYS {
+            self.activity
+                .retain(|_, activity| !self.is_expired(activity, now));
+        }
+        self.activity.entry(key).or_default()
+    }
+
+    /// Registra un'infrazione e calcola il silenziamento: `base_mute` la prima volta,
+    /// raddoppiato ad ogni infrazione successiva fino a `max_mute`
+    fn strike(&self, activity: &mut Activity, reason: FloodReason, now: Instant) -> FloodStrike {
+        // dopo un lungo periodo senza infrazioni si riparte dal silenziamento più breve
+        if activity
+            .last_strike
+            .is_some_and(|at| now.saturating_duration_since(at) > self.max_mute)
+        {
+            activity.strikes = 0;
+        }
+        let doublings = activity.strikes.min(31);
+        activity.strikes += 1;
+        activity.last_strike = Some(now);
+
+        FloodStrike {
+            reason,
+            mute: self
+                .base_mute
+                .saturating_mul(1 << doublings)
+                .min(self.max_mute),
+        }
+    }
+
+    /// Coppia non più rilevante: nessuna attività nella finestra né infrazioni recenti
+    fn is_expired(&self, activity: &Activity, now: Instant) -> bool {
+        activity
+            .last_seen()
+            .is_none_or(|at| now.saturating_duration_since(at) > self.window.max(self.max_mute))
+    }
+}
+
+impl Default for FloodGuard {
+    fn default() -> Self {
+        Self::new(&FloodConfig::default())
+    }
+}
+
+/// Hash del contenuto ignorando maiuscole e spazi, così "Ciao" e " ciao  " sono ripetizioni
+fn fingerprint(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for word in content.split_whitespace() {
+        word.to_lowercase().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+fn contains_link(content: &str) -> bool {
+    let content = content.to_lowercase();
+    content.contains("http://") || content.contains("https://") || content.contains("www.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guard() -> FloodGuard {
+        FloodGuard::new(&FloodConfig {
+            window_secs: 60,
+            repeat_threshold: 3,
+            link_threshold: 2,
+            join_leave_threshold: 4,
+            mute_secs: 30,
+            max_mute_secs: 100,
+        })
+    }
+
+    #[test]
+    fn test_repeated_message_with_escalating_mute() {
+        let guard = guard();
+        let start = Instant::now();
+
+        assert_eq!(guard.record_message_at(1, 1, "spam", start), None);
+        assert_eq!(guard.record_message_at(1, 1, " SPAM ", start), None);
+        // messaggi diversi, di altri utenti o in altre chat non contano
+        assert_eq!(guard.record_message_at(1, 1, "hello", start), None);
+        assert_eq!(guard.record_message_at(2, 1, "spam", start), None);
+        assert_eq!(guard.record_message_at(1, 2, "spam", start), None);
+
+        assert_eq!(
+            guard.record_message_at(1, 1, "spam", start),
+            Some(FloodStrike {
+                reason: FloodReason::RepeatedMessage,
+                mute: Duration::from_secs(30)
+            })
+        );
+
+        // la recidiva raddoppia il silenziamento fino al massimo
+        let mut mutes = Vec::new();
+        for _ in 0..2 {
+            for _ in 0..3 {
+                if let Some(strike) = guard.record_message_at(1, 1, "spam", start) {
+                    mutes.push(strike.mute);
+                }
+            }
+        }
+        assert_eq!(
+            mutes,
+            vec![Duration::from_secs(60), Duration::from_secs(100)]
+        );
+    }
+
+    #[test]
+    fn test_repetitions_outside_window_are_forgotten() {
+        let guard = guard();
+        let start = Instant::now();
+
+        guard.record_message_at(1, 1, "spam", start);
+        guard.record_message_at(1, 1, "spam", start);
+        let later = start + Duration::from_secs(61);
+        assert_eq!(guard.record_message_at(1, 1, "spam", later), None);
+    }
+
+    #[test]
+    fn test_link_spam() {
+        let guard = guard();
+        let start = Instant::now();
+
+        assert_eq!(
+            guard.record_message_at(1, 1, "see https://example.com", start),
+            None
+        );
+        assert_eq!(guard.record_message_at(1, 1, "no links here", start), None);
+        assert_eq!(
+            guard
+                .record_message_at(1, 1, "or WWW.example.org", start)
+                .map(|strike| strike.reason),
+            Some(FloodReason::LinkSpam)
+        );
+    }
+
+    #[test]
+    fn test_join_leave_thrash() {
+        let guard = guard();
+        let start = Instant::now();
+
+        assert_eq!(guard.record_membership_change_at(1, 1, true, start), None);
+        assert_eq!(guard.record_membership_change_at(1, 1, false, start), None);
+        assert_eq!(guard.record_membership_change_at(1, 1, true, start), None);
+        // quarta variazione, ma è un'uscita: l'utente non è più membro
+        assert_eq!(guard.record_membership_change_at(1, 1, false, start), None);
+        assert_eq!(
+            guard
+                .record_membership_change_at(1, 1, true, start)
+                .map(|strike| strike.reason),
+            Some(FloodReason::JoinLeaveThrash)
+        );
+
+        // soglie a 0: euristiche disabilitate
+        let disabled = FloodGuard::new(&FloodConfig {
+            repeat_threshold: 0,
+            link_threshold: 0,
+            join_leave_threshold: 0,
+            ..FloodConfig::default()
+        });
+        for _ in 0..10 {
+            assert_eq!(disabled.record_message(1, 1, "https://spam"), None);
+            assert_eq!(disabled.record_join(1, 1), None);
+        }
+    }
+}
@@ -0,0 +1,317 @@
+//! Idempotency - Deduplicazione delle richieste ripetute dai client
+//!
+//! Un client che non ha ricevuto la risposta (timeout, rete caduta) può ripetere una POST
+//! con lo stesso header `Idempotency-Key`: la risposta di successo della prima richiesta
+//! viene riprodotta invece di creare una seconda chat o un secondo invito. La chiave vale
+//! per utente e per route; le risposte sono conservate in memoria per `IDEMPOTENCY_KEY_TTL`
+//! e valgono per la singola istanza del server.
+
+use crate::core::AppState;
+use crate::core::error::AppError;
+use crate::entities::User;
+use axum::{
+    body::{Body, Bytes, to_bytes},
+    extract::{FromRequest, Request, State},
+    http::{HeaderMap, HeaderName, HeaderValue, Response, StatusCode},
+    middleware::Next,
+    response::IntoResponse,
+};
+use dashmap::{DashMap, mapref::entry::Entry};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Header con cui il client indica la chiave di idempotenza
+pub const IDEMPOTENCY_KEY_HEADER: HeaderName = HeaderName::from_static("idempotency-key");
+
+/// Header aggiunto alle risposte riprodotte da una richiesta precedente
+pub const IDEMPOTENT_REPLAYED_HEADER: HeaderName = HeaderName::from_static("idempotent-replayed");
+
+/// Per quanto tempo una risposta resta disponibile per i tentativi successivi
+pub const IDEMPOTENCY_KEY_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Lunghezza massima della chiave scelta dal client
+const MAX_KEY_LENGTH: usize = 255;
+
+/// Oltre questo numero di chiavi tracciate vengono scartate quelle scadute
+const MAX_TRACKED_KEYS: usize = 10_000;
+
+/// Chiave della mappa: utente (None per le route senza JWT), path della richiesta e chiave del client
+type StoreKey = (Option<i32>, String, String);
+
+/// Risposta conservata per essere riprodotta
+#[derive(Clone)]
+struct StoredResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl IntoResponse for StoredResponse {
+    fn into_response(self) -> axum::response::Response {
+        let mut response = (self.status, self.headers, self.body).into_response();
+        response
+            .headers_mut()
+            .insert(IDEMPOTENT_REPLAYED_HEADER, HeaderValue::from_static("true"));
+        response
+    }
+}
+
+enum Slot {
+    /// Richiesta ancora in esecuzione
+    InFlight { fingerprint: [u8; 32] },
+    /// Richiesta completata con successo
+    Completed {
+        fingerprint: [u8; 32],
+        response: StoredResponse,
+        expires_at: Instant,
+    },
+}
+
+/// Esito della registrazione di una richiesta con chiave di idempotenza
+enum Claim {
+    /// Prima richiesta con questa chiave: va eseguita
+    Started,
+    /// La stessa richiesta è già stata completata: la sua risposta va riprodotta
+    Replay(StoredResponse),
+    /// Una richiesta con la stessa chiave è ancora in esecuzione
+    InProgress,
+    /// La chiave è già stata usata con un corpo diverso
+    Mismatch,
+}
+
+/// Risposte delle richieste con chiave di idempotenza, per utente e route
+pub struct IdempotencyStore {
+    ttl: Duration,
+    slots: DashMap<StoreKey, Slot>,
+}
+
+impl IdempotencyStore {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            slots: DashMap::new(),
+        }
+    }
+
+    /// Registra l'inizio di una richiesta, se nessun'altra con la stessa chiave l'ha preceduta
+    fn claim(&self, key: StoreKey, fingerprint: [u8; 32]) -> Claim {
+        let now = Instant::now();
+        if self.slots.len() >= MAX_TRACKED_KEYS {
+            self.slots.retain(|_, slot| match slot {
+                Slot::InFlight { .. } => true,
+                Slot::Completed { expires_at, .. } => *expires_at > now,
+            });
+        }
+
+        match self.slots.entry(key) {
+            Entry::Occupied(mut entry) => match entry.get() {
+                Slot::InFlight { .. } => Claim::InProgress,
+                Slot::Completed { expires_at, .. } if *expires_at <= now => {
+                    entry.insert(Slot::InFlight { fingerprint });
+                    Claim::Started
+                }
+                Slot::Completed {
+                    fingerprint: stored,
+                    response,
+                    ..
+                } => {
+                    if *stored == fingerprint {
+                        Claim::Replay(response.clone())
+                    } else {
+                        Claim::Mismatch
+                    }
+                }
+            },
+            Entry::Vacant(entry) => {
+                entry.insert(Slot::InFlight { fingerprint });
+                Claim::Started
+            }
+        }
+    }
+
+    /// Conserva la risposta di una richiesta completata con successo
+    fn complete(&self, key: StoreKey, response: StoredResponse) {
+        if let Some(mut slot) = self.slots.get_mut(&key)
+            && let Slot::InFlight { fingerprint } = *slot
+        {
+            *slot = Slot::Completed {
+                fingerprint,
+                response,
+                expires_at: Instant::now() + self.ttl,
+            };
+        }
+    }
+
+    /// Libera la chiave di una richiesta fallita o interrotta, che può essere ripetuta
+    fn release(&self, key: &StoreKey) {
+        self.slots
+            .remove_if(key, |_, slot| matches!(slot, Slot::InFlight { .. }));
+    }
+}
+
+impl Default for IdempotencyStore {
+    fn default() -> Self {
+        Self::new(IDEMPOTENCY_KEY_TTL)
+    }
+}
+
+/// Libera la chiave se la richiesta non arriva a completamento (errore o client disconnesso)
+struct InFlightGuard<'a> {
+    store: &'a IdempotencyStore,
+    key: Option<StoreKey>,
+}
+
+impl InFlightGuard<'_> {
+    fn complete(mut self, response: StoredResponse) {
+        if let Some(key) = self.key.take() {
+            self.store.complete(key, response);
+        }
+    }
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(key) = &self.key {
+            self.store.release(key);
+        }
+    }
+}
+
+/// Middleware che deduplica le richieste con header `Idempotency-Key`
+///
+/// Va applicato alle singole route POST, dopo `authentication_middleware` se presente.
+/// Le richieste senza header passano invariate.
+pub async fn idempotency_middleware(
+    State(state): State<Arc<AppState>>,
+    req: Request,
+    next: Next,
+) -> Result<Response<Body>, AppError> {
+    // 1. Senza header la richiesta prosegue normalmente
+    // 2. Validare la chiave e leggere il corpo per calcolarne l'impronta
+    // 3. Riprodurre la risposta già conservata, oppure rifiutare una chiave in uso
+    //    da una richiesta in corso o già usata con un corpo diverso
+    // 4. Eseguire la richiesta e conservarne la risposta se ha avuto successo
+
+    let Some(value) = req.headers().get(IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(next.run(req).await);
+    };
+    let idempotency_key = value
+        .to_str()
+        .ok()
+        .filter(|key| !key.is_empty() && key.len() <= MAX_KEY_LENGTH)
+        .ok_or_else(|| {
+            warn!("Invalid Idempotency-Key header");
+            AppError::bad_request("Invalid Idempotency-Key header").with_details(format!(
+                "The key must be 1 to {} visible ASCII characters",
+                MAX_KEY_LENGTH
+            ))
+        })?
+        .to_string();
+
+    let user_id = req.extensions().get::<User>().map(|user| user.user_id);
+    let key = (user_id, req.uri().path().to_string(), idempotency_key);
+
+    let (parts, body) = req.into_parts();
+    let body = match Bytes::from_request(Request::new(body), &state).await {
+        Ok(body) => body,
+        Err(rejection) => return Ok(rejection.into_response()),
+    };
+    let fingerprint: [u8; 32] = Sha256::new()
+        .chain_update(parts.method.as_str())
+        .chain_update(&body)
+        .finalize()
+        .into();
+
+    match state.idempotency.claim(key.clone(), fingerprint) {
+        Claim::Started => {}
+        Claim::Replay(response) => return Ok(response.into_response()),
+        Claim::InProgress => {
+            warn!("Request with the same Idempotency-Key still in progress");
+            return Err(AppError::conflict(
+                "A request with this Idempotency-Key is already in progress",
+            ));
+        }
+        Claim::Mismatch => {
+            warn!("Idempotency-Key reused with a different request body");
+            return Err(AppError::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "Idempotency-Key already used for a different request",
+            ));
+        }
+    }
+
+    let guard = InFlightGuard {
+        store: &state.idempotency,
+        key: Some(key),
+    };
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+    if !response.status().is_success() {
+        // errori e rifiuti non vengono conservati: il client può ripetere la richiesta
+        return Ok(response);
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = to_bytes(body, usize::MAX).await?;
+    guard.complete(StoredResponse {
+        status: parts.status,
+        headers: parts.headers.clone(),
+        body: body.clone(),
+    });
+    Ok(Response::from_parts(parts, Body::from(body)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(idempotency_key: &str) -> StoreKey {
+        (Some(1), "/chats".to_string(), idempotency_key.to_string())
+    }
+
+    fn response(body: &'static str) -> StoredResponse {
+        StoredResponse {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: Bytes::from_static(body.as_bytes()),
+        }
+    }
+
+    #[test]
+    fn test_completed_request_is_replayed() {
+        let store = IdempotencyStore::default();
+
+        assert!(matches!(store.claim(key("a"), [1; 32]), Claim::Started));
+        assert!(matches!(store.claim(key("a"), [1; 32]), Claim::InProgress));
+
+        store.complete(key("a"), response("created"));
+        match store.claim(key("a"), [1; 32]) {
+            Claim::Replay(stored) => assert_eq!(stored.body, "created"),
+            _ => panic!("Expected a replayed response"),
+        }
+        assert!(matches!(store.claim(key("a"), [2; 32]), Claim::Mismatch));
+
+        // la stessa chiave di un altro utente è indipendente
+        let other_user = (Some(2), "/chats".to_string(), "a".to_string());
+        assert!(matches!(store.claim(other_user, [1; 32]), Claim::Started));
+    }
+
+    #[test]
+    fn test_released_key_can_be_retried() {
+        let store = IdempotencyStore::default();
+
+        assert!(matches!(store.claim(key("a"), [1; 32]), Claim::Started));
+        store.release(&key("a"));
+        assert!(matches!(store.claim(key("a"), [1; 32]), Claim::Started));
+    }
+
+    #[test]
+    fn test_expired_response_is_forgotten() {
+        let store = IdempotencyStore::new(Duration::ZERO);
+
+        assert!(matches!(store.claim(key("a"), [1; 32]), Claim::Started));
+        store.complete(key("a"), response("created"));
+        assert!(matches!(store.claim(key("a"), [2; 32]), Claim::Started));
+    }
+}
@@ -0,0 +1,208 @@
+//! LDAP - Verifica delle credenziali tramite bind su una directory LDAP
+//!
+//! Usato da `login_user` quando `AUTH_BACKEND=ldap`: la password non viene confrontata
+//! con l'hash locale ma verificata con un simple bind (LDAPv3) con il DN dell'utente.
+//! Il protocollo è gestito dal crate `ldap3`, con una connessione aperta per ogni login,
+//! in chiaro con `ldap://` oppure TLS con `ldaps://`.
+
+use crate::core::auth::AuthProvider;
+use crate::core::config::LdapConfig;
+use crate::entities::User;
+use futures_util::future::BoxFuture;
+use ldap3::{LdapConnAsync, LdapConnSettings, dn_escape};
+use reqwest::Url;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Tempo massimo concesso alla directory per rispondere al bind
+const LDAP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Result code LDAP di un bind riuscito
+const LDAP_SUCCESS: u32 = 0;
+
+/// Result code LDAP per credenziali non valide
+const LDAP_INVALID_CREDENTIALS: u32 = 49;
+
+/// Provider di autenticazione che verifica le credenziali con un bind LDAP
+pub struct LdapAuthProvider {
+    url: String,
+    bind_dn_template: String,
+}
+
+impl LdapAuthProvider {
+    /// Crea il provider da `LDAP_URL` (`ldap://host[:port]` o `ldaps://host[:port]`)
+    /// e dal template del DN, che deve contenere `{username}`
+    pub fn new(config: &LdapConfig) -> Result<Self, String> {
+        let url = Url::parse(&config.url).map_err(|e| format!("Invalid LDAP_URL: {}", e))?;
+        if !matches!(url.scheme(), "ldap" | "ldaps") {
+            return Err("Invalid LDAP_URL: must start with ldap:// or ldaps://".to_string());
+        }
+        if url.host_str().is_none_or(str::is_empty) {
+            return Err("Invalid LDAP_URL: missing host".to_string());
+        }
+
+        if !config.bind_dn_template.contains("{username}") {
+            return Err("Invalid LDAP_BIND_DN_TEMPLATE: must contain {username}".to_string());
+        }
+
+        Ok(Self {
+            url: config.url.clone(),
+            bind_dn_template: config.bind_dn_template.clone(),
+        })
+    }
+
+    /// DN con cui fare il bind per `username`, con i caratteri speciali codificati (RFC 4514)
+    fn bind_dn(&self, username: &str) -> String {
+        self.bind_dn_template
+            .replace("{username}", &dn_escape(username))
+    }
+
+    /// Apre la connessione ed esegue il bind, ritorna true se la directory accetta le credenziali
+    async fn bind(&self, dn: &str, password: &str) -> Result<bool, String> {
+        let settings = LdapConnSettings::new().set_conn_timeout(LDAP_TIMEOUT);
+        let (connection, mut ldap) = LdapConnAsync::with_settings(settings, &self.url)
+            .await
+            .map_err(|e| format!("Unable to connect to LDAP server: {}", e))?;
+        ldap3::drive!(connection);
+
+        let result = ldap
+            .simple_bind(dn, password)
+            .await
+            .map_err(|e| format!("LDAP bind failed: {}", e))?;
+
+        // l'unbind chiude la sessione, un suo errore non cambia l'esito del bind
+        let _ = ldap.unbind().await;
+
+        match result.rc {
+            LDAP_SUCCESS => Ok(true),
+            LDAP_INVALID_CREDENTIALS => Ok(false),
+            code => {
+                warn!("LDAP bind rejected with result code {}", code);
+                Err(format!("LDAP bind failed with result code {}", code))
+            }
+        }
+    }
+}
+
+impl AuthProvider for LdapAuthProvider {
+    fn authenticate<'a>(
+        &'a self,
+        username: &'a str,
+        password: &'a str,
+        _user: Option<&'a User>,
+    ) -> BoxFuture<'a, Result<bool, String>> {
+        Box::pin(async move {
+            // un bind con password vuota è un bind anonimo, che molti server accettano
+            if password.is_empty() {
+                return Ok(false);
+            }
+
+            let dn = self.bind_dn(username);
+            debug!("Binding to LDAP directory");
+            tokio::time::timeout(LDAP_TIMEOUT, self.bind(&dn, password))
+                .await
+                .unwrap_or_else(|_| Err("LDAP bind timed out".to_string()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Directory finta che accetta solo `password` come password e risponde
+    /// con il result code indicato negli altri casi
+    async fn start_mock_directory(failure_code: u8) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let mut buffer = vec![0u8; 1024];
+                    let n = socket.read(&mut buffer).await.unwrap();
+                    let accepted = buffer[..n].ends_with(b"password");
+                    let code = if accepted { 0 } else { failure_code };
+                    // messageID 1, BindResponse con resultCode, matchedDN e diagnosticMessage vuoti
+                    let response = [
+                        0x30, 0x0c, 0x02, 0x01, 0x01, 0x61, 0x07, 0x0a, 0x01, code, 0x04, 0x00,
+                        0x04, 0x00,
+                    ];
+                    socket.write_all(&response).await.unwrap();
+                });
+            }
+        });
+        port
+    }
+
+    fn local_directory(port: u16) -> LdapAuthProvider {
+        LdapAuthProvider::new(&LdapConfig {
+            url: format!("ldap://127.0.0.1:{}", port),
+            bind_dn_template: "uid={username},ou=people,dc=example,dc=org".to_string(),
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_ldap_bind() {
+        let port = start_mock_directory(LDAP_INVALID_CREDENTIALS as u8).await;
+        let provider = local_directory(port);
+
+        assert!(
+            provider
+                .authenticate("alice", "password", None)
+                .await
+                .unwrap()
+        );
+        assert!(!provider.authenticate("alice", "wrong", None).await.unwrap());
+        // il bind anonimo non arriva nemmeno alla directory
+        assert!(!provider.authenticate("alice", "", None).await.unwrap());
+
+        // un errore della directory non è un rifiuto delle credenziali
+        let port = start_mock_directory(52).await;
+        assert!(
+            local_directory(port)
+                .authenticate("alice", "wrong", None)
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_config_and_dn_escaping() {
+        let provider = local_directory(389);
+        assert_eq!(
+            provider.bind_dn("evil,ou=admins"),
+            "uid=evil\\2cou\\3dadmins,ou=people,dc=example,dc=org"
+        );
+        assert_eq!(
+            provider.bind_dn("#a b "),
+            "uid=\\23a b\\20,ou=people,dc=example,dc=org"
+        );
+
+        assert!(
+            LdapAuthProvider::new(&LdapConfig {
+                url: "ldaps://directory.example.org".to_string(),
+                bind_dn_template: "cn={username},dc=example,dc=org".to_string(),
+            })
+            .is_ok()
+        );
+
+        assert!(
+            LdapAuthProvider::new(&LdapConfig {
+                url: "http://directory.example.org".to_string(),
+                bind_dn_template: "cn={username}".to_string(),
+            })
+            .is_err()
+        );
+        assert!(
+            LdapAuthProvider::new(&LdapConfig {
+                url: "ldap://directory.example.org".to_string(),
+                bind_dn_template: "cn=fixed".to_string(),
+            })
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,218 @@
+//! Lockout - Protezione del login da attacchi a forza bruta
+//!
+//! I tentativi di login falliti sono contati per coppia (username, indirizzo IP): dopo
+//! `max_failures` errori consecutivi la coppia viene bloccata, e ogni ulteriore errore
+//! raddoppia la durata del blocco fino a `max_lockout`. Un login riuscito azzera il
+//! conteggio. Il volume complessivo di richieste per IP è limitato dal rate limiting.
+//! Lo stato è in memoria e vale per la singola istanza del server.
+
+use crate::core::config::LoginLockoutConfig;
+use dashmap::DashMap;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Oltre questo numero di coppie tracciate vengono scartate quelle inattive
+const MAX_TRACKED_KEYS: usize = 10_000;
+
+/// Tentativi falliti di una coppia (username, IP)
+struct FailedAttempts {
+    /// Errori consecutivi dall'ultimo login riuscito
+    failures: u32,
+    last_failure: Instant,
+    locked_until: Option<Instant>,
+}
+
+/// Contatore dei login falliti con blocco temporaneo a backoff esponenziale
+pub struct LoginThrottle {
+    max_failures: u32,
+    base_lockout: Duration,
+    max_lockout: Duration,
+    attempts: DashMap<(String, IpAddr), FailedAttempts>,
+}
+
+impl LoginThrottle {
+    /// Con `max_failures` uguale a 0 il blocco è disabilitato
+    pub fn new(config: &LoginLockoutConfig) -> Self {
+        Self {
+            max_failures: config.max_failures,
+            base_lockout: Duration::from_secs(config.lockout_secs),
+            max_lockout: Duration::from_secs(config.max_lockout_secs),
+            attempts: DashMap::new(),
+        }
+    }
+
+    /// Verifica se la coppia (username, IP) può tentare il login
+    ///
+    /// # Returns
+    /// * `Ok(())` se il login è consentito
+    /// * `Err(Duration)` - Tempo rimanente prima della fine del blocco
+    pub fn check(&self, username: &str, ip: IpAddr) -> Result<(), Duration> {
+        self.check_at(username, ip, Instant::now())
+    }
+
+    /// Registra un login fallito
+    ///
+    /// # Returns
+    /// Durata del blocco se questo errore l'ha fatto scattare
+    pub fn record_failure(&self, username: &str, ip: IpAddr) -> Option<Duration> {
+        self.record_failure_at(username, ip, Instant::now())
+    }
+
+    /// Registra un login riuscito, azzerando i tentativi falliti
+    pub fn record_success(&self, username: &str, ip: IpAddr) {
+        self.attempts.remove(&(username.to_lowercase(), ip));
+    }
+
+    fn check_at(&self, username: &str, ip: IpAddr, now: Instant) -> Result<(), Duration> {
+        match self
+            .attempts
+            .get(&(username.to_lowercase(), ip))
+            .and_then(|attempts| attempts.locked_until)
+        {
+            Some(locked_until) if locked_until > now => Err(locked_until - now),
+            _ => Ok(()),
+        }
+    }
+
+    fn record_failure_at(&self, username: &str, ip: IpAddr, now: Instant) -> Option<Duration> {
+        if self.max_failures == 0 {
+            return None;
+        }
+        let key = (username.to_lowercase(), ip);
+
+        if !self.attempts.contains_key(&key) && self.attempts.len() >= MAX_TRACKED_KEYS {
+            self.attempts
+                .retain(|_, attempts| !self.is_expired(attempts, now));
+        }
+
+        let mut attempts = self.attempts.entry(key).or_insert(FailedAttempts {
+            failures: 0,
+            last_failure: now,
+            locked_until: None,
+        });
+        // dopo un lungo periodo senza errori si riparte da zero
+        if self.is_expired(&attempts, now) {
+            attempts.failures = 0;
+            attempts.locked_until = None;
+        }
+        attempts.failures += 1;
+        attempts.last_failure = now;
+
+        if attempts.failures < self.max_failures {
+            return None;
+        }
+        let lockout = self.lockout_for(attempts.failures);
+        attempts.locked_until = Some(now + lockout);
+        warn!(
+            username = %username,
+            ip = %ip,
+            failures = attempts.failures,
+            lockout_secs = lockout.as_secs(),
+            "Login locked out after repeated failures"
+        );
+        Some(lockout)
+    }
+
+    /// Durata del blocco: `base_lockout` al raggiungimento di `max_failures`,
+    /// raddoppiata ad ogni errore successivo fino a `max_lockout`
+    fn lockout_for(&self, failures: u32) -> Duration {
+        let doublings = (failures - self.max_failures).min(31);
+        self.base_lockout
+            .saturating_mul(1 << doublings)
+            .min(self.max_lockout)
+    }
+
+    /// Tentativi non più rilevanti: nessun errore per un intero `max_lockout` dopo il blocco
+    fn is_expired(&self, attempts: &FailedAttempts, now: Instant) -> bool {
+        let idle_since = attempts.locked_until.unwrap_or(attempts.last_failure);
+        now.saturating_duration_since(idle_since) > self.max_lockout
+    }
+}
+
+impl Default for LoginThrottle {
+    fn default() -> Self {
+        Self::new(&LoginLockoutConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    fn throttle() -> LoginThrottle {
+        LoginThrottle::new(&LoginLockoutConfig {
+            max_failures: 3,
+            lockout_secs: 30,
+            max_lockout_secs: 100,
+        })
+    }
+
+    #[test]
+    fn test_lockout_with_exponential_backoff() {
+        let throttle = throttle();
+        let ip = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7));
+        let start = Instant::now();
+
+        assert_eq!(throttle.record_failure_at("alice", ip, start), None);
+        assert_eq!(throttle.record_failure_at("alice", ip, start), None);
+        assert!(throttle.check_at("alice", ip, start).is_ok());
+
+        // terzo errore: blocco di 30 secondi
+        assert_eq!(
+            throttle.record_failure_at("alice", ip, start),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            throttle.check_at("Alice", ip, start + Duration::from_secs(10)),
+            Err(Duration::from_secs(20))
+        );
+
+        // altri utenti e altri IP non sono bloccati
+        assert!(throttle.check_at("bob", ip, start).is_ok());
+        let other_ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        assert!(throttle.check_at("alice", other_ip, start).is_ok());
+
+        // finito il blocco, ogni errore raddoppia la durata fino al massimo
+        let later = start + Duration::from_secs(31);
+        assert!(throttle.check_at("alice", ip, later).is_ok());
+        assert_eq!(
+            throttle.record_failure_at("alice", ip, later),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(
+            throttle.record_failure_at("alice", ip, later),
+            Some(Duration::from_secs(100))
+        );
+
+        // un login riuscito azzera il conteggio
+        throttle.record_success("alice", ip);
+        assert!(throttle.check_at("alice", ip, later).is_ok());
+        assert_eq!(throttle.record_failure_at("alice", ip, later), None);
+    }
+
+    #[test]
+    fn test_failures_expire() {
+        let throttle = throttle();
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let start = Instant::now();
+
+        throttle.record_failure_at("alice", ip, start);
+        throttle.record_failure_at("alice", ip, start);
+
+        // dopo max_lockout senza errori il conteggio riparte
+        let later = start + Duration::from_secs(101);
+        assert_eq!(throttle.record_failure_at("alice", ip, later), None);
+
+        // blocco disabilitato
+        let disabled = LoginThrottle::new(&LoginLockoutConfig {
+            max_failures: 0,
+            ..LoginLockoutConfig::default()
+        });
+        for _ in 0..10 {
+            assert_eq!(disabled.record_failure("alice", ip), None);
+        }
+        assert!(disabled.check("alice", ip).is_ok());
+    }
+}
@@ -2,17 +2,77 @@
 //!
 //! Questo modulo contiene tutti i componenti "core" dell'applicazione:
 //! - Autenticazione e JWT
-//! - Configurazione
+//! - Configurazione, anche da file TOML o YAML, con i segreti da file o da Vault
 //! - Gestione errori
 //! - Stato applicazione
+//! - Storage degli allegati
+//! - Lista di revoca dei token
+//! - Cache chiave/valore in memoria o condivisa su Redis
+//! - Autenticazione tramite directory LDAP
+//! - Rate limiting delle richieste HTTP
+//! - Blocco del login dopo troppi tentativi falliti
+//! - Policy delle password
+//! - Hash delle password con algoritmo configurabile
+//! - Pubblicazione degli eventi di dominio su NATS o Kafka
+//! - Statistiche del server campionate per gli amministratori
+//! - Filtro dei contenuti dei messaggi
+//! - Rilevamento di flood e spam nelle chat
+//! - HTTPS/WSS nativi con certificati da file o via ACME
+//! - Politica CORS per i client nel browser
+//! - Limiti di dimensione del corpo delle richieste
+//! - Deduplicazione delle richieste ripetute con `Idempotency-Key`
+//! - GET condizionali con ETag sulle liste
+//! - Versioning delle route dell'API e percorsi legacy deprecati
+//! - Ricaricamento a caldo di livello di log, rate limit e feature flag
 
+pub mod acme;
 pub mod auth;
+pub mod body_limit;
+pub mod cache;
 pub mod config;
+pub mod config_file;
+pub mod content_filter;
+pub mod cors;
 pub mod error;
+pub mod etag;
+pub mod event_bus;
+pub mod flood;
+pub mod idempotency;
+pub mod ldap;
+pub mod lockout;
+pub mod password_hash;
+pub mod password_policy;
+pub mod rate_limit;
+pub mod reload;
+pub mod revocation;
+pub mod secrets;
+pub mod server_stats;
 pub mod state;
+pub mod storage;
+pub mod tls;
+pub mod versioning;
 
 // Re-exports per facilitare l'import
-pub use auth::{authentication_middleware, chat_membership_middleware, encode_jwt, require_role};
+pub use auth::{
+    AuthProvider, JwtKeys, Scope, ServerRole, authentication_middleware, build_auth_provider,
+    chat_membership_middleware, encode_jwt, generate_refresh_token, has_permission,
+    hash_refresh_token, require_permission, require_role, scope_middleware,
+};
+pub use body_limit::{MULTIPART_OVERHEAD_BYTES, body_limit_middleware};
+pub use cache::{SharedCache, build_shared_cache};
 pub use config::Config;
+pub use content_filter::{ContentFilter, FilterVerdict, build_content_filter};
+pub use cors::build_cors_layer;
 pub use error::AppError;
+pub use etag::etag_middleware;
+pub use event_bus::{DomainEvent, EventBus, build_event_bus};
+pub use idempotency::idempotency_middleware;
+pub use password_hash::PasswordHasher;
+pub use rate_limit::{ClientIp, ip_rate_limit_middleware};
+pub use reload::{LogLevel, ReloadableConfig, log_filter, reload_config, start_sighup_reloader};
+pub use revocation::{RevocationStore, build_revocation_store};
+pub use server_stats::{STATS_SAMPLE_INTERVAL, ServerStats, start_stats_sampler};
 pub use state::AppState;
+pub use storage::{AttachmentStorage, build_storage};
+pub use tls::{TlsListener, start_tls};
+pub use versioning::{API_V1_PREFIX, legacy_path_gate_middleware, legacy_path_middleware};
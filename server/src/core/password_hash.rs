@@ -0,0 +1,173 @@
+//! Password hashing - Algoritmo e parametri configurabili per l'hash delle password
+//!
+//! Le nuove password usano l'algoritmo configurato (bcrypt o Argon2id). La verifica
+//! riconosce l'algoritmo dall'hash salvato, così gli hash esistenti restano validi
+//! anche dopo un cambio di configurazione; al login successivo vengono ricalcolati
+//! con i parametri correnti (`needs_rehash`), senza bisogno di migrazioni.
+
+use crate::core::config::PasswordHashConfig;
+use argon2::password_hash::{PasswordHash, PasswordHasher as _, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+
+/// Algoritmo configurato, con i parametri già validati
+enum HashAlgorithm {
+    Bcrypt { cost: u32 },
+    Argon2id(Argon2<'static>),
+}
+
+/// Calcola gli hash delle nuove password con l'algoritmo configurato
+pub struct PasswordHasher {
+    algorithm: HashAlgorithm,
+}
+
+impl PasswordHasher {
+    /// Costruisce l'hasher verificando che i parametri siano accettati dall'algoritmo
+    pub fn new(config: &PasswordHashConfig) -> Result<Self, String> {
+        let algorithm = match *config {
+            PasswordHashConfig::Bcrypt { cost } => {
+                if !(4..=31).contains(&cost) {
+                    return Err("Invalid BCRYPT_COST: must be between 4 and 31".to_string());
+                }
+                HashAlgorithm::Bcrypt { cost }
+            }
+            PasswordHashConfig::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => {
+                let params = Params::new(memory_kib, iterations, parallelism, None)
+                    .map_err(|e| format!("Invalid Argon2 parameters: {}", e))?;
+                HashAlgorithm::Argon2id(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+            }
+        };
+        Ok(Self { algorithm })
+    }
+
+    /// Calcola l'hash della password
+    ///
+    /// # Returns
+    /// Hash in formato PHC (Argon2id) o modular crypt (bcrypt), che include algoritmo
+    /// e parametri, oppure la descrizione dell'errore
+    pub fn hash(&self, password: &str) -> Result<String, String> {
+        match &self.algorithm {
+            HashAlgorithm::Bcrypt { cost } => {
+                bcrypt::hash(password, *cost).map_err(|e| e.to_string())
+            }
+            HashAlgorithm::Argon2id(argon2) => {
+                let salt = SaltString::encode_b64(uuid::Uuid::new_v4().as_bytes())
+                    .map_err(|e| e.to_string())?;
+                argon2
+                    .hash_password(password.as_bytes(), &salt)
+                    .map(|hash| hash.to_string())
+                    .map_err(|e| e.to_string())
+            }
+        }
+    }
+
+    /// Verifica se l'hash salvato è stato calcolato con algoritmo o parametri diversi
+    /// da quelli configurati, e va quindi ricalcolato al prossimo login
+    pub fn needs_rehash(&self, hash: &str) -> bool {
+        match &self.algorithm {
+            HashAlgorithm::Bcrypt { cost } => bcrypt_cost(hash) != Some(*cost),
+            HashAlgorithm::Argon2id(argon2) => {
+                let Ok(parsed) = PasswordHash::new(hash) else {
+                    return true;
+                };
+                let configured = argon2.params();
+                parsed.algorithm != Algorithm::Argon2id.ident()
+                    || parsed.version != Some(Version::V0x13.into())
+                    || Params::try_from(&parsed).map_or(true, |params| {
+                        params.m_cost() != configured.m_cost()
+                            || params.t_cost() != configured.t_cost()
+                            || params.p_cost() != configured.p_cost()
+                    })
+            }
+        }
+    }
+}
+
+impl Default for PasswordHasher {
+    fn default() -> Self {
+        Self::new(&PasswordHashConfig::default()).expect("Default hashing parameters are valid")
+    }
+}
+
+/// Verifica la password con l'hash salvato, qualunque sia l'algoritmo che l'ha prodotto
+pub fn verify_password(password: &str, hash: &str) -> bool {
+    if hash.starts_with("$argon2") {
+        // algoritmo e parametri sono letti dall'hash stesso
+        PasswordHash::new(hash).is_ok_and(|parsed| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &parsed)
+                .is_ok()
+        })
+    } else {
+        bcrypt::verify(password, hash).unwrap_or(false)
+    }
+}
+
+/// Costo di un hash bcrypt (`$2b$<cost>$...`), None se non è un hash bcrypt
+fn bcrypt_cost(hash: &str) -> Option<u32> {
+    let mut parts = hash.split('$');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(""), Some(version), Some(cost)) if version.starts_with('2') => cost.parse().ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argon2_config(memory_kib: u32) -> PasswordHashConfig {
+        PasswordHashConfig::Argon2id {
+            memory_kib,
+            iterations: 1,
+            parallelism: 1,
+        }
+    }
+
+    #[test]
+    fn test_hash_and_verify() {
+        let bcrypt = PasswordHasher::new(&PasswordHashConfig::Bcrypt { cost: 4 }).unwrap();
+        let argon2 = PasswordHasher::new(&argon2_config(64)).unwrap();
+
+        let bcrypt_hash = bcrypt.hash("Correct7Horse").unwrap();
+        let argon2_hash = argon2.hash("Correct7Horse").unwrap();
+        assert!(bcrypt_hash.starts_with("$2b$04$"));
+        assert!(argon2_hash.starts_with("$argon2id$v=19$m=64,t=1,p=1$"));
+
+        // la verifica non dipende dall'algoritmo configurato
+        for hash in [&bcrypt_hash, &argon2_hash] {
+            assert!(verify_password("Correct7Horse", hash));
+            assert!(!verify_password("Wrong7Horse", hash));
+        }
+        assert!(!verify_password("Correct7Horse", "not-a-hash"));
+    }
+
+    #[test]
+    fn test_needs_rehash() {
+        let bcrypt = PasswordHasher::new(&PasswordHashConfig::Bcrypt { cost: 4 }).unwrap();
+        let stronger_bcrypt = PasswordHasher::new(&PasswordHashConfig::Bcrypt { cost: 5 }).unwrap();
+        let argon2 = PasswordHasher::new(&argon2_config(64)).unwrap();
+        let stronger_argon2 = PasswordHasher::new(&argon2_config(128)).unwrap();
+
+        let bcrypt_hash = bcrypt.hash("Correct7Horse").unwrap();
+        let argon2_hash = argon2.hash("Correct7Horse").unwrap();
+
+        assert!(!bcrypt.needs_rehash(&bcrypt_hash));
+        assert!(stronger_bcrypt.needs_rehash(&bcrypt_hash));
+        assert!(argon2.needs_rehash(&bcrypt_hash));
+
+        assert!(!argon2.needs_rehash(&argon2_hash));
+        assert!(stronger_argon2.needs_rehash(&argon2_hash));
+        assert!(bcrypt.needs_rehash(&argon2_hash));
+    }
+
+    #[test]
+    fn test_invalid_parameters() {
+        assert!(PasswordHasher::new(&PasswordHashConfig::Bcrypt { cost: 3 }).is_err());
+        assert!(PasswordHasher::new(&argon2_config(0)).is_err());
+        assert!(PasswordHasher::new(&PasswordHashConfig::default()).is_ok());
+    }
+}
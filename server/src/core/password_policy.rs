@@ -0,0 +1,151 @@
+//! Password policy - Requisiti delle password scelte dagli utenti
+//!
+//! La policy (lunghezza minima, classi di caratteri, rifiuto delle password più comuni)
+//! è letta dalla configurazione e applicata alla registrazione. Le violazioni sono
+//! restituite come errori di validazione sul campo, uno per regola non rispettata.
+//! Le regole si possono sostituire a caldo e valgono per le registrazioni successive.
+
+use crate::core::config::PasswordPolicyConfig;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::sync::RwLock;
+use validator::{ValidationError, ValidationErrors};
+
+lazy_static::lazy_static! {
+    /// Password trapelate più diffuse, confrontate in modo esatto
+    static ref COMMON_PASSWORDS: HashSet<&'static str> =
+        include_str!("common_passwords.txt").lines().collect();
+}
+
+/// Policy delle password, costruita dalla configurazione
+pub struct PasswordPolicy {
+    rules: RwLock<PasswordPolicyConfig>,
+}
+
+impl PasswordPolicy {
+    pub fn new(config: &PasswordPolicyConfig) -> Self {
+        Self {
+            rules: RwLock::new(config.clone()),
+        }
+    }
+
+    /// Sostituisce le regole, ad esempio dopo il ricaricamento della configurazione
+    pub fn apply(&self, config: &PasswordPolicyConfig) {
+        *self.rules.write().unwrap() = config.clone();
+    }
+
+    /// Regole attualmente in vigore
+    pub fn config(&self) -> PasswordPolicyConfig {
+        self.rules.read().unwrap().clone()
+    }
+
+    /// Verifica la password e aggiunge a `errors` una violazione per ogni regola
+    /// non rispettata, sotto il campo `field`
+    pub fn check(&self, field: &'static str, password: &str, errors: &mut ValidationErrors) {
+        let rules = self.config();
+        if password.chars().count() < rules.min_length {
+            errors.add(
+                field,
+                violation(
+                    "password_too_short",
+                    format!("Password must be at least {} characters", rules.min_length),
+                ),
+            );
+        }
+        if rules.require_mixed_case
+            && !(password.chars().any(char::is_uppercase)
+                && password.chars().any(char::is_lowercase))
+        {
+            errors.add(
+                field,
+                violation(
+                    "password_case",
+                    "Password must contain at least one uppercase and one lowercase letter",
+                ),
+            );
+        }
+        if rules.require_digit && !password.chars().any(char::is_numeric) {
+            errors.add(
+                field,
+                violation(
+                    "password_digit",
+                    "Password must contain at least one number",
+                ),
+            );
+        }
+        if rules.reject_common && COMMON_PASSWORDS.contains(password) {
+            errors.add(
+                field,
+                violation(
+                    "password_common",
+                    "Password is too common, choose a less predictable one",
+                ),
+            );
+        }
+    }
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self::new(&PasswordPolicyConfig::default())
+    }
+}
+
+fn violation(code: &'static str, message: impl Into<Cow<'static, str>>) -> ValidationError {
+    ValidationError::new(code).with_message(message.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn violations(policy: &PasswordPolicy, password: &str) -> Vec<String> {
+        let mut errors = ValidationErrors::new();
+        policy.check("password", password, &mut errors);
+        errors
+            .field_errors()
+            .get("password")
+            .map(|errors| errors.iter().map(|e| e.code.to_string()).collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_default_policy() {
+        let policy = PasswordPolicy::default();
+
+        assert!(violations(&policy, "Correct7Horse").is_empty());
+        assert_eq!(violations(&policy, "Pass1"), vec!["password_too_short"]);
+        assert_eq!(violations(&policy, "password99"), vec!["password_case"]);
+        assert_eq!(violations(&policy, "PasswordOnly"), vec!["password_digit"]);
+        assert_eq!(violations(&policy, "Password1"), vec!["password_common"]);
+
+        // tutte le violazioni sono riportate insieme
+        assert_eq!(
+            violations(&policy, "qwerty"),
+            vec![
+                "password_too_short",
+                "password_case",
+                "password_digit",
+                "password_common"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_configured_policy() {
+        let policy = PasswordPolicy::new(&PasswordPolicyConfig {
+            min_length: 12,
+            require_mixed_case: false,
+            require_digit: false,
+            reject_common: false,
+        });
+
+        assert!(violations(&policy, "correct horse battery").is_empty());
+        assert!(violations(&policy, "qwertyuiop12").is_empty());
+        assert_eq!(violations(&policy, "Password1"), vec!["password_too_short"]);
+
+        // le nuove regole valgono dal controllo successivo
+        policy.apply(&PasswordPolicyConfig::default());
+        assert_eq!(violations(&policy, "Password1"), vec!["password_common"]);
+    }
+}
@@ -0,0 +1,267 @@
+//! Rate limiting - Limite di richieste HTTP al minuto
+//!
+//! Ogni chiave (indirizzo IP del client per le route `/auth/*`, utente autenticato per
+//! tutte le altre) ha un token bucket con capacità pari al limite al minuto, ricaricato
+//! in modo continuo. Oltre il limite la richiesta riceve 429 con l'header `Retry-After`.
+//! Lo stato è in memoria e vale per la singola istanza del server; i limiti si possono
+//! cambiare a caldo con `RateLimits::apply` (ricaricamento della configurazione).
+
+use crate::core::AppState;
+use crate::core::config::RateLimitConfig;
+use crate::core::error::AppError;
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, FromRequestParts, Request, State},
+    http::{Extensions, HeaderMap, Response, request::Parts},
+    middleware::Next,
+};
+use dashmap::DashMap;
+use std::convert::Infallible;
+use std::hash::Hash;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Oltre questo numero di chiavi tracciate vengono scartati i bucket già ricaricati del tutto
+const MAX_TRACKED_KEYS: usize = 10_000;
+
+/// Token bucket di una singola chiave
+struct Bucket {
+    tokens: f64,
+    refreshed_at: Instant,
+}
+
+/// Limite di richieste al minuto per chiave (IP o utente); con limite 0 è disabilitato
+pub struct RateLimiter<K> {
+    per_minute: AtomicU32,
+    buckets: DashMap<K, Bucket>,
+}
+
+impl<K: Eq + Hash + Clone> RateLimiter<K> {
+    pub fn new(per_minute: u32) -> Self {
+        Self {
+            per_minute: AtomicU32::new(per_minute),
+            buckets: DashMap::new(),
+        }
+    }
+
+    pub fn per_minute(&self) -> u32 {
+        self.per_minute.load(Ordering::Relaxed)
+    }
+
+    /// Cambia il limite; i bucket esistenti si adeguano alla nuova capacità al prossimo uso
+    pub fn set_per_minute(&self, per_minute: u32) {
+        self.per_minute.store(per_minute, Ordering::Relaxed);
+    }
+
+    /// Consuma un token per la chiave indicata
+    ///
+    /// # Returns
+    /// * `Ok(())` se la richiesta rientra nel limite
+    /// * `Err(Duration)` - Tempo da attendere prima che sia disponibile un nuovo token
+    pub fn check(&self, key: &K) -> Result<(), Duration> {
+        let per_minute = self.per_minute();
+        if per_minute == 0 {
+            return Ok(());
+        }
+        let capacity = per_minute as f64;
+        let refill_per_sec = capacity / 60.0;
+        let now = Instant::now();
+
+        if !self.buckets.contains_key(key) && self.buckets.len() >= MAX_TRACKED_KEYS {
+            // un bucket pieno equivale a una chiave mai vista: si può dimenticare
+            self.buckets.retain(|_, bucket| {
+                let elapsed = now.duration_since(bucket.refreshed_at).as_secs_f64();
+                bucket.tokens + elapsed * refill_per_sec < capacity
+            });
+        }
+
+        let mut bucket = self.buckets.entry(key.clone()).or_insert(Bucket {
+            tokens: capacity,
+            refreshed_at: now,
+        });
+        let elapsed = now.duration_since(bucket.refreshed_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill_per_sec).min(capacity);
+        bucket.refreshed_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / refill_per_sec,
+            ))
+        }
+    }
+}
+
+/// Limiti di richieste dell'applicazione, costruiti dalla configurazione
+pub struct RateLimits {
+    /// Richieste al minuto per indirizzo IP sulle route `/auth/*`
+    pub auth: RateLimiter<IpAddr>,
+
+    /// Richieste al minuto per utente sulle route autenticate
+    pub user: RateLimiter<i32>,
+
+    /// Se true l'IP del client è letto da `X-Forwarded-For` (server dietro reverse proxy)
+    trust_forwarded_for: AtomicBool,
+}
+
+impl RateLimits {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        Self {
+            auth: RateLimiter::new(config.auth_per_minute),
+            user: RateLimiter::new(config.user_per_minute),
+            trust_forwarded_for: AtomicBool::new(config.trust_forwarded_for),
+        }
+    }
+
+    /// Applica nuovi limiti senza azzerare i bucket delle chiavi già tracciate
+    pub fn apply(&self, config: &RateLimitConfig) {
+        self.auth.set_per_minute(config.auth_per_minute);
+        self.user.set_per_minute(config.user_per_minute);
+        self.trust_forwarded_for
+            .store(config.trust_forwarded_for, Ordering::Relaxed);
+    }
+
+    /// Limiti attualmente in vigore
+    pub fn config(&self) -> RateLimitConfig {
+        RateLimitConfig {
+            auth_per_minute: self.auth.per_minute(),
+            user_per_minute: self.user.per_minute(),
+            trust_forwarded_for: self.trust_forwarded_for.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Indirizzo IP del client che ha inviato la richiesta
+    ///
+    /// Senza `ConnectInfo` (es. nei test) tutte le richieste condividono lo stesso bucket
+    fn client_ip(&self, headers: &HeaderMap, extensions: &Extensions) -> IpAddr {
+        let forwarded = if self.trust_forwarded_for.load(Ordering::Relaxed) {
+            forwarded_for(headers)
+        } else {
+            None
+        };
+        forwarded
+            .or_else(|| {
+                extensions
+                    .get::<ConnectInfo<SocketAddr>>()
+                    .map(|ConnectInfo(addr)| addr.ip())
+            })
+            .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED))
+    }
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        Self::new(&RateLimitConfig::default())
+    }
+}
+
+/// Primo indirizzo dell'header `X-Forwarded-For`, cioè il client originale
+fn forwarded_for(headers: &HeaderMap) -> Option<IpAddr> {
+    headers
+        .get("x-forwarded-for")?
+        .to_str()
+        .ok()?
+        .split(',')
+        .next()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Extractor dell'indirizzo IP del client, con le stesse regole del rate limiting
+pub struct ClientIp(pub IpAddr);
+
+impl FromRequestParts<Arc<AppState>> for ClientIp {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        Ok(ClientIp(
+            state
+                .rate_limits
+                .client_ip(&parts.headers, &parts.extensions),
+        ))
+    }
+}
+
+/// Middleware che limita le richieste per indirizzo IP (route `/auth/*`)
+pub async fn ip_rate_limit_middleware(
+    State(state): State<Arc<AppState>>,
+    req: Request,
+    next: Next,
+) -> Result<Response<Body>, AppError> {
+    let ip = state.rate_limits.client_ip(req.headers(), req.extensions());
+    if let Err(retry_after) = state.rate_limits.auth.check(&ip) {
+        warn!("Rate limit exceeded for {} on {}", ip, req.uri().path());
+        return Err(AppError::too_many_requests(
+            "Too many requests",
+            retry_after,
+        ));
+    }
+    Ok(next.run(req).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_rate_limiter_blocks_after_limit() {
+        let limiter = RateLimiter::new(3);
+
+        for _ in 0..3 {
+            assert!(limiter.check(&1).is_ok());
+        }
+        // il quarto token arriva dopo circa 20 secondi (3 al minuto)
+        let retry_after = limiter.check(&1).unwrap_err();
+        assert!(retry_after > Duration::from_secs(19));
+        assert!(retry_after <= Duration::from_secs(20));
+
+        // le altre chiavi hanno il proprio bucket
+        assert!(limiter.check(&2).is_ok());
+
+        // limite 0: disabilitato
+        let unlimited = RateLimiter::new(0);
+        for _ in 0..100 {
+            assert!(unlimited.check(&1).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_rate_limiter_applies_new_limit() {
+        let limiter = RateLimiter::new(10);
+        assert!(limiter.check(&1).is_ok());
+
+        // i 9 token rimasti vengono ridotti alla nuova capacità
+        limiter.set_per_minute(2);
+        assert!(limiter.check(&1).is_ok());
+        assert!(limiter.check(&1).is_ok());
+        assert!(limiter.check(&1).is_err());
+
+        limiter.set_per_minute(0);
+        assert!(limiter.check(&1).is_ok());
+    }
+
+    #[test]
+    fn test_forwarded_for() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(forwarded_for(&headers), None);
+
+        headers.insert(
+            "x-forwarded-for",
+            HeaderValue::from_static("203.0.113.7, 10.0.0.1"),
+        );
+        assert_eq!(forwarded_for(&headers), "203.0.113.7".parse().ok());
+
+        headers.insert("x-forwarded-for", HeaderValue::from_static("not-an-ip"));
+        assert_eq!(forwarded_for(&headers), None);
+    }
+}
@@ -0,0 +1,151 @@
+//! Hot reload - Impostazioni cambiate senza riavviare il server
+//!
+//! Alla ricezione di SIGHUP, o con POST /admin/config/reload, la configurazione viene
+//! riletta con le stesse regole dell'avvio e ne vengono applicati solo i valori che si
+//! possono cambiare a caldo: livello di log, limiti di richieste e feature flag (percorsi
+//! legacy dell'API, requisiti delle password). Database, indirizzo, TLS, storage e il resto
+//! restano quelli dell'avvio, così come le connessioni WebSocket aperte.
+//!
+//! Le variabili d'ambiente di un processo non cambiano dopo l'avvio e il `.env` viene letto
+//! una volta sola: i valori da modificare a caldo vanno tenuti nel file di configurazione
+//! (`--config`), riletto ad ogni ricaricamento.
+
+use crate::core::AppState;
+use crate::core::config::{Config, PasswordPolicyConfig, RateLimitConfig};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::{EnvFilter, Registry, reload};
+
+/// Handle del filtro dei log installato in `main`, per cambiarlo a runtime
+pub type LogFilterHandle = reload::Handle<EnvFilter, Registry>;
+
+/// Filtro dei log per il livello indicato; `RUST_LOG`, se impostata, ha la precedenza
+pub fn log_filter(log_level: &str) -> EnvFilter {
+    EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| format!("server={},tower_http=debug", log_level).into())
+}
+
+/// Livello di log corrente e filtro su cui applicarne uno nuovo
+pub struct LogLevel {
+    level: RwLock<String>,
+    /// None finché `main` non installa il subscriber (ad esempio nei test)
+    handle: Option<LogFilterHandle>,
+}
+
+impl LogLevel {
+    pub fn new(level: &str, handle: Option<LogFilterHandle>) -> Self {
+        Self {
+            level: RwLock::new(level.to_string()),
+            handle,
+        }
+    }
+
+    pub fn get(&self) -> String {
+        self.level.read().unwrap().clone()
+    }
+
+    /// Cambia il livello dei log del server (trace, debug, info, warn, error, off)
+    pub fn set(&self, level: &str) -> Result<(), String> {
+        LevelFilter::from_str(level).map_err(|_| format!("Invalid LOG_LEVEL: {}", level))?;
+        if let Some(handle) = &self.handle {
+            handle
+                .reload(log_filter(level))
+                .map_err(|e| format!("Failed to reload log filter: {}", e))?;
+        }
+        *self.level.write().unwrap() = level.to_string();
+        Ok(())
+    }
+}
+
+impl Default for LogLevel {
+    fn default() -> Self {
+        Self::new("info", None)
+    }
+}
+
+/// Valori della configurazione che si possono cambiare senza riavvio
+#[derive(Debug, Clone)]
+pub struct ReloadableConfig {
+    pub log_level: String,
+    pub rate_limit: RateLimitConfig,
+    /// Feature flag: percorsi dell'API senza prefisso di versione
+    pub legacy_api_paths: bool,
+    /// Feature flag: requisiti delle password alla registrazione
+    pub password_policy: PasswordPolicyConfig,
+}
+
+impl From<&Config> for ReloadableConfig {
+    fn from(config: &Config) -> Self {
+        Self {
+            log_level: config.log_level.clone(),
+            rate_limit: config.rate_limit.clone(),
+            legacy_api_paths: config.legacy_api_paths,
+            password_policy: config.password_policy.clone(),
+        }
+    }
+}
+
+/// Rilegge la configurazione e ne applica i valori ricaricabili
+///
+/// # Returns
+/// * `Ok(ReloadableConfig)` - Valori ora in vigore
+/// * `Err(String)` - Configurazione non valida: lo stato resta invariato
+pub async fn reload_config(state: &AppState) -> Result<ReloadableConfig, String> {
+    let config = ReloadableConfig::from(&Config::from_env().await?);
+    state.apply_config(&config)?;
+    info!(
+        log_level = %config.log_level,
+        auth_per_minute = config.rate_limit.auth_per_minute,
+        user_per_minute = config.rate_limit.user_per_minute,
+        legacy_api_paths = config.legacy_api_paths,
+        "Configuration reloaded"
+    );
+    Ok(config)
+}
+
+/// Avvia il task che ricarica la configurazione ad ogni SIGHUP (solo sistemi Unix)
+pub fn start_sighup_reloader(state: Arc<AppState>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{SignalKind, signal};
+
+            let mut hangup = match signal(SignalKind::hangup()) {
+                Ok(hangup) => hangup,
+                Err(e) => {
+                    error!("Failed to install SIGHUP handler: {}", e);
+                    return;
+                }
+            };
+            while hangup.recv().await.is_some() {
+                if let Err(e) = reload_config(&state).await {
+                    error!(
+                        "Configuration reload failed, keeping current settings: {}",
+                        e
+                    );
+                }
+            }
+        }
+        #[cfg(not(unix))]
+        drop(state);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_level_rejects_unknown_level() {
+        let log_level = LogLevel::default();
+
+        assert!(log_level.set("debug").is_ok());
+        assert_eq!(log_level.get(), "debug");
+
+        assert!(log_level.set("verbose").is_err());
+        assert_eq!(log_level.get(), "debug");
+    }
+}
@@ -0,0 +1,200 @@
+//! Revocation - Lista dei token JWT revocati prima della scadenza
+//!
+//! `authentication_middleware` consulta la lista tramite il `jti` del token. Il backend
+//! di default è in memoria e vale solo per la singola istanza del server; con `REDIS_URL`
+//! la lista sta nella cache condivisa tra più istanze e sopravvive ai riavvii.
+
+use crate::core::cache::SharedCache;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use futures_util::future::BoxFuture;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, Ordering};
+
+/// Ogni quanti secondi al massimo la lista in memoria scarta le revoche scadute
+const PRUNE_INTERVAL_SECS: i64 = 60;
+
+/// Backend della lista di revoca
+pub trait TokenRevocationStore: Send + Sync {
+    /// Revoca il token `jti`; la revoca serve solo fino a `expires_at`, poi il token
+    /// verrebbe comunque rifiutato perché scaduto
+    ///
+    /// # Returns
+    /// * `Err(String)` - Descrizione dell'errore del backend (solo per i log)
+    fn revoke<'a>(
+        &'a self,
+        jti: &'a str,
+        expires_at: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<(), String>>;
+
+    /// Verifica se il token `jti` è stato revocato
+    fn is_revoked<'a>(&'a self, jti: &'a str) -> BoxFuture<'a, Result<bool, String>>;
+}
+
+/// Lista di revoca condivisa dagli handler, clonabile a costo zero
+pub type RevocationStore = Arc<dyn TokenRevocationStore>;
+
+/// Costruisce la lista di revoca: sulla cache condivisa se c'è, altrimenti in memoria
+///
+/// La cache in memoria dei repository non va usata qui: può scartare le voci quando è
+/// piena, e una revoca dimenticata renderebbe di nuovo valido il token.
+pub fn build_revocation_store(shared_cache: Option<SharedCache>) -> RevocationStore {
+    match shared_cache {
+        Some(cache) => Arc::new(SharedRevocationStore::new(cache)),
+        None => Arc::new(InMemoryRevocationStore::new()),
+    }
+}
+
+/// Lista di revoca in memoria, per una singola istanza del server
+#[derive(Default)]
+pub struct InMemoryRevocationStore {
+    // Key: jti, Value: scadenza del token revocato
+    revoked: DashMap<String, DateTime<Utc>>,
+    // timestamp (secondi) dell'ultima pulizia delle revoche scadute
+    last_prune: AtomicI64,
+}
+
+impl InMemoryRevocationStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Scarta le revoche di token già scaduti, al più una volta ogni `PRUNE_INTERVAL_SECS`:
+    /// così una raffica di logout non scorre tutta la lista a ogni revoca
+    fn prune_expired(&self, now: DateTime<Utc>) {
+        let last_prune = self.last_prune.load(Ordering::Relaxed);
+        if now.timestamp() - last_prune < PRUNE_INTERVAL_SECS {
+            return;
+        }
+        // con più revoche concorrenti solo una esegue la pulizia
+        if self
+            .last_prune
+            .compare_exchange(
+                last_prune,
+                now.timestamp(),
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            )
+            .is_ok()
+        {
+            self.revoked.retain(|_, expires_at| *expires_at > now);
+        }
+    }
+}
+
+impl TokenRevocationStore for InMemoryRevocationStore {
+    fn revoke<'a>(
+        &'a self,
+        jti: &'a str,
+        expires_at: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<(), String>> {
+        Box::pin(async move {
+            // le revoche di token già scaduti non servono più
+            self.prune_expired(Utc::now());
+            self.revoked.insert(jti.to_string(), expires_at);
+            Ok(())
+        })
+    }
+
+    fn is_revoked<'a>(&'a self, jti: &'a str) -> BoxFuture<'a, Result<bool, String>> {
+        Box::pin(async move {
+            Ok(self
+                .revoked
+                .get(jti)
+                .is_some_and(|expires_at| *expires_at > Utc::now()))
+        })
+    }
+}
+
+/// Lista di revoca sulla cache condivisa: ogni token revocato è una chiave
+/// `revoked_jwt:{jti}` con TTL pari alla vita residua del token
+pub struct SharedRevocationStore {
+    cache: SharedCache,
+}
+
+impl SharedRevocationStore {
+    pub fn new(cache: SharedCache) -> Self {
+        Self { cache }
+    }
+
+    fn key(jti: &str) -> String {
+        format!("revoked_jwt:{}", jti)
+    }
+}
+
+impl TokenRevocationStore for SharedRevocationStore {
+    fn revoke<'a>(
+        &'a self,
+        jti: &'a str,
+        expires_at: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<(), String>> {
+        Box::pin(async move {
+            let Ok(ttl) = (expires_at - Utc::now()).to_std() else {
+                // token già scaduto, non serve revocarlo
+                return Ok(());
+            };
+            self.cache.set(&Self::key(jti), "1", ttl).await
+        })
+    }
+
+    fn is_revoked<'a>(&'a self, jti: &'a str) -> BoxFuture<'a, Result<bool, String>> {
+        Box::pin(async move { Ok(self.cache.get(&Self::key(jti)).await?.is_some()) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::cache::InMemoryCache;
+    use chrono::Duration as ChronoDuration;
+
+    #[tokio::test]
+    async fn test_in_memory_revocation() {
+        let store = InMemoryRevocationStore::new();
+        let expires_at = Utc::now() + ChronoDuration::minutes(15);
+
+        assert!(!store.is_revoked("jti-1").await.unwrap());
+        store.revoke("jti-1", expires_at).await.unwrap();
+        assert!(store.is_revoked("jti-1").await.unwrap());
+        assert!(!store.is_revoked("jti-2").await.unwrap());
+
+        // una revoca scaduta viene ignorata e rimossa alla prima pulizia successiva,
+        // che avviene al più una volta per intervallo
+        store
+            .revoke("jti-old", Utc::now() - ChronoDuration::minutes(1))
+            .await
+            .unwrap();
+        assert!(!store.is_revoked("jti-old").await.unwrap());
+        store.revoke("jti-3", expires_at).await.unwrap();
+        assert!(store.revoked.contains_key("jti-old"));
+
+        store
+            .last_prune
+            .fetch_sub(PRUNE_INTERVAL_SECS, Ordering::Relaxed);
+        store.revoke("jti-4", expires_at).await.unwrap();
+        assert!(!store.revoked.contains_key("jti-old"));
+        assert!(store.is_revoked("jti-3").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_shared_revocation() {
+        let cache: SharedCache = Arc::new(InMemoryCache::new(100));
+        let store = SharedRevocationStore::new(cache.clone());
+        let expires_at = Utc::now() + ChronoDuration::minutes(15);
+
+        store.revoke("jti-1", expires_at).await.unwrap();
+        assert!(store.is_revoked("jti-1").await.unwrap());
+        assert!(!store.is_revoked("jti-2").await.unwrap());
+        assert_eq!(
+            cache.get("revoked_jwt:jti-1").await.unwrap().as_deref(),
+            Some("1")
+        );
+
+        // un token già scaduto non viene scritto nella cache
+        store
+            .revoke("jti-old", Utc::now() - ChronoDuration::minutes(1))
+            .await
+            .unwrap();
+        assert!(cache.get("revoked_jwt:jti-old").await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,154 @@
+//! Server Stats - Statistiche del server per la dashboard di amministrazione
+//!
+//! I servizi incrementano solo un contatore atomico per ogni messaggio; un task in background
+//! campiona periodicamente contatore, utenti online e pool del database e pubblica l'ultimo
+//! campione su un canale `watch`, letto dagli stream SSE degli amministratori. Il costo del
+//! campionamento non dipende dal numero di dashboard aperte.
+
+use crate::core::AppState;
+use crate::dtos::{DbPoolStatsDTO, ServerStatsDTO};
+use chrono::Utc;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::debug;
+
+/// Intervallo tra due campioni delle statistiche
+pub const STATS_SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Finestra su cui si calcolano i messaggi al minuto
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Contatori aggiornati dai servizi e ultimo campione pubblicato
+pub struct ServerStats {
+    messages_total: AtomicU64,
+    /// None finché il task di campionamento non ha prodotto il primo campione
+    latest: watch::Sender<Option<ServerStatsDTO>>,
+}
+
+impl ServerStats {
+    pub fn new() -> Self {
+        Self {
+            messages_total: AtomicU64::new(0),
+            latest: watch::Sender::new(None),
+        }
+    }
+
+    /// Conta un messaggio salvato
+    pub fn record_message(&self) {
+        self.messages_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Ricevitore dei campioni: riceve subito l'ultimo pubblicato e poi ogni nuovo campione
+    pub fn subscribe(&self) -> watch::Receiver<Option<ServerStatsDTO>> {
+        self.latest.subscribe()
+    }
+
+    fn publish(&self, sample: ServerStatsDTO) {
+        // send_replace aggiorna il valore anche senza ricevitori, per chi si iscrive dopo
+        self.latest.send_replace(Some(sample));
+    }
+}
+
+/// Messaggi al minuto su una finestra mobile, a partire dai valori del contatore
+#[derive(Default)]
+struct MessageRate {
+    /// Campioni (istante, messaggi totali) dal più vecchio, entro la finestra
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl MessageRate {
+    fn per_minute(&mut self, now: Instant, total: u64) -> f64 {
+        self.samples.push_back((now, total));
+        // si tiene l'ultimo campione fuori dalla finestra come riferimento di partenza
+        while self
+            .samples
+            .get(1)
+            .is_some_and(|(at, _)| now.duration_since(*at) >= RATE_WINDOW)
+        {
+            self.samples.pop_front();
+        }
+
+        let (oldest_at, oldest_total) = self.samples[0];
+        let elapsed = now.duration_since(oldest_at).as_secs_f64();
+        if elapsed == 0.0 {
+            return 0.0;
+        }
+        (total - oldest_total) as f64 * 60.0 / elapsed
+    }
+}
+
+fn sample(state: &AppState, rate: &mut MessageRate) -> ServerStatsDTO {
+    let pool = state.health.pool_status();
+    let messages_total = state.server_stats.messages_total.load(Ordering::Relaxed);
+    ServerStatsDTO {
+        sampled_at: Utc::now(),
+        online_users: state.users_online.online_count(),
+        messages_per_minute: rate.per_minute(Instant::now(), messages_total),
+        db_pool: DbPoolStatsDTO::new(
+            pool.size,
+            pool.idle,
+            pool.min_connections,
+            pool.max_connections,
+        ),
+    }
+}
+
+/// Avvia il task che campiona le statistiche ogni `interval`
+pub fn start_stats_sampler(state: Arc<AppState>, interval: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut rate = MessageRate::default();
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let stats = sample(&state, &mut rate);
+            debug!(
+                online_users = stats.online_users,
+                messages_per_minute = stats.messages_per_minute,
+                db_pool_size = stats.db_pool.size,
+                db_pool_idle = stats.db_pool.idle,
+                db_pool_in_use = stats.db_pool.in_use,
+                db_pool_saturation = stats.db_pool.saturation,
+                "Server stats sampled"
+            );
+            state.server_stats.publish(stats);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_rate_first_sample_is_zero() {
+        let mut rate = MessageRate::default();
+        assert_eq!(rate.per_minute(Instant::now(), 42), 0.0);
+    }
+
+    #[test]
+    fn test_message_rate_scales_to_one_minute() {
+        let mut rate = MessageRate::default();
+        let start = Instant::now();
+        rate.per_minute(start, 100);
+        // 10 messaggi in 5 secondi = 120 al minuto
+        let per_minute = rate.per_minute(start + Duration::from_secs(5), 110);
+        assert!((per_minute - 120.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_message_rate_drops_samples_outside_window() {
+        let mut rate = MessageRate::default();
+        let start = Instant::now();
+        rate.per_minute(start, 0);
+        rate.per_minute(start + Duration::from_secs(30), 1_000);
+        rate.per_minute(start + Duration::from_secs(60), 1_000);
+        // il picco iniziale esce dalla finestra: nell'ultimo minuto nessun nuovo messaggio
+        let per_minute = rate.per_minute(start + Duration::from_secs(90), 1_000);
+        assert_eq!(per_minute, 0.0);
+        assert_eq!(rate.samples.len(), 3);
+    }
+}
@@ -3,34 +3,195 @@
 //! Contiene tutti i repository, configurazioni e stato condiviso
 //! necessario per gestire l'applicazione.
 
+use crate::core::{
+    AppError, AttachmentStorage, AuthProvider, ContentFilter, EventBus, RevocationStore,
+    ServerRole, ServerStats,
+};
+use crate::core::auth::{JwtKeys, LocalAuthProvider};
+use crate::core::cache::{InMemoryCache, SharedCache};
+use crate::core::config::{
+    AppProfile, CacheConfig, DEFAULT_MAX_ATTACHMENT_BYTES, DEFAULT_MAX_GROUP_MEMBERS,
+    DEFAULT_MAX_JSON_BODY_BYTES, FloodConfig, LoginLockoutConfig, PasswordPolicyConfig,
+    RateLimitConfig, WsConfig,
+};
+use crate::core::event_bus::DomainEvent;
+use crate::core::flood::FloodGuard;
+use crate::core::idempotency::IdempotencyStore;
+use crate::core::lockout::LoginThrottle;
+use crate::core::password_hash::PasswordHasher;
+use crate::core::password_policy::PasswordPolicy;
+use crate::core::rate_limit::RateLimits;
+use crate::core::reload::{LogLevel, ReloadableConfig};
+use crate::core::revocation::InMemoryRevocationStore;
 use crate::repositories::{
-    ChatRepository, InvitationRepository, MessageRepository, UserChatMetadataRepository,
-    UserRepository,
+    AttachmentRepository, AuditLogRepository, BannedMemberRepository, CachedUserChatMetadataRepo,
+    CachedUserRepo, ChatRepo, ChatRepository, ChatRoleRepository, DataExportRepository, DbPool,
+    DraftRepository, HealthRepository, InvitationRepository, InviteLinkRepository,
+    JoinRequestRepository, MessageRepo, MessageRepository, OfflineQueueRepository,
+    RefreshTokenRepository, ReportRepository, RepositoryCache, UnitOfWork, UserChatMetadataRepo,
+    UserChatMetadataRepository, UserIdentityRepository, UserKeysRepository, UserRepo,
+    UserRepository, WebhookRepository,
 };
+use crate::services::oidc::OidcClient;
+use crate::services::translation::TranslationProvider;
+use crate::ws::SharedPresence;
 use crate::ws::chatmap::ChatMap;
 use crate::ws::usermap::UserMap;
-use sqlx::MySqlPool;
+use object_store::memory::InMemory;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
 /// Stato globale dell'applicazione condiviso tra tutte le route e middleware
 pub struct AppState {
     /// Repository per la gestione degli utenti
-    pub user: UserRepository,
+    pub user: Arc<dyn UserRepo>,
 
     /// Repository per la gestione delle chat
-    pub chat: ChatRepository,
+    pub chat: Arc<dyn ChatRepo>,
 
     /// Repository per la gestione dei messaggi
-    pub msg: MessageRepository,
+    pub msg: Arc<dyn MessageRepo>,
 
     /// Repository per la gestione degli inviti
     pub invitation: InvitationRepository,
 
+    /// Repository per le richieste di accesso alle chat pubbliche
+    pub join_request: JoinRequestRepository,
+
+    /// Repository dei link di invito alle chat di gruppo
+    pub invite_link: InviteLinkRepository,
+
     /// Repository per la gestione dei metadati utente-chat
-    pub meta: UserChatMetadataRepository,
+    pub meta: Arc<dyn UserChatMetadataRepo>,
+
+    /// Repository per la gestione degli allegati
+    pub attachment: AttachmentRepository,
+
+    /// Repository per la gestione delle bozze
+    pub draft: DraftRepository,
+
+    /// Repository per la gestione degli utenti bannati
+    pub ban: BannedMemberRepository,
+
+    /// Repository per la gestione dei ruoli personalizzati delle chat
+    pub role: ChatRoleRepository,
+
+    /// Repository per il registro delle azioni amministrative
+    pub audit: AuditLogRepository,
+
+    /// Repository per i job di export dei dati personali
+    pub data_export: DataExportRepository,
+
+    /// Repository per i refresh token
+    pub refresh_token: RefreshTokenRepository,
+
+    /// Repository delle identità esterne (login SSO)
+    pub user_identity: UserIdentityRepository,
+
+    /// Repository dei webhook in ingresso
+    pub webhook: WebhookRepository,
+
+    /// Repository delle segnalazioni di messaggi e utenti
+    pub report: ReportRepository,
+
+    /// Repository delle chiavi pubbliche per la cifratura end-to-end
+    pub user_keys: UserKeysRepository,
+
+    /// Repository della coda di consegna dei messaggi per gli utenti offline
+    pub offline_queue: OfflineQueueRepository,
+
+    /// Controlli sul database per la sonda di readiness
+    pub health: HealthRepository,
+
+    /// Pool del database primario, usato solo per aprire le unit of work (`begin`)
+    db: DbPool,
+
+    /// Cache di utenti e appartenenze alle chat, None se disabilitata
+    pub repo_cache: Option<RepositoryCache>,
+
+    /// Cache condivisa tra le istanze del server (Redis), None con una singola istanza
+    pub shared_cache: Option<SharedCache>,
 
     /// Secret key per JWT token
     pub jwt_secret: String,
 
+    /// Chiavi che firmano e verificano i JWT (di default HS256 con `jwt_secret`)
+    pub jwt_keys: JwtKeys,
+
+    /// Profilo di esecuzione (`APP_ENV`), riportato dalle sonde di stato
+    pub profile: AppProfile,
+
+    /// Se il cookie del token porta l'attributo `Secure`
+    pub cookie_secure: bool,
+
+    /// Backend che verifica le credenziali al login (database locale o directory LDAP)
+    pub auth_provider: Arc<dyn AuthProvider>,
+
+    /// Lista degli access token revocati prima della scadenza, consultata ad ogni richiesta
+    pub revoked_tokens: RevocationStore,
+
+    /// Limiti di richieste al minuto per IP (route `/auth/*`) e per utente
+    pub rate_limits: RateLimits,
+
+    /// Tentativi di login falliti, per il blocco temporaneo dopo troppi errori
+    pub login_throttle: LoginThrottle,
+
+    /// Attività recente dei membri nelle chat, per silenziare automaticamente flood e spam
+    pub flood_guard: FloodGuard,
+
+    /// Risposte delle richieste con `Idempotency-Key`, riprodotte ai tentativi ripetuti
+    pub idempotency: IdempotencyStore,
+
+    /// Requisiti delle password scelte alla registrazione
+    pub password_policy: PasswordPolicy,
+
+    /// Algoritmo e parametri per l'hash delle nuove password
+    pub password_hasher: PasswordHasher,
+
+    /// Storage dei file allegati (disco locale o S3 compatibile)
+    pub storage: AttachmentStorage,
+
+    /// Dimensione massima accettata per un allegato, in byte
+    pub max_attachment_bytes: usize,
+
+    /// Dimensione massima del corpo delle richieste JSON, in byte
+    pub max_json_body_bytes: usize,
+
+    /// Se servire le route dell'API anche senza il prefisso di versione (percorsi deprecati)
+    legacy_api_paths: AtomicBool,
+
+    /// Livello di log corrente, modificabile ricaricando la configurazione
+    pub log_level: LogLevel,
+
+    /// Numero massimo di membri di una chat di gruppo, Owner compreso
+    pub max_group_members: usize,
+
+    /// Provider di traduzione automatica, None se non configurato
+    pub translator: Option<Arc<dyn TranslationProvider>>,
+
+    /// Client del provider OpenID Connect, None se il login SSO non è configurato
+    pub oidc: Option<Arc<OidcClient>>,
+
+    /// Coda verso il broker degli eventi di dominio, None se la pubblicazione è disabilitata
+    pub event_bus: Option<EventBus>,
+
+    /// Parole vietate e classificatore applicati ai messaggi delle chat che filtrano i contenuti
+    pub content_filter: ContentFilter,
+
+    /// Id degli amministratori del server, a cui il login assegna il ruolo ServerAdmin
+    pub server_admins: HashSet<i32>,
+
+    /// Contatori e ultimo campione delle statistiche mostrate agli amministratori
+    pub server_stats: ServerStats,
+
+    /// Batching e limiti delle connessioni WebSocket
+    pub ws_config: WsConfig,
+
+    /// Presenza online pubblicata per le altre istanze, None senza cache condivisa
+    pub presence: Option<SharedPresence>,
+
     /// Mappa concorrente degli utenti online con i loro canali WebSocket
     /// Key: user_id, Value: Sender per inviare messaggi al WebSocket dell'utente
     pub users_online: UserMap,
@@ -44,21 +205,445 @@ impl AppState {
     /// con il pool di connessioni fornito e la JWT secret.
     ///
     /// # Arguments
-    /// * `pool` - Pool di connessioni MySQL condiviso
+    /// * `pool` - Pool di connessioni condiviso, MySQL o (con la feature `sqlite`) SQLite
     /// * `jwt_secret` - Chiave segreta per la firma dei token JWT
     ///
     /// # Returns
-    /// Nuova istanza di AppState con tutti i repository inizializzati
-    pub fn new(pool: MySqlPool, jwt_secret: String) -> Self {
+    /// Nuova istanza di AppState con tutti i repository inizializzati sul pool.
+    /// Gli allegati sono tenuti in memoria finché non si chiama `with_storage`,
+    /// così come la lista di revoca dei token finché non si chiama `with_revocation_store`;
+    /// le password sono verificate sul database finché non si chiama `with_auth_provider`.
+    pub fn new(pool: impl Into<DbPool>, jwt_secret: String) -> Self {
+        let pool = pool.into();
+        Self::new_with_read_replica(pool.clone(), pool, jwt_secret)
+    }
+
+    /// Come `new`, ma con una replica in sola lettura per le letture pesanti
+    ///
+    /// Storico dei messaggi, liste dei membri, ricerche ed export leggono da `read_pool`;
+    /// scritture e controlli di permesso restano sul primario, così una replica in ritardo
+    /// non può far accettare o rifiutare un'operazione per errore.
+    ///
+    /// # Arguments
+    /// * `pool` - Pool di connessioni al database primario
+    /// * `read_pool` - Pool di connessioni alla replica
+    /// * `jwt_secret` - Chiave segreta per la firma dei token JWT
+    pub fn new_with_read_replica(
+        pool: impl Into<DbPool>,
+        read_pool: impl Into<DbPool>,
+        jwt_secret: String,
+    ) -> Self {
+        let pool = pool.into();
+        let read_pool = read_pool.into();
         Self {
-            user: UserRepository::new(pool.clone()),
-            chat: ChatRepository::new(pool.clone()),
-            msg: MessageRepository::new(pool.clone()),
+            user: Arc::new(UserRepository::new(pool.clone()).with_read_pool(read_pool.clone())),
+            chat: Arc::new(ChatRepository::new(pool.clone()).with_read_pool(read_pool.clone())),
+            msg: Arc::new(MessageRepository::new(pool.clone()).with_read_pool(read_pool.clone())),
             invitation: InvitationRepository::new(pool.clone()),
-            meta: UserChatMetadataRepository::new(pool),
+            join_request: JoinRequestRepository::new(pool.clone()),
+            invite_link: InviteLinkRepository::new(pool.clone()),
+            meta: Arc::new(UserChatMetadataRepository::new(pool.clone()).with_read_pool(read_pool)),
+            attachment: AttachmentRepository::new(pool.clone()),
+            draft: DraftRepository::new(pool.clone()),
+            ban: BannedMemberRepository::new(pool.clone()),
+            role: ChatRoleRepository::new(pool.clone()),
+            audit: AuditLogRepository::new(pool.clone()),
+            data_export: DataExportRepository::new(pool.clone()),
+            refresh_token: RefreshTokenRepository::new(pool.clone()),
+            user_identity: UserIdentityRepository::new(pool.clone()),
+            webhook: WebhookRepository::new(pool.clone()),
+            report: ReportRepository::new(pool.clone()),
+            user_keys: UserKeysRepository::new(pool.clone()),
+            offline_queue: OfflineQueueRepository::new(pool.clone()),
+            health: HealthRepository::new(pool.clone()),
+            db: pool,
+            repo_cache: None,
+            shared_cache: None,
+            jwt_keys: JwtKeys::hmac(&jwt_secret),
             jwt_secret,
+            profile: AppProfile::default(),
+            cookie_secure: true,
+            auth_provider: Arc::new(LocalAuthProvider),
+            revoked_tokens: Arc::new(InMemoryRevocationStore::new()),
+            rate_limits: RateLimits::default(),
+            login_throttle: LoginThrottle::default(),
+            flood_guard: FloodGuard::default(),
+            idempotency: IdempotencyStore::default(),
+            password_policy: PasswordPolicy::default(),
+            password_hasher: PasswordHasher::default(),
+            storage: Arc::new(InMemory::new()),
+            max_attachment_bytes: DEFAULT_MAX_ATTACHMENT_BYTES,
+            max_json_body_bytes: DEFAULT_MAX_JSON_BODY_BYTES,
+            legacy_api_paths: AtomicBool::new(true),
+            log_level: LogLevel::default(),
+            max_group_members: DEFAULT_MAX_GROUP_MEMBERS,
+            translator: None,
+            oidc: None,
+            event_bus: None,
+            content_filter: ContentFilter::default(),
+            server_admins: HashSet::new(),
+            server_stats: ServerStats::new(),
+            ws_config: WsConfig::default(),
+            presence: None,
             users_online: UserMap::new(),
             chats_online: ChatMap::new(),
         }
     }
+
+    /// Sostituisce il repository degli utenti (di default quello sul database)
+    ///
+    /// # Arguments
+    /// * `user` - Implementazione da usare, ad esempio una versione in memoria nei test
+    pub fn with_user_repo(mut self, user: Arc<dyn UserRepo>) -> Self {
+        self.user = user;
+        self
+    }
+
+    /// Sostituisce il repository delle chat (di default quello sul database)
+    pub fn with_chat_repo(mut self, chat: Arc<dyn ChatRepo>) -> Self {
+        self.chat = chat;
+        self
+    }
+
+    /// Sostituisce il repository dei messaggi (di default quello sul database)
+    pub fn with_message_repo(mut self, msg: Arc<dyn MessageRepo>) -> Self {
+        self.msg = msg;
+        self
+    }
+
+    /// Sostituisce il repository dei metadati utente-chat (di default quello sul database)
+    pub fn with_metadata_repo(mut self, meta: Arc<dyn UserChatMetadataRepo>) -> Self {
+        self.meta = meta;
+        self
+    }
+
+    /// Condivide presenza online e cache dei repository con le altre istanze del server
+    ///
+    /// Va chiamato prima di `with_repository_cache`, che altrimenti usa una cache in memoria.
+    /// La lista dei token revocati si passa a parte con `with_revocation_store`.
+    ///
+    /// # Arguments
+    /// * `cache` - Cache costruita con `build_shared_cache` dalla configurazione
+    pub fn with_shared_cache(mut self, cache: SharedCache) -> Self {
+        self.presence = Some(SharedPresence::new(cache.clone()));
+        self.shared_cache = Some(cache);
+        self
+    }
+
+    /// Mette una cache davanti ai repository di utenti e metadati utente-chat
+    ///
+    /// Va chiamato dopo gli eventuali `with_user_repo` e `with_metadata_repo`, perché
+    /// avvolge i repository presenti in quel momento. Con TTL a 0 non cambia nulla.
+    ///
+    /// # Arguments
+    /// * `config` - Durata e numero massimo delle voci lette dalla configurazione
+    pub fn with_repository_cache(mut self, config: &CacheConfig) -> Self {
+        if config.ttl_secs == 0 {
+            return self;
+        }
+        let store = self
+            .shared_cache
+            .clone()
+            .unwrap_or_else(|| Arc::new(InMemoryCache::new(config.max_entries)));
+        let cache = RepositoryCache::new(store, Duration::from_secs(config.ttl_secs));
+        self.user = Arc::new(CachedUserRepo::new(self.user, cache.clone()));
+        self.meta = Arc::new(CachedUserChatMetadataRepo::new(self.meta, cache.clone()));
+        self.repo_cache = Some(cache);
+        self
+    }
+
+    /// Apre una transazione condivisa tra i repository (metodi `*_in`)
+    ///
+    /// Le scritture diventano visibili tutte insieme con `UnitOfWork::commit`; se la unit of
+    /// work viene scartata prima, ad esempio per un `?` su un errore, vengono annullate.
+    pub async fn begin(&self) -> Result<UnitOfWork, sqlx::Error> {
+        UnitOfWork::begin(&self.db, self.repo_cache.clone()).await
+    }
+
+    /// Dimentica le appartenenze in cache di una chat, dopo una cancellazione a cascata
+    /// (chat eliminata, ruolo personalizzato rimosso) che non passa dal repository dei metadati
+    pub async fn invalidate_chat_members(&self, chat_id: i32) {
+        if let Some(cache) = &self.repo_cache {
+            cache.invalidate_chat(chat_id).await;
+        }
+    }
+
+    /// Verifica se l'utente è connesso a questa istanza o, con la cache condivisa, a un'altra
+    pub async fn is_user_online(&self, user_id: &i32) -> bool {
+        if self.users_online.is_user_online(user_id) {
+            return true;
+        }
+        match &self.presence {
+            Some(presence) => presence.is_online(*user_id).await,
+            None => false,
+        }
+    }
+
+    /// Sostituisce lo storage degli allegati e il relativo limite di dimensione
+    ///
+    /// # Arguments
+    /// * `storage` - Storage costruito con `build_storage` dalla configurazione
+    /// * `max_attachment_bytes` - Dimensione massima accettata per un allegato
+    pub fn with_storage(mut self, storage: AttachmentStorage, max_attachment_bytes: usize) -> Self {
+        self.storage = storage;
+        self.max_attachment_bytes = max_attachment_bytes;
+        self
+    }
+
+    /// Imposta il limite di dimensione del corpo delle richieste JSON
+    ///
+    /// # Arguments
+    /// * `max_json_body_bytes` - Limite applicato a tutte le route tranne gli upload di file
+    pub fn with_max_json_body_bytes(mut self, max_json_body_bytes: usize) -> Self {
+        self.max_json_body_bytes = max_json_body_bytes;
+        self
+    }
+
+    /// Abilita o disabilita i vecchi percorsi dell'API senza prefisso di versione
+    ///
+    /// # Arguments
+    /// * `legacy_api_paths` - Se false l'API è raggiungibile solo sotto `API_V1_PREFIX`
+    pub fn with_legacy_api_paths(self, legacy_api_paths: bool) -> Self {
+        self.legacy_api_paths
+            .store(legacy_api_paths, Ordering::Relaxed);
+        self
+    }
+
+    /// Se i vecchi percorsi senza prefisso di versione sono serviti in questo momento
+    pub fn legacy_api_paths(&self) -> bool {
+        self.legacy_api_paths.load(Ordering::Relaxed)
+    }
+
+    /// Collega il filtro dei log installato all'avvio, per cambiarne il livello a caldo
+    ///
+    /// # Arguments
+    /// * `log_level` - Livello iniziale e handle del filtro creato in `main`
+    pub fn with_log_level(mut self, log_level: LogLevel) -> Self {
+        self.log_level = log_level;
+        self
+    }
+
+    /// Valori della configurazione ricaricabile attualmente in vigore
+    pub fn reloadable_config(&self) -> ReloadableConfig {
+        ReloadableConfig {
+            log_level: self.log_level.get(),
+            rate_limit: self.rate_limits.config(),
+            legacy_api_paths: self.legacy_api_paths(),
+            password_policy: self.password_policy.config(),
+        }
+    }
+
+    /// Applica i valori ricaricabili della configurazione senza riavviare il server
+    ///
+    /// # Returns
+    /// * `Ok(())` se tutti i valori sono stati applicati
+    /// * `Err(String)` se il livello di log non è valido; in quel caso nulla cambia
+    pub fn apply_config(&self, config: &ReloadableConfig) -> Result<(), String> {
+        self.log_level.set(&config.log_level)?;
+        self.rate_limits.apply(&config.rate_limit);
+        self.legacy_api_paths
+            .store(config.legacy_api_paths, Ordering::Relaxed);
+        self.password_policy.apply(&config.password_policy);
+        Ok(())
+    }
+
+    /// Sostituisce le chiavi dei JWT: firma RS256/EdDSA o chiavi ritirate dopo una rotazione
+    pub fn with_jwt_keys(mut self, jwt_keys: JwtKeys) -> Self {
+        self.jwt_keys = jwt_keys;
+        self
+    }
+
+    /// Imposta il profilo di esecuzione e l'attributo `Secure` del cookie del token
+    ///
+    /// # Arguments
+    /// * `profile` - Profilo letto da `APP_ENV`
+    /// * `cookie_secure` - Da COOKIE_SECURE o dal default del profilo
+    pub fn with_profile(mut self, profile: AppProfile, cookie_secure: bool) -> Self {
+        self.profile = profile;
+        self.cookie_secure = cookie_secure;
+        self
+    }
+
+    /// Attributi del cookie `token` emesso al login e azzerato al logout
+    pub fn cookie_attributes(&self) -> &'static str {
+        if self.cookie_secure {
+            "HttpOnly; Secure; SameSite=Lax"
+        } else {
+            "HttpOnly; SameSite=Lax"
+        }
+    }
+
+    /// Imposta il numero massimo di membri di una chat di gruppo
+    ///
+    /// # Arguments
+    /// * `max_group_members` - Limite applicato a creazione, inviti, accettazione degli inviti,
+    ///   ingresso tramite link di invito, ingresso nelle chat pubbliche (diretto o approvato)
+    ///   e creazione dei bot webhook
+    pub fn with_max_group_members(mut self, max_group_members: usize) -> Self {
+        self.max_group_members = max_group_members;
+        self
+    }
+
+    /// Verifica che un gruppo con `current_members` membri possa accoglierne uno nuovo
+    ///
+    /// # Returns
+    /// * `Ok(())` se c'è ancora posto
+    /// * `Err(AppError::group_full)` (409) se il gruppo è pieno
+    pub fn ensure_group_has_room(&self, current_members: usize) -> Result<(), AppError> {
+        if current_members >= self.max_group_members {
+            return Err(AppError::group_full(self.max_group_members));
+        }
+        Ok(())
+    }
+
+    /// Come `ensure_group_has_room`, ma conta i membri all'interno della unit of work che
+    /// aggiungerà il nuovo membro
+    ///
+    /// La riga della chat resta bloccata fino alla fine della unit of work: due ingressi
+    /// concorrenti vengono serializzati e il secondo vede il membro aggiunto dal primo.
+    ///
+    /// # Returns
+    /// * `Ok(())` se c'è ancora posto
+    /// * `Err(AppError::group_full)` (409) se il gruppo è pieno
+    pub async fn ensure_group_has_room_in(
+        &self,
+        uow: &mut UnitOfWork,
+        chat_id: i32,
+    ) -> Result<(), AppError> {
+        let members = self.chat.lock_members_in(uow, &chat_id).await?;
+        self.ensure_group_has_room(members as usize)
+    }
+
+    /// Sostituisce la lista di revoca dei token (di default in memoria)
+    ///
+    /// # Arguments
+    /// * `revoked_tokens` - Lista costruita con `build_revocation_store` dalla configurazione
+    pub fn with_revocation_store(mut self, revoked_tokens: RevocationStore) -> Self {
+        self.revoked_tokens = revoked_tokens;
+        self
+    }
+
+    /// Sostituisce il backend che verifica le credenziali al login
+    ///
+    /// # Arguments
+    /// * `auth_provider` - Backend costruito con `build_auth_provider` dalla configurazione
+    pub fn with_auth_provider(mut self, auth_provider: Arc<dyn AuthProvider>) -> Self {
+        self.auth_provider = auth_provider;
+        self
+    }
+
+    /// Sostituisce i limiti di richieste al minuto (di default quelli di `RateLimitConfig`)
+    ///
+    /// # Arguments
+    /// * `config` - Limiti letti dalla configurazione
+    pub fn with_rate_limits(mut self, config: &RateLimitConfig) -> Self {
+        self.rate_limits = RateLimits::new(config);
+        self
+    }
+
+    /// Sostituisce la politica di blocco del login (di default quella di `LoginLockoutConfig`)
+    ///
+    /// # Arguments
+    /// * `config` - Soglia e durate del blocco lette dalla configurazione
+    pub fn with_login_lockout(mut self, config: &LoginLockoutConfig) -> Self {
+        self.login_throttle = LoginThrottle::new(config);
+        self
+    }
+
+    /// Sostituisce le soglie anti-flood (di default quelle di `FloodConfig`)
+    ///
+    /// # Arguments
+    /// * `config` - Soglie e durate del silenziamento lette dalla configurazione
+    pub fn with_flood_detection(mut self, config: &FloodConfig) -> Self {
+        self.flood_guard = FloodGuard::new(config);
+        self
+    }
+
+    /// Sostituisce i requisiti delle password (di default quelli di `PasswordPolicyConfig`)
+    ///
+    /// # Arguments
+    /// * `config` - Requisiti letti dalla configurazione
+    pub fn with_password_policy(mut self, config: &PasswordPolicyConfig) -> Self {
+        self.password_policy = PasswordPolicy::new(config);
+        self
+    }
+
+    /// Sostituisce batching e limiti delle connessioni WebSocket (di default quelli di `WsConfig`)
+    ///
+    /// # Arguments
+    /// * `config` - Parametri letti dalla configurazione, validi per le nuove connessioni
+    pub fn with_ws_config(mut self, config: WsConfig) -> Self {
+        self.ws_config = config;
+        self
+    }
+
+    /// Sostituisce l'algoritmo di hash delle password (di default bcrypt con costo standard)
+    ///
+    /// # Arguments
+    /// * `password_hasher` - Hasher costruito con `PasswordHasher::new` dalla configurazione
+    pub fn with_password_hasher(mut self, password_hasher: PasswordHasher) -> Self {
+        self.password_hasher = password_hasher;
+        self
+    }
+
+    /// Abilita il login SSO con il provider OpenID Connect indicato
+    ///
+    /// # Arguments
+    /// * `oidc` - Client usato da GET /auth/oidc/login e GET /auth/oidc/callback
+    pub fn with_oidc(mut self, oidc: Arc<OidcClient>) -> Self {
+        self.oidc = Some(oidc);
+        self
+    }
+
+    /// Abilita la traduzione dei messaggi con il provider indicato
+    ///
+    /// # Arguments
+    /// * `translator` - Provider usato da GET /chats/{chat_id}/messages/{message_id}/translate
+    pub fn with_translator(mut self, translator: Arc<dyn TranslationProvider>) -> Self {
+        self.translator = Some(translator);
+        self
+    }
+
+    /// Abilita la pubblicazione degli eventi di dominio
+    ///
+    /// # Arguments
+    /// * `event_bus` - Bus costruito con `build_event_bus` dalla configurazione
+    pub fn with_event_bus(mut self, event_bus: EventBus) -> Self {
+        self.event_bus = Some(event_bus);
+        self
+    }
+
+    /// Accoda un evento di dominio per il broker, se configurato; non attende la pubblicazione
+    pub fn publish_event(&self, event: DomainEvent) {
+        if let Some(event_bus) = &self.event_bus {
+            event_bus.publish(event);
+        }
+    }
+
+    /// Sostituisce il filtro dei contenuti (di default vuoto, nessun messaggio viene filtrato)
+    ///
+    /// # Arguments
+    /// * `content_filter` - Filtro costruito con `build_content_filter` dalla configurazione
+    pub fn with_content_filter(mut self, content_filter: ContentFilter) -> Self {
+        self.content_filter = content_filter;
+        self
+    }
+
+    /// Imposta gli amministratori del server
+    ///
+    /// # Arguments
+    /// * `server_admins` - Id degli utenti che ricevono il ruolo ServerAdmin nel JWT al login
+    pub fn with_server_admins(mut self, server_admins: Vec<i32>) -> Self {
+        self.server_admins = server_admins.into_iter().collect();
+        self
+    }
+
+    /// Ruolo sul server da includere nel JWT dell'utente
+    pub fn server_role(&self, user_id: i32) -> ServerRole {
+        if self.server_admins.contains(&user_id) {
+            ServerRole::ServerAdmin
+        } else {
+            ServerRole::User
+        }
+    }
 }
@@ -0,0 +1,54 @@
+//! Storage - Salvataggio dei file allegati
+//!
+//! Gli allegati vengono scritti tramite l'astrazione `ObjectStore`, così gli handler
+//! non dipendono dal backend scelto in configurazione (disco locale o S3 compatibile).
+
+use crate::core::config::StorageConfig;
+use object_store::{ObjectStore, aws::AmazonS3Builder, local::LocalFileSystem};
+use std::sync::Arc;
+
+/// Storage condiviso dagli handler, clonabile a costo zero
+pub type AttachmentStorage = Arc<dyn ObjectStore>;
+
+/// Costruisce lo storage degli allegati a partire dalla configurazione
+///
+/// # Arguments
+/// * `config` - Backend scelto tramite le variabili d'ambiente
+///
+/// # Returns
+/// Storage pronto all'uso, oppure un messaggio d'errore se la configurazione non è valida
+pub fn build_storage(config: &StorageConfig) -> Result<AttachmentStorage, String> {
+    match config {
+        StorageConfig::Local { root } => {
+            // LocalFileSystem richiede che la cartella radice esista già
+            std::fs::create_dir_all(root)
+                .map_err(|e| format!("Unable to create upload directory: {}", e))?;
+            let store = LocalFileSystem::new_with_prefix(root)
+                .map_err(|e| format!("Invalid upload directory: {}", e))?;
+            Ok(Arc::new(store))
+        }
+        StorageConfig::S3 {
+            bucket,
+            region,
+            endpoint,
+            access_key_id,
+            secret_access_key,
+        } => {
+            let mut builder = AmazonS3Builder::new()
+                .with_bucket_name(bucket)
+                .with_region(region)
+                .with_access_key_id(access_key_id)
+                .with_secret_access_key(secret_access_key);
+            if let Some(endpoint) = endpoint {
+                // gli endpoint compatibili (es. MinIO in locale) spesso non usano https
+                builder = builder
+                    .with_endpoint(endpoint)
+                    .with_allow_http(endpoint.starts_with("http://"));
+            }
+            let store = builder
+                .build()
+                .map_err(|e| format!("Invalid S3 configuration: {}", e))?;
+            Ok(Arc::new(store))
+        }
+    }
+}
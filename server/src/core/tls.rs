@@ -0,0 +1,348 @@
+//! TLS - HTTPS e WSS serviti direttamente dal server
+//!
+//! Per le installazioni piccole, dove un reverse proxy sarebbe solo un componente in più.
+//! Il certificato è letto da file PEM (`TLS_CERT_PATH`/`TLS_KEY_PATH`) e ricaricato quando
+//! i file cambiano, così un rinnovo esterno non richiede il riavvio, oppure è ottenuto e
+//! rinnovato dal client ACME in `acme`. Gli handshake avvengono in task separati dal ciclo
+//! di accettazione: un client lento o malevolo non blocca le altre connessioni.
+
+use crate::core::acme::{ACME_TLS_ALPN_PROTOCOL, start_acme};
+use crate::core::config::TlsConfig;
+use axum::serve::Listener;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::rustls::crypto::ring::default_provider;
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::server::{ClientHello, ResolvesServerCert};
+use tokio_rustls::rustls::sign::CertifiedKey;
+use tokio_rustls::server::TlsStream;
+use tracing::{debug, error, info, warn};
+
+/// Tempo massimo concesso a un client per completare l'handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Ogni quanto controllare se i file del certificato sono cambiati
+const CERT_RELOAD_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Connessioni con handshake completato in attesa di essere servite
+const ACCEPTED_QUEUE_CAPACITY: usize = 128;
+
+/// Sceglie il certificato da presentare a ogni handshake; sostituibile a caldo
+#[derive(Debug, Default)]
+pub struct CertResolver {
+    current: RwLock<Option<Arc<CertifiedKey>>>,
+    /// Certificati della sfida ACME TLS-ALPN-01 in corso, per dominio
+    challenges: RwLock<HashMap<String, Arc<CertifiedKey>>>,
+}
+
+impl CertResolver {
+    pub fn set_certificate(&self, certificate: Arc<CertifiedKey>) {
+        *self.current.write().unwrap() = Some(certificate);
+    }
+
+    pub fn has_certificate(&self) -> bool {
+        self.current.read().unwrap().is_some()
+    }
+
+    pub(crate) fn set_challenge(&self, domain: &str, certificate: Arc<CertifiedKey>) {
+        self.challenges
+            .write()
+            .unwrap()
+            .insert(domain.to_string(), certificate);
+    }
+
+    pub(crate) fn clear_challenges(&self) {
+        self.challenges.write().unwrap().clear();
+    }
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        let is_acme_challenge = client_hello
+            .alpn()
+            .is_some_and(|mut protocols| protocols.any(|p| p == ACME_TLS_ALPN_PROTOCOL));
+        if is_acme_challenge {
+            let domain = client_hello.server_name()?;
+            return self.challenges.read().unwrap().get(domain).cloned();
+        }
+
+        let certificate = self.current.read().unwrap().clone();
+        if certificate.is_none() {
+            warn!("TLS handshake rejected: no certificate available yet");
+        }
+        certificate
+    }
+}
+
+/// Combina catena di certificati e chiave privata, verificando che corrispondano
+pub(crate) fn certified_key(
+    chain: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+) -> Result<Arc<CertifiedKey>, String> {
+    if chain.is_empty() {
+        return Err("Certificate chain is empty".to_string());
+    }
+    CertifiedKey::from_der(chain, key, &default_provider())
+        .map(Arc::new)
+        .map_err(|e| format!("Invalid certificate or private key: {}", e))
+}
+
+/// Legge certificato (catena completa) e chiave privata da file PEM
+pub(crate) fn load_certificate(
+    cert_path: &Path,
+    key_path: &Path,
+) -> Result<Arc<CertifiedKey>, String> {
+    let chain = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Cannot read certificate {}: {}", cert_path.display(), e))?;
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .map_err(|e| format!("Cannot read private key {}: {}", key_path.display(), e))?;
+    certified_key(chain, key)
+}
+
+/// Listener che accetta connessioni TCP e completa l'handshake TLS prima di
+/// consegnarle ad axum
+pub struct TlsListener {
+    accepted: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+    local_addr: SocketAddr,
+}
+
+impl TlsListener {
+    pub fn new(listener: TcpListener, config: Arc<ServerConfig>) -> std::io::Result<Self> {
+        let local_addr = listener.local_addr()?;
+        let (tx, accepted) = mpsc::channel(ACCEPTED_QUEUE_CAPACITY);
+        tokio::spawn(accept_loop(listener, TlsAcceptor::from(config), tx));
+        Ok(Self {
+            accepted,
+            local_addr,
+        })
+    }
+}
+
+impl Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.accepted.recv().await {
+            Some(connection) => connection,
+            // il ciclo di accettazione termina solo quando il listener viene scartato
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+async fn accept_loop(
+    listener: TcpListener,
+    acceptor: TlsAcceptor,
+    tx: mpsc::Sender<(TlsStream<TcpStream>, SocketAddr)>,
+) {
+    loop {
+        let accepted = tokio::select! {
+            accepted = listener.accept() => accepted,
+            _ = tx.closed() => return,
+        };
+        let (stream, addr) = match accepted {
+            Ok(connection) => connection,
+            Err(e) if is_connection_error(&e) => continue,
+            Err(e) => {
+                // es. troppi file aperti: si riprova dopo una pausa
+                error!("Failed to accept TCP connection: {}", e);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+
+        let acceptor = acceptor.clone();
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            let stream =
+                match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                    Ok(Ok(stream)) => stream,
+                    Ok(Err(e)) => {
+                        debug!("TLS handshake with {} failed: {}", addr, e);
+                        return;
+                    }
+                    Err(_) => {
+                        debug!("TLS handshake with {} timed out", addr);
+                        return;
+                    }
+                };
+            // la CA chiude la connessione di verifica subito dopo l'handshake
+            if stream.get_ref().1.alpn_protocol() == Some(ACME_TLS_ALPN_PROTOCOL) {
+                debug!("ACME challenge handshake from {} completed", addr);
+                return;
+            }
+            let _ = tx.send((stream, addr)).await;
+        });
+    }
+}
+
+fn is_connection_error(e: &std::io::Error) -> bool {
+    matches!(
+        e.kind(),
+        std::io::ErrorKind::ConnectionRefused
+            | std::io::ErrorKind::ConnectionAborted
+            | std::io::ErrorKind::ConnectionReset
+    )
+}
+
+/// Prepara la configurazione TLS del server e avvia in background il ricaricamento
+/// del certificato da file o il client ACME
+///
+/// # Returns
+/// None se il TLS è disabilitato; errore se il certificato configurato non è valido
+pub fn start_tls(config: &TlsConfig) -> Result<Option<Arc<ServerConfig>>, String> {
+    let resolver = Arc::new(CertResolver::default());
+    let mut alpn_protocols = vec![b"http/1.1".to_vec()];
+
+    match config {
+        TlsConfig::Disabled => return Ok(None),
+        TlsConfig::Files {
+            cert_path,
+            key_path,
+        } => {
+            resolver.set_certificate(load_certificate(cert_path, key_path)?);
+            tokio::spawn(reload_certificate(
+                resolver.clone(),
+                cert_path.clone(),
+                key_path.clone(),
+            ));
+        }
+        TlsConfig::Acme(acme) => {
+            alpn_protocols.push(ACME_TLS_ALPN_PROTOCOL.to_vec());
+            start_acme(acme.clone(), resolver.clone());
+        }
+    }
+
+    let mut server_config = ServerConfig::builder_with_provider(Arc::new(default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| format!("Invalid TLS configuration: {}", e))?
+        .with_no_client_auth()
+        .with_cert_resolver(resolver);
+    server_config.alpn_protocols = alpn_protocols;
+    Ok(Some(Arc::new(server_config)))
+}
+
+/// Ricarica il certificato quando uno dei due file viene modificato; se i nuovi file
+/// non sono validi resta in uso il certificato precedente
+async fn reload_certificate(resolver: Arc<CertResolver>, cert_path: PathBuf, key_path: PathBuf) {
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    let mut last_modified: Option<SystemTime> = modified(&cert_path).max(modified(&key_path));
+
+    let mut interval = tokio::time::interval(CERT_RELOAD_INTERVAL);
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        let current = modified(&cert_path).max(modified(&key_path));
+        if current == last_modified {
+            continue;
+        }
+        match load_certificate(&cert_path, &key_path) {
+            Ok(certificate) => {
+                resolver.set_certificate(certificate);
+                last_modified = current;
+                info!("TLS certificate reloaded from {}", cert_path.display());
+            }
+            Err(e) => error!("TLS certificate not reloaded: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, routing::get};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_rustls::TlsConnector;
+    use tokio_rustls::rustls::pki_types::ServerName;
+    use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+
+    /// Certificato autofirmato per `localhost`, con la chiave in PKCS#8
+    fn self_signed() -> (CertificateDer<'static>, PrivateKeyDer<'static>) {
+        let key = rcgen::KeyPair::generate().unwrap();
+        let cert = rcgen::CertificateParams::new(vec!["localhost".to_string()])
+            .unwrap()
+            .self_signed(&key)
+            .unwrap();
+        (
+            cert.der().clone(),
+            PrivateKeyDer::Pkcs8(key.serialize_der().into()),
+        )
+    }
+
+    #[test]
+    fn test_load_certificate_from_pem_files() {
+        let dir = std::env::temp_dir().join(format!("ironlink-tls-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let key = rcgen::KeyPair::generate().unwrap();
+        let cert = rcgen::CertificateParams::new(vec!["localhost".to_string()])
+            .unwrap()
+            .self_signed(&key)
+            .unwrap();
+        std::fs::write(dir.join("cert.pem"), cert.pem()).unwrap();
+        std::fs::write(dir.join("key.pem"), key.serialize_pem()).unwrap();
+
+        assert!(load_certificate(&dir.join("cert.pem"), &dir.join("key.pem")).is_ok());
+        assert!(load_certificate(&dir.join("missing.pem"), &dir.join("key.pem")).is_err());
+
+        // chiave che non appartiene al certificato
+        let other = rcgen::KeyPair::generate().unwrap();
+        std::fs::write(dir.join("other.pem"), other.serialize_pem()).unwrap();
+        assert!(load_certificate(&dir.join("cert.pem"), &dir.join("other.pem")).is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_serve_https() {
+        let (cert, key) = self_signed();
+        let resolver = Arc::new(CertResolver::default());
+        resolver.set_certificate(certified_key(vec![cert.clone()], key).unwrap());
+        let server_config = ServerConfig::builder_with_provider(Arc::new(default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_cert_resolver(resolver);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let listener = TlsListener::new(listener, Arc::new(server_config)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new().route("/", get(|| async { "hello" }));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let mut roots = RootCertStore::empty();
+        roots.add(cert).unwrap();
+        let client_config = ClientConfig::builder_with_provider(Arc::new(default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let mut stream = TlsConnector::from(Arc::new(client_config))
+            .connect(ServerName::try_from("localhost").unwrap(), stream)
+            .await
+            .unwrap();
+
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.ends_with("hello"));
+    }
+}
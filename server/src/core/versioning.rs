@@ -0,0 +1,110 @@
+//! Versioning - Prefisso di versione delle route dell'API
+//!
+//! Le route sono servite sotto `/api/v1`: un cambiamento incompatibile (ad esempio un DTO
+//! con una forma diversa) potrà essere pubblicato sotto `/api/v2` lasciando invariata la v1.
+//! I vecchi percorsi senza prefisso restano disponibili finché `LEGACY_API_PATHS` lo
+//! consente, con gli header che indicano ai client il percorso che li sostituisce.
+
+use crate::core::AppState;
+use axum::{
+    body::Body,
+    extract::{MatchedPath, Request, State},
+    http::{HeaderName, HeaderValue, Response, StatusCode, header},
+    middleware::Next,
+    response::IntoResponse,
+};
+use std::sync::Arc;
+
+/// Prefisso della versione corrente dell'API
+pub const API_V1_PREFIX: &str = "/api/v1";
+
+/// Header che marca una risposta servita da un percorso deprecato
+pub const DEPRECATION_HEADER: HeaderName = HeaderName::from_static("deprecation");
+
+/// Middleware per le route servite senza prefisso di versione
+///
+/// Va applicato al router dei percorsi legacy. Le risposte delle route esistenti ricevono
+/// `Deprecation: true` e un header `Link` verso lo stesso percorso sotto `API_V1_PREFIX`;
+/// le richieste che non corrispondono a nessuna route passano invariate.
+pub async fn legacy_path_middleware(req: Request, next: Next) -> Response<Body> {
+    let successor = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|_| successor_link(req.uri().path(), req.uri().query()));
+    let mut response = next.run(req).await;
+
+    if let Some(link) = successor.and_then(|link| HeaderValue::try_from(link).ok()) {
+        let headers = response.headers_mut();
+        headers.insert(DEPRECATION_HEADER, HeaderValue::from_static("true"));
+        headers.append(header::LINK, link);
+    }
+    response
+}
+
+/// Middleware che nasconde i percorsi legacy (404) mentre `LEGACY_API_PATHS` è disattivato
+///
+/// Le route legacy sono sempre montate: il flag si può così cambiare ricaricando la
+/// configurazione, senza ricostruire il router.
+pub async fn legacy_path_gate_middleware(
+    State(state): State<Arc<AppState>>,
+    req: Request,
+    next: Next,
+) -> Response<Body> {
+    if !state.legacy_api_paths() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    next.run(req).await
+}
+
+/// Valore dell'header `Link` con il percorso versionato che sostituisce quello legacy
+fn successor_link(path: &str, query: Option<&str>) -> String {
+    match query {
+        Some(query) => format!(
+            "<{}{}?{}>; rel=\"successor-version\"",
+            API_V1_PREFIX, path, query
+        ),
+        None => format!("<{}{}>; rel=\"successor-version\"", API_V1_PREFIX, path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, http::StatusCode, middleware, routing::get};
+    use axum_test::TestServer;
+
+    fn server() -> TestServer {
+        let api = || Router::new().route("/chats/{chat_id}", get(|| async { "chat" }));
+        let app = Router::new()
+            .nest(API_V1_PREFIX, api())
+            .merge(api().layer(middleware::from_fn(legacy_path_middleware)));
+        TestServer::new(app).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_legacy_path_marked_deprecated() {
+        let server = server();
+
+        let response = server.get("/chats/3?limit=5").await;
+        response.assert_status_ok();
+        assert_eq!(response.header(DEPRECATION_HEADER), "true");
+        assert_eq!(
+            response.header(header::LINK),
+            "</api/v1/chats/3?limit=5>; rel=\"successor-version\""
+        );
+    }
+
+    #[tokio::test]
+    async fn test_versioned_path_not_deprecated() {
+        let server = server();
+
+        let response = server.get("/api/v1/chats/3").await;
+        response.assert_status_ok();
+        assert!(response.maybe_header(DEPRECATION_HEADER).is_none());
+
+        // un percorso inesistente non ha un sostituto da indicare
+        let response = server.get("/missing").await;
+        response.assert_status(StatusCode::NOT_FOUND);
+        assert!(response.maybe_header(header::LINK).is_none());
+    }
+}
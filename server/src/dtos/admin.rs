@@ -0,0 +1,121 @@
+//! Admin DTOs - Data Transfer Objects per le route di amministrazione del server
+
+use super::{ChatDTO, UserInChatDTO};
+use crate::core::ReloadableConfig;
+use crate::entities::User;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Utente visto dall'amministratore: comprende disattivazione e presenza reale,
+/// senza le restrizioni di privacy applicate agli altri utenti
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct AdminUserDTO {
+    pub user_id: i32,
+    pub username: String,
+    pub display_name: Option<String>,
+    pub last_seen: Option<DateTime<Utc>>,
+    pub deactivated_at: Option<DateTime<Utc>>,
+    pub online: bool,
+}
+
+impl AdminUserDTO {
+    pub fn new(user: User, online: bool) -> Self {
+        Self {
+            user_id: user.user_id,
+            username: user.username,
+            display_name: user.display_name,
+            last_seen: user.last_seen,
+            deactivated_at: user.deactivated_at,
+            online,
+        }
+    }
+}
+
+/// Dettaglio di una chat per l'amministratore, senza il contenuto dei messaggi
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct AdminChatDTO {
+    pub chat: ChatDTO,
+    pub members: Vec<UserInChatDTO>,
+    /// Messaggi non eliminati della chat
+    pub message_count: i64,
+    /// Membri con una connessione WebSocket attiva
+    pub online_members: usize,
+}
+
+/// Utenti connessi al server in questo momento
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct AdminStatsDTO {
+    pub online_users: usize,
+}
+
+/// Impostazioni che il server applica senza riavvio, con i valori in vigore
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct RuntimeConfigDTO {
+    pub log_level: String,
+    pub rate_limit_auth_per_minute: u32,
+    pub rate_limit_user_per_minute: u32,
+    pub rate_limit_trust_proxy: bool,
+    pub legacy_api_paths: bool,
+    pub password_min_length: usize,
+    pub password_require_mixed_case: bool,
+    pub password_require_digit: bool,
+    pub password_reject_common: bool,
+}
+
+impl From<ReloadableConfig> for RuntimeConfigDTO {
+    fn from(config: ReloadableConfig) -> Self {
+        Self {
+            log_level: config.log_level,
+            rate_limit_auth_per_minute: config.rate_limit.auth_per_minute,
+            rate_limit_user_per_minute: config.rate_limit.user_per_minute,
+            rate_limit_trust_proxy: config.rate_limit.trust_forwarded_for,
+            legacy_api_paths: config.legacy_api_paths,
+            password_min_length: config.password_policy.min_length,
+            password_require_mixed_case: config.password_policy.require_mixed_case,
+            password_require_digit: config.password_policy.require_digit,
+            password_reject_common: config.password_policy.reject_common,
+        }
+    }
+}
+
+/// Occupazione del pool di connessioni al database
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct DbPoolStatsDTO {
+    pub size: u32,
+    pub idle: usize,
+    /// Connessioni aperte impegnate da una query o da una transazione
+    pub in_use: u32,
+    pub min_connections: u32,
+    pub max_connections: u32,
+    /// Frazione delle connessioni massime in uso, da 0 a 1
+    pub saturation: f64,
+}
+
+impl DbPoolStatsDTO {
+    pub fn new(size: u32, idle: usize, min_connections: u32, max_connections: u32) -> Self {
+        let in_use = size.saturating_sub(idle as u32);
+        Self {
+            size,
+            idle,
+            in_use,
+            min_connections,
+            max_connections,
+            saturation: if max_connections == 0 {
+                0.0
+            } else {
+                f64::from(in_use) / f64::from(max_connections)
+            },
+        }
+    }
+}
+
+/// Campione delle statistiche del server inviato alla dashboard di amministrazione
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct ServerStatsDTO {
+    pub sampled_at: DateTime<Utc>,
+    pub online_users: usize,
+    /// Messaggi inviati nell'ultimo minuto (media mobile sui campioni disponibili)
+    pub messages_per_minute: f64,
+    pub db_pool: DbPoolStatsDTO,
+}
@@ -0,0 +1,44 @@
+//! Attachment DTOs - Data Transfer Objects per allegati
+
+use crate::entities::Attachment;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Struct per gestire io col client (la storage_key resta interna al server)
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct AttachmentDTO {
+    pub attachment_id: i32,
+    pub chat_id: i32,
+    pub uploader_id: i32,
+    pub file_name: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Attachment> for AttachmentDTO {
+    fn from(value: Attachment) -> Self {
+        Self {
+            attachment_id: value.attachment_id,
+            chat_id: value.chat_id,
+            uploader_id: value.uploader_id,
+            file_name: value.file_name,
+            content_type: value.content_type,
+            size_bytes: value.size_bytes,
+            created_at: value.created_at,
+        }
+    }
+}
+
+/// DTO per registrare un allegato già salvato sullo storage (senza attachment_id)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreateAttachmentDTO {
+    pub chat_id: i32,
+    pub uploader_id: i32,
+    pub file_name: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub storage_key: String,
+    pub created_at: DateTime<Utc>,
+}
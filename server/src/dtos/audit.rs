@@ -0,0 +1,45 @@
+//! Audit DTOs - Data Transfer Objects per il registro delle azioni amministrative
+
+use crate::entities::{AuditAction, AuditEntry};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Struct per gestire io col client, il payload viene restituito come JSON e non come stringa
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct AuditEntryDTO {
+    pub audit_id: i32,
+    pub chat_id: i32,
+    pub actor_id: Option<i32>,
+    pub target_user_id: Option<i32>,
+    pub action: AuditAction,
+    pub payload: Option<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<AuditEntry> for AuditEntryDTO {
+    fn from(value: AuditEntry) -> Self {
+        Self {
+            audit_id: value.audit_id,
+            chat_id: value.chat_id,
+            actor_id: value.actor_id,
+            target_user_id: value.target_user_id,
+            action: value.action,
+            // il payload è scritto solo dal server, quindi è sempre JSON valido
+            payload: value
+                .payload
+                .and_then(|payload| serde_json::from_str(&payload).ok()),
+            created_at: value.created_at,
+        }
+    }
+}
+
+/// DTO per registrare una nuova azione (senza audit_id e created_at, gestiti dal repository)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreateAuditEntryDTO {
+    pub chat_id: i32,
+    pub actor_id: i32,
+    pub target_user_id: Option<i32>,
+    pub action: AuditAction,
+    pub payload: Option<serde_json::Value>,
+}
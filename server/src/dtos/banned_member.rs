@@ -0,0 +1,26 @@
+//! BannedMember DTOs - Data Transfer Objects per gli utenti bannati
+
+use crate::entities::BannedMember;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Struct per gestire io col client, usata anche come payload dell'evento MemberBanned
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct BannedMemberDTO {
+    pub user_id: i32,
+    pub chat_id: i32,
+    pub banned_by: i32,
+    pub banned_at: DateTime<Utc>,
+}
+
+impl From<BannedMember> for BannedMemberDTO {
+    fn from(value: BannedMember) -> Self {
+        Self {
+            user_id: value.user_id,
+            chat_id: value.chat_id,
+            banned_by: value.banned_by,
+            banned_at: value.banned_at,
+        }
+    }
+}
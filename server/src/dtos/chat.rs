@@ -1,17 +1,37 @@
 //! Chat DTOs - Data Transfer Objects per chat
 
-use crate::entities::{Chat, ChatType};
+use super::{MessageDTO, UserInChatDTO};
+use crate::core::API_V1_PREFIX;
+use crate::entities::{Chat, ChatType, ContentFilterPolicy, InvitePolicy};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use validator::Validate;
 
 /// Struct per gestire io col client
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct ChatDTO {
     pub chat_id: Option<i32>,
     pub title: Option<String>,
     pub description: Option<String>,
     pub chat_type: Option<ChatType>,
     pub user_list: Option<Vec<i32>>, // lista user_id per chat private/gruppo
+    /// Se true solo Admin e Owner possono scrivere
+    pub announcement_only: Option<bool>,
+    /// Se true la chat compare nella directory delle chat pubbliche
+    pub is_public: Option<bool>,
+    /// Se true chi entra dalla directory deve essere approvato
+    pub requires_approval: Option<bool>,
+    /// Chi può invitare nuovi membri
+    pub invite_policy: Option<InvitePolicy>,
+    /// Cosa fare dei messaggi che non superano il filtro dei contenuti
+    pub content_filter: Option<ContentFilterPolicy>,
+    /// Percorso da cui scaricare l'avatar della chat
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avatar_url: Option<String>,
+    /// Presente se l'utente ha fissato la chat in cima alla lista
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pinned_at: Option<DateTime<Utc>>,
 }
 
 impl From<Chat> for ChatDTO {
@@ -21,7 +41,19 @@ impl From<Chat> for ChatDTO {
             title: value.title,
             description: value.description,
             chat_type: Some(value.chat_type),
+            avatar_url: value.avatar_attachment_id.map(|attachment_id| {
+                format!(
+                    "{}/chats/{}/attachments/{}",
+                    API_V1_PREFIX, value.chat_id, attachment_id
+                )
+            }),
+            announcement_only: Some(value.announcement_only),
+            is_public: Some(value.is_public),
+            requires_approval: Some(value.requires_approval),
+            invite_policy: Some(value.invite_policy),
+            content_filter: Some(value.content_filter),
             user_list: None, // da popolare manualmente se necessario
+            pinned_at: None, // dipende dall'utente, valorizzato da list_chats
         }
     }
 }
@@ -43,7 +75,7 @@ pub struct CreateChatDTO {
 }
 
 /// DTO per aggiornare una chat (solo campi modificabili)
-#[derive(Serialize, Deserialize, Debug, Clone, Validate)]
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
 pub struct UpdateChatDTO {
     #[validate(length(
         min = 1,
@@ -54,4 +86,49 @@ pub struct UpdateChatDTO {
 
     #[validate(length(max = 500, message = "Chat description must not exceed 500 characters"))]
     pub description: Option<String>,
+
+    /// Attiva/disattiva la modalità solo annunci
+    pub announcement_only: Option<bool>,
+
+    /// Rende la chat visibile (o meno) nella directory delle chat pubbliche
+    pub is_public: Option<bool>,
+
+    /// Richiede (o meno) l'approvazione per entrare dalla directory
+    pub requires_approval: Option<bool>,
+
+    /// Stabilisce chi può invitare nuovi membri
+    pub invite_policy: Option<InvitePolicy>,
+
+    /// Stabilisce cosa fare dei messaggi che non superano il filtro dei contenuti
+    pub content_filter: Option<ContentFilterPolicy>,
+}
+
+/// Chat pubblica come mostrata nella directory
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema, sqlx::FromRow)]
+pub struct PublicChatDTO {
+    pub chat_id: i32,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub member_count: i64,
+    /// Se true POST /chats/{chat_id}/join crea una richiesta da approvare
+    pub requires_approval: bool,
+}
+
+/// Voce della panoramica delle chat dell'utente: la chat con l'ultimo messaggio e i contatori
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ChatOverviewDTO {
+    /// Chat con l'eventuale pin dell'utente, senza `user_list`
+    pub chat: ChatDTO,
+    /// Ultimo messaggio visibile all'utente e non eliminato, assente se la chat è vuota
+    pub last_message: Option<MessageDTO>,
+    pub unread_count: i64,
+    pub member_count: i64,
+}
+
+/// Una riga dell'export NDJSON di una chat: prima la chat, poi i membri, poi i messaggi
+#[derive(Serialize, Debug)]
+pub enum ChatExportRecord {
+    Chat(ChatDTO),
+    Member(UserInChatDTO),
+    Message(MessageDTO),
 }
@@ -0,0 +1,52 @@
+//! ChatRole DTOs - Data Transfer Objects per i ruoli delle chat
+
+use crate::entities::{ChatPermission, ChatRole, UserRole};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+/// Struct per gestire io col client, i flag del database diventano una lista di permessi
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ChatRoleDTO {
+    pub role_id: i32,
+    pub chat_id: i32,
+    pub name: String,
+    /// Presente per le righe dei ruoli base della chat
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_role: Option<UserRole>,
+    pub permissions: Vec<ChatPermission>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<ChatRole> for ChatRoleDTO {
+    fn from(value: ChatRole) -> Self {
+        Self {
+            permissions: value.permissions(),
+            role_id: value.role_id,
+            chat_id: value.chat_id,
+            name: value.name,
+            base_role: value.base_role,
+            created_at: value.created_at,
+        }
+    }
+}
+
+/// DTO per creare un ruolo personalizzato (la chat è presa dal path)
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+pub struct CreateChatRoleDTO {
+    #[validate(length(
+        min = 1,
+        max = 50,
+        message = "Role name must be between 1 and 50 characters"
+    ))]
+    pub name: String,
+    #[serde(default)]
+    pub permissions: Vec<ChatPermission>,
+}
+
+/// DTO per assegnare (Some) o togliere (None) il ruolo personalizzato di un Member
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct AssignChatRoleDTO {
+    pub role_id: Option<i32>,
+}
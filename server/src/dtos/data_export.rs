@@ -0,0 +1,60 @@
+//! DataExport DTOs - Data Transfer Objects per l'export dei dati personali
+
+use super::{ChatDTO, InvitationDTO, MessageDTO, PrivacySettingsDTO, UserDTO, UserInChatDTO};
+use crate::core::API_V1_PREFIX;
+use crate::entities::{DataExport, DataExportStatus};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Percorso da cui scaricare l'archivio dell'ultimo export completato, sotto il prefisso dell'API
+pub const DATA_EXPORT_DOWNLOAD_PATH: &str = "/users/me/export/download";
+
+/// Stato di un job di export, restituito al client che lo interroga
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct DataExportDTO {
+    pub export_id: i32,
+    pub state: DataExportStatus,
+    pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<DateTime<Utc>>,
+    /// Presente solo quando l'archivio è pronto
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub download_url: Option<String>,
+}
+
+impl From<DataExport> for DataExportDTO {
+    fn from(value: DataExport) -> Self {
+        let download_url = (value.state == DataExportStatus::Completed)
+            .then(|| format!("{}{}", API_V1_PREFIX, DATA_EXPORT_DOWNLOAD_PATH));
+        Self {
+            export_id: value.export_id,
+            state: value.state,
+            created_at: value.created_at,
+            completed_at: value.completed_at,
+            download_url,
+        }
+    }
+}
+
+/// DTO per creare un job di export (senza export_id e risultato)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreateDataExportDTO {
+    pub user_id: i32,
+}
+
+/// Contenuto dell'archivio JSON con tutti i dati dell'utente
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct UserDataArchiveDTO {
+    pub generated_at: DateTime<Utc>,
+    pub profile: UserDTO,
+    pub privacy: PrivacySettingsDTO,
+    /// Chat di cui l'utente è membro
+    pub chats: Vec<ChatDTO>,
+    /// Ruolo e data di ingresso per ogni chat
+    pub memberships: Vec<UserInChatDTO>,
+    /// Messaggi inviati dall'utente (esclusi quelli eliminati)
+    pub messages: Vec<MessageDTO>,
+    pub invitations_received: Vec<InvitationDTO>,
+    pub invitations_sent: Vec<InvitationDTO>,
+}
@@ -0,0 +1,32 @@
+//! Draft DTOs - Data Transfer Objects per le bozze
+
+use crate::entities::Draft;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+/// Struct per gestire io col client (la bozza è sempre dell'utente autenticato)
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct DraftDTO {
+    pub chat_id: i32,
+    pub content: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<Draft> for DraftDTO {
+    fn from(value: Draft) -> Self {
+        Self {
+            chat_id: value.chat_id,
+            content: value.content,
+            updated_at: value.updated_at,
+        }
+    }
+}
+
+/// DTO per salvare una bozza, un contenuto vuoto elimina la bozza
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+pub struct UpsertDraftDTO {
+    #[validate(length(max = 5000, message = "Draft content must be at most 5000 characters"))]
+    pub content: String,
+}
@@ -0,0 +1,57 @@
+//! Health DTOs - Risposte delle sonde di stato usate dall'orchestratore (es. Kubernetes)
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Esito di una sonda o di uno dei suoi controlli
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Ok,
+    Unavailable,
+}
+
+/// Singolo controllo eseguito dalla sonda di readiness
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct HealthCheckDTO {
+    pub name: String,
+    pub status: HealthStatus,
+    /// Durata del controllo in millisecondi
+    pub duration_ms: u64,
+    /// Motivo del fallimento, assente se il controllo è riuscito
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct HealthDTO {
+    pub status: HealthStatus,
+    /// Versione del server in esecuzione
+    pub version: String,
+    /// Profilo di esecuzione (`development`, `staging` o `production`)
+    pub environment: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub checks: Vec<HealthCheckDTO>,
+}
+
+impl HealthDTO {
+    /// Risposta di una sonda senza controlli: il processo è in grado di rispondere
+    pub fn ok(environment: &str) -> Self {
+        Self::from_checks(environment, Vec::new())
+    }
+
+    /// Risposta con l'esito dei controlli: la sonda è ok solo se lo sono tutti
+    pub fn from_checks(environment: &str, checks: Vec<HealthCheckDTO>) -> Self {
+        let status = if checks.iter().all(|check| check.status == HealthStatus::Ok) {
+            HealthStatus::Ok
+        } else {
+            HealthStatus::Unavailable
+        };
+        Self {
+            status,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            environment: environment.to_string(),
+            checks,
+        }
+    }
+}
@@ -3,15 +3,18 @@
 use crate::{dtos::{ChatDTO, UserDTO}, entities::{Invitation, InvitationStatus}};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
 
 /// Struct per gestire io col client
-#[derive(Serialize, Deserialize, Debug, Clone)]
-#[allow(dead_code)] // Non attualmente usata, mantenuta per compatibilità futura
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct InvitationDTO {
     pub invite_id: Option<i32>,
     pub target_chat_id: Option<i32>,
     pub invited_id: Option<i32>,
     pub invitee_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
     pub state: Option<InvitationStatus>,
     pub created_at: Option<DateTime<Utc>>,
 }
@@ -23,6 +26,7 @@ impl From<Invitation> for InvitationDTO {
             target_chat_id: Some(value.target_chat_id),
             invited_id: Some(value.invited_id),
             invitee_id: Some(value.invitee_id),
+            note: value.note,
             state: Some(value.state),
             created_at: Some(value.created_at),
         }
@@ -35,6 +39,14 @@ pub struct CreateInvitationDTO {
     pub target_chat_id: i32,
     pub invited_id: i32,
     pub invitee_id: i32,
+    pub note: Option<String>,
+}
+
+/// Body facoltativo di un invito: messaggio dell'inviter per l'invitato
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Validate, ToSchema)]
+pub struct InviteToChatDTO {
+    #[validate(length(max = 200, message = "Invitation note must not exceed 200 characters"))]
+    pub note: Option<String>,
 }
 
 /// DTO per aggiornare un invito (solo lo stato è modificabile)
@@ -44,11 +56,25 @@ pub struct UpdateInvitationDTO {
 }
 
 /// DTO arricchito con informazioni complete dell'inviter e della chat
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct EnrichedInvitationDTO {
     pub invite_id: i32,
     pub state: InvitationStatus,
     pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
     pub inviter: Option<UserDTO>,
     pub chat: Option<ChatDTO>,
 }
+
+/// DTO di un invito inviato, arricchito con l'utente invitato e la chat
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct SentInvitationDTO {
+    pub invite_id: i32,
+    pub state: InvitationStatus,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub invited: Option<UserDTO>,
+    pub chat: Option<ChatDTO>,
+}
@@ -0,0 +1,56 @@
+//! InviteLink DTOs - Data Transfer Objects per i link di invito
+
+use crate::entities::InviteLink;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+/// Struct per gestire io col client, senza l'hash del token
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct InviteLinkDTO {
+    pub link_id: i32,
+    pub chat_id: i32,
+    pub created_by: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Token in chiaro, presente solo nella risposta alla creazione
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+impl From<InviteLink> for InviteLinkDTO {
+    fn from(value: InviteLink) -> Self {
+        Self {
+            link_id: value.link_id,
+            chat_id: value.chat_id,
+            created_by: value.created_by,
+            created_at: value.created_at,
+            expires_at: value.expires_at,
+            token: None,
+        }
+    }
+}
+
+/// DTO per creare un link di invito (la chat è presa dal path)
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Validate, ToSchema)]
+pub struct CreateInviteLinkDTO {
+    /// Validità del link in ore (al massimo 30 giorni), se assente il link non scade
+    #[validate(range(
+        min = 1,
+        max = 720,
+        message = "Expiration must be between 1 hour and 30 days"
+    ))]
+    #[serde(default)]
+    pub expires_in_hours: Option<i64>,
+}
+
+/// DTO per salvare un nuovo link di invito (solo l'hash del token)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NewInviteLinkDTO {
+    pub chat_id: i32,
+    pub token_hash: String,
+    pub created_by: i32,
+    pub expires_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,44 @@
+//! JoinRequest DTOs - Data Transfer Objects per le richieste di accesso alle chat pubbliche
+
+use super::UserDTO;
+use crate::entities::{JoinRequest, JoinRequestStatus};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Struct per gestire io col client, usata anche come payload delle notifiche WebSocket
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct JoinRequestDTO {
+    pub request_id: i32,
+    pub chat_id: i32,
+    pub user_id: i32,
+    pub state: JoinRequestStatus,
+    pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_at: Option<DateTime<Utc>>,
+    /// Utente che ha chiesto di entrare, valorizzato per chi deve approvare
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<UserDTO>,
+}
+
+impl From<JoinRequest> for JoinRequestDTO {
+    fn from(value: JoinRequest) -> Self {
+        Self {
+            request_id: value.request_id,
+            chat_id: value.chat_id,
+            user_id: value.user_id,
+            state: value.state,
+            created_at: value.created_at,
+            resolved_at: value.resolved_at,
+            user: None, // da popolare manualmente se necessario
+        }
+    }
+}
+
+/// DTO per creare una richiesta di accesso (senza request_id e risoluzione)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreateJoinRequestDTO {
+    pub chat_id: i32,
+    pub user_id: i32,
+    pub state: JoinRequestStatus,
+}
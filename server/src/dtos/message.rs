@@ -1,36 +1,64 @@
 //! Message DTOs - Data Transfer Objects per messaggi
 
-use crate::entities::{Message, MessageType};
-use chrono::{DateTime, Utc};
+use crate::entities::{ContentFormat, Message, MessageRevision, MessageType};
+use chrono::{DateTime, SubsecRound, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use validator::Validate;
 
 /// Struct per gestire io col client
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct MessageDTO {
     pub message_id: Option<i32>,
     pub chat_id: Option<i32>,
     pub sender_id: Option<i32>,
     pub content: Option<String>,
     pub message_type: Option<MessageType>,
+    /// Formato del contenuto, se assente il messaggio è testo semplice
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_format: Option<ContentFormat>,
     pub created_at: Option<DateTime<Utc>>,
+    /// Messaggio citato, deve appartenere alla stessa chat
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_to_message_id: Option<i32>,
+    /// Allegato caricato in precedenza, deve appartenere alla stessa chat
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attachment_id: Option<i32>,
+    /// Id generato dal client, restituito nel frame SendResult al mittente. Un messaggio
+    /// ritrasmesso con lo stesso id non viene salvato di nuovo
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_msg_id: Option<String>,
+    /// Presente solo per i messaggi eliminati (tombstone)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 impl From<Message> for MessageDTO {
     fn from(value: Message) -> Self {
+        // i messaggi eliminati diventano tombstone: il contenuto non viene mai esposto al client
+        let content = match value.deleted_at {
+            Some(_) => None,
+            None => Some(value.content),
+        };
         Self {
             message_id: Some(value.message_id),
             chat_id: Some(value.chat_id),
             sender_id: Some(value.sender_id),
-            content: Some(value.content),
+            content,
             message_type: Some(value.message_type),
+            content_format: Some(value.content_format),
             created_at: Some(value.created_at),
+            reply_to_message_id: value.reply_to_message_id,
+            attachment_id: value.attachment_id,
+            client_msg_id: None,
+            deleted_at: value.deleted_at,
         }
     }
 }
 
 /// DTO per creare un nuovo messaggio (senza message_id)
 #[derive(Serialize, Deserialize, Debug, Clone, Validate)]
+#[validate(schema(function = "validate_content_format"))]
 pub struct CreateMessageDTO {
     pub chat_id: i32,
     pub sender_id: i32,
@@ -42,24 +70,80 @@ pub struct CreateMessageDTO {
     ))]
     pub content: String,
     pub message_type: MessageType,
+    pub content_format: ContentFormat,
     pub created_at: DateTime<Utc>,
+    pub reply_to_message_id: Option<i32>,
+    pub attachment_id: Option<i32>,
+
+    #[validate(length(
+        min = 1,
+        max = 64,
+        message = "Client message id must be between 1 and 64 characters"
+    ))]
+    pub client_msg_id: Option<String>,
+}
+
+fn validate_content_format(message: &CreateMessageDTO) -> Result<(), validator::ValidationError> {
+    match message.content_format {
+        ContentFormat::Plain => Ok(()),
+        ContentFormat::Markdown => validate_markdown(&message.content),
+    }
 }
 
 impl TryFrom<MessageDTO> for CreateMessageDTO {
     type Error = &'static str;
     fn try_from(value: MessageDTO) -> Result<Self, Self::Error> {
+        let content_format = value.content_format.unwrap_or_default();
+        let content = value.content.ok_or("content missing")?;
+        // il markdown viene salvato già sanificato, così nessun client riceve HTML grezzo
+        let content = match content_format {
+            ContentFormat::Plain => content,
+            ContentFormat::Markdown => sanitize_markdown(&content),
+        };
         Ok(Self {
             chat_id: value.chat_id.ok_or("chat_id missing")?,
             sender_id: value.sender_id.ok_or("sender_id missing")?,
-            content: value.content.ok_or("content missing")?,
+            content,
             message_type: value.message_type.ok_or("message_type missing")?,
-            created_at: value.created_at.unwrap_or_else(Utc::now),
+            content_format,
+            // la data del client non è affidabile: conta quella di arrivo al server, al secondo
+            // come la colonna, così il messaggio inoltrato ha la stessa data di quello salvato
+            created_at: Utc::now().trunc_subsecs(0),
+            reply_to_message_id: value.reply_to_message_id,
+            attachment_id: value.attachment_id,
+            client_msg_id: value.client_msg_id,
         })
     }
 }
 
+/// Esito dell'invio di un messaggio via WebSocket, inviato al mittente che ha fornito
+/// `client_msg_id`. Se il messaggio è stato rifiutato sono presenti `code` e `message`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SendResultDTO {
+    pub client_msg_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Messaggi di una chat persi dal client mentre era disconnesso, reinviati alla riconnessione
+/// dal più vecchio al più recente
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct MissedMessagesDTO {
+    pub chat_id: i32,
+    pub messages: Vec<MessageDTO>,
+    /// Se true sono stati reinviati solo i messaggi più recenti: i precedenti vanno
+    /// recuperati con la cronologia paginata
+    pub truncated: bool,
+}
+
 /// DTO per aggiornare un messaggio (solo campi modificabili)
-#[derive(Serialize, Deserialize, Debug, Clone, Validate)]
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
 pub struct UpdateMessageDTO {
     #[validate(length(
         min = 1,
@@ -68,3 +152,223 @@ pub struct UpdateMessageDTO {
     ))]
     pub content: Option<String>,
 }
+
+/// Versione precedente di un messaggio, restituita dalla cronologia delle modifiche
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct MessageRevisionDTO {
+    pub revision_id: i32,
+    pub message_id: i32,
+    pub content: String,
+    /// Istante della modifica che ha sostituito questo contenuto
+    pub replaced_at: DateTime<Utc>,
+}
+
+impl From<MessageRevision> for MessageRevisionDTO {
+    fn from(value: MessageRevision) -> Self {
+        Self {
+            revision_id: value.revision_id,
+            message_id: value.message_id,
+            content: value.content,
+            replaced_at: value.replaced_at,
+        }
+    }
+}
+
+/// Traduzione automatica di un messaggio, allegata al messaggio originale
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct MessageTranslationDTO {
+    pub message: MessageDTO,
+    pub target_lang: String,
+    /// Lingua rilevata dal provider, se disponibile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_lang: Option<String>,
+    pub translated_content: String,
+}
+
+/// Schemi di link non ammessi nel markdown: eseguono codice o incorporano contenuti nel client
+const UNSAFE_LINK_SCHEMES: [&str; 4] = ["javascript:", "vbscript:", "data:", "file:"];
+
+/// Neutralizza l'HTML grezzo nel markdown: fuori dal codice `<` diventa `&lt;`, così il client
+/// non interpreta tag scritti dall'utente. I caratteri di controllo vengono rimossi.
+pub fn sanitize_markdown(content: &str) -> String {
+    let mut sanitized = String::with_capacity(content.len());
+    let mut in_fence = false;
+
+    for (i, line) in content.split('\n').enumerate() {
+        if i > 0 {
+            sanitized.push('\n');
+        }
+        let line: String = line
+            .chars()
+            .filter(|c| !c.is_control() || *c == '\t')
+            .collect();
+
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            sanitized.push_str(&line);
+            continue;
+        }
+        if in_fence {
+            sanitized.push_str(&line);
+            continue;
+        }
+        let line: Vec<char> = line.chars().collect();
+
+        // uno span di codice inline conta solo se il backtick viene chiuso sulla stessa riga,
+        // altrimenti il renderer mostrerebbe il backtick e interpreterebbe l'HTML che segue
+        let mut in_code = false;
+        for (j, c) in line.iter().enumerate() {
+            match c {
+                '`' if in_code => in_code = false,
+                '`' => in_code = line[j + 1..].contains(&'`'),
+                '<' if !in_code => {
+                    sanitized.push_str("&lt;");
+                    continue;
+                }
+                _ => {}
+            }
+            sanitized.push(*c);
+        }
+    }
+
+    sanitized
+}
+
+/// Rifiuta i link markdown (inline o reference) verso schemi non sicuri
+pub fn validate_markdown(content: &str) -> Result<(), validator::ValidationError> {
+    let is_unsafe = |target: &str| {
+        // i browser ignorano spazi e caratteri di controllo negli URL (es. "java\tscript:")
+        let normalized: String = target
+            .trim_start_matches('<')
+            .chars()
+            .filter(|c| !c.is_whitespace() && !c.is_control())
+            .flat_map(char::to_lowercase)
+            .collect();
+        UNSAFE_LINK_SCHEMES
+            .iter()
+            .any(|scheme| normalized.starts_with(scheme))
+    };
+
+    let inline_links = content.match_indices("](").map(|(i, _)| &content[i + 2..]);
+    let reference_links = content.lines().filter_map(|line| {
+        let line = line.trim_start();
+        let end = line.find("]:")?;
+        line.starts_with('[').then(|| &line[end + 2..])
+    });
+
+    if inline_links.chain(reference_links).any(is_unsafe) {
+        let mut error = validator::ValidationError::new("unsafe_markdown_link");
+        error.message = Some("Markdown links must not use unsafe schemes".into());
+        return Err(error);
+    }
+    Ok(())
+}
+
+/// Intervallo [start, end) da evidenziare nel contenuto, in caratteri (non byte)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct HighlightRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Risultato della ricerca full-text: il messaggio e le occorrenze dei termini cercati
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct MessageSearchResultDTO {
+    pub message: MessageDTO,
+    pub highlights: Vec<HighlightRange>,
+}
+
+impl MessageSearchResultDTO {
+    pub fn new(message: Message, query: &str) -> Self {
+        let highlights = highlight_ranges(&message.content, query);
+        Self {
+            message: MessageDTO::from(message),
+            highlights,
+        }
+    }
+}
+
+/// Calcola le occorrenze (case-insensitive) di ogni termine della query nel contenuto.
+/// Gli intervalli sovrapposti vengono fusi e restituiti in ordine.
+pub fn highlight_ranges(content: &str, query: &str) -> Vec<HighlightRange> {
+    let haystack: Vec<char> = content.chars().flat_map(char::to_lowercase).collect();
+    // to_lowercase può espandere un carattere in più caratteri: in quel caso gli offset
+    // non sarebbero più allineati al contenuto originale, quindi si rinuncia all'highlight
+    if haystack.len() != content.chars().count() {
+        return Vec::new();
+    }
+
+    let mut ranges: Vec<HighlightRange> = Vec::new();
+    for term in query.split_whitespace() {
+        let term: Vec<char> = term
+            .trim_matches(|c: char| !c.is_alphanumeric())
+            .chars()
+            .flat_map(char::to_lowercase)
+            .collect();
+        if term.is_empty() || term.len() > haystack.len() {
+            continue;
+        }
+        for start in 0..=haystack.len() - term.len() {
+            if haystack[start..start + term.len()] == term[..] {
+                ranges.push(HighlightRange {
+                    start,
+                    end: start + term.len(),
+                });
+            }
+        }
+    }
+
+    ranges.sort_by_key(|r| r.start);
+    let mut merged: Vec<HighlightRange> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight_ranges_case_insensitive_and_merged() {
+        let ranges = highlight_ranges("Hello hello world", "HELLO lo");
+        assert_eq!(
+            ranges,
+            vec![
+                HighlightRange { start: 0, end: 5 },
+                HighlightRange { start: 6, end: 11 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sanitize_markdown_escapes_html_outside_code() {
+        let sanitized = sanitize_markdown("**hi** <img src=x onerror=alert(1)> `<b>`\n```\n<div>\n```");
+        assert_eq!(
+            sanitized,
+            "**hi** &lt;img src=x onerror=alert(1)> `<b>`\n```\n<div>\n```"
+        );
+        // backtick non chiuso: l'HTML che segue va comunque neutralizzato
+        assert_eq!(sanitize_markdown("`<script>"), "`&lt;script>");
+    }
+
+    #[test]
+    fn test_validate_markdown_rejects_unsafe_links() {
+        assert!(validate_markdown("[docs](https://example.com)\n[x]: http://a.b").is_ok());
+        assert!(validate_markdown("[click](javascript:alert(1))").is_err());
+        assert!(validate_markdown("[click]( JavaScript:alert(1))").is_err());
+        assert!(validate_markdown("[ref]: data:text/html;base64,xyz").is_err());
+    }
+
+    #[test]
+    fn test_highlight_ranges_uses_char_offsets() {
+        // "è" occupa due byte ma un solo carattere
+        let ranges = highlight_ranges("caffè pronto", "pronto");
+        assert_eq!(ranges, vec![HighlightRange { start: 6, end: 12 }]);
+    }
+}
@@ -3,17 +3,74 @@
 //! Questo modulo contiene tutti i DTOs usati per la comunicazione client-server.
 //! I DTOs separano la rappresentazione esterna (API) dalla rappresentazione interna (entities).
 
+pub mod admin;
+pub mod attachment;
+pub mod audit;
+pub mod banned_member;
 pub mod chat;
+pub mod chat_role;
+pub mod data_export;
+pub mod draft;
+pub mod health;
 pub mod invitation;
+pub mod invite_link;
+pub mod join_request;
 pub mod message;
 pub mod query;
+pub mod refresh_token;
+pub mod report;
+pub mod search;
+pub mod session;
+pub mod sync;
 pub mod user;
 pub mod user_chat_metadata;
+pub mod user_identity;
+pub mod user_keys;
+pub mod webhook;
 
 // Re-exports per mantenere la compatibilità con il codice esistente
-pub use chat::{ChatDTO, CreateChatDTO, UpdateChatDTO};
-pub use invitation::{CreateInvitationDTO, EnrichedInvitationDTO, UpdateInvitationDTO};
-pub use message::{CreateMessageDTO, MessageDTO, UpdateMessageDTO};
-pub use query::{MessagesQuery, UserSearchQuery};
-pub use user::{CreateUserDTO, UpdateUserDTO, UserDTO};
-pub use user_chat_metadata::{CreateUserChatMetadataDTO, UpdateUserChatMetadataDTO, UserInChatDTO};
+pub use admin::{
+    AdminChatDTO, AdminStatsDTO, AdminUserDTO, DbPoolStatsDTO, RuntimeConfigDTO, ServerStatsDTO,
+};
+pub use attachment::{AttachmentDTO, CreateAttachmentDTO};
+pub use audit::{AuditEntryDTO, CreateAuditEntryDTO};
+pub use banned_member::BannedMemberDTO;
+pub use chat::{
+    ChatDTO, ChatExportRecord, ChatOverviewDTO, CreateChatDTO, PublicChatDTO, UpdateChatDTO,
+};
+pub use chat_role::{AssignChatRoleDTO, ChatRoleDTO, CreateChatRoleDTO};
+pub use data_export::{CreateDataExportDTO, DataExportDTO, UserDataArchiveDTO};
+pub use draft::{DraftDTO, UpsertDraftDTO};
+pub use health::{HealthCheckDTO, HealthDTO, HealthStatus};
+pub use invitation::{
+    CreateInvitationDTO, EnrichedInvitationDTO, InvitationDTO, InviteToChatDTO, SentInvitationDTO,
+    UpdateInvitationDTO,
+};
+pub use invite_link::{CreateInviteLinkDTO, InviteLinkDTO, NewInviteLinkDTO};
+pub use join_request::{CreateJoinRequestDTO, JoinRequestDTO};
+pub use message::{
+    CreateMessageDTO, MessageDTO, MessageRevisionDTO, MessageSearchResultDTO,
+    MessageTranslationDTO, MissedMessagesDTO, SendResultDTO, UpdateMessageDTO,
+};
+pub use query::{
+    AccountDeletionMode, AdminReportQuery, AdminUserQuery, AuditLogQuery, DeleteAccountQuery,
+    DiscoverChatsQuery, GlobalSearchQuery, MembersQuery, MessagePollQuery, MessageSearchQuery,
+    MessagesQuery, MuteMemberQuery, OidcCallbackQuery, SentInvitationsQuery, SyncQuery,
+    TranslateQuery, UserSearchQuery, WsConnectQuery,
+};
+pub use refresh_token::{AuthTokensDTO, CreateRefreshTokenDTO, RefreshTokenDTO};
+pub use report::{CreateReportDTO, ReportDTO, SubmitReportDTO};
+pub use search::GlobalSearchResultDTO;
+pub use session::SessionDTO;
+pub use sync::{SyncDTO, SyncToken};
+pub use user::{
+    CreateUserDTO, PresenceDTO, PrivacySettingsDTO, SetStatusDTO, UpdateProfileDTO, UpdateUserDTO,
+    UserDTO, UserStatusDTO,
+};
+pub use user_chat_metadata::{
+    CreateUserChatMetadataDTO, FloodAlertDTO, UnreadCountDTO, UpdateUserChatMetadataDTO,
+    UserInChatDTO,
+};
+pub use user_identity::CreateUserIdentityDTO;
+pub use user_keys::{KeyBundleDTO, KeyStatusDTO, UploadKeysDTO};
+pub use webhook::{CreateWebhookDTO, NewWebhookDTO, WebhookDTO, WebhookMessageDTO};
@@ -1,17 +1,286 @@
 //! Query DTOs - Data Transfer Objects per query di ricerca
 
+use crate::entities::{AuditAction, InvitationStatus, ReportStatus, UserRole};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
 
 /// DTO per query parameters di ricerca utenti
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct UserSearchQuery {
     pub search: String,
 }
 
+/// DTO per query parameters del redirect del provider OIDC
+#[derive(Serialize, Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct OidcCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    /// Presente se l'utente ha negato il consenso o il provider ha rifiutato la richiesta
+    pub error: Option<String>,
+}
+
+/// Modalità di cancellazione dell'account
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountDeletionMode {
+    /// Cancellazione immediata e definitiva
+    #[default]
+    Delete,
+    /// Disattivazione, riattivabile con un nuovo login entro la finestra prevista
+    Deactivate,
+}
+
+/// DTO per query parameters della cancellazione dell'account
+#[derive(Serialize, Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeleteAccountQuery {
+    pub mode: Option<AccountDeletionMode>,
+}
+
 /// DTO per query parameters di paginazione messaggi
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct MessagesQuery {
     #[serde(default)]
     pub before_date: Option<DateTime<Utc>>,
 }
+
+/// DTO per query parameters del long polling dei nuovi messaggi
+#[derive(Serialize, Deserialize, Debug, Validate, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MessagePollQuery {
+    /// Ultimo message_id ricevuto dal client
+    pub after: i32,
+    /// Attesa massima in secondi prima di rispondere senza messaggi
+    #[serde(default)]
+    #[validate(range(
+        min = 1,
+        max = 60,
+        message = "Timeout must be between 1 and 60 seconds"
+    ))]
+    pub timeout: Option<u64>,
+}
+
+/// DTO per query parameters della ricerca full-text nei messaggi di una chat
+#[derive(Serialize, Deserialize, Debug, Validate, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MessageSearchQuery {
+    #[validate(length(
+        min = 1,
+        max = 200,
+        message = "Search query must be between 1 and 200 characters"
+    ))]
+    pub q: String,
+    #[serde(default)]
+    pub before_date: Option<DateTime<Utc>>,
+    #[serde(default)]
+    #[validate(range(min = 1, max = 100, message = "Limit must be between 1 and 100"))]
+    pub limit: Option<i64>,
+}
+
+/// DTO per query parameters della ricerca globale (messaggi, chat e utenti)
+#[derive(Serialize, Deserialize, Debug, Validate, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GlobalSearchQuery {
+    #[validate(length(
+        min = 1,
+        max = 200,
+        message = "Search query must be between 1 and 200 characters"
+    ))]
+    pub q: String,
+    /// Numero massimo di risultati per ogni gruppo
+    #[serde(default)]
+    #[validate(range(min = 1, max = 50, message = "Limit must be between 1 and 50"))]
+    pub limit: Option<i64>,
+}
+
+/// DTO per query parameters della directory delle chat pubbliche
+#[derive(Serialize, Deserialize, Debug, Validate, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DiscoverChatsQuery {
+    /// Testo cercato in titolo e descrizione, se assente elenca tutte le chat pubbliche
+    #[serde(default)]
+    #[validate(length(max = 200, message = "Search query must not exceed 200 characters"))]
+    pub q: Option<String>,
+    #[serde(default)]
+    #[validate(range(min = 1, max = 50, message = "Limit must be between 1 and 50"))]
+    pub limit: Option<i64>,
+    #[serde(default)]
+    #[validate(range(min = 0, message = "Offset must not be negative"))]
+    pub offset: Option<i64>,
+}
+
+/// DTO per query parameters dell'audit log di una chat (dal più recente)
+#[derive(Serialize, Deserialize, Debug, Validate, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditLogQuery {
+    #[serde(default)]
+    #[validate(range(min = 1, max = 100, message = "Limit must be between 1 and 100"))]
+    pub limit: Option<i64>,
+    #[serde(default)]
+    #[validate(range(min = 0, message = "Offset must not be negative"))]
+    pub offset: Option<i64>,
+    /// Solo le voci di questa azione
+    #[serde(default)]
+    pub action: Option<AuditAction>,
+}
+
+/// DTO per query parameters della lista utenti dell'amministrazione
+#[derive(Serialize, Deserialize, Debug, Validate, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AdminUserQuery {
+    /// Testo contenuto nello username, senza filtro se assente
+    #[serde(default)]
+    pub search: Option<String>,
+    #[serde(default)]
+    #[validate(range(min = 1, max = 100, message = "Limit must be between 1 and 100"))]
+    pub limit: Option<i64>,
+    #[serde(default)]
+    #[validate(range(min = 0, message = "Offset must not be negative"))]
+    pub offset: Option<i64>,
+}
+
+/// DTO per query parameters della coda delle segnalazioni, dalla più vecchia
+#[derive(Serialize, Deserialize, Debug, Validate, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AdminReportQuery {
+    /// Stato delle segnalazioni, se assente solo quelle ancora da esaminare
+    #[serde(default)]
+    pub state: Option<ReportStatus>,
+    #[serde(default)]
+    #[validate(range(min = 1, max = 100, message = "Limit must be between 1 and 100"))]
+    pub limit: Option<i64>,
+    #[serde(default)]
+    #[validate(range(min = 0, message = "Offset must not be negative"))]
+    pub offset: Option<i64>,
+}
+
+/// DTO per query parameters degli inviti inviati, senza stato li elenca tutti
+#[derive(Serialize, Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SentInvitationsQuery {
+    #[serde(default)]
+    pub status: Option<InvitationStatus>,
+}
+
+/// DTO per query parameters della lista dei membri di una chat, ordinata per user_id
+#[derive(Serialize, Deserialize, Debug, Validate, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MembersQuery {
+    /// Membri per pagina, se assente vengono restituiti tutti
+    #[serde(default)]
+    #[validate(range(min = 1, max = 500, message = "Limit must be between 1 and 500"))]
+    pub limit: Option<i64>,
+    /// Ultimo user_id della pagina precedente
+    #[serde(default)]
+    pub after_user_id: Option<i32>,
+    /// Solo i membri con questo ruolo
+    #[serde(default)]
+    pub role: Option<UserRole>,
+}
+
+/// DTO per query parameters del silenziamento di un membro
+#[derive(Serialize, Deserialize, Debug, Validate, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MuteMemberQuery {
+    /// Durata del silenziamento in secondi (al massimo 30 giorni)
+    #[validate(range(
+        min = 1,
+        max = 2_592_000,
+        message = "Duration must be between 1 second and 30 days"
+    ))]
+    pub duration: i64,
+}
+
+/// DTO per query parameters della traduzione di un messaggio
+#[derive(Serialize, Deserialize, Debug, Validate, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TranslateQuery {
+    /// Lingua di destinazione (codice ISO 639-1, es. "en" oppure "pt-BR")
+    #[validate(custom(
+        function = "validate_language_code",
+        message = "Language must be an ISO 639-1 code, optionally with a region (e.g. en, pt-BR)"
+    ))]
+    pub lang: String,
+}
+
+/// DTO per query parameters della sincronizzazione incrementale
+#[derive(Serialize, Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SyncQuery {
+    /// `sync_token` restituito dalla sincronizzazione precedente, assente alla prima
+    #[serde(default)]
+    pub since: Option<String>,
+}
+
+/// DTO per query parameters della connessione WebSocket
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct WsConnectQuery {
+    /// Ultimo messaggio ricevuto per ogni chat, come coppie `chat_id:message_id` separate da
+    /// virgole (es. `1:42,3:17`): i messaggi successivi vengono reinviati prima di quelli in
+    /// tempo reale. Le chat non indicate non hanno replay
+    #[serde(default)]
+    #[validate(custom(
+        function = "validate_resume_cursors",
+        message = "Since must be a list of chat_id:message_id pairs (e.g. 1:42,3:17)"
+    ))]
+    pub since: Option<String>,
+    /// Nome del dispositivo, mostrato nell'elenco delle sessioni attive
+    #[serde(default)]
+    #[validate(length(
+        min = 1,
+        max = 64,
+        message = "Device name must be between 1 and 64 characters"
+    ))]
+    pub device: Option<String>,
+}
+
+impl WsConnectQuery {
+    /// Cursori di ripresa: chat_id -> ultimo message_id ricevuto dal client
+    pub fn resume_cursors(&self) -> HashMap<i32, i32> {
+        self.since
+            .as_deref()
+            .map(|since| since.split(',').filter_map(parse_resume_cursor).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Numero massimo di chat indicate in `since`
+const MAX_RESUME_CURSORS: usize = 1000;
+
+fn parse_resume_cursor(cursor: &str) -> Option<(i32, i32)> {
+    let (chat_id, message_id) = cursor.trim().split_once(':')?;
+    let chat_id = chat_id.parse().ok()?;
+    let message_id = message_id.parse().ok()?;
+    (message_id >= 0).then_some((chat_id, message_id))
+}
+
+fn validate_resume_cursors(since: &str) -> Result<(), validator::ValidationError> {
+    let cursors: Vec<&str> = since.split(',').collect();
+    if cursors.len() <= MAX_RESUME_CURSORS
+        && cursors
+            .iter()
+            .all(|cursor| parse_resume_cursor(cursor).is_some())
+    {
+        Ok(())
+    } else {
+        Err(validator::ValidationError::new("invalid_resume_cursor"))
+    }
+}
+
+fn validate_language_code(lang: &str) -> Result<(), validator::ValidationError> {
+    lazy_static::lazy_static! {
+        static ref LANG_REGEX: regex::Regex = regex::Regex::new(r"^[a-zA-Z]{2,3}(-[a-zA-Z]{2})?$").unwrap();
+    }
+
+    if LANG_REGEX.is_match(lang) {
+        Ok(())
+    } else {
+        Err(validator::ValidationError::new("invalid_language"))
+    }
+}
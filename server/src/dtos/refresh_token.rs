@@ -0,0 +1,30 @@
+//! RefreshToken DTOs - Data Transfer Objects per login e rotazione dei token
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// DTO per salvare un nuovo refresh token (solo l'hash)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreateRefreshTokenDTO {
+    pub user_id: i32,
+    pub family_id: String,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// DTO per POST /auth/refresh
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct RefreshTokenDTO {
+    pub refresh_token: String,
+}
+
+/// Coppia di token restituita da login e refresh
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct AuthTokensDTO {
+    pub access_token: String,
+    pub token_type: String,
+    /// Durata dell'access token in secondi
+    pub expires_in: i64,
+    pub refresh_token: String,
+}
@@ -0,0 +1,78 @@
+//! Report DTOs - Data Transfer Objects per le segnalazioni di messaggi e utenti
+
+use super::MessageDTO;
+use crate::entities::{Report, ReportReason, ReportStatus};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+/// Struct per gestire io col client
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ReportDTO {
+    pub report_id: i32,
+    /// Assente per le segnalazioni automatiche del filtro dei contenuti
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reporter_id: Option<i32>,
+    pub reported_user_id: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chat_id: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<i32>,
+    pub reason: ReportReason,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
+    pub state: ReportStatus,
+    pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_by: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_at: Option<DateTime<Utc>>,
+    /// Messaggio segnalato, valorizzato nella coda degli amministratori
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<MessageDTO>,
+}
+
+impl From<Report> for ReportDTO {
+    fn from(value: Report) -> Self {
+        Self {
+            report_id: value.report_id,
+            reporter_id: value.reporter_id,
+            reported_user_id: value.reported_user_id,
+            chat_id: value.chat_id,
+            message_id: value.message_id,
+            reason: value.reason,
+            details: value.details,
+            state: value.state,
+            created_at: value.created_at,
+            resolved_by: value.resolved_by,
+            resolved_at: value.resolved_at,
+            message: None, // da popolare manualmente se necessario
+        }
+    }
+}
+
+/// DTO inviato dal client per segnalare un messaggio o un utente (il bersaglio è nel path)
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+pub struct SubmitReportDTO {
+    pub reason: ReportReason,
+    /// Descrizione libera, utile soprattutto con il motivo `other`
+    #[serde(default)]
+    #[validate(length(
+        min = 1,
+        max = 500,
+        message = "Details must be between 1 and 500 characters"
+    ))]
+    pub details: Option<String>,
+}
+
+/// DTO per salvare una segnalazione (senza report_id e risoluzione)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreateReportDTO {
+    pub reporter_id: Option<i32>,
+    pub reported_user_id: i32,
+    pub chat_id: Option<i32>,
+    pub message_id: Option<i32>,
+    pub reason: ReportReason,
+    pub details: Option<String>,
+}
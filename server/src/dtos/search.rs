@@ -0,0 +1,16 @@
+//! Search DTOs - Data Transfer Objects per la ricerca globale
+
+use super::{ChatDTO, MessageSearchResultDTO, UserDTO};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Risultati della ricerca globale raggruppati per tipo
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct GlobalSearchResultDTO {
+    /// Messaggi delle chat dell'utente, dal più recente
+    pub messages: Vec<MessageSearchResultDTO>,
+    /// Chat dell'utente il cui titolo contiene la query
+    pub chats: Vec<ChatDTO>,
+    /// Utenti il cui username inizia con la query
+    pub users: Vec<UserDTO>,
+}
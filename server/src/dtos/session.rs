@@ -0,0 +1,37 @@
+//! Session DTOs - Data Transfer Objects per le connessioni WebSocket attive
+
+use crate::ws::signal_queue::QueueStats;
+use crate::ws::usermap::ConnectionInfo;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use utoipa::ToSchema;
+
+/// Connessione WebSocket attiva dell'utente, con i dati del dispositivo
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct SessionDTO {
+    pub connection_id: String,
+    pub device_name: Option<String>,
+    #[schema(value_type = Option<String>)]
+    pub ip: Option<IpAddr>,
+    pub user_agent: Option<String>,
+    pub connected_at: DateTime<Utc>,
+    /// Segnali in attesa di essere inviati: cresce se il client non legge abbastanza in fretta
+    pub queued_signals: usize,
+    /// Segnali scartati per overflow della coda dall'apertura della connessione
+    pub dropped_signals: u64,
+}
+
+impl SessionDTO {
+    pub fn new(connection: ConnectionInfo, stats: QueueStats) -> Self {
+        Self {
+            connection_id: connection.connection_id,
+            device_name: connection.device_name,
+            ip: connection.ip,
+            user_agent: connection.user_agent,
+            connected_at: connection.connected_at,
+            queued_signals: stats.queued,
+            dropped_signals: stats.dropped,
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! Sync DTOs - Data Transfer Objects per la sincronizzazione incrementale
+
+use super::{ChatDTO, EnrichedInvitationDTO, MissedMessagesDTO, SentInvitationDTO};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Posizione del client nella sincronizzazione, emessa dal server e opaca per il client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncToken {
+    /// Ultimo messaggio già consegnato al client
+    pub last_message_id: i32,
+    /// Istante a cui risalgono le modifiche già consegnate (membership e inviti). Ha la
+    /// precisione al secondo delle colonne timestamp: le modifiche vanno cercate a partire
+    /// da questo istante INCLUSO, a costo di restituirne di nuovo qualcuna
+    pub issued_at: DateTime<Utc>,
+}
+
+impl SyncToken {
+    /// Codifica il token come `{last_message_id}.{issued_at in secondi}` in base64 URL-safe
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(format!(
+            "{}.{}",
+            self.last_message_id,
+            self.issued_at.timestamp()
+        ))
+    }
+
+    /// Decodifica un token emesso da `encode`, None se non è valido
+    pub fn decode(token: &str) -> Option<Self> {
+        let raw = URL_SAFE_NO_PAD.decode(token).ok()?;
+        let raw = String::from_utf8(raw).ok()?;
+        let (message_id, seconds) = raw.split_once('.')?;
+        let last_message_id: i32 = message_id.parse().ok()?;
+        let issued_at = DateTime::from_timestamp(seconds.parse().ok()?, 0)?;
+        (last_message_id >= 0).then_some(Self {
+            last_message_id,
+            issued_at,
+        })
+    }
+}
+
+/// Modifiche avvenute dopo il token indicato dal client
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct SyncDTO {
+    /// Token da passare come `since` alla sincronizzazione successiva
+    pub sync_token: String,
+    /// Chat di cui l'utente è membro ora: quelle che il client conosce e che mancano qui
+    /// sono state lasciate o l'utente ne è stato rimosso
+    pub chat_ids: Vec<i32>,
+    /// Chat in cui l'utente è entrato dopo il token (tutte alla prima sincronizzazione)
+    pub joined_chats: Vec<ChatDTO>,
+    /// Messaggi nuovi delle chat dell'utente, raggruppati per chat
+    pub messages: Vec<MissedMessagesDTO>,
+    /// Inviti ricevuti dopo il token (i pendenti alla prima sincronizzazione)
+    pub received_invitations: Vec<EnrichedInvitationDTO>,
+    /// Inviti inviati dall'utente che hanno avuto una risposta dopo il token
+    pub answered_invitations: Vec<SentInvitationDTO>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sync_token_round_trip() {
+        let token = SyncToken {
+            last_message_id: 42,
+            issued_at: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+        };
+        assert_eq!(SyncToken::decode(&token.encode()), Some(token));
+    }
+
+    #[test]
+    fn test_sync_token_rejects_garbage() {
+        assert_eq!(SyncToken::decode("not a token"), None);
+        assert_eq!(SyncToken::decode(&URL_SAFE_NO_PAD.encode("42")), None);
+        assert_eq!(SyncToken::decode(&URL_SAFE_NO_PAD.encode("-1.0")), None);
+        assert_eq!(SyncToken::decode(&URL_SAFE_NO_PAD.encode("x.0")), None);
+    }
+}
@@ -1,27 +1,124 @@
 //! User DTOs - Data Transfer Objects per utenti
 
-use crate::entities::User;
+use crate::entities::{PresenceVisibility, User};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use validator::Validate;
 
 // struct per gestire io col client
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct UserDTO {
     pub id: Option<i32>,
     pub username: Option<String>,
+    /// Presenza, valorizzata dai service che conoscono lo stato della UserMap
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub online: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen: Option<DateTime<Utc>>,
+    /// Campi del profilo, assenti se l'utente non li ha impostati
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bio: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avatar_url: Option<String>,
+    /// Stato personalizzato, assente se non impostato o scaduto
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_expires_at: Option<DateTime<Utc>>,
 }
 
 impl From<User> for UserDTO {
     fn from(value: User) -> Self {
+        // un account disattivato è mostrato come anonimo, senza profilo né presenza
+        if value.deactivated_at.is_some() {
+            return Self {
+                id: Some(value.user_id),
+                username: Some(value.public_username().to_string()),
+                online: None,
+                last_seen: None,
+                display_name: None,
+                bio: None,
+                avatar_url: None,
+                status: None,
+                status_expires_at: None,
+            };
+        }
+        let status = value.active_status().map(str::to_string);
+        let status_expires_at = value.status_expires_at.filter(|_| status.is_some());
+        // il last_seen è esposto solo se visibile a tutti, altrimenti lo aggiunge
+        // with_presence dopo aver verificato chi sta guardando
+        let last_seen = value
+            .last_seen
+            .filter(|_| value.presence_visibility == PresenceVisibility::Everyone);
         Self {
             id: Some(value.user_id),
             username: Some(value.username),
+            online: None,
+            last_seen,
+            display_name: value.display_name,
+            bio: value.bio,
+            avatar_url: value.avatar_url,
+            status,
+            status_expires_at,
         }
     }
 }
 
+impl UserDTO {
+    /// Aggiunge lo stato di presenza; il last_seen in memoria è più recente di quello su db
+    pub fn with_presence(mut self, online: bool, last_seen: Option<DateTime<Utc>>) -> Self {
+        self.online = Some(online);
+        if last_seen.is_some() {
+            self.last_seen = last_seen;
+        }
+        self
+    }
+}
+
+/// Evento di cambio presenza inviato ai membri delle chat dell'utente
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PresenceDTO {
+    pub user_id: i32,
+    pub online: bool,
+    pub last_seen: Option<DateTime<Utc>>,
+}
+
+/// Evento di cambio dello stato personalizzato, inviato ai membri delle chat dell'utente
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct UserStatusDTO {
+    pub user_id: i32,
+    /// None se lo stato è stato cancellato
+    pub status: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// DTO per impostare lo stato personalizzato (status assente o vuoto = cancellazione)
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Validate, ToSchema)]
+pub struct SetStatusDTO {
+    #[validate(length(max = 100, message = "Status must not exceed 100 characters"))]
+    pub status: Option<String>,
+
+    /// Durata dello stato in secondi (al massimo 30 giorni), senza scadenza se assente
+    #[validate(range(
+        min = 1,
+        max = 2_592_000,
+        message = "Duration must be between 1 second and 30 days"
+    ))]
+    pub duration: Option<i64>,
+}
+
+/// Impostazioni di privacy dell'utente
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct PrivacySettingsDTO {
+    /// Chi può vedere stato online e last_seen
+    pub presence_visibility: PresenceVisibility,
+}
+
 /// DTO per creare un nuovo utente (senza user_id)
-#[derive(Serialize, Deserialize, Debug, Clone, Validate)]
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
 pub struct CreateUserDTO {
     #[validate(length(
         min = 3,
@@ -34,15 +131,11 @@ pub struct CreateUserDTO {
     ))]
     pub username: String,
 
-    #[validate(length(min = 8, message = "Password must be at least 8 characters"))]
-    #[validate(custom(
-        function = "validate_password_strength",
-        message = "Password must contain at least one uppercase, one lowercase, and one number"
-    ))]
+    /// Verificata da register_user con la PasswordPolicy configurata
     pub password: String,
 }
 
-fn validate_username(username: &str) -> Result<(), validator::ValidationError> {
+pub(crate) fn validate_username(username: &str) -> Result<(), validator::ValidationError> {
     lazy_static::lazy_static! {
         static ref USERNAME_REGEX: regex::Regex = regex::Regex::new(r"^[a-zA-Z0-9_]+$").unwrap();
     }
@@ -81,3 +174,33 @@ pub struct UpdateUserDTO {
     ))]
     pub password: Option<String>,
 }
+
+/// DTO per aggiornare il profilo dell'utente (solo i campi presenti vengono modificati,
+/// una stringa vuota cancella il campo)
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Validate, ToSchema)]
+pub struct UpdateProfileDTO {
+    #[validate(length(max = 50, message = "Display name must not exceed 50 characters"))]
+    pub display_name: Option<String>,
+
+    #[validate(length(max = 500, message = "Bio must not exceed 500 characters"))]
+    pub bio: Option<String>,
+
+    #[validate(length(max = 500, message = "Avatar URL must not exceed 500 characters"))]
+    #[validate(custom(
+        function = "validate_avatar_url",
+        message = "Avatar URL must start with http:// or https://"
+    ))]
+    pub avatar_url: Option<String>,
+}
+
+fn validate_avatar_url(avatar_url: &str) -> Result<(), validator::ValidationError> {
+    // la stringa vuota è ammessa: cancella l'avatar
+    if avatar_url.is_empty()
+        || avatar_url.starts_with("http://")
+        || avatar_url.starts_with("https://")
+    {
+        Ok(())
+    } else {
+        Err(validator::ValidationError::new("invalid_avatar_url"))
+    }
+}
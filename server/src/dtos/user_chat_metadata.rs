@@ -1,18 +1,28 @@
 //! UserChatMetadata DTOs - Data Transfer Objects per metadati utente-chat
 
-use crate::entities::{UserChatMetadata, UserRole};
+use crate::entities::{FloodReason, UserChatMetadata, UserRole};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 /// Struct per restituire info più semplici per le liste
 /// (nome = UserInChatDTO nell'originale, rappresenta un utente in una chat con il suo ruolo)
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct UserInChatDTO {
     pub user_id: Option<i32>,
     pub chat_id: Option<i32>,
     pub username: Option<String>,
     pub user_role: Option<UserRole>,
+    /// Riga di `chat_roles` del membro: il suo ruolo base o un ruolo personalizzato
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role_id: Option<i32>,
     pub member_since: Option<DateTime<Utc>>,
+    /// Presente se il membro è stato silenziato
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub muted_until: Option<DateTime<Utc>>,
+    /// Stato personalizzato del membro, se impostato e non scaduto
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
     //pub messages_visible_from: Option<DateTime<Utc>>,         // superfluo per il tipo di operazione
     //pub messages_received_until: Option<DateTime<Utc>>,       // superfluo per il tipo di operazione
 }
@@ -24,13 +34,27 @@ impl From<UserChatMetadata> for UserInChatDTO {
             chat_id: Some(value.chat_id),
             username: None, // Non è presente in UserChatMetadata, va popolato altrove
             user_role: value.user_role,
+            role_id: value.role_id,
             member_since: Some(value.member_since),
+            muted_until: value.muted_until,
+            // Non è presente in UserChatMetadata, va popolato altrove
+            status: None,
             // messages_visible_from: Some(value.messages_visible_from),
             // messages_received_until: Some(value.messages_received_until),
         }
     }
 }
 
+/// Avviso inviato ai moderatori online quando un membro viene silenziato automaticamente
+/// per flood o spam
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FloodAlertDTO {
+    pub chat_id: i32,
+    pub user_id: i32,
+    pub reason: FloodReason,
+    pub muted_until: DateTime<Utc>,
+}
+
 /// DTO per creare nuovi metadati utente-chat (senza member_since, messages_visible_from, messages_received_until - gestiti dal DB)
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CreateUserChatMetadataDTO {
@@ -48,4 +72,14 @@ pub struct UpdateUserChatMetadataDTO {
     pub user_role: Option<UserRole>,
     pub messages_visible_from: Option<DateTime<Utc>>,
     pub messages_received_until: Option<DateTime<Utc>>,
+    /// Versione su cui si basano le modifiche: se la riga è cambiata nel frattempo
+    /// l'aggiornamento fallisce con un conflitto (senza, vale quella letta dal repository)
+    pub version: Option<i32>,
+}
+
+/// Numero di messaggi non letti per una chat (badge lato client)
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema, sqlx::FromRow)]
+pub struct UnreadCountDTO {
+    pub chat_id: i32,
+    pub unread: i64,
 }
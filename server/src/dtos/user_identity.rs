@@ -0,0 +1,11 @@
+//! UserIdentity DTOs - Data Transfer Objects per le identità esterne
+
+use serde::{Deserialize, Serialize};
+
+/// DTO per collegare un'identità OIDC a un utente locale
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreateUserIdentityDTO {
+    pub user_id: i32,
+    pub issuer: String,
+    pub subject: String,
+}
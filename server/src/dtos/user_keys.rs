@@ -0,0 +1,112 @@
+//! UserKeys DTOs - Data Transfer Objects per le chiavi della cifratura end-to-end
+
+use crate::entities::{OneTimePrekey, UserKeys};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+/// Prekey pubblica con l'identificativo scelto dal client
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+pub struct PrekeyDTO {
+    pub key_id: i32,
+    #[validate(custom(
+        function = "validate_public_key",
+        message = "Public key must be a base64 encoded 32 or 33 byte key"
+    ))]
+    pub public_key: String,
+}
+
+impl From<OneTimePrekey> for PrekeyDTO {
+    fn from(value: OneTimePrekey) -> Self {
+        Self {
+            key_id: value.prekey_id,
+            public_key: value.public_key,
+        }
+    }
+}
+
+/// Signed prekey con la firma prodotta dalla chiave di identità
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+pub struct SignedPrekeyDTO {
+    pub key_id: i32,
+    #[validate(custom(
+        function = "validate_public_key",
+        message = "Public key must be a base64 encoded 32 or 33 byte key"
+    ))]
+    pub public_key: String,
+    #[validate(custom(
+        function = "validate_signature",
+        message = "Signature must be a base64 encoded 64 byte signature"
+    ))]
+    pub signature: String,
+}
+
+/// DTO per pubblicare le proprie chiavi. Le one-time prekey si aggiungono a quelle
+/// già pubblicate, a meno che non cambi la chiave di identità
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+pub struct UploadKeysDTO {
+    #[validate(custom(
+        function = "validate_public_key",
+        message = "Identity key must be a base64 encoded 32 or 33 byte key"
+    ))]
+    pub identity_key: String,
+    #[validate(nested)]
+    pub signed_prekey: SignedPrekeyDTO,
+    #[serde(default)]
+    #[validate(length(
+        max = 100,
+        message = "At most 100 one-time prekeys can be uploaded at once"
+    ))]
+    #[validate(nested)]
+    pub one_time_prekeys: Vec<PrekeyDTO>,
+}
+
+/// Bundle di chiavi per avviare una sessione cifrata con un utente.
+/// `one_time_prekey` è None se l'utente le ha esaurite
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct KeyBundleDTO {
+    pub user_id: i32,
+    pub identity_key: String,
+    pub signed_prekey: SignedPrekeyDTO,
+    pub one_time_prekey: Option<PrekeyDTO>,
+}
+
+impl KeyBundleDTO {
+    pub fn new(keys: UserKeys, one_time_prekey: Option<OneTimePrekey>) -> Self {
+        Self {
+            user_id: keys.user_id,
+            identity_key: keys.identity_key,
+            signed_prekey: SignedPrekeyDTO {
+                key_id: keys.signed_prekey_id,
+                public_key: keys.signed_prekey,
+                signature: keys.signed_prekey_signature,
+            },
+            one_time_prekey: one_time_prekey.map(PrekeyDTO::from),
+        }
+    }
+}
+
+/// Stato delle chiavi pubblicate, restituito dopo il caricamento: il client ricarica
+/// nuove one-time prekey quando `one_time_prekeys` scende
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct KeyStatusDTO {
+    pub one_time_prekeys: i64,
+}
+
+/// Chiave pubblica Curve25519: 32 byte, 33 con il byte di tipo usato da Signal
+fn validate_public_key(key: &str) -> Result<(), validator::ValidationError> {
+    match STANDARD.decode(key) {
+        Ok(bytes) if bytes.len() == 32 || bytes.len() == 33 => Ok(()),
+        _ => Err(validator::ValidationError::new("invalid_public_key")),
+    }
+}
+
+/// Firma XEdDSA/Ed25519 di 64 byte
+fn validate_signature(signature: &str) -> Result<(), validator::ValidationError> {
+    match STANDARD.decode(signature) {
+        Ok(bytes) if bytes.len() == 64 => Ok(()),
+        _ => Err(validator::ValidationError::new("invalid_signature")),
+    }
+}
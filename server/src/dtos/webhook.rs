@@ -0,0 +1,71 @@
+//! Webhook DTOs - Data Transfer Objects per i webhook in ingresso
+
+use crate::dtos::user::validate_username;
+use crate::entities::{ContentFormat, Webhook};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+/// Struct per gestire io col client, senza l'hash del token
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct WebhookDTO {
+    pub webhook_id: i32,
+    pub chat_id: i32,
+    pub bot_user_id: i32,
+    pub name: String,
+    pub created_by: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    /// Token in chiaro, presente solo nella risposta alla creazione
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+impl From<Webhook> for WebhookDTO {
+    fn from(value: Webhook) -> Self {
+        Self {
+            webhook_id: value.webhook_id,
+            chat_id: value.chat_id,
+            bot_user_id: value.bot_user_id,
+            name: value.name,
+            created_by: value.created_by,
+            created_at: value.created_at,
+            token: None,
+        }
+    }
+}
+
+/// DTO per creare un webhook (la chat è presa dal path).
+/// Il nome diventa lo username del bot, quindi segue le stesse regole
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+pub struct CreateWebhookDTO {
+    #[validate(length(
+        min = 3,
+        max = 50,
+        message = "Webhook name must be between 3 and 50 characters"
+    ))]
+    #[validate(custom(
+        function = "validate_username",
+        message = "Webhook name can only contain letters, numbers, and underscores"
+    ))]
+    pub name: String,
+}
+
+/// DTO per salvare un nuovo webhook (solo l'hash del token)
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct NewWebhookDTO {
+    pub chat_id: i32,
+    pub bot_user_id: i32,
+    pub name: String,
+    pub token_hash: String,
+    pub created_by: i32,
+}
+
+/// Body di POST /webhooks/{webhook_token}
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct WebhookMessageDTO {
+    pub content: String,
+    /// Formato del contenuto, se assente il messaggio è testo semplice
+    #[serde(default)]
+    pub content_format: Option<ContentFormat>,
+}
@@ -0,0 +1,19 @@
+//! Attachment entity - Entità allegato
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct Attachment {
+    pub attachment_id: i32,
+    pub chat_id: i32,     // chat in cui è stato caricato, un messaggio può usarlo solo nella stessa chat
+    pub uploader_id: i32, // utente che ha caricato il file
+    pub file_name: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    // percorso dell'oggetto nello storage (disco o bucket), non viene mai esposto al client
+    pub storage_key: String,
+    // messaggi non eliminati e avatar che usano l'allegato, a zero il file può essere eliminato
+    pub ref_count: i32,
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,18 @@
+//! AuditEntry entity - Entità voce del registro delle azioni amministrative
+
+use super::enums::AuditAction;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct AuditEntry {
+    pub audit_id: i32,
+    pub chat_id: i32,
+    // NULL se l'autore o il target hanno eliminato l'account
+    pub actor_id: Option<i32>,
+    pub target_user_id: Option<i32>,
+    pub action: AuditAction,
+    // dettagli dell'azione serializzati in JSON
+    pub payload: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
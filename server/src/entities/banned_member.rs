@@ -0,0 +1,13 @@
+//! BannedMember entity - Entità utente bannato da una chat
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct BannedMember {
+    pub user_id: i32,
+    pub chat_id: i32,
+    // Admin o Owner che ha applicato il ban
+    pub banned_by: i32,
+    pub banned_at: DateTime<Utc>,
+}
@@ -1,12 +1,24 @@
 //! Chat entity - Entità chat
 
-use super::enums::ChatType;
+use super::enums::{ChatType, ContentFilterPolicy, InvitePolicy};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
 pub struct Chat {
     pub chat_id: i32,
     pub title: Option<String>,
     pub description: Option<String>,
     pub chat_type: ChatType,
+    // allegato (caricato nella chat stessa) usato come immagine della chat
+    pub avatar_attachment_id: Option<i32>,
+    // se true solo Admin e Owner possono inviare messaggi
+    pub announcement_only: bool,
+    // se true la chat compare nella directory delle chat pubbliche
+    pub is_public: bool,
+    // se true (solo per chat pubbliche) chi vuole entrare deve essere approvato
+    pub requires_approval: bool,
+    // chi può invitare nuovi membri
+    pub invite_policy: InvitePolicy,
+    // cosa fare dei messaggi che non superano il filtro dei contenuti
+    pub content_filter: ContentFilterPolicy,
 }
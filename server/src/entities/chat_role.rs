@@ -0,0 +1,46 @@
+//! ChatRole entity - Entità ruolo di una chat, base o personalizzato
+
+use super::enums::{ChatPermission, UserRole};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct ChatRole {
+    pub role_id: i32,
+    pub chat_id: i32,
+    pub name: String,
+    // valorizzato per le righe dei ruoli base Owner/Admin/Member, NULL per i ruoli personalizzati
+    pub base_role: Option<UserRole>,
+    // un flag per ogni ChatPermission concesso a chi ha questo ruolo
+    pub can_invite: bool,
+    pub can_remove_members: bool,
+    pub can_moderate: bool,
+    pub can_delete_messages: bool,
+    pub can_pin_messages: bool,
+    pub can_edit_chat: bool,
+    pub can_post_announcements: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ChatRole {
+    /// Verifica se il ruolo concede il permesso indicato
+    pub fn grants(&self, permission: ChatPermission) -> bool {
+        match permission {
+            ChatPermission::InviteMembers => self.can_invite,
+            ChatPermission::RemoveMembers => self.can_remove_members,
+            ChatPermission::ModerateMembers => self.can_moderate,
+            ChatPermission::DeleteMessages => self.can_delete_messages,
+            ChatPermission::PinMessages => self.can_pin_messages,
+            ChatPermission::EditChat => self.can_edit_chat,
+            ChatPermission::PostAnnouncements => self.can_post_announcements,
+        }
+    }
+
+    /// Elenco dei permessi concessi dal ruolo
+    pub fn permissions(&self) -> Vec<ChatPermission> {
+        ChatPermission::ALL
+            .into_iter()
+            .filter(|permission| self.grants(*permission))
+            .collect()
+    }
+}
@@ -0,0 +1,16 @@
+//! DataExport entity - Entità job di export dei dati personali di un utente
+
+use super::enums::DataExportStatus;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct DataExport {
+    pub export_id: i32,
+    pub user_id: i32,
+    pub state: DataExportStatus,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    // chiave dell'archivio sullo storage, presente solo se completato
+    pub storage_key: Option<String>,
+}
@@ -0,0 +1,13 @@
+//! Draft entity - Entità bozza di messaggio
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct Draft {
+    pub user_id: i32,
+    pub chat_id: i32,
+    pub content: String,
+    // usato dai client per capire quale dispositivo ha scritto per ultimo
+    pub updated_at: DateTime<Utc>,
+}
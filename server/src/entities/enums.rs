@@ -1,17 +1,32 @@
 //! Enumerazioni - Tipi enumerati utilizzati nelle entità
 
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 // ********************* ENUMERAZIONI UTILI **********************//
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, sqlx::Type)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, sqlx::Type, ToSchema)]
 #[sqlx(type_name = "message_type", rename_all = "UPPERCASE")]
 pub enum MessageType {
     UserMessage,
     SystemMessage,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, sqlx::Type)]
+/// Formato del contenuto di un messaggio, indica al client come renderizzarlo
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "content_format", rename_all = "UPPERCASE")]
+pub enum ContentFormat {
+    #[default]
+    Plain,
+    Markdown,
+}
+
+/// Ruolo base di un membro, salvato nella colonna enum `userchatmetadata.user_role`
+///
+/// Il ruolo del membro è la riga di `chat_roles` indicata da `userchatmetadata.role_id`:
+/// l'enum resta come mappatura di compatibilità verso le righe base della chat (`base_role`),
+/// usata dai controlli sul ruolo e aggiornata insieme a `role_id`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, sqlx::Type, ToSchema)]
 #[sqlx(type_name = "user_role", rename_all = "UPPERCASE")]
 pub enum UserRole {
     Owner,
@@ -19,7 +34,7 @@ pub enum UserRole {
     Member,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, sqlx::Type, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::Type, PartialEq, ToSchema)]
 #[sqlx(type_name = "invitation_status", rename_all = "UPPERCASE")]
 pub enum InvitationStatus {
     Pending,
@@ -27,9 +42,153 @@ pub enum InvitationStatus {
     Rejected,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, sqlx::Type, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::Type, PartialEq, ToSchema)]
+#[sqlx(type_name = "join_request_status", rename_all = "UPPERCASE")]
+pub enum JoinRequestStatus {
+    Pending,
+    Approved,
+    Denied,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::Type, PartialEq, ToSchema)]
+#[sqlx(type_name = "report_status", rename_all = "UPPERCASE")]
+pub enum ReportStatus {
+    Pending,
+    /// Chiusa da un amministratore che ha preso provvedimenti
+    Resolved,
+    /// Chiusa senza provvedimenti
+    Dismissed,
+}
+
+/// Motivo di una segnalazione, scelto dall'utente che la invia
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "report_reason", rename_all = "SCREAMING_SNAKE_CASE")]
+#[serde(rename_all = "snake_case")]
+pub enum ReportReason {
+    Spam,
+    Harassment,
+    InappropriateContent,
+    /// L'utente si spaccia per un'altra persona
+    Impersonation,
+    /// Motivo descritto solo nei dettagli
+    Other,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::Type, PartialEq, ToSchema)]
+#[sqlx(type_name = "data_export_status", rename_all = "UPPERCASE")]
+pub enum DataExportStatus {
+    Pending,
+    Completed,
+    Failed,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::Type, PartialEq, ToSchema)]
 #[sqlx(type_name = "chat_type", rename_all = "UPPERCASE")]
 pub enum ChatType {
     Group,
     Private,
 }
+
+/// Permessi che un ruolo personalizzato può concedere ai membri di una chat
+/// (Owner e Admin li hanno già tutti per via del ruolo base)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ChatPermission {
+    InviteMembers,
+    RemoveMembers,
+    /// Bannare e silenziare i membri
+    ModerateMembers,
+    /// Eliminare i messaggi degli altri membri
+    DeleteMessages,
+    PinMessages,
+    /// Modificare titolo, descrizione, impostazioni e avatar della chat
+    EditChat,
+    /// Scrivere nelle chat di soli annunci
+    PostAnnouncements,
+}
+
+impl ChatPermission {
+    pub const ALL: [ChatPermission; 7] = [
+        ChatPermission::InviteMembers,
+        ChatPermission::RemoveMembers,
+        ChatPermission::ModerateMembers,
+        ChatPermission::DeleteMessages,
+        ChatPermission::PinMessages,
+        ChatPermission::EditChat,
+        ChatPermission::PostAnnouncements,
+    ];
+}
+
+/// Chi può vedere lo stato online e il last_seen di un utente
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "presence_visibility", rename_all = "UPPERCASE")]
+#[serde(rename_all = "snake_case")]
+pub enum PresenceVisibility {
+    /// Tutti gli utenti
+    Everyone,
+    /// Solo chi condivide almeno una chat con l'utente
+    Contacts,
+    /// Nessuno
+    Nobody,
+}
+
+/// Chi può invitare nuovi membri in una chat di gruppo
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "invite_policy", rename_all = "SCREAMING_SNAKE_CASE")]
+#[serde(rename_all = "snake_case")]
+pub enum InvitePolicy {
+    /// Qualsiasi membro
+    Everyone,
+    /// Solo chi ha il permesso InviteMembers (Owner, Admin, ruoli personalizzati)
+    AdminsOnly,
+}
+
+/// Cosa fare dei messaggi che non superano il filtro dei contenuti
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "content_filter_policy", rename_all = "UPPERCASE")]
+#[serde(rename_all = "snake_case")]
+pub enum ContentFilterPolicy {
+    /// Nessun controllo
+    #[default]
+    Off,
+    /// Consegnare il messaggio e segnalarlo agli amministratori del server
+    Flag,
+    /// Mascherare le parole vietate prima della consegna
+    Redact,
+    /// Rifiutare il messaggio
+    Reject,
+}
+
+/// Comportamento che ha fatto scattare il silenziamento automatico di un membro
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FloodReason {
+    /// Lo stesso messaggio inviato più volte di seguito
+    RepeatedMessage,
+    /// Troppi messaggi con link
+    LinkSpam,
+    /// Ingressi e uscite continui dalla chat
+    JoinLeaveThrash,
+}
+
+/// Azioni privilegiate registrate nell'audit log di una chat
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "audit_action", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AuditAction {
+    MemberRoleChanged,
+    MemberRemoved,
+    MemberBanned,
+    MemberMuted,
+    OwnershipTransferred,
+    /// Modifica di titolo, descrizione, impostazioni o avatar
+    ChatUpdated,
+    /// Eliminazione del messaggio di un altro membro
+    MessageDeleted,
+    CustomRoleCreated,
+    CustomRoleDeleted,
+    CustomRoleAssigned,
+    WebhookCreated,
+    WebhookDeleted,
+    InviteLinkCreated,
+    InviteLinkRevoked,
+}
@@ -4,12 +4,14 @@ use super::enums::InvitationStatus;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
 pub struct Invitation {
     pub invite_id: i32,
     pub target_chat_id: i32, // chat ( di gruppo ) in cui si viene invitati
     pub invited_id: i32,     // utente invitato
     pub invitee_id: i32,     // utente che invita
+    // messaggio facoltativo dell'inviter
+    pub note: Option<String>,
     pub state: InvitationStatus,
     pub created_at: DateTime<Utc>,
 }
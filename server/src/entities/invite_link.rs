@@ -0,0 +1,17 @@
+//! InviteLink entity - Entità link di invito ad una chat di gruppo
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct InviteLink {
+    pub link_id: i32,
+    pub chat_id: i32,
+    // hash SHA-256 del token, il valore in chiaro è mostrato solo alla creazione
+    pub token_hash: String,
+    // None se l'autore ha eliminato l'account
+    pub created_by: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    // None se il link non scade
+    pub expires_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,18 @@
+//! JoinRequest entity - Entità richiesta di accesso ad una chat pubblica
+
+use super::enums::JoinRequestStatus;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct JoinRequest {
+    pub request_id: i32,
+    pub chat_id: i32,
+    // utente che chiede di entrare
+    pub user_id: i32,
+    pub state: JoinRequestStatus,
+    pub created_at: DateTime<Utc>,
+    // membro che ha approvato o rifiutato, None se pending o approvata automaticamente
+    pub resolved_by: Option<i32>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
@@ -1,10 +1,10 @@
 //! Message entity - Entità messaggio
 
-use super::enums::MessageType;
+use super::enums::{ContentFormat, MessageType};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
 pub struct Message {
     pub message_id: i32,
     pub chat_id: i32,
@@ -15,4 +15,12 @@ pub struct Message {
     pub created_at: DateTime<Utc>,
     // campo rinominato rispetto a uml perchè type è una parola protetta
     pub message_type: MessageType,
+    // plain o markdown, il markdown viene sanificato prima del salvataggio
+    pub content_format: ContentFormat,
+    // messaggio citato (stessa chat), None se non è una risposta
+    pub reply_to_message_id: Option<i32>,
+    // allegato caricato in precedenza tramite POST /chats/{chat_id}/attachments
+    pub attachment_id: Option<i32>,
+    // valorizzato quando il messaggio viene eliminato (soft-delete), la riga resta nel db
+    pub deleted_at: Option<DateTime<Utc>>,
 }
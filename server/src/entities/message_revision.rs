@@ -0,0 +1,14 @@
+//! MessageRevision entity - Versione precedente di un messaggio modificato
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct MessageRevision {
+    pub revision_id: i32,
+    pub message_id: i32,
+    // contenuto del messaggio prima della modifica
+    pub content: String,
+    // istante in cui questo contenuto è stato sostituito dal successivo
+    pub replaced_at: DateTime<Utc>,
+}
@@ -3,17 +3,49 @@
 //! Questo modulo contiene tutte le entità (models) che rappresentano i dati persistiti nel database.
 //! Ogni entity corrisponde a una tabella nel database.
 
+pub mod attachment;
+pub mod audit_entry;
+pub mod banned_member;
 pub mod chat;
+pub mod chat_role;
+pub mod data_export;
+pub mod draft;
 pub mod enums;
 pub mod invitation;
+pub mod invite_link;
+pub mod join_request;
 pub mod message;
+pub mod message_revision;
+pub mod refresh_token;
+pub mod report;
 pub mod user;
 pub mod user_chat_metadata;
+pub mod user_identity;
+pub mod user_keys;
+pub mod webhook;
 
 // Re-exports per facilitare l'import
+pub use attachment::Attachment;
+pub use audit_entry::AuditEntry;
+pub use banned_member::BannedMember;
 pub use chat::Chat;
-pub use enums::{ChatType, InvitationStatus, MessageType, UserRole};
+pub use chat_role::ChatRole;
+pub use data_export::DataExport;
+pub use draft::Draft;
+pub use enums::{
+    AuditAction, ChatPermission, ChatType, ContentFilterPolicy, ContentFormat, DataExportStatus,
+    FloodReason, InvitationStatus, InvitePolicy, JoinRequestStatus, MessageType,
+    PresenceVisibility, ReportReason, ReportStatus, UserRole,
+};
 pub use invitation::Invitation;
+pub use invite_link::InviteLink;
+pub use join_request::JoinRequest;
 pub use message::Message;
+pub use message_revision::MessageRevision;
+pub use refresh_token::RefreshToken;
+pub use report::Report;
 pub use user::User;
 pub use user_chat_metadata::UserChatMetadata;
+pub use user_identity::UserIdentity;
+pub use user_keys::{OneTimePrekey, UserKeys};
+pub use webhook::Webhook;
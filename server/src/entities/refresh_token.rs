@@ -0,0 +1,25 @@
+//! RefreshToken entity - Entità refresh token emesso al login e ruotato ad ogni refresh
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct RefreshToken {
+    pub token_id: i32,
+    pub user_id: i32,
+    // tutti i token ottenuti per rotazione a partire dallo stesso login
+    pub family_id: String,
+    // hash SHA-256 del token, il valore in chiaro è noto solo al client
+    pub token_hash: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl RefreshToken {
+    /// Il token è già stato scambiato oppure la sua famiglia è stata revocata
+    pub fn is_consumed(&self) -> bool {
+        self.used_at.is_some() || self.revoked_at.is_some()
+    }
+}
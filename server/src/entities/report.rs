@@ -0,0 +1,24 @@
+//! Report entity - Entità segnalazione di un messaggio o di un utente
+
+use super::enums::{ReportReason, ReportStatus};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct Report {
+    pub report_id: i32,
+    // utente che ha inviato la segnalazione, None per quelle automatiche del filtro dei contenuti
+    pub reporter_id: Option<i32>,
+    // utente segnalato, per i messaggi è l'autore
+    pub reported_user_id: i32,
+    // chat e messaggio segnalati, None per le segnalazioni di un utente
+    pub chat_id: Option<i32>,
+    pub message_id: Option<i32>,
+    pub reason: ReportReason,
+    pub details: Option<String>,
+    pub state: ReportStatus,
+    pub created_at: DateTime<Utc>,
+    // amministratore del server che l'ha chiusa, None finché è pending
+    pub resolved_by: Option<i32>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
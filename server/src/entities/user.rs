@@ -1,9 +1,11 @@
 //! User entity - Entità utente con metodi per gestione password
 
-use bcrypt::{DEFAULT_COST, hash, verify};
+use super::enums::PresenceVisibility;
+use crate::core::password_hash;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
 pub struct User {
     /* se vogliamo rinominare campi usiamo la macro
      * #[serde(rename = "userId")]
@@ -11,17 +13,41 @@ pub struct User {
     pub user_id: i32,
     pub username: String,
     pub password: String,
+    // profilo, facoltativo
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub avatar_url: Option<String>,
+    // stato personalizzato, con scadenza facoltativa
+    pub status_text: Option<String>,
+    pub status_expires_at: Option<DateTime<Utc>>,
+    // ultimo accesso, aggiornato alla disconnessione dal websocket
+    pub last_seen: Option<DateTime<Utc>>,
+    // chi può vedere stato online e last_seen
+    pub presence_visibility: PresenceVisibility,
+    // impostato quando l'utente disattiva l'account, None se attivo
+    pub deactivated_at: Option<DateTime<Utc>>,
 }
 
 impl User {
-    /// Verify if target_password matches the stored hashed password
+    /// Verify if target_password matches the stored hashed password (bcrypt or Argon2id)
     pub fn verify_password(&self, target_password: &String) -> bool {
-        verify(target_password, &self.password).unwrap_or(false)
+        password_hash::verify_password(target_password, &self.password)
     }
 
-    /// Hash a password using bcrypt with default cost
-    pub fn hash_password(password: &str) -> Result<String, bcrypt::BcryptError> {
-        let hash = hash(password, DEFAULT_COST)?;
-        Ok(hash)
+    /// Username da mostrare agli altri utenti: anonimo se l'account è disattivato
+    pub fn public_username(&self) -> &str {
+        if self.deactivated_at.is_some() {
+            "Deleted User"
+        } else {
+            &self.username
+        }
+    }
+
+    /// Stato personalizzato ancora valido (None se assente o scaduto)
+    pub fn active_status(&self) -> Option<&str> {
+        match self.status_expires_at {
+            Some(expires_at) if expires_at <= Utc::now() => None,
+            _ => self.status_text.as_deref(),
+        }
     }
 }
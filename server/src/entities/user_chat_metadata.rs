@@ -4,11 +4,13 @@ use super::enums::UserRole;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
 pub struct UserChatMetadata {
     pub user_id: i32,
     pub chat_id: i32,
     pub user_role: Option<UserRole>,
+    // riga di chat_roles del membro: quella del ruolo base di user_role o un ruolo personalizzato
+    pub role_id: Option<i32>,
     pub member_since: DateTime<Utc>,
     // sostituisce deliver_from con un nome più esplicativo
     // sostituito al posto dell'id del messaggio il datetime, è da intendersi come
@@ -18,5 +20,11 @@ pub struct UserChatMetadata {
     // sostituito al posto dell'id del messaggio il date time, è da intendersi come
     // "ho ricevuto i messaggi fino a questo istante, istante INCLUSO"
     pub messages_received_until: DateTime<Utc>,
+    // valorizzato se l'utente ha fissato la chat in cima alla lista, le fissate più di recente vengono prima
+    pub pinned_at: Option<DateTime<Utc>>,
+    // valorizzato se un Admin/Owner ha silenziato il membro: non può scrivere fino a questo istante, ESCLUSO
+    pub muted_until: Option<DateTime<Utc>>,
+    // incrementata da ogni modifica al ruolo: gli aggiornamenti la confrontano con quella letta
+    pub version: i32,
     //per ora non esludo i due campi dalla deserializzazione
 }
@@ -0,0 +1,15 @@
+//! UserIdentity entity - Entità identità esterna (OpenID Connect) collegata a un utente
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct UserIdentity {
+    pub identity_id: i32,
+    pub user_id: i32,
+    // claim `iss` dell'id_token
+    pub issuer: String,
+    // claim `sub` dell'id_token, univoco per issuer
+    pub subject: String,
+    pub created_at: DateTime<Utc>,
+}
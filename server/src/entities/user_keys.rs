@@ -0,0 +1,24 @@
+//! UserKeys entity - Chiavi pubbliche per la cifratura end-to-end
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Chiave di identità e signed prekey di un utente, in base64
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct UserKeys {
+    pub user_id: i32,
+    pub identity_key: String,
+    pub signed_prekey_id: i32,
+    pub signed_prekey: String,
+    // firma della signed prekey con la chiave di identità, verificata solo dai client
+    pub signed_prekey_signature: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// One-time prekey non ancora consegnata
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct OneTimePrekey {
+    pub user_id: i32,
+    pub prekey_id: i32,
+    pub public_key: String,
+}
@@ -0,0 +1,18 @@
+//! Webhook entity - Entità webhook in ingresso che pubblica messaggi in una chat
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct Webhook {
+    pub webhook_id: i32,
+    pub chat_id: i32,
+    // utente bot, membro della chat, autore dei messaggi del webhook
+    pub bot_user_id: i32,
+    pub name: String,
+    // hash SHA-256 del token, il valore in chiaro è mostrato solo alla creazione
+    pub token_hash: String,
+    // None se l'autore ha eliminato l'account
+    pub created_by: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
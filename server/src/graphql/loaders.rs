@@ -0,0 +1,98 @@
+//! DataLoader - Caricamento in blocco delle entità collegate
+//!
+//! Durante una richiesta GraphQL i resolver chiedono utenti, chat e membri uno alla volta:
+//! i loader raccolgono le chiavi richieste nello stesso tick e le leggono con una sola
+//! query per tipo, invece di una query per ogni elemento delle liste.
+
+use crate::AppState;
+use crate::dtos::{ChatDTO, UserDTO};
+use crate::entities::UserChatMetadata;
+use crate::repositories::{ChatFilter, MemberFilter, Page, UserFilter};
+use async_graphql::dataloader::Loader;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Utenti per user_id, già convertiti in DTO (profilo pubblico)
+pub struct UserLoader {
+    state: Arc<AppState>,
+}
+
+impl UserLoader {
+    pub fn new(state: Arc<AppState>) -> Self {
+        Self { state }
+    }
+}
+
+impl Loader<i32> for UserLoader {
+    type Value = UserDTO;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(&self, keys: &[i32]) -> Result<HashMap<i32, Self::Value>, Self::Error> {
+        let filter = UserFilter {
+            ids: Some(keys.to_vec()),
+            ..UserFilter::default()
+        };
+        let users = self.state.user.read_many(&filter, &Page::ALL).await?;
+        Ok(users
+            .into_iter()
+            .map(|user| (user.user_id, UserDTO::from(user)))
+            .collect())
+    }
+}
+
+/// Chat per chat_id
+pub struct ChatLoader {
+    state: Arc<AppState>,
+}
+
+impl ChatLoader {
+    pub fn new(state: Arc<AppState>) -> Self {
+        Self { state }
+    }
+}
+
+impl Loader<i32> for ChatLoader {
+    type Value = ChatDTO;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(&self, keys: &[i32]) -> Result<HashMap<i32, Self::Value>, Self::Error> {
+        let filter = ChatFilter {
+            ids: Some(keys.to_vec()),
+            ..ChatFilter::default()
+        };
+        let chats = self.state.chat.read_many(&filter, &Page::ALL).await?;
+        Ok(chats
+            .into_iter()
+            .filter_map(|chat| Some((chat.chat_id?, chat)))
+            .collect())
+    }
+}
+
+/// Membri di ogni chat, per chat_id
+pub struct MembersLoader {
+    state: Arc<AppState>,
+}
+
+impl MembersLoader {
+    pub fn new(state: Arc<AppState>) -> Self {
+        Self { state }
+    }
+}
+
+impl Loader<i32> for MembersLoader {
+    type Value = Vec<UserChatMetadata>;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(&self, keys: &[i32]) -> Result<HashMap<i32, Self::Value>, Self::Error> {
+        let filter = MemberFilter {
+            chat_ids: Some(keys.to_vec()),
+            ..MemberFilter::default()
+        };
+        let members = self.state.meta.read_many(&filter, &Page::ALL).await?;
+        let mut by_chat: HashMap<i32, Self::Value> = HashMap::new();
+        for member in members {
+            by_chat.entry(member.chat_id).or_default().push(member);
+        }
+        Ok(by_chat)
+    }
+}
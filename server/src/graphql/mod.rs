@@ -0,0 +1,55 @@
+//! GraphQL Module - Endpoint `POST /graphql` per letture flessibili
+//!
+//! Espone chat, membri, messaggi e inviti come un grafo tipizzato, così il client può
+//! ottenere in una sola richiesta quello che via REST richiede una chiamata per chat.
+//! Le entità collegate sono caricate in blocco dai DataLoader, creati per ogni richiesta
+//! in modo che la cache non sopravviva oltre la richiesta stessa.
+
+pub mod loaders;
+pub mod schema;
+
+use crate::{AppState, entities::User};
+use async_graphql::dataloader::DataLoader;
+use async_graphql::{EmptyMutation, EmptySubscription, Schema};
+use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
+use axum::{Extension, extract::State};
+use loaders::{ChatLoader, MembersLoader, UserLoader};
+use schema::QueryRoot;
+use std::sync::Arc;
+use tracing::instrument;
+
+/// Profondità massima delle query, per evitare richieste annidate arbitrariamente costose
+const MAX_QUERY_DEPTH: usize = 8;
+
+pub type AppSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+lazy_static::lazy_static! {
+    /// Lo schema non dipende dallo stato: stato, utente e loader sono dati della richiesta
+    static ref SCHEMA: AppSchema = Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .limit_depth(MAX_QUERY_DEPTH)
+        .finish();
+}
+
+/// Esegue una query GraphQL per l'utente autenticato
+/// Operazioni:
+/// 1. Ottenere l'utente dall'Extension (inserito dall'authentication_middleware)
+/// 2. Aggiungere alla richiesta stato, utente e DataLoader nuovi
+/// 3. Eseguire la query sullo schema e ritornare la risposta (errori inclusi) in JSON
+#[instrument(skip(state, current_user, request), fields(user_id = %current_user.user_id))]
+pub async fn graphql_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(current_user): Extension<User>,
+    request: GraphQLRequest,
+) -> GraphQLResponse {
+    let users = DataLoader::new(UserLoader::new(state.clone()), tokio::spawn);
+    let chats = DataLoader::new(ChatLoader::new(state.clone()), tokio::spawn);
+    let members = DataLoader::new(MembersLoader::new(state.clone()), tokio::spawn);
+    let request = request
+        .into_inner()
+        .data(users)
+        .data(chats)
+        .data(members)
+        .data(state)
+        .data(current_user);
+    SCHEMA.execute(request).await.into()
+}
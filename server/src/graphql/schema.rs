@@ -0,0 +1,363 @@
+//! GraphQL schema - Tipi del grafo e query disponibili
+//!
+//! Sola lettura: le modifiche restano sulle API REST e sul WebSocket. Ogni resolver
+//! applica le stesse regole di visibilità delle route REST (membership della chat,
+//! `messages_visible_from`, profilo pubblico degli utenti).
+
+use super::loaders::{ChatLoader, MembersLoader, UserLoader};
+use crate::AppState;
+use crate::dtos::{ChatDTO, MessageDTO, UserDTO};
+use crate::entities::{Invitation, User, UserChatMetadata};
+use crate::repositories::{InvitationFilter, MemberFilter, MessageFilter, Page, ReadMany};
+use async_graphql::dataloader::DataLoader;
+use async_graphql::{Context, Enum, Error, Object, Result};
+use chrono::{DateTime, Utc};
+use std::fmt::Debug;
+use std::sync::Arc;
+use tracing::error;
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+#[graphql(name = "ChatType", remote = "crate::entities::ChatType")]
+pub enum GqlChatType {
+    Group,
+    Private,
+}
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+#[graphql(name = "UserRole", remote = "crate::entities::UserRole")]
+pub enum GqlUserRole {
+    Owner,
+    Admin,
+    Member,
+}
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+#[graphql(name = "MessageType", remote = "crate::entities::MessageType")]
+pub enum GqlMessageType {
+    UserMessage,
+    SystemMessage,
+}
+
+/// Gli errori interni sono registrati nei log, al client arriva solo un messaggio generico
+fn internal_error(err: impl Debug) -> Error {
+    error!("GraphQL resolver failed: {:?}", err);
+    Error::new("Internal server error")
+}
+
+fn app_state<'a>(ctx: &Context<'a>) -> &'a Arc<AppState> {
+    ctx.data_unchecked::<Arc<AppState>>()
+}
+
+fn current_user<'a>(ctx: &Context<'a>) -> &'a User {
+    ctx.data_unchecked::<User>()
+}
+
+async fn load_user(ctx: &Context<'_>, user_id: i32) -> Result<Option<UserNode>> {
+    let user = ctx
+        .data_unchecked::<DataLoader<UserLoader>>()
+        .load_one(user_id)
+        .await
+        .map_err(internal_error)?;
+    Ok(user.map(UserNode))
+}
+
+/// Profilo pubblico di un utente
+pub struct UserNode(UserDTO);
+
+#[Object(name = "User")]
+impl UserNode {
+    async fn id(&self) -> Option<i32> {
+        self.0.id
+    }
+
+    async fn username(&self) -> Option<&str> {
+        self.0.username.as_deref()
+    }
+
+    async fn display_name(&self) -> Option<&str> {
+        self.0.display_name.as_deref()
+    }
+
+    async fn bio(&self) -> Option<&str> {
+        self.0.bio.as_deref()
+    }
+
+    async fn avatar_url(&self) -> Option<&str> {
+        self.0.avatar_url.as_deref()
+    }
+
+    /// Stato personalizzato, assente se non impostato o scaduto
+    async fn status(&self) -> Option<&str> {
+        self.0.status.as_deref()
+    }
+
+    /// Presente solo se l'utente mostra l'ultimo accesso a tutti
+    async fn last_seen(&self) -> Option<DateTime<Utc>> {
+        self.0.last_seen
+    }
+}
+
+/// Chat vista dall'utente corrente; `membership` è None per le chat in cui è solo invitato
+pub struct ChatNode {
+    chat: ChatDTO,
+    membership: Option<UserChatMetadata>,
+}
+
+impl ChatNode {
+    /// Membership dell'utente corrente, richiesta per leggere membri e messaggi
+    fn require_membership(&self) -> Result<&UserChatMetadata> {
+        self.membership
+            .as_ref()
+            .ok_or_else(|| Error::new("You are not a member of this chat"))
+    }
+}
+
+#[Object(name = "Chat")]
+impl ChatNode {
+    async fn id(&self) -> Option<i32> {
+        self.chat.chat_id
+    }
+
+    async fn title(&self) -> Option<&str> {
+        self.chat.title.as_deref()
+    }
+
+    async fn description(&self) -> Option<&str> {
+        self.chat.description.as_deref()
+    }
+
+    async fn chat_type(&self) -> Option<GqlChatType> {
+        self.chat.chat_type.clone().map(GqlChatType::from)
+    }
+
+    async fn avatar_url(&self) -> Option<&str> {
+        self.chat.avatar_url.as_deref()
+    }
+
+    async fn is_public(&self) -> Option<bool> {
+        self.chat.is_public
+    }
+
+    /// Ruolo dell'utente corrente, assente se non è membro
+    async fn my_role(&self) -> Option<GqlUserRole> {
+        self.membership
+            .as_ref()
+            .and_then(|membership| membership.user_role.clone())
+            .map(GqlUserRole::from)
+    }
+
+    async fn members(&self, ctx: &Context<'_>) -> Result<Vec<MemberNode>> {
+        let membership = self.require_membership()?;
+        let members = ctx
+            .data_unchecked::<DataLoader<MembersLoader>>()
+            .load_one(membership.chat_id)
+            .await
+            .map_err(internal_error)?
+            .unwrap_or_default();
+        Ok(members.into_iter().map(MemberNode).collect())
+    }
+
+    /// Messaggi visibili all'utente, dal più recente, con la stessa paginazione di
+    /// `GET /chats/{chat_id}/messages`
+    async fn messages(
+        &self,
+        ctx: &Context<'_>,
+        before: Option<DateTime<Utc>>,
+        #[graphql(default = 50, validator(minimum = 1, maximum = 100))] limit: i64,
+    ) -> Result<Vec<MessageNode>> {
+        let membership = self.require_membership()?;
+        let filter = MessageFilter::History {
+            chat_id: membership.chat_id,
+            visible_from: membership.messages_visible_from,
+            before,
+        };
+        let messages = app_state(ctx)
+            .msg
+            .read_many(&filter, &Page::first(limit))
+            .await
+            .map_err(internal_error)?;
+        Ok(messages
+            .into_iter()
+            .map(|message| MessageNode(MessageDTO::from(message)))
+            .collect())
+    }
+}
+
+/// Membro di una chat con il suo ruolo
+pub struct MemberNode(UserChatMetadata);
+
+#[Object(name = "Member")]
+impl MemberNode {
+    async fn user_id(&self) -> i32 {
+        self.0.user_id
+    }
+
+    async fn role(&self) -> Option<GqlUserRole> {
+        self.0.user_role.clone().map(GqlUserRole::from)
+    }
+
+    async fn member_since(&self) -> DateTime<Utc> {
+        self.0.member_since
+    }
+
+    async fn muted_until(&self) -> Option<DateTime<Utc>> {
+        self.0.muted_until
+    }
+
+    async fn user(&self, ctx: &Context<'_>) -> Result<Option<UserNode>> {
+        load_user(ctx, self.0.user_id).await
+    }
+}
+
+/// Messaggio di una chat; i messaggi eliminati non hanno contenuto
+pub struct MessageNode(MessageDTO);
+
+#[Object(name = "Message")]
+impl MessageNode {
+    async fn id(&self) -> Option<i32> {
+        self.0.message_id
+    }
+
+    async fn chat_id(&self) -> Option<i32> {
+        self.0.chat_id
+    }
+
+    async fn content(&self) -> Option<&str> {
+        self.0.content.as_deref()
+    }
+
+    async fn message_type(&self) -> Option<GqlMessageType> {
+        self.0.message_type.clone().map(GqlMessageType::from)
+    }
+
+    async fn created_at(&self) -> Option<DateTime<Utc>> {
+        self.0.created_at
+    }
+
+    async fn reply_to_message_id(&self) -> Option<i32> {
+        self.0.reply_to_message_id
+    }
+
+    async fn attachment_id(&self) -> Option<i32> {
+        self.0.attachment_id
+    }
+
+    async fn deleted_at(&self) -> Option<DateTime<Utc>> {
+        self.0.deleted_at
+    }
+
+    async fn sender(&self, ctx: &Context<'_>) -> Result<Option<UserNode>> {
+        match self.0.sender_id {
+            Some(sender_id) => load_user(ctx, sender_id).await,
+            None => Ok(None),
+        }
+    }
+}
+
+/// Invito in attesa ricevuto dall'utente corrente
+pub struct InvitationNode(Invitation);
+
+#[Object(name = "Invitation")]
+impl InvitationNode {
+    async fn id(&self) -> i32 {
+        self.0.invite_id
+    }
+
+    async fn note(&self) -> Option<&str> {
+        self.0.note.as_deref()
+    }
+
+    async fn created_at(&self) -> DateTime<Utc> {
+        self.0.created_at
+    }
+
+    /// Chat dell'invito: membri e messaggi sono leggibili solo dopo averlo accettato
+    async fn chat(&self, ctx: &Context<'_>) -> Result<Option<ChatNode>> {
+        let chat = ctx
+            .data_unchecked::<DataLoader<ChatLoader>>()
+            .load_one(self.0.target_chat_id)
+            .await
+            .map_err(internal_error)?;
+        Ok(chat.map(|chat| ChatNode {
+            chat,
+            membership: None,
+        }))
+    }
+
+    async fn invited_by(&self, ctx: &Context<'_>) -> Result<Option<UserNode>> {
+        load_user(ctx, self.0.invitee_id).await
+    }
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// Utente autenticato
+    async fn me(&self, ctx: &Context<'_>) -> UserNode {
+        UserNode(UserDTO::from(current_user(ctx).clone()))
+    }
+
+    /// Chat di cui l'utente corrente è membro
+    async fn chats(&self, ctx: &Context<'_>) -> Result<Vec<ChatNode>> {
+        let memberships = app_state(ctx)
+            .meta
+            .read_many(
+                &MemberFilter::of_user(current_user(ctx).user_id).from_primary(),
+                &Page::ALL,
+            )
+            .await
+            .map_err(internal_error)?;
+        let mut chats = ctx
+            .data_unchecked::<DataLoader<ChatLoader>>()
+            .load_many(memberships.iter().map(|membership| membership.chat_id))
+            .await
+            .map_err(internal_error)?;
+
+        Ok(memberships
+            .into_iter()
+            .filter_map(|membership| {
+                let chat = chats.remove(&membership.chat_id)?;
+                Some(ChatNode {
+                    chat,
+                    membership: Some(membership),
+                })
+            })
+            .collect())
+    }
+
+    /// Chat indicata, se l'utente corrente ne è membro
+    async fn chat(&self, ctx: &Context<'_>, id: i32) -> Result<Option<ChatNode>> {
+        let state = app_state(ctx);
+        let Some(membership) = state
+            .meta
+            .read(&(current_user(ctx).user_id, id))
+            .await
+            .map_err(internal_error)?
+        else {
+            return Ok(None);
+        };
+        let chat = ctx
+            .data_unchecked::<DataLoader<ChatLoader>>()
+            .load_one(id)
+            .await
+            .map_err(internal_error)?;
+        Ok(chat.map(|chat| ChatNode {
+            chat,
+            membership: Some(membership),
+        }))
+    }
+
+    /// Inviti in attesa ricevuti dall'utente corrente
+    async fn invitations(&self, ctx: &Context<'_>) -> Result<Vec<InvitationNode>> {
+        let invitations = app_state(ctx)
+            .invitation
+            .read_many(
+                &InvitationFilter::pending_for(current_user(ctx).user_id),
+                &Page::ALL,
+            )
+            .await
+            .map_err(internal_error)?;
+        Ok(invitations.into_iter().map(InvitationNode).collect())
+    }
+}
@@ -3,33 +3,93 @@
 pub mod core;
 pub mod dtos;
 pub mod entities;
+pub mod graphql;
 pub mod monitoring;
+pub mod openapi;
 pub mod repositories;
 pub mod services;
 pub mod ws;
 
 // Re-export dei tipi principali per facilitare l'import
 pub use core::{AppError, AppState, auth, config};
-pub use services::root;
 
 use axum::{
-    Router, middleware,
-    routing::{any, delete, get, patch, post},
+    Router,
+    extract::DefaultBodyLimit,
+    middleware,
+    routing::{any, delete, get, patch, post, put},
 };
 use std::sync::Arc;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
 
 /// Crea il router principale dell'applicazione
 pub fn create_router(state: Arc<AppState>) -> Router {
-    use core::authentication_middleware;
+    use core::{API_V1_PREFIX, legacy_path_gate_middleware, legacy_path_middleware};
+    use openapi::{ApiDoc, OPENAPI_JSON_PATH, SWAGGER_UI_PATH};
+    use services::*;
+
+    let app = Router::new()
+        // sonde di stato per l'orchestratore, senza autenticazione e senza versione
+        .route("/healthz", get(healthz))
+        .route("/livez", get(livez))
+        .route("/readyz", get(readyz))
+        // specifica OpenAPI e Swagger UI, pubbliche come le sonde
+        .merge(SwaggerUi::new(SWAGGER_UI_PATH).url(OPENAPI_JSON_PATH, ApiDoc::openapi()))
+        .nest(API_V1_PREFIX, configure_api_routes(state.clone()));
+
+    // vecchi percorsi senza prefisso, deprecati: restano finché i client non migrano,
+    // serviti solo mentre LEGACY_API_PATHS è attivo (ricaricabile a caldo)
+    let app = app.merge(
+        configure_api_routes(state.clone())
+            .layer(middleware::from_fn(legacy_path_middleware))
+            .layer(middleware::from_fn_with_state(
+                state.clone(),
+                legacy_path_gate_middleware,
+            )),
+    );
+
+    app.with_state(state)
+}
+
+/// Configura tutte le routes dell'API, montate sotto il prefisso di versione
+fn configure_api_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
+    use core::{authentication_middleware, body_limit_middleware, idempotency_middleware};
+    use graphql::graphql_handler;
     use services::*;
     use ws::ws_handler;
 
     Router::new()
-        .route("/", get(root))
-        .nest("/auth", configure_auth_routes())
+        .nest("/auth", configure_auth_routes(state.clone()))
         .nest("/users", configure_user_routes(state.clone()))
         .nest("/chats", configure_chat_routes(state.clone()))
         .nest("/invitations", configure_invitation_routes(state.clone()))
+        .nest("/search", configure_search_routes(state.clone()))
+        .nest("/sync", configure_sync_routes(state.clone()))
+        .nest("/admin", configure_admin_routes(state.clone()))
+        // i webhook si autenticano con il token nel path, non con il JWT
+        .route(
+            "/webhooks/{webhook_token}",
+            post(post_webhook_message).layer(middleware::from_fn_with_state(
+                state.clone(),
+                idempotency_middleware,
+            )),
+        )
+        // chi conosce il token entra nella chat senza esserne ancora membro
+        .route(
+            "/invite_links/{invite_token}/join",
+            post(join_by_invite_link).layer(middleware::from_fn_with_state(
+                state.clone(),
+                authentication_middleware,
+            )),
+        )
+        .route(
+            "/graphql",
+            post(graphql_handler).layer(middleware::from_fn_with_state(
+                state.clone(),
+                authentication_middleware,
+            )),
+        )
         .route(
             "/ws",
             any(ws_handler).layer(middleware::from_fn_with_state(
@@ -37,15 +97,36 @@ pub fn create_router(state: Arc<AppState>) -> Router {
                 authentication_middleware,
             )),
         )
-        .with_state(state)
+        // limite JSON per tutte le route registrate fin qui
+        .layer(middleware::from_fn_with_state(
+            state.max_json_body_bytes,
+            body_limit_middleware,
+        ))
+        .nest("/chats", configure_upload_routes(state))
 }
 
-/// Configura le routes di autenticazione (login, register)
-fn configure_auth_routes() -> Router<Arc<AppState>> {
+/// Configura le routes di autenticazione (login, register, refresh, logout, SSO)
+fn configure_auth_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
+    use core::{authentication_middleware, ip_rate_limit_middleware};
     use services::*;
     Router::new()
         .route("/login", post(login_user))
         .route("/register", post(register_user))
+        .route("/refresh", post(refresh_tokens))
+        .route("/oidc/login", get(oidc_login))
+        .route("/oidc/callback", get(oidc_callback))
+        .route(
+            "/logout",
+            post(logout_user).layer(middleware::from_fn_with_state(
+                state.clone(),
+                authentication_middleware,
+            )),
+        )
+        // limite di richieste per IP: protegge login e registrazione da attacchi a forza bruta
+        .layer(middleware::from_fn_with_state(
+            state,
+            ip_rate_limit_middleware,
+        ))
 }
 
 /// Configura le routes per la gestione degli utenti
@@ -56,7 +137,22 @@ fn configure_user_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
     Router::new()
         .route("/", get(search_user_with_username))
         .route("/{user_id}", get(get_user_by_id))
+        .route("/{user_id}/report", post(report_user))
+        .route("/{user_id}/keys", get(get_key_bundle).put(upload_keys))
         .route("/me", delete(delete_my_account))
+        .route("/me/profile", get(get_my_profile).patch(update_my_profile))
+        .route("/me/status", put(set_my_status))
+        .route("/me/privacy", get(get_my_privacy).put(update_my_privacy))
+        .route("/me/sessions", get(list_my_sessions))
+        .route(
+            "/me/sessions/{connection_id}",
+            delete(disconnect_my_session),
+        )
+        .route(
+            "/me/export",
+            get(get_data_export_status).post(request_data_export),
+        )
+        .route("/me/export/download", get(download_data_export))
         .layer(middleware::from_fn_with_state(
             state,
             authentication_middleware,
@@ -65,12 +161,32 @@ fn configure_user_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
 
 /// Configura le routes per la gestione delle chat
 fn configure_chat_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
-    use core::{authentication_middleware, chat_membership_middleware};
+    use core::{
+        authentication_middleware, chat_membership_middleware, etag_middleware,
+        idempotency_middleware,
+    };
     use services::*;
 
     // Rotte che NON richiedono membership (solo autenticazione)
     let public_routes = Router::new()
-        .route("/", get(list_chats).post(create_chat))
+        .route(
+            "/",
+            get(list_chats).layer(middleware::from_fn(etag_middleware)),
+        )
+        .route(
+            "/",
+            post(create_chat).layer(middleware::from_fn_with_state(
+                state.clone(),
+                idempotency_middleware,
+            )),
+        )
+        .route("/unread", get(list_unread_counts))
+        .route(
+            "/overview",
+            get(list_chat_overview).layer(middleware::from_fn(etag_middleware)),
+        )
+        .route("/discover", get(discover_chats))
+        .route("/{chat_id}/join", post(join_chat))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             authentication_middleware,
@@ -78,9 +194,62 @@ fn configure_chat_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
 
     // Rotte che richiedono membership (autenticazione + membership middleware)
     let member_routes = Router::new()
+        .route("/{chat_id}", patch(update_chat).delete(delete_chat))
         .route("/{chat_id}/messages", get(get_chat_messages))
-        .route("/{chat_id}/members", get(list_chat_members))
-        .route("/{chat_id}/invite/{user_id}", post(invite_to_chat))
+        .route("/{chat_id}/messages/poll", get(poll_chat_messages))
+        .route("/{chat_id}/messages/search", get(search_chat_messages))
+        .route(
+            "/{chat_id}/messages/{message_id}",
+            patch(edit_message).delete(delete_message),
+        )
+        .route(
+            "/{chat_id}/messages/{message_id}/history",
+            get(get_message_history),
+        )
+        .route(
+            "/{chat_id}/messages/{message_id}/pin",
+            post(pin_message).delete(unpin_message),
+        )
+        .route(
+            "/{chat_id}/messages/{message_id}/translate",
+            get(translate_message),
+        )
+        .route(
+            "/{chat_id}/messages/{message_id}/report",
+            post(report_message),
+        )
+        .route("/{chat_id}/pins", get(list_pinned_messages))
+        .route("/{chat_id}/pin", post(pin_chat).delete(unpin_chat))
+        .route("/{chat_id}/draft", get(get_draft).put(save_draft))
+        .route("/{chat_id}/export", get(export_chat))
+        .route(
+            "/{chat_id}/attachments/{attachment_id}",
+            get(download_attachment),
+        )
+        .route(
+            "/{chat_id}/members",
+            get(list_chat_members).layer(middleware::from_fn(etag_middleware)),
+        )
+        .route(
+            "/{chat_id}/invite/{user_id}",
+            post(invite_to_chat).layer(middleware::from_fn_with_state(
+                state.clone(),
+                idempotency_middleware,
+            )),
+        )
+        .route(
+            "/{chat_id}/invite_links",
+            get(list_invite_links).post(create_invite_link),
+        )
+        .route(
+            "/{chat_id}/invite_links/{link_id}",
+            delete(revoke_invite_link),
+        )
+        .route("/{chat_id}/join_requests", get(list_join_requests))
+        .route(
+            "/{chat_id}/join_requests/{request_id}/{action}",
+            post(respond_to_join_request),
+        )
         .route(
             "/{chat_id}/members/{user_id}/role",
             patch(update_member_role),
@@ -90,6 +259,23 @@ fn configure_chat_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
             patch(transfer_ownership),
         )
         .route("/{chat_id}/members/{user_id}", delete(remove_member))
+        .route("/{chat_id}/members/{user_id}/ban", post(ban_member))
+        .route("/{chat_id}/members/{user_id}/mute", post(mute_member))
+        .route(
+            "/{chat_id}/members/{user_id}/custom_role",
+            put(assign_member_role),
+        )
+        .route(
+            "/{chat_id}/roles",
+            get(list_chat_roles).post(create_chat_role),
+        )
+        .route("/{chat_id}/roles/{role_id}", delete(delete_chat_role))
+        .route(
+            "/{chat_id}/webhooks",
+            get(list_webhooks).post(create_webhook),
+        )
+        .route("/{chat_id}/webhooks/{webhook_id}", delete(delete_webhook))
+        .route("/{chat_id}/audit", get(list_audit_log))
         .route("/{chat_id}/leave", post(leave_chat))
         .layer(middleware::from_fn_with_state(
             state.clone(),
@@ -103,16 +289,114 @@ fn configure_chat_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
     public_routes.merge(member_routes)
 }
 
+/// Configura le routes che ricevono file (allegati, avatar delle chat)
+///
+/// Hanno un limite di dimensione del corpo proprio, più ampio di quello JSON: il router
+/// principale le aggiunge dopo il layer del limite JSON, che quindi non le riguarda.
+fn configure_upload_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
+    use core::{
+        MULTIPART_OVERHEAD_BYTES, authentication_middleware, body_limit_middleware,
+        chat_membership_middleware,
+    };
+    use services::*;
+
+    // margine per il formato multipart: la dimensione esatta del file è controllata
+    // dall'handler con max_attachment_bytes
+    let max_upload_bytes = state.max_attachment_bytes + MULTIPART_OVERHEAD_BYTES;
+
+    Router::new()
+        .route("/{chat_id}/attachments", post(upload_attachment))
+        .route("/{chat_id}/avatar", put(update_chat_avatar))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            chat_membership_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state,
+            authentication_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            max_upload_bytes,
+            body_limit_middleware,
+        ))
+        // il limite di default degli estrattori di axum è sostituito dal middleware
+        .layer(DefaultBodyLimit::disable())
+}
+
 /// Configura le routes per la gestione degli inviti
 fn configure_invitation_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
-    use core::authentication_middleware;
+    use core::{authentication_middleware, etag_middleware};
     use services::*;
 
     Router::new()
-        .route("/pending", get(list_pending_invitations))
+        .route(
+            "/pending",
+            get(list_pending_invitations).layer(middleware::from_fn(etag_middleware)),
+        )
+        .route("/sent", get(list_sent_invitations))
         .route("/{invite_id}/{action}", post(respond_to_invitation))
         .layer(middleware::from_fn_with_state(
             state,
             authentication_middleware,
         ))
 }
+
+/// Configura le routes di amministrazione del server, riservate al ruolo ServerAdmin
+fn configure_admin_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
+    use core::{Scope, authentication_middleware, scope_middleware};
+    use services::*;
+
+    Router::new()
+        .route("/users", get(admin_list_users))
+        .route(
+            "/users/{user_id}/messages/export",
+            get(admin_export_user_messages),
+        )
+        .route(
+            "/chats/{chat_id}",
+            get(admin_get_chat).delete(admin_delete_chat),
+        )
+        .route("/chats/{chat_id}/export", get(admin_export_chat))
+        .route("/messages/{message_id}", delete(admin_delete_message))
+        .route("/stats", get(admin_online_stats))
+        .route("/stats/stream", get(admin_stats_stream))
+        .route("/config", get(admin_get_runtime_config))
+        .route("/config/reload", post(admin_reload_config))
+        .route("/reports", get(admin_list_reports))
+        .route("/reports/{report_id}/{action}", post(admin_resolve_report))
+        // i layer sono eseguiti dall'ultimo: prima l'autenticazione, poi il controllo dello scope
+        .layer(middleware::from_fn_with_state(
+            Scope::Admin,
+            scope_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state,
+            authentication_middleware,
+        ))
+}
+
+/// Configura le routes per la ricerca globale
+fn configure_search_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
+    use core::authentication_middleware;
+    use services::*;
+
+    Router::new()
+        .route("/", get(global_search))
+        .layer(middleware::from_fn_with_state(
+            state,
+            authentication_middleware,
+        ))
+}
+
+/// Configura la route della sincronizzazione incrementale
+fn configure_sync_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
+    use core::authentication_middleware;
+    use services::*;
+
+    Router::new()
+        .route("/", get(sync_changes))
+        .layer(middleware::from_fn_with_state(
+            state,
+            authentication_middleware,
+        ))
+}
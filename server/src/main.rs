@@ -1,30 +1,70 @@
 mod core;
 mod dtos;
 mod entities;
+mod graphql;
 mod monitoring;
+mod openapi;
 mod repositories;
 mod services;
 mod ws;
 
-use crate::core::{AppState, Config, authentication_middleware, chat_membership_middleware};
+use crate::core::config::LogFormat;
+use crate::core::{
+    API_V1_PREFIX, AppState, Config, JwtKeys, LogLevel, MULTIPART_OVERHEAD_BYTES, PasswordHasher,
+    STATS_SAMPLE_INTERVAL, Scope, TlsListener, authentication_middleware, body_limit_middleware,
+    build_auth_provider, build_content_filter, build_cors_layer, build_event_bus,
+    build_revocation_store, build_shared_cache, build_storage, chat_membership_middleware,
+    etag_middleware, idempotency_middleware, ip_rate_limit_middleware, legacy_path_gate_middleware,
+    legacy_path_middleware, log_filter, scope_middleware, start_sighup_reloader,
+    start_stats_sampler, start_tls,
+};
+use crate::graphql::graphql_handler;
 use crate::monitoring::{start_cpu_monitoring, CpuMonitorConfig};
+use crate::openapi::{ApiDoc, OPENAPI_JSON_PATH, SWAGGER_UI_PATH};
+use crate::repositories::DbPool;
+use crate::repositories::query_log::set_slow_query_threshold;
+use crate::services::oidc::OidcClient;
+use crate::services::translation::LibreTranslateProvider;
 use crate::services::*;
-use crate::ws::ws_handler;
+use crate::ws::{start_offline_queue_purger, start_presence_refresher, ws_handler};
 use axum::{
-    Router, middleware,
-    routing::{any, delete, get, patch, post},
+    Router,
+    extract::DefaultBodyLimit,
+    middleware,
+    routing::{any, delete, get, patch, post, put},
+    serve::ListenerExt,
 };
+use sqlx::MySqlPool;
 use sqlx::mysql::MySqlPoolOptions;
 use std::{net::SocketAddr, sync::Arc, time::Duration};
 use tokio::net::TcpListener;
-use tower_http::cors::{CorsLayer, Any};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use tracing_subscriber::{Layer, fmt, layer::SubscriberExt, reload, util::SubscriberInitExt};
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
+/// Tempo massimo di attesa per la chiusura delle connessioni WebSocket all'arresto
+const SHUTDOWN_DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
 
-/// Configura le routes di autenticazione (login, register)
-fn configure_auth_routes() -> Router<Arc<AppState>> {
+/// Configura le routes di autenticazione (login, register, refresh, logout, SSO)
+fn configure_auth_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
     Router::new()
         .route("/login", post(login_user))
         .route("/register", post(register_user))
+        .route("/refresh", post(refresh_tokens))
+        .route("/oidc/login", get(oidc_login))
+        .route("/oidc/callback", get(oidc_callback))
+        .route(
+            "/logout",
+            post(logout_user).layer(middleware::from_fn_with_state(
+                state.clone(),
+                authentication_middleware,
+            )),
+        )
+        // limite di richieste per IP: protegge login e registrazione da attacchi a forza bruta
+        .layer(middleware::from_fn_with_state(
+            state,
+            ip_rate_limit_middleware,
+        ))
 }
 
 /// Configura le routes per la gestione degli utenti
@@ -32,7 +72,22 @@ fn configure_user_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
     Router::new()
         .route("/", get(search_user_with_username))
         .route("/me", get(get_my_user).delete(delete_my_account))
+        .route("/me/profile", get(get_my_profile).patch(update_my_profile))
+        .route("/me/status", put(set_my_status))
+        .route("/me/privacy", get(get_my_privacy).put(update_my_privacy))
+        .route("/me/sessions", get(list_my_sessions))
+        .route(
+            "/me/sessions/{connection_id}",
+            delete(disconnect_my_session),
+        )
+        .route(
+            "/me/export",
+            get(get_data_export_status).post(request_data_export),
+        )
+        .route("/me/export/download", get(download_data_export))
         .route("/{user_id}", get(get_user_by_id))
+        .route("/{user_id}/report", post(report_user))
+        .route("/{user_id}/keys", get(get_key_bundle).put(upload_keys))
         .layer(middleware::from_fn_with_state(
             state,
             authentication_middleware,
@@ -43,7 +98,24 @@ fn configure_user_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
 fn configure_chat_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
     // Rotte che NON richiedono membership (solo autenticazione)
     let public_routes = Router::new()
-        .route("/", get(list_chats).post(create_chat))
+        .route(
+            "/",
+            get(list_chats).layer(middleware::from_fn(etag_middleware)),
+        )
+        .route(
+            "/",
+            post(create_chat).layer(middleware::from_fn_with_state(
+                state.clone(),
+                idempotency_middleware,
+            )),
+        )
+        .route("/unread", get(list_unread_counts))
+        .route(
+            "/overview",
+            get(list_chat_overview).layer(middleware::from_fn(etag_middleware)),
+        )
+        .route("/discover", get(discover_chats))
+        .route("/{chat_id}/join", post(join_chat))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             authentication_middleware,
@@ -51,15 +123,85 @@ fn configure_chat_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
 
     // Rotte che richiedono membership (autenticazione + membership middleware)
     let member_routes = Router::new()
+        .route("/{chat_id}", patch(update_chat).delete(delete_chat))
         .route("/{chat_id}/messages", get(get_chat_messages))
-        .route("/{chat_id}/members", get(list_chat_members))
-        .route("/{chat_id}/invite/{user_id}", post(invite_to_chat))
+        .route("/{chat_id}/messages/poll", get(poll_chat_messages))
+        .route("/{chat_id}/messages/search", get(search_chat_messages))
+        .route(
+            "/{chat_id}/messages/{message_id}",
+            patch(edit_message).delete(delete_message),
+        )
+        .route(
+            "/{chat_id}/messages/{message_id}/history",
+            get(get_message_history),
+        )
+        .route(
+            "/{chat_id}/messages/{message_id}/pin",
+            post(pin_message).delete(unpin_message),
+        )
+        .route(
+            "/{chat_id}/messages/{message_id}/translate",
+            get(translate_message),
+        )
+        .route(
+            "/{chat_id}/messages/{message_id}/report",
+            post(report_message),
+        )
+        .route("/{chat_id}/pins", get(list_pinned_messages))
+        .route("/{chat_id}/pin", post(pin_chat).delete(unpin_chat))
+        .route("/{chat_id}/draft", get(get_draft).put(save_draft))
+        .route("/{chat_id}/export", get(export_chat))
+        .route(
+            "/{chat_id}/attachments/{attachment_id}",
+            get(download_attachment),
+        )
+        .route(
+            "/{chat_id}/members",
+            get(list_chat_members).layer(middleware::from_fn(etag_middleware)),
+        )
+        .route(
+            "/{chat_id}/invite/{user_id}",
+            post(invite_to_chat).layer(middleware::from_fn_with_state(
+                state.clone(),
+                idempotency_middleware,
+            )),
+        )
+        .route(
+            "/{chat_id}/invite_links",
+            get(list_invite_links).post(create_invite_link),
+        )
+        .route(
+            "/{chat_id}/invite_links/{link_id}",
+            delete(revoke_invite_link),
+        )
+        .route("/{chat_id}/join_requests", get(list_join_requests))
+        .route(
+            "/{chat_id}/join_requests/{request_id}/{action}",
+            post(respond_to_join_request),
+        )
         .route(
             "/{chat_id}/members/{user_id}/role",
             patch(update_member_role),
         )
         .route("/{chat_id}/transfer_ownership/{new_owner_id}", patch(transfer_ownership))
         .route("/{chat_id}/members/{user_id}", delete(remove_member))
+        .route("/{chat_id}/members/{user_id}/ban", post(ban_member))
+        .route("/{chat_id}/members/{user_id}/mute", post(mute_member))
+        .route(
+            "/{chat_id}/members/{user_id}/custom_role",
+            put(assign_member_role),
+        )
+        .route(
+            "/{chat_id}/roles",
+            get(list_chat_roles).post(create_chat_role),
+        )
+        .route("/{chat_id}/roles/{role_id}", delete(delete_chat_role))
+        .route(
+            "/{chat_id}/webhooks",
+            get(list_webhooks).post(create_webhook),
+        )
+        .route("/{chat_id}/webhooks/{webhook_id}", delete(delete_webhook))
+        .route("/{chat_id}/audit", get(list_audit_log))
         .route("/{chat_id}/leave", post(leave_chat))
         .route("/{chat_id}/clean", post(clean_chat))
         .layer(middleware::from_fn_with_state(
@@ -74,10 +216,42 @@ fn configure_chat_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
     public_routes.merge(member_routes)
 }
 
+/// Configura le routes che ricevono file (allegati, avatar delle chat)
+///
+/// Hanno un limite di dimensione del corpo proprio, più ampio di quello JSON: il router
+/// principale le aggiunge dopo il layer del limite JSON, che quindi non le riguarda.
+fn configure_upload_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
+    // margine per il formato multipart: la dimensione esatta del file è controllata
+    // dall'handler con max_attachment_bytes
+    let max_upload_bytes = state.max_attachment_bytes + MULTIPART_OVERHEAD_BYTES;
+
+    Router::new()
+        .route("/{chat_id}/attachments", post(upload_attachment))
+        .route("/{chat_id}/avatar", put(update_chat_avatar))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            chat_membership_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state,
+            authentication_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            max_upload_bytes,
+            body_limit_middleware,
+        ))
+        // il limite di default degli estrattori di axum è sostituito dal middleware
+        .layer(DefaultBodyLimit::disable())
+}
+
 /// Configura le routes per la gestione degli inviti
 fn configure_invitation_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
     Router::new()
-        .route("/pending", get(list_pending_invitations))
+        .route(
+            "/pending",
+            get(list_pending_invitations).layer(middleware::from_fn(etag_middleware)),
+        )
+        .route("/sent", get(list_sent_invitations))
         .route("/{invite_id}/{action}", post(respond_to_invitation))
         .layer(middleware::from_fn_with_state(
             state,
@@ -85,48 +259,318 @@ fn configure_invitation_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
         ))
 }
 
+/// Configura le routes di amministrazione del server, riservate al ruolo ServerAdmin
+fn configure_admin_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/users", get(admin_list_users))
+        .route(
+            "/users/{user_id}/messages/export",
+            get(admin_export_user_messages),
+        )
+        .route(
+            "/chats/{chat_id}",
+            get(admin_get_chat).delete(admin_delete_chat),
+        )
+        .route("/chats/{chat_id}/export", get(admin_export_chat))
+        .route("/messages/{message_id}", delete(admin_delete_message))
+        .route("/stats", get(admin_online_stats))
+        .route("/stats/stream", get(admin_stats_stream))
+        .route("/config", get(admin_get_runtime_config))
+        .route("/config/reload", post(admin_reload_config))
+        .route("/reports", get(admin_list_reports))
+        .route("/reports/{report_id}/{action}", post(admin_resolve_report))
+        // i layer sono eseguiti dall'ultimo: prima l'autenticazione, poi il controllo dello scope
+        .layer(middleware::from_fn_with_state(
+            Scope::Admin,
+            scope_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state,
+            authentication_middleware,
+        ))
+}
+
+/// Configura le routes per la ricerca globale
+fn configure_search_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(global_search))
+        .layer(middleware::from_fn_with_state(
+            state,
+            authentication_middleware,
+        ))
+}
+
+/// Configura la route della sincronizzazione incrementale
+fn configure_sync_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(sync_changes))
+        .layer(middleware::from_fn_with_state(
+            state,
+            authentication_middleware,
+        ))
+}
+
+/// Configura tutte le routes dell'API, montate sotto il prefisso di versione
+fn configure_api_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
+    Router::new()
+        .nest("/auth", configure_auth_routes(state.clone()))
+        .nest("/users", configure_user_routes(state.clone()))
+        .nest("/chats", configure_chat_routes(state.clone()))
+        .nest("/invitations", configure_invitation_routes(state.clone()))
+        .nest("/search", configure_search_routes(state.clone()))
+        .nest("/sync", configure_sync_routes(state.clone()))
+        .nest("/admin", configure_admin_routes(state.clone()))
+        // i webhook si autenticano con il token nel path, non con il JWT
+        .route(
+            "/webhooks/{webhook_token}",
+            post(post_webhook_message).layer(middleware::from_fn_with_state(
+                state.clone(),
+                idempotency_middleware,
+            )),
+        )
+        // chi conosce il token entra nella chat senza esserne ancora membro
+        .route(
+            "/invite_links/{invite_token}/join",
+            post(join_by_invite_link).layer(middleware::from_fn_with_state(
+                state.clone(),
+                authentication_middleware,
+            )),
+        )
+        .route(
+            "/graphql",
+            post(graphql_handler).layer(middleware::from_fn_with_state(
+                state.clone(),
+                authentication_middleware,
+            )),
+        )
+        .route(
+            "/ws",
+            any(ws_handler).layer(middleware::from_fn_with_state(
+                state.clone(),
+                authentication_middleware,
+            )),
+        )
+        // limite JSON per tutte le route registrate fin qui
+        .layer(middleware::from_fn_with_state(
+            state.max_json_body_bytes,
+            body_limit_middleware,
+        ))
+        .nest("/chats", configure_upload_routes(state))
+}
+
+/// Attende Ctrl+C o SIGTERM, poi invia ai client WebSocket la chiusura "server restarting"
+///
+/// Al termine di questa future axum smette di accettare nuove connessioni
+async fn shutdown_signal(state: Arc<AppState>) {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    println!("Shutdown signal received, closing WebSocket connections...");
+    state.users_online.shutdown_all();
+}
+
+/// Attende che le connessioni WebSocket abbiano completato la chiusura (last_seen e
+/// presenza salvati sul database), al massimo per `SHUTDOWN_DRAIN_TIMEOUT`
+async fn drain_websockets(state: &AppState) {
+    let deadline = tokio::time::Instant::now() + SHUTDOWN_DRAIN_TIMEOUT;
+    while state.users_online.online_count() > 0 {
+        if tokio::time::Instant::now() >= deadline {
+            eprintln!(
+                "✗ {} WebSocket connections still open after {}s, shutting down anyway",
+                state.users_online.online_count(),
+                SHUTDOWN_DRAIN_TIMEOUT.as_secs()
+            );
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    println!("✓ All WebSocket connections closed");
+}
+
+/// Apre un pool verso `url`, riprovando ogni 2 secondi finché il database non risponde
+async fn connect_with_retry(pool_options: &MySqlPoolOptions, url: &str) -> MySqlPool {
+    loop {
+        match pool_options.clone().connect(url).await {
+            Ok(pool) => {
+                println!("✓ Database connection established successfully!");
+                return pool;
+            }
+            Err(e) => {
+                eprintln!("✗ Failed to connect to database: {}", e);
+                eprintln!("  Retrying in 2 seconds...");
+                tokio::time::sleep(Duration::from_secs(2)).await;
+            }
+        }
+    }
+}
+
+/// Apre il file SQLite indicato da `url` e applica le migrazioni di `migrations_sqlite/`
+#[cfg(feature = "sqlite")]
+async fn connect_sqlite(url: &str, max_connections: u32) -> DbPool {
+    let pool = repositories::sqlite::connect(url, max_connections)
+        .await
+        .expect("Failed to open the SQLite database");
+    repositories::sqlite::MIGRATOR
+        .run(&pool)
+        .await
+        .expect("Failed to apply the SQLite migrations");
+    println!("✓ SQLite database ready");
+    pool.into()
+}
+
+/// Apre il pool del database indicato da `url`: MySQL, oppure SQLite con la feature `sqlite`
+///
+/// Il pool SQLite usa solo il numero massimo di connessioni di `pool_options`.
+async fn connect_database(pool_options: &MySqlPoolOptions, url: &str) -> DbPool {
+    #[cfg(feature = "sqlite")]
+    if core::config::is_sqlite_url(url) {
+        return connect_sqlite(url, pool_options.get_max_connections()).await;
+    }
+    connect_with_retry(pool_options, url).await.into()
+}
+
 #[tokio::main]
 async fn main() {
-    // Carica la configurazione dalle variabili d'ambiente
-    let config = Config::from_env().expect("Failed to load configuration. Check your .env file.");
+    // Carica la configurazione dalle variabili d'ambiente; se non è valida elenca tutti
+    // i problemi ed esce
+    let config = Config::from_env().await.unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    });
 
-    // Inizializza il tracing subscriber con il log level dalla configurazione
+    // Inizializza il tracing subscriber con log level e formato dalla configurazione;
+    // il filtro resta sostituibile quando la configurazione viene ricaricata
+    let (log_filter_layer, log_filter_handle) = reload::Layer::new(log_filter(&config.log_level));
+    let log_format_layer = match config.log_format {
+        LogFormat::Pretty => fmt::layer().pretty().boxed(),
+        LogFormat::Full => fmt::layer().boxed(),
+        LogFormat::Compact => fmt::layer().compact().with_ansi(false).boxed(),
+    };
     tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| format!("server={},tower_http=debug", config.log_level).into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
+        .with(log_filter_layer)
+        .with(log_format_layer)
         .init();
 
     // Stampa info sulla configurazione
     config.print_info();
 
+    // Soglia oltre la quale le query dei repository finiscono nel log come lente
+    set_slow_query_threshold(Duration::from_millis(config.slow_query_threshold_ms));
+
     // Builder per configurare le connessioni al database con retry automatico
     let pool_options = MySqlPoolOptions::new()
         .max_connections(config.max_connections)
+        .min_connections(config.min_connections)
         .max_lifetime(Duration::from_secs(config.connection_lifetime_secs))
-        .acquire_timeout(Duration::from_secs(2)) // Timeout per l'acquisizione di una connessione dal pool
-        .test_before_acquire(true);
+        .acquire_timeout(Duration::from_secs(config.acquire_timeout_secs))
+        // 0 = le connessioni inattive restano aperte fino alla fine del loro ciclo di vita
+        .idle_timeout(
+            (config.idle_timeout_secs > 0).then(|| Duration::from_secs(config.idle_timeout_secs)),
+        )
+        .test_before_acquire(config.test_before_acquire);
 
     // Avvio il pool di connessioni al database con retry automatico ogni 2 secondi
     println!("Attempting to connect to database...");
-    let connection_pool = loop {
-        match pool_options.clone().connect(&config.database_url).await {
-            Ok(pool) => {
-                println!("✓ Database connection established successfully!");
-                break pool;
-            }
-            Err(e) => {
-                eprintln!("✗ Failed to connect to database: {}", e);
-                eprintln!("  Retrying in 2 seconds...");
-                tokio::time::sleep(Duration::from_secs(2)).await;
-            }
+    let connection_pool = connect_database(&pool_options, &config.database_url).await;
+
+    // Replica in sola lettura opzionale, con le stesse impostazioni del pool primario
+    let read_pool = match &config.database_read_url {
+        Some(url) => {
+            println!("Attempting to connect to database read replica...");
+            connect_database(&pool_options, url).await
         }
+        None => connection_pool.clone(),
     };
 
+    // Storage degli allegati (disco locale o S3 compatibile)
+    let storage = build_storage(&config.storage).expect("Failed to initialize attachment storage");
+
+    // Cache condivisa su Redis per i deployment con più istanze, se configurata
+    let shared_cache =
+        build_shared_cache(config.redis_url.as_deref()).expect("Failed to initialize shared cache");
+
+    // Lista dei token revocati (in memoria o sulla cache condivisa)
+    let revoked_tokens = build_revocation_store(shared_cache.clone());
+
+    // Backend che verifica le password al login (database locale o directory LDAP)
+    let auth_provider = build_auth_provider(&config.auth_backend)
+        .expect("Failed to initialize authentication backend");
+
+    // Parole vietate e classificatore esterno per le chat che filtrano i contenuti
+    let content_filter =
+        build_content_filter(&config.content_filter).expect("Failed to initialize content filter");
+
+    // Algoritmo di hash delle nuove password (bcrypt o Argon2id)
+    let password_hasher =
+        PasswordHasher::new(&config.password_hash).expect("Invalid password hashing configuration");
+
     // Creiamo lo stato dell'applicazione con i repository e la configurazione
-    let state = Arc::new(AppState::new(connection_pool, config.jwt_secret.clone()));
+    let mut state = AppState::new_with_read_replica(
+        connection_pool.clone(),
+        read_pool.clone(),
+        config.jwt_secret.clone(),
+    );
+    if let Some(cache) = shared_cache {
+        state = state.with_shared_cache(cache);
+    }
+    let mut state = state
+        .with_repository_cache(&config.cache)
+        .with_storage(storage, config.max_attachment_bytes)
+        .with_max_json_body_bytes(config.max_json_body_bytes)
+        .with_jwt_keys(JwtKeys::from_config(&config).expect("Invalid JWT signing keys"))
+        .with_profile(config.profile, config.cookie_secure)
+        .with_legacy_api_paths(config.legacy_api_paths)
+        .with_log_level(LogLevel::new(&config.log_level, Some(log_filter_handle)))
+        .with_revocation_store(revoked_tokens)
+        .with_auth_provider(auth_provider)
+        .with_rate_limits(&config.rate_limit)
+        .with_login_lockout(&config.login_lockout)
+        .with_flood_detection(&config.flood)
+        .with_password_policy(&config.password_policy)
+        .with_password_hasher(password_hasher)
+        .with_ws_config(config.ws.clone())
+        .with_max_group_members(config.max_group_members)
+        .with_content_filter(content_filter)
+        .with_server_admins(config.server_admins.clone());
+    if let Some(url) = config.translation_api_url.clone() {
+        state = state.with_translator(Arc::new(LibreTranslateProvider::new(
+            url,
+            config.translation_api_key.clone(),
+        )));
+    }
+    // Login SSO: i login in corso stanno sulla cache condivisa, se c'è
+    if let Some(oidc) = config.oidc.clone() {
+        let mut client = OidcClient::new(oidc);
+        if let Some(cache) = state.shared_cache.clone() {
+            client = client.with_shared_cache(cache);
+        }
+        state = state.with_oidc(Arc::new(client));
+    }
+    // Eventi di dominio verso NATS o Kafka, se configurati
+    if let Some(event_bus) =
+        build_event_bus(&config.event_bus).expect("Failed to initialize event bus")
+    {
+        state = state.with_event_bus(event_bus);
+    }
+    let state = Arc::new(state);
 
     // Avvio task di monitoraggio CPU in background
     let cpu_monitor_config = CpuMonitorConfig {
@@ -137,6 +581,37 @@ async fn main() {
     tokio::spawn(start_cpu_monitoring(cpu_monitor_config));
     println!("✓ CPU monitoring started (logging to cpu_stats.log)");
 
+    // Ricaricamento di livello di log, rate limit e feature flag ad ogni SIGHUP
+    start_sighup_reloader(state.clone());
+
+    // Avvio campionamento delle statistiche per lo stream degli amministratori
+    start_stats_sampler(state.clone(), STATS_SAMPLE_INTERVAL);
+
+    // Rinnovo periodico della presenza online pubblicata per le altre istanze
+    if let Some(presence) = state.presence.clone() {
+        start_presence_refresher(state.clone(), presence);
+    }
+
+    // Pulizia periodica della coda di consegna per gli utenti offline
+    start_offline_queue_purger(state.clone());
+
+    // Eliminazione periodica degli allegati non più usati da messaggi o avatar
+    start_attachment_collector(state.clone());
+
+    // Avvio task periodico di cancellazione degli account disattivati da troppo tempo
+    let purge_state = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60 * 60));
+        loop {
+            interval.tick().await;
+            match purge_expired_deactivations(&purge_state).await {
+                Ok(0) => {}
+                Ok(purged) => tracing::info!("Purged {} deactivated accounts", purged),
+                Err(_) => tracing::error!("Failed to purge deactivated accounts"),
+            }
+        }
+    });
+
     // Definizione indirizzo del server
     let addr = SocketAddr::from((
         config
@@ -145,42 +620,75 @@ async fn main() {
             .expect("Invalid SERVER_HOST format"),
         config.server_port,
     ));
-    println!("Server listening on http://{}", addr);
 
     // Creazione del listener TCP per ascoltare l'indirizzo
     let listener = TcpListener::bind(addr)
         .await
         .expect("Unable to start TCP listener.");
 
-    // Configurazione CORS per permettere richieste dal frontend
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any)
-        .expose_headers([
-            axum::http::header::AUTHORIZATION,
-            axum::http::header::SET_COOKIE,
-        ]);
+    // TLS opzionale, con certificato da file o ottenuto via ACME
+    let tls_config = start_tls(&config.tls).expect("Failed to initialize TLS");
+    let scheme = if tls_config.is_some() {
+        "https"
+    } else {
+        "http"
+    };
+    println!("Server listening on {}://{}", scheme, addr);
+
+    // Configurazione CORS per permettere richieste dai client nel browser
+    let cors = build_cors_layer(&config.cors).expect("Invalid CORS configuration");
 
     // Costruzione del router principale con tutte le routes
     let app = Router::new()
-        .route("/", get(root))
-        .nest("/auth", configure_auth_routes())
-        .nest("/users", configure_user_routes(state.clone()))
-        .nest("/chats", configure_chat_routes(state.clone()))
-        .nest("/invitations", configure_invitation_routes(state.clone()))
-        .route(
-            "/ws",
-            any(ws_handler).layer(middleware::from_fn_with_state(
+        // sonde di stato per l'orchestratore, senza autenticazione e senza versione
+        .route("/healthz", get(healthz))
+        .route("/livez", get(livez))
+        .route("/readyz", get(readyz))
+        // specifica OpenAPI e Swagger UI, pubbliche come le sonde
+        .merge(SwaggerUi::new(SWAGGER_UI_PATH).url(OPENAPI_JSON_PATH, ApiDoc::openapi()))
+        .nest(API_V1_PREFIX, configure_api_routes(state.clone()));
+
+    // vecchi percorsi senza prefisso, deprecati: restano finché i client non migrano,
+    // serviti solo mentre LEGACY_API_PATHS è attivo (ricaricabile a caldo)
+    let app = app.merge(
+        configure_api_routes(state.clone())
+            .layer(middleware::from_fn(legacy_path_middleware))
+            .layer(middleware::from_fn_with_state(
                 state.clone(),
-                authentication_middleware,
+                legacy_path_gate_middleware,
             )),
-        )
-        .layer(cors)
-        .with_state(state);
+    );
+
+    let app = app.layer(cors).with_state(state.clone());
 
     // Avvia il server
-    axum::serve(listener, app)
-        .await
-        .expect("Error serving the application");
+    // ConnectInfo serve al rate limiting per conoscere l'IP del client
+    let app = app.into_make_service_with_connect_info::<SocketAddr>();
+    let served = match tls_config {
+        Some(tls_config) => {
+            // tap_io rende disponibile ConnectInfo<SocketAddr> anche per il listener TLS
+            let listener = TlsListener::new(listener, tls_config)
+                .expect("Unable to start TLS listener.")
+                .tap_io(|stream| {
+                    if let Err(e) = stream.get_ref().0.set_nodelay(true) {
+                        tracing::debug!("Failed to set TCP_NODELAY: {}", e);
+                    }
+                });
+            axum::serve(listener, app)
+                .with_graceful_shutdown(shutdown_signal(state.clone()))
+                .await
+        }
+        None => {
+            axum::serve(listener, app)
+                .with_graceful_shutdown(shutdown_signal(state.clone()))
+                .await
+        }
+    };
+    served.expect("Error serving the application");
+
+    // Arresto: le connessioni WebSocket salvano il proprio stato prima che il pool venga chiuso
+    drain_websockets(&state).await;
+    connection_pool.close().await;
+    read_pool.close().await;
+    println!("✓ Database connections closed, server stopped");
 }
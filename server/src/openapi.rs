@@ -0,0 +1,211 @@
+//! OpenAPI - Specifica dell'API REST generata dal codice
+//!
+//! Le route sono descritte dagli attributi `#[utoipa::path]` sugli handler e i corpi dai
+//! DTO che derivano `ToSchema`: la specifica resta allineata al codice senza essere scritta
+//! a mano. Il router la serve su `/openapi.json`, con la Swagger UI su `/docs`. Le route
+//! dell'API sono documentate sotto `API_V1_PREFIX`, i percorsi legacy senza prefisso no.
+
+use crate::core::API_V1_PREFIX;
+use crate::core::error::ErrorResponse;
+use crate::services;
+use utoipa::{
+    Modify, OpenApi, ToSchema,
+    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+};
+
+/// Path della specifica JSON
+pub const OPENAPI_JSON_PATH: &str = "/openapi.json";
+
+/// Path della Swagger UI
+pub const SWAGGER_UI_PATH: &str = "/docs";
+
+/// Corpo multipart degli upload, con il file nel campo "file"
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub struct FileUpload {
+    #[schema(value_type = String, format = Binary)]
+    file: Vec<u8>,
+}
+
+/// Aggiunge lo schema di autenticazione JWT usato da `security` nella specifica
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build(),
+            ),
+        );
+    }
+}
+
+/// Specifica completa: sonde di stato e route dell'API versionate
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "IronLink API",
+        description = "API REST del server IronLink. Le route autenticate richiedono l'header \
+            `Authorization: Bearer <token>`; gli errori hanno sempre il corpo `ErrorResponse`. \
+            La messaggistica in tempo reale passa dal WebSocket `/api/v1/ws` e l'API GraphQL \
+            da `/api/v1/graphql`, non descritti in questa specifica."
+    ),
+    modifiers(&BearerAuth),
+    security(("bearer_auth" = [])),
+    components(schemas(ErrorResponse)),
+    tags((name = "health", description = "Sonde di stato per l'orchestratore")),
+    paths(
+        services::health::healthz,
+        services::health::livez,
+        services::health::readyz,
+    ),
+    nest((path = API_V1_PREFIX, api = ApiV1Doc))
+)]
+pub struct ApiDoc;
+
+/// Route dell'API versionate, annidate sotto `API_V1_PREFIX` in `ApiDoc`
+#[derive(OpenApi)]
+#[openapi(
+    tags(
+        (name = "auth", description = "Login, registrazione, rinnovo dei token e SSO"),
+        (name = "users", description = "Profilo, privacy, sessioni e dati dell'utente"),
+        (name = "chats", description = "Chat dell'utente e loro impostazioni"),
+        (name = "messages", description = "Messaggi e allegati di una chat"),
+        (name = "members", description = "Membri, ruoli di base e richieste di ingresso"),
+        (name = "roles", description = "Ruoli personalizzati delle chat"),
+        (name = "webhooks", description = "Webhook in ingresso per pubblicare messaggi"),
+        (name = "invitations", description = "Inviti ricevuti e inviati"),
+        (name = "search", description = "Ricerca globale"),
+        (name = "sync", description = "Sincronizzazione incrementale all'avvio del client"),
+        (name = "admin", description = "Amministrazione del server, riservata al ruolo ServerAdmin"),
+    ),
+    paths(
+        services::auth::login_user,
+        services::auth::register_user,
+        services::auth::refresh_tokens,
+        services::auth::logout_user,
+        services::auth::oidc_login,
+        services::auth::oidc_callback,
+        services::user::search_user_with_username,
+        services::user::get_my_user,
+        services::user::delete_my_account,
+        services::user::get_my_profile,
+        services::user::update_my_profile,
+        services::user::set_my_status,
+        services::user::get_my_privacy,
+        services::user::update_my_privacy,
+        services::user::list_my_sessions,
+        services::user::disconnect_my_session,
+        services::export::request_data_export,
+        services::export::get_data_export_status,
+        services::export::download_data_export,
+        services::user::get_user_by_id,
+        services::report::report_user,
+        services::user_keys::get_key_bundle,
+        services::user_keys::upload_keys,
+        services::chat::list_chats,
+        services::chat::create_chat,
+        services::chat::list_unread_counts,
+        services::chat::list_chat_overview,
+        services::chat::discover_chats,
+        services::join_request::join_chat,
+        services::chat::update_chat,
+        services::chat::delete_chat,
+        services::chat::update_chat_avatar,
+        services::chat::pin_chat,
+        services::chat::unpin_chat,
+        services::draft::get_draft,
+        services::draft::save_draft,
+        services::export::export_chat,
+        services::audit::list_audit_log,
+        services::chat::get_chat_messages,
+        services::chat::poll_chat_messages,
+        services::chat::search_chat_messages,
+        services::chat::edit_message,
+        services::chat::get_message_history,
+        services::chat::delete_message,
+        services::chat::pin_message,
+        services::chat::unpin_message,
+        services::chat::list_pinned_messages,
+        services::translation::translate_message,
+        services::report::report_message,
+        services::attachment::upload_attachment,
+        services::attachment::download_attachment,
+        services::membership::list_chat_members,
+        services::membership::invite_to_chat,
+        services::invite_link::list_invite_links,
+        services::invite_link::create_invite_link,
+        services::invite_link::revoke_invite_link,
+        services::invite_link::join_by_invite_link,
+        services::join_request::list_join_requests,
+        services::join_request::respond_to_join_request,
+        services::membership::update_member_role,
+        services::membership::transfer_ownership,
+        services::membership::remove_member,
+        services::membership::ban_member,
+        services::membership::mute_member,
+        services::membership::leave_chat,
+        services::membership::clean_chat,
+        services::role::list_chat_roles,
+        services::role::create_chat_role,
+        services::role::delete_chat_role,
+        services::role::assign_member_role,
+        services::webhook::list_webhooks,
+        services::webhook::create_webhook,
+        services::webhook::delete_webhook,
+        services::webhook::post_webhook_message,
+        services::membership::list_pending_invitations,
+        services::membership::list_sent_invitations,
+        services::membership::respond_to_invitation,
+        services::search::global_search,
+        services::sync::sync_changes,
+        services::admin::admin_list_users,
+        services::admin::admin_get_chat,
+        services::admin::admin_export_chat,
+        services::admin::admin_export_user_messages,
+        services::admin::admin_delete_chat,
+        services::admin::admin_delete_message,
+        services::admin::admin_online_stats,
+        services::admin::admin_stats_stream,
+        services::admin::admin_get_runtime_config,
+        services::admin::admin_reload_config,
+        services::admin::admin_list_reports,
+        services::admin::admin_resolve_report,
+    )
+)]
+struct ApiV1Doc;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec_documents_routes_and_security() {
+        let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+
+        let chats = &spec["paths"]["/api/v1/chats"];
+        assert!(chats["get"].is_object());
+        assert!(chats["post"]["requestBody"].is_object());
+        assert!(spec["paths"]["/chats"].is_null());
+        assert!(
+            spec["paths"]["/api/v1/chats/{chat_id}/messages/{message_id}"]["patch"].is_object()
+        );
+        assert!(spec["components"]["schemas"]["ChatDTO"].is_object());
+        assert!(spec["components"]["securitySchemes"]["bearer_auth"].is_object());
+
+        // le sonde e il login non richiedono il JWT
+        assert_eq!(
+            spec["paths"]["/healthz"]["get"]["security"],
+            serde_json::json!([{}])
+        );
+        assert_eq!(
+            spec["paths"]["/api/v1/auth/login"]["post"]["security"],
+            serde_json::json!([{}])
+        );
+    }
+}
@@ -0,0 +1,305 @@
+//! AttachmentRepository - Repository per la gestione degli allegati
+
+use super::query_log::timed;
+#[cfg(feature = "sqlite")]
+use super::sqlite;
+use super::{Create, DbPool, Delete, Read};
+use crate::dtos::CreateAttachmentDTO;
+use crate::entities::Attachment;
+use chrono::{DateTime, Utc};
+use sqlx::Error;
+use tracing::{debug, info, instrument};
+
+//ATTACHMENT REPOSITORY
+pub struct AttachmentRepository {
+    connection_pool: DbPool,
+}
+
+impl AttachmentRepository {
+    pub fn new(connection_pool: impl Into<DbPool>) -> Self {
+        Self {
+            connection_pool: connection_pool.into(),
+        }
+    }
+
+    /// Attachments no message or chat refers to, uploaded before `uploaded_before`
+    ///
+    /// # Arguments
+    /// * `uploaded_before` - Only attachments older than this are returned, so that a file
+    ///   uploaded for a message that is still being sent is not collected
+    /// * `limit` - Maximum number of attachments to return, oldest first
+    #[instrument(skip(self))]
+    pub async fn find_orphaned(
+        &self,
+        uploaded_before: &DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<Attachment>, Error> {
+        debug!("Fetching orphaned attachments");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::attachment::find_orphaned(pool, uploaded_before, limit).await;
+        }
+        let attachments = sqlx::query_as!(
+            Attachment,
+            r#"
+            SELECT 
+                attachment_id,
+                chat_id,
+                uploader_id,
+                file_name,
+                content_type,
+                size_bytes,
+                storage_key,
+                ref_count,
+                created_at
+            FROM attachments 
+            WHERE ref_count = 0 AND created_at < ? 
+            ORDER BY created_at 
+            LIMIT ?
+            "#,
+            uploaded_before,
+            limit
+        )
+        .fetch_all(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(attachments)
+    }
+
+    /// Delete an attachment only if it is still unreferenced
+    ///
+    /// # Returns
+    /// `true` if the row was deleted, `false` if a message or a chat started using the
+    /// attachment in the meantime (or it no longer exists)
+    #[instrument(skip(self))]
+    pub async fn delete_if_orphaned(&self, id: &i32) -> Result<bool, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::attachment::delete_if_orphaned(pool, id).await;
+        }
+        let result = sqlx::query!(
+            "DELETE FROM attachments WHERE attachment_id = ? AND ref_count = 0",
+            id
+        )
+        .execute(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Storage keys of every attachment of a chat
+    ///
+    /// Deleting the chat removes the rows by cascade: the files must be collected first.
+    #[instrument(skip(self))]
+    pub async fn find_storage_keys_by_chat(&self, chat_id: &i32) -> Result<Vec<String>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::attachment::find_storage_keys_by_chat(pool, chat_id).await;
+        }
+        let keys = sqlx::query_scalar!(
+            "SELECT storage_key FROM attachments WHERE chat_id = ?",
+            chat_id
+        )
+        .fetch_all(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(keys)
+    }
+}
+
+impl Create<Attachment, CreateAttachmentDTO> for AttachmentRepository {
+    #[instrument(skip(self, data), fields(chat_id = %data.chat_id, uploader_id = %data.uploader_id))]
+    async fn create(&self, data: &CreateAttachmentDTO) -> Result<Attachment, Error> {
+        debug!("Creating new attachment");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::attachment::create(pool, data).await;
+        }
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO attachments (chat_id, uploader_id, file_name, content_type, size_bytes, storage_key, created_at) 
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+            data.chat_id,
+            data.uploader_id,
+            data.file_name,
+            data.content_type,
+            data.size_bytes,
+            data.storage_key,
+            data.created_at
+        )
+        .execute(timed(self.connection_pool.mysql()))
+        .await?;
+
+        // Get the last inserted ID
+        let new_id = result.last_insert_id() as i32;
+
+        info!("Attachment created with id {}", new_id);
+
+        Ok(Attachment {
+            attachment_id: new_id,
+            chat_id: data.chat_id,
+            uploader_id: data.uploader_id,
+            file_name: data.file_name.clone(),
+            content_type: data.content_type.clone(),
+            size_bytes: data.size_bytes,
+            storage_key: data.storage_key.clone(),
+            ref_count: 0,
+            created_at: data.created_at,
+        })
+    }
+}
+
+impl Read<Attachment, i32> for AttachmentRepository {
+    async fn read(&self, id: &i32) -> Result<Option<Attachment>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::attachment::read(pool, id).await;
+        }
+        let attachment = sqlx::query_as!(
+            Attachment,
+            r#"
+            SELECT 
+                attachment_id,
+                chat_id,
+                uploader_id,
+                file_name,
+                content_type,
+                size_bytes,
+                storage_key,
+                ref_count,
+                created_at
+            FROM attachments 
+            WHERE attachment_id = ?
+            "#,
+            id
+        )
+        .fetch_optional(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(attachment)
+    }
+}
+
+impl Delete<i32> for AttachmentRepository {
+    async fn delete(&self, id: &i32) -> Result<(), Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::attachment::delete(pool, id).await;
+        }
+        // i messaggi che lo referenziano restano, attachment_id viene azzerato dal vincolo ON DELETE SET NULL
+        sqlx::query!("DELETE FROM attachments WHERE attachment_id = ?", id)
+            .execute(timed(self.connection_pool.mysql()))
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dtos::CreateMessageDTO;
+    use crate::entities::{ContentFormat, MessageType};
+    use crate::repositories::MessageRepository;
+    use sqlx::MySqlPool;
+
+    fn sample_attachment(chat_id: i32, uploader_id: i32) -> CreateAttachmentDTO {
+        CreateAttachmentDTO {
+            chat_id,
+            uploader_id,
+            file_name: "report.pdf".to_string(),
+            content_type: "application/pdf".to_string(),
+            size_bytes: 2048,
+            storage_key: format!("chats/{}/test-key", chat_id),
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Test: verifica che create salvi l'allegato e read lo restituisca
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_create_and_read_attachment(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = AttachmentRepository::new(pool.clone());
+
+        let created = repo.create(&sample_attachment(1, 1)).await?;
+        let read = repo
+            .read(&created.attachment_id)
+            .await?
+            .expect("Attachment should exist");
+
+        assert_eq!(read.chat_id, 1);
+        assert_eq!(read.uploader_id, 1);
+        assert_eq!(read.file_name, "report.pdf");
+        assert_eq!(read.content_type, "application/pdf");
+        assert_eq!(read.size_bytes, 2048);
+        assert_eq!(read.storage_key, "chats/1/test-key");
+
+        Ok(())
+    }
+
+    /// Test: verifica che read restituisca None per un allegato inesistente
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_read_nonexistent_attachment(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = AttachmentRepository::new(pool.clone());
+
+        assert!(repo.read(&999).await?.is_none());
+
+        Ok(())
+    }
+
+    /// Test: verifica che delete rimuova l'allegato
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_delete_attachment(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = AttachmentRepository::new(pool.clone());
+
+        let created = repo.create(&sample_attachment(2, 2)).await?;
+        repo.delete(&created.attachment_id).await?;
+
+        assert!(repo.read(&created.attachment_id).await?.is_none());
+
+        Ok(())
+    }
+
+    /// Test: verifica che ref_count segua i messaggi che usano l'allegato e che solo gli
+    /// allegati senza riferimenti vengano eliminati
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_ref_count_follows_messages(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = AttachmentRepository::new(pool.clone());
+        let messages = MessageRepository::new(pool.clone());
+
+        let attachment = repo.create(&sample_attachment(1, 1)).await?;
+        let message = messages
+            .create(&CreateMessageDTO {
+                chat_id: 1,
+                sender_id: 1,
+                content: "with attachment".to_string(),
+                message_type: MessageType::UserMessage,
+                created_at: Utc::now(),
+                reply_to_message_id: None,
+                attachment_id: Some(attachment.attachment_id),
+                client_msg_id: None,
+                content_format: ContentFormat::Plain,
+            })
+            .await?;
+
+        let read = repo.read(&attachment.attachment_id).await?.unwrap();
+        assert_eq!(read.ref_count, 1);
+        let later = Utc::now() + chrono::Duration::hours(1);
+        assert!(repo.find_orphaned(&later, 10).await?.is_empty());
+        assert!(!repo.delete_if_orphaned(&attachment.attachment_id).await?);
+
+        // il messaggio eliminato perde l'allegato
+        let deleted = messages.soft_delete(&message.message_id).await?;
+        assert_eq!(deleted.attachment_id, None);
+        let read = repo.read(&attachment.attachment_id).await?.unwrap();
+        assert_eq!(read.ref_count, 0);
+
+        let orphans = repo.find_orphaned(&later, 10).await?;
+        assert_eq!(orphans.len(), 1);
+        assert_eq!(orphans[0].attachment_id, attachment.attachment_id);
+        assert!(repo.delete_if_orphaned(&attachment.attachment_id).await?);
+        assert!(repo.read(&attachment.attachment_id).await?.is_none());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,199 @@
+//! AuditLogRepository - Repository per il registro delle azioni amministrative
+
+use super::query_log::timed;
+#[cfg(feature = "sqlite")]
+use super::sqlite;
+use super::{Create, DbPool, Page, ReadMany, UnitOfWork};
+use crate::dtos::CreateAuditEntryDTO;
+use crate::entities::{AuditAction, AuditEntry};
+use chrono::Utc;
+use sqlx::{Error, MySqlConnection};
+use tracing::{debug, instrument};
+
+//AUDIT LOG REPOSITORY
+pub struct AuditLogRepository {
+    connection_pool: DbPool,
+}
+
+impl AuditLogRepository {
+    pub fn new(connection_pool: impl Into<DbPool>) -> Self {
+        Self {
+            connection_pool: connection_pool.into(),
+        }
+    }
+
+    /// Registra una voce all'interno di una unit of work, insieme all'azione che descrive
+    #[instrument(skip(self, uow, data), fields(chat_id = %data.chat_id, actor_id = %data.actor_id, action = ?data.action))]
+    pub async fn create_in(
+        &self,
+        uow: &mut UnitOfWork,
+        data: &CreateAuditEntryDTO,
+    ) -> Result<AuditEntry, Error> {
+        debug!("Recording audit entry in unit of work");
+        #[cfg(feature = "sqlite")]
+        if let Some(conn) = uow.sqlite_conn() {
+            return sqlite::audit::insert(conn, data).await;
+        }
+        Self::insert(uow.conn(), data).await
+    }
+
+    async fn insert(
+        conn: &mut MySqlConnection,
+        data: &CreateAuditEntryDTO,
+    ) -> Result<AuditEntry, Error> {
+        let now = Utc::now();
+        let payload = data.payload.as_ref().map(|payload| payload.to_string());
+
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO audit_log (chat_id, actor_id, target_user_id, action, payload, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+            data.chat_id,
+            data.actor_id,
+            data.target_user_id,
+            &data.action,
+            payload,
+            now
+        )
+        .execute(timed(conn))
+        .await?;
+
+        Ok(AuditEntry {
+            audit_id: result.last_insert_id() as i32,
+            chat_id: data.chat_id,
+            actor_id: Some(data.actor_id),
+            target_user_id: data.target_user_id,
+            action: data.action,
+            payload,
+            created_at: now,
+        })
+    }
+}
+
+/// Filtro delle voci dell'audit log, restituite dalla più recente
+#[derive(Debug, Clone)]
+pub struct AuditFilter {
+    pub chat_id: i32,
+    pub action: Option<AuditAction>,
+}
+
+impl ReadMany<AuditEntry, AuditFilter> for AuditLogRepository {
+    async fn read_many(&self, filter: &AuditFilter, page: &Page) -> Result<Vec<AuditEntry>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::audit::read_many(pool, filter, page).await;
+        }
+        let entries = sqlx::query_as!(
+            AuditEntry,
+            r#"
+            SELECT
+                audit_id,
+                chat_id,
+                actor_id,
+                target_user_id,
+                action as "action: AuditAction",
+                payload,
+                created_at
+            FROM audit_log
+            WHERE chat_id = ?
+              AND (? IS NULL OR action = ?)
+            ORDER BY created_at DESC, audit_id DESC
+            LIMIT ? OFFSET ?
+            "#,
+            filter.chat_id,
+            filter.action.as_ref(),
+            filter.action.as_ref(),
+            page.limit,
+            page.offset
+        )
+        .fetch_all(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(entries)
+    }
+}
+
+impl Create<AuditEntry, CreateAuditEntryDTO> for AuditLogRepository {
+    #[instrument(skip(self, data), fields(chat_id = %data.chat_id, actor_id = %data.actor_id, action = ?data.action))]
+    async fn create(&self, data: &CreateAuditEntryDTO) -> Result<AuditEntry, Error> {
+        debug!("Recording audit entry");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::audit::insert(&mut *pool.acquire().await?, data).await;
+        }
+        Self::insert(&mut *self.connection_pool.mysql().acquire().await?, data).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use sqlx::MySqlPool;
+
+    /// Test: le voci vengono restituite dalla più recente e solo per la chat richiesta
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_create_and_list_newest_first(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = AuditLogRepository::new(pool);
+
+        let first = repo
+            .create(&CreateAuditEntryDTO {
+                chat_id: 1,
+                actor_id: 1,
+                target_user_id: Some(2),
+                action: AuditAction::MemberRemoved,
+                payload: None,
+            })
+            .await?;
+        let second = repo
+            .create(&CreateAuditEntryDTO {
+                chat_id: 1,
+                actor_id: 1,
+                target_user_id: None,
+                action: AuditAction::ChatUpdated,
+                payload: Some(json!({ "title": "New title" })),
+            })
+            .await?;
+        repo.create(&CreateAuditEntryDTO {
+            chat_id: 3,
+            actor_id: 1,
+            target_user_id: Some(3),
+            action: AuditAction::MemberRoleChanged,
+            payload: None,
+        })
+        .await?;
+
+        let filter = AuditFilter {
+            chat_id: 1,
+            action: None,
+        };
+        let entries = repo.read_many(&filter, &Page::first(10)).await?;
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].audit_id, second.audit_id);
+        assert_eq!(entries[0].action, AuditAction::ChatUpdated);
+        assert_eq!(
+            entries[0].payload.as_deref(),
+            Some(r#"{"title":"New title"}"#)
+        );
+        assert_eq!(entries[1].audit_id, first.audit_id);
+
+        let page = repo.read_many(&filter, &Page::new(1, 1)).await?;
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].audit_id, first.audit_id);
+
+        let removals = repo
+            .read_many(
+                &AuditFilter {
+                    chat_id: 1,
+                    action: Some(AuditAction::MemberRemoved),
+                },
+                &Page::ALL,
+            )
+            .await?;
+        assert_eq!(removals.len(), 1);
+        assert_eq!(removals[0].audit_id, first.audit_id);
+
+        Ok(())
+    }
+}
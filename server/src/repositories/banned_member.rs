@@ -0,0 +1,168 @@
+//! BannedMemberRepository - Repository per la gestione degli utenti bannati
+
+use super::query_log::timed;
+#[cfg(feature = "sqlite")]
+use super::sqlite;
+use super::user_chat_metadata::UserChatKey;
+use super::{DbPool, Read, UnitOfWork};
+use crate::entities::BannedMember;
+use chrono::Utc;
+use sqlx::{Error, MySqlConnection};
+use tracing::{debug, instrument};
+
+//BANNED MEMBER REPOSITORY
+pub struct BannedMemberRepository {
+    connection_pool: DbPool,
+}
+
+impl BannedMemberRepository {
+    pub fn new(connection_pool: impl Into<DbPool>) -> Self {
+        Self {
+            connection_pool: connection_pool.into(),
+        }
+    }
+
+    /// Banna l'utente dalla chat; bannare di nuovo aggiorna autore e data del ban
+    #[instrument(skip(self), fields(user_id = %user_id, chat_id = %chat_id, banned_by = %banned_by))]
+    pub async fn ban(
+        &self,
+        user_id: &i32,
+        chat_id: &i32,
+        banned_by: &i32,
+    ) -> Result<BannedMember, Error> {
+        debug!("Banning user from chat");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::banned_member::insert(
+                &mut *pool.acquire().await?,
+                user_id,
+                chat_id,
+                banned_by,
+            )
+            .await;
+        }
+        Self::insert(
+            &mut *self.connection_pool.mysql().acquire().await?,
+            user_id,
+            chat_id,
+            banned_by,
+        )
+        .await
+    }
+
+    /// Come `ban`, all'interno di una unit of work
+    #[instrument(skip(self, uow), fields(user_id = %user_id, chat_id = %chat_id, banned_by = %banned_by))]
+    pub async fn ban_in(
+        &self,
+        uow: &mut UnitOfWork,
+        user_id: &i32,
+        chat_id: &i32,
+        banned_by: &i32,
+    ) -> Result<BannedMember, Error> {
+        debug!("Banning user from chat in unit of work");
+        #[cfg(feature = "sqlite")]
+        if let Some(conn) = uow.sqlite_conn() {
+            return sqlite::banned_member::insert(conn, user_id, chat_id, banned_by).await;
+        }
+        Self::insert(uow.conn(), user_id, chat_id, banned_by).await
+    }
+
+    async fn insert(
+        conn: &mut MySqlConnection,
+        user_id: &i32,
+        chat_id: &i32,
+        banned_by: &i32,
+    ) -> Result<BannedMember, Error> {
+        let now = Utc::now();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO banned_members (user_id, chat_id, banned_by, banned_at)
+            VALUES (?, ?, ?, ?)
+            ON DUPLICATE KEY UPDATE banned_by = VALUES(banned_by), banned_at = VALUES(banned_at)
+            "#,
+            user_id,
+            chat_id,
+            banned_by,
+            now
+        )
+        .execute(timed(conn))
+        .await?;
+
+        Ok(BannedMember {
+            user_id: *user_id,
+            chat_id: *chat_id,
+            banned_by: *banned_by,
+            banned_at: now,
+        })
+    }
+
+    /// Verifica se l'utente è bannato dalla chat
+    pub async fn is_banned(&self, user_id: &i32, chat_id: &i32) -> Result<bool, Error> {
+        Ok(self.read(&(*user_id, *chat_id)).await?.is_some())
+    }
+}
+
+impl Read<BannedMember, UserChatKey> for BannedMemberRepository {
+    async fn read(&self, id: &UserChatKey) -> Result<Option<BannedMember>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::banned_member::read(pool, id).await;
+        }
+        let banned = sqlx::query_as!(
+            BannedMember,
+            r#"
+            SELECT 
+                user_id,
+                chat_id,
+                banned_by,
+                banned_at
+            FROM banned_members 
+            WHERE user_id = ? 
+            AND chat_id = ?
+            "#,
+            id.0,
+            id.1
+        )
+        .fetch_optional(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(banned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::MySqlPool;
+
+    /// Test: dopo il ban l'utente risulta bannato solo da quella chat
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_ban_and_is_banned(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = BannedMemberRepository::new(pool);
+
+        assert!(!repo.is_banned(&2, &1).await?);
+
+        let banned = repo.ban(&2, &1, &1).await?;
+        assert_eq!(banned.banned_by, 1);
+
+        assert!(repo.is_banned(&2, &1).await?);
+        assert!(!repo.is_banned(&2, &3).await?);
+
+        Ok(())
+    }
+
+    /// Test: bannare due volte non fallisce e aggiorna l'autore del ban
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_ban_twice_updates_author(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = BannedMemberRepository::new(pool);
+
+        repo.ban(&2, &1, &1).await?;
+        repo.ban(&2, &1, &3).await?;
+
+        let banned = repo.read(&(2, 1)).await?.expect("Ban should exist");
+        assert_eq!(banned.banned_by, 3);
+
+        Ok(())
+    }
+}
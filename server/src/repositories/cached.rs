@@ -0,0 +1,611 @@
+//! Cached repositories - Cache di utenti e appartenenze alle chat
+//!
+//! Ogni messaggio WebSocket e ogni richiesta sulle route di una chat leggono l'utente e la
+//! sua appartenenza alla chat: i due decoratori di questo modulo avvolgono i repository di
+//! `AppState` e servono queste letture da una `Cache` (in memoria, o su Redis quando più
+//! istanze del server condividono il database), con un TTL per voce.
+//! Le scritture passano sempre dal repository avvolto e invalidano le voci toccate; le
+//! cancellazioni a cascata (chat o ruolo eliminati) vanno invalidate da chi le esegue con
+//! `RepositoryCache::invalidate_chat`. Se la cache non risponde le letture ripiegano sul
+//! repository avvolto, e il TTL limita la durata di una voce rimasta indietro.
+
+use super::{
+    DeliveredBatch, MemberFilter, Page, UnitOfWork, UserChatMetadataRepo, UserFilter, UserRepo,
+    user_chat_metadata::UserChatKey,
+};
+use crate::core::SharedCache;
+use crate::dtos::{
+    CreateUserChatMetadataDTO, CreateUserDTO, UnreadCountDTO, UpdateProfileDTO,
+    UpdateUserChatMetadataDTO, UpdateUserDTO,
+};
+use crate::entities::{PresenceVisibility, User, UserChatMetadata, UserRole};
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use serde::{Serialize, de::DeserializeOwned};
+use sqlx::Error;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+use uuid::Uuid;
+
+/// Cache condivisa dai repository decorati, con le voci serializzate in JSON
+///
+/// Chiavi usate:
+/// - `user:{user_id}` e `username:{username}` (-> user_id) per gli utenti
+/// - `member:{chat_id}:{generation}:{user_id}` per le appartenenze, comprese le assenze
+/// - `member_gen:{chat_id}` per la generazione corrente delle appartenenze di una chat
+#[derive(Clone)]
+pub struct RepositoryCache {
+    store: SharedCache,
+    ttl: Duration,
+}
+
+impl RepositoryCache {
+    pub fn new(store: SharedCache, ttl: Duration) -> Self {
+        Self { store, ttl }
+    }
+
+    /// Dimentica tutte le appartenenze a una chat, dopo una modifica che le tocca in blocco
+    ///
+    /// Il backend non sa elencare le chiavi di una chat: cambiare generazione rende
+    /// irraggiungibili le voci precedenti, che scadono da sole entro il TTL.
+    pub async fn invalidate_chat(&self, chat_id: i32) {
+        let generation = Uuid::new_v4().to_string();
+        if let Err(e) = self
+            .store
+            .set(&Self::generation_key(chat_id), &generation, self.ttl)
+            .await
+        {
+            warn!(
+                "Failed to invalidate cached members of chat {}: {}",
+                chat_id, e
+            );
+        }
+    }
+
+    async fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        match self.store.get(key).await {
+            Ok(value) => value.and_then(|value| serde_json::from_str(&value).ok()),
+            Err(e) => {
+                warn!("Repository cache read failed: {}", e);
+                None
+            }
+        }
+    }
+
+    async fn put<T: Serialize>(&self, key: &str, value: &T) {
+        let Ok(value) = serde_json::to_string(value) else {
+            return;
+        };
+        if let Err(e) = self.store.set(key, &value, self.ttl).await {
+            warn!("Repository cache write failed: {}", e);
+        }
+    }
+
+    async fn forget(&self, key: &str) {
+        if let Err(e) = self.store.delete(key).await {
+            warn!("Repository cache invalidation failed: {}", e);
+        }
+    }
+
+    async fn put_user(&self, user: &User) {
+        self.put(&format!("user:{}", user.user_id), user).await;
+        self.put(&format!("username:{}", user.username), &user.user_id)
+            .await;
+    }
+
+    /// Chiave di un'appartenenza nella generazione corrente della chat,
+    /// None se la generazione non è leggibile (la cache va saltata)
+    async fn member_key(&self, (user_id, chat_id): &UserChatKey) -> Option<String> {
+        match self.store.get(&Self::generation_key(*chat_id)).await {
+            Ok(generation) => Some(format!(
+                "member:{}:{}:{}",
+                chat_id,
+                generation.unwrap_or_default(),
+                user_id
+            )),
+            Err(e) => {
+                warn!("Repository cache read failed: {}", e);
+                None
+            }
+        }
+    }
+
+    async fn forget_member(&self, key: &UserChatKey) {
+        if let Some(key) = self.member_key(key).await {
+            self.forget(&key).await;
+        }
+    }
+
+    fn generation_key(chat_id: i32) -> String {
+        format!("member_gen:{}", chat_id)
+    }
+}
+
+/// `UserRepo` che serve `read` e `find_by_username` dalla cache
+pub struct CachedUserRepo {
+    inner: Arc<dyn UserRepo>,
+    cache: RepositoryCache,
+}
+
+impl CachedUserRepo {
+    pub fn new(inner: Arc<dyn UserRepo>, cache: RepositoryCache) -> Self {
+        Self { inner, cache }
+    }
+
+    /// Restituisce il risultato di una scrittura dopo aver dimenticato l'utente modificato
+    async fn forget<T>(&self, user_id: i32, result: Result<T, Error>) -> Result<T, Error> {
+        self.cache.forget(&format!("user:{}", user_id)).await;
+        result
+    }
+}
+
+impl UserRepo for CachedUserRepo {
+    fn find_by_username<'a>(
+        &'a self,
+        username: &'a String,
+    ) -> BoxFuture<'a, Result<Option<User>, Error>> {
+        Box::pin(async move {
+            // l'associazione username -> id può essere vecchia: vale solo se l'utente in
+            // cache ha ancora lo stesso username
+            if let Some(user_id) = self
+                .cache
+                .get::<i32>(&format!("username:{}", username))
+                .await
+                && let Some(user) = self.cache.get::<User>(&format!("user:{}", user_id)).await
+                && &user.username == username
+            {
+                return Ok(Some(user));
+            }
+
+            let user = self.inner.find_by_username(username).await?;
+            if let Some(user) = &user {
+                self.cache.put_user(user).await;
+            }
+            Ok(user)
+        })
+    }
+
+    fn search_by_username_partial<'a>(
+        &'a self,
+        username_pattern: &'a String,
+    ) -> BoxFuture<'a, Result<Vec<User>, Error>> {
+        self.inner.search_by_username_partial(username_pattern)
+    }
+
+    fn update_last_seen<'a>(
+        &'a self,
+        user_id: &'a i32,
+        last_seen: &'a DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let result = self.inner.update_last_seen(user_id, last_seen).await;
+            self.forget(*user_id, result).await
+        })
+    }
+
+    fn set_deactivated_at<'a>(
+        &'a self,
+        user_id: &'a i32,
+        deactivated_at: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let result = self.inner.set_deactivated_at(user_id, deactivated_at).await;
+            self.forget(*user_id, result).await
+        })
+    }
+
+    fn find_deactivated_before<'a>(
+        &'a self,
+        cutoff: &'a DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Vec<User>, Error>> {
+        self.inner.find_deactivated_before(cutoff)
+    }
+
+    fn update_presence_visibility<'a>(
+        &'a self,
+        user_id: &'a i32,
+        visibility: &'a PresenceVisibility,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let result = self
+                .inner
+                .update_presence_visibility(user_id, visibility)
+                .await;
+            self.forget(*user_id, result).await
+        })
+    }
+
+    fn update_status<'a>(
+        &'a self,
+        user_id: &'a i32,
+        status_text: Option<&'a str>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let result = self
+                .inner
+                .update_status(user_id, status_text, expires_at)
+                .await;
+            self.forget(*user_id, result).await
+        })
+    }
+
+    fn update_profile<'a>(
+        &'a self,
+        user_id: &'a i32,
+        data: &'a UpdateProfileDTO,
+    ) -> BoxFuture<'a, Result<User, Error>> {
+        Box::pin(async move {
+            let result = self.inner.update_profile(user_id, data).await;
+            self.forget(*user_id, result).await
+        })
+    }
+
+    fn create<'a>(&'a self, data: &'a CreateUserDTO) -> BoxFuture<'a, Result<User, Error>> {
+        self.inner.create(data)
+    }
+
+    fn read<'a>(&'a self, id: &'a i32) -> BoxFuture<'a, Result<Option<User>, Error>> {
+        Box::pin(async move {
+            if let Some(user) = self.cache.get::<User>(&format!("user:{}", id)).await {
+                return Ok(Some(user));
+            }
+
+            let user = self.inner.read(id).await?;
+            if let Some(user) = &user {
+                self.cache.put_user(user).await;
+            }
+            Ok(user)
+        })
+    }
+
+    fn read_many<'a>(
+        &'a self,
+        filter: &'a UserFilter,
+        page: &'a Page,
+    ) -> BoxFuture<'a, Result<Vec<User>, Error>> {
+        self.inner.read_many(filter, page)
+    }
+
+    fn update<'a>(
+        &'a self,
+        id: &'a i32,
+        data: &'a UpdateUserDTO,
+    ) -> BoxFuture<'a, Result<User, Error>> {
+        Box::pin(async move {
+            let result = self.inner.update(id, data).await;
+            self.forget(*id, result).await
+        })
+    }
+
+    fn delete<'a>(&'a self, user_id: &'a i32) -> BoxFuture<'a, Result<(), Error>> {
+        // le appartenenze cancellate a cascata restano in cache fino al TTL, ma non sono
+        // raggiungibili: l'autenticazione rifiuta i token di un utente che non esiste più
+        Box::pin(async move {
+            let result = self.inner.delete(user_id).await;
+            self.forget(*user_id, result).await
+        })
+    }
+}
+
+/// `UserChatMetadataRepo` che serve `read` dalla cache
+pub struct CachedUserChatMetadataRepo {
+    inner: Arc<dyn UserChatMetadataRepo>,
+    cache: RepositoryCache,
+}
+
+impl CachedUserChatMetadataRepo {
+    pub fn new(inner: Arc<dyn UserChatMetadataRepo>, cache: RepositoryCache) -> Self {
+        Self { inner, cache }
+    }
+
+    /// Restituisce il risultato di una scrittura dopo aver dimenticato l'appartenenza modificata
+    async fn forget<T>(&self, key: UserChatKey, result: Result<T, Error>) -> Result<T, Error> {
+        self.cache.forget_member(&key).await;
+        result
+    }
+}
+
+impl UserChatMetadataRepo for CachedUserChatMetadataRepo {
+    fn transfer_ownership<'a>(
+        &'a self,
+        from_user_id: &'a i32,
+        to_user_id: &'a i32,
+        chat_id: &'a i32,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let result = self
+                .inner
+                .transfer_ownership(from_user_id, to_user_id, chat_id)
+                .await;
+            self.cache.forget_member(&(*from_user_id, *chat_id)).await;
+            self.forget((*to_user_id, *chat_id), result).await
+        })
+    }
+
+    fn count_unread_by_user_id<'a>(
+        &'a self,
+        user_id: &'a i32,
+    ) -> BoxFuture<'a, Result<Vec<UnreadCountDTO>, Error>> {
+        self.inner.count_unread_by_user_id(user_id)
+    }
+
+    fn create_many<'a>(
+        &'a self,
+        metadata_list: &'a [CreateUserChatMetadataDTO],
+    ) -> BoxFuture<'a, Result<Vec<UserChatMetadata>, Error>> {
+        Box::pin(async move {
+            let result = self.inner.create_many(metadata_list).await;
+            for metadata in metadata_list {
+                self.cache
+                    .forget_member(&(metadata.user_id, metadata.chat_id))
+                    .await;
+            }
+            result
+        })
+    }
+
+    // la cache della chat viene invalidata dal commit della unit of work
+    fn create_in<'a>(
+        &'a self,
+        uow: &'a mut UnitOfWork,
+        data: &'a CreateUserChatMetadataDTO,
+    ) -> BoxFuture<'a, Result<UserChatMetadata, Error>> {
+        self.inner.create_in(uow, data)
+    }
+
+    fn create_many_in<'a>(
+        &'a self,
+        uow: &'a mut UnitOfWork,
+        metadata_list: &'a [CreateUserChatMetadataDTO],
+    ) -> BoxFuture<'a, Result<Vec<UserChatMetadata>, Error>> {
+        self.inner.create_many_in(uow, metadata_list)
+    }
+
+    fn transfer_ownership_in<'a>(
+        &'a self,
+        uow: &'a mut UnitOfWork,
+        from_user_id: &'a i32,
+        to_user_id: &'a i32,
+        chat_id: &'a i32,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        self.inner
+            .transfer_ownership_in(uow, from_user_id, to_user_id, chat_id)
+    }
+
+    fn delete_in<'a>(
+        &'a self,
+        uow: &'a mut UnitOfWork,
+        id: &'a UserChatKey,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        self.inner.delete_in(uow, id)
+    }
+
+    fn count_by_chat_id<'a>(&'a self, chat_id: &'a i32) -> BoxFuture<'a, Result<i64, Error>> {
+        self.inner.count_by_chat_id(chat_id)
+    }
+
+    fn share_any_chat<'a>(
+        &'a self,
+        user_id: &'a i32,
+        other_user_id: &'a i32,
+    ) -> BoxFuture<'a, Result<bool, Error>> {
+        self.inner.share_any_chat(user_id, other_user_id)
+    }
+
+    fn set_pinned<'a>(
+        &'a self,
+        user_id: &'a i32,
+        chat_id: &'a i32,
+        pinned_at: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let result = self.inner.set_pinned(user_id, chat_id, pinned_at).await;
+            self.forget((*user_id, *chat_id), result).await
+        })
+    }
+
+    fn set_custom_role<'a>(
+        &'a self,
+        user_id: &'a i32,
+        chat_id: &'a i32,
+        role_id: Option<i32>,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let result = self.inner.set_custom_role(user_id, chat_id, role_id).await;
+            self.forget((*user_id, *chat_id), result).await
+        })
+    }
+
+    fn set_muted_until<'a>(
+        &'a self,
+        user_id: &'a i32,
+        chat_id: &'a i32,
+        muted_until: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let result = self
+                .inner
+                .set_muted_until(user_id, chat_id, muted_until)
+                .await;
+            self.forget((*user_id, *chat_id), result).await
+        })
+    }
+
+    fn advance_received_until<'a>(
+        &'a self,
+        user_id: &'a i32,
+        received: &'a [DeliveredBatch],
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let result = self.inner.advance_received_until(user_id, received).await;
+            for batch in received {
+                self.cache.forget_member(&(*user_id, batch.chat_id)).await;
+            }
+            result
+        })
+    }
+
+    fn update_user_role<'a>(
+        &'a self,
+        user_id: &'a i32,
+        chat_id: &'a i32,
+        new_role: &'a UserRole,
+        expected_version: &'a i32,
+    ) -> BoxFuture<'a, Result<UserChatMetadata, Error>> {
+        Box::pin(async move {
+            let result = self
+                .inner
+                .update_user_role(user_id, chat_id, new_role, expected_version)
+                .await;
+            self.forget((*user_id, *chat_id), result).await
+        })
+    }
+
+    fn create<'a>(
+        &'a self,
+        data: &'a CreateUserChatMetadataDTO,
+    ) -> BoxFuture<'a, Result<UserChatMetadata, Error>> {
+        Box::pin(async move {
+            let result = self.inner.create(data).await;
+            self.forget((data.user_id, data.chat_id), result).await
+        })
+    }
+
+    fn read<'a>(
+        &'a self,
+        id: &'a UserChatKey,
+    ) -> BoxFuture<'a, Result<Option<UserChatMetadata>, Error>> {
+        Box::pin(async move {
+            let Some(key) = self.cache.member_key(id).await else {
+                return self.inner.read(id).await;
+            };
+            // anche l'assenza di appartenenza viene conservata: i tentativi di accesso a una
+            // chat di cui non si è membri non arrivano al database
+            if let Some(metadata) = self.cache.get::<Option<UserChatMetadata>>(&key).await {
+                return Ok(metadata);
+            }
+
+            let metadata = self.inner.read(id).await?;
+            self.cache.put(&key, &metadata).await;
+            Ok(metadata)
+        })
+    }
+
+    // le pagine dei membri non passano dalla cache: ne esistono troppe combinazioni
+    fn read_many<'a>(
+        &'a self,
+        filter: &'a MemberFilter,
+        page: &'a Page,
+    ) -> BoxFuture<'a, Result<Vec<UserChatMetadata>, Error>> {
+        self.inner.read_many(filter, page)
+    }
+
+    fn update<'a>(
+        &'a self,
+        id: &'a UserChatKey,
+        data: &'a UpdateUserChatMetadataDTO,
+    ) -> BoxFuture<'a, Result<UserChatMetadata, Error>> {
+        Box::pin(async move {
+            let result = self.inner.update(id, data).await;
+            self.forget(*id, result).await
+        })
+    }
+
+    fn delete<'a>(&'a self, id: &'a UserChatKey) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let result = self.inner.delete(id).await;
+            self.forget(*id, result).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::cache::InMemoryCache;
+    use crate::repositories::{UserChatMetadataRepository, UserRepository};
+    use sqlx::MySqlPool;
+
+    fn cache() -> RepositoryCache {
+        RepositoryCache::new(Arc::new(InMemoryCache::new(1000)), Duration::from_secs(60))
+    }
+
+    /// Test: le appartenenze sono servite dalla cache finché una scrittura non le invalida
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_member_read_cached_until_write(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = CachedUserChatMetadataRepo::new(
+            Arc::new(UserChatMetadataRepository::new(pool.clone())),
+            cache(),
+        );
+
+        let member = repo.read(&(2, 1)).await?.unwrap();
+        assert_eq!(member.user_role, Some(UserRole::Member));
+
+        // una modifica che non passa dal repository non è vista fino all'invalidazione
+        sqlx::query!(
+            "UPDATE userchatmetadata SET user_role = 'ADMIN' WHERE user_id = ? AND chat_id = ?",
+            2,
+            1
+        )
+        .execute(&pool)
+        .await?;
+        let member = repo.read(&(2, 1)).await?.unwrap();
+        assert_eq!(member.user_role, Some(UserRole::Member));
+
+        repo.set_muted_until(&2, &1, None).await?;
+        let member = repo.read(&(2, 1)).await?.unwrap();
+        assert_eq!(member.user_role, Some(UserRole::Admin));
+
+        Ok(())
+    }
+
+    /// Test: invalidate_chat dimentica tutti i membri della chat, anche le assenze in cache
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_invalidate_chat_forgets_members(pool: MySqlPool) -> sqlx::Result<()> {
+        let cache = cache();
+        let repo = CachedUserChatMetadataRepo::new(
+            Arc::new(UserChatMetadataRepository::new(pool.clone())),
+            cache.clone(),
+        );
+
+        assert!(repo.read(&(1, 1)).await?.is_some());
+        assert!(repo.read(&(2, 1)).await?.is_some());
+
+        sqlx::query!("DELETE FROM userchatmetadata WHERE chat_id = ?", 1)
+            .execute(&pool)
+            .await?;
+        assert!(repo.read(&(1, 1)).await?.is_some());
+
+        cache.invalidate_chat(1).await;
+        assert!(repo.read(&(1, 1)).await?.is_none());
+        assert!(repo.read(&(2, 1)).await?.is_none());
+
+        Ok(())
+    }
+
+    /// Test: gli utenti letti per id o username sono invalidati dalle scritture sul profilo
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users")))]
+    async fn test_user_read_cached_until_write(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = CachedUserRepo::new(Arc::new(UserRepository::new(pool.clone())), cache());
+
+        let user = repo.find_by_username(&"alice".to_string()).await?.unwrap();
+        assert!(user.status_text.is_none());
+
+        sqlx::query!("UPDATE users SET status_text = 'away' WHERE user_id = ?", 1)
+            .execute(&pool)
+            .await?;
+        assert!(repo.read(&1).await?.unwrap().status_text.is_none());
+
+        repo.update_last_seen(&1, &Utc::now()).await?;
+        assert_eq!(
+            repo.read(&1).await?.unwrap().status_text.as_deref(),
+            Some("away")
+        );
+        let user = repo.find_by_username(&"alice".to_string()).await?.unwrap();
+        assert_eq!(user.status_text.as_deref(), Some("away"));
+
+        Ok(())
+    }
+}
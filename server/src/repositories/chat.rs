@@ -1,19 +1,41 @@
 //! ChatRepository - Repository per la gestione delle chat
 
-use super::{Create, Delete, Read, Update};
-use crate::dtos::{CreateChatDTO, UpdateChatDTO};
-use crate::entities::{Chat, ChatType};
-use sqlx::{Error, MySqlPool};
+use super::query_log::timed;
+#[cfg(feature = "sqlite")]
+use super::sqlite;
+use super::{ChatRoleRepository, Create, DbPool, Delete, Page, Read, ReadMany, UnitOfWork, Update};
+use crate::dtos::{
+    ChatDTO, ChatOverviewDTO, CreateChatDTO, MessageDTO, PublicChatDTO, UpdateChatDTO,
+};
+use crate::entities::{
+    Chat, ChatType, ContentFilterPolicy, ContentFormat, InvitePolicy, Message, MessageType,
+};
+use chrono::{DateTime, Utc};
+use sqlx::{Error, MySqlConnection};
 use tracing::{debug, info, instrument};
 
 // CHAT REPOSITORY
 pub struct ChatRepository {
-    connection_pool: MySqlPool,
+    connection_pool: DbPool,
+    // letture pesanti che tollerano un piccolo ritardo (storico, liste, ricerche)
+    read_pool: DbPool,
 }
 
 impl ChatRepository {
-    pub fn new(connection_pool: MySqlPool) -> Self {
-        Self { connection_pool }
+    pub fn new(connection_pool: impl Into<DbPool>) -> Self {
+        let connection_pool = connection_pool.into();
+        Self {
+            read_pool: connection_pool.clone(),
+            connection_pool,
+        }
+    }
+
+    /// Route the read-heavy queries of this repository to a read-only replica
+    ///
+    /// Writes, and reads that must see them immediately, stay on the primary pool.
+    pub fn with_read_pool(mut self, read_pool: impl Into<DbPool>) -> Self {
+        self.read_pool = read_pool.into();
+        self
     }
 
     /// Get private chat between two users (if exists)
@@ -25,6 +47,10 @@ impl ChatRepository {
         user2_id: &i32,
     ) -> Result<Option<Chat>, Error> {
         debug!("Finding private chat between two users");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::chat::get_private_chat_between_users(pool, user1_id, user2_id).await;
+        }
         let chat = sqlx::query_as!(
             Chat,
             r#"
@@ -32,18 +58,24 @@ impl ChatRepository {
                 c.chat_id,
                 c.title,
                 c.description,
-                c.chat_type as "chat_type: ChatType"
+                c.chat_type as "chat_type: ChatType",
+                c.avatar_attachment_id,
+                c.announcement_only as "announcement_only: bool",
+                c.is_public as "is_public: bool",
+                c.requires_approval as "requires_approval: bool",
+                c.invite_policy as "invite_policy: InvitePolicy",
+                c.content_filter as "content_filter: ContentFilterPolicy"
             FROM chats c
             INNER JOIN userchatmetadata ucm ON c.chat_id = ucm.chat_id
             WHERE c.chat_type = 'PRIVATE' 
             AND ucm.user_id IN (?, ?)
-            GROUP BY c.chat_id, c.title, c.description, c.chat_type
+            GROUP BY c.chat_id, c.title, c.description, c.chat_type, c.avatar_attachment_id, c.announcement_only, c.is_public, c.requires_approval, c.invite_policy, c.content_filter
             HAVING COUNT(DISTINCT ucm.user_id) = 2
             "#,
             user1_id,
             user2_id
         )
-        .fetch_optional(&self.connection_pool)
+        .fetch_optional(timed(self.connection_pool.mysql()))
         .await?;
 
         if chat.is_some() {
@@ -54,12 +86,269 @@ impl ChatRepository {
 
         Ok(chat)
     }
-}
 
-impl Create<Chat, CreateChatDTO> for ChatRepository {
-    #[instrument(skip(self, data), fields(chat_type = ?data.chat_type))]
-    async fn create(&self, data: &CreateChatDTO) -> Result<Chat, Error> {
-        debug!("Creating new chat");
+    /// Search the chats of a user whose title contains the given text
+    #[instrument(skip(self), fields(user_id = %user_id))]
+    pub async fn search_by_title_for_user(
+        &self,
+        user_id: &i32,
+        title_pattern: &str,
+        limit: i64,
+    ) -> Result<Vec<Chat>, Error> {
+        debug!("Searching user chats by title");
+        let pattern = format!("%{}%", title_pattern);
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.read_pool {
+            let chats =
+                sqlite::chat::search_by_title_for_user(pool, user_id, &pattern, limit).await?;
+            info!("Found {} chats matching title", chats.len());
+            return Ok(chats);
+        }
+        let chats = sqlx::query_as!(
+            Chat,
+            r#"
+            SELECT 
+                c.chat_id,
+                c.title,
+                c.description,
+                c.chat_type as "chat_type: ChatType",
+                c.avatar_attachment_id,
+                c.announcement_only as "announcement_only: bool",
+                c.is_public as "is_public: bool",
+                c.requires_approval as "requires_approval: bool",
+                c.invite_policy as "invite_policy: InvitePolicy",
+                c.content_filter as "content_filter: ContentFilterPolicy"
+            FROM chats c
+            INNER JOIN userchatmetadata ucm ON c.chat_id = ucm.chat_id
+            WHERE ucm.user_id = ?
+            AND c.title LIKE ?
+            ORDER BY c.title
+            LIMIT ?
+            "#,
+            user_id,
+            pattern,
+            limit
+        )
+        .fetch_all(timed(self.read_pool.mysql()))
+        .await?;
+
+        info!("Found {} chats matching title", chats.len());
+        Ok(chats)
+    }
+
+    /// Search the public group chats (directory), most populated first.
+    /// Without a query every public chat is listed; pagination is offset based.
+    #[instrument(skip(self))]
+    pub async fn search_public(
+        &self,
+        query: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<PublicChatDTO>, Error> {
+        debug!("Searching public chats");
+        let pattern = query.map(|q| format!("%{}%", q));
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.read_pool {
+            let chats =
+                sqlite::chat::search_public(pool, pattern.as_deref(), limit, offset).await?;
+            info!("Found {} public chats", chats.len());
+            return Ok(chats);
+        }
+        let chats = sqlx::query_as!(
+            PublicChatDTO,
+            r#"
+            SELECT
+                c.chat_id,
+                c.title,
+                c.description,
+                COUNT(ucm.user_id) as "member_count!: i64",
+                c.requires_approval as "requires_approval: bool"
+            FROM chats c
+            LEFT JOIN userchatmetadata ucm ON c.chat_id = ucm.chat_id
+            WHERE c.is_public = TRUE
+            AND c.chat_type = 'GROUP'
+            AND (? IS NULL OR c.title LIKE ? OR c.description LIKE ?)
+            GROUP BY c.chat_id, c.title, c.description, c.requires_approval
+            ORDER BY COUNT(ucm.user_id) DESC, c.chat_id
+            LIMIT ? OFFSET ?
+            "#,
+            pattern,
+            pattern,
+            pattern,
+            limit,
+            offset
+        )
+        .fetch_all(timed(self.read_pool.mysql()))
+        .await?;
+
+        info!("Found {} public chats", chats.len());
+        Ok(chats)
+    }
+
+    /// Overview of every chat of a user in a single query: the chat, the user's pin, the
+    /// stored unread counter, the member count and the last message visible to the user
+    /// (deleted messages are skipped). Pinned chats come first, then the most recently active.
+    #[instrument(skip(self))]
+    pub async fn find_overview_by_user_id(
+        &self,
+        user_id: &i32,
+    ) -> Result<Vec<ChatOverviewDTO>, Error> {
+        debug!("Loading chat overview");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.read_pool {
+            let rows = sqlite::chat::find_overview_by_user_id(pool, user_id).await?;
+            info!("Loaded overview of {} chats", rows.len());
+            return Ok(rows.into_iter().map(ChatOverviewDTO::from).collect());
+        }
+        let rows = sqlx::query_as!(
+            ChatOverviewRow,
+            r#"
+            SELECT
+                c.chat_id,
+                c.title,
+                c.description,
+                c.chat_type as "chat_type: ChatType",
+                c.avatar_attachment_id,
+                c.announcement_only as "announcement_only: bool",
+                c.is_public as "is_public: bool",
+                c.requires_approval as "requires_approval: bool",
+                c.invite_policy as "invite_policy: InvitePolicy",
+                c.content_filter as "content_filter: ContentFilterPolicy",
+                ucm.pinned_at,
+                CAST(ucm.unread_count AS SIGNED) as "unread_count!: i64",
+                (
+                    SELECT COUNT(*) FROM userchatmetadata members
+                    WHERE members.chat_id = c.chat_id
+                ) as "member_count!: i64",
+                m.message_id as "last_message_id?",
+                m.sender_id as "last_sender_id?",
+                m.content as "last_content?",
+                m.created_at as "last_created_at?",
+                m.message_type as "last_message_type?: MessageType",
+                m.content_format as "last_content_format?: ContentFormat",
+                m.reply_to_message_id as "last_reply_to_message_id?",
+                m.attachment_id as "last_attachment_id?"
+            FROM userchatmetadata ucm
+            INNER JOIN chats c ON c.chat_id = ucm.chat_id
+            LEFT JOIN messages m ON m.message_id = (
+                SELECT last.message_id FROM messages last
+                WHERE last.chat_id = ucm.chat_id
+                AND last.created_at >= ucm.messages_visible_from
+                AND last.deleted_at IS NULL
+                ORDER BY last.message_id DESC
+                LIMIT 1
+            )
+            WHERE ucm.user_id = ?
+            ORDER BY
+                ucm.pinned_at IS NULL,
+                ucm.pinned_at DESC,
+                COALESCE(m.created_at, ucm.member_since) DESC,
+                c.chat_id
+            "#,
+            user_id
+        )
+        .fetch_all(timed(self.read_pool.mysql()))
+        .await?;
+
+        info!("Loaded overview of {} chats", rows.len());
+        Ok(rows.into_iter().map(ChatOverviewDTO::from).collect())
+    }
+
+    /// Imposta l'allegato usato come avatar della chat
+    ///
+    /// Il riferimento passa dal vecchio avatar al nuovo nella stessa transazione: il vecchio,
+    /// se non usato altrove, verrà eliminato dal job di pulizia degli allegati.
+    #[instrument(skip(self), fields(chat_id = %chat_id, attachment_id = %attachment_id))]
+    pub async fn set_avatar(&self, chat_id: &i32, attachment_id: &i32) -> Result<Chat, Error> {
+        debug!("Updating chat avatar");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            sqlite::chat::set_avatar(pool, chat_id, attachment_id).await?;
+            return self
+                .read(chat_id)
+                .await?
+                .ok_or_else(|| sqlx::Error::RowNotFound);
+        }
+        let mut tx = self.connection_pool.mysql().begin().await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE attachments a
+            JOIN chats c ON c.avatar_attachment_id = a.attachment_id
+            SET a.ref_count = a.ref_count - 1
+            WHERE c.chat_id = ?
+            "#,
+            chat_id
+        )
+        .execute(timed(&mut *tx))
+        .await?;
+
+        sqlx::query!(
+            "UPDATE chats SET avatar_attachment_id = ? WHERE chat_id = ?",
+            attachment_id,
+            chat_id
+        )
+        .execute(timed(&mut *tx))
+        .await?;
+
+        sqlx::query!(
+            "UPDATE attachments SET ref_count = ref_count + 1 WHERE attachment_id = ?",
+            attachment_id
+        )
+        .execute(timed(&mut *tx))
+        .await?;
+
+        tx.commit().await?;
+
+        self.read(chat_id).await?.ok_or_else(|| sqlx::Error::RowNotFound)
+    }
+
+    /// Create a chat as part of a unit of work
+    #[instrument(skip(self, uow, data), fields(chat_type = ?data.chat_type))]
+    pub async fn create_in(
+        &self,
+        uow: &mut UnitOfWork,
+        data: &CreateChatDTO,
+    ) -> Result<Chat, Error> {
+        debug!("Creating new chat in unit of work");
+        #[cfg(feature = "sqlite")]
+        if let Some(conn) = uow.sqlite_conn() {
+            return sqlite::chat::insert(conn, data).await;
+        }
+        Self::insert(uow.conn(), data).await
+    }
+
+    /// Lock the chat row until the unit of work ends and count the members of the chat
+    ///
+    /// Flows adding a member take this lock before inserting, so concurrent joins are
+    /// serialized and cannot all pass the group size check. The count is a locking read:
+    /// it sees the members committed by whoever held the lock before.
+    #[instrument(skip(self, uow), fields(chat_id = %chat_id))]
+    pub async fn lock_members_in(&self, uow: &mut UnitOfWork, chat_id: &i32) -> Result<i64, Error> {
+        debug!("Locking chat for a membership change");
+        #[cfg(feature = "sqlite")]
+        if let Some(conn) = uow.sqlite_conn() {
+            return sqlite::chat::lock_members(conn, chat_id).await;
+        }
+        sqlx::query_scalar!(
+            "SELECT chat_id FROM chats WHERE chat_id = ? FOR UPDATE",
+            chat_id
+        )
+        .fetch_optional(timed(uow.conn()))
+        .await?
+        .ok_or(Error::RowNotFound)?;
+
+        let count = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM userchatmetadata WHERE chat_id = ? FOR SHARE",
+            chat_id
+        )
+        .fetch_one(timed(uow.conn()))
+        .await?;
+
+        Ok(count)
+    }
+
+    async fn insert(conn: &mut MySqlConnection, data: &CreateChatDTO) -> Result<Chat, Error> {
         // Insert chat using MySQL syntax
         let result = sqlx::query!(
             r#"
@@ -70,12 +359,15 @@ impl Create<Chat, CreateChatDTO> for ChatRepository {
             data.description,
             data.chat_type
         )
-        .execute(&self.connection_pool)
+        .execute(timed(&mut *conn))
         .await?;
 
         // Get the last inserted ID
         let new_id = result.last_insert_id() as i32;
 
+        // Ogni chat nasce con le righe dei ruoli base a cui puntano i membri
+        ChatRoleRepository::insert_base_roles(conn, new_id).await?;
+
         info!("Chat created with id {}", new_id);
 
         // Return the created chat with the new ID
@@ -84,14 +376,222 @@ impl Create<Chat, CreateChatDTO> for ChatRepository {
             title: data.title.clone(),
             description: data.description.clone(),
             chat_type: data.chat_type.clone(),
+            avatar_attachment_id: None,
+            announcement_only: false,
+            is_public: false,
+            requires_approval: false,
+            invite_policy: InvitePolicy::AdminsOnly,
+            content_filter: ContentFilterPolicy::Off,
         })
     }
 }
 
+/// Riga di `read_many`: la chat con il pin del membro, NULL se non filtrata per membro
+#[derive(sqlx::FromRow)]
+pub(crate) struct UserChatRow {
+    chat_id: i32,
+    title: Option<String>,
+    description: Option<String>,
+    chat_type: ChatType,
+    avatar_attachment_id: Option<i32>,
+    announcement_only: bool,
+    is_public: bool,
+    requires_approval: bool,
+    invite_policy: InvitePolicy,
+    content_filter: ContentFilterPolicy,
+    pinned_at: Option<DateTime<Utc>>,
+}
+
+impl From<UserChatRow> for ChatDTO {
+    fn from(row: UserChatRow) -> Self {
+        let mut chat = ChatDTO::from(Chat {
+            chat_id: row.chat_id,
+            title: row.title,
+            description: row.description,
+            chat_type: row.chat_type,
+            avatar_attachment_id: row.avatar_attachment_id,
+            announcement_only: row.announcement_only,
+            is_public: row.is_public,
+            requires_approval: row.requires_approval,
+            invite_policy: row.invite_policy,
+            content_filter: row.content_filter,
+        });
+        chat.pinned_at = row.pinned_at;
+        chat
+    }
+}
+
+/// Riga di `find_overview_by_user_id`: i campi `last_*` sono NULL se la chat non ha messaggi
+/// visibili all'utente
+#[derive(sqlx::FromRow)]
+pub(crate) struct ChatOverviewRow {
+    chat_id: i32,
+    title: Option<String>,
+    description: Option<String>,
+    chat_type: ChatType,
+    avatar_attachment_id: Option<i32>,
+    announcement_only: bool,
+    is_public: bool,
+    requires_approval: bool,
+    invite_policy: InvitePolicy,
+    content_filter: ContentFilterPolicy,
+    pinned_at: Option<DateTime<Utc>>,
+    unread_count: i64,
+    member_count: i64,
+    last_message_id: Option<i32>,
+    last_sender_id: Option<i32>,
+    last_content: Option<String>,
+    last_created_at: Option<DateTime<Utc>>,
+    last_message_type: Option<MessageType>,
+    last_content_format: Option<ContentFormat>,
+    last_reply_to_message_id: Option<i32>,
+    last_attachment_id: Option<i32>,
+}
+
+impl From<ChatOverviewRow> for ChatOverviewDTO {
+    fn from(row: ChatOverviewRow) -> Self {
+        let last_message = match (
+            row.last_message_id,
+            row.last_sender_id,
+            row.last_content,
+            row.last_created_at,
+            row.last_message_type,
+            row.last_content_format,
+        ) {
+            (
+                Some(message_id),
+                Some(sender_id),
+                Some(content),
+                Some(created_at),
+                Some(message_type),
+                Some(content_format),
+            ) => Some(MessageDTO::from(Message {
+                message_id,
+                chat_id: row.chat_id,
+                sender_id,
+                content,
+                created_at,
+                message_type,
+                content_format,
+                reply_to_message_id: row.last_reply_to_message_id,
+                attachment_id: row.last_attachment_id,
+                deleted_at: None,
+            })),
+            _ => None,
+        };
+
+        let mut chat = ChatDTO::from(Chat {
+            chat_id: row.chat_id,
+            title: row.title,
+            description: row.description,
+            chat_type: row.chat_type,
+            avatar_attachment_id: row.avatar_attachment_id,
+            announcement_only: row.announcement_only,
+            is_public: row.is_public,
+            requires_approval: row.requires_approval,
+            invite_policy: row.invite_policy,
+            content_filter: row.content_filter,
+        });
+        chat.pinned_at = row.pinned_at;
+
+        Self {
+            chat,
+            last_message,
+            unread_count: row.unread_count,
+            member_count: row.member_count,
+        }
+    }
+}
+
+/// Filtro delle chat, con il pin del membro se filtrate per membro
+///
+/// Le chat fissate vengono prima (la più recente in cima), poi le altre per id.
+/// `user_list` resta da riempire al chiamante.
+#[derive(Debug, Clone, Default)]
+pub struct ChatFilter {
+    /// Se presente, solo le chat con questi id (quelli inesistenti sono ignorati)
+    pub ids: Option<Vec<i32>>,
+    /// Se presente, solo le chat di cui l'utente è membro
+    pub member_id: Option<i32>,
+}
+
+impl ReadMany<ChatDTO, ChatFilter> for ChatRepository {
+    #[instrument(skip(self))]
+    async fn read_many(&self, filter: &ChatFilter, page: &Page) -> Result<Vec<ChatDTO>, Error> {
+        debug!("Listing chats");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.read_pool {
+            let rows = sqlite::chat::read_many(pool, filter, page).await?;
+            info!("Found {} chats", rows.len());
+            return Ok(rows.into_iter().map(ChatDTO::from).collect());
+        }
+        // array JSON espanso da JSON_TABLE: un solo parametro qualunque sia il numero di chat
+        let ids = filter
+            .ids
+            .as_ref()
+            .map(|ids| serde_json::to_string(ids).unwrap_or_else(|_| "[]".to_string()));
+        let rows = sqlx::query_as!(
+            UserChatRow,
+            r#"
+            SELECT
+                c.chat_id,
+                c.title,
+                c.description,
+                c.chat_type as "chat_type: ChatType",
+                c.avatar_attachment_id,
+                c.announcement_only as "announcement_only: bool",
+                c.is_public as "is_public: bool",
+                c.requires_approval as "requires_approval: bool",
+                c.invite_policy as "invite_policy: InvitePolicy",
+                c.content_filter as "content_filter: ContentFilterPolicy",
+                ucm.pinned_at
+            FROM chats c
+            LEFT JOIN userchatmetadata ucm ON ucm.chat_id = c.chat_id AND ucm.user_id = ?
+            WHERE (? IS NULL OR ucm.user_id IS NOT NULL)
+              AND (? IS NULL OR c.chat_id IN (
+                  SELECT id FROM JSON_TABLE(?, '$[*]' COLUMNS (id INT PATH '$')) AS ids
+              ))
+            ORDER BY ucm.pinned_at IS NULL, ucm.pinned_at DESC, c.chat_id
+            LIMIT ? OFFSET ?
+            "#,
+            filter.member_id,
+            filter.member_id,
+            ids,
+            ids,
+            page.limit,
+            page.offset
+        )
+        .fetch_all(timed(self.read_pool.mysql()))
+        .await?;
+
+        info!("Found {} chats", rows.len());
+        Ok(rows.into_iter().map(ChatDTO::from).collect())
+    }
+}
+
+impl Create<Chat, CreateChatDTO> for ChatRepository {
+    #[instrument(skip(self, data), fields(chat_type = ?data.chat_type))]
+    async fn create(&self, data: &CreateChatDTO) -> Result<Chat, Error> {
+        debug!("Creating new chat");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::chat::create(pool, data).await;
+        }
+        let mut tx = self.connection_pool.mysql().begin().await?;
+        let chat = Self::insert(&mut tx, data).await?;
+        tx.commit().await?;
+        Ok(chat)
+    }
+}
+
 impl Read<Chat, i32> for ChatRepository {
     #[instrument(skip(self), fields(chat_id = %id))]
     async fn read(&self, id: &i32) -> Result<Option<Chat>, Error> {
         debug!("Reading chat by id");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::chat::read(pool, id).await;
+        }
         let chat = sqlx::query_as!(
             Chat,
             r#"
@@ -99,13 +599,19 @@ impl Read<Chat, i32> for ChatRepository {
                 chat_id,
                 title,
                 description,
-                chat_type as "chat_type: ChatType"
+                chat_type as "chat_type: ChatType",
+                avatar_attachment_id,
+                announcement_only as "announcement_only: bool",
+                is_public as "is_public: bool",
+                requires_approval as "requires_approval: bool",
+                invite_policy as "invite_policy: InvitePolicy",
+                content_filter as "content_filter: ContentFilterPolicy"
             FROM chats 
             WHERE chat_id = ?
             "#,
             id
         )
-        .fetch_optional(&self.connection_pool)
+        .fetch_optional(timed(self.connection_pool.mysql()))
         .await?;
 
         if chat.is_some() {
@@ -129,11 +635,25 @@ impl Update<Chat, UpdateChatDTO, i32> for ChatRepository {
             .ok_or_else(|| sqlx::Error::RowNotFound)?;
 
         // If no fields to update, return current chat
-        if data.title.is_none() && data.description.is_none() {
+        if data.title.is_none()
+            && data.description.is_none()
+            && data.announcement_only.is_none()
+            && data.is_public.is_none()
+            && data.requires_approval.is_none()
+            && data.invite_policy.is_none()
+            && data.content_filter.is_none()
+        {
             debug!("No fields to update, returning current chat");
             return Ok(current_chat);
         }
 
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            sqlite::chat::update(pool, id, data).await?;
+            info!("Chat updated successfully");
+            return self.read(id).await?.ok_or_else(|| sqlx::Error::RowNotFound);
+        }
+
         // Build dynamic UPDATE query using QueryBuilder (idiomatic SQLx way)
         let mut query_builder = sqlx::QueryBuilder::new("UPDATE chats SET ");
 
@@ -146,11 +666,34 @@ impl Update<Chat, UpdateChatDTO, i32> for ChatRepository {
             separated.push("description = ");
             separated.push_bind_unseparated(description);
         }
+        if let Some(announcement_only) = data.announcement_only {
+            separated.push("announcement_only = ");
+            separated.push_bind_unseparated(announcement_only);
+        }
+        if let Some(is_public) = data.is_public {
+            separated.push("is_public = ");
+            separated.push_bind_unseparated(is_public);
+        }
+        if let Some(requires_approval) = data.requires_approval {
+            separated.push("requires_approval = ");
+            separated.push_bind_unseparated(requires_approval);
+        }
+        if let Some(invite_policy) = data.invite_policy {
+            separated.push("invite_policy = ");
+            separated.push_bind_unseparated(invite_policy);
+        }
+        if let Some(content_filter) = data.content_filter {
+            separated.push("content_filter = ");
+            separated.push_bind_unseparated(content_filter);
+        }
 
         query_builder.push(" WHERE chat_id = ");
         query_builder.push_bind(id);
 
-        query_builder.build().execute(&self.connection_pool).await?;
+        query_builder
+            .build()
+            .execute(timed(self.connection_pool.mysql()))
+            .await?;
 
         info!("Chat updated successfully");
 
@@ -163,8 +706,14 @@ impl Delete<i32> for ChatRepository {
     #[instrument(skip(self), fields(chat_id = %id))]
     async fn delete(&self, id: &i32) -> Result<(), Error> {
         debug!("Deleting chat");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            sqlite::chat::delete(pool, id).await?;
+            info!("Chat deleted successfully");
+            return Ok(());
+        }
         sqlx::query!("DELETE FROM chats WHERE chat_id = ?", id)
-            .execute(&self.connection_pool)
+            .execute(timed(self.connection_pool.mysql()))
             .await?;
 
         info!("Chat deleted successfully");
@@ -175,7 +724,9 @@ impl Delete<i32> for ChatRepository {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::entities::ChatType;
+    use crate::dtos::CreateUserChatMetadataDTO;
+    use crate::entities::{ChatType, UserRole};
+    use crate::repositories::{MemberFilter, UserChatMetadataRepository};
     use sqlx::MySqlPool;
 
     /*------------------------------------------- */
@@ -503,6 +1054,306 @@ mod tests {
         Ok(())
     }
 
+    /*------------------------------------------- */
+    /* Unit tests: create_in (unit of work)       */
+    /*------------------------------------------- */
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users")))]
+    async fn test_create_in_visible_only_after_commit(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = ChatRepository::new(pool.clone());
+        let meta_repo = UserChatMetadataRepository::new(pool.clone());
+        let create_dto = CreateChatDTO {
+            title: Some("Atomic Chat".to_string()),
+            description: None,
+            chat_type: ChatType::Group,
+        };
+
+        let mut uow = UnitOfWork::begin(&pool.clone().into(), None).await?;
+        let chat = repo.create_in(&mut uow, &create_dto).await?;
+        let now = Utc::now();
+        meta_repo
+            .create_in(
+                &mut uow,
+                &CreateUserChatMetadataDTO {
+                    user_id: 1,
+                    chat_id: chat.chat_id,
+                    user_role: Some(UserRole::Owner),
+                    member_since: now,
+                    messages_visible_from: now,
+                    messages_received_until: now,
+                },
+            )
+            .await?;
+
+        // prima del commit le scritture non sono visibili alle altre connessioni
+        assert!(repo.read(&chat.chat_id).await?.is_none());
+
+        uow.commit().await?;
+
+        assert!(repo.read(&chat.chat_id).await?.is_some());
+        let members = meta_repo
+            .read_many(&MemberFilter::in_chat(chat.chat_id), &Page::ALL)
+            .await?;
+        assert_eq!(members.len(), 1);
+        assert_eq!(members[0].user_role, Some(UserRole::Owner));
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users")))]
+    async fn test_create_in_dropped_unit_of_work_rolls_back(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = ChatRepository::new(pool.clone());
+        let meta_repo = UserChatMetadataRepository::new(pool.clone());
+
+        let chat_id = {
+            let mut uow = UnitOfWork::begin(&pool.clone().into(), None).await?;
+            let chat = repo
+                .create_in(
+                    &mut uow,
+                    &CreateChatDTO {
+                        title: Some("Never Committed".to_string()),
+                        description: None,
+                        chat_type: ChatType::Group,
+                    },
+                )
+                .await?;
+            let now = Utc::now();
+            meta_repo
+                .create_in(
+                    &mut uow,
+                    &CreateUserChatMetadataDTO {
+                        user_id: 1,
+                        chat_id: chat.chat_id,
+                        user_role: Some(UserRole::Owner),
+                        member_since: now,
+                        messages_visible_from: now,
+                        messages_received_until: now,
+                    },
+                )
+                .await?;
+            chat.chat_id
+            // uow esce dallo scope senza commit
+        };
+
+        assert!(repo.read(&chat_id).await?.is_none());
+        assert!(
+            meta_repo
+                .read_many(&MemberFilter::in_chat(chat_id), &Page::ALL)
+                .await?
+                .is_empty()
+        );
+
+        Ok(())
+    }
+
+    /*------------------------------------------- */
+    /* Unit tests: lock_members_in                */
+    /*------------------------------------------- */
+
+    /// Test: un secondo ingresso attende il commit del primo e ne vede il nuovo membro
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_lock_members_in_serializes_joins(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = ChatRepository::new(pool.clone());
+        let meta_repo = UserChatMetadataRepository::new(pool.clone());
+
+        let mut first = UnitOfWork::begin(&pool.clone().into(), None).await?;
+        assert_eq!(repo.lock_members_in(&mut first, &3).await?, 2);
+        let now = Utc::now();
+        meta_repo
+            .create_in(
+                &mut first,
+                &CreateUserChatMetadataDTO {
+                    user_id: 2,
+                    chat_id: 3,
+                    user_role: Some(UserRole::Member),
+                    member_since: now,
+                    messages_visible_from: now,
+                    messages_received_until: now,
+                },
+            )
+            .await?;
+
+        let second = tokio::spawn({
+            let pool = pool.clone();
+            async move {
+                let repo = ChatRepository::new(pool.clone());
+                let mut uow = UnitOfWork::begin(&pool.clone().into(), None).await?;
+                let count = repo.lock_members_in(&mut uow, &3).await?;
+                uow.rollback().await?;
+                sqlx::Result::Ok(count)
+            }
+        });
+
+        // finché il primo ingresso non fa commit il secondo resta in attesa del lock
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        assert!(!second.is_finished());
+
+        first.commit().await?;
+        assert_eq!(second.await.unwrap()?, 3);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_lock_members_in_missing_chat(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = ChatRepository::new(pool.clone());
+
+        let mut uow = UnitOfWork::begin(&pool.clone().into(), None).await?;
+        let result = repo.lock_members_in(&mut uow, &999).await;
+        assert!(matches!(result, Err(Error::RowNotFound)));
+
+        Ok(())
+    }
+
+    /*------------------------------------------- */
+    /* Unit tests: search_public                  */
+    /*------------------------------------------- */
+
+    /// Test: solo le chat di gruppo pubbliche compaiono nella directory
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_search_public_only_public_groups(pool: MySqlPool) -> sqlx::Result<()> {
+        sqlx::query!("UPDATE chats SET is_public = TRUE WHERE chat_id IN (2, 3)")
+            .execute(&pool)
+            .await?;
+
+        let repo = ChatRepository::new(pool);
+
+        // la chat 1 non è pubblica, la 2 è privata
+        let chats = repo.search_public(None, 20, 0).await?;
+        assert_eq!(chats.len(), 1);
+        assert_eq!(chats[0].chat_id, 3);
+        assert_eq!(chats[0].member_count, 2);
+
+        // la query cerca anche nella descrizione
+        let chats = repo.search_public(Some("sviluppo"), 20, 0).await?;
+        assert_eq!(chats.len(), 1);
+
+        let chats = repo.search_public(Some("nothing like this"), 20, 0).await?;
+        assert!(chats.is_empty());
+
+        Ok(())
+    }
+
+    /*------------------------------------------- */
+    /* Unit tests: read_many                      */
+    /*------------------------------------------- */
+
+    fn member_of(user_id: i32) -> ChatFilter {
+        ChatFilter {
+            member_id: Some(user_id),
+            ..ChatFilter::default()
+        }
+    }
+
+    /// Test: le chat dell'utente arrivano con il pin, prima quelle fissate
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_read_many_member_pinned_first(pool: MySqlPool) -> sqlx::Result<()> {
+        sqlx::query!(
+            "UPDATE userchatmetadata SET pinned_at = NOW() WHERE user_id = 1 AND chat_id = 3"
+        )
+        .execute(&pool)
+        .await?;
+
+        let repo = ChatRepository::new(pool);
+
+        let chats = repo.read_many(&member_of(1), &Page::ALL).await?;
+        let ids: Vec<Option<i32>> = chats.iter().map(|c| c.chat_id).collect();
+        assert_eq!(ids, vec![Some(3), Some(1), Some(2)]);
+        assert!(chats[0].pinned_at.is_some());
+        assert!(chats[1].pinned_at.is_none());
+        // i membri non fanno parte della query
+        assert!(chats.iter().all(|c| c.user_list.is_none()));
+
+        // Bob non è membro del Dev Team
+        let chats = repo.read_many(&member_of(2), &Page::ALL).await?;
+        assert_eq!(chats.len(), 2);
+
+        Ok(())
+    }
+
+    /// Test: chat per id, anche ristrette a quelle di un membro
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_read_many_by_ids(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = ChatRepository::new(pool);
+
+        let filter = ChatFilter {
+            ids: Some(vec![3, 2, 999]),
+            ..ChatFilter::default()
+        };
+        let chats = repo.read_many(&filter, &Page::ALL).await?;
+        let ids: Vec<Option<i32>> = chats.iter().map(|c| c.chat_id).collect();
+        assert_eq!(ids, vec![Some(2), Some(3)]);
+        assert!(chats.iter().all(|c| c.pinned_at.is_none()));
+
+        // Bob non è membro del Dev Team
+        let filter = ChatFilter {
+            member_id: Some(2),
+            ..filter
+        };
+        let chats = repo.read_many(&filter, &Page::ALL).await?;
+        let ids: Vec<Option<i32>> = chats.iter().map(|c| c.chat_id).collect();
+        assert_eq!(ids, vec![Some(2)]);
+
+        Ok(())
+    }
+
+    /*------------------------------------------- */
+    /* Unit tests: find_overview_by_user_id       */
+    /*------------------------------------------- */
+
+    /// Test: una riga per chat con ultimo messaggio visibile, non letti e membri
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_find_overview_by_user_id(pool: MySqlPool) -> sqlx::Result<()> {
+        // Bob vede i messaggi delle fixture, ha fissato la chat privata e ha due non letti
+        // nel General Chat; l'ultimo messaggio della chat privata è stato eliminato
+        sqlx::query!(
+            "UPDATE userchatmetadata SET messages_visible_from = NOW() - INTERVAL 1 HOUR WHERE user_id = 2"
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query!(
+            "UPDATE userchatmetadata SET pinned_at = NOW() WHERE user_id = 2 AND chat_id = 2"
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query!(
+            "UPDATE userchatmetadata SET unread_count = 2 WHERE user_id = 2 AND chat_id = 1"
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query!("UPDATE messages SET deleted_at = NOW() WHERE message_id = 5")
+            .execute(&pool)
+            .await?;
+
+        let repo = ChatRepository::new(pool);
+
+        let overview = repo.find_overview_by_user_id(&2).await?;
+        assert_eq!(overview.len(), 2);
+
+        // la chat fissata viene prima
+        let private = &overview[0];
+        assert_eq!(private.chat.chat_id, Some(2));
+        assert!(private.chat.pinned_at.is_some());
+        assert_eq!(private.member_count, 2);
+        assert_eq!(private.unread_count, 0);
+        let last = private.last_message.as_ref().unwrap();
+        assert_eq!(last.message_id, Some(4));
+
+        let general = &overview[1];
+        assert_eq!(general.chat.chat_id, Some(1));
+        assert_eq!(general.member_count, 3);
+        assert_eq!(general.unread_count, 2);
+        let last = general.last_message.as_ref().unwrap();
+        assert_eq!(last.message_id, Some(3));
+        assert_eq!(last.content.as_deref(), Some("Good morning!"));
+
+        // per Charlie i messaggi del Dev Team precedono messages_visible_from
+        let overview = repo.find_overview_by_user_id(&3).await?;
+        let dev_team = overview.iter().find(|o| o.chat.chat_id == Some(3)).unwrap();
+        assert!(dev_team.last_message.is_none());
+
+        Ok(())
+    }
+
     /*------------------------------------------- */
     /* Unit tests: read                           */
     /*------------------------------------------- */
@@ -577,6 +1428,11 @@ mod tests {
         let update_dto = UpdateChatDTO {
             title: Some("Updated Title".to_string()),
             description: Some("Updated Description".to_string()),
+            announcement_only: None,
+            is_public: None,
+            requires_approval: None,
+            invite_policy: None,
+            content_filter: None,
         };
 
         // Testa l'aggiornamento
@@ -606,6 +1462,11 @@ mod tests {
         let update_dto = UpdateChatDTO {
             title: Some("New Title Only".to_string()),
             description: None, // Non aggiornare la description
+            announcement_only: None,
+            is_public: None,
+            requires_approval: None,
+            invite_policy: None,
+            content_filter: None,
         };
 
         let updated_chat = repo.update(&1, &update_dto).await?;
@@ -619,6 +1480,34 @@ mod tests {
         Ok(())
     }
 
+    #[sqlx::test]
+    async fn test_update_chat_announcement_only(pool: MySqlPool) -> sqlx::Result<()> {
+        // Setup
+        sqlx::query!("INSERT INTO chats (chat_id, title, description, chat_type) VALUES (1, 'Original Title', 'Original Description', 'GROUP')")
+            .execute(&pool)
+            .await?;
+
+        let repo = ChatRepository::new(pool);
+        assert!(!repo.read(&1).await?.unwrap().announcement_only);
+
+        let update_dto = UpdateChatDTO {
+            title: None,
+            description: None,
+            announcement_only: Some(true),
+            is_public: None,
+            requires_approval: None,
+            invite_policy: None,
+            content_filter: None,
+        };
+
+        let updated_chat = repo.update(&1, &update_dto).await?;
+
+        assert!(updated_chat.announcement_only);
+        assert_eq!(updated_chat.title, Some("Original Title".to_string()));
+
+        Ok(())
+    }
+
     #[sqlx::test]
     async fn test_update_chat_with_no_changes(pool: MySqlPool) -> sqlx::Result<()> {
         // Setup
@@ -632,6 +1521,11 @@ mod tests {
         let update_dto = UpdateChatDTO {
             title: None,
             description: None,
+            announcement_only: None,
+            is_public: None,
+            requires_approval: None,
+            invite_policy: None,
+            content_filter: None,
         };
 
         // Dovrebbe restituire la chat invariata
@@ -653,6 +1547,11 @@ mod tests {
         let update_dto = UpdateChatDTO {
             title: Some("New Title".to_string()),
             description: None,
+            announcement_only: None,
+            is_public: None,
+            requires_approval: None,
+            invite_policy: None,
+            content_filter: None,
         };
 
         // Testa l'aggiornamento di una chat inesistente
@@ -0,0 +1,352 @@
+//! ChatRoleRepository - Repository per la gestione dei ruoli delle chat, base e personalizzati
+
+use super::query_log::timed;
+#[cfg(feature = "sqlite")]
+use super::sqlite;
+use super::{DbPool, Delete, Page, Read, ReadMany};
+use crate::dtos::CreateChatRoleDTO;
+use crate::entities::{ChatPermission, ChatRole, UserRole};
+use chrono::Utc;
+use sqlx::{Error, MySqlConnection};
+use tracing::{debug, info, instrument};
+
+//CHAT ROLE REPOSITORY
+pub struct ChatRoleRepository {
+    connection_pool: DbPool,
+}
+
+impl ChatRoleRepository {
+    pub fn new(connection_pool: impl Into<DbPool>) -> Self {
+        Self {
+            connection_pool: connection_pool.into(),
+        }
+    }
+
+    /// Crea un ruolo nella chat indicata, un nome già usato nella stessa chat viola il vincolo di unicità
+    #[instrument(skip(self, data), fields(chat_id = %chat_id, name = %data.name))]
+    pub async fn create(&self, chat_id: &i32, data: &CreateChatRoleDTO) -> Result<ChatRole, Error> {
+        debug!("Creating new chat role");
+        let grants = |permission| data.permissions.contains(&permission);
+        let role = ChatRole {
+            role_id: 0,
+            chat_id: *chat_id,
+            name: data.name.clone(),
+            base_role: None,
+            can_invite: grants(ChatPermission::InviteMembers),
+            can_remove_members: grants(ChatPermission::RemoveMembers),
+            can_moderate: grants(ChatPermission::ModerateMembers),
+            can_delete_messages: grants(ChatPermission::DeleteMessages),
+            can_pin_messages: grants(ChatPermission::PinMessages),
+            can_edit_chat: grants(ChatPermission::EditChat),
+            can_post_announcements: grants(ChatPermission::PostAnnouncements),
+            created_at: Utc::now(),
+        };
+
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            let role_id = sqlite::chat_role::create(pool, &role).await?;
+            info!("Chat role created with id {}", role_id);
+            return Ok(ChatRole { role_id, ..role });
+        }
+
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO chat_roles (chat_id, name, can_invite, can_remove_members, can_moderate,
+                can_delete_messages, can_pin_messages, can_edit_chat, can_post_announcements, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+            role.chat_id,
+            role.name,
+            role.can_invite,
+            role.can_remove_members,
+            role.can_moderate,
+            role.can_delete_messages,
+            role.can_pin_messages,
+            role.can_edit_chat,
+            role.can_post_announcements,
+            role.created_at
+        )
+        .execute(timed(self.connection_pool.mysql()))
+        .await?;
+
+        let new_id = result.last_insert_id() as i32;
+
+        info!("Chat role created with id {}", new_id);
+
+        Ok(ChatRole {
+            role_id: new_id,
+            ..role
+        })
+    }
+
+    /// Verifica se nella chat esiste già un ruolo con questo nome
+    pub async fn name_exists(&self, chat_id: &i32, name: &str) -> Result<bool, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::chat_role::name_exists(pool, chat_id, name).await;
+        }
+        let count = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM chat_roles WHERE chat_id = ? AND name = ?",
+            chat_id,
+            name
+        )
+        .fetch_one(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(count > 0)
+    }
+
+    /// Riga del ruolo base della chat, a cui corrisponde il valore di `user_role`
+    pub async fn find_base(
+        &self,
+        chat_id: &i32,
+        base_role: &UserRole,
+    ) -> Result<Option<ChatRole>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::chat_role::find_base(pool, chat_id, base_role).await;
+        }
+        let role = sqlx::query_as!(
+            ChatRole,
+            r#"
+            SELECT
+                role_id,
+                chat_id,
+                name,
+                base_role as "base_role: UserRole",
+                can_invite as "can_invite: bool",
+                can_remove_members as "can_remove_members: bool",
+                can_moderate as "can_moderate: bool",
+                can_delete_messages as "can_delete_messages: bool",
+                can_pin_messages as "can_pin_messages: bool",
+                can_edit_chat as "can_edit_chat: bool",
+                can_post_announcements as "can_post_announcements: bool",
+                created_at
+            FROM chat_roles
+            WHERE chat_id = ? AND base_role = ?
+            "#,
+            chat_id,
+            base_role
+        )
+        .fetch_optional(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(role)
+    }
+
+    /// Crea le righe dei ruoli base di una nuova chat: Owner e Admin con tutti i permessi,
+    /// Member senza permessi aggiuntivi
+    pub(crate) async fn insert_base_roles(
+        conn: &mut MySqlConnection,
+        chat_id: i32,
+    ) -> Result<(), Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO chat_roles (chat_id, name, base_role, can_invite, can_remove_members, can_moderate,
+                can_delete_messages, can_pin_messages, can_edit_chat, can_post_announcements, created_at)
+            VALUES
+                (?, 'Owner', 'OWNER', 1, 1, 1, 1, 1, 1, 1, NOW()),
+                (?, 'Admin', 'ADMIN', 1, 1, 1, 1, 1, 1, 1, NOW()),
+                (?, 'Member', 'MEMBER', 0, 0, 0, 0, 0, 0, 0, NOW())
+            "#,
+            chat_id,
+            chat_id,
+            chat_id
+        )
+        .execute(timed(conn))
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Filtro dei ruoli personalizzati, restituiti in ordine di creazione
+#[derive(Debug, Clone)]
+pub struct ChatRoleFilter {
+    pub chat_id: i32,
+}
+
+impl ReadMany<ChatRole, ChatRoleFilter> for ChatRoleRepository {
+    async fn read_many(
+        &self,
+        filter: &ChatRoleFilter,
+        page: &Page,
+    ) -> Result<Vec<ChatRole>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::chat_role::read_many(pool, filter, page).await;
+        }
+        let roles = sqlx::query_as!(
+            ChatRole,
+            r#"
+            SELECT
+                role_id,
+                chat_id,
+                name,
+                base_role as "base_role: UserRole",
+                can_invite as "can_invite: bool",
+                can_remove_members as "can_remove_members: bool",
+                can_moderate as "can_moderate: bool",
+                can_delete_messages as "can_delete_messages: bool",
+                can_pin_messages as "can_pin_messages: bool",
+                can_edit_chat as "can_edit_chat: bool",
+                can_post_announcements as "can_post_announcements: bool",
+                created_at
+            FROM chat_roles
+            WHERE chat_id = ?
+            ORDER BY role_id
+            LIMIT ? OFFSET ?
+            "#,
+            filter.chat_id,
+            page.limit,
+            page.offset
+        )
+        .fetch_all(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(roles)
+    }
+}
+
+impl Read<ChatRole, i32> for ChatRoleRepository {
+    async fn read(&self, id: &i32) -> Result<Option<ChatRole>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::chat_role::read(pool, id).await;
+        }
+        let role = sqlx::query_as!(
+            ChatRole,
+            r#"
+            SELECT
+                role_id,
+                chat_id,
+                name,
+                base_role as "base_role: UserRole",
+                can_invite as "can_invite: bool",
+                can_remove_members as "can_remove_members: bool",
+                can_moderate as "can_moderate: bool",
+                can_delete_messages as "can_delete_messages: bool",
+                can_pin_messages as "can_pin_messages: bool",
+                can_edit_chat as "can_edit_chat: bool",
+                can_post_announcements as "can_post_announcements: bool",
+                created_at
+            FROM chat_roles
+            WHERE role_id = ?
+            "#,
+            id
+        )
+        .fetch_optional(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(role)
+    }
+}
+
+impl Delete<i32> for ChatRoleRepository {
+    async fn delete(&self, id: &i32) -> Result<(), Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::chat_role::delete(pool, id).await;
+        }
+        let mut tx = self.connection_pool.mysql().begin().await?;
+
+        // i membri con questo ruolo tornano alla riga base Member della chat
+        sqlx::query!(
+            r#"
+            UPDATE userchatmetadata m
+            JOIN chat_roles base ON base.chat_id = m.chat_id AND base.base_role = 'MEMBER'
+            SET m.role_id = base.role_id
+            WHERE m.role_id = ?
+            "#,
+            id
+        )
+        .execute(timed(&mut *tx))
+        .await?;
+
+        sqlx::query!("DELETE FROM chat_roles WHERE role_id = ?", id)
+            .execute(timed(&mut *tx))
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::UserChatMetadataRepository;
+    use sqlx::MySqlPool;
+
+    fn moderator() -> CreateChatRoleDTO {
+        CreateChatRoleDTO {
+            name: "Moderator".to_string(),
+            permissions: vec![ChatPermission::ModerateMembers, ChatPermission::PinMessages],
+        }
+    }
+
+    /// Test: il ruolo creato conserva solo i permessi richiesti
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_create_and_read(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = ChatRoleRepository::new(pool);
+
+        let created = repo.create(&1, &moderator()).await?;
+        let role = repo
+            .read(&created.role_id)
+            .await?
+            .expect("Role should exist");
+
+        assert_eq!(role.chat_id, 1);
+        assert_eq!(role.name, "Moderator");
+        assert_eq!(
+            role.permissions(),
+            vec![ChatPermission::ModerateMembers, ChatPermission::PinMessages]
+        );
+        assert!(repo.name_exists(&1, "Moderator").await?);
+        assert!(!repo.name_exists(&3, "Moderator").await?);
+
+        Ok(())
+    }
+
+    /// Test: ogni chat ha le righe dei ruoli base, Owner e Admin con tutti i permessi
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_find_base_roles(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = ChatRoleRepository::new(pool);
+
+        let owner = repo.find_base(&1, &UserRole::Owner).await?.unwrap();
+        let member = repo.find_base(&1, &UserRole::Member).await?.unwrap();
+
+        assert_eq!(owner.chat_id, 1);
+        assert_eq!(owner.base_role, Some(UserRole::Owner));
+        assert_eq!(owner.permissions(), ChatPermission::ALL.to_vec());
+        assert!(member.permissions().is_empty());
+        assert!(repo.name_exists(&1, "Admin").await?);
+
+        Ok(())
+    }
+
+    /// Test: eliminando un ruolo i membri che lo avevano tornano al ruolo base Member
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_delete_clears_member_role(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = ChatRoleRepository::new(pool.clone());
+        let meta = UserChatMetadataRepository::new(pool);
+
+        let role = repo.create(&1, &moderator()).await?;
+        meta.set_custom_role(&2, &1, Some(role.role_id)).await?;
+        assert_eq!(
+            meta.read(&(2, 1)).await?.unwrap().role_id,
+            Some(role.role_id)
+        );
+
+        repo.delete(&role.role_id).await?;
+
+        let member = repo.find_base(&1, &UserRole::Member).await?.unwrap();
+        assert!(repo.read(&role.role_id).await?.is_none());
+        assert_eq!(
+            meta.read(&(2, 1)).await?.unwrap().role_id,
+            Some(member.role_id)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+//! DataExportRepository - Repository per i job di export dei dati personali
+
+use super::query_log::timed;
+#[cfg(feature = "sqlite")]
+use super::sqlite;
+use super::{Create, DbPool, Read};
+use crate::dtos::CreateDataExportDTO;
+use crate::entities::{DataExport, DataExportStatus};
+use chrono::Utc;
+use sqlx::Error;
+use tracing::{debug, info, instrument};
+
+//DATA EXPORT REPOSITORY
+pub struct DataExportRepository {
+    connection_pool: DbPool,
+}
+
+impl DataExportRepository {
+    pub fn new(connection_pool: impl Into<DbPool>) -> Self {
+        Self {
+            connection_pool: connection_pool.into(),
+        }
+    }
+
+    /// Ultimo export richiesto dall'utente
+    pub async fn find_latest_by_user_id(&self, user_id: &i32) -> Result<Option<DataExport>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::data_export::find_latest_by_user_id(pool, user_id).await;
+        }
+        let export = sqlx::query_as!(
+            DataExport,
+            r#"
+            SELECT
+                export_id,
+                user_id,
+                state as "state: DataExportStatus",
+                created_at,
+                completed_at,
+                storage_key
+            FROM data_exports
+            WHERE user_id = ?
+            ORDER BY created_at DESC, export_id DESC
+            LIMIT 1
+            "#,
+            user_id
+        )
+        .fetch_optional(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(export)
+    }
+
+    /// Segna il job come completato con la chiave dell'archivio sullo storage
+    #[instrument(skip(self, storage_key), fields(export_id = %export_id))]
+    pub async fn complete(&self, export_id: &i32, storage_key: &str) -> Result<(), Error> {
+        debug!("Completing data export");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::data_export::complete(pool, export_id, storage_key).await;
+        }
+        sqlx::query!(
+            r#"
+            UPDATE data_exports
+            SET state = 'COMPLETED', storage_key = ?, completed_at = ?
+            WHERE export_id = ?
+            "#,
+            storage_key,
+            Utc::now(),
+            export_id
+        )
+        .execute(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(())
+    }
+
+    /// Segna il job come fallito
+    #[instrument(skip(self), fields(export_id = %export_id))]
+    pub async fn fail(&self, export_id: &i32) -> Result<(), Error> {
+        debug!("Marking data export as failed");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::data_export::fail(pool, export_id).await;
+        }
+        sqlx::query!(
+            "UPDATE data_exports SET state = 'FAILED', completed_at = ? WHERE export_id = ?",
+            Utc::now(),
+            export_id
+        )
+        .execute(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(())
+    }
+}
+
+impl Create<DataExport, CreateDataExportDTO> for DataExportRepository {
+    /// Crea un nuovo job PENDING per l'utente indicato
+    #[instrument(skip(self, data), fields(user_id = %data.user_id))]
+    async fn create(&self, data: &CreateDataExportDTO) -> Result<DataExport, Error> {
+        debug!("Creating new data export job");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::data_export::create(pool, data).await;
+        }
+        let now = Utc::now();
+
+        let result = sqlx::query!(
+            "INSERT INTO data_exports (user_id, state, created_at) VALUES (?, 'PENDING', ?)",
+            data.user_id,
+            now
+        )
+        .execute(timed(self.connection_pool.mysql()))
+        .await?;
+
+        let new_id = result.last_insert_id() as i32;
+
+        info!("Data export job created with id {}", new_id);
+
+        Ok(DataExport {
+            export_id: new_id,
+            user_id: data.user_id,
+            state: DataExportStatus::Pending,
+            created_at: now,
+            completed_at: None,
+            storage_key: None,
+        })
+    }
+}
+
+impl Read<DataExport, i32> for DataExportRepository {
+    async fn read(&self, id: &i32) -> Result<Option<DataExport>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::data_export::read(pool, id).await;
+        }
+        let export = sqlx::query_as!(
+            DataExport,
+            r#"
+            SELECT
+                export_id,
+                user_id,
+                state as "state: DataExportStatus",
+                created_at,
+                completed_at,
+                storage_key
+            FROM data_exports
+            WHERE export_id = ?
+            "#,
+            id
+        )
+        .fetch_optional(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(export)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::MySqlPool;
+
+    /// Test: il job nasce pending e l'ultimo job dell'utente riflette il completamento
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users")))]
+    async fn test_create_and_complete(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = DataExportRepository::new(pool);
+
+        assert!(repo.find_latest_by_user_id(&1).await?.is_none());
+
+        let created = repo.create(&CreateDataExportDTO { user_id: 1 }).await?;
+        assert_eq!(created.state, DataExportStatus::Pending);
+
+        repo.complete(&created.export_id, "exports/1/archive.json")
+            .await?;
+
+        let latest = repo.find_latest_by_user_id(&1).await?.unwrap();
+        assert_eq!(latest.export_id, created.export_id);
+        assert_eq!(latest.state, DataExportStatus::Completed);
+        assert_eq!(
+            latest.storage_key.as_deref(),
+            Some("exports/1/archive.json")
+        );
+        assert!(latest.completed_at.is_some());
+
+        // gli export degli altri utenti non sono visibili
+        assert!(repo.find_latest_by_user_id(&2).await?.is_none());
+
+        Ok(())
+    }
+}
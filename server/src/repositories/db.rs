@@ -0,0 +1,85 @@
+//! DbPool - Pool di connessioni del backend scelto all'avvio
+//!
+//! Il backend di default è MySQL. Con la feature `sqlite` il server può usare anche un file
+//! SQLite (DATABASE_URL che inizia con `sqlite:`): i repository guardano la variante del pool
+//! e passano le query SQLite al modulo `repositories::sqlite`.
+
+use sqlx::MySqlPool;
+#[cfg(feature = "sqlite")]
+use sqlx::SqlitePool;
+
+/// Pool di connessioni al database primario, MySQL o SQLite
+#[derive(Debug, Clone)]
+pub enum DbPool {
+    MySql(MySqlPool),
+    #[cfg(feature = "sqlite")]
+    Sqlite(SqlitePool),
+}
+
+impl DbPool {
+    /// Pool MySQL, usato dalle query verificate con `query!`
+    ///
+    /// I repository controllano prima la variante SQLite, quindi qui il pool è sempre MySQL.
+    pub(crate) fn mysql(&self) -> &MySqlPool {
+        match self {
+            DbPool::MySql(pool) => pool,
+            #[cfg(feature = "sqlite")]
+            DbPool::Sqlite(_) => unreachable!("MySQL query on a SQLite pool"),
+        }
+    }
+
+    /// Connessioni aperte, in uso o inattive
+    pub fn size(&self) -> u32 {
+        match self {
+            DbPool::MySql(pool) => pool.size(),
+            #[cfg(feature = "sqlite")]
+            DbPool::Sqlite(pool) => pool.size(),
+        }
+    }
+
+    /// Connessioni aperte in attesa di una query
+    pub fn num_idle(&self) -> usize {
+        match self {
+            DbPool::MySql(pool) => pool.num_idle(),
+            #[cfg(feature = "sqlite")]
+            DbPool::Sqlite(pool) => pool.num_idle(),
+        }
+    }
+
+    /// Limiti di connessioni del pool, come (minimo, massimo)
+    pub fn connection_limits(&self) -> (u32, u32) {
+        match self {
+            DbPool::MySql(pool) => (
+                pool.options().get_min_connections(),
+                pool.options().get_max_connections(),
+            ),
+            #[cfg(feature = "sqlite")]
+            DbPool::Sqlite(pool) => (
+                pool.options().get_min_connections(),
+                pool.options().get_max_connections(),
+            ),
+        }
+    }
+
+    /// Chiude il pool aspettando che le connessioni in uso vengano restituite
+    pub async fn close(&self) {
+        match self {
+            DbPool::MySql(pool) => pool.close().await,
+            #[cfg(feature = "sqlite")]
+            DbPool::Sqlite(pool) => pool.close().await,
+        }
+    }
+}
+
+impl From<MySqlPool> for DbPool {
+    fn from(pool: MySqlPool) -> Self {
+        DbPool::MySql(pool)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl From<SqlitePool> for DbPool {
+    fn from(pool: SqlitePool) -> Self {
+        DbPool::Sqlite(pool)
+    }
+}
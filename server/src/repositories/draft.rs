@@ -0,0 +1,153 @@
+//! DraftRepository - Repository per la gestione delle bozze
+
+use super::query_log::timed;
+#[cfg(feature = "sqlite")]
+use super::sqlite;
+use super::user_chat_metadata::UserChatKey;
+use super::{DbPool, Delete, Read};
+use crate::entities::Draft;
+use chrono::Utc;
+use sqlx::Error;
+use tracing::{debug, instrument};
+
+//DRAFT REPOSITORY
+pub struct DraftRepository {
+    connection_pool: DbPool,
+}
+
+impl DraftRepository {
+    pub fn new(connection_pool: impl Into<DbPool>) -> Self {
+        Self {
+            connection_pool: connection_pool.into(),
+        }
+    }
+
+    /// Salva la bozza dell'utente per la chat, sovrascrivendo quella precedente
+    #[instrument(skip(self, content), fields(user_id = %user_id, chat_id = %chat_id))]
+    pub async fn upsert(&self, user_id: &i32, chat_id: &i32, content: &str) -> Result<Draft, Error> {
+        debug!("Saving draft");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::draft::upsert(pool, user_id, chat_id, content).await;
+        }
+        let now = Utc::now();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO drafts (user_id, chat_id, content, updated_at)
+            VALUES (?, ?, ?, ?)
+            ON DUPLICATE KEY UPDATE content = VALUES(content), updated_at = VALUES(updated_at)
+            "#,
+            user_id,
+            chat_id,
+            content,
+            now
+        )
+        .execute(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(Draft {
+            user_id: *user_id,
+            chat_id: *chat_id,
+            content: content.to_string(),
+            updated_at: now,
+        })
+    }
+}
+
+impl Read<Draft, UserChatKey> for DraftRepository {
+    async fn read(&self, id: &UserChatKey) -> Result<Option<Draft>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::draft::read(pool, id).await;
+        }
+        let draft = sqlx::query_as!(
+            Draft,
+            r#"
+            SELECT 
+                user_id,
+                chat_id,
+                content,
+                updated_at
+            FROM drafts 
+            WHERE user_id = ? 
+            AND chat_id = ?
+            "#,
+            id.0,
+            id.1
+        )
+        .fetch_optional(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(draft)
+    }
+}
+
+impl Delete<UserChatKey> for DraftRepository {
+    async fn delete(&self, id: &UserChatKey) -> Result<(), Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::draft::delete(pool, id).await;
+        }
+        sqlx::query!(
+            "DELETE FROM drafts WHERE user_id = ? AND chat_id = ?",
+            id.0,
+            id.1
+        )
+        .execute(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::MySqlPool;
+
+    /// Test: upsert crea la bozza e una seconda chiamata la sovrascrive
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_upsert_overwrites_draft(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = DraftRepository::new(pool.clone());
+
+        repo.upsert(&1, &1, "first version").await?;
+        repo.upsert(&1, &1, "second version").await?;
+
+        let draft = repo.read(&(1, 1)).await?.expect("Draft should exist");
+        assert_eq!(draft.content, "second version");
+
+        let count = sqlx::query_scalar!("SELECT COUNT(*) FROM drafts WHERE user_id = 1 AND chat_id = 1")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(count, 1);
+
+        Ok(())
+    }
+
+    /// Test: le bozze sono separate per utente
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_drafts_are_per_user(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = DraftRepository::new(pool.clone());
+
+        repo.upsert(&1, &1, "alice draft").await?;
+
+        assert!(repo.read(&(2, 1)).await?.is_none());
+        assert_eq!(repo.read(&(1, 1)).await?.unwrap().content, "alice draft");
+
+        Ok(())
+    }
+
+    /// Test: delete rimuove la bozza
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_delete_draft(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = DraftRepository::new(pool.clone());
+
+        repo.upsert(&2, &1, "bob draft").await?;
+        repo.delete(&(2, 1)).await?;
+
+        assert!(repo.read(&(2, 1)).await?.is_none());
+
+        Ok(())
+    }
+}
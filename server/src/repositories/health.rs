@@ -0,0 +1,91 @@
+//! HealthRepository - Controlli sullo stato del database per la sonda di readiness
+//! e per le statistiche del server
+
+use super::DbPool;
+use super::query_log::timed;
+#[cfg(feature = "sqlite")]
+use super::sqlite;
+use sqlx::Error;
+use sqlx::migrate::Migrator;
+use tracing::{debug, instrument};
+
+/// Migrazioni MySQL incluse nel binario in fase di compilazione
+static MIGRATOR: Migrator = sqlx::migrate!();
+
+/// Occupazione del pool di connessioni al database
+#[derive(Debug, Clone, Copy)]
+pub struct PoolStatus {
+    /// Connessioni aperte, in uso o inattive
+    pub size: u32,
+    /// Connessioni aperte in attesa di una query
+    pub idle: usize,
+    pub min_connections: u32,
+    pub max_connections: u32,
+}
+
+//HEALTH REPOSITORY
+pub struct HealthRepository {
+    connection_pool: DbPool,
+}
+
+impl HealthRepository {
+    pub fn new(connection_pool: impl Into<DbPool>) -> Self {
+        Self {
+            connection_pool: connection_pool.into(),
+        }
+    }
+
+    /// Stato attuale del pool, senza eseguire query
+    pub fn pool_status(&self) -> PoolStatus {
+        let (min_connections, max_connections) = self.connection_pool.connection_limits();
+        PoolStatus {
+            size: self.connection_pool.size(),
+            idle: self.connection_pool.num_idle(),
+            min_connections,
+            max_connections,
+        }
+    }
+
+    /// Verifica che il database accetti connessioni e risponda alle query
+    #[instrument(skip(self))]
+    pub async fn ping(&self) -> Result<(), Error> {
+        debug!("Pinging database");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::health::ping(pool).await;
+        }
+        sqlx::query!("SELECT 1 AS ok")
+            .fetch_one(timed(self.connection_pool.mysql()))
+            .await?;
+        Ok(())
+    }
+
+    /// Versioni delle migrazioni incluse nel binario che il database non ha ancora applicato
+    #[instrument(skip(self))]
+    pub async fn pending_migrations(&self) -> Result<Vec<i64>, Error> {
+        debug!("Comparing applied migrations");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            let applied = sqlite::health::applied_migrations(pool).await?;
+            return Ok(pending(&sqlite::MIGRATOR, &applied));
+        }
+        // _sqlx_migrations è creata da `sqlx migrate run` e non appartiene allo schema
+        // dell'applicazione, per questo la query non passa dalla verifica di query!
+        let applied: Vec<i64> =
+            sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success = TRUE")
+                .fetch_all(timed(self.connection_pool.mysql()))
+                .await?;
+
+        Ok(pending(&MIGRATOR, &applied))
+    }
+}
+
+/// Versioni di `migrator` non presenti tra quelle applicate
+fn pending(migrator: &Migrator, applied: &[i64]) -> Vec<i64> {
+    migrator
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+        .map(|migration| migration.version)
+        .filter(|version| !applied.contains(version))
+        .collect()
+}
@@ -0,0 +1,667 @@
+//! In-memory repositories - Implementazioni in memoria delle interfacce dei repository
+//!
+//! Usate dai test unitari che costruiscono un `AppState` senza database (vedi i builder
+//! `with_*_repo`). Seguono le stesse regole delle implementazioni su MySQL: ordinamenti,
+//! paginazione, `RowNotFound` sulle righe mancanti e conflitti di `version`. La
+//! `UnitOfWork` passata ai metodi `_in` viene ignorata: le scritture sono subito visibili.
+
+use super::{
+    DeliveredBatch, MemberFilter, Page, UnitOfWork, UserChatMetadataRepo, UserFilter, UserRepo,
+    user_chat_metadata::{UserChatKey, version_conflict},
+};
+use crate::dtos::{
+    CreateUserChatMetadataDTO, CreateUserDTO, UnreadCountDTO, UpdateProfileDTO,
+    UpdateUserChatMetadataDTO, UpdateUserDTO,
+};
+use crate::entities::{PresenceVisibility, User, UserChatMetadata, UserRole};
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use sqlx::Error;
+use std::sync::RwLock;
+
+/// Slice of `items` selected by `page`, as `LIMIT`/`OFFSET`
+fn paginate<T>(items: impl Iterator<Item = T>, page: &Page) -> Vec<T> {
+    items
+        .skip(page.offset.max(0) as usize)
+        .take(page.limit.max(0) as usize)
+        .collect()
+}
+
+/// Utenti in memoria, con gli stessi id progressivi assegnati da MySQL
+#[derive(Default)]
+pub struct InMemoryUserRepo {
+    users: RwLock<Vec<User>>,
+}
+
+impl InMemoryUserRepo {
+    pub fn new(users: Vec<User>) -> Self {
+        Self {
+            users: RwLock::new(users),
+        }
+    }
+
+    fn find(&self, predicate: impl Fn(&User) -> bool) -> Vec<User> {
+        let users = self.users.read().unwrap();
+        users
+            .iter()
+            .filter(|user| predicate(user))
+            .cloned()
+            .collect()
+    }
+
+    /// Apply `change` to the user, `RowNotFound` if it does not exist
+    fn modify(&self, user_id: &i32, change: impl FnOnce(&mut User)) -> Result<User, Error> {
+        let mut users = self.users.write().unwrap();
+        let user = users
+            .iter_mut()
+            .find(|user| user.user_id == *user_id)
+            .ok_or(Error::RowNotFound)?;
+        change(user);
+        Ok(user.clone())
+    }
+
+    /// Like `modify`, but a missing user is not an error (an UPDATE touching no rows)
+    fn modify_if_present(&self, user_id: &i32, change: impl FnOnce(&mut User)) {
+        let _ = self.modify(user_id, change);
+    }
+}
+
+/// Valore da salvare per un campo del profilo: una stringa vuota lo cancella
+fn profile_field(current: &mut Option<String>, new: &Option<String>) {
+    if let Some(value) = new {
+        *current = Some(value.clone()).filter(|value| !value.is_empty());
+    }
+}
+
+impl UserRepo for InMemoryUserRepo {
+    fn find_by_username<'a>(
+        &'a self,
+        username: &'a String,
+    ) -> BoxFuture<'a, Result<Option<User>, Error>> {
+        let user = self.find(|user| &user.username == username).pop();
+        Box::pin(async move { Ok(user) })
+    }
+
+    fn search_by_username_partial<'a>(
+        &'a self,
+        username_pattern: &'a String,
+    ) -> BoxFuture<'a, Result<Vec<User>, Error>> {
+        let mut users = self.find(|user| {
+            user.username.starts_with(username_pattern.as_str()) && user.deactivated_at.is_none()
+        });
+        users.truncate(10);
+        Box::pin(async move { Ok(users) })
+    }
+
+    fn update_last_seen<'a>(
+        &'a self,
+        user_id: &'a i32,
+        last_seen: &'a DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        self.modify_if_present(user_id, |user| user.last_seen = Some(*last_seen));
+        Box::pin(async move { Ok(()) })
+    }
+
+    fn set_deactivated_at<'a>(
+        &'a self,
+        user_id: &'a i32,
+        deactivated_at: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        self.modify_if_present(user_id, |user| user.deactivated_at = deactivated_at);
+        Box::pin(async move { Ok(()) })
+    }
+
+    fn find_deactivated_before<'a>(
+        &'a self,
+        cutoff: &'a DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Vec<User>, Error>> {
+        let users = self.find(|user| user.deactivated_at.is_some_and(|at| at < *cutoff));
+        Box::pin(async move { Ok(users) })
+    }
+
+    fn update_presence_visibility<'a>(
+        &'a self,
+        user_id: &'a i32,
+        visibility: &'a PresenceVisibility,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        self.modify_if_present(user_id, |user| {
+            user.presence_visibility = visibility.clone()
+        });
+        Box::pin(async move { Ok(()) })
+    }
+
+    fn update_status<'a>(
+        &'a self,
+        user_id: &'a i32,
+        status_text: Option<&'a str>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        self.modify_if_present(user_id, |user| {
+            user.status_text = status_text.map(str::to_string);
+            user.status_expires_at = expires_at;
+        });
+        Box::pin(async move { Ok(()) })
+    }
+
+    fn update_profile<'a>(
+        &'a self,
+        user_id: &'a i32,
+        data: &'a UpdateProfileDTO,
+    ) -> BoxFuture<'a, Result<User, Error>> {
+        let result = self.modify(user_id, |user| {
+            profile_field(&mut user.display_name, &data.display_name);
+            profile_field(&mut user.bio, &data.bio);
+            profile_field(&mut user.avatar_url, &data.avatar_url);
+        });
+        Box::pin(async move { result })
+    }
+
+    fn create<'a>(&'a self, data: &'a CreateUserDTO) -> BoxFuture<'a, Result<User, Error>> {
+        let mut users = self.users.write().unwrap();
+        let user = User {
+            user_id: users.iter().map(|user| user.user_id).max().unwrap_or(0) + 1,
+            username: data.username.clone(),
+            password: data.password.clone(),
+            display_name: None,
+            bio: None,
+            avatar_url: None,
+            status_text: None,
+            status_expires_at: None,
+            last_seen: None,
+            presence_visibility: PresenceVisibility::Everyone,
+            deactivated_at: None,
+        };
+        users.push(user.clone());
+        Box::pin(async move { Ok(user) })
+    }
+
+    fn read<'a>(&'a self, id: &'a i32) -> BoxFuture<'a, Result<Option<User>, Error>> {
+        let user = self.find(|user| user.user_id == *id).pop();
+        Box::pin(async move { Ok(user) })
+    }
+
+    fn read_many<'a>(
+        &'a self,
+        filter: &'a UserFilter,
+        page: &'a Page,
+    ) -> BoxFuture<'a, Result<Vec<User>, Error>> {
+        let mut users = self.find(|user| {
+            filter
+                .search
+                .as_ref()
+                .is_none_or(|search| user.username.contains(search.as_str()))
+                && filter
+                    .ids
+                    .as_ref()
+                    .is_none_or(|ids| ids.contains(&user.user_id))
+        });
+        users.sort_by_key(|user| user.user_id);
+        let users = paginate(users.into_iter(), page);
+        Box::pin(async move { Ok(users) })
+    }
+
+    fn update<'a>(
+        &'a self,
+        id: &'a i32,
+        data: &'a UpdateUserDTO,
+    ) -> BoxFuture<'a, Result<User, Error>> {
+        let result = self.modify(id, |user| {
+            if let Some(password) = &data.password {
+                user.password = password.clone();
+            }
+        });
+        Box::pin(async move { result })
+    }
+
+    fn delete<'a>(&'a self, user_id: &'a i32) -> BoxFuture<'a, Result<(), Error>> {
+        // stesso soft delete del repository su MySQL
+        self.modify_if_present(user_id, |user| {
+            user.username = "Deleted User".to_string();
+            user.password = String::new();
+            user.display_name = None;
+            user.bio = None;
+            user.avatar_url = None;
+            user.status_text = None;
+            user.status_expires_at = None;
+            user.deactivated_at = None;
+        });
+        Box::pin(async move { Ok(()) })
+    }
+}
+
+/// Membership in memoria
+///
+/// Non conosce i messaggi: `count_unread_by_user_id` riporta 0 per ogni chat.
+#[derive(Default)]
+pub struct InMemoryUserChatMetadataRepo {
+    members: RwLock<Vec<UserChatMetadata>>,
+}
+
+impl InMemoryUserChatMetadataRepo {
+    pub fn new(members: Vec<UserChatMetadata>) -> Self {
+        Self {
+            members: RwLock::new(members),
+        }
+    }
+
+    fn find(&self, predicate: impl Fn(&UserChatMetadata) -> bool) -> Vec<UserChatMetadata> {
+        let members = self.members.read().unwrap();
+        members
+            .iter()
+            .filter(|meta| predicate(meta))
+            .cloned()
+            .collect()
+    }
+
+    fn get(&self, id: &UserChatKey) -> Option<UserChatMetadata> {
+        self.find(|meta| meta.user_id == id.0 && meta.chat_id == id.1)
+            .pop()
+    }
+
+    /// Apply `change` to the membership, `RowNotFound` if it does not exist
+    fn modify(
+        &self,
+        id: &UserChatKey,
+        change: impl FnOnce(&mut UserChatMetadata) -> Result<(), Error>,
+    ) -> Result<UserChatMetadata, Error> {
+        let mut members = self.members.write().unwrap();
+        let meta = members
+            .iter_mut()
+            .find(|meta| meta.user_id == id.0 && meta.chat_id == id.1)
+            .ok_or(Error::RowNotFound)?;
+        change(meta)?;
+        Ok(meta.clone())
+    }
+
+    /// Like `modify`, but a missing membership is not an error (an UPDATE touching no rows)
+    fn modify_if_present(&self, id: &UserChatKey, change: impl FnOnce(&mut UserChatMetadata)) {
+        let _ = self.modify(id, |meta| {
+            change(meta);
+            Ok(())
+        });
+    }
+
+    fn insert(&self, data: &CreateUserChatMetadataDTO) -> Result<UserChatMetadata, Error> {
+        Self::insert_into(&mut self.members.write().unwrap(), data)
+    }
+
+    /// Insert all memberships or none of them
+    fn insert_many(
+        &self,
+        metadata_list: &[CreateUserChatMetadataDTO],
+    ) -> Result<Vec<UserChatMetadata>, Error> {
+        let mut members = self.members.write().unwrap();
+        let mut staged = members.clone();
+        let created = metadata_list
+            .iter()
+            .map(|data| Self::insert_into(&mut staged, data))
+            .collect::<Result<Vec<_>, _>>()?;
+        *members = staged;
+        Ok(created)
+    }
+
+    fn insert_into(
+        members: &mut Vec<UserChatMetadata>,
+        data: &CreateUserChatMetadataDTO,
+    ) -> Result<UserChatMetadata, Error> {
+        if members
+            .iter()
+            .any(|meta| meta.user_id == data.user_id && meta.chat_id == data.chat_id)
+        {
+            // come la chiave primaria (user_id, chat_id) su MySQL
+            return Err(Error::InvalidArgument(format!(
+                "duplicate membership of user {} in chat {}",
+                data.user_id, data.chat_id
+            )));
+        }
+        let meta = UserChatMetadata {
+            user_id: data.user_id,
+            chat_id: data.chat_id,
+            user_role: data.user_role.clone(),
+            role_id: None,
+            member_since: data.member_since,
+            messages_visible_from: data.messages_visible_from,
+            messages_received_until: data.messages_received_until,
+            pinned_at: None,
+            muted_until: None,
+            version: 0,
+        };
+        members.push(meta.clone());
+        Ok(meta)
+    }
+}
+
+impl UserChatMetadataRepo for InMemoryUserChatMetadataRepo {
+    fn transfer_ownership<'a>(
+        &'a self,
+        from_user_id: &'a i32,
+        to_user_id: &'a i32,
+        chat_id: &'a i32,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        let result = if self.get(&(*from_user_id, *chat_id)).is_none()
+            || self.get(&(*to_user_id, *chat_id)).is_none()
+        {
+            Err(Error::RowNotFound)
+        } else {
+            for (user_id, role) in [
+                (from_user_id, UserRole::Admin),
+                (to_user_id, UserRole::Owner),
+            ] {
+                self.modify_if_present(&(*user_id, *chat_id), |meta| {
+                    meta.user_role = Some(role);
+                    meta.version += 1;
+                });
+            }
+            Ok(())
+        };
+        Box::pin(async move { result })
+    }
+
+    fn count_unread_by_user_id<'a>(
+        &'a self,
+        user_id: &'a i32,
+    ) -> BoxFuture<'a, Result<Vec<UnreadCountDTO>, Error>> {
+        let counts = self
+            .find(|meta| meta.user_id == *user_id)
+            .into_iter()
+            .map(|meta| UnreadCountDTO {
+                chat_id: meta.chat_id,
+                unread: 0,
+            })
+            .collect();
+        Box::pin(async move { Ok(counts) })
+    }
+
+    fn create_many<'a>(
+        &'a self,
+        metadata_list: &'a [CreateUserChatMetadataDTO],
+    ) -> BoxFuture<'a, Result<Vec<UserChatMetadata>, Error>> {
+        let result = self.insert_many(metadata_list);
+        Box::pin(async move { result })
+    }
+
+    fn create_in<'a>(
+        &'a self,
+        _uow: &'a mut UnitOfWork,
+        data: &'a CreateUserChatMetadataDTO,
+    ) -> BoxFuture<'a, Result<UserChatMetadata, Error>> {
+        let result = self.insert(data);
+        Box::pin(async move { result })
+    }
+
+    fn create_many_in<'a>(
+        &'a self,
+        _uow: &'a mut UnitOfWork,
+        metadata_list: &'a [CreateUserChatMetadataDTO],
+    ) -> BoxFuture<'a, Result<Vec<UserChatMetadata>, Error>> {
+        let result = self.insert_many(metadata_list);
+        Box::pin(async move { result })
+    }
+
+    fn transfer_ownership_in<'a>(
+        &'a self,
+        _uow: &'a mut UnitOfWork,
+        from_user_id: &'a i32,
+        to_user_id: &'a i32,
+        chat_id: &'a i32,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        self.transfer_ownership(from_user_id, to_user_id, chat_id)
+    }
+
+    fn delete_in<'a>(
+        &'a self,
+        _uow: &'a mut UnitOfWork,
+        id: &'a UserChatKey,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        UserChatMetadataRepo::delete(self, id)
+    }
+
+    fn count_by_chat_id<'a>(&'a self, chat_id: &'a i32) -> BoxFuture<'a, Result<i64, Error>> {
+        let count = self.find(|meta| meta.chat_id == *chat_id).len() as i64;
+        Box::pin(async move { Ok(count) })
+    }
+
+    fn share_any_chat<'a>(
+        &'a self,
+        user_id: &'a i32,
+        other_user_id: &'a i32,
+    ) -> BoxFuture<'a, Result<bool, Error>> {
+        let other_chats: Vec<i32> = self
+            .find(|meta| meta.user_id == *other_user_id)
+            .iter()
+            .map(|meta| meta.chat_id)
+            .collect();
+        let shared = !self
+            .find(|meta| meta.user_id == *user_id && other_chats.contains(&meta.chat_id))
+            .is_empty();
+        Box::pin(async move { Ok(shared) })
+    }
+
+    fn set_pinned<'a>(
+        &'a self,
+        user_id: &'a i32,
+        chat_id: &'a i32,
+        pinned_at: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        self.modify_if_present(&(*user_id, *chat_id), |meta| meta.pinned_at = pinned_at);
+        Box::pin(async move { Ok(()) })
+    }
+
+    fn set_custom_role<'a>(
+        &'a self,
+        user_id: &'a i32,
+        chat_id: &'a i32,
+        role_id: Option<i32>,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        self.modify_if_present(&(*user_id, *chat_id), |meta| meta.role_id = role_id);
+        Box::pin(async move { Ok(()) })
+    }
+
+    fn set_muted_until<'a>(
+        &'a self,
+        user_id: &'a i32,
+        chat_id: &'a i32,
+        muted_until: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        self.modify_if_present(&(*user_id, *chat_id), |meta| meta.muted_until = muted_until);
+        Box::pin(async move { Ok(()) })
+    }
+
+    fn advance_received_until<'a>(
+        &'a self,
+        user_id: &'a i32,
+        received: &'a [DeliveredBatch],
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        for batch in received {
+            // il marcatore avanza soltanto
+            self.modify_if_present(&(*user_id, batch.chat_id), |meta| {
+                if meta.messages_received_until < batch.received_until {
+                    meta.messages_received_until = batch.received_until;
+                }
+            });
+        }
+        Box::pin(async move { Ok(()) })
+    }
+
+    fn update_user_role<'a>(
+        &'a self,
+        user_id: &'a i32,
+        chat_id: &'a i32,
+        new_role: &'a UserRole,
+        expected_version: &'a i32,
+    ) -> BoxFuture<'a, Result<UserChatMetadata, Error>> {
+        let result = self.modify(&(*user_id, *chat_id), |meta| {
+            if meta.version != *expected_version {
+                return Err(version_conflict());
+            }
+            meta.user_role = Some(new_role.clone());
+            meta.version += 1;
+            Ok(())
+        });
+        Box::pin(async move { result })
+    }
+
+    fn create<'a>(
+        &'a self,
+        data: &'a CreateUserChatMetadataDTO,
+    ) -> BoxFuture<'a, Result<UserChatMetadata, Error>> {
+        let result = self.insert(data);
+        Box::pin(async move { result })
+    }
+
+    fn read<'a>(
+        &'a self,
+        id: &'a UserChatKey,
+    ) -> BoxFuture<'a, Result<Option<UserChatMetadata>, Error>> {
+        let meta = self.get(id);
+        Box::pin(async move { Ok(meta) })
+    }
+
+    fn read_many<'a>(
+        &'a self,
+        filter: &'a MemberFilter,
+        page: &'a Page,
+    ) -> BoxFuture<'a, Result<Vec<UserChatMetadata>, Error>> {
+        let mut members = self.find(|meta| {
+            filter.chat_id.is_none_or(|chat_id| meta.chat_id == chat_id)
+                && filter
+                    .chat_ids
+                    .as_ref()
+                    .is_none_or(|chat_ids| chat_ids.contains(&meta.chat_id))
+                && filter.user_id.is_none_or(|user_id| meta.user_id == user_id)
+                && meta.user_id > filter.after_user_id
+                && filter
+                    .role
+                    .as_ref()
+                    .is_none_or(|role| meta.user_role.as_ref() == Some(role))
+        });
+        members.sort_by_key(|meta| (meta.chat_id, meta.user_id));
+        let members = paginate(members.into_iter(), page);
+        Box::pin(async move { Ok(members) })
+    }
+
+    fn update<'a>(
+        &'a self,
+        id: &'a UserChatKey,
+        data: &'a UpdateUserChatMetadataDTO,
+    ) -> BoxFuture<'a, Result<UserChatMetadata, Error>> {
+        let result = self.modify(id, |meta| {
+            if data.version.is_some_and(|version| version != meta.version) {
+                return Err(version_conflict());
+            }
+            if data.user_role.is_none()
+                && data.messages_visible_from.is_none()
+                && data.messages_received_until.is_none()
+            {
+                return Ok(());
+            }
+            if let Some(role) = &data.user_role {
+                meta.user_role = Some(role.clone());
+            }
+            if let Some(visible_from) = data.messages_visible_from {
+                meta.messages_visible_from = visible_from;
+            }
+            if let Some(received_until) = data.messages_received_until {
+                meta.messages_received_until = received_until;
+            }
+            meta.version += 1;
+            Ok(())
+        });
+        Box::pin(async move { result })
+    }
+
+    fn delete<'a>(&'a self, id: &'a UserChatKey) -> BoxFuture<'a, Result<(), Error>> {
+        self.members
+            .write()
+            .unwrap()
+            .retain(|meta| meta.user_id != id.0 || meta.chat_id != id.1);
+        Box::pin(async move { Ok(()) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::is_version_conflict;
+
+    fn member(user_id: i32, chat_id: i32, role: UserRole) -> CreateUserChatMetadataDTO {
+        let now = Utc::now();
+        CreateUserChatMetadataDTO {
+            user_id,
+            chat_id,
+            user_role: Some(role),
+            member_since: now,
+            messages_visible_from: now,
+            messages_received_until: now,
+        }
+    }
+
+    /// Test: utenti creati con id progressivi e cercati per username
+    #[tokio::test]
+    async fn test_users_create_and_search() {
+        let repo = InMemoryUserRepo::default();
+        for username in ["alice", "alfred", "bob"] {
+            let data = CreateUserDTO {
+                username: username.to_string(),
+                password: "hash".to_string(),
+            };
+            repo.create(&data).await.unwrap();
+        }
+
+        let bob = repo.find_by_username(&"bob".to_string()).await.unwrap();
+        assert_eq!(bob.map(|user| user.user_id), Some(3));
+        let found = repo.search_by_username_partial(&"al".to_string()).await;
+        assert_eq!(found.unwrap().len(), 2);
+        let page = repo
+            .read_many(&UserFilter::default(), &Page::new(1, 1))
+            .await;
+        assert_eq!(page.unwrap()[0].username, "alfred");
+        assert!(matches!(
+            repo.update_profile(&9, &UpdateProfileDTO::default()).await,
+            Err(Error::RowNotFound)
+        ));
+    }
+
+    /// Test: le modifiche al ruolo rispettano la versione letta, come su MySQL
+    #[tokio::test]
+    async fn test_members_role_change_checks_version() {
+        let repo = InMemoryUserChatMetadataRepo::default();
+        repo.create_many(&[
+            member(1, 10, UserRole::Owner),
+            member(2, 10, UserRole::Member),
+        ])
+        .await
+        .unwrap();
+
+        let admin = repo.update_user_role(&2, &10, &UserRole::Admin, &0).await;
+        assert_eq!(admin.unwrap().version, 1);
+        let stale = repo.update_user_role(&2, &10, &UserRole::Member, &0).await;
+        assert!(stale.is_err_and(|err| is_version_conflict(&err)));
+        assert!(matches!(
+            repo.update_user_role(&3, &10, &UserRole::Member, &0).await,
+            Err(Error::RowNotFound)
+        ));
+
+        repo.transfer_ownership(&1, &2, &10).await.unwrap();
+        let owner = repo.read(&(2, 10)).await.unwrap().unwrap();
+        assert_eq!(owner.user_role, Some(UserRole::Owner));
+        assert!(repo.share_any_chat(&1, &2).await.unwrap());
+    }
+
+    /// Test: create_many inserisce tutte le membership o nessuna
+    #[tokio::test]
+    async fn test_members_create_many_is_atomic() {
+        let repo = InMemoryUserChatMetadataRepo::default();
+        repo.create(&member(1, 10, UserRole::Owner)).await.unwrap();
+
+        let result = repo
+            .create_many(&[
+                member(2, 10, UserRole::Member),
+                member(1, 10, UserRole::Member),
+            ])
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(repo.count_by_chat_id(&10).await.unwrap(), 1);
+    }
+}
@@ -0,0 +1,843 @@
+//! Repository interfaces - Interfacce object-safe dei repository usati da servizi e WebSocket
+//!
+//! `AppState` conserva questi repository come `Arc<dyn ...>`: l'implementazione reale è
+//! quella sul database (MySQL o SQLite), ma un test unitario può sostituirla con una versione
+//! in memoria (vedi i builder `with_*_repo` di `AppState`) senza bisogno di un database attivo.
+//! I metodi restituiscono `BoxFuture` perché i trait con `async fn` non sono utilizzabili
+//! come trait object; ognuno delega al metodo omonimo del repository concreto.
+
+use super::{
+    ChatFilter, ChatRepository, Create, Delete, DeliveredBatch, MemberFilter, MessageFilter,
+    MessageRepository, Page, Read, ReadMany, UnitOfWork, Update, UserChatMetadataRepository,
+    UserFilter, UserRepository, user_chat_metadata::UserChatKey,
+};
+use crate::dtos::{
+    ChatDTO, ChatOverviewDTO, CreateChatDTO, CreateMessageDTO, CreateUserChatMetadataDTO,
+    CreateUserDTO, PublicChatDTO, UnreadCountDTO, UpdateChatDTO, UpdateMessageDTO,
+    UpdateProfileDTO, UpdateUserChatMetadataDTO, UpdateUserDTO,
+};
+use crate::entities::{
+    Chat, Message, MessageRevision, PresenceVisibility, User, UserChatMetadata, UserRole,
+};
+use chrono::{DateTime, Utc};
+use futures::{future::BoxFuture, stream::BoxStream};
+use sqlx::Error;
+
+/// Operazioni sugli utenti, implementate sul database da `UserRepository`
+pub trait UserRepo: Send + Sync {
+    fn find_by_username<'a>(
+        &'a self,
+        username: &'a String,
+    ) -> BoxFuture<'a, Result<Option<User>, Error>>;
+    fn search_by_username_partial<'a>(
+        &'a self,
+        username_pattern: &'a String,
+    ) -> BoxFuture<'a, Result<Vec<User>, Error>>;
+    fn update_last_seen<'a>(
+        &'a self,
+        user_id: &'a i32,
+        last_seen: &'a DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+    fn set_deactivated_at<'a>(
+        &'a self,
+        user_id: &'a i32,
+        deactivated_at: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+    fn find_deactivated_before<'a>(
+        &'a self,
+        cutoff: &'a DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Vec<User>, Error>>;
+    fn update_presence_visibility<'a>(
+        &'a self,
+        user_id: &'a i32,
+        visibility: &'a PresenceVisibility,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+    fn update_status<'a>(
+        &'a self,
+        user_id: &'a i32,
+        status_text: Option<&'a str>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+    fn update_profile<'a>(
+        &'a self,
+        user_id: &'a i32,
+        data: &'a UpdateProfileDTO,
+    ) -> BoxFuture<'a, Result<User, Error>>;
+    fn create<'a>(&'a self, data: &'a CreateUserDTO) -> BoxFuture<'a, Result<User, Error>>;
+    fn read<'a>(&'a self, id: &'a i32) -> BoxFuture<'a, Result<Option<User>, Error>>;
+    fn read_many<'a>(
+        &'a self,
+        filter: &'a UserFilter,
+        page: &'a Page,
+    ) -> BoxFuture<'a, Result<Vec<User>, Error>>;
+    fn update<'a>(
+        &'a self,
+        id: &'a i32,
+        data: &'a UpdateUserDTO,
+    ) -> BoxFuture<'a, Result<User, Error>>;
+    fn delete<'a>(&'a self, user_id: &'a i32) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+impl UserRepo for UserRepository {
+    fn find_by_username<'a>(
+        &'a self,
+        username: &'a String,
+    ) -> BoxFuture<'a, Result<Option<User>, Error>> {
+        Box::pin(UserRepository::find_by_username(self, username))
+    }
+
+    fn search_by_username_partial<'a>(
+        &'a self,
+        username_pattern: &'a String,
+    ) -> BoxFuture<'a, Result<Vec<User>, Error>> {
+        Box::pin(UserRepository::search_by_username_partial(
+            self,
+            username_pattern,
+        ))
+    }
+
+    fn update_last_seen<'a>(
+        &'a self,
+        user_id: &'a i32,
+        last_seen: &'a DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(UserRepository::update_last_seen(self, user_id, last_seen))
+    }
+
+    fn set_deactivated_at<'a>(
+        &'a self,
+        user_id: &'a i32,
+        deactivated_at: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(UserRepository::set_deactivated_at(
+            self,
+            user_id,
+            deactivated_at,
+        ))
+    }
+
+    fn find_deactivated_before<'a>(
+        &'a self,
+        cutoff: &'a DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Vec<User>, Error>> {
+        Box::pin(UserRepository::find_deactivated_before(self, cutoff))
+    }
+
+    fn update_presence_visibility<'a>(
+        &'a self,
+        user_id: &'a i32,
+        visibility: &'a PresenceVisibility,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(UserRepository::update_presence_visibility(
+            self, user_id, visibility,
+        ))
+    }
+
+    fn update_status<'a>(
+        &'a self,
+        user_id: &'a i32,
+        status_text: Option<&'a str>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(UserRepository::update_status(
+            self,
+            user_id,
+            status_text,
+            expires_at,
+        ))
+    }
+
+    fn update_profile<'a>(
+        &'a self,
+        user_id: &'a i32,
+        data: &'a UpdateProfileDTO,
+    ) -> BoxFuture<'a, Result<User, Error>> {
+        Box::pin(UserRepository::update_profile(self, user_id, data))
+    }
+
+    fn create<'a>(&'a self, data: &'a CreateUserDTO) -> BoxFuture<'a, Result<User, Error>> {
+        Box::pin(Create::create(self, data))
+    }
+
+    fn read<'a>(&'a self, id: &'a i32) -> BoxFuture<'a, Result<Option<User>, Error>> {
+        Box::pin(Read::read(self, id))
+    }
+
+    fn read_many<'a>(
+        &'a self,
+        filter: &'a UserFilter,
+        page: &'a Page,
+    ) -> BoxFuture<'a, Result<Vec<User>, Error>> {
+        Box::pin(ReadMany::read_many(self, filter, page))
+    }
+
+    fn update<'a>(
+        &'a self,
+        id: &'a i32,
+        data: &'a UpdateUserDTO,
+    ) -> BoxFuture<'a, Result<User, Error>> {
+        Box::pin(Update::update(self, id, data))
+    }
+
+    fn delete<'a>(&'a self, user_id: &'a i32) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(Delete::delete(self, user_id))
+    }
+}
+
+/// Operazioni sulle chat, implementate sul database da `ChatRepository`
+pub trait ChatRepo: Send + Sync {
+    fn get_private_chat_between_users<'a>(
+        &'a self,
+        user1_id: &'a i32,
+        user2_id: &'a i32,
+    ) -> BoxFuture<'a, Result<Option<Chat>, Error>>;
+    fn search_by_title_for_user<'a>(
+        &'a self,
+        user_id: &'a i32,
+        title_pattern: &'a str,
+        limit: i64,
+    ) -> BoxFuture<'a, Result<Vec<Chat>, Error>>;
+    fn search_public<'a>(
+        &'a self,
+        query: Option<&'a str>,
+        limit: i64,
+        offset: i64,
+    ) -> BoxFuture<'a, Result<Vec<PublicChatDTO>, Error>>;
+    fn find_overview_by_user_id<'a>(
+        &'a self,
+        user_id: &'a i32,
+    ) -> BoxFuture<'a, Result<Vec<ChatOverviewDTO>, Error>>;
+    fn set_avatar<'a>(
+        &'a self,
+        chat_id: &'a i32,
+        attachment_id: &'a i32,
+    ) -> BoxFuture<'a, Result<Chat, Error>>;
+    fn create_in<'a>(
+        &'a self,
+        uow: &'a mut UnitOfWork,
+        data: &'a CreateChatDTO,
+    ) -> BoxFuture<'a, Result<Chat, Error>>;
+    fn lock_members_in<'a>(
+        &'a self,
+        uow: &'a mut UnitOfWork,
+        chat_id: &'a i32,
+    ) -> BoxFuture<'a, Result<i64, Error>>;
+    fn create<'a>(&'a self, data: &'a CreateChatDTO) -> BoxFuture<'a, Result<Chat, Error>>;
+    fn read<'a>(&'a self, id: &'a i32) -> BoxFuture<'a, Result<Option<Chat>, Error>>;
+    fn read_many<'a>(
+        &'a self,
+        filter: &'a ChatFilter,
+        page: &'a Page,
+    ) -> BoxFuture<'a, Result<Vec<ChatDTO>, Error>>;
+    fn update<'a>(
+        &'a self,
+        id: &'a i32,
+        data: &'a UpdateChatDTO,
+    ) -> BoxFuture<'a, Result<Chat, Error>>;
+    fn delete<'a>(&'a self, id: &'a i32) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+impl ChatRepo for ChatRepository {
+    fn get_private_chat_between_users<'a>(
+        &'a self,
+        user1_id: &'a i32,
+        user2_id: &'a i32,
+    ) -> BoxFuture<'a, Result<Option<Chat>, Error>> {
+        Box::pin(ChatRepository::get_private_chat_between_users(
+            self, user1_id, user2_id,
+        ))
+    }
+
+    fn search_by_title_for_user<'a>(
+        &'a self,
+        user_id: &'a i32,
+        title_pattern: &'a str,
+        limit: i64,
+    ) -> BoxFuture<'a, Result<Vec<Chat>, Error>> {
+        Box::pin(ChatRepository::search_by_title_for_user(
+            self,
+            user_id,
+            title_pattern,
+            limit,
+        ))
+    }
+
+    fn search_public<'a>(
+        &'a self,
+        query: Option<&'a str>,
+        limit: i64,
+        offset: i64,
+    ) -> BoxFuture<'a, Result<Vec<PublicChatDTO>, Error>> {
+        Box::pin(ChatRepository::search_public(self, query, limit, offset))
+    }
+
+    fn find_overview_by_user_id<'a>(
+        &'a self,
+        user_id: &'a i32,
+    ) -> BoxFuture<'a, Result<Vec<ChatOverviewDTO>, Error>> {
+        Box::pin(ChatRepository::find_overview_by_user_id(self, user_id))
+    }
+
+    fn set_avatar<'a>(
+        &'a self,
+        chat_id: &'a i32,
+        attachment_id: &'a i32,
+    ) -> BoxFuture<'a, Result<Chat, Error>> {
+        Box::pin(ChatRepository::set_avatar(self, chat_id, attachment_id))
+    }
+
+    fn create_in<'a>(
+        &'a self,
+        uow: &'a mut UnitOfWork,
+        data: &'a CreateChatDTO,
+    ) -> BoxFuture<'a, Result<Chat, Error>> {
+        Box::pin(ChatRepository::create_in(self, uow, data))
+    }
+
+    fn lock_members_in<'a>(
+        &'a self,
+        uow: &'a mut UnitOfWork,
+        chat_id: &'a i32,
+    ) -> BoxFuture<'a, Result<i64, Error>> {
+        Box::pin(ChatRepository::lock_members_in(self, uow, chat_id))
+    }
+
+    fn create<'a>(&'a self, data: &'a CreateChatDTO) -> BoxFuture<'a, Result<Chat, Error>> {
+        Box::pin(Create::create(self, data))
+    }
+
+    fn read<'a>(&'a self, id: &'a i32) -> BoxFuture<'a, Result<Option<Chat>, Error>> {
+        Box::pin(Read::read(self, id))
+    }
+
+    fn read_many<'a>(
+        &'a self,
+        filter: &'a ChatFilter,
+        page: &'a Page,
+    ) -> BoxFuture<'a, Result<Vec<ChatDTO>, Error>> {
+        Box::pin(ReadMany::read_many(self, filter, page))
+    }
+
+    fn update<'a>(
+        &'a self,
+        id: &'a i32,
+        data: &'a UpdateChatDTO,
+    ) -> BoxFuture<'a, Result<Chat, Error>> {
+        Box::pin(Update::update(self, id, data))
+    }
+
+    fn delete<'a>(&'a self, id: &'a i32) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(Delete::delete(self, id))
+    }
+}
+
+/// Operazioni sui messaggi, implementate sul database da `MessageRepository`
+pub trait MessageRepo: Send + Sync {
+    fn count_by_chat_id<'a>(&'a self, chat_id: &'a i32) -> BoxFuture<'a, Result<i64, Error>>;
+    fn find_by_client_msg_id<'a>(
+        &'a self,
+        sender_id: &'a i32,
+        client_msg_id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<Message>, Error>>;
+    fn stream_by_chat<'a>(
+        &'a self,
+        chat_id: &'a i32,
+        messages_visible_from: &'a DateTime<Utc>,
+    ) -> BoxStream<'a, Result<Message, Error>>;
+    fn stream_by_sender_id<'a>(
+        &'a self,
+        sender_id: &'a i32,
+    ) -> BoxStream<'a, Result<Message, Error>>;
+    fn soft_delete<'a>(&'a self, id: &'a i32) -> BoxFuture<'a, Result<Message, Error>>;
+    fn search_in_chat<'a>(
+        &'a self,
+        chat_id: &'a i32,
+        query: &'a str,
+        messages_visible_from: &'a DateTime<Utc>,
+        before_date: Option<&'a DateTime<Utc>>,
+        limit: i64,
+    ) -> BoxFuture<'a, Result<Vec<Message>, Error>>;
+    fn search_for_user<'a>(
+        &'a self,
+        user_id: &'a i32,
+        query: &'a str,
+        limit: i64,
+    ) -> BoxFuture<'a, Result<Vec<Message>, Error>>;
+    fn pin<'a>(
+        &'a self,
+        chat_id: &'a i32,
+        message_id: &'a i32,
+        pinned_by: &'a i32,
+    ) -> BoxFuture<'a, Result<bool, Error>>;
+    fn unpin<'a>(
+        &'a self,
+        chat_id: &'a i32,
+        message_id: &'a i32,
+    ) -> BoxFuture<'a, Result<bool, Error>>;
+    fn find_pinned<'a>(
+        &'a self,
+        chat_id: &'a i32,
+        messages_visible_from: &'a DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Vec<Message>, Error>>;
+    fn delete_messages_before<'a>(
+        &'a self,
+        chat_id: &'a i32,
+        before_date: &'a DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<u64, Error>>;
+    fn find_revisions<'a>(
+        &'a self,
+        message_id: &'a i32,
+    ) -> BoxFuture<'a, Result<Vec<MessageRevision>, Error>>;
+    fn create_in<'a>(
+        &'a self,
+        uow: &'a mut UnitOfWork,
+        data: &'a CreateMessageDTO,
+    ) -> BoxFuture<'a, Result<Message, Error>>;
+    fn create<'a>(&'a self, data: &'a CreateMessageDTO) -> BoxFuture<'a, Result<Message, Error>>;
+    fn read<'a>(&'a self, id: &'a i32) -> BoxFuture<'a, Result<Option<Message>, Error>>;
+    fn read_many<'a>(
+        &'a self,
+        filter: &'a MessageFilter,
+        page: &'a Page,
+    ) -> BoxFuture<'a, Result<Vec<Message>, Error>>;
+    fn update<'a>(
+        &'a self,
+        id: &'a i32,
+        data: &'a UpdateMessageDTO,
+    ) -> BoxFuture<'a, Result<Message, Error>>;
+    fn delete<'a>(&'a self, id: &'a i32) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+impl MessageRepo for MessageRepository {
+    fn count_by_chat_id<'a>(&'a self, chat_id: &'a i32) -> BoxFuture<'a, Result<i64, Error>> {
+        Box::pin(MessageRepository::count_by_chat_id(self, chat_id))
+    }
+
+    fn find_by_client_msg_id<'a>(
+        &'a self,
+        sender_id: &'a i32,
+        client_msg_id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<Message>, Error>> {
+        Box::pin(MessageRepository::find_by_client_msg_id(
+            self,
+            sender_id,
+            client_msg_id,
+        ))
+    }
+
+    fn stream_by_chat<'a>(
+        &'a self,
+        chat_id: &'a i32,
+        messages_visible_from: &'a DateTime<Utc>,
+    ) -> BoxStream<'a, Result<Message, Error>> {
+        MessageRepository::stream_by_chat(self, chat_id, messages_visible_from)
+    }
+
+    fn stream_by_sender_id<'a>(
+        &'a self,
+        sender_id: &'a i32,
+    ) -> BoxStream<'a, Result<Message, Error>> {
+        MessageRepository::stream_by_sender_id(self, sender_id)
+    }
+
+    fn soft_delete<'a>(&'a self, id: &'a i32) -> BoxFuture<'a, Result<Message, Error>> {
+        Box::pin(MessageRepository::soft_delete(self, id))
+    }
+
+    fn search_in_chat<'a>(
+        &'a self,
+        chat_id: &'a i32,
+        query: &'a str,
+        messages_visible_from: &'a DateTime<Utc>,
+        before_date: Option<&'a DateTime<Utc>>,
+        limit: i64,
+    ) -> BoxFuture<'a, Result<Vec<Message>, Error>> {
+        Box::pin(MessageRepository::search_in_chat(
+            self,
+            chat_id,
+            query,
+            messages_visible_from,
+            before_date,
+            limit,
+        ))
+    }
+
+    fn search_for_user<'a>(
+        &'a self,
+        user_id: &'a i32,
+        query: &'a str,
+        limit: i64,
+    ) -> BoxFuture<'a, Result<Vec<Message>, Error>> {
+        Box::pin(MessageRepository::search_for_user(
+            self, user_id, query, limit,
+        ))
+    }
+
+    fn pin<'a>(
+        &'a self,
+        chat_id: &'a i32,
+        message_id: &'a i32,
+        pinned_by: &'a i32,
+    ) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(MessageRepository::pin(self, chat_id, message_id, pinned_by))
+    }
+
+    fn unpin<'a>(
+        &'a self,
+        chat_id: &'a i32,
+        message_id: &'a i32,
+    ) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(MessageRepository::unpin(self, chat_id, message_id))
+    }
+
+    fn find_pinned<'a>(
+        &'a self,
+        chat_id: &'a i32,
+        messages_visible_from: &'a DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Vec<Message>, Error>> {
+        Box::pin(MessageRepository::find_pinned(
+            self,
+            chat_id,
+            messages_visible_from,
+        ))
+    }
+
+    fn delete_messages_before<'a>(
+        &'a self,
+        chat_id: &'a i32,
+        before_date: &'a DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<u64, Error>> {
+        Box::pin(MessageRepository::delete_messages_before(
+            self,
+            chat_id,
+            before_date,
+        ))
+    }
+
+    fn find_revisions<'a>(
+        &'a self,
+        message_id: &'a i32,
+    ) -> BoxFuture<'a, Result<Vec<MessageRevision>, Error>> {
+        Box::pin(MessageRepository::find_revisions(self, message_id))
+    }
+
+    fn create_in<'a>(
+        &'a self,
+        uow: &'a mut UnitOfWork,
+        data: &'a CreateMessageDTO,
+    ) -> BoxFuture<'a, Result<Message, Error>> {
+        Box::pin(MessageRepository::create_in(self, uow, data))
+    }
+
+    fn create<'a>(&'a self, data: &'a CreateMessageDTO) -> BoxFuture<'a, Result<Message, Error>> {
+        Box::pin(Create::create(self, data))
+    }
+
+    fn read<'a>(&'a self, id: &'a i32) -> BoxFuture<'a, Result<Option<Message>, Error>> {
+        Box::pin(Read::read(self, id))
+    }
+
+    fn read_many<'a>(
+        &'a self,
+        filter: &'a MessageFilter,
+        page: &'a Page,
+    ) -> BoxFuture<'a, Result<Vec<Message>, Error>> {
+        Box::pin(ReadMany::read_many(self, filter, page))
+    }
+
+    fn update<'a>(
+        &'a self,
+        id: &'a i32,
+        data: &'a UpdateMessageDTO,
+    ) -> BoxFuture<'a, Result<Message, Error>> {
+        Box::pin(Update::update(self, id, data))
+    }
+
+    fn delete<'a>(&'a self, id: &'a i32) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(Delete::delete(self, id))
+    }
+}
+
+/// Operazioni sulle membership (metadati utente-chat), implementate sul database da
+/// `UserChatMetadataRepository`
+pub trait UserChatMetadataRepo: Send + Sync {
+    fn transfer_ownership<'a>(
+        &'a self,
+        from_user_id: &'a i32,
+        to_user_id: &'a i32,
+        chat_id: &'a i32,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+    fn count_unread_by_user_id<'a>(
+        &'a self,
+        user_id: &'a i32,
+    ) -> BoxFuture<'a, Result<Vec<UnreadCountDTO>, Error>>;
+    fn create_many<'a>(
+        &'a self,
+        metadata_list: &'a [CreateUserChatMetadataDTO],
+    ) -> BoxFuture<'a, Result<Vec<UserChatMetadata>, Error>>;
+    fn create_in<'a>(
+        &'a self,
+        uow: &'a mut UnitOfWork,
+        data: &'a CreateUserChatMetadataDTO,
+    ) -> BoxFuture<'a, Result<UserChatMetadata, Error>>;
+    fn create_many_in<'a>(
+        &'a self,
+        uow: &'a mut UnitOfWork,
+        metadata_list: &'a [CreateUserChatMetadataDTO],
+    ) -> BoxFuture<'a, Result<Vec<UserChatMetadata>, Error>>;
+    fn transfer_ownership_in<'a>(
+        &'a self,
+        uow: &'a mut UnitOfWork,
+        from_user_id: &'a i32,
+        to_user_id: &'a i32,
+        chat_id: &'a i32,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+    fn delete_in<'a>(
+        &'a self,
+        uow: &'a mut UnitOfWork,
+        id: &'a UserChatKey,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+    fn count_by_chat_id<'a>(&'a self, chat_id: &'a i32) -> BoxFuture<'a, Result<i64, Error>>;
+    fn share_any_chat<'a>(
+        &'a self,
+        user_id: &'a i32,
+        other_user_id: &'a i32,
+    ) -> BoxFuture<'a, Result<bool, Error>>;
+    fn set_pinned<'a>(
+        &'a self,
+        user_id: &'a i32,
+        chat_id: &'a i32,
+        pinned_at: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+    fn set_custom_role<'a>(
+        &'a self,
+        user_id: &'a i32,
+        chat_id: &'a i32,
+        role_id: Option<i32>,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+    fn set_muted_until<'a>(
+        &'a self,
+        user_id: &'a i32,
+        chat_id: &'a i32,
+        muted_until: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+    fn advance_received_until<'a>(
+        &'a self,
+        user_id: &'a i32,
+        received: &'a [DeliveredBatch],
+    ) -> BoxFuture<'a, Result<(), Error>>;
+    fn update_user_role<'a>(
+        &'a self,
+        user_id: &'a i32,
+        chat_id: &'a i32,
+        new_role: &'a UserRole,
+        expected_version: &'a i32,
+    ) -> BoxFuture<'a, Result<UserChatMetadata, Error>>;
+    fn create<'a>(
+        &'a self,
+        data: &'a CreateUserChatMetadataDTO,
+    ) -> BoxFuture<'a, Result<UserChatMetadata, Error>>;
+    fn read<'a>(
+        &'a self,
+        id: &'a UserChatKey,
+    ) -> BoxFuture<'a, Result<Option<UserChatMetadata>, Error>>;
+    fn read_many<'a>(
+        &'a self,
+        filter: &'a MemberFilter,
+        page: &'a Page,
+    ) -> BoxFuture<'a, Result<Vec<UserChatMetadata>, Error>>;
+    fn update<'a>(
+        &'a self,
+        id: &'a UserChatKey,
+        data: &'a UpdateUserChatMetadataDTO,
+    ) -> BoxFuture<'a, Result<UserChatMetadata, Error>>;
+    fn delete<'a>(&'a self, id: &'a UserChatKey) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+impl UserChatMetadataRepo for UserChatMetadataRepository {
+    fn transfer_ownership<'a>(
+        &'a self,
+        from_user_id: &'a i32,
+        to_user_id: &'a i32,
+        chat_id: &'a i32,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(UserChatMetadataRepository::transfer_ownership(
+            self,
+            from_user_id,
+            to_user_id,
+            chat_id,
+        ))
+    }
+
+    fn count_unread_by_user_id<'a>(
+        &'a self,
+        user_id: &'a i32,
+    ) -> BoxFuture<'a, Result<Vec<UnreadCountDTO>, Error>> {
+        Box::pin(UserChatMetadataRepository::count_unread_by_user_id(
+            self, user_id,
+        ))
+    }
+
+    fn create_many<'a>(
+        &'a self,
+        metadata_list: &'a [CreateUserChatMetadataDTO],
+    ) -> BoxFuture<'a, Result<Vec<UserChatMetadata>, Error>> {
+        Box::pin(UserChatMetadataRepository::create_many(self, metadata_list))
+    }
+
+    fn create_in<'a>(
+        &'a self,
+        uow: &'a mut UnitOfWork,
+        data: &'a CreateUserChatMetadataDTO,
+    ) -> BoxFuture<'a, Result<UserChatMetadata, Error>> {
+        Box::pin(UserChatMetadataRepository::create_in(self, uow, data))
+    }
+
+    fn create_many_in<'a>(
+        &'a self,
+        uow: &'a mut UnitOfWork,
+        metadata_list: &'a [CreateUserChatMetadataDTO],
+    ) -> BoxFuture<'a, Result<Vec<UserChatMetadata>, Error>> {
+        Box::pin(UserChatMetadataRepository::create_many_in(
+            self,
+            uow,
+            metadata_list,
+        ))
+    }
+
+    fn transfer_ownership_in<'a>(
+        &'a self,
+        uow: &'a mut UnitOfWork,
+        from_user_id: &'a i32,
+        to_user_id: &'a i32,
+        chat_id: &'a i32,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(UserChatMetadataRepository::transfer_ownership_in(
+            self,
+            uow,
+            from_user_id,
+            to_user_id,
+            chat_id,
+        ))
+    }
+
+    fn delete_in<'a>(
+        &'a self,
+        uow: &'a mut UnitOfWork,
+        id: &'a UserChatKey,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(UserChatMetadataRepository::delete_in(self, uow, id))
+    }
+
+    fn count_by_chat_id<'a>(&'a self, chat_id: &'a i32) -> BoxFuture<'a, Result<i64, Error>> {
+        Box::pin(UserChatMetadataRepository::count_by_chat_id(self, chat_id))
+    }
+
+    fn share_any_chat<'a>(
+        &'a self,
+        user_id: &'a i32,
+        other_user_id: &'a i32,
+    ) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(UserChatMetadataRepository::share_any_chat(
+            self,
+            user_id,
+            other_user_id,
+        ))
+    }
+
+    fn set_pinned<'a>(
+        &'a self,
+        user_id: &'a i32,
+        chat_id: &'a i32,
+        pinned_at: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(UserChatMetadataRepository::set_pinned(
+            self, user_id, chat_id, pinned_at,
+        ))
+    }
+
+    fn set_custom_role<'a>(
+        &'a self,
+        user_id: &'a i32,
+        chat_id: &'a i32,
+        role_id: Option<i32>,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(UserChatMetadataRepository::set_custom_role(
+            self, user_id, chat_id, role_id,
+        ))
+    }
+
+    fn set_muted_until<'a>(
+        &'a self,
+        user_id: &'a i32,
+        chat_id: &'a i32,
+        muted_until: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(UserChatMetadataRepository::set_muted_until(
+            self,
+            user_id,
+            chat_id,
+            muted_until,
+        ))
+    }
+
+    fn advance_received_until<'a>(
+        &'a self,
+        user_id: &'a i32,
+        received: &'a [DeliveredBatch],
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(UserChatMetadataRepository::advance_received_until(
+            self, user_id, received,
+        ))
+    }
+
+    fn update_user_role<'a>(
+        &'a self,
+        user_id: &'a i32,
+        chat_id: &'a i32,
+        new_role: &'a UserRole,
+        expected_version: &'a i32,
+    ) -> BoxFuture<'a, Result<UserChatMetadata, Error>> {
+        Box::pin(UserChatMetadataRepository::update_user_role(
+            self,
+            user_id,
+            chat_id,
+            new_role,
+            expected_version,
+        ))
+    }
+
+    fn create<'a>(
+        &'a self,
+        data: &'a CreateUserChatMetadataDTO,
+    ) -> BoxFuture<'a, Result<UserChatMetadata, Error>> {
+        Box::pin(Create::create(self, data))
+    }
+
+    fn read<'a>(
+        &'a self,
+        id: &'a UserChatKey,
+    ) -> BoxFuture<'a, Result<Option<UserChatMetadata>, Error>> {
+        Box::pin(Read::read(self, id))
+    }
+
+    fn read_many<'a>(
+        &'a self,
+        filter: &'a MemberFilter,
+        page: &'a Page,
+    ) -> BoxFuture<'a, Result<Vec<UserChatMetadata>, Error>> {
+        Box::pin(ReadMany::read_many(self, filter, page))
+    }
+
+    fn update<'a>(
+        &'a self,
+        id: &'a UserChatKey,
+        data: &'a UpdateUserChatMetadataDTO,
+    ) -> BoxFuture<'a, Result<UserChatMetadata, Error>> {
+        Box::pin(Update::update(self, id, data))
+    }
+
+    fn delete<'a>(&'a self, id: &'a UserChatKey) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(Delete::delete(self, id))
+    }
+}
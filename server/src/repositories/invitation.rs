@@ -1,39 +1,90 @@
 //! InvitationRepository - Repository per la gestione degli inviti
 
-use super::{Create, Delete, Read, Update};
+use super::query_log::timed;
+#[cfg(feature = "sqlite")]
+use super::sqlite;
+use super::{Create, DbPool, Delete, Page, Read, ReadMany, UnitOfWork, Update};
 use crate::dtos::{CreateInvitationDTO, UpdateInvitationDTO};
 use crate::entities::{Invitation, InvitationStatus};
-use sqlx::{Error, MySqlPool};
+use chrono::{DateTime, Utc};
+use sqlx::Error;
 use tracing::{debug, info, instrument};
 
 //INVITATION REPOSITORY
 pub struct InvitationRepository {
-    connection_pool: MySqlPool,
+    connection_pool: DbPool,
 }
 
 impl InvitationRepository {
-    pub fn new(connection_pool: MySqlPool) -> Self {
-        Self { connection_pool }
+    pub fn new(connection_pool: impl Into<DbPool>) -> Self {
+        Self {
+            connection_pool: connection_pool.into(),
+        }
     }
 
-    /// Get all pending invitations for a specific user
-    pub async fn find_many_by_user_id(&self, user_id: &i32) -> Result<Vec<Invitation>, Error> {
+    /// Get the invitations received by a user from `since` (included) in any state, oldest first
+    pub async fn find_received_since(
+        &self,
+        user_id: &i32,
+        since: &DateTime<Utc>,
+    ) -> Result<Vec<Invitation>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::invitation::find_received_since(pool, user_id, since).await;
+        }
         let invitations = sqlx::query_as!(
             Invitation,
             r#"
-            SELECT 
+            SELECT
                 invite_id,
                 target_chat_id,
                 invited_id,
                 invitee_id,
+                note,
                 state as "state: InvitationStatus",
                 created_at
-            FROM invitations 
-            WHERE invited_id = ? AND state = 'PENDING'
+            FROM invitations
+            WHERE invited_id = ? AND created_at >= ?
+            ORDER BY created_at ASC, invite_id ASC
+            "#,
+            user_id,
+            since
+        )
+        .fetch_all(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(invitations)
+    }
+
+    /// Get the invitations sent by a user and answered from `since` (included), oldest first
+    pub async fn find_answered_since(
+        &self,
+        inviter_id: &i32,
+        since: &DateTime<Utc>,
+    ) -> Result<Vec<Invitation>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::invitation::find_answered_since(pool, inviter_id, since).await;
+        }
+        let invitations = sqlx::query_as!(
+            Invitation,
+            r#"
+            SELECT
+                invite_id,
+                target_chat_id,
+                invited_id,
+                invitee_id,
+                note,
+                state as "state: InvitationStatus",
+                created_at
+            FROM invitations
+            WHERE invitee_id = ? AND updated_at >= ?
+            ORDER BY updated_at ASC, invite_id ASC
             "#,
-            user_id
+            inviter_id,
+            since
         )
-        .fetch_all(&self.connection_pool)
+        .fetch_all(timed(self.connection_pool.mysql()))
         .await?;
 
         Ok(invitations)
@@ -45,16 +96,119 @@ impl InvitationRepository {
         user_id: &i32,
         chat_id: &i32,
     ) -> Result<bool, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::invitation::has_pending_invitation(pool, user_id, chat_id).await;
+        }
         let count = sqlx::query!(
             "SELECT COUNT(*) as count FROM invitations WHERE invited_id = ? AND target_chat_id = ? AND state = 'PENDING'",
             user_id,
             chat_id
         )
-        .fetch_one(&self.connection_pool)
+        .fetch_one(timed(self.connection_pool.mysql()))
         .await?;
 
         Ok(count.count > 0)
     }
+
+    /// Record the answer to a pending invitation as part of a unit of work
+    ///
+    /// Fails with `RowNotFound` if the invitation is no longer pending, so two concurrent
+    /// answers cannot both take effect.
+    #[instrument(skip(self, uow), fields(invite_id = %invite_id, state = ?state))]
+    pub async fn answer_in(
+        &self,
+        uow: &mut UnitOfWork,
+        invite_id: &i32,
+        state: &InvitationStatus,
+    ) -> Result<(), Error> {
+        debug!("Answering invitation in unit of work");
+        #[cfg(feature = "sqlite")]
+        if let Some(conn) = uow.sqlite_conn() {
+            if sqlite::invitation::answer(conn, invite_id, state).await? == 0 {
+                return Err(Error::RowNotFound);
+            }
+            return Ok(());
+        }
+        let result = sqlx::query!(
+            "UPDATE invitations SET state = ?, updated_at = ? WHERE invite_id = ? AND state = 'PENDING'",
+            state,
+            Utc::now(),
+            invite_id
+        )
+        .execute(timed(uow.conn()))
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(Error::RowNotFound);
+        }
+        Ok(())
+    }
+}
+
+/// Filter for invitation lists; invitations are returned newest first
+#[derive(Debug, Clone, Default)]
+pub struct InvitationFilter {
+    /// Only the invitations received by this user
+    pub invited_id: Option<i32>,
+    /// Only the invitations sent by this user
+    pub inviter_id: Option<i32>,
+    pub state: Option<InvitationStatus>,
+}
+
+impl InvitationFilter {
+    /// Pending invitations received by the user
+    pub fn pending_for(user_id: i32) -> Self {
+        Self {
+            invited_id: Some(user_id),
+            state: Some(InvitationStatus::Pending),
+            ..Self::default()
+        }
+    }
+}
+
+impl ReadMany<Invitation, InvitationFilter> for InvitationRepository {
+    async fn read_many(
+        &self,
+        filter: &InvitationFilter,
+        page: &Page,
+    ) -> Result<Vec<Invitation>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::invitation::read_many(pool, filter, page).await;
+        }
+        let invitations = sqlx::query_as!(
+            Invitation,
+            r#"
+            SELECT
+                invite_id,
+                target_chat_id,
+                invited_id,
+                invitee_id,
+                note,
+                state as "state: InvitationStatus",
+                created_at
+            FROM invitations
+            WHERE (? IS NULL OR invited_id = ?)
+              AND (? IS NULL OR invitee_id = ?)
+              AND (? IS NULL OR state = ?)
+            ORDER BY created_at DESC, invite_id DESC
+            LIMIT ? OFFSET ?
+            "#,
+            filter.invited_id,
+            filter.invited_id,
+            filter.inviter_id,
+            filter.inviter_id,
+            filter.state.as_ref(),
+            filter.state.as_ref(),
+            page.limit,
+            page.offset
+        )
+        .fetch_all(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(invitations)
+    }
 }
 
 impl Create<Invitation, CreateInvitationDTO> for InvitationRepository {
@@ -66,18 +220,34 @@ impl Create<Invitation, CreateInvitationDTO> for InvitationRepository {
         let now = chrono::Utc::now();
         let state = InvitationStatus::Pending; // default state
 
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            let new_id = sqlite::invitation::insert(pool, data, &state, now).await?;
+            info!("Invitation created with id {}", new_id);
+            return Ok(Invitation {
+                invite_id: new_id,
+                target_chat_id: data.target_chat_id,
+                invited_id: data.invited_id,
+                invitee_id: data.invitee_id,
+                note: data.note.clone(),
+                state,
+                created_at: now,
+            });
+        }
+
         let result = sqlx::query!(
             r#"
-            INSERT INTO invitations (target_chat_id, invited_id, invitee_id, state, created_at) 
-            VALUES (?, ?, ?, ?, ?)
+            INSERT INTO invitations (target_chat_id, invited_id, invitee_id, note, state, created_at) 
+            VALUES (?, ?, ?, ?, ?, ?)
             "#,
             data.target_chat_id,
             data.invited_id,
             data.invitee_id,
+            data.note,
             state,
             now
         )
-        .execute(&self.connection_pool)
+        .execute(timed(self.connection_pool.mysql()))
         .await?;
 
         // Get the last inserted ID
@@ -91,6 +261,7 @@ impl Create<Invitation, CreateInvitationDTO> for InvitationRepository {
             target_chat_id: data.target_chat_id,
             invited_id: data.invited_id,
             invitee_id: data.invitee_id,
+            note: data.note.clone(),
             state,
             created_at: now,
         })
@@ -99,6 +270,10 @@ impl Create<Invitation, CreateInvitationDTO> for InvitationRepository {
 
 impl Read<Invitation, i32> for InvitationRepository {
     async fn read(&self, id: &i32) -> Result<Option<Invitation>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::invitation::read(pool, id).await;
+        }
         let invitation = sqlx::query_as!(
             Invitation,
             r#"
@@ -107,6 +282,7 @@ impl Read<Invitation, i32> for InvitationRepository {
                 target_chat_id,
                 invited_id,
                 invitee_id,
+                note,
                 state as "state: InvitationStatus",
                 created_at
             FROM invitations 
@@ -114,7 +290,7 @@ impl Read<Invitation, i32> for InvitationRepository {
             "#,
             id
         )
-        .fetch_optional(&self.connection_pool)
+        .fetch_optional(timed(self.connection_pool.mysql()))
         .await?;
 
         Ok(invitation)
@@ -134,13 +310,20 @@ impl Update<Invitation, UpdateInvitationDTO, i32> for InvitationRepository {
             return Ok(current_invitation);
         }
 
-        // Update invitation state
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            sqlite::invitation::update_state(pool, id, &data.state).await?;
+            return self.read(id).await?.ok_or_else(|| sqlx::Error::RowNotFound);
+        }
+
+        // Update invitation state, recording when it was answered
         sqlx::query!(
-            "UPDATE invitations SET state = ? WHERE invite_id = ?",
+            "UPDATE invitations SET state = ?, updated_at = ? WHERE invite_id = ?",
             data.state,
+            Utc::now(),
             id
         )
-        .execute(&self.connection_pool)
+        .execute(timed(self.connection_pool.mysql()))
         .await?;
 
         // Fetch and return the updated invitation
@@ -150,8 +333,12 @@ impl Update<Invitation, UpdateInvitationDTO, i32> for InvitationRepository {
 
 impl Delete<i32> for InvitationRepository {
     async fn delete(&self, id: &i32) -> Result<(), Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::invitation::delete(pool, id).await;
+        }
         sqlx::query!("DELETE FROM invitations WHERE invite_id = ?", id)
-            .execute(&self.connection_pool)
+            .execute(timed(self.connection_pool.mysql()))
             .await?;
 
         Ok(())
@@ -164,17 +351,19 @@ mod tests {
     use sqlx::MySqlPool;
 
     // ============================================================================
-    // Tests for find_many_by_user_id method
+    // Tests for read_many: pending invitations received by a user
     // ============================================================================
 
-    /// Test: verifica che find_many_by_user_id restituisca solo inviti PENDING
+    /// Test: verifica che read_many con pending_for restituisca solo inviti PENDING
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats", "invitations")))]
-    async fn test_find_many_by_user_id_returns_only_pending(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_read_many_pending_returns_only_pending(pool: MySqlPool) -> sqlx::Result<()> {
         let repo = InvitationRepository::new(pool.clone());
 
         // Assumendo che il fixture "invitations" contenga inviti con invited_id = 1
         let user_id = 1;
-        let invitations = repo.find_many_by_user_id(&user_id).await?;
+        let invitations = repo
+            .read_many(&InvitationFilter::pending_for(user_id), &Page::ALL)
+            .await?;
 
         // Verifica che tutti gli inviti restituiti siano PENDING
         for inv in &invitations {
@@ -187,13 +376,15 @@ mod tests {
 
     /// Test: verifica che restituisca un array vuoto per utenti senza inviti
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
-    async fn test_find_many_by_user_id_returns_empty_when_no_invitations(
+    async fn test_read_many_pending_returns_empty_when_no_invitations(
         pool: MySqlPool,
     ) -> sqlx::Result<()> {
         let repo = InvitationRepository::new(pool.clone());
 
         let user_id = 999; // utente senza inviti
-        let invitations = repo.find_many_by_user_id(&user_id).await?;
+        let invitations = repo
+            .read_many(&InvitationFilter::pending_for(user_id), &Page::ALL)
+            .await?;
 
         assert!(invitations.is_empty());
         Ok(())
@@ -201,7 +392,7 @@ mod tests {
 
     /// Test: verifica che gli inviti ACCEPTED/REJECTED siano esclusi
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats", "invitations")))]
-    async fn test_find_many_by_user_id_excludes_non_pending_invitations(
+    async fn test_read_many_pending_excludes_non_pending_invitations(
         pool: MySqlPool,
     ) -> sqlx::Result<()> {
         let repo = InvitationRepository::new(pool.clone());
@@ -212,12 +403,15 @@ mod tests {
             target_chat_id: 1,
             invited_id: user_id,
             invitee_id: 3,
+            note: None,
         };
 
         let created = repo.create(&invite).await?;
 
         // Verifica che l'invito PENDING venga restituito
-        let invitations_pending = repo.find_many_by_user_id(&user_id).await?;
+        let invitations_pending = repo
+            .read_many(&InvitationFilter::pending_for(user_id), &Page::ALL)
+            .await?;
         assert!(
             invitations_pending
                 .iter()
@@ -233,7 +427,9 @@ mod tests {
         .await?;
 
         // Verifica che l'invito ACCEPTED non venga restituito
-        let invitations_after = repo.find_many_by_user_id(&user_id).await?;
+        let invitations_after = repo
+            .read_many(&InvitationFilter::pending_for(user_id), &Page::ALL)
+            .await?;
         assert!(
             !invitations_after
                 .iter()
@@ -245,7 +441,7 @@ mod tests {
 
     /// Test: verifica il comportamento CASCADE quando viene eliminata una chat
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats", "invitations")))]
-    async fn test_find_many_by_user_id_cascade_on_chat_delete(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_read_many_pending_cascade_on_chat_delete(pool: MySqlPool) -> sqlx::Result<()> {
         let repo = InvitationRepository::new(pool.clone());
 
         let user_id = 1;
@@ -256,12 +452,15 @@ mod tests {
             target_chat_id: chat_id,
             invited_id: user_id,
             invitee_id: 2,
+            note: None,
         };
 
         let created = repo.create(&invite).await?;
 
         // Verifica che l'invito esista
-        let invitations_before = repo.find_many_by_user_id(&user_id).await?;
+        let invitations_before = repo
+            .read_many(&InvitationFilter::pending_for(user_id), &Page::ALL)
+            .await?;
         assert!(
             invitations_before
                 .iter()
@@ -274,7 +473,9 @@ mod tests {
             .await?;
 
         // Verifica che gli inviti per quella chat siano stati eliminati in cascata
-        let invitations_after = repo.find_many_by_user_id(&user_id).await?;
+        let invitations_after = repo
+            .read_many(&InvitationFilter::pending_for(user_id), &Page::ALL)
+            .await?;
         assert!(
             !invitations_after
                 .iter()
@@ -286,9 +487,7 @@ mod tests {
 
     /// Test: verifica il comportamento CASCADE quando viene eliminato l'utente invitante (invitee)
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats", "invitations")))]
-    async fn test_find_many_by_user_id_cascade_on_inviter_delete(
-        pool: MySqlPool,
-    ) -> sqlx::Result<()> {
+    async fn test_read_many_pending_cascade_on_inviter_delete(pool: MySqlPool) -> sqlx::Result<()> {
         let repo = InvitationRepository::new(pool.clone());
 
         let inviter_id = 1;
@@ -299,12 +498,15 @@ mod tests {
             target_chat_id: 1,
             invited_id,
             invitee_id: inviter_id,
+            note: None,
         };
 
         let created = repo.create(&invite).await?;
 
         // Verifica che l'invito esista
-        let invitations_before = repo.find_many_by_user_id(&invited_id).await?;
+        let invitations_before = repo
+            .read_many(&InvitationFilter::pending_for(invited_id), &Page::ALL)
+            .await?;
         assert!(
             invitations_before
                 .iter()
@@ -317,7 +519,9 @@ mod tests {
             .await?;
 
         // Verifica che gli inviti da quell'utente siano stati eliminati in cascata
-        let invitations_after = repo.find_many_by_user_id(&invited_id).await?;
+        let invitations_after = repo
+            .read_many(&InvitationFilter::pending_for(invited_id), &Page::ALL)
+            .await?;
         assert!(
             !invitations_after
                 .iter()
@@ -329,9 +533,7 @@ mod tests {
 
     /// Test: verifica il comportamento CASCADE quando viene eliminato l'utente invitato (invited)
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats", "invitations")))]
-    async fn test_find_many_by_user_id_cascade_on_invited_delete(
-        pool: MySqlPool,
-    ) -> sqlx::Result<()> {
+    async fn test_read_many_pending_cascade_on_invited_delete(pool: MySqlPool) -> sqlx::Result<()> {
         let repo = InvitationRepository::new(pool.clone());
 
         let inviter_id = 1;
@@ -342,6 +544,7 @@ mod tests {
             target_chat_id: 1,
             invited_id,
             invitee_id: inviter_id,
+            note: None,
         };
 
         repo.create(&invite).await?;
@@ -353,7 +556,9 @@ mod tests {
 
         // Verifica che gli inviti per quell'utente siano stati eliminati in cascata
         // (non dovrebbe esserci nessun invito restituito)
-        let invitations_after = repo.find_many_by_user_id(&invited_id).await?;
+        let invitations_after = repo
+            .read_many(&InvitationFilter::pending_for(invited_id), &Page::ALL)
+            .await?;
         assert!(invitations_after.is_empty());
 
         Ok(())
@@ -361,7 +566,7 @@ mod tests {
 
     /// Test: verifica che restituisca correttamente più inviti PENDING per lo stesso utente
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats", "invitations")))]
-    async fn test_find_many_by_user_id_multiple_pending_invitations(
+    async fn test_read_many_pending_multiple_pending_invitations(
         pool: MySqlPool,
     ) -> sqlx::Result<()> {
         let repo = InvitationRepository::new(pool.clone());
@@ -375,12 +580,15 @@ mod tests {
                 target_chat_id: chat_id,
                 invited_id: user_id,
                 invitee_id: 1,
+                note: None,
             };
             let created = repo.create(&invite).await?;
             created_ids.push(created.invite_id);
         }
 
-        let invitations = repo.find_many_by_user_id(&user_id).await?;
+        let invitations = repo
+            .read_many(&InvitationFilter::pending_for(user_id), &Page::ALL)
+            .await?;
 
         // Verifica che tutti gli inviti creati siano restituiti
         for created_id in &created_ids {
@@ -399,6 +607,77 @@ mod tests {
         Ok(())
     }
 
+    // ============================================================================
+    // Tests for read_many: invitations sent by a user
+    // ============================================================================
+
+    /// Test: verifica che restituisca solo gli inviti inviati dall'utente, filtrati per stato
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats", "invitations")))]
+    async fn test_read_many_sent_filters_by_state(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = InvitationRepository::new(pool.clone());
+
+        // Dal fixture: Alice (1) ha inviato solo l'invito 2 (ACCEPTED)
+        let sent_by_alice = |state| InvitationFilter {
+            inviter_id: Some(1),
+            state,
+            ..InvitationFilter::default()
+        };
+        let all = repo.read_many(&sent_by_alice(None), &Page::ALL).await?;
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].invite_id, 2);
+        assert_eq!(all[0].invitee_id, 1);
+
+        let accepted = repo
+            .read_many(&sent_by_alice(Some(InvitationStatus::Accepted)), &Page::ALL)
+            .await?;
+        assert_eq!(accepted.len(), 1);
+
+        let pending = repo
+            .read_many(&sent_by_alice(Some(InvitationStatus::Pending)), &Page::ALL)
+            .await?;
+        assert!(pending.is_empty());
+
+        Ok(())
+    }
+
+    // ============================================================================
+    // Tests for find_received_since and find_answered_since methods
+    // ============================================================================
+
+    /// Test: restituiscono solo gli inviti ricevuti o con una risposta dopo la data indicata
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats", "invitations")))]
+    async fn test_find_changes_since(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = InvitationRepository::new(pool.clone());
+        let since = Utc::now() - chrono::Duration::minutes(90);
+
+        // Dal fixture: solo l'invito 1 a Charlie è dell'ultima ora e mezza
+        let received = repo.find_received_since(&3, &since).await?;
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].invite_id, 1);
+        assert!(repo.find_received_since(&1, &since).await?.is_empty());
+
+        // Gli inviti delle fixture non hanno ancora avuto una risposta registrata
+        assert!(repo.find_answered_since(&2, &since).await?.is_empty());
+
+        let before_answer = Utc::now() - chrono::Duration::seconds(1);
+        repo.update(
+            &1,
+            &UpdateInvitationDTO {
+                state: Some(InvitationStatus::Rejected),
+            },
+        )
+        .await?;
+
+        let answered = repo.find_answered_since(&2, &before_answer).await?;
+        assert_eq!(answered.len(), 1);
+        assert_eq!(answered[0].state, InvitationStatus::Rejected);
+        // la risposta non compare per chi ha chiesto solo le modifiche successive
+        let later = Utc::now() + chrono::Duration::seconds(1);
+        assert!(repo.find_answered_since(&2, &later).await?.is_empty());
+
+        Ok(())
+    }
+
     // ============================================================================
     // Tests for has_pending_invitation method
     // ============================================================================
@@ -496,6 +775,7 @@ mod tests {
             target_chat_id: chat_id,
             invited_id: user_id,
             invitee_id: 1,
+            note: None,
         };
 
         let created = repo.create(&invite).await?;
@@ -687,6 +967,7 @@ mod tests {
             target_chat_id: chat_id,
             invited_id: user_id,
             invitee_id: 1,
+            note: None,
         };
 
         let result = repo.create(&duplicate_invite).await;
@@ -714,6 +995,7 @@ mod tests {
             target_chat_id: 1,
             invited_id: 2,
             invitee_id: 1,
+            note: None,
         };
 
         let created = repo.create(&invite_dto).await?;
@@ -728,6 +1010,27 @@ mod tests {
         Ok(())
     }
 
+    /// Test: verifica che la nota dell'inviter venga salvata e riletta
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_create_invitation_with_note(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = InvitationRepository::new(pool.clone());
+
+        let invite_dto = CreateInvitationDTO {
+            target_chat_id: 1,
+            invited_id: 2,
+            invitee_id: 1,
+            note: Some("Join us!".to_string()),
+        };
+
+        let created = repo.create(&invite_dto).await?;
+        let read = repo.read(&created.invite_id).await?.unwrap();
+
+        assert_eq!(created.note.as_deref(), Some("Join us!"));
+        assert_eq!(read.note.as_deref(), Some("Join us!"));
+
+        Ok(())
+    }
+
     /// Test: verifica che create fallisca con FK violation per chat inesistente
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
     async fn test_create_invitation_fails_with_invalid_chat(pool: MySqlPool) -> sqlx::Result<()> {
@@ -737,6 +1040,7 @@ mod tests {
             target_chat_id: 9999, // chat inesistente
             invited_id: 2,
             invitee_id: 1,
+            note: None,
         };
 
         let result = repo.create(&invite_dto).await;
@@ -760,6 +1064,7 @@ mod tests {
             target_chat_id: 1,
             invited_id: 9999, // utente inesistente
             invitee_id: 1,
+            note: None,
         };
 
         let result = repo.create(&invite_dto).await;
@@ -783,6 +1088,7 @@ mod tests {
             target_chat_id: 1,
             invited_id: 2,
             invitee_id: 9999, // utente inesistente
+            note: None,
         };
 
         let result = repo.create(&invite_dto).await;
@@ -805,6 +1111,7 @@ mod tests {
             target_chat_id: 1,
             invited_id: 3,
             invitee_id: 1,
+            note: None,
         };
 
         let result = repo.create(&duplicate_dto).await;
@@ -830,6 +1137,7 @@ mod tests {
                 target_chat_id: chat_id,
                 invited_id: user_id,
                 invitee_id: 1,
+                note: None,
             };
 
             let created = repo.create(&invite_dto).await?;
@@ -857,6 +1165,7 @@ mod tests {
             target_chat_id: 1,
             invited_id: 3,
             invitee_id: 2,
+            note: None,
         };
 
         let created = repo.create(&new_invite).await?;
@@ -916,6 +1225,7 @@ mod tests {
             target_chat_id: 1,
             invited_id: 2,
             invitee_id: 1,
+            note: None,
         };
 
         let created = repo.create(&invite_dto).await?;
@@ -1209,6 +1519,7 @@ mod tests {
             target_chat_id: 2,
             invited_id: 2,
             invitee_id: 1,
+            note: None,
         };
 
         let created = repo.create(&invite_dto).await?;
@@ -1298,6 +1609,7 @@ mod tests {
             target_chat_id: original.target_chat_id,
             invited_id: original.invited_id,
             invitee_id: original.invitee_id,
+            note: None,
         };
 
         let recreated = repo.create(&recreate_dto).await?;
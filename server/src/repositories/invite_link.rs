@@ -0,0 +1,198 @@
+//! InviteLinkRepository - Repository per i link di invito alle chat di gruppo
+
+use super::query_log::timed;
+#[cfg(feature = "sqlite")]
+use super::sqlite;
+use super::{Create, DbPool, Delete, Page, Read, ReadMany};
+use crate::dtos::NewInviteLinkDTO;
+use crate::entities::InviteLink;
+use chrono::Utc;
+use sqlx::Error;
+use tracing::{debug, info, instrument};
+
+//INVITE LINK REPOSITORY
+pub struct InviteLinkRepository {
+    connection_pool: DbPool,
+}
+
+impl InviteLinkRepository {
+    pub fn new(connection_pool: impl Into<DbPool>) -> Self {
+        Self {
+            connection_pool: connection_pool.into(),
+        }
+    }
+
+    /// Cerca un link di invito tramite l'hash del suo token, anche se scaduto
+    pub async fn find_by_token_hash(&self, token_hash: &str) -> Result<Option<InviteLink>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::invite_link::find_by_token_hash(pool, token_hash).await;
+        }
+        let link = sqlx::query_as!(
+            InviteLink,
+            r#"
+            SELECT link_id, chat_id, token_hash, created_by, created_at, expires_at
+            FROM invite_links
+            WHERE token_hash = ?
+            "#,
+            token_hash
+        )
+        .fetch_optional(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(link)
+    }
+}
+
+/// Filtro dei link di invito, restituiti in ordine di creazione
+#[derive(Debug, Clone)]
+pub struct InviteLinkFilter {
+    pub chat_id: i32,
+}
+
+impl ReadMany<InviteLink, InviteLinkFilter> for InviteLinkRepository {
+    async fn read_many(
+        &self,
+        filter: &InviteLinkFilter,
+        page: &Page,
+    ) -> Result<Vec<InviteLink>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::invite_link::read_many(pool, filter, page).await;
+        }
+        let links = sqlx::query_as!(
+            InviteLink,
+            r#"
+            SELECT link_id, chat_id, token_hash, created_by, created_at, expires_at
+            FROM invite_links
+            WHERE chat_id = ?
+            ORDER BY link_id
+            LIMIT ? OFFSET ?
+            "#,
+            filter.chat_id,
+            page.limit,
+            page.offset
+        )
+        .fetch_all(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(links)
+    }
+}
+
+impl Create<InviteLink, NewInviteLinkDTO> for InviteLinkRepository {
+    #[instrument(skip(self, data), fields(chat_id = %data.chat_id, created_by = %data.created_by))]
+    async fn create(&self, data: &NewInviteLinkDTO) -> Result<InviteLink, Error> {
+        debug!("Creating new invite link");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::invite_link::create(pool, data).await;
+        }
+        let now = Utc::now();
+
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO invite_links (chat_id, token_hash, created_by, created_at, expires_at)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+            data.chat_id,
+            data.token_hash,
+            data.created_by,
+            now,
+            data.expires_at
+        )
+        .execute(timed(self.connection_pool.mysql()))
+        .await?;
+
+        let new_id = result.last_insert_id() as i32;
+
+        info!("Invite link created with id {}", new_id);
+
+        Ok(InviteLink {
+            link_id: new_id,
+            chat_id: data.chat_id,
+            token_hash: data.token_hash.clone(),
+            created_by: Some(data.created_by),
+            created_at: now,
+            expires_at: data.expires_at,
+        })
+    }
+}
+
+impl Read<InviteLink, i32> for InviteLinkRepository {
+    async fn read(&self, id: &i32) -> Result<Option<InviteLink>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::invite_link::read(pool, id).await;
+        }
+        let link = sqlx::query_as!(
+            InviteLink,
+            r#"
+            SELECT link_id, chat_id, token_hash, created_by, created_at, expires_at
+            FROM invite_links
+            WHERE link_id = ?
+            "#,
+            id
+        )
+        .fetch_optional(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(link)
+    }
+}
+
+impl Delete<i32> for InviteLinkRepository {
+    async fn delete(&self, id: &i32) -> Result<(), Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::invite_link::delete(pool, id).await;
+        }
+        sqlx::query!("DELETE FROM invite_links WHERE link_id = ?", id)
+            .execute(timed(self.connection_pool.mysql()))
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::MySqlPool;
+
+    /// Test: il link è ritrovato tramite l'hash del token e tra quelli della sua chat
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_create_and_find(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = InviteLinkRepository::new(pool);
+
+        let created = repo
+            .create(&NewInviteLinkDTO {
+                chat_id: 3,
+                token_hash: "a".repeat(64),
+                created_by: 1,
+                expires_at: None,
+            })
+            .await?;
+
+        let found = repo.find_by_token_hash(&"a".repeat(64)).await?.unwrap();
+        assert_eq!(found.link_id, created.link_id);
+        assert_eq!(found.created_by, Some(1));
+        assert!(found.expires_at.is_none());
+        assert!(repo.find_by_token_hash(&"b".repeat(64)).await?.is_none());
+
+        let listed = repo
+            .read_many(&InviteLinkFilter { chat_id: 3 }, &Page::ALL)
+            .await?;
+        assert_eq!(listed.len(), 1);
+        assert!(
+            repo.read_many(&InviteLinkFilter { chat_id: 1 }, &Page::ALL)
+                .await?
+                .is_empty()
+        );
+
+        repo.delete(&created.link_id).await?;
+        assert!(repo.read(&created.link_id).await?.is_none());
+
+        Ok(())
+    }
+}
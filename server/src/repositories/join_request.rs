@@ -0,0 +1,375 @@
+//! JoinRequestRepository - Repository per le richieste di accesso alle chat pubbliche
+
+use super::query_log::timed;
+#[cfg(feature = "sqlite")]
+use super::sqlite;
+use super::{Create, DbPool, Page, Read, ReadMany, UnitOfWork};
+use crate::dtos::CreateJoinRequestDTO;
+use crate::entities::{JoinRequest, JoinRequestStatus};
+use chrono::Utc;
+use sqlx::{Error, MySqlConnection};
+use tracing::{debug, info, instrument};
+
+//JOIN REQUEST REPOSITORY
+pub struct JoinRequestRepository {
+    connection_pool: DbPool,
+}
+
+impl JoinRequestRepository {
+    pub fn new(connection_pool: impl Into<DbPool>) -> Self {
+        Self {
+            connection_pool: connection_pool.into(),
+        }
+    }
+
+    /// Verifica se l'utente ha già una richiesta pending per la chat
+    pub async fn has_pending_request(&self, user_id: &i32, chat_id: &i32) -> Result<bool, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::join_request::has_pending_request(pool, user_id, chat_id).await;
+        }
+        let count = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM join_requests WHERE user_id = ? AND chat_id = ? AND state = 'PENDING'",
+            user_id,
+            chat_id
+        )
+        .fetch_one(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(count > 0)
+    }
+
+    /// Approva o rifiuta una richiesta pending e la ritorna aggiornata
+    #[instrument(skip(self), fields(request_id = %request_id, state = ?state, resolved_by = %resolved_by))]
+    pub async fn resolve(
+        &self,
+        request_id: &i32,
+        state: &JoinRequestStatus,
+        resolved_by: &i32,
+    ) -> Result<JoinRequest, Error> {
+        debug!("Resolving join request");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            let mut conn = pool.acquire().await?;
+            sqlite::join_request::update_state(&mut conn, request_id, state, resolved_by).await?;
+            return sqlite::join_request::select(&mut conn, request_id)
+                .await?
+                .ok_or_else(|| sqlx::Error::RowNotFound);
+        }
+        let mut conn = self.connection_pool.mysql().acquire().await?;
+        Self::update_state(&mut conn, request_id, state, resolved_by).await?;
+
+        Self::select(&mut conn, request_id)
+            .await?
+            .ok_or_else(|| sqlx::Error::RowNotFound)
+    }
+
+    /// Come `resolve`, all'interno di una unit of work
+    ///
+    /// Fallisce con `RowNotFound` se la richiesta non è più pending: due approvazioni
+    /// concorrenti non possono ammettere lo stesso utente due volte.
+    #[instrument(skip(self, uow), fields(request_id = %request_id, state = ?state, resolved_by = %resolved_by))]
+    pub async fn resolve_in(
+        &self,
+        uow: &mut UnitOfWork,
+        request_id: &i32,
+        state: &JoinRequestStatus,
+        resolved_by: &i32,
+    ) -> Result<JoinRequest, Error> {
+        debug!("Resolving join request in unit of work");
+        #[cfg(feature = "sqlite")]
+        if let Some(conn) = uow.sqlite_conn() {
+            if sqlite::join_request::update_state(conn, request_id, state, resolved_by).await? == 0
+            {
+                return Err(Error::RowNotFound);
+            }
+            return sqlite::join_request::select(conn, request_id)
+                .await?
+                .ok_or_else(|| sqlx::Error::RowNotFound);
+        }
+        if Self::update_state(uow.conn(), request_id, state, resolved_by).await? == 0 {
+            return Err(Error::RowNotFound);
+        }
+
+        Self::select(uow.conn(), request_id)
+            .await?
+            .ok_or_else(|| sqlx::Error::RowNotFound)
+    }
+
+    /// Crea una richiesta all'interno di una unit of work, vedi `create`
+    pub async fn create_in(
+        &self,
+        uow: &mut UnitOfWork,
+        data: &CreateJoinRequestDTO,
+    ) -> Result<JoinRequest, Error> {
+        #[cfg(feature = "sqlite")]
+        if let Some(conn) = uow.sqlite_conn() {
+            return sqlite::join_request::insert(conn, data).await;
+        }
+        Self::insert(uow.conn(), data).await
+    }
+
+    async fn update_state(
+        conn: &mut MySqlConnection,
+        request_id: &i32,
+        state: &JoinRequestStatus,
+        resolved_by: &i32,
+    ) -> Result<u64, Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE join_requests
+            SET state = ?, resolved_by = ?, resolved_at = ?
+            WHERE request_id = ? AND state = 'PENDING'
+            "#,
+            state,
+            resolved_by,
+            Utc::now(),
+            request_id
+        )
+        .execute(timed(conn))
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn insert(
+        conn: &mut MySqlConnection,
+        data: &CreateJoinRequestDTO,
+    ) -> Result<JoinRequest, Error> {
+        let now = Utc::now();
+        // le richieste approvate automaticamente nascono già risolte
+        let resolved_at = (data.state != JoinRequestStatus::Pending).then_some(now);
+
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO join_requests (chat_id, user_id, state, created_at, resolved_at)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+            data.chat_id,
+            data.user_id,
+            &data.state,
+            now,
+            resolved_at
+        )
+        .execute(timed(conn))
+        .await?;
+
+        let new_id = result.last_insert_id() as i32;
+
+        info!("Join request created with id {}", new_id);
+
+        Ok(JoinRequest {
+            request_id: new_id,
+            chat_id: data.chat_id,
+            user_id: data.user_id,
+            state: data.state.clone(),
+            created_at: now,
+            resolved_by: None,
+            resolved_at,
+        })
+    }
+
+    async fn select(conn: &mut MySqlConnection, id: &i32) -> Result<Option<JoinRequest>, Error> {
+        let request = sqlx::query_as!(
+            JoinRequest,
+            r#"
+            SELECT
+                request_id,
+                chat_id,
+                user_id,
+                state as "state: JoinRequestStatus",
+                created_at,
+                resolved_by,
+                resolved_at
+            FROM join_requests
+            WHERE request_id = ?
+            "#,
+            id
+        )
+        .fetch_optional(timed(conn))
+        .await?;
+
+        Ok(request)
+    }
+}
+
+impl Create<JoinRequest, CreateJoinRequestDTO> for JoinRequestRepository {
+    #[instrument(skip(self, data), fields(chat_id = %data.chat_id, user_id = %data.user_id, state = ?data.state))]
+    async fn create(&self, data: &CreateJoinRequestDTO) -> Result<JoinRequest, Error> {
+        debug!("Creating new join request");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::join_request::insert(&mut *pool.acquire().await?, data).await;
+        }
+        Self::insert(&mut *self.connection_pool.mysql().acquire().await?, data).await
+    }
+}
+
+/// Filtro delle richieste di accesso a una chat, restituite dalla più vecchia
+#[derive(Debug, Clone)]
+pub struct JoinRequestFilter {
+    pub chat_id: i32,
+    /// Solo le richieste in questo stato
+    pub state: Option<JoinRequestStatus>,
+}
+
+impl ReadMany<JoinRequest, JoinRequestFilter> for JoinRequestRepository {
+    async fn read_many(
+        &self,
+        filter: &JoinRequestFilter,
+        page: &Page,
+    ) -> Result<Vec<JoinRequest>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::join_request::read_many(pool, filter, page).await;
+        }
+        let requests = sqlx::query_as!(
+            JoinRequest,
+            r#"
+            SELECT
+                request_id,
+                chat_id,
+                user_id,
+                state as "state: JoinRequestStatus",
+                created_at,
+                resolved_by,
+                resolved_at
+            FROM join_requests
+            WHERE chat_id = ? AND (? IS NULL OR state = ?)
+            ORDER BY created_at, request_id
+            LIMIT ? OFFSET ?
+            "#,
+            filter.chat_id,
+            filter.state.as_ref(),
+            filter.state.as_ref(),
+            page.limit,
+            page.offset
+        )
+        .fetch_all(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(requests)
+    }
+}
+
+impl Read<JoinRequest, i32> for JoinRequestRepository {
+    async fn read(&self, id: &i32) -> Result<Option<JoinRequest>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::join_request::select(&mut *pool.acquire().await?, id).await;
+        }
+        Self::select(&mut *self.connection_pool.mysql().acquire().await?, id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::MySqlPool;
+
+    /// Test: una richiesta pending compare tra quelle della chat finché non viene risolta
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_create_and_resolve(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = JoinRequestRepository::new(pool);
+
+        let created = repo
+            .create(&CreateJoinRequestDTO {
+                chat_id: 3,
+                user_id: 2,
+                state: JoinRequestStatus::Pending,
+            })
+            .await?;
+        assert!(created.resolved_at.is_none());
+        assert!(repo.has_pending_request(&2, &3).await?);
+
+        let pending_filter = JoinRequestFilter {
+            chat_id: 3,
+            state: Some(JoinRequestStatus::Pending),
+        };
+        let pending = repo.read_many(&pending_filter, &Page::ALL).await?;
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].request_id, created.request_id);
+
+        let resolved = repo
+            .resolve(&created.request_id, &JoinRequestStatus::Denied, &1)
+            .await?;
+        assert_eq!(resolved.state, JoinRequestStatus::Denied);
+        assert_eq!(resolved.resolved_by, Some(1));
+        assert!(resolved.resolved_at.is_some());
+
+        assert!(!repo.has_pending_request(&2, &3).await?);
+        assert!(
+            repo.read_many(&pending_filter, &Page::ALL)
+                .await?
+                .is_empty()
+        );
+
+        Ok(())
+    }
+
+    /// Test: una richiesta già risolta non viene modificata di nuovo
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_resolve_only_pending(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = JoinRequestRepository::new(pool);
+
+        let created = repo
+            .create(&CreateJoinRequestDTO {
+                chat_id: 3,
+                user_id: 2,
+                state: JoinRequestStatus::Approved,
+            })
+            .await?;
+        assert!(created.resolved_at.is_some());
+
+        let read = repo
+            .resolve(&created.request_id, &JoinRequestStatus::Denied, &1)
+            .await?;
+        assert_eq!(read.state, JoinRequestStatus::Approved);
+        assert_eq!(read.resolved_by, None);
+
+        Ok(())
+    }
+
+    /// Test: nella unit of work una richiesta già decisa fa fallire la seconda decisione
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_resolve_in_rejects_second_decision(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = JoinRequestRepository::new(pool.clone());
+
+        let created = repo
+            .create(&CreateJoinRequestDTO {
+                chat_id: 3,
+                user_id: 2,
+                state: JoinRequestStatus::Pending,
+            })
+            .await?;
+
+        let mut uow = UnitOfWork::begin(&pool.clone().into(), None).await?;
+        let resolved = repo
+            .resolve_in(
+                &mut uow,
+                &created.request_id,
+                &JoinRequestStatus::Approved,
+                &1,
+            )
+            .await?;
+        assert_eq!(resolved.state, JoinRequestStatus::Approved);
+        uow.commit().await?;
+
+        let mut uow = UnitOfWork::begin(&pool.clone().into(), None).await?;
+        let second = repo
+            .resolve_in(
+                &mut uow,
+                &created.request_id,
+                &JoinRequestStatus::Denied,
+                &1,
+            )
+            .await;
+        assert!(matches!(second, Err(Error::RowNotFound)));
+        uow.rollback().await?;
+
+        let read = repo.read(&created.request_id).await?.unwrap();
+        assert_eq!(read.state, JoinRequestStatus::Approved);
+
+        Ok(())
+    }
+}
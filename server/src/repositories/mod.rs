@@ -59,21 +59,76 @@
 // ************************* MODULI REPOSITORY ************************* //
 
 // Dichiarazione dei sotto-moduli
+pub mod attachment;
+pub mod audit;
+pub mod banned_member;
+pub mod cached;
 pub mod chat;
+pub mod chat_role;
+pub mod data_export;
+pub mod db;
+pub mod draft;
+pub mod health;
+#[cfg(test)]
+pub mod in_memory;
+pub mod interfaces;
 pub mod invitation;
+pub mod invite_link;
+pub mod join_request;
 pub mod message;
+pub mod offline_queue;
+pub mod query_log;
+pub mod refresh_token;
+pub mod report;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
 pub mod traits;
+pub mod unit_of_work;
 pub mod user;
 pub mod user_chat_metadata;
+pub mod user_identity;
+pub mod user_keys;
+pub mod webhook;
 
 // Re-esportazione dei trait per facilitare l'import
-pub use traits::{Create, Delete, Read, Update};
+pub use traits::{Create, Delete, Page, Read, ReadMany, Update};
 
-// Note: ReadMany is exported but not yet used. It will be available when needed.
+// Interfacce dei repository conservati in AppState come trait object
+pub use interfaces::{ChatRepo, MessageRepo, UserChatMetadataRepo, UserRepo};
+
+// Decoratori con cache in memoria per i repository più letti
+pub use cached::{CachedUserChatMetadataRepo, CachedUserRepo, RepositoryCache};
+
+// Implementazioni in memoria per i test unitari senza database
+#[cfg(test)]
+pub use in_memory::{InMemoryUserChatMetadataRepo, InMemoryUserRepo};
+
+// Transazione condivisa tra più repository
+pub use unit_of_work::UnitOfWork;
+
+// Pool del backend scelto all'avvio (MySQL o SQLite)
+pub use db::DbPool;
 
 // Re-esportazione delle struct dei repository per facilitare l'import
-pub use chat::ChatRepository;
-pub use invitation::InvitationRepository;
-pub use message::MessageRepository;
-pub use user::UserRepository;
-pub use user_chat_metadata::UserChatMetadataRepository;
+pub use attachment::AttachmentRepository;
+pub use audit::{AuditFilter, AuditLogRepository};
+pub use banned_member::BannedMemberRepository;
+pub use chat::{ChatFilter, ChatRepository};
+pub use chat_role::{ChatRoleFilter, ChatRoleRepository};
+pub use data_export::DataExportRepository;
+pub use draft::DraftRepository;
+pub use health::HealthRepository;
+pub use invitation::{InvitationFilter, InvitationRepository};
+pub use invite_link::{InviteLinkFilter, InviteLinkRepository};
+pub use join_request::{JoinRequestFilter, JoinRequestRepository};
+pub use message::{MessageFilter, MessageRepository};
+pub use offline_queue::OfflineQueueRepository;
+pub use refresh_token::RefreshTokenRepository;
+pub use report::{ReportFilter, ReportRepository};
+pub use user::{UserFilter, UserRepository};
+pub use user_chat_metadata::{
+    DeliveredBatch, MemberFilter, UserChatMetadataRepository, is_version_conflict,
+};
+pub use user_identity::UserIdentityRepository;
+pub use user_keys::UserKeysRepository;
+pub use webhook::{WebhookFilter, WebhookRepository};
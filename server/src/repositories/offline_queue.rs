@@ -0,0 +1,254 @@
+//! OfflineQueueRepository - Repository per la coda di consegna degli utenti offline
+
+use super::DbPool;
+use super::query_log::timed;
+#[cfg(feature = "sqlite")]
+use super::sqlite;
+use crate::entities::Message;
+use chrono::{DateTime, Utc};
+use sqlx::Error;
+use tracing::{debug, instrument};
+
+//OFFLINE QUEUE REPOSITORY
+pub struct OfflineQueueRepository {
+    connection_pool: DbPool,
+}
+
+impl OfflineQueueRepository {
+    pub fn new(connection_pool: impl Into<DbPool>) -> Self {
+        Self {
+            connection_pool: connection_pool.into(),
+        }
+    }
+
+    /// Queue a message for the given recipients
+    ///
+    /// Queuing the same message twice for a user is a no-op.
+    ///
+    /// # Returns
+    /// Number of rows added to the queue
+    #[instrument(skip(self, user_ids), fields(recipients = user_ids.len()))]
+    pub async fn enqueue(&self, message_id: &i32, user_ids: &[i32]) -> Result<u64, Error> {
+        if user_ids.is_empty() {
+            return Ok(0);
+        }
+
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::offline_queue::enqueue(pool, message_id, user_ids).await;
+        }
+
+        let now = Utc::now();
+        let mut query_builder = sqlx::QueryBuilder::new(
+            "INSERT IGNORE INTO offline_queue (user_id, message_id, enqueued_at) ",
+        );
+        query_builder.push_values(user_ids, |mut row, user_id| {
+            row.push_bind(user_id).push_bind(message_id).push_bind(now);
+        });
+
+        let result = query_builder
+            .build()
+            .execute(timed(self.connection_pool.mysql()))
+            .await?;
+        debug!(
+            "Queued message {} for {} users",
+            message_id,
+            result.rows_affected()
+        );
+        Ok(result.rows_affected())
+    }
+
+    /// Undelivered messages queued for a user, oldest first
+    ///
+    /// Messages of chats the user has left, or that are no longer visible to them
+    /// (`messages_visible_from`), are skipped.
+    ///
+    /// # Arguments
+    /// * `user_id` - The recipient
+    /// * `after_message_id` - Only messages with a greater ID are returned (0 for the first page)
+    /// * `limit` - Maximum number of messages to return
+    #[instrument(skip(self))]
+    pub async fn find_pending(
+        &self,
+        user_id: &i32,
+        after_message_id: &i32,
+        limit: i64,
+    ) -> Result<Vec<Message>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::offline_queue::find_pending(pool, user_id, after_message_id, limit)
+                .await;
+        }
+        let messages = sqlx::query_as!(
+            Message,
+            r#"
+            SELECT
+                m.message_id,
+                m.chat_id,
+                m.sender_id,
+                m.content,
+                m.created_at,
+                m.message_type as "message_type: crate::entities::MessageType",
+                m.content_format as "content_format: crate::entities::ContentFormat",
+                m.reply_to_message_id,
+                m.attachment_id,
+                m.deleted_at
+            FROM offline_queue q
+            JOIN messages m ON m.message_id = q.message_id
+            JOIN userchatmetadata ucm ON ucm.user_id = q.user_id AND ucm.chat_id = m.chat_id
+            WHERE q.user_id = ?
+              AND q.delivered_at IS NULL
+              AND q.message_id > ?
+              AND m.created_at >= ucm.messages_visible_from
+            ORDER BY q.message_id ASC
+            LIMIT ?
+            "#,
+            user_id,
+            after_message_id,
+            limit
+        )
+        .fetch_all(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(messages)
+    }
+
+    /// Mark as delivered every queued message of a user up to `up_to_message_id` (included)
+    ///
+    /// # Returns
+    /// Number of rows marked
+    #[instrument(skip(self))]
+    pub async fn mark_delivered(
+        &self,
+        user_id: &i32,
+        up_to_message_id: &i32,
+    ) -> Result<u64, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::offline_queue::mark_delivered(pool, user_id, up_to_message_id).await;
+        }
+        let result = sqlx::query!(
+            r#"
+            UPDATE offline_queue
+            SET delivered_at = ?
+            WHERE user_id = ? AND message_id <= ? AND delivered_at IS NULL
+            "#,
+            Utc::now(),
+            user_id,
+            up_to_message_id
+        )
+        .execute(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Delete rows delivered before `delivered_before` and rows queued before
+    /// `enqueued_before`, delivered or not
+    ///
+    /// # Returns
+    /// Number of rows deleted
+    #[instrument(skip(self))]
+    pub async fn purge(
+        &self,
+        delivered_before: &DateTime<Utc>,
+        enqueued_before: &DateTime<Utc>,
+    ) -> Result<u64, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::offline_queue::purge(pool, delivered_before, enqueued_before).await;
+        }
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM offline_queue
+            WHERE delivered_at < ? OR enqueued_at < ?
+            "#,
+            delivered_before,
+            enqueued_before
+        )
+        .execute(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::MySqlPool;
+
+    /// Rende visibili ai membri i messaggi delle fixture, creati prima dell'ingresso in chat
+    async fn show_fixture_messages(pool: &MySqlPool) -> sqlx::Result<()> {
+        sqlx::query!("UPDATE userchatmetadata SET messages_visible_from = NOW() - INTERVAL 1 HOUR")
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Test: i messaggi in coda vengono restituiti in ordine e una sola volta
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_pending_messages_until_delivered(pool: MySqlPool) -> sqlx::Result<()> {
+        show_fixture_messages(&pool).await?;
+        let repo = OfflineQueueRepository::new(pool.clone());
+
+        // messaggi di Alice nella chat 1 e nella chat 2, accodati per Bob
+        assert_eq!(repo.enqueue(&4, &[2]).await?, 1);
+        assert_eq!(repo.enqueue(&1, &[2, 3]).await?, 2);
+        assert_eq!(repo.enqueue(&1, &[2]).await?, 0);
+
+        let pending = repo.find_pending(&2, &0, 10).await?;
+        let ids: Vec<i32> = pending.iter().map(|m| m.message_id).collect();
+        assert_eq!(ids, vec![1, 4]);
+
+        // la pagina successiva parte dopo l'ultimo messaggio ricevuto
+        let pending = repo.find_pending(&2, &1, 10).await?;
+        assert_eq!(pending.len(), 1);
+
+        assert_eq!(repo.mark_delivered(&2, &1).await?, 1);
+        let pending = repo.find_pending(&2, &0, 10).await?;
+        assert_eq!(pending[0].message_id, 4);
+        // la coda di Charlie non è toccata
+        assert_eq!(repo.find_pending(&3, &0, 10).await?.len(), 1);
+
+        Ok(())
+    }
+
+    /// Test: i messaggi delle chat abbandonate non vengono consegnati
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_pending_skips_left_chats(pool: MySqlPool) -> sqlx::Result<()> {
+        show_fixture_messages(&pool).await?;
+        let repo = OfflineQueueRepository::new(pool.clone());
+
+        repo.enqueue(&6, &[3]).await?;
+        sqlx::query!("DELETE FROM userchatmetadata WHERE user_id = 3 AND chat_id = 3")
+            .execute(&pool)
+            .await?;
+
+        assert!(repo.find_pending(&3, &0, 10).await?.is_empty());
+
+        Ok(())
+    }
+
+    /// Test: purge elimina le righe consegnate e quelle troppo vecchie
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_purge(pool: MySqlPool) -> sqlx::Result<()> {
+        show_fixture_messages(&pool).await?;
+        let repo = OfflineQueueRepository::new(pool.clone());
+
+        repo.enqueue(&1, &[2, 3]).await?;
+        repo.enqueue(&4, &[2]).await?;
+        repo.mark_delivered(&2, &1).await?;
+
+        let later = Utc::now() + chrono::Duration::seconds(1);
+        let long_ago = Utc::now() - chrono::Duration::days(1);
+        assert_eq!(repo.purge(&later, &long_ago).await?, 1);
+        assert_eq!(repo.find_pending(&2, &0, 10).await?.len(), 1);
+
+        // anche le righe mai consegnate scadono
+        assert_eq!(repo.purge(&long_ago, &later).await?, 2);
+        assert!(repo.find_pending(&3, &0, 10).await?.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,227 @@
+//! Query log - Tempi delle query e log delle query lente
+//!
+//! I repository eseguono le query attraverso `timed(executor)`: ogni query ha uno span
+//! `db.query` con l'SQL e la durata, e quelle che superano la soglia impostata all'avvio
+//! (`SLOW_QUERY_THRESHOLD_MS`) producono un warning con SQL, hash dei parametri e durata.
+//! I valori dei parametri non finiscono mai nei log: possono contenere password e messaggi,
+//! l'hash basta a capire se le esecuzioni lente ripetono gli stessi argomenti.
+//! Il wrapper funziona con qualsiasi backend di sqlx, MySQL o SQLite.
+
+use futures::future::{self, BoxFuture, FutureExt};
+use futures::stream::{self, BoxStream, StreamExt};
+use sqlx::{Database, Describe, Either, Error, Execute, Executor};
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tracing::{Span, debug_span, field, trace, warn};
+
+// 0 = log delle query lente disattivato
+static SLOW_QUERY_THRESHOLD_MS: AtomicU64 = AtomicU64::new(0);
+
+/// Set the duration above which a query is logged as slow; zero disables the log
+pub fn set_slow_query_threshold(threshold: Duration) {
+    SLOW_QUERY_THRESHOLD_MS.store(threshold.as_millis() as u64, Ordering::Relaxed);
+}
+
+fn slow_query_threshold() -> Option<Duration> {
+    match SLOW_QUERY_THRESHOLD_MS.load(Ordering::Relaxed) {
+        0 => None,
+        ms => Some(Duration::from_millis(ms)),
+    }
+}
+
+/// Executor that times the queries run through the wrapped pool, connection or transaction
+#[derive(Debug)]
+pub struct Timed<E>(E);
+
+/// Time the queries run on `executor`, e.g. `.fetch_all(timed(&self.connection_pool))`
+pub fn timed<E>(executor: E) -> Timed<E> {
+    Timed(executor)
+}
+
+/// Misura di una query in corso: lo span resta aperto finché la query non termina
+struct QueryTimer<'q> {
+    sql: &'q str,
+    // hash dei parametri, calcolato solo con il log delle query lente attivo
+    params_hash: Option<u64>,
+    threshold: Option<Duration>,
+    started: Instant,
+    span: Span,
+}
+
+impl<'q> QueryTimer<'q> {
+    fn start(sql: &'q str, arguments: Option<&impl Debug>) -> Self {
+        let threshold = slow_query_threshold();
+        Self {
+            sql,
+            params_hash: threshold.map(|_| hash_arguments(arguments)),
+            threshold,
+            started: Instant::now(),
+            span: debug_span!("db.query", db.statement = sql, elapsed_ms = field::Empty),
+        }
+    }
+
+    fn finish(self) {
+        let elapsed = self.started.elapsed();
+        let elapsed_ms = elapsed.as_millis() as u64;
+        self.span.record("elapsed_ms", elapsed_ms);
+        trace!(parent: &self.span, elapsed_ms, "Query finished");
+
+        if self.threshold.is_some_and(|threshold| elapsed >= threshold) {
+            warn!(
+                parent: &self.span,
+                sql = %compact_sql(self.sql),
+                params_hash = %format!("{:016x}", self.params_hash.unwrap_or_default()),
+                elapsed_ms,
+                "Slow query"
+            );
+        }
+    }
+}
+
+/// Hash dei parametri codificati; uguale per esecuzioni con gli stessi argomenti
+fn hash_arguments(arguments: Option<&impl Debug>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    // gli argomenti di sqlx non espongono i byte codificati, ma il Debug li contiene tutti
+    format!("{:?}", arguments).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// SQL su una sola riga, senza l'indentazione delle stringhe raw dei repository
+fn compact_sql(sql: &str) -> String {
+    sql.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn encode_error(error: sqlx::error::BoxDynError) -> Error {
+    Error::Encode(error)
+}
+
+impl<'c, E, DB> Executor<'c> for Timed<E>
+where
+    E: Executor<'c, Database = DB>,
+    DB: Database,
+    for<'q> DB::Arguments<'q>: Debug,
+{
+    type Database = DB;
+
+    fn fetch_many<'e, 'q: 'e, Q>(
+        self,
+        mut query: Q,
+    ) -> BoxStream<'e, Result<Either<DB::QueryResult, DB::Row>, Error>>
+    where
+        'c: 'e,
+        Q: 'q + Execute<'q, DB>,
+    {
+        let sql = query.sql();
+        let arguments = match query.take_arguments() {
+            Ok(arguments) => arguments,
+            Err(error) => return stream::once(future::err(encode_error(error))).boxed(),
+        };
+        let timer = QueryTimer::start(sql, arguments.as_ref());
+
+        // la query termina quando il flusso delle righe si esaurisce
+        let finished = stream::once(async move {
+            timer.finish();
+            None
+        });
+        self.0
+            .fetch_many((sql, arguments))
+            .map(Some)
+            .chain(finished)
+            .filter_map(future::ready)
+            .boxed()
+    }
+
+    fn fetch_optional<'e, 'q: 'e, Q>(
+        self,
+        mut query: Q,
+    ) -> BoxFuture<'e, Result<Option<DB::Row>, Error>>
+    where
+        'c: 'e,
+        Q: 'q + Execute<'q, DB>,
+    {
+        let sql = query.sql();
+        let arguments = match query.take_arguments() {
+            Ok(arguments) => arguments,
+            Err(error) => return future::err(encode_error(error)).boxed(),
+        };
+        let timer = QueryTimer::start(sql, arguments.as_ref());
+
+        let row = self.0.fetch_optional((sql, arguments));
+        async move {
+            let row = row.await;
+            timer.finish();
+            row
+        }
+        .boxed()
+    }
+
+    fn prepare_with<'e, 'q: 'e>(
+        self,
+        sql: &'q str,
+        parameters: &'e [DB::TypeInfo],
+    ) -> BoxFuture<'e, Result<DB::Statement<'q>, Error>>
+    where
+        'c: 'e,
+    {
+        self.0.prepare_with(sql, parameters)
+    }
+
+    fn describe<'e, 'q: 'e>(self, sql: &'q str) -> BoxFuture<'e, Result<Describe<DB>, Error>>
+    where
+        'c: 'e,
+    {
+        self.0.describe(sql)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::MySqlPool;
+
+    #[test]
+    fn test_compact_sql_joins_lines() {
+        let sql = r#"
+            SELECT user_id
+            FROM users
+            WHERE username = ?
+            "#;
+        assert_eq!(
+            compact_sql(sql),
+            "SELECT user_id FROM users WHERE username = ?"
+        );
+    }
+
+    #[test]
+    fn test_slow_query_threshold_zero_disables_log() {
+        set_slow_query_threshold(Duration::from_millis(250));
+        assert_eq!(slow_query_threshold(), Some(Duration::from_millis(250)));
+
+        set_slow_query_threshold(Duration::ZERO);
+        assert_eq!(slow_query_threshold(), None);
+    }
+
+    #[sqlx::test]
+    async fn test_timed_forwards_queries_and_arguments(pool: MySqlPool) -> sqlx::Result<()> {
+        let one: i64 = sqlx::query_scalar("SELECT 1").fetch_one(timed(&pool)).await?;
+        assert_eq!(one, 1);
+
+        let mut tx = pool.begin().await?;
+        let doubled: Vec<(i64,)> = sqlx::query_as("SELECT ? * 2")
+            .bind(21i64)
+            .fetch_all(timed(&mut *tx))
+            .await?;
+        assert_eq!(doubled, vec![(42,)]);
+        let missing: Option<(i64,)> = sqlx::query_as("SELECT 1 FROM users WHERE user_id = ?")
+            .bind(-1)
+            .fetch_optional(timed(&mut *tx))
+            .await?;
+        assert!(missing.is_none());
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,200 @@
+//! RefreshTokenRepository - Repository per i refresh token e le loro famiglie
+
+use super::query_log::timed;
+#[cfg(feature = "sqlite")]
+use super::sqlite;
+use super::{Create, DbPool};
+use crate::dtos::CreateRefreshTokenDTO;
+use crate::entities::RefreshToken;
+use chrono::Utc;
+use sqlx::Error;
+use tracing::{debug, info, instrument, warn};
+
+//REFRESH TOKEN REPOSITORY
+pub struct RefreshTokenRepository {
+    connection_pool: DbPool,
+}
+
+impl RefreshTokenRepository {
+    pub fn new(connection_pool: impl Into<DbPool>) -> Self {
+        Self {
+            connection_pool: connection_pool.into(),
+        }
+    }
+
+    /// Cerca un refresh token tramite il suo hash
+    pub async fn find_by_hash(&self, token_hash: &str) -> Result<Option<RefreshToken>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::refresh_token::find_by_hash(pool, token_hash).await;
+        }
+        let token = sqlx::query_as!(
+            RefreshToken,
+            r#"
+            SELECT
+                token_id,
+                user_id,
+                family_id,
+                token_hash,
+                created_at,
+                expires_at,
+                used_at,
+                revoked_at
+            FROM refresh_tokens
+            WHERE token_hash = ?
+            "#,
+            token_hash
+        )
+        .fetch_optional(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(token)
+    }
+
+    /// Segna il token come usato. Ritorna false se era già stato usato o revocato,
+    /// così due refresh concorrenti con lo stesso token non possono riuscire entrambi
+    #[instrument(skip(self), fields(token_id = %token_id))]
+    pub async fn mark_used(&self, token_id: &i32) -> Result<bool, Error> {
+        debug!("Marking refresh token as used");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::refresh_token::mark_used(pool, token_id).await;
+        }
+        let result = sqlx::query!(
+            r#"
+            UPDATE refresh_tokens
+            SET used_at = ?
+            WHERE token_id = ? AND used_at IS NULL AND revoked_at IS NULL
+            "#,
+            Utc::now(),
+            token_id
+        )
+        .execute(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Revoca tutti i token della famiglia (riuso di un token già ruotato)
+    #[instrument(skip(self, family_id))]
+    pub async fn revoke_family(&self, family_id: &str) -> Result<(), Error> {
+        warn!("Revoking refresh token family");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::refresh_token::revoke_family(pool, family_id).await;
+        }
+        sqlx::query!(
+            "UPDATE refresh_tokens SET revoked_at = ? WHERE family_id = ? AND revoked_at IS NULL",
+            Utc::now(),
+            family_id
+        )
+        .execute(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(())
+    }
+
+    /// Revoca tutti i refresh token dell'utente (account cancellato o disattivato)
+    #[instrument(skip(self), fields(user_id = %user_id))]
+    pub async fn revoke_all_by_user_id(&self, user_id: &i32) -> Result<(), Error> {
+        debug!("Revoking all refresh tokens of user");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::refresh_token::revoke_all_by_user_id(pool, user_id).await;
+        }
+        sqlx::query!(
+            "UPDATE refresh_tokens SET revoked_at = ? WHERE user_id = ? AND revoked_at IS NULL",
+            Utc::now(),
+            user_id
+        )
+        .execute(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(())
+    }
+}
+
+impl Create<RefreshToken, CreateRefreshTokenDTO> for RefreshTokenRepository {
+    #[instrument(skip(self, data), fields(user_id = %data.user_id))]
+    async fn create(&self, data: &CreateRefreshTokenDTO) -> Result<RefreshToken, Error> {
+        debug!("Storing new refresh token");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::refresh_token::create(pool, data).await;
+        }
+        let now = Utc::now();
+
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO refresh_tokens (user_id, family_id, token_hash, created_at, expires_at)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+            data.user_id,
+            data.family_id,
+            data.token_hash,
+            now,
+            data.expires_at
+        )
+        .execute(timed(self.connection_pool.mysql()))
+        .await?;
+
+        let new_id = result.last_insert_id() as i32;
+
+        info!("Refresh token stored with id {}", new_id);
+
+        Ok(RefreshToken {
+            token_id: new_id,
+            user_id: data.user_id,
+            family_id: data.family_id.clone(),
+            token_hash: data.token_hash.clone(),
+            created_at: now,
+            expires_at: data.expires_at,
+            used_at: None,
+            revoked_at: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use sqlx::MySqlPool;
+
+    fn new_token(user_id: i32, family_id: &str, token_hash: &str) -> CreateRefreshTokenDTO {
+        CreateRefreshTokenDTO {
+            user_id,
+            family_id: family_id.to_string(),
+            token_hash: token_hash.to_string(),
+            expires_at: Utc::now() + Duration::days(1),
+        }
+    }
+
+    /// Test: un token può essere usato una sola volta e la revoca colpisce solo la sua famiglia
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users")))]
+    async fn test_mark_used_and_revoke_family(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = RefreshTokenRepository::new(pool);
+
+        let first = repo
+            .create(&new_token(1, "family-a", &"a".repeat(64)))
+            .await?;
+        let second = repo
+            .create(&new_token(1, "family-a", &"b".repeat(64)))
+            .await?;
+        let other = repo
+            .create(&new_token(1, "family-b", &"c".repeat(64)))
+            .await?;
+
+        assert!(repo.mark_used(&first.token_id).await?);
+        assert!(!repo.mark_used(&first.token_id).await?);
+
+        repo.revoke_family("family-a").await?;
+
+        let second = repo.find_by_hash(&second.token_hash).await?.unwrap();
+        assert!(second.is_consumed());
+        let other = repo.find_by_hash(&other.token_hash).await?.unwrap();
+        assert!(!other.is_consumed());
+
+        Ok(())
+    }
+}
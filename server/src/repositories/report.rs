@@ -0,0 +1,276 @@
+//! ReportRepository - Repository per le segnalazioni di messaggi e utenti
+
+use super::query_log::timed;
+#[cfg(feature = "sqlite")]
+use super::sqlite;
+use super::{Create, DbPool, Page, Read, ReadMany};
+use crate::dtos::CreateReportDTO;
+use crate::entities::{Report, ReportStatus};
+use chrono::Utc;
+use sqlx::Error;
+use tracing::{debug, info, instrument};
+
+//REPORT REPOSITORY
+pub struct ReportRepository {
+    connection_pool: DbPool,
+}
+
+impl ReportRepository {
+    pub fn new(connection_pool: impl Into<DbPool>) -> Self {
+        Self {
+            connection_pool: connection_pool.into(),
+        }
+    }
+
+    /// Verifica se l'utente ha già una segnalazione pending sullo stesso bersaglio
+    /// (lo stesso messaggio, oppure l'utente quando `message_id` è None)
+    pub async fn has_pending_report(
+        &self,
+        reporter_id: &i32,
+        reported_user_id: &i32,
+        message_id: Option<i32>,
+    ) -> Result<bool, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::report::has_pending_report(
+                pool,
+                reporter_id,
+                reported_user_id,
+                message_id,
+            )
+            .await;
+        }
+        // <=> confronta anche i NULL: una segnalazione dell'utente non blocca quelle dei suoi messaggi
+        let count = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) FROM reports
+            WHERE reporter_id = ? AND reported_user_id = ? AND message_id <=> ? AND state = 'PENDING'
+            "#,
+            reporter_id,
+            reported_user_id,
+            message_id
+        )
+        .fetch_one(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(count > 0)
+    }
+
+    /// Chiude una segnalazione pending e la ritorna aggiornata
+    #[instrument(skip(self), fields(report_id = %report_id, state = ?state, resolved_by = %resolved_by))]
+    pub async fn resolve(
+        &self,
+        report_id: &i32,
+        state: &ReportStatus,
+        resolved_by: &i32,
+    ) -> Result<Report, Error> {
+        debug!("Resolving report");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            sqlite::report::resolve(pool, report_id, state, resolved_by).await?;
+            return self
+                .read(report_id)
+                .await?
+                .ok_or_else(|| sqlx::Error::RowNotFound);
+        }
+        sqlx::query!(
+            r#"
+            UPDATE reports
+            SET state = ?, resolved_by = ?, resolved_at = ?
+            WHERE report_id = ? AND state = 'PENDING'
+            "#,
+            state,
+            resolved_by,
+            Utc::now(),
+            report_id
+        )
+        .execute(timed(self.connection_pool.mysql()))
+        .await?;
+
+        self.read(report_id)
+            .await?
+            .ok_or_else(|| sqlx::Error::RowNotFound)
+    }
+}
+
+/// Filtro delle segnalazioni, restituite dalla più vecchia
+#[derive(Debug, Clone, Default)]
+pub struct ReportFilter {
+    /// None per le segnalazioni in qualsiasi stato
+    pub state: Option<ReportStatus>,
+}
+
+impl ReadMany<Report, ReportFilter> for ReportRepository {
+    async fn read_many(&self, filter: &ReportFilter, page: &Page) -> Result<Vec<Report>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::report::read_many(pool, filter, page).await;
+        }
+        let reports = sqlx::query_as!(
+            Report,
+            r#"
+            SELECT
+                report_id,
+                reporter_id,
+                reported_user_id,
+                chat_id,
+                message_id,
+                reason as "reason: crate::entities::ReportReason",
+                details,
+                state as "state: ReportStatus",
+                created_at,
+                resolved_by,
+                resolved_at
+            FROM reports
+            WHERE (? IS NULL OR state = ?)
+            ORDER BY created_at, report_id
+            LIMIT ? OFFSET ?
+            "#,
+            filter.state.as_ref(),
+            filter.state.as_ref(),
+            page.limit,
+            page.offset
+        )
+        .fetch_all(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(reports)
+    }
+}
+
+impl Create<Report, CreateReportDTO> for ReportRepository {
+    #[instrument(skip(self, data), fields(reporter_id = ?data.reporter_id, reported_user_id = %data.reported_user_id, reason = ?data.reason))]
+    async fn create(&self, data: &CreateReportDTO) -> Result<Report, Error> {
+        debug!("Creating new report");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::report::create(pool, data).await;
+        }
+        let now = Utc::now();
+
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO reports (reporter_id, reported_user_id, chat_id, message_id, reason, details, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+            data.reporter_id,
+            data.reported_user_id,
+            data.chat_id,
+            data.message_id,
+            &data.reason,
+            data.details,
+            now
+        )
+        .execute(timed(self.connection_pool.mysql()))
+        .await?;
+
+        let new_id = result.last_insert_id() as i32;
+
+        info!("Report created with id {}", new_id);
+
+        Ok(Report {
+            report_id: new_id,
+            reporter_id: data.reporter_id,
+            reported_user_id: data.reported_user_id,
+            chat_id: data.chat_id,
+            message_id: data.message_id,
+            reason: data.reason,
+            details: data.details.clone(),
+            state: ReportStatus::Pending,
+            created_at: now,
+            resolved_by: None,
+            resolved_at: None,
+        })
+    }
+}
+
+impl Read<Report, i32> for ReportRepository {
+    async fn read(&self, id: &i32) -> Result<Option<Report>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::report::read(pool, id).await;
+        }
+        let report = sqlx::query_as!(
+            Report,
+            r#"
+            SELECT
+                report_id,
+                reporter_id,
+                reported_user_id,
+                chat_id,
+                message_id,
+                reason as "reason: crate::entities::ReportReason",
+                details,
+                state as "state: ReportStatus",
+                created_at,
+                resolved_by,
+                resolved_at
+            FROM reports
+            WHERE report_id = ?
+            "#,
+            id
+        )
+        .fetch_optional(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::ReportReason;
+    use sqlx::MySqlPool;
+
+    /// Test: una segnalazione pending compare nella coda finché non viene chiusa
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_create_and_resolve(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = ReportRepository::new(pool);
+
+        let created = repo
+            .create(&CreateReportDTO {
+                reporter_id: Some(2),
+                reported_user_id: 1,
+                chat_id: Some(1),
+                message_id: Some(1),
+                reason: ReportReason::Spam,
+                details: None,
+            })
+            .await?;
+        assert_eq!(created.state, ReportStatus::Pending);
+        assert!(repo.has_pending_report(&2, &1, Some(1)).await?);
+        // la segnalazione del messaggio non conta come segnalazione dell'utente
+        assert!(!repo.has_pending_report(&2, &1, None).await?);
+
+        let pending_filter = ReportFilter {
+            state: Some(ReportStatus::Pending),
+        };
+        let pending = repo.read_many(&pending_filter, &Page::first(10)).await?;
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].report_id, created.report_id);
+
+        let resolved = repo
+            .resolve(&created.report_id, &ReportStatus::Dismissed, &3)
+            .await?;
+        assert_eq!(resolved.state, ReportStatus::Dismissed);
+        assert_eq!(resolved.resolved_by, Some(3));
+        assert!(resolved.resolved_at.is_some());
+
+        assert!(!repo.has_pending_report(&2, &1, Some(1)).await?);
+        assert!(
+            repo.read_many(&pending_filter, &Page::first(10))
+                .await?
+                .is_empty()
+        );
+
+        // una segnalazione già chiusa non viene modificata di nuovo
+        let read = repo
+            .resolve(&created.report_id, &ReportStatus::Resolved, &1)
+            .await?;
+        assert_eq!(read.state, ReportStatus::Dismissed);
+        assert_eq!(read.resolved_by, Some(3));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,104 @@
+//! Query SQLite di `AttachmentRepository`
+
+use crate::dtos::CreateAttachmentDTO;
+use crate::entities::Attachment;
+use crate::repositories::query_log::timed;
+use chrono::{DateTime, Utc};
+use sqlx::{Error, SqlitePool};
+
+pub(crate) async fn find_orphaned(
+    pool: &SqlitePool,
+    uploaded_before: &DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<Attachment>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT attachment_id, chat_id, uploader_id, file_name, content_type, size_bytes,
+            storage_key, ref_count, created_at
+        FROM attachments
+        WHERE ref_count = 0 AND created_at < ?
+        ORDER BY created_at
+        LIMIT ?
+        "#,
+    )
+    .bind(uploaded_before)
+    .bind(limit)
+    .fetch_all(timed(pool))
+    .await
+}
+
+pub(crate) async fn delete_if_orphaned(pool: &SqlitePool, id: &i32) -> Result<bool, Error> {
+    let result = sqlx::query("DELETE FROM attachments WHERE attachment_id = ? AND ref_count = 0")
+        .bind(id)
+        .execute(timed(pool))
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub(crate) async fn find_storage_keys_by_chat(
+    pool: &SqlitePool,
+    chat_id: &i32,
+) -> Result<Vec<String>, Error> {
+    sqlx::query_scalar("SELECT storage_key FROM attachments WHERE chat_id = ?")
+        .bind(chat_id)
+        .fetch_all(timed(pool))
+        .await
+}
+
+pub(crate) async fn create(
+    pool: &SqlitePool,
+    data: &CreateAttachmentDTO,
+) -> Result<Attachment, Error> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO attachments (chat_id, uploader_id, file_name, content_type, size_bytes,
+            storage_key, created_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(data.chat_id)
+    .bind(data.uploader_id)
+    .bind(&data.file_name)
+    .bind(&data.content_type)
+    .bind(data.size_bytes)
+    .bind(&data.storage_key)
+    .bind(data.created_at)
+    .execute(timed(pool))
+    .await?;
+
+    Ok(Attachment {
+        attachment_id: result.last_insert_rowid() as i32,
+        chat_id: data.chat_id,
+        uploader_id: data.uploader_id,
+        file_name: data.file_name.clone(),
+        content_type: data.content_type.clone(),
+        size_bytes: data.size_bytes,
+        storage_key: data.storage_key.clone(),
+        ref_count: 0,
+        created_at: data.created_at,
+    })
+}
+
+pub(crate) async fn read(pool: &SqlitePool, id: &i32) -> Result<Option<Attachment>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT attachment_id, chat_id, uploader_id, file_name, content_type, size_bytes,
+            storage_key, ref_count, created_at
+        FROM attachments
+        WHERE attachment_id = ?
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(timed(pool))
+    .await
+}
+
+pub(crate) async fn delete(pool: &SqlitePool, id: &i32) -> Result<(), Error> {
+    sqlx::query("DELETE FROM attachments WHERE attachment_id = ?")
+        .bind(id)
+        .execute(timed(pool))
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,66 @@
+//! Query SQLite di `AuditLogRepository`
+
+use crate::dtos::CreateAuditEntryDTO;
+use crate::entities::AuditEntry;
+use crate::repositories::Page;
+use crate::repositories::audit::AuditFilter;
+use crate::repositories::query_log::timed;
+use chrono::Utc;
+use sqlx::{Error, SqliteConnection, SqlitePool};
+
+pub(crate) async fn insert(
+    conn: &mut SqliteConnection,
+    data: &CreateAuditEntryDTO,
+) -> Result<AuditEntry, Error> {
+    let now = Utc::now();
+    let payload = data.payload.as_ref().map(|payload| payload.to_string());
+
+    let result = sqlx::query(
+        r#"
+        INSERT INTO audit_log (chat_id, actor_id, target_user_id, action, payload, created_at)
+        VALUES (?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(data.chat_id)
+    .bind(data.actor_id)
+    .bind(data.target_user_id)
+    .bind(data.action)
+    .bind(&payload)
+    .bind(now)
+    .execute(timed(conn))
+    .await?;
+
+    Ok(AuditEntry {
+        audit_id: result.last_insert_rowid() as i32,
+        chat_id: data.chat_id,
+        actor_id: Some(data.actor_id),
+        target_user_id: data.target_user_id,
+        action: data.action,
+        payload,
+        created_at: now,
+    })
+}
+
+pub(crate) async fn read_many(
+    pool: &SqlitePool,
+    filter: &AuditFilter,
+    page: &Page,
+) -> Result<Vec<AuditEntry>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT audit_id, chat_id, actor_id, target_user_id, action, payload, created_at
+        FROM audit_log
+        WHERE chat_id = ?
+          AND (? IS NULL OR action = ?)
+        ORDER BY created_at DESC, audit_id DESC
+        LIMIT ? OFFSET ?
+        "#,
+    )
+    .bind(filter.chat_id)
+    .bind(filter.action)
+    .bind(filter.action)
+    .bind(page.limit)
+    .bind(page.offset)
+    .fetch_all(timed(pool))
+    .await
+}
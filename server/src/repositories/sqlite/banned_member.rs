@@ -0,0 +1,55 @@
+//! Query SQLite di `BannedMemberRepository`
+
+use crate::entities::BannedMember;
+use crate::repositories::query_log::timed;
+use crate::repositories::user_chat_metadata::UserChatKey;
+use chrono::Utc;
+use sqlx::{Error, SqliteConnection, SqlitePool};
+
+pub(crate) async fn insert(
+    conn: &mut SqliteConnection,
+    user_id: &i32,
+    chat_id: &i32,
+    banned_by: &i32,
+) -> Result<BannedMember, Error> {
+    let now = Utc::now();
+
+    sqlx::query(
+        r#"
+        INSERT INTO banned_members (user_id, chat_id, banned_by, banned_at)
+        VALUES (?, ?, ?, ?)
+        ON CONFLICT (user_id, chat_id)
+        DO UPDATE SET banned_by = excluded.banned_by, banned_at = excluded.banned_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(chat_id)
+    .bind(banned_by)
+    .bind(now)
+    .execute(timed(conn))
+    .await?;
+
+    Ok(BannedMember {
+        user_id: *user_id,
+        chat_id: *chat_id,
+        banned_by: *banned_by,
+        banned_at: now,
+    })
+}
+
+pub(crate) async fn read(
+    pool: &SqlitePool,
+    id: &UserChatKey,
+) -> Result<Option<BannedMember>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT user_id, chat_id, banned_by, banned_at
+        FROM banned_members
+        WHERE user_id = ? AND chat_id = ?
+        "#,
+    )
+    .bind(id.0)
+    .bind(id.1)
+    .fetch_optional(timed(pool))
+    .await
+}
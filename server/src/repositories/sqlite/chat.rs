@@ -0,0 +1,319 @@
+//! Query SQLite di `ChatRepository`
+
+use super::begin_immediate;
+use crate::dtos::{CreateChatDTO, PublicChatDTO, UpdateChatDTO};
+use crate::entities::{Chat, ContentFilterPolicy, InvitePolicy};
+use crate::repositories::Page;
+use crate::repositories::chat::{ChatFilter, ChatOverviewRow, UserChatRow};
+use crate::repositories::query_log::timed;
+use crate::repositories::sqlite;
+use sqlx::{Error, QueryBuilder, Sqlite, SqliteConnection, SqlitePool};
+
+pub(crate) async fn get_private_chat_between_users(
+    pool: &SqlitePool,
+    user1_id: &i32,
+    user2_id: &i32,
+) -> Result<Option<Chat>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT c.chat_id, c.title, c.description, c.chat_type, c.avatar_attachment_id,
+            c.announcement_only, c.is_public, c.requires_approval, c.invite_policy,
+            c.content_filter
+        FROM chats c
+        INNER JOIN userchatmetadata ucm ON c.chat_id = ucm.chat_id
+        WHERE c.chat_type = 'PRIVATE'
+        AND ucm.user_id IN (?, ?)
+        GROUP BY c.chat_id
+        HAVING COUNT(DISTINCT ucm.user_id) = 2
+        "#,
+    )
+    .bind(user1_id)
+    .bind(user2_id)
+    .fetch_optional(timed(pool))
+    .await
+}
+
+pub(crate) async fn search_by_title_for_user(
+    pool: &SqlitePool,
+    user_id: &i32,
+    pattern: &str,
+    limit: i64,
+) -> Result<Vec<Chat>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT c.chat_id, c.title, c.description, c.chat_type, c.avatar_attachment_id,
+            c.announcement_only, c.is_public, c.requires_approval, c.invite_policy,
+            c.content_filter
+        FROM chats c
+        INNER JOIN userchatmetadata ucm ON c.chat_id = ucm.chat_id
+        WHERE ucm.user_id = ?
+        AND c.title LIKE ?
+        ORDER BY c.title
+        LIMIT ?
+        "#,
+    )
+    .bind(user_id)
+    .bind(pattern)
+    .bind(limit)
+    .fetch_all(timed(pool))
+    .await
+}
+
+pub(crate) async fn search_public(
+    pool: &SqlitePool,
+    pattern: Option<&str>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<PublicChatDTO>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT c.chat_id, c.title, c.description, COUNT(ucm.user_id) AS member_count,
+            c.requires_approval
+        FROM chats c
+        LEFT JOIN userchatmetadata ucm ON c.chat_id = ucm.chat_id
+        WHERE c.is_public = 1
+        AND c.chat_type = 'GROUP'
+        AND (? IS NULL OR c.title LIKE ? OR c.description LIKE ?)
+        GROUP BY c.chat_id
+        ORDER BY COUNT(ucm.user_id) DESC, c.chat_id
+        LIMIT ? OFFSET ?
+        "#,
+    )
+    .bind(pattern)
+    .bind(pattern)
+    .bind(pattern)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(timed(pool))
+    .await
+}
+
+pub(crate) async fn find_overview_by_user_id(
+    pool: &SqlitePool,
+    user_id: &i32,
+) -> Result<Vec<ChatOverviewRow>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT
+            c.chat_id, c.title, c.description, c.chat_type, c.avatar_attachment_id,
+            c.announcement_only, c.is_public, c.requires_approval, c.invite_policy,
+            c.content_filter,
+            ucm.pinned_at,
+            ucm.unread_count,
+            (
+                SELECT COUNT(*) FROM userchatmetadata members
+                WHERE members.chat_id = c.chat_id
+            ) AS member_count,
+            m.message_id AS last_message_id,
+            m.sender_id AS last_sender_id,
+            m.content AS last_content,
+            m.created_at AS last_created_at,
+            m.message_type AS last_message_type,
+            m.content_format AS last_content_format,
+            m.reply_to_message_id AS last_reply_to_message_id,
+            m.attachment_id AS last_attachment_id
+        FROM userchatmetadata ucm
+        INNER JOIN chats c ON c.chat_id = ucm.chat_id
+        LEFT JOIN messages m ON m.message_id = (
+            SELECT last.message_id FROM messages last
+            WHERE last.chat_id = ucm.chat_id
+            AND last.created_at >= ucm.messages_visible_from
+            AND last.deleted_at IS NULL
+            ORDER BY last.message_id DESC
+            LIMIT 1
+        )
+        WHERE ucm.user_id = ?
+        ORDER BY
+            ucm.pinned_at IS NULL,
+            ucm.pinned_at DESC,
+            COALESCE(m.created_at, ucm.member_since) DESC,
+            c.chat_id
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(timed(pool))
+    .await
+}
+
+/// Sposta il riferimento dal vecchio avatar della chat al nuovo
+pub(crate) async fn set_avatar(
+    pool: &SqlitePool,
+    chat_id: &i32,
+    attachment_id: &i32,
+) -> Result<(), Error> {
+    let mut tx = begin_immediate(pool).await?;
+
+    sqlx::query(
+        r#"
+        UPDATE attachments SET ref_count = ref_count - 1
+        WHERE attachment_id = (SELECT avatar_attachment_id FROM chats WHERE chat_id = ?)
+        "#,
+    )
+    .bind(chat_id)
+    .execute(timed(&mut *tx))
+    .await?;
+
+    sqlx::query("UPDATE chats SET avatar_attachment_id = ? WHERE chat_id = ?")
+        .bind(attachment_id)
+        .bind(chat_id)
+        .execute(timed(&mut *tx))
+        .await?;
+
+    sqlx::query("UPDATE attachments SET ref_count = ref_count + 1 WHERE attachment_id = ?")
+        .bind(attachment_id)
+        .execute(timed(&mut *tx))
+        .await?;
+
+    tx.commit().await
+}
+
+/// Conta i membri della chat dentro la unit of work
+///
+/// La transazione della unit of work ha già il lock di scrittura (`BEGIN IMMEDIATE`),
+/// quindi non servono i lock di riga della versione MySQL.
+pub(crate) async fn lock_members(conn: &mut SqliteConnection, chat_id: &i32) -> Result<i64, Error> {
+    sqlx::query_scalar::<_, i32>("SELECT chat_id FROM chats WHERE chat_id = ?")
+        .bind(chat_id)
+        .fetch_optional(timed(&mut *conn))
+        .await?
+        .ok_or(Error::RowNotFound)?;
+
+    sqlx::query_scalar("SELECT COUNT(*) FROM userchatmetadata WHERE chat_id = ?")
+        .bind(chat_id)
+        .fetch_one(timed(&mut *conn))
+        .await
+}
+
+pub(crate) async fn insert(
+    conn: &mut SqliteConnection,
+    data: &CreateChatDTO,
+) -> Result<Chat, Error> {
+    let result = sqlx::query("INSERT INTO chats (title, description, chat_type) VALUES (?, ?, ?)")
+        .bind(&data.title)
+        .bind(&data.description)
+        .bind(&data.chat_type)
+        .execute(timed(&mut *conn))
+        .await?;
+
+    let new_id = result.last_insert_rowid() as i32;
+
+    // Ogni chat nasce con le righe dei ruoli base a cui puntano i membri
+    sqlite::chat_role::insert_base_roles(conn, new_id).await?;
+
+    Ok(Chat {
+        chat_id: new_id,
+        title: data.title.clone(),
+        description: data.description.clone(),
+        chat_type: data.chat_type.clone(),
+        avatar_attachment_id: None,
+        announcement_only: false,
+        is_public: false,
+        requires_approval: false,
+        invite_policy: InvitePolicy::AdminsOnly,
+        content_filter: ContentFilterPolicy::Off,
+    })
+}
+
+pub(crate) async fn read_many(
+    pool: &SqlitePool,
+    filter: &ChatFilter,
+    page: &Page,
+) -> Result<Vec<UserChatRow>, Error> {
+    // array JSON espanso da json_each: un solo parametro qualunque sia il numero di chat
+    let ids = filter
+        .ids
+        .as_ref()
+        .map(|ids| serde_json::to_string(ids).unwrap_or_else(|_| "[]".to_string()));
+
+    sqlx::query_as(
+        r#"
+        SELECT c.chat_id, c.title, c.description, c.chat_type, c.avatar_attachment_id,
+            c.announcement_only, c.is_public, c.requires_approval, c.invite_policy,
+            c.content_filter, ucm.pinned_at
+        FROM chats c
+        LEFT JOIN userchatmetadata ucm ON ucm.chat_id = c.chat_id AND ucm.user_id = ?
+        WHERE (? IS NULL OR ucm.user_id IS NOT NULL)
+          AND (? IS NULL OR c.chat_id IN (SELECT value FROM json_each(?)))
+        ORDER BY ucm.pinned_at IS NULL, ucm.pinned_at DESC, c.chat_id
+        LIMIT ? OFFSET ?
+        "#,
+    )
+    .bind(filter.member_id)
+    .bind(filter.member_id)
+    .bind(&ids)
+    .bind(&ids)
+    .bind(page.limit)
+    .bind(page.offset)
+    .fetch_all(timed(pool))
+    .await
+}
+
+pub(crate) async fn create(pool: &SqlitePool, data: &CreateChatDTO) -> Result<Chat, Error> {
+    let mut tx = begin_immediate(pool).await?;
+    let chat = insert(&mut tx, data).await?;
+    tx.commit().await?;
+    Ok(chat)
+}
+
+pub(crate) async fn read(pool: &SqlitePool, id: &i32) -> Result<Option<Chat>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT chat_id, title, description, chat_type, avatar_attachment_id, announcement_only,
+            is_public, requires_approval, invite_policy, content_filter
+        FROM chats
+        WHERE chat_id = ?
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(timed(pool))
+    .await
+}
+
+/// Aggiorna i campi presenti nel DTO; il DTO ha almeno un campo valorizzato
+pub(crate) async fn update(pool: &SqlitePool, id: &i32, data: &UpdateChatDTO) -> Result<(), Error> {
+    let mut query_builder = QueryBuilder::<Sqlite>::new("UPDATE chats SET ");
+
+    let mut separated = query_builder.separated(", ");
+    if let Some(ref title) = data.title {
+        separated.push("title = ");
+        separated.push_bind_unseparated(title);
+    }
+    if let Some(ref description) = data.description {
+        separated.push("description = ");
+        separated.push_bind_unseparated(description);
+    }
+    if let Some(announcement_only) = data.announcement_only {
+        separated.push("announcement_only = ");
+        separated.push_bind_unseparated(announcement_only);
+    }
+    if let Some(is_public) = data.is_public {
+        separated.push("is_public = ");
+        separated.push_bind_unseparated(is_public);
+    }
+    if let Some(requires_approval) = data.requires_approval {
+        separated.push("requires_approval = ");
+        separated.push_bind_unseparated(requires_approval);
+    }
+    if let Some(invite_policy) = data.invite_policy {
+        separated.push("invite_policy = ");
+        separated.push_bind_unseparated(invite_policy);
+    }
+    if let Some(content_filter) = data.content_filter {
+        separated.push("content_filter = ");
+        separated.push_bind_unseparated(content_filter);
+    }
+
+    query_builder.push(" WHERE chat_id = ");
+    query_builder.push_bind(id);
+
+    query_builder.build().execute(timed(pool)).await?;
+    Ok(())
+}
+
+pub(crate) async fn delete(pool: &SqlitePool, id: &i32) -> Result<(), Error> {
+    sqlx::query("DELETE FROM chats WHERE chat_id = ?")
+        .bind(id)
+        .execute(timed(pool))
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,152 @@
+//! Query SQLite di `ChatRoleRepository`
+
+use super::begin_immediate;
+use crate::entities::{ChatRole, UserRole};
+use crate::repositories::Page;
+use crate::repositories::chat_role::ChatRoleFilter;
+use crate::repositories::query_log::timed;
+use chrono::Utc;
+use sqlx::{Error, SqliteConnection, SqlitePool};
+
+/// Inserisce il ruolo e restituisce il suo id
+pub(crate) async fn create(pool: &SqlitePool, role: &ChatRole) -> Result<i32, Error> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO chat_roles (chat_id, name, can_invite, can_remove_members, can_moderate,
+            can_delete_messages, can_pin_messages, can_edit_chat, can_post_announcements, created_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(role.chat_id)
+    .bind(&role.name)
+    .bind(role.can_invite)
+    .bind(role.can_remove_members)
+    .bind(role.can_moderate)
+    .bind(role.can_delete_messages)
+    .bind(role.can_pin_messages)
+    .bind(role.can_edit_chat)
+    .bind(role.can_post_announcements)
+    .bind(role.created_at)
+    .execute(timed(pool))
+    .await?;
+
+    Ok(result.last_insert_rowid() as i32)
+}
+
+pub(crate) async fn name_exists(
+    pool: &SqlitePool,
+    chat_id: &i32,
+    name: &str,
+) -> Result<bool, Error> {
+    let count: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM chat_roles WHERE chat_id = ? AND name = ?")
+            .bind(chat_id)
+            .bind(name)
+            .fetch_one(timed(pool))
+            .await?;
+
+    Ok(count > 0)
+}
+
+pub(crate) async fn find_base(
+    pool: &SqlitePool,
+    chat_id: &i32,
+    base_role: &UserRole,
+) -> Result<Option<ChatRole>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT role_id, chat_id, name, base_role, can_invite, can_remove_members, can_moderate,
+            can_delete_messages, can_pin_messages, can_edit_chat, can_post_announcements, created_at
+        FROM chat_roles
+        WHERE chat_id = ? AND base_role = ?
+        "#,
+    )
+    .bind(chat_id)
+    .bind(base_role)
+    .fetch_optional(timed(pool))
+    .await
+}
+
+pub(crate) async fn insert_base_roles(
+    conn: &mut SqliteConnection,
+    chat_id: i32,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO chat_roles (chat_id, name, base_role, can_invite, can_remove_members, can_moderate,
+            can_delete_messages, can_pin_messages, can_edit_chat, can_post_announcements, created_at)
+        VALUES
+            (?1, 'Owner', 'OWNER', 1, 1, 1, 1, 1, 1, 1, ?2),
+            (?1, 'Admin', 'ADMIN', 1, 1, 1, 1, 1, 1, 1, ?2),
+            (?1, 'Member', 'MEMBER', 0, 0, 0, 0, 0, 0, 0, ?2)
+        "#,
+    )
+    .bind(chat_id)
+    .bind(Utc::now())
+    .execute(timed(conn))
+    .await?;
+
+    Ok(())
+}
+
+pub(crate) async fn read_many(
+    pool: &SqlitePool,
+    filter: &ChatRoleFilter,
+    page: &Page,
+) -> Result<Vec<ChatRole>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT role_id, chat_id, name, base_role, can_invite, can_remove_members, can_moderate,
+            can_delete_messages, can_pin_messages, can_edit_chat, can_post_announcements, created_at
+        FROM chat_roles
+        WHERE chat_id = ?
+        ORDER BY role_id
+        LIMIT ? OFFSET ?
+        "#,
+    )
+    .bind(filter.chat_id)
+    .bind(page.limit)
+    .bind(page.offset)
+    .fetch_all(timed(pool))
+    .await
+}
+
+pub(crate) async fn read(pool: &SqlitePool, id: &i32) -> Result<Option<ChatRole>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT role_id, chat_id, name, base_role, can_invite, can_remove_members, can_moderate,
+            can_delete_messages, can_pin_messages, can_edit_chat, can_post_announcements, created_at
+        FROM chat_roles
+        WHERE role_id = ?
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(timed(pool))
+    .await
+}
+
+pub(crate) async fn delete(pool: &SqlitePool, id: &i32) -> Result<(), Error> {
+    let mut tx = begin_immediate(pool).await?;
+
+    // i membri con questo ruolo tornano alla riga base Member della chat
+    sqlx::query(
+        r#"
+        UPDATE userchatmetadata AS m
+        SET role_id = base.role_id
+        FROM chat_roles AS base
+        WHERE base.chat_id = m.chat_id AND base.base_role = 'MEMBER'
+          AND m.role_id = ?
+        "#,
+    )
+    .bind(id)
+    .execute(timed(&mut *tx))
+    .await?;
+
+    sqlx::query("DELETE FROM chat_roles WHERE role_id = ?")
+        .bind(id)
+        .execute(timed(&mut *tx))
+        .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
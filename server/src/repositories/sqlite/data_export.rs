@@ -0,0 +1,93 @@
+//! Query SQLite di `DataExportRepository`
+
+use crate::dtos::CreateDataExportDTO;
+use crate::entities::{DataExport, DataExportStatus};
+use crate::repositories::query_log::timed;
+use chrono::Utc;
+use sqlx::{Error, SqlitePool};
+
+pub(crate) async fn find_latest_by_user_id(
+    pool: &SqlitePool,
+    user_id: &i32,
+) -> Result<Option<DataExport>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT export_id, user_id, state, created_at, completed_at, storage_key
+        FROM data_exports
+        WHERE user_id = ?
+        ORDER BY created_at DESC, export_id DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(timed(pool))
+    .await
+}
+
+pub(crate) async fn complete(
+    pool: &SqlitePool,
+    export_id: &i32,
+    storage_key: &str,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        UPDATE data_exports
+        SET state = 'COMPLETED', storage_key = ?, completed_at = ?
+        WHERE export_id = ?
+        "#,
+    )
+    .bind(storage_key)
+    .bind(Utc::now())
+    .bind(export_id)
+    .execute(timed(pool))
+    .await?;
+
+    Ok(())
+}
+
+pub(crate) async fn fail(pool: &SqlitePool, export_id: &i32) -> Result<(), Error> {
+    sqlx::query("UPDATE data_exports SET state = 'FAILED', completed_at = ? WHERE export_id = ?")
+        .bind(Utc::now())
+        .bind(export_id)
+        .execute(timed(pool))
+        .await?;
+
+    Ok(())
+}
+
+pub(crate) async fn create(
+    pool: &SqlitePool,
+    data: &CreateDataExportDTO,
+) -> Result<DataExport, Error> {
+    let now = Utc::now();
+
+    let result = sqlx::query(
+        "INSERT INTO data_exports (user_id, state, created_at) VALUES (?, 'PENDING', ?)",
+    )
+    .bind(data.user_id)
+    .bind(now)
+    .execute(timed(pool))
+    .await?;
+
+    Ok(DataExport {
+        export_id: result.last_insert_rowid() as i32,
+        user_id: data.user_id,
+        state: DataExportStatus::Pending,
+        created_at: now,
+        completed_at: None,
+        storage_key: None,
+    })
+}
+
+pub(crate) async fn read(pool: &SqlitePool, id: &i32) -> Result<Option<DataExport>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT export_id, user_id, state, created_at, completed_at, storage_key
+        FROM data_exports
+        WHERE export_id = ?
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(timed(pool))
+    .await
+}
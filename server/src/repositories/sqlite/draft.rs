@@ -0,0 +1,62 @@
+//! Query SQLite di `DraftRepository`
+
+use crate::entities::Draft;
+use crate::repositories::query_log::timed;
+use crate::repositories::user_chat_metadata::UserChatKey;
+use chrono::Utc;
+use sqlx::{Error, SqlitePool};
+
+pub(crate) async fn upsert(
+    pool: &SqlitePool,
+    user_id: &i32,
+    chat_id: &i32,
+    content: &str,
+) -> Result<Draft, Error> {
+    let now = Utc::now();
+
+    sqlx::query(
+        r#"
+        INSERT INTO drafts (user_id, chat_id, content, updated_at)
+        VALUES (?, ?, ?, ?)
+        ON CONFLICT (user_id, chat_id)
+        DO UPDATE SET content = excluded.content, updated_at = excluded.updated_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(chat_id)
+    .bind(content)
+    .bind(now)
+    .execute(timed(pool))
+    .await?;
+
+    Ok(Draft {
+        user_id: *user_id,
+        chat_id: *chat_id,
+        content: content.to_string(),
+        updated_at: now,
+    })
+}
+
+pub(crate) async fn read(pool: &SqlitePool, id: &UserChatKey) -> Result<Option<Draft>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT user_id, chat_id, content, updated_at
+        FROM drafts
+        WHERE user_id = ? AND chat_id = ?
+        "#,
+    )
+    .bind(id.0)
+    .bind(id.1)
+    .fetch_optional(timed(pool))
+    .await
+}
+
+pub(crate) async fn delete(pool: &SqlitePool, id: &UserChatKey) -> Result<(), Error> {
+    sqlx::query("DELETE FROM drafts WHERE user_id = ? AND chat_id = ?")
+        .bind(id.0)
+        .bind(id.1)
+        .execute(timed(pool))
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,16 @@
+//! Query SQLite di `HealthRepository`
+
+use crate::repositories::query_log::timed;
+use sqlx::{Error, SqlitePool};
+
+pub(crate) async fn ping(pool: &SqlitePool) -> Result<(), Error> {
+    sqlx::query("SELECT 1").execute(timed(pool)).await?;
+    Ok(())
+}
+
+/// Versioni delle migrazioni già applicate da `sqlite::MIGRATOR`
+pub(crate) async fn applied_migrations(pool: &SqlitePool) -> Result<Vec<i64>, Error> {
+    sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success = 1")
+        .fetch_all(timed(pool))
+        .await
+}
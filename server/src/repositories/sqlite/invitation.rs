@@ -0,0 +1,172 @@
+//! Query SQLite di `InvitationRepository`
+
+use crate::dtos::CreateInvitationDTO;
+use crate::entities::{Invitation, InvitationStatus};
+use crate::repositories::Page;
+use crate::repositories::invitation::InvitationFilter;
+use crate::repositories::query_log::timed;
+use chrono::{DateTime, Utc};
+use sqlx::{Error, SqliteConnection, SqlitePool};
+
+pub(crate) async fn find_received_since(
+    pool: &SqlitePool,
+    user_id: &i32,
+    since: &DateTime<Utc>,
+) -> Result<Vec<Invitation>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT invite_id, target_chat_id, invited_id, invitee_id, note, state, created_at
+        FROM invitations
+        WHERE invited_id = ? AND created_at >= ?
+        ORDER BY created_at ASC, invite_id ASC
+        "#,
+    )
+    .bind(user_id)
+    .bind(since)
+    .fetch_all(timed(pool))
+    .await
+}
+
+pub(crate) async fn find_answered_since(
+    pool: &SqlitePool,
+    inviter_id: &i32,
+    since: &DateTime<Utc>,
+) -> Result<Vec<Invitation>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT invite_id, target_chat_id, invited_id, invitee_id, note, state, created_at
+        FROM invitations
+        WHERE invitee_id = ? AND updated_at >= ?
+        ORDER BY updated_at ASC, invite_id ASC
+        "#,
+    )
+    .bind(inviter_id)
+    .bind(since)
+    .fetch_all(timed(pool))
+    .await
+}
+
+pub(crate) async fn has_pending_invitation(
+    pool: &SqlitePool,
+    user_id: &i32,
+    chat_id: &i32,
+) -> Result<bool, Error> {
+    let count: i64 = sqlx::query_scalar(
+        r#"
+        SELECT COUNT(*) FROM invitations
+        WHERE invited_id = ? AND target_chat_id = ? AND state = 'PENDING'
+        "#,
+    )
+    .bind(user_id)
+    .bind(chat_id)
+    .fetch_one(timed(pool))
+    .await?;
+
+    Ok(count > 0)
+}
+
+/// Registra la risposta a un invito ancora in attesa e restituisce le righe aggiornate
+pub(crate) async fn answer(
+    conn: &mut SqliteConnection,
+    invite_id: &i32,
+    state: &InvitationStatus,
+) -> Result<u64, Error> {
+    let result = sqlx::query(
+        "UPDATE invitations SET state = ?, updated_at = ? WHERE invite_id = ? AND state = 'PENDING'",
+    )
+    .bind(state)
+    .bind(Utc::now())
+    .bind(invite_id)
+    .execute(timed(conn))
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+pub(crate) async fn read_many(
+    pool: &SqlitePool,
+    filter: &InvitationFilter,
+    page: &Page,
+) -> Result<Vec<Invitation>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT invite_id, target_chat_id, invited_id, invitee_id, note, state, created_at
+        FROM invitations
+        WHERE (? IS NULL OR invited_id = ?)
+          AND (? IS NULL OR invitee_id = ?)
+          AND (? IS NULL OR state = ?)
+        ORDER BY created_at DESC, invite_id DESC
+        LIMIT ? OFFSET ?
+        "#,
+    )
+    .bind(filter.invited_id)
+    .bind(filter.invited_id)
+    .bind(filter.inviter_id)
+    .bind(filter.inviter_id)
+    .bind(&filter.state)
+    .bind(&filter.state)
+    .bind(page.limit)
+    .bind(page.offset)
+    .fetch_all(timed(pool))
+    .await
+}
+
+/// Inserisce l'invito e restituisce il suo id
+pub(crate) async fn insert(
+    pool: &SqlitePool,
+    data: &CreateInvitationDTO,
+    state: &InvitationStatus,
+    now: DateTime<Utc>,
+) -> Result<i32, Error> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO invitations (target_chat_id, invited_id, invitee_id, note, state, created_at)
+        VALUES (?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(data.target_chat_id)
+    .bind(data.invited_id)
+    .bind(data.invitee_id)
+    .bind(&data.note)
+    .bind(state)
+    .bind(now)
+    .execute(timed(pool))
+    .await?;
+
+    Ok(result.last_insert_rowid() as i32)
+}
+
+pub(crate) async fn read(pool: &SqlitePool, id: &i32) -> Result<Option<Invitation>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT invite_id, target_chat_id, invited_id, invitee_id, note, state, created_at
+        FROM invitations
+        WHERE invite_id = ?
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(timed(pool))
+    .await
+}
+
+pub(crate) async fn update_state(
+    pool: &SqlitePool,
+    id: &i32,
+    state: &Option<InvitationStatus>,
+) -> Result<(), Error> {
+    sqlx::query("UPDATE invitations SET state = ?, updated_at = ? WHERE invite_id = ?")
+        .bind(state)
+        .bind(Utc::now())
+        .bind(id)
+        .execute(timed(pool))
+        .await?;
+    Ok(())
+}
+
+pub(crate) async fn delete(pool: &SqlitePool, id: &i32) -> Result<(), Error> {
+    sqlx::query("DELETE FROM invitations WHERE invite_id = ?")
+        .bind(id)
+        .execute(timed(pool))
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,98 @@
+//! Query SQLite di `InviteLinkRepository`
+
+use crate::dtos::NewInviteLinkDTO;
+use crate::entities::InviteLink;
+use crate::repositories::Page;
+use crate::repositories::invite_link::InviteLinkFilter;
+use crate::repositories::query_log::timed;
+use chrono::Utc;
+use sqlx::{Error, SqlitePool};
+
+pub(crate) async fn find_by_token_hash(
+    pool: &SqlitePool,
+    token_hash: &str,
+) -> Result<Option<InviteLink>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT link_id, chat_id, token_hash, created_by, created_at, expires_at
+        FROM invite_links
+        WHERE token_hash = ?
+        "#,
+    )
+    .bind(token_hash)
+    .fetch_optional(timed(pool))
+    .await
+}
+
+pub(crate) async fn read_many(
+    pool: &SqlitePool,
+    filter: &InviteLinkFilter,
+    page: &Page,
+) -> Result<Vec<InviteLink>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT link_id, chat_id, token_hash, created_by, created_at, expires_at
+        FROM invite_links
+        WHERE chat_id = ?
+        ORDER BY link_id
+        LIMIT ? OFFSET ?
+        "#,
+    )
+    .bind(filter.chat_id)
+    .bind(page.limit)
+    .bind(page.offset)
+    .fetch_all(timed(pool))
+    .await
+}
+
+pub(crate) async fn create(
+    pool: &SqlitePool,
+    data: &NewInviteLinkDTO,
+) -> Result<InviteLink, Error> {
+    let now = Utc::now();
+
+    let result = sqlx::query(
+        r#"
+        INSERT INTO invite_links (chat_id, token_hash, created_by, created_at, expires_at)
+        VALUES (?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(data.chat_id)
+    .bind(&data.token_hash)
+    .bind(data.created_by)
+    .bind(now)
+    .bind(data.expires_at)
+    .execute(timed(pool))
+    .await?;
+
+    Ok(InviteLink {
+        link_id: result.last_insert_rowid() as i32,
+        chat_id: data.chat_id,
+        token_hash: data.token_hash.clone(),
+        created_by: Some(data.created_by),
+        created_at: now,
+        expires_at: data.expires_at,
+    })
+}
+
+pub(crate) async fn read(pool: &SqlitePool, id: &i32) -> Result<Option<InviteLink>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT link_id, chat_id, token_hash, created_by, created_at, expires_at
+        FROM invite_links
+        WHERE link_id = ?
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(timed(pool))
+    .await
+}
+
+pub(crate) async fn delete(pool: &SqlitePool, id: &i32) -> Result<(), Error> {
+    sqlx::query("DELETE FROM invite_links WHERE link_id = ?")
+        .bind(id)
+        .execute(timed(pool))
+        .await?;
+
+    Ok(())
+}
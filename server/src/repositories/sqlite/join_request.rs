@@ -0,0 +1,120 @@
+//! Query SQLite di `JoinRequestRepository`
+
+use crate::dtos::CreateJoinRequestDTO;
+use crate::entities::{JoinRequest, JoinRequestStatus};
+use crate::repositories::Page;
+use crate::repositories::join_request::JoinRequestFilter;
+use crate::repositories::query_log::timed;
+use chrono::Utc;
+use sqlx::{Error, SqliteConnection, SqlitePool};
+
+pub(crate) async fn update_state(
+    conn: &mut SqliteConnection,
+    request_id: &i32,
+    state: &JoinRequestStatus,
+    resolved_by: &i32,
+) -> Result<u64, Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE join_requests
+        SET state = ?, resolved_by = ?, resolved_at = ?
+        WHERE request_id = ? AND state = 'PENDING'
+        "#,
+    )
+    .bind(state)
+    .bind(resolved_by)
+    .bind(Utc::now())
+    .bind(request_id)
+    .execute(timed(conn))
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+pub(crate) async fn insert(
+    conn: &mut SqliteConnection,
+    data: &CreateJoinRequestDTO,
+) -> Result<JoinRequest, Error> {
+    let now = Utc::now();
+    // le richieste approvate automaticamente nascono già risolte
+    let resolved_at = (data.state != JoinRequestStatus::Pending).then_some(now);
+
+    let result = sqlx::query(
+        r#"
+        INSERT INTO join_requests (chat_id, user_id, state, created_at, resolved_at)
+        VALUES (?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(data.chat_id)
+    .bind(data.user_id)
+    .bind(&data.state)
+    .bind(now)
+    .bind(resolved_at)
+    .execute(timed(conn))
+    .await?;
+
+    Ok(JoinRequest {
+        request_id: result.last_insert_rowid() as i32,
+        chat_id: data.chat_id,
+        user_id: data.user_id,
+        state: data.state.clone(),
+        created_at: now,
+        resolved_by: None,
+        resolved_at,
+    })
+}
+
+pub(crate) async fn select(
+    conn: &mut SqliteConnection,
+    id: &i32,
+) -> Result<Option<JoinRequest>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT request_id, chat_id, user_id, state, created_at, resolved_by, resolved_at
+        FROM join_requests
+        WHERE request_id = ?
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(timed(conn))
+    .await
+}
+
+pub(crate) async fn read_many(
+    pool: &SqlitePool,
+    filter: &JoinRequestFilter,
+    page: &Page,
+) -> Result<Vec<JoinRequest>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT request_id, chat_id, user_id, state, created_at, resolved_by, resolved_at
+        FROM join_requests
+        WHERE chat_id = ? AND (? IS NULL OR state = ?)
+        ORDER BY created_at, request_id
+        LIMIT ? OFFSET ?
+        "#,
+    )
+    .bind(filter.chat_id)
+    .bind(&filter.state)
+    .bind(&filter.state)
+    .bind(page.limit)
+    .bind(page.offset)
+    .fetch_all(timed(pool))
+    .await
+}
+
+pub(crate) async fn has_pending_request(
+    pool: &SqlitePool,
+    user_id: &i32,
+    chat_id: &i32,
+) -> Result<bool, Error> {
+    let count: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM join_requests WHERE user_id = ? AND chat_id = ? AND state = 'PENDING'",
+    )
+    .bind(user_id)
+    .bind(chat_id)
+    .fetch_one(timed(pool))
+    .await?;
+
+    Ok(count > 0)
+}
@@ -0,0 +1,517 @@
+//! Query SQLite di `MessageRepository`
+
+use super::begin_immediate;
+use crate::dtos::{CreateMessageDTO, UpdateMessageDTO};
+use crate::entities::{Message, MessageRevision};
+use crate::repositories::Page;
+use crate::repositories::message::MessageFilter;
+use crate::repositories::query_log::timed;
+use chrono::{DateTime, Utc};
+use futures::stream::BoxStream;
+use sqlx::{Error, SqliteConnection, SqlitePool};
+
+pub(crate) async fn count_by_chat_id(pool: &SqlitePool, chat_id: &i32) -> Result<i64, Error> {
+    sqlx::query_scalar("SELECT COUNT(*) FROM messages WHERE chat_id = ? AND deleted_at IS NULL")
+        .bind(chat_id)
+        .fetch_one(timed(pool))
+        .await
+}
+
+pub(crate) async fn find_by_client_msg_id(
+    pool: &SqlitePool,
+    sender_id: &i32,
+    client_msg_id: &str,
+) -> Result<Option<Message>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT message_id, chat_id, sender_id, content, created_at, message_type,
+            content_format, reply_to_message_id, attachment_id, deleted_at
+        FROM messages
+        WHERE sender_id = ? AND client_msg_id = ?
+        "#,
+    )
+    .bind(sender_id)
+    .bind(client_msg_id)
+    .fetch_optional(timed(pool))
+    .await
+}
+
+pub(crate) fn stream_by_chat(
+    pool: &SqlitePool,
+    chat_id: i32,
+    messages_visible_from: DateTime<Utc>,
+) -> BoxStream<'_, Result<Message, Error>> {
+    sqlx::query_as(
+        r#"
+        SELECT message_id, chat_id, sender_id, content, created_at, message_type,
+            content_format, reply_to_message_id, attachment_id, deleted_at
+        FROM messages
+        WHERE chat_id = ?
+          AND created_at >= ?
+        ORDER BY created_at ASC, message_id ASC
+        "#,
+    )
+    .bind(chat_id)
+    .bind(messages_visible_from)
+    .fetch(timed(pool))
+}
+
+pub(crate) fn stream_by_sender_id(
+    pool: &SqlitePool,
+    sender_id: i32,
+) -> BoxStream<'_, Result<Message, Error>> {
+    sqlx::query_as(
+        r#"
+        SELECT message_id, chat_id, sender_id, content, created_at, message_type,
+            content_format, reply_to_message_id, attachment_id, deleted_at
+        FROM messages
+        WHERE sender_id = ?
+          AND message_type = 'USERMESSAGE'
+        ORDER BY created_at ASC, message_id ASC
+        "#,
+    )
+    .bind(sender_id)
+    .fetch(timed(pool))
+}
+
+/// Segna il messaggio come eliminato, liberando il suo allegato
+pub(crate) async fn soft_delete(pool: &SqlitePool, id: &i32) -> Result<(), Error> {
+    let mut tx = begin_immediate(pool).await?;
+
+    // il messaggio smette di contare come non letto: va fatto prima di impostare deleted_at
+    decrement_unread_counts(&mut tx, id).await?;
+    release_attachment(&mut tx, id).await?;
+
+    sqlx::query(
+        r#"
+        UPDATE messages
+        SET deleted_at = ?, attachment_id = NULL
+        WHERE message_id = ? AND deleted_at IS NULL
+        "#,
+    )
+    .bind(Utc::now())
+    .bind(id)
+    .execute(timed(&mut *tx))
+    .await?;
+
+    tx.commit().await
+}
+
+async fn decrement_unread_counts(
+    conn: &mut SqliteConnection,
+    message_id: &i32,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        UPDATE userchatmetadata AS ucm
+        SET unread_count = ucm.unread_count - 1
+        FROM messages AS m
+        WHERE m.chat_id = ucm.chat_id
+        AND m.message_id = ?
+        AND m.deleted_at IS NULL
+        AND m.sender_id <> ucm.user_id
+        AND m.created_at > ucm.messages_received_until
+        AND m.created_at >= ucm.messages_visible_from
+        AND ucm.unread_count > 0
+        "#,
+    )
+    .bind(message_id)
+    .execute(timed(conn))
+    .await?;
+
+    Ok(())
+}
+
+async fn release_attachment(conn: &mut SqliteConnection, message_id: &i32) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        UPDATE attachments SET ref_count = ref_count - 1
+        WHERE attachment_id = (SELECT attachment_id FROM messages WHERE message_id = ?)
+        "#,
+    )
+    .bind(message_id)
+    .execute(timed(conn))
+    .await?;
+
+    Ok(())
+}
+
+/// Ricerca nei messaggi di una chat con un `LIKE` sul contenuto, al posto del full-text MySQL
+pub(crate) async fn search_in_chat(
+    pool: &SqlitePool,
+    chat_id: &i32,
+    query: &str,
+    messages_visible_from: &DateTime<Utc>,
+    before: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<Message>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT message_id, chat_id, sender_id, content, created_at, message_type,
+            content_format, reply_to_message_id, attachment_id, deleted_at
+        FROM messages
+        WHERE chat_id = ?
+          AND content LIKE ?
+          AND created_at >= ?
+          AND created_at < ?
+          AND deleted_at IS NULL
+        ORDER BY created_at DESC
+        LIMIT ?
+        "#,
+    )
+    .bind(chat_id)
+    .bind(format!("%{}%", query))
+    .bind(messages_visible_from)
+    .bind(before)
+    .bind(limit)
+    .fetch_all(timed(pool))
+    .await
+}
+
+pub(crate) async fn search_for_user(
+    pool: &SqlitePool,
+    user_id: &i32,
+    query: &str,
+    limit: i64,
+) -> Result<Vec<Message>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT m.message_id, m.chat_id, m.sender_id, m.content, m.created_at, m.message_type,
+            m.content_format, m.reply_to_message_id, m.attachment_id, m.deleted_at
+        FROM messages m
+        INNER JOIN userchatmetadata ucm ON m.chat_id = ucm.chat_id
+        WHERE ucm.user_id = ?
+          AND m.content LIKE ?
+          AND m.created_at >= ucm.messages_visible_from
+          AND m.deleted_at IS NULL
+        ORDER BY m.created_at DESC
+        LIMIT ?
+        "#,
+    )
+    .bind(user_id)
+    .bind(format!("%{}%", query))
+    .bind(limit)
+    .fetch_all(timed(pool))
+    .await
+}
+
+pub(crate) async fn pin(
+    pool: &SqlitePool,
+    chat_id: &i32,
+    message_id: &i32,
+    pinned_by: &i32,
+) -> Result<bool, Error> {
+    let result = sqlx::query(
+        r#"
+        INSERT OR IGNORE INTO pinned_messages (chat_id, message_id, pinned_by, pinned_at)
+        VALUES (?, ?, ?, ?)
+        "#,
+    )
+    .bind(chat_id)
+    .bind(message_id)
+    .bind(pinned_by)
+    .bind(Utc::now())
+    .execute(timed(pool))
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub(crate) async fn unpin(
+    pool: &SqlitePool,
+    chat_id: &i32,
+    message_id: &i32,
+) -> Result<bool, Error> {
+    let result = sqlx::query("DELETE FROM pinned_messages WHERE chat_id = ? AND message_id = ?")
+        .bind(chat_id)
+        .bind(message_id)
+        .execute(timed(pool))
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub(crate) async fn find_pinned(
+    pool: &SqlitePool,
+    chat_id: &i32,
+    messages_visible_from: &DateTime<Utc>,
+) -> Result<Vec<Message>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT m.message_id, m.chat_id, m.sender_id, m.content, m.created_at, m.message_type,
+            m.content_format, m.reply_to_message_id, m.attachment_id, m.deleted_at
+        FROM pinned_messages p
+        JOIN messages m ON m.message_id = p.message_id
+        WHERE p.chat_id = ?
+          AND m.created_at >= ?
+          AND m.deleted_at IS NULL
+        ORDER BY p.pinned_at DESC
+        "#,
+    )
+    .bind(chat_id)
+    .bind(messages_visible_from)
+    .fetch_all(timed(pool))
+    .await
+}
+
+pub(crate) async fn delete_messages_before(
+    pool: &SqlitePool,
+    chat_id: &i32,
+    before_date: &DateTime<Utc>,
+) -> Result<u64, Error> {
+    let mut tx = begin_immediate(pool).await?;
+
+    // più messaggi possono condividere un allegato: i riferimenti si liberano insieme
+    sqlx::query(
+        r#"
+        UPDATE attachments AS a
+        SET ref_count = a.ref_count - r.refs
+        FROM (
+            SELECT attachment_id, COUNT(*) AS refs
+            FROM messages
+            WHERE chat_id = ? AND created_at < ? AND attachment_id IS NOT NULL
+            GROUP BY attachment_id
+        ) AS r
+        WHERE r.attachment_id = a.attachment_id
+        "#,
+    )
+    .bind(chat_id)
+    .bind(before_date)
+    .execute(timed(&mut *tx))
+    .await?;
+
+    let result = sqlx::query("DELETE FROM messages WHERE chat_id = ? AND created_at < ?")
+        .bind(chat_id)
+        .bind(before_date)
+        .execute(timed(&mut *tx))
+        .await?;
+
+    tx.commit().await?;
+    Ok(result.rows_affected())
+}
+
+pub(crate) async fn find_revisions(
+    pool: &SqlitePool,
+    message_id: &i32,
+) -> Result<Vec<MessageRevision>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT revision_id, message_id, content, replaced_at
+        FROM message_revisions
+        WHERE message_id = ?
+        ORDER BY revision_id
+        "#,
+    )
+    .bind(message_id)
+    .fetch_all(timed(pool))
+    .await
+}
+
+pub(crate) async fn insert(
+    conn: &mut SqliteConnection,
+    data: &CreateMessageDTO,
+) -> Result<Message, Error> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO messages (chat_id, sender_id, content, message_type, content_format,
+            created_at, reply_to_message_id, attachment_id, client_msg_id)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(data.chat_id)
+    .bind(data.sender_id)
+    .bind(&data.content)
+    .bind(&data.message_type)
+    .bind(&data.content_format)
+    .bind(data.created_at)
+    .bind(data.reply_to_message_id)
+    .bind(data.attachment_id)
+    .bind(&data.client_msg_id)
+    .execute(timed(&mut *conn))
+    .await?;
+
+    let new_id = result.last_insert_rowid() as i32;
+
+    if let Some(attachment_id) = data.attachment_id {
+        sqlx::query("UPDATE attachments SET ref_count = ref_count + 1 WHERE attachment_id = ?")
+            .bind(attachment_id)
+            .execute(timed(&mut *conn))
+            .await?;
+    }
+
+    // il messaggio è non letto per ogni altro membro che non l'aveva ancora ricevuto
+    sqlx::query(
+        r#"
+        UPDATE userchatmetadata
+        SET unread_count = unread_count + 1
+        WHERE chat_id = ?
+        AND user_id <> ?
+        AND messages_received_until < ?
+        AND messages_visible_from <= ?
+        "#,
+    )
+    .bind(data.chat_id)
+    .bind(data.sender_id)
+    .bind(data.created_at)
+    .bind(data.created_at)
+    .execute(timed(&mut *conn))
+    .await?;
+
+    Ok(Message {
+        message_id: new_id,
+        chat_id: data.chat_id,
+        sender_id: data.sender_id,
+        content: data.content.clone(),
+        created_at: data.created_at,
+        message_type: data.message_type.clone(),
+        content_format: data.content_format.clone(),
+        reply_to_message_id: data.reply_to_message_id,
+        attachment_id: data.attachment_id,
+        deleted_at: None,
+    })
+}
+
+pub(crate) async fn read_many(
+    pool: &SqlitePool,
+    filter: &MessageFilter,
+    page: &Page,
+) -> Result<Vec<Message>, Error> {
+    match filter {
+        MessageFilter::History {
+            chat_id,
+            visible_from,
+            before,
+        } => {
+            // senza before la pagina parte dal messaggio più recente
+            sqlx::query_as(
+                r#"
+                SELECT message_id, chat_id, sender_id, content, created_at, message_type,
+                    content_format, reply_to_message_id, attachment_id, deleted_at
+                FROM messages
+                WHERE chat_id = ?
+                  AND created_at >= ?
+                  AND (? IS NULL OR created_at < ?)
+                ORDER BY created_at DESC
+                LIMIT ? OFFSET ?
+                "#,
+            )
+            .bind(chat_id)
+            .bind(visible_from)
+            .bind(before)
+            .bind(before)
+            .bind(page.limit)
+            .bind(page.offset)
+            .fetch_all(timed(pool))
+            .await
+        }
+        MessageFilter::Sender { sender_id } => {
+            sqlx::query_as(
+                r#"
+                SELECT message_id, chat_id, sender_id, content, created_at, message_type,
+                    content_format, reply_to_message_id, attachment_id, deleted_at
+                FROM messages
+                WHERE sender_id = ?
+                  AND deleted_at IS NULL
+                  AND message_type = 'USERMESSAGE'
+                ORDER BY created_at ASC, message_id ASC
+                LIMIT ? OFFSET ?
+                "#,
+            )
+            .bind(sender_id)
+            .bind(page.limit)
+            .bind(page.offset)
+            .fetch_all(timed(pool))
+            .await
+        }
+        MessageFilter::After {
+            chat_id,
+            visible_from,
+            after_message_id,
+        } => {
+            sqlx::query_as(
+                r#"
+                SELECT message_id, chat_id, sender_id, content, created_at, message_type,
+                    content_format, reply_to_message_id, attachment_id, deleted_at
+                FROM messages
+                WHERE chat_id = ?
+                  AND created_at >= ?
+                  AND message_id > ?
+                ORDER BY message_id DESC
+                LIMIT ? OFFSET ?
+                "#,
+            )
+            .bind(chat_id)
+            .bind(visible_from)
+            .bind(after_message_id)
+            .bind(page.limit)
+            .bind(page.offset)
+            .fetch_all(timed(pool))
+            .await
+        }
+    }
+}
+
+pub(crate) async fn create(pool: &SqlitePool, data: &CreateMessageDTO) -> Result<Message, Error> {
+    let mut tx = begin_immediate(pool).await?;
+    let message = insert(&mut tx, data).await?;
+    tx.commit().await?;
+    Ok(message)
+}
+
+pub(crate) async fn read(pool: &SqlitePool, id: &i32) -> Result<Option<Message>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT message_id, chat_id, sender_id, content, created_at, message_type,
+            content_format, reply_to_message_id, attachment_id, deleted_at
+        FROM messages
+        WHERE message_id = ?
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(timed(pool))
+    .await
+}
+
+/// Salva il contenuto precedente come revisione e aggiorna il messaggio
+pub(crate) async fn update(
+    pool: &SqlitePool,
+    id: &i32,
+    data: &UpdateMessageDTO,
+) -> Result<(), Error> {
+    let mut tx = begin_immediate(pool).await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO message_revisions (message_id, content, replaced_at)
+        SELECT message_id, content, ?
+        FROM messages
+        WHERE message_id = ?
+        "#,
+    )
+    .bind(Utc::now())
+    .bind(id)
+    .execute(timed(&mut *tx))
+    .await?;
+
+    sqlx::query("UPDATE messages SET content = ? WHERE message_id = ?")
+        .bind(&data.content)
+        .bind(id)
+        .execute(timed(&mut *tx))
+        .await?;
+
+    tx.commit().await
+}
+
+pub(crate) async fn delete(pool: &SqlitePool, id: &i32) -> Result<(), Error> {
+    let mut tx = begin_immediate(pool).await?;
+
+    decrement_unread_counts(&mut tx, id).await?;
+    release_attachment(&mut tx, id).await?;
+
+    sqlx::query("DELETE FROM messages WHERE message_id = ?")
+        .bind(id)
+        .execute(timed(&mut *tx))
+        .await?;
+
+    tx.commit().await
+}
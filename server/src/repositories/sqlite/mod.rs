@@ -0,0 +1,75 @@
+//! Query SQLite dei repository
+//!
+//! Con la feature `sqlite` ogni repository controlla la variante di `DbPool` e, se il pool è
+//! SQLite, passa la chiamata alla funzione omonima di questo modulo. Le query MySQL sono
+//! verificate a compile time con `query!`; queste usano `query`/`query_as` con i parametri
+//! legati a runtime, perché le macro verificano contro un solo database.
+//!
+//! Differenze di dialetto rispetto alle query MySQL:
+//! - `ON DUPLICATE KEY UPDATE` diventa `ON CONFLICT (..) DO UPDATE SET col = excluded.col`
+//!   e `INSERT IGNORE` diventa `INSERT OR IGNORE`
+//! - `LIMIT ?, ?` diventa `LIMIT ? OFFSET ?`
+//! - SQLite non ha lock di riga: le transazioni che in MySQL leggono con `FOR UPDATE` o
+//!   `FOR SHARE` iniziano con `BEGIN IMMEDIATE`, che prende subito il lock di scrittura
+//!   sul database, e leggono senza clausole di lock
+//! - `NOW()` è sostituito da un `Utc::now()` legato come parametro, così le date restano
+//!   nel formato RFC 3339 che sqlx usa per `DateTime<Utc>` e si confrontano come testo
+//! - `UPDATE .. JOIN` diventa `UPDATE .. FROM`, `<=>` diventa `IS`, `GREATEST` diventa `max`
+//! - la ricerca full-text (`MATCH .. AGAINST`) diventa un `LIKE` sul contenuto
+
+use sqlx::migrate::Migrator;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions};
+use sqlx::{Error, Sqlite, SqlitePool, Transaction};
+use std::str::FromStr;
+use std::time::Duration;
+
+pub mod attachment;
+pub mod audit;
+pub mod banned_member;
+pub mod chat;
+pub mod chat_role;
+pub mod data_export;
+pub mod draft;
+pub mod health;
+pub mod invitation;
+pub mod invite_link;
+pub mod join_request;
+pub mod message;
+pub mod offline_queue;
+pub mod refresh_token;
+pub mod report;
+pub mod user;
+pub mod user_chat_metadata;
+pub mod user_identity;
+pub mod user_keys;
+pub mod webhook;
+
+/// Migrazioni dello schema SQLite, applicate all'avvio del server
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations_sqlite");
+
+/// Apre il pool SQLite, creando il file del database se non esiste
+///
+/// Il journal WAL permette letture concorrenti alla scrittura in corso; le scritture
+/// restano serializzate e attendono il lock fino a `busy_timeout`.
+pub async fn connect(url: &str, max_connections: u32) -> Result<SqlitePool, Error> {
+    let options = SqliteConnectOptions::from_str(url)?
+        .create_if_missing(true)
+        .journal_mode(SqliteJournalMode::Wal)
+        .foreign_keys(true)
+        .busy_timeout(Duration::from_secs(5));
+
+    SqlitePoolOptions::new()
+        .max_connections(max_connections)
+        .connect_with(options)
+        .await
+}
+
+/// Apre una transazione che prende subito il lock di scrittura
+///
+/// Sostituisce i `SELECT .. FOR UPDATE` delle query MySQL: due transazioni che leggono e
+/// poi scrivono le stesse righe vengono serializzate invece di fallire con `SQLITE_BUSY`.
+pub(crate) async fn begin_immediate(
+    pool: &SqlitePool,
+) -> Result<Transaction<'static, Sqlite>, Error> {
+    pool.begin_with("BEGIN IMMEDIATE").await
+}
@@ -0,0 +1,86 @@
+//! Query SQLite di `OfflineQueueRepository`
+
+use crate::entities::Message;
+use crate::repositories::query_log::timed;
+use chrono::{DateTime, Utc};
+use sqlx::{Error, QueryBuilder, Sqlite, SqlitePool};
+
+pub(crate) async fn enqueue(
+    pool: &SqlitePool,
+    message_id: &i32,
+    user_ids: &[i32],
+) -> Result<u64, Error> {
+    let now = Utc::now();
+    let mut query_builder = QueryBuilder::<Sqlite>::new(
+        "INSERT OR IGNORE INTO offline_queue (user_id, message_id, enqueued_at) ",
+    );
+    query_builder.push_values(user_ids, |mut row, user_id| {
+        row.push_bind(user_id).push_bind(message_id).push_bind(now);
+    });
+
+    let result = query_builder.build().execute(timed(pool)).await?;
+    Ok(result.rows_affected())
+}
+
+pub(crate) async fn find_pending(
+    pool: &SqlitePool,
+    user_id: &i32,
+    after_message_id: &i32,
+    limit: i64,
+) -> Result<Vec<Message>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT m.message_id, m.chat_id, m.sender_id, m.content, m.created_at, m.message_type,
+            m.content_format, m.reply_to_message_id, m.attachment_id, m.deleted_at
+        FROM offline_queue q
+        JOIN messages m ON m.message_id = q.message_id
+        JOIN userchatmetadata ucm ON ucm.user_id = q.user_id AND ucm.chat_id = m.chat_id
+        WHERE q.user_id = ?
+          AND q.delivered_at IS NULL
+          AND q.message_id > ?
+          AND m.created_at >= ucm.messages_visible_from
+        ORDER BY q.message_id ASC
+        LIMIT ?
+        "#,
+    )
+    .bind(user_id)
+    .bind(after_message_id)
+    .bind(limit)
+    .fetch_all(timed(pool))
+    .await
+}
+
+pub(crate) async fn mark_delivered(
+    pool: &SqlitePool,
+    user_id: &i32,
+    up_to_message_id: &i32,
+) -> Result<u64, Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE offline_queue
+        SET delivered_at = ?
+        WHERE user_id = ? AND message_id <= ? AND delivered_at IS NULL
+        "#,
+    )
+    .bind(Utc::now())
+    .bind(user_id)
+    .bind(up_to_message_id)
+    .execute(timed(pool))
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+pub(crate) async fn purge(
+    pool: &SqlitePool,
+    delivered_before: &DateTime<Utc>,
+    enqueued_before: &DateTime<Utc>,
+) -> Result<u64, Error> {
+    let result = sqlx::query("DELETE FROM offline_queue WHERE delivered_at < ? OR enqueued_at < ?")
+        .bind(delivered_before)
+        .bind(enqueued_before)
+        .execute(timed(pool))
+        .await?;
+
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,96 @@
+//! Query SQLite di `RefreshTokenRepository`
+
+use crate::dtos::CreateRefreshTokenDTO;
+use crate::entities::RefreshToken;
+use crate::repositories::query_log::timed;
+use chrono::Utc;
+use sqlx::{Error, SqlitePool};
+
+pub(crate) async fn find_by_hash(
+    pool: &SqlitePool,
+    token_hash: &str,
+) -> Result<Option<RefreshToken>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT token_id, user_id, family_id, token_hash, created_at, expires_at, used_at,
+            revoked_at
+        FROM refresh_tokens
+        WHERE token_hash = ?
+        "#,
+    )
+    .bind(token_hash)
+    .fetch_optional(timed(pool))
+    .await
+}
+
+pub(crate) async fn mark_used(pool: &SqlitePool, token_id: &i32) -> Result<bool, Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE refresh_tokens
+        SET used_at = ?
+        WHERE token_id = ? AND used_at IS NULL AND revoked_at IS NULL
+        "#,
+    )
+    .bind(Utc::now())
+    .bind(token_id)
+    .execute(timed(pool))
+    .await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+pub(crate) async fn revoke_family(pool: &SqlitePool, family_id: &str) -> Result<(), Error> {
+    sqlx::query(
+        "UPDATE refresh_tokens SET revoked_at = ? WHERE family_id = ? AND revoked_at IS NULL",
+    )
+    .bind(Utc::now())
+    .bind(family_id)
+    .execute(timed(pool))
+    .await?;
+
+    Ok(())
+}
+
+pub(crate) async fn revoke_all_by_user_id(pool: &SqlitePool, user_id: &i32) -> Result<(), Error> {
+    sqlx::query(
+        "UPDATE refresh_tokens SET revoked_at = ? WHERE user_id = ? AND revoked_at IS NULL",
+    )
+    .bind(Utc::now())
+    .bind(user_id)
+    .execute(timed(pool))
+    .await?;
+
+    Ok(())
+}
+
+pub(crate) async fn create(
+    pool: &SqlitePool,
+    data: &CreateRefreshTokenDTO,
+) -> Result<RefreshToken, Error> {
+    let now = Utc::now();
+
+    let result = sqlx::query(
+        r#"
+        INSERT INTO refresh_tokens (user_id, family_id, token_hash, created_at, expires_at)
+        VALUES (?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(data.user_id)
+    .bind(&data.family_id)
+    .bind(&data.token_hash)
+    .bind(now)
+    .bind(data.expires_at)
+    .execute(timed(pool))
+    .await?;
+
+    Ok(RefreshToken {
+        token_id: result.last_insert_rowid() as i32,
+        user_id: data.user_id,
+        family_id: data.family_id.clone(),
+        token_hash: data.token_hash.clone(),
+        created_at: now,
+        expires_at: data.expires_at,
+        used_at: None,
+        revoked_at: None,
+    })
+}
@@ -0,0 +1,126 @@
+//! Query SQLite di `ReportRepository`
+
+use crate::dtos::CreateReportDTO;
+use crate::entities::{Report, ReportStatus};
+use crate::repositories::Page;
+use crate::repositories::query_log::timed;
+use crate::repositories::report::ReportFilter;
+use chrono::Utc;
+use sqlx::{Error, SqlitePool};
+
+pub(crate) async fn has_pending_report(
+    pool: &SqlitePool,
+    reporter_id: &i32,
+    reported_user_id: &i32,
+    message_id: Option<i32>,
+) -> Result<bool, Error> {
+    // IS è l'equivalente SQLite di <=>: confronta anche i NULL
+    let count: i64 = sqlx::query_scalar(
+        r#"
+        SELECT COUNT(*) FROM reports
+        WHERE reporter_id = ? AND reported_user_id = ? AND message_id IS ? AND state = 'PENDING'
+        "#,
+    )
+    .bind(reporter_id)
+    .bind(reported_user_id)
+    .bind(message_id)
+    .fetch_one(timed(pool))
+    .await?;
+
+    Ok(count > 0)
+}
+
+pub(crate) async fn resolve(
+    pool: &SqlitePool,
+    report_id: &i32,
+    state: &ReportStatus,
+    resolved_by: &i32,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        UPDATE reports
+        SET state = ?, resolved_by = ?, resolved_at = ?
+        WHERE report_id = ? AND state = 'PENDING'
+        "#,
+    )
+    .bind(state)
+    .bind(resolved_by)
+    .bind(Utc::now())
+    .bind(report_id)
+    .execute(timed(pool))
+    .await?;
+
+    Ok(())
+}
+
+pub(crate) async fn read_many(
+    pool: &SqlitePool,
+    filter: &ReportFilter,
+    page: &Page,
+) -> Result<Vec<Report>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT report_id, reporter_id, reported_user_id, chat_id, message_id, reason, details,
+            state, created_at, resolved_by, resolved_at
+        FROM reports
+        WHERE (? IS NULL OR state = ?)
+        ORDER BY created_at, report_id
+        LIMIT ? OFFSET ?
+        "#,
+    )
+    .bind(&filter.state)
+    .bind(&filter.state)
+    .bind(page.limit)
+    .bind(page.offset)
+    .fetch_all(timed(pool))
+    .await
+}
+
+pub(crate) async fn create(pool: &SqlitePool, data: &CreateReportDTO) -> Result<Report, Error> {
+    let now = Utc::now();
+
+    let result = sqlx::query(
+        r#"
+        INSERT INTO reports (reporter_id, reported_user_id, chat_id, message_id, reason, details,
+            created_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(data.reporter_id)
+    .bind(data.reported_user_id)
+    .bind(data.chat_id)
+    .bind(data.message_id)
+    .bind(data.reason)
+    .bind(&data.details)
+    .bind(now)
+    .execute(timed(pool))
+    .await?;
+
+    Ok(Report {
+        report_id: result.last_insert_rowid() as i32,
+        reporter_id: data.reporter_id,
+        reported_user_id: data.reported_user_id,
+        chat_id: data.chat_id,
+        message_id: data.message_id,
+        reason: data.reason,
+        details: data.details.clone(),
+        state: ReportStatus::Pending,
+        created_at: now,
+        resolved_by: None,
+        resolved_at: None,
+    })
+}
+
+pub(crate) async fn read(pool: &SqlitePool, id: &i32) -> Result<Option<Report>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT report_id, reporter_id, reported_user_id, chat_id, message_id, reason, details,
+            state, created_at, resolved_by, resolved_at
+        FROM reports
+        WHERE report_id = ?
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(timed(pool))
+    .await
+}
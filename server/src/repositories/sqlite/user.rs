@@ -0,0 +1,233 @@
+//! Query SQLite di `UserRepository`
+
+use crate::dtos::{CreateUserDTO, UpdateProfileDTO};
+use crate::entities::{PresenceVisibility, User};
+use crate::repositories::Page;
+use crate::repositories::query_log::timed;
+use crate::repositories::user::UserFilter;
+use chrono::{DateTime, Utc};
+use sqlx::{Error, QueryBuilder, Sqlite, SqlitePool};
+
+pub(crate) async fn find_by_username(
+    pool: &SqlitePool,
+    username: &str,
+) -> Result<Option<User>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT user_id, username, password, display_name, bio, avatar_url, status_text,
+            status_expires_at, last_seen, presence_visibility, deactivated_at
+        FROM users
+        WHERE username = ?
+        "#,
+    )
+    .bind(username)
+    .fetch_optional(timed(pool))
+    .await
+}
+
+pub(crate) async fn search_by_username_partial(
+    pool: &SqlitePool,
+    pattern: &str,
+) -> Result<Vec<User>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT user_id, username, password, display_name, bio, avatar_url, status_text,
+            status_expires_at, last_seen, presence_visibility, deactivated_at
+        FROM users
+        WHERE username LIKE ? AND deactivated_at IS NULL
+        LIMIT 10
+        "#,
+    )
+    .bind(pattern)
+    .fetch_all(timed(pool))
+    .await
+}
+
+pub(crate) async fn update_last_seen(
+    pool: &SqlitePool,
+    user_id: &i32,
+    last_seen: &DateTime<Utc>,
+) -> Result<(), Error> {
+    sqlx::query("UPDATE users SET last_seen = ? WHERE user_id = ?")
+        .bind(last_seen)
+        .bind(user_id)
+        .execute(timed(pool))
+        .await?;
+    Ok(())
+}
+
+pub(crate) async fn set_deactivated_at(
+    pool: &SqlitePool,
+    user_id: &i32,
+    deactivated_at: Option<DateTime<Utc>>,
+) -> Result<(), Error> {
+    sqlx::query("UPDATE users SET deactivated_at = ? WHERE user_id = ?")
+        .bind(deactivated_at)
+        .bind(user_id)
+        .execute(timed(pool))
+        .await?;
+    Ok(())
+}
+
+pub(crate) async fn find_deactivated_before(
+    pool: &SqlitePool,
+    cutoff: &DateTime<Utc>,
+) -> Result<Vec<User>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT user_id, username, password, display_name, bio, avatar_url, status_text,
+            status_expires_at, last_seen, presence_visibility, deactivated_at
+        FROM users
+        WHERE deactivated_at IS NOT NULL AND deactivated_at < ?
+        "#,
+    )
+    .bind(cutoff)
+    .fetch_all(timed(pool))
+    .await
+}
+
+pub(crate) async fn update_presence_visibility(
+    pool: &SqlitePool,
+    user_id: &i32,
+    visibility: &PresenceVisibility,
+) -> Result<(), Error> {
+    sqlx::query("UPDATE users SET presence_visibility = ? WHERE user_id = ?")
+        .bind(visibility)
+        .bind(user_id)
+        .execute(timed(pool))
+        .await?;
+    Ok(())
+}
+
+pub(crate) async fn update_status(
+    pool: &SqlitePool,
+    user_id: &i32,
+    status_text: Option<&str>,
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<(), Error> {
+    sqlx::query("UPDATE users SET status_text = ?, status_expires_at = ? WHERE user_id = ?")
+        .bind(status_text)
+        .bind(expires_at)
+        .bind(user_id)
+        .execute(timed(pool))
+        .await?;
+    Ok(())
+}
+
+/// Aggiorna i campi del profilo presenti nel DTO; il DTO ha almeno un campo valorizzato
+pub(crate) async fn update_profile(
+    pool: &SqlitePool,
+    user_id: &i32,
+    data: &UpdateProfileDTO,
+) -> Result<(), Error> {
+    let mut query_builder = QueryBuilder::<Sqlite>::new("UPDATE users SET ");
+
+    let mut separated = query_builder.separated(", ");
+    if let Some(ref display_name) = data.display_name {
+        separated.push("display_name = NULLIF(");
+        separated.push_bind_unseparated(display_name);
+        separated.push_unseparated(", '')");
+    }
+    if let Some(ref bio) = data.bio {
+        separated.push("bio = NULLIF(");
+        separated.push_bind_unseparated(bio);
+        separated.push_unseparated(", '')");
+    }
+    if let Some(ref avatar_url) = data.avatar_url {
+        separated.push("avatar_url = NULLIF(");
+        separated.push_bind_unseparated(avatar_url);
+        separated.push_unseparated(", '')");
+    }
+
+    query_builder.push(" WHERE user_id = ");
+    query_builder.push_bind(user_id);
+
+    query_builder.build().execute(timed(pool)).await?;
+    Ok(())
+}
+
+pub(crate) async fn read_many(
+    pool: &SqlitePool,
+    filter: &UserFilter,
+    page: &Page,
+) -> Result<Vec<User>, Error> {
+    let pattern = filter.search.as_ref().map(|search| format!("%{}%", search));
+    // gli id sono passati come array JSON e letti con json_each, come JSON_TABLE su MySQL
+    let ids = filter
+        .ids
+        .as_ref()
+        .map(|ids| serde_json::to_string(ids).unwrap_or_else(|_| "[]".to_string()));
+
+    sqlx::query_as(
+        r#"
+        SELECT user_id, username, password, display_name, bio, avatar_url, status_text,
+            status_expires_at, last_seen, presence_visibility, deactivated_at
+        FROM users
+        WHERE (? IS NULL OR username LIKE ?)
+          AND (? IS NULL OR user_id IN (SELECT value FROM json_each(?)))
+        ORDER BY user_id
+        LIMIT ? OFFSET ?
+        "#,
+    )
+    .bind(&pattern)
+    .bind(&pattern)
+    .bind(&ids)
+    .bind(&ids)
+    .bind(page.limit)
+    .bind(page.offset)
+    .fetch_all(timed(pool))
+    .await
+}
+
+/// Inserisce l'utente e restituisce il suo id
+pub(crate) async fn insert(pool: &SqlitePool, data: &CreateUserDTO) -> Result<i32, Error> {
+    let result = sqlx::query("INSERT INTO users (username, password) VALUES (?, ?)")
+        .bind(&data.username)
+        .bind(&data.password)
+        .execute(timed(pool))
+        .await?;
+
+    Ok(result.last_insert_rowid() as i32)
+}
+
+pub(crate) async fn read(pool: &SqlitePool, id: &i32) -> Result<Option<User>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT user_id, username, password, display_name, bio, avatar_url, status_text,
+            status_expires_at, last_seen, presence_visibility, deactivated_at
+        FROM users
+        WHERE user_id = ?
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(timed(pool))
+    .await
+}
+
+pub(crate) async fn update_password(
+    pool: &SqlitePool,
+    id: &i32,
+    password: &str,
+) -> Result<(), Error> {
+    sqlx::query("UPDATE users SET password = ? WHERE user_id = ?")
+        .bind(password)
+        .bind(id)
+        .execute(timed(pool))
+        .await?;
+    Ok(())
+}
+
+pub(crate) async fn delete(pool: &SqlitePool, user_id: &i32) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        UPDATE users
+        SET username = 'Deleted User', password = '', display_name = NULL, bio = NULL,
+            avatar_url = NULL, status_text = NULL, status_expires_at = NULL, deactivated_at = NULL
+        WHERE user_id = ?
+        "#,
+    )
+    .bind(user_id)
+    .execute(timed(pool))
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,417 @@
+//! Query SQLite di `UserChatMetadataRepository`
+
+use super::begin_immediate;
+use crate::dtos::{CreateUserChatMetadataDTO, UnreadCountDTO, UpdateUserChatMetadataDTO};
+use crate::entities::{UserChatMetadata, UserRole};
+use crate::repositories::Page;
+use crate::repositories::query_log::timed;
+use crate::repositories::user_chat_metadata::{DeliveredBatch, MemberFilter, UserChatKey};
+use chrono::{DateTime, Utc};
+use sqlx::{Error, QueryBuilder, Sqlite, SqliteConnection, SqlitePool};
+
+pub(crate) async fn find_members(
+    pool: &SqlitePool,
+    filter: &MemberFilter,
+    page: &Page,
+) -> Result<Vec<UserChatMetadata>, Error> {
+    // array JSON espanso da json_each: un solo parametro qualunque sia il numero di chat
+    let chat_ids = filter
+        .chat_ids
+        .as_ref()
+        .map(|ids| serde_json::to_string(ids).unwrap_or_else(|_| "[]".to_string()));
+
+    sqlx::query_as(
+        r#"
+        SELECT user_id, chat_id, user_role, role_id, member_since, messages_visible_from,
+            messages_received_until, pinned_at, muted_until, version
+        FROM userchatmetadata
+        WHERE (? IS NULL OR chat_id = ?)
+          AND (? IS NULL OR chat_id IN (SELECT value FROM json_each(?)))
+          AND (? IS NULL OR user_id = ?)
+          AND user_id > ?
+          AND (? IS NULL OR user_role = ?)
+        ORDER BY chat_id, user_id
+        LIMIT ? OFFSET ?
+        "#,
+    )
+    .bind(filter.chat_id)
+    .bind(filter.chat_id)
+    .bind(&chat_ids)
+    .bind(&chat_ids)
+    .bind(filter.user_id)
+    .bind(filter.user_id)
+    .bind(filter.after_user_id)
+    .bind(&filter.role)
+    .bind(&filter.role)
+    .bind(page.limit)
+    .bind(page.offset)
+    .fetch_all(timed(pool))
+    .await
+}
+
+pub(crate) async fn transfer_ownership(
+    pool: &SqlitePool,
+    from_user_id: &i32,
+    to_user_id: &i32,
+    chat_id: &i32,
+) -> Result<(), Error> {
+    let mut tx = begin_immediate(pool).await?;
+    swap_owner(&mut tx, from_user_id, to_user_id, chat_id).await?;
+    tx.commit().await
+}
+
+pub(crate) async fn swap_owner(
+    conn: &mut SqliteConnection,
+    from_user_id: &i32,
+    to_user_id: &i32,
+    chat_id: &i32,
+) -> Result<(), Error> {
+    // entrambi i membri devono esistere
+    for user_id in [from_user_id, to_user_id] {
+        sqlx::query_scalar::<_, i32>(
+            "SELECT user_id FROM userchatmetadata WHERE user_id = ? AND chat_id = ?",
+        )
+        .bind(user_id)
+        .bind(chat_id)
+        .fetch_optional(timed(&mut *conn))
+        .await?
+        .ok_or(Error::RowNotFound)?;
+    }
+
+    for (user_id, role) in [(from_user_id, "ADMIN"), (to_user_id, "OWNER")] {
+        sqlx::query(
+            r#"
+            UPDATE userchatmetadata
+            SET user_role = ?1,
+                role_id = (SELECT r.role_id FROM chat_roles r
+                    WHERE r.chat_id = userchatmetadata.chat_id AND r.base_role = ?1),
+                version = version + 1
+            WHERE user_id = ?2 AND chat_id = ?3
+            "#,
+        )
+        .bind(role)
+        .bind(user_id)
+        .bind(chat_id)
+        .execute(timed(&mut *conn))
+        .await?;
+    }
+
+    Ok(())
+}
+
+pub(crate) async fn count_unread_by_user_id(
+    pool: &SqlitePool,
+    user_id: &i32,
+) -> Result<Vec<UnreadCountDTO>, Error> {
+    sqlx::query_as("SELECT chat_id, unread_count AS unread FROM userchatmetadata WHERE user_id = ?")
+        .bind(user_id)
+        .fetch_all(timed(pool))
+        .await
+}
+
+pub(crate) async fn create_many(
+    pool: &SqlitePool,
+    metadata_list: &[CreateUserChatMetadataDTO],
+) -> Result<Vec<UserChatMetadata>, Error> {
+    let mut tx = begin_immediate(pool).await?;
+
+    let mut created = Vec::with_capacity(metadata_list.len());
+    for data in metadata_list {
+        created.push(insert(&mut tx, data).await?);
+    }
+
+    tx.commit().await?;
+    Ok(created)
+}
+
+pub(crate) async fn remove(conn: &mut SqliteConnection, id: &UserChatKey) -> Result<(), Error> {
+    sqlx::query("DELETE FROM userchatmetadata WHERE user_id = ? AND chat_id = ?")
+        .bind(id.0)
+        .bind(id.1)
+        .execute(timed(conn))
+        .await?;
+    Ok(())
+}
+
+pub(crate) async fn insert(
+    conn: &mut SqliteConnection,
+    data: &CreateUserChatMetadataDTO,
+) -> Result<UserChatMetadata, Error> {
+    // il membro punta alla riga del suo ruolo base nella chat
+    let role_id: Option<i32> =
+        sqlx::query_scalar("SELECT role_id FROM chat_roles WHERE chat_id = ? AND base_role = ?")
+            .bind(data.chat_id)
+            .bind(&data.user_role)
+            .fetch_optional(timed(&mut *conn))
+            .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO userchatmetadata (user_id, chat_id, user_role, role_id, member_since,
+            messages_visible_from, messages_received_until)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(data.user_id)
+    .bind(data.chat_id)
+    .bind(&data.user_role)
+    .bind(role_id)
+    .bind(data.member_since)
+    .bind(data.messages_visible_from)
+    .bind(data.messages_received_until)
+    .execute(timed(conn))
+    .await?;
+
+    Ok(UserChatMetadata {
+        user_id: data.user_id,
+        chat_id: data.chat_id,
+        user_role: data.user_role.clone(),
+        member_since: data.member_since,
+        messages_visible_from: data.messages_visible_from,
+        messages_received_until: data.messages_received_until,
+        pinned_at: None,
+        muted_until: None,
+        version: 0,
+        role_id,
+    })
+}
+
+pub(crate) async fn count_by_chat_id(pool: &SqlitePool, chat_id: &i32) -> Result<i64, Error> {
+    sqlx::query_scalar("SELECT COUNT(*) FROM userchatmetadata WHERE chat_id = ?")
+        .bind(chat_id)
+        .fetch_one(timed(pool))
+        .await
+}
+
+pub(crate) async fn share_any_chat(
+    pool: &SqlitePool,
+    user_id: &i32,
+    other_user_id: &i32,
+) -> Result<bool, Error> {
+    let count: i64 = sqlx::query_scalar(
+        r#"
+        SELECT COUNT(*)
+        FROM userchatmetadata a
+        INNER JOIN userchatmetadata b ON a.chat_id = b.chat_id
+        WHERE a.user_id = ? AND b.user_id = ?
+        "#,
+    )
+    .bind(user_id)
+    .bind(other_user_id)
+    .fetch_one(timed(pool))
+    .await?;
+
+    Ok(count > 0)
+}
+
+pub(crate) async fn set_pinned(
+    pool: &SqlitePool,
+    user_id: &i32,
+    chat_id: &i32,
+    pinned_at: Option<DateTime<Utc>>,
+) -> Result<(), Error> {
+    sqlx::query("UPDATE userchatmetadata SET pinned_at = ? WHERE user_id = ? AND chat_id = ?")
+        .bind(pinned_at)
+        .bind(user_id)
+        .bind(chat_id)
+        .execute(timed(pool))
+        .await?;
+    Ok(())
+}
+
+pub(crate) async fn set_custom_role(
+    pool: &SqlitePool,
+    user_id: &i32,
+    chat_id: &i32,
+    role_id: Option<i32>,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        UPDATE userchatmetadata
+        SET role_id = COALESCE(?, (SELECT r.role_id FROM chat_roles r
+            WHERE r.chat_id = userchatmetadata.chat_id AND r.base_role = 'MEMBER'))
+        WHERE user_id = ? AND chat_id = ?
+        "#,
+    )
+    .bind(role_id)
+    .bind(user_id)
+    .bind(chat_id)
+    .execute(timed(pool))
+    .await?;
+    Ok(())
+}
+
+pub(crate) async fn set_muted_until(
+    pool: &SqlitePool,
+    user_id: &i32,
+    chat_id: &i32,
+    muted_until: Option<DateTime<Utc>>,
+) -> Result<(), Error> {
+    sqlx::query("UPDATE userchatmetadata SET muted_until = ? WHERE user_id = ? AND chat_id = ?")
+        .bind(muted_until)
+        .bind(user_id)
+        .bind(chat_id)
+        .execute(timed(pool))
+        .await?;
+    Ok(())
+}
+
+pub(crate) async fn advance_received_until(
+    pool: &SqlitePool,
+    user_id: &i32,
+    received: &[DeliveredBatch],
+) -> Result<(), Error> {
+    let mut tx = begin_immediate(pool).await?;
+
+    for batch in received {
+        sqlx::query(
+            r#"
+            UPDATE userchatmetadata
+            SET unread_count = CASE
+                    WHEN ? >= (SELECT MAX(m.message_id) FROM messages m WHERE m.chat_id = ?)
+                    THEN 0
+                    ELSE max(unread_count - ?, 0)
+                END,
+                messages_received_until = ?
+            WHERE user_id = ? AND chat_id = ? AND messages_received_until < ?
+            "#,
+        )
+        .bind(batch.last_message_id)
+        .bind(batch.chat_id)
+        .bind(batch.unread)
+        .bind(batch.received_until)
+        .bind(user_id)
+        .bind(batch.chat_id)
+        .bind(batch.received_until)
+        .execute(timed(&mut *tx))
+        .await?;
+    }
+
+    tx.commit().await
+}
+
+/// Cambia il ruolo del membro se la riga è ancora alla versione attesa
+///
+/// Restituisce il numero di righe aggiornate.
+pub(crate) async fn update_user_role(
+    pool: &SqlitePool,
+    user_id: &i32,
+    chat_id: &i32,
+    new_role: &UserRole,
+    expected_version: &i32,
+) -> Result<u64, Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE userchatmetadata
+        SET user_role = ?1,
+            role_id = (SELECT r.role_id FROM chat_roles r
+                WHERE r.chat_id = userchatmetadata.chat_id AND r.base_role = ?1),
+            version = version + 1
+        WHERE user_id = ?2 AND chat_id = ?3 AND version = ?4
+        "#,
+    )
+    .bind(new_role)
+    .bind(user_id)
+    .bind(chat_id)
+    .bind(expected_version)
+    .execute(timed(pool))
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+pub(crate) async fn create(
+    pool: &SqlitePool,
+    data: &CreateUserChatMetadataDTO,
+) -> Result<UserChatMetadata, Error> {
+    insert(&mut *pool.acquire().await?, data).await
+}
+
+pub(crate) async fn read(
+    pool: &SqlitePool,
+    id: &UserChatKey,
+) -> Result<Option<UserChatMetadata>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT user_id, chat_id, user_role, role_id, member_since, messages_visible_from,
+            messages_received_until, pinned_at, muted_until, version
+        FROM userchatmetadata
+        WHERE user_id = ? AND chat_id = ?
+        "#,
+    )
+    .bind(id.0)
+    .bind(id.1)
+    .fetch_optional(timed(pool))
+    .await
+}
+
+/// Aggiorna i campi presenti nel DTO se la riga è ancora a `expected_version`
+///
+/// Restituisce il numero di righe aggiornate.
+pub(crate) async fn update(
+    pool: &SqlitePool,
+    id: &UserChatKey,
+    data: &UpdateUserChatMetadataDTO,
+    expected_version: i32,
+) -> Result<u64, Error> {
+    let mut query_builder = QueryBuilder::<Sqlite>::new("UPDATE userchatmetadata SET ");
+
+    let mut separated = query_builder.separated(", ");
+    separated.push("version = version + 1");
+    if let Some(ref role) = data.user_role {
+        separated.push("user_role = ");
+        separated.push_bind_unseparated(role);
+        separated.push(
+            "role_id = (SELECT r.role_id FROM chat_roles r \
+             WHERE r.chat_id = userchatmetadata.chat_id AND r.base_role = ",
+        );
+        separated.push_bind_unseparated(role);
+        separated.push_unseparated(")");
+    }
+    if let Some(ref visible_from) = data.messages_visible_from {
+        separated.push("messages_visible_from = ");
+        separated.push_bind_unseparated(visible_from);
+    }
+    if let Some(ref received_until) = data.messages_received_until {
+        separated.push("messages_received_until = ");
+        separated.push_bind_unseparated(received_until);
+    }
+    if data.messages_visible_from.is_some() || data.messages_received_until.is_some() {
+        // SQLite valuta ogni SET sui valori precedenti all'UPDATE (MySQL da sinistra a destra):
+        // il controllo usa i nuovi marker legati di nuovo come parametri
+        separated.push("unread_count = CASE WHEN ");
+        match data.messages_received_until {
+            Some(received_until) => separated.push_bind_unseparated(received_until),
+            None => separated.push_unseparated("messages_received_until"),
+        };
+        separated.push_unseparated(
+            " >= (SELECT MAX(m.created_at) FROM messages m \
+             WHERE m.chat_id = userchatmetadata.chat_id) OR ",
+        );
+        match data.messages_visible_from {
+            Some(visible_from) => separated.push_bind_unseparated(visible_from),
+            None => separated.push_unseparated("messages_visible_from"),
+        };
+        separated.push_unseparated(
+            " > (SELECT MAX(m.created_at) FROM messages m \
+             WHERE m.chat_id = userchatmetadata.chat_id) \
+             THEN 0 ELSE unread_count END",
+        );
+    }
+
+    query_builder.push(" WHERE user_id = ");
+    query_builder.push_bind(id.0);
+
+    query_builder.push(" AND chat_id = ");
+    query_builder.push_bind(id.1);
+
+    query_builder.push(" AND version = ");
+    query_builder.push_bind(expected_version);
+
+    let result = query_builder.build().execute(timed(pool)).await?;
+    Ok(result.rows_affected())
+}
+
+pub(crate) async fn delete(pool: &SqlitePool, id: &UserChatKey) -> Result<(), Error> {
+    remove(&mut *pool.acquire().await?, id).await
+}
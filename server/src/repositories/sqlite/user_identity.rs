@@ -0,0 +1,50 @@
+//! Query SQLite di `UserIdentityRepository`
+
+use crate::dtos::CreateUserIdentityDTO;
+use crate::entities::UserIdentity;
+use crate::repositories::query_log::timed;
+use chrono::Utc;
+use sqlx::{Error, SqlitePool};
+
+pub(crate) async fn find_by_subject(
+    pool: &SqlitePool,
+    issuer: &str,
+    subject: &str,
+) -> Result<Option<UserIdentity>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT identity_id, user_id, issuer, subject, created_at
+        FROM user_identities
+        WHERE issuer = ? AND subject = ?
+        "#,
+    )
+    .bind(issuer)
+    .bind(subject)
+    .fetch_optional(timed(pool))
+    .await
+}
+
+pub(crate) async fn create(
+    pool: &SqlitePool,
+    data: &CreateUserIdentityDTO,
+) -> Result<UserIdentity, Error> {
+    let now = Utc::now();
+
+    let result = sqlx::query(
+        "INSERT INTO user_identities (user_id, issuer, subject, created_at) VALUES (?, ?, ?, ?)",
+    )
+    .bind(data.user_id)
+    .bind(&data.issuer)
+    .bind(&data.subject)
+    .bind(now)
+    .execute(timed(pool))
+    .await?;
+
+    Ok(UserIdentity {
+        identity_id: result.last_insert_rowid() as i32,
+        user_id: data.user_id,
+        issuer: data.issuer.clone(),
+        subject: data.subject.clone(),
+        created_at: now,
+    })
+}
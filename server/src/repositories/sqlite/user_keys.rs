@@ -0,0 +1,126 @@
+//! Query SQLite di `UserKeysRepository`
+
+use super::begin_immediate;
+use crate::dtos::UploadKeysDTO;
+use crate::entities::{OneTimePrekey, UserKeys};
+use crate::repositories::query_log::timed;
+use chrono::Utc;
+use sqlx::{Error, SqlitePool};
+
+pub(crate) async fn upsert(
+    pool: &SqlitePool,
+    user_id: &i32,
+    keys: &UploadKeysDTO,
+) -> Result<i64, Error> {
+    let mut tx = begin_immediate(pool).await?;
+
+    let previous_identity: Option<String> =
+        sqlx::query_scalar("SELECT identity_key FROM user_keys WHERE user_id = ?")
+            .bind(user_id)
+            .fetch_optional(timed(&mut *tx))
+            .await?;
+    if previous_identity.is_some_and(|identity| identity != keys.identity_key) {
+        sqlx::query("DELETE FROM one_time_prekeys WHERE user_id = ?")
+            .bind(user_id)
+            .execute(timed(&mut *tx))
+            .await?;
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO user_keys (user_id, identity_key, signed_prekey_id, signed_prekey,
+            signed_prekey_signature, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?)
+        ON CONFLICT (user_id) DO UPDATE SET
+            identity_key = excluded.identity_key,
+            signed_prekey_id = excluded.signed_prekey_id,
+            signed_prekey = excluded.signed_prekey,
+            signed_prekey_signature = excluded.signed_prekey_signature,
+            updated_at = excluded.updated_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(&keys.identity_key)
+    .bind(keys.signed_prekey.key_id)
+    .bind(&keys.signed_prekey.public_key)
+    .bind(&keys.signed_prekey.signature)
+    .bind(Utc::now())
+    .execute(timed(&mut *tx))
+    .await?;
+
+    for prekey in &keys.one_time_prekeys {
+        sqlx::query(
+            r#"
+            INSERT INTO one_time_prekeys (user_id, prekey_id, public_key)
+            VALUES (?, ?, ?)
+            ON CONFLICT (user_id, prekey_id) DO UPDATE SET public_key = excluded.public_key
+            "#,
+        )
+        .bind(user_id)
+        .bind(prekey.key_id)
+        .bind(&prekey.public_key)
+        .execute(timed(&mut *tx))
+        .await?;
+    }
+
+    let available: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM one_time_prekeys WHERE user_id = ?")
+            .bind(user_id)
+            .fetch_one(timed(&mut *tx))
+            .await?;
+
+    tx.commit().await?;
+    Ok(available)
+}
+
+pub(crate) async fn count_one_time_prekeys(pool: &SqlitePool, user_id: &i32) -> Result<i64, Error> {
+    sqlx::query_scalar("SELECT COUNT(*) FROM one_time_prekeys WHERE user_id = ?")
+        .bind(user_id)
+        .fetch_one(timed(pool))
+        .await
+}
+
+pub(crate) async fn take_one_time_prekey(
+    pool: &SqlitePool,
+    user_id: &i32,
+) -> Result<Option<OneTimePrekey>, Error> {
+    let mut tx = begin_immediate(pool).await?;
+
+    let prekey: Option<OneTimePrekey> = sqlx::query_as(
+        r#"
+        SELECT user_id, prekey_id, public_key
+        FROM one_time_prekeys
+        WHERE user_id = ?
+        ORDER BY prekey_id
+        LIMIT 1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(timed(&mut *tx))
+    .await?;
+
+    if let Some(prekey) = &prekey {
+        sqlx::query("DELETE FROM one_time_prekeys WHERE user_id = ? AND prekey_id = ?")
+            .bind(prekey.user_id)
+            .bind(prekey.prekey_id)
+            .execute(timed(&mut *tx))
+            .await?;
+    }
+
+    tx.commit().await?;
+    Ok(prekey)
+}
+
+pub(crate) async fn read(pool: &SqlitePool, id: &i32) -> Result<Option<UserKeys>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT user_id, identity_key, signed_prekey_id, signed_prekey, signed_prekey_signature,
+            updated_at
+        FROM user_keys
+        WHERE user_id = ?
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(timed(pool))
+    .await
+}
@@ -0,0 +1,91 @@
+//! Query SQLite di `WebhookRepository`
+
+use crate::dtos::NewWebhookDTO;
+use crate::entities::Webhook;
+use crate::repositories::Page;
+use crate::repositories::query_log::timed;
+use crate::repositories::webhook::WebhookFilter;
+use chrono::{DateTime, Utc};
+use sqlx::{Error, SqlitePool};
+
+pub(crate) async fn find_by_token_hash(
+    pool: &SqlitePool,
+    token_hash: &str,
+) -> Result<Option<Webhook>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT webhook_id, chat_id, bot_user_id, name, token_hash, created_by, created_at
+        FROM webhooks
+        WHERE token_hash = ?
+        "#,
+    )
+    .bind(token_hash)
+    .fetch_optional(timed(pool))
+    .await
+}
+
+pub(crate) async fn read_many(
+    pool: &SqlitePool,
+    filter: &WebhookFilter,
+    page: &Page,
+) -> Result<Vec<Webhook>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT webhook_id, chat_id, bot_user_id, name, token_hash, created_by, created_at
+        FROM webhooks
+        WHERE chat_id = ?
+        ORDER BY webhook_id
+        LIMIT ? OFFSET ?
+        "#,
+    )
+    .bind(filter.chat_id)
+    .bind(page.limit)
+    .bind(page.offset)
+    .fetch_all(timed(pool))
+    .await
+}
+
+/// Inserisce il webhook e restituisce il suo id
+pub(crate) async fn insert(
+    pool: &SqlitePool,
+    data: &NewWebhookDTO,
+    now: DateTime<Utc>,
+) -> Result<i32, Error> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO webhooks (chat_id, bot_user_id, name, token_hash, created_by, created_at)
+        VALUES (?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(data.chat_id)
+    .bind(data.bot_user_id)
+    .bind(&data.name)
+    .bind(&data.token_hash)
+    .bind(data.created_by)
+    .bind(now)
+    .execute(timed(pool))
+    .await?;
+
+    Ok(result.last_insert_rowid() as i32)
+}
+
+pub(crate) async fn read(pool: &SqlitePool, id: &i32) -> Result<Option<Webhook>, Error> {
+    sqlx::query_as(
+        r#"
+        SELECT webhook_id, chat_id, bot_user_id, name, token_hash, created_by, created_at
+        FROM webhooks
+        WHERE webhook_id = ?
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(timed(pool))
+    .await
+}
+
+pub(crate) async fn delete(pool: &SqlitePool, id: &i32) -> Result<(), Error> {
+    sqlx::query("DELETE FROM webhooks WHERE webhook_id = ?")
+        .bind(id)
+        .execute(timed(pool))
+        .await?;
+    Ok(())
+}
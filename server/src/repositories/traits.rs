@@ -75,3 +75,56 @@ pub trait Delete<Id> {
     /// * `Err(sqlx::Error)` - Error during deletion
     async fn delete(&self, id: &Id) -> Result<(), sqlx::Error>;
 }
+
+/// Trait for reading the entities matching a filter
+///
+/// Each repository defines its own filter struct next to the implementation; the fields left
+/// to `None` do not restrict the result. The order is part of the filter when a repository
+/// supports more than one, otherwise it is the one documented on the filter.
+///
+/// # Type Parameters
+/// * `Entity` - Type of the returned entities
+/// * `Filter` - Conditions the entities must satisfy
+#[allow(async_fn_in_trait)]
+pub trait ReadMany<Entity, Filter> {
+    /// Reads one page of the entities matching the filter
+    ///
+    /// # Arguments
+    /// * `filter` - Conditions (and order) of the read
+    /// * `page` - Slice of the ordered result to return
+    ///
+    /// # Returns
+    /// * `Ok(Vec<Entity>)` - Matching entities, possibly empty
+    /// * `Err(sqlx::Error)` - Error during reading
+    async fn read_many(&self, filter: &Filter, page: &Page) -> Result<Vec<Entity>, sqlx::Error>;
+}
+
+/// Slice of an ordered result, as `LIMIT`/`OFFSET`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Page {
+    pub limit: i64,
+    pub offset: i64,
+}
+
+impl Page {
+    /// The whole result
+    pub const ALL: Page = Page {
+        limit: i64::MAX,
+        offset: 0,
+    };
+
+    /// The first `limit` entities
+    pub fn first(limit: i64) -> Self {
+        Self { limit, offset: 0 }
+    }
+
+    pub fn new(limit: i64, offset: i64) -> Self {
+        Self { limit, offset }
+    }
+}
+
+impl Default for Page {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
@@ -0,0 +1,104 @@
+//! UnitOfWork - Transazione condivisa tra più repository
+
+#[cfg(feature = "sqlite")]
+use super::sqlite;
+use super::{DbPool, RepositoryCache};
+use sqlx::{Error, MySql, MySqlConnection, Transaction};
+#[cfg(feature = "sqlite")]
+use sqlx::{Sqlite, SqliteConnection};
+use tracing::debug;
+
+/// A transaction on the primary database shared by the repositories taking part in one
+/// service flow
+///
+/// Repository methods ending in `_in` write through it. Nothing is visible to other
+/// connections until `commit`; dropping the unit of work without committing rolls back.
+pub struct UnitOfWork {
+    tx: Tx,
+    cache: Option<RepositoryCache>,
+    // chat con appartenenze scritte nella transazione, da togliere dalla cache dopo il commit
+    touched_chats: Vec<i32>,
+}
+
+/// Transazione aperta sul backend del pool
+enum Tx {
+    MySql(Transaction<'static, MySql>),
+    #[cfg(feature = "sqlite")]
+    Sqlite(Transaction<'static, Sqlite>),
+}
+
+impl UnitOfWork {
+    /// Start a transaction; `cache` is the repository cache to invalidate on commit, if any
+    ///
+    /// On SQLite the transaction takes the write lock immediately, in place of the row locks
+    /// (`FOR UPDATE`) that the MySQL queries take along the way.
+    pub async fn begin(pool: &DbPool, cache: Option<RepositoryCache>) -> Result<Self, Error> {
+        let tx = match pool {
+            DbPool::MySql(pool) => Tx::MySql(pool.begin().await?),
+            #[cfg(feature = "sqlite")]
+            DbPool::Sqlite(pool) => Tx::Sqlite(sqlite::begin_immediate(pool).await?),
+        };
+        Ok(Self {
+            tx,
+            cache,
+            touched_chats: Vec::new(),
+        })
+    }
+
+    /// Connection of the transaction, to be used as executor by the repositories
+    ///
+    /// The repositories check `sqlite_conn` first, so here the transaction is always MySQL.
+    pub(crate) fn conn(&mut self) -> &mut MySqlConnection {
+        match &mut self.tx {
+            Tx::MySql(tx) => tx,
+            #[cfg(feature = "sqlite")]
+            Tx::Sqlite(_) => unreachable!("MySQL query on a SQLite unit of work"),
+        }
+    }
+
+    /// Connection of the transaction if the unit of work runs on SQLite
+    #[cfg(feature = "sqlite")]
+    pub(crate) fn sqlite_conn(&mut self) -> Option<&mut SqliteConnection> {
+        match &mut self.tx {
+            Tx::Sqlite(tx) => Some(tx),
+            Tx::MySql(_) => None,
+        }
+    }
+
+    /// Record that memberships of `chat_id` were written in this transaction
+    ///
+    /// The cached memberships of the chat are invalidated only after the commit: doing it
+    /// earlier would let a concurrent read cache the state from before the transaction.
+    pub(crate) fn touch_chat_members(&mut self, chat_id: i32) {
+        if !self.touched_chats.contains(&chat_id) {
+            self.touched_chats.push(chat_id);
+        }
+    }
+
+    /// Make every write of the unit of work visible at once
+    pub async fn commit(self) -> Result<(), Error> {
+        match self.tx {
+            Tx::MySql(tx) => tx.commit().await?,
+            #[cfg(feature = "sqlite")]
+            Tx::Sqlite(tx) => tx.commit().await?,
+        }
+
+        if let Some(cache) = &self.cache {
+            for chat_id in &self.touched_chats {
+                cache.invalidate_chat(*chat_id).await;
+            }
+        }
+
+        debug!("Unit of work committed");
+        Ok(())
+    }
+
+    /// Discard every write of the unit of work
+    pub async fn rollback(self) -> Result<(), Error> {
+        match self.tx {
+            Tx::MySql(tx) => tx.rollback().await,
+            #[cfg(feature = "sqlite")]
+            Tx::Sqlite(tx) => tx.rollback().await,
+        }
+    }
+}
@@ -1,19 +1,37 @@
 //! UserRepository - Repository per la gestione degli utenti
 
-use super::{Create, Delete, Read, Update};
-use crate::dtos::{CreateUserDTO, UpdateUserDTO};
-use crate::entities::User;
-use sqlx::{Error, MySqlPool};
+use super::query_log::timed;
+#[cfg(feature = "sqlite")]
+use super::sqlite;
+use super::{Create, DbPool, Delete, Page, Read, ReadMany, Update};
+use crate::dtos::{CreateUserDTO, UpdateProfileDTO, UpdateUserDTO};
+use crate::entities::{PresenceVisibility, User};
+use chrono::{DateTime, Utc};
+use sqlx::Error;
 use tracing::{debug, info, instrument};
 
 // USER REPO
 pub struct UserRepository {
-    connection_pool: MySqlPool,
+    connection_pool: DbPool,
+    // letture pesanti che tollerano un piccolo ritardo (storico, liste, ricerche)
+    read_pool: DbPool,
 }
 
 impl UserRepository {
-    pub fn new(connection_pool: MySqlPool) -> UserRepository {
-        Self { connection_pool }
+    pub fn new(connection_pool: impl Into<DbPool>) -> UserRepository {
+        let connection_pool = connection_pool.into();
+        Self {
+            read_pool: connection_pool.clone(),
+            connection_pool,
+        }
+    }
+
+    /// Route the read-heavy queries of this repository to a read-only replica
+    ///
+    /// Writes, and reads that must see them immediately, stay on the primary pool.
+    pub fn with_read_pool(mut self, read_pool: impl Into<DbPool>) -> Self {
+        self.read_pool = read_pool.into();
+        self
     }
 
     ///considero l'username univoco
@@ -22,12 +40,31 @@ impl UserRepository {
     #[instrument(skip(self), fields(username = %username))]
     pub async fn find_by_username(&self, username: &String) -> Result<Option<User>, Error> {
         debug!("Finding user by username");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::user::find_by_username(pool, username).await;
+        }
         let user = sqlx::query_as!(
             User,
-            "SELECT user_id, username, password FROM users WHERE username = ?",
+            r#"
+            SELECT
+                user_id,
+                username,
+                password,
+                display_name,
+                bio,
+                avatar_url,
+                status_text,
+                status_expires_at,
+                last_seen,
+                presence_visibility as "presence_visibility: PresenceVisibility",
+                deactivated_at
+            FROM users
+            WHERE username = ?
+            "#,
             username
         )
-        .fetch_optional(&self.connection_pool)
+        .fetch_optional(timed(self.connection_pool.mysql()))
         .await?;
 
         if user.is_some() {
@@ -47,30 +84,324 @@ impl UserRepository {
     ) -> Result<Vec<User>, Error> {
         debug!("Searching users with partial username match");
         let pattern = format!("{}%", username_pattern);
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.read_pool {
+            let users = sqlite::user::search_by_username_partial(pool, &pattern).await?;
+            info!("Found {} users matching pattern", users.len());
+            return Ok(users);
+        }
         let users = sqlx::query_as!(
             User,
-            "SELECT user_id, username, password FROM users WHERE username LIKE ? LIMIT 10",
+            r#"
+            SELECT
+                user_id,
+                username,
+                password,
+                display_name,
+                bio,
+                avatar_url,
+                status_text,
+                status_expires_at,
+                last_seen,
+                presence_visibility as "presence_visibility: PresenceVisibility",
+                deactivated_at
+            FROM users
+            WHERE username LIKE ? AND deactivated_at IS NULL
+            LIMIT 10
+            "#,
             pattern
         )
-        .fetch_all(&self.connection_pool)
+        .fetch_all(timed(self.read_pool.mysql()))
         .await?;
 
         info!("Found {} users matching pattern", users.len());
         Ok(users)
     }
+
+    /// Persist the last time the user has been seen online
+    #[instrument(skip(self), fields(user_id = %user_id))]
+    pub async fn update_last_seen(
+        &self,
+        user_id: &i32,
+        last_seen: &DateTime<Utc>,
+    ) -> Result<(), Error> {
+        debug!("Updating user last_seen");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::user::update_last_seen(pool, user_id, last_seen).await;
+        }
+        sqlx::query!(
+            "UPDATE users SET last_seen = ? WHERE user_id = ?",
+            last_seen,
+            user_id
+        )
+        .execute(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(())
+    }
+
+    /// Disattiva (Some) o riattiva (None) l'account dell'utente
+    #[instrument(skip(self), fields(user_id = %user_id))]
+    pub async fn set_deactivated_at(
+        &self,
+        user_id: &i32,
+        deactivated_at: Option<DateTime<Utc>>,
+    ) -> Result<(), Error> {
+        debug!("Updating user deactivation");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::user::set_deactivated_at(pool, user_id, deactivated_at).await;
+        }
+        sqlx::query!(
+            "UPDATE users SET deactivated_at = ? WHERE user_id = ?",
+            deactivated_at,
+            user_id
+        )
+        .execute(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(())
+    }
+
+    /// Account disattivati prima della data indicata (fuori dalla finestra di riattivazione)
+    pub async fn find_deactivated_before(
+        &self,
+        cutoff: &DateTime<Utc>,
+    ) -> Result<Vec<User>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::user::find_deactivated_before(pool, cutoff).await;
+        }
+        let users = sqlx::query_as!(
+            User,
+            r#"
+            SELECT
+                user_id,
+                username,
+                password,
+                display_name,
+                bio,
+                avatar_url,
+                status_text,
+                status_expires_at,
+                last_seen,
+                presence_visibility as "presence_visibility: PresenceVisibility",
+                deactivated_at
+            FROM users
+            WHERE deactivated_at IS NOT NULL AND deactivated_at < ?
+            "#,
+            cutoff
+        )
+        .fetch_all(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(users)
+    }
+
+    /// Aggiorna chi può vedere lo stato online e il last_seen dell'utente
+    #[instrument(skip(self), fields(user_id = %user_id, visibility = ?visibility))]
+    pub async fn update_presence_visibility(
+        &self,
+        user_id: &i32,
+        visibility: &PresenceVisibility,
+    ) -> Result<(), Error> {
+        debug!("Updating user presence visibility");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::user::update_presence_visibility(pool, user_id, visibility).await;
+        }
+        sqlx::query!(
+            "UPDATE users SET presence_visibility = ? WHERE user_id = ?",
+            visibility,
+            user_id
+        )
+        .execute(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(())
+    }
+
+    /// Imposta (o cancella, con None) lo stato personalizzato dell'utente
+    #[instrument(skip(self, status_text), fields(user_id = %user_id))]
+    pub async fn update_status(
+        &self,
+        user_id: &i32,
+        status_text: Option<&str>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<(), Error> {
+        debug!("Updating user status");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::user::update_status(pool, user_id, status_text, expires_at).await;
+        }
+        sqlx::query!(
+            "UPDATE users SET status_text = ?, status_expires_at = ? WHERE user_id = ?",
+            status_text,
+            expires_at,
+            user_id
+        )
+        .execute(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(())
+    }
+
+    /// Aggiorna i campi del profilo presenti nel DTO; una stringa vuota cancella il campo
+    #[instrument(skip(self, data), fields(user_id = %user_id))]
+    pub async fn update_profile(
+        &self,
+        user_id: &i32,
+        data: &UpdateProfileDTO,
+    ) -> Result<User, Error> {
+        debug!("Updating user profile");
+        let current_user = self
+            .read(user_id)
+            .await?
+            .ok_or_else(|| sqlx::Error::RowNotFound)?;
+
+        if data.display_name.is_none() && data.bio.is_none() && data.avatar_url.is_none() {
+            debug!("No profile fields to update, returning current user");
+            return Ok(current_user);
+        }
+
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            sqlite::user::update_profile(pool, user_id, data).await?;
+            info!("User profile updated");
+            return self
+                .read(user_id)
+                .await?
+                .ok_or_else(|| sqlx::Error::RowNotFound);
+        }
+
+        let mut query_builder = sqlx::QueryBuilder::new("UPDATE users SET ");
+
+        let mut separated = query_builder.separated(", ");
+        if let Some(ref display_name) = data.display_name {
+            separated.push("display_name = NULLIF(");
+            separated.push_bind_unseparated(display_name);
+            separated.push_unseparated(", '')");
+        }
+        if let Some(ref bio) = data.bio {
+            separated.push("bio = NULLIF(");
+            separated.push_bind_unseparated(bio);
+            separated.push_unseparated(", '')");
+        }
+        if let Some(ref avatar_url) = data.avatar_url {
+            separated.push("avatar_url = NULLIF(");
+            separated.push_bind_unseparated(avatar_url);
+            separated.push_unseparated(", '')");
+        }
+
+        query_builder.push(" WHERE user_id = ");
+        query_builder.push_bind(user_id);
+
+        query_builder
+            .build()
+            .execute(timed(self.connection_pool.mysql()))
+            .await?;
+
+        info!("User profile updated");
+
+        self.read(user_id)
+            .await?
+            .ok_or_else(|| sqlx::Error::RowNotFound)
+    }
+}
+
+/// Filtro della lista degli utenti, compresi quelli disattivati, ordinati per id
+#[derive(Debug, Clone, Default)]
+pub struct UserFilter {
+    /// Se presente, solo gli username che contengono il testo in qualsiasi posizione
+    pub search: Option<String>,
+    /// Se presente, solo gli utenti con questi id (quelli inesistenti sono ignorati)
+    pub ids: Option<Vec<i32>>,
+}
+
+impl ReadMany<User, UserFilter> for UserRepository {
+    #[instrument(skip(self))]
+    async fn read_many(&self, filter: &UserFilter, page: &Page) -> Result<Vec<User>, Error> {
+        debug!("Listing users");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.read_pool {
+            let users = sqlite::user::read_many(pool, filter, page).await?;
+            info!("Found {} users", users.len());
+            return Ok(users);
+        }
+        let pattern = filter.search.as_ref().map(|search| format!("%{}%", search));
+        // gli id sono passati come array JSON: un solo parametro qualunque sia il loro numero
+        let ids = filter
+            .ids
+            .as_ref()
+            .map(|ids| serde_json::to_string(ids).unwrap_or_else(|_| "[]".to_string()));
+        let users = sqlx::query_as!(
+            User,
+            r#"
+            SELECT
+                user_id,
+                username,
+                password,
+                display_name,
+                bio,
+                avatar_url,
+                status_text,
+                status_expires_at,
+                last_seen,
+                presence_visibility as "presence_visibility: PresenceVisibility",
+                deactivated_at
+            FROM users
+            WHERE (? IS NULL OR username LIKE ?)
+              AND (? IS NULL OR user_id IN (
+                  SELECT id FROM JSON_TABLE(?, '$[*]' COLUMNS (id INT PATH '$')) AS ids
+              ))
+            ORDER BY user_id
+            LIMIT ? OFFSET ?
+            "#,
+            pattern,
+            pattern,
+            ids,
+            ids,
+            page.limit,
+            page.offset
+        )
+        .fetch_all(timed(self.read_pool.mysql()))
+        .await?;
+
+        info!("Found {} users", users.len());
+        Ok(users)
+    }
 }
 
 impl Create<User, CreateUserDTO> for UserRepository {
     #[instrument(skip(self, data), fields(username = %data.username))]
     async fn create(&self, data: &CreateUserDTO) -> Result<User, Error> {
         debug!("Creating new user");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            let new_id = sqlite::user::insert(pool, data).await?;
+            info!("User created with id {}", new_id);
+            return Ok(User {
+                user_id: new_id,
+                username: data.username.clone(),
+                password: data.password.clone(),
+                display_name: None,
+                bio: None,
+                avatar_url: None,
+                status_text: None,
+                status_expires_at: None,
+                last_seen: None,
+                presence_visibility: PresenceVisibility::Everyone,
+                deactivated_at: None,
+            });
+        }
         // Insert user and get the ID using MySQL syntax
         let result = sqlx::query!(
             "INSERT INTO users (username, password) VALUES (?, ?)",
             data.username,
             data.password
         )
-        .execute(&self.connection_pool)
+        .execute(timed(self.connection_pool.mysql()))
         .await?;
 
         // Get the last inserted ID
@@ -83,6 +414,14 @@ impl Create<User, CreateUserDTO> for UserRepository {
             user_id: new_id,
             username: data.username.clone(),
             password: data.password.clone(),
+            display_name: None,
+            bio: None,
+            avatar_url: None,
+            status_text: None,
+            status_expires_at: None,
+            last_seen: None,
+            presence_visibility: PresenceVisibility::Everyone,
+            deactivated_at: None,
         })
     }
 }
@@ -91,12 +430,31 @@ impl Read<User, i32> for UserRepository {
     #[instrument(skip(self), fields(user_id = %id))]
     async fn read(&self, id: &i32) -> Result<Option<User>, Error> {
         debug!("Reading user by id");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::user::read(pool, id).await;
+        }
         let user = sqlx::query_as!(
             User,
-            "SELECT user_id, username, password FROM users WHERE user_id = ?",
+            r#"
+            SELECT
+                user_id,
+                username,
+                password,
+                display_name,
+                bio,
+                avatar_url,
+                status_text,
+                status_expires_at,
+                last_seen,
+                presence_visibility as "presence_visibility: PresenceVisibility",
+                deactivated_at
+            FROM users
+            WHERE user_id = ?
+            "#,
             id
         )
-        .fetch_optional(&self.connection_pool)
+        .fetch_optional(timed(self.connection_pool.mysql()))
         .await?;
 
         if user.is_some() {
@@ -122,12 +480,18 @@ impl Update<User, UpdateUserDTO, i32> for UserRepository {
         // Only password can be updated
         if let Some(ref password) = data.password {
             debug!("Updating user password");
+            #[cfg(feature = "sqlite")]
+            if let DbPool::Sqlite(pool) = &self.connection_pool {
+                sqlite::user::update_password(pool, id, password).await?;
+                info!("User password updated");
+                return self.read(id).await?.ok_or_else(|| sqlx::Error::RowNotFound);
+            }
             sqlx::query!(
                 "UPDATE users SET password = ? WHERE user_id = ?",
                 password,
                 id
             )
-            .execute(&self.connection_pool)
+            .execute(timed(self.connection_pool.mysql()))
             .await?;
 
             info!("User password updated");
@@ -143,16 +507,22 @@ impl Update<User, UpdateUserDTO, i32> for UserRepository {
 }
 
 impl Delete<i32> for UserRepository {
-    /// Soft delete user by setting username to "Deleted User" and clearing password ""
+    /// Soft delete user by setting username to "Deleted User", clearing password "" and profile
     /// This preserves message history while anonymizing the user
     #[instrument(skip(self), fields(user_id = %user_id))]
     async fn delete(&self, user_id: &i32) -> Result<(), Error> {
         debug!("Soft deleting user");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            sqlite::user::delete(pool, user_id).await?;
+            info!("User soft deleted successfully");
+            return Ok(());
+        }
         sqlx::query!(
-            "UPDATE users SET username = 'Deleted User', password = '' WHERE user_id = ?",
+            "UPDATE users SET username = 'Deleted User', password = '', display_name = NULL, bio = NULL, avatar_url = NULL, status_text = NULL, status_expires_at = NULL, deactivated_at = NULL WHERE user_id = ?",
             user_id
         )
-        .execute(&self.connection_pool)
+        .execute(timed(self.connection_pool.mysql()))
         .await?;
 
         info!("User soft deleted successfully");
@@ -1,26 +1,66 @@
 //! UserChatMetadataRepository - Repository per la gestione dei metadati utente-chat
 
-use super::{Create, Delete, Read, Update};
-use crate::dtos::{CreateUserChatMetadataDTO, UpdateUserChatMetadataDTO};
+use super::query_log::timed;
+#[cfg(feature = "sqlite")]
+use super::sqlite;
+use super::{Create, DbPool, Delete, Page, Read, ReadMany, UnitOfWork, Update};
+use crate::dtos::{CreateUserChatMetadataDTO, UnreadCountDTO, UpdateUserChatMetadataDTO};
 use crate::entities::{UserChatMetadata, UserRole};
-use sqlx::{Error, MySqlPool};
+use chrono::{DateTime, Utc};
+use sqlx::{Error, MySqlConnection};
 use tracing::{debug, info, instrument};
 
+/// Message carried by the error returned when a compare-and-set finds a newer `version`
+const STALE_VERSION: &str = "userchatmetadata row was modified concurrently";
+
+pub(crate) fn version_conflict() -> Error {
+    Error::InvalidArgument(STALE_VERSION.to_string())
+}
+
+/// Whether the error comes from a compare-and-set on a stale `version`
+pub fn is_version_conflict(err: &Error) -> bool {
+    matches!(err, Error::InvalidArgument(message) if message == STALE_VERSION)
+}
+
 // USERCHATMETADATA REPO
 pub struct UserChatMetadataRepository {
-    connection_pool: MySqlPool,
+    connection_pool: DbPool,
+    // letture pesanti che tollerano un piccolo ritardo (storico, liste, ricerche)
+    read_pool: DbPool,
 }
 
 impl UserChatMetadataRepository {
-    pub fn new(connection_pool: MySqlPool) -> Self {
-        Self { connection_pool }
+    pub fn new(connection_pool: impl Into<DbPool>) -> Self {
+        let connection_pool = connection_pool.into();
+        Self {
+            read_pool: connection_pool.clone(),
+            connection_pool,
+        }
+    }
+
+    /// Route the read-heavy queries of this repository to a read-only replica
+    ///
+    /// Writes, and reads that must see them immediately, stay on the primary pool.
+    pub fn with_read_pool(mut self, read_pool: impl Into<DbPool>) -> Self {
+        self.read_pool = read_pool.into();
+        self
     }
 
-    /// Get all members of a specific chat
-    pub async fn find_many_by_chat_id(
+    async fn find_members_in(
         &self,
-        chat_id: &i32,
+        pool: &DbPool,
+        filter: &MemberFilter,
+        page: &Page,
     ) -> Result<Vec<UserChatMetadata>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = pool {
+            return sqlite::user_chat_metadata::find_members(pool, filter, page).await;
+        }
+        // array JSON espanso da JSON_TABLE: un solo parametro qualunque sia il numero di chat
+        let chat_ids = filter
+            .chat_ids
+            .as_ref()
+            .map(|ids| serde_json::to_string(ids).unwrap_or_else(|_| "[]".to_string()));
         let metadata_list = sqlx::query_as!(
             UserChatMetadata,
             r#"
@@ -28,15 +68,37 @@ impl UserChatMetadataRepository {
                 user_id,
                 chat_id,
                 user_role as "user_role: UserRole",
+                role_id,
                 member_since,
                 messages_visible_from,
-                messages_received_until
+                messages_received_until,
+                pinned_at,
+                muted_until,
+                version
             FROM userchatmetadata 
-            WHERE chat_id = ?
+            WHERE (? IS NULL OR chat_id = ?)
+              AND (? IS NULL OR chat_id IN (
+                  SELECT id FROM JSON_TABLE(?, '$[*]' COLUMNS (id INT PATH '$')) AS ids
+              ))
+              AND (? IS NULL OR user_id = ?)
+              AND user_id > ?
+              AND (? IS NULL OR user_role = ?)
+            ORDER BY chat_id, user_id
+            LIMIT ? OFFSET ?
             "#,
-            chat_id
+            filter.chat_id,
+            filter.chat_id,
+            chat_ids,
+            chat_ids,
+            filter.user_id,
+            filter.user_id,
+            filter.after_user_id,
+            filter.role.as_ref(),
+            filter.role.as_ref(),
+            page.limit,
+            page.offset
         )
-        .fetch_all(&self.connection_pool)
+        .fetch_all(timed(pool.mysql()))
         .await?;
 
         Ok(metadata_list)
@@ -49,9 +111,50 @@ impl UserChatMetadataRepository {
         to_user_id: &i32,
         chat_id: &i32,
     ) -> Result<(), Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::user_chat_metadata::transfer_ownership(
+                pool,
+                from_user_id,
+                to_user_id,
+                chat_id,
+            )
+            .await;
+        }
         // Start a transaction for atomicity
-        let mut tx = self.connection_pool.begin().await?;
+        let mut tx = self.connection_pool.mysql().begin().await?;
+        Self::swap_owner(&mut tx, from_user_id, to_user_id, chat_id).await?;
+        // Commit the transaction
+        tx.commit().await?;
 
+        Ok(())
+    }
+
+    /// Transfer ownership as part of a unit of work, see `transfer_ownership`
+    pub async fn transfer_ownership_in(
+        &self,
+        uow: &mut UnitOfWork,
+        from_user_id: &i32,
+        to_user_id: &i32,
+        chat_id: &i32,
+    ) -> Result<(), Error> {
+        #[cfg(feature = "sqlite")]
+        if let Some(conn) = uow.sqlite_conn() {
+            sqlite::user_chat_metadata::swap_owner(conn, from_user_id, to_user_id, chat_id).await?;
+            uow.touch_chat_members(*chat_id);
+            return Ok(());
+        }
+        Self::swap_owner(uow.conn(), from_user_id, to_user_id, chat_id).await?;
+        uow.touch_chat_members(*chat_id);
+        Ok(())
+    }
+
+    async fn swap_owner(
+        conn: &mut MySqlConnection,
+        from_user_id: &i32,
+        to_user_id: &i32,
+        chat_id: &i32,
+    ) -> Result<(), Error> {
         // Verify old owner exists and has correct role
         let _old_owner = sqlx::query_as!(
             UserChatMetadata,
@@ -59,15 +162,19 @@ impl UserChatMetadataRepository {
                    user_id,
                    chat_id,
                    user_role as "user_role: UserRole",
+                   role_id,
                    member_since,
                    messages_visible_from,
-                   messages_received_until
+                   messages_received_until,
+                   pinned_at,
+                   muted_until,
+                   version
                FROM userchatmetadata 
                WHERE user_id = ? AND chat_id = ?"#,
             from_user_id,
             chat_id
         )
-        .fetch_optional(&mut *tx)
+        .fetch_optional(timed(&mut *conn))
         .await?
         .ok_or(Error::RowNotFound)?;
 
@@ -78,63 +185,81 @@ impl UserChatMetadataRepository {
                    user_id,
                    chat_id,
                    user_role as "user_role: UserRole",
+                   role_id,
                    member_since,
                    messages_visible_from,
-                   messages_received_until
+                   messages_received_until,
+                   pinned_at,
+                   muted_until,
+                   version
                FROM userchatmetadata 
                WHERE user_id = ? AND chat_id = ?"#,
             to_user_id,
             chat_id
         )
-        .fetch_optional(&mut *tx)
+        .fetch_optional(timed(&mut *conn))
         .await?
         .ok_or(Error::RowNotFound)?;
 
         // Update the old owner to admin
         sqlx::query!(
-            "UPDATE userchatmetadata SET user_role = 'ADMIN' WHERE user_id = ? AND chat_id = ?",
+            r#"
+            UPDATE userchatmetadata
+            SET user_role = 'ADMIN',
+                role_id = (SELECT r.role_id FROM chat_roles r WHERE r.chat_id = userchatmetadata.chat_id AND r.base_role = 'ADMIN'),
+                version = version + 1
+            WHERE user_id = ? AND chat_id = ?
+            "#,
             from_user_id,
             chat_id
         )
-        .execute(&mut *tx)
+        .execute(timed(&mut *conn))
         .await?;
 
         // Update the new owner
         sqlx::query!(
-            "UPDATE userchatmetadata SET user_role = 'OWNER' WHERE user_id = ? AND chat_id = ?",
+            r#"
+            UPDATE userchatmetadata
+            SET user_role = 'OWNER',
+                role_id = (SELECT r.role_id FROM chat_roles r WHERE r.chat_id = userchatmetadata.chat_id AND r.base_role = 'OWNER'),
+                version = version + 1
+            WHERE user_id = ? AND chat_id = ?
+            "#,
             to_user_id,
             chat_id
         )
-        .execute(&mut *tx)
+        .execute(timed(&mut *conn))
         .await?;
 
-        // Commit the transaction
-        tx.commit().await?;
-
         Ok(())
     }
 
-    /// Get all chats for a specific user
-    pub async fn find_many_by_user_id(
+    /// Unread messages for every chat of a user
+    ///
+    /// A message is unread when it was created after `messages_received_until`,
+    /// is visible to the user (`messages_visible_from`), was not sent by the user
+    /// and has not been deleted. Reads the `unread_count` kept up to date by message
+    /// writes and marker updates. Chats without unread messages are returned with 0.
+    pub async fn count_unread_by_user_id(
         &self,
         user_id: &i32,
-    ) -> Result<Vec<UserChatMetadata>, Error> {
+    ) -> Result<Vec<UnreadCountDTO>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::user_chat_metadata::count_unread_by_user_id(pool, user_id).await;
+        }
         let result = sqlx::query_as!(
-            UserChatMetadata,
+            UnreadCountDTO,
             r#"
-        SELECT
-            user_id,
-            chat_id,
-            user_role as "user_role: UserRole",
-            member_since,
-            messages_visible_from,
-            messages_received_until
-        FROM userchatmetadata
-        WHERE user_id = ?
-        "#,
+            SELECT
+                chat_id,
+                CAST(unread_count AS SIGNED) as "unread!: i64"
+            FROM userchatmetadata
+            WHERE user_id = ?
+            "#,
             user_id
         )
-        .fetch_all(&self.connection_pool)
+        .fetch_all(timed(self.connection_pool.mysql()))
         .await?;
 
         Ok(result)
@@ -150,46 +275,319 @@ impl UserChatMetadataRepository {
             return Ok(Vec::new());
         }
 
-        let mut tx = self.connection_pool.begin().await?;
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::user_chat_metadata::create_many(pool, metadata_list).await;
+        }
+
+        let mut tx = self.connection_pool.mysql().begin().await?;
 
         let mut created = Vec::with_capacity(metadata_list.len());
 
         for data in metadata_list {
+            created.push(Self::insert(&mut tx, data).await?);
+        }
+
+        tx.commit().await?;
+
+        Ok(created)
+    }
+
+    /// Create a membership as part of a unit of work
+    ///
+    /// The cached memberships of the chat are invalidated when the unit of work commits.
+    pub async fn create_in(
+        &self,
+        uow: &mut UnitOfWork,
+        data: &CreateUserChatMetadataDTO,
+    ) -> Result<UserChatMetadata, Error> {
+        #[cfg(feature = "sqlite")]
+        if let Some(conn) = uow.sqlite_conn() {
+            let created = sqlite::user_chat_metadata::insert(conn, data).await?;
+            uow.touch_chat_members(data.chat_id);
+            return Ok(created);
+        }
+        let created = Self::insert(uow.conn(), data).await?;
+        uow.touch_chat_members(data.chat_id);
+        Ok(created)
+    }
+
+    /// Create multiple memberships as part of a unit of work, see `create_in`
+    pub async fn create_many_in(
+        &self,
+        uow: &mut UnitOfWork,
+        metadata_list: &[CreateUserChatMetadataDTO],
+    ) -> Result<Vec<UserChatMetadata>, Error> {
+        let mut created = Vec::with_capacity(metadata_list.len());
+        for data in metadata_list {
+            created.push(self.create_in(uow, data).await?);
+        }
+        Ok(created)
+    }
+
+    /// Delete a membership as part of a unit of work, see `create_in`
+    pub async fn delete_in(&self, uow: &mut UnitOfWork, id: &UserChatKey) -> Result<(), Error> {
+        #[cfg(feature = "sqlite")]
+        if let Some(conn) = uow.sqlite_conn() {
+            sqlite::user_chat_metadata::remove(conn, id).await?;
+            uow.touch_chat_members(id.1);
+            return Ok(());
+        }
+        Self::remove(uow.conn(), id).await?;
+        uow.touch_chat_members(id.1);
+        Ok(())
+    }
+
+    async fn remove(conn: &mut MySqlConnection, id: &UserChatKey) -> Result<(), Error> {
+        sqlx::query!(
+            "DELETE FROM userchatmetadata WHERE user_id = ? AND chat_id=?",
+            id.0,
+            id.1
+        )
+        .execute(timed(conn))
+        .await?;
+
+        Ok(())
+    }
+
+    async fn insert(
+        conn: &mut MySqlConnection,
+        data: &CreateUserChatMetadataDTO,
+    ) -> Result<UserChatMetadata, Error> {
+        // Il membro punta alla riga del suo ruolo base nella chat
+        let role_id = sqlx::query_scalar!(
+            "SELECT role_id FROM chat_roles WHERE chat_id = ? AND base_role = ?",
+            data.chat_id,
+            data.user_role
+        )
+        .fetch_optional(timed(&mut *conn))
+        .await?;
+
+        // Insert metadata using MySQL syntax
+        sqlx::query!(
+            r#"
+            INSERT INTO userchatmetadata 
+            (user_id, chat_id, user_role, role_id, member_since, messages_visible_from, messages_received_until) 
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+            data.user_id,
+            data.chat_id,
+            data.user_role,
+            role_id,
+            data.member_since,
+            data.messages_visible_from,
+            data.messages_received_until
+        )
+        .execute(timed(conn))
+        .await?;
+
+        info!(
+            "User chat metadata created for user {} in chat {}",
+            data.user_id, data.chat_id
+        );
+
+        // Return the created metadata
+        Ok(UserChatMetadata {
+            user_id: data.user_id,
+            chat_id: data.chat_id,
+            user_role: data.user_role.clone(),
+            member_since: data.member_since,
+            messages_visible_from: data.messages_visible_from,
+            messages_received_until: data.messages_received_until,
+            pinned_at: None,
+            muted_until: None,
+            version: 0,
+            role_id,
+        })
+    }
+
+    /// Count the members of a chat
+    pub async fn count_by_chat_id(&self, chat_id: &i32) -> Result<i64, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::user_chat_metadata::count_by_chat_id(pool, chat_id).await;
+        }
+        let count = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM userchatmetadata WHERE chat_id = ?",
+            chat_id
+        )
+        .fetch_one(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(count)
+    }
+
+    /// Verifica se due utenti condividono almeno una chat
+    pub async fn share_any_chat(&self, user_id: &i32, other_user_id: &i32) -> Result<bool, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::user_chat_metadata::share_any_chat(pool, user_id, other_user_id).await;
+        }
+        let count = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*)
+            FROM userchatmetadata a
+            INNER JOIN userchatmetadata b ON a.chat_id = b.chat_id
+            WHERE a.user_id = ? AND b.user_id = ?
+            "#,
+            user_id,
+            other_user_id
+        )
+        .fetch_one(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(count > 0)
+    }
+
+    /// Fissa (Some) o sgancia (None) la chat in cima alla lista dell'utente
+    pub async fn set_pinned(
+        &self,
+        user_id: &i32,
+        chat_id: &i32,
+        pinned_at: Option<DateTime<Utc>>,
+    ) -> Result<(), Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::user_chat_metadata::set_pinned(pool, user_id, chat_id, pinned_at).await;
+        }
+        sqlx::query!(
+            "UPDATE userchatmetadata SET pinned_at = ? WHERE user_id = ? AND chat_id = ?",
+            pinned_at,
+            user_id,
+            chat_id
+        )
+        .execute(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(())
+    }
+
+    /// Assegna (Some) o toglie (None) il ruolo personalizzato del membro
+    ///
+    /// Togliendolo il membro torna alla riga del ruolo base Member della chat.
+    pub async fn set_custom_role(
+        &self,
+        user_id: &i32,
+        chat_id: &i32,
+        role_id: Option<i32>,
+    ) -> Result<(), Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::user_chat_metadata::set_custom_role(pool, user_id, chat_id, role_id)
+                .await;
+        }
+        sqlx::query!(
+            r#"
+            UPDATE userchatmetadata
+            SET role_id = COALESCE(?, (SELECT r.role_id FROM chat_roles r WHERE r.chat_id = userchatmetadata.chat_id AND r.base_role = 'MEMBER'))
+            WHERE user_id = ? AND chat_id = ?
+            "#,
+            role_id,
+            user_id,
+            chat_id
+        )
+        .execute(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(())
+    }
+
+    /// Silenzia il membro fino all'istante indicato (Some) o rimuove il silenziamento (None)
+    pub async fn set_muted_until(
+        &self,
+        user_id: &i32,
+        chat_id: &i32,
+        muted_until: Option<DateTime<Utc>>,
+    ) -> Result<(), Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::user_chat_metadata::set_muted_until(
+                pool,
+                user_id,
+                chat_id,
+                muted_until,
+            )
+            .await;
+        }
+        sqlx::query!(
+            "UPDATE userchatmetadata SET muted_until = ? WHERE user_id = ? AND chat_id = ?",
+            muted_until,
+            user_id,
+            chat_id
+        )
+        .execute(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(())
+    }
+
+    /// Advance `messages_received_until` of a user in several chats at once
+    ///
+    /// Each marker only moves forward: a chat whose marker is already past the delivered
+    /// messages (e.g. after `clean_chat`) is left untouched. `unread_count` is maintained
+    /// without counting messages: it drops to 0 once the batch reaches the newest message
+    /// of the chat, otherwise it decreases by the unread messages of the batch. All updates
+    /// run in a single transaction.
+    ///
+    /// # Arguments
+    /// * `user_id` - The user ID
+    /// * `received` - Messages delivered per chat since the previous update
+    #[instrument(skip(self, received), fields(chats = received.len()))]
+    pub async fn advance_received_until(
+        &self,
+        user_id: &i32,
+        received: &[DeliveredBatch],
+    ) -> Result<(), Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            sqlite::user_chat_metadata::advance_received_until(pool, user_id, received).await?;
+            debug!("Advanced received markers of {} chats", received.len());
+            return Ok(());
+        }
+        let mut tx = self.connection_pool.mysql().begin().await?;
+
+        for batch in received {
+            // MAX(message_id) per chat è una lettura dell'indice, non una scansione
             sqlx::query!(
                 r#"
-                INSERT INTO userchatmetadata (user_id, chat_id, user_role, member_since, messages_visible_from, messages_received_until) 
-                VALUES (?, ?, ?, ?, ?, ?)
+                UPDATE userchatmetadata
+                SET unread_count = CASE
+                        WHEN ? >= (SELECT MAX(m.message_id) FROM messages m WHERE m.chat_id = ?)
+                        THEN 0
+                        ELSE GREATEST(unread_count - ?, 0)
+                    END,
+                    messages_received_until = ?
+                WHERE user_id = ? AND chat_id = ? AND messages_received_until < ?
                 "#,
-                data.user_id,
-                data.chat_id,
-                data.user_role,
-                data.member_since,
-                data.messages_visible_from,
-                data.messages_received_until
+                batch.last_message_id,
+                batch.chat_id,
+                batch.unread,
+                batch.received_until,
+                user_id,
+                batch.chat_id,
+                batch.received_until
             )
-            .execute(&mut *tx)
+            .execute(timed(&mut *tx))
             .await?;
-
-            created.push(UserChatMetadata {
-                user_id: data.user_id,
-                chat_id: data.chat_id,
-                user_role: data.user_role.clone(),
-                member_since: data.member_since,
-                messages_visible_from: data.messages_visible_from,
-                messages_received_until: data.messages_received_until,
-            });
         }
 
         tx.commit().await?;
 
-        Ok(created)
+        debug!("Advanced received markers of {} chats", received.len());
+        Ok(())
     }
 
+    /// Change the role of a member if the row is still at `expected_version`
+    ///
+    /// The member is moved to the matching base role row of the chat, replacing any custom role.
+    /// Fails with `RowNotFound` if the member does not exist and with a version conflict
+    /// (see `is_version_conflict`) if someone else changed the row after it was read.
     pub async fn update_user_role(
         &self,
         user_id: &i32,
         chat_id: &i32,
         new_role: &UserRole,
+        expected_version: &i32,
     ) -> Result<UserChatMetadata, Error> {
         // Mappo l'enum sul valore testuale usato in DB
         let role_str = match new_role {
@@ -198,23 +596,46 @@ impl UserChatMetadataRepository {
             UserRole::Member => "MEMBER",
         };
 
-        // UPDATE mirato su chiave composta (user_id, chat_id)
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            let updated = sqlite::user_chat_metadata::update_user_role(
+                pool,
+                user_id,
+                chat_id,
+                new_role,
+                expected_version,
+            )
+            .await?;
+            if updated == 0 {
+                return Err(self.missing_or_conflict(&(*user_id, *chat_id)).await);
+            }
+            return self
+                .read(&(*user_id, *chat_id))
+                .await?
+                .ok_or_else(|| sqlx::Error::RowNotFound);
+        }
+
+        // UPDATE mirato su chiave composta (user_id, chat_id), solo se la versione coincide
         let result = sqlx::query!(
             r#"
             UPDATE userchatmetadata
-            SET user_role = ?
-            WHERE user_id = ? AND chat_id = ?
+            SET user_role = ?,
+                role_id = (SELECT r.role_id FROM chat_roles r WHERE r.chat_id = userchatmetadata.chat_id AND r.base_role = ?),
+                version = version + 1
+            WHERE user_id = ? AND chat_id = ? AND version = ?
             "#,
             role_str,
+            role_str,
             user_id,
-            chat_id
+            chat_id,
+            expected_version
         )
-        .execute(&self.connection_pool)
+        .execute(timed(self.connection_pool.mysql()))
         .await?;
 
-        // Se nessuna riga è stata toccata, la coppia (user_id, chat_id) non esiste
+        // Nessuna riga toccata: la coppia (user_id, chat_id) non esiste o la versione è cambiata
         if result.rows_affected() == 0 {
-            return Err(sqlx::Error::RowNotFound);
+            return Err(self.missing_or_conflict(&(*user_id, *chat_id)).await);
         }
 
         // Ritorno il record aggiornato
@@ -222,43 +643,100 @@ impl UserChatMetadataRepository {
             .await?
             .ok_or_else(|| sqlx::Error::RowNotFound)
     }
+
+    /// Error for a compare-and-set that matched no row
+    async fn missing_or_conflict(&self, id: &UserChatKey) -> Error {
+        match self.read(id).await {
+            Ok(Some(_)) => version_conflict(),
+            Ok(None) => Error::RowNotFound,
+            Err(err) => err,
+        }
+    }
+}
+
+/// Filter for the memberships of chats and users, ordered by chat id and then user id
+///
+/// Keyset pagination on the user id: when listing the members of a single chat the next
+/// page starts after the last `user_id` returned, so a chat with thousands of members never
+/// needs an OFFSET scan. Rows are read from the replica, where a member added a moment ago
+/// may be missing: permission checks and membership changes set `read_primary`.
+#[derive(Debug, Clone, Default)]
+pub struct MemberFilter {
+    /// Only the members of this chat
+    pub chat_id: Option<i32>,
+    /// Only the members of these chats
+    pub chat_ids: Option<Vec<i32>>,
+    /// Only the memberships of this user
+    pub user_id: Option<i32>,
+    pub role: Option<UserRole>,
+    /// Last user id of the previous page, 0 for the first one
+    pub after_user_id: i32,
+    /// Read from the primary pool, to see the writes made just before
+    pub read_primary: bool,
+}
+
+impl MemberFilter {
+    /// Members of a chat
+    pub fn in_chat(chat_id: i32) -> Self {
+        Self {
+            chat_id: Some(chat_id),
+            ..Self::default()
+        }
+    }
+
+    /// Chats of a user
+    pub fn of_user(user_id: i32) -> Self {
+        Self {
+            user_id: Some(user_id),
+            ..Self::default()
+        }
+    }
+
+    /// Same filter, read from the primary pool
+    pub fn from_primary(self) -> Self {
+        Self {
+            read_primary: true,
+            ..self
+        }
+    }
+}
+
+/// Messages of one chat delivered to a member since its marker was last advanced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DeliveredBatch {
+    pub chat_id: i32,
+    /// Creation time of the newest delivered message, the new `messages_received_until`
+    pub received_until: DateTime<Utc>,
+    /// Id of the newest delivered message
+    pub last_message_id: i32,
+    /// Delivered messages sent by other members, subtracted from `unread_count`
+    pub unread: i64,
+}
+
+impl ReadMany<UserChatMetadata, MemberFilter> for UserChatMetadataRepository {
+    async fn read_many(
+        &self,
+        filter: &MemberFilter,
+        page: &Page,
+    ) -> Result<Vec<UserChatMetadata>, Error> {
+        let pool = if filter.read_primary {
+            &self.connection_pool
+        } else {
+            &self.read_pool
+        };
+        self.find_members_in(pool, filter, page).await
+    }
 }
 
 impl Create<UserChatMetadata, CreateUserChatMetadataDTO> for UserChatMetadataRepository {
     #[instrument(skip(self, data), fields(user_id = %data.user_id, chat_id = %data.chat_id))]
     async fn create(&self, data: &CreateUserChatMetadataDTO) -> Result<UserChatMetadata, Error> {
         debug!("Creating new user chat metadata");
-        // Insert metadata using MySQL syntax
-        sqlx::query!(
-            r#"
-            INSERT INTO userchatmetadata 
-            (user_id, chat_id, user_role, member_since, messages_visible_from, messages_received_until) 
-            VALUES (?, ?, ?, ?, ?, ?)
-            "#,
-            data.user_id,
-            data.chat_id,
-            data.user_role,
-            data.member_since,
-            data.messages_visible_from,
-            data.messages_received_until
-        )
-        .execute(&self.connection_pool)
-        .await?;
-
-        info!(
-            "User chat metadata created for user {} in chat {}",
-            data.user_id, data.chat_id
-        );
-
-        // Return the created metadata
-        Ok(UserChatMetadata {
-            user_id: data.user_id,
-            chat_id: data.chat_id,
-            user_role: data.user_role.clone(),
-            member_since: data.member_since,
-            messages_visible_from: data.messages_visible_from,
-            messages_received_until: data.messages_received_until,
-        })
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::user_chat_metadata::create(pool, data).await;
+        }
+        Self::insert(&mut *self.connection_pool.mysql().acquire().await?, data).await
     }
 }
 
@@ -274,6 +752,10 @@ pub type UserChatKey = (i32, i32);
 
 impl Read<UserChatMetadata, UserChatKey> for UserChatMetadataRepository {
     async fn read(&self, id: &UserChatKey) -> Result<Option<UserChatMetadata>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::user_chat_metadata::read(pool, id).await;
+        }
         let metadata = sqlx::query_as!(
             UserChatMetadata,
             r#"
@@ -281,9 +763,13 @@ impl Read<UserChatMetadata, UserChatKey> for UserChatMetadataRepository {
                 user_id,
                 chat_id,
                 user_role as "user_role: UserRole",
+                role_id,
                 member_since,
                 messages_visible_from,
-                messages_received_until
+                messages_received_until,
+                pinned_at,
+                muted_until,
+                version
             FROM userchatmetadata 
             WHERE user_id = ? 
             AND chat_id = ?
@@ -291,7 +777,7 @@ impl Read<UserChatMetadata, UserChatKey> for UserChatMetadataRepository {
             id.0,
             id.1
         )
-        .fetch_optional(&self.connection_pool)
+        .fetch_optional(timed(self.connection_pool.mysql()))
         .await?;
 
         Ok(metadata)
@@ -312,6 +798,12 @@ impl Update<UserChatMetadata, UpdateUserChatMetadataDTO, UserChatKey>
             .await?
             .ok_or_else(|| sqlx::Error::RowNotFound)?;
 
+        if let Some(version) = data.version
+            && version != current_metadata.version
+        {
+            return Err(version_conflict());
+        }
+
         // If no fields to update, return current metadata
         if data.user_role.is_none()
             && data.messages_visible_from.is_none()
@@ -320,13 +812,34 @@ impl Update<UserChatMetadata, UpdateUserChatMetadataDTO, UserChatKey>
             return Ok(current_metadata);
         }
 
+        // Compare-and-set against the version the caller based its changes on, or the one
+        // just read when the caller does not provide it
+        let expected_version = data.version.unwrap_or(current_metadata.version);
+
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            let updated =
+                sqlite::user_chat_metadata::update(pool, id, data, expected_version).await?;
+            if updated == 0 {
+                return Err(self.missing_or_conflict(id).await);
+            }
+            return self.read(id).await?.ok_or_else(|| sqlx::Error::RowNotFound);
+        }
+
         // Build dynamic UPDATE query using QueryBuilder (idiomatic SQLx way)
         let mut query_builder = sqlx::QueryBuilder::new("UPDATE userchatmetadata SET ");
 
         let mut separated = query_builder.separated(", ");
+        separated.push("version = version + 1");
         if let Some(ref role) = data.user_role {
             separated.push("user_role = ");
             separated.push_bind_unseparated(role);
+            separated.push(
+                "role_id = (SELECT r.role_id FROM chat_roles r \
+                 WHERE r.chat_id = userchatmetadata.chat_id AND r.base_role = ",
+            );
+            separated.push_bind_unseparated(role);
+            separated.push_unseparated(")");
         }
         if let Some(ref visible_from) = data.messages_visible_from {
             separated.push("messages_visible_from = ");
@@ -336,6 +849,17 @@ impl Update<UserChatMetadata, UpdateUserChatMetadataDTO, UserChatKey>
             separated.push("messages_received_until = ");
             separated.push_bind_unseparated(received_until);
         }
+        if data.messages_visible_from.is_some() || data.messages_received_until.is_some() {
+            // MySQL assigns left to right: the check already sees the new markers. Markers
+            // past the newest message leave nothing unread; otherwise the counter is kept,
+            // since only advance_received_until knows how many messages were delivered
+            separated.push(
+                "unread_count = CASE WHEN \
+                 userchatmetadata.messages_received_until >= (SELECT MAX(m.created_at) FROM messages m WHERE m.chat_id = userchatmetadata.chat_id) \
+                 OR userchatmetadata.messages_visible_from > (SELECT MAX(m.created_at) FROM messages m WHERE m.chat_id = userchatmetadata.chat_id) \
+                 THEN 0 ELSE unread_count END",
+            );
+        }
 
         query_builder.push(" WHERE user_id = ");
         query_builder.push_bind(id.0);
@@ -343,7 +867,16 @@ impl Update<UserChatMetadata, UpdateUserChatMetadataDTO, UserChatKey>
         query_builder.push(" AND chat_id = ");
         query_builder.push_bind(id.1);
 
-        query_builder.build().execute(&self.connection_pool).await?;
+        query_builder.push(" AND version = ");
+        query_builder.push_bind(expected_version);
+
+        let result = query_builder
+            .build()
+            .execute(timed(self.connection_pool.mysql()))
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(self.missing_or_conflict(id).await);
+        }
 
         // Fetch and return the updated metadata
         self.read(id).await?.ok_or_else(|| sqlx::Error::RowNotFound)
@@ -352,15 +885,11 @@ impl Update<UserChatMetadata, UpdateUserChatMetadataDTO, UserChatKey>
 
 impl Delete<UserChatKey> for UserChatMetadataRepository {
     async fn delete(&self, id: &UserChatKey) -> Result<(), Error> {
-        sqlx::query!(
-            "DELETE FROM userchatmetadata WHERE user_id = ? AND chat_id=?",
-            id.0,
-            id.1
-        )
-        .execute(&self.connection_pool)
-        .await?;
-
-        Ok(())
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::user_chat_metadata::delete(pool, id).await;
+        }
+        Self::remove(&mut *self.connection_pool.mysql().acquire().await?, id).await
     }
 }
 
@@ -370,17 +899,19 @@ mod tests {
     use crate::entities::UserRole;
     use sqlx::MySqlPool;
 
-    /*----------------------------------*/
-    /* Unit tests: find_many_by_chat_id */
-    /*----------------------------------*/
+    /*--------------------------------------------*/
+    /* Unit tests: read_many (membri di una chat) */
+    /*--------------------------------------------*/
 
     /// Test: trova tutti i membri di una chat esistente
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
-    async fn test_find_many_by_chat_id_success(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_members_of_chat_success(pool: MySqlPool) -> sqlx::Result<()> {
         let repo = UserChatMetadataRepository::new(pool);
 
         // La "General Chat" (chat_id=1) ha 3 membri: alice, bob, charlie
-        let result = repo.find_many_by_chat_id(&1).await?;
+        let result = repo
+            .read_many(&MemberFilter::in_chat(1), &Page::ALL)
+            .await?;
 
         assert_eq!(result.len(), 3);
 
@@ -395,11 +926,13 @@ mod tests {
 
     /// Test: trova i membri di una chat privata
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
-    async fn test_find_many_by_chat_id_private_chat(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_members_of_chat_private_chat(pool: MySqlPool) -> sqlx::Result<()> {
         let repo = UserChatMetadataRepository::new(pool);
 
         // La chat privata Alice-Bob (chat_id=2) ha 2 membri
-        let result = repo.find_many_by_chat_id(&2).await?;
+        let result = repo
+            .read_many(&MemberFilter::in_chat(2), &Page::ALL)
+            .await?;
 
         assert_eq!(result.len(), 2);
 
@@ -412,11 +945,13 @@ mod tests {
 
     /// Test: restituisce lista vuota per chat inesistente
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
-    async fn test_find_many_by_chat_id_not_found(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_members_of_chat_not_found(pool: MySqlPool) -> sqlx::Result<()> {
         let repo = UserChatMetadataRepository::new(pool);
 
         // Chat inesistente
-        let result = repo.find_many_by_chat_id(&999).await?;
+        let result = repo
+            .read_many(&MemberFilter::in_chat(999), &Page::ALL)
+            .await?;
 
         assert_eq!(result.len(), 0);
         assert!(result.is_empty());
@@ -424,13 +959,85 @@ mod tests {
         Ok(())
     }
 
+    /// Test: pagine di membri successive all'ultimo user_id, con filtro per ruolo
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_read_many_members_page(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = UserChatMetadataRepository::new(pool);
+
+        let page_after = |after_user_id| MemberFilter {
+            chat_id: Some(1),
+            after_user_id,
+            ..MemberFilter::default()
+        };
+        let first = repo.read_many(&page_after(0), &Page::first(2)).await?;
+        let first_ids: Vec<i32> = first.iter().map(|m| m.user_id).collect();
+        assert_eq!(first_ids, vec![1, 2]);
+
+        let second = repo.read_many(&page_after(2), &Page::first(2)).await?;
+        let second_ids: Vec<i32> = second.iter().map(|m| m.user_id).collect();
+        assert_eq!(second_ids, vec![3]);
+
+        assert!(
+            repo.read_many(&page_after(3), &Page::first(2))
+                .await?
+                .is_empty()
+        );
+
+        // Solo i Member della "General Chat": bob e charlie
+        let filter = MemberFilter {
+            chat_id: Some(1),
+            role: Some(UserRole::Member),
+            ..MemberFilter::default()
+        };
+        let members = repo.read_many(&filter, &Page::first(10)).await?;
+        let member_ids: Vec<i32> = members.iter().map(|m| m.user_id).collect();
+        assert_eq!(member_ids, vec![2, 3]);
+
+        Ok(())
+    }
+
+    /// Test: membri di più chat insieme, anche ristretti a un solo utente
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_read_many_members_of_several_chats(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = UserChatMetadataRepository::new(pool);
+
+        let filter = MemberFilter {
+            chat_ids: Some(vec![2, 3]),
+            ..MemberFilter::default()
+        };
+        let members = repo.read_many(&filter, &Page::ALL).await?;
+        let keys: Vec<(i32, i32)> = members.iter().map(|m| (m.chat_id, m.user_id)).collect();
+        assert_eq!(keys, vec![(2, 1), (2, 2), (3, 1), (3, 3)]);
+
+        // Charlie è solo nella chat 3 tra le due
+        let filter = MemberFilter {
+            user_id: Some(3),
+            ..filter
+        }
+        .from_primary();
+        let members = repo.read_many(&filter, &Page::ALL).await?;
+        let keys: Vec<(i32, i32)> = members.iter().map(|m| (m.chat_id, m.user_id)).collect();
+        assert_eq!(keys, vec![(3, 3)]);
+
+        // Nessuna chat richiesta: nessun membro
+        let filter = MemberFilter {
+            chat_ids: Some(Vec::new()),
+            ..MemberFilter::default()
+        };
+        assert!(repo.read_many(&filter, &Page::ALL).await?.is_empty());
+
+        Ok(())
+    }
+
     /// Test: verifica che i ruoli siano caricati correttamente
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
-    async fn test_find_many_by_chat_id_with_roles(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_members_of_chat_with_roles(pool: MySqlPool) -> sqlx::Result<()> {
         let repo = UserChatMetadataRepository::new(pool);
 
         // General Chat (chat_id=1): alice=OWNER, bob=MEMBER, charlie=MEMBER
-        let result = repo.find_many_by_chat_id(&1).await?;
+        let result = repo
+            .read_many(&MemberFilter::in_chat(1), &Page::ALL)
+            .await?;
 
         // Trova alice (user_id=1)
         let alice_metadata = result.iter().find(|m| m.user_id == 1).unwrap();
@@ -449,11 +1056,13 @@ mod tests {
 
     /// Test: verifica i diversi ruoli in una chat (OWNER, ADMIN, MEMBER)
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
-    async fn test_find_many_by_chat_id_different_roles(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_members_of_chat_different_roles(pool: MySqlPool) -> sqlx::Result<()> {
         let repo = UserChatMetadataRepository::new(pool);
 
         // Dev Team (chat_id=3): alice=OWNER, charlie=ADMIN
-        let result = repo.find_many_by_chat_id(&3).await?;
+        let result = repo
+            .read_many(&MemberFilter::in_chat(3), &Page::ALL)
+            .await?;
 
         assert_eq!(result.len(), 2);
 
@@ -468,11 +1077,13 @@ mod tests {
 
     /// Test CASCADE: eliminazione di un utente elimina i suoi metadata
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
-    async fn test_find_many_by_chat_id_cascade_delete_user(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_members_of_chat_cascade_delete_user(pool: MySqlPool) -> sqlx::Result<()> {
         let repo = UserChatMetadataRepository::new(pool.clone());
 
         // Prima: General Chat ha 3 membri
-        let result_before = repo.find_many_by_chat_id(&1).await?;
+        let result_before = repo
+            .read_many(&MemberFilter::in_chat(1), &Page::ALL)
+            .await?;
         assert_eq!(result_before.len(), 3);
 
         // Elimina Bob (user_id=2)
@@ -482,7 +1093,9 @@ mod tests {
             .await?;
 
         // Dopo: General Chat dovrebbe avere solo 2 membri
-        let result_after = repo.find_many_by_chat_id(&1).await?;
+        let result_after = repo
+            .read_many(&MemberFilter::in_chat(1), &Page::ALL)
+            .await?;
         assert_eq!(result_after.len(), 2);
 
         // Verifica che Bob non sia più presente
@@ -496,11 +1109,13 @@ mod tests {
 
     /// Test CASCADE: eliminazione di una chat elimina tutti i metadata associati
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
-    async fn test_find_many_by_chat_id_cascade_delete_chat(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_members_of_chat_cascade_delete_chat(pool: MySqlPool) -> sqlx::Result<()> {
         let repo = UserChatMetadataRepository::new(pool.clone());
 
         // Prima: General Chat (chat_id=1) ha 3 membri
-        let result_before = repo.find_many_by_chat_id(&1).await?;
+        let result_before = repo
+            .read_many(&MemberFilter::in_chat(1), &Page::ALL)
+            .await?;
         assert_eq!(result_before.len(), 3);
 
         // Elimina la chat
@@ -510,7 +1125,9 @@ mod tests {
             .await?;
 
         // Dopo: nessun metadata dovrebbe esistere per quella chat
-        let result_after = repo.find_many_by_chat_id(&1).await?;
+        let result_after = repo
+            .read_many(&MemberFilter::in_chat(1), &Page::ALL)
+            .await?;
         assert_eq!(result_after.len(), 0);
         assert!(result_after.is_empty());
 
@@ -531,14 +1148,20 @@ mod tests {
 
     /// Test CASCADE: eliminazione di utente che è OWNER in più chat
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
-    async fn test_find_many_by_chat_id_cascade_delete_owner(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_members_of_chat_cascade_delete_owner(pool: MySqlPool) -> sqlx::Result<()> {
         let repo = UserChatMetadataRepository::new(pool.clone());
 
         // Alice (user_id=1) è OWNER in chat 1, 2 e 3
         // Verifica stato iniziale
-        let chat1_before = repo.find_many_by_chat_id(&1).await?;
-        let chat2_before = repo.find_many_by_chat_id(&2).await?;
-        let chat3_before = repo.find_many_by_chat_id(&3).await?;
+        let chat1_before = repo
+            .read_many(&MemberFilter::in_chat(1), &Page::ALL)
+            .await?;
+        let chat2_before = repo
+            .read_many(&MemberFilter::in_chat(2), &Page::ALL)
+            .await?;
+        let chat3_before = repo
+            .read_many(&MemberFilter::in_chat(3), &Page::ALL)
+            .await?;
 
         assert_eq!(chat1_before.len(), 3); // General Chat
         assert_eq!(chat2_before.len(), 2); // Private Alice-Bob
@@ -551,9 +1174,15 @@ mod tests {
             .await?;
 
         // Verifica che Alice sia stata rimossa da tutte le chat
-        let chat1_after = repo.find_many_by_chat_id(&1).await?;
-        let chat2_after = repo.find_many_by_chat_id(&2).await?;
-        let chat3_after = repo.find_many_by_chat_id(&3).await?;
+        let chat1_after = repo
+            .read_many(&MemberFilter::in_chat(1), &Page::ALL)
+            .await?;
+        let chat2_after = repo
+            .read_many(&MemberFilter::in_chat(2), &Page::ALL)
+            .await?;
+        let chat3_after = repo
+            .read_many(&MemberFilter::in_chat(3), &Page::ALL)
+            .await?;
 
         assert_eq!(chat1_after.len(), 2); // bob e charlie rimangono
         assert_eq!(chat2_after.len(), 1); // solo bob rimane
@@ -569,11 +1198,13 @@ mod tests {
 
     /// Test: aggiunta di un nuovo membro e verifica che sia trovato
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
-    async fn test_find_many_by_chat_id_after_adding_member(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_members_of_chat_after_adding_member(pool: MySqlPool) -> sqlx::Result<()> {
         let repo = UserChatMetadataRepository::new(pool.clone());
 
         // Dev Team (chat_id=3) inizialmente ha 2 membri: alice e charlie
-        let result_before = repo.find_many_by_chat_id(&3).await?;
+        let result_before = repo
+            .read_many(&MemberFilter::in_chat(3), &Page::ALL)
+            .await?;
         assert_eq!(result_before.len(), 2);
 
         // Aggiungi Bob al Dev Team
@@ -588,7 +1219,9 @@ mod tests {
         .await?;
 
         // Ora dovrebbe avere 3 membri
-        let result_after = repo.find_many_by_chat_id(&3).await?;
+        let result_after = repo
+            .read_many(&MemberFilter::in_chat(3), &Page::ALL)
+            .await?;
         assert_eq!(result_after.len(), 3);
 
         // Verifica che Bob sia presente
@@ -601,11 +1234,13 @@ mod tests {
 
     /// Test: rimozione di un membro specifico
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
-    async fn test_find_many_by_chat_id_after_removing_member(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_members_of_chat_after_removing_member(pool: MySqlPool) -> sqlx::Result<()> {
         let repo = UserChatMetadataRepository::new(pool.clone());
 
         // General Chat (chat_id=1) ha 3 membri
-        let result_before = repo.find_many_by_chat_id(&1).await?;
+        let result_before = repo
+            .read_many(&MemberFilter::in_chat(1), &Page::ALL)
+            .await?;
         assert_eq!(result_before.len(), 3);
 
         // Rimuovi Charlie dalla General Chat
@@ -618,7 +1253,9 @@ mod tests {
         .await?;
 
         // Ora dovrebbe avere 2 membri
-        let result_after = repo.find_many_by_chat_id(&1).await?;
+        let result_after = repo
+            .read_many(&MemberFilter::in_chat(1), &Page::ALL)
+            .await?;
         assert_eq!(result_after.len(), 2);
 
         // Verifica che Charlie non ci sia più
@@ -633,10 +1270,12 @@ mod tests {
 
     /// Test: verifica che i timestamp siano caricati correttamente
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
-    async fn test_find_many_by_chat_id_with_timestamps(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_members_of_chat_with_timestamps(pool: MySqlPool) -> sqlx::Result<()> {
         let repo = UserChatMetadataRepository::new(pool);
 
-        let result = repo.find_many_by_chat_id(&1).await?;
+        let result = repo
+            .read_many(&MemberFilter::in_chat(1), &Page::ALL)
+            .await?;
 
         // Verifica che tutti i membri abbiano timestamp validi
         for metadata in result {
@@ -650,13 +1289,15 @@ mod tests {
 
     /// Test CASCADE: eliminazione di più utenti contemporaneamente
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
-    async fn test_find_many_by_chat_id_cascade_delete_multiple_users(
+    async fn test_members_of_chat_cascade_delete_multiple_users(
         pool: MySqlPool,
     ) -> sqlx::Result<()> {
         let repo = UserChatMetadataRepository::new(pool.clone());
 
         // General Chat (chat_id=1) ha 3 membri
-        let result_before = repo.find_many_by_chat_id(&1).await?;
+        let result_before = repo
+            .read_many(&MemberFilter::in_chat(1), &Page::ALL)
+            .await?;
         assert_eq!(result_before.len(), 3);
 
         // Elimina Bob e Charlie
@@ -665,7 +1306,9 @@ mod tests {
             .await?;
 
         // Dovrebbe rimanere solo Alice
-        let result_after = repo.find_many_by_chat_id(&1).await?;
+        let result_after = repo
+            .read_many(&MemberFilter::in_chat(1), &Page::ALL)
+            .await?;
         assert_eq!(result_after.len(), 1);
         assert_eq!(result_after[0].user_id, 1);
         assert_eq!(result_after[0].user_role, Some(UserRole::Owner));
@@ -702,6 +1345,10 @@ mod tests {
         assert_eq!(alice_after.user_role, Some(UserRole::Admin));
         assert_eq!(bob_after.user_role, Some(UserRole::Owner));
 
+        // Entrambi puntano alle righe base della chat: Owner = 1, Admin = 2
+        assert_eq!(alice_after.role_id, Some(2));
+        assert_eq!(bob_after.role_id, Some(1));
+
         Ok(())
     }
 
@@ -797,7 +1444,9 @@ mod tests {
         assert_eq!(bob_after.user_role, Some(UserRole::Owner));
 
         // La chat dovrebbe avere 2 membri invece di 3
-        let members = repo.find_many_by_chat_id(&1).await?;
+        let members = repo
+            .read_many(&MemberFilter::in_chat(1), &Page::ALL)
+            .await?;
         assert_eq!(members.len(), 2);
 
         Ok(())
@@ -859,7 +1508,9 @@ mod tests {
         assert!(bob_after.is_none());
 
         // Verifica che la chat non abbia membri
-        let members = repo.find_many_by_chat_id(&1).await?;
+        let members = repo
+            .read_many(&MemberFilter::in_chat(1), &Page::ALL)
+            .await?;
         assert_eq!(members.len(), 0);
 
         Ok(())
@@ -957,17 +1608,19 @@ mod tests {
         Ok(())
     }
 
-    /*----------------------------------*/
-    /* Unit tests: find_many_by_user_id */
-    /*----------------------------------*/
+    /*-------------------------------------------*/
+    /* Unit tests: read_many (chat di un utente) */
+    /*-------------------------------------------*/
 
     /// Test: trova tutte le chat di un utente
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
-    async fn test_find_many_by_user_id_success(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_chats_of_user_success(pool: MySqlPool) -> sqlx::Result<()> {
         let repo = UserChatMetadataRepository::new(pool);
 
         // Alice (user_id=1) è in 3 chat: General Chat (1), Private Alice-Bob (2), Dev Team (3)
-        let result = repo.find_many_by_user_id(&1).await?;
+        let result = repo
+            .read_many(&MemberFilter::of_user(1), &Page::ALL)
+            .await?;
 
         assert_eq!(result.len(), 3);
 
@@ -982,11 +1635,13 @@ mod tests {
 
     /// Test: trova le chat di un utente che è in meno chat
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
-    async fn test_find_many_by_user_id_fewer_chats(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_chats_of_user_fewer_chats(pool: MySqlPool) -> sqlx::Result<()> {
         let repo = UserChatMetadataRepository::new(pool);
 
         // Bob (user_id=2) è in 2 chat: General Chat (1), Private Alice-Bob (2)
-        let result = repo.find_many_by_user_id(&2).await?;
+        let result = repo
+            .read_many(&MemberFilter::of_user(2), &Page::ALL)
+            .await?;
 
         assert_eq!(result.len(), 2);
 
@@ -1000,11 +1655,13 @@ mod tests {
 
     /// Test: restituisce lista vuota per utente inesistente
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
-    async fn test_find_many_by_user_id_not_found(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_chats_of_user_not_found(pool: MySqlPool) -> sqlx::Result<()> {
         let repo = UserChatMetadataRepository::new(pool);
 
         // Utente inesistente
-        let result = repo.find_many_by_user_id(&999).await?;
+        let result = repo
+            .read_many(&MemberFilter::of_user(999), &Page::ALL)
+            .await?;
 
         assert_eq!(result.len(), 0);
         assert!(result.is_empty());
@@ -1014,18 +1671,22 @@ mod tests {
 
     /// Test: verifica i ruoli dell'utente nelle varie chat
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
-    async fn test_find_many_by_user_id_with_roles(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_chats_of_user_with_roles(pool: MySqlPool) -> sqlx::Result<()> {
         let repo = UserChatMetadataRepository::new(pool);
 
         // Alice è OWNER in tutte e 3 le sue chat
-        let result = repo.find_many_by_user_id(&1).await?;
+        let result = repo
+            .read_many(&MemberFilter::of_user(1), &Page::ALL)
+            .await?;
 
         for metadata in &result {
             assert_eq!(metadata.user_role, Some(UserRole::Owner));
         }
 
         // Charlie (user_id=3): MEMBER in General Chat, ADMIN in Dev Team
-        let charlie_result = repo.find_many_by_user_id(&3).await?;
+        let charlie_result = repo
+            .read_many(&MemberFilter::of_user(3), &Page::ALL)
+            .await?;
 
         let general_chat = charlie_result.iter().find(|m| m.chat_id == 1).unwrap();
         assert_eq!(general_chat.user_role, Some(UserRole::Member));
@@ -1038,10 +1699,12 @@ mod tests {
 
     /// Test: verifica che i timestamp siano caricati correttamente
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
-    async fn test_find_many_by_user_id_with_timestamps(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_chats_of_user_with_timestamps(pool: MySqlPool) -> sqlx::Result<()> {
         let repo = UserChatMetadataRepository::new(pool);
 
-        let result = repo.find_many_by_user_id(&1).await?;
+        let result = repo
+            .read_many(&MemberFilter::of_user(1), &Page::ALL)
+            .await?;
 
         // Verifica che tutti i metadata abbiano timestamp validi
         for metadata in result {
@@ -1055,11 +1718,13 @@ mod tests {
 
     /// Test CASCADE: eliminazione di un utente elimina tutti i suoi metadata
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
-    async fn test_find_many_by_user_id_cascade_delete_user(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_chats_of_user_cascade_delete_user(pool: MySqlPool) -> sqlx::Result<()> {
         let repo = UserChatMetadataRepository::new(pool.clone());
 
         // Prima: Bob è in 2 chat
-        let result_before = repo.find_many_by_user_id(&2).await?;
+        let result_before = repo
+            .read_many(&MemberFilter::of_user(2), &Page::ALL)
+            .await?;
         assert_eq!(result_before.len(), 2);
 
         // Elimina Bob
@@ -1069,7 +1734,9 @@ mod tests {
             .await?;
 
         // Dopo: Bob non dovrebbe avere metadata
-        let result_after = repo.find_many_by_user_id(&2).await?;
+        let result_after = repo
+            .read_many(&MemberFilter::of_user(2), &Page::ALL)
+            .await?;
         assert_eq!(result_after.len(), 0);
         assert!(result_after.is_empty());
 
@@ -1090,11 +1757,13 @@ mod tests {
 
     /// Test CASCADE: eliminazione di una chat rimuove il metadata per quell'utente
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
-    async fn test_find_many_by_user_id_cascade_delete_chat(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_chats_of_user_cascade_delete_chat(pool: MySqlPool) -> sqlx::Result<()> {
         let repo = UserChatMetadataRepository::new(pool.clone());
 
         // Prima: Alice è in 3 chat
-        let result_before = repo.find_many_by_user_id(&1).await?;
+        let result_before = repo
+            .read_many(&MemberFilter::of_user(1), &Page::ALL)
+            .await?;
         assert_eq!(result_before.len(), 3);
 
         // Elimina General Chat (chat_id=1)
@@ -1104,7 +1773,9 @@ mod tests {
             .await?;
 
         // Dopo: Alice dovrebbe essere in 2 chat
-        let result_after = repo.find_many_by_user_id(&1).await?;
+        let result_after = repo
+            .read_many(&MemberFilter::of_user(1), &Page::ALL)
+            .await?;
         assert_eq!(result_after.len(), 2);
 
         // Verifica che la chat eliminata non sia più presente
@@ -1118,13 +1789,13 @@ mod tests {
 
     /// Test CASCADE: eliminazione di più chat
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
-    async fn test_find_many_by_user_id_cascade_delete_multiple_chats(
-        pool: MySqlPool,
-    ) -> sqlx::Result<()> {
+    async fn test_chats_of_user_cascade_delete_multiple_chats(pool: MySqlPool) -> sqlx::Result<()> {
         let repo = UserChatMetadataRepository::new(pool.clone());
 
         // Alice è in 3 chat
-        let result_before = repo.find_many_by_user_id(&1).await?;
+        let result_before = repo
+            .read_many(&MemberFilter::of_user(1), &Page::ALL)
+            .await?;
         assert_eq!(result_before.len(), 3);
 
         // Elimina 2 chat
@@ -1133,7 +1804,9 @@ mod tests {
             .await?;
 
         // Alice dovrebbe essere solo in 1 chat
-        let result_after = repo.find_many_by_user_id(&1).await?;
+        let result_after = repo
+            .read_many(&MemberFilter::of_user(1), &Page::ALL)
+            .await?;
         assert_eq!(result_after.len(), 1);
         assert_eq!(result_after[0].chat_id, 3); // Solo Dev Team rimane
 
@@ -1142,13 +1815,15 @@ mod tests {
 
     /// Test CASCADE: eliminazione di utente con molte chat
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
-    async fn test_find_many_by_user_id_cascade_delete_user_with_multiple_chats(
+    async fn test_chats_of_user_cascade_delete_user_with_multiple_chats(
         pool: MySqlPool,
     ) -> sqlx::Result<()> {
         let repo = UserChatMetadataRepository::new(pool.clone());
 
         // Alice (user_id=1) è OWNER in 3 chat
-        let result_before = repo.find_many_by_user_id(&1).await?;
+        let result_before = repo
+            .read_many(&MemberFilter::of_user(1), &Page::ALL)
+            .await?;
         assert_eq!(result_before.len(), 3);
 
         // Verifica che sia presente in tutte e 3
@@ -1164,7 +1839,9 @@ mod tests {
             .await?;
 
         // Alice non dovrebbe avere più metadata
-        let result_after = repo.find_many_by_user_id(&1).await?;
+        let result_after = repo
+            .read_many(&MemberFilter::of_user(1), &Page::ALL)
+            .await?;
         assert_eq!(result_after.len(), 0);
 
         // Le chat dovrebbero esistere ancora (non sono state eliminate)
@@ -1187,11 +1864,13 @@ mod tests {
 
     /// Test: aggiunta di un utente a una nuova chat
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
-    async fn test_find_many_by_user_id_after_adding_to_chat(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_chats_of_user_after_adding_to_chat(pool: MySqlPool) -> sqlx::Result<()> {
         let repo = UserChatMetadataRepository::new(pool.clone());
 
         // Bob inizialmente è in 2 chat
-        let result_before = repo.find_many_by_user_id(&2).await?;
+        let result_before = repo
+            .read_many(&MemberFilter::of_user(2), &Page::ALL)
+            .await?;
         assert_eq!(result_before.len(), 2);
 
         // Aggiungi Bob al Dev Team (chat_id=3)
@@ -1206,7 +1885,9 @@ mod tests {
         .await?;
 
         // Ora Bob dovrebbe essere in 3 chat
-        let result_after = repo.find_many_by_user_id(&2).await?;
+        let result_after = repo
+            .read_many(&MemberFilter::of_user(2), &Page::ALL)
+            .await?;
         assert_eq!(result_after.len(), 3);
 
         let chat_ids: Vec<i32> = result_after.iter().map(|m| m.chat_id).collect();
@@ -1219,11 +1900,13 @@ mod tests {
 
     /// Test: rimozione di un utente da una chat specifica
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
-    async fn test_find_many_by_user_id_after_leaving_chat(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_chats_of_user_after_leaving_chat(pool: MySqlPool) -> sqlx::Result<()> {
         let repo = UserChatMetadataRepository::new(pool.clone());
 
         // Alice è in 3 chat
-        let result_before = repo.find_many_by_user_id(&1).await?;
+        let result_before = repo
+            .read_many(&MemberFilter::of_user(1), &Page::ALL)
+            .await?;
         assert_eq!(result_before.len(), 3);
 
         // Alice lascia General Chat (chat_id=1)
@@ -1236,7 +1919,9 @@ mod tests {
         .await?;
 
         // Ora Alice dovrebbe essere in 2 chat
-        let result_after = repo.find_many_by_user_id(&1).await?;
+        let result_after = repo
+            .read_many(&MemberFilter::of_user(1), &Page::ALL)
+            .await?;
         assert_eq!(result_after.len(), 2);
 
         let chat_ids: Vec<i32> = result_after.iter().map(|m| m.chat_id).collect();
@@ -1249,13 +1934,13 @@ mod tests {
 
     /// Test: verifica risultati dopo trasferimento ownership
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
-    async fn test_find_many_by_user_id_after_ownership_transfer(
-        pool: MySqlPool,
-    ) -> sqlx::Result<()> {
+    async fn test_chats_of_user_after_ownership_transfer(pool: MySqlPool) -> sqlx::Result<()> {
         let repo = UserChatMetadataRepository::new(pool.clone());
 
         // Stato iniziale
-        let alice_before = repo.find_many_by_user_id(&1).await?;
+        let alice_before = repo
+            .read_many(&MemberFilter::of_user(1), &Page::ALL)
+            .await?;
         assert_eq!(alice_before.len(), 3);
 
         // Verifica che Alice sia OWNER in tutte le sue chat
@@ -1267,7 +1952,9 @@ mod tests {
         repo.transfer_ownership(&1, &2, &1).await?;
 
         // Alice dovrebbe essere ancora in 3 chat
-        let alice_after = repo.find_many_by_user_id(&1).await?;
+        let alice_after = repo
+            .read_many(&MemberFilter::of_user(1), &Page::ALL)
+            .await?;
         assert_eq!(alice_after.len(), 3);
 
         // Ma il suo ruolo in General Chat dovrebbe essere ADMIN
@@ -1286,7 +1973,7 @@ mod tests {
 
     /// Test: utente in solo una chat (caso minimo)
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
-    async fn test_find_many_by_user_id_single_chat(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_chats_of_user_single_chat(pool: MySqlPool) -> sqlx::Result<()> {
         let repo = UserChatMetadataRepository::new(pool.clone());
 
         // Rimuovi Bob da tutte le chat tranne una
@@ -1299,7 +1986,9 @@ mod tests {
         .await?;
 
         // Bob dovrebbe essere solo in 1 chat
-        let result = repo.find_many_by_user_id(&2).await?;
+        let result = repo
+            .read_many(&MemberFilter::of_user(2), &Page::ALL)
+            .await?;
         assert_eq!(result.len(), 1);
         assert_eq!(result[0].chat_id, 1);
 
@@ -1308,13 +1997,13 @@ mod tests {
 
     /// Test CASCADE: eliminazione di tutte le chat di un utente
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
-    async fn test_find_many_by_user_id_cascade_delete_all_user_chats(
-        pool: MySqlPool,
-    ) -> sqlx::Result<()> {
+    async fn test_chats_of_user_cascade_delete_all_user_chats(pool: MySqlPool) -> sqlx::Result<()> {
         let repo = UserChatMetadataRepository::new(pool.clone());
 
         // Bob è in 2 chat (chat_id=1, chat_id=2)
-        let result_before = repo.find_many_by_user_id(&2).await?;
+        let result_before = repo
+            .read_many(&MemberFilter::of_user(2), &Page::ALL)
+            .await?;
         assert_eq!(result_before.len(), 2);
 
         // Elimina entrambe le chat di Bob
@@ -1323,7 +2012,9 @@ mod tests {
             .await?;
 
         // Bob non dovrebbe avere più chat
-        let result_after = repo.find_many_by_user_id(&2).await?;
+        let result_after = repo
+            .read_many(&MemberFilter::of_user(2), &Page::ALL)
+            .await?;
         assert_eq!(result_after.len(), 0);
         assert!(result_after.is_empty());
 
@@ -1341,11 +2032,13 @@ mod tests {
 
     /// Test: verifica ordinamento dei risultati (se presente)
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
-    async fn test_find_many_by_user_id_result_order(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_chats_of_user_result_order(pool: MySqlPool) -> sqlx::Result<()> {
         let repo = UserChatMetadataRepository::new(pool);
 
         // Alice è in 3 chat
-        let result = repo.find_many_by_user_id(&1).await?;
+        let result = repo
+            .read_many(&MemberFilter::of_user(1), &Page::ALL)
+            .await?;
         assert_eq!(result.len(), 3);
 
         // Verifica che tutti i risultati siano validi e abbiano lo stesso user_id
@@ -1359,14 +2052,16 @@ mod tests {
 
     /// Test CASCADE: interazione tra eliminazione utente e chat
     #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
-    async fn test_find_many_by_user_id_cascade_mixed_operations(
-        pool: MySqlPool,
-    ) -> sqlx::Result<()> {
+    async fn test_chats_of_user_cascade_mixed_operations(pool: MySqlPool) -> sqlx::Result<()> {
         let repo = UserChatMetadataRepository::new(pool.clone());
 
         // Stato iniziale: Bob in 2 chat, Charlie in 2 chat
-        let bob_before = repo.find_many_by_user_id(&2).await?;
-        let charlie_before = repo.find_many_by_user_id(&3).await?;
+        let bob_before = repo
+            .read_many(&MemberFilter::of_user(2), &Page::ALL)
+            .await?;
+        let charlie_before = repo
+            .read_many(&MemberFilter::of_user(3), &Page::ALL)
+            .await?;
         assert_eq!(bob_before.len(), 2);
         assert_eq!(charlie_before.len(), 2);
 
@@ -1381,11 +2076,15 @@ mod tests {
             .await?;
 
         // Bob non dovrebbe avere metadata
-        let bob_after = repo.find_many_by_user_id(&2).await?;
+        let bob_after = repo
+            .read_many(&MemberFilter::of_user(2), &Page::ALL)
+            .await?;
         assert_eq!(bob_after.len(), 0);
 
         // Charlie dovrebbe essere in 1 sola chat (Dev Team)
-        let charlie_after = repo.find_many_by_user_id(&3).await?;
+        let charlie_after = repo
+            .read_many(&MemberFilter::of_user(3), &Page::ALL)
+            .await?;
         assert_eq!(charlie_after.len(), 1);
         assert_eq!(charlie_after[0].chat_id, 3);
 
@@ -1453,7 +2152,9 @@ mod tests {
         assert_eq!(result[2].user_role, Some(UserRole::Member));
 
         // Verifica che siano stati effettivamente inseriti nel database
-        let chat_members = repo.find_many_by_chat_id(&new_chat_id).await?;
+        let chat_members = repo
+            .read_many(&MemberFilter::in_chat(new_chat_id), &Page::ALL)
+            .await?;
         assert_eq!(chat_members.len(), 3);
 
         Ok(())
@@ -1549,7 +2250,9 @@ mod tests {
         assert!(result.is_err());
 
         // Verifica che nessun metadata sia stato creato (rollback automatico)
-        let chat_members = repo.find_many_by_chat_id(&new_chat_id).await?;
+        let chat_members = repo
+            .read_many(&MemberFilter::in_chat(new_chat_id), &Page::ALL)
+            .await?;
         assert_eq!(
             chat_members.len(),
             0,
@@ -1692,7 +2395,9 @@ mod tests {
         assert_eq!(result.len(), 2);
 
         // Verifica che i metadata esistano
-        let members = repo.find_many_by_chat_id(&new_chat_id).await?;
+        let members = repo
+            .read_many(&MemberFilter::in_chat(new_chat_id), &Page::ALL)
+            .await?;
         assert_eq!(members.len(), 2);
 
         // Elimina la chat - CASCADE dovrebbe eliminare tutti i metadata
@@ -1701,7 +2406,9 @@ mod tests {
             .await?;
 
         // Verifica che i metadata siano stati eliminati
-        let members_after = repo.find_many_by_chat_id(&new_chat_id).await?;
+        let members_after = repo
+            .read_many(&MemberFilter::in_chat(new_chat_id), &Page::ALL)
+            .await?;
         assert_eq!(
             members_after.len(),
             0,
@@ -1763,7 +2470,9 @@ mod tests {
         assert_eq!(result.len(), 10);
 
         // Verifica nel database
-        let members = repo.find_many_by_chat_id(&new_chat_id).await?;
+        let members = repo
+            .read_many(&MemberFilter::in_chat(new_chat_id), &Page::ALL)
+            .await?;
         assert_eq!(members.len(), 10);
 
         Ok(())
@@ -1945,7 +2654,9 @@ mod tests {
         repo.create_many(&metadata_list).await?;
 
         // Verifica creazione
-        let members_before = repo.find_many_by_chat_id(&new_chat_id).await?;
+        let members_before = repo
+            .read_many(&MemberFilter::in_chat(new_chat_id), &Page::ALL)
+            .await?;
         assert_eq!(members_before.len(), 2);
 
         // Elimina entrambi gli utenti
@@ -1958,7 +2669,9 @@ mod tests {
         .await?;
 
         // Verifica che i metadata siano stati eliminati
-        let members_after = repo.find_many_by_chat_id(&new_chat_id).await?;
+        let members_after = repo
+            .read_many(&MemberFilter::in_chat(new_chat_id), &Page::ALL)
+            .await?;
         assert_eq!(
             members_after.len(),
             0,
@@ -1982,15 +2695,41 @@ mod tests {
         assert_eq!(before.user_role, Some(UserRole::Member));
 
         // Promuovi Bob ad ADMIN
-        let result = repo.update_user_role(&2, &1, &UserRole::Admin).await?;
+        let result = repo.update_user_role(&2, &1, &UserRole::Admin, &0).await?;
 
         assert_eq!(result.user_id, 2);
         assert_eq!(result.chat_id, 1);
         assert_eq!(result.user_role, Some(UserRole::Admin));
 
-        // Verifica nel database
+        // Verifica nel database: Bob passa dalla riga base Member (3) a quella Admin (2)
         let after = repo.read(&(2, 1)).await?.unwrap();
         assert_eq!(after.user_role, Some(UserRole::Admin));
+        assert_eq!(before.role_id, Some(3));
+        assert_eq!(after.role_id, Some(2));
+
+        Ok(())
+    }
+
+    /// Test: il nuovo membro punta alla riga del suo ruolo base nella chat
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_create_points_to_base_role(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = UserChatMetadataRepository::new(pool);
+
+        let now = chrono::Utc::now();
+        let created = repo
+            .create(&CreateUserChatMetadataDTO {
+                user_id: 2,
+                chat_id: 3,
+                user_role: Some(UserRole::Member),
+                member_since: now,
+                messages_visible_from: now,
+                messages_received_until: now,
+            })
+            .await?;
+
+        // La riga 9 è il ruolo base Member del Dev Team
+        assert_eq!(created.role_id, Some(9));
+        assert_eq!(repo.read(&(2, 3)).await?.unwrap().role_id, Some(9));
 
         Ok(())
     }
@@ -2005,7 +2744,7 @@ mod tests {
         assert_eq!(before.user_role, Some(UserRole::Admin));
 
         // Promuovi Charlie a OWNER
-        let result = repo.update_user_role(&3, &3, &UserRole::Owner).await?;
+        let result = repo.update_user_role(&3, &3, &UserRole::Owner, &0).await?;
 
         assert_eq!(result.user_role, Some(UserRole::Owner));
 
@@ -2022,7 +2761,7 @@ mod tests {
         assert_eq!(before.user_role, Some(UserRole::Owner));
 
         // Degrada Alice a MEMBER
-        let result = repo.update_user_role(&1, &1, &UserRole::Member).await?;
+        let result = repo.update_user_role(&1, &1, &UserRole::Member, &0).await?;
 
         assert_eq!(result.user_role, Some(UserRole::Member));
 
@@ -2039,7 +2778,7 @@ mod tests {
         let repo = UserChatMetadataRepository::new(pool);
 
         // Tentativo di aggiornare utente inesistente
-        let result = repo.update_user_role(&999, &1, &UserRole::Admin).await;
+        let result = repo.update_user_role(&999, &1, &UserRole::Admin, &0).await;
 
         assert!(result.is_err());
         assert!(matches!(result.unwrap_err(), sqlx::Error::RowNotFound));
@@ -2053,7 +2792,7 @@ mod tests {
         let repo = UserChatMetadataRepository::new(pool);
 
         // Tentativo di aggiornare in una chat inesistente
-        let result = repo.update_user_role(&1, &999, &UserRole::Admin).await;
+        let result = repo.update_user_role(&1, &999, &UserRole::Admin, &0).await;
 
         assert!(result.is_err());
         assert!(matches!(result.unwrap_err(), sqlx::Error::RowNotFound));
@@ -2067,7 +2806,7 @@ mod tests {
         let repo = UserChatMetadataRepository::new(pool);
 
         // Bob (user_id=2) non è nel Dev Team (chat_id=3)
-        let result = repo.update_user_role(&2, &3, &UserRole::Admin).await;
+        let result = repo.update_user_role(&2, &3, &UserRole::Admin, &0).await;
 
         assert!(result.is_err());
         assert!(matches!(result.unwrap_err(), sqlx::Error::RowNotFound));
@@ -2087,7 +2826,7 @@ mod tests {
         let original_received_until = before.messages_received_until;
 
         // Aggiorna solo il ruolo
-        repo.update_user_role(&2, &1, &UserRole::Admin).await?;
+        repo.update_user_role(&2, &1, &UserRole::Admin, &0).await?;
 
         // Verifica che gli altri campi siano invariati
         let after = repo.read(&(2, 1)).await?.unwrap();
@@ -2109,7 +2848,7 @@ mod tests {
         assert_eq!(before.user_role, Some(UserRole::Owner));
 
         // "Aggiorna" a OWNER (stesso valore)
-        let result = repo.update_user_role(&1, &1, &UserRole::Owner).await?;
+        let result = repo.update_user_role(&1, &1, &UserRole::Owner, &0).await?;
 
         assert_eq!(result.user_role, Some(UserRole::Owner));
 
@@ -2130,15 +2869,19 @@ mod tests {
         assert_eq!(metadata.user_role, Some(UserRole::Member));
 
         // MEMBER -> ADMIN
-        let result1 = repo.update_user_role(&2, &1, &UserRole::Admin).await?;
+        let result1 = repo.update_user_role(&2, &1, &UserRole::Admin, &0).await?;
         assert_eq!(result1.user_role, Some(UserRole::Admin));
 
         // ADMIN -> OWNER
-        let result2 = repo.update_user_role(&2, &1, &UserRole::Owner).await?;
+        let result2 = repo
+            .update_user_role(&2, &1, &UserRole::Owner, &result1.version)
+            .await?;
         assert_eq!(result2.user_role, Some(UserRole::Owner));
 
         // OWNER -> MEMBER
-        let result3 = repo.update_user_role(&2, &1, &UserRole::Member).await?;
+        let result3 = repo
+            .update_user_role(&2, &1, &UserRole::Member, &result2.version)
+            .await?;
         assert_eq!(result3.user_role, Some(UserRole::Member));
 
         // Verifica finale
@@ -2158,7 +2901,7 @@ mod tests {
         assert_eq!(before.user_role, Some(UserRole::Member));
 
         // Promuovi Bob a OWNER nella chat privata
-        let result = repo.update_user_role(&2, &2, &UserRole::Owner).await?;
+        let result = repo.update_user_role(&2, &2, &UserRole::Owner, &0).await?;
 
         assert_eq!(result.user_role, Some(UserRole::Owner));
 
@@ -2171,7 +2914,7 @@ mod tests {
         let repo = UserChatMetadataRepository::new(pool.clone());
 
         // Promuovi Bob ad ADMIN
-        repo.update_user_role(&2, &1, &UserRole::Admin).await?;
+        repo.update_user_role(&2, &1, &UserRole::Admin, &0).await?;
 
         // Verifica l'aggiornamento
         let after_update = repo.read(&(2, 1)).await?.unwrap();
@@ -2198,7 +2941,7 @@ mod tests {
         let repo = UserChatMetadataRepository::new(pool.clone());
 
         // Promuovi Bob ad ADMIN nella General Chat
-        repo.update_user_role(&2, &1, &UserRole::Admin).await?;
+        repo.update_user_role(&2, &1, &UserRole::Admin, &0).await?;
 
         // Verifica l'aggiornamento
         let after_update = repo.read(&(2, 1)).await?.unwrap();
@@ -2210,7 +2953,9 @@ mod tests {
             .await?;
 
         // Nessun metadata dovrebbe esistere per questa chat
-        let members = repo.find_many_by_chat_id(&1).await?;
+        let members = repo
+            .read_many(&MemberFilter::in_chat(1), &Page::ALL)
+            .await?;
         assert_eq!(
             members.len(),
             0,
@@ -2226,8 +2971,8 @@ mod tests {
         let repo = UserChatMetadataRepository::new(pool);
 
         // General Chat (chat_id=1): aggiorna ruoli di più utenti
-        repo.update_user_role(&2, &1, &UserRole::Admin).await?;
-        repo.update_user_role(&3, &1, &UserRole::Admin).await?;
+        repo.update_user_role(&2, &1, &UserRole::Admin, &0).await?;
+        repo.update_user_role(&3, &1, &UserRole::Admin, &0).await?;
 
         // Verifica che entrambi siano stati aggiornati
         let bob = repo.read(&(2, 1)).await?.unwrap();
@@ -2255,8 +3000,9 @@ mod tests {
         let alice = repo.read(&(1, 1)).await?.unwrap();
         assert_eq!(alice.user_role, Some(UserRole::Admin));
 
-        // Degrada Alice a MEMBER
-        repo.update_user_role(&1, &1, &UserRole::Member).await?;
+        // Degrada Alice a MEMBER (il trasferimento ha incrementato la versione)
+        repo.update_user_role(&1, &1, &UserRole::Member, &alice.version)
+            .await?;
 
         let alice_after = repo.read(&(1, 1)).await?.unwrap();
         assert_eq!(alice_after.user_role, Some(UserRole::Member));
@@ -2315,11 +3061,11 @@ mod tests {
         repo.create_many(&metadata_list).await?;
 
         // Aggiorna a ruoli diversi
-        repo.update_user_role(&1, &new_chat_id, &UserRole::Owner)
+        repo.update_user_role(&1, &new_chat_id, &UserRole::Owner, &0)
             .await?;
-        repo.update_user_role(&2, &new_chat_id, &UserRole::Admin)
+        repo.update_user_role(&2, &new_chat_id, &UserRole::Admin, &0)
             .await?;
-        repo.update_user_role(&3, &new_chat_id, &UserRole::Member)
+        repo.update_user_role(&3, &new_chat_id, &UserRole::Member, &0)
             .await?; // Rimane MEMBER
 
         // Verifica
@@ -2342,11 +3088,13 @@ mod tests {
         let repo = UserChatMetadataRepository::new(pool.clone());
 
         // Aggiorna più ruoli nella General Chat
-        repo.update_user_role(&2, &1, &UserRole::Admin).await?;
-        repo.update_user_role(&3, &1, &UserRole::Admin).await?;
+        repo.update_user_role(&2, &1, &UserRole::Admin, &0).await?;
+        repo.update_user_role(&3, &1, &UserRole::Admin, &0).await?;
 
         // Verifica gli aggiornamenti
-        let members_before = repo.find_many_by_chat_id(&1).await?;
+        let members_before = repo
+            .read_many(&MemberFilter::in_chat(1), &Page::ALL)
+            .await?;
         assert_eq!(members_before.len(), 3);
 
         // Conta gli admin
@@ -2362,7 +3110,9 @@ mod tests {
             .await?;
 
         // Tutti i metadata dovrebbero essere eliminati
-        let members_after = repo.find_many_by_chat_id(&1).await?;
+        let members_after = repo
+            .read_many(&MemberFilter::in_chat(1), &Page::ALL)
+            .await?;
         assert_eq!(members_after.len(), 0);
 
         Ok(())
@@ -2374,16 +3124,75 @@ mod tests {
         let repo = UserChatMetadataRepository::new(pool);
 
         // Aggiornamento valido
-        let result = repo.update_user_role(&2, &1, &UserRole::Admin).await;
+        let result = repo.update_user_role(&2, &1, &UserRole::Admin, &0).await;
         assert!(result.is_ok(), "L'aggiornamento dovrebbe avere successo");
 
         // Aggiornamento invalido (metadata inesistente)
-        let result_invalid = repo.update_user_role(&999, &999, &UserRole::Admin).await;
+        let result_invalid = repo
+            .update_user_role(&999, &999, &UserRole::Admin, &0)
+            .await;
         assert!(result_invalid.is_err(), "Dovrebbe fallire con RowNotFound");
 
         Ok(())
     }
 
+    /// Test: un secondo aggiornamento basato sulla stessa versione non sovrascrive il primo
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_update_user_role_stale_version(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = UserChatMetadataRepository::new(pool);
+
+        // Due amministratori leggono Bob alla stessa versione
+        let read = repo.read(&(2, 1)).await?.unwrap();
+
+        let first = repo
+            .update_user_role(&2, &1, &UserRole::Admin, &read.version)
+            .await?;
+        assert_eq!(first.version, read.version + 1);
+
+        let second = repo
+            .update_user_role(&2, &1, &UserRole::Member, &read.version)
+            .await;
+        assert!(is_version_conflict(&second.unwrap_err()));
+
+        let after = repo.read(&(2, 1)).await?.unwrap();
+        assert_eq!(after.user_role, Some(UserRole::Admin));
+        assert_eq!(after.version, first.version);
+
+        Ok(())
+    }
+
+    /// Test: update con una versione superata fallisce senza modificare la riga
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_update_with_stale_version(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = UserChatMetadataRepository::new(pool);
+
+        let read = repo.read(&(3, 1)).await?.unwrap();
+        repo.transfer_ownership(&1, &3, &1).await?;
+
+        let update_dto = UpdateUserChatMetadataDTO {
+            user_role: Some(UserRole::Admin),
+            messages_visible_from: None,
+            messages_received_until: None,
+            version: Some(read.version),
+        };
+        let result = repo.update(&(3, 1), &update_dto).await;
+        assert!(is_version_conflict(&result.unwrap_err()));
+
+        let after = repo.read(&(3, 1)).await?.unwrap();
+        assert_eq!(after.user_role, Some(UserRole::Owner));
+
+        // Con la versione corrente l'aggiornamento va a buon fine
+        let update_dto = UpdateUserChatMetadataDTO {
+            version: Some(after.version),
+            ..update_dto
+        };
+        let updated = repo.update(&(3, 1), &update_dto).await?;
+        assert_eq!(updated.user_role, Some(UserRole::Admin));
+        assert_eq!(updated.version, after.version + 1);
+
+        Ok(())
+    }
+
     /*------------------------------------*/
     /* Unit tests: create (casi negativi) */
     /*------------------------------------*/
@@ -2710,7 +3519,9 @@ mod tests {
         }
 
         // Verifica che tutti i metadata esistano
-        let members = repo.find_many_by_chat_id(&new_chat_id).await?;
+        let members = repo
+            .read_many(&MemberFilter::in_chat(new_chat_id), &Page::ALL)
+            .await?;
         assert_eq!(members.len(), 3, "Dovrebbero esserci 3 membri");
 
         // Elimina la chat
@@ -2719,7 +3530,9 @@ mod tests {
             .await?;
 
         // Verifica che TUTTI i metadata siano stati eliminati (CASCADE)
-        let members_after = repo.find_many_by_chat_id(&new_chat_id).await?;
+        let members_after = repo
+            .read_many(&MemberFilter::in_chat(new_chat_id), &Page::ALL)
+            .await?;
         assert_eq!(
             members_after.len(),
             0,
@@ -2794,7 +3607,9 @@ mod tests {
         }
 
         // Verifica che l'utente sia in 2 chat
-        let user_chats = repo.find_many_by_user_id(&new_user_id).await?;
+        let user_chats = repo
+            .read_many(&MemberFilter::of_user(new_user_id), &Page::ALL)
+            .await?;
         assert_eq!(user_chats.len(), 2, "L'utente dovrebbe essere in 2 chat");
 
         // Elimina l'utente
@@ -2803,7 +3618,9 @@ mod tests {
             .await?;
 
         // Verifica che TUTTI i metadata siano stati eliminati (CASCADE)
-        let user_chats_after = repo.find_many_by_user_id(&new_user_id).await?;
+        let user_chats_after = repo
+            .read_many(&MemberFilter::of_user(new_user_id), &Page::ALL)
+            .await?;
         assert_eq!(
             user_chats_after.len(),
             0,
@@ -2886,10 +3703,30 @@ mod tests {
         }
 
         // Verifica: 4 metadata creati (2 utenti x 2 chat)
-        assert_eq!(repo.find_many_by_chat_id(&chat1_id).await?.len(), 2);
-        assert_eq!(repo.find_many_by_chat_id(&chat2_id).await?.len(), 2);
-        assert_eq!(repo.find_many_by_user_id(&user1_id).await?.len(), 2);
-        assert_eq!(repo.find_many_by_user_id(&user2_id).await?.len(), 2);
+        assert_eq!(
+            repo.read_many(&MemberFilter::in_chat(chat1_id), &Page::ALL)
+                .await?
+                .len(),
+            2
+        );
+        assert_eq!(
+            repo.read_many(&MemberFilter::in_chat(chat2_id), &Page::ALL)
+                .await?
+                .len(),
+            2
+        );
+        assert_eq!(
+            repo.read_many(&MemberFilter::of_user(user1_id), &Page::ALL)
+                .await?
+                .len(),
+            2
+        );
+        assert_eq!(
+            repo.read_many(&MemberFilter::of_user(user2_id), &Page::ALL)
+                .await?
+                .len(),
+            2
+        );
 
         // Elimina user1 - dovrebbe rimuovere 2 metadata (user1 in chat1 e chat2)
         sqlx::query!("DELETE FROM users WHERE user_id = ?", user1_id)
@@ -2897,17 +3734,23 @@ mod tests {
             .await?;
 
         assert_eq!(
-            repo.find_many_by_chat_id(&chat1_id).await?.len(),
+            repo.read_many(&MemberFilter::in_chat(chat1_id), &Page::ALL)
+                .await?
+                .len(),
             1,
             "Chat1 dovrebbe avere 1 membro"
         );
         assert_eq!(
-            repo.find_many_by_chat_id(&chat2_id).await?.len(),
+            repo.read_many(&MemberFilter::in_chat(chat2_id), &Page::ALL)
+                .await?
+                .len(),
             1,
             "Chat2 dovrebbe avere 1 membro"
         );
         assert_eq!(
-            repo.find_many_by_user_id(&user1_id).await?.len(),
+            repo.read_many(&MemberFilter::of_user(user1_id), &Page::ALL)
+                .await?
+                .len(),
             0,
             "User1 non dovrebbe avere metadata"
         );
@@ -2918,17 +3761,23 @@ mod tests {
             .await?;
 
         assert_eq!(
-            repo.find_many_by_chat_id(&chat1_id).await?.len(),
+            repo.read_many(&MemberFilter::in_chat(chat1_id), &Page::ALL)
+                .await?
+                .len(),
             0,
             "Chat1 non dovrebbe avere membri"
         );
         assert_eq!(
-            repo.find_many_by_chat_id(&chat2_id).await?.len(),
+            repo.read_many(&MemberFilter::in_chat(chat2_id), &Page::ALL)
+                .await?
+                .len(),
             1,
             "Chat2 dovrebbe ancora avere 1 membro"
         );
         assert_eq!(
-            repo.find_many_by_user_id(&user2_id).await?.len(),
+            repo.read_many(&MemberFilter::of_user(user2_id), &Page::ALL)
+                .await?
+                .len(),
             1,
             "User2 dovrebbe essere in 1 chat"
         );
@@ -3000,7 +3849,9 @@ mod tests {
         );
 
         // Verifica: dovrebbero esserci solo 2 metadata (quello fallito non è stato inserito)
-        let members = repo.find_many_by_chat_id(&new_chat_id).await?;
+        let members = repo
+            .read_many(&MemberFilter::in_chat(new_chat_id), &Page::ALL)
+            .await?;
         assert_eq!(
             members.len(),
             2,
@@ -3166,7 +4017,9 @@ mod tests {
         let repo = UserChatMetadataRepository::new(pool.clone());
 
         // Alice è in 3 chat (fixtures)
-        let chats_before = repo.find_many_by_user_id(&1).await?;
+        let chats_before = repo
+            .read_many(&MemberFilter::of_user(1), &Page::ALL)
+            .await?;
         let alice_chat_ids: Vec<i32> = chats_before.iter().map(|m| m.chat_id).collect();
 
         // Verifica che Alice sia in tutte quelle chat
@@ -3203,7 +4056,9 @@ mod tests {
         let repo = UserChatMetadataRepository::new(pool.clone());
 
         // General Chat ha 3 membri
-        let members_before = repo.find_many_by_chat_id(&1).await?;
+        let members_before = repo
+            .read_many(&MemberFilter::in_chat(1), &Page::ALL)
+            .await?;
         let user_ids: Vec<i32> = members_before.iter().map(|m| m.user_id).collect();
 
         assert_eq!(user_ids.len(), 3, "La General Chat dovrebbe avere 3 membri");
@@ -3535,6 +4390,7 @@ mod tests {
             user_role: Some(UserRole::Admin),
             messages_visible_from: None,
             messages_received_until: None,
+            version: None,
         };
 
         let result = repo.update(&(2, 3), &update_dto).await;
@@ -3556,6 +4412,7 @@ mod tests {
             user_role: Some(UserRole::Member),
             messages_visible_from: None,
             messages_received_until: None,
+            version: None,
         };
 
         let result = repo.update(&(999, 1), &update_dto).await;
@@ -3577,6 +4434,7 @@ mod tests {
             user_role: Some(UserRole::Owner),
             messages_visible_from: None,
             messages_received_until: None,
+            version: None,
         };
 
         let result = repo.update(&(1, 999), &update_dto).await;
@@ -3598,6 +4456,7 @@ mod tests {
             user_role: Some(UserRole::Admin),
             messages_visible_from: None,
             messages_received_until: None,
+            version: None,
         };
 
         let result = repo.update(&(888, 999), &update_dto).await;
@@ -3619,6 +4478,7 @@ mod tests {
             user_role: Some(UserRole::Member),
             messages_visible_from: None,
             messages_received_until: None,
+            version: None,
         };
 
         let result = repo.update(&(-1, -1), &update_dto).await;
@@ -3637,6 +4497,7 @@ mod tests {
             user_role: Some(UserRole::Owner),
             messages_visible_from: None,
             messages_received_until: None,
+            version: None,
         };
 
         let result = repo.update(&(0, 0), &update_dto).await;
@@ -3678,6 +4539,7 @@ mod tests {
             user_role: Some(UserRole::Admin),
             messages_visible_from: None,
             messages_received_until: None,
+            version: None,
         };
 
         let updated = repo.update(&(new_user_id, 1), &update_dto).await?;
@@ -3733,6 +4595,7 @@ mod tests {
             user_role: None,
             messages_visible_from: None,
             messages_received_until: Some(future),
+            version: None,
         };
 
         let updated = repo.update(&(1, new_chat_id), &update_dto).await?;
@@ -3767,6 +4630,7 @@ mod tests {
             user_role: Some(UserRole::Admin),
             messages_visible_from: None,
             messages_received_until: None,
+            version: None,
         };
 
         repo.update(&(2, 1), &update_dto).await?;
@@ -3810,6 +4674,7 @@ mod tests {
             user_role: Some(UserRole::Admin),
             messages_visible_from: None,
             messages_received_until: None,
+            version: None,
         };
 
         let result = repo.update(&(2, 1), &update_dto).await;
@@ -3841,6 +4706,7 @@ mod tests {
             user_role: Some(UserRole::Admin),
             messages_visible_from: None,
             messages_received_until: None,
+            version: None,
         };
 
         let result = repo.update(&(1, 1), &update_dto).await;
@@ -3889,6 +4755,7 @@ mod tests {
             user_role: Some(UserRole::Admin),
             messages_visible_from: None,
             messages_received_until: None,
+            version: None,
         };
 
         let result = repo.update(&(1, new_chat_id), &update_dto).await;
@@ -3911,6 +4778,7 @@ mod tests {
             user_role: None,
             messages_visible_from: None,
             messages_received_until: None,
+            version: None,
         };
 
         // Anche se il DTO è vuoto, dovrebbe fallire perché il metadata non esiste
@@ -3990,6 +4858,7 @@ mod tests {
             user_role: Some(UserRole::Admin),
             messages_visible_from: None,
             messages_received_until: None,
+            version: None,
         };
 
         for &user_id in &[user1_id, user2_id] {
@@ -4044,6 +4913,7 @@ mod tests {
             user_role: Some(UserRole::Admin),
             messages_visible_from: None,
             messages_received_until: None,
+            version: None,
         };
 
         // User valido, chat invalida
@@ -4082,6 +4952,7 @@ mod tests {
             user_role: Some(UserRole::Admin),
             messages_visible_from: None,
             messages_received_until: None,
+            version: None,
         };
 
         // Tenta update multiple volte - dovrebbero tutti fallire
@@ -4106,6 +4977,7 @@ mod tests {
             user_role: Some(UserRole::Admin),
             messages_visible_from: None,
             messages_received_until: None,
+            version: None,
         };
 
         let result = repo.update(&(999, 1), &invalid_update).await;
@@ -4116,6 +4988,7 @@ mod tests {
             user_role: Some(UserRole::Admin),
             messages_visible_from: None,
             messages_received_until: None,
+            version: None,
         };
 
         let result2 = repo.update(&(1, 1), &valid_update).await;
@@ -4168,7 +5041,9 @@ mod tests {
         );
 
         // Verifica che i metadata della chat siano intatti
-        let members = repo.find_many_by_chat_id(&1).await?;
+        let members = repo
+            .read_many(&MemberFilter::in_chat(1), &Page::ALL)
+            .await?;
         assert_eq!(
             members.len(),
             3,
@@ -4191,7 +5066,9 @@ mod tests {
         );
 
         // Verifica che i metadata di Alice siano intatti
-        let alice_chats = repo.find_many_by_user_id(&1).await?;
+        let alice_chats = repo
+            .read_many(&MemberFilter::of_user(1), &Page::ALL)
+            .await?;
         assert_eq!(
             alice_chats.len(),
             3,
@@ -4373,7 +5250,9 @@ mod tests {
         }
 
         // Verifica che l'utente sia in 2 chat
-        let user_chats = repo.find_many_by_user_id(&new_user_id).await?;
+        let user_chats = repo
+            .read_many(&MemberFilter::of_user(new_user_id), &Page::ALL)
+            .await?;
         assert_eq!(user_chats.len(), 2);
 
         // Elimina l'utente - CASCADE dovrebbe eliminare automaticamente tutti i metadata
@@ -4382,7 +5261,9 @@ mod tests {
             .await?;
 
         // Verifica che tutti i metadata siano stati eliminati da CASCADE
-        let user_chats_after = repo.find_many_by_user_id(&new_user_id).await?;
+        let user_chats_after = repo
+            .read_many(&MemberFilter::of_user(new_user_id), &Page::ALL)
+            .await?;
         assert_eq!(
             user_chats_after.len(),
             0,
@@ -4429,7 +5310,9 @@ mod tests {
         }
 
         // Verifica che ci siano 3 membri
-        let members = repo.find_many_by_chat_id(&new_chat_id).await?;
+        let members = repo
+            .read_many(&MemberFilter::in_chat(new_chat_id), &Page::ALL)
+            .await?;
         assert_eq!(members.len(), 3);
 
         // Elimina la chat - CASCADE dovrebbe eliminare tutti i metadata
@@ -4438,7 +5321,9 @@ mod tests {
             .await?;
 
         // Verifica che tutti i metadata siano stati eliminati da CASCADE
-        let members_after = repo.find_many_by_chat_id(&new_chat_id).await?;
+        let members_after = repo
+            .read_many(&MemberFilter::in_chat(new_chat_id), &Page::ALL)
+            .await?;
         assert_eq!(
             members_after.len(),
             0,
@@ -4490,7 +5375,9 @@ mod tests {
         repo.delete(&(3, new_chat_id)).await?;
 
         // Verifica che tutti siano stati eliminati
-        let members = repo.find_many_by_chat_id(&new_chat_id).await?;
+        let members = repo
+            .read_many(&MemberFilter::in_chat(new_chat_id), &Page::ALL)
+            .await?;
         assert_eq!(members.len(), 0);
 
         Ok(())
@@ -4502,14 +5389,19 @@ mod tests {
         let repo = UserChatMetadataRepository::new(pool.clone());
 
         // Alice è in 3 chat
-        let initial_count = repo.find_many_by_user_id(&1).await?.len();
+        let initial_count = repo
+            .read_many(&MemberFilter::of_user(1), &Page::ALL)
+            .await?
+            .len();
         assert_eq!(initial_count, 3);
 
         // Delete Alice dalla General Chat
         repo.delete(&(1, 1)).await?;
 
         // Alice dovrebbe essere ancora in 2 chat
-        let after_delete = repo.find_many_by_user_id(&1).await?;
+        let after_delete = repo
+            .read_many(&MemberFilter::of_user(1), &Page::ALL)
+            .await?;
         assert_eq!(after_delete.len(), 2);
 
         // Verifica che sia stata eliminata solo dalla General Chat
@@ -4526,14 +5418,18 @@ mod tests {
         let repo = UserChatMetadataRepository::new(pool.clone());
 
         // General Chat ha 3 membri
-        let initial_members = repo.find_many_by_chat_id(&1).await?;
+        let initial_members = repo
+            .read_many(&MemberFilter::in_chat(1), &Page::ALL)
+            .await?;
         assert_eq!(initial_members.len(), 3);
 
         // Delete Bob dalla General Chat
         repo.delete(&(2, 1)).await?;
 
         // Dovrebbero rimanere 2 membri
-        let after_delete = repo.find_many_by_chat_id(&1).await?;
+        let after_delete = repo
+            .read_many(&MemberFilter::in_chat(1), &Page::ALL)
+            .await?;
         assert_eq!(after_delete.len(), 2);
 
         // Verifica che solo Bob sia stato eliminato
@@ -4749,4 +5645,104 @@ mod tests {
 
         Ok(())
     }
+
+    /*------------------------*/
+    /* Unit tests: set_pinned */
+    /*------------------------*/
+
+    /// Test: set_pinned salva e azzera pinned_at solo per l'utente indicato
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_set_pinned_roundtrip(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = UserChatMetadataRepository::new(pool.clone());
+
+        repo.set_pinned(&1, &1, Some(Utc::now())).await?;
+        assert!(repo.read(&(1, 1)).await?.unwrap().pinned_at.is_some());
+        // gli altri membri della chat non sono influenzati
+        assert!(repo.read(&(2, 1)).await?.unwrap().pinned_at.is_none());
+
+        repo.set_pinned(&1, &1, None).await?;
+        assert!(repo.read(&(1, 1)).await?.unwrap().pinned_at.is_none());
+
+        Ok(())
+    }
+
+    /*-----------------------------*/
+    /* Unit tests: set_muted_until */
+    /*-----------------------------*/
+
+    /// Test: set_muted_until salva e azzera muted_until solo per l'utente indicato
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_set_muted_until_roundtrip(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = UserChatMetadataRepository::new(pool.clone());
+
+        repo.set_muted_until(&2, &1, Some(Utc::now() + chrono::Duration::hours(1)))
+            .await?;
+        assert!(repo.read(&(2, 1)).await?.unwrap().muted_until.is_some());
+        // gli altri membri della chat non sono influenzati
+        assert!(repo.read(&(3, 1)).await?.unwrap().muted_until.is_none());
+
+        repo.set_muted_until(&2, &1, None).await?;
+        assert!(repo.read(&(2, 1)).await?.unwrap().muted_until.is_none());
+
+        Ok(())
+    }
+
+    /*------------------------------------*/
+    /* Unit tests: advance_received_until */
+    /*------------------------------------*/
+
+    /// Test: il marcatore avanza solo in avanti e il contatore dei non letti scala dei
+    /// messaggi consegnati, azzerandosi all'ultimo messaggio della chat
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_advance_received_until_only_forward(pool: MySqlPool) -> sqlx::Result<()> {
+        async fn unread_in_general(repo: &UserChatMetadataRepository) -> sqlx::Result<i64> {
+            let counts = repo.count_unread_by_user_id(&2).await?;
+            Ok(counts.iter().find(|c| c.chat_id == 1).unwrap().unread)
+        }
+        let repo = UserChatMetadataRepository::new(pool.clone());
+
+        // Bob non ha ricevuto nulla dell'ultima ora: due messaggi non suoi
+        let an_hour_ago = Utc::now() - chrono::Duration::hours(1);
+        sqlx::query!(
+            "UPDATE userchatmetadata SET messages_visible_from = ?, messages_received_until = ?, unread_count = 2 WHERE user_id = 2 AND chat_id = 1",
+            an_hour_ago,
+            an_hour_ago
+        )
+        .execute(&pool)
+        .await?;
+
+        // consegnati i messaggi 1 (di Alice) e 2 (di Bob): resta quello di Charlie
+        let delivered = Utc::now() - chrono::Duration::seconds(510);
+        let batch = DeliveredBatch {
+            chat_id: 1,
+            received_until: delivered,
+            last_message_id: 2,
+            unread: 1,
+        };
+        repo.advance_received_until(&2, &[batch]).await?;
+        assert_eq!(unread_in_general(&repo).await?, 1);
+
+        // un istante precedente non fa tornare indietro il marcatore né il contatore
+        let stale = DeliveredBatch {
+            received_until: an_hour_ago,
+            ..batch
+        };
+        repo.advance_received_until(&2, &[stale]).await?;
+        let metadata = repo.read(&(2, 1)).await?.unwrap();
+        assert!(metadata.messages_received_until > an_hour_ago);
+        assert_eq!(unread_in_general(&repo).await?, 1);
+
+        // raggiunto l'ultimo messaggio della chat il contatore si azzera,
+        // anche se il batch ne contava di più
+        let latest = DeliveredBatch {
+            chat_id: 1,
+            received_until: Utc::now(),
+            last_message_id: 3,
+            unread: 5,
+        };
+        repo.advance_received_until(&2, &[latest]).await?;
+        assert_eq!(unread_in_general(&repo).await?, 0);
+
+        Ok(())
+    }
 }
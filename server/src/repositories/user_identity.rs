@@ -0,0 +1,120 @@
+//! UserIdentityRepository - Repository per le identità esterne degli utenti
+
+use super::query_log::timed;
+#[cfg(feature = "sqlite")]
+use super::sqlite;
+use super::{Create, DbPool};
+use crate::dtos::CreateUserIdentityDTO;
+use crate::entities::UserIdentity;
+use chrono::Utc;
+use sqlx::Error;
+use tracing::{debug, info, instrument};
+
+//USER IDENTITY REPOSITORY
+pub struct UserIdentityRepository {
+    connection_pool: DbPool,
+}
+
+impl UserIdentityRepository {
+    pub fn new(connection_pool: impl Into<DbPool>) -> Self {
+        Self {
+            connection_pool: connection_pool.into(),
+        }
+    }
+
+    /// Cerca l'identità tramite issuer e subject dell'id_token
+    pub async fn find_by_subject(
+        &self,
+        issuer: &str,
+        subject: &str,
+    ) -> Result<Option<UserIdentity>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::user_identity::find_by_subject(pool, issuer, subject).await;
+        }
+        let identity = sqlx::query_as!(
+            UserIdentity,
+            r#"
+            SELECT identity_id, user_id, issuer, subject, created_at
+            FROM user_identities
+            WHERE issuer = ? AND subject = ?
+            "#,
+            issuer,
+            subject
+        )
+        .fetch_optional(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(identity)
+    }
+}
+
+impl Create<UserIdentity, CreateUserIdentityDTO> for UserIdentityRepository {
+    #[instrument(skip(self, data), fields(user_id = %data.user_id))]
+    async fn create(&self, data: &CreateUserIdentityDTO) -> Result<UserIdentity, Error> {
+        debug!("Linking external identity to user");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::user_identity::create(pool, data).await;
+        }
+        let now = Utc::now();
+
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO user_identities (user_id, issuer, subject, created_at)
+            VALUES (?, ?, ?, ?)
+            "#,
+            data.user_id,
+            data.issuer,
+            data.subject,
+            now
+        )
+        .execute(timed(self.connection_pool.mysql()))
+        .await?;
+
+        let new_id = result.last_insert_id() as i32;
+
+        info!("External identity linked with id {}", new_id);
+
+        Ok(UserIdentity {
+            identity_id: new_id,
+            user_id: data.user_id,
+            issuer: data.issuer.clone(),
+            subject: data.subject.clone(),
+            created_at: now,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::MySqlPool;
+
+    /// Test: l'identità è ritrovata solo con la stessa coppia issuer/subject
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users")))]
+    async fn test_create_and_find_by_subject(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = UserIdentityRepository::new(pool);
+
+        repo.create(&CreateUserIdentityDTO {
+            user_id: 1,
+            issuer: "https://sso.example.com".to_string(),
+            subject: "abc-123".to_string(),
+        })
+        .await?;
+
+        let found = repo
+            .find_by_subject("https://sso.example.com", "abc-123")
+            .await?
+            .unwrap();
+        assert_eq!(found.user_id, 1);
+
+        assert!(
+            repo.find_by_subject("https://other.example.com", "abc-123")
+                .await?
+                .is_none()
+        );
+
+        Ok(())
+    }
+}
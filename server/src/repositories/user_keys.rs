@@ -0,0 +1,243 @@
+//! UserKeysRepository - Repository per le chiavi pubbliche della cifratura end-to-end
+
+use super::query_log::timed;
+#[cfg(feature = "sqlite")]
+use super::sqlite;
+use super::{DbPool, Read};
+use crate::dtos::UploadKeysDTO;
+use crate::entities::{OneTimePrekey, UserKeys};
+use chrono::Utc;
+use sqlx::Error;
+use tracing::{debug, instrument};
+
+//USER KEYS REPOSITORY
+pub struct UserKeysRepository {
+    connection_pool: DbPool,
+}
+
+impl UserKeysRepository {
+    pub fn new(connection_pool: impl Into<DbPool>) -> Self {
+        Self {
+            connection_pool: connection_pool.into(),
+        }
+    }
+
+    /// Pubblica chiave di identità e signed prekey dell'utente e aggiunge le one-time prekey,
+    /// sostituendo quelle con lo stesso id. Se la chiave di identità cambia, le one-time
+    /// prekey precedenti vengono eliminate
+    ///
+    /// # Returns
+    /// Numero di one-time prekey disponibili dopo il caricamento
+    #[instrument(skip(self, keys), fields(user_id = %user_id))]
+    pub async fn upsert(&self, user_id: &i32, keys: &UploadKeysDTO) -> Result<i64, Error> {
+        debug!("Publishing encryption keys");
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::user_keys::upsert(pool, user_id, keys).await;
+        }
+        let mut tx = self.connection_pool.mysql().begin().await?;
+
+        let previous_identity = sqlx::query_scalar!(
+            "SELECT identity_key FROM user_keys WHERE user_id = ? FOR UPDATE",
+            user_id
+        )
+        .fetch_optional(timed(&mut *tx))
+        .await?;
+        if previous_identity.is_some_and(|identity| identity != keys.identity_key) {
+            debug!("Identity key changed, discarding old one-time prekeys");
+            sqlx::query!("DELETE FROM one_time_prekeys WHERE user_id = ?", user_id)
+                .execute(timed(&mut *tx))
+                .await?;
+        }
+
+        let updated_at = Utc::now();
+        sqlx::query!(
+            r#"
+            INSERT INTO user_keys (user_id, identity_key, signed_prekey_id, signed_prekey, signed_prekey_signature, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            ON DUPLICATE KEY UPDATE
+                identity_key = VALUES(identity_key),
+                signed_prekey_id = VALUES(signed_prekey_id),
+                signed_prekey = VALUES(signed_prekey),
+                signed_prekey_signature = VALUES(signed_prekey_signature),
+                updated_at = VALUES(updated_at)
+            "#,
+            user_id,
+            keys.identity_key,
+            keys.signed_prekey.key_id,
+            keys.signed_prekey.public_key,
+            keys.signed_prekey.signature,
+            updated_at
+        )
+        .execute(timed(&mut *tx))
+        .await?;
+
+        for prekey in &keys.one_time_prekeys {
+            sqlx::query!(
+                r#"
+                INSERT INTO one_time_prekeys (user_id, prekey_id, public_key)
+                VALUES (?, ?, ?)
+                ON DUPLICATE KEY UPDATE public_key = VALUES(public_key)
+                "#,
+                user_id,
+                prekey.key_id,
+                prekey.public_key
+            )
+            .execute(timed(&mut *tx))
+            .await?;
+        }
+
+        let available = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM one_time_prekeys WHERE user_id = ?",
+            user_id
+        )
+        .fetch_one(timed(&mut *tx))
+        .await?;
+
+        tx.commit().await?;
+        Ok(available)
+    }
+
+    /// Numero di one-time prekey dell'utente non ancora consegnate
+    pub async fn count_one_time_prekeys(&self, user_id: &i32) -> Result<i64, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::user_keys::count_one_time_prekeys(pool, user_id).await;
+        }
+        sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM one_time_prekeys WHERE user_id = ?",
+            user_id
+        )
+        .fetch_one(timed(self.connection_pool.mysql()))
+        .await
+    }
+
+    /// Estrae la one-time prekey con l'id più basso e la elimina, così nessun'altra
+    /// richiesta può riceverla. None se l'utente le ha esaurite
+    #[instrument(skip(self), fields(user_id = %user_id))]
+    pub async fn take_one_time_prekey(
+        &self,
+        user_id: &i32,
+    ) -> Result<Option<OneTimePrekey>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::user_keys::take_one_time_prekey(pool, user_id).await;
+        }
+        let mut tx = self.connection_pool.mysql().begin().await?;
+
+        let prekey = sqlx::query_as!(
+            OneTimePrekey,
+            r#"
+            SELECT user_id, prekey_id, public_key
+            FROM one_time_prekeys
+            WHERE user_id = ?
+            ORDER BY prekey_id
+            LIMIT 1
+            FOR UPDATE
+            "#,
+            user_id
+        )
+        .fetch_optional(timed(&mut *tx))
+        .await?;
+
+        if let Some(prekey) = &prekey {
+            sqlx::query!(
+                "DELETE FROM one_time_prekeys WHERE user_id = ? AND prekey_id = ?",
+                prekey.user_id,
+                prekey.prekey_id
+            )
+            .execute(timed(&mut *tx))
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(prekey)
+    }
+}
+
+impl Read<UserKeys, i32> for UserKeysRepository {
+    async fn read(&self, id: &i32) -> Result<Option<UserKeys>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::user_keys::read(pool, id).await;
+        }
+        let keys = sqlx::query_as!(
+            UserKeys,
+            r#"
+            SELECT
+                user_id,
+                identity_key,
+                signed_prekey_id,
+                signed_prekey,
+                signed_prekey_signature,
+                updated_at
+            FROM user_keys
+            WHERE user_id = ?
+            "#,
+            id
+        )
+        .fetch_optional(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dtos::user_keys::{PrekeyDTO, SignedPrekeyDTO};
+    use sqlx::MySqlPool;
+
+    fn upload(identity_key: &str, prekey_ids: &[i32]) -> UploadKeysDTO {
+        UploadKeysDTO {
+            identity_key: identity_key.to_string(),
+            signed_prekey: SignedPrekeyDTO {
+                key_id: 1,
+                public_key: "signed".to_string(),
+                signature: "signature".to_string(),
+            },
+            one_time_prekeys: prekey_ids
+                .iter()
+                .map(|&key_id| PrekeyDTO {
+                    key_id,
+                    public_key: format!("prekey-{}", key_id),
+                })
+                .collect(),
+        }
+    }
+
+    /// Test: le one-time prekey vengono consegnate una sola volta, in ordine di id
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users")))]
+    async fn test_take_one_time_prekey_consumes_keys(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = UserKeysRepository::new(pool.clone());
+
+        assert_eq!(repo.upsert(&1, &upload("identity", &[2, 1])).await?, 2);
+
+        let first = repo.take_one_time_prekey(&1).await?.expect("Prekey 1");
+        assert_eq!(first.prekey_id, 1);
+        let second = repo.take_one_time_prekey(&1).await?.expect("Prekey 2");
+        assert_eq!(second.prekey_id, 2);
+        assert!(repo.take_one_time_prekey(&1).await?.is_none());
+
+        // chiave di identità e signed prekey restano disponibili
+        let keys = repo.read(&1).await?.expect("Keys should exist");
+        assert_eq!(keys.identity_key, "identity");
+
+        Ok(())
+    }
+
+    /// Test: le one-time prekey si accumulano, ma un cambio di identità le azzera
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users")))]
+    async fn test_upsert_replaces_prekeys_on_identity_change(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = UserKeysRepository::new(pool.clone());
+
+        repo.upsert(&1, &upload("identity", &[1, 2])).await?;
+        assert_eq!(repo.upsert(&1, &upload("identity", &[2, 3])).await?, 3);
+
+        assert_eq!(repo.upsert(&1, &upload("new-identity", &[10])).await?, 1);
+        assert_eq!(repo.count_one_time_prekeys(&1).await?, 1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,208 @@
+//! WebhookRepository - Repository per i webhook in ingresso delle chat
+
+use super::query_log::timed;
+#[cfg(feature = "sqlite")]
+use super::sqlite;
+use super::{Create, DbPool, Delete, Page, Read, ReadMany};
+use crate::dtos::NewWebhookDTO;
+use crate::entities::Webhook;
+use chrono::Utc;
+use sqlx::Error;
+use tracing::{debug, info, instrument};
+
+//WEBHOOK REPOSITORY
+pub struct WebhookRepository {
+    connection_pool: DbPool,
+}
+
+impl WebhookRepository {
+    pub fn new(connection_pool: impl Into<DbPool>) -> Self {
+        Self {
+            connection_pool: connection_pool.into(),
+        }
+    }
+
+    /// Cerca un webhook tramite l'hash del suo token
+    pub async fn find_by_token_hash(&self, token_hash: &str) -> Result<Option<Webhook>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::webhook::find_by_token_hash(pool, token_hash).await;
+        }
+        let webhook = sqlx::query_as!(
+            Webhook,
+            r#"
+            SELECT webhook_id, chat_id, bot_user_id, name, token_hash, created_by, created_at
+            FROM webhooks
+            WHERE token_hash = ?
+            "#,
+            token_hash
+        )
+        .fetch_optional(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(webhook)
+    }
+}
+
+/// Filtro dei webhook, restituiti in ordine di creazione
+#[derive(Debug, Clone)]
+pub struct WebhookFilter {
+    pub chat_id: i32,
+}
+
+impl ReadMany<Webhook, WebhookFilter> for WebhookRepository {
+    async fn read_many(&self, filter: &WebhookFilter, page: &Page) -> Result<Vec<Webhook>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::webhook::read_many(pool, filter, page).await;
+        }
+        let webhooks = sqlx::query_as!(
+            Webhook,
+            r#"
+            SELECT webhook_id, chat_id, bot_user_id, name, token_hash, created_by, created_at
+            FROM webhooks
+            WHERE chat_id = ?
+            ORDER BY webhook_id
+            LIMIT ? OFFSET ?
+            "#,
+            filter.chat_id,
+            page.limit,
+            page.offset
+        )
+        .fetch_all(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(webhooks)
+    }
+}
+
+impl Create<Webhook, NewWebhookDTO> for WebhookRepository {
+    #[instrument(skip(self, data), fields(chat_id = %data.chat_id, name = %data.name))]
+    async fn create(&self, data: &NewWebhookDTO) -> Result<Webhook, Error> {
+        debug!("Creating new webhook");
+        let now = Utc::now();
+
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            let new_id = sqlite::webhook::insert(pool, data, now).await?;
+            info!("Webhook created with id {}", new_id);
+            return Ok(Webhook {
+                webhook_id: new_id,
+                chat_id: data.chat_id,
+                bot_user_id: data.bot_user_id,
+                name: data.name.clone(),
+                token_hash: data.token_hash.clone(),
+                created_by: Some(data.created_by),
+                created_at: now,
+            });
+        }
+
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO webhooks (chat_id, bot_user_id, name, token_hash, created_by, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+            data.chat_id,
+            data.bot_user_id,
+            data.name,
+            data.token_hash,
+            data.created_by,
+            now
+        )
+        .execute(timed(self.connection_pool.mysql()))
+        .await?;
+
+        let new_id = result.last_insert_id() as i32;
+
+        info!("Webhook created with id {}", new_id);
+
+        Ok(Webhook {
+            webhook_id: new_id,
+            chat_id: data.chat_id,
+            bot_user_id: data.bot_user_id,
+            name: data.name.clone(),
+            token_hash: data.token_hash.clone(),
+            created_by: Some(data.created_by),
+            created_at: now,
+        })
+    }
+}
+
+impl Read<Webhook, i32> for WebhookRepository {
+    async fn read(&self, id: &i32) -> Result<Option<Webhook>, Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::webhook::read(pool, id).await;
+        }
+        let webhook = sqlx::query_as!(
+            Webhook,
+            r#"
+            SELECT webhook_id, chat_id, bot_user_id, name, token_hash, created_by, created_at
+            FROM webhooks
+            WHERE webhook_id = ?
+            "#,
+            id
+        )
+        .fetch_optional(timed(self.connection_pool.mysql()))
+        .await?;
+
+        Ok(webhook)
+    }
+}
+
+impl Delete<i32> for WebhookRepository {
+    async fn delete(&self, id: &i32) -> Result<(), Error> {
+        #[cfg(feature = "sqlite")]
+        if let DbPool::Sqlite(pool) = &self.connection_pool {
+            return sqlite::webhook::delete(pool, id).await;
+        }
+        sqlx::query!("DELETE FROM webhooks WHERE webhook_id = ?", id)
+            .execute(timed(self.connection_pool.mysql()))
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::MySqlPool;
+
+    /// Test: il webhook è ritrovato tramite l'hash del token e tra quelli della sua chat
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users", "chats")))]
+    async fn test_create_and_find(pool: MySqlPool) -> sqlx::Result<()> {
+        let repo = WebhookRepository::new(pool);
+
+        // charlie fa da bot del webhook nella chat 3
+        let created = repo
+            .create(&NewWebhookDTO {
+                chat_id: 3,
+                bot_user_id: 3,
+                name: "ci_bot".to_string(),
+                token_hash: "a".repeat(64),
+                created_by: 1,
+            })
+            .await?;
+
+        let found = repo.find_by_token_hash(&"a".repeat(64)).await?.unwrap();
+        assert_eq!(found.webhook_id, created.webhook_id);
+        assert_eq!(found.created_by, Some(1));
+        assert!(repo.find_by_token_hash(&"b".repeat(64)).await?.is_none());
+
+        let listed = repo
+            .read_many(&WebhookFilter { chat_id: 3 }, &Page::ALL)
+            .await?;
+        assert_eq!(listed.len(), 1);
+        assert!(
+            repo.read_many(&WebhookFilter { chat_id: 1 }, &Page::ALL)
+                .await?
+                .is_empty()
+        );
+
+        repo.delete(&created.webhook_id).await?;
+        assert!(repo.read(&created.webhook_id).await?.is_none());
+
+        Ok(())
+    }
+}
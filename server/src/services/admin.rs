@@ -0,0 +1,521 @@
+//! Admin services - Amministrazione del server, riservata agli utenti con ruolo ServerAdmin
+
+use crate::core::{AppError, AppState, reload_config};
+use crate::dtos::{
+    AdminChatDTO, AdminReportQuery, AdminStatsDTO, AdminUserDTO, AdminUserQuery, ChatDTO,
+    ChatExportRecord, MessageDTO, ReportDTO, RuntimeConfigDTO,
+};
+use crate::entities::{AuditAction, Message, MessageType, ReportStatus, User};
+use crate::repositories::{Page, Read, ReadMany, ReportFilter, UserFilter};
+use crate::services::audit;
+use crate::services::chat::remove_chat;
+use crate::services::export::{ExportSource, ndjson_export};
+use crate::services::membership::load_chat_members;
+use crate::ws::chatmap::ChatEvent;
+use axum::{
+    Extension,
+    extract::{Json, Path, Query, State},
+    http::StatusCode,
+    response::{
+        IntoResponse,
+        sse::{Event, KeepAlive, Sse},
+    },
+};
+use chrono::DateTime;
+use serde_json::json;
+use std::sync::Arc;
+use tokio_stream::wrappers::WatchStream;
+use tokio_stream::{Stream, StreamExt};
+use tracing::{debug, info, instrument, warn};
+use validator::Validate;
+
+/// Utenti registrati
+#[utoipa::path(
+    get,
+    path = "/admin/users",
+    tag = "admin",
+    params(AdminUserQuery),
+    responses(
+        (status = 200, description = "Utenti", body = Vec<AdminUserDTO>),
+    )
+)]
+#[instrument(skip(state))]
+pub async fn admin_list_users(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<AdminUserQuery>,
+) -> Result<Json<Vec<AdminUserDTO>>, AppError> {
+    debug!("Listing users for admin");
+    // 1. Validare limit e offset
+    // 2. Recuperare gli utenti, compresi i disattivati, filtrati per username se richiesto
+    // 3. Aggiungere a ciascuno la presenza reale dalla UserMap, senza le regole di privacy
+
+    params.validate()?;
+    let filter = UserFilter {
+        search: params.search.clone(),
+        ..UserFilter::default()
+    };
+    let page = Page::new(params.limit.unwrap_or(50), params.offset.unwrap_or(0));
+
+    let users = state.user.read_many(&filter, &page).await?;
+
+    let mut result: Vec<AdminUserDTO> = Vec::with_capacity(users.len());
+    for user in users {
+        let online = state.is_user_online(&user.user_id).await;
+        result.push(AdminUserDTO::new(user, online));
+    }
+
+    info!("Found {} users", result.len());
+    Ok(Json(result))
+}
+
+/// Dettagli di una chat qualsiasi
+#[utoipa::path(
+    get,
+    path = "/admin/chats/{chat_id}",
+    tag = "admin",
+    params(("chat_id" = i32, Path, description = "ID della chat")),
+    responses(
+        (status = 200, description = "Chat", body = AdminChatDTO),
+        (status = 404, description = "Chat non trovata"),
+    )
+)]
+#[instrument(skip(state), fields(chat_id = %chat_id))]
+pub async fn admin_get_chat(
+    State(state): State<Arc<AppState>>,
+    Path(chat_id): Path<i32>,
+) -> Result<Json<AdminChatDTO>, AppError> {
+    debug!("Inspecting chat for admin");
+    // 1. Recuperare la chat, NOT_FOUND se non esiste
+    // 2. Recuperare i membri con il loro username e il numero di messaggi
+    // 3. Contare i membri online, anche su altre istanze se la presenza è condivisa
+
+    let chat = state.chat.read(&chat_id).await?.ok_or_else(|| {
+        warn!("Chat {} not found", chat_id);
+        AppError::not_found("Chat not found")
+    })?;
+
+    let members = load_chat_members(&state, &chat_id).await?;
+    let message_count = state.msg.count_by_chat_id(&chat_id).await?;
+
+    let mut online_members = 0;
+    for user_id in members.iter().filter_map(|member| member.user_id) {
+        if state.is_user_online(&user_id).await {
+            online_members += 1;
+        }
+    }
+
+    info!(
+        "Chat has {} members, {} online",
+        members.len(),
+        online_members
+    );
+    Ok(Json(AdminChatDTO {
+        chat: ChatDTO::from(chat),
+        members,
+        message_count,
+        online_members,
+    }))
+}
+
+/// Esporta in NDJSON l'intera cronologia di una chat qualsiasi
+#[utoipa::path(
+    get,
+    path = "/admin/chats/{chat_id}/export",
+    tag = "admin",
+    params(("chat_id" = i32, Path, description = "ID della chat")),
+    responses(
+        (
+            status = 200,
+            description = "Una riga JSON per la chat, una per ogni membro e una per ogni messaggio",
+            body = String,
+            content_type = "application/x-ndjson",
+        ),
+        (status = 404, description = "Chat non trovata"),
+    )
+)]
+#[instrument(skip(state, current_user), fields(chat_id = %chat_id, user_id = %current_user.user_id))]
+pub async fn admin_export_chat(
+    State(state): State<Arc<AppState>>,
+    Path(chat_id): Path<i32>,
+    Extension(current_user): Extension<User>,
+) -> Result<impl IntoResponse, AppError> {
+    debug!("Exporting chat for admin");
+    // 1. Recuperare la chat e i membri, NOT_FOUND se non esiste (prima dello stream)
+    // 2. Ritornare in streaming chat, membri e tutti i messaggi, senza il limite di
+    //    messages_visible_from dei singoli membri (gli eliminati restano tombstone)
+
+    let chat = state.chat.read(&chat_id).await?.ok_or_else(|| {
+        warn!("Chat {} not found", chat_id);
+        AppError::not_found("Chat not found")
+    })?;
+    let members = load_chat_members(&state, &chat_id).await?;
+
+    let mut header_records = vec![ChatExportRecord::Chat(ChatDTO::from(chat))];
+    header_records.extend(members.into_iter().map(ChatExportRecord::Member));
+
+    info!("Chat history exported by server admin");
+    let source = ExportSource::Chat {
+        chat_id,
+        visible_from: DateTime::UNIX_EPOCH,
+    };
+    Ok(ndjson_export(
+        state,
+        format!("admin-chat-{}.ndjson", chat_id),
+        header_records,
+        source,
+    ))
+}
+
+/// Esporta in NDJSON i messaggi scritti da un utente in tutte le chat
+#[utoipa::path(
+    get,
+    path = "/admin/users/{user_id}/messages/export",
+    tag = "admin",
+    params(("user_id" = i32, Path, description = "ID dell'utente")),
+    responses(
+        (
+            status = 200,
+            description = "Una riga JSON per ogni messaggio dell'utente, dal più vecchio",
+            body = String,
+            content_type = "application/x-ndjson",
+        ),
+        (status = 404, description = "Utente non trovato"),
+    )
+)]
+#[instrument(skip(state, current_user), fields(target_user = %user_id, user_id = %current_user.user_id))]
+pub async fn admin_export_user_messages(
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<i32>,
+    Extension(current_user): Extension<User>,
+) -> Result<impl IntoResponse, AppError> {
+    debug!("Exporting user messages for admin");
+    // 1. Verificare che l'utente esista, NOT_FOUND altrimenti (prima dello stream)
+    // 2. Ritornare in streaming i messaggi scritti dall'utente in ogni chat, esclusi quelli
+    //    di sistema (gli eliminati restano tombstone)
+
+    if state.user.read(&user_id).await?.is_none() {
+        warn!("User {} not found", user_id);
+        return Err(AppError::not_found("User not found"));
+    }
+
+    info!("User messages exported by server admin");
+    Ok(ndjson_export(
+        state,
+        format!("admin-user-{}-messages.ndjson", user_id),
+        Vec::new(),
+        ExportSource::Sender(user_id),
+    ))
+}
+
+/// Statistiche del server e utenti online
+#[utoipa::path(
+    get,
+    path = "/admin/stats",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Statistiche correnti", body = AdminStatsDTO),
+    )
+)]
+#[instrument(skip(state))]
+pub async fn admin_online_stats(State(state): State<Arc<AppState>>) -> Json<AdminStatsDTO> {
+    Json(AdminStatsDTO {
+        online_users: state.users_online.online_count(),
+    })
+}
+
+/// Statistiche del server in streaming
+#[utoipa::path(
+    get,
+    path = "/admin/stats/stream",
+    tag = "admin",
+    responses(
+        (
+            status = 200,
+            description = "Un evento con le statistiche a ogni campionamento",
+            body = AdminStatsDTO,
+            content_type = "text/event-stream",
+        ),
+    )
+)]
+#[instrument(skip(state))]
+pub async fn admin_stats_stream(
+    State(state): State<Arc<AppState>>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    debug!("Opening server stats stream");
+    // 1. Iscriversi ai campioni pubblicati dal task di campionamento
+    // 2. Inviare subito l'ultimo campione disponibile e poi ogni nuovo campione come evento "stats"
+    // 3. Mantenere viva la connessione nei proxy con commenti periodici
+
+    let stream = WatchStream::new(state.server_stats.subscribe())
+        .filter_map(|stats| stats)
+        .map(|stats| Event::default().event("stats").json_data(stats));
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// Impostazioni ricaricabili a caldo attualmente in vigore
+#[utoipa::path(
+    get,
+    path = "/admin/config",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Impostazioni correnti", body = RuntimeConfigDTO),
+    )
+)]
+#[instrument(skip(state))]
+pub async fn admin_get_runtime_config(
+    State(state): State<Arc<AppState>>,
+) -> Json<RuntimeConfigDTO> {
+    Json(RuntimeConfigDTO::from(state.reloadable_config()))
+}
+
+/// Ricarica la configurazione senza riavviare il server (come SIGHUP)
+#[utoipa::path(
+    post,
+    path = "/admin/config/reload",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Impostazioni applicate", body = RuntimeConfigDTO),
+        (status = 422, description = "Configurazione non valida, nessuna impostazione cambiata"),
+    )
+)]
+#[instrument(skip(state, current_user), fields(user_id = %current_user.user_id))]
+pub async fn admin_reload_config(
+    State(state): State<Arc<AppState>>,
+    Extension(current_user): Extension<User>,
+) -> Result<Json<RuntimeConfigDTO>, AppError> {
+    debug!("Reloading configuration as server admin");
+    // 1. Rileggere variabili d'ambiente, segreti e file di configurazione con le regole dell'avvio
+    // 2. UNPROCESSABLE_ENTITY se la configurazione non è valida, lasciando tutto com'era
+    // 3. Applicare livello di log, rate limit e feature flag e ritornare i valori in vigore
+
+    let config = reload_config(&state).await.map_err(|e| {
+        warn!("Configuration reload failed: {}", e);
+        AppError::new(StatusCode::UNPROCESSABLE_ENTITY, "Invalid configuration").with_details(e)
+    })?;
+
+    info!("Configuration reloaded by server admin");
+    Ok(Json(RuntimeConfigDTO::from(config)))
+}
+
+/// Elimina un messaggio qualsiasi
+#[utoipa::path(
+    delete,
+    path = "/admin/messages/{message_id}",
+    tag = "admin",
+    params(("message_id" = i32, Path, description = "ID del messaggio")),
+    responses(
+        (status = 200, description = "Messaggio eliminato", body = MessageDTO),
+        (status = 404, description = "Messaggio non trovato"),
+    )
+)]
+#[instrument(skip(state, current_user), fields(message_id = %message_id, user_id = %current_user.user_id))]
+pub async fn admin_delete_message(
+    State(state): State<Arc<AppState>>,
+    Path(message_id): Path<i32>,
+    Extension(current_user): Extension<User>,
+) -> Result<Json<MessageDTO>, AppError> {
+    debug!("Deleting message as server admin");
+    // 1. Recuperare il messaggio, NOT_FOUND se non esiste
+    // 2. Eliminarlo come amministratore del server e ritornare il tombstone
+
+    let message = state.msg.read(&message_id).await?.ok_or_else(|| {
+        warn!("Message {} not found", message_id);
+        AppError::not_found("Message not found")
+    })?;
+
+    let tombstone = delete_message_as_admin(&state, current_user.user_id, message).await?;
+
+    info!("Message deleted by server admin");
+    Ok(Json(tombstone))
+}
+
+/// Elimina un messaggio per conto di un amministratore del server
+///
+/// # Returns
+/// Il tombstone inviato ai membri online della chat
+async fn delete_message_as_admin(
+    state: &AppState,
+    admin_id: i32,
+    message: Message,
+) -> Result<MessageDTO, AppError> {
+    // 1. I messaggi di sistema non possono essere eliminati
+    // 2. Marcare il messaggio come eliminato (soft-delete, come per i moderatori della chat)
+    // 3. Registrare l'eliminazione nell'audit log della chat, visibile ad Admin e Owner
+    // 4. Inviare l'evento MessageDeleted con il tombstone ai membri online
+
+    if message.message_type == MessageType::SystemMessage {
+        warn!("Admin attempted to delete a system message");
+        return Err(AppError::forbidden("System messages cannot be deleted"));
+    }
+
+    let deleted = state.msg.soft_delete(&message.message_id).await?;
+
+    audit::record(
+        state,
+        message.chat_id,
+        admin_id,
+        AuditAction::MessageDeleted,
+        Some(message.sender_id),
+        Some(json!({ "message_id": message.message_id, "server_admin": true })),
+    )
+    .await?;
+
+    let tombstone = MessageDTO::from(deleted);
+
+    let _ = state.chats_online.send_event(
+        &message.chat_id,
+        ChatEvent::MessageDeleted(Arc::new(tombstone.clone())),
+    );
+
+    Ok(tombstone)
+}
+
+/// Elimina una chat qualsiasi
+#[utoipa::path(
+    delete,
+    path = "/admin/chats/{chat_id}",
+    tag = "admin",
+    params(("chat_id" = i32, Path, description = "ID della chat")),
+    responses(
+        (status = 200, description = "Chat eliminata"),
+        (status = 404, description = "Chat non trovata"),
+    )
+)]
+#[instrument(skip(state), fields(chat_id = %chat_id))]
+pub async fn admin_delete_chat(
+    State(state): State<Arc<AppState>>,
+    Path(chat_id): Path<i32>,
+) -> Result<(), AppError> {
+    debug!("Deleting chat as server admin");
+    // 1. Verificare che la chat esista, NOT_FOUND altrimenti
+    // 2. Cancellare la chat e notificare i membri online, come per l'eliminazione dell'Owner
+
+    if state.chat.read(&chat_id).await?.is_none() {
+        warn!("Chat {} not found", chat_id);
+        return Err(AppError::not_found("Chat not found"));
+    }
+
+    remove_chat(&state, chat_id).await?;
+
+    Ok(())
+}
+
+/// Coda delle segnalazioni, dalla più vecchia
+#[utoipa::path(
+    get,
+    path = "/admin/reports",
+    tag = "admin",
+    params(AdminReportQuery),
+    responses(
+        (status = 200, description = "Segnalazioni", body = Vec<ReportDTO>),
+    )
+)]
+#[instrument(skip(state))]
+pub async fn admin_list_reports(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<AdminReportQuery>,
+) -> Result<Json<Vec<ReportDTO>>, AppError> {
+    debug!("Listing reports for admin");
+    // 1. Validare limit e offset
+    // 2. Recuperare le segnalazioni nello stato richiesto (di default quelle da esaminare)
+    // 3. Aggiungere a ciascuna il messaggio segnalato, tombstone se già eliminato
+
+    params.validate()?;
+    let filter = ReportFilter {
+        state: Some(params.state.unwrap_or(ReportStatus::Pending)),
+    };
+    let page = Page::new(params.limit.unwrap_or(50), params.offset.unwrap_or(0));
+
+    let reports = state.report.read_many(&filter, &page).await?;
+
+    let mut result = Vec::with_capacity(reports.len());
+    for report in reports {
+        let message = match report.message_id {
+            Some(message_id) => state.msg.read(&message_id).await?.map(MessageDTO::from),
+            None => None,
+        };
+        let mut dto = ReportDTO::from(report);
+        dto.message = message;
+        result.push(dto);
+    }
+
+    info!("Found {} reports", result.len());
+    Ok(Json(result))
+}
+
+/// Chiude una segnalazione
+#[utoipa::path(
+    post,
+    path = "/admin/reports/{report_id}/{action}",
+    tag = "admin",
+    params(
+        ("report_id" = i32, Path, description = "ID della segnalazione"),
+        ("action" = String, Path, description = "resolve, dismiss oppure delete_message"),
+    ),
+    responses(
+        (status = 200, description = "Segnalazione chiusa", body = ReportDTO),
+        (status = 400, description = "Azione non valida"),
+        (status = 404, description = "Segnalazione non trovata"),
+        (status = 409, description = "Segnalazione già chiusa"),
+    )
+)]
+#[instrument(skip(state, current_user), fields(report_id = %report_id, action = %action, user_id = %current_user.user_id))]
+pub async fn admin_resolve_report(
+    State(state): State<Arc<AppState>>,
+    Path((report_id, action)): Path<(i32, String)>,
+    Extension(current_user): Extension<User>,
+) -> Result<Json<ReportDTO>, AppError> {
+    debug!("Resolving report as server admin");
+    // 1. Validare che action sia "resolve", "dismiss" o "delete_message"
+    // 2. Recuperare la segnalazione, NOT_FOUND se non esiste
+    // 3. CONFLICT se è già stata chiusa
+    // 4. Con "delete_message" eliminare il messaggio segnalato, se non è già stato eliminato
+    // 5. Chiudere la segnalazione e ritornarla
+
+    let (new_state, delete_message) = match action.as_str() {
+        "resolve" => (ReportStatus::Resolved, false),
+        "dismiss" => (ReportStatus::Dismissed, false),
+        "delete_message" => (ReportStatus::Resolved, true),
+        _ => {
+            warn!("Invalid report action: {}", action);
+            return Err(AppError::bad_request(
+                "Action must be 'resolve', 'dismiss' or 'delete_message'",
+            ));
+        }
+    };
+
+    let report = state.report.read(&report_id).await?.ok_or_else(|| {
+        warn!("Report {} not found", report_id);
+        AppError::not_found("Report not found")
+    })?;
+
+    if report.state != ReportStatus::Pending {
+        warn!("Report {} already closed", report_id);
+        return Err(AppError::conflict("Report already closed"));
+    }
+
+    if delete_message {
+        let message_id = report.message_id.ok_or_else(|| {
+            warn!("Report {} does not target a message", report_id);
+            AppError::bad_request("Report does not target a message")
+        })?;
+        // il messaggio può essere stato eliminato dopo la segnalazione, dall'autore o da un moderatore
+        if let Some(message) = state
+            .msg
+            .read(&message_id)
+            .await?
+            .filter(|m| m.deleted_at.is_none())
+        {
+            delete_message_as_admin(&state, current_user.user_id, message).await?;
+        }
+    }
+
+    let resolved = state
+        .report
+        .resolve(&report_id, &new_state, &current_user.user_id)
+        .await?;
+
+    info!("Report closed as {:?}", resolved.state);
+    Ok(Json(ReportDTO::from(resolved)))
+}
@@ -0,0 +1,329 @@
+//! Attachment services - Caricamento dei file allegati ai messaggi
+
+use crate::core::{AppError, AppState};
+use crate::dtos::{AttachmentDTO, CreateAttachmentDTO};
+use crate::entities::{Attachment, User, UserChatMetadata};
+use crate::repositories::{Create, Read};
+use axum::{
+    Extension,
+    extract::{Json, Multipart, Path, State, multipart::MultipartError},
+    http::{StatusCode, header},
+    response::IntoResponse,
+};
+use chrono::Utc;
+use object_store::{PutPayload, path::Path as StoragePath};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, instrument, warn};
+
+/// Nome del campo multipart che contiene il file
+const FILE_FIELD: &str = "file";
+
+/// Content type usato quando il client non lo specifica
+const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// Età minima di un allegato senza riferimenti prima che venga eliminato: copre il tempo tra
+/// il caricamento e l'invio del messaggio che lo usa
+pub const ATTACHMENT_ORPHAN_GRACE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Intervallo tra due esecuzioni del job di pulizia degli allegati
+pub const ATTACHMENT_GC_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Allegati esaminati al massimo ad ogni esecuzione, i restanti passano alla successiva
+const ATTACHMENT_GC_BATCH: i64 = 500;
+
+/// Carica un allegato nella chat
+#[utoipa::path(
+    post,
+    path = "/chats/{chat_id}/attachments",
+    tag = "messages",
+    params(("chat_id" = i32, Path, description = "ID della chat")),
+    request_body(content = crate::openapi::FileUpload, content_type = "multipart/form-data"),
+    responses(
+        (
+            status = 200,
+            description = "Allegato salvato, da citare nel messaggio",
+            body = AttachmentDTO,
+        ),
+        (status = 400, description = "Corpo multipart non valido"),
+        (status = 413, description = "Allegato troppo grande"),
+        (status = 415, description = "Tipo di file non ammesso"),
+    )
+)]
+#[instrument(skip(state, current_user, multipart), fields(chat_id = %chat_id, user_id = %current_user.user_id))]
+pub async fn upload_attachment(
+    State(state): State<Arc<AppState>>,
+    Path(chat_id): Path<i32>,
+    Extension(current_user): Extension<User>, // la membership è già verificata dal chat_membership_middleware
+    multipart: Multipart,
+) -> Result<Json<AttachmentDTO>, AppError> {
+    debug!("Uploading attachment");
+    // 1. Salvare il file ricevuto sullo storage e registrarlo nel database
+    // 2. Ritornare l'allegato, il client userà attachment_id nel messaggio
+
+    let attachment =
+        store_upload(&state, chat_id, current_user.user_id, multipart, |_| true).await?;
+
+    info!(
+        attachment_id = attachment.attachment_id,
+        size_bytes = attachment.size_bytes,
+        "Attachment uploaded successfully"
+    );
+    Ok(Json(AttachmentDTO::from(attachment)))
+}
+
+/// Scarica un allegato della chat
+#[utoipa::path(
+    get,
+    path = "/chats/{chat_id}/attachments/{attachment_id}",
+    tag = "messages",
+    params(
+        ("chat_id" = i32, Path, description = "ID della chat"),
+        ("attachment_id" = i32, Path, description = "ID dell'allegato"),
+    ),
+    responses(
+        (
+            status = 200,
+            description = "Contenuto del file",
+            body = Vec<u8>,
+            content_type = "application/octet-stream",
+        ),
+        (status = 404, description = "Allegato non trovato"),
+    )
+)]
+#[instrument(skip(state, _metadata), fields(chat_id = %chat_id, attachment_id = %attachment_id))]
+pub async fn download_attachment(
+    State(state): State<Arc<AppState>>,
+    Path((chat_id, attachment_id)): Path<(i32, i32)>,
+    Extension(_metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware
+) -> Result<impl IntoResponse, AppError> {
+    debug!("Downloading attachment");
+    // 1. Recuperare l'allegato, errore NOT_FOUND se non esiste o appartiene ad un'altra chat
+    // 2. Leggere il file dallo storage
+    // 3. Ritornare il contenuto con content type e nome file originali
+
+    let attachment = state
+        .attachment
+        .read(&attachment_id)
+        .await?
+        .filter(|a| a.chat_id == chat_id)
+        .ok_or_else(|| {
+            warn!("Attachment {} not found in chat {}", attachment_id, chat_id);
+            AppError::not_found("Attachment not found")
+        })?;
+
+    let data = state
+        .storage
+        .get(&StoragePath::from(attachment.storage_key.as_str()))
+        .await
+        .map_err(|e| {
+            error!("Failed to read attachment from storage: {}", e);
+            AppError::internal_server_error("Failed to read attachment")
+        })?
+        .bytes()
+        .await
+        .map_err(|e| {
+            error!("Failed to read attachment from storage: {}", e);
+            AppError::internal_server_error("Failed to read attachment")
+        })?;
+
+    // il nome arriva dal client: nell'header si tengono solo caratteri ascii sicuri
+    let safe_name: String = attachment
+        .file_name
+        .chars()
+        .filter(|c| (c.is_ascii_graphic() && *c != '"' && *c != '\\') || *c == ' ')
+        .collect();
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, attachment.content_type),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("inline; filename=\"{}\"", safe_name),
+            ),
+        ],
+        data,
+    ))
+}
+
+/// Errore di lettura del corpo multipart: 413 se il corpo ha superato il limite
+/// della route imposto dal body_limit_middleware, 400 altrimenti
+fn multipart_error(e: MultipartError) -> AppError {
+    if e.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        AppError::new(StatusCode::PAYLOAD_TOO_LARGE, "Attachment too large")
+    } else {
+        AppError::bad_request("Invalid multipart body").with_details(e.body_text())
+    }
+}
+
+/// Legge il campo "file" dal corpo multipart, lo salva sullo storage e lo registra nel database.
+/// Condiviso da tutti gli endpoint che ricevono file (allegati, avatar delle chat).
+///
+/// # Arguments
+/// * `accept_content_type` - Filtro sul content type dichiarato dal client, UNSUPPORTED_MEDIA_TYPE se rifiutato
+pub(crate) async fn store_upload(
+    state: &AppState,
+    chat_id: i32,
+    uploader_id: i32,
+    mut multipart: Multipart,
+    accept_content_type: impl Fn(&str) -> bool,
+) -> Result<Attachment, AppError> {
+    // 1. Cercare il campo "file" nel corpo multipart, errore BAD_REQUEST se assente
+    // 2. Leggere il contenuto a blocchi, errore PAYLOAD_TOO_LARGE oltre il limite configurato
+    // 3. Salvare il file sullo storage con una chiave univoca
+    // 4. Registrare l'allegato nel database, rimuovendo il file se l'inserimento fallisce
+
+    let mut field = loop {
+        match multipart.next_field().await.map_err(|e| {
+            warn!("Invalid multipart body: {}", e);
+            multipart_error(e)
+        })? {
+            Some(field) if field.name() == Some(FILE_FIELD) => break field,
+            Some(_) => continue,
+            None => {
+                warn!("Multipart body without file field");
+                return Err(AppError::bad_request("Missing file field"));
+            }
+        }
+    };
+
+    // si tiene solo il nome finale, eventuali percorsi inviati dal client vengono scartati
+    let file_name = field
+        .file_name()
+        .and_then(|name| name.rsplit(['/', '\\']).next())
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| AppError::bad_request("Missing file name"))?
+        .chars()
+        .take(255)
+        .collect::<String>();
+    let content_type = field
+        .content_type()
+        .unwrap_or(DEFAULT_CONTENT_TYPE)
+        .to_string();
+
+    if !accept_content_type(&content_type) {
+        warn!(content_type, "Unsupported attachment content type");
+        return Err(AppError::new(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "Unsupported file type",
+        ));
+    }
+
+    let mut data = Vec::new();
+    while let Some(chunk) = field.chunk().await.map_err(|e| {
+        warn!("Failed to read attachment body: {}", e);
+        multipart_error(e)
+    })? {
+        if data.len() + chunk.len() > state.max_attachment_bytes {
+            warn!("Attachment exceeds {} bytes", state.max_attachment_bytes);
+            return Err(AppError::new(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "Attachment too large",
+            ));
+        }
+        data.extend_from_slice(&chunk);
+    }
+
+    if data.is_empty() {
+        warn!("Empty attachment");
+        return Err(AppError::bad_request("Attachment is empty"));
+    }
+
+    let size_bytes = data.len() as i64;
+    let storage_key = format!("chats/{}/{}", chat_id, uuid::Uuid::new_v4());
+    let location = StoragePath::from(storage_key.as_str());
+
+    state
+        .storage
+        .put(&location, PutPayload::from(data))
+        .await
+        .map_err(|e| {
+            error!("Failed to store attachment: {}", e);
+            AppError::internal_server_error("Failed to store attachment")
+        })?;
+
+    let created = state
+        .attachment
+        .create(&CreateAttachmentDTO {
+            chat_id,
+            uploader_id,
+            file_name,
+            content_type,
+            size_bytes,
+            storage_key,
+            created_at: Utc::now(),
+        })
+        .await;
+
+    match created {
+        Ok(attachment) => Ok(attachment),
+        Err(e) => {
+            // senza la riga nel db il file non sarebbe più raggiungibile
+            if let Err(cleanup) = state.storage.delete(&location).await {
+                error!("Failed to remove orphan attachment: {}", cleanup);
+            }
+            Err(e.into())
+        }
+    }
+}
+
+/// Elimina gli allegati rimasti senza riferimenti da più di `ATTACHMENT_ORPHAN_GRACE`:
+/// prima la riga nel database, solo se nel frattempo nessuno ha iniziato ad usarlo, poi il
+/// file sullo storage
+///
+/// # Returns
+/// Numero di allegati eliminati
+#[instrument(skip(state))]
+pub async fn collect_orphaned_attachments(state: &AppState) -> Result<u64, sqlx::Error> {
+    let uploaded_before = Utc::now() - ATTACHMENT_ORPHAN_GRACE;
+    let orphans = state
+        .attachment
+        .find_orphaned(&uploaded_before, ATTACHMENT_GC_BATCH)
+        .await?;
+
+    let mut collected = 0;
+    for attachment in orphans {
+        if !state
+            .attachment
+            .delete_if_orphaned(&attachment.attachment_id)
+            .await?
+        {
+            debug!(
+                attachment_id = attachment.attachment_id,
+                "Attachment referenced again, kept"
+            );
+            continue;
+        }
+        remove_stored_files(state, &[attachment.storage_key]).await;
+        collected += 1;
+    }
+
+    Ok(collected)
+}
+
+/// Elimina dallo storage i file indicati. Un errore viene solo registrato: il file resta
+/// sullo storage ma non è più raggiungibile
+pub(crate) async fn remove_stored_files(state: &AppState, storage_keys: &[String]) {
+    for key in storage_keys {
+        if let Err(e) = state.storage.delete(&StoragePath::from(key.as_str())).await {
+            error!("Failed to remove attachment {} from storage: {}", key, e);
+        }
+    }
+}
+
+/// Avvia il task che esegue periodicamente `collect_orphaned_attachments`
+pub fn start_attachment_collector(state: Arc<AppState>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(ATTACHMENT_GC_INTERVAL);
+        loop {
+            ticker.tick().await;
+            match collect_orphaned_attachments(&state).await {
+                Ok(0) => {}
+                Ok(collected) => info!(collected, "Orphaned attachments removed"),
+                Err(e) => error!("Failed to collect orphaned attachments: {:?}", e),
+            }
+        }
+    })
+}
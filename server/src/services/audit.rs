@@ -0,0 +1,112 @@
+//! Audit services - Registro delle azioni amministrative sulle chat
+
+use crate::core::{AppError, AppState, require_role};
+use crate::dtos::{AuditEntryDTO, AuditLogQuery, CreateAuditEntryDTO};
+use crate::entities::{AuditAction, UserChatMetadata, UserRole};
+use crate::repositories::{AuditFilter, Create, Page, ReadMany, UnitOfWork};
+use axum::{
+    Extension,
+    extract::{Json, Path, Query, State},
+};
+use std::sync::Arc;
+use tracing::{debug, info, instrument};
+use validator::Validate;
+
+/// Audit log delle azioni amministrative nella chat, dal più recente
+#[utoipa::path(
+    get,
+    path = "/chats/{chat_id}/audit",
+    tag = "chats",
+    params(
+        ("chat_id" = i32, Path, description = "ID della chat"),
+        AuditLogQuery,
+    ),
+    responses(
+        (status = 200, description = "Voci dell'audit log", body = Vec<AuditEntryDTO>),
+        (status = 403, description = "Permessi insufficienti"),
+    )
+)]
+#[instrument(skip(state, metadata), fields(chat_id = %chat_id, user_id = %metadata.user_id))]
+pub async fn list_audit_log(
+    State(state): State<Arc<AppState>>,
+    Path(chat_id): Path<i32>,
+    Query(params): Query<AuditLogQuery>,
+    Extension(metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware
+) -> Result<Json<Vec<AuditEntryDTO>>, AppError> {
+    debug!("Listing audit log");
+    // 1. Verificare che current_user sia Admin o Owner, altrimenti FORBIDDEN (fail-fast)
+    // 2. Validare limit e offset
+    // 3. Recuperare le voci dalla più recente, eventualmente solo quelle di un'azione, e ritornarle
+
+    require_role(&metadata, &[UserRole::Admin, UserRole::Owner])?;
+
+    params.validate()?;
+    let limit = params.limit.unwrap_or(50);
+    let offset = params.offset.unwrap_or(0);
+
+    let entries = state
+        .audit
+        .read_many(
+            &AuditFilter {
+                chat_id,
+                action: params.action,
+            },
+            &Page::new(limit, offset),
+        )
+        .await?;
+
+    info!("Found {} audit entries", entries.len());
+    Ok(Json(entries.into_iter().map(AuditEntryDTO::from).collect()))
+}
+
+/// Registra un'azione privilegiata nell'audit log della chat.
+/// Va chiamata dopo che l'azione è andata a buon fine.
+pub(crate) async fn record(
+    state: &AppState,
+    chat_id: i32,
+    actor_id: i32,
+    action: AuditAction,
+    target_user_id: Option<i32>,
+    payload: Option<serde_json::Value>,
+) -> Result<(), AppError> {
+    state
+        .audit
+        .create(&CreateAuditEntryDTO {
+            chat_id,
+            actor_id,
+            target_user_id,
+            action,
+            payload,
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// Come [`record`], ma scrive la voce nella stessa unit of work dell'azione,
+/// così l'audit log non può divergere da ciò che è stato davvero applicato.
+pub(crate) async fn record_in(
+    state: &AppState,
+    uow: &mut UnitOfWork,
+    chat_id: i32,
+    actor_id: i32,
+    action: AuditAction,
+    target_user_id: Option<i32>,
+    payload: Option<serde_json::Value>,
+) -> Result<(), AppError> {
+    state
+        .audit
+        .create_in(
+            uow,
+            &CreateAuditEntryDTO {
+                chat_id,
+                actor_id,
+                target_user_id,
+                action,
+                payload,
+            },
+        )
+        .await?;
+
+    Ok(())
+}
@@ -1,103 +1,614 @@
 //! Auth services - Gestione autenticazione e registrazione utenti
 
-use crate::core::{AppError, AppState, encode_jwt};
-use crate::dtos::{CreateUserDTO, UserDTO};
+use crate::core::auth::Claims;
+use crate::core::config::{ACCOUNT_REACTIVATION_WINDOW_DAYS, REFRESH_TOKEN_TTL_DAYS};
+use crate::core::{
+    AppError, AppState, ClientIp, encode_jwt, generate_refresh_token, hash_refresh_token,
+};
+use crate::dtos::{
+    AuthTokensDTO, CreateRefreshTokenDTO, CreateUserDTO, CreateUserIdentityDTO, OidcCallbackQuery,
+    RefreshTokenDTO, UpdateUserDTO, UserDTO,
+};
 use crate::entities::User;
 use crate::repositories::Create;
+use crate::services::oidc::{
+    OIDC_STATE_COOKIE, OidcError, OidcIdentity, PENDING_LOGIN_TTL, username_hint,
+};
 use axum::{
-    extract::{Json, State},
+    Extension,
+    extract::{Json, Query, State},
     http::{HeaderMap, HeaderValue, StatusCode},
-    response::IntoResponse,
+    response::{IntoResponse, Redirect},
 };
+use chrono::{DateTime, Duration, Utc};
 use std::sync::Arc;
 use tracing::{debug, error, info, instrument, warn};
 use validator::Validate;
 
 /// DTO per il login (solo username e password)
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, utoipa::ToSchema)]
 pub struct LoginDTO {
     pub username: String,
     pub password: String,
 }
 
+/// Login con username e password, ritorna access e refresh token
+#[utoipa::path(
+    post,
+    path = "/auth/login",
+    tag = "auth",
+    request_body = LoginDTO,
+    responses(
+        (
+            status = 200,
+            description = "Login riuscito, l'access token è anche nell'header Authorization",
+            body = AuthTokensDTO,
+        ),
+        (status = 401, description = "Credenziali non valide"),
+        (status = 429, description = "Troppi tentativi falliti, riprovare dopo Retry-After"),
+    ),
+    security(())
+)]
 #[instrument(skip(state, body), fields(username = %body.username))]
 pub async fn login_user(
     State(state): State<Arc<AppState>>,
+    ClientIp(ip): ClientIp,
     Json(body): Json<LoginDTO>, // JSON body
 ) -> Result<impl IntoResponse, AppError> {
     debug!("Login attempt for user");
     // 1. Estrarre lo username dal body della richiesta, ritornare errore BAD_REQUEST se mancante
     // 2. Verificare che la password sia stata fornita nel body, altrimenti ritornare errore UNAUTHORIZED (fail-fast prima della query DB)
     // 3. Bloccare il caso in cui si sta cercando di fare login con "Deleted User" (controllo string prima della query DB)
-    // 4. Cercare l'utente nel database tramite username
-    // 5. Se l'utente non esiste, ritornare errore UNAUTHORIZED
-    // 6. Verificare che la password fornita, dopo essere hashata, corrisponda all'hash memorizzato
-    // 7. Se la password non corrisponde, ritornare errore UNAUTHORIZED con messaggio specifico
-    // 8. Generare un token JWT con il metodo encode che prende in input userid, username e il segreto
-    // 9. Costruire un cookie HttpOnly, Secure, SameSite=Lax con il token e durata 24 ore
-    // 10. Creare gli headers HTTP con Set-Cookie e Authorization (Bearer token)
-    // 11. Ritornare StatusCode::OK con gli headers
+    // 4. Se la coppia (username, IP) è bloccata per troppi tentativi falliti, ritornare TOO_MANY_REQUESTS
+    // 5. Cercare l'utente nel database tramite username (con LDAP può non esistere ancora)
+    // 6. Verificare le credenziali con il backend configurato (hash locale o bind LDAP),
+    //    ritornare SERVICE_UNAVAILABLE se il backend non è raggiungibile
+    // 7. Se l'utente non esiste e il backend non l'ha riconosciuto, registrare il fallimento
+    //    e ritornare errore UNAUTHORIZED
+    // 8. Se la password non è corretta, registrare il fallimento e ritornare errore UNAUTHORIZED
+    //    con messaggio specifico
+    // 9. Al primo accesso di un utente della directory, creare l'utente locale
+    // 10. Azzerare i tentativi falliti della coppia (username, IP)
+    // 11. Con il backend locale, se la password è stata salvata con algoritmo o parametri
+    //     diversi da quelli configurati, ricalcolarne l'hash (errori solo loggati)
+    // 12. Se l'account è disattivato, riattivarlo se la finestra di riattivazione non è scaduta
+    // 13. Emettere access token e refresh token di una nuova famiglia
+    // 14. Ritornare StatusCode::OK con gli headers (Set-Cookie, Authorization) e i token nel body
 
     if body.username == "Deleted User" {
         warn!("Login attempt with 'Deleted User' username");
         return Err(AppError::unauthorized("Invalid username or password"));
     }
 
-    let user = match state.user.find_by_username(&body.username).await? {
-        Some(user) => {
+    if let Err(retry_after) = state.login_throttle.check(&body.username, ip) {
+        warn!("Login attempt while locked out");
+        return Err(AppError::too_many_requests(
+            "Too many failed login attempts",
+            retry_after,
+        ));
+    }
+
+    let stored = state.user.find_by_username(&body.username).await?;
+
+    let authenticated = state
+        .auth_provider
+        .authenticate(&body.username, &body.password, stored.as_ref())
+        .await
+        .map_err(|e| {
+            error!("Authentication backend error: {}", e);
+            AppError::service_unavailable("Authentication backend unavailable")
+        })?;
+
+    let user = match (stored, authenticated) {
+        (None, false) => {
+            warn!("User not found in database");
+            state.login_throttle.record_failure(&body.username, ip);
+            return Err(AppError::unauthorized("Invalid username or password"));
+        }
+        (Some(_), false) => {
+            warn!("Invalid password for user");
+            state.login_throttle.record_failure(&body.username, ip);
+            return Err(AppError::unauthorized(
+                "Username or password are not correct.",
+            ));
+        }
+        (Some(user), true) => {
             debug!("User found in database");
             user
         }
-        None => {
-            warn!("User not found in database");
-            return Err(AppError::unauthorized("Invalid username or password"));
+        (None, true) => {
+            let user = create_external_user(&state, body.username.clone()).await?;
+            info!("Provisioned user {} from directory login", user.username);
+            user
         }
     };
+    state.login_throttle.record_success(&body.username, ip);
 
-    if !user.verify_password(&body.password) {
-        warn!("Invalid password for user");
-        return Err(AppError::unauthorized(
-            "Username or password are not correct.",
-        ));
+    // con un backend esterno la password locale è casuale e non va né verificata né ricalcolata
+    if state.auth_provider.uses_local_passwords()
+        && state.password_hasher.needs_rehash(&user.password)
+        && user.verify_password(&body.password)
+    {
+        match state.password_hasher.hash(&body.password) {
+            Ok(password) => {
+                let update = UpdateUserDTO {
+                    password: Some(password),
+                };
+                match state.user.update(&user.user_id, &update).await {
+                    Ok(_) => info!("Password rehashed with the configured parameters"),
+                    Err(e) => error!("Failed to store rehashed password: {}", e),
+                }
+            }
+            Err(e) => error!("Failed to rehash password: {}", e),
+        }
     }
 
-    let token = encode_jwt(&user.username, user.user_id, &state.jwt_secret)?;
+    if !reactivate_if_deactivated(&state, &user).await? {
+        warn!("Login attempt on account past its reactivation window");
+        return Err(AppError::unauthorized("Invalid username or password"));
+    }
+
+    let family_id = uuid::Uuid::new_v4().to_string();
+    let (headers, tokens) = issue_tokens(&state, &user, family_id).await?;
+
+    info!("User logged in successfully: {}", user.username);
+    Ok((StatusCode::OK, headers, Json(tokens)))
+}
+
+/// Scambia un refresh token con una nuova coppia di token
+#[utoipa::path(
+    post,
+    path = "/auth/refresh",
+    tag = "auth",
+    request_body = RefreshTokenDTO,
+    responses(
+        (
+            status = 200,
+            description = "Nuova coppia di token, il refresh token usato non è più valido",
+            body = AuthTokensDTO,
+        ),
+        (status = 401, description = "Refresh token non valido, scaduto o già usato"),
+    ),
+    security(())
+)]
+#[instrument(skip(state, body))]
+pub async fn refresh_tokens(
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<RefreshTokenDTO>, // JSON body
+) -> Result<impl IntoResponse, AppError> {
+    debug!("Refresh token rotation attempt");
+    // 1. Cercare il refresh token tramite il suo hash, ritornare UNAUTHORIZED se non esiste
+    // 2. Se il token è già stato usato o revocato, è un riuso: revocare l'intera famiglia
+    //    (il token potrebbe essere stato rubato) e ritornare UNAUTHORIZED
+    // 3. Se il token è scaduto ritornare UNAUTHORIZED
+    // 4. Segnare il token come usato; se nel frattempo un'altra richiesta l'ha già usato,
+    //    trattare il caso come un riuso
+    // 5. Verificare che l'utente esista ancora e non sia disattivato
+    // 6. Emettere una nuova coppia di token nella stessa famiglia e ritornarla
+
+    let stored = state
+        .refresh_token
+        .find_by_hash(&hash_refresh_token(&body.refresh_token))
+        .await?
+        .ok_or_else(|| {
+            warn!("Unknown refresh token");
+            AppError::unauthorized("Invalid refresh token")
+        })?;
+
+    if stored.is_consumed() {
+        warn!("Refresh token reuse detected, revoking family");
+        state.refresh_token.revoke_family(&stored.family_id).await?;
+        return Err(AppError::unauthorized("Invalid refresh token"));
+    }
 
+    if stored.expires_at < Utc::now() {
+        warn!("Expired refresh token");
+        return Err(AppError::unauthorized("Refresh token expired"));
+    }
+
+    if !state.refresh_token.mark_used(&stored.token_id).await? {
+        warn!("Refresh token consumed concurrently, revoking family");
+        state.refresh_token.revoke_family(&stored.family_id).await?;
+        return Err(AppError::unauthorized("Invalid refresh token"));
+    }
+
+    let user = match state.user.read(&stored.user_id).await? {
+        Some(user) if user.deactivated_at.is_none() => user,
+        _ => {
+            warn!("Refresh token of a missing or deactivated user");
+            return Err(AppError::unauthorized("Invalid refresh token"));
+        }
+    };
+
+    let (headers, tokens) = issue_tokens(&state, &user, stored.family_id).await?;
+
+    info!("Tokens refreshed for user: {}", user.username);
+    Ok((StatusCode::OK, headers, Json(tokens)))
+}
+
+/// Logout: revoca l'access token usato e l'eventuale refresh token della sessione
+#[utoipa::path(
+    post,
+    path = "/auth/logout",
+    tag = "auth",
+    request_body = Option<RefreshTokenDTO>,
+    responses(
+        (status = 200, description = "Sessione chiusa"),
+    )
+)]
+#[instrument(skip(state, current_user, claims, body), fields(user_id = %current_user.user_id))]
+pub async fn logout_user(
+    State(state): State<Arc<AppState>>,
+    Extension(current_user): Extension<User>, // ottenuto dall'autenticazione tramite token jwt
+    Extension(claims): Extension<Claims>,     // claims del token usato per la richiesta
+    body: Option<Json<RefreshTokenDTO>>,      // refresh token della sessione, opzionale
+) -> Result<impl IntoResponse, AppError> {
+    debug!("Logout requested");
+    // 1. Revocare l'access token corrente fino alla sua scadenza naturale
+    // 2. Se il client invia il proprio refresh token, revocarne l'intera famiglia
+    //    (solo se appartiene all'utente corrente)
+    // 3. Chiudere la connessione WebSocket aperta con questo access token, se c'è
+    // 4. Creare un cookie con Max-Age=0 per forzare il logout lato client
+    // 5. Ritornare StatusCode::OK con gli headers
+
+    let expires_at = DateTime::from_timestamp(claims.exp as i64, 0).unwrap_or_else(Utc::now);
+    state
+        .revoked_tokens
+        .revoke(&claims.jti, expires_at)
+        .await
+        .map_err(|e| {
+            error!("Failed to revoke access token: {}", e);
+            AppError::service_unavailable("Token revocation store unavailable")
+        })?;
+
+    if let Some(Json(body)) = body {
+        let stored = state
+            .refresh_token
+            .find_by_hash(&hash_refresh_token(&body.refresh_token))
+            .await?;
+        if let Some(stored) = stored.filter(|t| t.user_id == current_user.user_id) {
+            state.refresh_token.revoke_family(&stored.family_id).await?;
+        }
+    }
+
+    // le connessioni aperte con token di altre sessioni restano attive
+    state
+        .users_online
+        .disconnect_token(&current_user.user_id, &claims.jti);
+
+    let cookie = format!("token=; {}; Path=/; Max-Age=0", state.cookie_attributes());
+    let mut headers = HeaderMap::new();
+    headers.insert("Set-Cookie", HeaderValue::from_str(&cookie).unwrap());
+
+    info!("User logged out");
+    Ok((StatusCode::OK, headers))
+}
+
+/// Avvia il login SSO reindirizzando al provider OIDC
+#[utoipa::path(
+    get,
+    path = "/auth/oidc/login",
+    tag = "auth",
+    responses(
+        (status = 303, description = "Redirect alla pagina di login del provider"),
+        (status = 503, description = "SSO non configurato"),
+    ),
+    security(())
+)]
+#[instrument(skip(state))]
+pub async fn oidc_login(State(state): State<Arc<AppState>>) -> Result<impl IntoResponse, AppError> {
+    debug!("SSO login requested");
+    // 1. Verificare che il login SSO sia configurato, altrimenti SERVICE_UNAVAILABLE
+    // 2. Generare state, nonce e PKCE
+    // 3. Legare lo state al browser con un cookie e reindirizzare l'utente al provider
+
+    let oidc = state
+        .oidc
+        .as_ref()
+        .ok_or_else(|| AppError::service_unavailable("Single sign-on is not configured"))?;
+
+    let (url, oidc_state) = oidc.authorization_url().await.map_err(oidc_error)?;
+
+    let cookie = format!(
+        "{}={}; {}; Path=/; Max-Age={}",
+        OIDC_STATE_COOKIE,
+        oidc_state,
+        state.cookie_attributes(),
+        PENDING_LOGIN_TTL.as_secs()
+    );
+    let mut headers = HeaderMap::new();
+    headers.insert("Set-Cookie", HeaderValue::from_str(&cookie).unwrap());
+
+    Ok((headers, Redirect::to(&url)))
+}
+
+/// Completa il login SSO con il codice inviato dal provider OIDC
+#[utoipa::path(
+    get,
+    path = "/auth/oidc/callback",
+    tag = "auth",
+    params(OidcCallbackQuery),
+    responses(
+        (status = 200, description = "Login riuscito", body = AuthTokensDTO),
+        (status = 400, description = "Parametri mancanti, state non valido o cookie dello state assente"),
+        (status = 401, description = "Accesso negato dal provider"),
+        (status = 502, description = "Errore del provider"),
+        (status = 503, description = "SSO non configurato"),
+    ),
+    security(())
+)]
+#[instrument(skip(state, headers, params))]
+pub async fn oidc_callback(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap, // cookie `oidc_state` impostato da GET /auth/oidc/login
+    Query(params): Query<OidcCallbackQuery>, // ?code=...&state=... inviati dal provider
+) -> Result<impl IntoResponse, AppError> {
+    debug!("SSO callback received");
+    // 1. Verificare che il login SSO sia configurato, altrimenti SERVICE_UNAVAILABLE
+    // 2. Se il provider ha rifiutato il login (parametro error) ritornare UNAUTHORIZED
+    // 3. Verificare la presenza di code e state, altrimenti BAD_REQUEST
+    // 3.1 Verificare che lo state sia quello del cookie del browser, altrimenti BAD_REQUEST
+    // 4. Scambiare il codice per l'id_token e verificarlo
+    // 5. Cercare l'utente collegato all'identità; al primo accesso crearne uno nuovo
+    // 6. Se l'account è disattivato, riattivarlo se la finestra non è scaduta
+    // 7. Emettere access token e refresh token come per il login con password
+
+    let oidc = state
+        .oidc
+        .as_ref()
+        .ok_or_else(|| AppError::service_unavailable("Single sign-on is not configured"))?;
+
+    if let Some(error) = params.error {
+        warn!("Identity provider rejected the login: {}", error);
+        return Err(AppError::unauthorized("Single sign-on was denied"));
+    }
+
+    let (Some(code), Some(oidc_state)) = (params.code, params.state) else {
+        return Err(AppError::bad_request("Missing code or state"));
+    };
+
+    // uno state valido ma avviato da un altro browser è un tentativo di login CSRF
+    if cookie_value(&headers, OIDC_STATE_COOKIE) != Some(oidc_state.as_str()) {
+        warn!("SSO callback without a matching state cookie");
+        return Err(oidc_error(OidcError::InvalidState));
+    }
+
+    let identity = oidc
+        .exchange_code(&code, &oidc_state)
+        .await
+        .map_err(oidc_error)?;
+
+    let linked = state
+        .user_identity
+        .find_by_subject(&identity.issuer, &identity.subject)
+        .await?;
+    let user = match linked {
+        Some(linked) => state
+            .user
+            .read(&linked.user_id)
+            .await?
+            .ok_or_else(|| AppError::unauthorized("Account is no longer available"))?,
+        None => provision_oidc_user(&state, &identity).await?,
+    };
+
+    // gli account cancellati restano anonimi e non possono rientrare
+    if user.username == "Deleted User" || !reactivate_if_deactivated(&state, &user).await? {
+        warn!("SSO login on a deleted or expired account");
+        return Err(AppError::unauthorized("Account is no longer available"));
+    }
+
+    let family_id = uuid::Uuid::new_v4().to_string();
+    let (mut headers, tokens) = issue_tokens(&state, &user, family_id).await?;
+
+    let cookie = format!(
+        "{}=; {}; Path=/; Max-Age=0",
+        OIDC_STATE_COOKIE,
+        state.cookie_attributes()
+    );
+    headers.append("Set-Cookie", HeaderValue::from_str(&cookie).unwrap());
+
+    info!("User logged in via SSO: {}", user.username);
+    Ok((StatusCode::OK, headers, Json(tokens)))
+}
+
+/// Crea l'utente locale per un'identità OIDC al suo primo accesso.
+/// Lo username deriva dai claims, con un suffisso numerico se già occupato
+async fn provision_oidc_user(state: &AppState, identity: &OidcIdentity) -> Result<User, AppError> {
+    let base = username_hint(identity);
+    let mut username = base.clone();
+    let mut suffix = 1;
+    while state.user.find_by_username(&username).await?.is_some() {
+        if suffix > 100 {
+            return Err(AppError::conflict("Unable to choose a username"));
+        }
+        username = format!("{}_{}", base, suffix);
+        suffix += 1;
+    }
+
+    let user = create_external_user(state, username).await?;
+
+    state
+        .user_identity
+        .create(&CreateUserIdentityDTO {
+            user_id: user.user_id,
+            issuer: identity.issuer.clone(),
+            subject: identity.subject.clone(),
+        })
+        .await?;
+
+    info!("Provisioned user {} from SSO identity", user.username);
+    Ok(user)
+}
+
+/// Crea l'utente locale di un'identità gestita altrove (provider SSO, directory LDAP, bot dei webhook).
+/// La password locale è casuale e mai comunicata: l'utente entra solo tramite il suo backend
+pub(crate) async fn create_external_user(
+    state: &AppState,
+    username: String,
+) -> Result<User, AppError> {
+    let password_hash = state
+        .password_hasher
+        .hash(&generate_refresh_token())
+        .map_err(|e| {
+            error!("Failed to hash password: {}", e);
+            AppError::internal_server_error("Failed to hash password")
+        })?;
+
+    let user = state
+        .user
+        .create(&CreateUserDTO {
+            username,
+            password: password_hash,
+        })
+        .await?;
+    Ok(user)
+}
+
+/// Converte gli errori del login OIDC nella risposta HTTP
+fn oidc_error(e: OidcError) -> AppError {
+    match e {
+        OidcError::InvalidState => AppError::bad_request("Invalid or expired login state"),
+        OidcError::Provider(details) => {
+            error!("Identity provider error: {}", details);
+            AppError::new(StatusCode::BAD_GATEWAY, "Identity provider error")
+        }
+        OidcError::InvalidToken(details) => {
+            warn!("Invalid id_token: {}", details);
+            AppError::unauthorized("Invalid identity token")
+        }
+        OidcError::Store(details) => {
+            error!("Pending login store error: {}", details);
+            AppError::internal_server_error("Failed to process the login")
+        }
+    }
+}
+
+/// Valore del cookie `name` inviato dal browser, se presente
+fn cookie_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all("Cookie")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+/// Riattiva l'account se è disattivato ed è ancora nella finestra di riattivazione.
+/// Ritorna false se la finestra è scaduta e l'accesso va rifiutato
+async fn reactivate_if_deactivated(state: &AppState, user: &User) -> Result<bool, AppError> {
+    let Some(deactivated_at) = user.deactivated_at else {
+        return Ok(true);
+    };
+    if deactivated_at + Duration::days(ACCOUNT_REACTIVATION_WINDOW_DAYS) < Utc::now() {
+        return Ok(false);
+    }
+    state.user.set_deactivated_at(&user.user_id, None).await?;
+    info!("Account reactivated");
+    Ok(true)
+}
+
+/// Emette un access token JWT e un nuovo refresh token nella famiglia indicata.
+/// Ritorna gli headers Set-Cookie/Authorization e il body con entrambi i token
+async fn issue_tokens(
+    state: &AppState,
+    user: &User,
+    family_id: String,
+) -> Result<(HeaderMap, AuthTokensDTO), AppError> {
+    let access_token = encode_jwt(
+        &user.username,
+        user.user_id,
+        state.server_role(user.user_id),
+        &state.jwt_keys,
+    )?;
+
+    let refresh_token = generate_refresh_token();
+    state
+        .refresh_token
+        .create(&CreateRefreshTokenDTO {
+            user_id: user.user_id,
+            family_id,
+            token_hash: hash_refresh_token(&refresh_token),
+            expires_at: Utc::now() + Duration::days(REFRESH_TOKEN_TTL_DAYS),
+        })
+        .await?;
+
+    let expires_in = state.jwt_keys.access_token_ttl().num_seconds();
     let cookie_value = format!(
-        "token={}; HttpOnly; Secure; SameSite=Lax; Max-Age={}",
-        token,
-        24 * 60 * 60
+        "token={}; {}; Max-Age={}",
+        access_token,
+        state.cookie_attributes(),
+        expires_in
     );
 
     let mut headers = HeaderMap::new();
     headers.insert("Set-Cookie", HeaderValue::from_str(&cookie_value).unwrap());
     headers.insert(
         "Authorization",
-        HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        HeaderValue::from_str(&format!("Bearer {}", access_token)).unwrap(),
     );
 
-    info!("User logged in successfully: {}", user.username);
-    Ok((StatusCode::OK, headers))
+    let tokens = AuthTokensDTO {
+        access_token,
+        token_type: "Bearer".to_string(),
+        expires_in,
+        refresh_token,
+    };
+
+    Ok((headers, tokens))
 }
 
+/// Registra un nuovo utente
+#[utoipa::path(
+    post,
+    path = "/auth/register",
+    tag = "auth",
+    request_body = CreateUserDTO,
+    responses(
+        (status = 200, description = "Utente creato", body = UserDTO),
+        (status = 400, description = "Dati non validi o password troppo debole"),
+        (status = 403, description = "Gli account sono gestiti dalla directory (AUTH_BACKEND=ldap)"),
+        (status = 409, description = "Username già in uso"),
+    ),
+    security(())
+)]
 #[instrument(skip(state, body), fields(username = %body.username))]
 pub async fn register_user(
     State(state): State<Arc<AppState>>,
     Json(body): Json<CreateUserDTO>, // JSON body
 ) -> Result<Json<UserDTO>, AppError> {
     debug!("User registration attempt");
-    // 1. Validare il DTO con validator (username/password format, lunghezza, "Deleted User")
-    // 2. Controllare se esiste già un utente con lo stesso username nel database
-    // 3. Se l'utente esiste già, ritornare errore CONFLICT con messaggio "Username already exists"
-    // 4. Generare l'hash della password fornita
-    // 5. Se la generazione dell'hash fallisce, ritornare errore INTERNAL_SERVER_ERROR
-    // 6. Creare un nuovo oggetto CreateUserDTO con username e password hashata
-    // 7. Salvare il nuovo utente nel database tramite il metodo create
-    // 8. Convertire l'utente creato in UserDTO
-    // 9. Ritornare il DTO dell'utente creato come risposta JSON
-
-    // Validazione con validator (include controllo "Deleted User")
-    body.validate()?;
+    // 1. Con un backend esterno (LDAP) gli account nascono al primo login, ritornare FORBIDDEN
+    // 2. Validare il DTO con validator (username format, lunghezza, "Deleted User") e la
+    //    password con la policy configurata, riportando tutti gli errori per campo
+    // 3. Controllare se esiste già un utente con lo stesso username nel database
+    // 4. Se l'utente esiste già, ritornare errore CONFLICT con messaggio "Username already exists"
+    // 5. Generare l'hash della password fornita
+    // 6. Se la generazione dell'hash fallisce, ritornare errore INTERNAL_SERVER_ERROR
+    // 7. Creare un nuovo oggetto CreateUserDTO con username e password hashata
+    // 8. Salvare il nuovo utente nel database tramite il metodo create
+    // 9. Convertire l'utente creato in UserDTO
+    // 10. Ritornare il DTO dell'utente creato come risposta JSON
+
+    // una password locale non servirebbe a entrare e lo username potrebbe sottrarre
+    // l'account a un utente della directory che non ha ancora fatto il primo accesso
+    if !state.auth_provider.uses_local_passwords() {
+        warn!("Registration attempt with an external authentication backend");
+        return Err(AppError::forbidden(
+            "Accounts are managed by the directory, sign in with your directory credentials",
+        ));
+    }
+
+    // Validazione con validator (include controllo "Deleted User") e policy delle password
+    let mut errors = body.validate().err().unwrap_or_default();
+    state
+        .password_policy
+        .check("password", &body.password, &mut errors);
+    if !errors.is_empty() {
+        warn!("Invalid registration data");
+        return Err(errors.into());
+    }
 
     // Controllare se esiste già un utente con lo stesso username
     if let Some(_) = state.user.find_by_username(&body.username).await? {
@@ -105,8 +616,8 @@ pub async fn register_user(
         return Err(AppError::conflict("Username already exists"));
     }
 
-    let password_hash = User::hash_password(&body.password).map_err(|e| {
-        error!("Failed to hash password: {:?}", e);
+    let password_hash = state.password_hasher.hash(&body.password).map_err(|e| {
+        error!("Failed to hash password: {}", e);
         AppError::internal_server_error("Failed to hash password")
     })?;
 
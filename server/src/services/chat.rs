@@ -1,21 +1,37 @@
 //! Chat services - Gestione operazioni sulle chat
 
-use crate::core::{AppError, AppState};
-use crate::dtos::{ChatDTO, CreateChatDTO, CreateUserChatMetadataDTO, MessageDTO, MessagesQuery};
-use crate::entities::{Chat, ChatType, User, UserChatMetadata, UserRole};
-use crate::repositories::{Create, Read};
+use crate::core::{AppError, AppState, DomainEvent, require_permission, require_role};
+use crate::dtos::{
+    ChatDTO, ChatOverviewDTO, CreateChatDTO, CreateUserChatMetadataDTO, DiscoverChatsQuery,
+    MessageDTO, MessagePollQuery, MessageRevisionDTO, MessageSearchQuery, MessageSearchResultDTO,
+    MessagesQuery, MissedMessagesDTO, PublicChatDTO, UnreadCountDTO, UpdateChatDTO,
+    UpdateMessageDTO,
+    message::{sanitize_markdown, validate_markdown},
+};
+use crate::entities::{
+    AuditAction, ChatPermission, ChatType, ContentFormat, MessageType, User, UserChatMetadata,
+    UserRole,
+};
+use crate::repositories::{ChatFilter, MemberFilter, MessageFilter, Page};
+use crate::services::attachment::{remove_stored_files, store_upload};
+use crate::services::audit;
+use crate::ws::POLL_DEFAULT_TIMEOUT_SECS;
+use crate::ws::chatmap::ChatEvent;
+use crate::ws::event_handlers::wait_for_messages;
+use crate::ws::usermap::InternalSignal;
 use axum::{
     Extension,
-    extract::{Json, Path, Query, State},
+    extract::{Json, Multipart, Path, Query, State},
 };
 use chrono::Utc;
-use futures_util::future::try_join_all;
+use serde_json::json;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tracing::{debug, info, instrument, warn};
 use validator::Validate;
 
 /// DTO per creare una chat (estende CreateChatDTO con user_list per chat private)
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, utoipa::ToSchema)]
 pub struct CreateChatRequestDTO {
     pub title: Option<String>,
     pub description: Option<String>,
@@ -23,6 +39,24 @@ pub struct CreateChatRequestDTO {
     pub user_list: Option<Vec<i32>>, // Solo per chat private
 }
 
+/// Chat dell'utente, prima quelle fissate
+#[utoipa::path(
+    get,
+    path = "/chats",
+    tag = "chats",
+    params(
+        ("If-None-Match" = Option<String>, Header, description = "ETag di una risposta precedente"),
+    ),
+    responses(
+        (
+            status = 200,
+            description = "Chat dell'utente",
+            body = Vec<ChatDTO>,
+            headers(("ETag" = String, description = "Hash del corpo della risposta")),
+        ),
+        (status = 304, description = "Lista invariata rispetto a If-None-Match"),
+    )
+)]
 #[instrument(skip(state, current_user), fields(user_id = %current_user.user_id))]
 pub async fn list_chats(
     State(state): State<Arc<AppState>>,
@@ -30,48 +64,155 @@ pub async fn list_chats(
 ) -> Result<Json<Vec<ChatDTO>>, AppError> {
     debug!("Listing chats for user");
     // 1. Ottenere l'utente corrente dall'Extension (autenticato tramite JWT)
-    // 2. Recuperare tutti i metadata dell'utente dal database tramite user_id (singola query)
-    // 3. Estrarre tutti i chat_id dai metadata trovati
-    // 4. Recuperare tutte le chat con query parallele (primary key lookup, velocissimo)
-    // 5. Convertire ogni Chat in ChatDTO (trasformazione in memoria, nessun I/O)
-    // 6. Ritornare la lista di ChatDTO come risposta JSON
-    let chat_ids: Vec<i32> = state
-        .meta
-        .find_many_by_user_id(&current_user.user_id)
-        .await?
-        .into_iter()
-        .map(|s| s.chat_id)
-        .collect();
+    // 2. Recuperare le chat dell'utente con il relativo pin in una sola query (JOIN tra
+    //    metadata e chat), già ordinate: prima le fissate, le più recenti in cima
+    // 3. Recuperare i membri di tutte le chat con una sola query
+    // 4. Popolare user_list di ogni chat (trasformazione in memoria, nessun I/O)
+    // 5. Ritornare la lista di ChatDTO come risposta JSON
+    let filter = ChatFilter {
+        member_id: Some(current_user.user_id),
+        ..ChatFilter::default()
+    };
+    let mut chats_dto = state.chat.read_many(&filter, &Page::ALL).await?;
+
+    debug!("User is member of {} chats", chats_dto.len());
 
-    debug!("User is member of {} chats", chat_ids.len());
-
-    let chats: Vec<Chat> = try_join_all(chat_ids.clone().into_iter().map(|cid| {
-        let state = state.clone();
-        async move { state.chat.read(&cid).await }
-    }))
-    .await?
-    .into_iter()
-    .flatten()
-    .collect();
-
-    // Popola user_list per ogni chat
-    let mut chats_dto: Vec<ChatDTO> = Vec::new();
-    for chat in chats {
-        let mut dto = ChatDTO::from(chat);
-        
-        // Recupera i membri della chat per popolare user_list
-        let chat_id = dto.chat_id.unwrap();
-        let members = state.meta.find_many_by_chat_id(&chat_id).await?;
-        debug!("Chat {} has {} members: {:?}", chat_id, members.len(), members.iter().map(|m| m.user_id).collect::<Vec<_>>());
-        dto.user_list = Some(members.into_iter().map(|m| m.user_id).collect());
-        
-        chats_dto.push(dto);
+    let chat_ids: Vec<i32> = chats_dto.iter().filter_map(|c| c.chat_id).collect();
+    let mut members_by_chat: HashMap<i32, Vec<i32>> = HashMap::new();
+    let filter = MemberFilter {
+        chat_ids: Some(chat_ids),
+        ..MemberFilter::default()
+    };
+    for member in state.meta.read_many(&filter, &Page::ALL).await? {
+        members_by_chat
+            .entry(member.chat_id)
+            .or_default()
+            .push(member.user_id);
+    }
+
+    for dto in &mut chats_dto {
+        dto.user_list = dto
+            .chat_id
+            .map(|chat_id| members_by_chat.remove(&chat_id).unwrap_or_default());
     }
 
     info!("Successfully retrieved {} chats", chats_dto.len());
     Ok(Json(chats_dto))
 }
 
+/// Numero di messaggi non letti per ogni chat
+#[utoipa::path(
+    get,
+    path = "/chats/unread",
+    tag = "chats",
+    responses(
+        (status = 200, description = "Messaggi non letti per chat", body = Vec<UnreadCountDTO>),
+    )
+)]
+#[instrument(skip(state, current_user), fields(user_id = %current_user.user_id))]
+pub async fn list_unread_counts(
+    State(state): State<Arc<AppState>>,
+    Extension(current_user): Extension<User>, // ottenuto dall'autenticazione JWT
+) -> Result<Json<Vec<UnreadCountDTO>>, AppError> {
+    debug!("Counting unread messages");
+    // 1. Ottenere l'utente corrente dall'Extension
+    // 2. Leggere il contatore unread_count di ogni chat dell'utente (nessun conteggio sui messaggi)
+    // 3. Ritornare la lista di conteggi come risposta JSON
+
+    let counts = state
+        .meta
+        .count_unread_by_user_id(&current_user.user_id)
+        .await?;
+
+    info!("Computed unread counts for {} chats", counts.len());
+    Ok(Json(counts))
+}
+
+/// Panoramica delle chat dell'utente: ultimo messaggio, non letti e numero di membri
+#[utoipa::path(
+    get,
+    path = "/chats/overview",
+    tag = "chats",
+    params(
+        ("If-None-Match" = Option<String>, Header, description = "ETag di una risposta precedente"),
+    ),
+    responses(
+        (
+            status = 200,
+            description = "Chat dell'utente, prima le fissate e poi le più recenti",
+            body = Vec<ChatOverviewDTO>,
+            headers(("ETag" = String, description = "Hash del corpo della risposta")),
+        ),
+        (status = 304, description = "Panoramica invariata rispetto a If-None-Match"),
+    )
+)]
+#[instrument(skip(state, current_user), fields(user_id = %current_user.user_id))]
+pub async fn list_chat_overview(
+    State(state): State<Arc<AppState>>,
+    Extension(current_user): Extension<User>, // ottenuto dall'autenticazione JWT
+) -> Result<Json<Vec<ChatOverviewDTO>>, AppError> {
+    debug!("Loading chat overview");
+    // 1. Ottenere l'utente corrente dall'Extension
+    // 2. Recuperare con una sola query chat, pin, ultimo messaggio visibile, contatore dei
+    //    non letti e numero di membri di ogni chat dell'utente
+    // 3. Ritornare la panoramica, già ordinata dal repository
+
+    let overview = state
+        .chat
+        .find_overview_by_user_id(&current_user.user_id)
+        .await?;
+
+    info!("Loaded overview of {} chats", overview.len());
+    Ok(Json(overview))
+}
+
+/// Directory delle chat pubbliche
+#[utoipa::path(
+    get,
+    path = "/chats/discover",
+    tag = "chats",
+    params(DiscoverChatsQuery),
+    responses(
+        (status = 200, description = "Chat pubbliche", body = Vec<PublicChatDTO>),
+    )
+)]
+#[instrument(skip(state, current_user, params), fields(user_id = %current_user.user_id))]
+pub async fn discover_chats(
+    State(state): State<Arc<AppState>>,
+    Extension(current_user): Extension<User>, // ottenuto dall'autenticazione JWT
+    Query(params): Query<DiscoverChatsQuery>,
+) -> Result<Json<Vec<PublicChatDTO>>, AppError> {
+    debug!("Discovering public chats");
+    // 1. Estrarre q, limit e offset dalla query string e validarli
+    // 2. Cercare le chat di gruppo pubbliche per titolo o descrizione (singola query con conteggio membri)
+    // 3. Ritornare la pagina di risultati, le chat più popolate per prime
+
+    params.validate()?;
+    let limit = params.limit.unwrap_or(20);
+    let offset = params.offset.unwrap_or(0);
+    let query = params.q.as_deref().map(str::trim).filter(|q| !q.is_empty());
+
+    let chats = state.chat.search_public(query, limit, offset).await?;
+
+    info!("Found {} public chats", chats.len());
+    Ok(Json(chats))
+}
+
+/// Crea una chat di gruppo o privata
+#[utoipa::path(
+    post,
+    path = "/chats",
+    tag = "chats",
+    params(
+        ("Idempotency-Key" = Option<String>, Header, description = "Chiave scelta dal client per ripetere la richiesta senza duplicarla"),
+    ),
+    request_body = CreateChatRequestDTO,
+    responses(
+        (status = 200, description = "Chat creata", body = ChatDTO),
+        (status = 400, description = "Dati non validi"),
+        (status = 409, description = "Chat privata già esistente"),
+    )
+)]
 #[instrument(skip(state, current_user, body), fields(user_id = %current_user.user_id, chat_type = ?body.chat_type))]
 pub async fn create_chat(
     State(state): State<Arc<AppState>>,
@@ -89,7 +230,7 @@ pub async fn create_chat(
     // 7. Creare ChatCreateDTO con title=None, description=None, chat_type=Private
     // 8. Salvare la chat nel database (la chiave primaria è autoincrementale)
     // 9. Creare metadata per entrambi gli utenti con ruolo Member e timestamp correnti (preparazione in memoria)
-    // 10. Salvare entrambi i metadata nel database in batch/transazione
+    // 10. Salvare entrambi i metadata nel database
     //
     // CASO ChatType::Group:
     // 1. Creare ChatCreateDTO con title e description dal body, chat_type=Group
@@ -97,11 +238,16 @@ pub async fn create_chat(
     // 3. Creare metadata per current_user con ruolo Owner e timestamp correnti
     // 4. Salvare il metadata nel database
     //
+    // Chat e metadata sono scritti nella stessa unit of work: se un inserimento fallisce non
+    // resta una chat senza membri.
+    //
     // FINALE:
-    // 1. Convertire la chat creata in ChatDTO (trasformazione in memoria)
-    // 2. Ritornare il ChatDTO come risposta JSON
+    // 1. Convertire la chat creata in ChatDTO, con la lista dei membri
+    // 2. Inviare il segnale ChatCreated a tutti i membri iniziali online
+    // 3. Ritornare il ChatDTO come risposta JSON
 
     let chat;
+    let mut uow;
     match body.chat_type {
         ChatType::Private => {
             debug!("Creating private chat");
@@ -146,7 +292,8 @@ pub async fn create_chat(
                 description: None,
                 chat_type: ChatType::Private,
             };
-            chat = state.chat.create(&new_chat).await?;
+            uow = state.begin().await?;
+            chat = state.chat.create_in(&mut uow, &new_chat).await?;
 
             debug!("Private chat created with id {}", chat.chat_id);
 
@@ -169,28 +316,15 @@ pub async fn create_chat(
                 messages_received_until: now,
             };
 
-            // Create both metadata in a single transaction for atomicity
             state
                 .meta
-                .create_many(&[metadata_current_user, metadata_second_user])
+                .create_many_in(&mut uow, &[metadata_current_user, metadata_second_user])
                 .await?;
 
             info!(
                 "Private chat created successfully between users {} and {}",
                 current_user.user_id, second_user_id
             );
-
-            // Notifica gli utenti online di aggiungere la chat al loro stream
-            info!("Attempting to send AddChat signal to user {}", current_user.user_id);
-            state.users_online.send_server_message_if_online(
-                &current_user.user_id,
-                crate::ws::usermap::InternalSignal::AddChat(chat.chat_id),
-            );
-            info!("Attempting to send AddChat signal to user {}", second_user_id);
-            state.users_online.send_server_message_if_online(
-                second_user_id,
-                crate::ws::usermap::InternalSignal::AddChat(chat.chat_id),
-            );
         }
 
         ChatType::Group => {
@@ -204,7 +338,11 @@ pub async fn create_chat(
             // Validazione con validator
             new_chat.validate()?;
 
-            chat = state.chat.create(&new_chat).await?;
+            // Il gruppo nasce con il solo Owner, che deve rientrare nel limite configurato
+            state.ensure_group_has_room(0)?;
+
+            uow = state.begin().await?;
+            chat = state.chat.create_in(&mut uow, &new_chat).await?;
 
             debug!("Group chat created with id {}", chat.chat_id);
 
@@ -218,31 +356,60 @@ pub async fn create_chat(
                 messages_received_until: now,
             };
 
-            state.meta.create(&metadata_owner).await?;
+            state.meta.create_in(&mut uow, &metadata_owner).await?;
 
             info!(
                 "Group chat '{}' created successfully by user {}",
                 chat.title.as_ref().unwrap_or(&String::from("Unnamed")),
                 current_user.user_id
             );
-
-            // Notifica l'utente online di aggiungere la chat al suo stream
-            state.users_online.send_server_message_if_online(
-                &current_user.user_id,
-                crate::ws::usermap::InternalSignal::AddChat(chat.chat_id),
-            );
         }
     }
+    uow.commit().await?;
 
     let mut chat_dto = ChatDTO::from(chat);
     
     // Popola user_list prima di restituire
-    let members = state.meta.find_many_by_chat_id(&chat_dto.chat_id.unwrap()).await?;
-    chat_dto.user_list = Some(members.into_iter().map(|m| m.user_id).collect());
+    let members = state
+        .meta
+        .read_many(
+            &MemberFilter::in_chat(chat_dto.chat_id.unwrap()).from_primary(),
+            &Page::ALL,
+        )
+        .await?;
+    chat_dto.user_list = Some(members.iter().map(|m| m.user_id).collect());
+
+    // Notifica i membri online, che aggiungono la chat al loro stream senza ricaricare la lista
+    for member in &members {
+        state
+            .users_online
+            .send_server_message_if_online(&member.user_id, InternalSignal::ChatCreated(chat_dto.clone()));
+    }
+
+    state.publish_event(DomainEvent::ChatCreated {
+        chat_id: chat_dto.chat_id.unwrap(),
+        chat_type: body.chat_type.clone(),
+        created_by: current_user.user_id,
+        members: members.iter().map(|m| m.user_id).collect(),
+        created_at: Utc::now(),
+    });
     
     Ok(Json(chat_dto))
 }
 
+/// Messaggi della chat, paginati per data
+#[utoipa::path(
+    get,
+    path = "/chats/{chat_id}/messages",
+    tag = "messages",
+    params(
+        ("chat_id" = i32, Path, description = "ID della chat"),
+        MessagesQuery,
+    ),
+    responses(
+        (status = 200, description = "Messaggi della chat", body = Vec<MessageDTO>),
+    )
+)]
 #[instrument(skip(state, metadata), fields(chat_id = %chat_id, user_id = %metadata.user_id))]
 pub async fn get_chat_messages(
     State(state): State<Arc<AppState>>,
@@ -265,19 +432,708 @@ pub async fn get_chat_messages(
         (None, 50)
     };
 
+    let filter = MessageFilter::History {
+        chat_id,
+        visible_from: metadata.messages_visible_from,
+        before: before_date,
+    };
+    let messages = state.msg.read_many(&filter, &Page::first(limit)).await?;
+
+    info!("Retrieved {} messages for chat", messages.len());
+
+    let messages_dto: Vec<MessageDTO> = messages.into_iter().map(MessageDTO::from).collect();
+
+    Ok(Json(messages_dto))
+}
+
+/// Long polling dei nuovi messaggi, alternativa al WebSocket
+#[utoipa::path(
+    get,
+    path = "/chats/{chat_id}/messages/poll",
+    tag = "messages",
+    params(
+        ("chat_id" = i32, Path, description = "ID della chat"),
+        MessagePollQuery,
+    ),
+    responses(
+        (
+            status = 200,
+            description = "Messaggi successivi ad after, vuoto allo scadere del timeout",
+            body = MissedMessagesDTO,
+        ),
+        (status = 400, description = "Parametri non validi"),
+    )
+)]
+#[instrument(skip(state, params, metadata), fields(chat_id = %chat_id, user_id = %metadata.user_id))]
+pub async fn poll_chat_messages(
+    State(state): State<Arc<AppState>>,
+    Path(chat_id): Path<i32>,
+    Query(params): Query<MessagePollQuery>,
+    Extension(metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware
+) -> Result<Json<MissedMessagesDTO>, AppError> {
+    debug!("Polling chat messages");
+    // 1. Estrarre chat_id dal path e i query parameters (after e timeout opzionale)
+    // 2. Validare i parametri
+    // 3. Se esistono messaggi successivi ad after, ritornarli subito
+    // 4. Altrimenti attendere un nuovo messaggio sul canale broadcast della chat, fino al timeout
+    // 5. Ritornare i messaggi (lista vuota se il timeout è scaduto) come risposta JSON
+
+    params.validate()?;
+
+    let wait = std::time::Duration::from_secs(params.timeout.unwrap_or(POLL_DEFAULT_TIMEOUT_SECS));
+    let messages = wait_for_messages(&state, &metadata, params.after, wait).await?;
+
+    info!("Poll returned {} messages", messages.messages.len());
+
+    Ok(Json(messages))
+}
+
+/// Ricerca full-text nei messaggi della chat
+#[utoipa::path(
+    get,
+    path = "/chats/{chat_id}/messages/search",
+    tag = "messages",
+    params(
+        ("chat_id" = i32, Path, description = "ID della chat"),
+        MessageSearchQuery,
+    ),
+    responses(
+        (
+            status = 200,
+            description = "Messaggi trovati con le occorrenze da evidenziare",
+            body = Vec<MessageSearchResultDTO>,
+        ),
+        (status = 400, description = "Parametri non validi"),
+    )
+)]
+#[instrument(skip(state, params, metadata), fields(chat_id = %chat_id, user_id = %metadata.user_id))]
+pub async fn search_chat_messages(
+    State(state): State<Arc<AppState>>,
+    Path(chat_id): Path<i32>,
+    Query(params): Query<MessageSearchQuery>,
+    Extension(metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware
+) -> Result<Json<Vec<MessageSearchResultDTO>>, AppError> {
+    debug!("Searching chat messages");
+    // 1. Estrarre chat_id dal path e i query parameters (q, before_date e limit opzionali)
+    // 2. Validare i parametri di ricerca
+    // 3. Cercare i messaggi tramite indice FULLTEXT, rispettando messages_visible_from
+    // 4. Calcolare per ogni risultato gli offset dei termini da evidenziare
+    // 5. Ritornare la lista dei risultati come risposta JSON
+
+    params.validate()?;
+
     let messages = state
         .msg
-        .find_many_paginated(
+        .search_in_chat(
             &chat_id,
+            &params.q,
             &metadata.messages_visible_from,
-            before_date.as_ref(),
-            limit,
+            params.before_date.as_ref(),
+            params.limit.unwrap_or(50),
         )
         .await?;
 
-    info!("Retrieved {} messages for chat", messages.len());
+    info!("Search returned {} messages", messages.len());
 
-    let messages_dto: Vec<MessageDTO> = messages.into_iter().map(MessageDTO::from).collect();
+    let results = messages
+        .into_iter()
+        .map(|m| MessageSearchResultDTO::new(m, &params.q))
+        .collect();
 
-    Ok(Json(messages_dto))
+    Ok(Json(results))
+}
+
+/// Modifica il contenuto di un proprio messaggio
+#[utoipa::path(
+    patch,
+    path = "/chats/{chat_id}/messages/{message_id}",
+    tag = "messages",
+    params(
+        ("chat_id" = i32, Path, description = "ID della chat"),
+        ("message_id" = i32, Path, description = "ID del messaggio"),
+    ),
+    request_body = UpdateMessageDTO,
+    responses(
+        (status = 200, description = "Messaggio modificato", body = MessageDTO),
+        (status = 400, description = "Contenuto non valido"),
+        (status = 403, description = "Messaggio di un altro utente"),
+        (status = 404, description = "Messaggio non trovato"),
+        (status = 409, description = "Messaggio eliminato"),
+    )
+)]
+#[instrument(skip(state, current_user, _metadata, body), fields(chat_id = %chat_id, message_id = %message_id, user_id = %current_user.user_id))]
+pub async fn edit_message(
+    State(state): State<Arc<AppState>>,
+    Path((chat_id, message_id)): Path<(i32, i32)>,
+    Extension(current_user): Extension<User>,
+    Extension(_metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware
+    Json(mut body): Json<UpdateMessageDTO>,
+) -> Result<Json<MessageDTO>, AppError> {
+    debug!("Editing message");
+    // 1. Estrarre chat_id e message_id dal path, nuovo contenuto dal body JSON
+    // 2. Validare il DTO (lunghezza del contenuto) e verificare che il contenuto sia presente
+    // 3. Recuperare il messaggio dal database, errore NOT_FOUND se non esiste o appartiene ad un'altra chat
+    // 4. Verificare che current_user sia l'autore del messaggio, che non sia eliminato e che non sia un messaggio di sistema
+    // 5. Se il messaggio è markdown, validare e sanificare il nuovo contenuto
+    // 6. Aggiornare il contenuto tramite il repository
+    // 7. Inviare l'evento MessageEdited a tutti i membri online della chat via ChatMap
+    // 8. Ritornare il MessageDTO aggiornato
+
+    body.validate()?;
+
+    if body.content.is_none() {
+        warn!("Edit attempted without content");
+        return Err(AppError::bad_request("Message content is required"));
+    }
+
+    let message = state
+        .msg
+        .read(&message_id)
+        .await?
+        .filter(|m| m.chat_id == chat_id)
+        .ok_or_else(|| {
+            warn!("Message {} not found in chat {}", message_id, chat_id);
+            AppError::not_found("Message not found")
+        })?;
+
+    if message.sender_id != current_user.user_id {
+        warn!("User attempted to edit a message of another user");
+        return Err(AppError::forbidden("You can only edit your own messages"));
+    }
+
+    if message.deleted_at.is_some() {
+        warn!("User attempted to edit a deleted message");
+        return Err(AppError::conflict("Deleted messages cannot be edited"));
+    }
+
+    if message.message_type == MessageType::SystemMessage {
+        warn!("User attempted to edit a system message");
+        return Err(AppError::forbidden("System messages cannot be edited"));
+    }
+
+    // il formato non cambia con la modifica, quindi il markdown passa dalla stessa sanificazione della creazione
+    if message.content_format == ContentFormat::Markdown {
+        if let Some(content) = body.content.as_deref() {
+            validate_markdown(content).map_err(|e| {
+                warn!("Unsafe markdown in edited message");
+                AppError::bad_request("Validation error").with_details(e.to_string())
+            })?;
+            body.content = Some(sanitize_markdown(content));
+        }
+    }
+
+    let updated = state.msg.update(&message_id, &body).await?;
+    let message_dto = MessageDTO::from(updated);
+
+    let _ = state.chats_online.send_event(
+        &chat_id,
+        ChatEvent::MessageEdited(Arc::new(message_dto.clone())),
+    );
+
+    info!("Message edited successfully");
+    Ok(Json(message_dto))
+}
+
+/// Cronologia delle modifiche di un messaggio, dalla versione più vecchia
+#[utoipa::path(
+    get,
+    path = "/chats/{chat_id}/messages/{message_id}/history",
+    tag = "messages",
+    params(
+        ("chat_id" = i32, Path, description = "ID della chat"),
+        ("message_id" = i32, Path, description = "ID del messaggio"),
+    ),
+    responses(
+        (status = 200, description = "Versioni precedenti del messaggio", body = Vec<MessageRevisionDTO>),
+        (status = 403, description = "Permessi insufficienti"),
+        (status = 404, description = "Messaggio non trovato"),
+    )
+)]
+#[instrument(skip(state, metadata), fields(chat_id = %chat_id, message_id = %message_id, user_id = %metadata.user_id))]
+pub async fn get_message_history(
+    State(state): State<Arc<AppState>>,
+    Path((chat_id, message_id)): Path<(i32, i32)>,
+    Extension(metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware
+) -> Result<Json<Vec<MessageRevisionDTO>>, AppError> {
+    debug!("Fetching message history");
+    // 1. Verificare che current_user sia Admin o Owner, altrimenti FORBIDDEN (fail-fast)
+    // 2. Recuperare il messaggio, errore NOT_FOUND se non esiste o appartiene ad un'altra chat
+    //    (anche se eliminato: la cronologia serve proprio per le contestazioni)
+    // 3. Recuperare le revisioni e ritornarle; il contenuto attuale è quello del messaggio
+
+    require_role(&metadata, &[UserRole::Admin, UserRole::Owner])?;
+
+    state
+        .msg
+        .read(&message_id)
+        .await?
+        .filter(|m| m.chat_id == chat_id)
+        .ok_or_else(|| {
+            warn!("Message {} not found in chat {}", message_id, chat_id);
+            AppError::not_found("Message not found")
+        })?;
+
+    let revisions = state.msg.find_revisions(&message_id).await?;
+
+    info!("Found {} revisions", revisions.len());
+    Ok(Json(
+        revisions
+            .into_iter()
+            .map(MessageRevisionDTO::from)
+            .collect(),
+    ))
+}
+
+/// Elimina un messaggio
+#[utoipa::path(
+    delete,
+    path = "/chats/{chat_id}/messages/{message_id}",
+    tag = "messages",
+    params(
+        ("chat_id" = i32, Path, description = "ID della chat"),
+        ("message_id" = i32, Path, description = "ID del messaggio"),
+    ),
+    responses(
+        (status = 200, description = "Messaggio eliminato", body = MessageDTO),
+        (status = 403, description = "Permessi insufficienti"),
+        (status = 404, description = "Messaggio non trovato"),
+    )
+)]
+#[instrument(skip(state, current_user, metadata), fields(chat_id = %chat_id, message_id = %message_id, user_id = %current_user.user_id))]
+pub async fn delete_message(
+    State(state): State<Arc<AppState>>,
+    Path((chat_id, message_id)): Path<(i32, i32)>,
+    Extension(current_user): Extension<User>,
+    Extension(metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware
+) -> Result<Json<MessageDTO>, AppError> {
+    debug!("Deleting message");
+    // 1. Estrarre chat_id e message_id dal path della URL
+    // 2. Recuperare il messaggio dal database, errore NOT_FOUND se non esiste o appartiene ad un'altra chat
+    // 3. Verificare che current_user sia l'autore del messaggio oppure abbia il permesso DeleteMessages
+    // 4. I messaggi di sistema non possono essere eliminati
+    // 5. Marcare il messaggio come eliminato (soft-delete, la riga resta nel database)
+    //    se il messaggio era di un altro utente, registrare l'eliminazione nell'audit log
+    // 6. Inviare l'evento MessageDeleted con il tombstone a tutti i membri online della chat
+    // 7. Ritornare il tombstone
+
+    let message = state
+        .msg
+        .read(&message_id)
+        .await?
+        .filter(|m| m.chat_id == chat_id)
+        .ok_or_else(|| {
+            warn!("Message {} not found in chat {}", message_id, chat_id);
+            AppError::not_found("Message not found")
+        })?;
+
+    if message.sender_id != current_user.user_id {
+        // solo chi ha il permesso DeleteMessages può eliminare messaggi altrui
+        require_permission(&state, &metadata, ChatPermission::DeleteMessages).await?;
+    }
+
+    if message.message_type == MessageType::SystemMessage {
+        warn!("User attempted to delete a system message");
+        return Err(AppError::forbidden("System messages cannot be deleted"));
+    }
+
+    let deleted = state.msg.soft_delete(&message_id).await?;
+
+    if message.sender_id != current_user.user_id {
+        audit::record(
+            &state,
+            chat_id,
+            current_user.user_id,
+            AuditAction::MessageDeleted,
+            Some(message.sender_id),
+            Some(json!({ "message_id": message_id })),
+        )
+        .await?;
+    }
+
+    let tombstone = MessageDTO::from(deleted);
+
+    let _ = state.chats_online.send_event(
+        &chat_id,
+        ChatEvent::MessageDeleted(Arc::new(tombstone.clone())),
+    );
+
+    info!("Message deleted successfully");
+    Ok(Json(tombstone))
+}
+
+/// Fissa un messaggio nella chat
+#[utoipa::path(
+    post,
+    path = "/chats/{chat_id}/messages/{message_id}/pin",
+    tag = "messages",
+    params(
+        ("chat_id" = i32, Path, description = "ID della chat"),
+        ("message_id" = i32, Path, description = "ID del messaggio"),
+    ),
+    responses(
+        (status = 200, description = "Messaggio fissato"),
+        (status = 403, description = "Permessi insufficienti"),
+        (status = 404, description = "Messaggio non trovato"),
+    )
+)]
+#[instrument(skip(state, current_user, metadata), fields(chat_id = %chat_id, message_id = %message_id, user_id = %current_user.user_id))]
+pub async fn pin_message(
+    State(state): State<Arc<AppState>>,
+    Path((chat_id, message_id)): Path<(i32, i32)>,
+    Extension(current_user): Extension<User>,
+    Extension(metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware
+) -> Result<(), AppError> {
+    debug!("Pinning message");
+    // 1. Verificare che current_user abbia il permesso PinMessages, altrimenti FORBIDDEN (fail-fast)
+    // 2. Recuperare il messaggio, errore NOT_FOUND se non esiste o appartiene ad un'altra chat
+    // 3. I messaggi eliminati non possono essere fissati
+    // 4. Salvare il pin (idempotente)
+    // 5. Se il pin è nuovo, inviare l'evento MessagePinned ai membri online della chat
+
+    require_permission(&state, &metadata, ChatPermission::PinMessages).await?;
+
+    let message = state
+        .msg
+        .read(&message_id)
+        .await?
+        .filter(|m| m.chat_id == chat_id)
+        .ok_or_else(|| {
+            warn!("Message {} not found in chat {}", message_id, chat_id);
+            AppError::not_found("Message not found")
+        })?;
+
+    if message.deleted_at.is_some() {
+        warn!("Attempted to pin a deleted message");
+        return Err(AppError::conflict("Deleted messages cannot be pinned"));
+    }
+
+    let pinned = state
+        .msg
+        .pin(&chat_id, &message_id, &current_user.user_id)
+        .await?;
+
+    if pinned {
+        let _ = state.chats_online.send_event(
+            &chat_id,
+            ChatEvent::MessagePinned(Arc::new(MessageDTO::from(message))),
+        );
+        info!("Message pinned successfully");
+    } else {
+        debug!("Message was already pinned");
+    }
+
+    Ok(())
+}
+
+/// Rimuove un messaggio da quelli fissati
+#[utoipa::path(
+    delete,
+    path = "/chats/{chat_id}/messages/{message_id}/pin",
+    tag = "messages",
+    params(
+        ("chat_id" = i32, Path, description = "ID della chat"),
+        ("message_id" = i32, Path, description = "ID del messaggio"),
+    ),
+    responses(
+        (status = 200, description = "Messaggio non più fissato"),
+        (status = 403, description = "Permessi insufficienti"),
+        (status = 404, description = "Messaggio non trovato"),
+    )
+)]
+#[instrument(skip(state, current_user, metadata), fields(chat_id = %chat_id, message_id = %message_id, user_id = %current_user.user_id))]
+pub async fn unpin_message(
+    State(state): State<Arc<AppState>>,
+    Path((chat_id, message_id)): Path<(i32, i32)>,
+    Extension(current_user): Extension<User>,
+    Extension(metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware
+) -> Result<(), AppError> {
+    debug!("Unpinning message");
+    // 1. Verificare che current_user abbia il permesso PinMessages, altrimenti FORBIDDEN (fail-fast)
+    // 2. Rimuovere il pin, errore NOT_FOUND se il messaggio non era fissato in questa chat
+    // 3. Inviare l'evento MessageUnpinned ai membri online della chat
+
+    require_permission(&state, &metadata, ChatPermission::PinMessages).await?;
+
+    if !state.msg.unpin(&chat_id, &message_id).await? {
+        warn!("Message {} is not pinned in chat {}", message_id, chat_id);
+        return Err(AppError::not_found("Message is not pinned"));
+    }
+
+    if let Some(message) = state.msg.read(&message_id).await? {
+        let _ = state.chats_online.send_event(
+            &chat_id,
+            ChatEvent::MessageUnpinned(Arc::new(MessageDTO::from(message))),
+        );
+    }
+
+    info!("Message unpinned successfully");
+    Ok(())
+}
+
+/// Messaggi fissati nella chat
+#[utoipa::path(
+    get,
+    path = "/chats/{chat_id}/pins",
+    tag = "messages",
+    params(("chat_id" = i32, Path, description = "ID della chat")),
+    responses(
+        (status = 200, description = "Messaggi fissati", body = Vec<MessageDTO>),
+    )
+)]
+#[instrument(skip(state, metadata), fields(chat_id = %chat_id, user_id = %metadata.user_id))]
+pub async fn list_pinned_messages(
+    State(state): State<Arc<AppState>>,
+    Path(chat_id): Path<i32>,
+    Extension(metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware
+) -> Result<Json<Vec<MessageDTO>>, AppError> {
+    debug!("Fetching pinned messages");
+    // 1. Recuperare i messaggi fissati visibili all'utente (rispettando messages_visible_from)
+    // 2. Convertire in MessageDTO e ritornare la lista
+
+    let messages = state
+        .msg
+        .find_pinned(&chat_id, &metadata.messages_visible_from)
+        .await?;
+
+    info!("Retrieved {} pinned messages for chat", messages.len());
+
+    Ok(Json(messages.into_iter().map(MessageDTO::from).collect()))
+}
+
+/// Carica l'immagine della chat
+#[utoipa::path(
+    put,
+    path = "/chats/{chat_id}/avatar",
+    tag = "chats",
+    params(("chat_id" = i32, Path, description = "ID della chat")),
+    request_body(content = crate::openapi::FileUpload, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Chat aggiornata", body = ChatDTO),
+        (status = 403, description = "Permessi insufficienti"),
+        (status = 413, description = "Immagine troppo grande"),
+        (status = 415, description = "Il file non è un'immagine"),
+    )
+)]
+#[instrument(skip(state, metadata, multipart), fields(chat_id = %chat_id, user_id = %metadata.user_id))]
+pub async fn update_chat_avatar(
+    State(state): State<Arc<AppState>>,
+    Path(chat_id): Path<i32>,
+    Extension(metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware
+    multipart: Multipart,
+) -> Result<Json<ChatDTO>, AppError> {
+    debug!("Updating chat avatar");
+    // 1. Verificare che current_user abbia il permesso EditChat, altrimenti FORBIDDEN (fail-fast)
+    // 2. Salvare l'immagine come allegato della chat (solo content type image/*)
+    // 3. Impostare l'allegato come avatar della chat e registrare la modifica nell'audit log
+    // 4. Inviare l'evento ChatUpdated ai membri online della chat
+    // 5. Ritornare la chat aggiornata
+
+    require_permission(&state, &metadata, ChatPermission::EditChat).await?;
+
+    let attachment = store_upload(
+        &state,
+        chat_id,
+        metadata.user_id,
+        multipart,
+        |content_type| content_type.starts_with("image/"),
+    )
+    .await?;
+
+    let chat = state
+        .chat
+        .set_avatar(&chat_id, &attachment.attachment_id)
+        .await?;
+
+    audit::record(
+        &state,
+        chat_id,
+        metadata.user_id,
+        AuditAction::ChatUpdated,
+        None,
+        Some(json!({ "avatar_attachment_id": attachment.attachment_id })),
+    )
+    .await?;
+    let chat_dto = ChatDTO::from(chat);
+
+    let _ = state
+        .chats_online
+        .send_event(&chat_id, ChatEvent::ChatUpdated(Arc::new(chat_dto.clone())));
+
+    info!("Chat avatar updated successfully");
+    Ok(Json(chat_dto))
+}
+
+/// Aggiorna titolo, descrizione e impostazioni della chat
+#[utoipa::path(
+    patch,
+    path = "/chats/{chat_id}",
+    tag = "chats",
+    params(("chat_id" = i32, Path, description = "ID della chat")),
+    request_body = UpdateChatDTO,
+    responses(
+        (status = 200, description = "Chat aggiornata", body = ChatDTO),
+        (status = 400, description = "Dati non validi"),
+        (status = 403, description = "Permessi insufficienti"),
+    )
+)]
+#[instrument(skip(state, metadata, body), fields(chat_id = %chat_id, user_id = %metadata.user_id))]
+pub async fn update_chat(
+    State(state): State<Arc<AppState>>,
+    Path(chat_id): Path<i32>,
+    Extension(metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware
+    Json(body): Json<UpdateChatDTO>,
+) -> Result<Json<ChatDTO>, AppError> {
+    debug!("Updating chat");
+    // 1. Validare il DTO (lunghezza di titolo e descrizione)
+    // 2. Verificare che current_user abbia il permesso EditChat, altrimenti FORBIDDEN
+    // 3. Aggiornare i campi presenti (titolo, descrizione, solo annunci, chi può invitare, filtro dei
+    //    contenuti) tramite il repository
+    //    e registrare la modifica nell'audit log
+    // 4. Inviare l'evento ChatUpdated ai membri online della chat
+    // 5. Ritornare la chat aggiornata
+
+    body.validate()?;
+
+    require_permission(&state, &metadata, ChatPermission::EditChat).await?;
+
+    let chat = state.chat.update(&chat_id, &body).await?;
+
+    audit::record(
+        &state,
+        chat_id,
+        metadata.user_id,
+        AuditAction::ChatUpdated,
+        None,
+        serde_json::to_value(&body).ok(),
+    )
+    .await?;
+
+    let chat_dto = ChatDTO::from(chat);
+
+    let _ = state
+        .chats_online
+        .send_event(&chat_id, ChatEvent::ChatUpdated(Arc::new(chat_dto.clone())));
+
+    info!("Chat updated successfully");
+    Ok(Json(chat_dto))
+}
+
+/// Elimina la chat
+#[utoipa::path(
+    delete,
+    path = "/chats/{chat_id}",
+    tag = "chats",
+    params(("chat_id" = i32, Path, description = "ID della chat")),
+    responses(
+        (status = 200, description = "Chat eliminata"),
+        (status = 403, description = "Solo il proprietario può eliminare la chat"),
+    )
+)]
+#[instrument(skip(state, metadata), fields(chat_id = %chat_id, user_id = %metadata.user_id))]
+pub async fn delete_chat(
+    State(state): State<Arc<AppState>>,
+    Path(chat_id): Path<i32>,
+    Extension(metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware
+) -> Result<(), AppError> {
+    debug!("Deleting chat");
+    // 1. Verificare che current_user sia l'Owner della chat, altrimenti FORBIDDEN
+    // 2. Recuperare i membri prima della cancellazione, servono per le notifiche
+    // 3. Cancellare la chat: ON DELETE CASCADE elimina metadata, messaggi, inviti e allegati,
+    //    i file degli allegati vengono poi rimossi dallo storage
+    // 4. Rimuovere il channel broadcast della chat dalla ChatMap
+    // 5. Inviare il segnale ChatDeleted a tutti i membri online
+
+    require_role(&metadata, &[UserRole::Owner])?;
+
+    remove_chat(&state, chat_id).await?;
+
+    Ok(())
+}
+
+/// Cancella la chat e invia il segnale ChatDeleted ai membri online
+/// (usata anche dall'amministrazione del server)
+pub(crate) async fn remove_chat(state: &AppState, chat_id: i32) -> Result<(), AppError> {
+    let members = state
+        .meta
+        .read_many(&MemberFilter::in_chat(chat_id).from_primary(), &Page::ALL)
+        .await?;
+    // le righe degli allegati spariscono con la chat, i file vanno rimossi a parte
+    let storage_keys = state.attachment.find_storage_keys_by_chat(&chat_id).await?;
+
+    state.chat.delete(&chat_id).await?;
+    state.invalidate_chat_members(chat_id).await;
+    remove_stored_files(state, &storage_keys).await;
+
+    state.chats_online.remove_chat(&chat_id);
+
+    for member in &members {
+        state
+            .users_online
+            .send_server_message_if_online(&member.user_id, InternalSignal::ChatDeleted(chat_id));
+    }
+
+    info!("Chat deleted, notified {} members", members.len());
+    Ok(())
+}
+
+/// Fissa la chat in cima alla lista dell'utente
+#[utoipa::path(
+    post,
+    path = "/chats/{chat_id}/pin",
+    tag = "chats",
+    params(("chat_id" = i32, Path, description = "ID della chat")),
+    responses(
+        (status = 200, description = "Chat fissata"),
+    )
+)]
+#[instrument(skip(state, metadata), fields(chat_id = %chat_id, user_id = %metadata.user_id))]
+pub async fn pin_chat(
+    State(state): State<Arc<AppState>>,
+    Path(chat_id): Path<i32>,
+    Extension(metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware
+) -> Result<(), AppError> {
+    debug!("Pinning chat");
+    // 1. Se la chat è già fissata non fare nulla, così l'ordine non cambia (idempotente)
+    // 2. Altrimenti salvare pinned_at = adesso: la chat va in cima alle fissate
+
+    if metadata.pinned_at.is_some() {
+        debug!("Chat was already pinned");
+        return Ok(());
+    }
+
+    state
+        .meta
+        .set_pinned(&metadata.user_id, &chat_id, Some(Utc::now()))
+        .await?;
+
+    info!("Chat pinned successfully");
+    Ok(())
+}
+
+/// Rimuove la chat da quelle fissate
+#[utoipa::path(
+    delete,
+    path = "/chats/{chat_id}/pin",
+    tag = "chats",
+    params(("chat_id" = i32, Path, description = "ID della chat")),
+    responses(
+        (status = 200, description = "Chat non più fissata"),
+    )
+)]
+#[instrument(skip(state, metadata), fields(chat_id = %chat_id, user_id = %metadata.user_id))]
+pub async fn unpin_chat(
+    State(state): State<Arc<AppState>>,
+    Path(chat_id): Path<i32>,
+    Extension(metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware
+) -> Result<(), AppError> {
+    debug!("Unpinning chat");
+    // 1. Azzerare pinned_at, la chat torna nell'ordine normale (idempotente)
+
+    state
+        .meta
+        .set_pinned(&metadata.user_id, &chat_id, None)
+        .await?;
+
+    info!("Chat unpinned successfully");
+    Ok(())
 }
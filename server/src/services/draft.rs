@@ -0,0 +1,82 @@
+//! Draft services - Bozze dei messaggi sincronizzate tra dispositivi
+
+use crate::core::{AppError, AppState};
+use crate::dtos::{DraftDTO, UpsertDraftDTO};
+use crate::entities::User;
+use crate::repositories::{Delete, Read};
+use axum::{
+    Extension,
+    extract::{Json, Path, State},
+};
+use std::sync::Arc;
+use tracing::{debug, info, instrument};
+use validator::Validate;
+
+/// Bozza del messaggio salvata per la chat, null se assente
+#[utoipa::path(
+    get,
+    path = "/chats/{chat_id}/draft",
+    tag = "chats",
+    params(("chat_id" = i32, Path, description = "ID della chat")),
+    responses(
+        (status = 200, description = "Bozza o null", body = Option<DraftDTO>),
+    )
+)]
+#[instrument(skip(state, current_user), fields(chat_id = %chat_id, user_id = %current_user.user_id))]
+pub async fn get_draft(
+    State(state): State<Arc<AppState>>,
+    Path(chat_id): Path<i32>,
+    Extension(current_user): Extension<User>, // la membership è già verificata dal chat_membership_middleware
+) -> Result<Json<Option<DraftDTO>>, AppError> {
+    debug!("Fetching draft");
+    // 1. Recuperare la bozza dell'utente per la chat, null se non esiste
+
+    let draft = state
+        .draft
+        .read(&(current_user.user_id, chat_id))
+        .await?
+        .map(DraftDTO::from);
+
+    Ok(Json(draft))
+}
+
+/// Salva la bozza del messaggio, un contenuto vuoto la elimina
+#[utoipa::path(
+    put,
+    path = "/chats/{chat_id}/draft",
+    tag = "chats",
+    params(("chat_id" = i32, Path, description = "ID della chat")),
+    request_body = UpsertDraftDTO,
+    responses(
+        (status = 200, description = "Bozza salvata, null se eliminata", body = Option<DraftDTO>),
+        (status = 400, description = "Dati non validi"),
+    )
+)]
+#[instrument(skip(state, current_user, body), fields(chat_id = %chat_id, user_id = %current_user.user_id))]
+pub async fn save_draft(
+    State(state): State<Arc<AppState>>,
+    Path(chat_id): Path<i32>,
+    Extension(current_user): Extension<User>, // la membership è già verificata dal chat_membership_middleware
+    Json(body): Json<UpsertDraftDTO>,
+) -> Result<Json<Option<DraftDTO>>, AppError> {
+    debug!("Saving draft");
+    // 1. Validare il contenuto
+    // 2. Se il contenuto è vuoto eliminare la bozza e ritornare null
+    // 3. Altrimenti salvare la bozza (sovrascrivendo la precedente) e ritornarla
+
+    body.validate()?;
+
+    if body.content.trim().is_empty() {
+        state.draft.delete(&(current_user.user_id, chat_id)).await?;
+        info!("Draft cleared");
+        return Ok(Json(None));
+    }
+
+    let draft = state
+        .draft
+        .upsert(&current_user.user_id, &chat_id, &body.content)
+        .await?;
+
+    info!("Draft saved");
+    Ok(Json(Some(DraftDTO::from(draft))))
+}
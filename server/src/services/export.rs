@@ -0,0 +1,436 @@
+//! Export services - Esportazione della cronologia di una chat e dei dati personali
+
+use crate::core::{AppError, AppState, require_role};
+use crate::dtos::{
+    ChatDTO, ChatExportRecord, CreateDataExportDTO, DataExportDTO, InvitationDTO, MessageDTO,
+    PrivacySettingsDTO, UserDTO, UserDataArchiveDTO, UserInChatDTO,
+};
+use crate::entities::{DataExport, DataExportStatus, Message, User, UserChatMetadata, UserRole};
+use crate::repositories::{Create, InvitationFilter, MemberFilter, MessageFilter, Page, ReadMany};
+use crate::services::membership::load_chat_members;
+use axum::{
+    Extension,
+    body::Body,
+    extract::{Json, Path, State},
+    http::{StatusCode, header},
+    response::IntoResponse,
+};
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use futures::stream::BoxStream;
+use object_store::{PutPayload, path::Path as StoragePath};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{debug, error, info, instrument, warn};
+
+/// Righe NDJSON in attesa di essere scritte sul socket: se il client legge lentamente
+/// la lettura dal database si ferma, così la memoria resta limitata
+const EXPORT_CHANNEL_CAPACITY: usize = 64;
+
+/// Esporta la cronologia della chat in formato NDJSON
+#[utoipa::path(
+    get,
+    path = "/chats/{chat_id}/export",
+    tag = "chats",
+    params(("chat_id" = i32, Path, description = "ID della chat")),
+    responses(
+        (
+            status = 200,
+            description = "Una riga JSON per la chat e una per ogni messaggio",
+            body = String,
+            content_type = "application/x-ndjson",
+        ),
+        (status = 403, description = "Permessi insufficienti"),
+    )
+)]
+#[instrument(skip(state, metadata), fields(chat_id = %chat_id, user_id = %metadata.user_id))]
+pub async fn export_chat(
+    State(state): State<Arc<AppState>>,
+    Path(chat_id): Path<i32>,
+    Extension(metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware
+) -> Result<impl IntoResponse, AppError> {
+    debug!("Exporting chat");
+    // 1. Verificare che current_user sia l'Owner della chat, altrimenti FORBIDDEN
+    // 2. Recuperare la chat e i suoi membri (prima di iniziare lo stream, così gli errori sono HTTP)
+    // 3. Ritornare il body in streaming con il content type NDJSON: chat, membri e poi i
+    //    messaggi letti in streaming dal database (rispettando messages_visible_from)
+
+    require_role(&metadata, &[UserRole::Owner])?;
+
+    let chat = state.chat.read(&chat_id).await?.ok_or_else(|| {
+        warn!("Chat {} not found", chat_id);
+        AppError::not_found("Chat not found")
+    })?;
+    let members = load_chat_members(&state, &chat_id).await?;
+
+    let mut header_records = vec![ChatExportRecord::Chat(ChatDTO::from(chat))];
+    header_records.extend(members.into_iter().map(ChatExportRecord::Member));
+
+    let source = ExportSource::Chat {
+        chat_id,
+        visible_from: metadata.messages_visible_from,
+    };
+    Ok(ndjson_export(
+        state,
+        format!("chat-{}.ndjson", chat_id),
+        header_records,
+        source,
+    ))
+}
+
+/// Messaggi letti in streaming dal database per un export NDJSON
+pub(crate) enum ExportSource {
+    /// Messaggi di una chat creati da `visible_from` in poi
+    Chat {
+        chat_id: i32,
+        visible_from: DateTime<Utc>,
+    },
+    /// Messaggi scritti da un utente in tutte le chat
+    Sender(i32),
+}
+
+impl ExportSource {
+    fn stream<'a>(&'a self, state: &'a AppState) -> BoxStream<'a, Result<Message, sqlx::Error>> {
+        match self {
+            Self::Chat {
+                chat_id,
+                visible_from,
+            } => state.msg.stream_by_chat(chat_id, visible_from),
+            Self::Sender(sender_id) => state.msg.stream_by_sender_id(sender_id),
+        }
+    }
+}
+
+/// Risposta NDJSON in streaming: prima i record di intestazione, poi un record per ogni
+/// messaggio della sorgente, senza mai caricare l'intera cronologia in memoria.
+/// Le righe passano da un task su un canale limitato (`EXPORT_CHANNEL_CAPACITY`)
+pub(crate) fn ndjson_export(
+    state: Arc<AppState>,
+    filename: String,
+    header_records: Vec<ChatExportRecord>,
+    source: ExportSource,
+) -> impl IntoResponse {
+    let (tx, rx) = mpsc::channel::<Result<String, std::io::Error>>(EXPORT_CHANNEL_CAPACITY);
+
+    tokio::spawn(async move {
+        for record in &header_records {
+            if tx.send(to_ndjson_line(record)).await.is_err() {
+                return;
+            }
+        }
+
+        let mut exported = 0usize;
+        let mut messages = source.stream(&state);
+        while let Some(row) = messages.next().await {
+            let line = match row {
+                Ok(message) => {
+                    exported += 1;
+                    to_ndjson_line(&ChatExportRecord::Message(MessageDTO::from(message)))
+                }
+                Err(e) => {
+                    // lo stream viene interrotto: il client riceve una risposta troncata
+                    error!("Failed to read messages during export: {:?}", e);
+                    Err(std::io::Error::other(e))
+                }
+            };
+            let failed = line.is_err();
+            if tx.send(line).await.is_err() {
+                warn!("Client disconnected during export");
+                return;
+            }
+            if failed {
+                return;
+            }
+        }
+
+        info!(messages = exported, "Export completed");
+    });
+
+    (
+        [
+            (header::CONTENT_TYPE, "application/x-ndjson".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        Body::from_stream(ReceiverStream::new(rx)),
+    )
+}
+
+/// Serializza un record come singola riga NDJSON
+fn to_ndjson_line(record: &ChatExportRecord) -> Result<String, std::io::Error> {
+    let mut line = serde_json::to_string(record).map_err(std::io::Error::other)?;
+    line.push('\n');
+    Ok(line)
+}
+
+/// Avvia l'esportazione dei dati personali dell'utente autenticato
+#[utoipa::path(
+    post,
+    path = "/users/me/export",
+    tag = "users",
+    responses(
+        (
+            status = 202,
+            description = "Esportazione in preparazione (o già in corso)",
+            body = DataExportDTO,
+        ),
+    )
+)]
+#[instrument(skip(state, current_user), fields(user_id = %current_user.user_id))]
+pub async fn request_data_export(
+    State(state): State<Arc<AppState>>,
+    Extension(current_user): Extension<User>, // ottenuto dall'autenticazione JWT
+) -> Result<impl IntoResponse, AppError> {
+    debug!("Requesting personal data export");
+    // 1. Se c'è già un export in corso ritornarlo, senza avviarne un altro
+    // 2. Eliminare dallo storage l'archivio dell'export precedente, se presente
+    // 3. Creare il job PENDING nel database
+    // 4. Avviare il task in background che genera l'archivio
+    // 5. Ritornare ACCEPTED con lo stato del job, da interrogare con GET /users/me/export
+
+    let previous = state
+        .data_export
+        .find_latest_by_user_id(&current_user.user_id)
+        .await?;
+
+    if let Some(previous) = previous {
+        if previous.state == DataExportStatus::Pending {
+            info!("Data export {} already in progress", previous.export_id);
+            return Ok((StatusCode::ACCEPTED, Json(DataExportDTO::from(previous))));
+        }
+        if let Some(key) = previous.storage_key {
+            // best effort: un archivio rimasto sullo storage non blocca la nuova richiesta
+            if let Err(e) = state.storage.delete(&StoragePath::from(key.as_str())).await {
+                warn!("Failed to delete previous export archive: {}", e);
+            }
+        }
+    }
+
+    let export = state
+        .data_export
+        .create(&CreateDataExportDTO {
+            user_id: current_user.user_id,
+        })
+        .await?;
+
+    tokio::spawn(run_data_export(state.clone(), export.clone()));
+
+    info!("Data export {} started", export.export_id);
+    Ok((StatusCode::ACCEPTED, Json(DataExportDTO::from(export))))
+}
+
+/// Stato dell'ultima esportazione dei dati personali
+#[utoipa::path(
+    get,
+    path = "/users/me/export",
+    tag = "users",
+    responses(
+        (status = 200, description = "Stato dell'esportazione", body = DataExportDTO),
+        (status = 404, description = "Nessuna esportazione richiesta"),
+    )
+)]
+#[instrument(skip(state, current_user), fields(user_id = %current_user.user_id))]
+pub async fn get_data_export_status(
+    State(state): State<Arc<AppState>>,
+    Extension(current_user): Extension<User>, // ottenuto dall'autenticazione JWT
+) -> Result<Json<DataExportDTO>, AppError> {
+    debug!("Fetching personal data export status");
+    // 1. Recuperare l'ultimo export richiesto dall'utente, NOT_FOUND se non ne ha mai richiesti
+    // 2. Ritornarne lo stato (con il link di download se completato)
+
+    let export = state
+        .data_export
+        .find_latest_by_user_id(&current_user.user_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("No data export requested"))?;
+
+    Ok(Json(DataExportDTO::from(export)))
+}
+
+/// Scarica l'archivio dei dati personali come file JSON
+#[utoipa::path(
+    get,
+    path = "/users/me/export/download",
+    tag = "users",
+    responses(
+        (status = 200, description = "Archivio dei dati personali", body = UserDataArchiveDTO),
+        (status = 404, description = "Nessuna esportazione richiesta"),
+        (status = 409, description = "Esportazione non ancora pronta"),
+    )
+)]
+#[instrument(skip(state, current_user), fields(user_id = %current_user.user_id))]
+pub async fn download_data_export(
+    State(state): State<Arc<AppState>>,
+    Extension(current_user): Extension<User>, // ottenuto dall'autenticazione JWT
+) -> Result<impl IntoResponse, AppError> {
+    debug!("Downloading personal data export");
+    // 1. Recuperare l'ultimo export dell'utente, NOT_FOUND se non esiste
+    // 2. Verificare che sia completato, altrimenti CONFLICT
+    // 3. Leggere l'archivio dallo storage e ritornarlo come allegato JSON
+
+    let export = state
+        .data_export
+        .find_latest_by_user_id(&current_user.user_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("No data export requested"))?;
+
+    let storage_key = match (&export.state, export.storage_key) {
+        (DataExportStatus::Completed, Some(key)) => key,
+        (export_state, _) => {
+            warn!(
+                "Data export {} is not ready: {:?}",
+                export.export_id, export_state
+            );
+            return Err(AppError::conflict("Data export is not available")
+                .with_details(format!("Data export is {:?}", export_state)));
+        }
+    };
+
+    let data = state
+        .storage
+        .get(&StoragePath::from(storage_key.as_str()))
+        .await
+        .map_err(|e| {
+            error!("Failed to read export archive from storage: {}", e);
+            AppError::internal_server_error("Failed to read data export")
+        })?
+        .bytes()
+        .await
+        .map_err(|e| {
+            error!("Failed to read export archive from storage: {}", e);
+            AppError::internal_server_error("Failed to read data export")
+        })?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/json".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"user-{}-export.json\"",
+                    current_user.user_id
+                ),
+            ),
+        ],
+        data,
+    ))
+}
+
+/// Job in background: genera l'archivio, lo salva sullo storage e aggiorna lo stato del job
+#[instrument(skip(state, export), fields(export_id = %export.export_id, user_id = %export.user_id))]
+async fn run_data_export(state: Arc<AppState>, export: DataExport) {
+    let result = match build_user_archive(&state, &export.user_id).await {
+        Ok(archive) => serde_json::to_vec_pretty(&archive).map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+
+    let outcome = match result {
+        Ok(data) => {
+            let storage_key = format!("exports/{}/{}.json", export.user_id, uuid::Uuid::new_v4());
+            match state
+                .storage
+                .put(
+                    &StoragePath::from(storage_key.as_str()),
+                    PutPayload::from(data),
+                )
+                .await
+            {
+                Ok(_) => {
+                    state
+                        .data_export
+                        .complete(&export.export_id, &storage_key)
+                        .await
+                }
+                Err(e) => {
+                    error!("Failed to store export archive: {}", e);
+                    state.data_export.fail(&export.export_id).await
+                }
+            }
+        }
+        Err(e) => {
+            error!("Failed to build export archive: {}", e);
+            state.data_export.fail(&export.export_id).await
+        }
+    };
+
+    match outcome {
+        Ok(()) => info!("Data export job finished"),
+        Err(e) => error!("Failed to update data export job: {:?}", e),
+    }
+}
+
+/// Raccoglie profilo, membership, messaggi e inviti dell'utente
+async fn build_user_archive(
+    state: &AppState,
+    user_id: &i32,
+) -> Result<UserDataArchiveDTO, sqlx::Error> {
+    let user = state
+        .user
+        .read(user_id)
+        .await?
+        .ok_or_else(|| sqlx::Error::RowNotFound)?;
+
+    let metadata = state
+        .meta
+        .read_many(&MemberFilter::of_user(*user_id).from_primary(), &Page::ALL)
+        .await?;
+    let mut chats = Vec::with_capacity(metadata.len());
+    for meta in &metadata {
+        if let Some(chat) = state.chat.read(&meta.chat_id).await? {
+            chats.push(ChatDTO::from(chat));
+        }
+    }
+
+    let messages = state
+        .msg
+        .read_many(
+            &MessageFilter::Sender {
+                sender_id: *user_id,
+            },
+            &Page::ALL,
+        )
+        .await?;
+    let received = state
+        .invitation
+        .read_many(
+            &InvitationFilter {
+                invited_id: Some(*user_id),
+                ..InvitationFilter::default()
+            },
+            &Page::ALL,
+        )
+        .await?;
+    let sent = state
+        .invitation
+        .read_many(
+            &InvitationFilter {
+                inviter_id: Some(*user_id),
+                ..InvitationFilter::default()
+            },
+            &Page::ALL,
+        )
+        .await?;
+
+    let privacy = PrivacySettingsDTO {
+        presence_visibility: user.presence_visibility,
+    };
+    // l'archivio è per l'utente stesso: il last_seen va incluso qualunque sia la privacy
+    let last_seen = user.last_seen;
+
+    Ok(UserDataArchiveDTO {
+        generated_at: Utc::now(),
+        profile: UserDTO {
+            last_seen,
+            ..UserDTO::from(user)
+        },
+        privacy,
+        chats,
+        memberships: metadata.into_iter().map(UserInChatDTO::from).collect(),
+        messages: messages.into_iter().map(MessageDTO::from).collect(),
+        invitations_received: received.into_iter().map(InvitationDTO::from).collect(),
+        invitations_sent: sent.into_iter().map(InvitationDTO::from).collect(),
+    })
+}
@@ -0,0 +1,115 @@
+//! Health services - Sonde di liveness e readiness per l'orchestratore (es. Kubernetes)
+
+use crate::core::AppState;
+use crate::dtos::{HealthCheckDTO, HealthDTO, HealthStatus};
+use axum::{
+    extract::{Json, State},
+    http::StatusCode,
+};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{instrument, warn};
+
+/// Tempo massimo di ogni controllo: le sonde hanno un timeout breve e un database bloccato
+/// deve risultare non pronto, non far scadere la richiesta
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Il processo è avviato e risponde alle richieste HTTP
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "health",
+    responses(
+        (status = 200, description = "Processo attivo", body = HealthDTO),
+    ),
+    security(())
+)]
+pub async fn healthz(State(state): State<Arc<AppState>>) -> Json<HealthDTO> {
+    Json(HealthDTO::ok(state.profile.as_str()))
+}
+
+/// Liveness: non dipende dal database, perché riavviare il processo non risolverebbe
+/// un database irraggiungibile
+#[utoipa::path(
+    get,
+    path = "/livez",
+    tag = "health",
+    responses(
+        (status = 200, description = "Processo attivo", body = HealthDTO),
+    ),
+    security(())
+)]
+pub async fn livez(State(state): State<Arc<AppState>>) -> Json<HealthDTO> {
+    Json(HealthDTO::ok(state.profile.as_str()))
+}
+
+/// Readiness: il server può ricevere traffico solo se il database risponde e ha applicato
+/// tutte le migrazioni incluse nel binario
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "health",
+    responses(
+        (status = 200, description = "Pronto a ricevere traffico", body = HealthDTO),
+        (
+            status = 503,
+            description = "Database irraggiungibile o migrazioni mancanti",
+            body = HealthDTO,
+        ),
+    ),
+    security(())
+)]
+#[instrument(skip(state))]
+pub async fn readyz(State(state): State<Arc<AppState>>) -> (StatusCode, Json<HealthDTO>) {
+    // 1. Eseguire i controlli in parallelo, ciascuno con il proprio timeout
+    let (database, migrations) = tokio::join!(
+        run_check("database", async {
+            state.health.ping().await.map_err(|e| {
+                warn!("Database ping failed: {}", e);
+                "database unreachable".to_string()
+            })
+        }),
+        run_check("migrations", async {
+            match state.health.pending_migrations().await {
+                Ok(pending) if pending.is_empty() => Ok(()),
+                Ok(pending) => Err(format!("pending migrations: {:?}", pending)),
+                Err(e) => {
+                    warn!("Failed to read applied migrations: {}", e);
+                    Err("applied migrations unavailable".to_string())
+                }
+            }
+        }),
+    );
+
+    // 2. Rispondere 503 se almeno un controllo è fallito, così l'orchestratore non invia traffico
+    let health = HealthDTO::from_checks(state.profile.as_str(), vec![database, migrations]);
+    let status = match health.status {
+        HealthStatus::Ok => StatusCode::OK,
+        HealthStatus::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+    };
+
+    (status, Json(health))
+}
+
+/// Esegue un controllo della readiness misurandone la durata
+async fn run_check<F>(name: &str, check: F) -> HealthCheckDTO
+where
+    F: Future<Output = Result<(), String>>,
+{
+    let start = Instant::now();
+    let result = tokio::time::timeout(CHECK_TIMEOUT, check)
+        .await
+        .unwrap_or_else(|_| Err(format!("timed out after {}s", CHECK_TIMEOUT.as_secs())));
+
+    HealthCheckDTO {
+        name: name.to_string(),
+        status: if result.is_ok() {
+            HealthStatus::Ok
+        } else {
+            HealthStatus::Unavailable
+        },
+        duration_ms: start.elapsed().as_millis() as u64,
+        error: result.err(),
+    }
+}
@@ -0,0 +1,295 @@
+//! Invite link services - Link di invito alle chat di gruppo
+//!
+//! Chi può invitare secondo la `invite_policy` della chat crea un link con un token segreto.
+//! Qualsiasi utente autenticato che conosce il token entra nella chat con
+//! POST /invite_links/{invite_token}/join, passando per gli stessi controlli degli inviti:
+//! ban, limite di membri e policy della chat.
+
+use crate::core::{
+    AppError, AppState, generate_refresh_token, has_permission, hash_refresh_token,
+    require_permission,
+};
+use crate::dtos::{ChatDTO, CreateInviteLinkDTO, InviteLinkDTO, NewInviteLinkDTO};
+use crate::entities::{
+    AuditAction, ChatPermission, ChatType, InviteLink, InvitePolicy, User, UserChatMetadata,
+};
+use crate::repositories::{Create, Delete, InviteLinkFilter, Page, Read, ReadMany};
+use crate::services::audit;
+use crate::services::join_request::{admit_member, announce_member};
+use axum::{
+    Extension,
+    extract::{Json, Path, State},
+    http::StatusCode,
+};
+use chrono::{Duration, Utc};
+use serde_json::json;
+use std::sync::Arc;
+use tracing::{debug, info, instrument, warn};
+use validator::Validate;
+
+/// Link di invito della chat
+#[utoipa::path(
+    get,
+    path = "/chats/{chat_id}/invite_links",
+    tag = "members",
+    params(("chat_id" = i32, Path, description = "ID della chat")),
+    responses(
+        (status = 200, description = "Link di invito della chat", body = Vec<InviteLinkDTO>),
+        (status = 403, description = "Permessi insufficienti"),
+    )
+)]
+#[instrument(skip(state, metadata), fields(chat_id = %chat_id, user_id = %metadata.user_id))]
+pub async fn list_invite_links(
+    State(state): State<Arc<AppState>>,
+    Path(chat_id): Path<i32>,
+    Extension(metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware
+) -> Result<Json<Vec<InviteLinkDTO>>, AppError> {
+    debug!("Listing chat invite links");
+    // 1. Verificare che current_user abbia il permesso InviteMembers, altrimenti FORBIDDEN
+    // 2. Recuperare i link della chat (i token non sono mai più mostrati)
+
+    require_permission(&state, &metadata, ChatPermission::InviteMembers).await?;
+
+    let links = state
+        .invite_link
+        .read_many(&InviteLinkFilter { chat_id }, &Page::ALL)
+        .await?;
+
+    Ok(Json(links.into_iter().map(InviteLinkDTO::from).collect()))
+}
+
+/// Crea un link di invito
+#[utoipa::path(
+    post,
+    path = "/chats/{chat_id}/invite_links",
+    tag = "members",
+    params(("chat_id" = i32, Path, description = "ID della chat")),
+    request_body = Option<CreateInviteLinkDTO>,
+    responses(
+        (status = 200, description = "Link creato, con il token in chiaro", body = InviteLinkDTO),
+        (status = 400, description = "Chat privata o scadenza non valida"),
+        (status = 403, description = "Permessi insufficienti"),
+    )
+)]
+#[instrument(skip(state, metadata, body), fields(chat_id = %chat_id, user_id = %metadata.user_id))]
+pub async fn create_invite_link(
+    State(state): State<Arc<AppState>>,
+    Path(chat_id): Path<i32>,
+    Extension(metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware
+    // body facoltativo con la validità del link
+    body: Option<Json<CreateInviteLinkDTO>>,
+) -> Result<Json<InviteLinkDTO>, AppError> {
+    debug!("Creating invite link");
+    // 1. Validare la scadenza se presente
+    // 2. Verificare che la chat sia un gruppo, altrimenti BAD_REQUEST
+    // 3. Se la invite_policy è AdminsOnly verificare che current_user abbia il permesso
+    //    InviteMembers, altrimenti FORBIDDEN
+    // 4. Generare il token, salvarne l'hash e registrare la creazione nell'audit log
+    // 5. Ritornare il link con il token in chiaro (unica volta in cui viene mostrato)
+
+    let Json(body) = body.unwrap_or_default();
+    body.validate()?;
+
+    let chat = state.chat.read(&chat_id).await?.ok_or_else(|| {
+        warn!("Chat {} not found", chat_id);
+        AppError::not_found("Chat not found")
+    })?;
+    if chat.chat_type != ChatType::Group {
+        warn!("Attempted to create an invite link for a private chat");
+        return Err(AppError::bad_request(
+            "Invite links can only be created for group chats",
+        ));
+    }
+
+    if chat.invite_policy == InvitePolicy::AdminsOnly {
+        require_permission(&state, &metadata, ChatPermission::InviteMembers).await?;
+    }
+
+    let token = generate_refresh_token();
+    let link = state
+        .invite_link
+        .create(&NewInviteLinkDTO {
+            chat_id,
+            token_hash: hash_refresh_token(&token),
+            created_by: metadata.user_id,
+            expires_at: body
+                .expires_in_hours
+                .map(|hours| Utc::now() + Duration::hours(hours)),
+        })
+        .await?;
+
+    audit::record(
+        &state,
+        chat_id,
+        metadata.user_id,
+        AuditAction::InviteLinkCreated,
+        None,
+        Some(json!({ "link_id": link.link_id, "expires_at": link.expires_at })),
+    )
+    .await?;
+
+    info!("Invite link {} created", link.link_id);
+    Ok(Json(InviteLinkDTO {
+        token: Some(token),
+        ..InviteLinkDTO::from(link)
+    }))
+}
+
+/// Revoca un link di invito
+#[utoipa::path(
+    delete,
+    path = "/chats/{chat_id}/invite_links/{link_id}",
+    tag = "members",
+    params(
+        ("chat_id" = i32, Path, description = "ID della chat"),
+        ("link_id" = i32, Path, description = "ID del link"),
+    ),
+    responses(
+        (status = 200, description = "Link revocato"),
+        (status = 403, description = "Permessi insufficienti"),
+        (status = 404, description = "Link non trovato"),
+    )
+)]
+#[instrument(skip(state, metadata), fields(chat_id = %chat_id, link_id = %link_id, user_id = %metadata.user_id))]
+pub async fn revoke_invite_link(
+    State(state): State<Arc<AppState>>,
+    Path((chat_id, link_id)): Path<(i32, i32)>,
+    Extension(metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware
+) -> Result<(), AppError> {
+    debug!("Revoking invite link");
+    // 1. Verificare che il link appartenga a questa chat, altrimenti NOT_FOUND
+    // 2. Chi non ha creato il link deve avere il permesso InviteMembers, altrimenti FORBIDDEN
+    // 3. Eliminare il link: il token smette di funzionare
+    // 4. Registrare la revoca nell'audit log
+
+    let link = state
+        .invite_link
+        .read(&link_id)
+        .await?
+        .filter(|link| link.chat_id == chat_id)
+        .ok_or_else(|| {
+            warn!("Invite link {} not found in chat {}", link_id, chat_id);
+            AppError::not_found("Invite link not found")
+        })?;
+
+    if link.created_by != Some(metadata.user_id) {
+        require_permission(&state, &metadata, ChatPermission::InviteMembers).await?;
+    }
+
+    state.invite_link.delete(&link_id).await?;
+
+    audit::record(
+        &state,
+        chat_id,
+        metadata.user_id,
+        AuditAction::InviteLinkRevoked,
+        link.created_by,
+        Some(json!({ "link_id": link_id })),
+    )
+    .await?;
+
+    info!("Invite link revoked");
+    Ok(())
+}
+
+/// Entra in una chat di gruppo tramite un link di invito
+#[utoipa::path(
+    post,
+    path = "/invite_links/{invite_token}/join",
+    tag = "members",
+    params(("invite_token" = String, Path, description = "Token segreto del link di invito")),
+    responses(
+        (status = 200, description = "Entrato nella chat", body = ChatDTO),
+        (status = 403, description = "Utente bannato o link non più valido per la policy della chat"),
+        (status = 404, description = "Link non trovato o revocato"),
+        (status = 409, description = "Già membro o gruppo pieno"),
+        (status = 410, description = "Link scaduto"),
+    )
+)]
+#[instrument(skip(state, current_user, invite_token), fields(user_id = %current_user.user_id))]
+pub async fn join_by_invite_link(
+    State(state): State<Arc<AppState>>,
+    Path(invite_token): Path<String>,
+    Extension(current_user): Extension<User>, // ottenuto dall'autenticazione JWT
+) -> Result<Json<ChatDTO>, AppError> {
+    debug!("Joining chat by invite link");
+    // 1. Cercare il link tramite l'hash del token, NOT_FOUND se non esiste (o è stato revocato)
+    //    e GONE se è scaduto
+    // 2. Verificare che current_user non sia già membro (CONFLICT) né bannato (FORBIDDEN)
+    // 3. Se la invite_policy della chat è AdminsOnly, il link vale solo se chi l'ha creato
+    //    ha ancora il permesso InviteMembers (la policy può essere cambiata dopo la creazione)
+    // 4. Aggiungere l'utente come Member con il messaggio di sistema, nella stessa unit of work
+    //    che verifica il limite di membri tenendo bloccata la chat
+    // 5. Dopo il commit inviare ChatJoined e il messaggio di sistema ai membri online
+    // 6. Ritornare la chat
+
+    let link = state
+        .invite_link
+        .find_by_token_hash(&hash_refresh_token(&invite_token))
+        .await?
+        .ok_or_else(|| {
+            warn!("Unknown invite link token");
+            AppError::not_found("Invite link not found")
+        })?;
+
+    if link
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= Utc::now())
+    {
+        warn!("Invite link {} has expired", link.link_id);
+        return Err(AppError::new(StatusCode::GONE, "Invite link has expired"));
+    }
+
+    let chat_id = link.chat_id;
+    let chat = state.chat.read(&chat_id).await?.ok_or_else(|| {
+        warn!("Chat {} not found", chat_id);
+        AppError::not_found("Chat not found")
+    })?;
+
+    if state
+        .meta
+        .read(&(current_user.user_id, chat_id))
+        .await?
+        .is_some()
+    {
+        warn!("User is already a member of chat {}", chat_id);
+        return Err(AppError::conflict("You are already a member of this chat"));
+    }
+
+    if state.ban.is_banned(&current_user.user_id, &chat_id).await? {
+        warn!("Banned user attempted to join chat {}", chat_id);
+        return Err(AppError::forbidden("You are banned from this chat"));
+    }
+
+    if chat.invite_policy == InvitePolicy::AdminsOnly && !creator_can_invite(&state, &link).await? {
+        warn!(
+            "Invite link {} was created by a member who can no longer invite",
+            link.link_id
+        );
+        return Err(AppError::forbidden(
+            "This invite link is no longer valid for this chat",
+        ));
+    }
+
+    let mut uow = state.begin().await?;
+    let joined = admit_member(&state, &mut uow, &chat, &current_user).await?;
+    uow.commit().await?;
+    announce_member(&state, &chat, &current_user, joined).await;
+
+    info!(
+        "User joined chat {} by invite link {}",
+        chat_id, link.link_id
+    );
+    Ok(Json(ChatDTO::from(chat)))
+}
+
+/// Verifica che l'autore del link sia ancora membro della chat con il permesso InviteMembers
+async fn creator_can_invite(state: &AppState, link: &InviteLink) -> Result<bool, AppError> {
+    let Some(created_by) = link.created_by else {
+        return Ok(false);
+    };
+    let Some(creator) = state.meta.read(&(created_by, link.chat_id)).await? else {
+        return Ok(false);
+    };
+    Ok(has_permission(state, &creator, ChatPermission::InviteMembers).await?)
+}
@@ -0,0 +1,389 @@
+//! Join request services - Ingresso nelle chat pubbliche, con approvazione se richiesta
+
+use crate::core::{AppError, AppState, DomainEvent, has_permission, require_permission};
+use crate::dtos::{
+    ChatDTO, CreateJoinRequestDTO, CreateMessageDTO, CreateUserChatMetadataDTO, JoinRequestDTO,
+    MessageDTO,
+};
+use crate::entities::{
+    Chat, ChatPermission, ChatType, ContentFormat, JoinRequestStatus, Message, MessageType, User,
+    UserChatMetadata, UserRole,
+};
+use crate::repositories::{
+    Create, JoinRequestFilter, MemberFilter, Page, Read, ReadMany, UnitOfWork,
+};
+use crate::ws::enqueue_for_offline_members;
+use crate::ws::event_handlers::apply_flood_strike;
+use crate::ws::usermap::InternalSignal;
+use axum::{
+    Extension,
+    extract::{Json, Path, State},
+};
+use chrono::Utc;
+use std::sync::Arc;
+use tracing::{debug, info, instrument, warn};
+use validator::Validate;
+
+/// Entra in una chat pubblica o chiede di entrarvi
+#[utoipa::path(
+    post,
+    path = "/chats/{chat_id}/join",
+    tag = "chats",
+    params(("chat_id" = i32, Path, description = "ID della chat")),
+    responses(
+        (
+            status = 200,
+            description = "Richiesta registrata, approvata subito se la chat è aperta",
+            body = JoinRequestDTO,
+        ),
+        (status = 403, description = "Utente bannato o chat non pubblica"),
+        (status = 404, description = "Chat non trovata"),
+        (status = 409, description = "Già membro o richiesta in attesa"),
+    )
+)]
+#[instrument(skip(state, current_user), fields(chat_id = %chat_id, user_id = %current_user.user_id))]
+pub async fn join_chat(
+    State(state): State<Arc<AppState>>,
+    Path(chat_id): Path<i32>,
+    Extension(current_user): Extension<User>, // ottenuto dall'autenticazione JWT
+) -> Result<Json<JoinRequestDTO>, AppError> {
+    debug!("Joining public chat");
+    // 1. Verificare che la chat esista e sia un gruppo pubblico, altrimenti NOT_FOUND
+    // 2. Verificare che current_user non sia già membro (CONFLICT) né bannato (FORBIDDEN)
+    // 3. Se la chat non richiede approvazione: aggiungere subito il membro e salvare la richiesta come APPROVED
+    // 4. Altrimenti verificare che non esista già una richiesta pending (CONFLICT) e crearla
+    // 5. Notificare la nuova richiesta a tutti i membri online che possono approvarla
+    // 6. Ritornare la richiesta
+
+    let chat = state
+        .chat
+        .read(&chat_id)
+        .await?
+        .filter(|chat| chat.is_public && chat.chat_type == ChatType::Group)
+        .ok_or_else(|| {
+            warn!("Public chat not found: {}", chat_id);
+            AppError::not_found("Chat not found")
+        })?;
+
+    if state
+        .meta
+        .read(&(current_user.user_id, chat_id))
+        .await?
+        .is_some()
+    {
+        warn!("User is already a member of chat {}", chat_id);
+        return Err(AppError::conflict("You are already a member of this chat"));
+    }
+
+    if state.ban.is_banned(&current_user.user_id, &chat_id).await? {
+        warn!("Banned user attempted to join chat {}", chat_id);
+        return Err(AppError::forbidden("You are banned from this chat"));
+    }
+
+    if !chat.requires_approval {
+        // ingresso e richiesta già approvata sono scritti nella stessa unit of work
+        let mut uow = state.begin().await?;
+        let joined = admit_member(&state, &mut uow, &chat, &current_user).await?;
+        let request = state
+            .join_request
+            .create_in(
+                &mut uow,
+                &CreateJoinRequestDTO {
+                    chat_id,
+                    user_id: current_user.user_id,
+                    state: JoinRequestStatus::Approved,
+                },
+            )
+            .await?;
+        uow.commit().await?;
+        announce_member(&state, &chat, &current_user, joined).await;
+
+        info!("User joined public chat");
+        return Ok(Json(JoinRequestDTO::from(request)));
+    }
+
+    if state
+        .join_request
+        .has_pending_request(&current_user.user_id, &chat_id)
+        .await?
+    {
+        warn!("Pending join request already exists for chat {}", chat_id);
+        return Err(AppError::conflict(
+            "There is already a pending join request for this chat",
+        ));
+    }
+
+    let request = state
+        .join_request
+        .create(&CreateJoinRequestDTO {
+            chat_id,
+            user_id: current_user.user_id,
+            state: JoinRequestStatus::Pending,
+        })
+        .await?;
+
+    let request_dto = JoinRequestDTO {
+        user: Some(current_user.into()),
+        ..JoinRequestDTO::from(request)
+    };
+
+    for member in state
+        .meta
+        .read_many(&MemberFilter::in_chat(chat_id).from_primary(), &Page::ALL)
+        .await?
+    {
+        if has_permission(&state, &member, ChatPermission::InviteMembers).await? {
+            state.users_online.send_server_message_if_online(
+                &member.user_id,
+                InternalSignal::JoinRequest(request_dto.clone()),
+            );
+        }
+    }
+
+    info!("Join request {} created", request_dto.request_id);
+    Ok(Json(request_dto))
+}
+
+/// Richieste di ingresso in attesa
+#[utoipa::path(
+    get,
+    path = "/chats/{chat_id}/join_requests",
+    tag = "members",
+    params(("chat_id" = i32, Path, description = "ID della chat")),
+    responses(
+        (status = 200, description = "Richieste in attesa", body = Vec<JoinRequestDTO>),
+        (status = 403, description = "Permessi insufficienti"),
+    )
+)]
+#[instrument(skip(state, metadata), fields(chat_id = %chat_id, user_id = %metadata.user_id))]
+pub async fn list_join_requests(
+    State(state): State<Arc<AppState>>,
+    Path(chat_id): Path<i32>,
+    Extension(metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware
+) -> Result<Json<Vec<JoinRequestDTO>>, AppError> {
+    debug!("Listing pending join requests");
+    // 1. Verificare che current_user abbia il permesso InviteMembers, altrimenti FORBIDDEN (fail-fast)
+    // 2. Recuperare le richieste pending della chat, dalla più vecchia
+    // 3. Arricchire ogni richiesta con i dati dell'utente che vuole entrare
+
+    require_permission(&state, &metadata, ChatPermission::InviteMembers).await?;
+
+    let requests = state
+        .join_request
+        .read_many(
+            &JoinRequestFilter {
+                chat_id,
+                state: Some(JoinRequestStatus::Pending),
+            },
+            &Page::ALL,
+        )
+        .await?;
+
+    let mut result = Vec::with_capacity(requests.len());
+    for request in requests {
+        let user = state
+            .user
+            .read(&request.user_id)
+            .await?
+            .map(|user| user.into());
+        result.push(JoinRequestDTO {
+            user,
+            ..JoinRequestDTO::from(request)
+        });
+    }
+
+    info!("Found {} pending join requests", result.len());
+    Ok(Json(result))
+}
+
+/// Approva o rifiuta una richiesta di ingresso
+#[utoipa::path(
+    post,
+    path = "/chats/{chat_id}/join_requests/{request_id}/{action}",
+    tag = "members",
+    params(
+        ("chat_id" = i32, Path, description = "ID della chat"),
+        ("request_id" = i32, Path, description = "ID della richiesta"),
+        ("action" = String, Path, description = "approve oppure deny"),
+    ),
+    responses(
+        (status = 200, description = "Richiesta aggiornata", body = JoinRequestDTO),
+        (status = 400, description = "Azione non valida"),
+        (status = 403, description = "Permessi insufficienti"),
+        (status = 404, description = "Richiesta non trovata"),
+        (status = 409, description = "Richiesta già decisa"),
+    )
+)]
+#[instrument(skip(state, current_user, metadata), fields(chat_id = %chat_id, request_id = %request_id, action = %action, user_id = %current_user.user_id))]
+pub async fn respond_to_join_request(
+    State(state): State<Arc<AppState>>,
+    Path((chat_id, request_id, action)): Path<(i32, i32, String)>,
+    Extension(current_user): Extension<User>,
+    Extension(metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware
+) -> Result<Json<JoinRequestDTO>, AppError> {
+    debug!("Responding to join request");
+    // 1. Validare che action sia "approve" o "deny"
+    // 2. Verificare che current_user abbia il permesso InviteMembers, altrimenti FORBIDDEN
+    // 3. Recuperare la richiesta, NOT_FOUND se non esiste o appartiene ad un'altra chat
+    // 4. Verificare che la richiesta sia ancora pending, altrimenti CONFLICT
+    // 5. Se approve: aggiungere l'utente alla chat (ban e posti disponibili sono verificati di nuovo)
+    // 6. Salvare l'esito con chi l'ha deciso, nella stessa unit of work dell'ingresso; CONFLICT
+    //    se nel frattempo la richiesta è stata decisa da un altro
+    // 7. Notificare l'esito all'utente che ha fatto la richiesta (se online)
+    // 8. Ritornare la richiesta aggiornata
+
+    let new_state = match action.as_str() {
+        "approve" => JoinRequestStatus::Approved,
+        "deny" => JoinRequestStatus::Denied,
+        _ => {
+            warn!("Invalid join request action: {}", action);
+            return Err(AppError::bad_request("Action must be 'approve' or 'deny'"));
+        }
+    };
+
+    require_permission(&state, &metadata, ChatPermission::InviteMembers).await?;
+
+    let request = state
+        .join_request
+        .read(&request_id)
+        .await?
+        .filter(|request| request.chat_id == chat_id)
+        .ok_or_else(|| {
+            warn!("Join request {} not found in chat {}", request_id, chat_id);
+            AppError::not_found("Join request not found")
+        })?;
+
+    if request.state != JoinRequestStatus::Pending {
+        warn!(
+            "Join request {} is already processed: {:?}",
+            request_id, request.state
+        );
+        return Err(AppError::conflict("Join request is already processed")
+            .with_details(format!("Join request is already {:?}", request.state)));
+    }
+
+    // ingresso del membro ed esito della richiesta sono scritti nella stessa unit of work
+    let mut uow = state.begin().await?;
+    let mut admitted = None;
+    if new_state == JoinRequestStatus::Approved {
+        let requester = state.user.read(&request.user_id).await?.ok_or_else(|| {
+            warn!("Requesting user {} not found", request.user_id);
+            AppError::not_found("User not found")
+        })?;
+
+        if state.ban.is_banned(&requester.user_id, &chat_id).await? {
+            warn!("Attempted to approve banned user {}", requester.user_id);
+            return Err(AppError::forbidden("User is banned from this chat"));
+        }
+
+        let chat = state.chat.read(&chat_id).await?.ok_or_else(|| {
+            warn!("Chat {} not found", chat_id);
+            AppError::not_found("Chat not found")
+        })?;
+        let joined = admit_member(&state, &mut uow, &chat, &requester).await?;
+        admitted = Some((chat, requester, joined));
+    }
+
+    // una decisione concorrente sulla stessa richiesta la trova già risolta
+    let resolved = state
+        .join_request
+        .resolve_in(&mut uow, &request_id, &new_state, &current_user.user_id)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => {
+                warn!("Join request {} was resolved concurrently", request_id);
+                AppError::conflict("Join request is already processed")
+            }
+            e => e.into(),
+        })?;
+    uow.commit().await?;
+
+    if let Some((chat, requester, joined)) = admitted {
+        announce_member(&state, &chat, &requester, joined).await;
+    }
+    let resolved_dto = JoinRequestDTO::from(resolved);
+
+    state.users_online.send_server_message_if_online(
+        &request.user_id,
+        InternalSignal::JoinRequestResolved(resolved_dto.clone()),
+    );
+
+    info!("Join request {:?}", new_state);
+    Ok(Json(resolved_dto))
+}
+
+/// Aggiunge l'utente come Member della chat e salva il messaggio di sistema che notifica
+/// l'ingresso, all'interno della unit of work del chiamante.
+/// Fallisce con CONFLICT se il gruppo ha raggiunto il numero massimo di membri; la chat
+/// resta bloccata fino al commit, così ingressi concorrenti non superano il limite.
+pub(crate) async fn admit_member(
+    state: &AppState,
+    uow: &mut UnitOfWork,
+    chat: &Chat,
+    user: &User,
+) -> Result<Message, AppError> {
+    let chat_id = chat.chat_id;
+    state
+        .ensure_group_has_room_in(uow, chat_id)
+        .await
+        .inspect_err(|_| warn!("Chat {} is full", chat_id))?;
+
+    let now = Utc::now();
+    let create_dto = CreateMessageDTO {
+        chat_id,
+        sender_id: user.user_id,
+        content: format!("User {} has joined the chat", user.username),
+        message_type: MessageType::SystemMessage,
+        created_at: now,
+        reply_to_message_id: None,
+        attachment_id: None,
+        client_msg_id: None,
+        content_format: ContentFormat::Plain,
+    };
+
+    create_dto
+        .validate()
+        .map_err(|_| AppError::bad_request("Validation error"))?;
+
+    state
+        .meta
+        .create_in(
+            uow,
+            &CreateUserChatMetadataDTO {
+                user_id: user.user_id,
+                chat_id,
+                user_role: Some(UserRole::Member),
+                member_since: now,
+                messages_visible_from: now,
+                messages_received_until: now,
+            },
+        )
+        .await?;
+
+    Ok(state.msg.create_in(uow, &create_dto).await?)
+}
+
+/// Dopo il commit dell'ingresso: invia ChatJoined al nuovo membro se online e il messaggio
+/// di sistema a tutta la chat. Conta l'ingresso per il rilevamento anti-flood, che può
+/// silenziare subito il nuovo membro.
+pub(crate) async fn announce_member(state: &AppState, chat: &Chat, user: &User, joined: Message) {
+    let chat_id = chat.chat_id;
+    state.users_online.send_server_message_if_online(
+        &user.user_id,
+        InternalSignal::ChatJoined(ChatDTO::from(chat.clone())),
+    );
+    state.publish_event(DomainEvent::MemberJoined {
+        chat_id,
+        user_id: user.user_id,
+        joined_at: joined.created_at,
+    });
+    // chi entra ed esce di continuo viene silenziato appena rientra
+    if let Some(strike) = state.flood_guard.record_join(user.user_id, chat_id) {
+        apply_flood_strike(state, chat_id, user.user_id, strike).await;
+    }
+
+    let _ = state
+        .chats_online
+        .send(&chat_id, Arc::new(MessageDTO::from(joined.clone())));
+    enqueue_for_offline_members(state, &joined).await;
+}
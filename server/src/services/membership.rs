@@ -1,41 +1,126 @@
 //! Membership services - Gestione membri e ruoli nelle chat
 
-use crate::core::{AppError, AppState, require_role};
+use crate::core::{AppError, AppState, DomainEvent, require_permission, require_role};
 use crate::dtos::{
-    CreateInvitationDTO, CreateMessageDTO, CreateUserChatMetadataDTO, EnrichedInvitationDTO,
-    MessageDTO, UpdateInvitationDTO, UserInChatDTO,
+    BannedMemberDTO, ChatDTO, CreateInvitationDTO, CreateMessageDTO, CreateUserChatMetadataDTO,
+    EnrichedInvitationDTO, InviteToChatDTO, MembersQuery, MessageDTO, MuteMemberQuery,
+    SentInvitationDTO, SentInvitationsQuery, UserInChatDTO,
 };
-use crate::entities::{ChatType, InvitationStatus, MessageType, User, UserChatMetadata, UserRole};
-use crate::repositories::{Create, Delete, Read, Update};
+use crate::entities::{
+    AuditAction, ChatPermission, ChatType, ContentFormat, Invitation, InvitationStatus,
+    InvitePolicy, MessageType, User, UserChatMetadata, UserRole,
+};
+use crate::repositories::{Create, InvitationFilter, MemberFilter, Page, Read, ReadMany};
+use crate::services::audit;
+use crate::ws::chatmap::ChatEvent;
+use crate::ws::enqueue_for_offline_members;
+use crate::ws::event_handlers::apply_flood_strike;
 use crate::ws::usermap::InternalSignal;
 use axum::{
     Extension,
-    extract::{Json, Path, State},
+    extract::{Json, Path, Query, State},
 };
 use axum_macros::debug_handler;
-use chrono::Utc;
+use chrono::{Duration, Utc};
+use serde_json::json;
 use std::sync::Arc;
 use tracing::{debug, error, info, instrument, warn};
 use validator::Validate;
 
-#[instrument(skip(state, _metadata), fields(chat_id = %chat_id))]
+/// Membri della chat, ordinati per user_id e paginabili con `limit` e `after_user_id`
+#[utoipa::path(
+    get,
+    path = "/chats/{chat_id}/members",
+    tag = "members",
+    params(
+        ("chat_id" = i32, Path, description = "ID della chat"),
+        MembersQuery,
+        ("If-None-Match" = Option<String>, Header, description = "ETag di una risposta precedente"),
+    ),
+    responses(
+        (
+            status = 200,
+            description = "Membri della chat",
+            body = Vec<UserInChatDTO>,
+            headers(("ETag" = String, description = "Hash del corpo della risposta")),
+        ),
+        (status = 304, description = "Lista invariata rispetto a If-None-Match"),
+        (status = 400, description = "Limite non valido"),
+    )
+)]
+#[instrument(skip(state, _metadata, params), fields(chat_id = %chat_id))]
 pub async fn list_chat_members(
     State(state): State<Arc<AppState>>,
     Path(chat_id): Path<i32>,
+    Query(params): Query<MembersQuery>,
     Extension(_metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware (verifica già la membership)
 ) -> Result<Json<Vec<UserInChatDTO>>, AppError> {
     debug!("Listing members for chat");
     // 1. Estrarre chat_id dal path della URL
     // 2. Ottenere metadata dell'utente dall'Extension (membership già verificata dal middleware)
-    // 3. Recuperare tutti i metadata associati alla chat tramite chat_id (singola query)
-    // 4. Estrarre tutti gli user_id dai metadata
-    // 5. Recuperare tutti gli utenti con query parallele per ogni user_id
-    // 6. Combinare le informazioni degli utenti con i metadata (join in memoria)
-    // 7. Convertire ogni combinazione in UserInChatDTO (trasformazione in memoria)
-    // 8. Ritornare la lista di UserInChatDTO come risposta JSON
+    // 3. Validare limit (senza limit la pagina contiene tutti i membri)
+    // 4. Recuperare i metadata della pagina: user_id successivi ad after_user_id, filtrati
+    //    per ruolo se richiesto (singola query)
+    // 5. Estrarre tutti gli user_id dai metadata
+    // 6. Recuperare tutti gli utenti con query parallele per ogni user_id
+    // 7. Combinare le informazioni degli utenti con i metadata (join in memoria)
+    // 8. Convertire ogni combinazione in UserInChatDTO (trasformazione in memoria)
+    // 9. Ritornare la lista di UserInChatDTO come risposta JSON
+
+    params.validate()?;
+
+    let filter = MemberFilter {
+        chat_id: Some(chat_id),
+        role: params.role,
+        after_user_id: params.after_user_id.unwrap_or(0),
+        ..MemberFilter::default()
+    };
+    let page = Page::first(params.limit.unwrap_or(i64::MAX));
+    let meta = state.meta.read_many(&filter, &page).await?;
+    let result = with_usernames(&state, meta).await?;
+
+    info!("Successfully retrieved {} members", result.len());
+    Ok(Json(result))
+}
+
+/// Un Member che agisce grazie a un ruolo personalizzato può intervenire solo sugli altri Member,
+/// mai su Admin e Owner
+fn ensure_can_act_on(
+    current: &UserChatMetadata,
+    target: &UserChatMetadata,
+) -> Result<(), AppError> {
+    if matches!(current.user_role, Some(UserRole::Member))
+        && !matches!(target.user_role, Some(UserRole::Member))
+    {
+        warn!(
+            "Member {} attempted to act on {:?} {}",
+            current.user_id, target.user_role, target.user_id
+        );
+        return Err(AppError::forbidden("Members can act only on other members"));
+    }
+
+    Ok(())
+}
 
-    let meta = state.meta.find_many_by_chat_id(&chat_id).await?;
+/// Recupera tutti i membri della chat con il loro username, senza paginazione (per gli export)
+///
+/// Solo per le risposte al client: i membri sono letti dalla replica, se configurata.
+pub(crate) async fn load_chat_members(
+    state: &AppState,
+    chat_id: &i32,
+) -> Result<Vec<UserInChatDTO>, AppError> {
+    let meta = state
+        .meta
+        .read_many(&MemberFilter::in_chat(*chat_id), &Page::ALL)
+        .await?;
+    with_usernames(state, meta).await
+}
 
+/// Completa i metadata dei membri con username e stato, mantenendone l'ordine
+async fn with_usernames(
+    state: &AppState,
+    meta: Vec<UserChatMetadata>,
+) -> Result<Vec<UserInChatDTO>, AppError> {
     debug!("Found {} members in chat", meta.len());
 
     let user_ids: Vec<i32> = meta.iter().map(|m| m.user_id).collect();
@@ -50,17 +135,38 @@ pub async fn list_chat_members(
             result.push(UserInChatDTO {
                 user_id: Some(user.user_id),
                 chat_id: Some(m.chat_id),
-                username: Some(user.username),
+                status: user.active_status().map(str::to_string),
+                username: Some(user.public_username().to_string()),
                 user_role: m.user_role.clone(),
+                role_id: m.role_id,
                 member_since: Some(m.member_since),
+                // i silenziamenti scaduti non vengono mostrati
+                muted_until: m.muted_until.filter(|until| *until > Utc::now()),
             });
         }
     }
 
-    info!("Successfully retrieved {} members", result.len());
-    Ok(Json(result))
+    Ok(result)
 }
 
+/// Inviti ricevuti in attesa di risposta
+#[utoipa::path(
+    get,
+    path = "/invitations/pending",
+    tag = "invitations",
+    params(
+        ("If-None-Match" = Option<String>, Header, description = "ETag di una risposta precedente"),
+    ),
+    responses(
+        (
+            status = 200,
+            description = "Inviti in attesa",
+            body = Vec<EnrichedInvitationDTO>,
+            headers(("ETag" = String, description = "Hash del corpo della risposta")),
+        ),
+        (status = 304, description = "Lista invariata rispetto a If-None-Match"),
+    )
+)]
 #[instrument(skip(state, current_user), fields(user_id = %current_user.user_id))]
 pub async fn list_pending_invitations(
     State(state): State<Arc<AppState>>,
@@ -74,7 +180,10 @@ pub async fn list_pending_invitations(
 
     let invitations = state
         .invitation
-        .find_many_by_user_id(&current_user.user_id)
+        .read_many(
+            &InvitationFilter::pending_for(current_user.user_id),
+            &Page::ALL,
+        )
         .await?;
 
     info!("Found {} pending invitations", invitations.len());
@@ -83,55 +192,159 @@ pub async fn list_pending_invitations(
     let mut enriched_invitations = Vec::new();
     
     for invitation in invitations {
-        // Recupera l'utente inviter completo (campo invitee_id contiene l'inviter)
-        let inviter = state
-            .user
-            .read(&invitation.invitee_id)
-            .await
-            .ok()
-            .flatten()
-            .map(|user| user.into());
-
-        // Recupera la chat completa
-        let chat = state
-            .chat
-            .read(&invitation.target_chat_id)
-            .await
-            .ok()
-            .flatten()
-            .map(|chat| chat.into());
-
-        enriched_invitations.push(EnrichedInvitationDTO {
-            invite_id: invitation.invite_id,
-            state: invitation.state,
-            created_at: invitation.created_at,
-            inviter,
-            chat,
-        });
+        enriched_invitations.push(enrich_received_invitation(&state, invitation).await);
     }
 
     Ok(Json(enriched_invitations))
 }
 
+/// Arricchisce un invito ricevuto con l'inviter e la chat (usato anche da /sync).
+/// Se una delle due letture fallisce il campo resta vuoto
+pub(crate) async fn enrich_received_invitation(
+    state: &AppState,
+    invitation: Invitation,
+) -> EnrichedInvitationDTO {
+    // Recupera l'utente inviter completo (campo invitee_id contiene l'inviter)
+    let inviter = state
+        .user
+        .read(&invitation.invitee_id)
+        .await
+        .ok()
+        .flatten()
+        .map(|user| user.into());
+
+    // Recupera la chat completa
+    let chat = state
+        .chat
+        .read(&invitation.target_chat_id)
+        .await
+        .ok()
+        .flatten()
+        .map(|chat| chat.into());
+
+    EnrichedInvitationDTO {
+        invite_id: invitation.invite_id,
+        state: invitation.state,
+        created_at: invitation.created_at,
+        note: invitation.note,
+        inviter,
+        chat,
+    }
+}
+
+/// Inviti inviati dall'utente
+#[utoipa::path(
+    get,
+    path = "/invitations/sent",
+    tag = "invitations",
+    params(SentInvitationsQuery),
+    responses(
+        (status = 200, description = "Inviti inviati", body = Vec<SentInvitationDTO>),
+    )
+)]
+#[instrument(skip(state, current_user), fields(user_id = %current_user.user_id, status = ?params.status))]
+pub async fn list_sent_invitations(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<SentInvitationsQuery>,
+    Extension(current_user): Extension<User>,
+) -> Result<Json<Vec<SentInvitationDTO>>, AppError> {
+    debug!("Listing invitations sent by user");
+    // 1. Recuperare gli inviti inviati dall'utente corrente, filtrati per stato se richiesto
+    // 2. Per ogni invito, arricchire con l'utente invitato e la chat
+    // 3. Ritornare la lista di SentInvitationDTO dal più recente
+
+    let filter = InvitationFilter {
+        inviter_id: Some(current_user.user_id),
+        state: params.status,
+        ..InvitationFilter::default()
+    };
+    let invitations = state.invitation.read_many(&filter, &Page::ALL).await?;
+
+    info!("Found {} sent invitations", invitations.len());
+
+    let mut sent_invitations = Vec::with_capacity(invitations.len());
+
+    for invitation in invitations {
+        sent_invitations.push(enrich_sent_invitation(&state, invitation).await?);
+    }
+
+    Ok(Json(sent_invitations))
+}
+
+/// Arricchisce un invito inviato con l'utente invitato e la chat (usato anche da /sync)
+pub(crate) async fn enrich_sent_invitation(
+    state: &AppState,
+    invitation: Invitation,
+) -> Result<SentInvitationDTO, AppError> {
+    let invited = state
+        .user
+        .read(&invitation.invited_id)
+        .await?
+        .map(|user| user.into());
+
+    let chat = state
+        .chat
+        .read(&invitation.target_chat_id)
+        .await?
+        .map(|chat| chat.into());
+
+    Ok(SentInvitationDTO {
+        invite_id: invitation.invite_id,
+        state: invitation.state,
+        created_at: invitation.created_at,
+        note: invitation.note,
+        invited,
+        chat,
+    })
+}
+
+/// Invita un utente nella chat
+#[utoipa::path(
+    post,
+    path = "/chats/{chat_id}/invite/{user_id}",
+    tag = "members",
+    params(
+        ("chat_id" = i32, Path, description = "ID della chat"),
+        ("user_id" = i32, Path, description = "ID dell'utente"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Chiave scelta dal client per ripetere la richiesta senza duplicarla"),
+    ),
+    request_body = Option<InviteToChatDTO>,
+    responses(
+        (status = 200, description = "Invito inviato"),
+        (status = 403, description = "Permessi insufficienti"),
+        (status = 404, description = "Utente non trovato"),
+        (status = 409, description = "Utente già membro o già invitato"),
+    )
+)]
 #[instrument(skip(state, current_user, metadata), fields(chat_id = %chat_id, inviting_user = %current_user.user_id, target_user = %user_id))]
 pub async fn invite_to_chat(
     State(state): State<Arc<AppState>>,
     Path((chat_id, user_id)): Path<(i32, i32)>,
     Extension(current_user): Extension<User>,
     Extension(metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware
+    // body facoltativo con la nota per l'invitato
+    body: Option<Json<InviteToChatDTO>>,
 ) -> Result<(), AppError> {
     debug!("Inviting user to chat");
     // 1. Estrarre chat_id e user_id dal path, ottenere utente corrente e metadata dall'Extension
-    // 2. Verificare che current_user sia Admin o Owner tramite metadata
-    // 3. Verificare che la chat esista e sia di tipo Group (non si può invitare in chat private)
+    // 2. Validare la nota se presente
+    // 3. Verificare che la chat esista e sia di tipo Group (non si può invitare in chat private);
+    //    se la invite_policy è AdminsOnly verificare che current_user abbia il permesso InviteMembers
     // 4. Verificare che l'utente target esista nel database (fail-fast su controllo basilare)
-    // 5. Verificare che l'utente target non sia già membro
-    // 6. Controllare se esiste già un invito pending
-    // 7. Creare l'invitation nel database
-    // 8. Inviare l'invitation via WebSocket all'utente invitato (se online)
-    // 9. Ritornare OK
-
-    require_role(&metadata, &[UserRole::Admin, UserRole::Owner])?;
+    // 5. Verificare che l'utente target non sia già membro e che non sia bannato dalla chat
+    // 6. Verificare che il gruppo non abbia raggiunto il numero massimo di membri
+    // 7. Controllare se esiste già un invito pending
+    // 8. Creare l'invitation nel database
+    // 9. Inviare l'invitation via WebSocket all'utente invitato (se online)
+    // 10. Ritornare OK
+
+    let Json(body) = body.unwrap_or_default();
+    body.validate()?;
+    // una nota vuota o di soli spazi equivale a nessuna nota
+    let note = body
+        .note
+        .map(|note| note.trim().to_string())
+        .filter(|note| !note.is_empty());
 
     // Verificare che la chat esista e sia di tipo Group
     let chat = state.chat.read(&chat_id).await?.ok_or_else(|| {
@@ -146,6 +359,10 @@ pub async fn invite_to_chat(
         ));
     }
 
+    if chat.invite_policy == InvitePolicy::AdminsOnly {
+        require_permission(&state, &metadata, ChatPermission::InviteMembers).await?;
+    }
+
     // Verificare che l'utente target esista nel database
     if state.user.read(&user_id).await?.is_none() {
         warn!("Target user not found: {}", user_id);
@@ -158,6 +375,20 @@ pub async fn invite_to_chat(
         return Err(AppError::conflict("User is already a member of this chat"));
     }
 
+    if state.ban.is_banned(&user_id, &chat_id).await? {
+        warn!("User {} is banned from chat {}", user_id, chat_id);
+        return Err(AppError::forbidden("User is banned from this chat"));
+    }
+
+    // Verificare che ci sia ancora posto nel gruppo; il controllo definitivo avviene
+    // all'accettazione, nella stessa transazione dell'ingresso
+    let members = state.meta.count_by_chat_id(&chat_id).await?;
+    state
+        .ensure_group_has_room(members as usize)
+        .inspect_err(|_| {
+            warn!("Chat {} is full ({} members)", chat_id, members);
+        })?;
+
     // Controllare se esiste già un invito pending
     if state
         .invitation
@@ -180,6 +411,7 @@ pub async fn invite_to_chat(
             target_chat_id: chat_id,
             invited_id: user_id,
             invitee_id: current_user.user_id,
+            note,
         })
         .await?;
 
@@ -208,6 +440,7 @@ pub async fn invite_to_chat(
         invite_id: invitation.invite_id,
         state: invitation.state,
         created_at: invitation.created_at,
+        note: invitation.note,
         inviter,
         chat: chat_dto,
     };
@@ -240,6 +473,10 @@ pub async fn invite_to_chat(
         content: system_message_content,
         message_type: MessageType::SystemMessage,
         created_at: Utc::now(),
+        reply_to_message_id: None,
+        attachment_id: None,
+        client_msg_id: None,
+        content_format: ContentFormat::Plain,
     };
     
     create_message_dto
@@ -255,12 +492,30 @@ pub async fn invite_to_chat(
     // Broadcast del messaggio di sistema a tutti i membri online della chat via ChatMap
     let _ = state
         .chats_online
-        .send(&chat_id, Arc::new(MessageDTO::from(saved_message)));
+        .send(&chat_id, Arc::new(MessageDTO::from(saved_message.clone())));
+    // e consegna ai membri offline alla prossima connessione
+    enqueue_for_offline_members(&state, &saved_message).await;
 
     info!("User successfully invited to chat");
     Ok(())
 }
 
+/// Accetta o rifiuta un invito
+#[utoipa::path(
+    post,
+    path = "/invitations/{invite_id}/{action}",
+    tag = "invitations",
+    params(
+        ("invite_id" = i32, Path, description = "ID dell'invito"),
+        ("action" = String, Path, description = "accept oppure reject"),
+    ),
+    responses(
+        (status = 200, description = "Invito aggiornato"),
+        (status = 400, description = "Azione non valida"),
+        (status = 404, description = "Invito non trovato"),
+        (status = 409, description = "Invito già deciso"),
+    )
+)]
 #[instrument(skip(state, current_user), fields(invite_id = %invite_id, action = %action, user_id = %current_user.user_id))]
 pub async fn respond_to_invitation(
     State(state): State<Arc<AppState>>,
@@ -273,13 +528,17 @@ pub async fn respond_to_invitation(
     // 3. Validare che action sia "accept" o "reject"
     // 4. Recuperare l'invito dal database
     // 5. Verificare che l'invito sia pending e che current_user sia l'invitato
-    // 6. Se accept: creare metadata per aggiungere l'utente alla chat con ruolo Member
-    // 7. Se accept e utente online: inviare segnale AddChat per sottoscriversi ai messaggi
-    // 8. Aggiornare lo stato dell'invito (Accepted/Rejected)
-    // 9. Creare messaggio di sistema nella chat target con notifica appropriata
-    // 10. Salvare il messaggio dopo validazione
-    // 11. Inviare il messaggio tramite WebSocket a tutti i membri online
-    // 12. Ritornare OK
+    // 6. Se accept: verificare che l'utente non sia stato bannato e che il gruppo non sia pieno
+    //    (entrambe le cose possono essere cambiate dopo l'invito)
+    //    e creare metadata per aggiungere l'utente alla chat con ruolo Member
+    // 7. Aggiornare lo stato dell'invito (Accepted/Rejected), solo se ancora pending
+    // 8. Creare messaggio di sistema nella chat target con notifica appropriata e salvarlo
+    //    dopo validazione; metadata, stato dell'invito e messaggio sono scritti nella stessa
+    //    unit of work
+    // 9. Se accept e utente online: inviare segnale ChatJoined per sottoscriversi ai messaggi;
+    //    se entra ed esce di continuo dalla chat viene silenziato (rilevamento anti-flood)
+    // 10. Inviare il messaggio tramite WebSocket a tutti i membri online
+    // 11. Ritornare OK
 
     // Validare action
     let new_status = match action.as_str() {
@@ -319,71 +578,126 @@ pub async fn respond_to_invitation(
     }
 
     let chat_id = invitation.target_chat_id;
+    let accepted = matches!(new_status, InvitationStatus::Accepted);
 
-    // Se accetta, aggiungere l'utente alla chat
-    if matches!(new_status, InvitationStatus::Accepted) {
+    if accepted {
         debug!("User accepted invitation, adding to chat {}", chat_id);
-        let now = Utc::now();
-        state
-            .meta
-            .create(&CreateUserChatMetadataDTO {
-                user_id: current_user.user_id,
-                chat_id,
-                user_role: Some(UserRole::Member),
-                member_since: now,
-                messages_visible_from: now,
-                messages_received_until: now,
-            })
-            .await?;
-
-        // Se l'utente è online, inviare segnale AddChat per sottoscriversi ai messaggi della chat
-        state
-            .users_online
-            .send_server_message_if_online(&current_user.user_id, InternalSignal::AddChat(chat_id));
+        if state.ban.is_banned(&current_user.user_id, &chat_id).await? {
+            warn!(
+                "Banned user {} attempted to join chat {}",
+                current_user.user_id, chat_id
+            );
+            return Err(AppError::forbidden("You are banned from this chat"));
+        }
     } else {
         debug!("User rejected invitation");
     }
 
-    // Aggiornare lo stato dell'invito
-    state
-        .invitation
-        .update(
-            &invite_id,
-            &UpdateInvitationDTO {
-                state: Some(new_status.clone()),
-            },
-        )
-        .await?;
-
     // Creare messaggio di sistema appropriato
-    let content = if matches!(new_status, InvitationStatus::Accepted) {
+    let content = if accepted {
         format!("User {} has joined the chat", current_user.username)
     } else {
         format!("User {} has declined the invitation", current_user.username)
     };
 
+    let now = Utc::now();
     let create_dto = CreateMessageDTO {
         chat_id,
         sender_id: current_user.user_id,
         content,
         message_type: MessageType::SystemMessage,
-        created_at: Utc::now(),
+        created_at: now,
+        reply_to_message_id: None,
+        attachment_id: None,
+        client_msg_id: None,
+        content_format: ContentFormat::Plain,
     };
 
     create_dto
         .validate()
         .map_err(|_| AppError::bad_request("Validation error"))?;
 
-    let saved_message = state.msg.create(&create_dto).await?;
+    // Ingresso, stato dell'invito e messaggio di sistema sono scritti nella stessa unit of work
+    let mut uow = state.begin().await?;
+    if accepted {
+        // il posto nel gruppo è verificato con la chat bloccata fino al commit dell'ingresso
+        state
+            .ensure_group_has_room_in(&mut uow, chat_id)
+            .await
+            .inspect_err(|_| warn!("Chat {} is full", chat_id))?;
+        state
+            .meta
+            .create_in(
+                &mut uow,
+                &CreateUserChatMetadataDTO {
+                    user_id: current_user.user_id,
+                    chat_id,
+                    user_role: Some(UserRole::Member),
+                    member_since: now,
+                    messages_visible_from: now,
+                    messages_received_until: now,
+                },
+            )
+            .await?;
+    }
+
+    // Una risposta concorrente allo stesso invito trova l'invito non più pending
+    state
+        .invitation
+        .answer_in(&mut uow, &invite_id, &new_status)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => {
+                warn!("Invitation {} was answered concurrently", invite_id);
+                AppError::conflict("Invitation is already processed")
+            }
+            e => e.into(),
+        })?;
+
+    let saved_message = state.msg.create_in(&mut uow, &create_dto).await?;
+    uow.commit().await?;
+
+    if accepted {
+        // Se l'utente è online, inviare segnale ChatJoined per sottoscriversi ai messaggi della chat
+        let chat = state.chat.read(&chat_id).await?.ok_or_else(|| {
+            warn!("Chat {} not found", chat_id);
+            AppError::not_found("Chat not found")
+        })?;
+        state.users_online.send_server_message_if_online(
+            &current_user.user_id,
+            InternalSignal::ChatJoined(ChatDTO::from(chat)),
+        );
+        state.publish_event(DomainEvent::MemberJoined {
+            chat_id,
+            user_id: current_user.user_id,
+            joined_at: now,
+        });
+
+        if let Some(strike) = state.flood_guard.record_join(current_user.user_id, chat_id) {
+            apply_flood_strike(&state, chat_id, current_user.user_id, strike).await;
+        }
+    }
 
     let _ = state
         .chats_online
-        .send(&chat_id, Arc::new(MessageDTO::from(saved_message)));
+        .send(&chat_id, Arc::new(MessageDTO::from(saved_message.clone())));
+    enqueue_for_offline_members(&state, &saved_message).await;
 
     info!("Invitation response processed successfully");
     Ok(())
 }
 
+/// Esce dalla chat
+#[utoipa::path(
+    post,
+    path = "/chats/{chat_id}/leave",
+    tag = "members",
+    params(("chat_id" = i32, Path, description = "ID della chat")),
+    responses(
+        (status = 200, description = "Uscito dalla chat"),
+        (status = 409, description = "Il proprietario deve prima trasferire la chat"),
+    )
+)]
 #[instrument(skip(state, current_user, metadata), fields(chat_id = %chat_id, user_id = %current_user.user_id))]
 pub async fn leave_chat(
     State(state): State<Arc<AppState>>,
@@ -395,18 +709,22 @@ pub async fn leave_chat(
     // 1. Estrarre chat_id dal path della URL
     // 2. Ottenere l'utente corrente e metadata dall'Extension
     // 3. Verificare il ruolo: se è Owner, ritornare errore CONFLICT con messaggio specifico (fail-fast, controllo in memoria)
-    // 4. Cancellare i metadata di current_user per questa chat dal database
-    // 5. Se utente online: inviare segnale RemoveChat per disiscriversi dai messaggi della chat
-    // 6. Creare un messaggio di sistema che notifica l'uscita (i messaggi dell'utente rimangono nel DB)
-    // 7. Salvare il messaggio nel database
+    // 4. Creare un messaggio di sistema che notifica l'uscita (i messaggi dell'utente rimangono nel DB)
+    // 5. Cancellare i metadata di current_user per questa chat e salvare il messaggio nella
+    //    stessa unit of work, poi contare l'uscita per il rilevamento anti-flood
+    // 6. Eliminare fisicamente i messaggi che nessun membro rimasto può più vedere
+    // 7. Se utente online: inviare segnale RemoveChat per disiscriversi dai messaggi della chat
     // 8. Inviare il messaggio tramite WebSocket a tutti i membri online (operazione non bloccante)
     // 9. Ritornare StatusCode::OK
 
     // L'Owner non può lasciare la chat, a meno che non sia l'unico membro
     if matches!(metadata.user_role, Some(UserRole::Owner)) {
         // Contare i membri della chat
-        let members = state.meta.find_many_by_chat_id(&chat_id).await?;
-        
+        let members = state
+            .meta
+            .read_many(&MemberFilter::in_chat(chat_id).from_primary(), &Page::ALL)
+            .await?;
+
         if members.len() > 1 {
             warn!("Owner attempted to leave chat with other members present");
             return Err(AppError::conflict(
@@ -417,12 +735,47 @@ pub async fn leave_chat(
         debug!("Owner is the only member, allowing exit");
     }
 
-    state.meta.delete(&(current_user.user_id, chat_id)).await?;
+    let message_dto = MessageDTO {
+        message_id: None,
+        chat_id: Some(chat_id),
+        sender_id: Some(current_user.user_id),
+        content: Some(format!("User {} has left the chat", current_user.username)),
+        message_type: Some(MessageType::SystemMessage),
+        created_at: Some(Utc::now()),
+        deleted_at: None,
+        reply_to_message_id: None,
+        client_msg_id: None,
+        attachment_id: None,
+        content_format: None,
+    };
+
+    let create_dto = CreateMessageDTO::try_from(message_dto)
+        .map_err(|_| AppError::bad_request("Failed to build message dto"))?;
+
+    create_dto
+        .validate()
+        .map_err(|_| AppError::bad_request("Validation error"))?;
+
+    // Uscita e messaggio di sistema sono scritti nella stessa unit of work
+    let mut uow = state.begin().await?;
+    state
+        .meta
+        .delete_in(&mut uow, &(current_user.user_id, chat_id))
+        .await?;
+    let saved_message = state.msg.create_in(&mut uow, &create_dto).await?;
+    uow.commit().await?;
+
+    state
+        .flood_guard
+        .record_leave(current_user.user_id, chat_id);
 
     // Dopo che l'utente esce, controllare se ci sono messaggi da eliminare fisicamente
     // Recupera tutti i metadata rimanenti della chat
-    let remaining_metadata = state.meta.find_many_by_chat_id(&chat_id).await?;
-    
+    let remaining_metadata = state
+        .meta
+        .read_many(&MemberFilter::in_chat(chat_id).from_primary(), &Page::ALL)
+        .await?;
+
     if !remaining_metadata.is_empty() {
         // Trova il messages_visible_from più vecchio tra i membri rimasti
         let oldest_visible_date = remaining_metadata
@@ -450,29 +803,208 @@ pub async fn leave_chat(
         .users_online
         .send_server_message_if_online(&current_user.user_id, InternalSignal::RemoveChat(chat_id));
 
-    let message_dto = MessageDTO {
-        message_id: None,
-        chat_id: Some(chat_id),
-        sender_id: Some(current_user.user_id),
-        content: Some(format!("User {} has left the chat", current_user.username)),
-        message_type: Some(MessageType::SystemMessage),
-        created_at: Some(Utc::now()),
-    };
+    let _ = state
+        .chats_online
+        .send(&chat_id, Arc::new(MessageDTO::from(saved_message.clone())));
+    enqueue_for_offline_members(&state, &saved_message).await;
+    info!("User successfully left chat");
+    Ok(())
+}
 
-    let create_dto = CreateMessageDTO::try_from(message_dto.clone())
-        .map_err(|_| AppError::bad_request("Failed to build message dto"))?;
+/// Banna un membro dalla chat
+#[utoipa::path(
+    post,
+    path = "/chats/{chat_id}/members/{user_id}/ban",
+    tag = "members",
+    params(
+        ("chat_id" = i32, Path, description = "ID della chat"),
+        ("user_id" = i32, Path, description = "ID dell'utente"),
+    ),
+    responses(
+        (status = 200, description = "Membro bannato", body = BannedMemberDTO),
+        (status = 403, description = "Permessi insufficienti"),
+        (status = 404, description = "Utente non membro della chat"),
+    )
+)]
+#[instrument(skip(state, current_user, current_metadata), fields(chat_id = %chat_id, banning_user = %current_user.user_id, target_user = %user_id))]
+pub async fn ban_member(
+    State(state): State<Arc<AppState>>,
+    Path((chat_id, user_id)): Path<(i32, i32)>,
+    Extension(current_user): Extension<User>,
+    Extension(current_metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware
+) -> Result<Json<BannedMemberDTO>, AppError> {
+    debug!("Banning member from chat");
+    // 1. Verificare che current_user abbia il permesso ModerateMembers, altrimenti FORBIDDEN (fail-fast)
+    // 2. Verificare che non si stia cercando di bannare sé stessi
+    // 3. Verificare che l'utente target esista
+    // 4. Se il target è membro: non si può bannare l'Owner
+    // 5. Salvare il ban (anche per utenti non membri, così non potranno essere invitati) e registrarlo nell'audit log
+    // 6. Se il target era membro: cancellare i suoi metadata (stessa unit of work dei passi precedenti)
+    //    e inviargli RemoveChat
+    // 7. Inviare l'evento MemberBanned a tutti i membri online della chat
+    // 8. Ritornare il ban
+
+    require_permission(&state, &current_metadata, ChatPermission::ModerateMembers).await?;
+
+    if user_id == current_user.user_id {
+        warn!("User attempted to ban themselves");
+        return Err(AppError::bad_request("You cannot ban yourself"));
+    }
 
-    create_dto
-        .validate()
-        .map_err(|_| AppError::bad_request("Validation error"))?;
+    if state.user.read(&user_id).await?.is_none() {
+        warn!("Target user not found: {}", user_id);
+        return Err(AppError::not_found("User not found"));
+    }
 
-    let _saved_message = state.msg.create(&create_dto).await?;
+    let target_meta = state.meta.read(&(user_id, chat_id)).await?;
 
-    let _ = state.chats_online.send(&chat_id, Arc::new(message_dto));
-    info!("User successfully left chat");
-    Ok(())
+    if target_meta
+        .as_ref()
+        .is_some_and(|meta| matches!(meta.user_role, Some(UserRole::Owner)))
+    {
+        warn!("Attempted to ban owner from chat");
+        return Err(AppError::forbidden("You cannot ban the owner of the chat"));
+    }
+    if let Some(meta) = &target_meta {
+        ensure_can_act_on(&current_metadata, meta)?;
+    }
+
+    // Ban, voce dell'audit log e uscita del membro sono scritti nella stessa unit of work
+    let mut uow = state.begin().await?;
+    let banned = state
+        .ban
+        .ban_in(&mut uow, &user_id, &chat_id, &current_user.user_id)
+        .await?;
+
+    audit::record_in(
+        &state,
+        &mut uow,
+        chat_id,
+        current_user.user_id,
+        AuditAction::MemberBanned,
+        Some(user_id),
+        Some(json!({ "was_member": target_meta.is_some() })),
+    )
+    .await?;
+
+    if target_meta.is_some() {
+        state.meta.delete_in(&mut uow, &(user_id, chat_id)).await?;
+    }
+    uow.commit().await?;
+
+    if target_meta.is_some() {
+        info!(
+            "Sending RemoveChat signal to banned user {} for chat {}",
+            user_id, chat_id
+        );
+        state
+            .users_online
+            .send_server_message_if_online(&user_id, InternalSignal::RemoveChat(chat_id));
+    }
+
+    let banned_dto = BannedMemberDTO::from(banned);
+
+    let _ = state
+        .chats_online
+        .send_event(&chat_id, ChatEvent::MemberBanned(banned_dto.clone()));
+
+    info!("Member banned from chat");
+    Ok(Json(banned_dto))
+}
+
+/// Silenzia un membro per la durata indicata
+#[utoipa::path(
+    post,
+    path = "/chats/{chat_id}/members/{user_id}/mute",
+    tag = "members",
+    params(
+        ("chat_id" = i32, Path, description = "ID della chat"),
+        ("user_id" = i32, Path, description = "ID dell'utente"),
+        MuteMemberQuery,
+    ),
+    responses(
+        (status = 200, description = "Membro silenziato", body = UserInChatDTO),
+        (status = 400, description = "Durata non valida"),
+        (status = 403, description = "Permessi insufficienti"),
+        (status = 404, description = "Utente non membro della chat"),
+    )
+)]
+#[instrument(skip(state, current_user, current_metadata), fields(chat_id = %chat_id, muting_user = %current_user.user_id, target_user = %user_id))]
+pub async fn mute_member(
+    State(state): State<Arc<AppState>>,
+    Path((chat_id, user_id)): Path<(i32, i32)>,
+    Query(params): Query<MuteMemberQuery>,
+    Extension(current_user): Extension<User>,
+    Extension(current_metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware
+) -> Result<Json<UserInChatDTO>, AppError> {
+    debug!("Muting member in chat");
+    // 1. Verificare che current_user abbia il permesso ModerateMembers, altrimenti FORBIDDEN (fail-fast)
+    // 2. Validare la durata
+    // 3. Verificare che non si stia cercando di silenziare sé stessi
+    // 4. Recuperare i metadata del target, se non è membro NOT_FOUND
+    // 5. Verificare che il target non sia l'Owner, altrimenti FORBIDDEN
+    // 6. Salvare muted_until = adesso + durata (sovrascrive un eventuale silenziamento precedente) e registrarlo nell'audit log
+    // 7. Ritornare il membro aggiornato
+
+    require_permission(&state, &current_metadata, ChatPermission::ModerateMembers).await?;
+
+    params.validate()?;
+
+    if user_id == current_user.user_id {
+        warn!("User attempted to mute themselves");
+        return Err(AppError::bad_request("You cannot mute yourself"));
+    }
+
+    let mut target_meta = state.meta.read(&(user_id, chat_id)).await?.ok_or_else(|| {
+        warn!(
+            "Target user {} is not a member of chat {}",
+            user_id, chat_id
+        );
+        AppError::not_found("The user to be muted is not a member of this chat")
+    })?;
+
+    if matches!(target_meta.user_role, Some(UserRole::Owner)) {
+        warn!("Attempted to mute owner of chat");
+        return Err(AppError::forbidden("You cannot mute the owner of the chat"));
+    }
+    ensure_can_act_on(&current_metadata, &target_meta)?;
+
+    let muted_until = Utc::now() + Duration::seconds(params.duration);
+    state
+        .meta
+        .set_muted_until(&user_id, &chat_id, Some(muted_until))
+        .await?;
+    target_meta.muted_until = Some(muted_until);
+
+    audit::record(
+        &state,
+        chat_id,
+        current_user.user_id,
+        AuditAction::MemberMuted,
+        Some(user_id),
+        Some(json!({ "muted_until": muted_until, "duration": params.duration })),
+    )
+    .await?;
+
+    info!("Member muted until {}", muted_until);
+    Ok(Json(UserInChatDTO::from(target_meta)))
 }
 
+/// Rimuove un membro dalla chat
+#[utoipa::path(
+    delete,
+    path = "/chats/{chat_id}/members/{user_id}",
+    tag = "members",
+    params(
+        ("chat_id" = i32, Path, description = "ID della chat"),
+        ("user_id" = i32, Path, description = "ID dell'utente"),
+    ),
+    responses(
+        (status = 200, description = "Membro rimosso"),
+        (status = 403, description = "Permessi insufficienti"),
+        (status = 404, description = "Utente non membro della chat"),
+    )
+)]
 #[instrument(skip(state, current_user, current_metadata), fields(chat_id = %chat_id, removing_user = %current_user.user_id, target_user = %user_id))]
 pub async fn remove_member(
     State(state): State<Arc<AppState>>,
@@ -483,16 +1015,16 @@ pub async fn remove_member(
     debug!("Removing member from chat");
     // 1. Estrarre chat_id e user_id dal path dalla URL
     // 2. Ottenere l'utente corrente e metadata dall'Extension
-    // 3. Verificare che current_user sia Admin o Owner, altrimenti ritornare errore FORBIDDEN (fail-fast)
+    // 3. Verificare che current_user abbia il permesso RemoveMembers, altrimenti ritornare errore FORBIDDEN (fail-fast)
     // 4. Recuperare metadata dell'utente target per verificare membership (singola query)
     // 5. Verificare che non si stia cercando di rimuovere l'Owner, altrimenti ritornare errore FORBIDDEN (controllo in memoria)
-    // 6. Cancellare i metadata dell'utente target per questa chat dal database
+    // 6. Cancellare i metadata dell'utente target per questa chat dal database e registrare la rimozione nell'audit log
     // 7. Creare un messaggio di sistema che notifica la rimozione del membro (i messaggi dell'utente rimangono nel DB)
     // 8. Salvare il messaggio nel database dopo validazione
     // 9. Inviare il messaggio tramite WebSocket a tutti i membri online della chat (operazione non bloccante)
     // 10. Ritornare StatusCode::OK
 
-    require_role(&current_metadata, &[UserRole::Admin, UserRole::Owner])?;
+    require_permission(&state, &current_metadata, ChatPermission::RemoveMembers).await?;
 
     let target_meta = state.meta.read(&(user_id, chat_id)).await?.ok_or_else(|| {
         warn!(
@@ -509,13 +1041,27 @@ pub async fn remove_member(
             "You cannot remove the owner of the chat",
         ));
     }
+    ensure_can_act_on(&current_metadata, &target_meta)?;
 
     state.meta.delete(&(user_id, chat_id)).await?;
 
+    audit::record(
+        &state,
+        chat_id,
+        current_user.user_id,
+        AuditAction::MemberRemoved,
+        Some(user_id),
+        None,
+    )
+    .await?;
+
     // Dopo la rimozione del membro, controllare se ci sono messaggi da eliminare fisicamente
     // Recupera tutti i metadata rimanenti della chat
-    let remaining_metadata = state.meta.find_many_by_chat_id(&chat_id).await?;
-    
+    let remaining_metadata = state
+        .meta
+        .read_many(&MemberFilter::in_chat(chat_id).from_primary(), &Page::ALL)
+        .await?;
+
     if !remaining_metadata.is_empty() {
         // Trova il messages_visible_from più vecchio tra i membri rimasti
         let oldest_visible_date = remaining_metadata
@@ -562,22 +1108,47 @@ pub async fn remove_member(
         )),
         message_type: Some(MessageType::SystemMessage),
         created_at: Some(Utc::now()),
+        deleted_at: None,
+        reply_to_message_id: None,
+        client_msg_id: None,
+        attachment_id: None,
+        content_format: None,
     };
 
-    let create_dto = CreateMessageDTO::try_from(message_dto.clone())
+    let create_dto = CreateMessageDTO::try_from(message_dto)
         .map_err(|_| AppError::bad_request("Failed to build message dto"))?;
 
     create_dto
         .validate()
         .map_err(|_| AppError::bad_request("Validation error"))?;
 
-    let _saved_message = state.msg.create(&create_dto).await?;
+    let saved_message = state.msg.create(&create_dto).await?;
 
-    let _ = state.chats_online.send(&chat_id, Arc::new(message_dto));
+    let _ = state
+        .chats_online
+        .send(&chat_id, Arc::new(MessageDTO::from(saved_message.clone())));
+    enqueue_for_offline_members(&state, &saved_message).await;
     info!("Member successfully removed from chat");
     Ok(())
 }
 
+/// Cambia il ruolo di un membro
+#[utoipa::path(
+    patch,
+    path = "/chats/{chat_id}/members/{user_id}/role",
+    tag = "members",
+    params(
+        ("chat_id" = i32, Path, description = "ID della chat"),
+        ("user_id" = i32, Path, description = "ID dell'utente"),
+    ),
+    request_body = UserRole,
+    responses(
+        (status = 200, description = "Ruolo aggiornato"),
+        (status = 403, description = "Permessi insufficienti"),
+        (status = 404, description = "Utente non membro della chat"),
+        (status = 409, description = "Ruolo modificato da un altro amministratore nel frattempo"),
+    )
+)]
 #[debug_handler]
 #[instrument(skip(state, current_user, current_metadata, body), fields(chat_id = %chat_id, updating_user = %current_user.user_id, target_user = %user_id, new_role = ?body))]
 pub async fn update_member_role(
@@ -594,7 +1165,8 @@ pub async fn update_member_role(
     // 4. Recuperare metadata dell'utente target per verificare membership (singola query)
     // 5. Verificare le regole di promozione: Owner può modificare tutti, Admin può modificare solo Member (controllo in memoria)
     // 6. Admin non può assegnare ruolo Owner (controllo in memoria)
-    // 7. Aggiornare il campo user_role nei metadata dell'utente target
+    // 7. Aggiornare il campo user_role nei metadata dell'utente target se la versione letta al punto 4
+    //    è ancora quella corrente (altrimenti CONFLICT) e registrare il cambio nell'audit log
     // 8. Creare un messaggio di sistema che notifica il cambio di ruolo
     // 9. Salvare il messaggio nel database dopo validazione
     // 10. Inviare il messaggio tramite WebSocket a tutti i membri online della chat (operazione non bloccante)
@@ -636,9 +1208,19 @@ pub async fn update_member_role(
 
     state
         .meta
-        .update_user_role(&user_id, &chat_id, &body)
+        .update_user_role(&user_id, &chat_id, &body, &target_meta.version)
         .await?;
 
+    audit::record(
+        &state,
+        chat_id,
+        current_user.user_id,
+        AuditAction::MemberRoleChanged,
+        Some(user_id),
+        Some(json!({ "from": target_meta.user_role, "to": body })),
+    )
+    .await?;
+
     let target_user_opt = state.user.read(&user_id).await?;
 
     let target_username = target_user_opt
@@ -656,22 +1238,45 @@ pub async fn update_member_role(
         )),
         message_type: Some(MessageType::SystemMessage),
         created_at: Some(Utc::now()),
+        deleted_at: None,
+        reply_to_message_id: None,
+        client_msg_id: None,
+        attachment_id: None,
+        content_format: None,
     };
 
-    let create_dto = CreateMessageDTO::try_from(message_dto.clone())
+    let create_dto = CreateMessageDTO::try_from(message_dto)
         .map_err(|_| AppError::bad_request("Failed to build message dto"))?;
 
     create_dto
         .validate()
         .map_err(|_| AppError::bad_request("Validation error"))?;
 
-    let _saved_message = state.msg.create(&create_dto).await?;
+    let saved_message = state.msg.create(&create_dto).await?;
 
-    let _ = state.chats_online.send(&chat_id, Arc::new(message_dto));
+    let _ = state
+        .chats_online
+        .send(&chat_id, Arc::new(MessageDTO::from(saved_message.clone())));
+    enqueue_for_offline_members(&state, &saved_message).await;
     info!("Member role updated successfully");
     Ok(())
 }
 
+/// Trasferisce la proprietà della chat a un altro membro
+#[utoipa::path(
+    patch,
+    path = "/chats/{chat_id}/transfer_ownership/{new_owner_id}",
+    tag = "members",
+    params(
+        ("chat_id" = i32, Path, description = "ID della chat"),
+        ("new_owner_id" = i32, Path, description = "ID del nuovo proprietario"),
+    ),
+    responses(
+        (status = 200, description = "Proprietà trasferita"),
+        (status = 403, description = "Solo il proprietario può trasferire la chat"),
+        (status = 404, description = "Utente non membro della chat"),
+    )
+)]
 #[instrument(skip(state, current_user, metadata), fields(chat_id = %chat_id, current_owner = %current_user.user_id, new_owner = %new_owner_id))]
 pub async fn transfer_ownership(
     State(state): State<Arc<AppState>>,
@@ -686,9 +1291,9 @@ pub async fn transfer_ownership(
     // 4. Verificare che current_user non stia trasferendo a se stesso (controllo in memoria)
     // 5. Verificare che la chat esista e sia di tipo Group (le chat private non hanno owner)
     // 6. Verificare che il nuovo owner esista come utente nel sistema
-    // 7. Trasferire ownership con metodo atomico: current_user diventa Admin, new_owner diventa Owner
-    // 8. Creare un messaggio di sistema che notifica il trasferimento di ownership
-    // 9. Salvare il messaggio nel database dopo validazione
+    // 7. Creare un messaggio di sistema che notifica il trasferimento di ownership e validarlo
+    // 8. Trasferire ownership con metodo atomico: current_user diventa Admin, new_owner diventa Owner
+    // 9. Registrarlo nell'audit log e salvare il messaggio, nella stessa unit of work del passo 8
     // 10. Inviare il messaggio tramite WebSocket a tutti i membri online della chat (operazione non bloccante)
     // 11. Ritornare StatusCode::OK
 
@@ -734,17 +1339,6 @@ pub async fn transfer_ownership(
         ));
     }
 
-    debug!("Performing ownership transfer");
-    // Trasferisce la proprietà dal current_user al nuovo owner
-    state
-        .meta
-        .transfer_ownership(&current_user.user_id, &new_owner_id, &chat_id)
-        .await
-        .map_err(|e| {
-            error!("Failed to transfer ownership: {:?}", e);
-            AppError::internal_server_error("Failed to transfer ownership")
-        })?;
-
     let message_dto = MessageDTO {
         message_id: None,
         chat_id: Some(chat_id),
@@ -755,9 +1349,14 @@ pub async fn transfer_ownership(
         )),
         message_type: Some(MessageType::SystemMessage),
         created_at: Some(Utc::now()),
+        deleted_at: None,
+        reply_to_message_id: None,
+        client_msg_id: None,
+        attachment_id: None,
+        content_format: None,
     };
 
-    let create_dto = CreateMessageDTO::try_from(message_dto.clone()).map_err(|e| {
+    let create_dto = CreateMessageDTO::try_from(message_dto).map_err(|e| {
         AppError::bad_request("Failed to build message dto").with_details(e.to_string())
     })?;
 
@@ -765,13 +1364,51 @@ pub async fn transfer_ownership(
         .validate()
         .map_err(|e| AppError::bad_request("Validation error").with_details(e.to_string()))?;
 
-    let _saved_message = state.msg.create(&create_dto).await?;
+    debug!("Performing ownership transfer");
+    // Trasferisce la proprietà dal current_user al nuovo owner; scambio dei ruoli, audit log e
+    // messaggio di sistema sono scritti nella stessa unit of work
+    let mut uow = state.begin().await?;
+    state
+        .meta
+        .transfer_ownership_in(&mut uow, &current_user.user_id, &new_owner_id, &chat_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to transfer ownership: {:?}", e);
+            AppError::internal_server_error("Failed to transfer ownership")
+        })?;
+
+    audit::record_in(
+        &state,
+        &mut uow,
+        chat_id,
+        current_user.user_id,
+        AuditAction::OwnershipTransferred,
+        Some(new_owner_id),
+        None,
+    )
+    .await?;
+
+    let saved_message = state.msg.create_in(&mut uow, &create_dto).await?;
+    uow.commit().await?;
 
-    let _ = state.chats_online.send(&chat_id, Arc::new(message_dto));
+    let _ = state
+        .chats_online
+        .send(&chat_id, Arc::new(MessageDTO::from(saved_message.clone())));
+    enqueue_for_offline_members(&state, &saved_message).await;
     info!("Ownership transferred successfully");
     Ok(())
 }
 
+/// Nasconde all'utente i messaggi della chat inviati finora
+#[utoipa::path(
+    post,
+    path = "/chats/{chat_id}/clean",
+    tag = "members",
+    params(("chat_id" = i32, Path, description = "ID della chat")),
+    responses(
+        (status = 200, description = "Cronologia nascosta"),
+    )
+)]
 #[instrument(skip(state, current_user, _metadata), fields(user_id = %current_user.user_id, chat_id = %chat_id))]
 pub async fn clean_chat(
     State(state): State<Arc<AppState>>,
@@ -797,6 +1434,7 @@ pub async fn clean_chat(
         user_role: None,
         messages_visible_from: Some(now),
         messages_received_until: Some(now),
+        version: None,
     };
 
     state
@@ -806,8 +1444,11 @@ pub async fn clean_chat(
 
     // Recupera tutti i metadata della chat per trovare la data più vecchia
     // da cui un utente può ancora vedere i messaggi
-    let all_metadata = state.meta.find_many_by_chat_id(&chat_id).await?;
-    
+    let all_metadata = state
+        .meta
+        .read_many(&MemberFilter::in_chat(chat_id).from_primary(), &Page::ALL)
+        .await?;
+
     if !all_metadata.is_empty() {
         // Trova il messages_visible_from PIÙ VECCHIO tra tutti i membri
         // Possiamo eliminare fisicamente solo i messaggi antecedenti a questa data,
@@ -3,25 +3,63 @@
 //! Questo modulo organizza i service handlers in sotto-moduli separati per una migliore manutenibilità.
 //! Ogni modulo gestisce gli endpoint HTTP per una specifica funzionalità.
 
+pub mod admin;
+pub mod attachment;
+pub mod audit;
 pub mod auth;
 pub mod chat;
+pub mod draft;
+pub mod export;
+pub mod health;
+pub mod invite_link;
+pub mod join_request;
 pub mod membership;
+pub mod oidc;
+pub mod report;
+pub mod role;
+pub mod search;
+pub mod sync;
+pub mod translation;
 pub mod user;
+pub mod user_keys;
+pub mod webhook;
 
 // Re-exports per facilitare l'import
-pub use auth::{login_user, register_user};
-pub use chat::{create_chat, get_chat_messages, list_chats};
+pub use admin::{
+    admin_delete_chat, admin_delete_message, admin_export_chat, admin_export_user_messages,
+    admin_get_chat, admin_get_runtime_config, admin_list_reports, admin_list_users,
+    admin_online_stats, admin_reload_config, admin_resolve_report, admin_stats_stream,
+};
+pub use attachment::{download_attachment, start_attachment_collector, upload_attachment};
+pub use audit::list_audit_log;
+pub use auth::{login_user, logout_user, oidc_callback, oidc_login, refresh_tokens, register_user};
+pub use chat::{
+    create_chat, delete_chat, delete_message, discover_chats, edit_message, get_chat_messages,
+    get_message_history, list_chat_overview, list_chats, list_pinned_messages, list_unread_counts,
+    pin_chat, pin_message, poll_chat_messages, search_chat_messages, unpin_chat, unpin_message,
+    update_chat, update_chat_avatar,
+};
+pub use draft::{get_draft, save_draft};
+pub use export::{download_data_export, export_chat, get_data_export_status, request_data_export};
+pub use health::{healthz, livez, readyz};
+pub use invite_link::{
+    create_invite_link, join_by_invite_link, list_invite_links, revoke_invite_link,
+};
+pub use join_request::{join_chat, list_join_requests, respond_to_join_request};
 pub use membership::{
-    clean_chat, invite_to_chat, leave_chat, list_chat_members, list_pending_invitations,
-    remove_member, respond_to_invitation, transfer_ownership, update_member_role,
+    ban_member, clean_chat, invite_to_chat, leave_chat, list_chat_members,
+    list_pending_invitations, list_sent_invitations, mute_member, remove_member,
+    respond_to_invitation, transfer_ownership, update_member_role,
 };
-pub use user::{delete_my_account, get_my_user, get_user_by_id, search_user_with_username};
-
-use crate::AppState;
-use axum::{extract::State, http::StatusCode, response::IntoResponse};
-use std::sync::Arc;
-
-/// Root endpoint - health check
-pub async fn root(State(_state): State<Arc<AppState>>) -> impl IntoResponse {
-    (StatusCode::OK, "Server is running!")
-}
+pub use report::{report_message, report_user};
+pub use role::{assign_member_role, create_chat_role, delete_chat_role, list_chat_roles};
+pub use search::global_search;
+pub use sync::sync_changes;
+pub use translation::translate_message;
+pub use user::{
+    delete_my_account, disconnect_my_session, get_my_privacy, get_my_profile, get_my_user,
+    get_user_by_id, list_my_sessions, purge_expired_deactivations, search_user_with_username,
+    set_my_status, update_my_privacy, update_my_profile,
+};
+pub use user_keys::{get_key_bundle, upload_keys};
+pub use webhook::{create_webhook, delete_webhook, list_webhooks, post_webhook_message};
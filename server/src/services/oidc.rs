@@ -0,0 +1,405 @@
+//! OIDC services - Login SSO tramite un provider OpenID Connect
+//!
+//! Implementa l'authorization code flow con PKCE: GET /auth/oidc/login reindirizza al
+//! provider, che poi richiama GET /auth/oidc/callback con il codice da scambiare per
+//! l'id_token. Gli handler sono in `services::auth`; qui c'è solo il dialogo con il provider.
+//!
+//! I login in corso stanno nella cache condivisa: con `REDIS_URL` la callback può arrivare
+//! a un nodo diverso da quello che ha avviato il login, senza Redis restano nella memoria
+//! del processo e il flusso funziona solo con una singola istanza (o con sessioni sticky).
+
+use crate::core::SharedCache;
+use crate::core::cache::InMemoryCache;
+use crate::core::config::OidcConfig;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use jsonwebtoken::jwk::{Jwk, JwkSet};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode, decode_header};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{OnceCell, RwLock};
+use tracing::{debug, instrument, warn};
+
+/// Tempo concesso all'utente per completare il login sul provider
+pub const PENDING_LOGIN_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Cookie che lega il parametro `state` al browser che ha avviato il login
+pub const OIDC_STATE_COOKIE: &str = "oidc_state";
+
+/// Login in corso tenuti in memoria quando non c'è una cache condivisa
+const MAX_PENDING_LOGINS: u64 = 10_000;
+
+/// Dopo quanto tempo le chiavi pubblicate dal provider vengono rilette
+const JWKS_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Intervallo minimo tra due letture del JWKS causate da un `kid` sconosciuto,
+/// così un token con un `kid` inventato non fa interrogare il provider a ogni richiesta
+const JWKS_MIN_REFRESH: Duration = Duration::from_secs(60);
+
+/// Errori del login OIDC
+#[derive(Debug)]
+pub enum OidcError {
+    /// Parametro `state` sconosciuto o scaduto (login non iniziato da qui o troppo vecchio)
+    InvalidState,
+    /// Il provider non risponde o risponde in modo inatteso
+    Provider(String),
+    /// id_token non valido (algoritmo, firma, issuer, audience, nonce o scadenza)
+    InvalidToken(String),
+    /// La cache dei login in corso non è raggiungibile
+    Store(String),
+}
+
+/// Identità dell'utente autenticato dal provider
+#[derive(Debug, Clone)]
+pub struct OidcIdentity {
+    pub issuer: String,
+    pub subject: String,
+    pub preferred_username: Option<String>,
+    pub email: Option<String>,
+}
+
+/// Endpoint pubblicati dal provider in `/.well-known/openid-configuration`
+#[derive(Deserialize, Debug, Clone)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+    /// Algoritmi con cui il provider firma gli id_token
+    #[serde(default)]
+    id_token_signing_alg_values_supported: Vec<String>,
+}
+
+/// Login iniziato e non ancora concluso, salvato nella cache con chiave `oidc_state:<state>`
+#[derive(Serialize, Deserialize)]
+struct PendingLogin {
+    nonce: String,
+    code_verifier: String,
+}
+
+/// Chiavi pubbliche del provider lette da `jwks_uri`
+struct CachedJwks {
+    keys: JwkSet,
+    fetched_at: Instant,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Deserialize)]
+struct IdTokenClaims {
+    iss: String,
+    sub: String,
+    nonce: Option<String>,
+    preferred_username: Option<String>,
+    email: Option<String>,
+}
+
+/// Client del provider OIDC configurato
+pub struct OidcClient {
+    http: reqwest::Client,
+    config: OidcConfig,
+    metadata: OnceCell<ProviderMetadata>,
+    jwks: RwLock<Option<CachedJwks>>,
+    pending: SharedCache,
+}
+
+impl OidcClient {
+    pub fn new(config: OidcConfig) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            config,
+            metadata: OnceCell::new(),
+            jwks: RwLock::new(None),
+            pending: Arc::new(InMemoryCache::new(MAX_PENDING_LOGINS)),
+        }
+    }
+
+    /// Salva i login in corso nella cache condivisa tra le istanze
+    ///
+    /// # Arguments
+    /// * `cache` - Cache costruita con `build_shared_cache` dalla configurazione
+    pub fn with_shared_cache(mut self, cache: SharedCache) -> Self {
+        self.pending = cache;
+        self
+    }
+
+    /// Metadata del provider, letti alla prima richiesta e poi tenuti in cache
+    async fn metadata(&self) -> Result<&ProviderMetadata, OidcError> {
+        self.metadata
+            .get_or_try_init(|| async {
+                let url = format!(
+                    "{}/.well-known/openid-configuration",
+                    self.config.issuer_url.trim_end_matches('/')
+                );
+                debug!("Fetching OIDC discovery document");
+                self.http
+                    .get(&url)
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .map_err(|e| OidcError::Provider(format!("Discovery failed: {}", e)))?
+                    .json::<ProviderMetadata>()
+                    .await
+                    .map_err(|e| OidcError::Provider(format!("Invalid discovery document: {}", e)))
+            })
+            .await
+    }
+
+    /// Inizia un nuovo login e ritorna l'URL del provider a cui reindirizzare l'utente
+    /// insieme allo `state`, da salvare nel cookie `OIDC_STATE_COOKIE`
+    #[instrument(skip(self))]
+    pub async fn authorization_url(&self) -> Result<(String, String), OidcError> {
+        let metadata = self.metadata().await?;
+
+        let state = random_token();
+        let nonce = random_token();
+        let code_verifier = random_token();
+        let code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()));
+
+        let url = Url::parse_with_params(
+            &metadata.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", self.config.client_id.as_str()),
+                ("redirect_uri", self.config.redirect_url.as_str()),
+                ("scope", "openid profile email"),
+                ("state", state.as_str()),
+                ("nonce", nonce.as_str()),
+                ("code_challenge", code_challenge.as_str()),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .map_err(|e| OidcError::Provider(format!("Invalid authorization endpoint: {}", e)))?;
+
+        // i login abbandonati scadono da soli con il TTL della cache
+        let pending = serde_json::to_string(&PendingLogin {
+            nonce,
+            code_verifier,
+        })
+        .map_err(|e| OidcError::Store(e.to_string()))?;
+        self.pending
+            .set(&pending_key(&state), &pending, PENDING_LOGIN_TTL)
+            .await
+            .map_err(OidcError::Store)?;
+
+        Ok((url.to_string(), state))
+    }
+
+    /// Conclude il login: scambia il codice per l'id_token e ne verifica firma e claims
+    #[instrument(skip(self, code, state))]
+    pub async fn exchange_code(&self, code: &str, state: &str) -> Result<OidcIdentity, OidcError> {
+        // lo state vale una sola volta, anche con più richieste concorrenti
+        let pending = self
+            .pending
+            .take(&pending_key(state))
+            .await
+            .map_err(OidcError::Store)?
+            .ok_or(OidcError::InvalidState)?;
+        let pending: PendingLogin =
+            serde_json::from_str(&pending).map_err(|e| OidcError::Store(e.to_string()))?;
+
+        let metadata = self.metadata().await?;
+
+        let token_response = self
+            .http
+            .post(&metadata.token_endpoint)
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", self.config.redirect_url.as_str()),
+                ("client_id", self.config.client_id.as_str()),
+                ("client_secret", self.config.client_secret.as_str()),
+                ("code_verifier", pending.code_verifier.as_str()),
+            ])
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| OidcError::Provider(format!("Token exchange failed: {}", e)))?
+            .json::<TokenResponse>()
+            .await
+            .map_err(|e| OidcError::Provider(format!("Invalid token response: {}", e)))?;
+
+        let claims = self
+            .validate_id_token(metadata, &token_response.id_token)
+            .await?;
+
+        if claims.nonce.as_deref() != Some(pending.nonce.as_str()) {
+            warn!("OIDC nonce mismatch");
+            return Err(OidcError::InvalidToken("Nonce mismatch".to_string()));
+        }
+
+        Ok(OidcIdentity {
+            issuer: claims.iss,
+            subject: claims.sub,
+            preferred_username: claims.preferred_username,
+            email: claims.email,
+        })
+    }
+
+    /// Verifica algoritmo, firma, issuer, audience e scadenza dell'id_token.
+    /// L'algoritmo deve essere tra quelli dichiarati dal provider; le firme HMAC usano il
+    /// client secret e sono accettate solo con `OIDC_ALLOW_HMAC`, quelle asimmetriche le
+    /// chiavi pubblicate dal provider in `jwks_uri`
+    async fn validate_id_token(
+        &self,
+        metadata: &ProviderMetadata,
+        id_token: &str,
+    ) -> Result<IdTokenClaims, OidcError> {
+        let header = decode_header(id_token)
+            .map_err(|e| OidcError::InvalidToken(format!("Malformed id_token: {}", e)))?;
+
+        if !self.allowed_algorithms(metadata).contains(&header.alg) {
+            return Err(OidcError::InvalidToken(format!(
+                "Signing algorithm {:?} is not accepted",
+                header.alg
+            )));
+        }
+
+        let key = if is_hmac(header.alg) {
+            DecodingKey::from_secret(self.config.client_secret.as_bytes())
+        } else {
+            let jwk = self.signing_key(metadata, header.kid.as_deref()).await?;
+            // una chiave dichiarata per un algoritmo non vale per gli altri
+            if let Some(key_algorithm) = jwk.common.key_algorithm
+                && key_algorithm.to_string().parse::<Algorithm>().ok() != Some(header.alg)
+            {
+                return Err(OidcError::InvalidToken(format!(
+                    "Signing key is for {} but the id_token uses {:?}",
+                    key_algorithm, header.alg
+                )));
+            }
+            DecodingKey::from_jwk(&jwk)
+                .map_err(|e| OidcError::InvalidToken(format!("Unusable signing key: {}", e)))?
+        };
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&metadata.issuer]);
+        validation.set_audience(&[&self.config.client_id]);
+
+        decode::<IdTokenClaims>(id_token, &key, &validation)
+            .map(|data| data.claims)
+            .map_err(|e| OidcError::InvalidToken(format!("Invalid id_token: {}", e)))
+    }
+
+    /// Algoritmi accettati per l'id_token: quelli dichiarati dal provider nel discovery
+    /// document (RS256 se non ne dichiara, come da specifica), senza HMAC se non abilitato
+    fn allowed_algorithms(&self, metadata: &ProviderMetadata) -> Vec<Algorithm> {
+        let advertised: Vec<Algorithm> =
+            if metadata.id_token_signing_alg_values_supported.is_empty() {
+                vec![Algorithm::RS256]
+            } else {
+                // "none" e gli algoritmi non supportati vengono ignorati
+                metadata
+                    .id_token_signing_alg_values_supported
+                    .iter()
+                    .filter_map(|alg| alg.parse().ok())
+                    .collect()
+            };
+        advertised
+            .into_iter()
+            .filter(|alg| self.config.allow_hmac || !is_hmac(*alg))
+            .collect()
+    }
+
+    /// Chiave pubblica del provider con cui verificare la firma, dal JWKS in cache.
+    /// Il JWKS viene riletto quando scade o quando il `kid` non è noto (il provider
+    /// potrebbe aver ruotato le chiavi), ma non più spesso di `JWKS_MIN_REFRESH`
+    async fn signing_key(
+        &self,
+        metadata: &ProviderMetadata,
+        kid: Option<&str>,
+    ) -> Result<Jwk, OidcError> {
+        if let Some(cached) = self.jwks.read().await.as_ref() {
+            let age = cached.fetched_at.elapsed();
+            match select_key(&cached.keys, kid) {
+                Ok(jwk) if age < JWKS_TTL => return Ok(jwk),
+                Err(e) if age < JWKS_MIN_REFRESH => return Err(e),
+                _ => {}
+            }
+        }
+
+        debug!("Fetching OIDC signing keys");
+        let keys = self
+            .http
+            .get(&metadata.jwks_uri)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| OidcError::Provider(format!("JWKS fetch failed: {}", e)))?
+            .json::<JwkSet>()
+            .await
+            .map_err(|e| OidcError::Provider(format!("Invalid JWKS: {}", e)))?;
+
+        let selected = select_key(&keys, kid);
+        *self.jwks.write().await = Some(CachedJwks {
+            keys,
+            fetched_at: Instant::now(),
+        });
+        selected
+    }
+}
+
+/// Chiave del JWKS indicata da `kid`. Senza `kid` il token è accettato solo se il
+/// provider pubblica una sola chiave, altrimenti non si può sapere quale abbia firmato
+fn select_key(jwks: &JwkSet, kid: Option<&str>) -> Result<Jwk, OidcError> {
+    match kid {
+        Some(kid) => jwks
+            .find(kid)
+            .cloned()
+            .ok_or_else(|| OidcError::InvalidToken("Unknown signing key".to_string())),
+        None => match jwks.keys.as_slice() {
+            [jwk] => Ok(jwk.clone()),
+            _ => Err(OidcError::InvalidToken(
+                "id_token has no kid and the provider publishes several keys".to_string(),
+            )),
+        },
+    }
+}
+
+fn is_hmac(alg: Algorithm) -> bool {
+    matches!(alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512)
+}
+
+fn pending_key(state: &str) -> String {
+    format!("oidc_state:{}", state)
+}
+
+/// Stringa casuale URL-safe per state, nonce e code verifier
+fn random_token() -> String {
+    format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+/// Ricava uno username valido (lettere, numeri e underscore, 3-50 caratteri)
+/// dai claims dell'identità, senza garantirne l'unicità
+pub fn username_hint(identity: &OidcIdentity) -> String {
+    let source = identity
+        .preferred_username
+        .as_deref()
+        .or_else(|| {
+            identity
+                .email
+                .as_deref()
+                .and_then(|email| email.split('@').next())
+        })
+        .unwrap_or("user");
+
+    let mut username: String = source
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .take(40)
+        .collect();
+    while username.len() < 3 {
+        username.push('_');
+    }
+    username
+}
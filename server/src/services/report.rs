@@ -0,0 +1,157 @@
+//! Report services - Segnalazioni di messaggi e utenti da parte degli utenti
+//!
+//! Le segnalazioni finiscono nella coda degli amministratori del server
+//! (GET /admin/reports), che le chiudono da `services::admin`.
+
+use crate::core::{AppError, AppState};
+use crate::dtos::{CreateReportDTO, ReportDTO, SubmitReportDTO};
+use crate::entities::{MessageType, User};
+use crate::repositories::Create;
+use axum::{
+    Extension,
+    extract::{Json, Path, State},
+};
+use std::sync::Arc;
+use tracing::{debug, info, instrument, warn};
+use validator::Validate;
+
+/// Segnala un messaggio ai moderatori del server
+#[utoipa::path(
+    post,
+    path = "/chats/{chat_id}/messages/{message_id}/report",
+    tag = "messages",
+    params(
+        ("chat_id" = i32, Path, description = "ID della chat"),
+        ("message_id" = i32, Path, description = "ID del messaggio"),
+    ),
+    request_body = SubmitReportDTO,
+    responses(
+        (status = 200, description = "Segnalazione registrata", body = ReportDTO),
+        (status = 400, description = "Dati non validi"),
+        (status = 404, description = "Messaggio non trovato"),
+    )
+)]
+#[instrument(skip(state, current_user, body), fields(chat_id = %chat_id, message_id = %message_id, user_id = %current_user.user_id))]
+pub async fn report_message(
+    State(state): State<Arc<AppState>>,
+    Path((chat_id, message_id)): Path<(i32, i32)>,
+    Extension(current_user): Extension<User>, // la membership è verificata dal chat_membership_middleware
+    Json(body): Json<SubmitReportDTO>,
+) -> Result<Json<ReportDTO>, AppError> {
+    debug!("Reporting message");
+    // 1. Validare i dettagli della segnalazione
+    // 2. Recuperare il messaggio, NOT_FOUND se non esiste, è di un'altra chat o è stato eliminato
+    // 3. Non si possono segnalare i messaggi di sistema né i propri messaggi
+    // 4. CONFLICT se l'utente ha già una segnalazione in attesa per lo stesso messaggio
+    // 5. Salvare la segnalazione, con l'autore del messaggio come utente segnalato
+
+    body.validate()?;
+
+    let message = state
+        .msg
+        .read(&message_id)
+        .await?
+        .filter(|m| m.chat_id == chat_id && m.deleted_at.is_none())
+        .ok_or_else(|| {
+            warn!("Message {} not found in chat {}", message_id, chat_id);
+            AppError::not_found("Message not found")
+        })?;
+
+    if message.message_type == MessageType::SystemMessage {
+        warn!("User attempted to report a system message");
+        return Err(AppError::bad_request("System messages cannot be reported"));
+    }
+
+    if message.sender_id == current_user.user_id {
+        warn!("User attempted to report their own message");
+        return Err(AppError::bad_request("You cannot report your own message"));
+    }
+
+    if state
+        .report
+        .has_pending_report(&current_user.user_id, &message.sender_id, Some(message_id))
+        .await?
+    {
+        warn!("Message already reported by this user");
+        return Err(AppError::conflict("Message already reported"));
+    }
+
+    let report = state
+        .report
+        .create(&CreateReportDTO {
+            reporter_id: Some(current_user.user_id),
+            reported_user_id: message.sender_id,
+            chat_id: Some(chat_id),
+            message_id: Some(message_id),
+            reason: body.reason,
+            details: body.details,
+        })
+        .await?;
+
+    info!("Message reported with report id {}", report.report_id);
+    Ok(Json(ReportDTO::from(report)))
+}
+
+/// Segnala un utente ai moderatori del server
+#[utoipa::path(
+    post,
+    path = "/users/{user_id}/report",
+    tag = "users",
+    params(("user_id" = i32, Path, description = "ID dell'utente")),
+    request_body = SubmitReportDTO,
+    responses(
+        (status = 200, description = "Segnalazione registrata", body = ReportDTO),
+        (status = 400, description = "Dati non validi"),
+        (status = 404, description = "Utente non trovato"),
+    )
+)]
+#[instrument(skip(state, current_user, body), fields(reported_user_id = %user_id, user_id = %current_user.user_id))]
+pub async fn report_user(
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<i32>,
+    Extension(current_user): Extension<User>,
+    Json(body): Json<SubmitReportDTO>,
+) -> Result<Json<ReportDTO>, AppError> {
+    debug!("Reporting user");
+    // 1. Validare i dettagli della segnalazione
+    // 2. Non ci si può segnalare da soli
+    // 3. Verificare che l'utente segnalato esista, NOT_FOUND altrimenti
+    // 4. CONFLICT se l'utente ha già una segnalazione in attesa per lo stesso utente
+    // 5. Salvare la segnalazione, senza chat né messaggio
+
+    body.validate()?;
+
+    if user_id == current_user.user_id {
+        warn!("User attempted to report themselves");
+        return Err(AppError::bad_request("You cannot report yourself"));
+    }
+
+    if state.user.read(&user_id).await?.is_none() {
+        warn!("User {} not found", user_id);
+        return Err(AppError::not_found("User not found"));
+    }
+
+    if state
+        .report
+        .has_pending_report(&current_user.user_id, &user_id, None)
+        .await?
+    {
+        warn!("User already reported by this user");
+        return Err(AppError::conflict("User already reported"));
+    }
+
+    let report = state
+        .report
+        .create(&CreateReportDTO {
+            reporter_id: Some(current_user.user_id),
+            reported_user_id: user_id,
+            chat_id: None,
+            message_id: None,
+            reason: body.reason,
+            details: body.details,
+        })
+        .await?;
+
+    info!("User reported with report id {}", report.report_id);
+    Ok(Json(ReportDTO::from(report)))
+}
@@ -0,0 +1,259 @@
+//! Role services - Ruoli della chat: le righe base Owner/Admin/Member e i ruoli personalizzati
+//! con permessi definiti dall'Owner
+
+use crate::core::{AppError, AppState, require_role};
+use crate::dtos::{AssignChatRoleDTO, ChatRoleDTO, CreateChatRoleDTO, UserInChatDTO};
+use crate::entities::{AuditAction, UserChatMetadata, UserRole};
+use crate::repositories::{ChatRoleFilter, Delete, Page, Read, ReadMany};
+use crate::services::audit;
+use axum::{
+    Extension,
+    extract::{Json, Path, State},
+};
+use serde_json::json;
+use std::sync::Arc;
+use tracing::{debug, info, instrument, warn};
+use validator::Validate;
+
+/// Ruoli della chat, prima quelli base e poi quelli personalizzati
+#[utoipa::path(
+    get,
+    path = "/chats/{chat_id}/roles",
+    tag = "roles",
+    params(("chat_id" = i32, Path, description = "ID della chat")),
+    responses(
+        (status = 200, description = "Ruoli della chat", body = Vec<ChatRoleDTO>),
+    )
+)]
+#[instrument(skip(state, _metadata), fields(chat_id = %chat_id))]
+pub async fn list_chat_roles(
+    State(state): State<Arc<AppState>>,
+    Path(chat_id): Path<i32>,
+    Extension(_metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware (verifica già la membership)
+) -> Result<Json<Vec<ChatRoleDTO>>, AppError> {
+    debug!("Listing chat roles");
+    // 1. Recuperare i ruoli della chat (base e personalizzati), visibili a tutti i membri
+
+    let roles = state
+        .role
+        .read_many(&ChatRoleFilter { chat_id }, &Page::ALL)
+        .await?;
+
+    Ok(Json(roles.into_iter().map(ChatRoleDTO::from).collect()))
+}
+
+/// Crea un ruolo personalizzato con i permessi indicati
+#[utoipa::path(
+    post,
+    path = "/chats/{chat_id}/roles",
+    tag = "roles",
+    params(("chat_id" = i32, Path, description = "ID della chat")),
+    request_body = CreateChatRoleDTO,
+    responses(
+        (status = 200, description = "Ruolo creato", body = ChatRoleDTO),
+        (status = 400, description = "Dati non validi"),
+        (status = 403, description = "Permessi insufficienti"),
+        (status = 409, description = "Nome già in uso"),
+    )
+)]
+#[instrument(skip(state, metadata, body), fields(chat_id = %chat_id, user_id = %metadata.user_id))]
+pub async fn create_chat_role(
+    State(state): State<Arc<AppState>>,
+    Path(chat_id): Path<i32>,
+    Extension(metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware
+    Json(mut body): Json<CreateChatRoleDTO>,
+) -> Result<Json<ChatRoleDTO>, AppError> {
+    debug!("Creating chat role");
+    // 1. Verificare che current_user sia l'Owner della chat, altrimenti FORBIDDEN
+    // 2. Validare il nome (spazi iniziali e finali esclusi)
+    // 3. Verificare che nella chat non esista già un ruolo con lo stesso nome, altrimenti CONFLICT
+    // 4. Salvare il ruolo, registrarlo nell'audit log e ritornarlo
+
+    require_role(&metadata, &[UserRole::Owner])?;
+
+    body.name = body.name.trim().to_string();
+    body.validate()?;
+
+    if state.role.name_exists(&chat_id, &body.name).await? {
+        warn!("Role {} already exists in chat", body.name);
+        return Err(AppError::conflict(
+            "A role with this name already exists in this chat",
+        ));
+    }
+
+    let role = state.role.create(&chat_id, &body).await?;
+
+    audit::record(
+        &state,
+        chat_id,
+        metadata.user_id,
+        AuditAction::CustomRoleCreated,
+        None,
+        Some(json!({
+            "role_id": role.role_id,
+            "name": role.name,
+            "permissions": role.permissions(),
+        })),
+    )
+    .await?;
+
+    info!("Chat role {} created", role.role_id);
+    Ok(Json(ChatRoleDTO::from(role)))
+}
+
+/// Elimina un ruolo personalizzato
+#[utoipa::path(
+    delete,
+    path = "/chats/{chat_id}/roles/{role_id}",
+    tag = "roles",
+    params(
+        ("chat_id" = i32, Path, description = "ID della chat"),
+        ("role_id" = i32, Path, description = "ID del ruolo"),
+    ),
+    responses(
+        (status = 200, description = "Ruolo eliminato"),
+        (status = 400, description = "I ruoli base non possono essere eliminati"),
+        (status = 403, description = "Permessi insufficienti"),
+        (status = 404, description = "Ruolo non trovato"),
+    )
+)]
+#[instrument(skip(state, metadata), fields(chat_id = %chat_id, role_id = %role_id))]
+pub async fn delete_chat_role(
+    State(state): State<Arc<AppState>>,
+    Path((chat_id, role_id)): Path<(i32, i32)>,
+    Extension(metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware
+) -> Result<(), AppError> {
+    debug!("Deleting chat role");
+    // 1. Verificare che current_user sia l'Owner della chat, altrimenti FORBIDDEN
+    // 2. Verificare che il ruolo appartenga a questa chat, altrimenti NOT_FOUND
+    // 3. Verificare che non sia un ruolo base, altrimenti BAD_REQUEST
+    // 4. Eliminare il ruolo: i membri che lo avevano tornano al ruolo base Member
+    // 5. Registrare l'eliminazione nell'audit log
+
+    require_role(&metadata, &[UserRole::Owner])?;
+
+    let role = state
+        .role
+        .read(&role_id)
+        .await?
+        .filter(|role| role.chat_id == chat_id)
+        .ok_or_else(|| {
+            warn!("Role {} not found in chat {}", role_id, chat_id);
+            AppError::not_found("Role not found")
+        })?;
+
+    if role.base_role.is_some() {
+        warn!("Attempted to delete base role {}", role_id);
+        return Err(AppError::bad_request("Base roles cannot be deleted"));
+    }
+
+    state.role.delete(&role_id).await?;
+    // i membri con questo ruolo sono passati alla riga base Member
+    state.invalidate_chat_members(chat_id).await;
+
+    audit::record(
+        &state,
+        chat_id,
+        metadata.user_id,
+        AuditAction::CustomRoleDeleted,
+        None,
+        Some(json!({ "role_id": role_id, "name": role.name })),
+    )
+    .await?;
+
+    info!("Chat role deleted");
+    Ok(())
+}
+
+/// Assegna o toglie un ruolo personalizzato a un membro
+#[utoipa::path(
+    put,
+    path = "/chats/{chat_id}/members/{user_id}/custom_role",
+    tag = "roles",
+    params(
+        ("chat_id" = i32, Path, description = "ID della chat"),
+        ("user_id" = i32, Path, description = "ID dell'utente"),
+    ),
+    request_body = AssignChatRoleDTO,
+    responses(
+        (status = 200, description = "Membro aggiornato", body = UserInChatDTO),
+        (status = 400, description = "Ruolo base o membro non Member"),
+        (status = 403, description = "Permessi insufficienti"),
+        (status = 404, description = "Ruolo o membro non trovato"),
+    )
+)]
+#[instrument(skip(state, metadata, body), fields(chat_id = %chat_id, target_user = %user_id, role_id = ?body.role_id))]
+pub async fn assign_member_role(
+    State(state): State<Arc<AppState>>,
+    Path((chat_id, user_id)): Path<(i32, i32)>,
+    Extension(metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware
+    Json(body): Json<AssignChatRoleDTO>,
+) -> Result<Json<UserInChatDTO>, AppError> {
+    debug!("Assigning custom role to member");
+    // 1. Verificare che current_user sia l'Owner della chat, altrimenti FORBIDDEN
+    // 2. Recuperare i metadata del target, se non è membro NOT_FOUND; i ruoli personalizzati
+    //    sostituiscono solo il ruolo base Member, altrimenti BAD_REQUEST
+    // 3. Se viene assegnato un ruolo, verificare che appartenga a questa chat (NOT_FOUND)
+    //    e che sia personalizzato (BAD_REQUEST): i ruoli base cambiano con promozioni e retrocessioni
+    // 4. Salvare il ruolo (null riporta al ruolo base Member) e registrare l'assegnazione nell'audit log
+    // 5. Ritornare il membro aggiornato
+
+    require_role(&metadata, &[UserRole::Owner])?;
+
+    let target_meta = state.meta.read(&(user_id, chat_id)).await?.ok_or_else(|| {
+        warn!(
+            "Target user {} is not a member of chat {}",
+            user_id, chat_id
+        );
+        AppError::not_found("The user is not a member of this chat")
+    })?;
+
+    if target_meta.user_role != Some(UserRole::Member) {
+        warn!("Target user {} is not a plain member", user_id);
+        return Err(AppError::bad_request(
+            "Custom roles can only be assigned to members",
+        ));
+    }
+
+    if let Some(role_id) = body.role_id {
+        let role = state
+            .role
+            .read(&role_id)
+            .await?
+            .filter(|role| role.chat_id == chat_id)
+            .ok_or_else(|| {
+                warn!("Role {} not found in chat {}", role_id, chat_id);
+                AppError::not_found("Role not found")
+            })?;
+
+        if role.base_role.is_some() {
+            warn!("Attempted to assign base role {} as custom role", role_id);
+            return Err(AppError::bad_request(
+                "Base roles are assigned by promoting or demoting the member",
+            ));
+        }
+    }
+
+    state
+        .meta
+        .set_custom_role(&user_id, &chat_id, body.role_id)
+        .await?;
+    let target_meta = state
+        .meta
+        .read(&(user_id, chat_id))
+        .await?
+        .ok_or_else(|| AppError::not_found("The user is not a member of this chat"))?;
+
+    audit::record(
+        &state,
+        chat_id,
+        metadata.user_id,
+        AuditAction::CustomRoleAssigned,
+        Some(user_id),
+        Some(json!({ "role_id": body.role_id })),
+    )
+    .await?;
+
+    info!("Custom role assigned");
+    Ok(Json(UserInChatDTO::from(target_meta)))
+}
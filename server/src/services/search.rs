@@ -0,0 +1,79 @@
+//! Search services - Ricerca globale su messaggi, chat e utenti
+
+use crate::core::{AppError, AppState};
+use crate::dtos::{
+    ChatDTO, GlobalSearchQuery, GlobalSearchResultDTO, MessageSearchResultDTO, UserDTO,
+};
+use crate::entities::User;
+use axum::{
+    Extension,
+    extract::{Json, Query, State},
+};
+use std::sync::Arc;
+use tracing::{debug, info, instrument};
+use validator::Validate;
+
+/// Ricerca globale tra messaggi, chat e utenti
+#[utoipa::path(
+    get,
+    path = "/search",
+    tag = "search",
+    params(GlobalSearchQuery),
+    responses(
+        (
+            status = 200,
+            description = "Risultati raggruppati per tipo",
+            body = GlobalSearchResultDTO,
+        ),
+        (status = 400, description = "Parametri non validi"),
+    )
+)]
+#[instrument(skip(state, current_user, params), fields(user_id = %current_user.user_id))]
+pub async fn global_search(
+    State(state): State<Arc<AppState>>,
+    Extension(current_user): Extension<User>, // ottenuto dall'autenticazione JWT
+    Query(params): Query<GlobalSearchQuery>,
+) -> Result<Json<GlobalSearchResultDTO>, AppError> {
+    debug!("Global search");
+    // 1. Estrarre q e limit dalla query string e validarli
+    // 2. Eseguire in parallelo le tre ricerche (ognuna è una singola query):
+    //    - messaggi delle chat dell'utente (FULLTEXT, rispettando messages_visible_from)
+    //    - chat dell'utente per titolo
+    //    - utenti per username (prefisso)
+    // 3. Escludere l'utente corrente dai risultati utenti
+    // 4. Ritornare i risultati raggruppati per tipo
+
+    params.validate()?;
+    let limit = params.limit.unwrap_or(20);
+
+    let (messages, chats, users) = tokio::try_join!(
+        state
+            .msg
+            .search_for_user(&current_user.user_id, &params.q, limit),
+        state
+            .chat
+            .search_by_title_for_user(&current_user.user_id, &params.q, limit),
+        state.user.search_by_username_partial(&params.q),
+    )?;
+
+    let result = GlobalSearchResultDTO {
+        messages: messages
+            .into_iter()
+            .map(|m| MessageSearchResultDTO::new(m, &params.q))
+            .collect(),
+        chats: chats.into_iter().map(ChatDTO::from).collect(),
+        users: users
+            .into_iter()
+            .filter(|u| u.user_id != current_user.user_id)
+            .map(UserDTO::from)
+            .collect(),
+    };
+
+    info!(
+        messages = result.messages.len(),
+        chats = result.chats.len(),
+        users = result.users.len(),
+        "Global search completed"
+    );
+    Ok(Json(result))
+}
@@ -0,0 +1,203 @@
+//! Sync services - Sincronizzazione incrementale per i client all'avvio
+
+use crate::core::{AppError, AppState};
+use crate::dtos::{ChatDTO, MessageDTO, MissedMessagesDTO, SyncDTO, SyncQuery, SyncToken};
+use crate::entities::{User, UserChatMetadata};
+use crate::repositories::{InvitationFilter, MemberFilter, MessageFilter, Page, ReadMany};
+use crate::services::membership::{enrich_received_invitation, enrich_sent_invitation};
+use axum::{
+    Extension,
+    extract::{Json, Query, State},
+};
+use chrono::{DateTime, Timelike, Utc};
+use futures::future::try_join_all;
+use std::sync::Arc;
+use tracing::{debug, info, instrument};
+
+/// Messaggi restituiti al massimo per ogni chat: i precedenti vanno recuperati con la
+/// cronologia paginata
+pub const SYNC_MAX_MESSAGES_PER_CHAT: i64 = 100;
+
+/// Tutte le modifiche dall'ultima sincronizzazione
+#[utoipa::path(
+    get,
+    path = "/sync",
+    tag = "sync",
+    params(SyncQuery),
+    responses(
+        (status = 200, description = "Modifiche successive al token", body = SyncDTO),
+        (status = 400, description = "Token di sincronizzazione non valido"),
+    )
+)]
+#[instrument(skip(state, current_user, params), fields(user_id = %current_user.user_id))]
+pub async fn sync_changes(
+    State(state): State<Arc<AppState>>,
+    Extension(current_user): Extension<User>, // ottenuto dall'autenticazione JWT
+    Query(params): Query<SyncQuery>,
+) -> Result<Json<SyncDTO>, AppError> {
+    debug!("Incremental sync");
+    // 1. Decodificare il token `since`, se presente (400 se non è stato emesso dal server)
+    // 2. Fissare l'istante del nuovo token prima di leggere: ciò che cambia durante la
+    //    richiesta (o nello stesso secondo) viene restituito di nuovo alla successiva
+    // 3. Recuperare le membership dell'utente: gli id delle chat permettono al client di
+    //    scoprire quelle lasciate, le chat con member_since successivo al token sono nuove
+    // 4. Recuperare in parallelo i messaggi successivi al token di ogni chat, rispettando
+    //    messages_visible_from e al massimo SYNC_MAX_MESSAGES_PER_CHAT per chat
+    // 5. Recuperare gli inviti ricevuti e quelli inviati che hanno avuto una risposta
+    //    (alla prima sincronizzazione solo i ricevuti ancora pendenti)
+    // 6. Emettere il nuovo token con l'ultimo messaggio restituito
+
+    let since = params
+        .since
+        .as_deref()
+        .map(|token| {
+            SyncToken::decode(token).ok_or_else(|| AppError::bad_request("Invalid sync token"))
+        })
+        .transpose()?;
+    // le colonne timestamp arrotondano al secondo: il token parte dall'inizio del secondo
+    let issued_at = Utc::now().with_nanosecond(0).unwrap_or_else(Utc::now);
+    let last_message_id = since.map(|token| token.last_message_id).unwrap_or(0);
+
+    let mut memberships = state
+        .meta
+        .read_many(
+            &MemberFilter::of_user(current_user.user_id).from_primary(),
+            &Page::ALL,
+        )
+        .await?;
+    // ordine stabile tra una sincronizzazione e l'altra
+    memberships.sort_by_key(|m| m.chat_id);
+    let chat_ids: Vec<i32> = memberships.iter().map(|m| m.chat_id).collect();
+
+    let joined_chats: Vec<ChatDTO> = try_join_all(
+        memberships
+            .iter()
+            .filter(|m| since.is_none_or(|token| m.member_since >= token.issued_at))
+            .map(|m| load_joined_chat(&state, m)),
+    )
+    .await?
+    .into_iter()
+    .flatten()
+    .collect();
+
+    let messages: Vec<MissedMessagesDTO> = try_join_all(memberships.iter().map(|m| {
+        load_new_messages(
+            &state,
+            m.chat_id,
+            &m.messages_visible_from,
+            &last_message_id,
+        )
+    }))
+    .await?
+    .into_iter()
+    .flatten()
+    .collect();
+
+    let (received, answered) = match since {
+        Some(token) => tokio::try_join!(
+            state
+                .invitation
+                .find_received_since(&current_user.user_id, &token.issued_at),
+            state
+                .invitation
+                .find_answered_since(&current_user.user_id, &token.issued_at),
+        )?,
+        None => (
+            state
+                .invitation
+                .read_many(
+                    &InvitationFilter::pending_for(current_user.user_id),
+                    &Page::ALL,
+                )
+                .await?,
+            Vec::new(),
+        ),
+    };
+
+    let mut received_invitations = Vec::with_capacity(received.len());
+    for invitation in received {
+        received_invitations.push(enrich_received_invitation(&state, invitation).await);
+    }
+    let mut answered_invitations = Vec::with_capacity(answered.len());
+    for invitation in answered {
+        answered_invitations.push(enrich_sent_invitation(&state, invitation).await?);
+    }
+
+    let sync_token = SyncToken {
+        last_message_id: messages
+            .iter()
+            .flat_map(|chat| chat.messages.iter().filter_map(|m| m.message_id))
+            .fold(last_message_id, i32::max),
+        issued_at,
+    };
+
+    info!(
+        chats = chat_ids.len(),
+        joined = joined_chats.len(),
+        chats_with_messages = messages.len(),
+        received = received_invitations.len(),
+        answered = answered_invitations.len(),
+        "Sync completed"
+    );
+    Ok(Json(SyncDTO {
+        sync_token: sync_token.encode(),
+        chat_ids,
+        joined_chats,
+        messages,
+        received_invitations,
+        answered_invitations,
+    }))
+}
+
+/// Chat in cui l'utente è entrato, con i membri e l'eventuale pin come in `list_chats`
+async fn load_joined_chat(
+    state: &AppState,
+    membership: &UserChatMetadata,
+) -> Result<Option<ChatDTO>, AppError> {
+    let Some(chat) = state.chat.read(&membership.chat_id).await? else {
+        return Ok(None);
+    };
+    let members = state
+        .meta
+        .read_many(
+            &MemberFilter::in_chat(membership.chat_id).from_primary(),
+            &Page::ALL,
+        )
+        .await?;
+
+    let mut dto = ChatDTO::from(chat);
+    dto.user_list = Some(members.into_iter().map(|m| m.user_id).collect());
+    dto.pinned_at = membership.pinned_at;
+    Ok(Some(dto))
+}
+
+/// Messaggi della chat successivi a `after_message_id`, dal più vecchio; None se non ce ne sono
+async fn load_new_messages(
+    state: &AppState,
+    chat_id: i32,
+    visible_from: &DateTime<Utc>,
+    after_message_id: &i32,
+) -> Result<Option<MissedMessagesDTO>, AppError> {
+    // uno in più del limite, per sapere se ne restano altri da recuperare con la cronologia
+    let filter = MessageFilter::After {
+        chat_id,
+        visible_from: *visible_from,
+        after_message_id: *after_message_id,
+    };
+    let mut messages = state
+        .msg
+        .read_many(&filter, &Page::first(SYNC_MAX_MESSAGES_PER_CHAT + 1))
+        .await?;
+    if messages.is_empty() {
+        return Ok(None);
+    }
+    let truncated = messages.len() as i64 > SYNC_MAX_MESSAGES_PER_CHAT;
+    messages.truncate(SYNC_MAX_MESSAGES_PER_CHAT as usize);
+    messages.reverse();
+
+    Ok(Some(MissedMessagesDTO {
+        chat_id,
+        messages: messages.into_iter().map(MessageDTO::from).collect(),
+        truncated,
+    }))
+}
@@ -0,0 +1,185 @@
+//! Translation services - Traduzione automatica dei messaggi su richiesta
+//!
+//! Il provider è un trait così da poter cambiare servizio (o usarne uno finto nei test)
+//! senza toccare l'handler. Se nessun provider è configurato l'endpoint risponde 503.
+
+use crate::core::{AppError, AppState};
+use crate::dtos::{MessageDTO, MessageTranslationDTO, TranslateQuery};
+use crate::entities::UserChatMetadata;
+use axum::{
+    Extension,
+    extract::{Json, Path, Query, State},
+    http::StatusCode,
+};
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{debug, error, info, instrument, warn};
+use validator::Validate;
+
+/// Risultato di una traduzione
+#[derive(Debug, Clone)]
+pub struct Translation {
+    pub text: String,
+    /// Lingua rilevata dal provider, None se il provider non la restituisce
+    pub source_lang: Option<String>,
+}
+
+/// Servizio di traduzione automatica
+pub trait TranslationProvider: Send + Sync {
+    /// Traduce `text` nella lingua `target_lang` (codice ISO 639-1)
+    ///
+    /// # Returns
+    /// * `Ok(Translation)` - Testo tradotto
+    /// * `Err(String)` - Descrizione dell'errore del provider (solo per i log)
+    fn translate<'a>(
+        &'a self,
+        text: &'a str,
+        target_lang: &'a str,
+    ) -> BoxFuture<'a, Result<Translation, String>>;
+}
+
+/// Provider compatibile con l'API di LibreTranslate (`POST {base_url}/translate`)
+pub struct LibreTranslateProvider {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+}
+
+impl LibreTranslateProvider {
+    pub fn new(base_url: String, api_key: Option<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct LibreTranslateRequest<'a> {
+    q: &'a str,
+    source: &'a str,
+    target: &'a str,
+    format: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    api_key: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LibreTranslateResponse {
+    translated_text: String,
+    detected_language: Option<DetectedLanguage>,
+}
+
+#[derive(Deserialize)]
+struct DetectedLanguage {
+    language: String,
+}
+
+impl TranslationProvider for LibreTranslateProvider {
+    fn translate<'a>(
+        &'a self,
+        text: &'a str,
+        target_lang: &'a str,
+    ) -> BoxFuture<'a, Result<Translation, String>> {
+        Box::pin(async move {
+            let response = self
+                .client
+                .post(format!("{}/translate", self.base_url))
+                .json(&LibreTranslateRequest {
+                    q: text,
+                    source: "auto",
+                    target: target_lang,
+                    format: "text",
+                    api_key: self.api_key.as_deref(),
+                })
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+                .map_err(|e| e.to_string())?;
+
+            let body: LibreTranslateResponse = response.json().await.map_err(|e| e.to_string())?;
+
+            Ok(Translation {
+                text: body.translated_text,
+                source_lang: body.detected_language.map(|d| d.language),
+            })
+        })
+    }
+}
+
+/// Traduce un messaggio nella lingua richiesta
+#[utoipa::path(
+    get,
+    path = "/chats/{chat_id}/messages/{message_id}/translate",
+    tag = "messages",
+    params(
+        ("chat_id" = i32, Path, description = "ID della chat"),
+        ("message_id" = i32, Path, description = "ID del messaggio"),
+        TranslateQuery,
+    ),
+    responses(
+        (status = 200, description = "Messaggio tradotto", body = MessageTranslationDTO),
+        (status = 400, description = "Lingua non valida"),
+        (status = 404, description = "Messaggio non trovato"),
+        (status = 409, description = "Messaggio eliminato"),
+        (status = 502, description = "Errore del servizio di traduzione"),
+        (status = 503, description = "Traduzione non configurata"),
+    )
+)]
+#[instrument(skip(state, metadata), fields(chat_id = %chat_id, message_id = %message_id, lang = %params.lang))]
+pub async fn translate_message(
+    State(state): State<Arc<AppState>>,
+    Path((chat_id, message_id)): Path<(i32, i32)>,
+    Query(params): Query<TranslateQuery>,
+    Extension(metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware
+) -> Result<Json<MessageTranslationDTO>, AppError> {
+    debug!("Translating message");
+    // 1. Validare la lingua di destinazione
+    // 2. Verificare che un provider di traduzione sia configurato, altrimenti SERVICE_UNAVAILABLE
+    // 3. Recuperare il messaggio, errore NOT_FOUND se non esiste, appartiene ad un'altra chat
+    //    o è precedente alla finestra di visibilità dell'utente
+    // 4. I messaggi eliminati non hanno contenuto da tradurre
+    // 5. Chiedere la traduzione al provider, BAD_GATEWAY se fallisce
+    // 6. Ritornare il messaggio con la traduzione allegata
+
+    params.validate()?;
+
+    let translator = state.translator.as_ref().ok_or_else(|| {
+        warn!("Translation requested but no provider is configured");
+        AppError::service_unavailable("Translation is not configured")
+    })?;
+
+    let message = state
+        .msg
+        .read(&message_id)
+        .await?
+        .filter(|m| m.chat_id == chat_id && m.created_at >= metadata.messages_visible_from)
+        .ok_or_else(|| {
+            warn!("Message {} not found in chat {}", message_id, chat_id);
+            AppError::not_found("Message not found")
+        })?;
+
+    if message.deleted_at.is_some() {
+        warn!("Attempted to translate a deleted message");
+        return Err(AppError::conflict("Deleted messages cannot be translated"));
+    }
+
+    let translation = translator
+        .translate(&message.content, &params.lang)
+        .await
+        .map_err(|e| {
+            error!("Translation provider error: {}", e);
+            AppError::new(StatusCode::BAD_GATEWAY, "Translation provider error")
+        })?;
+
+    info!("Message translated successfully");
+    Ok(Json(MessageTranslationDTO {
+        message: MessageDTO::from(message),
+        target_lang: params.lang,
+        source_lang: translation.source_lang,
+        translated_content: translation.text,
+    }))
+}
@@ -1,19 +1,39 @@
 //! User services - Gestione utenti
 
+use crate::core::auth::Claims;
+use crate::core::config::ACCOUNT_REACTIVATION_WINDOW_DAYS;
 use crate::core::{AppError, AppState};
-use crate::dtos::{UserDTO, UserSearchQuery};
-use crate::entities::{User, UserRole};
-use crate::repositories::{Delete, Read};
+use crate::dtos::{
+    AccountDeletionMode, DeleteAccountQuery, PrivacySettingsDTO, SessionDTO, SetStatusDTO,
+    UpdateProfileDTO, UserDTO, UserSearchQuery, UserStatusDTO,
+};
+use crate::entities::{PresenceVisibility, User, UserRole};
+use crate::repositories::{MemberFilter, Page};
+use crate::services::attachment::remove_stored_files;
+use crate::ws::event_handlers::broadcast_status;
+use crate::ws::usermap::InternalSignal;
 use axum::{
     Extension,
     extract::{Json, Path, Query, State},
     http::{HeaderMap, HeaderValue, StatusCode},
     response::IntoResponse,
 };
+use chrono::{DateTime, Duration, Utc};
 use futures::future;
 use std::sync::Arc;
-use tracing::{debug, info, instrument, warn};
+use tracing::{debug, error, info, instrument, warn};
+use validator::Validate;
 
+/// Cerca utenti per username
+#[utoipa::path(
+    get,
+    path = "/users",
+    tag = "users",
+    params(UserSearchQuery),
+    responses(
+        (status = 200, description = "Utenti trovati", body = Vec<UserDTO>),
+    )
+)]
 #[instrument(skip(state, current_user), fields(search = %params.search, user_id = %current_user.user_id))]
 pub async fn search_user_with_username(
     State(state): State<Arc<AppState>>,
@@ -24,7 +44,7 @@ pub async fn search_user_with_username(
     // 1. Estrarre il parametro search dalla query string
     // 2. Cercare nel database tutti gli utenti con username che contiene parzialmente la query, cercando solo all'inizio dello username
     // 3. Filtrare l'utente corrente dai risultati
-    // 4. Convertire ogni utente trovato in UserDTO
+    // 4. Convertire ogni utente trovato in UserDTO, con la presenza solo se visibile a current_user
     // 5. Ritornare la lista di UserDTO come risposta JSON
     let users = state
         .user
@@ -32,16 +52,27 @@ pub async fn search_user_with_username(
         .await?;
     
     // Filtra l'utente corrente dai risultati
-    let filtered_users: Vec<UserDTO> = users
+    let mut filtered_users: Vec<UserDTO> = Vec::with_capacity(users.len());
+    for u in users
         .into_iter()
         .filter(|u| u.user_id != current_user.user_id)
-        .map(UserDTO::from)
-        .collect();
+    {
+        filtered_users.push(user_with_presence(&state, &current_user.user_id, u).await?);
+    }
     
     info!("Found {} users matching search criteria (excluding current user)", filtered_users.len());
     Ok(Json::from(filtered_users))
 }
 
+/// Utente autenticato
+#[utoipa::path(
+    get,
+    path = "/users/me",
+    tag = "users",
+    responses(
+        (status = 200, description = "Utente corrente", body = UserDTO),
+    )
+)]
 #[instrument(skip(current_user), fields(user_id = %current_user.user_id))]
 pub async fn get_my_user(
     Extension(current_user): Extension<User>, // ottenuto dall'autenticazione JWT
@@ -52,46 +83,386 @@ pub async fn get_my_user(
     Ok(Json(UserDTO::from(current_user)))
 }
 
-#[instrument(skip(state), fields(user_id = %user_id))]
+/// Profilo dell'utente autenticato
+#[utoipa::path(
+    get,
+    path = "/users/me/profile",
+    tag = "users",
+    responses(
+        (status = 200, description = "Profilo corrente", body = UserDTO),
+    )
+)]
+#[instrument(skip(current_user), fields(user_id = %current_user.user_id))]
+pub async fn get_my_profile(
+    Extension(current_user): Extension<User>, // ottenuto dall'autenticazione JWT
+) -> Result<Json<UserDTO>, AppError> {
+    debug!("Fetching current user profile");
+    // L'utente caricato dal middleware contiene già i campi del profilo
+    Ok(Json(UserDTO::from(current_user)))
+}
+
+/// Aggiorna il profilo dell'utente autenticato
+#[utoipa::path(
+    patch,
+    path = "/users/me/profile",
+    tag = "users",
+    request_body = UpdateProfileDTO,
+    responses(
+        (status = 200, description = "Profilo aggiornato", body = UserDTO),
+        (status = 400, description = "Dati non validi"),
+    )
+)]
+#[instrument(skip(state, current_user, body), fields(user_id = %current_user.user_id))]
+pub async fn update_my_profile(
+    State(state): State<Arc<AppState>>,
+    Extension(current_user): Extension<User>, // ottenuto dall'autenticazione JWT
+    Json(body): Json<UpdateProfileDTO>,
+) -> Result<Json<UserDTO>, AppError> {
+    debug!("Updating current user profile");
+    // 1. Rimuovere gli spazi iniziali e finali dai campi presenti
+    // 2. Validare il DTO (lunghezze e formato dell'URL dell'avatar)
+    // 3. Aggiornare i campi presenti tramite il repository (stringa vuota = campo cancellato)
+    // 4. Ritornare il profilo aggiornato
+
+    let body = UpdateProfileDTO {
+        display_name: body.display_name.map(|value| value.trim().to_string()),
+        bio: body.bio.map(|value| value.trim().to_string()),
+        avatar_url: body.avatar_url.map(|value| value.trim().to_string()),
+    };
+    body.validate()?;
+
+    let user = state
+        .user
+        .update_profile(&current_user.user_id, &body)
+        .await?;
+
+    info!("User profile updated successfully");
+    Ok(Json(UserDTO::from(user)))
+}
+
+/// Imposta lo stato personalizzato dell'utente autenticato
+#[utoipa::path(
+    put,
+    path = "/users/me/status",
+    tag = "users",
+    request_body = SetStatusDTO,
+    responses(
+        (status = 200, description = "Stato aggiornato", body = UserStatusDTO),
+        (status = 400, description = "Dati non validi"),
+    )
+)]
+#[instrument(skip(state, current_user, body), fields(user_id = %current_user.user_id))]
+pub async fn set_my_status(
+    State(state): State<Arc<AppState>>,
+    Extension(current_user): Extension<User>, // ottenuto dall'autenticazione JWT
+    Json(body): Json<SetStatusDTO>,
+) -> Result<Json<UserStatusDTO>, AppError> {
+    debug!("Setting current user status");
+    // 1. Validare il DTO (lunghezza dello stato e durata)
+    // 2. Uno stato assente o vuoto cancella quello corrente (e la sua scadenza)
+    // 3. Calcolare la scadenza dalla durata, se presente
+    // 4. Salvare lo stato tramite il repository
+    // 5. Inviare l'evento StatusChanged sulle chat dell'utente (ricevuto dai membri online)
+    // 6. Ritornare lo stato impostato
+
+    body.validate()?;
+
+    let status = body
+        .status
+        .map(|status| status.trim().to_string())
+        .filter(|status| !status.is_empty());
+    let expires_at = body
+        .duration
+        .filter(|_| status.is_some())
+        .map(|duration| Utc::now() + Duration::seconds(duration));
+
+    state
+        .user
+        .update_status(&current_user.user_id, status.as_deref(), expires_at)
+        .await?;
+
+    let status_dto = UserStatusDTO {
+        user_id: current_user.user_id,
+        status,
+        expires_at,
+    };
+
+    broadcast_status(&state, status_dto.clone()).await;
+
+    info!("User status updated successfully");
+    Ok(Json(status_dto))
+}
+
+/// Utente per ID, null se non esiste
+#[utoipa::path(
+    get,
+    path = "/users/{user_id}",
+    tag = "users",
+    params(("user_id" = i32, Path, description = "ID dell'utente")),
+    responses(
+        (status = 200, description = "Utente trovato o null", body = Option<UserDTO>),
+    )
+)]
+#[instrument(skip(state, current_user), fields(user_id = %user_id, viewer_id = %current_user.user_id))]
 pub async fn get_user_by_id(
     State(state): State<Arc<AppState>>,
+    // ottenuto dall'autenticazione JWT
+    Extension(current_user): Extension<User>,
     Path(user_id): Path<i32>, // parametro dalla URL /users/:user_id
 ) -> Result<Json<Option<UserDTO>>, AppError> {
     debug!("Fetching user by ID");
     // 1. Estrarre user_id dal path della URL
     // 2. Cercare l'utente nel database tramite user_id
-    // 3. Se l'utente esiste, convertirlo in UserDTO
+    // 3. Se l'utente esiste, convertirlo in UserDTO aggiungendo lo stato di presenza
+    //    solo se la sua impostazione di privacy lo rende visibile a current_user
     // 4. Ritornare Option<UserDTO> come risposta JSON (Some se trovato, None se non trovato)
-    let user_option = state.user.read(&user_id).await?;
-    if user_option.is_some() {
-        info!("User found");
-    } else {
+    let Some(user) = state.user.read(&user_id).await? else {
         warn!("User not found");
+        return Ok(Json(None));
+    };
+    info!("User found");
+
+    let user_dto = user_with_presence(&state, &current_user.user_id, user).await?;
+    Ok(Json(Some(user_dto)))
+}
+
+/// Converte l'utente in UserDTO aggiungendo online e last_seen se viewer_id può vederli
+async fn user_with_presence(
+    state: &AppState,
+    viewer_id: &i32,
+    user: User,
+) -> Result<UserDTO, AppError> {
+    let visible = match user.presence_visibility {
+        PresenceVisibility::Everyone => true,
+        PresenceVisibility::Contacts => {
+            user.user_id == *viewer_id
+                || state.meta.share_any_chat(viewer_id, &user.user_id).await?
+        }
+        PresenceVisibility::Nobody => user.user_id == *viewer_id,
+    };
+
+    if !visible {
+        return Ok(UserDTO::from(user));
+    }
+
+    // ultima disconnessione registrata in memoria, più recente di quella su db
+    let online = state.is_user_online(&user.user_id).await;
+    let last_seen = state.users_online.last_seen(&user.user_id);
+    let user_dto = UserDTO {
+        last_seen: user.last_seen,
+        ..UserDTO::from(user)
+    };
+    Ok(user_dto.with_presence(online, last_seen))
+}
+
+/// Impostazioni di privacy dell'utente autenticato
+#[utoipa::path(
+    get,
+    path = "/users/me/privacy",
+    tag = "users",
+    responses(
+        (status = 200, description = "Impostazioni correnti", body = PrivacySettingsDTO),
+    )
+)]
+#[instrument(skip(current_user), fields(user_id = %current_user.user_id))]
+pub async fn get_my_privacy(
+    Extension(current_user): Extension<User>, // ottenuto dall'autenticazione JWT
+) -> Result<Json<PrivacySettingsDTO>, AppError> {
+    debug!("Fetching current user privacy settings");
+    Ok(Json(PrivacySettingsDTO {
+        presence_visibility: current_user.presence_visibility,
+    }))
+}
+
+/// Aggiorna le impostazioni di privacy dell'utente autenticato
+#[utoipa::path(
+    put,
+    path = "/users/me/privacy",
+    tag = "users",
+    request_body = PrivacySettingsDTO,
+    responses(
+        (status = 200, description = "Impostazioni aggiornate", body = PrivacySettingsDTO),
+    )
+)]
+#[instrument(skip(state, current_user, body), fields(user_id = %current_user.user_id))]
+pub async fn update_my_privacy(
+    State(state): State<Arc<AppState>>,
+    Extension(current_user): Extension<User>, // ottenuto dall'autenticazione JWT
+    Json(body): Json<PrivacySettingsDTO>,
+) -> Result<Json<PrivacySettingsDTO>, AppError> {
+    debug!("Updating current user privacy settings");
+    // 1. Salvare la nuova visibilità della presenza tramite il repository
+    // 2. Ritornare le impostazioni aggiornate
+    //    (la nuova visibilità vale dai prossimi eventi di presenza e dalle prossime letture)
+
+    state
+        .user
+        .update_presence_visibility(&current_user.user_id, &body.presence_visibility)
+        .await?;
+
+    info!("Privacy settings updated successfully");
+    Ok(Json(body))
+}
+
+/// Connessioni WebSocket attive dell'utente autenticato
+#[utoipa::path(
+    get,
+    path = "/users/me/sessions",
+    tag = "users",
+    responses(
+        (status = 200, description = "Sessioni attive", body = Vec<SessionDTO>),
+    )
+)]
+#[instrument(skip(state, current_user), fields(user_id = %current_user.user_id))]
+pub async fn list_my_sessions(
+    State(state): State<Arc<AppState>>,
+    Extension(current_user): Extension<User>, // ottenuto dall'autenticazione JWT
+) -> Result<Json<Vec<SessionDTO>>, AppError> {
+    debug!("Listing active sessions");
+    // Ritorna la connessione WebSocket attiva dell'utente (al più una), con i dati del dispositivo
+    // e lo stato della sua coda di segnali
+    let user_id = current_user.user_id;
+    let sessions = state
+        .users_online
+        .connection(&user_id)
+        .map(|connection| {
+            let stats = state.users_online.queue_stats(&user_id).unwrap_or_default();
+            SessionDTO::new(connection, stats)
+        })
+        .into_iter()
+        .collect();
+    Ok(Json(sessions))
+}
+
+/// Chiude una connessione WebSocket dell'utente autenticato
+#[utoipa::path(
+    delete,
+    path = "/users/me/sessions/{connection_id}",
+    tag = "users",
+    params(("connection_id" = String, Path, description = "ID della connessione")),
+    responses(
+        (status = 204, description = "Connessione chiusa"),
+        (status = 404, description = "Connessione non trovata"),
+    )
+)]
+#[instrument(skip(state, current_user), fields(user_id = %current_user.user_id))]
+pub async fn disconnect_my_session(
+    State(state): State<Arc<AppState>>,
+    Extension(current_user): Extension<User>, // ottenuto dall'autenticazione JWT
+    Path(connection_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    debug!("Disconnecting session {}", connection_id);
+    // 1. Verificare che la connessione indicata sia quella attiva dell'utente
+    // 2. Inviarle il segnale di chiusura (il token resta valido, il client può riconnettersi)
+    if !state
+        .users_online
+        .disconnect(&current_user.user_id, &connection_id)
+    {
+        warn!("Session {} not found", connection_id);
+        return Err(AppError::not_found("Session not found"));
     }
-    Ok(Json(user_option.map(UserDTO::from)))
+
+    info!("Session disconnected");
+    Ok(StatusCode::NO_CONTENT)
 }
 
-#[instrument(skip(state, current_user), fields(user_id = %current_user.user_id, username = %current_user.username))]
+/// Cancella o disattiva l'account dell'utente autenticato
+#[utoipa::path(
+    delete,
+    path = "/users/me",
+    tag = "users",
+    params(DeleteAccountQuery),
+    responses(
+        (
+            status = 200,
+            description = "Account cancellato o disattivato",
+            body = String,
+            content_type = "text/plain",
+        ),
+    )
+)]
+#[instrument(skip(state, current_user, params), fields(user_id = %current_user.user_id, username = %current_user.username))]
 pub async fn delete_my_account(
     State(state): State<Arc<AppState>>,
     Extension(current_user): Extension<User>, // ottenuto dall'autenticazione tramite token jwt
+    Extension(claims): Extension<Claims>,     // claims del token usato per la richiesta
+    Query(params): Query<DeleteAccountQuery>, // ?mode=delete (default) oppure ?mode=deactivate
 ) -> Result<impl IntoResponse, AppError> {
     info!("User account deletion initiated");
     // 1. Ottenere l'utente corrente dall'Extension (autenticato tramite JWT)
-    // 2. Recuperare tutti i metadata dell'utente per identificare chat ownership (singola query)
+    // 2. Se mode=deactivate: disattivare l'account senza cancellare nulla. Finché è disattivato
+    //    l'utente appare come "Deleted User" e non può autenticarsi; rifacendo il login entro
+    //    la finestra di riattivazione l'account torna attivo, dopo viene cancellato dal purge
+    // 3. Altrimenti cancellare subito l'account (ownership, metadata, anonimizzazione)
+    // 4. Revocare l'access token corrente e tutti i refresh token dell'utente
+    // 5. Chiudere la connessione WebSocket dell'utente, se online
+    // 6. Creare un cookie con Max-Age=0 per forzare il logout lato client
+    // 7. Ritornare StatusCode::OK con gli headers e messaggio
+    let message = match params.mode.unwrap_or_default() {
+        AccountDeletionMode::Deactivate => {
+            info!("Deactivating user account");
+            state
+                .user
+                .set_deactivated_at(&current_user.user_id, Some(Utc::now()))
+                .await?;
+            "Account deactivated successfully"
+        }
+        AccountDeletionMode::Delete => {
+            delete_account_data(&state, &current_user.user_id).await?;
+            "Account deleted successfully"
+        }
+    };
+
+    let expires_at = DateTime::from_timestamp(claims.exp as i64, 0).unwrap_or_else(Utc::now);
+    state
+        .revoked_tokens
+        .revoke(&claims.jti, expires_at)
+        .await
+        .map_err(|e| {
+            error!("Failed to revoke access token: {}", e);
+            AppError::service_unavailable("Token revocation store unavailable")
+        })?;
+
+    state
+        .refresh_token
+        .revoke_all_by_user_id(&current_user.user_id)
+        .await?;
+
+    state
+        .users_online
+        .send_server_message_if_online(&current_user.user_id, InternalSignal::Shutdown);
+
+    let cookie = format!("token=; {}; Path=/; Max-Age=0", state.cookie_attributes());
+    let mut headers = HeaderMap::new();
+    headers.insert("Set-Cookie", HeaderValue::from_str(&cookie).unwrap());
+
+    info!("{}", message);
+    Ok((StatusCode::OK, headers, message))
+}
+
+/// Cancella definitivamente l'account: trasferisce o elimina le chat di cui è Owner,
+/// rimuove le membership e anonimizza l'utente mantenendo la cronologia dei messaggi
+pub(crate) async fn delete_account_data(state: &AppState, user_id: &i32) -> Result<(), AppError> {
+    // 1. Recuperare tutti i metadata dell'utente per identificare chat ownership (singola query)
     let user_metadata = state
         .meta
-        .find_many_by_user_id(&current_user.user_id)
+        .read_many(&MemberFilter::of_user(*user_id).from_primary(), &Page::ALL)
         .await?;
 
     debug!("Found {} chat memberships for user", user_metadata.len());
 
-    // 3. Gestire il caso degli ownership: se l'utente è owner di gruppi
+    // 2. Gestire il caso degli ownership: se l'utente è owner di gruppi
     for metadata in &user_metadata {
         if matches!(metadata.user_role, Some(UserRole::Owner)) {
             debug!("Handling ownership transfer for chat {}", metadata.chat_id);
             // Recuperare tutti i membri della chat
-            let chat_members = state.meta.find_many_by_chat_id(&metadata.chat_id).await?;
+            let chat_members = state
+                .meta
+                .read_many(
+                    &MemberFilter::in_chat(metadata.chat_id).from_primary(),
+                    &Page::ALL,
+                )
+                .await?;
 
             if chat_members.len() == 1 {
                 // Se l'owner è l'unico membro, cancellare la chat completamente
@@ -100,20 +471,21 @@ pub async fn delete_my_account(
                     "Deleting chat {} (user is the only member)",
                     metadata.chat_id
                 );
+                let storage_keys = state
+                    .attachment
+                    .find_storage_keys_by_chat(&metadata.chat_id)
+                    .await?;
                 state.chat.delete(&metadata.chat_id).await?;
+                state.invalidate_chat_members(metadata.chat_id).await;
+                remove_stored_files(state, &storage_keys).await;
             } else {
                 // Cercare un admin a cui trasferire l'ownership
                 let new_owner = chat_members
                     .iter()
-                    .find(|m| {
-                        m.user_id != current_user.user_id
-                            && matches!(m.user_role, Some(UserRole::Admin))
-                    })
+                    .find(|m| m.user_id != *user_id && matches!(m.user_role, Some(UserRole::Admin)))
                     .or_else(|| {
                         // Se non c'è un admin, prendi qualsiasi altro membro
-                        chat_members
-                            .iter()
-                            .find(|m| m.user_id != current_user.user_id)
+                        chat_members.iter().find(|m| m.user_id != *user_id)
                     });
 
                 if let Some(new_owner) = new_owner {
@@ -124,24 +496,20 @@ pub async fn delete_my_account(
                     );
                     state
                         .meta
-                        .transfer_ownership(
-                            &current_user.user_id,
-                            &new_owner.user_id,
-                            &metadata.chat_id,
-                        )
+                        .transfer_ownership(user_id, &new_owner.user_id, &metadata.chat_id)
                         .await?;
                 }
             }
         }
     }
 
-    // 4. Cancellare tutti i metadata (UserChatMetadata) associati all'utente
-    // (solo per le chat non eliminate al punto 3 - quelle erano già cancellate da CASCADE)
+    // 3. Cancellare tutti i metadata (UserChatMetadata) associati all'utente
+    // (solo per le chat non eliminate al punto 2 - quelle erano già cancellate da CASCADE)
     // Raccogliere le chiavi per la cancellazione
 
     let meta_ids: Vec<(i32, i32)> = state
         .meta
-        .find_many_by_user_id(&current_user.user_id)
+        .read_many(&MemberFilter::of_user(*user_id).from_primary(), &Page::ALL)
         .await?
         .into_iter()
         .map(|m| (m.user_id, m.chat_id))
@@ -151,16 +519,25 @@ pub async fn delete_my_account(
     // Cancellazione effettiva
     future::join_all(meta_ids.iter().map(|k| state.meta.delete(&k))).await;
 
-    // 5-6. Rinominare lo username dell'utente con "Deleted User" e sostituire la password con stringa vuota
+    // 4. Rinominare lo username dell'utente con "Deleted User" e sostituire la password con stringa vuota
     info!("Soft deleting user account");
-    state.user.delete(&current_user.user_id).await?;
+    state.user.delete(user_id).await?;
 
-    // 7-8. Creare un cookie con Max-Age=0 per forzare il logout lato client
-    let cookie = "token=; HttpOnly; Secure; SameSite=Lax; Path=/; Max-Age=0";
-    let mut headers = HeaderMap::new();
-    headers.insert("Set-Cookie", HeaderValue::from_str(cookie).unwrap());
+    info!("Account data deleted");
+    Ok(())
+}
+
+/// Cancella gli account disattivati da più della finestra di riattivazione.
+/// Eseguito periodicamente in background, ritorna il numero di account cancellati.
+#[instrument(skip(state))]
+pub async fn purge_expired_deactivations(state: &AppState) -> Result<usize, AppError> {
+    let cutoff = Utc::now() - Duration::days(ACCOUNT_REACTIVATION_WINDOW_DAYS);
+    let expired = state.user.find_deactivated_before(&cutoff).await?;
+
+    for user in &expired {
+        info!("Purging deactivated account {}", user.user_id);
+        delete_account_data(state, &user.user_id).await?;
+    }
 
-    // 9. Ritornare StatusCode::OK con gli headers e messaggio
-    info!("Account deleted successfully");
-    Ok((StatusCode::OK, headers, "Account deleted successfully"))
+    Ok(expired.len())
 }
@@ -0,0 +1,123 @@
+//! UserKeys services - Scambio delle chiavi per la cifratura end-to-end
+//!
+//! Il server pubblica solo chiavi pubbliche: ogni client carica chiave di identità,
+//! signed prekey e un lotto di one-time prekey, e chi vuole avviare una sessione
+//! X3DH con lui ne scarica il bundle. Le one-time prekey vengono consegnate una sola
+//! volta; le chiavi private e i messaggi in chiaro non passano mai dal server.
+
+use crate::core::{AppError, AppState};
+use crate::dtos::{KeyBundleDTO, KeyStatusDTO, UploadKeysDTO};
+use crate::entities::User;
+use crate::repositories::Read;
+use axum::{
+    Extension,
+    extract::{Json, Path, State},
+};
+use std::sync::Arc;
+use tracing::{debug, info, instrument, warn};
+use validator::Validate;
+
+/// Massimo di one-time prekey conservate per utente
+const MAX_ONE_TIME_PREKEYS: i64 = 200;
+
+/// Carica le chiavi pubbliche di cifratura end-to-end dell'utente autenticato
+#[utoipa::path(
+    put,
+    path = "/users/{user_id}/keys",
+    tag = "users",
+    params(("user_id" = i32, Path, description = "ID dell'utente")),
+    request_body = UploadKeysDTO,
+    responses(
+        (status = 200, description = "Chiavi salvate", body = KeyStatusDTO),
+        (status = 400, description = "Chiavi non valide"),
+        (status = 403, description = "Chiavi di un altro utente"),
+    )
+)]
+#[instrument(skip(state, current_user, body), fields(user_id = %user_id, current_user_id = %current_user.user_id))]
+pub async fn upload_keys(
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<i32>,
+    Extension(current_user): Extension<User>,
+    Json(body): Json<UploadKeysDTO>,
+) -> Result<Json<KeyStatusDTO>, AppError> {
+    debug!("Uploading encryption keys");
+    // 1. Verificare che l'utente stia pubblicando le proprie chiavi
+    // 2. Validare formato di chiavi e firma
+    // 3. Se la chiave di identità non cambia, le nuove one-time prekey si sommano a quelle
+    //    esistenti: rifiutare il caricamento se supererebbero il massimo
+    // 4. Salvare le chiavi e ritornare il numero di one-time prekey disponibili
+
+    if user_id != current_user.user_id {
+        warn!("Attempt to upload keys for another user");
+        return Err(AppError::forbidden(
+            "You can only upload your own encryption keys",
+        ));
+    }
+
+    body.validate()?;
+
+    let same_identity = state
+        .user_keys
+        .read(&user_id)
+        .await?
+        .is_some_and(|keys| keys.identity_key == body.identity_key);
+    if same_identity {
+        let stored = state.user_keys.count_one_time_prekeys(&user_id).await?;
+        if stored + body.one_time_prekeys.len() as i64 > MAX_ONE_TIME_PREKEYS {
+            warn!("Too many one-time prekeys");
+            return Err(
+                AppError::bad_request("Too many one-time prekeys").with_details(format!(
+                    "At most {} one-time prekeys can be stored",
+                    MAX_ONE_TIME_PREKEYS
+                )),
+            );
+        }
+    }
+
+    let one_time_prekeys = state.user_keys.upsert(&user_id, &body).await?;
+
+    info!("Encryption keys uploaded");
+    Ok(Json(KeyStatusDTO { one_time_prekeys }))
+}
+
+/// Bundle di chiavi pubbliche per avviare una sessione cifrata con l'utente
+#[utoipa::path(
+    get,
+    path = "/users/{user_id}/keys",
+    tag = "users",
+    params(("user_id" = i32, Path, description = "ID dell'utente")),
+    responses(
+        (
+            status = 200,
+            description = "Bundle di chiavi, consuma una prekey monouso",
+            body = KeyBundleDTO,
+        ),
+        (status = 404, description = "L'utente non ha caricato chiavi"),
+    )
+)]
+#[instrument(skip(state, current_user), fields(user_id = %user_id, current_user_id = %current_user.user_id))]
+pub async fn get_key_bundle(
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<i32>,
+    Extension(current_user): Extension<User>,
+) -> Result<Json<KeyBundleDTO>, AppError> {
+    debug!("Fetching key bundle");
+    // 1. Recuperare le chiavi pubblicate dall'utente, 404 se non ne ha
+    // 2. Consumare una one-time prekey, se disponibile: nessun'altra richiesta la riceverà
+    // 3. Ritornare il bundle
+
+    let Some(keys) = state.user_keys.read(&user_id).await? else {
+        warn!("User has no published keys");
+        return Err(AppError::not_found(
+            "User has not published encryption keys",
+        ));
+    };
+
+    let one_time_prekey = state.user_keys.take_one_time_prekey(&user_id).await?;
+    if one_time_prekey.is_none() {
+        warn!("User has run out of one-time prekeys");
+    }
+
+    info!("Key bundle fetched");
+    Ok(Json(KeyBundleDTO::new(keys, one_time_prekey)))
+}
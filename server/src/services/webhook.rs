@@ -0,0 +1,296 @@
+//! Webhook services - Webhook in ingresso per pubblicare messaggi da servizi esterni
+//!
+//! Owner e Admin creano un webhook per una chat di gruppo: viene creato un utente bot,
+//! membro della chat, e un token segreto. POST /webhooks/{webhook_token} pubblica un
+//! messaggio a nome del bot passando per le stesse validazioni e lo stesso broadcast
+//! dei messaggi inviati via WebSocket.
+
+use crate::core::{AppError, AppState, generate_refresh_token, hash_refresh_token, require_role};
+use crate::dtos::{
+    CreateUserChatMetadataDTO, CreateWebhookDTO, MessageDTO, NewWebhookDTO, WebhookDTO,
+    WebhookMessageDTO,
+};
+use crate::entities::{AuditAction, ChatType, MessageType, UserChatMetadata, UserRole};
+use crate::repositories::{Create, Delete, Page, Read, ReadMany, WebhookFilter};
+use crate::services::audit;
+use crate::services::auth::create_external_user;
+use crate::ws::event_handlers::{MessageRejection, deliver_message};
+use axum::{
+    Extension,
+    extract::{Json, Path, State},
+};
+use chrono::Utc;
+use serde_json::json;
+use std::sync::Arc;
+use tracing::{debug, error, info, instrument, warn};
+use validator::Validate;
+
+/// Webhook in ingresso della chat
+#[utoipa::path(
+    get,
+    path = "/chats/{chat_id}/webhooks",
+    tag = "webhooks",
+    params(("chat_id" = i32, Path, description = "ID della chat")),
+    responses(
+        (status = 200, description = "Webhook della chat", body = Vec<WebhookDTO>),
+        (status = 403, description = "Permessi insufficienti"),
+    )
+)]
+#[instrument(skip(state, metadata), fields(chat_id = %chat_id, user_id = %metadata.user_id))]
+pub async fn list_webhooks(
+    State(state): State<Arc<AppState>>,
+    Path(chat_id): Path<i32>,
+    Extension(metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware
+) -> Result<Json<Vec<WebhookDTO>>, AppError> {
+    debug!("Listing chat webhooks");
+    // 1. Verificare che current_user sia Owner o Admin della chat, altrimenti FORBIDDEN
+    // 2. Recuperare i webhook della chat (i token non sono mai più mostrati)
+
+    require_role(&metadata, &[UserRole::Owner, UserRole::Admin])?;
+
+    let webhooks = state
+        .webhook
+        .read_many(&WebhookFilter { chat_id }, &Page::ALL)
+        .await?;
+
+    Ok(Json(webhooks.into_iter().map(WebhookDTO::from).collect()))
+}
+
+/// Crea un webhook in ingresso, il token è mostrato solo in questa risposta
+#[utoipa::path(
+    post,
+    path = "/chats/{chat_id}/webhooks",
+    tag = "webhooks",
+    params(("chat_id" = i32, Path, description = "ID della chat")),
+    request_body = CreateWebhookDTO,
+    responses(
+        (status = 200, description = "Webhook creato", body = WebhookDTO),
+        (status = 400, description = "Dati non validi"),
+        (status = 403, description = "Permessi insufficienti"),
+    )
+)]
+#[instrument(skip(state, metadata, body), fields(chat_id = %chat_id, user_id = %metadata.user_id))]
+pub async fn create_webhook(
+    State(state): State<Arc<AppState>>,
+    Path(chat_id): Path<i32>,
+    Extension(metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware
+    Json(body): Json<CreateWebhookDTO>,
+) -> Result<Json<WebhookDTO>, AppError> {
+    debug!("Creating webhook");
+    // 1. Verificare che current_user sia Owner o Admin della chat, altrimenti FORBIDDEN
+    // 2. Validare il nome, che diventa lo username del bot
+    // 3. Verificare che la chat sia un gruppo, altrimenti BAD_REQUEST
+    // 4. Verificare che lo username non sia già occupato, altrimenti CONFLICT
+    // 5. Verificare che il gruppo abbia posto per il bot, altrimenti CONFLICT
+    // 6. Creare l'utente bot e aggiungerlo alla chat come Member (la chat resta bloccata
+    //    dal passo 5 fino all'ingresso)
+    // 7. Generare il token, salvarne l'hash e registrare la creazione nell'audit log
+    // 8. Ritornare il webhook con il token in chiaro (unica volta in cui viene mostrato)
+
+    require_role(&metadata, &[UserRole::Owner, UserRole::Admin])?;
+    body.validate()?;
+
+    let chat = state.chat.read(&chat_id).await?.ok_or_else(|| {
+        warn!("Chat {} not found", chat_id);
+        AppError::not_found("Chat not found")
+    })?;
+    if chat.chat_type != ChatType::Group {
+        warn!("Attempted to create a webhook in a private chat");
+        return Err(AppError::bad_request(
+            "Webhooks can only be added to group chats",
+        ));
+    }
+
+    if state.user.find_by_username(&body.name).await?.is_some() {
+        warn!("Username {} already taken", body.name);
+        return Err(AppError::conflict("Username already taken"));
+    }
+
+    // la chat resta bloccata dal controllo del posto fino all'ingresso del bot, così un
+    // ingresso concorrente non può occupare l'ultimo posto nel frattempo
+    let mut uow = state.begin().await?;
+    state.ensure_group_has_room_in(&mut uow, chat_id).await?;
+
+    let bot = create_external_user(&state, body.name.clone()).await?;
+
+    let now = Utc::now();
+    state
+        .meta
+        .create_in(
+            &mut uow,
+            &CreateUserChatMetadataDTO {
+                user_id: bot.user_id,
+                chat_id,
+                user_role: Some(UserRole::Member),
+                member_since: now,
+                messages_visible_from: now,
+                messages_received_until: now,
+            },
+        )
+        .await?;
+    uow.commit().await?;
+
+    let token = generate_refresh_token();
+    let webhook = state
+        .webhook
+        .create(&NewWebhookDTO {
+            chat_id,
+            bot_user_id: bot.user_id,
+            name: body.name,
+            token_hash: hash_refresh_token(&token),
+            created_by: metadata.user_id,
+        })
+        .await?;
+
+    audit::record(
+        &state,
+        chat_id,
+        metadata.user_id,
+        AuditAction::WebhookCreated,
+        Some(bot.user_id),
+        Some(json!({ "webhook_id": webhook.webhook_id, "name": webhook.name })),
+    )
+    .await?;
+
+    info!("Webhook {} created", webhook.webhook_id);
+    Ok(Json(WebhookDTO {
+        token: Some(token),
+        ..WebhookDTO::from(webhook)
+    }))
+}
+
+/// Elimina un webhook
+#[utoipa::path(
+    delete,
+    path = "/chats/{chat_id}/webhooks/{webhook_id}",
+    tag = "webhooks",
+    params(
+        ("chat_id" = i32, Path, description = "ID della chat"),
+        ("webhook_id" = i32, Path, description = "ID del webhook"),
+    ),
+    responses(
+        (status = 200, description = "Webhook eliminato"),
+        (status = 403, description = "Permessi insufficienti"),
+        (status = 404, description = "Webhook non trovato"),
+    )
+)]
+#[instrument(skip(state, metadata), fields(chat_id = %chat_id, webhook_id = %webhook_id))]
+pub async fn delete_webhook(
+    State(state): State<Arc<AppState>>,
+    Path((chat_id, webhook_id)): Path<(i32, i32)>,
+    Extension(metadata): Extension<UserChatMetadata>, // ottenuto dal chat_membership_middleware
+) -> Result<(), AppError> {
+    debug!("Deleting webhook");
+    // 1. Verificare che current_user sia Owner o Admin della chat, altrimenti FORBIDDEN
+    // 2. Verificare che il webhook appartenga a questa chat, altrimenti NOT_FOUND
+    // 3. Eliminare il webhook: il token smette di funzionare
+    // 4. Far uscire il bot dalla chat (resta autore dei messaggi già inviati)
+    // 5. Registrare l'eliminazione nell'audit log
+
+    require_role(&metadata, &[UserRole::Owner, UserRole::Admin])?;
+
+    let webhook = state
+        .webhook
+        .read(&webhook_id)
+        .await?
+        .filter(|webhook| webhook.chat_id == chat_id)
+        .ok_or_else(|| {
+            warn!("Webhook {} not found in chat {}", webhook_id, chat_id);
+            AppError::not_found("Webhook not found")
+        })?;
+
+    state.webhook.delete(&webhook_id).await?;
+    state.meta.delete(&(webhook.bot_user_id, chat_id)).await?;
+
+    audit::record(
+        &state,
+        chat_id,
+        metadata.user_id,
+        AuditAction::WebhookDeleted,
+        Some(webhook.bot_user_id),
+        Some(json!({ "webhook_id": webhook_id, "name": webhook.name })),
+    )
+    .await?;
+
+    info!("Webhook deleted");
+    Ok(())
+}
+
+/// Pubblica un messaggio nella chat tramite webhook, autenticato dal token nel path
+#[utoipa::path(
+    post,
+    path = "/webhooks/{webhook_token}",
+    tag = "webhooks",
+    params(
+        ("webhook_token" = String, Path, description = "Token segreto del webhook"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Chiave scelta dal client per ripetere la richiesta senza duplicarla"),
+    ),
+    request_body = WebhookMessageDTO,
+    responses(
+        (status = 200, description = "Messaggio pubblicato", body = MessageDTO),
+        (status = 400, description = "Contenuto non valido"),
+        (status = 404, description = "Token non valido"),
+    ),
+    security(())
+)]
+#[instrument(skip(state, webhook_token, body))]
+pub async fn post_webhook_message(
+    State(state): State<Arc<AppState>>,
+    Path(webhook_token): Path<String>,
+    Json(body): Json<WebhookMessageDTO>,
+) -> Result<Json<MessageDTO>, AppError> {
+    debug!("Incoming webhook message");
+    // 1. Cercare il webhook tramite l'hash del token, altrimenti NOT_FOUND
+    // 2. Costruire il messaggio a nome del bot del webhook
+    // 3. Consegnarlo come un messaggio WebSocket (validazione, permessi, broadcast, salvataggio)
+    // 4. Ritornare il messaggio salvato
+
+    let webhook = state
+        .webhook
+        .find_by_token_hash(&hash_refresh_token(&webhook_token))
+        .await?
+        .ok_or_else(|| {
+            warn!("Unknown webhook token");
+            AppError::not_found("Webhook not found")
+        })?;
+
+    let message = MessageDTO {
+        message_id: None,
+        chat_id: Some(webhook.chat_id),
+        sender_id: Some(webhook.bot_user_id),
+        content: Some(body.content),
+        message_type: Some(MessageType::UserMessage),
+        content_format: body.content_format,
+        created_at: None,
+        reply_to_message_id: None,
+        attachment_id: None,
+        client_msg_id: None,
+        deleted_at: None,
+    };
+
+    let saved = deliver_message(&state, webhook.bot_user_id, message)
+        .await
+        .map_err(rejection_error)?;
+
+    info!(
+        webhook_id = webhook.webhook_id,
+        message_id = saved.message_id,
+        "Webhook message delivered"
+    );
+    Ok(Json(MessageDTO::from(saved)))
+}
+
+/// Converte il rifiuto di un messaggio nella risposta HTTP per il chiamante del webhook
+fn rejection_error(rejection: MessageRejection) -> AppError {
+    match rejection {
+        MessageRejection::Invalid(message) => AppError::bad_request(message),
+        MessageRejection::NotMember => {
+            AppError::forbidden("The webhook bot is no longer a member of this chat")
+        }
+        MessageRejection::Restricted { message, .. } => AppError::forbidden(message),
+        MessageRejection::Internal(message) => {
+            error!("Webhook message not delivered: {}", message);
+            AppError::internal_server_error(message)
+        }
+    }
+}
@@ -1,15 +1,58 @@
-use crate::dtos::MessageDTO;
+use crate::dtos::{BannedMemberDTO, ChatDTO, MessageDTO, PresenceDTO, UserStatusDTO};
 use crate::ws::BROADCAST_CHANNEL_CAPACITY;
 use dashmap::DashMap;
+use serde::Serialize;
 use std::sync::Arc;
 use tokio::sync::broadcast;
 use tokio::sync::broadcast::error::SendError;
 use tokio::sync::broadcast::{Receiver, Sender};
-use tracing::{info, instrument, warn};
+use tracing::{debug, info, instrument, warn};
+
+/// Eventi diffusi sul canale broadcast di una chat.
+/// I payload sono in Arc: il broadcast clona l'evento per ogni rx, così si copia solo il puntatore.
+#[derive(Serialize, Debug, Clone)]
+pub enum ChatEvent {
+    /// Nuovo messaggio, inviato al client nei batch
+    Message(Arc<MessageDTO>),
+    /// Messaggio modificato dal suo autore
+    MessageEdited(Arc<MessageDTO>),
+    /// Messaggio eliminato (soft-delete), il payload è il tombstone senza contenuto
+    MessageDeleted(Arc<MessageDTO>),
+    /// Messaggio fissato da un Admin/Owner
+    MessagePinned(Arc<MessageDTO>),
+    /// Messaggio rimosso dai fissati
+    MessageUnpinned(Arc<MessageDTO>),
+    /// Un membro della chat si è connesso o disconnesso
+    PresenceChanged(PresenceDTO),
+    /// Un membro della chat ha impostato o cancellato il proprio stato personalizzato
+    StatusChanged(UserStatusDTO),
+    /// Titolo, descrizione o avatar della chat modificati da un Admin/Owner
+    ChatUpdated(Arc<ChatDTO>),
+    /// Un utente è stato bannato dalla chat da un Admin/Owner
+    MemberBanned(BannedMemberDTO),
+}
 
+impl ChatEvent {
+    /// Restituisce il messaggio se l'evento è un nuovo messaggio
+    #[allow(dead_code)]
+    pub fn as_message(&self) -> Option<&MessageDTO> {
+        match self {
+            ChatEvent::Message(msg) => Some(msg),
+            _ => None,
+        }
+    }
+}
+
+/// Canali broadcast delle chat con almeno un iscritto online.
+///
+/// La DashMap divide le chiavi in shard con lock indipendenti, così chat diverse non si
+/// contendono lo stesso lock. Creazione e rimozione di un canale avvengono sotto il lock
+/// del suo shard (entry / remove_if): un iscritto non può finire su un canale appena rimosso.
+/// I canali senza più receiver vengono eliminati in modo lazy, al primo invio fallito
+/// o quando una connessione rilascia le proprie sottoscrizioni.
 pub struct ChatMap {
     /// Attribute to retrieve the tx head og a broadcast channel by chat_id field
-    channels: DashMap<i32, Sender<Arc<MessageDTO>>>,
+    channels: DashMap<i32, Sender<ChatEvent>>,
 }
 
 impl ChatMap {
@@ -20,53 +63,92 @@ impl ChatMap {
     }
 
     #[instrument(skip(self), fields(chat_id))]
-    pub fn subscribe(&self, chat_id: &i32) -> Receiver<Arc<MessageDTO>> {
-        match self.channels.get(chat_id) {
-            // required subscription on non existing chat channel
-            None => {
+    pub fn subscribe(&self, chat_id: &i32) -> Receiver<ChatEvent> {
+        // subscribe to an existing channel == get a rx head == subscribe to a tx;
+        // il lock dello shard resta acquisito fino alla subscribe
+        self.channels
+            .entry(*chat_id)
+            .or_insert_with(|| {
                 info!("Creating new broadcast channel for chat");
                 // Arc<Message> to share the ref, not the message. Avoid unuseful copies of message on each rx.
-                let (tx, rx) = broadcast::channel::<Arc<MessageDTO>>(BROADCAST_CHANNEL_CAPACITY);
-                self.channels.insert(*chat_id, tx);
-                rx
-            }
-            // subscribe to an existing channel == get a rx head == subscribe to a tx
-            Some(c) => {
-                info!("Subscribing to existing broadcast channel");
-                c.value().subscribe()
-            }
-        }
+                broadcast::channel::<ChatEvent>(BROADCAST_CHANNEL_CAPACITY).0
+            })
+            .subscribe()
     }
 
     #[instrument(skip(self, chat_ids))]
-    pub fn subscribe_multiple(&self, chat_ids: Vec<i32>) -> Vec<Receiver<Arc<MessageDTO>>> {
+    pub fn subscribe_multiple(&self, chat_ids: Vec<i32>) -> Vec<Receiver<ChatEvent>> {
         info!(count = chat_ids.len(), "Subscribing to multiple chats");
         chat_ids.into_iter().map(|id| self.subscribe(&id)).collect()
     }
 
-    #[instrument(skip(self, msg), fields(chat_id))]
+    /// Invia un nuovo messaggio a tutti gli iscritti della chat
     pub fn send(
         &self,
         chat_id: &i32,
         msg: Arc<MessageDTO>,
-    ) -> Result<usize, SendError<Arc<MessageDTO>>> {
-        if let Some(chat) = self.channels.get(chat_id) {
-            match chat.send(msg.clone()) {
-                Ok(n) => {
-                    info!(receivers = n, "Message broadcast to receivers");
-                    Ok(n)
-                }
-                Err(e) => {
-                    warn!("No active receivers, removing channel");
-                    // Nessuno sta ascoltando, rimuovi il channel
-                    drop(chat); // Rilascia il lock
-                    self.channels.remove(chat_id);
-                    Err(e)
-                }
-            }
-        } else {
+    ) -> Result<usize, SendError<ChatEvent>> {
+        self.send_event(chat_id, ChatEvent::Message(msg))
+    }
+
+    /// Invia un evento generico a tutti gli iscritti della chat
+    #[instrument(skip(self, event), fields(chat_id))]
+    pub fn send_event(
+        &self,
+        chat_id: &i32,
+        event: ChatEvent,
+    ) -> Result<usize, SendError<ChatEvent>> {
+        let Some(chat) = self.channels.get(chat_id) else {
             warn!("Attempted to send to non-existent chat channel");
-            Err(SendError(msg))
+            return Err(SendError(event));
+        };
+        let result = chat.send(event);
+        drop(chat); // Rilascia il lock dello shard prima di un'eventuale rimozione
+
+        match result {
+            Ok(n) => {
+                info!(receivers = n, "Message broadcast to receivers");
+                Ok(n)
+            }
+            Err(e) => {
+                warn!("No active receivers, removing channel");
+                self.evict_if_unsubscribed(chat_id);
+                Err(e)
+            }
+        }
+    }
+
+    /// Rimuove il channel della chat (es. chat eliminata): i BroadcastStream degli iscritti
+    /// terminano quando anche l'ultimo Sender viene droppato
+    #[instrument(skip(self), fields(chat_id))]
+    pub fn remove_chat(&self, chat_id: &i32) {
+        if self.channels.remove(chat_id).is_some() {
+            info!("Broadcast channel removed");
+        }
+    }
+
+    /// Rimuove il channel della chat se non ha più receiver.
+    /// Il controllo avviene sotto il lock dello shard, quindi una subscribe concorrente
+    /// trova il canale ancora presente oppure ne crea uno nuovo
+    ///
+    /// # Returns
+    /// true se il canale è stato rimosso
+    pub fn evict_if_unsubscribed(&self, chat_id: &i32) -> bool {
+        self.channels
+            .remove_if(chat_id, |_, tx| tx.receiver_count() == 0)
+            .is_some()
+    }
+
+    /// Rilascia le sottoscrizioni di una connessione che si chiude o lascia delle chat:
+    /// da chiamare dopo aver droppato i receiver, elimina i canali rimasti senza iscritti
+    #[instrument(skip(self, chat_ids))]
+    pub fn release(&self, chat_ids: impl IntoIterator<Item = i32>) {
+        let evicted = chat_ids
+            .into_iter()
+            .filter(|chat_id| self.evict_if_unsubscribed(chat_id))
+            .count();
+        if evicted > 0 {
+            debug!(evicted, "Evicted broadcast channels without subscribers");
         }
     }
 
@@ -112,6 +194,11 @@ mod tests {
             content: Some(content.to_string()),
             created_at: Some(Utc::now()),
             message_type: Some(MessageType::UserMessage),
+            deleted_at: None,
+            reply_to_message_id: None,
+            client_msg_id: None,
+            attachment_id: None,
+            content_format: None,
         })
     }
 
@@ -243,12 +330,12 @@ mod tests {
         assert!(received1.is_ok(), "Receiver 1 should receive the message");
         assert!(received2.is_ok(), "Receiver 2 should receive the message");
         assert_eq!(
-            received1.unwrap().content,
+            received1.unwrap().as_message().unwrap().content,
             Some("hello world".to_string()),
             "Message content should match"
         );
         assert_eq!(
-            received2.unwrap().content,
+            received2.unwrap().as_message().unwrap().content,
             Some("hello world".to_string()),
             "Message content should match"
         );
@@ -288,4 +375,63 @@ mod tests {
             "Chat channel should not exist after removal"
         );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_remove_chat_closes_subscribers() {
+        let chatmap = ChatMap::new();
+        let chat_id = 1;
+
+        let mut rx = chatmap.subscribe(&chat_id);
+        chatmap.remove_chat(&chat_id);
+
+        assert!(
+            !chatmap.has_chat_channel(&chat_id),
+            "Channel should be removed"
+        );
+        // l'unico Sender è stato droppato, il receiver risulta chiuso
+        assert!(matches!(
+            rx.try_recv(),
+            Err(broadcast::error::TryRecvError::Closed)
+        ));
+    }
+
+    #[test]
+    fn test_release_evicts_only_unsubscribed_channels() {
+        let chatmap = ChatMap::new();
+
+        let rx1 = chatmap.subscribe(&1);
+        let _rx2 = chatmap.subscribe(&2);
+        drop(rx1);
+
+        chatmap.release(vec![1, 2, 3]);
+
+        assert!(
+            !chatmap.has_chat_channel(&1),
+            "Unsubscribed channel should be evicted"
+        );
+        assert!(
+            chatmap.has_chat_channel(&2),
+            "Subscribed channel should be kept"
+        );
+        assert!(!chatmap.evict_if_unsubscribed(&2));
+    }
+
+    #[test]
+    fn test_concurrent_subscribe_shares_one_channel() {
+        let chatmap = Arc::new(ChatMap::new());
+        let chat_id = 1;
+
+        // tutte le subscribe concorrenti devono finire sullo stesso canale:
+        // un canale sovrascritto lascerebbe i suoi iscritti senza messaggi
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let chatmap = chatmap.clone();
+                std::thread::spawn(move || chatmap.subscribe(&chat_id))
+            })
+            .collect();
+        let receivers: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+        let result = chatmap.send(&chat_id, create_test_message(chat_id, "hello"));
+        assert_eq!(result.unwrap(), receivers.len());
+    }
+}
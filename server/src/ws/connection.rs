@@ -1,65 +1,365 @@
 //! WebSocket Connection Management - Gestione connessioni WebSocket
 
-use crate::ws::{BATCH_INTERVAL, BATCH_MAX_SIZE, RATE_LIMITER_MILLIS, TIMEOUT_DURATION_SECONDS};
+use crate::ws::PRESENCE_BROADCAST;
 use crate::{
     AppState,
-    dtos::MessageDTO,
-    ws::{event_handlers::process_message, usermap::InternalSignal},
+    core::config::{WsConfig, WsOverflowPolicy},
+    dtos::{MessageDTO, MissedMessagesDTO},
+    repositories::{DeliveredBatch, MemberFilter, Page},
+    ws::{
+        chatmap::ChatEvent,
+        event_handlers::{
+            broadcast_presence, load_lagged_messages, load_missed_messages, process_message,
+        },
+        offline_queue::{OFFLINE_QUEUE_PAGE_SIZE, group_by_chat},
+        signal_queue::{SignalReceiver, SignalSender, signal_channel},
+        usermap::{ConnectionInfo, InternalSignal},
+    },
 };
 use axum::extract::ws::Utf8Bytes;
-use axum::extract::ws::{Message, WebSocket};
+use axum::extract::ws::{CloseFrame, Message, WebSocket, close_code};
+use axum::http::HeaderValue;
+use bytes::Bytes;
+use chrono::Utc;
 use futures_util::stream::{SplitSink, SplitStream};
 use futures_util::{SinkExt, StreamExt};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
-use tokio::time::Duration;
-use tokio::time::{interval, timeout};
+use tokio::time::{Duration, Instant, sleep, timeout};
 use tokio_stream::StreamMap;
 use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
 use tracing::{error, info, instrument, warn};
 
-#[instrument(skip(ws, state), fields(user_id))]
-pub async fn handle_socket(ws: WebSocket, state: Arc<AppState>, user_id: i32) {
-    info!("WebSocket connection established");
+/// Codifica dei frame inviati al client, negoziata con l'header `Sec-WebSocket-Protocol`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WsEncoding {
+    /// Frame di testo JSON, usati se il client non richiede un sottoprotocollo
+    #[default]
+    Json,
+    /// Frame binari MessagePack, con le stesse chiavi del JSON
+    MessagePack,
+}
+
+impl WsEncoding {
+    /// Sottoprotocolli supportati, in ordine di preferenza del server
+    pub const PROTOCOLS: [&'static str; 2] = ["msgpack", "json"];
+
+    /// Codifica corrispondente al sottoprotocollo scelto durante l'upgrade
+    pub fn from_protocol(protocol: Option<&HeaderValue>) -> Self {
+        match protocol.and_then(|protocol| protocol.to_str().ok()) {
+            Some("msgpack") => WsEncoding::MessagePack,
+            _ => WsEncoding::Json,
+        }
+    }
+
+    /// Serializza un valore nel frame da inviare al client
+    pub fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Message, axum::Error> {
+        match self {
+            WsEncoding::Json => serde_json::to_string(value)
+                .map(|json| Message::Text(Utf8Bytes::from(json)))
+                .map_err(axum::Error::new),
+            WsEncoding::MessagePack => rmp_serde::to_vec_named(value)
+                .map(|bytes| Message::Binary(Bytes::from(bytes)))
+                .map_err(axum::Error::new),
+        }
+    }
+
+    /// Frame di un errore generico: in JSON resta testo semplice, non un documento JSON
+    fn encode_error(&self, message: &'static str) -> Result<Message, axum::Error> {
+        match self {
+            WsEncoding::Json => Ok(Message::Text(Utf8Bytes::from_static(message))),
+            WsEncoding::MessagePack => self.encode(message),
+        }
+    }
+}
+
+/// Deserializza un frame ricevuto dal client: testo JSON o binario MessagePack,
+/// qualunque sia la codifica negoziata per i frame in uscita
+pub fn decode_frame<T: DeserializeOwned>(frame: &Message) -> Option<T> {
+    match frame {
+        Message::Text(text) => serde_json::from_str(text.as_str()).ok(),
+        Message::Binary(bytes) => rmp_serde::from_slice(bytes).ok(),
+        _ => None,
+    }
+}
+
+/// Token bucket dei messaggi in ingresso di una connessione: consente raffiche fino a
+/// `message_burst` messaggi, poi ne accetta `messages_per_second` al secondo
+struct MessageBucket {
+    capacity: f64,
+    refill_per_sec: f64,
+    tokens: f64,
+    refreshed_at: Instant,
+}
+
+impl MessageBucket {
+    fn new(config: &WsConfig) -> Self {
+        let capacity = config.message_burst.max(1) as f64;
+        Self {
+            capacity,
+            refill_per_sec: config.messages_per_second as f64,
+            tokens: capacity,
+            refreshed_at: Instant::now(),
+        }
+    }
+
+    /// Consuma un token
+    ///
+    /// # Returns
+    /// * `Ok(())` se il messaggio rientra nel limite
+    /// * `Err(Duration)` - Tempo da attendere prima che sia disponibile un nuovo token
+    fn try_acquire_at(&mut self, now: Instant) -> Result<(), Duration> {
+        if self.refill_per_sec == 0.0 {
+            return Ok(());
+        }
+        let elapsed = now.duration_since(self.refreshed_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.refreshed_at = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - self.tokens) / self.refill_per_sec,
+            ))
+        }
+    }
+
+    /// Attende che sia disponibile un token: oltre il limite il client viene rallentato
+    /// (la lettura dal socket si ferma), non disconnesso
+    async fn acquire(&mut self) {
+        while let Err(wait) = self.try_acquire_at(Instant::now()) {
+            sleep(wait).await;
+        }
+    }
+}
+
+/// Messaggi già consegnati al client da una connessione, per chat
+#[derive(Default)]
+struct DeliveryCursor {
+    /// Ultimo message_id inviato (o indicato dal client alla riconnessione)
+    last_seen: HashMap<i32, i32>,
+    /// Messaggi inviati con un replay dal database, che possono arrivare anche dal canale
+    /// broadcast e non vanno consegnati due volte
+    replayed: HashMap<i32, HashSet<i32>>,
+}
+
+impl DeliveryCursor {
+    fn new(since: &HashMap<i32, i32>) -> Self {
+        Self {
+            last_seen: since.clone(),
+            replayed: HashMap::new(),
+        }
+    }
+
+    fn last_seen(&self, chat_id: i32) -> Option<i32> {
+        self.last_seen.get(&chat_id).copied()
+    }
+
+    /// Registra un messaggio arrivato dal canale broadcast. I messaggi vengono inoltrati solo
+    /// dopo il salvataggio, quindi `message_id` è quello assegnato dal database
+    ///
+    /// # Returns
+    /// false se il messaggio era già stato inviato con un replay
+    fn record_live(&mut self, message: &MessageDTO) -> bool {
+        let (Some(chat_id), Some(message_id)) = (message.chat_id, message.message_id) else {
+            return true;
+        };
+        if let Some(replayed) = self.replayed.get_mut(&chat_id)
+            && replayed.remove(&message_id)
+        {
+            return false;
+        }
+        self.advance(chat_id, message_id);
+        true
+    }
+
+    /// Registra i messaggi inviati con un replay dal database
+    fn record_replay(&mut self, replay: &MissedMessagesDTO) {
+        let replayed = self.replayed.entry(replay.chat_id).or_default();
+        replayed.clear();
+        let mut newest = None;
+        for message_id in replay.messages.iter().filter_map(|m| m.message_id) {
+            replayed.insert(message_id);
+            newest = newest.max(Some(message_id));
+        }
+        if let Some(message_id) = newest {
+            self.advance(replay.chat_id, message_id);
+        }
+    }
+
+    fn advance(&mut self, chat_id: i32, message_id: i32) {
+        let last_seen = self.last_seen.entry(chat_id).or_insert(message_id);
+        *last_seen = (*last_seen).max(message_id);
+    }
+}
+
+/// Messaggi consegnati per chat, in attesa di diventare il nuovo `messages_received_until`
+/// del membro e di essere scalati dal suo `unread_count`
+///
+/// Gli aggiornamenti sono raggruppati: al più uno ogni `flush_interval`, per tutte le chat
+/// con consegne nuove, così una chat molto attiva non scrive sul database ad ogni batch.
+struct ReceivedMarkers {
+    user_id: i32,
+    pending: HashMap<i32, DeliveredBatch>,
+    /// None se l'aggiornamento automatico è disabilitato
+    flush_interval: Option<Duration>,
+    flushed_at: Instant,
+}
+
+impl ReceivedMarkers {
+    fn new(config: &WsConfig, user_id: i32) -> Self {
+        Self {
+            user_id,
+            pending: HashMap::new(),
+            flush_interval: (config.received_flush_interval_ms > 0)
+                .then(|| Duration::from_millis(config.received_flush_interval_ms)),
+            flushed_at: Instant::now(),
+        }
+    }
+
+    /// Registra i messaggi appena inviati al client. `created_at` è quello del messaggio
+    /// salvato: il server ignora la data indicata da chi lo ha inviato
+    fn record<'a>(&mut self, messages: impl IntoIterator<Item = &'a MessageDTO>) {
+        if self.flush_interval.is_none() {
+            return;
+        }
+        for message in messages {
+            let (Some(chat_id), Some(message_id), Some(created_at)) =
+                (message.chat_id, message.message_id, message.created_at)
+            else {
+                continue;
+            };
+            let batch = self.pending.entry(chat_id).or_insert(DeliveredBatch {
+                chat_id,
+                received_until: created_at,
+                last_message_id: message_id,
+                unread: 0,
+            });
+            batch.received_until = batch.received_until.max(created_at);
+            batch.last_message_id = batch.last_message_id.max(message_id);
+            // i propri messaggi non sono mai contati tra i non letti
+            if message.sender_id != Some(self.user_id) {
+                batch.unread += 1;
+            }
+        }
+    }
+
+    /// Marcatori da scrivere se è trascorso `flush_interval` dall'ultimo aggiornamento
+    fn take_due(&mut self, now: Instant) -> Option<Vec<DeliveredBatch>> {
+        let interval = self.flush_interval?;
+        if self.pending.is_empty() || now.duration_since(self.flushed_at) < interval {
+            return None;
+        }
+        self.flushed_at = now;
+        Some(self.take_all())
+    }
+
+    fn take_all(&mut self) -> Vec<DeliveredBatch> {
+        self.pending.drain().map(|(_, batch)| batch).collect()
+    }
+}
+
+/// Avanza `messages_received_until` del membro per le chat consegnate. Un errore viene
+/// solo registrato: il marcatore resta indietro e i messaggi risultano ancora non letti
+async fn flush_received_markers(state: &AppState, user_id: i32, received: Vec<DeliveredBatch>) {
+    if received.is_empty() {
+        return;
+    }
+    if let Err(e) = state.meta.advance_received_until(&user_id, &received).await {
+        warn!("Failed to advance received markers: {:?}", e);
+    }
+}
+
+/// `since` contiene, per le chat indicate dal client che si riconnette, l'ultimo
+/// message_id ricevuto: i messaggi successivi vengono reinviati prima di quelli in tempo reale
+#[instrument(skip(ws, state, since, connection), fields(user_id))]
+pub async fn handle_socket(
+    ws: WebSocket,
+    state: Arc<AppState>,
+    user_id: i32,
+    since: HashMap<i32, i32>,
+    encoding: WsEncoding,
+    connection: ConnectionInfo,
+) {
+    info!(?encoding, "WebSocket connection established");
 
     // Dividiamo il WebSocket in due metà: sender e receiver
     let (ws_tx, ws_rx) = ws.split();
 
-    // Creiamo un canale unbounded per comunicazione interna
-    // considerare passaggio a unbounded channel per non perdere eventuali segnali
-    let (int_tx, int_rx) = unbounded_channel::<InternalSignal>();
+    // Coda limitata per la comunicazione interna: un client lento non può farla crescere
+    // senza limiti, oltre la capacità si applica la policy di overflow configurata
+    let (int_tx, int_rx) = signal_channel(
+        state.ws_config.signal_queue_capacity,
+        state.ws_config.overflow_policy,
+    );
 
     // Salviamo nello stato il trasmettitore di watch associato all'utente
     // Il ricevitore sarà usato dal task dedicato alla scrittura
-    state.users_online.register_online(user_id, int_tx.clone());
+    let epoch = state
+        .users_online
+        .register_connection(user_id, int_tx.clone(), connection);
+    if let Some(presence) = &state.presence {
+        presence.mark_online(user_id).await;
+    }
     info!("User registered as online");
 
+    if PRESENCE_BROADCAST {
+        let presence_state = state.clone();
+        tokio::spawn(async move {
+            broadcast_presence(&presence_state, user_id, true, None).await;
+        });
+    }
+
     // dobbiamo iniziare un task che stia in ascolto del websocket
-    tokio::spawn(listen_ws(user_id, ws_rx, int_tx.clone(), state.clone()));
+    tokio::spawn(listen_ws(
+        user_id,
+        epoch,
+        ws_rx,
+        int_tx.clone(),
+        state.clone(),
+    ));
 
     // creare un task che sta in ascolto sull'insieme dei canali broadcast
-    tokio::spawn(write_ws(user_id, ws_tx, int_rx, state));
+    tokio::spawn(write_ws(user_id, ws_tx, int_rx, state, since, encoding));
 }
 
-#[instrument(skip(websocket_tx, internal_rx, state), fields(user_id))]
+#[instrument(skip(websocket_tx, internal_rx, state, since), fields(user_id))]
 pub async fn write_ws(
     user_id: i32,
     mut websocket_tx: SplitSink<WebSocket, Message>,
-    mut internal_rx: UnboundedReceiver<InternalSignal>,
+    mut internal_rx: SignalReceiver,
     state: Arc<AppState>,
+    since: HashMap<i32, i32>,
+    encoding: WsEncoding,
 ) {
     info!("Write task started");
 
-    let chat_vec: Vec<i32> = match state.meta.find_many_by_user_id(&user_id).await {
+    // i messaggi creati da ora arrivano dai canali broadcast: è il punto da cui recuperarli
+    // se un canale resta indietro prima di aver consegnato messaggi della chat
+    let connected_at = Utc::now();
+    let mut cursor = DeliveryCursor::new(&since);
+    let mut received = ReceivedMarkers::new(&state.ws_config, user_id);
+
+    let chats = match state
+        .meta
+        .read_many(&MemberFilter::of_user(user_id).from_primary(), &Page::ALL)
+        .await
+    {
         Ok(chats) => {
             info!(chat_count = chats.len(), "User chats loaded");
-            chats.iter().map(|m| m.chat_id).collect()
+            chats
         }
         Err(e) => {
             error!("Failed to load user chats: {:?}", e);
             return; // Termina se DB fallisce
         }
     };
+    let chat_vec: Vec<i32> = chats.iter().map(|m| m.chat_id).collect();
 
     let mut stream_map = StreamMap::new();
 
@@ -71,24 +371,125 @@ pub async fn write_ws(
         .for_each(|(rx, chat_id)| {
             stream_map.insert(chat_id, BroadcastStream::new(rx));
         });
-    
+
+    // ripresa della sessione: i messaggi persi vengono inviati prima di quelli in tempo reale,
+    // che nel frattempo restano in coda nei canali appena sottoscritti
+    if !since.is_empty() {
+        match load_missed_messages(&state, &chats, &since).await {
+            Ok(missed) => {
+                for replay in missed {
+                    cursor.record_replay(&replay);
+                    let frame = serde_json::json!({"Replay": replay});
+                    if let Err(e) = send_frame(&mut websocket_tx, encoding, &frame).await {
+                        warn!("Failed to replay missed messages: {:?}", e);
+                        return;
+                    }
+                    received.record(&replay.messages);
+                }
+            }
+            Err(e) => {
+                error!("Failed to load missed messages: {:?}", e);
+            }
+        }
+    }
+    drop(chats); // i metadata non servono per il resto della connessione
+
+    // messaggi salvati mentre l'utente era offline, anch'essi prima del tempo reale
+    let drained = drain_offline_queue(
+        &state,
+        user_id,
+        &mut websocket_tx,
+        encoding,
+        &mut cursor,
+        &mut received,
+    )
+    .await;
+    if let Err(e) = drained {
+        warn!("Failed to deliver offline queue: {:?}", e);
+        return;
+    }
 
     let mut batch: Vec<Arc<MessageDTO>> = Vec::new();
-    let mut interval = tokio::time::interval(Duration::from_millis(BATCH_INTERVAL));
+    let batch_max_size = state.ws_config.batch_max_size;
+    let mut interval =
+        tokio::time::interval(Duration::from_millis(state.ws_config.batch_interval_ms));
     interval.tick().await; // Consuma primo tick immediato
 
     'external: loop {
         tokio::select! {
-            Some((_, result)) = tokio_stream::StreamExt::next(&mut stream_map) => {
-                if let Ok(msg) = result {
-                    batch.push(msg);
-                    if batch.len() >= BATCH_MAX_SIZE {
-                        if send_batch(&mut websocket_tx, &batch).await.is_err() {
-                            warn!("Failed to send batch, closing connection");
+            Some((chat_id, result)) = tokio_stream::StreamExt::next(&mut stream_map) => {
+                match result {
+                    Ok(ChatEvent::Message(msg)) => {
+                        if !cursor.record_live(&msg) {
+                            continue;
+                        }
+                        batch.push(msg);
+                        if batch.len() >= batch_max_size {
+                            if send_frame(&mut websocket_tx, encoding, &batch).await.is_err() {
+                                warn!("Failed to send batch, closing connection");
+                                break 'external;
+                            }
+                            info!(batch_size = batch.len(), "Batch sent");
+                            received.record(batch.iter().map(Arc::as_ref));
+                            batch.clear();
+                        }
+                    }
+                    Ok(event) => {
+                        // gli eventi diversi dai messaggi non vengono accodati nel batch,
+                        // ma prima svuoto il batch per non alterare l'ordine lato client
+                        if !batch.is_empty() {
+                            if send_frame(&mut websocket_tx, encoding, &batch).await.is_err() {
+                                warn!("Failed to send batch before event, closing connection");
+                                break 'external;
+                            }
+                            received.record(batch.iter().map(Arc::as_ref));
+                            batch.clear();
+                        }
+                        if send_frame(&mut websocket_tx, encoding, &event).await.is_err() {
+                            warn!("Failed to send chat event, closing connection");
                             break 'external;
                         }
-                        info!(batch_size = batch.len(), "Batch sent");
-                        batch.clear();
+                    }
+                    Err(BroadcastStreamRecvError::Lagged(skipped)) => {
+                        warn!(chat_id, skipped, "Chat channel lagged, resyncing from database");
+                        // svuoto il batch, così last_seen è l'ultimo messaggio davvero inviato
+                        if !batch.is_empty() {
+                            if send_frame(&mut websocket_tx, encoding, &batch).await.is_err() {
+                                warn!("Failed to send batch before resync, closing connection");
+                                break 'external;
+                            }
+                            received.record(batch.iter().map(Arc::as_ref));
+                            batch.clear();
+                        }
+
+                        let last_seen = cursor.last_seen(chat_id);
+                        let frame = serde_json::json!({
+                            "Resync": {"chat_id": chat_id, "last_seen": last_seen}
+                        });
+                        if send_frame(&mut websocket_tx, encoding, &frame).await.is_err() {
+                            warn!("Failed to send resync, closing connection");
+                            break 'external;
+                        }
+
+                        // se il recupero fallisce il client ha comunque last_seen per
+                        // ricaricare la cronologia via HTTP
+                        match load_lagged_messages(&state, user_id, chat_id, last_seen, connected_at)
+                            .await
+                        {
+                            Ok(Some(replay)) => {
+                                cursor.record_replay(&replay);
+                                let frame = serde_json::json!({"Replay": replay});
+                                if send_frame(&mut websocket_tx, encoding, &frame).await.is_err() {
+                                    warn!("Failed to send resync replay, closing connection");
+                                    break 'external;
+                                }
+                                received.record(&replay.messages);
+                            }
+                            Ok(None) => {}
+                            Err(e) => {
+                                error!("Failed to load lagged messages: {:?}", e);
+                            }
+                        }
                     }
                 }
             }
@@ -97,13 +498,22 @@ pub async fn write_ws(
             // altrimenti aspetterei troppo
             _ = interval.tick() => {
                 if !batch.is_empty() {
-                    if send_batch(&mut websocket_tx, &batch).await.is_err() {
+                    if send_frame(&mut websocket_tx, encoding, &batch).await.is_err() {
                         warn!("Failed to send batch on interval, closing connection");
                         break 'external;
                     }
                     info!(batch_size = batch.len(), "Batch sent on interval");
+                    received.record(batch.iter().map(Arc::as_ref));
                     batch.clear();
                 }
+
+                // i marcatori vengono scritti in un task separato per non rallentare l'invio
+                if let Some(markers) = received.take_due(Instant::now()) {
+                    let flush_state = state.clone();
+                    tokio::spawn(async move {
+                        flush_received_markers(&flush_state, user_id, markers).await;
+                    });
+                }
             }
 
             signal = internal_rx.recv() => {
@@ -112,51 +522,147 @@ pub async fn write_ws(
                         info!("Shutdown signal received");
                         break 'external;
                     }
-                    Some(InternalSignal::AddChat(chat_id)) => {
-                        info!(chat_id, "Adding chat subscription");
-                        let rx = state.chats_online.subscribe(&chat_id);
-                        stream_map.insert(chat_id, BroadcastStream::new(rx));
-                        
-                        // Invia notifica al client
-                        let msg = serde_json::json!({"AddChat": chat_id});
-                        if let Ok(json) = serde_json::to_string(&msg) {
-                            if let Err(e) = websocket_tx.send(Message::Text(Utf8Bytes::from(json))).await {
-                                error!("Failed to send AddChat notification: {:?}", e);
-                                break 'external;
-                            }
+                    Some(InternalSignal::ServerShutdown) => {
+                        info!("Server shutting down, closing connection");
+                        // il batch va inviato prima del Close, dopo il client non legge più
+                        if !batch.is_empty()
+                            && send_frame(&mut websocket_tx, encoding, &batch).await.is_ok()
+                        {
+                            received.record(batch.iter().map(Arc::as_ref));
+                        }
+                        batch.clear();
+                        let close = Message::Close(Some(CloseFrame {
+                            code: close_code::RESTART,
+                            reason: Utf8Bytes::from_static("Server restarting"),
+                        }));
+                        let _ = websocket_tx.send(close).await;
+                        break 'external;
+                    }
+                    Some(InternalSignal::QueueOverflow { dropped }) => {
+                        warn!(dropped, "Client too slow, signals dropped");
+                        if state.ws_config.overflow_policy == WsOverflowPolicy::Disconnect {
+                            let close = Message::Close(Some(CloseFrame {
+                                code: close_code::AGAIN,
+                                reason: Utf8Bytes::from_static("Client too slow"),
+                            }));
+                            let _ = websocket_tx.send(close).await;
+                            break 'external;
+                        }
+
+                        // il client deve ricaricare chat e inviti, alcuni aggiornamenti sono persi
+                        let msg = serde_json::json!({"ResyncHint": {"dropped_signals": dropped}});
+                        if let Err(e) = send_frame(&mut websocket_tx, encoding, &msg).await {
+                            error!("Failed to send resync hint: {:?}", e);
+                            break 'external;
+                        }
+                    }
+                    Some(InternalSignal::ChatCreated(chat)) => {
+                        // sottoscrivo la chat prima di notificarla, così il client non perde messaggi
+                        if let Some(chat_id) = chat.chat_id {
+                            info!(chat_id, "Adding subscription for created chat");
+                            let rx = state.chats_online.subscribe(&chat_id);
+                            stream_map.insert(chat_id, BroadcastStream::new(rx));
+                        }
+
+                        let msg = serde_json::json!({"ChatCreated": chat});
+                        if let Err(e) = send_frame(&mut websocket_tx, encoding, &msg).await {
+                            error!("Failed to send ChatCreated notification: {:?}", e);
+                            break 'external;
+                        }
+                    }
+                    Some(InternalSignal::ChatJoined(chat)) => {
+                        if let Some(chat_id) = chat.chat_id {
+                            info!(chat_id, "Adding subscription for joined chat");
+                            let rx = state.chats_online.subscribe(&chat_id);
+                            stream_map.insert(chat_id, BroadcastStream::new(rx));
+                        }
+
+                        let msg = serde_json::json!({"ChatJoined": chat});
+                        if let Err(e) = send_frame(&mut websocket_tx, encoding, &msg).await {
+                            error!("Failed to send ChatJoined notification: {:?}", e);
+                            break 'external;
                         }
                     }
                     Some(InternalSignal::RemoveChat(chat_id)) => {
                         info!(chat_id, "Removing chat subscription");
                         stream_map.remove(&chat_id);
+                        state.chats_online.evict_if_unsubscribed(&chat_id);
                         
                         // Invia notifica al client
                         let msg = serde_json::json!({"RemoveChat": chat_id});
-                        if let Ok(json) = serde_json::to_string(&msg) {
-                            if let Err(e) = websocket_tx.send(Message::Text(Utf8Bytes::from(json))).await {
-                                error!("Failed to send RemoveChat notification: {:?}", e);
-                                break 'external;
-                            }
+                        if let Err(e) = send_frame(&mut websocket_tx, encoding, &msg).await {
+                            error!("Failed to send RemoveChat notification: {:?}", e);
+                            break 'external;
+                        }
+                    }
+                    Some(InternalSignal::ChatDeleted(chat_id)) => {
+                        info!(chat_id, "Chat deleted, removing subscription");
+                        stream_map.remove(&chat_id);
+                        state.chats_online.evict_if_unsubscribed(&chat_id);
+
+                        // Invia notifica al client
+                        let msg = serde_json::json!({"ChatDeleted": chat_id});
+                        if let Err(e) = send_frame(&mut websocket_tx, encoding, &msg).await {
+                            error!("Failed to send ChatDeleted notification: {:?}", e);
+                            break 'external;
                         }
                     }
                     Some(InternalSignal::Error(err_msg)) => {
                         warn!(error_message = err_msg, "Sending error message to client");
-                        if let Err(e) = websocket_tx.send(Message::Text(Utf8Bytes::from(err_msg))).await {
+                        let frame = encoding.encode_error(err_msg);
+                        if let Err(e) = send_encoded(&mut websocket_tx, frame).await {
                             error!("Failed to send error message: {:?}", e);
                             break;
                         }
                     }
+                    Some(InternalSignal::ChatError { chat_id, code, message }) => {
+                        warn!(chat_id, code, "Sending chat error to client");
+                        let msg = serde_json::json!({
+                            "Error": {"chat_id": chat_id, "code": code, "message": message}
+                        });
+                        if let Err(e) = send_frame(&mut websocket_tx, encoding, &msg).await {
+                            error!("Failed to send chat error: {:?}", e);
+                            break 'external;
+                        }
+                    }
                     Some(InternalSignal::Invitation(invitation)) => {
                         info!(invite_id = invitation.invite_id, "Sending invitation to client");
-                        // Wrappa l'invitation in un oggetto per consistenza con AddChat/RemoveChat
+                        // Wrappa l'invitation in un oggetto per consistenza con ChatCreated/RemoveChat
                         let wrapped = serde_json::json!({"Invitation": invitation});
-                        if let Ok(json) = serde_json::to_string(&wrapped) {
-                            if let Err(e) = websocket_tx.send(Message::Text(Utf8Bytes::from(json))).await {
-                                error!("Failed to send invitation: {:?}", e);
-                                break 'external;
-                            }
-                        } else {
-                            error!("Failed to serialize invitation");
+                        if let Err(e) = send_frame(&mut websocket_tx, encoding, &wrapped).await {
+                            error!("Failed to send invitation: {:?}", e);
+                            break 'external;
+                        }
+                    }
+                    Some(InternalSignal::JoinRequest(request)) => {
+                        info!(request_id = request.request_id, "Sending join request to client");
+                        let wrapped = serde_json::json!({"JoinRequest": request});
+                        if let Err(e) = send_frame(&mut websocket_tx, encoding, &wrapped).await {
+                            error!("Failed to send join request: {:?}", e);
+                            break 'external;
+                        }
+                    }
+                    Some(InternalSignal::JoinRequestResolved(request)) => {
+                        info!(request_id = request.request_id, "Sending join request outcome to client");
+                        let wrapped = serde_json::json!({"JoinRequestResolved": request});
+                        if let Err(e) = send_frame(&mut websocket_tx, encoding, &wrapped).await {
+                            error!("Failed to send join request outcome: {:?}", e);
+                            break 'external;
+                        }
+                    }
+                    Some(InternalSignal::FloodAlert(alert)) => {
+                        info!(chat_id = alert.chat_id, flagged_user = alert.user_id, "Sending flood alert to client");
+                        let wrapped = serde_json::json!({"FloodAlert": alert});
+                        if let Err(e) = send_frame(&mut websocket_tx, encoding, &wrapped).await {
+                            error!("Failed to send flood alert: {:?}", e);
+                            break 'external;
+                        }
+                    }
+                    Some(InternalSignal::SendResult(result)) => {
+                        let wrapped = serde_json::json!({"SendResult": result});
+                        if let Err(e) = send_frame(&mut websocket_tx, encoding, &wrapped).await {
+                            error!("Failed to send SendResult: {:?}", e);
+                            break 'external;
                         }
                     }
                     None => {
@@ -174,46 +680,127 @@ pub async fn write_ws(
             batch_size = batch.len(),
             "Sending final batch before shutdown"
         );
-        let _ = send_batch(&mut websocket_tx, &batch).await;
+        let sent = send_frame(&mut websocket_tx, encoding, &batch).await;
+        if sent.is_ok() {
+            received.record(batch.iter().map(Arc::as_ref));
+        }
     }
+    flush_received_markers(&state, user_id, received.take_all()).await;
+
+    // i receiver vanno droppati prima, altrimenti i canali risultano ancora sottoscritti
+    let subscribed: Vec<i32> = stream_map.keys().copied().collect();
+    drop(stream_map);
+    state.chats_online.release(subscribed);
 
     info!("Write task terminated");
 }
 
-#[instrument(skip(websocket_tx, batch))]
-async fn send_batch(
+/// Invia al client la coda offline dell'utente a pagine di `OFFLINE_QUEUE_PAGE_SIZE`,
+/// segnando i messaggi come consegnati dopo ogni pagina inviata. I messaggi già reinviati
+/// per i cursori `since` non vengono ripetuti. Un errore del database interrompe solo lo
+/// svuotamento: i messaggi restano in coda per la connessione successiva
+///
+/// # Returns
+/// Err se l'invio al client fallisce
+async fn drain_offline_queue(
+    state: &AppState,
+    user_id: i32,
+    websocket_tx: &mut SplitSink<WebSocket, Message>,
+    encoding: WsEncoding,
+    cursor: &mut DeliveryCursor,
+    received: &mut ReceivedMarkers,
+) -> Result<(), axum::Error> {
+    let mut after_message_id = 0;
+    loop {
+        let page = match state
+            .offline_queue
+            .find_pending(&user_id, &after_message_id, OFFLINE_QUEUE_PAGE_SIZE)
+            .await
+        {
+            Ok(page) => page,
+            Err(e) => {
+                error!("Failed to load offline queue: {:?}", e);
+                return Ok(());
+            }
+        };
+        let Some(last_message_id) = page.last().map(|m| m.message_id) else {
+            return Ok(());
+        };
+        let last_page = (page.len() as i64) < OFFLINE_QUEUE_PAGE_SIZE;
+
+        let unsent: Vec<_> = page
+            .into_iter()
+            .filter(|m| {
+                cursor
+                    .last_seen(m.chat_id)
+                    .is_none_or(|seen| m.message_id > seen)
+            })
+            .collect();
+        for replay in group_by_chat(unsent) {
+            let frame = serde_json::json!({"Replay": replay});
+            send_frame(websocket_tx, encoding, &frame).await?;
+            received.record(&replay.messages);
+            if let Some(newest) = replay.messages.last().and_then(|m| m.message_id) {
+                cursor.advance(replay.chat_id, newest);
+            }
+        }
+
+        match state
+            .offline_queue
+            .mark_delivered(&user_id, &last_message_id)
+            .await
+        {
+            Ok(delivered) => info!(delivered, "Offline queue delivered"),
+            Err(e) => warn!("Failed to mark offline queue as delivered: {:?}", e),
+        }
+        if last_page {
+            return Ok(());
+        }
+        after_message_id = last_message_id;
+    }
+}
+
+/// Invia al client un valore (batch di messaggi, evento o segnale) con la codifica negoziata
+async fn send_frame<T: Serialize + ?Sized>(
     websocket_tx: &mut SplitSink<WebSocket, Message>,
-    batch: &[Arc<MessageDTO>],
+    encoding: WsEncoding,
+    value: &T,
 ) -> Result<(), axum::Error> {
-    let json = serde_json::to_string(&batch).map_err(|e| {
-        error!("Failed to serialize batch: {:?}", e);
-        axum::Error::new(e)
+    send_encoded(websocket_tx, encoding.encode(value)).await
+}
+
+async fn send_encoded(
+    websocket_tx: &mut SplitSink<WebSocket, Message>,
+    frame: Result<Message, axum::Error>,
+) -> Result<(), axum::Error> {
+    let frame = frame.map_err(|e| {
+        error!("Failed to serialize frame: {:?}", e);
+        e
     })?;
-    websocket_tx
-        .send(Message::Text(Utf8Bytes::from(json)))
-        .await
-        .map_err(|e| {
-            error!("Failed to send batch through WebSocket: {:?}", e);
-            e
-        })
+    websocket_tx.send(frame).await.map_err(|e| {
+        error!("Failed to send frame through WebSocket: {:?}", e);
+        e
+    })
 }
 
 #[instrument(skip(websocket_rx, internal_tx, state), fields(user_id))]
 pub async fn listen_ws(
     user_id: i32,
+    epoch: u64,
     mut websocket_rx: SplitStream<WebSocket>,
-    internal_tx: UnboundedSender<InternalSignal>,
+    internal_tx: SignalSender,
     state: Arc<AppState>,
 ) {
     info!("Listen task started");
 
-    let mut rate_limiter = interval(Duration::from_millis(RATE_LIMITER_MILLIS));
-    let timeout_duration = Duration::from_secs(TIMEOUT_DURATION_SECONDS);
+    let mut rate_limiter = MessageBucket::new(&state.ws_config);
+    let timeout_secs = state.ws_config.idle_timeout_secs;
+    let timeout_duration = Duration::from_secs(timeout_secs);
 
     loop {
         match timeout(timeout_duration, StreamExt::next(&mut websocket_rx)).await {
             Ok(Some(msg_result)) => {
-                rate_limiter.tick().await;
+                rate_limiter.acquire().await;
 
                 let msg = match msg_result {
                     Ok(m) => m,
@@ -224,8 +811,8 @@ pub async fn listen_ws(
                 };
 
                 match msg {
-                    Message::Text(text) => {
-                        if let Ok(event) = serde_json::from_str::<MessageDTO>(&text) {
+                    Message::Text(_) | Message::Binary(_) => {
+                        if let Some(event) = decode_frame::<MessageDTO>(&msg) {
                             info!("Message received from client");
                             process_message(&state, user_id, event).await;
                         } else {
@@ -244,10 +831,7 @@ pub async fn listen_ws(
                 break;
             }
             Err(_) => {
-                warn!(
-                    timeout_secs = TIMEOUT_DURATION_SECONDS,
-                    "Connection timeout"
-                );
+                warn!(timeout_secs, "Connection timeout");
                 break;
             }
         }
@@ -256,6 +840,182 @@ pub async fn listen_ws(
     // Cleanup
     info!("Cleaning up connection");
     let _ = internal_tx.send(InternalSignal::Shutdown);
-    state.users_online.remove_from_online(&user_id);
+    let Some(last_seen) = state.users_online.remove_connection(&user_id, epoch) else {
+        // l'utente si è riconnesso prima della chiusura di questa connessione: resta online
+        info!("Listen task terminated");
+        return;
+    };
+    if let Some(presence) = &state.presence {
+        presence.mark_offline(user_id).await;
+    }
+    if let Err(e) = state.user.update_last_seen(&user_id, &last_seen).await {
+        error!("Failed to persist last_seen: {:?}", e);
+    }
+    if PRESENCE_BROADCAST {
+        broadcast_presence(&state, user_id, false, Some(last_seen)).await;
+    }
     info!("Listen task terminated");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::DateTime;
+
+    fn bucket(messages_per_second: u32, message_burst: u32) -> MessageBucket {
+        MessageBucket::new(&WsConfig {
+            messages_per_second,
+            message_burst,
+            ..WsConfig::default()
+        })
+    }
+
+    #[test]
+    fn test_message_bucket_allows_burst_then_throttles() {
+        let mut bucket = bucket(10, 3);
+        let start = bucket.refreshed_at;
+
+        for _ in 0..3 {
+            assert!(bucket.try_acquire_at(start).is_ok());
+        }
+        // oltre la raffica si attende il prossimo token (10 al secondo)
+        let wait = bucket.try_acquire_at(start).unwrap_err();
+        assert!(wait > Duration::from_millis(99));
+        assert!(wait <= Duration::from_millis(100));
+
+        // dopo 200ms sono disponibili due token, non di più
+        let later = start + Duration::from_millis(200);
+        assert!(bucket.try_acquire_at(later).is_ok());
+        assert!(bucket.try_acquire_at(later).is_ok());
+        assert!(bucket.try_acquire_at(later).is_err());
+
+        // la raffica non supera la capacità anche dopo una lunga inattività
+        let much_later = later + Duration::from_secs(60);
+        for _ in 0..3 {
+            assert!(bucket.try_acquire_at(much_later).is_ok());
+        }
+        assert!(bucket.try_acquire_at(much_later).is_err());
+    }
+
+    fn message(chat_id: i32, message_id: i32) -> MessageDTO {
+        serde_json::from_value(serde_json::json!({
+            "message_id": message_id,
+            "chat_id": chat_id,
+            "sender_id": 1,
+            "content": "hi",
+            "message_type": "UserMessage",
+            "created_at": null
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_delivery_cursor_skips_replayed_messages() {
+        let mut cursor = DeliveryCursor::new(&HashMap::from([(1, 10)]));
+        assert_eq!(cursor.last_seen(1), Some(10));
+        assert_eq!(cursor.last_seen(2), None);
+
+        assert!(cursor.record_live(&message(1, 11)));
+        assert!(cursor.record_live(&message(2, 5)));
+        assert_eq!(cursor.last_seen(1), Some(11));
+        assert_eq!(cursor.last_seen(2), Some(5));
+
+        // dopo un Lagged i messaggi recuperati dal database avanzano il cursore...
+        cursor.record_replay(&MissedMessagesDTO {
+            chat_id: 1,
+            messages: vec![message(1, 12), message(1, 13)],
+            truncated: false,
+        });
+        assert_eq!(cursor.last_seen(1), Some(13));
+
+        // ...e non vengono consegnati di nuovo se arrivano anche dal canale broadcast
+        assert!(!cursor.record_live(&message(1, 13)));
+        assert!(cursor.record_live(&message(1, 14)));
+        assert!(!cursor.record_live(&message(1, 12)));
+        assert_eq!(cursor.last_seen(1), Some(14));
+    }
+
+    #[test]
+    fn test_received_markers_debounced() {
+        // il destinatario è l'utente 2, i messaggi di `message` sono dell'utente 1
+        let mut received = ReceivedMarkers::new(
+            &WsConfig {
+                received_flush_interval_ms: 1000,
+                ..WsConfig::default()
+            },
+            2,
+        );
+        let start = received.flushed_at;
+        let after = |ms: u64| start + Duration::from_millis(ms);
+        let sent_at = |secs: i64| DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap();
+        let sent = |chat_id: i32, message_id: i32, secs: i64| MessageDTO {
+            created_at: Some(sent_at(secs)),
+            ..message(chat_id, message_id)
+        };
+        let own = MessageDTO {
+            sender_id: Some(2),
+            ..sent(1, 4, 4)
+        };
+
+        received.record(&[sent(1, 5, 5), sent(1, 3, 3), own, sent(2, 8, 4)]);
+        // prima dell'intervallo non si scrive nulla
+        assert!(received.take_due(after(500)).is_none());
+
+        let mut due = received.take_due(after(1000)).unwrap();
+        due.sort();
+        let expected = vec![
+            DeliveredBatch {
+                chat_id: 1,
+                received_until: sent_at(5),
+                last_message_id: 5,
+                unread: 2,
+            },
+            DeliveredBatch {
+                chat_id: 2,
+                received_until: sent_at(4),
+                last_message_id: 8,
+                unread: 1,
+            },
+        ];
+        assert_eq!(due, expected);
+
+        // l'intervallo riparte dall'ultima scrittura
+        received.record(&[sent(1, 6, 6)]);
+        assert!(received.take_due(after(1500)).is_none());
+        let batch = DeliveredBatch {
+            chat_id: 1,
+            received_until: sent_at(6),
+            last_message_id: 6,
+            unread: 1,
+        };
+        assert_eq!(received.take_all(), vec![batch]);
+        assert!(received.take_due(after(10_000)).is_none());
+    }
+
+    #[test]
+    fn test_received_markers_disabled() {
+        let mut received = ReceivedMarkers::new(
+            &WsConfig {
+                received_flush_interval_ms: 0,
+                ..WsConfig::default()
+            },
+            2,
+        );
+        received.record(&[MessageDTO {
+            created_at: Some(Utc::now()),
+            ..message(1, 1)
+        }]);
+        let later = Instant::now() + Duration::from_secs(60);
+        assert!(received.take_due(later).is_none());
+        assert!(received.take_all().is_empty());
+    }
+
+    #[test]
+    fn test_message_bucket_disabled() {
+        let mut bucket = bucket(0, 1);
+        let now = Instant::now();
+        for _ in 0..1000 {
+            assert!(bucket.try_acquire_at(now).is_ok());
+        }
+    }
+}
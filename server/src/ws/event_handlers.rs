@@ -1,46 +1,157 @@
 //! WebSocket Event Handlers - Handler per eventi WebSocket
 
-use tracing::{error, info, instrument, warn};
+use chrono::{DateTime, TimeDelta, Utc};
+use tracing::{debug, error, info, instrument, warn};
 use validator::Validate;
 
 use crate::AppState;
-use crate::dtos::{CreateMessageDTO, MessageDTO};
-use crate::entities::MessageType;
-use crate::repositories::{Create, Read};
+use crate::core::flood::FloodStrike;
+use crate::core::{DomainEvent, FilterVerdict, has_permission};
+use crate::dtos::{
+    CreateMessageDTO, CreateReportDTO, FloodAlertDTO, MessageDTO, MissedMessagesDTO, PresenceDTO,
+    SendResultDTO, UserStatusDTO,
+};
+use crate::entities::{
+    ChatPermission, ContentFilterPolicy, Message, MessageType, PresenceVisibility, ReportReason,
+    UserChatMetadata, UserRole,
+};
+use crate::repositories::{Create, MemberFilter, MessageFilter, Page, Read};
+use crate::ws::REPLAY_MAX_MESSAGES;
+use crate::ws::chatmap::ChatEvent;
+use crate::ws::offline_queue::enqueue_for_offline_members;
 use crate::ws::usermap::InternalSignal;
+use std::collections::HashMap;
 use std::sync::Arc;
+use tokio::sync::broadcast::Receiver;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::{Duration, Instant, timeout_at};
+
+/// Motivo per cui un messaggio non è stato consegnato
+#[derive(Debug, PartialEq)]
+pub enum MessageRejection {
+    /// Messaggio malformato o con riferimenti (risposta, allegato) non validi
+    Invalid(&'static str),
+    /// Il mittente non è membro della chat
+    NotMember,
+    /// Il mittente è membro ma al momento non può scrivere nella chat
+    Restricted {
+        chat_id: i32,
+        code: &'static str,
+        message: &'static str,
+    },
+    /// Errore del database
+    Internal(&'static str),
+}
+
+impl MessageRejection {
+    /// Codice leggibile dal client, riportato nel SendResult
+    pub fn code(&self) -> &'static str {
+        match self {
+            MessageRejection::Invalid(_) => "INVALID",
+            MessageRejection::NotMember => "NOT_MEMBER",
+            MessageRejection::Restricted { code, .. } => code,
+            MessageRejection::Internal(_) => "INTERNAL",
+        }
+    }
+
+    /// Descrizione dell'errore per il mittente
+    pub fn message(&self) -> &'static str {
+        match self {
+            MessageRejection::Invalid(message) | MessageRejection::Internal(message) => message,
+            MessageRejection::NotMember => "You don't belong to that group.",
+            MessageRejection::Restricted { message, .. } => message,
+        }
+    }
+
+    /// Segnale da inviare al mittente connesso via WebSocket
+    pub fn into_signal(self) -> InternalSignal {
+        match self {
+            MessageRejection::Restricted {
+                chat_id,
+                code,
+                message,
+            } => InternalSignal::ChatError {
+                chat_id,
+                code,
+                message,
+            },
+            rejection => InternalSignal::Error(rejection.message()),
+        }
+    }
+}
 
 #[instrument(skip(state, msg), fields(user_id, chat_id = msg.chat_id))]
 pub async fn process_message(state: &Arc<AppState>, user_id: i32, msg: MessageDTO) {
     info!("Processing message from user");
 
-    let input_message = match CreateMessageDTO::try_from(msg.clone()) {
+    let client_msg_id = msg.client_msg_id.clone();
+
+    let result = deliver_message(state, user_id, msg).await;
+
+    // euristiche anti-flood sui messaggi consegnati: un'infrazione silenzia il mittente
+    // e blocca i messaggi successivi
+    if let Ok(saved) = &result
+        && let Some(strike) =
+            state
+                .flood_guard
+                .record_message(user_id, saved.chat_id, &saved.content)
+    {
+        apply_flood_strike(state, saved.chat_id, user_id, strike).await;
+    }
+
+    let signal = match (result, client_msg_id) {
+        // se il client ha fornito il proprio id, l'esito (positivo o negativo) arriva nel SendResult
+        (Ok(saved), Some(client_msg_id)) => InternalSignal::SendResult(SendResultDTO {
+            client_msg_id,
+            message_id: Some(saved.message_id),
+            created_at: Some(saved.created_at),
+            code: None,
+            message: None,
+        }),
+        (Err(rejection), Some(client_msg_id)) => InternalSignal::SendResult(SendResultDTO {
+            client_msg_id,
+            message_id: None,
+            created_at: None,
+            code: Some(rejection.code().to_string()),
+            message: Some(rejection.message().to_string()),
+        }),
+        (Ok(_), None) => return,
+        (Err(rejection), None) => rejection.into_signal(),
+    };
+    state
+        .users_online
+        .send_server_message_if_online(&user_id, signal);
+}
+
+/// Valida il messaggio di `user_id`, lo inoltra ai membri online della chat e lo salva.
+/// Usato sia dai messaggi ricevuti via WebSocket sia dai webhook in ingresso
+///
+/// # Returns
+/// * `Ok(Message)` - Messaggio salvato con l'id assegnato dal server
+/// * `Err(MessageRejection)` - Motivo per cui il messaggio non è stato consegnato
+pub async fn deliver_message(
+    state: &Arc<AppState>,
+    user_id: i32,
+    msg: MessageDTO,
+) -> Result<Message, MessageRejection> {
+    let mut input_message = match CreateMessageDTO::try_from(msg) {
         Ok(msg) => msg,
         Err(e) => {
             warn!("Malformed message received: {:?}", e);
-            state.users_online.send_server_message_if_online(
-                &user_id,
-                InternalSignal::Error("Malformed message."),
-            );
-            return;
+            return Err(MessageRejection::Invalid("Malformed message."));
         }
     };
 
     if let Err(e) = input_message.validate() {
         warn!("Message validation failed: {:?}", e);
-        state
-            .users_online
-            .send_server_message_if_online(&user_id, InternalSignal::Error("Malformed message."));
-        return;
+        return Err(MessageRejection::Invalid("Malformed message."));
     };
 
     if input_message.message_type == MessageType::SystemMessage {
         warn!("User attempted to send system message");
-        state.users_online.send_server_message_if_online(
-            &user_id,
-            InternalSignal::Error("You cannot send system type messages."),
-        );
-        return;
+        return Err(MessageRejection::Invalid(
+            "You cannot send system type messages.",
+        ));
     }
 
     // Verifica che il sender_id corrisponda all'utente autenticato
@@ -50,46 +161,205 @@ pub async fn process_message(state: &Arc<AppState>, user_id: i32, msg: MessageDT
             actual_sender_id = input_message.sender_id,
             "User attempted to spoof sender_id"
         );
-        state.users_online.send_server_message_if_online(
-            &user_id,
-            InternalSignal::Error("Malformed message."),
-        );
-        return;
+        return Err(MessageRejection::Invalid("Malformed message."));
+    }
+
+    // messaggio ritrasmesso dal client (es. dopo una riconnessione): è già stato inoltrato e
+    // salvato, quindi ritorno quello esistente senza consegnarlo di nuovo
+    if let Some(client_msg_id) = &input_message.client_msg_id {
+        match state
+            .msg
+            .find_by_client_msg_id(&user_id, client_msg_id)
+            .await
+        {
+            Ok(Some(saved)) => {
+                info!(
+                    message_id = saved.message_id,
+                    "Retransmitted message already stored, not delivered again"
+                );
+                return Ok(saved);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                error!("Failed to look up message by client id: {:?}", e);
+                return Err(MessageRejection::Internal("Internal server error."));
+            }
+        }
     }
 
     // se la chat non esistesse, allora non esisterebbe neanche il metadata, quindi non controllo l'esistenza della chat.
-    match state.meta.read(&(user_id, input_message.chat_id)).await {
+    let metadata = match state.meta.read(&(user_id, input_message.chat_id)).await {
         Ok(Some(val)) => val,
         Ok(None) => {
             warn!(
                 chat_id = input_message.chat_id,
                 "User does not belong to chat"
             );
-            state.users_online.send_server_message_if_online(
-                &user_id,
-                InternalSignal::Error("You don't belong to that group."),
-            );
-            return;
+            return Err(MessageRejection::NotMember);
         }
         Err(e) => {
             error!("Failed to read user metadata: {:?}", e);
-            state.users_online.send_server_message_if_online(
-                &user_id,
-                InternalSignal::Error("Internal server error."),
-            );
-            return;
+            return Err(MessageRejection::Internal("Internal server error."));
+        }
+    };
+
+    // i membri silenziati non possono scrivere fino alla scadenza del silenziamento
+    if metadata.muted_until.is_some_and(|until| until > Utc::now()) {
+        warn!(
+            chat_id = input_message.chat_id,
+            "Muted member attempted to post"
+        );
+        return Err(MessageRejection::Restricted {
+            chat_id: input_message.chat_id,
+            code: "MUTED",
+            message: "You are muted in this chat.",
+        });
+    }
+
+    let chat = match state.chat.read(&input_message.chat_id).await {
+        Ok(Some(chat)) => chat,
+        Ok(None) => {
+            // chat eliminata dopo la lettura del metadata
+            warn!(chat_id = input_message.chat_id, "Chat no longer exists");
+            return Err(MessageRejection::NotMember);
+        }
+        Err(e) => {
+            error!("Failed to read chat: {:?}", e);
+            return Err(MessageRejection::Internal("Internal server error."));
         }
     };
 
-    // bene, l'utente appartiene alla chat, quindi può inviare il messaggio
-    // invio prima ad utenti online (sia per chat private che di gruppo)
+    // nelle chat di soli annunci possono scrivere solo Admin, Owner e i membri
+    // con un ruolo personalizzato che concede PostAnnouncements
+    if chat.announcement_only
+        && !matches!(
+            metadata.user_role,
+            Some(UserRole::Admin) | Some(UserRole::Owner)
+        )
+    {
+        match has_permission(state, &metadata, ChatPermission::PostAnnouncements).await {
+            Ok(true) => {}
+            Ok(false) => {
+                warn!(
+                    chat_id = input_message.chat_id,
+                    "Member attempted to post in announcement-only chat"
+                );
+                return Err(MessageRejection::Restricted {
+                    chat_id: input_message.chat_id,
+                    code: "ANNOUNCEMENT_ONLY",
+                    message: "Only admins can post in this chat.",
+                });
+            }
+            Err(e) => {
+                error!("Failed to check announcement permissions: {:?}", e);
+                return Err(MessageRejection::Internal("Internal server error."));
+            }
+        }
+    }
+
+    // se il messaggio è una risposta, il messaggio citato deve esistere nella stessa chat
+    if let Some(reply_to_id) = input_message.reply_to_message_id {
+        match state.msg.read(&reply_to_id).await {
+            Ok(Some(quoted)) if quoted.chat_id == input_message.chat_id => {}
+            Ok(_) => {
+                warn!(
+                    chat_id = input_message.chat_id,
+                    reply_to_message_id = reply_to_id,
+                    "Quoted message does not belong to chat"
+                );
+                return Err(MessageRejection::Invalid("Invalid reply reference."));
+            }
+            Err(e) => {
+                error!("Failed to read quoted message: {:?}", e);
+                return Err(MessageRejection::Internal("Internal server error."));
+            }
+        }
+    }
+
+    // se il messaggio porta un allegato, deve essere stato caricato nella stessa chat
+    if let Some(attachment_id) = input_message.attachment_id {
+        match state.attachment.read(&attachment_id).await {
+            Ok(Some(attachment)) if attachment.chat_id == input_message.chat_id => {}
+            Ok(_) => {
+                warn!(
+                    chat_id = input_message.chat_id,
+                    attachment_id, "Attachment does not belong to chat"
+                );
+                return Err(MessageRejection::Invalid("Invalid attachment reference."));
+            }
+            Err(e) => {
+                error!("Failed to read attachment: {:?}", e);
+                return Err(MessageRejection::Internal("Internal server error."));
+            }
+        }
+    }
+
+    // filtro dei contenuti: la policy della chat decide se rifiutare, mascherare o segnalare
+    let mut flagged = false;
+    if chat.content_filter != ContentFilterPolicy::Off && state.content_filter.is_enabled() {
+        if let FilterVerdict::Matched { redacted } =
+            state.content_filter.check(&input_message.content).await
+        {
+            match (chat.content_filter, redacted) {
+                (ContentFilterPolicy::Flag, _) => flagged = true,
+                (ContentFilterPolicy::Redact, Some(redacted)) => {
+                    info!(chat_id = input_message.chat_id, "Message content redacted");
+                    input_message.content = redacted;
+                }
+                // segnalato dal classificatore: non c'è una parte da mascherare, si rifiuta
+                _ => {
+                    warn!(
+                        chat_id = input_message.chat_id,
+                        "Message rejected by content filter"
+                    );
+                    return Err(MessageRejection::Restricted {
+                        chat_id: input_message.chat_id,
+                        code: "CONTENT_REJECTED",
+                        message: "Your message contains content that is not allowed in this chat.",
+                    });
+                }
+            }
+        }
+    }
+
+    // salvo prima in db: i membri online ricevono il messaggio con l'id e la data assegnati
+    // dal server, gli stessi usati dai replay e dai marcatori di ricezione
+    let saved = match state.msg.create(&input_message).await {
+        Ok(saved) => saved,
+        Err(e)
+            if input_message.client_msg_id.is_some()
+                && e.as_database_error()
+                    .is_some_and(|e| e.is_unique_violation()) =>
+        {
+            // ritrasmissione concorrente: l'altra copia è stata salvata e inoltrata per prima
+            let client_msg_id = input_message.client_msg_id.as_deref().unwrap_or_default();
+            return match state
+                .msg
+                .find_by_client_msg_id(&user_id, client_msg_id)
+                .await
+            {
+                Ok(Some(saved)) => Ok(saved),
+                _ => Err(MessageRejection::Internal("Internal server error.")),
+            };
+        }
+        Err(e) => {
+            error!("Failed to persist message to database: {:?}", e);
+            return Err(MessageRejection::Internal(
+                "Something went wrong and your message was not stored correctly!",
+            ));
+        }
+    };
+    info!(message_id = saved.message_id, "Message stored successfully");
+    state.server_stats.record_message();
+
+    // poi invio ad utenti online (sia per chat private che di gruppo)
     match state
         .chats_online
-        .send(&input_message.chat_id, Arc::from(msg))
+        .send(&saved.chat_id, Arc::new(MessageDTO::from(saved.clone())))
     {
         Ok(n) => {
             info!(
-                chat_id = input_message.chat_id,
+                chat_id = saved.chat_id,
                 receivers = n,
                 "Message broadcast to {} receivers", n
             );
@@ -98,22 +368,441 @@ pub async fn process_message(state: &Arc<AppState>, user_id: i32, msg: MessageDT
             // Nessun ricevitore online per questa chat (canale non esiste o nessuno iscritto)
             // Questo è normale per chat nuove o quando tutti gli utenti sono offline
             warn!(
-                chat_id = input_message.chat_id,
-                "No online receivers for this chat, message will be stored for later delivery"
+                chat_id = saved.chat_id,
+                "No online receivers for this chat, message stored for later delivery"
             );
         }
     }
 
-    // salvo in db per utenti offline
-    if let Err(e) = state.msg.create(&input_message).await {
-        error!("Failed to persist message to database: {:?}", e);
+    if flagged {
+        flag_message(state, &saved).await;
+    }
+    enqueue_for_offline_members(state, &saved).await;
+    state.publish_event(DomainEvent::MessageCreated {
+        message_id: saved.message_id,
+        chat_id: saved.chat_id,
+        sender_id: saved.sender_id,
+        message_type: saved.message_type.clone(),
+        created_at: saved.created_at,
+    });
+    Ok(saved)
+}
+
+/// Crea la segnalazione automatica di un messaggio consegnato nonostante il filtro dei
+/// contenuti. Un errore non annulla l'invio, che è già avvenuto
+async fn flag_message(state: &AppState, message: &Message) {
+    let report = CreateReportDTO {
+        reporter_id: None,
+        reported_user_id: message.sender_id,
+        chat_id: Some(message.chat_id),
+        message_id: Some(message.message_id),
+        reason: ReportReason::InappropriateContent,
+        details: Some("Flagged by content filter".to_string()),
+    };
+    match state.report.create(&report).await {
+        Ok(report) => info!(
+            message_id = message.message_id,
+            report_id = report.report_id,
+            "Message flagged by content filter"
+        ),
+        Err(e) => error!("Failed to flag message: {:?}", e),
+    }
+}
+
+/// Silenzia `user_id` nella chat per un'infrazione rilevata da `FloodGuard`, poi avvisa il
+/// membro e chi può moderare la chat. Chi modera la chat non viene silenziato. Gli errori
+/// sono solo registrati, l'operazione che ha fatto scattare il rilevamento è già avvenuta
+pub async fn apply_flood_strike(state: &AppState, chat_id: i32, user_id: i32, strike: FloodStrike) {
+    let members = match state
+        .meta
+        .read_many(&MemberFilter::in_chat(chat_id).from_primary(), &Page::ALL)
+        .await
+    {
+        Ok(members) => members,
+        Err(e) => {
+            error!("Failed to load chat members: {:?}", e);
+            return;
+        }
+    };
+    let mut moderators = Vec::new();
+    for member in &members {
+        match has_permission(state, member, ChatPermission::ModerateMembers).await {
+            Ok(true) => moderators.push(member.user_id),
+            Ok(false) => {}
+            Err(e) => error!("Failed to check moderation permissions: {:?}", e),
+        }
+    }
+    if !members.iter().any(|member| member.user_id == user_id) || moderators.contains(&user_id) {
+        debug!(chat_id, user_id, "Flood strike ignored");
+        return;
+    }
+
+    let muted_until = Utc::now() + TimeDelta::seconds(strike.mute.as_secs() as i64);
+    if let Err(e) = state
+        .meta
+        .set_muted_until(&user_id, &chat_id, Some(muted_until))
+        .await
+    {
+        error!("Failed to mute flooding member: {:?}", e);
+        return;
+    }
+    warn!(
+        chat_id,
+        user_id,
+        reason = ?strike.reason,
+        mute_secs = strike.mute.as_secs(),
+        "Member automatically muted for flooding"
+    );
+
+    state.users_online.send_server_message_if_online(
+        &user_id,
+        InternalSignal::ChatError {
+            chat_id,
+            code: "FLOOD_MUTED",
+            message: "You have been temporarily muted for flooding this chat.",
+        },
+    );
+    let alert = FloodAlertDTO {
+        chat_id,
+        user_id,
+        reason: strike.reason,
+        muted_until,
+    };
+    for moderator_id in moderators {
         state.users_online.send_server_message_if_online(
-            &user_id,
-            InternalSignal::Error(
-                "Something went wrong and your message was not stored correctly!",
-            ),
+            &moderator_id,
+            InternalSignal::FloodAlert(alert.clone()),
         );
-    } else {
-        info!("Message processed and stored successfully");
+    }
+}
+
+/// Carica i messaggi persi da un client che si riconnette, per le chat di cui ha indicato
+/// l'ultimo messaggio ricevuto. Per ogni chat vengono reinviati al più `REPLAY_MAX_MESSAGES`
+/// messaggi, i più recenti; le chat senza messaggi nuovi sono omesse
+#[instrument(skip(state, chats, since))]
+pub async fn load_missed_messages(
+    state: &Arc<AppState>,
+    chats: &[UserChatMetadata],
+    since: &HashMap<i32, i32>,
+) -> Result<Vec<MissedMessagesDTO>, sqlx::Error> {
+    let mut missed = Vec::new();
+
+    // le chat indicate dal client ma di cui non è membro vengono ignorate
+    for metadata in chats {
+        let Some(after_message_id) = since.get(&metadata.chat_id) else {
+            continue;
+        };
+        if let Some(replay) = load_messages_after(
+            state,
+            metadata.chat_id,
+            &metadata.messages_visible_from,
+            after_message_id,
+        )
+        .await?
+        {
+            missed.push(replay);
+        }
+    }
+
+    Ok(missed)
+}
+
+/// Carica i messaggi persi da una connessione il cui canale broadcast della chat è rimasto
+/// indietro (`Lagged`). Se la connessione non ha ancora ricevuto messaggi della chat, sono
+/// quelli creati dopo `connected_at`. None se non ci sono messaggi o l'utente non è più membro
+#[instrument(skip(state))]
+pub async fn load_lagged_messages(
+    state: &Arc<AppState>,
+    user_id: i32,
+    chat_id: i32,
+    last_seen: Option<i32>,
+    connected_at: DateTime<Utc>,
+) -> Result<Option<MissedMessagesDTO>, sqlx::Error> {
+    let Some(metadata) = state.meta.read(&(user_id, chat_id)).await? else {
+        return Ok(None);
+    };
+    let visible_from = match last_seen {
+        Some(_) => metadata.messages_visible_from,
+        None => metadata.messages_visible_from.max(connected_at),
+    };
+    load_messages_after(state, chat_id, &visible_from, &last_seen.unwrap_or(0)).await
+}
+
+/// Long polling: attende messaggi della chat successivi a `after_message_id`, per i client
+/// che non riescono ad aprire un WebSocket
+///
+/// Il canale broadcast della chat viene sottoscritto prima di leggere il database, così un
+/// messaggio salvato tra la lettura e l'attesa non va perso. I messaggi sono sempre letti
+/// dal database, il canale serve solo a sapere quando rileggerlo.
+///
+/// # Returns
+/// Messaggi nuovi, oppure una lista vuota se non ne arrivano entro `wait`
+#[instrument(skip(state, metadata), fields(chat_id = metadata.chat_id, user_id = metadata.user_id))]
+pub async fn wait_for_messages(
+    state: &Arc<AppState>,
+    metadata: &UserChatMetadata,
+    after_message_id: i32,
+    wait: Duration,
+) -> Result<MissedMessagesDTO, sqlx::Error> {
+    let chat_id = metadata.chat_id;
+    let visible_from = &metadata.messages_visible_from;
+    let mut rx = state.chats_online.subscribe(&chat_id);
+    let deadline = Instant::now() + wait;
+
+    let result = poll_chat_channel(
+        state,
+        chat_id,
+        visible_from,
+        after_message_id,
+        deadline,
+        &mut rx,
+    )
+    .await;
+
+    // il long polling non deve lasciare nella mappa un canale rimasto senza iscritti
+    drop(rx);
+    state.chats_online.evict_if_unsubscribed(&chat_id);
+    result
+}
+
+/// Rilegge il database finché non trova messaggi successivi a `after_message_id`,
+/// attendendo sul canale `rx` tra una lettura e l'altra fino a `deadline`
+async fn poll_chat_channel(
+    state: &Arc<AppState>,
+    chat_id: i32,
+    visible_from: &DateTime<Utc>,
+    after_message_id: i32,
+    deadline: Instant,
+    rx: &mut Receiver<ChatEvent>,
+) -> Result<MissedMessagesDTO, sqlx::Error> {
+    loop {
+        if let Some(found) =
+            load_messages_after(state, chat_id, visible_from, &after_message_id).await?
+        {
+            return Ok(found);
+        }
+
+        // nessun messaggio nuovo: attende il prossimo sul canale broadcast
+        loop {
+            match timeout_at(deadline, rx.recv()).await {
+                Ok(Ok(ChatEvent::Message(msg))) if msg.message_id > Some(after_message_id) => {
+                    break;
+                }
+                Ok(Ok(_)) => continue,
+                // messaggi persi dal canale: sono comunque sul database
+                Ok(Err(RecvError::Lagged(_))) => break,
+                // canale rimosso (chat eliminata) o attesa scaduta
+                Ok(Err(RecvError::Closed)) | Err(_) => {
+                    debug!("No new messages before timeout");
+                    return Ok(MissedMessagesDTO {
+                        chat_id,
+                        messages: Vec::new(),
+                        truncated: false,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Messaggi della chat successivi a `after_message_id`, al più `REPLAY_MAX_MESSAGES` (i più
+/// recenti) dal più vecchio al più recente
+async fn load_messages_after(
+    state: &Arc<AppState>,
+    chat_id: i32,
+    visible_from: &DateTime<Utc>,
+    after_message_id: &i32,
+) -> Result<Option<MissedMessagesDTO>, sqlx::Error> {
+    // uno in più del limite, per sapere se ne restano altri da recuperare via HTTP
+    let filter = MessageFilter::After {
+        chat_id,
+        visible_from: *visible_from,
+        after_message_id: *after_message_id,
+    };
+    let mut messages = state
+        .msg
+        .read_many(&filter, &Page::first(REPLAY_MAX_MESSAGES + 1))
+        .await?;
+    if messages.is_empty() {
+        return Ok(None);
+    }
+    let truncated = messages.len() as i64 > REPLAY_MAX_MESSAGES;
+    messages.truncate(REPLAY_MAX_MESSAGES as usize);
+    messages.reverse();
+
+    debug!(
+        chat_id,
+        count = messages.len(),
+        truncated,
+        "Missed messages loaded"
+    );
+    Ok(Some(MissedMessagesDTO {
+        chat_id,
+        messages: messages.into_iter().map(MessageDTO::from).collect(),
+        truncated,
+    }))
+}
+
+/// Invia un evento PresenceChanged su tutte le chat dell'utente.
+/// Gli iscritti ai canali sono proprio i contatti online, quindi non serve altro fan-out:
+/// l'evento viene omesso solo se l'utente ha scelto di non mostrare la presenza a nessuno.
+#[instrument(skip(state), fields(user_id))]
+pub async fn broadcast_presence(
+    state: &Arc<AppState>,
+    user_id: i32,
+    online: bool,
+    last_seen: Option<DateTime<Utc>>,
+) {
+    match state.user.read(&user_id).await {
+        Ok(Some(user)) if user.presence_visibility == PresenceVisibility::Nobody => {
+            debug!("Presence hidden by user privacy settings, broadcast skipped");
+            return;
+        }
+        Ok(_) => {}
+        Err(e) => {
+            error!("Failed to load user for presence broadcast: {:?}", e);
+            return;
+        }
+    }
+
+    let chats = match state
+        .meta
+        .read_many(&MemberFilter::of_user(user_id).from_primary(), &Page::ALL)
+        .await
+    {
+        Ok(chats) => chats,
+        Err(e) => {
+            error!("Failed to load user chats for presence broadcast: {:?}", e);
+            return;
+        }
+    };
+
+    let presence = PresenceDTO {
+        user_id,
+        online,
+        last_seen,
+    };
+
+    for meta in chats {
+        // errore = nessun iscritto online per la chat, non c'è nessuno da avvisare
+        let _ = state
+            .chats_online
+            .send_event(&meta.chat_id, ChatEvent::PresenceChanged(presence.clone()));
+    }
+    debug!(online, "Presence broadcast completed");
+}
+
+/// Invia un evento StatusChanged su tutte le chat dell'utente, come per la presenza.
+#[instrument(skip(state, status), fields(user_id = status.user_id))]
+pub async fn broadcast_status(state: &Arc<AppState>, status: UserStatusDTO) {
+    let chats = match state
+        .meta
+        .read_many(
+            &MemberFilter::of_user(status.user_id).from_primary(),
+            &Page::ALL,
+        )
+        .await
+    {
+        Ok(chats) => chats,
+        Err(e) => {
+            error!("Failed to load user chats for status broadcast: {:?}", e);
+            return;
+        }
+    };
+
+    for meta in chats {
+        // errore = nessun iscritto online per la chat, non c'è nessuno da avvisare
+        let _ = state
+            .chats_online
+            .send_event(&meta.chat_id, ChatEvent::StatusChanged(status.clone()));
+    }
+    debug!("Status broadcast completed");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::User;
+    use crate::repositories::{InMemoryUserChatMetadataRepo, InMemoryUserRepo};
+    use sqlx::MySqlPool;
+    use tokio::sync::broadcast::error::TryRecvError;
+
+    fn user(user_id: i32, presence_visibility: PresenceVisibility) -> User {
+        User {
+            user_id,
+            username: format!("user{}", user_id),
+            password: String::new(),
+            display_name: None,
+            bio: None,
+            avatar_url: None,
+            status_text: None,
+            status_expires_at: None,
+            last_seen: None,
+            presence_visibility,
+            deactivated_at: None,
+        }
+    }
+
+    fn member(user_id: i32, chat_id: i32) -> UserChatMetadata {
+        let now = Utc::now();
+        UserChatMetadata {
+            user_id,
+            chat_id,
+            user_role: Some(UserRole::Member),
+            role_id: None,
+            member_since: now,
+            messages_visible_from: now,
+            messages_received_until: now,
+            pinned_at: None,
+            muted_until: None,
+            version: 0,
+        }
+    }
+
+    /// Stato con repository in memoria: il pool non apre mai una connessione
+    fn state(users: Vec<User>, members: Vec<UserChatMetadata>) -> Arc<AppState> {
+        let pool = MySqlPool::connect_lazy("mysql://localhost/ironlink").unwrap();
+        Arc::new(
+            AppState::new(pool, "secret".to_string())
+                .with_user_repo(Arc::new(InMemoryUserRepo::new(users)))
+                .with_metadata_repo(Arc::new(InMemoryUserChatMetadataRepo::new(members))),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_presence_reaches_user_chats() {
+        let state = state(
+            vec![user(1, PresenceVisibility::Everyone)],
+            vec![member(1, 10), member(1, 20), member(2, 30)],
+        );
+        let mut first = state.chats_online.subscribe(&10);
+        let mut second = state.chats_online.subscribe(&20);
+        let mut other = state.chats_online.subscribe(&30);
+
+        broadcast_presence(&state, 1, true, None).await;
+
+        for rx in [&mut first, &mut second] {
+            match rx.try_recv() {
+                Ok(ChatEvent::PresenceChanged(presence)) => {
+                    assert_eq!(presence.user_id, 1);
+                    assert!(presence.online);
+                }
+                other => panic!("expected PresenceChanged, got {:?}", other),
+            }
+        }
+        assert!(matches!(other.try_recv(), Err(TryRecvError::Empty)));
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_presence_hidden_by_privacy() {
+        let state = state(
+            vec![user(1, PresenceVisibility::Nobody)],
+            vec![member(1, 10)],
+        );
+        let mut rx = state.chats_online.subscribe(&10);
+
+        broadcast_presence(&state, 1, false, Some(Utc::now())).await;
+
+        assert!(matches!(rx.try_recv(), Err(TryRecvError::Empty)));
     }
 }
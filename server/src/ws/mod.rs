@@ -6,59 +6,94 @@
 //! - Gestione connessioni (split sender/receiver)
 //! - Handler per eventi WebSocket (messaggi, inviti)
 //! - Utility per broadcasting e invio errori
+//! - Presenza online condivisa tra più istanze del server
+//! - Coda di consegna dei messaggi per gli utenti offline
 
 pub mod chatmap;
 pub mod connection;
 pub mod event_handlers;
+pub mod offline_queue;
+pub mod presence;
+pub mod signal_queue;
 pub mod usermap;
 
 // Re-exports pubblici
-pub use connection::handle_socket;
+pub use connection::{WsEncoding, handle_socket};
+pub use offline_queue::{enqueue_for_offline_members, start_offline_queue_purger};
+pub use presence::{SharedPresence, start_presence_refresher};
 
-use crate::{AppState, entities::User};
+use crate::{
+    AppState,
+    core::{AppError, ClientIp, auth::Claims},
+    dtos::WsConnectQuery,
+    entities::User,
+    ws::usermap::ConnectionInfo,
+};
 use axum::{
     Extension,
-    extract::{State, ws::WebSocketUpgrade},
+    extract::{Query, State, ws::WebSocketUpgrade},
+    http::{HeaderMap, header::USER_AGENT},
     response::Response,
 };
 use std::sync::Arc;
 use tracing::{info, instrument};
+use validator::Validate;
 
 // how many messages should the channel contain?
 const BROADCAST_CHANNEL_CAPACITY: usize = 100;
 
-/// Intervallo massimo tra invii batch (ms)
-const BATCH_INTERVAL: u64 = 1000;
-
-/// Numero massimo di messaggi per batch
-const BATCH_MAX_SIZE: usize = 10;
+/// Se true, connessioni e disconnessioni vengono notificate ai membri delle chat dell'utente
+const PRESENCE_BROADCAST: bool = true;
 
-/// Delay minimo tra messaggi client (ms) - max 100 msg/sec
-const RATE_LIMITER_MILLIS: u64 = 10;
+/// Numero massimo di messaggi persi reinviati per chat alla riconnessione
+const REPLAY_MAX_MESSAGES: i64 = 500;
 
-/// Timeout inattività prima di chiudere connessione (secondi)
-const TIMEOUT_DURATION_SECONDS: u64 = 300;
+/// Attesa di default del long polling dei messaggi, se il client non la indica
+pub const POLL_DEFAULT_TIMEOUT_SECS: u64 = 30;
 
 /// Entry point per gestire richieste di upgrade WebSocket
 /// Operazioni:
 /// 1. Estrarre user_id dall'autenticazione JWT
-/// 2. Eseguire upgrade HTTP -> WebSocket
-/// 3. Passare la connessione ad handle_socket
-#[instrument(skip(ws, state, current_user), fields(user_id = current_user.user_id))]
+/// 2. Validare i cursori di ripresa (`since`), se il client si sta riconnettendo
+/// 3. Raccogliere i dati della connessione (dispositivo, IP, user agent)
+/// 4. Negoziare la codifica dei frame (JSON o MessagePack) dal sottoprotocollo richiesto
+/// 5. Eseguire upgrade HTTP -> WebSocket
+/// 6. Passare la connessione ad handle_socket
+#[instrument(skip(ws, state, current_user, claims, query, headers), fields(user_id = current_user.user_id))]
 pub async fn ws_handler(
     ws: WebSocketUpgrade,
     State(state): State<Arc<AppState>>,
     Extension(current_user): Extension<User>, // ottenuto dall'autenticazione JWT
-) -> Response {
+    Extension(claims): Extension<Claims>,     // il jti lega la connessione al token
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
+    Query(query): Query<WsConnectQuery>,
+) -> Result<Response, AppError> {
     let user_id = current_user.user_id;
     info!("WebSocket upgrade requested");
 
+    query.validate()?;
+    let since = query.resume_cursors();
+
+    let user_agent = headers
+        .get(USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let connection =
+        ConnectionInfo::new(query.device, Some(ip), user_agent).with_token_jti(claims.jti);
+
+    // il sottoprotocollo scelto viene riportato nella risposta all'upgrade
+    let ws = ws.protocols(WsEncoding::PROTOCOLS);
+    let encoding = WsEncoding::from_protocol(ws.selected_protocol());
+
     // Gestisce automaticamente l'upgrade a WebSocket.
     // Se l'upgrade fallisce, ritorna un errore; altrimenti restituisce la nuova connessione al client.
 
-    ws
+    Ok(ws
         // Possibile limitazione dei buffer, default 128 KB
         //.read_buffer_size(4*1024)
         //.write_buffer_size(16*1024)
-        .on_upgrade(move |socket| handle_socket(socket, state, user_id))
+        .on_upgrade(move |socket| {
+            handle_socket(socket, state, user_id, since, encoding, connection)
+        }))
 }
@@ -0,0 +1,154 @@
+//! Offline Queue - Consegna affidabile dei messaggi agli utenti offline
+//!
+//! Ogni messaggio salvato viene accodato per i membri della chat che non hanno una
+//! connessione aperta su questa istanza. Alla connessione successiva `write_ws` invia la
+//! coda in ordine, prima del traffico in tempo reale, e segna i messaggi come consegnati:
+//! il client non deve ricaricare la cronologia per sapere cosa si è perso.
+
+use crate::AppState;
+use crate::dtos::{MessageDTO, MissedMessagesDTO};
+use crate::entities::Message;
+use crate::repositories::{MemberFilter, Page};
+use chrono::Utc;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{error, info, instrument, warn};
+
+/// Messaggi in coda letti dal database per ogni pagina inviata al client
+pub const OFFLINE_QUEUE_PAGE_SIZE: i64 = 200;
+
+/// Per quanto tempo restano le righe già consegnate
+pub const OFFLINE_QUEUE_DELIVERED_RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Età oltre la quale un messaggio mai consegnato esce dalla coda: il client lo ritrova
+/// comunque nella cronologia
+pub const OFFLINE_QUEUE_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Intervallo tra due pulizie della coda
+pub const OFFLINE_QUEUE_PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Accoda il messaggio per i membri della chat senza una connessione aperta, escluso il
+/// mittente. Va chiamata dopo l'invio sul canale broadcast: chi si connette dopo il
+/// controllo riceve il messaggio dalla coda. Un errore viene solo registrato
+#[instrument(skip(state, message), fields(chat_id = message.chat_id, message_id = message.message_id))]
+pub async fn enqueue_for_offline_members(state: &AppState, message: &Message) {
+    let members = match state
+        .meta
+        .read_many(
+            &MemberFilter::in_chat(message.chat_id).from_primary(),
+            &Page::ALL,
+        )
+        .await
+    {
+        Ok(members) => members,
+        Err(e) => {
+            error!("Failed to load members for offline queue: {:?}", e);
+            return;
+        }
+    };
+    // le altre istanze non ricevono il traffico in tempo reale di questa: conta solo
+    // la connessione locale
+    let offline: Vec<i32> = members
+        .iter()
+        .map(|member| member.user_id)
+        .filter(|user_id| {
+            *user_id != message.sender_id && !state.users_online.is_user_online(user_id)
+        })
+        .collect();
+
+    if let Err(e) = state
+        .offline_queue
+        .enqueue(&message.message_id, &offline)
+        .await
+    {
+        warn!("Failed to queue message for offline members: {:?}", e);
+    }
+}
+
+/// Divide una pagina della coda in replay per chat, mantenendo l'ordine complessivo:
+/// messaggi consecutivi della stessa chat finiscono nello stesso replay
+pub fn group_by_chat(messages: Vec<Message>) -> Vec<MissedMessagesDTO> {
+    let mut replays: Vec<MissedMessagesDTO> = Vec::new();
+    for message in messages {
+        match replays.last_mut() {
+            Some(replay) if replay.chat_id == message.chat_id => {
+                replay.messages.push(MessageDTO::from(message));
+            }
+            _ => replays.push(MissedMessagesDTO {
+                chat_id: message.chat_id,
+                messages: vec![MessageDTO::from(message)],
+                truncated: false,
+            }),
+        }
+    }
+    replays
+}
+
+/// Avvia il task che elimina periodicamente le righe consegnate da più di
+/// `OFFLINE_QUEUE_DELIVERED_RETENTION` e quelle in coda da più di `OFFLINE_QUEUE_MAX_AGE`
+pub fn start_offline_queue_purger(state: Arc<AppState>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(OFFLINE_QUEUE_PURGE_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let now = Utc::now();
+            let delivered_before = now - OFFLINE_QUEUE_DELIVERED_RETENTION;
+            let enqueued_before = now - OFFLINE_QUEUE_MAX_AGE;
+            match state
+                .offline_queue
+                .purge(&delivered_before, &enqueued_before)
+                .await
+            {
+                Ok(0) => {}
+                Ok(purged) => info!(purged, "Offline queue purged"),
+                Err(e) => error!("Failed to purge offline queue: {:?}", e),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::{ContentFormat, MessageType};
+
+    fn message(chat_id: i32, message_id: i32) -> Message {
+        Message {
+            message_id,
+            chat_id,
+            sender_id: 1,
+            content: "hi".to_string(),
+            created_at: Utc::now(),
+            message_type: MessageType::UserMessage,
+            content_format: ContentFormat::Plain,
+            reply_to_message_id: None,
+            attachment_id: None,
+            deleted_at: None,
+        }
+    }
+
+    #[test]
+    fn test_group_by_chat_keeps_order() {
+        let replays = group_by_chat(vec![
+            message(1, 10),
+            message(1, 11),
+            message(2, 12),
+            message(1, 13),
+        ]);
+
+        let grouped: Vec<(i32, Vec<Option<i32>>)> = replays
+            .iter()
+            .map(|r| (r.chat_id, r.messages.iter().map(|m| m.message_id).collect()))
+            .collect();
+        assert_eq!(
+            grouped,
+            vec![
+                (1, vec![Some(10), Some(11)]),
+                (2, vec![Some(12)]),
+                (1, vec![Some(13)]),
+            ]
+        );
+        assert!(replays.iter().all(|r| !r.truncated));
+    }
+}
@@ -0,0 +1,99 @@
+//! Presence - Stato online condiviso tra le istanze del server
+//!
+//! `UserMap` conosce solo le connessioni aperte su questa istanza. Con la cache condivisa
+//! ogni istanza pubblica anche una chiave `presence:{user_id}` per gli utenti connessi a
+//! lei, con un TTL breve rinnovato periodicamente: se un nodo si ferma senza chiudere le
+//! connessioni i suoi utenti risultano offline entro `PRESENCE_TTL`.
+
+use crate::AppState;
+use crate::core::SharedCache;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+/// Durata di una chiave di presenza non rinnovata
+pub const PRESENCE_TTL: Duration = Duration::from_secs(60);
+
+/// Intervallo di rinnovo delle chiavi di presenza degli utenti connessi
+pub const PRESENCE_REFRESH_INTERVAL: Duration = Duration::from_secs(20);
+
+/// Presenza online pubblicata sulla cache condivisa
+#[derive(Clone)]
+pub struct SharedPresence {
+    cache: SharedCache,
+}
+
+impl SharedPresence {
+    pub fn new(cache: SharedCache) -> Self {
+        Self { cache }
+    }
+
+    /// Segna l'utente come online su tutte le istanze
+    pub async fn mark_online(&self, user_id: i32) {
+        if let Err(e) = self.cache.set(&Self::key(user_id), "1", PRESENCE_TTL).await {
+            warn!("Failed to publish presence of user {}: {}", user_id, e);
+        }
+    }
+
+    /// Segna l'utente come offline dopo la chiusura della sua ultima connessione.
+    /// Se è ancora connesso a un'altra istanza, quella lo ripubblica al rinnovo successivo
+    pub async fn mark_offline(&self, user_id: i32) {
+        if let Err(e) = self.cache.delete(&Self::key(user_id)).await {
+            warn!("Failed to clear presence of user {}: {}", user_id, e);
+        }
+    }
+
+    /// Verifica se l'utente è connesso a una qualsiasi istanza (false se la cache non risponde)
+    pub async fn is_online(&self, user_id: i32) -> bool {
+        match self.cache.get(&Self::key(user_id)).await {
+            Ok(value) => value.is_some(),
+            Err(e) => {
+                warn!("Failed to read presence of user {}: {}", user_id, e);
+                false
+            }
+        }
+    }
+
+    fn key(user_id: i32) -> String {
+        format!("presence:{}", user_id)
+    }
+}
+
+/// Avvia il task che rinnova ogni `PRESENCE_REFRESH_INTERVAL` la presenza degli utenti
+/// connessi a questa istanza
+pub fn start_presence_refresher(state: Arc<AppState>, presence: SharedPresence) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(PRESENCE_REFRESH_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let connections = state.users_online.connections();
+            for (user_id, _) in &connections {
+                presence.mark_online(*user_id).await;
+            }
+            debug!(users = connections.len(), "Shared presence refreshed");
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::cache::InMemoryCache;
+
+    #[tokio::test]
+    async fn test_presence_visible_through_shared_cache() {
+        let cache: SharedCache = Arc::new(InMemoryCache::new(100));
+        // due istanze che puntano alla stessa cache
+        let node_a = SharedPresence::new(cache.clone());
+        let node_b = SharedPresence::new(cache);
+
+        assert!(!node_b.is_online(1).await);
+        node_a.mark_online(1).await;
+        assert!(node_b.is_online(1).await);
+        assert!(!node_b.is_online(2).await);
+
+        node_a.mark_offline(1).await;
+        assert!(!node_b.is_online(1).await);
+    }
+}
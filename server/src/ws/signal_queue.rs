@@ -0,0 +1,288 @@
+//! Signal Queue - Coda limitata dei segnali interni di una connessione WebSocket
+//!
+//! Sostituisce il canale unbounded tra servizi e task di scrittura: se il client è lento
+//! e il task di scrittura resta indietro, la coda non cresce oltre `capacity`. Al
+//! superamento si applica la `WsOverflowPolicy` configurata: scartare i segnali più vecchi
+//! (il client riceve poi un suggerimento di risincronizzazione) oppure chiudere la connessione.
+
+use crate::core::config::WsOverflowPolicy;
+use crate::ws::usermap::InternalSignal;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+use tokio::sync::mpsc::error::{SendError, TryRecvError};
+use tracing::warn;
+
+/// Stato della coda, per osservare quanto una connessione è in ritardo
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueueStats {
+    /// Segnali in attesa di essere inviati al client
+    pub queued: usize,
+    /// Segnali scartati per overflow dall'apertura della connessione
+    pub dropped: u64,
+}
+
+struct Shared {
+    queue: Mutex<VecDeque<InternalSignal>>,
+    notify: Notify,
+    capacity: usize,
+    policy: WsOverflowPolicy,
+    /// Segnali scartati non ancora segnalati al task di scrittura
+    pending_dropped: AtomicU64,
+    dropped_total: AtomicU64,
+    /// Overflow con policy Disconnect: la coda non accetta più segnali
+    overflowed: AtomicBool,
+    receiver_closed: AtomicBool,
+    senders: AtomicUsize,
+}
+
+fn is_shutdown(signal: &InternalSignal) -> bool {
+    matches!(
+        signal,
+        InternalSignal::Shutdown | InternalSignal::ServerShutdown
+    )
+}
+
+/// Crea una coda di segnali limitata a `capacity` elementi
+pub fn signal_channel(capacity: usize, policy: WsOverflowPolicy) -> (SignalSender, SignalReceiver) {
+    let shared = Arc::new(Shared {
+        queue: Mutex::new(VecDeque::new()),
+        notify: Notify::new(),
+        capacity: capacity.max(1),
+        policy,
+        pending_dropped: AtomicU64::new(0),
+        dropped_total: AtomicU64::new(0),
+        overflowed: AtomicBool::new(false),
+        receiver_closed: AtomicBool::new(false),
+        senders: AtomicUsize::new(1),
+    });
+    (
+        SignalSender {
+            shared: shared.clone(),
+        },
+        SignalReceiver { shared },
+    )
+}
+
+/// Lato di invio della coda, registrato nella `UserMap`
+pub struct SignalSender {
+    shared: Arc<Shared>,
+}
+
+impl SignalSender {
+    /// Accoda un segnale senza mai bloccare il chiamante
+    ///
+    /// # Returns
+    /// Errore con il segnale se la connessione è chiusa o è stata chiusa per overflow
+    pub fn send(&self, signal: InternalSignal) -> Result<(), SendError<InternalSignal>> {
+        let shared = &self.shared;
+        if shared.receiver_closed.load(Ordering::Acquire)
+            || shared.overflowed.load(Ordering::Acquire)
+        {
+            return Err(SendError(signal));
+        }
+
+        let mut queue = shared.queue.lock().unwrap();
+        // i segnali di chiusura devono sempre arrivare, anche a coda piena
+        if queue.len() >= shared.capacity && !is_shutdown(&signal) {
+            shared.dropped_total.fetch_add(1, Ordering::Relaxed);
+            match shared.policy {
+                WsOverflowPolicy::DropOldest => {
+                    let oldest = queue.iter().position(|queued| !is_shutdown(queued));
+                    if let Some(index) = oldest {
+                        queue.remove(index);
+                    }
+                    shared.pending_dropped.fetch_add(1, Ordering::Relaxed);
+                    warn!(
+                        capacity = shared.capacity,
+                        "Signal queue full, dropping oldest signal"
+                    );
+                }
+                WsOverflowPolicy::Disconnect => {
+                    let dropped = queue.len() as u64;
+                    queue.clear();
+                    shared
+                        .pending_dropped
+                        .fetch_add(dropped + 1, Ordering::Relaxed);
+                    shared.overflowed.store(true, Ordering::Release);
+                    warn!(
+                        capacity = shared.capacity,
+                        "Signal queue full, closing connection"
+                    );
+                    drop(queue);
+                    shared.notify.notify_one();
+                    return Err(SendError(signal));
+                }
+            }
+        }
+        queue.push_back(signal);
+        drop(queue);
+        shared.notify.notify_one();
+        Ok(())
+    }
+
+    /// Profondità della coda e segnali scartati
+    pub fn stats(&self) -> QueueStats {
+        QueueStats {
+            queued: self.shared.queue.lock().unwrap().len(),
+            dropped: self.shared.dropped_total.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Clone for SignalSender {
+    fn clone(&self) -> Self {
+        self.shared.senders.fetch_add(1, Ordering::Relaxed);
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Drop for SignalSender {
+    fn drop(&mut self) {
+        if self.shared.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            // ultimo sender: il ricevitore deve accorgersi che la coda è chiusa
+            self.shared.notify.notify_one();
+        }
+    }
+}
+
+/// Lato di ricezione della coda, letto dal task di scrittura
+pub struct SignalReceiver {
+    shared: Arc<Shared>,
+}
+
+impl SignalReceiver {
+    /// Attende il prossimo segnale
+    ///
+    /// # Returns
+    /// * `Some(InternalSignal::QueueOverflow)` se sono stati scartati segnali dall'ultima lettura
+    /// * `None` se tutti i sender sono stati chiusi e la coda è vuota
+    pub async fn recv(&mut self) -> Option<InternalSignal> {
+        loop {
+            match self.try_recv() {
+                Ok(signal) => return Some(signal),
+                Err(TryRecvError::Disconnected) => return None,
+                Err(TryRecvError::Empty) => self.shared.notify.notified().await,
+            }
+        }
+    }
+
+    /// Legge il prossimo segnale senza attendere
+    pub fn try_recv(&mut self) -> Result<InternalSignal, TryRecvError> {
+        let shared = &self.shared;
+        let dropped = shared.pending_dropped.swap(0, Ordering::AcqRel);
+        if dropped > 0 {
+            return Ok(InternalSignal::QueueOverflow { dropped });
+        }
+        if shared.overflowed.load(Ordering::Acquire) {
+            return Err(TryRecvError::Disconnected);
+        }
+        if let Some(signal) = shared.queue.lock().unwrap().pop_front() {
+            return Ok(signal);
+        }
+        if shared.senders.load(Ordering::Acquire) == 0 {
+            Err(TryRecvError::Disconnected)
+        } else {
+            Err(TryRecvError::Empty)
+        }
+    }
+}
+
+impl Drop for SignalReceiver {
+    fn drop(&mut self) {
+        self.shared.receiver_closed.store(true, Ordering::Release);
+        self.shared.queue.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drop_oldest_keeps_newest_signals() {
+        let (tx, mut rx) = signal_channel(2, WsOverflowPolicy::DropOldest);
+
+        tx.send(InternalSignal::RemoveChat(1)).unwrap();
+        tx.send(InternalSignal::RemoveChat(2)).unwrap();
+        tx.send(InternalSignal::RemoveChat(3)).unwrap();
+        assert_eq!(
+            tx.stats(),
+            QueueStats {
+                queued: 2,
+                dropped: 1
+            }
+        );
+
+        // prima il suggerimento di risincronizzazione, poi i segnali più recenti
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(InternalSignal::QueueOverflow { dropped: 1 })
+        ));
+        assert!(matches!(rx.try_recv(), Ok(InternalSignal::RemoveChat(2))));
+        assert!(matches!(rx.try_recv(), Ok(InternalSignal::RemoveChat(3))));
+        assert!(matches!(rx.try_recv(), Err(TryRecvError::Empty)));
+
+        // Shutdown non viene mai scartato
+        tx.send(InternalSignal::Shutdown).unwrap();
+        tx.send(InternalSignal::RemoveChat(4)).unwrap();
+        tx.send(InternalSignal::RemoveChat(5)).unwrap();
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(InternalSignal::QueueOverflow { dropped: 1 })
+        ));
+        assert!(matches!(rx.try_recv(), Ok(InternalSignal::Shutdown)));
+        assert!(matches!(rx.try_recv(), Ok(InternalSignal::RemoveChat(5))));
+
+        // nemmeno quello di arresto del server
+        tx.send(InternalSignal::ServerShutdown).unwrap();
+        tx.send(InternalSignal::RemoveChat(6)).unwrap();
+        tx.send(InternalSignal::RemoveChat(7)).unwrap();
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(InternalSignal::QueueOverflow { dropped: 1 })
+        ));
+        assert!(matches!(rx.try_recv(), Ok(InternalSignal::ServerShutdown)));
+        assert!(matches!(rx.try_recv(), Ok(InternalSignal::RemoveChat(7))));
+    }
+
+    #[test]
+    fn test_disconnect_on_overflow() {
+        let (tx, mut rx) = signal_channel(2, WsOverflowPolicy::Disconnect);
+
+        tx.send(InternalSignal::RemoveChat(1)).unwrap();
+        tx.send(InternalSignal::RemoveChat(2)).unwrap();
+        assert!(tx.send(InternalSignal::RemoveChat(3)).is_err());
+
+        // la coda viene svuotata e non accetta altri segnali
+        assert!(tx.send(InternalSignal::Shutdown).is_err());
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(InternalSignal::QueueOverflow { dropped: 3 })
+        ));
+        assert!(matches!(rx.try_recv(), Err(TryRecvError::Disconnected)));
+    }
+
+    #[tokio::test]
+    async fn test_recv_ends_when_senders_dropped() {
+        let (tx, mut rx) = signal_channel(2, WsOverflowPolicy::DropOldest);
+        let other_tx = tx.clone();
+
+        tx.send(InternalSignal::RemoveChat(1)).unwrap();
+        drop(tx);
+        drop(other_tx);
+        assert!(matches!(
+            rx.recv().await,
+            Some(InternalSignal::RemoveChat(1))
+        ));
+        assert!(rx.recv().await.is_none());
+
+        // senza ricevitore i segnali vengono rifiutati
+        let (tx, rx) = signal_channel(2, WsOverflowPolicy::DropOldest);
+        drop(rx);
+        assert!(tx.send(InternalSignal::Shutdown).is_err());
+    }
+}
@@ -1,63 +1,336 @@
-use dashmap::DashMap;
-use tokio::sync::mpsc::UnboundedSender;
+use chrono::{DateTime, Utc};
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
 use tracing::{info, instrument, warn};
 
-use crate::dtos::EnrichedInvitationDTO;
+use crate::dtos::{ChatDTO, EnrichedInvitationDTO, FloodAlertDTO, JoinRequestDTO, SendResultDTO};
+use crate::ws::signal_queue::{QueueStats, SignalSender};
 
 pub enum InternalSignal {
     Shutdown,
-    AddChat(i32),
+    /// Il server si sta arrestando: la connessione va chiusa con il codice "server restarting"
+    ServerShutdown,
+    /// Nuova chat di cui l'utente è membro fin dalla creazione, il client deve aggiungerla
+    ChatCreated(ChatDTO),
+    /// L'utente è entrato in una chat esistente (invito accettato o richiesta approvata)
+    ChatJoined(ChatDTO),
     RemoveChat(i32),
+    /// La chat è stata eliminata dall'Owner, il client deve chiuderla
+    ChatDeleted(i32),
     Error(&'static str),
+    /// Errore strutturato relativo ad una chat, con un codice leggibile dal client
+    ChatError {
+        chat_id: i32,
+        code: &'static str,
+        message: &'static str,
+    },
     Invitation(EnrichedInvitationDTO),
+    /// Nuova richiesta di accesso, inviata a chi può approvarla
+    JoinRequest(JoinRequestDTO),
+    /// Esito di una richiesta di accesso, inviato all'utente che l'ha fatta
+    JoinRequestResolved(JoinRequestDTO),
+    /// Membro silenziato automaticamente per flood o spam, inviato a chi modera la chat
+    FloodAlert(FloodAlertDTO),
+    /// Esito di un messaggio inviato con `client_msg_id`: id assegnato dal server o errore
+    SendResult(SendResultDTO),
+    /// Generato dalla coda dei segnali, non inviato dai servizi: `dropped` segnali sono
+    /// stati scartati perché il client non li leggeva abbastanza in fretta
+    QueueOverflow {
+        dropped: u64,
+    },
 }
 
+/// Dati della connessione WebSocket di un utente, raccolti durante l'upgrade
+#[derive(Debug, Clone)]
+pub struct ConnectionInfo {
+    /// Identificativo della connessione, usato per chiuderla da remoto
+    pub connection_id: String,
+    /// Nome del dispositivo indicato dal client (`?device=`)
+    pub device_name: Option<String>,
+    pub ip: Option<IpAddr>,
+    pub user_agent: Option<String>,
+    pub connected_at: DateTime<Utc>,
+    /// `jti` dell'access token con cui è stata aperta, per chiuderla al logout di quel token
+    pub token_jti: Option<String>,
+}
+
+impl ConnectionInfo {
+    pub fn new(
+        device_name: Option<String>,
+        ip: Option<IpAddr>,
+        user_agent: Option<String>,
+    ) -> Self {
+        Self {
+            connection_id: uuid::Uuid::new_v4().to_string(),
+            device_name,
+            ip,
+            user_agent,
+            connected_at: Utc::now(),
+            token_jti: None,
+        }
+    }
+
+    /// Associa la connessione all'access token usato per aprirla
+    pub fn with_token_jti(mut self, jti: String) -> Self {
+        self.token_jti = Some(jti);
+        self
+    }
+}
+
+/// Connessione attiva di un utente
+struct OnlineEntry {
+    /// Epoca della registrazione: distingue la connessione attuale da quelle che ha sostituito
+    epoch: u64,
+    tx: SignalSender,
+    connection: ConnectionInfo,
+}
+
+/// Utenti online, consultata ad ogni messaggio e segnale inviato.
+///
+/// Le letture (ricerca della coda di un utente, broadcast) non prendono lock: la mappa è una
+/// hash table lock-free in cui le voci rimosse o sostituite vengono liberate solo quando
+/// nessun thread le sta più leggendo (reclamation a epoche). Per questo il `SignalSender`
+/// di una connessione rimossa può sopravvivere per poco alla rimozione: la chiusura della
+/// connessione passa sempre dal segnale di Shutdown, non dal drop del sender.
 pub struct UserMap {
-    users_online: DashMap<i32, UnboundedSender<InternalSignal>>,
+    users_online: papaya::HashMap<i32, OnlineEntry>,
+    /// Istante dell'ultima disconnessione, per rispondere senza andare sul db
+    disconnected_at: papaya::HashMap<i32, DateTime<Utc>>,
+    /// Ultima epoca assegnata a una registrazione
+    epoch: AtomicU64,
 }
 
 impl UserMap {
     pub fn new() -> Self {
         UserMap {
-            users_online: DashMap::new(),
+            users_online: papaya::HashMap::new(),
+            disconnected_at: papaya::HashMap::new(),
+            epoch: AtomicU64::new(0),
         }
     }
 
-    #[instrument(skip(self, tx), fields(user_id))]
-    pub fn register_online(&self, user_id: i32, tx: UnboundedSender<InternalSignal>) {
+    /// Registra l'utente come online, senza dati sul dispositivo
+    pub fn register_online(&self, user_id: i32, tx: SignalSender) {
+        self.register_connection(user_id, tx, ConnectionInfo::new(None, None, None));
+    }
+
+    /// Registra l'utente come online con i dati della sua connessione,
+    /// sostituendo l'eventuale connessione precedente
+    ///
+    /// # Returns
+    /// Epoca della registrazione, da passare a `remove_connection` alla chiusura
+    #[instrument(skip(self, tx, connection), fields(user_id, connection_id = %connection.connection_id))]
+    pub fn register_connection(
+        &self,
+        user_id: i32,
+        tx: SignalSender,
+        connection: ConnectionInfo,
+    ) -> u64 {
         info!("Registering user {} as online", user_id);
-        self.users_online.insert(user_id, tx);
-        info!("Total online users: {}", self.users_online.len());
+        let epoch = self.epoch.fetch_add(1, Ordering::Relaxed) + 1;
+        let users_online = self.users_online.pin();
+        users_online.insert(
+            user_id,
+            OnlineEntry {
+                epoch,
+                tx,
+                connection,
+            },
+        );
+        self.disconnected_at.pin().remove(&user_id);
+        info!("Total online users: {}", users_online.len());
+        epoch
     }
 
+    /// Rimuove l'utente dagli online e ritorna l'istante di disconnessione (il suo last_seen),
+    /// qualunque sia la sua connessione attiva
+    #[allow(dead_code)]
     #[instrument(skip(self), fields(user_id))]
-    pub fn remove_from_online(&self, user_id: &i32) {
+    pub fn remove_from_online(&self, user_id: &i32) -> DateTime<Utc> {
+        info!("Removing user from online");
+        let now = Utc::now();
+        self.users_online.pin().remove(user_id);
+        self.disconnected_at.pin().insert(*user_id, now);
+        now
+    }
+
+    /// Rimuove la connessione registrata con `epoch`, se è ancora quella attiva dell'utente.
+    /// Una connessione che si chiude dopo essere stata sostituita da una nuova (es. riconnessione
+    /// rapida) non deve far risultare offline l'utente
+    ///
+    /// # Returns
+    /// * `Some(DateTime)` - Istante di disconnessione (il last_seen dell'utente)
+    /// * `None` se l'utente ha nel frattempo una connessione più recente
+    #[instrument(skip(self), fields(user_id, epoch))]
+    pub fn remove_connection(&self, user_id: &i32, epoch: u64) -> Option<DateTime<Utc>> {
+        let users_online = self.users_online.pin();
+        let removed = users_online.remove_if(user_id, |_, entry| entry.epoch == epoch);
+        if !matches!(removed, Ok(Some(_))) {
+            info!("Connection already replaced, user stays online");
+            return None;
+        }
         info!("Removing user from online");
-        self.users_online.remove(&user_id);
+        let now = Utc::now();
+        self.disconnected_at.pin().insert(*user_id, now);
+        Some(now)
+    }
+
+    /// Istante di connessione se l'utente è online
+    #[allow(dead_code)]
+    pub fn connected_since(&self, user_id: &i32) -> Option<DateTime<Utc>> {
+        self.users_online
+            .pin()
+            .get(user_id)
+            .map(|entry| entry.connection.connected_at)
+    }
+
+    /// Dati della connessione se l'utente è online
+    pub fn connection(&self, user_id: &i32) -> Option<ConnectionInfo> {
+        self.users_online
+            .pin()
+            .get(user_id)
+            .map(|entry| entry.connection.clone())
+    }
+
+    /// Profondità della coda dei segnali e segnali scartati, se l'utente è online
+    pub fn queue_stats(&self, user_id: &i32) -> Option<QueueStats> {
+        self.users_online
+            .pin()
+            .get(user_id)
+            .map(|entry| entry.tx.stats())
+    }
+
+    /// Connessioni di tutti gli utenti online su questa istanza
+    pub fn connections(&self) -> Vec<(i32, ConnectionInfo)> {
+        self.users_online
+            .pin()
+            .iter()
+            .map(|(user_id, entry)| (*user_id, entry.connection.clone()))
+            .collect()
+    }
+
+    /// Chiude la connessione indicata, se è ancora quella attiva dell'utente
+    ///
+    /// # Returns
+    /// true se la connessione esisteva ed è stato inviato il segnale di chiusura
+    #[instrument(skip(self), fields(user_id))]
+    pub fn disconnect(&self, user_id: &i32, connection_id: &str) -> bool {
+        self.disconnect_if(user_id, |connection| {
+            connection.connection_id == connection_id
+        })
+    }
+
+    /// Chiude la connessione aperta con l'access token `jti`, se è ancora quella attiva
+    /// dell'utente. Le connessioni aperte con altri token (altre sessioni) restano aperte
+    ///
+    /// # Returns
+    /// true se la connessione esisteva ed è stato inviato il segnale di chiusura
+    #[instrument(skip(self, jti), fields(user_id))]
+    pub fn disconnect_token(&self, user_id: &i32, jti: &str) -> bool {
+        self.disconnect_if(user_id, |connection| {
+            connection.token_jti.as_deref() == Some(jti)
+        })
+    }
+
+    fn disconnect_if(&self, user_id: &i32, matches: impl Fn(&ConnectionInfo) -> bool) -> bool {
+        let users_online = self.users_online.pin();
+        let Some(entry) = users_online
+            .get(user_id)
+            .filter(|entry| matches(&entry.connection))
+        else {
+            return false;
+        };
+        info!(
+            "Disconnecting connection {}",
+            entry.connection.connection_id
+        );
+        // il segnale va alla connessione verificata, anche se nel frattempo è stata sostituita
+        if entry.tx.send(InternalSignal::Shutdown).is_err() {
+            warn!("Failed to send Shutdown: connection already closed");
+        }
+        true
+    }
+
+    /// Chiude tutte le connessioni, all'arresto del server
+    ///
+    /// # Returns
+    /// Numero di connessioni a cui è stato inviato il segnale di chiusura
+    pub fn shutdown_all(&self) -> usize {
+        let closed = self
+            .users_online
+            .pin()
+            .values()
+            .filter(|entry| entry.tx.send(InternalSignal::ServerShutdown).is_ok())
+            .count();
+        info!("Server shutdown signal sent to {} connections", closed);
+        closed
+    }
+
+    /// Ultima disconnessione registrata da questo processo (None se mai disconnesso o online)
+    pub fn last_seen(&self, user_id: &i32) -> Option<DateTime<Utc>> {
+        self.disconnected_at.pin().get(user_id).copied()
     }
 
     #[instrument(skip(self, message), fields(user_id))]
     pub fn send_server_message_if_online(&self, user_id: &i32, message: InternalSignal) {
         let message_type = match &message {
             InternalSignal::Shutdown => "Shutdown",
-            InternalSignal::AddChat(chat_id) => {
-                info!("Sending AddChat signal for chat_id {}", chat_id);
-                "AddChat"
+            InternalSignal::ServerShutdown => "ServerShutdown",
+            InternalSignal::ChatCreated(chat) => {
+                info!("Sending ChatCreated signal for chat_id {:?}", chat.chat_id);
+                "ChatCreated"
+            }
+            InternalSignal::ChatJoined(chat) => {
+                info!("Sending ChatJoined signal for chat_id {:?}", chat.chat_id);
+                "ChatJoined"
             }
             InternalSignal::RemoveChat(chat_id) => {
                 info!("Sending RemoveChat signal for chat_id {}", chat_id);
                 "RemoveChat"
             }
+            InternalSignal::ChatDeleted(chat_id) => {
+                info!("Sending ChatDeleted signal for chat_id {}", chat_id);
+                "ChatDeleted"
+            }
             InternalSignal::Error(_) => "Error",
+            InternalSignal::ChatError { code, .. } => {
+                info!("Sending ChatError signal with code {}", code);
+                "ChatError"
+            }
             InternalSignal::Invitation(inv) => {
                 info!("Sending Invitation signal for invite_id {}", inv.invite_id);
                 "Invitation"
             }
+            InternalSignal::JoinRequest(request) => {
+                info!(
+                    "Sending JoinRequest signal for request_id {}",
+                    request.request_id
+                );
+                "JoinRequest"
+            }
+            InternalSignal::JoinRequestResolved(request) => {
+                info!(
+                    "Sending JoinRequestResolved signal for request_id {}",
+                    request.request_id
+                );
+                "JoinRequestResolved"
+            }
+            InternalSignal::FloodAlert(alert) => {
+                info!("Sending FloodAlert signal for chat_id {}", alert.chat_id);
+                "FloodAlert"
+            }
+            InternalSignal::SendResult(result) => {
+                info!(
+                    "Sending SendResult signal for client_msg_id {}",
+                    result.client_msg_id
+                );
+                "SendResult"
+            }
+            InternalSignal::QueueOverflow { .. } => "QueueOverflow",
         };
 
-        if let Some(entry) = self.users_online.get(&user_id) {
-            let tx = entry.value();
-            if let Err(e) = tx.send(message) {
+        if let Some(entry) = self.users_online.pin().get(user_id) {
+            if let Err(e) = entry.tx.send(message) {
                 warn!("Failed to send {} message to user: {:?}", message_type, e);
             } else {
                 info!("{} message sent to online user", message_type);
@@ -68,15 +341,13 @@ impl UserMap {
     }
 
     /// Get the count of online users
-    #[allow(dead_code)]
     pub fn online_count(&self) -> usize {
-        self.users_online.len()
+        self.users_online.pin().len()
     }
 
     /// Check if a specific user is online
-    #[allow(dead_code)]
     pub fn is_user_online(&self, user_id: &i32) -> bool {
-        self.users_online.contains_key(user_id)
+        self.users_online.pin().contains_key(user_id)
     }
 }
 
@@ -0,0 +1,639 @@
+//! Integration tests per le route di amministrazione del server
+
+mod common;
+
+#[cfg(test)]
+mod admin_tests {
+    use super::common::*;
+    use axum_test::http::{HeaderName, StatusCode};
+    use serde_json::json;
+    use server::core::{AppState, ServerRole, encode_jwt, start_stats_sampler};
+    use server::dtos::RuntimeConfigDTO;
+    use sqlx::MySqlPool;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+
+    /// Token di charlie con il ruolo di amministratore del server
+    fn create_admin_jwt(state: &AppState) -> String {
+        encode_jwt(
+            &"charlie".to_string(),
+            3,
+            ServerRole::ServerAdmin,
+            &state.jwt_keys,
+        )
+        .expect("Encoding JWT must succeed")
+    }
+
+    // ============================================================
+    // Test per il controllo del ruolo ServerAdmin
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_admin_routes_forbidden_without_role(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .get("/admin/stats")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_forbidden();
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_admin_routes_require_authentication(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state);
+
+        let response = server.get("/admin/stats").await;
+
+        response.assert_status_forbidden();
+
+        Ok(())
+    }
+
+    /// Login diretto, per gli utenti già registrati
+    async fn login(server: &axum_test::TestServer, username: &str, password: &str) -> String {
+        let response = server
+            .post("/auth/login")
+            .json(&json!({ "username": username, "password": password }))
+            .await;
+        response.assert_status_ok();
+        response
+            .headers()
+            .get("authorization")
+            .and_then(|header| header.to_str().ok())
+            .and_then(|header| header.strip_prefix("Bearer "))
+            .expect("Authorization header should carry a bearer token")
+            .to_string()
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_login_assigns_server_admin_role(pool: MySqlPool) -> sqlx::Result<()> {
+        let jwt_secret = "ilmiobellissimosegretochevaassolutamentecambiato";
+        let registration = create_test_server(create_test_state(&pool));
+        get_auth_token(&registration, "moderator", "Moderator123")
+            .await
+            .expect("Registration and login must succeed");
+        let moderator = create_test_state(&pool)
+            .user
+            .find_by_username("moderator")
+            .await?
+            .unwrap();
+
+        let state = Arc::new(
+            AppState::new(pool.clone(), jwt_secret.to_string())
+                .with_server_admins(vec![moderator.user_id]),
+        );
+        let server = create_test_server(state);
+
+        // il ruolo viene assegnato al login in base alla configurazione
+        let token = login(&server, "moderator", "Moderator123").await;
+
+        server
+            .get("/admin/stats")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await
+            .assert_status_ok();
+
+        // eliminato l'account, chi registra di nuovo lo stesso username non eredita il ruolo
+        server
+            .delete("/users/me")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await
+            .assert_status_ok();
+        let token = get_auth_token(&server, "moderator", "Impostor123")
+            .await
+            .expect("Registration and login must succeed");
+
+        server
+            .get("/admin/stats")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await
+            .assert_status_forbidden();
+
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per GET /admin/users e GET /admin/stats
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_admin_list_users(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_admin_jwt(&state);
+
+        // anche gli account disattivati sono visibili all'amministratore
+        sqlx::query!("UPDATE users SET deactivated_at = NOW() WHERE user_id = 2")
+            .execute(&pool)
+            .await?;
+        let (tx, _rx) = create_signal_channel();
+        state.users_online.register_online(1, tx);
+
+        let response = server
+            .get("/admin/users")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let users: Vec<serde_json::Value> = response.json();
+        assert_eq!(users.len(), 3);
+        assert_eq!(users[0]["username"], "alice");
+        assert_eq!(users[0]["online"], true);
+        assert_eq!(users[1]["username"], "bob");
+        assert!(users[1]["deactivated_at"].is_string());
+        assert_eq!(users[2]["online"], false);
+
+        let response = server
+            .get("/admin/users?search=li&limit=10")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let users: Vec<serde_json::Value> = response.json();
+        let usernames: Vec<&str> = users
+            .iter()
+            .map(|user| user["username"].as_str().unwrap())
+            .collect();
+        assert_eq!(usernames, vec!["alice", "charlie"]);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_admin_list_users_invalid_limit(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_admin_jwt(&state);
+
+        let response = server
+            .get("/admin/users?limit=0")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_bad_request();
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_admin_online_stats(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_admin_jwt(&state);
+
+        let (alice_tx, _alice_rx) = create_signal_channel();
+        let (bob_tx, _bob_rx) = create_signal_channel();
+        state.users_online.register_online(1, alice_tx);
+        state.users_online.register_online(2, bob_tx);
+
+        let response = server
+            .get("/admin/stats")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let stats: serde_json::Value = response.json();
+        assert_eq!(stats["online_users"], 2);
+
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per GET /admin/stats/stream
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_admin_stats_stream_forbidden_without_role(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .get("/admin/stats/stream")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_forbidden();
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_admin_stats_stream(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let token = create_admin_jwt(&state);
+
+        let (alice_tx, _alice_rx) = create_signal_channel();
+        state.users_online.register_online(1, alice_tx);
+        state.server_stats.record_message();
+
+        // lo stream SSE non termina: serve un server reale da cui leggere i primi eventi
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = server::create_router(state.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let sampler = start_stats_sampler(state.clone(), Duration::from_millis(50));
+
+        let mut response = reqwest::Client::new()
+            .get(format!("http://{}/admin/stats/stream", addr))
+            .bearer_auth(&token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+
+        let mut body = String::new();
+        let event = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let chunk = response.chunk().await.unwrap().expect("stream ended");
+                body.push_str(&String::from_utf8_lossy(&chunk));
+                if let Some(start) = body.find("event: stats\ndata: ") {
+                    let data = &body[start + "event: stats\ndata: ".len()..];
+                    if let Some(end) = data.find('\n') {
+                        return data[..end].to_string();
+                    }
+                }
+            }
+        })
+        .await
+        .expect("No stats event received");
+        sampler.abort();
+
+        let stats: serde_json::Value = serde_json::from_str(&event).unwrap();
+        assert_eq!(stats["online_users"], 1);
+        assert!(stats["messages_per_minute"].is_number());
+        assert!(stats["db_pool"]["max_connections"].as_u64().unwrap() > 0);
+        assert!(
+            stats["db_pool"]["in_use"].as_u64().unwrap()
+                <= stats["db_pool"]["size"].as_u64().unwrap()
+        );
+        assert!(stats["db_pool"]["min_connections"].is_u64());
+        assert!(stats["db_pool"]["saturation"].is_number());
+
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per GET /admin/config e POST /admin/config/reload
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_runtime_config_applied_without_restart(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_admin_jwt(&state);
+
+        let response = server
+            .get("/admin/config")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+        response.assert_status_ok();
+        let config: serde_json::Value = response.json();
+        assert_eq!(config["legacy_api_paths"], true);
+        assert_eq!(config["rate_limit_auth_per_minute"], 20);
+
+        // percorsi legacy disattivati e un solo tentativo di login al minuto
+        let mut reloadable = state.reloadable_config();
+        reloadable.legacy_api_paths = false;
+        reloadable.rate_limit.auth_per_minute = 1;
+        state.apply_config(&reloadable).unwrap();
+
+        server
+            .get("/admin/config")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+
+        let response = server
+            .get("/api/v1/admin/config")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+        response.assert_status_ok();
+        let config: serde_json::Value = response.json();
+        assert_eq!(config["legacy_api_paths"], false);
+        assert_eq!(config["rate_limit_auth_per_minute"], 1);
+
+        let login = json!({ "username": "alice", "password": "wrongpassword" });
+        server
+            .post("/api/v1/auth/login")
+            .json(&login)
+            .await
+            .assert_status_unauthorized();
+        server
+            .post("/api/v1/auth/login")
+            .json(&login)
+            .await
+            .assert_status(StatusCode::TOO_MANY_REQUESTS);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_reload_config(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+
+        // solo gli amministratori del server possono ricaricare la configurazione
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+        server
+            .post("/admin/config/reload")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await
+            .assert_status_forbidden();
+
+        let token = create_admin_jwt(&state);
+        let response = server
+            .post("/admin/config/reload")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+        response.assert_status_ok();
+        let config: serde_json::Value = response.json();
+        assert_eq!(
+            config,
+            serde_json::to_value(RuntimeConfigDTO::from(state.reloadable_config())).unwrap()
+        );
+
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per GET /admin/chats/{chat_id}
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_admin_get_chat(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_admin_jwt(&state);
+
+        let (tx, _rx) = create_signal_channel();
+        state.users_online.register_online(2, tx);
+
+        // l'amministratore può ispezionare anche le chat di cui non è membro
+        let response = server
+            .get("/admin/chats/2")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let chat: serde_json::Value = response.json();
+        assert_eq!(chat["chat"]["chat_id"], 2);
+        assert_eq!(chat["members"].as_array().unwrap().len(), 2);
+        assert_eq!(chat["message_count"], 2);
+        assert_eq!(chat["online_members"], 1);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_admin_get_chat_not_found(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_admin_jwt(&state);
+
+        let response = server
+            .get("/admin/chats/999")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_not_found();
+
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per GET /admin/chats/{chat_id}/export e
+    // GET /admin/users/{user_id}/messages/export
+    // ============================================================
+
+    fn ndjson_records(body: &str) -> Vec<serde_json::Value> {
+        body.lines()
+            .map(|line| serde_json::from_str(line).expect("each line is a JSON object"))
+            .collect()
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_admin_export_chat(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_admin_jwt(&state);
+
+        // charlie non è membro della chat privata e i messaggi precedono l'ingresso dei
+        // membri: l'export dell'amministratore li include comunque
+        let response = server
+            .get("/admin/chats/2/export")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        assert_eq!(response.header("content-type"), "application/x-ndjson");
+
+        let records = ndjson_records(&response.text());
+        assert_eq!(records.len(), 5);
+        assert_eq!(records[0]["Chat"]["chat_id"], 2);
+        assert!(records[1..3].iter().all(|r| r.get("Member").is_some()));
+        let ids: Vec<i64> = records[3..]
+            .iter()
+            .map(|r| r["Message"]["message_id"].as_i64().unwrap())
+            .collect();
+        assert_eq!(ids, vec![4, 5]);
+
+        let response = server
+            .get("/admin/chats/999/export")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_not_found();
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_admin_export_user_messages(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_admin_jwt(&state);
+
+        let response = server
+            .get("/admin/users/1/messages/export")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+
+        // i messaggi di alice in tutte le chat, dal più vecchio
+        let records = ndjson_records(&response.text());
+        let ids: Vec<i64> = records
+            .iter()
+            .map(|r| r["Message"]["message_id"].as_i64().unwrap())
+            .collect();
+        assert_eq!(ids, vec![1, 4, 6]);
+
+        let response = server
+            .get("/admin/users/999/messages/export")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_not_found();
+
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per DELETE /admin/messages/{message_id} e DELETE /admin/chats/{chat_id}
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_admin_delete_message(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_admin_jwt(&state);
+
+        let response = server
+            .delete("/admin/messages/4")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let tombstone: serde_json::Value = response.json();
+        assert_eq!(tombstone["message_id"], 4);
+        assert!(tombstone["deleted_at"].is_string());
+
+        // l'eliminazione resta nell'audit log della chat, con l'amministratore come autore
+        let entry =
+            sqlx::query!("SELECT actor_id, target_user_id FROM audit_log WHERE chat_id = 2")
+                .fetch_one(&pool)
+                .await?;
+        assert_eq!(entry.actor_id, Some(3));
+        assert_eq!(entry.target_user_id, Some(1));
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_admin_delete_message_not_found(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_admin_jwt(&state);
+
+        let response = server
+            .delete("/admin/messages/999")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_not_found();
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_admin_delete_chat(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_admin_jwt(&state);
+
+        let (bob_tx, mut bob_rx) = create_signal_channel();
+        state.users_online.register_online(2, bob_tx);
+
+        let response = server
+            .delete("/admin/chats/2")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        assert!(matches!(
+            bob_rx.try_recv(),
+            Ok(server::ws::usermap::InternalSignal::ChatDeleted(2))
+        ));
+
+        let response = server
+            .delete("/admin/chats/2")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status(StatusCode::NOT_FOUND);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,472 @@
+//! Integration tests per il caricamento degli allegati
+
+mod common;
+
+#[cfg(test)]
+mod attachment_tests {
+    use super::common::*;
+    use axum_test::http::{HeaderName, StatusCode};
+    use axum_test::multipart::{MultipartForm, Part};
+    use chrono::Utc;
+    use object_store::{ObjectStore, memory::InMemory, path::Path as StoragePath};
+    use server::core::AppState;
+    use server::dtos::CreateMessageDTO;
+    use server::entities::{ContentFormat, MessageType};
+    use server::services::attachment::collect_orphaned_attachments;
+    use sqlx::MySqlPool;
+    use std::sync::Arc;
+
+    fn text_file(content: &'static str, file_name: &str) -> MultipartForm {
+        MultipartForm::new().add_part(
+            "file",
+            Part::bytes(content.as_bytes())
+                .file_name(file_name)
+                .mime_type("text/plain"),
+        )
+    }
+
+    // ============================================================
+    // Test per POST /chats/{chat_id}/attachments - upload_attachment
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_upload_attachment_success(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .post("/chats/1/attachments")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .multipart(text_file("hello attachment", "notes.txt"))
+            .await;
+
+        response.assert_status_ok();
+        let attachment: serde_json::Value = response.json();
+        assert_eq!(attachment["chat_id"], 1);
+        assert_eq!(attachment["uploader_id"], 1);
+        assert_eq!(attachment["file_name"], "notes.txt");
+        assert_eq!(attachment["content_type"], "text/plain");
+        assert_eq!(attachment["size_bytes"], 16);
+        // la chiave di storage resta interna al server
+        assert!(attachment.get("storage_key").is_none());
+
+        // il file deve essere presente sullo storage alla chiave registrata nel db
+        let attachment_id = attachment["attachment_id"].as_i64().unwrap() as i32;
+        let storage_key = sqlx::query_scalar!(
+            "SELECT storage_key FROM attachments WHERE attachment_id = ?",
+            attachment_id
+        )
+        .fetch_one(&pool)
+        .await?;
+        let meta = state
+            .storage
+            .head(&StoragePath::from(storage_key.as_str()))
+            .await
+            .expect("Attachment should be stored");
+        assert_eq!(meta.size, 16);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_upload_attachment_strips_client_path(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .post("/chats/1/attachments")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .multipart(text_file("data", "../../etc/passwd"))
+            .await;
+
+        response.assert_status_ok();
+        let attachment: serde_json::Value = response.json();
+        assert_eq!(attachment["file_name"], "passwd");
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_upload_attachment_not_member(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        // Bob non è membro della chat 3 (Dev Team)
+        let response = server
+            .post("/chats/3/attachments")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .multipart(text_file("data", "notes.txt"))
+            .await;
+
+        response.assert_status_forbidden();
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_upload_attachment_missing_file_field(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .post("/chats/1/attachments")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .multipart(MultipartForm::new().add_text("description", "no file here"))
+            .await;
+
+        response.assert_status_bad_request();
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_upload_attachment_too_large(pool: MySqlPool) -> sqlx::Result<()> {
+        let jwt_secret = "ilmiobellissimosegretochevaassolutamentecambiato";
+        let state = Arc::new(
+            AppState::new(pool.clone(), jwt_secret.to_string())
+                .with_storage(Arc::new(InMemory::new()), 8),
+        );
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .post("/chats/1/attachments")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .multipart(text_file("more than eight bytes", "big.txt"))
+            .await;
+
+        response.assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+
+        let count = sqlx::query_scalar!("SELECT COUNT(*) FROM attachments")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(count, 0);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_upload_attachment_not_bound_by_json_limit(pool: MySqlPool) -> sqlx::Result<()> {
+        let jwt_secret = "ilmiobellissimosegretochevaassolutamentecambiato";
+        let state = Arc::new(
+            AppState::new(pool.clone(), jwt_secret.to_string()).with_max_json_body_bytes(1024),
+        );
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        // gli upload hanno il proprio limite (max_attachment_bytes), non quello JSON
+        let form = MultipartForm::new().add_part(
+            "file",
+            Part::bytes(vec![b'a'; 4096])
+                .file_name("big.txt")
+                .mime_type("text/plain"),
+        );
+        let response = server
+            .post("/chats/1/attachments")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .multipart(form)
+            .await;
+
+        response.assert_status_ok();
+        assert_eq!(response.json::<serde_json::Value>()["size_bytes"], 4096);
+
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per GET /chats/{chat_id}/attachments/{attachment_id} - download_attachment
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_download_attachment(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .post("/chats/1/attachments")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .multipart(text_file("downloadable", "notes.txt"))
+            .await;
+        let attachment_id = response.json::<serde_json::Value>()["attachment_id"]
+            .as_i64()
+            .unwrap();
+
+        let response = server
+            .get(&format!("/chats/1/attachments/{}", attachment_id))
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        assert_eq!(response.header("content-type"), "text/plain");
+        assert_eq!(response.as_bytes().as_ref(), b"downloadable");
+
+        // l'allegato appartiene alla chat 1, non è raggiungibile dalla chat 2
+        let response = server
+            .get(&format!("/chats/2/attachments/{}", attachment_id))
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_not_found();
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per PUT /chats/{chat_id}/avatar - update_chat_avatar
+    // ============================================================
+
+    fn png_file() -> MultipartForm {
+        MultipartForm::new().add_part(
+            "file",
+            Part::bytes(&b"\x89PNG\r\n\x1a\nfake"[..])
+                .file_name("avatar.png")
+                .mime_type("image/png"),
+        )
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_update_chat_avatar_success(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        // Charlie è online e iscritto alla chat 1
+        let mut rx = state.chats_online.subscribe(&1);
+
+        let response = server
+            .put("/chats/1/avatar")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .multipart(png_file())
+            .await;
+
+        response.assert_status_ok();
+        let chat: serde_json::Value = response.json();
+        let avatar_url = chat["avatar_url"].as_str().expect("avatar_url should be set");
+        assert!(avatar_url.starts_with("/api/v1/chats/1/attachments/"));
+
+        match rx.try_recv() {
+            Ok(server::ws::chatmap::ChatEvent::ChatUpdated(updated)) => {
+                assert_eq!(updated.avatar_url.as_deref(), Some(avatar_url));
+            }
+            other => panic!("Expected ChatUpdated event, got {:?}", other),
+        }
+
+        // l'avatar si scarica dall'URL restituito
+        let response = server
+            .get(avatar_url)
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+        response.assert_status_ok();
+        assert_eq!(response.header("content-type"), "image/png");
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_update_chat_avatar_as_member(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        // Bob è MEMBER della chat 1
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        let response = server
+            .put("/chats/1/avatar")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .multipart(png_file())
+            .await;
+
+        response.assert_status_forbidden();
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_update_chat_avatar_rejects_non_images(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .put("/chats/1/avatar")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .multipart(text_file("not an image", "avatar.txt"))
+            .await;
+
+        response.assert_status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let count = sqlx::query_scalar!("SELECT COUNT(*) FROM attachments")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(count, 0);
+
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per il job di pulizia - collect_orphaned_attachments
+    // ============================================================
+
+    async fn upload(server: &axum_test::TestServer, auth: &str, name: &str) -> i32 {
+        let response = server
+            .post("/chats/1/attachments")
+            .add_header(HeaderName::from_static("authorization"), auth.to_string())
+            .multipart(text_file("attachment body", name))
+            .await;
+        response.assert_status_ok();
+        response.json::<serde_json::Value>()["attachment_id"]
+            .as_i64()
+            .unwrap() as i32
+    }
+
+    async fn is_stored(state: &AppState, pool: &MySqlPool, attachment_id: i32) -> bool {
+        let Some(key) = sqlx::query_scalar!(
+            "SELECT storage_key FROM attachments WHERE attachment_id = ?",
+            attachment_id
+        )
+        .fetch_optional(pool)
+        .await
+        .unwrap() else {
+            return false;
+        };
+        state
+            .storage
+            .head(&StoragePath::from(key.as_str()))
+            .await
+            .is_ok()
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_collect_orphaned_attachments(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+        let auth = format!("Bearer {}", token);
+
+        // un allegato mai usato, uno usato da un messaggio e l'avatar della chat
+        let unused = upload(&server, &auth, "unused.txt").await;
+        let in_message = upload(&server, &auth, "used.txt").await;
+        let message = state
+            .msg
+            .create(&CreateMessageDTO {
+                chat_id: 1,
+                sender_id: 1,
+                content: "see attachment".to_string(),
+                message_type: MessageType::UserMessage,
+                created_at: Utc::now(),
+                reply_to_message_id: None,
+                attachment_id: Some(in_message),
+                client_msg_id: None,
+                content_format: ContentFormat::Plain,
+            })
+            .await?;
+        server
+            .put("/chats/1/avatar")
+            .add_header(HeaderName::from_static("authorization"), auth.clone())
+            .multipart(png_file())
+            .await
+            .assert_status_ok();
+        let avatar =
+            sqlx::query_scalar!("SELECT avatar_attachment_id FROM chats WHERE chat_id = 1")
+                .fetch_one(&pool)
+                .await?
+                .unwrap();
+
+        // entro il periodo di grazia nessun allegato viene eliminato
+        assert_eq!(collect_orphaned_attachments(&state).await?, 0);
+
+        sqlx::query!("UPDATE attachments SET created_at = NOW() - INTERVAL 2 DAY")
+            .execute(&pool)
+            .await?;
+
+        assert_eq!(collect_orphaned_attachments(&state).await?, 1);
+        assert!(!is_stored(&state, &pool, unused).await);
+        assert!(is_stored(&state, &pool, in_message).await);
+        assert!(is_stored(&state, &pool, avatar).await);
+
+        // eliminato il messaggio, il suo allegato non è più usato
+        server
+            .delete(&format!("/chats/1/messages/{}", message.message_id))
+            .add_header(HeaderName::from_static("authorization"), auth.clone())
+            .await
+            .assert_status_ok();
+
+        assert_eq!(collect_orphaned_attachments(&state).await?, 1);
+        assert!(!is_stored(&state, &pool, in_message).await);
+        assert!(is_stored(&state, &pool, avatar).await);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_delete_chat_removes_attachment_files(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+        let auth = format!("Bearer {}", token);
+
+        let attachment_id = upload(&server, &auth, "notes.txt").await;
+        let key = sqlx::query_scalar!(
+            "SELECT storage_key FROM attachments WHERE attachment_id = ?",
+            attachment_id
+        )
+        .fetch_one(&pool)
+        .await?;
+
+        // Alice è Owner della chat 1
+        server
+            .delete("/chats/1")
+            .add_header(HeaderName::from_static("authorization"), auth)
+            .await
+            .assert_status_ok();
+
+        assert!(
+            state
+                .storage
+                .head(&StoragePath::from(key.as_str()))
+                .await
+                .is_err()
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+//! Integration tests per l'audit log delle chat
+
+mod common;
+
+#[cfg(test)]
+mod audit_tests {
+    use super::common::*;
+    use axum_test::http::HeaderName;
+    use serde_json::json;
+    use sqlx::MySqlPool;
+
+    // ============================================================
+    // Test per GET /chats/{chat_id}/audit - list_audit_log
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_audit_log_records_privileged_actions(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        // Alice (OWNER) rimuove Bob e poi rinomina la chat
+        server
+            .delete("/chats/1/members/2")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await
+            .assert_status_ok();
+
+        server
+            .patch("/chats/1")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "title": "Renamed" }))
+            .await
+            .assert_status_ok();
+
+        let response = server
+            .get("/chats/1/audit")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let entries: Vec<serde_json::Value> = response.json();
+        assert_eq!(entries.len(), 2);
+
+        // dalla più recente
+        assert_eq!(entries[0]["action"], "ChatUpdated");
+        assert_eq!(entries[0]["actor_id"], 1);
+        assert_eq!(entries[0]["payload"]["title"], "Renamed");
+
+        assert_eq!(entries[1]["action"], "MemberRemoved");
+        assert_eq!(entries[1]["actor_id"], 1);
+        assert_eq!(entries[1]["target_user_id"], 2);
+
+        // solo le rimozioni di membri
+        let response = server
+            .get("/chats/1/audit?action=MemberRemoved")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let entries: Vec<serde_json::Value> = response.json();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0]["target_user_id"], 2);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_audit_log_not_admin(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        // Bob è solo MEMBER della chat 1
+        let response = server
+            .get("/chats/1/audit")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_forbidden();
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_audit_log_invalid_limit(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .get("/chats/1/audit?limit=0")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_bad_request();
+        Ok(())
+    }
+}
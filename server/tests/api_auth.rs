@@ -3,6 +3,8 @@
 //! Test per:
 //! - POST /auth/login
 //! - POST /auth/register
+//! - POST /auth/refresh
+//! - POST /auth/logout
 //!
 //! Questi test usano `#[sqlx::test]` che:
 //! - Crea automaticamente un database di test isolato
@@ -15,8 +17,19 @@ mod common;
 #[cfg(test)]
 mod auth_tests {
     use super::common::*;
+    use axum_test::http::{HeaderName, StatusCode};
+    use futures_util::future::BoxFuture;
     use serde_json::json;
+    use server::auth::decode_jwt;
+    use server::config::{
+        AccessTokenConfig, AppProfile, LoginLockoutConfig, PasswordHashConfig,
+        PasswordPolicyConfig, RateLimitConfig,
+    };
+    use server::core::{AppState, AuthProvider, JwtKeys, PasswordHasher, Scope};
+    use server::entities::User;
+    use server::ws::usermap::{ConnectionInfo, InternalSignal};
     use sqlx::MySqlPool;
+    use std::sync::Arc;
 
     // ============================================================
     // Test per POST /auth/login - login_user
@@ -69,6 +82,99 @@ mod auth_tests {
         Ok(())
     }
 
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_login_cookie_without_secure_in_development(pool: MySqlPool) -> sqlx::Result<()> {
+        let jwt_secret = "ilmiobellissimosegretochevaassolutamentecambiato";
+        let state = Arc::new(
+            AppState::new(pool.clone(), jwt_secret.to_string())
+                .with_profile(AppProfile::Development, false),
+        );
+        let server = create_test_server(state);
+
+        let credentials = json!({
+            "username": "cookietest",
+            "password": "TestCookie123"
+        });
+        server
+            .post("/auth/register")
+            .json(&credentials)
+            .await
+            .assert_status_ok();
+
+        let response = server.post("/auth/login").json(&credentials).await;
+
+        response.assert_status_ok();
+        // senza Secure il browser salva il cookie anche su http://localhost
+        let cookie = response
+            .headers()
+            .get("set-cookie")
+            .unwrap()
+            .to_str()
+            .unwrap();
+        assert!(cookie.contains("HttpOnly; SameSite=Lax"));
+        assert!(!cookie.contains("Secure"));
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_login_uses_configured_token_claims(pool: MySqlPool) -> sqlx::Result<()> {
+        let jwt_secret = "ilmiobellissimosegretochevaassolutamentecambiato";
+        let jwt_keys = JwtKeys::hmac(jwt_secret).with_access_token(AccessTokenConfig {
+            ttl_minutes: 60,
+            issuer: Some("https://chat.example.org".to_string()),
+            audience: Some("ironlink-api".to_string()),
+        });
+        let state =
+            Arc::new(AppState::new(pool.clone(), jwt_secret.to_string()).with_jwt_keys(jwt_keys));
+        let server = create_test_server(state.clone());
+
+        let credentials = json!({
+            "username": "claimstest",
+            "password": "TestClaims123"
+        });
+        server
+            .post("/auth/register")
+            .json(&credentials)
+            .await
+            .assert_status_ok();
+
+        let response = server.post("/auth/login").json(&credentials).await;
+
+        response.assert_status_ok();
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["expires_in"], 3600);
+        let access_token = body["access_token"].as_str().unwrap().to_string();
+        let claims = decode_jwt(&access_token, &state.jwt_keys)
+            .expect("Issued token must be valid")
+            .claims;
+        assert_eq!(claims.iss.as_deref(), Some("https://chat.example.org"));
+        assert_eq!(claims.aud.as_deref(), Some("ironlink-api"));
+        assert_eq!(claims.scopes, vec![Scope::Chat]);
+
+        server
+            .get("/users/me")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", access_token),
+            )
+            .await
+            .assert_status_ok();
+
+        // un token senza issuer e audience non è destinato a questo server
+        let foreign = create_test_jwt(1, "alice", &state.jwt_secret);
+        server
+            .get("/users/me")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", foreign),
+            )
+            .await
+            .assert_status_unauthorized();
+
+        Ok(())
+    }
+
     #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
     async fn test_login_wrong_password(pool: MySqlPool) -> sqlx::Result<()> {
         let state = create_test_state(&pool);
@@ -332,6 +438,75 @@ mod auth_tests {
         Ok(())
     }
 
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_register_common_password(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+
+        let body = json!({
+            "username": "newuser",
+            "password": "Password1"
+        });
+
+        let response = server.post("/auth/register").json(&body).await;
+
+        response.assert_status_bad_request();
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_register_reports_field_errors(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+
+        let body = json!({
+            "username": "a!",
+            "password": "qwerty"
+        });
+
+        let response = server.post("/auth/register").json(&body).await;
+
+        response.assert_status_bad_request();
+        let error: serde_json::Value = response.json();
+        assert_eq!(error["error"], "Validation error");
+        assert_eq!(error["fields"]["username"].as_array().unwrap().len(), 2);
+        // una voce per ogni regola violata: lunghezza, maiuscole/minuscole, cifra, password comune
+        let password_errors = error["fields"]["password"].as_array().unwrap();
+        assert_eq!(password_errors.len(), 4);
+        assert_eq!(password_errors[0], "Password must be at least 8 characters");
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_register_with_configured_policy(pool: MySqlPool) -> sqlx::Result<()> {
+        let jwt_secret = "ilmiobellissimosegretochevaassolutamentecambiato";
+        let state = Arc::new(
+            AppState::new(pool.clone(), jwt_secret.to_string()).with_password_policy(
+                &PasswordPolicyConfig {
+                    min_length: 16,
+                    require_mixed_case: false,
+                    require_digit: false,
+                    reject_common: true,
+                },
+            ),
+        );
+        let server = create_test_server(state.clone());
+
+        // Password123 rispetta la policy di default ma non la lunghezza configurata
+        server
+            .post("/auth/register")
+            .json(&json!({ "username": "newuser", "password": "Password123" }))
+            .await
+            .assert_status_bad_request();
+
+        server
+            .post("/auth/register")
+            .json(&json!({ "username": "newuser", "password": "correct horse battery" }))
+            .await
+            .assert_status_ok();
+        Ok(())
+    }
+
     #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
     async fn test_register_missing_username(pool: MySqlPool) -> sqlx::Result<()> {
         let state = create_test_state(&pool);
@@ -443,4 +618,544 @@ mod auth_tests {
 
         Ok(())
     }
+
+    // ============================================================
+    // Test per POST /auth/refresh - refresh_tokens
+    // ============================================================
+
+    /// Registra un utente e ritorna il refresh token ottenuto dal login
+    async fn register_and_login(server: &axum_test::TestServer) -> String {
+        let credentials = json!({
+            "username": "refreshuser",
+            "password": "Refresh123"
+        });
+        server
+            .post("/auth/register")
+            .json(&credentials)
+            .await
+            .assert_status_ok();
+
+        let response = server.post("/auth/login").json(&credentials).await;
+        response.assert_status_ok();
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["token_type"], "Bearer");
+        assert!(body["access_token"].is_string());
+        body["refresh_token"].as_str().unwrap().to_string()
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_refresh_rotates_token(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let refresh_token = register_and_login(&server).await;
+
+        let response = server
+            .post("/auth/refresh")
+            .json(&json!({ "refresh_token": refresh_token }))
+            .await;
+        response.assert_status_ok();
+        assert!(response.headers().get("authorization").is_some());
+
+        let body: serde_json::Value = response.json();
+        let rotated = body["refresh_token"].as_str().unwrap();
+        assert_ne!(rotated, refresh_token, "Refresh token should be rotated");
+
+        // Il nuovo access token è valido sulle rotte protette
+        let access_token = body["access_token"].as_str().unwrap();
+        server
+            .get("/users/me/profile")
+            .add_header(
+                axum_test::http::HeaderName::from_static("authorization"),
+                format!("Bearer {}", access_token),
+            )
+            .await
+            .assert_status_ok();
+
+        // Il token ruotato può essere usato a sua volta
+        server
+            .post("/auth/refresh")
+            .json(&json!({ "refresh_token": rotated }))
+            .await
+            .assert_status_ok();
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_refresh_reuse_revokes_family(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let refresh_token = register_and_login(&server).await;
+
+        let response = server
+            .post("/auth/refresh")
+            .json(&json!({ "refresh_token": refresh_token }))
+            .await;
+        response.assert_status_ok();
+        let body: serde_json::Value = response.json();
+        let rotated = body["refresh_token"].as_str().unwrap().to_string();
+
+        // Riuso del token già ruotato: rifiutato
+        server
+            .post("/auth/refresh")
+            .json(&json!({ "refresh_token": refresh_token }))
+            .await
+            .assert_status_unauthorized();
+
+        // L'intera famiglia è revocata, anche il token più recente
+        server
+            .post("/auth/refresh")
+            .json(&json!({ "refresh_token": rotated }))
+            .await
+            .assert_status_unauthorized();
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_refresh_invalid_token(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+
+        server
+            .post("/auth/refresh")
+            .json(&json!({ "refresh_token": "not-a-real-token" }))
+            .await
+            .assert_status_unauthorized();
+
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per POST /auth/logout - logout_user
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_logout_revokes_tokens(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let refresh_token = register_and_login(&server).await;
+
+        let response = server
+            .post("/auth/refresh")
+            .json(&json!({ "refresh_token": refresh_token }))
+            .await;
+        response.assert_status_ok();
+        let body: serde_json::Value = response.json();
+        let access_token = body["access_token"].as_str().unwrap().to_string();
+        let refresh_token = body["refresh_token"].as_str().unwrap().to_string();
+
+        let response = server
+            .post("/auth/logout")
+            .add_header(
+                axum_test::http::HeaderName::from_static("authorization"),
+                format!("Bearer {}", access_token),
+            )
+            .json(&json!({ "refresh_token": refresh_token }))
+            .await;
+        response.assert_status_ok();
+        let cookie = response.headers().get("set-cookie").unwrap();
+        assert!(cookie.to_str().unwrap().contains("Max-Age=0"));
+
+        // L'access token usato per il logout non è più accettato
+        server
+            .get("/users/me/profile")
+            .add_header(
+                axum_test::http::HeaderName::from_static("authorization"),
+                format!("Bearer {}", access_token),
+            )
+            .await
+            .assert_status_unauthorized();
+
+        // Nemmeno il refresh token della sessione
+        server
+            .post("/auth/refresh")
+            .json(&json!({ "refresh_token": refresh_token }))
+            .await
+            .assert_status_unauthorized();
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_logout_only_revokes_current_token(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let current = create_test_jwt(1, "alice", &state.jwt_secret);
+        let other_session = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        // Alice è connessa via WebSocket con il token dell'altra sessione
+        let other_jti = decode_jwt(&other_session, &state.jwt_keys)
+            .unwrap()
+            .claims
+            .jti;
+        let (tx, mut rx) = create_signal_channel();
+        state.users_online.register_connection(
+            1,
+            tx,
+            ConnectionInfo::new(None, None, None).with_token_jti(other_jti),
+        );
+
+        server
+            .post("/auth/logout")
+            .add_header(
+                axum_test::http::HeaderName::from_static("authorization"),
+                format!("Bearer {}", current),
+            )
+            .await
+            .assert_status_ok();
+
+        // Un token di un'altra sessione dello stesso utente resta valido,
+        // e la connessione aperta con quel token non viene chiusa
+        server
+            .get("/users/me/profile")
+            .add_header(
+                axum_test::http::HeaderName::from_static("authorization"),
+                format!("Bearer {}", other_session),
+            )
+            .await
+            .assert_status_ok();
+        assert!(rx.try_recv().is_err());
+
+        // Il logout dell'altra sessione chiude la sua connessione
+        server
+            .post("/auth/logout")
+            .add_header(
+                axum_test::http::HeaderName::from_static("authorization"),
+                format!("Bearer {}", other_session),
+            )
+            .await
+            .assert_status_ok();
+        assert!(matches!(rx.try_recv(), Ok(InternalSignal::Shutdown)));
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_logout_without_token(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+
+        let response = server.post("/auth/logout").await;
+        response.assert_status_forbidden();
+
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per il backend di autenticazione (AuthProvider)
+    // ============================================================
+
+    /// Directory finta: accetta `directory-secret` per qualunque username
+    /// e fallisce se `available` è false
+    struct DirectoryStub {
+        available: bool,
+    }
+
+    impl AuthProvider for DirectoryStub {
+        fn authenticate<'a>(
+            &'a self,
+            _username: &'a str,
+            password: &'a str,
+            _user: Option<&'a User>,
+        ) -> BoxFuture<'a, Result<bool, String>> {
+            Box::pin(async move {
+                if !self.available {
+                    return Err("directory unreachable".to_string());
+                }
+                Ok(password == "directory-secret")
+            })
+        }
+    }
+
+    fn create_directory_state(pool: &MySqlPool, available: bool) -> Arc<AppState> {
+        let jwt_secret = "ilmiobellissimosegretochevaassolutamentecambiato";
+        Arc::new(
+            AppState::new(pool.clone(), jwt_secret.to_string())
+                .with_auth_provider(Arc::new(DirectoryStub { available })),
+        )
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_login_with_directory_provisions_user(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_directory_state(&pool, true);
+        let server = create_test_server(state.clone());
+
+        // Primo accesso di un utente della directory: viene creato l'utente locale
+        let response = server
+            .post("/auth/login")
+            .json(&json!({ "username": "newcomer", "password": "directory-secret" }))
+            .await;
+        response.assert_status_ok();
+        let body: serde_json::Value = response.json();
+        assert!(body["access_token"].is_string());
+        let user = state.user.find_by_username("newcomer").await?.unwrap();
+
+        // Il secondo accesso ritrova lo stesso utente
+        server
+            .post("/auth/login")
+            .json(&json!({ "username": "newcomer", "password": "directory-secret" }))
+            .await
+            .assert_status_ok();
+        let again = state.user.find_by_username("newcomer").await?.unwrap();
+        assert_eq!(again.user_id, user.user_id);
+
+        // Un utente già esistente entra con la password della directory, non con quella locale
+        server
+            .post("/auth/login")
+            .json(&json!({ "username": "alice", "password": "password123" }))
+            .await
+            .assert_status_unauthorized();
+        server
+            .post("/auth/login")
+            .json(&json!({ "username": "alice", "password": "directory-secret" }))
+            .await
+            .assert_status_ok();
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_login_directory_unavailable(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_directory_state(&pool, false);
+        let server = create_test_server(state.clone());
+
+        server
+            .post("/auth/login")
+            .json(&json!({ "username": "alice", "password": "directory-secret" }))
+            .await
+            .assert_status(axum::http::StatusCode::SERVICE_UNAVAILABLE);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_register_disabled_with_directory(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_directory_state(&pool, true);
+        let server = create_test_server(state.clone());
+
+        // Con la directory gli account nascono al primo login, non con la registrazione
+        server
+            .post("/auth/register")
+            .json(&json!({ "username": "squatter", "password": "LocalPassword123" }))
+            .await
+            .assert_status_forbidden();
+        assert!(state.user.find_by_username("squatter").await?.is_none());
+
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per il blocco del login dopo troppi tentativi falliti
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_login_lockout_after_failures(pool: MySqlPool) -> sqlx::Result<()> {
+        let jwt_secret = "ilmiobellissimosegretochevaassolutamentecambiato";
+        let state = Arc::new(
+            AppState::new(pool.clone(), jwt_secret.to_string()).with_login_lockout(
+                &LoginLockoutConfig {
+                    max_failures: 2,
+                    lockout_secs: 60,
+                    max_lockout_secs: 600,
+                },
+            ),
+        );
+        let server = create_test_server(state.clone());
+
+        for _ in 0..2 {
+            server
+                .post("/auth/login")
+                .json(&json!({ "username": "alice", "password": "wrong" }))
+                .await
+                .assert_status_unauthorized();
+        }
+
+        // Durante il blocco anche la password corretta viene rifiutata
+        let response = server
+            .post("/auth/login")
+            .json(&json!({ "username": "alice", "password": "password123" }))
+            .await;
+        response.assert_status(StatusCode::TOO_MANY_REQUESTS);
+        let retry_after: u64 = response
+            .header("retry-after")
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!((55..=60).contains(&retry_after));
+
+        // Gli altri utenti non sono bloccati
+        server
+            .post("/auth/login")
+            .json(&json!({ "username": "bob", "password": "password123" }))
+            .await
+            .assert_status_ok();
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_login_success_resets_failures(pool: MySqlPool) -> sqlx::Result<()> {
+        let jwt_secret = "ilmiobellissimosegretochevaassolutamentecambiato";
+        let state = Arc::new(
+            AppState::new(pool.clone(), jwt_secret.to_string()).with_login_lockout(
+                &LoginLockoutConfig {
+                    max_failures: 2,
+                    lockout_secs: 60,
+                    max_lockout_secs: 600,
+                },
+            ),
+        );
+        let server = create_test_server(state.clone());
+
+        // errore, successo, errore: mai due errori consecutivi
+        for password in ["wrong", "password123", "wrong"] {
+            server
+                .post("/auth/login")
+                .json(&json!({ "username": "alice", "password": password }))
+                .await;
+        }
+        server
+            .post("/auth/login")
+            .json(&json!({ "username": "alice", "password": "password123" }))
+            .await
+            .assert_status_ok();
+
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per l'algoritmo di hash delle password
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_login_rehashes_password(pool: MySqlPool) -> sqlx::Result<()> {
+        let jwt_secret = "ilmiobellissimosegretochevaassolutamentecambiato";
+        let hasher = PasswordHasher::new(&PasswordHashConfig::Argon2id {
+            memory_kib: 64,
+            iterations: 1,
+            parallelism: 1,
+        })
+        .unwrap();
+        let state = Arc::new(
+            AppState::new(pool.clone(), jwt_secret.to_string()).with_password_hasher(hasher),
+        );
+        let server = create_test_server(state.clone());
+
+        // Le fixture usano bcrypt: il login riesce e l'hash passa ad Argon2id
+        let before = state.user.find_by_username("alice").await?.unwrap();
+        assert!(before.password.starts_with("$2"));
+        server
+            .post("/auth/login")
+            .json(&json!({ "username": "alice", "password": "password123" }))
+            .await
+            .assert_status_ok();
+        let after = state.user.find_by_username("alice").await?.unwrap();
+        assert!(after.password.starts_with("$argon2id$v=19$m=64,t=1,p=1$"));
+
+        // Il nuovo hash è valido, quello con password sbagliata non viene toccato
+        server
+            .post("/auth/login")
+            .json(&json!({ "username": "alice", "password": "password123" }))
+            .await
+            .assert_status_ok();
+        server
+            .post("/auth/login")
+            .json(&json!({ "username": "bob", "password": "wrong" }))
+            .await
+            .assert_status_unauthorized();
+        let bob = state.user.find_by_username("bob").await?.unwrap();
+        assert!(bob.password.starts_with("$2"));
+
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per il rate limiting
+    // ============================================================
+
+    fn create_rate_limited_state(
+        pool: &MySqlPool,
+        auth_per_minute: u32,
+        user_per_minute: u32,
+    ) -> Arc<AppState> {
+        let jwt_secret = "ilmiobellissimosegretochevaassolutamentecambiato";
+        Arc::new(
+            AppState::new(pool.clone(), jwt_secret.to_string()).with_rate_limits(
+                &RateLimitConfig {
+                    auth_per_minute,
+                    user_per_minute,
+                    trust_forwarded_for: false,
+                },
+            ),
+        )
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_login_rate_limited(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_rate_limited_state(&pool, 2, 0);
+        let server = create_test_server(state.clone());
+
+        for _ in 0..2 {
+            server
+                .post("/auth/login")
+                .json(&json!({ "username": "alice", "password": "wrong" }))
+                .await
+                .assert_status_unauthorized();
+        }
+
+        // Oltre il limite anche le credenziali corrette vengono rifiutate
+        let response = server
+            .post("/auth/login")
+            .json(&json!({ "username": "alice", "password": "password123" }))
+            .await;
+        response.assert_status(StatusCode::TOO_MANY_REQUESTS);
+        let retry_after: u64 = response
+            .header("retry-after")
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!((1..=30).contains(&retry_after));
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_user_rate_limited(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_rate_limited_state(&pool, 0, 1);
+        let server = create_test_server(state.clone());
+        let alice_token = create_test_jwt(1, "alice", &state.jwt_secret);
+        let bob_token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        server
+            .get("/users/me")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", alice_token),
+            )
+            .await
+            .assert_status_ok();
+        server
+            .get("/users/me")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", alice_token),
+            )
+            .await
+            .assert_status(StatusCode::TOO_MANY_REQUESTS);
+
+        // Il limite è per utente: bob non è influenzato
+        server
+            .get("/users/me")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", bob_token),
+            )
+            .await
+            .assert_status_ok();
+
+        Ok(())
+    }
 }
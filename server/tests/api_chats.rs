@@ -5,9 +5,13 @@ mod common;
 #[cfg(test)]
 mod chat_tests {
     use super::common::*;
-    use axum_test::http::HeaderName;
+    use axum_test::http::{HeaderName, StatusCode};
     use serde_json::json;
+    use server::core::AppState;
+    use server::dtos::UpdateUserChatMetadataDTO;
+    use server::ws::usermap::InternalSignal;
     use sqlx::MySqlPool;
+    use std::sync::Arc;
 
     // ============================================================
     // Test per GET /chats - list_chats
@@ -46,6 +50,58 @@ mod chat_tests {
         Ok(())
     }
 
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_get_chats_not_modified(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .get("/chats")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+        response.assert_status_ok();
+        let etag = response.header("etag");
+
+        // lista invariata: 304 senza corpo
+        let response = server
+            .get("/chats")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .add_header(HeaderName::from_static("if-none-match"), etag.clone())
+            .await;
+        response.assert_status(StatusCode::NOT_MODIFIED);
+        assert!(response.text().is_empty());
+
+        // dopo la creazione di una chat l'ETag precedente non è più valido
+        server
+            .post("/chats")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "title": "New Chat", "chat_type": "Group" }))
+            .await
+            .assert_status_ok();
+        let response = server
+            .get("/chats")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .add_header(HeaderName::from_static("if-none-match"), etag.clone())
+            .await;
+        response.assert_status_ok();
+        assert_ne!(response.header("etag"), etag);
+
+        Ok(())
+    }
+
     #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
     async fn test_get_chats_without_token(pool: MySqlPool) -> sqlx::Result<()> {
         let state = create_test_state(&pool);
@@ -74,6 +130,188 @@ mod chat_tests {
         Ok(())
     }
 
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_get_chats_pinned_first(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        // Alice fissa la chat 3 (Dev Team)
+        let response = server
+            .post("/chats/3/pin")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+        response.assert_status_ok();
+
+        let response = server
+            .get("/chats")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+        response.assert_status_ok();
+        let chats: Vec<serde_json::Value> = response.json();
+        assert_eq!(chats[0]["chat_id"], 3);
+        assert!(!chats[0]["pinned_at"].is_null());
+        assert!(chats[1..].iter().all(|c| c.get("pinned_at").is_none()));
+
+        // Il pin è personale: Charlie vede la chat 3 senza pinned_at
+        let charlie_token = create_test_jwt(3, "charlie", &state.jwt_secret);
+        let response = server
+            .get("/chats")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", charlie_token),
+            )
+            .await;
+        let chats: Vec<serde_json::Value> = response.json();
+        assert!(chats.iter().all(|c| c.get("pinned_at").is_none()));
+
+        // Dopo l'unpin la chat torna nell'ordine normale
+        let response = server
+            .delete("/chats/3/pin")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+        response.assert_status_ok();
+
+        let pinned_at = sqlx::query_scalar!(
+            "SELECT pinned_at FROM userchatmetadata WHERE user_id = 1 AND chat_id = 3"
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert!(pinned_at.is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_pin_chat_not_member(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        // Bob non è membro della chat 3 (Dev Team)
+        let response = server
+            .post("/chats/3/pin")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_forbidden();
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per GET /chats/unread - list_unread_counts
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_get_unread_counts(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        // Bob ha ricevuto i messaggi della chat 1 solo fino a un'ora fa: l'aggiornamento
+        // dei marcatori passa dal repository, che ricalcola il contatore
+        let an_hour_ago = chrono::Utc::now() - chrono::Duration::hours(1);
+        state
+            .meta
+            .update(
+                &(2, 1),
+                &UpdateUserChatMetadataDTO {
+                    user_role: None,
+                    messages_visible_from: Some(an_hour_ago),
+                    messages_received_until: Some(an_hour_ago),
+                    version: None,
+                },
+            )
+            .await?;
+
+        let response = server
+            .get("/chats/unread")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let counts: Vec<serde_json::Value> = response.json();
+        assert_eq!(counts.len(), 2, "Bob è membro di due chat");
+
+        let unread_for = |chat_id: i64| {
+            counts
+                .iter()
+                .find(|c| c["chat_id"] == chat_id)
+                .map(|c| c["unread"].as_i64().unwrap())
+        };
+        // I messaggi di Alice e Charlie, escluso quello inviato da Bob
+        assert_eq!(unread_for(1), Some(2));
+        assert_eq!(unread_for(2), Some(0));
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_get_chat_overview(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        let an_hour_ago = chrono::Utc::now() - chrono::Duration::hours(1);
+        state
+            .meta
+            .update(
+                &(2, 1),
+                &UpdateUserChatMetadataDTO {
+                    user_role: None,
+                    messages_visible_from: Some(an_hour_ago),
+                    messages_received_until: Some(an_hour_ago),
+                    version: None,
+                },
+            )
+            .await?;
+
+        let response = server
+            .get("/chats/overview")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let overview: Vec<serde_json::Value> = response.json();
+        assert_eq!(overview.len(), 2, "Bob è membro di due chat");
+
+        let overview_for = |chat_id: i64| {
+            overview
+                .iter()
+                .find(|o| o["chat"]["chat_id"] == chat_id)
+                .unwrap()
+        };
+        let general = overview_for(1);
+        assert_eq!(general["member_count"], 3);
+        assert_eq!(general["unread_count"], 2);
+        assert_eq!(general["last_message"]["message_id"], 3);
+
+        // nella chat privata i messaggi delle fixture precedono l'ingresso di Bob
+        let private = overview_for(2);
+        assert_eq!(private["member_count"], 2);
+        assert!(private["last_message"].is_null());
+
+        Ok(())
+    }
+
     // ============================================================
     // Test per POST /chats - create_chat
     // ============================================================
@@ -120,6 +358,12 @@ mod chat_tests {
             "user_list": [1, 3]  // alice e charlie
         });
 
+        // Alice e charlie sono online
+        let (alice_tx, mut alice_rx) = create_signal_channel();
+        state.users_online.register_online(1, alice_tx);
+        let (charlie_tx, mut charlie_rx) = create_signal_channel();
+        state.users_online.register_online(3, charlie_tx);
+
         let response = server
             .post("/chats")
             .add_header(
@@ -134,6 +378,20 @@ mod chat_tests {
         assert_eq!(chat["chat_type"], "Private");
         assert!(chat.get("chat_id").is_some());
 
+        // Entrambi i membri ricevono la nuova chat senza ricaricare la lista
+        for rx in [&mut alice_rx, &mut charlie_rx] {
+            match rx.try_recv() {
+                Ok(InternalSignal::ChatCreated(created)) => {
+                    assert_eq!(
+                        created.chat_id,
+                        chat["chat_id"].as_i64().map(|id| id as i32)
+                    );
+                    assert_eq!(created.user_list.map(|users| users.len()), Some(2));
+                }
+                _ => panic!("Expected ChatCreated signal"),
+            }
+        }
+
         Ok(())
     }
 
@@ -202,6 +460,40 @@ mod chat_tests {
         Ok(())
     }
 
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_create_chat_body_too_large(pool: MySqlPool) -> sqlx::Result<()> {
+        let jwt_secret = "ilmiobellissimosegretochevaassolutamentecambiato";
+        let state = Arc::new(
+            AppState::new(pool.clone(), jwt_secret.to_string()).with_max_json_body_bytes(1024),
+        );
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let body = json!({
+            "title": "New Chat",
+            "description": "x".repeat(2048),
+            "chat_type": "Group"
+        });
+
+        let response = server
+            .post("/chats")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&body)
+            .await;
+
+        response.assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+        let error: serde_json::Value = response.json();
+        assert_eq!(error["error"], "Request body too large");
+        assert_eq!(
+            error["details"],
+            "The request body can be at most 1024 bytes"
+        );
+        Ok(())
+    }
+
     // ============================================================
     // Test per GET /chats/{chat_id}/messages - get_chat_messages
     // ============================================================
@@ -274,17 +566,21 @@ mod chat_tests {
     }
 
     // ============================================================
-    // Test per GET /chats/{chat_id}/members - list_chat_members
+    // Test per GET /chats/{chat_id}/messages/search - search_chat_messages
     // ============================================================
 
-    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
-    async fn test_get_chat_members_success(pool: MySqlPool) -> sqlx::Result<()> {
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_search_chat_messages_with_highlights(pool: MySqlPool) -> sqlx::Result<()> {
         let state = create_test_state(&pool);
         let server = create_test_server(state.clone());
         let token = create_test_jwt(1, "alice", &state.jwt_secret);
 
+        sqlx::query!("UPDATE userchatmetadata SET messages_visible_from = NOW() - INTERVAL 1 HOUR WHERE chat_id = 1")
+            .execute(&pool)
+            .await?;
+
         let response = server
-            .get("/chats/1/members")
+            .get("/chats/1/messages/search?q=everyone")
             .add_header(
                 HeaderName::from_static("authorization"),
                 format!("Bearer {}", token),
@@ -292,44 +588,92 @@ mod chat_tests {
             .await;
 
         response.assert_status_ok();
-        let members: Vec<serde_json::Value> = response.json();
-        assert_eq!(members.len(), 3, "General Chat dovrebbe avere 3 membri");
+        let results: Vec<serde_json::Value> = response.json();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0]["message"]["message_id"], 1);
+        // "Hello everyone!" -> "everyone" inizia al carattere 6
+        assert_eq!(results[0]["highlights"][0]["start"], 6);
+        assert_eq!(results[0]["highlights"][0]["end"], 14);
 
         Ok(())
     }
 
-    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
-    async fn test_get_chat_members_not_member(pool: MySqlPool) -> sqlx::Result<()> {
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_search_chat_messages_empty_query(pool: MySqlPool) -> sqlx::Result<()> {
         let state = create_test_state(&pool);
         let server = create_test_server(state.clone());
-        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
 
-        // Bob non è membro della chat 3
         let response = server
-            .get("/chats/3/members")
+            .get("/chats/1/messages/search?q=")
             .add_header(
                 HeaderName::from_static("authorization"),
                 format!("Bearer {}", token),
             )
             .await;
 
-        response.assert_status_forbidden();
+        response.assert_status_bad_request();
         Ok(())
     }
 
     // ============================================================
-    // Test per POST /chats/{chat_id}/invite/{user_id} - invite_to_chat
+    // Test per GET /chats/{chat_id}/messages/poll - poll_chat_messages
     // ============================================================
 
-    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
-    async fn test_invite_to_chat_success(pool: MySqlPool) -> sqlx::Result<()> {
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_poll_chat_messages(pool: MySqlPool) -> sqlx::Result<()> {
+        use server::ws::event_handlers::process_message;
+
         let state = create_test_state(&pool);
         let server = create_test_server(state.clone());
         let token = create_test_jwt(1, "alice", &state.jwt_secret);
 
-        // Alice (OWNER) invita Bob alla chat 3 (Dev Team)
+        sqlx::query!("UPDATE userchatmetadata SET messages_visible_from = NOW() - INTERVAL 1 HOUR WHERE chat_id = 1")
+            .execute(&pool)
+            .await?;
+
+        // messaggi già presenti: risposta immediata
         let response = server
-            .post("/chats/3/invite/2")
+            .get("/chats/1/messages/poll?after=1")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["messages"][0]["message_id"], 2);
+        assert_eq!(body["messages"][1]["message_id"], 3);
+
+        // nessun messaggio nuovo: la richiesta resta in attesa finché Bob non scrive
+        let poll = server
+            .get("/chats/1/messages/poll?after=3&timeout=10")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            );
+        let send = async {
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+            let message = serde_json::from_value(json!({
+                "chat_id": 1,
+                "sender_id": 2,
+                "content": "Are you still there?",
+                "message_type": "UserMessage"
+            }))
+            .expect("Valid message");
+            process_message(&state, 2, message).await;
+        };
+        let (response, _) = tokio::join!(poll, send);
+
+        response.assert_status_ok();
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["messages"].as_array().unwrap().len(), 1);
+        assert_eq!(body["messages"][0]["content"], "Are you still there?");
+
+        // timeout scaduto: lista vuota
+        let response = server
+            .get("/chats/1/messages/poll?after=999&timeout=1")
             .add_header(
                 HeaderName::from_static("authorization"),
                 format!("Bearer {}", token),
@@ -337,39 +681,513 @@ mod chat_tests {
             .await;
 
         response.assert_status_ok();
+        let body: serde_json::Value = response.json();
+        assert!(body["messages"].as_array().unwrap().is_empty());
+
         Ok(())
     }
 
-    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
-    async fn test_invite_to_chat_creates_invitation_in_db(pool: MySqlPool) -> sqlx::Result<()> {
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_poll_chat_messages_invalid_timeout(pool: MySqlPool) -> sqlx::Result<()> {
         let state = create_test_state(&pool);
         let server = create_test_server(state.clone());
-        let alice_id = 1;
-        let bob_id = 2;
-        let chat_id = 3;
-        let token = create_test_jwt(alice_id, "alice", &state.jwt_secret);
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
 
-        // Verifica che l'invito NON esista prima della chiamata
-        // NOTA: Nel codice invited_id=user_id (invitato) e invitee_id=inviter (chi invita)
-        // I nomi sono invertiti rispetto a ciò che suggeriscono
-        let invitations_before = sqlx::query!(
-            "SELECT COUNT(*) as count FROM invitations 
-             WHERE target_chat_id = ? AND invited_id = ? AND invitee_id = ?",
-            chat_id,
-            bob_id,  // invited_id = l'utente che viene invitato (Bob)
-            alice_id // invitee_id = l'utente che invita (Alice)
-        )
-        .fetch_one(&pool)
-        .await?;
+        let response = server
+            .get("/chats/1/messages/poll?after=1&timeout=0")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
 
-        assert_eq!(
-            invitations_before.count, 0,
-            "Non dovrebbero esserci inviti prima della chiamata"
-        );
+        response.assert_status_bad_request();
+        Ok(())
+    }
 
-        // Alice (OWNER) invita Bob alla chat 3 (Dev Team) tramite HTTP
+    // ============================================================
+    // Test per PATCH /chats/{chat_id}/messages/{message_id} - edit_message
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_edit_message_success(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        // Alice modifica il proprio messaggio 1 nella chat 1
         let response = server
-            .post(&format!("/chats/{}/invite/{}", chat_id, bob_id))
+            .patch("/chats/1/messages/1")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "content": "Hello everyone! (edited)" }))
+            .await;
+
+        response.assert_status_ok();
+        let message: serde_json::Value = response.json();
+        assert_eq!(message["message_id"], 1);
+        assert_eq!(message["content"], "Hello everyone! (edited)");
+
+        let content = sqlx::query_scalar!("SELECT content FROM messages WHERE message_id = 1")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(content, "Hello everyone! (edited)");
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_edit_message_not_author(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        // Alice cerca di modificare il messaggio 2 scritto da Bob
+        let response = server
+            .patch("/chats/1/messages/2")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "content": "Not my message" }))
+            .await;
+
+        response.assert_status_forbidden();
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_edit_message_wrong_chat(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        // Il messaggio 4 appartiene alla chat 2, non alla chat 1
+        let response = server
+            .patch("/chats/1/messages/4")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "content": "Wrong chat" }))
+            .await;
+
+        response.assert_status_not_found();
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per GET /chats/{chat_id}/messages/{message_id}/history - get_message_history
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_get_message_history(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+        let auth = format!("Bearer {}", token);
+
+        // Alice modifica due volte il proprio messaggio 1
+        for content in ["Hello everyone! (edited)", "Hello everyone!!"] {
+            server
+                .patch("/chats/1/messages/1")
+                .add_header(HeaderName::from_static("authorization"), auth.clone())
+                .json(&json!({ "content": content }))
+                .await
+                .assert_status_ok();
+        }
+
+        // Alice è Owner della chat 1
+        let response = server
+            .get("/chats/1/messages/1/history")
+            .add_header(HeaderName::from_static("authorization"), auth.clone())
+            .await;
+
+        response.assert_status_ok();
+        let revisions: Vec<serde_json::Value> = response.json();
+        assert_eq!(revisions.len(), 2);
+        assert_eq!(revisions[0]["content"], "Hello everyone!");
+        assert_eq!(revisions[1]["content"], "Hello everyone! (edited)");
+        assert!(revisions.iter().all(|r| r["message_id"] == 1));
+
+        // un messaggio mai modificato non ha revisioni
+        let response = server
+            .get("/chats/1/messages/2/history")
+            .add_header(HeaderName::from_static("authorization"), auth.clone())
+            .await;
+        response.assert_status_ok();
+        assert!(response.json::<Vec<serde_json::Value>>().is_empty());
+
+        // Il messaggio 4 appartiene alla chat 2, non alla chat 1
+        let response = server
+            .get("/chats/1/messages/4/history")
+            .add_header(HeaderName::from_static("authorization"), auth)
+            .await;
+        response.assert_status_not_found();
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_get_message_history_member_forbidden(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        // Bob è un semplice membro della chat 1
+        let response = server
+            .get("/chats/1/messages/1/history")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_forbidden();
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per DELETE /chats/{chat_id}/messages/{message_id} - delete_message
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_delete_message_returns_tombstone(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        // Bob elimina il proprio messaggio 2 nella chat 1
+        let response = server
+            .delete("/chats/1/messages/2")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let tombstone: serde_json::Value = response.json();
+        assert_eq!(tombstone["message_id"], 2);
+        assert!(tombstone["content"].is_null(), "Il tombstone non espone il contenuto");
+        assert!(!tombstone["deleted_at"].is_null());
+
+        // La riga resta nel database
+        let deleted_at = sqlx::query_scalar!("SELECT deleted_at FROM messages WHERE message_id = 2")
+            .fetch_one(&pool)
+            .await?;
+        assert!(deleted_at.is_some());
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_deleted_message_is_tombstone_in_history(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        // Messaggi inseriti con timestamp successivo a messages_visible_from
+        sqlx::query!("UPDATE userchatmetadata SET messages_visible_from = NOW() - INTERVAL 1 HOUR WHERE chat_id = 1")
+            .execute(&pool)
+            .await?;
+
+        server
+            .delete("/chats/1/messages/1")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await
+            .assert_status_ok();
+
+        let response = server
+            .get("/chats/1/messages")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let messages: Vec<serde_json::Value> = response.json();
+        assert_eq!(messages.len(), 3, "Il messaggio eliminato resta nella cronologia");
+
+        let tombstone = messages
+            .iter()
+            .find(|m| m["message_id"] == 1)
+            .expect("Il tombstone deve essere presente");
+        assert!(tombstone["content"].is_null());
+        assert!(!tombstone["deleted_at"].is_null());
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_delete_message_of_other_user_as_member(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        // Bob (MEMBER) cerca di eliminare il messaggio 3 di Charlie
+        let response = server
+            .delete("/chats/1/messages/3")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_forbidden();
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_delete_message_of_other_user_as_owner(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        // Alice (OWNER) può moderare i messaggi degli altri membri
+        let response = server
+            .delete("/chats/1/messages/3")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per POST/DELETE /chats/{chat_id}/messages/{message_id}/pin e GET /chats/{chat_id}/pins
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_pin_message_success(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let alice_token = create_test_jwt(1, "alice", &state.jwt_secret);
+        let bob_token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        sqlx::query!("UPDATE userchatmetadata SET messages_visible_from = NOW() - INTERVAL 1 HOUR WHERE chat_id = 1")
+            .execute(&pool)
+            .await?;
+
+        // Alice (OWNER) fissa il messaggio 2
+        server
+            .post("/chats/1/messages/2/pin")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", alice_token),
+            )
+            .await
+            .assert_status_ok();
+
+        // Bob (MEMBER) vede il messaggio fissato
+        let response = server
+            .get("/chats/1/pins")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", bob_token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let pins: Vec<serde_json::Value> = response.json();
+        assert_eq!(pins.len(), 1);
+        assert_eq!(pins[0]["message_id"], 2);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_pin_message_not_admin(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        // Bob (MEMBER) non può fissare messaggi
+        let response = server
+            .post("/chats/1/messages/1/pin")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_forbidden();
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_unpin_message_not_pinned(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .delete("/chats/1/messages/1/pin")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_not_found();
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per GET /chats/{chat_id}/members - list_chat_members
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_get_chat_members_success(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .get("/chats/1/members")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let members: Vec<serde_json::Value> = response.json();
+        assert_eq!(members.len(), 3, "General Chat dovrebbe avere 3 membri");
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_get_chat_members_not_member(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        // Bob non è membro della chat 3
+        let response = server
+            .get("/chats/3/members")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_forbidden();
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_get_chat_members_paginated(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+        let auth = format!("Bearer {}", token);
+
+        let response = server
+            .get("/chats/1/members?limit=2")
+            .add_header(HeaderName::from_static("authorization"), auth.clone())
+            .await;
+
+        response.assert_status_ok();
+        let members: Vec<serde_json::Value> = response.json();
+        assert_eq!(members.len(), 2);
+        assert_eq!(members[0]["user_id"], 1);
+        assert_eq!(members[1]["user_id"], 2);
+
+        // La pagina successiva parte dall'ultimo user_id ricevuto
+        let response = server
+            .get("/chats/1/members?limit=2&after_user_id=2")
+            .add_header(HeaderName::from_static("authorization"), auth.clone())
+            .await;
+
+        response.assert_status_ok();
+        let members: Vec<serde_json::Value> = response.json();
+        assert_eq!(members.len(), 1);
+        assert_eq!(members[0]["user_id"], 3);
+
+        let response = server
+            .get("/chats/1/members?role=Owner")
+            .add_header(HeaderName::from_static("authorization"), auth.clone())
+            .await;
+
+        response.assert_status_ok();
+        let members: Vec<serde_json::Value> = response.json();
+        assert_eq!(members.len(), 1);
+        assert_eq!(members[0]["user_id"], 1);
+
+        let response = server
+            .get("/chats/1/members?limit=0")
+            .add_header(HeaderName::from_static("authorization"), auth)
+            .await;
+
+        response.assert_status_bad_request();
+
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per POST /chats/{chat_id}/invite/{user_id} - invite_to_chat
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_invite_to_chat_success(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        // Alice (OWNER) invita Bob alla chat 3 (Dev Team)
+        let response = server
+            .post("/chats/3/invite/2")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_invite_to_chat_creates_invitation_in_db(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let alice_id = 1;
+        let bob_id = 2;
+        let chat_id = 3;
+        let token = create_test_jwt(alice_id, "alice", &state.jwt_secret);
+
+        // Verifica che l'invito NON esista prima della chiamata
+        // NOTA: Nel codice invited_id=user_id (invitato) e invitee_id=inviter (chi invita)
+        // I nomi sono invertiti rispetto a ciò che suggeriscono
+        let invitations_before = sqlx::query!(
+            "SELECT COUNT(*) as count FROM invitations 
+             WHERE target_chat_id = ? AND invited_id = ? AND invitee_id = ?",
+            chat_id,
+            bob_id,  // invited_id = l'utente che viene invitato (Bob)
+            alice_id // invitee_id = l'utente che invita (Alice)
+        )
+        .fetch_one(&pool)
+        .await?;
+
+        assert_eq!(
+            invitations_before.count, 0,
+            "Non dovrebbero esserci inviti prima della chiamata"
+        );
+
+        // Alice (OWNER) invita Bob alla chat 3 (Dev Team) tramite HTTP
+        let response = server
+            .post(&format!("/chats/{}/invite/{}", chat_id, bob_id))
             .add_header(
                 HeaderName::from_static("authorization"),
                 format!("Bearer {}", token),
@@ -389,44 +1207,760 @@ mod chat_tests {
         .fetch_one(&pool)
         .await?;
 
-        assert_eq!(
-            invitations_after.count, 1,
-            "Dovrebbe esistere esattamente un invito dopo la chiamata"
-        );
+        assert_eq!(
+            invitations_after.count, 1,
+            "Dovrebbe esistere esattamente un invito dopo la chiamata"
+        );
+
+        // Verifica i dettagli dell'invito creato
+        let invitation = sqlx::query!(
+            "SELECT invite_id, target_chat_id, invited_id, invitee_id, state 
+             FROM invitations 
+             WHERE target_chat_id = ? AND invited_id = ? AND invitee_id = ?",
+            chat_id,
+            bob_id,
+            alice_id
+        )
+        .fetch_one(&pool)
+        .await?;
+
+        assert_eq!(invitation.target_chat_id, chat_id, "Chat ID deve corrispondere");
+        assert_eq!(invitation.invited_id, bob_id, "invited_id deve essere Bob (l'invitato)");
+        assert_eq!(invitation.invitee_id, alice_id, "invitee_id deve essere Alice (l'inviter)");
+        assert_eq!(invitation.state, "PENDING", "Stato deve essere PENDING");
+        assert!(
+            invitation.invite_id > 0,
+            "Invitation ID deve essere valido"
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_invite_to_chat_with_note(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let alice_token = create_test_jwt(1, "alice", &state.jwt_secret);
+        let bob_token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        // Alice (OWNER) invita Bob alla chat 3 con un messaggio
+        let response = server
+            .post("/chats/3/invite/2")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", alice_token),
+            )
+            .json(&json!({ "note": "  Come help us with the release  " }))
+            .await;
+
+        response.assert_status_ok();
+
+        // Bob vede la nota nella lista degli inviti pendenti
+        let response = server
+            .get("/invitations/pending")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", bob_token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let invitations: Vec<serde_json::Value> = response.json();
+        assert_eq!(invitations.len(), 1);
+        assert_eq!(invitations[0]["note"], "Come help us with the release");
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_invite_to_chat_note_too_long(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .post("/chats/3/invite/2")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "note": "a".repeat(201) }))
+            .await;
+
+        response.assert_status_bad_request();
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per GET /invitations/sent - list_sent_invitations
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_list_sent_invitations(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        server
+            .post("/chats/3/invite/2")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await
+            .assert_status_ok();
+
+        let response = server
+            .get("/invitations/sent?status=Pending")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let invitations: Vec<serde_json::Value> = response.json();
+        assert_eq!(invitations.len(), 1);
+        assert_eq!(invitations[0]["state"], "Pending");
+        assert_eq!(invitations[0]["invited"]["username"], "bob");
+        assert_eq!(invitations[0]["chat"]["chat_id"], 3);
+
+        // Nessun invito accettato
+        let response = server
+            .get("/invitations/sent?status=Accepted")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let invitations: Vec<serde_json::Value> = response.json();
+        assert!(invitations.is_empty());
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_list_sent_invitations_invalid_status(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .get("/invitations/sent?status=Unknown")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_bad_request();
+        Ok(())
+    }
+
+    /// AppState con gruppi di al massimo 2 membri
+    fn create_small_group_state(pool: &MySqlPool) -> Arc<AppState> {
+        let jwt_secret = "ilmiobellissimosegretochevaassolutamentecambiato";
+        Arc::new(AppState::new(pool.clone(), jwt_secret.to_string()).with_max_group_members(2))
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_invite_to_full_group(pool: MySqlPool) -> sqlx::Result<()> {
+        // la chat 3 (Dev Team) ha già 2 membri
+        let state = create_small_group_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .post("/chats/3/invite/2")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status(StatusCode::CONFLICT);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["error"], "Group is full");
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_accept_invitation_to_full_group(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_small_group_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        // invito inviato quando il gruppo aveva ancora posto
+        sqlx::query!(
+            "INSERT INTO invitations (invite_id, target_chat_id, invited_id, invitee_id, state, created_at) VALUES (10, 3, 2, 1, 'PENDING', NOW())"
+        )
+        .execute(&pool)
+        .await?;
+
+        let response = server
+            .post("/invitations/10/accept")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status(StatusCode::CONFLICT);
+
+        let membership = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM userchatmetadata WHERE user_id = 2 AND chat_id = 3"
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(membership, 0);
+
+        // l'invito resta pending, Bob potrà accettarlo quando si libera un posto
+        let invitation_state = sqlx::query_scalar!("SELECT state FROM invitations WHERE invite_id = 10")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(invitation_state, "PENDING");
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_invite_to_chat_not_admin(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        // Bob (MEMBER) cerca di invitare charlie alla chat 1
+        let response = server
+            .post("/chats/1/invite/3")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_forbidden();
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_invite_to_chat_policy_everyone(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let alice_token = create_test_jwt(1, "alice", &state.jwt_secret);
+        let bob_token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        // Charlie non fa più parte della chat 1
+        sqlx::query!("DELETE FROM userchatmetadata WHERE user_id = 3 AND chat_id = 1")
+            .execute(&pool)
+            .await?;
+
+        // Alice (OWNER) permette a tutti i membri di invitare
+        let response = server
+            .patch("/chats/1")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", alice_token),
+            )
+            .json(&json!({ "invite_policy": "everyone" }))
+            .await;
+
+        response.assert_status_ok();
+        let chat: serde_json::Value = response.json();
+        assert_eq!(chat["invite_policy"], "everyone");
+
+        // Bob (MEMBER) ora può invitare Charlie
+        let response = server
+            .post("/chats/1/invite/3")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", bob_token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_invite_to_chat_already_member(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        // Alice cerca di invitare Bob alla chat 1 dove è già membro
+        let response = server
+            .post("/chats/1/invite/2")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_conflict();
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_invite_to_private_chat(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        // Tentativo di invitare a una chat privata (non permesso)
+        let response = server
+            .post("/chats/2/invite/3")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_bad_request();
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_invite_self_to_chat(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let alice_id = 1;
+        let token = create_test_jwt(alice_id, "alice", &state.jwt_secret);
+
+        // Alice cerca di invitare se stessa alla chat 3 (già membro)
+        let response = server
+            .post(&format!("/chats/3/invite/{}", alice_id))
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        // Dovrebbe fallire perché è già membro (conflict)
+        response.assert_status_conflict();
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_invite_nonexistent_user_to_chat(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        // Alice cerca di invitare un utente inesistente
+        let response = server
+            .post("/chats/3/invite/99999")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_not_found();
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_invite_verifies_inviter_is_member(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        // Bob (NON membro della chat 3) cerca di invitare Charlie alla chat 3
+        // Dovrebbe fallire perché Bob non è membro della chat 3
+        let response = server
+            .post("/chats/3/invite/3")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_forbidden();
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per PATCH /chats/{chat_id}/members/{user_id} - update_member_role
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_update_member_role_success(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        // Alice (OWNER) promuove Bob ad ADMIN nella chat 1
+        let response = server
+            .patch("/chats/1/members/2/role")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&"Admin")
+            .await;
+
+        response.assert_status_ok();
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_update_member_role_not_owner(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(3, "charlie", &state.jwt_secret);
+
+        // Charlie (ADMIN) cerca di modificare il ruolo (solo OWNER può)
+        let response = server
+            .patch("/chats/3/members/1/role")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&"Admin")
+            .await;
+
+        response.assert_status_forbidden();
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_update_member_role_to_owner(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        // Non si può promuovere a OWNER con questo endpoint
+        let response = server
+            .patch("/chats/1/members/2/role")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&"Owner")
+            .await;
+
+        response.assert_status_forbidden();
+        Ok(())
+    }
 
-        // Verifica i dettagli dell'invito creato
-        let invitation = sqlx::query!(
-            "SELECT invite_id, target_chat_id, invited_id, invitee_id, state 
-             FROM invitations 
-             WHERE target_chat_id = ? AND invited_id = ? AND invitee_id = ?",
-            chat_id,
-            bob_id,
-            alice_id
+    // ============================================================
+    // Test per PATCH /chats/{chat_id}/transfer_ownership - transfer_ownership
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_transfer_ownership_success(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        // Alice trasferisce ownership a Bob nella chat 1
+        let response = server
+            .patch("/chats/1/transfer_ownership/2")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_transfer_ownership_not_owner(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        // Bob non è OWNER, non può trasferire ownership
+        let response = server
+            .patch("/chats/1/transfer_ownership/3")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_forbidden();
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_transfer_ownership_to_non_member(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        // Alice cerca di trasferire ownership della chat 3 (Dev Team) a Bob
+        // Bob non è membro della chat 3 (solo Alice e Charlie sono membri)
+        let response = server
+            .patch("/chats/3/transfer_ownership/2")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_not_found();
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per DELETE /chats/{chat_id}/members/{user_id} - remove_member
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_remove_member_success(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        // Alice (OWNER) rimuove Bob dalla chat 1
+        let response = server
+            .delete("/chats/1/members/2")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_remove_member_not_admin(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        // Bob (MEMBER) cerca di rimuovere Charlie
+        let response = server
+            .delete("/chats/1/members/3")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_forbidden();
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_remove_owner(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(3, "charlie", &state.jwt_secret);
+
+        // Charlie (ADMIN) cerca di rimuovere Alice (OWNER)
+        let response = server
+            .delete("/chats/3/members/1")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_forbidden();
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per POST /chats/{chat_id}/members/{user_id}/ban - ban_member
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_ban_member_success(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let alice_token = create_test_jwt(1, "alice", &state.jwt_secret);
+        let bob_token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        // Bob è online e iscritto alla chat 1
+        let (tx, mut signal_rx) = create_signal_channel();
+        state.users_online.register_online(2, tx);
+        let mut chat_rx = state.chats_online.subscribe(&1);
+
+        // Alice (OWNER) banna Bob dalla chat 1
+        let response = server
+            .post("/chats/1/members/2/ban")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", alice_token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["user_id"], 2);
+        assert_eq!(body["chat_id"], 1);
+        assert_eq!(body["banned_by"], 1);
+
+        assert!(matches!(
+            signal_rx.try_recv(),
+            Ok(InternalSignal::RemoveChat(1))
+        ));
+        match chat_rx.try_recv() {
+            Ok(server::ws::chatmap::ChatEvent::MemberBanned(banned)) => {
+                assert_eq!(banned.user_id, 2);
+            }
+            other => panic!("Expected MemberBanned event, got {:?}", other),
+        }
+
+        let membership = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM userchatmetadata WHERE user_id = 2 AND chat_id = 1"
         )
         .fetch_one(&pool)
         .await?;
+        assert_eq!(membership, 0);
 
-        assert_eq!(invitation.target_chat_id, chat_id, "Chat ID deve corrispondere");
-        assert_eq!(invitation.invited_id, bob_id, "invited_id deve essere Bob (l'invitato)");
-        assert_eq!(invitation.invitee_id, alice_id, "invitee_id deve essere Alice (l'inviter)");
-        assert_eq!(invitation.state, "PENDING", "Stato deve essere PENDING");
-        assert!(
-            invitation.invite_id > 0,
-            "Invitation ID deve essere valido"
-        );
+        // Il middleware rifiuta le richieste dell'utente bannato
+        let response = server
+            .get("/chats/1/messages")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", bob_token),
+            )
+            .await;
+
+        response.assert_status_forbidden();
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["error"], "You are banned from this chat");
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_ban_member_not_admin(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        // Bob (MEMBER) cerca di bannare Charlie
+        let response = server
+            .post("/chats/1/members/3/ban")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_forbidden();
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_ban_owner(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(3, "charlie", &state.jwt_secret);
+
+        // Charlie (ADMIN) cerca di bannare Alice (OWNER)
+        let response = server
+            .post("/chats/3/members/1/ban")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_forbidden();
+
+        let bans = sqlx::query_scalar!("SELECT COUNT(*) FROM banned_members WHERE chat_id = 3")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(bans, 0);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_invite_banned_user(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        // Bob non è membro della chat 3 ma viene bannato preventivamente
+        let response = server
+            .post("/chats/3/members/2/ban")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+        response.assert_status_ok();
+
+        let response = server
+            .post("/chats/3/invite/2")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_forbidden();
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per POST /chats/{chat_id}/members/{user_id}/mute - mute_member
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_mute_member_success(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        // Alice (OWNER) silenzia Bob nella chat 1 per un'ora
+        let response = server
+            .post("/chats/1/members/2/mute?duration=3600")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["user_id"], 2);
+        assert!(body["muted_until"].is_string());
+
+        let muted_until = sqlx::query_scalar!(
+            "SELECT muted_until FROM userchatmetadata WHERE user_id = 2 AND chat_id = 1"
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert!(muted_until.is_some());
+
+        // Il silenziamento compare nella lista dei membri
+        let response = server
+            .get("/chats/1/members")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+        let members: Vec<serde_json::Value> = response.json();
+        let bob = members.iter().find(|m| m["user_id"] == 2).unwrap();
+        assert!(bob["muted_until"].is_string());
 
         Ok(())
     }
 
     #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
-    async fn test_invite_to_chat_not_admin(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_mute_member_not_admin(pool: MySqlPool) -> sqlx::Result<()> {
         let state = create_test_state(&pool);
         let server = create_test_server(state.clone());
         let token = create_test_jwt(2, "bob", &state.jwt_secret);
 
-        // Bob (MEMBER) cerca di invitare charlie alla chat 1
+        // Bob (MEMBER) cerca di silenziare Charlie
         let response = server
-            .post("/chats/1/invite/3")
+            .post("/chats/1/members/3/mute?duration=60")
             .add_header(
                 HeaderName::from_static("authorization"),
                 format!("Bearer {}", token),
@@ -438,33 +1972,32 @@ mod chat_tests {
     }
 
     #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
-    async fn test_invite_to_chat_already_member(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_mute_owner(pool: MySqlPool) -> sqlx::Result<()> {
         let state = create_test_state(&pool);
         let server = create_test_server(state.clone());
-        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+        let token = create_test_jwt(3, "charlie", &state.jwt_secret);
 
-        // Alice cerca di invitare Bob alla chat 1 dove è già membro
+        // Charlie (ADMIN) cerca di silenziare Alice (OWNER)
         let response = server
-            .post("/chats/1/invite/2")
+            .post("/chats/3/members/1/mute?duration=60")
             .add_header(
                 HeaderName::from_static("authorization"),
                 format!("Bearer {}", token),
             )
             .await;
 
-        response.assert_status_conflict();
+        response.assert_status_forbidden();
         Ok(())
     }
 
     #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
-    async fn test_invite_to_private_chat(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_mute_member_invalid_duration(pool: MySqlPool) -> sqlx::Result<()> {
         let state = create_test_state(&pool);
         let server = create_test_server(state.clone());
         let token = create_test_jwt(1, "alice", &state.jwt_secret);
 
-        // Tentativo di invitare a una chat privata (non permesso)
         let response = server
-            .post("/chats/2/invite/3")
+            .post("/chats/1/members/2/mute?duration=0")
             .add_header(
                 HeaderName::from_static("authorization"),
                 format!("Bearer {}", token),
@@ -475,124 +2008,171 @@ mod chat_tests {
         Ok(())
     }
 
+    // ============================================================
+    // Test per POST /chats/{chat_id}/leave - leave_chat
+    // ============================================================
+
     #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
-    async fn test_invite_self_to_chat(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_leave_chat_success(pool: MySqlPool) -> sqlx::Result<()> {
         let state = create_test_state(&pool);
         let server = create_test_server(state.clone());
-        let alice_id = 1;
-        let token = create_test_jwt(alice_id, "alice", &state.jwt_secret);
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
 
-        // Alice cerca di invitare se stessa alla chat 3 (già membro)
+        // Bob lascia la chat 1
         let response = server
-            .post(&format!("/chats/3/invite/{}", alice_id))
+            .post("/chats/1/leave")
             .add_header(
                 HeaderName::from_static("authorization"),
                 format!("Bearer {}", token),
             )
             .await;
 
-        // Dovrebbe fallire perché è già membro (conflict)
-        response.assert_status_conflict();
+        response.assert_status_ok();
         Ok(())
     }
 
     #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
-    async fn test_invite_nonexistent_user_to_chat(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_leave_chat_not_member(pool: MySqlPool) -> sqlx::Result<()> {
         let state = create_test_state(&pool);
         let server = create_test_server(state.clone());
-        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
 
-        // Alice cerca di invitare un utente inesistente
+        // Bob cerca di lasciare la chat 3 di cui non è membro
         let response = server
-            .post("/chats/3/invite/99999")
+            .post("/chats/3/leave")
             .add_header(
                 HeaderName::from_static("authorization"),
                 format!("Bearer {}", token),
             )
             .await;
 
-        response.assert_status_not_found();
+        response.assert_status_forbidden();
         Ok(())
     }
 
     #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
-    async fn test_invite_verifies_inviter_is_member(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_leave_chat_as_owner(pool: MySqlPool) -> sqlx::Result<()> {
         let state = create_test_state(&pool);
         let server = create_test_server(state.clone());
-        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
 
-        // Bob (NON membro della chat 3) cerca di invitare Charlie alla chat 3
-        // Dovrebbe fallire perché Bob non è membro della chat 3
+        // Alice (OWNER) cerca di lasciare la chat
         let response = server
-            .post("/chats/3/invite/3")
+            .post("/chats/1/leave")
             .add_header(
                 HeaderName::from_static("authorization"),
                 format!("Bearer {}", token),
             )
             .await;
 
-        response.assert_status_forbidden();
+        response.assert_status_conflict();
         Ok(())
     }
 
     // ============================================================
-    // Test per PATCH /chats/{chat_id}/members/{user_id} - update_member_role
+    // Test per PUT/GET /chats/{chat_id}/draft - save_draft, get_draft
     // ============================================================
 
     #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
-    async fn test_update_member_role_success(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_draft_roundtrip(pool: MySqlPool) -> sqlx::Result<()> {
         let state = create_test_state(&pool);
         let server = create_test_server(state.clone());
         let token = create_test_jwt(1, "alice", &state.jwt_secret);
 
-        // Alice (OWNER) promuove Bob ad ADMIN nella chat 1
+        // Nessuna bozza salvata -> null
         let response = server
-            .patch("/chats/1/members/2/role")
+            .get("/chats/1/draft")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+        response.assert_status_ok();
+        assert!(response.json::<serde_json::Value>().is_null());
+
+        let response = server
+            .put("/chats/1/draft")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "content": "Work in progress" }))
+            .await;
+        response.assert_status_ok();
+        let saved: serde_json::Value = response.json();
+        assert_eq!(saved["chat_id"], 1);
+        assert_eq!(saved["content"], "Work in progress");
+
+        // Un altro dispositivo di Alice ritrova la bozza
+        let response = server
+            .get("/chats/1/draft")
             .add_header(
                 HeaderName::from_static("authorization"),
                 format!("Bearer {}", token),
             )
-            .json(&"Admin")
             .await;
+        response.assert_status_ok();
+        let draft: serde_json::Value = response.json();
+        assert_eq!(draft["content"], "Work in progress");
 
+        // Bob non vede la bozza di Alice
+        let bob_token = create_test_jwt(2, "bob", &state.jwt_secret);
+        let response = server
+            .get("/chats/1/draft")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", bob_token),
+            )
+            .await;
         response.assert_status_ok();
+        assert!(response.json::<serde_json::Value>().is_null());
+
         Ok(())
     }
 
     #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
-    async fn test_update_member_role_not_owner(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_draft_cleared_with_empty_content(pool: MySqlPool) -> sqlx::Result<()> {
         let state = create_test_state(&pool);
         let server = create_test_server(state.clone());
-        let token = create_test_jwt(3, "charlie", &state.jwt_secret);
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        sqlx::query!("INSERT INTO drafts (user_id, chat_id, content, updated_at) VALUES (1, 1, 'old', NOW())")
+            .execute(&pool)
+            .await?;
 
-        // Charlie (ADMIN) cerca di modificare il ruolo (solo OWNER può)
         let response = server
-            .patch("/chats/3/members/1/role")
+            .put("/chats/1/draft")
             .add_header(
                 HeaderName::from_static("authorization"),
                 format!("Bearer {}", token),
             )
-            .json(&"Admin")
+            .json(&json!({ "content": "" }))
             .await;
+        response.assert_status_ok();
+        assert!(response.json::<serde_json::Value>().is_null());
+
+        let count = sqlx::query_scalar!("SELECT COUNT(*) FROM drafts WHERE user_id = 1 AND chat_id = 1")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(count, 0);
 
-        response.assert_status_forbidden();
         Ok(())
     }
 
     #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
-    async fn test_update_member_role_to_owner(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_draft_not_member(pool: MySqlPool) -> sqlx::Result<()> {
         let state = create_test_state(&pool);
         let server = create_test_server(state.clone());
-        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
 
-        // Non si può promuovere a OWNER con questo endpoint
+        // Bob non è membro della chat 3 (Dev Team)
         let response = server
-            .patch("/chats/1/members/2/role")
+            .put("/chats/3/draft")
             .add_header(
                 HeaderName::from_static("authorization"),
                 format!("Bearer {}", token),
             )
-            .json(&"Owner")
+            .json(&json!({ "content": "sneaky" }))
             .await;
 
         response.assert_status_forbidden();
@@ -600,18 +2180,53 @@ mod chat_tests {
     }
 
     // ============================================================
-    // Test per PATCH /chats/{chat_id}/transfer_ownership - transfer_ownership
+    // Test per GET /chats/discover - discover_chats
     // ============================================================
 
     #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
-    async fn test_transfer_ownership_success(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_discover_chats(pool: MySqlPool) -> sqlx::Result<()> {
         let state = create_test_state(&pool);
         let server = create_test_server(state.clone());
-        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+        // Bob non è membro della chat 3, ma la vede nella directory
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        // la chat privata 2 non deve comparire anche se marcata pubblica
+        sqlx::query!("UPDATE chats SET is_public = TRUE WHERE chat_id IN (1, 2, 3)")
+            .execute(&pool)
+            .await?;
 
-        // Alice trasferisce ownership a Bob nella chat 1
         let response = server
-            .patch("/chats/1/transfer_ownership/2")
+            .get("/chats/discover")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let chats: Vec<serde_json::Value> = response.json();
+        let ids: Vec<i64> = chats.iter().map(|c| c["chat_id"].as_i64().unwrap()).collect();
+        // ordinate per numero di membri: chat 1 (3 membri), chat 3 (2 membri)
+        assert_eq!(ids, vec![1, 3]);
+        assert_eq!(chats[0]["member_count"], 3);
+
+        let response = server
+            .get("/chats/discover?q=Dev")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let chats: Vec<serde_json::Value> = response.json();
+        assert_eq!(chats.len(), 1);
+        assert_eq!(chats[0]["chat_id"], 3);
+        assert_eq!(chats[0]["member_count"], 2);
+
+        // seconda pagina da un elemento
+        let response = server
+            .get("/chats/discover?limit=1&offset=1")
             .add_header(
                 HeaderName::from_static("authorization"),
                 format!("Bearer {}", token),
@@ -619,84 +2234,132 @@ mod chat_tests {
             .await;
 
         response.assert_status_ok();
+        let chats: Vec<serde_json::Value> = response.json();
+        assert_eq!(chats.len(), 1);
+        assert_eq!(chats[0]["chat_id"], 3);
+
         Ok(())
     }
 
     #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
-    async fn test_transfer_ownership_not_owner(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_discover_chats_invalid_limit(pool: MySqlPool) -> sqlx::Result<()> {
         let state = create_test_state(&pool);
         let server = create_test_server(state.clone());
-        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
 
-        // Bob non è OWNER, non può trasferire ownership
         let response = server
-            .patch("/chats/1/transfer_ownership/3")
+            .get("/chats/discover?limit=500")
             .add_header(
                 HeaderName::from_static("authorization"),
                 format!("Bearer {}", token),
             )
             .await;
 
-        response.assert_status_forbidden();
+        response.assert_status_bad_request();
         Ok(())
     }
 
+    // ============================================================
+    // Test per PATCH /chats/{chat_id} - update_chat
+    // ============================================================
+
     #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
-    async fn test_transfer_ownership_to_non_member(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_update_chat_success(pool: MySqlPool) -> sqlx::Result<()> {
         let state = create_test_state(&pool);
         let server = create_test_server(state.clone());
-        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+        // Charlie è ADMIN della chat 3
+        let token = create_test_jwt(3, "charlie", &state.jwt_secret);
+
+        let mut rx = state.chats_online.subscribe(&3);
 
-        // Alice cerca di trasferire ownership della chat 3 (Dev Team) a Bob
-        // Bob non è membro della chat 3 (solo Alice e Charlie sono membri)
         let response = server
-            .patch("/chats/3/transfer_ownership/2")
+            .patch("/chats/3")
             .add_header(
                 HeaderName::from_static("authorization"),
                 format!("Bearer {}", token),
             )
+            .json(&json!({ "title": "Core Team" }))
             .await;
 
-        response.assert_status_not_found();
+        response.assert_status_ok();
+        let chat: serde_json::Value = response.json();
+        assert_eq!(chat["title"], "Core Team");
+
+        match rx.try_recv() {
+            Ok(server::ws::chatmap::ChatEvent::ChatUpdated(updated)) => {
+                assert_eq!(updated.title.as_deref(), Some("Core Team"));
+            }
+            other => panic!("Expected ChatUpdated event, got {:?}", other),
+        }
+
+        let title = sqlx::query_scalar!("SELECT title FROM chats WHERE chat_id = 3")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(title.as_deref(), Some("Core Team"));
+
         Ok(())
     }
 
-    // ============================================================
-    // Test per DELETE /chats/{chat_id}/members/{user_id} - remove_member
-    // ============================================================
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_update_chat_announcement_only(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .patch("/chats/1")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "announcement_only": true }))
+            .await;
+
+        response.assert_status_ok();
+        let chat: serde_json::Value = response.json();
+        assert_eq!(chat["announcement_only"], true);
+        // gli altri campi restano invariati
+        assert_eq!(chat["title"], "General Chat");
+
+        Ok(())
+    }
 
     #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
-    async fn test_remove_member_success(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_update_chat_content_filter(pool: MySqlPool) -> sqlx::Result<()> {
         let state = create_test_state(&pool);
         let server = create_test_server(state.clone());
         let token = create_test_jwt(1, "alice", &state.jwt_secret);
 
-        // Alice (OWNER) rimuove Bob dalla chat 1
         let response = server
-            .delete("/chats/1/members/2")
+            .patch("/chats/1")
             .add_header(
                 HeaderName::from_static("authorization"),
                 format!("Bearer {}", token),
             )
+            .json(&json!({ "content_filter": "redact" }))
             .await;
 
         response.assert_status_ok();
+        let chat: serde_json::Value = response.json();
+        assert_eq!(chat["content_filter"], "redact");
+
         Ok(())
     }
 
     #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
-    async fn test_remove_member_not_admin(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_update_chat_as_member(pool: MySqlPool) -> sqlx::Result<()> {
         let state = create_test_state(&pool);
         let server = create_test_server(state.clone());
+        // Bob è MEMBER della chat 1
         let token = create_test_jwt(2, "bob", &state.jwt_secret);
 
-        // Bob (MEMBER) cerca di rimuovere Charlie
         let response = server
-            .delete("/chats/1/members/3")
+            .patch("/chats/1")
             .add_header(
                 HeaderName::from_static("authorization"),
                 format!("Bearer {}", token),
             )
+            .json(&json!({ "description": "hijacked" }))
             .await;
 
         response.assert_status_forbidden();
@@ -704,37 +2367,42 @@ mod chat_tests {
     }
 
     #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
-    async fn test_remove_owner(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_update_chat_invalid_title(pool: MySqlPool) -> sqlx::Result<()> {
         let state = create_test_state(&pool);
         let server = create_test_server(state.clone());
-        let token = create_test_jwt(3, "charlie", &state.jwt_secret);
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
 
-        // Charlie (ADMIN) cerca di rimuovere Alice (OWNER)
         let response = server
-            .delete("/chats/3/members/1")
+            .patch("/chats/1")
             .add_header(
                 HeaderName::from_static("authorization"),
                 format!("Bearer {}", token),
             )
+            .json(&json!({ "title": "" }))
             .await;
 
-        response.assert_status_forbidden();
+        response.assert_status_bad_request();
         Ok(())
     }
 
     // ============================================================
-    // Test per POST /chats/{chat_id}/leave - leave_chat
+    // Test per DELETE /chats/{chat_id} - delete_chat
     // ============================================================
 
     #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
-    async fn test_leave_chat_success(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_delete_chat_success(pool: MySqlPool) -> sqlx::Result<()> {
         let state = create_test_state(&pool);
         let server = create_test_server(state.clone());
-        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+        // Alice è OWNER della chat 1
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        // Bob è online e iscritto alla chat 1
+        let (tx, mut signal_rx) = create_signal_channel();
+        state.users_online.register_online(2, tx);
+        let _chat_rx = state.chats_online.subscribe(&1);
 
-        // Bob lascia la chat 1
         let response = server
-            .post("/chats/1/leave")
+            .delete("/chats/1")
             .add_header(
                 HeaderName::from_static("authorization"),
                 format!("Bearer {}", token),
@@ -742,18 +2410,40 @@ mod chat_tests {
             .await;
 
         response.assert_status_ok();
+
+        assert!(matches!(
+            signal_rx.try_recv(),
+            Ok(InternalSignal::ChatDeleted(1))
+        ));
+        assert!(!state.chats_online.has_chat_channel(&1));
+
+        let chats = sqlx::query_scalar!("SELECT COUNT(*) FROM chats WHERE chat_id = 1")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(chats, 0);
+
+        // ON DELETE CASCADE su metadata e messaggi
+        let members = sqlx::query_scalar!("SELECT COUNT(*) FROM userchatmetadata WHERE chat_id = 1")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(members, 0);
+        let messages = sqlx::query_scalar!("SELECT COUNT(*) FROM messages WHERE chat_id = 1")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(messages, 0);
+
         Ok(())
     }
 
     #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
-    async fn test_leave_chat_not_member(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_delete_chat_as_admin(pool: MySqlPool) -> sqlx::Result<()> {
         let state = create_test_state(&pool);
         let server = create_test_server(state.clone());
-        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+        // Charlie è ADMIN della chat 3, non OWNER
+        let token = create_test_jwt(3, "charlie", &state.jwt_secret);
 
-        // Bob cerca di lasciare la chat 3 di cui non è membro
         let response = server
-            .post("/chats/3/leave")
+            .delete("/chats/3")
             .add_header(
                 HeaderName::from_static("authorization"),
                 format!("Bearer {}", token),
@@ -761,25 +2451,94 @@ mod chat_tests {
             .await;
 
         response.assert_status_forbidden();
+
+        let chats = sqlx::query_scalar!("SELECT COUNT(*) FROM chats WHERE chat_id = 3")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(chats, 1);
+
         Ok(())
     }
 
     #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
-    async fn test_leave_chat_as_owner(pool: MySqlPool) -> sqlx::Result<()> {
+    async fn test_delete_chat_not_member(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        // Bob non è membro della chat 3
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        let response = server
+            .delete("/chats/3")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_forbidden();
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per GET /chats/{chat_id}/export - export_chat
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_export_chat_ndjson(pool: MySqlPool) -> sqlx::Result<()> {
         let state = create_test_state(&pool);
         let server = create_test_server(state.clone());
         let token = create_test_jwt(1, "alice", &state.jwt_secret);
 
-        // Alice (OWNER) cerca di lasciare la chat
+        sqlx::query!("UPDATE userchatmetadata SET messages_visible_from = NOW() - INTERVAL 1 HOUR WHERE chat_id = 1")
+            .execute(&pool)
+            .await?;
+
         let response = server
-            .post("/chats/1/leave")
+            .get("/chats/1/export")
             .add_header(
                 HeaderName::from_static("authorization"),
                 format!("Bearer {}", token),
             )
             .await;
 
-        response.assert_status_conflict();
+        response.assert_status_ok();
+        assert_eq!(response.header("content-type"), "application/x-ndjson");
+
+        let records: Vec<serde_json::Value> = response
+            .text()
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("each line is a JSON object"))
+            .collect();
+
+        // chat, 3 membri e i 3 messaggi della chat 1 in ordine cronologico
+        assert_eq!(records.len(), 7);
+        assert_eq!(records[0]["Chat"]["chat_id"], 1);
+        assert!(records[1..4].iter().all(|r| r.get("Member").is_some()));
+        let contents: Vec<&str> = records[4..]
+            .iter()
+            .map(|r| r["Message"]["content"].as_str().unwrap())
+            .collect();
+        assert_eq!(contents, vec!["Hello everyone!", "Hi Alice!", "Good morning!"]);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_export_chat_as_member(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        // Bob è MEMBER della chat 1
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        let response = server
+            .get("/chats/1/export")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_forbidden();
         Ok(())
     }
 }
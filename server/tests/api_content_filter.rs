@@ -0,0 +1,155 @@
+//! Integration tests per il filtro dei contenuti applicato da deliver_message
+
+mod common;
+
+#[cfg(test)]
+mod content_filter_tests {
+    use super::common::*;
+    use futures_util::future::BoxFuture;
+    use server::core::AppState;
+    use server::core::content_filter::{ContentClassifier, ContentFilter};
+    use server::dtos::MessageDTO;
+    use server::ws::event_handlers::{MessageRejection, deliver_message};
+    use sqlx::MySqlPool;
+    use std::sync::Arc;
+
+    /// Classificatore che segnala ogni messaggio contenente "buy now"
+    struct SpamClassifier;
+
+    impl ContentClassifier for SpamClassifier {
+        fn classify<'a>(&'a self, text: &'a str) -> BoxFuture<'a, Result<bool, String>> {
+            Box::pin(async move { Ok(text.to_lowercase().contains("buy now")) })
+        }
+    }
+
+    fn create_filtered_state(pool: &MySqlPool) -> Arc<AppState> {
+        let filter =
+            ContentFilter::new(["darn".to_string()]).with_classifier(Arc::new(SpamClassifier));
+        Arc::new(
+            AppState::new(
+                pool.clone(),
+                "ilmiobellissimosegretochevaassolutamentecambiato".to_string(),
+            )
+            .with_content_filter(filter),
+        )
+    }
+
+    async fn set_policy(pool: &MySqlPool, chat_id: i32, policy: &str) -> sqlx::Result<()> {
+        sqlx::query!(
+            "UPDATE chats SET content_filter = ? WHERE chat_id = ?",
+            policy,
+            chat_id
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Messaggio di alice nella chat 1
+    fn alice_message(content: &str) -> MessageDTO {
+        serde_json::from_value(serde_json::json!({
+            "chat_id": 1,
+            "sender_id": 1,
+            "content": content,
+            "message_type": "UserMessage"
+        }))
+        .expect("Valid message")
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_filter_off_delivers_unchanged(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_filtered_state(&pool);
+
+        let saved = deliver_message(&state, 1, alice_message("darn it"))
+            .await
+            .expect("Message must be delivered");
+        assert_eq!(saved.content, "darn it");
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_filter_reject(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_filtered_state(&pool);
+        set_policy(&pool, 1, "REJECT").await?;
+
+        let result = deliver_message(&state, 1, alice_message("darn it")).await;
+        let rejection = result.expect_err("Message must be rejected");
+        assert_eq!(rejection.code(), "CONTENT_REJECTED");
+
+        // il messaggio non è stato salvato
+        let count = sqlx::query_scalar!("SELECT COUNT(*) FROM messages WHERE content = 'darn it'")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(count, 0);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_filter_redact(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_filtered_state(&pool);
+        set_policy(&pool, 1, "REDACT").await?;
+
+        let saved = deliver_message(&state, 1, alice_message("Darn, that was close"))
+            .await
+            .expect("Message must be delivered");
+        assert_eq!(saved.content, "****, that was close");
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_filter_redact_rejects_classifier_match(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_filtered_state(&pool);
+        set_policy(&pool, 1, "REDACT").await?;
+
+        // il classificatore non indica cosa mascherare, quindi il messaggio viene rifiutato
+        let result = deliver_message(&state, 1, alice_message("Buy now, limited offer")).await;
+        assert!(matches!(
+            result,
+            Err(MessageRejection::Restricted {
+                code: "CONTENT_REJECTED",
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_filter_flag_creates_report(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_filtered_state(&pool);
+        set_policy(&pool, 1, "FLAG").await?;
+
+        let saved = deliver_message(&state, 1, alice_message("darn it"))
+            .await
+            .expect("Message must be delivered");
+        assert_eq!(saved.content, "darn it");
+
+        // segnalazione automatica, senza utente segnalante
+        let report = sqlx::query!(
+            "SELECT reporter_id, reported_user_id, message_id FROM reports WHERE state = 'PENDING'"
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(report.reporter_id, None);
+        assert_eq!(report.reported_user_id, 1);
+        assert_eq!(report.message_id, Some(saved.message_id));
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_filter_clean_message_passes(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_filtered_state(&pool);
+        set_policy(&pool, 1, "REJECT").await?;
+
+        let saved = deliver_message(&state, 1, alice_message("Good morning everyone"))
+            .await
+            .expect("Message must be delivered");
+        assert_eq!(saved.content, "Good morning everyone");
+
+        Ok(())
+    }
+}
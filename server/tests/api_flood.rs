@@ -0,0 +1,166 @@
+//! Integration tests per il rilevamento di flood e spam e il silenziamento automatico
+
+mod common;
+
+#[cfg(test)]
+mod flood_tests {
+    use super::common::*;
+    use axum_test::http::HeaderName;
+    use server::core::AppState;
+    use server::core::config::FloodConfig;
+    use server::dtos::MessageDTO;
+    use server::entities::FloodReason;
+    use server::ws::event_handlers::{deliver_message, process_message};
+    use server::ws::signal_queue::SignalReceiver;
+    use server::ws::usermap::InternalSignal;
+    use sqlx::MySqlPool;
+    use std::sync::Arc;
+
+    fn create_flood_state(pool: &MySqlPool) -> Arc<AppState> {
+        let config = FloodConfig {
+            repeat_threshold: 3,
+            link_threshold: 3,
+            join_leave_threshold: 3,
+            ..FloodConfig::default()
+        };
+        Arc::new(
+            AppState::new(
+                pool.clone(),
+                "ilmiobellissimosegretochevaassolutamentecambiato".to_string(),
+            )
+            .with_flood_detection(&config),
+        )
+    }
+
+    fn chat_message(chat_id: i32, sender_id: i32, content: &str) -> MessageDTO {
+        serde_json::from_value(serde_json::json!({
+            "chat_id": chat_id,
+            "sender_id": sender_id,
+            "content": content,
+            "message_type": "UserMessage"
+        }))
+        .expect("Valid message")
+    }
+
+    /// Segnali ricevuti finora da una connessione
+    fn drain(rx: &mut SignalReceiver) -> Vec<InternalSignal> {
+        std::iter::from_fn(|| rx.try_recv().ok()).collect()
+    }
+
+    async fn is_muted(pool: &MySqlPool, user_id: i32, chat_id: i32) -> sqlx::Result<bool> {
+        let muted = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM userchatmetadata WHERE user_id = ? AND chat_id = ? AND muted_until > NOW()",
+            user_id,
+            chat_id
+        )
+        .fetch_one(pool)
+        .await?;
+        Ok(muted > 0)
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_repeated_message_mutes_sender_and_alerts_moderators(
+        pool: MySqlPool,
+    ) -> sqlx::Result<()> {
+        let state = create_flood_state(&pool);
+        let (alice_tx, mut alice_rx) = create_signal_channel();
+        let (bob_tx, mut bob_rx) = create_signal_channel();
+        state.users_online.register_online(1, alice_tx);
+        state.users_online.register_online(2, bob_tx);
+
+        // Bob (MEMBER della chat 1) ripete lo stesso messaggio
+        for _ in 0..3 {
+            process_message(&state, 2, chat_message(1, 2, "Cheap watches!")).await;
+        }
+        assert!(is_muted(&pool, 2, 1).await?);
+
+        let bob_signals = drain(&mut bob_rx);
+        assert!(bob_signals.iter().any(|signal| matches!(
+            signal,
+            InternalSignal::ChatError {
+                chat_id: 1,
+                code: "FLOOD_MUTED",
+                ..
+            }
+        )));
+
+        // Alice è OWNER della chat 1: riceve l'avviso
+        let alert = drain(&mut alice_rx)
+            .into_iter()
+            .find_map(|signal| match signal {
+                InternalSignal::FloodAlert(alert) => Some(alert),
+                _ => None,
+            })
+            .expect("Owner must receive a flood alert");
+        assert_eq!(alert.chat_id, 1);
+        assert_eq!(alert.user_id, 2);
+        assert_eq!(alert.reason, FloodReason::RepeatedMessage);
+
+        // i messaggi successivi sono bloccati dal silenziamento
+        let rejection = deliver_message(&state, 2, chat_message(1, 2, "Something else"))
+            .await
+            .expect_err("Muted member cannot post");
+        assert_eq!(rejection.code(), "MUTED");
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_link_spam_mutes_sender(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_flood_state(&pool);
+
+        for link in ["https://a.example", "https://b.example", "www.c.example"] {
+            process_message(&state, 2, chat_message(1, 2, &format!("Visit {}", link))).await;
+        }
+        assert!(is_muted(&pool, 2, 1).await?);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_moderators_are_not_muted(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_flood_state(&pool);
+
+        // Alice è OWNER della chat 1
+        for _ in 0..3 {
+            process_message(&state, 1, chat_message(1, 1, "Reminder: meeting at 10")).await;
+        }
+        assert!(!is_muted(&pool, 1, 1).await?);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_join_leave_thrash_mutes_member(pool: MySqlPool) -> sqlx::Result<()> {
+        sqlx::query!(
+            "UPDATE chats SET is_public = TRUE, requires_approval = FALSE WHERE chat_id = 3"
+        )
+        .execute(&pool)
+        .await?;
+        let state = create_flood_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+        let (alice_tx, mut alice_rx) = create_signal_channel();
+        state.users_online.register_online(1, alice_tx);
+
+        // Bob entra, esce e rientra nella chat 3
+        for path in ["/chats/3/join", "/chats/3/leave", "/chats/3/join"] {
+            server
+                .post(path)
+                .add_header(
+                    HeaderName::from_static("authorization"),
+                    format!("Bearer {}", token),
+                )
+                .await
+                .assert_status_ok();
+        }
+        assert!(is_muted(&pool, 2, 3).await?);
+
+        assert!(drain(&mut alice_rx).iter().any(|signal| matches!(
+            signal,
+            InternalSignal::FloodAlert(alert) if alert.reason == FloodReason::JoinLeaveThrash
+        )));
+
+        Ok(())
+    }
+}
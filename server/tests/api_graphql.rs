@@ -0,0 +1,137 @@
+//! Integration tests per l'endpoint GraphQL
+
+mod common;
+
+#[cfg(test)]
+mod graphql_tests {
+    use super::common::*;
+    use axum_test::http::HeaderName;
+    use serde_json::json;
+    use sqlx::MySqlPool;
+
+    // ============================================================
+    // Test per POST /graphql - graphql_handler
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_graphql_chats_with_members_and_messages(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        sqlx::query!("UPDATE userchatmetadata SET messages_visible_from = NOW() - INTERVAL 1 HOUR WHERE user_id = 1")
+            .execute(&pool)
+            .await?;
+
+        let query = r#"{
+            me { username }
+            chats {
+                id
+                title
+                myRole
+                members { userId role user { username } }
+                messages(limit: 2) { id content sender { username } }
+            }
+        }"#;
+        let response = server
+            .post("/graphql")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "query": query }))
+            .await;
+
+        response.assert_status_ok();
+        let body: serde_json::Value = response.json();
+        assert!(
+            body["errors"].is_null(),
+            "Unexpected errors: {}",
+            body["errors"]
+        );
+        assert_eq!(body["data"]["me"]["username"], "alice");
+
+        let chats = body["data"]["chats"].as_array().unwrap();
+        assert_eq!(chats.len(), 3);
+        let general = chats.iter().find(|chat| chat["id"] == 1).unwrap();
+        assert_eq!(general["myRole"], "OWNER");
+
+        let mut members: Vec<_> = general["members"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|member| member["user"]["username"].as_str().unwrap())
+            .collect();
+        members.sort();
+        assert_eq!(members, vec!["alice", "bob", "charlie"]);
+
+        // i messaggi più recenti, con il mittente caricato dal DataLoader
+        let messages = general["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0]["id"], 3);
+        assert_eq!(messages[0]["sender"]["username"], "charlie");
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "invitations")))]
+    async fn test_graphql_chat_requires_membership(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        // Bob non è membro della chat 3
+        let response = server
+            .post("/graphql")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "query": "{ chat(id: 3) { id title } }" }))
+            .await;
+
+        response.assert_status_ok();
+        let body: serde_json::Value = response.json();
+        assert!(body["data"]["chat"].is_null());
+
+        // Charlie vede la chat dell'invito, ma non i suoi messaggi
+        let token = create_test_jwt(3, "charlie", &state.jwt_secret);
+        let query = r#"{
+            invitations { id invitedBy { username } chat { title messages { id } } }
+        }"#;
+        let response = server
+            .post("/graphql")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "query": query }))
+            .await;
+
+        response.assert_status_ok();
+        let body: serde_json::Value = response.json();
+        let invitation = &body["data"]["invitations"][0];
+        assert_eq!(invitation["id"], 1);
+        assert_eq!(invitation["invitedBy"]["username"], "bob");
+        assert_eq!(
+            body["errors"][0]["message"],
+            "You are not a member of this chat"
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_graphql_requires_authentication(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+
+        let response = server
+            .post("/graphql")
+            .json(&json!({ "query": "{ me { username } }" }))
+            .await;
+
+        response.assert_status_unauthorized();
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+//! Integration tests per le sonde di stato (healthz, livez, readyz)
+
+mod common;
+
+#[cfg(test)]
+mod health_tests {
+    use super::common::*;
+    use axum_test::http::StatusCode;
+    use server::config::AppProfile;
+    use server::core::AppState;
+    use sqlx::MySqlPool;
+    use std::sync::Arc;
+
+    // ============================================================
+    // Test per GET /healthz e GET /livez
+    // ============================================================
+
+    #[sqlx::test]
+    async fn test_healthz_returns_ok(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state);
+
+        let response = server.get("/healthz").await;
+
+        response.assert_status_ok();
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["status"], "ok");
+        assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(body["environment"], "development");
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_health_reports_profile(pool: MySqlPool) -> sqlx::Result<()> {
+        let jwt_secret = "ilmiobellissimosegretochevaassolutamentecambiato";
+        let state = Arc::new(
+            AppState::new(pool.clone(), jwt_secret.to_string())
+                .with_profile(AppProfile::Production, true),
+        );
+        let server = create_test_server(state);
+
+        for path in ["/healthz", "/livez", "/readyz"] {
+            let response = server.get(path).await;
+
+            response.assert_status_ok();
+            let body: serde_json::Value = response.json();
+            assert_eq!(body["environment"], "production");
+        }
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_livez_does_not_query_database(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state);
+
+        // con il pool chiuso il processo resta vivo: riavviarlo non servirebbe
+        pool.close().await;
+        let response = server.get("/livez").await;
+
+        response.assert_status_ok();
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["status"], "ok");
+
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per GET /readyz
+    // ============================================================
+
+    #[sqlx::test]
+    async fn test_readyz_ready_with_migrations_applied(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state);
+
+        let response = server.get("/readyz").await;
+
+        response.assert_status_ok();
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["status"], "ok");
+        let checks = body["checks"].as_array().unwrap();
+        assert_eq!(checks.len(), 2);
+        assert!(checks.iter().all(|check| check["status"] == "ok"));
+        assert!(checks.iter().all(|check| check.get("error").is_none()));
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_readyz_unavailable_with_pending_migration(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state);
+
+        // la tabella delle migrazioni non fa parte dello schema verificato da query!
+        sqlx::query("DELETE FROM _sqlx_migrations WHERE version = 31")
+            .execute(&pool)
+            .await?;
+
+        let response = server.get("/readyz").await;
+
+        response.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["status"], "unavailable");
+        assert_eq!(body["checks"][0]["name"], "database");
+        assert_eq!(body["checks"][0]["status"], "ok");
+        assert_eq!(body["checks"][1]["name"], "migrations");
+        assert_eq!(body["checks"][1]["status"], "unavailable");
+        assert_eq!(body["checks"][1]["error"], "pending migrations: [31]");
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_readyz_unavailable_without_database(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state);
+
+        pool.close().await;
+        let response = server.get("/readyz").await;
+
+        response.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["status"], "unavailable");
+        assert_eq!(body["checks"][0]["status"], "unavailable");
+        assert_eq!(body["checks"][0]["error"], "database unreachable");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,117 @@
+//! Integration tests per le richieste ripetute con header Idempotency-Key
+
+mod common;
+
+#[cfg(test)]
+mod idempotency_tests {
+    use super::common::*;
+    use axum_test::http::{HeaderName, StatusCode};
+    use serde_json::json;
+    use sqlx::MySqlPool;
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_create_chat_retry_is_replayed(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let body = json!({
+            "title": "Retried Chat",
+            "chat_type": "Group"
+        });
+
+        let mut chat_ids = Vec::new();
+        for replayed in [false, true] {
+            let response = server
+                .post("/chats")
+                .add_header(
+                    HeaderName::from_static("authorization"),
+                    format!("Bearer {}", token),
+                )
+                .add_header(HeaderName::from_static("idempotency-key"), "create-1")
+                .json(&body)
+                .await;
+            response.assert_status_ok();
+            assert_eq!(
+                response.maybe_header("idempotent-replayed").is_some(),
+                replayed
+            );
+            chat_ids.push(response.json::<serde_json::Value>()["chat_id"].clone());
+        }
+        assert_eq!(chat_ids[0], chat_ids[1]);
+
+        let count = sqlx::query_scalar!("SELECT COUNT(*) FROM chats WHERE title = 'Retried Chat'")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(count, 1);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_invite_retry_is_replayed(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        // senza chiave il secondo invito verrebbe rifiutato perché già esistente
+        for _ in 0..2 {
+            let response = server
+                .post("/chats/3/invite/2")
+                .add_header(
+                    HeaderName::from_static("authorization"),
+                    format!("Bearer {}", token),
+                )
+                .add_header(HeaderName::from_static("idempotency-key"), "invite-1")
+                .await;
+            response.assert_status_ok();
+        }
+
+        let count = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM invitations WHERE target_chat_id = 3 AND invited_id = 2"
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(count, 1);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_key_reused_with_different_body_rejected(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        for (title, status) in [
+            ("First Chat", StatusCode::OK),
+            ("Second Chat", StatusCode::UNPROCESSABLE_ENTITY),
+        ] {
+            let response = server
+                .post("/chats")
+                .add_header(
+                    HeaderName::from_static("authorization"),
+                    format!("Bearer {}", token),
+                )
+                .add_header(HeaderName::from_static("idempotency-key"), "create-1")
+                .json(&json!({ "title": title, "chat_type": "Group" }))
+                .await;
+            response.assert_status(status);
+        }
+
+        // la stessa chiave di un altro utente non è in conflitto
+        let bob = create_test_jwt(2, "bob", &state.jwt_secret);
+        let response = server
+            .post("/chats")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", bob),
+            )
+            .add_header(HeaderName::from_static("idempotency-key"), "create-1")
+            .json(&json!({ "title": "Second Chat", "chat_type": "Group" }))
+            .await;
+        response.assert_status_ok();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,333 @@
+//! Integration tests per i link di invito
+//!
+//! Test per:
+//! - GET/POST /chats/{chat_id}/invite_links
+//! - DELETE /chats/{chat_id}/invite_links/{link_id}
+//! - POST /invite_links/{invite_token}/join
+
+mod common;
+
+#[cfg(test)]
+mod invite_link_tests {
+    use super::common::*;
+    use axum_test::http::{HeaderName, StatusCode};
+    use serde_json::json;
+    use server::AppState;
+    use server::core::hash_refresh_token;
+    use server::repositories::Read;
+    use sqlx::MySqlPool;
+    use std::sync::Arc;
+
+    /// Crea un link di invito per la chat come l'utente del token e ritorna la risposta
+    async fn create_link(
+        server: &axum_test::TestServer,
+        token: &str,
+        chat_id: i32,
+    ) -> serde_json::Value {
+        let response = server
+            .post(&format!("/chats/{}/invite_links", chat_id))
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+        response.assert_status_ok();
+        response.json()
+    }
+
+    /// Entra nella chat del link come l'utente del token
+    async fn join(
+        server: &axum_test::TestServer,
+        token: &str,
+        invite_token: &str,
+    ) -> axum_test::TestResponse {
+        server
+            .post(&format!("/invite_links/{}/join", invite_token))
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await
+    }
+
+    // ============================================================
+    // Test per /chats/{chat_id}/invite_links - create, list, revoke
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_create_and_list_invite_links(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let alice_token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let link = create_link(&server, &alice_token, 3).await;
+        assert_eq!(link["chat_id"], 3);
+        assert_eq!(link["created_by"], 1);
+        assert!(link["token"].is_string());
+        assert!(link.get("expires_at").is_none());
+
+        // Il token non viene più mostrato
+        let response = server
+            .get("/chats/3/invite_links")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", alice_token),
+            )
+            .await;
+        response.assert_status_ok();
+        let links: Vec<serde_json::Value> = response.json();
+        assert_eq!(links.len(), 1);
+        assert!(links[0].get("token").is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_create_invite_link_follows_invite_policy(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let alice_token = create_test_jwt(1, "alice", &state.jwt_secret);
+        let bob_token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        // Con la policy di default (admins_only) Bob, semplice MEMBER, non può creare link
+        server
+            .post("/chats/1/invite_links")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", bob_token),
+            )
+            .await
+            .assert_status_forbidden();
+
+        // Con la policy everyone sì
+        sqlx::query!("UPDATE chats SET invite_policy = 'EVERYONE' WHERE chat_id = 1")
+            .execute(&pool)
+            .await?;
+        let link = create_link(&server, &bob_token, 1).await;
+        assert_eq!(link["created_by"], 2);
+
+        // Le chat private non hanno link di invito
+        server
+            .post("/chats/2/invite_links")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", alice_token),
+            )
+            .await
+            .assert_status_bad_request();
+
+        // La scadenza deve essere compresa tra 1 ora e 30 giorni
+        server
+            .post("/chats/1/invite_links")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", alice_token),
+            )
+            .json(&json!({ "expires_in_hours": 0 }))
+            .await
+            .assert_status_bad_request();
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_revoke_invite_link(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let alice_token = create_test_jwt(1, "alice", &state.jwt_secret);
+        let bob_token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        let link = create_link(&server, &alice_token, 1).await;
+        let link_id = link["link_id"].as_i64().unwrap();
+
+        // Bob, semplice MEMBER, non può revocare il link creato da Alice
+        server
+            .delete(&format!("/chats/1/invite_links/{}", link_id))
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", bob_token),
+            )
+            .await
+            .assert_status_forbidden();
+
+        server
+            .delete(&format!("/chats/1/invite_links/{}", link_id))
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", alice_token),
+            )
+            .await
+            .assert_status_ok();
+
+        // Il link revocato non esiste più
+        let token_hash = hash_refresh_token(link["token"].as_str().unwrap());
+        assert!(
+            state
+                .invite_link
+                .find_by_token_hash(&token_hash)
+                .await?
+                .is_none()
+        );
+
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per /invite_links/{invite_token}/join - join_by_invite_link
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_join_by_invite_link(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let alice_token = create_test_jwt(1, "alice", &state.jwt_secret);
+        let bob_token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        // Bob non è membro della chat 3 (Dev Team)
+        let link = create_link(&server, &alice_token, 3).await;
+        let invite_token = link["token"].as_str().unwrap();
+
+        let response = join(&server, &bob_token, invite_token).await;
+        response.assert_status_ok();
+        let chat: serde_json::Value = response.json();
+        assert_eq!(chat["chat_id"], 3);
+
+        let meta = state.meta.read(&(2, 3)).await?.unwrap();
+        assert_eq!(meta.user_role, Some(server::entities::UserRole::Member));
+
+        // L'ingresso è annunciato con un messaggio di sistema
+        let announced = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM messages WHERE chat_id = 3 AND sender_id = 2 AND message_type = 'SYSTEMMESSAGE'"
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(announced, 1);
+
+        // Il link resta valido per altri utenti, ma chi è già membro riceve CONFLICT
+        join(&server, &bob_token, invite_token)
+            .await
+            .assert_status(StatusCode::CONFLICT);
+
+        // Un token sconosciuto non corrisponde a nessun link
+        join(&server, &bob_token, "unknown")
+            .await
+            .assert_status_not_found();
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_join_by_expired_invite_link(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let bob_token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        let token_hash = hash_refresh_token("expiredtoken");
+        sqlx::query!(
+            "INSERT INTO invite_links (chat_id, token_hash, created_by, created_at, expires_at) VALUES (3, ?, 1, NOW() - INTERVAL 2 DAY, NOW() - INTERVAL 1 DAY)",
+            token_hash
+        )
+        .execute(&pool)
+        .await?;
+
+        join(&server, &bob_token, "expiredtoken")
+            .await
+            .assert_status(StatusCode::GONE);
+        assert!(state.meta.read(&(2, 3)).await?.is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_join_by_revoked_invite_link(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let alice_token = create_test_jwt(1, "alice", &state.jwt_secret);
+        let bob_token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        let link = create_link(&server, &alice_token, 3).await;
+        server
+            .delete(&format!("/chats/3/invite_links/{}", link["link_id"]))
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", alice_token),
+            )
+            .await
+            .assert_status_ok();
+
+        // Il token del link revocato non funziona più
+        join(&server, &bob_token, link["token"].as_str().unwrap())
+            .await
+            .assert_status_not_found();
+        assert!(state.meta.read(&(2, 3)).await?.is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_join_by_invite_link_banned(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let alice_token = create_test_jwt(1, "alice", &state.jwt_secret);
+        let bob_token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        state.ban.ban(&2, &3, &1).await?;
+        let link = create_link(&server, &alice_token, 3).await;
+
+        join(&server, &bob_token, link["token"].as_str().unwrap())
+            .await
+            .assert_status_forbidden();
+        assert!(state.meta.read(&(2, 3)).await?.is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_join_by_invite_link_full_group(pool: MySqlPool) -> sqlx::Result<()> {
+        // la chat 3 (Dev Team) ha già 2 membri
+        let jwt_secret = "ilmiobellissimosegretochevaassolutamentecambiato";
+        let state =
+            Arc::new(AppState::new(pool.clone(), jwt_secret.to_string()).with_max_group_members(2));
+        let server = create_test_server(state.clone());
+        let alice_token = create_test_jwt(1, "alice", &state.jwt_secret);
+        let bob_token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        let link = create_link(&server, &alice_token, 3).await;
+
+        let response = join(&server, &bob_token, link["token"].as_str().unwrap()).await;
+        response.assert_status(StatusCode::CONFLICT);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["error"], "Group is full");
+        assert!(state.meta.read(&(2, 3)).await?.is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_join_link_of_member_after_policy_change(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let bob_token = create_test_jwt(2, "bob", &state.jwt_secret);
+        let charlie_token = create_test_jwt(3, "charlie", &state.jwt_secret);
+
+        // Charlie esce dalla chat 1, Bob crea un link quando tutti possono invitare
+        sqlx::query!("DELETE FROM userchatmetadata WHERE user_id = 3 AND chat_id = 1")
+            .execute(&pool)
+            .await?;
+        sqlx::query!("UPDATE chats SET invite_policy = 'EVERYONE' WHERE chat_id = 1")
+            .execute(&pool)
+            .await?;
+        let link = create_link(&server, &bob_token, 1).await;
+
+        // Con la policy admins_only il link di un semplice MEMBER non vale più
+        sqlx::query!("UPDATE chats SET invite_policy = 'ADMINS_ONLY' WHERE chat_id = 1")
+            .execute(&pool)
+            .await?;
+        join(&server, &charlie_token, link["token"].as_str().unwrap())
+            .await
+            .assert_status_forbidden();
+        assert!(state.meta.read(&(3, 1)).await?.is_none());
+
+        Ok(())
+    }
+}
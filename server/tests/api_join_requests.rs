@@ -0,0 +1,264 @@
+//! Integration tests per l'ingresso nelle chat pubbliche e le richieste di accesso
+
+mod common;
+
+#[cfg(test)]
+mod join_request_tests {
+    use super::common::*;
+    use axum_test::http::HeaderName;
+    use server::ws::usermap::InternalSignal;
+    use sqlx::MySqlPool;
+
+    /// Rende pubblica la chat 3 (Dev Team: Alice OWNER, Charlie ADMIN)
+    async fn make_dev_team_public(pool: &MySqlPool, requires_approval: bool) -> sqlx::Result<()> {
+        sqlx::query!(
+            "UPDATE chats SET is_public = TRUE, requires_approval = ? WHERE chat_id = 3",
+            requires_approval
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn is_member(pool: &MySqlPool, user_id: i32, chat_id: i32) -> sqlx::Result<bool> {
+        let count = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM userchatmetadata WHERE user_id = ? AND chat_id = ?",
+            user_id,
+            chat_id
+        )
+        .fetch_one(pool)
+        .await?;
+        Ok(count > 0)
+    }
+
+    // ============================================================
+    // Test per POST /chats/{chat_id}/join - join_chat
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_join_open_public_chat(pool: MySqlPool) -> sqlx::Result<()> {
+        make_dev_team_public(&pool, false).await?;
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        let response = server
+            .post("/chats/3/join")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let request: serde_json::Value = response.json();
+        assert_eq!(request["state"], "Approved");
+        assert!(is_member(&pool, 2, 3).await?);
+
+        // Entrare di nuovo non è possibile
+        let response = server
+            .post("/chats/3/join")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_conflict();
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_join_non_public_chat(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        let response = server
+            .post("/chats/3/join")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_not_found();
+        assert!(!is_member(&pool, 2, 3).await?);
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per il flusso di approvazione
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_join_request_approved(pool: MySqlPool) -> sqlx::Result<()> {
+        make_dev_team_public(&pool, true).await?;
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let bob_token = create_test_jwt(2, "bob", &state.jwt_secret);
+        let charlie_token = create_test_jwt(3, "charlie", &state.jwt_secret);
+
+        let (charlie_tx, mut charlie_rx) = create_signal_channel();
+        state.users_online.register_online(3, charlie_tx);
+        let (bob_tx, mut bob_rx) = create_signal_channel();
+        state.users_online.register_online(2, bob_tx);
+
+        let response = server
+            .post("/chats/3/join")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", bob_token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let request: serde_json::Value = response.json();
+        assert_eq!(request["state"], "Pending");
+        let request_id = request["request_id"].as_i64().unwrap();
+        assert!(!is_member(&pool, 2, 3).await?);
+
+        // Charlie (ADMIN) riceve la notifica della richiesta
+        match charlie_rx.try_recv() {
+            Ok(InternalSignal::JoinRequest(request)) => {
+                assert_eq!(request.request_id as i64, request_id);
+                assert_eq!(
+                    request.user.and_then(|user| user.username).as_deref(),
+                    Some("bob")
+                );
+            }
+            _ => panic!("Expected JoinRequest signal"),
+        }
+
+        // Una seconda richiesta mentre la prima è pending viene rifiutata
+        server
+            .post("/chats/3/join")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", bob_token),
+            )
+            .await
+            .assert_status_conflict();
+
+        let response = server
+            .get("/chats/3/join_requests")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", charlie_token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let requests: Vec<serde_json::Value> = response.json();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0]["user"]["username"], "bob");
+
+        let response = server
+            .post(&format!("/chats/3/join_requests/{}/approve", request_id))
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", charlie_token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let request: serde_json::Value = response.json();
+        assert_eq!(request["state"], "Approved");
+        assert!(is_member(&pool, 2, 3).await?);
+
+        // Bob viene aggiunto alla chat e riceve l'esito
+        match bob_rx.try_recv() {
+            Ok(InternalSignal::ChatJoined(chat)) => assert_eq!(chat.chat_id, Some(3)),
+            _ => panic!("Expected ChatJoined signal"),
+        }
+        match bob_rx.try_recv() {
+            Ok(InternalSignal::JoinRequestResolved(request)) => {
+                assert_eq!(request.request_id as i64, request_id);
+            }
+            _ => panic!("Expected JoinRequestResolved signal"),
+        }
+
+        // La richiesta non può essere decisa di nuovo
+        server
+            .post(&format!("/chats/3/join_requests/{}/deny", request_id))
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", charlie_token),
+            )
+            .await
+            .assert_status_conflict();
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_join_request_denied(pool: MySqlPool) -> sqlx::Result<()> {
+        make_dev_team_public(&pool, true).await?;
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let bob_token = create_test_jwt(2, "bob", &state.jwt_secret);
+        let alice_token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .post("/chats/3/join")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", bob_token),
+            )
+            .await;
+        let request: serde_json::Value = response.json();
+        let request_id = request["request_id"].as_i64().unwrap();
+
+        let response = server
+            .post(&format!("/chats/3/join_requests/{}/deny", request_id))
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", alice_token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let request: serde_json::Value = response.json();
+        assert_eq!(request["state"], "Denied");
+        assert!(!is_member(&pool, 2, 3).await?);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_join_requests_not_admin(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        // Bob è solo MEMBER della chat 1
+        let response = server
+            .get("/chats/1/join_requests")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_forbidden();
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_join_request_invalid_action(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .post("/chats/3/join_requests/1/maybe")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_bad_request();
+        Ok(())
+    }
+}
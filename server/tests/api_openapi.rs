@@ -0,0 +1,38 @@
+//! Integration tests per la specifica OpenAPI e la Swagger UI
+
+mod common;
+
+#[cfg(test)]
+mod openapi_tests {
+    use super::common::*;
+    use sqlx::MySqlPool;
+
+    #[sqlx::test]
+    async fn test_openapi_json_served_without_auth(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state);
+
+        let response = server.get("/openapi.json").await;
+
+        response.assert_status_ok();
+        let spec: serde_json::Value = response.json();
+        assert!(spec["openapi"].as_str().unwrap().starts_with("3."));
+        assert!(spec["paths"]["/api/v1/chats/{chat_id}/members"]["get"].is_object());
+        assert!(spec["paths"]["/api/v1/webhooks/{webhook_token}"]["post"].is_object());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_swagger_ui_served(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state);
+
+        let response = server.get("/docs/").await;
+
+        response.assert_status_ok();
+        assert!(response.text().contains("swagger"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,407 @@
+//! Integration tests per le segnalazioni di messaggi e utenti e la coda degli amministratori
+
+mod common;
+
+#[cfg(test)]
+mod report_tests {
+    use super::common::*;
+    use axum_test::http::{HeaderName, StatusCode};
+    use serde_json::json;
+    use server::core::{AppState, ServerRole, encode_jwt};
+    use sqlx::MySqlPool;
+
+    /// Token di charlie con il ruolo di amministratore del server
+    fn create_admin_jwt(state: &AppState) -> String {
+        encode_jwt(
+            &"charlie".to_string(),
+            3,
+            ServerRole::ServerAdmin,
+            &state.jwt_keys,
+        )
+        .expect("Encoding JWT must succeed")
+    }
+
+    /// Inserisce una segnalazione pending di bob sul messaggio 1 di alice (chat 1)
+    async fn insert_message_report(pool: &MySqlPool) -> sqlx::Result<i32> {
+        let result = sqlx::query!(
+            "INSERT INTO reports (reporter_id, reported_user_id, chat_id, message_id, reason, created_at) VALUES (2, 1, 1, 1, 'SPAM', NOW())"
+        )
+        .execute(pool)
+        .await?;
+        Ok(result.last_insert_id() as i32)
+    }
+
+    // ============================================================
+    // Test per POST /chats/{chat_id}/messages/{message_id}/report
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_report_message(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        let response = server
+            .post("/chats/1/messages/1/report")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "reason": "spam", "details": "Link pubblicitari" }))
+            .await;
+
+        response.assert_status_ok();
+        let report: serde_json::Value = response.json();
+        assert_eq!(report["reporter_id"], 2);
+        assert_eq!(report["reported_user_id"], 1);
+        assert_eq!(report["message_id"], 1);
+        assert_eq!(report["reason"], "spam");
+        assert_eq!(report["state"], "Pending");
+
+        // una seconda segnalazione dello stesso messaggio è rifiutata finché la prima è aperta
+        let response = server
+            .post("/chats/1/messages/1/report")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "reason": "harassment" }))
+            .await;
+
+        response.assert_status_conflict();
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_report_own_message(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .post("/chats/1/messages/1/report")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "reason": "spam" }))
+            .await;
+
+        response.assert_status_bad_request();
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_report_message_of_another_chat(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        // il messaggio 6 è della chat 3, di cui bob non è membro
+        let response = server
+            .post("/chats/1/messages/6/report")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "reason": "spam" }))
+            .await;
+
+        response.assert_status(StatusCode::NOT_FOUND);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_report_message_not_member(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        let response = server
+            .post("/chats/3/messages/6/report")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "reason": "spam" }))
+            .await;
+
+        response.assert_status_forbidden();
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_report_message_details_too_long(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        let response = server
+            .post("/chats/1/messages/1/report")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "reason": "other", "details": "a".repeat(501) }))
+            .await;
+
+        response.assert_status_bad_request();
+
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per POST /users/{user_id}/report
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_report_user(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        let response = server
+            .post("/users/1/report")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "reason": "impersonation" }))
+            .await;
+
+        response.assert_status_ok();
+        let report: serde_json::Value = response.json();
+        assert_eq!(report["reported_user_id"], 1);
+        assert!(report.get("message_id").is_none());
+        assert_eq!(report["reason"], "impersonation");
+
+        let response = server
+            .post("/users/1/report")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "reason": "spam" }))
+            .await;
+
+        response.assert_status_conflict();
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_report_user_invalid_target(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        let response = server
+            .post("/users/2/report")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "reason": "spam" }))
+            .await;
+
+        response.assert_status_bad_request();
+
+        let response = server
+            .post("/users/999/report")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "reason": "spam" }))
+            .await;
+
+        response.assert_status(StatusCode::NOT_FOUND);
+
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per GET /admin/reports e POST /admin/reports/{report_id}/{action}
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_admin_list_reports(pool: MySqlPool) -> sqlx::Result<()> {
+        let report_id = insert_message_report(&pool).await?;
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_admin_jwt(&state);
+
+        let response = server
+            .get("/admin/reports")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let reports: Vec<serde_json::Value> = response.json();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0]["report_id"], report_id);
+        assert_eq!(reports[0]["message"]["content"], "Hello everyone!");
+
+        let response = server
+            .get("/admin/reports?state=Dismissed")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let reports: Vec<serde_json::Value> = response.json();
+        assert!(reports.is_empty());
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_admin_list_reports_forbidden_without_role(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .get("/admin/reports")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_forbidden();
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_admin_dismiss_report(pool: MySqlPool) -> sqlx::Result<()> {
+        let report_id = insert_message_report(&pool).await?;
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_admin_jwt(&state);
+
+        let response = server
+            .post(&format!("/admin/reports/{}/dismiss", report_id))
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let report: serde_json::Value = response.json();
+        assert_eq!(report["state"], "Dismissed");
+        assert_eq!(report["resolved_by"], 3);
+
+        // il messaggio segnalato resta visibile
+        let deleted_at =
+            sqlx::query_scalar!("SELECT deleted_at FROM messages WHERE message_id = 1")
+                .fetch_one(&pool)
+                .await?;
+        assert!(deleted_at.is_none());
+
+        // una segnalazione chiusa non può essere chiusa di nuovo
+        let response = server
+            .post(&format!("/admin/reports/{}/resolve", report_id))
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_conflict();
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_admin_resolve_report_deleting_message(pool: MySqlPool) -> sqlx::Result<()> {
+        let report_id = insert_message_report(&pool).await?;
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_admin_jwt(&state);
+
+        let response = server
+            .post(&format!("/admin/reports/{}/delete_message", report_id))
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let report: serde_json::Value = response.json();
+        assert_eq!(report["state"], "Resolved");
+
+        let deleted_at =
+            sqlx::query_scalar!("SELECT deleted_at FROM messages WHERE message_id = 1")
+                .fetch_one(&pool)
+                .await?;
+        assert!(deleted_at.is_some());
+
+        let audit_count = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM audit_log WHERE chat_id = 1 AND action = 'MESSAGE_DELETED' AND actor_id = 3"
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(audit_count, 1);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_admin_resolve_report_invalid_action(pool: MySqlPool) -> sqlx::Result<()> {
+        let result = sqlx::query!(
+            "INSERT INTO reports (reporter_id, reported_user_id, reason, created_at) VALUES (2, 1, 'HARASSMENT', NOW())"
+        )
+        .execute(&pool)
+        .await?;
+        let report_id = result.last_insert_id() as i32;
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_admin_jwt(&state);
+
+        let response = server
+            .post(&format!("/admin/reports/{}/ban", report_id))
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_bad_request();
+
+        // la segnalazione di un utente non ha un messaggio da eliminare
+        let response = server
+            .post(&format!("/admin/reports/{}/delete_message", report_id))
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_bad_request();
+
+        let response = server
+            .post("/admin/reports/999/resolve")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status(StatusCode::NOT_FOUND);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,411 @@
+//! Integration tests per i ruoli delle chat, base e personalizzati
+
+mod common;
+
+#[cfg(test)]
+mod role_tests {
+    use super::common::*;
+    use axum_test::http::{HeaderName, StatusCode};
+    use serde_json::json;
+    use sqlx::MySqlPool;
+
+    // ============================================================
+    // Test per /chats/{chat_id}/roles - create_chat_role, list_chat_roles
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_create_and_list_roles(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let alice_token = create_test_jwt(1, "alice", &state.jwt_secret);
+        let bob_token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        // Alice (OWNER) crea un ruolo nella chat 1
+        let response = server
+            .post("/chats/1/roles")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", alice_token),
+            )
+            .json(&json!({ "name": "  Moderator ", "permissions": ["MODERATE_MEMBERS", "PIN_MESSAGES"] }))
+            .await;
+
+        response.assert_status_ok();
+        let role: serde_json::Value = response.json();
+        assert_eq!(role["name"], "Moderator");
+        assert_eq!(role["chat_id"], 1);
+        assert_eq!(
+            role["permissions"],
+            json!(["MODERATE_MEMBERS", "PIN_MESSAGES"])
+        );
+
+        // Tutti i membri vedono i ruoli della chat, prima i tre ruoli base
+        let response = server
+            .get("/chats/1/roles")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", bob_token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let roles: Vec<serde_json::Value> = response.json();
+        assert_eq!(roles.len(), 4);
+        assert_eq!(roles[0]["base_role"], "Owner");
+        assert_eq!(roles[2]["base_role"], "Member");
+        assert_eq!(roles[3]["role_id"], role["role_id"]);
+        assert!(roles[3].get("base_role").is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_create_role_not_owner(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(3, "charlie", &state.jwt_secret);
+
+        // Charlie è ADMIN della chat 3, ma solo l'Owner definisce i ruoli
+        let response = server
+            .post("/chats/3/roles")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "name": "Helper", "permissions": [] }))
+            .await;
+
+        response.assert_status_forbidden();
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_create_role_duplicate_name(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        for expected in [StatusCode::OK, StatusCode::CONFLICT] {
+            let response = server
+                .post("/chats/1/roles")
+                .add_header(
+                    HeaderName::from_static("authorization"),
+                    format!("Bearer {}", token),
+                )
+                .json(&json!({ "name": "Helper" }))
+                .await;
+
+            response.assert_status(expected);
+        }
+
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per l'applicazione dei permessi dei ruoli personalizzati
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_custom_role_grants_permission(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let alice_token = create_test_jwt(1, "alice", &state.jwt_secret);
+        let bob_token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        // Senza ruolo personalizzato Bob (MEMBER) non può fissare messaggi
+        let response = server
+            .post("/chats/1/messages/1/pin")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", bob_token),
+            )
+            .await;
+        response.assert_status_forbidden();
+
+        let response = server
+            .post("/chats/1/roles")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", alice_token),
+            )
+            .json(&json!({ "name": "Curator", "permissions": ["PIN_MESSAGES"] }))
+            .await;
+        let role: serde_json::Value = response.json();
+
+        let response = server
+            .put("/chats/1/members/2/custom_role")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", alice_token),
+            )
+            .json(&json!({ "role_id": role["role_id"] }))
+            .await;
+        response.assert_status_ok();
+        let member: serde_json::Value = response.json();
+        assert_eq!(member["role_id"], role["role_id"]);
+        assert_eq!(member["user_role"], "Member");
+
+        // Con il ruolo Curator Bob può fissare messaggi
+        let response = server
+            .post("/chats/1/messages/1/pin")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", bob_token),
+            )
+            .await;
+        response.assert_status_ok();
+
+        // ma non ottiene gli altri permessi da Admin
+        let response = server
+            .post("/chats/1/members/3/mute?duration=60")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", bob_token),
+            )
+            .await;
+        response.assert_status_forbidden();
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_custom_role_cannot_act_on_admins(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let alice_token = create_test_jwt(1, "alice", &state.jwt_secret);
+        let bob_token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        sqlx::query!(
+            "UPDATE userchatmetadata SET user_role = 'ADMIN', role_id = 2 WHERE user_id = 3 AND chat_id = 1"
+        )
+        .execute(&pool)
+        .await?;
+
+        let response = server
+            .post("/chats/1/roles")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", alice_token),
+            )
+            .json(&json!({ "name": "Moderator", "permissions": ["MODERATE_MEMBERS"] }))
+            .await;
+        let role: serde_json::Value = response.json();
+
+        server
+            .put("/chats/1/members/2/custom_role")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", alice_token),
+            )
+            .json(&json!({ "role_id": role["role_id"] }))
+            .await
+            .assert_status_ok();
+
+        // Bob (MEMBER con ruolo Moderator) non può silenziare Charlie (ADMIN)
+        let response = server
+            .post("/chats/1/members/3/mute?duration=60")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", bob_token),
+            )
+            .await;
+        response.assert_status_forbidden();
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_assign_role_from_other_chat(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        // ruolo definito nella chat 3
+        let response = server
+            .post("/chats/3/roles")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "name": "Helper", "permissions": ["INVITE_MEMBERS"] }))
+            .await;
+        let role: serde_json::Value = response.json();
+
+        // non può essere assegnato a un membro della chat 1
+        let response = server
+            .put("/chats/1/members/2/custom_role")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "role_id": role["role_id"] }))
+            .await;
+
+        response.assert_status_not_found();
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_delete_role(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .post("/chats/1/roles")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "name": "Helper", "permissions": ["INVITE_MEMBERS"] }))
+            .await;
+        let role: serde_json::Value = response.json();
+        let role_id = role["role_id"].as_i64().unwrap();
+
+        server
+            .put("/chats/1/members/2/custom_role")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "role_id": role_id }))
+            .await
+            .assert_status_ok();
+
+        let response = server
+            .delete(&format!("/chats/1/roles/{}", role_id))
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+        response.assert_status_ok();
+
+        // Bob resta membro e torna al ruolo base Member (riga 3 della chat 1)
+        let role_of_bob = sqlx::query_scalar!(
+            "SELECT role_id FROM userchatmetadata WHERE user_id = 2 AND chat_id = 1"
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(role_of_bob, Some(3));
+
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per i ruoli base Owner/Admin/Member
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_base_roles_cannot_be_deleted_or_assigned(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        // La riga 3 è il ruolo base Member della chat 1
+        server
+            .delete("/chats/1/roles/3")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await
+            .assert_status_bad_request();
+
+        // La riga 2 è il ruolo base Admin: si ottiene solo con la promozione
+        server
+            .put("/chats/1/members/2/custom_role")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "role_id": 2 }))
+            .await
+            .assert_status_bad_request();
+
+        // Il nome dei ruoli base è riservato
+        server
+            .post("/chats/1/roles")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "name": "Admin" }))
+            .await
+            .assert_status(StatusCode::CONFLICT);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_custom_role_only_for_members(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .post("/chats/3/roles")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "name": "Helper", "permissions": ["INVITE_MEMBERS"] }))
+            .await;
+        let role: serde_json::Value = response.json();
+
+        // Charlie è ADMIN della chat 3: un ruolo personalizzato sostituirebbe il suo ruolo base
+        server
+            .put("/chats/3/members/3/custom_role")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "role_id": role["role_id"] }))
+            .await
+            .assert_status_bad_request();
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_permissions_follow_base_role_rows(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let alice_token = create_test_jwt(1, "alice", &state.jwt_secret);
+        let bob_token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        // I permessi di Bob (MEMBER) vengono dalla riga base Member della chat 1
+        sqlx::query!("UPDATE chat_roles SET can_pin_messages = 1 WHERE role_id = 3")
+            .execute(&pool)
+            .await?;
+
+        server
+            .post("/chats/1/messages/1/pin")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", bob_token),
+            )
+            .await
+            .assert_status_ok();
+
+        // Promosso ad ADMIN, Bob passa alla riga base Admin
+        server
+            .patch("/chats/1/members/2/role")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", alice_token),
+            )
+            .json(&"Admin")
+            .await
+            .assert_status_ok();
+
+        let role_of_bob = sqlx::query_scalar!(
+            "SELECT role_id FROM userchatmetadata WHERE user_id = 2 AND chat_id = 1"
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(role_of_bob, Some(2));
+
+        Ok(())
+    }
+}
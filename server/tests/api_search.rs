@@ -0,0 +1,109 @@
+//! Integration tests per l'endpoint di ricerca globale
+
+mod common;
+
+#[cfg(test)]
+mod search_tests {
+    use super::common::*;
+    use axum_test::http::HeaderName;
+    use sqlx::MySqlPool;
+
+    // ============================================================
+    // Test per GET /search - global_search
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_global_search_groups_results(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        sqlx::query!("UPDATE userchatmetadata SET messages_visible_from = NOW() - INTERVAL 1 HOUR WHERE user_id = 1")
+            .execute(&pool)
+            .await?;
+
+        // "meeting" compare nel messaggio 6 della chat Dev Team
+        let response = server
+            .get("/search?q=meeting")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let result: serde_json::Value = response.json();
+        let messages = result["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0]["message"]["message_id"], 6);
+        assert!(result["chats"].as_array().unwrap().is_empty());
+        assert!(result["users"].as_array().unwrap().is_empty());
+
+        // "Dev" trova la chat per titolo
+        let response = server
+            .get("/search?q=Dev")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let result: serde_json::Value = response.json();
+        let chats = result["chats"].as_array().unwrap();
+        assert_eq!(chats.len(), 1);
+        assert_eq!(chats[0]["chat_id"], 3);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_global_search_only_accessible_chats(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        // Bob non è membro della chat Dev Team
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        sqlx::query!("UPDATE userchatmetadata SET messages_visible_from = NOW() - INTERVAL 1 HOUR")
+            .execute(&pool)
+            .await?;
+
+        let response = server
+            .get("/search?q=meeting")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let result: serde_json::Value = response.json();
+        assert!(result["messages"].as_array().unwrap().is_empty());
+
+        // L'utente corrente non compare tra i risultati utenti
+        let response = server
+            .get("/search?q=bob")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let result: serde_json::Value = response.json();
+        assert!(result["users"].as_array().unwrap().is_empty());
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_global_search_without_token(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+
+        let response = server.get("/search?q=test").await;
+
+        response.assert_status_forbidden();
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+//! Integration tests per la sincronizzazione incrementale
+
+mod common;
+
+#[cfg(test)]
+mod sync_tests {
+    use super::common::*;
+    use axum_test::http::HeaderName;
+    use server::dtos::UpdateInvitationDTO;
+    use server::entities::InvitationStatus;
+    use server::repositories::Update;
+    use sqlx::MySqlPool;
+
+    fn message_ids(chat: &serde_json::Value) -> Vec<i64> {
+        chat["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| m["message_id"].as_i64().unwrap())
+            .collect()
+    }
+
+    // ============================================================
+    // Test per GET /sync - sync_changes
+    // ============================================================
+
+    #[sqlx::test(fixtures(
+        path = "../fixtures",
+        scripts("users", "chats", "messages", "invitations")
+    ))]
+    async fn test_sync_initial_then_incremental(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+        let auth = format!("Bearer {}", token);
+
+        // membership e messaggi delle fixture risalgono a prima della sincronizzazione
+        sqlx::query!(
+            "UPDATE userchatmetadata SET messages_visible_from = NOW() - INTERVAL 1 HOUR, member_since = NOW() - INTERVAL 1 HOUR"
+        )
+        .execute(&pool)
+        .await?;
+
+        // Prima sincronizzazione: tutte le chat di Bob con i loro messaggi
+        let response = server
+            .get("/sync")
+            .add_header(HeaderName::from_static("authorization"), auth.clone())
+            .await;
+
+        response.assert_status_ok();
+        let first: serde_json::Value = response.json();
+        assert_eq!(first["chat_ids"], serde_json::json!([1, 2]));
+        assert_eq!(first["joined_chats"].as_array().unwrap().len(), 2);
+        let messages = first["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(message_ids(&messages[0]), vec![1, 2, 3]);
+        assert_eq!(message_ids(&messages[1]), vec![4, 5]);
+        assert_eq!(messages[0]["truncated"], false);
+        // l'unico invito ricevuto da Bob è già stato accettato
+        assert!(first["received_invitations"].as_array().unwrap().is_empty());
+        assert!(first["answered_invitations"].as_array().unwrap().is_empty());
+
+        // Modifiche dopo il token: un messaggio nuovo, Bob entra nel Dev Team e Charlie
+        // rifiuta l'invito di Bob
+        sqlx::query!(
+            "INSERT INTO messages (message_id, chat_id, sender_id, content, message_type, created_at) VALUES (8, 2, 1, 'Still there?', 'USERMESSAGE', NOW())"
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query!(
+            "INSERT INTO userchatmetadata (user_id, chat_id, messages_visible_from, messages_received_until, user_role, member_since) VALUES (2, 3, NOW(), NOW(), 'MEMBER', NOW())"
+        )
+        .execute(&pool)
+        .await?;
+        state
+            .invitation
+            .update(
+                &1,
+                &UpdateInvitationDTO {
+                    state: Some(InvitationStatus::Rejected),
+                },
+            )
+            .await?;
+
+        let since = first["sync_token"].as_str().unwrap();
+        let response = server
+            .get(&format!("/sync?since={}", since))
+            .add_header(HeaderName::from_static("authorization"), auth)
+            .await;
+
+        response.assert_status_ok();
+        let second: serde_json::Value = response.json();
+        assert_eq!(second["chat_ids"], serde_json::json!([1, 2, 3]));
+        let joined = second["joined_chats"].as_array().unwrap();
+        assert_eq!(joined.len(), 1);
+        assert_eq!(joined[0]["chat_id"], 3);
+        // i messaggi del Dev Team precedono l'ingresso di Bob
+        let messages = second["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0]["chat_id"], 2);
+        assert_eq!(message_ids(&messages[0]), vec![8]);
+        let answered = second["answered_invitations"].as_array().unwrap();
+        assert_eq!(answered.len(), 1);
+        assert_eq!(answered[0]["invite_id"], 1);
+        assert_ne!(second["sync_token"], first["sync_token"]);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_sync_rejects_invalid_token(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .get("/sync?since=not-a-token")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_bad_request();
+
+        Ok(())
+    }
+}
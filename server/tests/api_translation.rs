@@ -0,0 +1,132 @@
+//! Integration tests per la traduzione dei messaggi
+
+mod common;
+
+#[cfg(test)]
+mod translation_tests {
+    use super::common::*;
+    use axum_test::http::HeaderName;
+    use futures_util::future::BoxFuture;
+    use server::core::AppState;
+    use server::services::translation::{Translation, TranslationProvider};
+    use sqlx::MySqlPool;
+    use std::sync::Arc;
+
+    /// Provider finto: "traduce" mettendo il testo in maiuscolo
+    struct UppercaseTranslator;
+
+    impl TranslationProvider for UppercaseTranslator {
+        fn translate<'a>(
+            &'a self,
+            text: &'a str,
+            _target_lang: &'a str,
+        ) -> BoxFuture<'a, Result<Translation, String>> {
+            Box::pin(async move {
+                Ok(Translation {
+                    text: text.to_uppercase(),
+                    source_lang: Some("en".to_string()),
+                })
+            })
+        }
+    }
+
+    fn create_translating_state(pool: &MySqlPool) -> Arc<AppState> {
+        let jwt_secret = "ilmiobellissimosegretochevaassolutamentecambiato";
+        Arc::new(
+            AppState::new(pool.clone(), jwt_secret.to_string())
+                .with_translator(Arc::new(UppercaseTranslator)),
+        )
+    }
+
+    // ============================================================
+    // Test per GET /chats/{chat_id}/messages/{message_id}/translate
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_translate_message_success(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_translating_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        sqlx::query!("UPDATE userchatmetadata SET messages_visible_from = NOW() - INTERVAL 1 HOUR WHERE user_id = 1")
+            .execute(&pool)
+            .await?;
+
+        let response = server
+            .get("/chats/1/messages/1/translate?lang=it")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let result: serde_json::Value = response.json();
+        assert_eq!(result["message"]["message_id"], 1);
+        assert_eq!(result["message"]["content"], "Hello everyone!");
+        assert_eq!(result["target_lang"], "it");
+        assert_eq!(result["source_lang"], "en");
+        assert_eq!(result["translated_content"], "HELLO EVERYONE!");
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_translate_message_not_configured(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .get("/chats/1/messages/1/translate?lang=it")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_service_unavailable();
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_translate_message_invalid_lang(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_translating_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .get("/chats/1/messages/1/translate?lang=not-a-language")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_bad_request();
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_translate_message_wrong_chat(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_translating_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        sqlx::query!("UPDATE userchatmetadata SET messages_visible_from = NOW() - INTERVAL 1 HOUR WHERE user_id = 1")
+            .execute(&pool)
+            .await?;
+
+        // il messaggio 4 appartiene alla chat 2
+        let response = server
+            .get("/chats/1/messages/4/translate?lang=it")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_not_found();
+        Ok(())
+    }
+}
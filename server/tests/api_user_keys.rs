@@ -0,0 +1,175 @@
+//! Integration tests per lo scambio delle chiavi della cifratura end-to-end
+
+mod common;
+
+#[cfg(test)]
+mod user_keys_tests {
+    use super::common::*;
+    use axum_test::http::{HeaderName, StatusCode};
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+    use serde_json::json;
+    use sqlx::MySqlPool;
+
+    fn key(byte: u8) -> String {
+        STANDARD.encode([byte; 32])
+    }
+
+    fn upload_body(identity: u8, prekey_ids: &[i32]) -> serde_json::Value {
+        json!({
+            "identity_key": key(identity),
+            "signed_prekey": {
+                "key_id": 1,
+                "public_key": key(100),
+                "signature": STANDARD.encode([7u8; 64])
+            },
+            "one_time_prekeys": prekey_ids
+                .iter()
+                .map(|&id| json!({ "key_id": id, "public_key": key(id as u8) }))
+                .collect::<Vec<_>>()
+        })
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_fetch_bundle_consumes_one_time_prekeys(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let alice = create_test_jwt(1, "alice", &state.jwt_secret);
+        let bob = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        let response = server
+            .put("/users/1/keys")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", alice),
+            )
+            .json(&upload_body(1, &[1, 2]))
+            .await;
+        response.assert_status_ok();
+        assert_eq!(response.json::<serde_json::Value>()["one_time_prekeys"], 2);
+
+        // ogni richiesta riceve una one-time prekey diversa, poi non ne restano
+        for expected in [
+            json!({ "key_id": 1, "public_key": key(1) }),
+            json!({ "key_id": 2, "public_key": key(2) }),
+            serde_json::Value::Null,
+        ] {
+            let response = server
+                .get("/users/1/keys")
+                .add_header(
+                    HeaderName::from_static("authorization"),
+                    format!("Bearer {}", bob),
+                )
+                .await;
+            response.assert_status_ok();
+            let bundle: serde_json::Value = response.json();
+            assert_eq!(bundle["user_id"], 1);
+            assert_eq!(bundle["identity_key"], key(1));
+            assert_eq!(bundle["signed_prekey"]["public_key"], key(100));
+            assert_eq!(bundle["one_time_prekey"], expected);
+        }
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_upload_keys_for_another_user_forbidden(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        let response = server
+            .put("/users/1/keys")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&upload_body(1, &[1]))
+            .await;
+        response.assert_status(StatusCode::FORBIDDEN);
+
+        let stored = sqlx::query_scalar!("SELECT COUNT(*) FROM user_keys")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(stored, 0);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_upload_invalid_key_rejected(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let mut body = upload_body(1, &[1]);
+        body["signed_prekey"]["signature"] = json!(STANDARD.encode([7u8; 10]));
+
+        let response = server
+            .put("/users/1/keys")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&body)
+            .await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_fetch_bundle_without_keys_not_found(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .get("/users/2/keys")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+        response.assert_status(StatusCode::NOT_FOUND);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_new_identity_key_discards_old_prekeys(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        for (identity, prekeys, expected) in [(1, vec![1, 2], 2), (1, vec![3], 3), (2, vec![4], 1)]
+        {
+            let response = server
+                .put("/users/1/keys")
+                .add_header(
+                    HeaderName::from_static("authorization"),
+                    format!("Bearer {}", token),
+                )
+                .json(&upload_body(identity, &prekeys))
+                .await;
+            response.assert_status_ok();
+            assert_eq!(
+                response.json::<serde_json::Value>()["one_time_prekeys"],
+                expected
+            );
+        }
+
+        let bundle: serde_json::Value = server
+            .get("/users/1/keys")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await
+            .json();
+        assert_eq!(bundle["identity_key"], key(2));
+        assert_eq!(bundle["one_time_prekey"]["key_id"], 4);
+
+        Ok(())
+    }
+}
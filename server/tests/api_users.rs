@@ -3,7 +3,12 @@
 //! Test per:
 //! - GET /users?search=username
 //! - GET /users/{user_id}
-//! - DELETE /users/me
+//! - DELETE /users/me, DELETE /users/me?mode=deactivate
+//! - GET/PATCH /users/me/profile
+//! - PUT /users/me/status
+//! - GET/PUT /users/me/privacy
+//! - GET /users/me/sessions, DELETE /users/me/sessions/{connection_id}
+//! - POST/GET /users/me/export, GET /users/me/export/download
 
 mod common;
 
@@ -13,6 +18,7 @@ mod user_tests {
     use axum_test::http::HeaderName;
     use serde_json::json;
     use server::repositories::Read;
+    use server::ws::usermap::{ConnectionInfo, InternalSignal};
     use sqlx::MySqlPool;
 
     // ============================================================
@@ -448,4 +454,706 @@ mod user_tests {
 
         Ok(())
     }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_deleted_account_token_not_reused_by_new_owner_of_username(
+        pool: MySqlPool,
+    ) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+        let auth = || {
+            (
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+        };
+
+        let (name, value) = auth();
+        server
+            .delete("/users/me")
+            .add_header(name, value)
+            .await
+            .assert_status_ok();
+
+        // lo username liberato dalla cancellazione viene preso da un altro utente
+        server
+            .post("/auth/register")
+            .json(&json!({"username": "bob", "password": "NewBob1234"}))
+            .await
+            .assert_status_ok();
+
+        // il vecchio token di bob non vale né per lui né per il nuovo "bob"
+        let (name, value) = auth();
+        server
+            .get("/users/me/profile")
+            .add_header(name, value)
+            .await
+            .assert_status_unauthorized();
+
+        // anche senza revoca, un token con id e username che non coincidono è rifiutato
+        let forged = create_test_jwt(1, "bob", &state.jwt_secret);
+        server
+            .get("/users/me/profile")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", forged),
+            )
+            .await
+            .assert_status_unauthorized();
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_deactivate_account_anonymizes_user(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let bob_token = create_test_jwt(2, "bob", &state.jwt_secret);
+        let alice_token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        server
+            .delete("/users/me?mode=deactivate")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", bob_token),
+            )
+            .await
+            .assert_status_ok();
+
+        // L'account resta nel database, membership comprese
+        let bob = state.user.read(&2).await?.expect("Bob should still exist");
+        assert_eq!(bob.username, "bob");
+        assert!(bob.deactivated_at.is_some());
+
+        // Gli altri utenti vedono "Deleted User"
+        let response = server
+            .get("/users/2")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", alice_token),
+            )
+            .await;
+        response.assert_status_ok();
+        let user: serde_json::Value = response.json();
+        assert_eq!(user["username"], "Deleted User");
+
+        let response = server
+            .get("/chats/1/members")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", alice_token),
+            )
+            .await;
+        response.assert_status_ok();
+        let members: Vec<serde_json::Value> = response.json();
+        let bob_member = members
+            .iter()
+            .find(|m| m["user_id"] == 2)
+            .expect("Bob should still be a member");
+        assert_eq!(bob_member["username"], "Deleted User");
+
+        // Il token emesso prima della disattivazione non è più valido
+        server
+            .get("/users/me/profile")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", bob_token),
+            )
+            .await
+            .assert_status_unauthorized();
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_deactivated_account_reactivated_on_login(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+
+        let credentials = json!({
+            "username": "tempuser",
+            "password": "TempPass123"
+        });
+        server
+            .post("/auth/register")
+            .json(&credentials)
+            .await
+            .assert_status_ok();
+
+        let login_response = server.post("/auth/login").json(&credentials).await;
+        login_response.assert_status_ok();
+        let auth_header = login_response.headers().get("authorization").unwrap();
+        let token = auth_header
+            .to_str()
+            .unwrap()
+            .strip_prefix("Bearer ")
+            .unwrap();
+
+        server
+            .delete("/users/me?mode=deactivate")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await
+            .assert_status_ok();
+
+        // Login entro la finestra di riattivazione
+        server
+            .post("/auth/login")
+            .json(&credentials)
+            .await
+            .assert_status_ok();
+
+        let user = state.user.find_by_username("tempuser").await?.unwrap();
+        assert!(
+            user.deactivated_at.is_none(),
+            "Account should be reactivated"
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_purge_expired_deactivations(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+
+        // Bob disattivato oltre la finestra, charlie ancora entro la finestra
+        sqlx::query("UPDATE users SET deactivated_at = NOW() - INTERVAL 31 DAY WHERE user_id = 2")
+            .execute(&pool)
+            .await?;
+        sqlx::query("UPDATE users SET deactivated_at = NOW() - INTERVAL 1 DAY WHERE user_id = 3")
+            .execute(&pool)
+            .await?;
+
+        let purged = server::services::purge_expired_deactivations(&state)
+            .await
+            .unwrap_or_else(|_| panic!("Purge should succeed"));
+        assert_eq!(purged, 1);
+
+        let bob = state.user.read(&2).await?.unwrap();
+        assert_eq!(bob.username, "Deleted User");
+        assert!(bob.deactivated_at.is_none());
+
+        let charlie = state.user.read(&3).await?.unwrap();
+        assert_eq!(charlie.username, "charlie");
+        assert!(charlie.deactivated_at.is_some());
+
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per GET/PATCH /users/me/profile - get_my_profile, update_my_profile
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_update_profile_success(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let alice_token = create_test_jwt(1, "alice", &state.jwt_secret);
+        let bob_token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        let response = server
+            .patch("/users/me/profile")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", alice_token),
+            )
+            .json(&json!({
+                "display_name": "  Alice Liddell ",
+                "bio": "Down the rabbit hole",
+                "avatar_url": "https://example.com/alice.png"
+            }))
+            .await;
+
+        response.assert_status_ok();
+        let profile: serde_json::Value = response.json();
+        assert_eq!(profile["display_name"], "Alice Liddell");
+        assert_eq!(profile["bio"], "Down the rabbit hole");
+
+        // I campi del profilo sono visibili anche agli altri utenti
+        let response = server
+            .get("/users/1")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", bob_token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let user: serde_json::Value = response.json();
+        assert_eq!(user["display_name"], "Alice Liddell");
+        assert_eq!(user["avatar_url"], "https://example.com/alice.png");
+
+        // Una stringa vuota cancella il campo, gli altri restano invariati
+        server
+            .patch("/users/me/profile")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", alice_token),
+            )
+            .json(&json!({ "bio": "" }))
+            .await
+            .assert_status_ok();
+
+        let response = server
+            .get("/users/me/profile")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", alice_token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let profile: serde_json::Value = response.json();
+        assert!(profile.get("bio").is_none());
+        assert_eq!(profile["display_name"], "Alice Liddell");
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_update_profile_invalid_avatar_url(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .patch("/users/me/profile")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "avatar_url": "javascript:alert(1)" }))
+            .await;
+
+        response.assert_status_bad_request();
+
+        let user = state.user.read(&1).await?.unwrap();
+        assert!(user.avatar_url.is_none());
+
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per PUT /users/me/status - set_my_status
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_set_status_broadcast_and_members(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        // Un membro online della chat 1 è iscritto al canale
+        let mut rx = state.chats_online.subscribe(&1);
+
+        let response = server
+            .put("/users/me/status")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "status": "in a meeting", "duration": 3600 }))
+            .await;
+
+        response.assert_status_ok();
+        let status: serde_json::Value = response.json();
+        assert_eq!(status["status"], "in a meeting");
+        assert!(status["expires_at"].is_string());
+
+        match rx.try_recv() {
+            Ok(server::ws::chatmap::ChatEvent::StatusChanged(event)) => {
+                assert_eq!(event.user_id, 2);
+                assert_eq!(event.status.as_deref(), Some("in a meeting"));
+            }
+            _ => panic!("Expected StatusChanged event"),
+        }
+
+        // Lo stato compare nella lista dei membri
+        let response = server
+            .get("/chats/1/members")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let members: Vec<serde_json::Value> = response.json();
+        let bob = members.iter().find(|m| m["user_id"] == 2).unwrap();
+        assert_eq!(bob["status"], "in a meeting");
+
+        // Uno stato vuoto lo cancella
+        let response = server
+            .put("/users/me/status")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "status": "" }))
+            .await;
+
+        response.assert_status_ok();
+        let status: serde_json::Value = response.json();
+        assert!(status["status"].is_null());
+
+        let user = state.user.read(&2).await?.unwrap();
+        assert!(user.status_text.is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_expired_status_not_shown(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        sqlx::query!(
+            "UPDATE users SET status_text = 'on holiday', status_expires_at = ? WHERE user_id = 2",
+            chrono::Utc::now() - chrono::Duration::hours(1)
+        )
+        .execute(&pool)
+        .await?;
+
+        let response = server
+            .get("/users/2")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let user: serde_json::Value = response.json();
+        assert!(user.get("status").is_none());
+        assert!(user.get("status_expires_at").is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_set_status_too_long(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .put("/users/me/status")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "status": "a".repeat(101) }))
+            .await;
+
+        response.assert_status_bad_request();
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per GET/PUT /users/me/privacy - visibilità della presenza
+    // ============================================================
+
+    async fn set_presence_visibility(
+        server: &axum_test::TestServer,
+        token: &str,
+        visibility: &str,
+    ) {
+        server
+            .put("/users/me/privacy")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "presence_visibility": visibility }))
+            .await
+            .assert_status_ok();
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_presence_hidden_from_non_contacts(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let alice_token = create_test_jwt(1, "alice", &state.jwt_secret);
+        let bob_token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        state.user.update_last_seen(&2, &chrono::Utc::now()).await?;
+        set_presence_visibility(&server, &bob_token, "contacts").await;
+
+        let response = server
+            .get("/users/me/privacy")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", bob_token),
+            )
+            .await;
+        response.assert_status_ok();
+        let privacy: serde_json::Value = response.json();
+        assert_eq!(privacy["presence_visibility"], "contacts");
+
+        // Alice e Bob non hanno chat in comune
+        let response = server
+            .get("/users/2")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", alice_token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let user: serde_json::Value = response.json();
+        assert_eq!(user["username"], "bob");
+        assert!(user.get("online").is_none());
+        assert!(user.get("last_seen").is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_presence_visible_to_contacts(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let alice_token = create_test_jwt(1, "alice", &state.jwt_secret);
+        let bob_token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        state.user.update_last_seen(&2, &chrono::Utc::now()).await?;
+        set_presence_visibility(&server, &bob_token, "contacts").await;
+
+        // Alice e Bob sono entrambi membri della chat 1
+        let response = server
+            .get("/users?search=bo")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", alice_token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let users: Vec<serde_json::Value> = response.json();
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0]["online"], false);
+        assert!(users[0]["last_seen"].is_string());
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_presence_nobody_hides_events(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let alice_token = create_test_jwt(1, "alice", &state.jwt_secret);
+        let bob_token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        set_presence_visibility(&server, &bob_token, "nobody").await;
+
+        let mut rx = state.chats_online.subscribe(&1);
+        server::ws::event_handlers::broadcast_presence(&state, 2, true, None).await;
+        assert!(rx.try_recv().is_err(), "No presence event expected");
+
+        // Nemmeno chi condivide una chat vede la presenza
+        let response = server
+            .get("/users/2")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", alice_token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let user: serde_json::Value = response.json();
+        assert!(user.get("online").is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_update_privacy_invalid_value(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .put("/users/me/privacy")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "presence_visibility": "friends" }))
+            .await;
+
+        response.assert_status_unprocessable_entity();
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per /users/me/sessions - connessioni WebSocket attive
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_list_and_disconnect_sessions(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        // Nessuna connessione attiva
+        let response = server
+            .get("/users/me/sessions")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+        response.assert_status_ok();
+        let sessions: Vec<serde_json::Value> = response.json();
+        assert!(sessions.is_empty());
+
+        // Alice si connette dal telefono
+        let connection = ConnectionInfo::new(
+            Some("phone".to_string()),
+            "203.0.113.7".parse().ok(),
+            Some("IronLink/1.0".to_string()),
+        );
+        let connection_id = connection.connection_id.clone();
+        let (tx, mut rx) = create_signal_channel();
+        state.users_online.register_connection(1, tx, connection);
+
+        let response = server
+            .get("/users/me/sessions")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+        response.assert_status_ok();
+        let sessions: Vec<serde_json::Value> = response.json();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0]["connection_id"], connection_id.as_str());
+        assert_eq!(sessions[0]["device_name"], "phone");
+        assert_eq!(sessions[0]["ip"], "203.0.113.7");
+        assert_eq!(sessions[0]["user_agent"], "IronLink/1.0");
+        assert_eq!(sessions[0]["queued_signals"], 0);
+        assert_eq!(sessions[0]["dropped_signals"], 0);
+
+        // Una connessione sconosciuta non viene toccata
+        server
+            .delete("/users/me/sessions/not-a-connection")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await
+            .assert_status_not_found();
+        assert!(rx.try_recv().is_err());
+
+        server
+            .delete(&format!("/users/me/sessions/{}", connection_id))
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await
+            .assert_status(axum::http::StatusCode::NO_CONTENT);
+        assert!(matches!(rx.try_recv(), Ok(InternalSignal::Shutdown)));
+
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per l'export dei dati personali - /users/me/export
+    // ============================================================
+
+    #[sqlx::test(fixtures(
+        path = "../fixtures",
+        scripts("users", "chats", "messages", "invitations")
+    ))]
+    async fn test_data_export_flow(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        // Prima della richiesta non c'è nessun export
+        server
+            .get("/users/me/export")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await
+            .assert_status_not_found();
+
+        let response = server
+            .post("/users/me/export")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status(axum::http::StatusCode::ACCEPTED);
+        let export: serde_json::Value = response.json();
+        let export_id = export["export_id"].as_i64().unwrap();
+
+        // Il job gira in background: si interroga lo stato finché non è completato
+        let mut status = serde_json::Value::Null;
+        for _ in 0..50 {
+            let response = server
+                .get("/users/me/export")
+                .add_header(
+                    HeaderName::from_static("authorization"),
+                    format!("Bearer {}", token),
+                )
+                .await;
+            response.assert_status_ok();
+            status = response.json();
+            if status["state"] != "Pending" {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+
+        assert_eq!(status["export_id"].as_i64(), Some(export_id));
+        assert_eq!(status["state"], "Completed");
+        assert_eq!(status["download_url"], "/api/v1/users/me/export/download");
+
+        let response = server
+            .get("/users/me/export/download")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let archive: serde_json::Value = response.json();
+        assert_eq!(archive["profile"]["username"], "alice");
+        assert_eq!(archive["chats"].as_array().unwrap().len(), 3);
+        assert_eq!(archive["memberships"].as_array().unwrap().len(), 3);
+        // messaggi 1, 4 e 6 dal fixture
+        assert_eq!(archive["messages"].as_array().unwrap().len(), 3);
+        assert_eq!(archive["invitations_sent"].as_array().unwrap().len(), 1);
+        assert_eq!(archive["invitations_received"].as_array().unwrap().len(), 1);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_data_export_download_without_export(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        let response = server
+            .get("/users/me/export/download")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_not_found();
+        Ok(())
+    }
 }
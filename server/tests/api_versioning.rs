@@ -0,0 +1,81 @@
+//! Integration tests per le route versionate sotto /api/v1 e i percorsi legacy
+
+mod common;
+
+#[cfg(test)]
+mod versioning_tests {
+    use super::common::*;
+    use axum_test::http::{HeaderName, StatusCode};
+    use server::core::AppState;
+    use sqlx::MySqlPool;
+    use std::sync::Arc;
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_versioned_path_not_deprecated(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .get("/api/v1/chats")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        assert!(response.maybe_header("deprecation").is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_legacy_path_points_to_versioned_path(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .get("/chats/1/members")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        assert_eq!(response.header("deprecation"), "true");
+        assert_eq!(
+            response.header("link"),
+            "</api/v1/chats/1/members>; rel=\"successor-version\""
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_legacy_paths_disabled(pool: MySqlPool) -> sqlx::Result<()> {
+        let jwt_secret = "ilmiobellissimosegretochevaassolutamentecambiato";
+        let state = Arc::new(
+            AppState::new(pool.clone(), jwt_secret.to_string()).with_legacy_api_paths(false),
+        );
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let response = server
+            .get("/chats")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+        response.assert_status(StatusCode::NOT_FOUND);
+
+        // le sonde di stato non hanno versione e restano alla radice
+        let response = server.get("/healthz").await;
+        response.assert_status_ok();
+
+        Ok(())
+    }
+}
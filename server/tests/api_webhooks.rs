@@ -0,0 +1,183 @@
+//! Integration tests per i webhook in ingresso
+//!
+//! Test per:
+//! - GET/POST /chats/{chat_id}/webhooks
+//! - DELETE /chats/{chat_id}/webhooks/{webhook_id}
+//! - POST /webhooks/{webhook_token}
+
+mod common;
+
+#[cfg(test)]
+mod webhook_tests {
+    use super::common::*;
+    use axum_test::http::{HeaderName, StatusCode};
+    use serde_json::json;
+    use server::repositories::Read;
+    use sqlx::MySqlPool;
+
+    /// Crea un webhook nella chat 1 come alice (OWNER) e ritorna la risposta
+    async fn create_webhook(server: &axum_test::TestServer, token: &str) -> serde_json::Value {
+        let response = server
+            .post("/chats/1/webhooks")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .json(&json!({ "name": "ci_bot" }))
+            .await;
+        response.assert_status_ok();
+        response.json()
+    }
+
+    // ============================================================
+    // Test per /chats/{chat_id}/webhooks - create_webhook, list_webhooks, delete_webhook
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_create_and_list_webhooks(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let alice_token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let webhook = create_webhook(&server, &alice_token).await;
+        assert_eq!(webhook["name"], "ci_bot");
+        assert!(webhook["token"].is_string());
+
+        // Il bot è un nuovo utente, membro della chat
+        let bot_id = webhook["bot_user_id"].as_i64().unwrap() as i32;
+        let bot = state.user.find_by_username("ci_bot").await?.unwrap();
+        assert_eq!(bot.user_id, bot_id);
+        assert!(state.meta.read(&(bot_id, 1)).await?.is_some());
+
+        // Il token non viene più mostrato
+        let response = server
+            .get("/chats/1/webhooks")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", alice_token),
+            )
+            .await;
+        response.assert_status_ok();
+        let webhooks: Vec<serde_json::Value> = response.json();
+        assert_eq!(webhooks.len(), 1);
+        assert!(webhooks[0].get("token").is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_create_webhook_not_allowed(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let alice_token = create_test_jwt(1, "alice", &state.jwt_secret);
+        let bob_token = create_test_jwt(2, "bob", &state.jwt_secret);
+
+        // Bob è solo MEMBER della chat 1
+        server
+            .post("/chats/1/webhooks")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", bob_token),
+            )
+            .json(&json!({ "name": "ci_bot" }))
+            .await
+            .assert_status_forbidden();
+
+        // Le chat private non hanno webhook
+        server
+            .post("/chats/2/webhooks")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", alice_token),
+            )
+            .json(&json!({ "name": "ci_bot" }))
+            .await
+            .assert_status_bad_request();
+
+        // Il nome del bot non può essere uno username già usato
+        server
+            .post("/chats/1/webhooks")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", alice_token),
+            )
+            .json(&json!({ "name": "charlie" }))
+            .await
+            .assert_status(StatusCode::CONFLICT);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_delete_webhook_revokes_token(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let alice_token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let webhook = create_webhook(&server, &alice_token).await;
+        let bot_id = webhook["bot_user_id"].as_i64().unwrap() as i32;
+
+        server
+            .delete(&format!("/chats/1/webhooks/{}", webhook["webhook_id"]))
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", alice_token),
+            )
+            .await
+            .assert_status_ok();
+
+        // Il token non funziona più e il bot non è più membro della chat
+        server
+            .post(&format!("/webhooks/{}", webhook["token"].as_str().unwrap()))
+            .json(&json!({ "content": "build passed" }))
+            .await
+            .assert_status_not_found();
+        assert!(state.meta.read(&(bot_id, 1)).await?.is_none());
+
+        Ok(())
+    }
+
+    // ============================================================
+    // Test per POST /webhooks/{webhook_token} - post_webhook_message
+    // ============================================================
+
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_post_webhook_message(pool: MySqlPool) -> sqlx::Result<()> {
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let alice_token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        let webhook = create_webhook(&server, &alice_token).await;
+        let url = format!("/webhooks/{}", webhook["token"].as_str().unwrap());
+
+        let response = server
+            .post(&url)
+            .json(&json!({ "content": "**build** passed", "content_format": "Markdown" }))
+            .await;
+        response.assert_status_ok();
+        let message: serde_json::Value = response.json();
+        assert_eq!(message["chat_id"], 1);
+        assert_eq!(message["sender_id"], webhook["bot_user_id"]);
+        assert_eq!(message["message_type"], "UserMessage");
+
+        // Il messaggio è salvato come quelli inviati via WebSocket
+        let message_id = message["message_id"].as_i64().unwrap() as i32;
+        let saved = state.msg.read(&message_id).await?.unwrap();
+        assert_eq!(saved.content, "**build** passed");
+
+        // Le stesse validazioni dei messaggi WebSocket
+        server
+            .post(&url)
+            .json(&json!({ "content": "" }))
+            .await
+            .assert_status_bad_request();
+
+        server
+            .post("/webhooks/not-a-real-token")
+            .json(&json!({ "content": "build passed" }))
+            .await
+            .assert_status_not_found();
+
+        Ok(())
+    }
+}
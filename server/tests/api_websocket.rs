@@ -16,9 +16,10 @@ mod common;
 
 #[cfg(test)]
 mod ws_tests {
+    use server::ws::signal_queue::SignalReceiver;
     use server::ws::usermap::{UserMap, InternalSignal};
+    use server::repositories::{MemberFilter, Page};
     use super::common::*;
-    use tokio::sync::mpsc;
     use tracing::info;
     // ============================================================
     // WF0 Test unitario per UserMap - verifica sovrascrittura connessioni duplicate
@@ -33,7 +34,7 @@ mod ws_tests {
         let user_id = 1;
 
         // Prima connessione - crea il primo channel
-        let (tx1, mut rx1) = mpsc::unbounded_channel();
+        let (tx1, mut rx1) = create_signal_channel();
         user_map.register_online(user_id, tx1);
 
         // Verifica che l'utente sia registrato
@@ -42,7 +43,7 @@ mod ws_tests {
 
         // Seconda connessione - crea il secondo channel per lo stesso user_id
         // Questo simula l'utente che si connette di nuovo (es. da un altro dispositivo o refresh)
-        let (tx2, mut _rx2) = mpsc::unbounded_channel();
+        let (tx2, mut _rx2) = create_signal_channel();
         user_map.register_online(user_id, tx2);
 
         // Verifica che:
@@ -64,6 +65,152 @@ mod ws_tests {
         );
     }
 
+    /// Test che verifica i timestamp di presenza registrati dalla UserMap
+    #[tokio::test]
+    async fn test_wf0_usermap_tracks_presence_timestamps() {
+        let user_map = UserMap::new();
+        let user_id = 1;
+
+        let (tx, _rx) = create_signal_channel();
+        user_map.register_online(user_id, tx);
+
+        assert!(user_map.connected_since(&user_id).is_some());
+        assert!(user_map.last_seen(&user_id).is_none(), "Online user has no last_seen");
+
+        let disconnected_at = user_map.remove_from_online(&user_id);
+
+        assert!(!user_map.is_user_online(&user_id));
+        assert!(user_map.connected_since(&user_id).is_none());
+        assert_eq!(user_map.last_seen(&user_id), Some(disconnected_at));
+
+        // Una nuova connessione azzera l'ultima disconnessione
+        let (tx, _rx) = create_signal_channel();
+        user_map.register_online(user_id, tx);
+        assert!(user_map.last_seen(&user_id).is_none());
+    }
+
+    /// Test che verifica che la chiusura di una connessione già sostituita da una
+    /// riconnessione non faccia risultare offline l'utente
+    #[tokio::test]
+    async fn test_wf0_usermap_stale_connection_cleanup() {
+        use server::ws::usermap::ConnectionInfo;
+
+        let user_map = UserMap::new();
+        let user_id = 1;
+
+        let (old_tx, _old_rx) = create_signal_channel();
+        let old_epoch =
+            user_map.register_connection(user_id, old_tx, ConnectionInfo::new(None, None, None));
+        let (new_tx, _new_rx) = create_signal_channel();
+        let new_epoch =
+            user_map.register_connection(user_id, new_tx, ConnectionInfo::new(None, None, None));
+        assert_ne!(old_epoch, new_epoch);
+
+        // il cleanup della vecchia connessione arriva dopo la riconnessione
+        assert!(user_map.remove_connection(&user_id, old_epoch).is_none());
+        assert!(user_map.is_user_online(&user_id));
+        assert!(user_map.last_seen(&user_id).is_none());
+
+        let disconnected_at = user_map.remove_connection(&user_id, new_epoch);
+        assert!(disconnected_at.is_some());
+        assert!(!user_map.is_user_online(&user_id));
+        assert_eq!(user_map.last_seen(&user_id), disconnected_at);
+    }
+
+    /// Test che verifica l'invio della chiusura a tutte le connessioni all'arresto del server
+    #[tokio::test]
+    async fn test_wf0_usermap_shutdown_all() {
+        let user_map = UserMap::new();
+
+        let (alice_tx, mut alice_rx) = create_signal_channel();
+        let (bob_tx, mut bob_rx) = create_signal_channel();
+        user_map.register_online(1, alice_tx);
+        user_map.register_online(2, bob_tx);
+
+        assert_eq!(user_map.shutdown_all(), 2);
+        assert!(matches!(
+            alice_rx.try_recv(),
+            Ok(InternalSignal::ServerShutdown)
+        ));
+        assert!(matches!(
+            bob_rx.try_recv(),
+            Ok(InternalSignal::ServerShutdown)
+        ));
+
+        // le connessioni restano registrate finché i task non completano la chiusura
+        assert_eq!(user_map.online_count(), 2);
+    }
+
+    /// Test che verifica la codifica dei frame negoziata con Sec-WebSocket-Protocol:
+    /// JSON di default, MessagePack se richiesto, con lo stesso contenuto in entrambi i casi
+    #[test]
+    fn test_wf0_ws_encoding_roundtrip() {
+        use axum::extract::ws::Message;
+        use axum::http::HeaderValue;
+        use server::dtos::MessageDTO;
+        use server::ws::WsEncoding;
+        use server::ws::connection::decode_frame;
+
+        assert_eq!(WsEncoding::from_protocol(None), WsEncoding::Json);
+        assert_eq!(
+            WsEncoding::from_protocol(Some(&HeaderValue::from_static("msgpack"))),
+            WsEncoding::MessagePack
+        );
+
+        let message: MessageDTO = serde_json::from_str(
+            r#"{"chat_id": 1, "sender_id": 1, "content": "Packed", "message_type": "UserMessage", "client_msg_id": "tmp-1"}"#,
+        )
+        .expect("Valid JSON");
+
+        let json = WsEncoding::Json.encode(&message).unwrap();
+        let packed = WsEncoding::MessagePack.encode(&message).unwrap();
+        assert!(matches!(json, Message::Text(_)));
+        assert!(matches!(packed, Message::Binary(_)));
+
+        // Entrambi i frame si decodificano nello stesso messaggio
+        for frame in [json, packed] {
+            let decoded: MessageDTO = decode_frame(&frame).expect("Decodable frame");
+            assert_eq!(decoded.content.as_deref(), Some("Packed"));
+            assert_eq!(decoded.client_msg_id.as_deref(), Some("tmp-1"));
+        }
+
+        // MessagePack usa le stesse chiavi del JSON
+        let packed = WsEncoding::MessagePack.encode(&message).unwrap();
+        let value: serde_json::Value = decode_frame(&packed).unwrap();
+        assert_eq!(value["message_type"], "UserMessage");
+    }
+
+    /// Verifica che last_seen venga salvato sul db ed esposto in UserDTO insieme alla presenza
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users")))]
+    async fn test_wf0_last_seen_exposed_in_user_dto(pool: sqlx::MySqlPool) -> sqlx::Result<()> {
+        use axum_test::http::HeaderName;
+
+        let state = create_test_state(&pool);
+        let server = create_test_server(state.clone());
+        let token = create_test_jwt(1, "alice", &state.jwt_secret);
+
+        // Bob si connette e si disconnette
+        let (tx, _rx) = create_signal_channel();
+        state.users_online.register_online(2, tx);
+        let last_seen = state.users_online.remove_from_online(&2);
+        state.user.update_last_seen(&2, &last_seen).await?;
+
+        let response = server
+            .get("/users/2")
+            .add_header(
+                HeaderName::from_static("authorization"),
+                format!("Bearer {}", token),
+            )
+            .await;
+
+        response.assert_status_ok();
+        let user: serde_json::Value = response.json();
+        assert_eq!(user["online"], false);
+        assert!(!user["last_seen"].is_null());
+
+        Ok(())
+    }
+
     // ============================================================
     // WF0 Test per verifica caricamento chat dal DB e registrazione nella ChatMap
     // ============================================================
@@ -81,7 +228,7 @@ mod ws_tests {
         let user_id = 1; // Alice dai fixtures
 
         // Verifica quante chat ha l'utente nel database
-        let user_chats = state.meta.find_many_by_user_id(&user_id).await
+        let user_chats = state.meta.read_many(&MemberFilter::of_user(user_id).from_primary(), &Page::ALL).await
             .expect("Failed to load user chats from DB");
         
         let chat_count = user_chats.len();
@@ -121,6 +268,11 @@ mod ws_tests {
                 content: Some("Test message".to_string()),
                 message_type: Some(MessageType::UserMessage),
                 created_at: Some(chrono::Utc::now()),
+                deleted_at: None,
+                reply_to_message_id: None,
+                client_msg_id: None,
+                attachment_id: None,
+                content_format: None,
             });
 
             // Invia il messaggio al canale broadcast
@@ -168,7 +320,7 @@ mod ws_tests {
         let user_id = 1; // Alice dai fixtures
         
         // Crea il channel per simulare la connessione WebSocket dell'utente
-        let (internal_tx, mut _internal_rx) = tokio::sync::mpsc::unbounded_channel::<InternalSignal>();
+        let (internal_tx, mut _internal_rx) = create_signal_channel();
         
         // Registra l'utente come online (simula la connessione WebSocket)
         state.users_online.register_online(user_id, internal_tx.clone());
@@ -258,7 +410,7 @@ mod ws_tests {
         );
 
         // 4. Verifica che il canale sia ancora funzionante
-        let test_signal = internal_tx.send(InternalSignal::AddChat(999));
+        let test_signal = internal_tx.send(InternalSignal::RemoveChat(999));
         assert!(
             test_signal.is_ok(),
             "Should still be able to send signals to the user after malformed messages"
@@ -300,7 +452,7 @@ mod ws_tests {
         let user_id = 1; // Alice dai fixtures
         
         // Crea il channel per ricevere messaggi dal server
-        let (internal_tx, mut internal_rx) = tokio::sync::mpsc::unbounded_channel::<InternalSignal>();
+        let (internal_tx, mut internal_rx) = create_signal_channel();
         
         // Registra l'utente come online
         state.users_online.register_online(user_id, internal_tx.clone());
@@ -431,6 +583,564 @@ mod ws_tests {
         Ok(())
     }
 
+    /// WF1 - Verifica la validazione di reply_to_message_id in process_message
+    ///
+    /// Scenario:
+    /// 1. Alice risponde ad un messaggio di un'altra chat -> InternalSignal::Error
+    /// 2. Alice risponde ad un messaggio della stessa chat -> salvato con il riferimento
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats", "messages")))]
+    async fn test_wf1_reply_to_message_must_belong_to_same_chat(pool: sqlx::MySqlPool) -> sqlx::Result<()> {
+        use server::ws::event_handlers::process_message;
+
+        let state = create_test_state(&pool);
+        let user_id = 1; // Alice dai fixtures
+
+        let (internal_tx, mut internal_rx) = create_signal_channel();
+        state.users_online.register_online(user_id, internal_tx.clone());
+
+        // SCENARIO 1: il messaggio 4 appartiene alla chat privata (chat 2), non alla chat 1
+        let reply_wrong_chat = serde_json::from_str::<server::dtos::MessageDTO>(
+            r#"{"chat_id": 1, "sender_id": 1, "content": "Reply", "message_type": "UserMessage", "reply_to_message_id": 4}"#
+        ).expect("Valid JSON");
+
+        process_message(&state, user_id, reply_wrong_chat).await;
+
+        match internal_rx.try_recv() {
+            Ok(InternalSignal::Error(msg)) => assert_eq!(msg, "Invalid reply reference."),
+            _ => panic!("Expected Error signal for reply to another chat"),
+        }
+
+        // SCENARIO 2: risposta valida al messaggio 2 di Bob nella chat 1
+        let valid_reply = serde_json::from_str::<server::dtos::MessageDTO>(
+            r#"{"chat_id": 1, "sender_id": 1, "content": "Valid reply", "message_type": "UserMessage", "reply_to_message_id": 2}"#
+        ).expect("Valid JSON");
+
+        process_message(&state, user_id, valid_reply).await;
+
+        assert!(internal_rx.try_recv().is_err(), "No error expected for a valid reply");
+
+        let saved = sqlx::query!(
+            "SELECT reply_to_message_id FROM messages WHERE chat_id = 1 AND content = 'Valid reply'"
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(saved.reply_to_message_id, Some(2));
+
+        Ok(())
+    }
+
+    /// WF1 - Verifica la validazione di attachment_id in process_message
+    ///
+    /// Scenario:
+    /// 1. Alice invia nella chat 1 un allegato caricato nella chat 2 -> InternalSignal::Error
+    /// 2. Alice invia nella chat 1 un allegato caricato nella chat 1 -> salvato con il riferimento
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_wf1_attachment_must_belong_to_same_chat(pool: sqlx::MySqlPool) -> sqlx::Result<()> {
+        use server::ws::event_handlers::process_message;
+
+        let state = create_test_state(&pool);
+        let user_id = 1; // Alice dai fixtures
+
+        sqlx::query!(
+            "INSERT INTO attachments (attachment_id, chat_id, uploader_id, file_name, content_type, size_bytes, storage_key, created_at) VALUES
+            (1, 1, 1, 'a.txt', 'text/plain', 1, 'chats/1/a', NOW()),
+            (2, 2, 1, 'b.txt', 'text/plain', 1, 'chats/2/b', NOW())"
+        )
+        .execute(&pool)
+        .await?;
+
+        let (internal_tx, mut internal_rx) = create_signal_channel();
+        state.users_online.register_online(user_id, internal_tx.clone());
+
+        // SCENARIO 1: l'allegato 2 è stato caricato nella chat privata (chat 2)
+        let wrong_chat = serde_json::from_str::<server::dtos::MessageDTO>(
+            r#"{"chat_id": 1, "sender_id": 1, "content": "File", "message_type": "UserMessage", "attachment_id": 2}"#
+        ).expect("Valid JSON");
+
+        process_message(&state, user_id, wrong_chat).await;
+
+        match internal_rx.try_recv() {
+            Ok(InternalSignal::Error(msg)) => assert_eq!(msg, "Invalid attachment reference."),
+            _ => panic!("Expected Error signal for attachment of another chat"),
+        }
+
+        // SCENARIO 2: allegato della stessa chat
+        let valid = serde_json::from_str::<server::dtos::MessageDTO>(
+            r#"{"chat_id": 1, "sender_id": 1, "content": "Valid file", "message_type": "UserMessage", "attachment_id": 1}"#
+        ).expect("Valid JSON");
+
+        process_message(&state, user_id, valid).await;
+
+        assert!(internal_rx.try_recv().is_err(), "No error expected for a valid attachment");
+
+        let saved = sqlx::query!(
+            "SELECT attachment_id FROM messages WHERE chat_id = 1 AND content = 'Valid file'"
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(saved.attachment_id, Some(1));
+
+        Ok(())
+    }
+
+    /// WF1 - Verifica la gestione dei messaggi markdown in process_message
+    ///
+    /// Scenario:
+    /// 1. Alice invia un link javascript: in markdown -> InternalSignal::Error
+    /// 2. Alice invia markdown con HTML grezzo -> salvato sanificato con content_format MARKDOWN
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_wf1_markdown_message_is_validated_and_sanitized(pool: sqlx::MySqlPool) -> sqlx::Result<()> {
+        use server::ws::event_handlers::process_message;
+
+        let state = create_test_state(&pool);
+        let user_id = 1; // Alice dai fixtures
+
+        let (internal_tx, mut internal_rx) = create_signal_channel();
+        state.users_online.register_online(user_id, internal_tx.clone());
+
+        // SCENARIO 1: link con schema non sicuro
+        let unsafe_link = serde_json::from_str::<server::dtos::MessageDTO>(
+            r#"{"chat_id": 1, "sender_id": 1, "content": "[click](javascript:alert(1))", "message_type": "UserMessage", "content_format": "Markdown"}"#
+        ).expect("Valid JSON");
+
+        process_message(&state, user_id, unsafe_link).await;
+
+        match internal_rx.try_recv() {
+            Ok(InternalSignal::Error(msg)) => assert_eq!(msg, "Malformed message."),
+            _ => panic!("Expected Error signal for unsafe markdown link"),
+        }
+
+        // SCENARIO 2: l'HTML grezzo viene neutralizzato prima del salvataggio
+        let with_html = serde_json::from_str::<server::dtos::MessageDTO>(
+            r#"{"chat_id": 1, "sender_id": 1, "content": "**bold** <script>x</script>", "message_type": "UserMessage", "content_format": "Markdown"}"#
+        ).expect("Valid JSON");
+
+        process_message(&state, user_id, with_html).await;
+
+        assert!(internal_rx.try_recv().is_err(), "No error expected for sanitized markdown");
+
+        let saved = sqlx::query!(
+            "SELECT content, content_format FROM messages WHERE chat_id = 1 AND sender_id = 1 ORDER BY message_id DESC LIMIT 1"
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(saved.content, "**bold** &lt;script>x&lt;/script>");
+        assert_eq!(saved.content_format, "MARKDOWN");
+
+        Ok(())
+    }
+
+    /// WF1 - Verifica le chat di soli annunci in process_message
+    ///
+    /// Scenario:
+    /// 1. Bob (MEMBER) scrive nella chat 1 di soli annunci -> InternalSignal::ChatError
+    /// 2. Alice (OWNER) scrive nella stessa chat -> messaggio salvato
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_wf1_announcement_only_chat_rejects_members(pool: sqlx::MySqlPool) -> sqlx::Result<()> {
+        use server::ws::event_handlers::process_message;
+
+        let state = create_test_state(&pool);
+
+        sqlx::query!("UPDATE chats SET announcement_only = TRUE WHERE chat_id = 1")
+            .execute(&pool)
+            .await?;
+
+        let (bob_tx, mut bob_rx) = create_signal_channel();
+        state.users_online.register_online(2, bob_tx);
+        let (alice_tx, mut alice_rx) = create_signal_channel();
+        state.users_online.register_online(1, alice_tx);
+
+        // SCENARIO 1: Bob è MEMBER
+        let from_member = serde_json::from_str::<server::dtos::MessageDTO>(
+            r#"{"chat_id": 1, "sender_id": 2, "content": "Can I post?", "message_type": "UserMessage"}"#
+        ).expect("Valid JSON");
+
+        process_message(&state, 2, from_member).await;
+
+        match bob_rx.try_recv() {
+            Ok(InternalSignal::ChatError { chat_id, code, .. }) => {
+                assert_eq!(chat_id, 1);
+                assert_eq!(code, "ANNOUNCEMENT_ONLY");
+            }
+            _ => panic!("Expected ChatError signal for member in announcement-only chat"),
+        }
+
+        // SCENARIO 2: Alice è OWNER
+        let from_owner = serde_json::from_str::<server::dtos::MessageDTO>(
+            r#"{"chat_id": 1, "sender_id": 1, "content": "Announcement", "message_type": "UserMessage"}"#
+        ).expect("Valid JSON");
+
+        process_message(&state, 1, from_owner).await;
+
+        assert!(alice_rx.try_recv().is_err(), "No error expected for the owner");
+
+        let count = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM messages WHERE chat_id = 1 AND content IN ('Can I post?', 'Announcement')"
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(count, 1);
+
+        Ok(())
+    }
+
+    /// WF1 - Verifica il silenziamento dei membri in process_message
+    ///
+    /// Scenario:
+    /// 1. Bob è silenziato nella chat 1 -> InternalSignal::ChatError, messaggio non salvato
+    /// 2. Il silenziamento scade -> il messaggio di Bob viene salvato
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_wf1_muted_member_cannot_post(pool: sqlx::MySqlPool) -> sqlx::Result<()> {
+        use server::ws::event_handlers::process_message;
+
+        let state = create_test_state(&pool);
+
+        sqlx::query!(
+            "UPDATE userchatmetadata SET muted_until = NOW() + INTERVAL 1 HOUR WHERE user_id = 2 AND chat_id = 1"
+        )
+        .execute(&pool)
+        .await?;
+
+        let (bob_tx, mut bob_rx) = create_signal_channel();
+        state.users_online.register_online(2, bob_tx);
+
+        // SCENARIO 1: silenziamento attivo
+        let while_muted = serde_json::from_str::<server::dtos::MessageDTO>(
+            r#"{"chat_id": 1, "sender_id": 2, "content": "Am I muted?", "message_type": "UserMessage"}"#
+        ).expect("Valid JSON");
+
+        process_message(&state, 2, while_muted).await;
+
+        match bob_rx.try_recv() {
+            Ok(InternalSignal::ChatError { chat_id, code, .. }) => {
+                assert_eq!(chat_id, 1);
+                assert_eq!(code, "MUTED");
+            }
+            _ => panic!("Expected ChatError signal for muted member"),
+        }
+
+        // SCENARIO 2: silenziamento scaduto
+        sqlx::query!(
+            "UPDATE userchatmetadata SET muted_until = NOW() - INTERVAL 1 MINUTE WHERE user_id = 2 AND chat_id = 1"
+        )
+        .execute(&pool)
+        .await?;
+
+        let after_expiry = serde_json::from_str::<server::dtos::MessageDTO>(
+            r#"{"chat_id": 1, "sender_id": 2, "content": "Back again", "message_type": "UserMessage"}"#
+        ).expect("Valid JSON");
+
+        process_message(&state, 2, after_expiry).await;
+
+        assert!(bob_rx.try_recv().is_err(), "No error expected after the mute expired");
+
+        let count = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM messages WHERE chat_id = 1 AND content IN ('Am I muted?', 'Back again')"
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(count, 1);
+
+        Ok(())
+    }
+
+    /// WF1 - Verifica che il mittente riceva il SendResult con il message_id assegnato dal server
+    ///
+    /// Scenario:
+    /// 1. Alice invia un messaggio con client_msg_id -> riceve InternalSignal::SendResult
+    /// 2. L'id nel SendResult corrisponde alla riga salvata nel database
+    /// 3. Un messaggio senza client_msg_id non genera alcun SendResult
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_wf1_sender_receives_send_result_with_message_id(pool: sqlx::MySqlPool) -> sqlx::Result<()> {
+        use server::ws::event_handlers::process_message;
+
+        let state = create_test_state(&pool);
+        let user_id = 1; // Alice dai fixtures
+
+        let (internal_tx, mut internal_rx) = create_signal_channel();
+        state.users_online.register_online(user_id, internal_tx.clone());
+
+        let message = serde_json::from_str::<server::dtos::MessageDTO>(
+            r#"{"chat_id": 1, "sender_id": 1, "content": "Acked", "message_type": "UserMessage", "client_msg_id": "tmp-42"}"#
+        ).expect("Valid JSON");
+
+        process_message(&state, user_id, message).await;
+
+        let result = match internal_rx.try_recv() {
+            Ok(InternalSignal::SendResult(result)) => result,
+            _ => panic!("Expected SendResult signal"),
+        };
+        assert_eq!(result.client_msg_id, "tmp-42");
+        assert!(result.code.is_none());
+
+        let saved = sqlx::query!("SELECT content FROM messages WHERE message_id = ?", result.message_id)
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(saved.content, "Acked");
+
+        // Senza client_msg_id nessun SendResult
+        let message = serde_json::from_str::<server::dtos::MessageDTO>(
+            r#"{"chat_id": 1, "sender_id": 1, "content": "Not acked", "message_type": "UserMessage"}"#
+        ).expect("Valid JSON");
+
+        process_message(&state, user_id, message).await;
+        assert!(internal_rx.try_recv().is_err(), "No SendResult expected without client_msg_id");
+
+        Ok(())
+    }
+
+    /// WF1 - Verifica che un messaggio ritrasmesso con lo stesso client_msg_id non venga duplicato
+    ///
+    /// Scenario:
+    /// 1. Alice invia due volte lo stesso messaggio con client_msg_id "retry-1"
+    /// 2. Entrambi i SendResult riportano lo stesso message_id
+    /// 3. Nel database c'è una sola riga e Bob riceve il messaggio una sola volta
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_wf1_retransmitted_message_is_deduplicated(pool: sqlx::MySqlPool) -> sqlx::Result<()> {
+        use server::ws::event_handlers::process_message;
+
+        let state = create_test_state(&pool);
+
+        let (alice_tx, mut alice_rx) = create_signal_channel();
+        state.users_online.register_online(1, alice_tx);
+        let mut bob_chat_rx = state.chats_online.subscribe_multiple(vec![1]).remove(0);
+
+        let mut message_ids = Vec::new();
+        for _ in 0..2 {
+            let message = serde_json::from_str::<server::dtos::MessageDTO>(
+                r#"{"chat_id": 1, "sender_id": 1, "content": "Sent twice", "message_type": "UserMessage", "client_msg_id": "retry-1"}"#
+            ).expect("Valid JSON");
+            process_message(&state, 1, message).await;
+
+            match alice_rx.try_recv() {
+                Ok(InternalSignal::SendResult(result)) => message_ids.push(result.message_id),
+                _ => panic!("Expected SendResult signal"),
+            }
+        }
+        assert!(message_ids[0].is_some());
+        assert_eq!(message_ids[0], message_ids[1]);
+
+        let count = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM messages WHERE chat_id = 1 AND content = 'Sent twice'"
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(count, 1);
+
+        assert!(bob_chat_rx.try_recv().is_ok());
+        assert!(bob_chat_rx.try_recv().is_err(), "Retransmission must not be broadcast again");
+
+        Ok(())
+    }
+
+    /// WF1 - Verifica che il broadcast parta dopo il salvataggio, con i dati del server
+    ///
+    /// Scenario:
+    /// 1. Alice invia un messaggio con un created_at nel passato
+    /// 2. Bob riceve dal canale il messaggio con il message_id assegnato dal database
+    /// 3. Il created_at è quello del server, non quello indicato dal client
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_wf1_broadcast_carries_stored_message(pool: sqlx::MySqlPool) -> sqlx::Result<()> {
+        use server::ws::event_handlers::process_message;
+
+        let state = create_test_state(&pool);
+        let mut bob_chat_rx = state.chats_online.subscribe_multiple(vec![1]).remove(0);
+
+        let message = serde_json::from_str::<server::dtos::MessageDTO>(
+            r#"{"chat_id": 1, "sender_id": 1, "content": "Backdated", "message_type": "UserMessage", "created_at": "2000-01-01T00:00:00Z"}"#
+        ).expect("Valid JSON");
+        let before = chrono::Utc::now();
+        process_message(&state, 1, message).await;
+
+        let event = bob_chat_rx
+            .try_recv()
+            .expect("Bob should receive the message");
+        let received = event.as_message().expect("Should be a message event");
+
+        let saved = sqlx::query!(
+            "SELECT message_id, created_at FROM messages WHERE chat_id = 1 AND content = 'Backdated'"
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(received.message_id, Some(saved.message_id));
+        assert_eq!(received.created_at, Some(saved.created_at));
+        assert!(saved.created_at >= before - chrono::Duration::seconds(1));
+
+        Ok(())
+    }
+
+    /// WF1 - Verifica che un messaggio rifiutato con client_msg_id riceva un SendResult di errore
+    ///
+    /// Scenario:
+    /// 1. Alice invia un messaggio ad una chat di cui non fa parte
+    /// 2. Riceve un SendResult con codice NOT_MEMBER e senza message_id
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_wf1_rejected_message_send_result(pool: sqlx::MySqlPool) -> sqlx::Result<()> {
+        use server::ws::event_handlers::process_message;
+
+        let state = create_test_state(&pool);
+        let user_id = 2; // Bob non è membro della chat 3
+
+        let (internal_tx, mut internal_rx) = create_signal_channel();
+        state.users_online.register_online(user_id, internal_tx);
+
+        let message = serde_json::from_str::<server::dtos::MessageDTO>(
+            r#"{"chat_id": 3, "sender_id": 2, "content": "Let me in", "message_type": "UserMessage", "client_msg_id": "tmp-7"}"#
+        ).expect("Valid JSON");
+
+        process_message(&state, user_id, message).await;
+
+        match internal_rx.try_recv() {
+            Ok(InternalSignal::SendResult(result)) => {
+                assert_eq!(result.client_msg_id, "tmp-7");
+                assert_eq!(result.message_id, None);
+                assert_eq!(result.code.as_deref(), Some("NOT_MEMBER"));
+            }
+            _ => panic!("Expected SendResult signal"),
+        }
+
+        Ok(())
+    }
+
+    /// WF1 - Verifica il replay dei messaggi persi alla riconnessione
+    ///
+    /// Scenario:
+    /// 1. Alice invia tre messaggi nella chat 1 e uno nella chat 2
+    /// 2. Bob si riconnette indicando come ultimo messaggio ricevuto il primo della chat 1
+    /// 3. Riceve solo i due messaggi successivi della chat 1, dal più vecchio
+    /// 4. Le chat non indicate e quelle di cui non è membro sono ignorate
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_wf1_missed_messages_replayed_on_resume(pool: sqlx::MySqlPool) -> sqlx::Result<()> {
+        use server::ws::event_handlers::{load_missed_messages, process_message};
+        use std::collections::HashMap;
+
+        let state = create_test_state(&pool);
+
+        for (chat_id, content) in [(1, "First"), (1, "Second"), (1, "Third"), (2, "Private")] {
+            let message = serde_json::from_str::<server::dtos::MessageDTO>(&format!(
+                r#"{{"chat_id": {}, "sender_id": 1, "content": "{}", "message_type": "UserMessage"}}"#,
+                chat_id, content
+            ))
+            .expect("Valid JSON");
+            process_message(&state, 1, message).await;
+        }
+
+        let first_id = sqlx::query_scalar!(
+            "SELECT message_id FROM messages WHERE chat_id = 1 AND content = 'First'"
+        )
+        .fetch_one(&pool)
+        .await?;
+
+        let bob_chats = state.meta.read_many(&MemberFilter::of_user(2).from_primary(), &Page::ALL).await?;
+        let since = HashMap::from([(1, first_id), (3, 0)]);
+        let missed = load_missed_messages(&state, &bob_chats, &since).await?;
+
+        assert_eq!(missed.len(), 1, "Only chat 1 should be replayed");
+        assert_eq!(missed[0].chat_id, 1);
+        assert!(!missed[0].truncated);
+        let contents: Vec<_> = missed[0]
+            .messages
+            .iter()
+            .map(|m| m.content.clone().unwrap())
+            .collect();
+        assert_eq!(contents, vec!["Second", "Third"]);
+
+        Ok(())
+    }
+
+    /// WF1 - Verifica che i messaggi vengano accodati per i membri offline
+    ///
+    /// Scenario:
+    /// 1. Charlie è connesso, Bob no
+    /// 2. Alice invia due messaggi nella chat 1
+    /// 3. Solo Bob li trova nella coda offline, in ordine; il mittente non li riceve
+    /// 4. Una volta consegnati non vengono più restituiti
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_wf1_offline_members_get_queued_messages(pool: sqlx::MySqlPool) -> sqlx::Result<()> {
+        use server::ws::event_handlers::process_message;
+
+        let state = create_test_state(&pool);
+        let (charlie_tx, _charlie_rx) = create_signal_channel();
+        state.users_online.register_online(3, charlie_tx);
+
+        for content in ["First", "Second"] {
+            let message = serde_json::from_str::<server::dtos::MessageDTO>(&format!(
+                r#"{{"chat_id": 1, "sender_id": 1, "content": "{}", "message_type": "UserMessage"}}"#,
+                content
+            ))
+            .expect("Valid JSON");
+            process_message(&state, 1, message).await;
+        }
+
+        let queued = state.offline_queue.find_pending(&2, &0, 10).await?;
+        let contents: Vec<_> = queued.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["First", "Second"]);
+        assert!(state.offline_queue.find_pending(&3, &0, 10).await?.is_empty());
+        assert!(state.offline_queue.find_pending(&1, &0, 10).await?.is_empty());
+
+        state
+            .offline_queue
+            .mark_delivered(&2, &queued[1].message_id)
+            .await?;
+        assert!(state.offline_queue.find_pending(&2, &0, 10).await?.is_empty());
+
+        Ok(())
+    }
+
+    /// WF1 - Verifica il recupero dal database quando il canale broadcast di una chat resta
+    /// indietro (`Lagged`)
+    ///
+    /// Scenario:
+    /// 1. Bob è connesso da prima che Alice invii tre messaggi nella chat 1
+    /// 2. Con l'ultimo messaggio consegnato noto, recupera solo i successivi
+    /// 3. Senza messaggi consegnati, recupera quelli creati dopo la connessione
+    /// 4. Nelle chat di cui non è membro non recupera nulla
+    #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
+    async fn test_wf1_lagged_chat_resynced_from_database(
+        pool: sqlx::MySqlPool,
+    ) -> sqlx::Result<()> {
+        use server::ws::event_handlers::{load_lagged_messages, process_message};
+
+        let state = create_test_state(&pool);
+        let connected_at = chrono::Utc::now() - chrono::Duration::seconds(1);
+
+        for content in ["First", "Second", "Third"] {
+            let message = serde_json::from_str::<server::dtos::MessageDTO>(&format!(
+                r#"{{"chat_id": 1, "sender_id": 1, "content": "{}", "message_type": "UserMessage"}}"#,
+                content
+            ))
+            .expect("Valid JSON");
+            process_message(&state, 1, message).await;
+        }
+
+        let first_id = sqlx::query_scalar!(
+            "SELECT message_id FROM messages WHERE chat_id = 1 AND content = 'First'"
+        )
+        .fetch_one(&pool)
+        .await?;
+
+        let replay = load_lagged_messages(&state, 2, 1, Some(first_id), connected_at)
+            .await?
+            .expect("Messages after last_seen should be loaded");
+        let contents: Vec<_> = replay
+            .messages
+            .iter()
+            .map(|m| m.content.clone().unwrap())
+            .collect();
+        assert_eq!(contents, vec!["Second", "Third"]);
+
+        let replay = load_lagged_messages(&state, 2, 1, None, connected_at)
+            .await?
+            .expect("Messages created after connecting should be loaded");
+        assert_eq!(replay.messages.len(), 3);
+
+        // Bob non è membro della chat 3
+        let replay = load_lagged_messages(&state, 2, 3, None, connected_at).await?;
+        assert!(replay.is_none());
+
+        Ok(())
+    }
+
     // ============================================================
     // WF1: Test salvataggio messaggio nel database dopo invio WebSocket
     // ============================================================
@@ -455,7 +1165,7 @@ mod ws_tests {
         let chat_id = 2;  // Chat PRIVATA Alice-Bob dai fixtures
         
         // Crea il channel per ricevere messaggi dal server
-        let (internal_tx, mut internal_rx) = tokio::sync::mpsc::unbounded_channel::<InternalSignal>();
+        let (internal_tx, mut internal_rx) = create_signal_channel();
         
         // Registra Alice come online (Bob rimane offline)
         state.users_online.register_online(alice_id, internal_tx.clone());
@@ -542,7 +1252,7 @@ mod ws_tests {
                 panic!("Unexpected error received for valid message: {}", msg);
             }
             Ok(_) => {
-                // Altri tipi di segnale potrebbero essere OK (es. AddChat)
+                // Altri tipi di segnale potrebbero essere OK (es. ChatJoined)
                 info!("Received non-error signal (this may be expected)");
             }
         }
@@ -584,7 +1294,7 @@ mod ws_tests {
         let chat_id = 2;  // Chat PRIVATE Alice-Bob dai fixtures
         
         // Solo Alice è online
-        let (internal_tx_alice, mut internal_rx_alice) = tokio::sync::mpsc::unbounded_channel::<InternalSignal>();
+        let (internal_tx_alice, mut internal_rx_alice) = create_signal_channel();
         state.users_online.register_online(alice_id, internal_tx_alice.clone());
         
         // Bob NON è registrato come online (simula utente offline)
@@ -765,7 +1475,7 @@ mod ws_tests {
         let chat_id = 1; // Chat di gruppo dai fixtures
         
         // Solo Alice è online
-        let (internal_tx_alice, mut internal_rx_alice) = tokio::sync::mpsc::unbounded_channel::<InternalSignal>();
+        let (internal_tx_alice, mut internal_rx_alice) = create_signal_channel();
         state.users_online.register_online(alice_id, internal_tx_alice.clone());
         
         // Bob e Charlie NON sono registrati come online (simulano utenti offline)
@@ -939,11 +1649,11 @@ mod ws_tests {
         let chat_id = 2;  // Chat PRIVATA Alice-Bob
         
         // Setup Alice (sender)
-        let (internal_tx_alice, mut _internal_rx_alice) = tokio::sync::mpsc::unbounded_channel::<InternalSignal>();
+        let (internal_tx_alice, mut _internal_rx_alice) = create_signal_channel();
         state.users_online.register_online(alice_id, internal_tx_alice.clone());
         
         // Setup Bob (receiver) - ONLINE e sottoscritto alla chat
-        let (internal_tx_bob, mut _internal_rx_bob) = tokio::sync::mpsc::unbounded_channel::<InternalSignal>();
+        let (internal_tx_bob, mut _internal_rx_bob) = create_signal_channel();
         state.users_online.register_online(bob_id, internal_tx_bob.clone());
         
         // Bob sottoscrivi alla chat per ricevere messaggi via broadcast
@@ -1000,6 +1710,7 @@ mod ws_tests {
         let received_message = received_message
             .expect("Timeout error")
             .expect("Should receive a message");
+        let received_message = received_message.as_message().expect("Should be a message event").clone();
 
         // === FASE 5: Verifica attributi del messaggio ricevuto ===
         info!("Message received by Bob: {:?}", received_message);
@@ -1089,7 +1800,7 @@ mod ws_tests {
         let chat_id = 1;  // Chat di GRUPPO (General Chat)
         
         // Setup Alice
-        let (internal_tx_alice, mut _internal_rx_alice) = tokio::sync::mpsc::unbounded_channel::<InternalSignal>();
+        let (internal_tx_alice, mut _internal_rx_alice) = create_signal_channel();
         state.users_online.register_online(alice_id, internal_tx_alice.clone());
         
         // Alice sottoscrivi alla chat di gruppo per ricevere messaggi via broadcast
@@ -1161,6 +1872,7 @@ mod ws_tests {
         let received_message = received_message
             .expect("Timeout error")
             .expect("Should receive a message");
+        let received_message = received_message.as_message().expect("Should be a message event").clone();
 
         // === FASE 5: Verifica attributi del messaggio ricevuto ===
         info!("Message received by Alice (echo): {:?}", received_message);
@@ -1259,10 +1971,10 @@ mod ws_tests {
         let chat_id = 3;    // Dev Team (GROUP)
         
         // Registra Alice e Charlie come online
-        let (internal_tx_alice, mut _internal_rx_alice) = tokio::sync::mpsc::unbounded_channel::<InternalSignal>();
+        let (internal_tx_alice, mut _internal_rx_alice) = create_signal_channel();
         state.users_online.register_online(alice_id, internal_tx_alice.clone());
         
-        let (internal_tx_charlie, mut _internal_rx_charlie) = tokio::sync::mpsc::unbounded_channel::<InternalSignal>();
+        let (internal_tx_charlie, mut _internal_rx_charlie) = create_signal_channel();
         state.users_online.register_online(charlie_id, internal_tx_charlie.clone());
         
         // Alice e Charlie si sottoscrivono alla chat 3 per ricevere messaggi broadcast
@@ -1344,6 +2056,7 @@ mod ws_tests {
         let alice_message = alice_message
             .expect("Timeout waiting for Alice's message")
             .expect("Alice should receive a message");
+        let alice_message = alice_message.as_message().expect("Should be a message event").clone();
         
         info!("Alice received message: {:?}", alice_message);
         
@@ -1381,6 +2094,7 @@ mod ws_tests {
         let charlie_message = charlie_message
             .expect("Timeout waiting for Charlie's message")
             .expect("Charlie should receive a message");
+        let charlie_message = charlie_message.as_message().expect("Should be a message event").clone();
         
         info!("Charlie received message: {:?}", charlie_message);
         
@@ -1474,11 +2188,11 @@ mod ws_tests {
         let chat_id = 3;    // Dev Team (GROUP)
         
         // Registra Alice come online (inviter)
-        let (internal_tx_alice, mut _internal_rx_alice) = tokio::sync::mpsc::unbounded_channel::<InternalSignal>();
+        let (internal_tx_alice, mut _internal_rx_alice) = create_signal_channel();
         state.users_online.register_online(alice_id, internal_tx_alice.clone());
         
         // Registra Bob come online (invitato) - lui riceverà la notifica
-        let (internal_tx_bob, mut internal_rx_bob) = tokio::sync::mpsc::unbounded_channel::<InternalSignal>();
+        let (internal_tx_bob, mut internal_rx_bob) = create_signal_channel();
         state.users_online.register_online(bob_id, internal_tx_bob.clone());
         
         assert!(state.users_online.is_user_online(&alice_id), "Alice should be online");
@@ -2328,15 +3042,15 @@ mod ws_tests {
         let chat_id = 1; // Chat di GRUPPO (General Chat)
         
         // Setup Alice (sender 1)
-        let (internal_tx_alice, mut _internal_rx_alice) = tokio::sync::mpsc::unbounded_channel::<InternalSignal>();
+        let (internal_tx_alice, mut _internal_rx_alice) = create_signal_channel();
         state.users_online.register_online(alice_id, internal_tx_alice.clone());
         
         // Setup Bob (sender 2)
-        let (internal_tx_bob, mut _internal_rx_bob) = tokio::sync::mpsc::unbounded_channel::<InternalSignal>();
+        let (internal_tx_bob, mut _internal_rx_bob) = create_signal_channel();
         state.users_online.register_online(bob_id, internal_tx_bob.clone());
         
         // Setup Charlie (receiver) - ONLINE e sottoscritto alla chat
-        let (internal_tx_charlie, mut _internal_rx_charlie) = tokio::sync::mpsc::unbounded_channel::<InternalSignal>();
+        let (internal_tx_charlie, mut _internal_rx_charlie) = create_signal_channel();
         state.users_online.register_online(charlie_id, internal_tx_charlie.clone());
         
         // Charlie sottoscrivi alla chat per ricevere messaggi via broadcast
@@ -2409,6 +3123,7 @@ mod ws_tests {
         let received_message_from_alice = received_message_from_alice
             .expect("Timeout waiting for Charlie to receive Alice's message")
             .expect("Charlie should receive a message from Alice");
+        let received_message_from_alice = received_message_from_alice.as_message().expect("Should be a message event").clone();
 
         // === FASE 5: Verifica attributi del messaggio di Alice ricevuto da Charlie ===
         info!("Message received by Charlie from Alice: {:?}", received_message_from_alice);
@@ -2472,6 +3187,7 @@ mod ws_tests {
         let received_message_from_bob = received_message_from_bob
             .expect("Timeout waiting for Charlie to receive Bob's message")
             .expect("Charlie should receive a message from Bob");
+        let received_message_from_bob = received_message_from_bob.as_message().expect("Should be a message event").clone();
 
         // === FASE 8: Verifica attributi del messaggio di Bob ricevuto da Charlie ===
         info!("Message received by Charlie from Bob: {:?}", received_message_from_bob);
@@ -2572,7 +3288,7 @@ mod ws_tests {
         info!("✓ Alice and Bob are members of chat {}", chat_id);
         
         // === FASE 2: Alice è online, Bob è offline ===
-        let (internal_tx_alice, mut _internal_rx_alice) = tokio::sync::mpsc::unbounded_channel::<InternalSignal>();
+        let (internal_tx_alice, mut _internal_rx_alice) = create_signal_channel();
         state.users_online.register_online(alice_id, internal_tx_alice.clone());
         
         // Bob NON viene registrato (rimane offline)
@@ -2640,7 +3356,7 @@ mod ws_tests {
         info!("✓ Attempting to send message to disconnected Alice did not panic");
         
         // === FASE 8: Verifica che possiamo riconnettere Alice ===
-        let (internal_tx_alice_new, mut _internal_rx_alice_new) = tokio::sync::mpsc::unbounded_channel::<InternalSignal>();
+        let (internal_tx_alice_new, mut _internal_rx_alice_new) = create_signal_channel();
         state.users_online.register_online(alice_id, internal_tx_alice_new.clone());
         
         assert!(
@@ -2682,7 +3398,6 @@ mod ws_tests {
     /// Questo test verifica la corretta terminazione dei task WebSocket
     #[sqlx::test(fixtures(path = "../fixtures", scripts("users", "chats")))]
     async fn test_wf5_websocket_tasks_terminate_on_disconnect(pool: sqlx::MySqlPool) -> sqlx::Result<()> {
-        use tokio::sync::mpsc::unbounded_channel;
         use tokio::time::{timeout, Duration};
         
         // === FASE 1: Setup - Crea stato del server ===
@@ -2692,8 +3407,8 @@ mod ws_tests {
         info!("Setting up test for user {}", alice_id);
         
         // === FASE 2: Simula connessione WebSocket - Crea i canali ===
-        // Creiamo un canale unbounded per comunicazione interna (come in handle_socket)
-        let (internal_tx, internal_rx) = unbounded_channel::<InternalSignal>();
+        // Creiamo la coda dei segnali per comunicazione interna (come in handle_socket)
+        let (internal_tx, internal_rx) = create_signal_channel();
         
         // Registriamo Alice come online (come fa handle_socket)
         state.users_online.register_online(alice_id, internal_tx.clone());
@@ -2732,11 +3447,9 @@ mod ws_tests {
         
         // === FASE 5: Spawna il task write_ws (simula il comportamento reale) ===
         let write_task = tokio::spawn(async move {
-            use tokio::sync::mpsc::UnboundedReceiver;
-            
             info!("Write task started (simulation)");
             
-            let mut internal_rx_local: UnboundedReceiver<InternalSignal> = internal_rx;
+            let mut internal_rx_local: SignalReceiver = internal_rx;
             
             // Aspetta il segnale di Shutdown (come fa il vero write_ws)
             loop {
@@ -2861,10 +3574,10 @@ mod ws_tests {
         let chat_id = 1;    // General Chat (GROUP)
         
         // Registra Alice e Bob come online
-        let (internal_tx_alice, mut _internal_rx_alice) = tokio::sync::mpsc::unbounded_channel::<InternalSignal>();
+        let (internal_tx_alice, mut _internal_rx_alice) = create_signal_channel();
         state.users_online.register_online(alice_id, internal_tx_alice.clone());
         
-        let (internal_tx_bob, mut _internal_rx_bob) = tokio::sync::mpsc::unbounded_channel::<InternalSignal>();
+        let (internal_tx_bob, mut _internal_rx_bob) = create_signal_channel();
         state.users_online.register_online(bob_id, internal_tx_bob.clone());
         
         // Alice e Bob si sottoscrivono alla chat 1
@@ -2954,6 +3667,7 @@ mod ws_tests {
         let alice_message = alice_message
             .expect("Timeout")
             .expect("Alice should receive message");
+        let alice_message = alice_message.as_message().expect("Should be a message event").clone();
         
         info!("Alice received message: {:?}", alice_message);
         
@@ -2995,6 +3709,7 @@ mod ws_tests {
         let bob_message = bob_message
             .expect("Timeout")
             .expect("Bob should receive message");
+        let bob_message = bob_message.as_message().expect("Should be a message event").clone();
         
         info!("Bob received message: {:?}", bob_message);
         
@@ -3325,10 +4040,10 @@ mod ws_tests {
         let chat_id = 1;    // General Chat (GROUP)
         
         // Registra Alice e Bob come online
-        let (internal_tx_alice, mut _internal_rx_alice) = tokio::sync::mpsc::unbounded_channel::<InternalSignal>();
+        let (internal_tx_alice, mut _internal_rx_alice) = create_signal_channel();
         state.users_online.register_online(alice_id, internal_tx_alice.clone());
         
-        let (internal_tx_bob, mut _internal_rx_bob) = tokio::sync::mpsc::unbounded_channel::<InternalSignal>();
+        let (internal_tx_bob, mut _internal_rx_bob) = create_signal_channel();
         state.users_online.register_online(bob_id, internal_tx_bob.clone());
         
         // Alice e Bob si sottoscrivono alla chat 1
@@ -3418,6 +4133,7 @@ mod ws_tests {
         let bob_removal_message = bob_removal_message
             .expect("Timeout")
             .expect("Bob should receive message");
+        let bob_removal_message = bob_removal_message.as_message().expect("Should be a message event").clone();
         
         info!("Bob received removal message: {:?}", bob_removal_message);
         
@@ -3474,6 +4190,7 @@ mod ws_tests {
         let alice_echo = alice_echo
             .expect("Timeout")
             .expect("Alice should receive message");
+        let alice_echo = alice_echo.as_message().expect("Should be a message event").clone();
         
         assert_eq!(
             alice_echo.message_type,
@@ -3550,7 +4267,7 @@ mod ws_tests {
         
         // === FASE 11: Verifica che Bob NON possa più caricare messaggi dal DB ===
         // Simula una riconnessione: Bob prova a caricare le sue chat
-        let bob_chats = state.meta.find_many_by_user_id(&bob_id).await?;
+        let bob_chats = state.meta.read_many(&MemberFilter::of_user(bob_id).from_primary(), &Page::ALL).await?;
         
         // Bob non dovrebbe più vedere la chat 1 tra le sue chat
         let chat_1_visible = bob_chats.iter().any(|m| m.chat_id == chat_id);
@@ -1,8 +1,10 @@
 use axum_test::TestServer;
+use serde_json::json;
 use server::core::AppState;
+use server::core::config::WsConfig;
+use server::ws::signal_queue::{SignalReceiver, SignalSender, signal_channel};
 use sqlx::MySqlPool;
 use std::sync::Arc;
-use serde_json::json;
 
 /// Crea un AppState per i test
 ///
@@ -40,7 +42,6 @@ pub fn create_test_server(state: Arc<AppState>) -> TestServer {
 /// # Returns
 /// Token JWT valido per 24 ore
 
-
 #[allow(dead_code)]
 pub fn create_test_jwt(user_id: i32, username: &str, jwt_secret: &str) -> String {
     use chrono::{Duration, Utc};
@@ -53,6 +54,7 @@ pub fn create_test_jwt(user_id: i32, username: &str, jwt_secret: &str) -> String
         username: String,
         exp: usize,
         iat: usize,
+        jti: String,
     }
 
     let now = Utc::now();
@@ -66,6 +68,7 @@ pub fn create_test_jwt(user_id: i32, username: &str, jwt_secret: &str) -> String
         username: username.to_string(),
         exp: expiration,
         iat: now.timestamp() as usize,
+        jti: uuid::Uuid::new_v4().to_string(),
     };
 
     encode(
@@ -76,7 +79,6 @@ pub fn create_test_jwt(user_id: i32, username: &str, jwt_secret: &str) -> String
     .expect("Failed to create JWT token")
 }
 
-
 /// Utility per creare solo il token JWT senza connessione WebSocket
 ///
 /// # Arguments
@@ -87,7 +89,6 @@ pub fn create_test_jwt(user_id: i32, username: &str, jwt_secret: &str) -> String
 /// # Returns
 /// Token JWT come String
 
-
 #[allow(dead_code)]
 pub async fn get_auth_token(
     server: &TestServer,
@@ -99,7 +100,7 @@ pub async fn get_auth_token(
         "username": username,
         "password": password
     });
-    
+
     let register_response = server.post("/auth/register").json(&register_body).await;
     register_response.assert_status_ok();
 
@@ -119,7 +120,7 @@ pub async fn get_auth_token(
         .expect("Authorization header should be present")
         .to_str()
         .expect("Authorization header should be valid string");
-    
+
     let token = auth_header
         .strip_prefix("Bearer ")
         .expect("Authorization should start with 'Bearer '")
@@ -127,4 +128,13 @@ pub async fn get_auth_token(
 
     Ok(token)
 }
-    
+
+/// Crea la coda dei segnali di una connessione WebSocket, come fa `handle_socket`
+///
+/// # Returns
+/// Coppia (sender da registrare nella UserMap, receiver da cui leggere i segnali)
+#[allow(dead_code)]
+pub fn create_signal_channel() -> (SignalSender, SignalReceiver) {
+    let config = WsConfig::default();
+    signal_channel(config.signal_queue_capacity, config.overflow_policy)
+}